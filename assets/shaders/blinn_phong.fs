@@ -0,0 +1,27 @@
+#version 100
+precision mediump float;
+
+// The direction and color of a directional light in view space.
+uniform vec3 u_LitViewDir;
+uniform vec3 u_LitColor;
+
+uniform vec3 u_Ambient;
+uniform vec4 u_Color;
+uniform vec3 u_Specular;
+uniform float u_Shininess;
+
+varying vec3 v_EyeFragPos;
+varying vec3 v_EyeNormal;
+
+void main() {
+    vec3 normal = normalize(v_EyeNormal);
+    vec3 lightDir = -normalize(u_LitViewDir);
+    vec3 viewDir = normalize(-v_EyeFragPos);
+    vec3 halfDir = normalize(lightDir + viewDir);
+
+    vec3 diffuse = max(dot(normal, lightDir), 0.0) * u_Color.rgb;
+    vec3 specular = pow(max(dot(normal, halfDir), 0.0), u_Shininess) * u_Specular;
+
+    vec3 result = u_Ambient * u_Color.rgb + (diffuse + specular) * u_LitColor;
+    gl_FragColor = vec4(result, u_Color.a);
+}
@@ -0,0 +1,9 @@
+#version 100
+precision mediump float;
+
+varying vec3 v_EyeFragPos;
+varying vec3 v_EyeNormal;
+
+void main() {
+    gl_FragColor = vec4(normalize(v_EyeNormal) * 0.5 + 0.5, 1.0);
+}
@@ -0,0 +1,20 @@
+#version 100
+precision mediump float;
+
+attribute vec3 Position;
+attribute vec3 Normal;
+
+uniform mat4 u_MVPMatrix;
+uniform mat4 u_ModelViewMatrix;
+uniform mat4 u_ViewNormalMatrix;
+
+varying vec3 v_EyeFragPos;
+varying vec3 v_EyeNormal;
+
+void main() {
+    gl_Position = u_MVPMatrix * vec4(Position, 1.0);
+
+    vec4 eyePos = u_ModelViewMatrix * vec4(Position, 1.0);
+    v_EyeFragPos = eyePos.xyz / eyePos.w;
+    v_EyeNormal = vec3(u_ViewNormalMatrix * vec4(Normal, 0.0));
+}
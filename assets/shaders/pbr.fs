@@ -0,0 +1,67 @@
+#version 100
+precision mediump float;
+
+// The direction and color of a directional light in view space.
+uniform vec3 u_LitViewDir;
+uniform vec3 u_LitColor;
+
+uniform vec3 u_Ambient;
+uniform vec4 u_Color;
+uniform float u_Metallic;
+uniform float u_Roughness;
+
+varying vec3 v_EyeFragPos;
+varying vec3 v_EyeNormal;
+
+const float PI = 3.14159265;
+
+// GGX/Trowbridge-Reitz normal distribution.
+float DistributionGGX(float NdotH, float roughness)
+{
+    float a = roughness * roughness;
+    float a2 = a * a;
+    float d = NdotH * NdotH * (a2 - 1.0) + 1.0;
+    return a2 / max(PI * d * d, 0.0001);
+}
+
+// Smith's method with Schlick-GGX.
+float GeometrySmith(float NdotV, float NdotL, float roughness)
+{
+    float r = roughness + 1.0;
+    float k = (r * r) / 8.0;
+    float gv = NdotV / (NdotV * (1.0 - k) + k);
+    float gl = NdotL / (NdotL * (1.0 - k) + k);
+    return gv * gl;
+}
+
+vec3 FresnelSchlick(float cosTheta, vec3 F0)
+{
+    return F0 + (1.0 - F0) * pow(1.0 - cosTheta, 5.0);
+}
+
+void main() {
+    vec3 N = normalize(v_EyeNormal);
+    vec3 V = normalize(-v_EyeFragPos);
+    vec3 L = -normalize(u_LitViewDir);
+    vec3 H = normalize(V + L);
+
+    float NdotL = max(dot(N, L), 0.0);
+    float NdotV = max(dot(N, V), 0.0001);
+    float NdotH = max(dot(N, H), 0.0);
+
+    vec3 albedo = u_Color.rgb;
+    float roughness = clamp(u_Roughness, 0.04, 1.0);
+    vec3 F0 = mix(vec3(0.04), albedo, u_Metallic);
+
+    float D = DistributionGGX(NdotH, roughness);
+    float G = GeometrySmith(NdotV, NdotL, roughness);
+    vec3 F = FresnelSchlick(max(dot(H, V), 0.0), F0);
+
+    vec3 specular = D * G * F / max(4.0 * NdotV * NdotL, 0.0001);
+    vec3 kd = (vec3(1.0) - F) * (1.0 - u_Metallic);
+
+    vec3 result = (kd * albedo / PI + specular) * u_LitColor * NdotL;
+    result += u_Ambient * albedo;
+
+    gl_FragColor = vec4(result, u_Color.a);
+}
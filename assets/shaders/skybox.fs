@@ -0,0 +1,21 @@
+#version 100
+precision mediump float;
+
+uniform vec3 u_ZenithColor;
+uniform vec3 u_HorizonColor;
+uniform vec3 u_GroundColor;
+
+varying vec3 v_ViewRay;
+
+void main() {
+    float y = normalize(v_ViewRay).y;
+
+    vec3 color;
+    if (y > 0.0) {
+        color = mix(u_HorizonColor, u_ZenithColor, pow(y, 0.5));
+    } else {
+        color = mix(u_HorizonColor, u_GroundColor, pow(-y, 0.3));
+    }
+
+    gl_FragColor = vec4(color, 1.0);
+}
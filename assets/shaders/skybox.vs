@@ -0,0 +1,16 @@
+#version 100
+precision mediump float;
+
+attribute vec2 Position;
+
+uniform mat4 u_InvViewProjMatrix;
+
+varying vec3 v_ViewRay;
+
+void main() {
+    // Places the fullscreen triangle at far plane.
+    gl_Position = vec4(Position, 1.0, 1.0);
+
+    vec4 ray = u_InvViewProjMatrix * vec4(Position, 1.0, 1.0);
+    v_ViewRay = ray.xyz / ray.w;
+}
@@ -0,0 +1,11 @@
+#version 100
+precision mediump float;
+
+uniform sampler2D u_Texture;
+
+varying vec2 v_Texcoord;
+varying vec4 v_Color;
+
+void main() {
+    gl_FragColor = v_Color * texture2D(u_Texture, v_Texcoord);
+}
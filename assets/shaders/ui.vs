@@ -0,0 +1,17 @@
+#version 100
+precision mediump float;
+
+attribute vec2 Position;
+attribute vec2 Texcoord0;
+attribute vec4 Color0;
+
+uniform mat4 u_ProjMatrix;
+
+varying vec2 v_Texcoord;
+varying vec4 v_Color;
+
+void main() {
+    gl_Position = u_ProjMatrix * vec4(Position, 0.0, 1.0);
+    v_Texcoord = Texcoord0;
+    v_Color = Color0;
+}
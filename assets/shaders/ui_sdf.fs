@@ -0,0 +1,41 @@
+#version 100
+precision mediump float;
+
+uniform sampler2D u_Texture;
+uniform float u_Softness;
+uniform float u_OutlineWidth;
+uniform vec4 u_OutlineColor;
+uniform vec2 u_ShadowOffset;
+uniform float u_ShadowSoftness;
+uniform vec4 u_ShadowColor;
+
+varying vec2 v_Texcoord;
+varying vec4 v_Color;
+
+void main() {
+    // The edge of shape is at 0.5, and the outline grows outwards.
+    float d = texture2D(u_Texture, v_Texcoord).r;
+    float fill = smoothstep(0.5 - u_Softness, 0.5 + u_Softness, d);
+    vec4 color = vec4(v_Color.rgb, v_Color.a * fill);
+
+    // The outline is composited behind the shape, it's skipped entirely without width
+    // so the anti-aliased edges are not tinted with the outline color.
+    if (u_OutlineWidth > 0.0) {
+        float edge = 0.5 - u_OutlineWidth;
+        float outline = smoothstep(edge - u_Softness, edge + u_Softness, d);
+        outline *= u_OutlineColor.a * (1.0 - color.a);
+
+        float alpha = color.a + outline;
+        vec3 rgb = (color.rgb * color.a + u_OutlineColor.rgb * outline) / max(alpha, 0.0001);
+        color = vec4(rgb, alpha);
+    }
+
+    // Drop shadow or glow (with zero offset) behind the shape.
+    float s = texture2D(u_Texture, v_Texcoord - u_ShadowOffset).r;
+    float shadow = smoothstep(0.5 - u_ShadowSoftness, 0.5 + u_ShadowSoftness, s);
+    shadow *= u_ShadowColor.a * (1.0 - color.a);
+
+    float alpha = color.a + shadow;
+    vec3 rgb = (color.rgb * color.a + u_ShadowColor.rgb * shadow) / max(alpha, 0.0001);
+    gl_FragColor = vec4(rgb, alpha);
+}
@@ -0,0 +1,11 @@
+#version 100
+precision mediump float;
+
+uniform vec4 u_Color;
+uniform sampler2D u_Texture;
+
+varying vec2 v_Texcoord;
+
+void main() {
+    gl_FragColor = u_Color * texture2D(u_Texture, v_Texcoord);
+}
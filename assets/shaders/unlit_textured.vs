@@ -0,0 +1,14 @@
+#version 100
+precision mediump float;
+
+attribute vec3 Position;
+attribute vec2 Texcoord0;
+
+uniform mat4 u_MVPMatrix;
+
+varying vec2 v_Texcoord;
+
+void main() {
+    gl_Position = u_MVPMatrix * vec4(Position, 1.0);
+    v_Texcoord = Texcoord0;
+}
@@ -0,0 +1,14 @@
+#version 100
+precision mediump float;
+
+attribute vec3 Position;
+attribute vec4 Color0;
+
+uniform mat4 u_MVPMatrix;
+
+varying vec4 v_Color;
+
+void main() {
+    gl_Position = u_MVPMatrix * vec4(Position, 1.0);
+    v_Color = Color0;
+}
@@ -0,0 +1,135 @@
+//! Compares the allocation throughput of `ShardedHandlePool` with a `HandlePool` behind
+//! a mutex, when the handles are created and freed from multiple threads.
+
+#[macro_use]
+extern crate criterion;
+extern crate crayon;
+
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Barrier, Mutex};
+use std::thread;
+
+use crayon::utils::{Handle, HandlePool, ShardedHandlePool};
+use criterion::Criterion;
+
+const HANDLES_PER_THREAD: usize = 10_000;
+
+// The threads which create and free handles in every iteration. They are spawned
+// before the timing starts, so the iterations measure the allocations rather than the
+// creations of threads.
+struct Churn {
+    start: Arc<Barrier>,
+    end: Arc<Barrier>,
+    stop: Arc<AtomicBool>,
+    threads: Vec<thread::JoinHandle<()>>,
+}
+
+impl Churn {
+    fn new<C, F>(threads: usize, create: C, free: F) -> Self
+    where
+        C: Fn() -> Handle + Send + Sync + 'static,
+        F: Fn(Handle) + Send + Sync + 'static,
+    {
+        let start = Arc::new(Barrier::new(threads + 1));
+        let end = Arc::new(Barrier::new(threads + 1));
+        let stop = Arc::new(AtomicBool::new(false));
+        let funcs = Arc::new((create, free));
+
+        let threads = (0..threads)
+            .map(|_| {
+                let (start, end, stop) = (start.clone(), end.clone(), stop.clone());
+                let funcs = funcs.clone();
+
+                thread::spawn(move || {
+                    let mut handles = Vec::with_capacity(HANDLES_PER_THREAD);
+
+                    loop {
+                        start.wait();
+                        if stop.load(Ordering::Acquire) {
+                            return;
+                        }
+
+                        for _ in 0..HANDLES_PER_THREAD {
+                            handles.push((funcs.0)());
+                        }
+
+                        for v in handles.drain(..) {
+                            (funcs.1)(v);
+                        }
+
+                        end.wait();
+                    }
+                })
+            })
+            .collect();
+
+        Churn {
+            start: start,
+            end: end,
+            stop: stop,
+            threads: threads,
+        }
+    }
+
+    fn run(&self) {
+        self.start.wait();
+        self.end.wait();
+    }
+}
+
+impl Drop for Churn {
+    fn drop(&mut self) {
+        self.stop.store(true, Ordering::Release);
+        self.start.wait();
+
+        for v in self.threads.drain(..) {
+            v.join().unwrap();
+        }
+    }
+}
+
+fn mutex(c: &mut Criterion) {
+    c.bench_function_over_inputs(
+        "handle_pool_mutex",
+        |bench, &threads| {
+            let pool = Arc::new(Mutex::new(HandlePool::new()));
+            let (p1, p2) = (pool.clone(), pool.clone());
+            let churn = Churn::new(
+                threads,
+                move || p1.lock().unwrap().create(),
+                move |v| {
+                    p2.lock().unwrap().free(v);
+                },
+            );
+
+            bench.iter(|| churn.run())
+        },
+        vec![1, 2, 4, 8],
+    );
+}
+
+fn sharded(c: &mut Criterion) {
+    c.bench_function_over_inputs(
+        "handle_pool_sharded",
+        |bench, &threads| {
+            let pool = Arc::new(ShardedHandlePool::new(threads));
+            let (p1, p2) = (pool.clone(), pool.clone());
+            let churn = Churn::new(
+                threads,
+                move || p1.create(),
+                move |v| {
+                    p2.free(v);
+                },
+            );
+
+            bench.iter(|| {
+                churn.run();
+                pool.reconcile();
+            })
+        },
+        vec![1, 2, 4, 8],
+    );
+}
+
+criterion_group!(benches, mutex, sharded);
+criterion_main!(benches);
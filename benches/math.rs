@@ -0,0 +1,105 @@
+//! Compares the SIMD fast paths in `math::simd` with the generic implementations.
+
+#[macro_use]
+extern crate criterion;
+extern crate crayon;
+
+use criterion::Criterion;
+use crayon::math::prelude::*;
+use crayon::math::{self, simd, Aabb3, Frustum, PlaneRelation, Projection};
+
+fn matrix(i: usize) -> math::Matrix4<f32> {
+    let rotation = math::Quaternion::from_angle_y(math::Deg(i as f32 * 7.0));
+    let position = math::Vector3::new(i as f32, -(i as f32), i as f32 * 0.5);
+    math::Matrix4::from_translation(position) * math::Matrix4::from(rotation)
+}
+
+fn aabbs() -> Vec<Aabb3<f32>> {
+    (0..4096)
+        .map(|i| {
+            let min = math::Point3::new((i % 64) as f32 - 32.0, 0.0, (i / 64) as f32);
+            Aabb3::new(min, min + math::Vector3::new(1.0, 2.0, 1.0))
+        })
+        .collect()
+}
+
+fn frustum() -> Frustum<f32> {
+    Frustum::new(Projection::perspective(math::Deg(60.0).into(), 1.5, 0.1, 50.0))
+}
+
+fn mul_matrix4(c: &mut Criterion) {
+    let (a, b) = (matrix(1), matrix(2));
+    c.bench_function("mul_matrix4", move |bench| bench.iter(|| a * b));
+    c.bench_function("mul_matrix4_simd", move |bench| {
+        bench.iter(|| simd::mul_matrix4(&a, &b))
+    });
+}
+
+fn transform_points(c: &mut Criterion) {
+    let m = matrix(1);
+    let src: Vec<_> = (0..4096)
+        .map(|i| math::Point3::new(i as f32, 1.0, -(i as f32)))
+        .collect();
+
+    let points = src.clone();
+    c.bench_function("transform_points", move |bench| {
+        let mut dst = points.clone();
+        bench.iter(|| {
+            for (p, v) in points.iter().zip(dst.iter_mut()) {
+                *v = m.transform_point(*p);
+            }
+        })
+    });
+
+    c.bench_function("transform_points_simd", move |bench| {
+        let mut dst = src.clone();
+        bench.iter(|| simd::transform_points(&m, &src, &mut dst))
+    });
+}
+
+fn transform_aabbs(c: &mut Criterion) {
+    let m = matrix(1);
+    let src = aabbs();
+
+    let aabbs = src.clone();
+    c.bench_function("transform_aabbs", move |bench| {
+        bench.iter(|| aabbs.iter().map(|v| v.transform(&m)).collect::<Vec<_>>())
+    });
+
+    c.bench_function("transform_aabbs_simd", move |bench| {
+        bench.iter(|| {
+            src.iter()
+                .map(|v| simd::transform_aabb(&m, v))
+                .collect::<Vec<_>>()
+        })
+    });
+}
+
+fn culling(c: &mut Criterion) {
+    let frustum = frustum();
+    let src = aabbs();
+
+    let aabbs = src.clone();
+    c.bench_function("culling", move |bench| {
+        let mut dst = vec![PlaneRelation::Out; aabbs.len()];
+        bench.iter(|| {
+            for (v, relation) in aabbs.iter().zip(dst.iter_mut()) {
+                *relation = frustum.contains(v);
+            }
+        })
+    });
+
+    c.bench_function("culling_simd", move |bench| {
+        let mut dst = vec![PlaneRelation::Out; src.len()];
+        bench.iter(|| simd::contains_aabbs(&frustum, &src, &mut dst))
+    });
+}
+
+criterion_group!(
+    benches,
+    mul_matrix4,
+    transform_points,
+    transform_aabbs,
+    culling
+);
+criterion_main!(benches);
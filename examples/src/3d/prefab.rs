@@ -54,7 +54,7 @@ impl Window {
 
 impl Application for Window {
     fn on_update(&mut self, ctx: &Context) -> Result<()> {
-        self.world.advance();
+        self.world.advance()?;
 
         if let GesturePan::Move { movement, .. } = ctx.input.finger_pan() {
             let rotation = math::Euler::new(
@@ -0,0 +1,18 @@
+// The clip plane in view space, the fragments behind it are discarded.
+uniform vec4 u_ClipPlane;
+
+varying vec2 v_Local;
+varying vec4 v_Shape;
+varying vec4 v_Color;
+varying vec3 v_EyeFragPos;
+
+void main() {
+    if (dot(vec4(v_EyeFragPos, 1.0), u_ClipPlane) < 0.0) discard;
+
+    // The signed distance to the rounded rectangle.
+    vec2 q = abs(v_Local) - v_Shape.xy + v_Shape.z;
+    float d = length(max(q, 0.0)) + min(max(q.x, q.y), 0.0) - v_Shape.z;
+
+    float alpha = 1.0 - smoothstep(-max(v_Shape.w, 0.0001), 0.0, d);
+    gl_FragColor = vec4(v_Color.rgb, v_Color.a * alpha);
+}
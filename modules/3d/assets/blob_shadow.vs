@@ -0,0 +1,23 @@
+attribute vec3 Position;
+// The position on quad relative to its center.
+attribute vec2 Texcoord0;
+// The half extents (xy), the radius of corners (z) and the width of blurry edges (w).
+attribute vec4 Texcoord1;
+attribute vec4 Color0;
+
+uniform mat4 u_ViewMatrix;
+uniform mat4 u_ProjMatrix;
+
+varying vec2 v_Local;
+varying vec4 v_Shape;
+varying vec4 v_Color;
+varying vec3 v_EyeFragPos;
+
+void main() {
+    vec4 eyePos = u_ViewMatrix * vec4(Position, 1.0);
+    v_Local = Texcoord0;
+    v_Shape = Texcoord1;
+    v_Color = Color0;
+    v_EyeFragPos = eyePos.xyz;
+    gl_Position = u_ProjMatrix * eyePos;
+}
@@ -0,0 +1,22 @@
+uniform sampler2D u_Texture;
+uniform vec4 u_Color;
+// The scale (xy) and offset (zw) which map the world position to texcoords.
+uniform vec4 u_TilingOffset;
+// The distances to eye where the plane starts and ends fading out.
+uniform vec2 u_Fade;
+// The clip plane in view space, the fragments behind it are discarded.
+uniform vec4 u_ClipPlane;
+
+varying vec3 v_WorldPos;
+varying vec3 v_EyeFragPos;
+
+void main() {
+    if (dot(vec4(v_EyeFragPos, 1.0), u_ClipPlane) < 0.0) discard;
+
+    vec2 uv = v_WorldPos.xz * u_TilingOffset.xy + u_TilingOffset.zw;
+    vec4 color = texture2D(u_Texture, uv) * u_Color;
+
+    float distance = length(v_EyeFragPos);
+    float fade = (u_Fade.y - distance) / max(u_Fade.y - u_Fade.x, 0.0001);
+    gl_FragColor = vec4(color.rgb, color.a * clamp(fade, 0.0, 1.0));
+}
@@ -0,0 +1,14 @@
+attribute vec3 Position;
+
+uniform mat4 u_ViewMatrix;
+uniform mat4 u_ProjMatrix;
+
+varying vec3 v_WorldPos;
+varying vec3 v_EyeFragPos;
+
+void main() {
+    vec4 eyePos = u_ViewMatrix * vec4(Position, 1.0);
+    v_WorldPos = Position;
+    v_EyeFragPos = eyePos.xyz;
+    gl_Position = u_ProjMatrix * eyePos;
+}
@@ -0,0 +1,6 @@
+// The index of object encoded into RGB, see `encode_picking_id`.
+uniform vec4 u_Id;
+
+void main() {
+    gl_FragColor = u_Id;
+}
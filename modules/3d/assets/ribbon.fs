@@ -0,0 +1,13 @@
+uniform sampler2D u_Texture;
+// The clip plane in view space, the fragments behind it are discarded.
+uniform vec4 u_ClipPlane;
+
+varying vec2 v_Texcoord;
+varying vec4 v_Color;
+varying vec3 v_EyeFragPos;
+
+void main() {
+    if (dot(vec4(v_EyeFragPos, 1.0), u_ClipPlane) < 0.0) discard;
+
+    gl_FragColor = texture2D(u_Texture, v_Texcoord) * v_Color;
+}
@@ -0,0 +1,18 @@
+attribute vec3 Position;
+attribute vec2 Texcoord0;
+attribute vec4 Color0;
+
+uniform mat4 u_ViewMatrix;
+uniform mat4 u_ProjMatrix;
+
+varying vec2 v_Texcoord;
+varying vec4 v_Color;
+varying vec3 v_EyeFragPos;
+
+void main() {
+    vec4 eyePos = u_ViewMatrix * vec4(Position, 1.0);
+    v_Texcoord = Texcoord0;
+    v_Color = Color0;
+    v_EyeFragPos = eyePos.xyz;
+    gl_Position = u_ProjMatrix * eyePos;
+}
@@ -0,0 +1,13 @@
+varying float v_Depth;
+
+// Packs the depth in range [0, 1) into the channels of RGBA8 texture, since depth
+// textures are not available everywhere.
+vec4 PackDepth(float depth)
+{
+    vec4 v = fract(depth * vec4(1.0, 255.0, 65025.0, 16581375.0));
+    return v - v.yzww * vec4(1.0 / 255.0, 1.0 / 255.0, 1.0 / 255.0, 0.0);
+}
+
+void main() {
+    gl_FragColor = PackDepth(clamp(v_Depth, 0.0, 0.99999));
+}
@@ -0,0 +1,11 @@
+attribute vec3 Position;
+
+uniform mat4 u_MVPMatrix;
+
+varying float v_Depth;
+
+void main() {
+    gl_Position = u_MVPMatrix * vec4(Position, 1.0);
+    // The orthographic projection of light keeps w as 1.
+    v_Depth = gl_Position.z * 0.5 + 0.5;
+}
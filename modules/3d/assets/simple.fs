@@ -1,67 +1,316 @@
+#ifdef EYE_POSITION
 varying vec3 v_EyeFragPos;
+#endif
+
+#ifdef EYE_NORMAL
 varying vec3 v_EyeNormal;
-// varying vec2 v_Texcoord;
+#endif
+
+#ifdef NORMALMAP
+// The normals in tangent space.
+uniform sampler2D u_NormalMap;
+varying vec2 v_Texcoord;
+varying vec4 v_EyeTangent;
+#endif
 
+#ifdef UNIFORM_BUFFER
+// The camera and lights of current pass, which are uploaded once and shared by all
+// the drawcalls.
+layout(std140) uniform Camera {
+    mat4 u_InvViewMatrix;
+};
+
+layout(std140) uniform Lights {
+    vec3 u_DirLitViewDir[MAX_DIR_LITS];
+    vec3 u_DirLitColor[MAX_DIR_LITS];
+    // The linear and quadratic factors of attenuation are packed into the w.
+    vec4 u_PointLitViewPos[MAX_POINT_LITS];
+    vec4 u_PointLitColor[MAX_POINT_LITS];
+};
+#else
 uniform vec3 u_DirLitViewDir[MAX_DIR_LITS];
 uniform vec3 u_DirLitColor[MAX_DIR_LITS];
 
-uniform vec3 u_PointLitViewPos[MAX_POINT_LITS];
-uniform vec3 u_PointLitColor[MAX_POINT_LITS];
-uniform vec3 u_PointLitAttenuation[MAX_POINT_LITS];
+// The linear and quadratic factors of attenuation are packed into the w.
+uniform vec4 u_PointLitViewPos[MAX_POINT_LITS];
+uniform vec4 u_PointLitColor[MAX_POINT_LITS];
+
+uniform mat4 u_InvViewMatrix;
+#endif
 
 uniform vec3 u_Ambient;
 uniform vec3 u_Diffuse;
 uniform vec3 u_Specular;
 uniform float u_Shininess;
-// uniform sampler2D u_Texture;
+
+// The ambient radiance in spherical harmonics, interpolated from irradiance probes.
+// The 9 coefficients are packed tightly into 7 vec4s.
+uniform vec4 u_SH[7];
+
+// (mode, density, start, end), where mode is 0 (disabled), 1 (linear), 2 (exp) or 3 (exp2).
+uniform vec4 u_FogParams;
+uniform vec3 u_FogColor;
+
+#ifdef LIGHTMAP
+// The baked lighting of static lits and ambient.
+uniform sampler2D u_Lightmap;
+varying vec2 v_LightmapCoord;
+#endif
+
+#ifdef CLIP_PLANE
+// The clip plane in view space, the fragments behind it are discarded.
+uniform vec4 u_ClipPlane;
+#endif
+
+#ifdef WATER
+// The textures of planar reflection, the reflection is drawn by mirrored camera so
+// it's flipped horizontally.
+uniform sampler2D u_ReflectionTexture;
+uniform sampler2D u_RefractionTexture;
+// (strength, frequency, phase, fresnel power) of distortion.
+uniform vec4 u_WaterParams;
+uniform vec2 u_ScreenSize;
+#endif
+
+#ifdef SHADOWS
+#ifdef GL_FRAGMENT_PRECISION_HIGH
+#define SHADOW_PRECISION highp
+#else
+#define SHADOW_PRECISION mediump
+#endif
+
+// The atlas of cascades, which packs depths into RGBA.
+uniform sampler2D u_ShadowMap;
+// The matrices from world space into the tiles of cascades.
+uniform SHADOW_PRECISION mat4 u_ShadowMatrices[4];
+// The view distances where cascades end.
+uniform vec4 u_ShadowSplits;
+// (cascades, texel size, depth bias, soft).
+uniform SHADOW_PRECISION vec4 u_ShadowParams;
+#endif
+
+#ifdef EYE_NORMAL
+vec3 CalculateNormal()
+{
+    vec3 normal = normalize(v_EyeNormal);
+
+#ifdef NORMALMAP
+    // Builds the tangent space with the interpolated tangent, which is orthogonalized
+    // against the normal again.
+    vec3 tangent = normalize(v_EyeTangent.xyz - normal * dot(normal, v_EyeTangent.xyz));
+    vec3 bitangent = cross(normal, tangent) * v_EyeTangent.w;
+    vec3 n = texture2D(u_NormalMap, v_Texcoord).xyz * 2.0 - 1.0;
+    normal = normalize(mat3(tangent, bitangent, normal) * n);
+#endif
+
+    return normal;
+}
+#endif
+
+#ifdef LIT
+#ifdef DEBUG_LIGHTING_ONLY
+#define MAT_AMBIENT vec3(1.0, 1.0, 1.0)
+#define MAT_DIFFUSE vec3(1.0, 1.0, 1.0)
+#else
+#define MAT_AMBIENT u_Ambient
+#define MAT_DIFFUSE u_Diffuse
+#endif
 
 vec3 CalculateLight(vec3 normal, vec3 viewDir, vec3 lightDir, vec3 reflectDir, float shadow)
 {
-    vec3 diffuse = max(dot(normal, -lightDir), 0.0) * u_Diffuse;
+    vec3 diffuse = max(dot(normal, -lightDir), 0.0) * MAT_DIFFUSE;
     vec3 specular = pow(max(dot(viewDir, reflectDir), 0.0), u_Shininess) * u_Specular;
     return (1.0 - shadow) * (0.5 * diffuse + specular);
 }
 
-// float CalculateShadow(sampler2D shadowTexture, vec3 shadowPos, float bias)
-// {
-//     // transform to [0, 1] range.
-//     shadowPos = shadowPos * 0.5 + 0.5;
-//     float closestDepth = texture2D(shadowTexture, shadowPos.xy).r;
-//     return (shadowPos.z - bias) > closestDepth ? 0.5 : 0.0;
-// }
+// Evaluates the irradiance divided by PI with the normal in world space.
+vec3 CalculateSH(vec3 n)
+{
+    const float c1 = 0.429043;
+    const float c2 = 0.511664;
+    const float c3 = 0.743125;
+    const float c4 = 0.886227;
+    const float c5 = 0.247708;
+
+    vec3 l0 = u_SH[0].xyz;
+    vec3 l1 = vec3(u_SH[0].w, u_SH[1].xy);
+    vec3 l2 = vec3(u_SH[1].zw, u_SH[2].x);
+    vec3 l3 = u_SH[2].yzw;
+    vec3 l4 = u_SH[3].xyz;
+    vec3 l5 = vec3(u_SH[3].w, u_SH[4].xy);
+    vec3 l6 = vec3(u_SH[4].zw, u_SH[5].x);
+    vec3 l7 = u_SH[5].yzw;
+    vec3 l8 = u_SH[6].xyz;
+
+    vec3 irradiance =
+        c1 * l8 * (n.x * n.x - n.y * n.y) +
+        c3 * l6 * n.z * n.z +
+        c4 * l0 -
+        c5 * l6 +
+        2.0 * c1 * (l4 * n.x * n.y + l7 * n.x * n.z + l5 * n.y * n.z) +
+        2.0 * c2 * (l3 * n.x + l1 * n.y + l2 * n.z);
+
+    return max(irradiance / 3.14159265, vec3(0.0, 0.0, 0.0));
+}
+
+#ifdef SHADOWS
+float SampleShadow(SHADOW_PRECISION vec2 uv, SHADOW_PRECISION float depth)
+{
+    SHADOW_PRECISION vec4 rgba = texture2D(u_ShadowMap, uv);
+    SHADOW_PRECISION float closestDepth =
+        dot(rgba, vec4(1.0, 1.0 / 255.0, 1.0 / 65025.0, 1.0 / 16581375.0));
+    return depth > closestDepth ? 1.0 : 0.0;
+}
+
+// Picks the cascade with the view distance of fragment, and compares its depth with
+// the one in shadow map.
+float CalculateShadow(float bias)
+{
+    SHADOW_PRECISION vec4 worldPos = u_InvViewMatrix * vec4(v_EyeFragPos, 1.0);
+
+    for (int i = 0; i < 4; i++)
+    {
+        if (float(i) >= u_ShadowParams.x) break;
+
+        if (v_EyeFragPos.z <= u_ShadowSplits[i])
+        {
+            SHADOW_PRECISION vec3 p = (u_ShadowMatrices[i] * worldPos).xyz;
+            SHADOW_PRECISION float depth = p.z - u_ShadowParams.z - bias;
+
+            if (u_ShadowParams.w > 0.5)
+            {
+                SHADOW_PRECISION float t = 0.5 * u_ShadowParams.y;
+                return 0.25 * (
+                    SampleShadow(p.xy + vec2(-t, -t), depth) +
+                    SampleShadow(p.xy + vec2(t, -t), depth) +
+                    SampleShadow(p.xy + vec2(-t, t), depth) +
+                    SampleShadow(p.xy + vec2(t, t), depth));
+            }
+
+            return SampleShadow(p.xy, depth);
+        }
+    }
+
+    return 0.0;
+}
+#endif
+
+// Estimates the mipmap level with the distance to eye, since there is no texcoords
+// in this shader yet.
+vec3 CalculateMipmapTint()
+{
+    float level = log2(max(length(v_EyeFragPos), 1.0));
+    if (level < 1.0) return vec3(1.0, 1.0, 1.0);
+    if (level < 2.0) return vec3(0.0, 0.0, 1.0);
+    if (level < 3.0) return vec3(0.0, 1.0, 0.0);
+    if (level < 4.0) return vec3(1.0, 1.0, 0.0);
+    return vec3(1.0, 0.0, 0.0);
+}
+
+vec3 ApplyFog(vec3 color, float distance)
+{
+    float factor = 1.0;
+    if (u_FogParams.x > 2.5) {
+        float v = u_FogParams.y * distance;
+        factor = exp(-v * v);
+    } else if (u_FogParams.x > 1.5) {
+        factor = exp(-u_FogParams.y * distance);
+    } else if (u_FogParams.x > 0.5) {
+        factor = (u_FogParams.w - distance) / max(u_FogParams.w - u_FogParams.z, 0.0001);
+    }
+
+    return mix(u_FogColor, color, clamp(factor, 0.0, 1.0));
+}
+
+#ifdef WATER
+// Mixes the lit color with the reflection, the refraction is tinted by lighting.
+vec3 ApplyWater(vec3 color, vec3 normal, vec3 viewDir)
+{
+    vec3 p = vec3(u_InvViewMatrix * vec4(v_EyeFragPos, 1.0)) * u_WaterParams.y;
+    float t = u_WaterParams.z;
+
+    // Sums a few ripples along different directions.
+    vec2 offset = vec2(
+        sin(p.x + t) + sin((p.x + p.z) * 0.7 - 2.0 * t),
+        cos(p.z + t) + cos((p.x - p.z) * 0.8 + 2.0 * t)) * 0.5 * u_WaterParams.x;
+
+    vec2 uv = gl_FragCoord.xy / u_ScreenSize;
+    vec3 reflection = texture2D(u_ReflectionTexture, vec2(1.0 - uv.x, uv.y) + offset).rgb;
+    vec3 refraction = texture2D(u_RefractionTexture, uv + offset).rgb;
+
+    float fresnel = pow(1.0 - max(dot(-viewDir, normal), 0.0), u_WaterParams.w);
+    return mix(refraction * color, reflection, clamp(fresnel, 0.0, 1.0));
+}
+#endif
+#endif
 
 void main()
 {
-    vec3 normal = normalize(v_EyeNormal);
+#ifdef CLIP_PLANE
+    if (dot(vec4(v_EyeFragPos, 1.0), u_ClipPlane) < 0.0) discard;
+#endif
+
+#if defined(DEBUG_OVERDRAW)
+    gl_FragColor = vec4(0.1, 0.04, 0.02, 1.0);
+#elif defined(DEBUG_WIREFRAME)
+    gl_FragColor = vec4(0.0, 1.0, 0.0, 1.0);
+#elif defined(DEBUG_NORMALS)
+    gl_FragColor = vec4(CalculateNormal() * 0.5 + 0.5, 1.0);
+#else
+    vec3 normal = CalculateNormal();
     vec3 viewDir = normalize(v_EyeFragPos);
-    vec3 result = 0.2 * u_Ambient;
+
+#ifdef LIGHTMAP
+    // The ambient and directional lights are baked into lightmap, only the point
+    // lights are dynamic.
+    vec3 result = MAT_DIFFUSE * texture2D(u_Lightmap, v_LightmapCoord).rgb;
+#else
+    vec3 worldNormal = normalize(vec3(u_InvViewMatrix * vec4(normal, 0.0)));
+    vec3 result = 0.2 * MAT_AMBIENT * CalculateSH(worldNormal);
 
     // directional light
     for(int i = 0; i < MAX_DIR_LITS; i++)
     {
         // slope-scale depth bias
         float bias = max(0.005 * (1.0 - dot(normal, u_DirLitViewDir[i])), 0.0005);
-        // float shadow = CalculateShadow(scn_DirLitShadowTexture[i], v_DirLitShadowPos[i], bias);
+        float shadow = 0.0;
+#ifdef SHADOWS
+        // Only the first directional light casts shadows.
+        if (i == 0) shadow = CalculateShadow(bias);
+#endif
 
         vec3 reflectDir = reflect(-u_DirLitViewDir[i], normal);
-        // result += CalculateLight(normal, viewDir, u_DirLitViewDir[i], reflectDir, shadow) * u_DirLitColor[i];
-        result += CalculateLight(normal, viewDir, u_DirLitViewDir[i], reflectDir, 0.0) * u_DirLitColor[i];
+        result += CalculateLight(normal, viewDir, u_DirLitViewDir[i], reflectDir, shadow) * u_DirLitColor[i];
     }
+#endif
 
     // point lights
     for(int i = 0; i < MAX_POINT_LITS; i++)
     {
-        vec3 lightDir2 = normalize(v_EyeFragPos - u_PointLitViewPos[i]);
+        vec3 lightDir2 = normalize(v_EyeFragPos - u_PointLitViewPos[i].xyz);
         vec3 reflectDir2 = reflect(-lightDir2, normal);
-        float distance = length(u_PointLitViewPos[i] - v_EyeFragPos);
+        float distance = length(u_PointLitViewPos[i].xyz - v_EyeFragPos);
         float attenuation =
-            u_PointLitAttenuation[i].x +
-            u_PointLitAttenuation[i].y * distance +
-            u_PointLitAttenuation[i].z * (distance * distance);
+            1.0 +
+            u_PointLitViewPos[i].w * distance +
+            u_PointLitColor[i].w * (distance * distance);
 
-        vec3 power = CalculateLight(normal, viewDir, lightDir2, reflectDir2, 0.0) * u_PointLitColor[i];
+        vec3 power = CalculateLight(normal, viewDir, lightDir2, reflectDir2, 0.0) * u_PointLitColor[i].rgb;
         result += max(power * attenuation, vec3(0.0, 0.0, 0.0));
     }
 
+#ifdef WATER
+    result = ApplyWater(result, normal, viewDir);
+#endif
+
+#ifdef DEBUG_MIPMAP
+    result = mix(result, CalculateMipmapTint(), 0.5);
+#endif
+
+#ifdef FOG
+    result = ApplyFog(result, length(v_EyeFragPos));
+#endif
+
     gl_FragColor = vec4(result, 1.0);
+#endif
 }
\ No newline at end of file
@@ -1,20 +1,71 @@
 attribute vec3 Position;
+
+#ifdef EYE_NORMAL
 attribute vec3 Normal;
-// attribute vec2 Texcoord;
+uniform mat4 u_ViewNormalMatrix;
+varying vec3 v_EyeNormal;
+#endif
+
+#ifdef NORMALMAP
+attribute vec2 Texcoord0;
+// The tangent in xyz, and the handedness of bitangent in w.
+attribute vec4 Tangent;
+varying vec2 v_Texcoord;
+varying vec4 v_EyeTangent;
+#endif
+
+#ifdef SKINNED
+// The indices and weights of the 4 bones which affect the vertex.
+attribute vec4 Indices;
+attribute vec4 Weight;
+uniform mat4 u_BoneMatrices[MAX_SKIN_BONES];
+#endif
+
+#ifdef LIGHTMAP
+attribute vec2 Texcoord1;
+uniform vec4 u_LightmapScaleOffset;
+varying vec2 v_LightmapCoord;
+#endif
 
-uniform mat4 u_ModelViewMatrix;
 uniform mat4 u_MVPMatrix;
-uniform mat4 u_ViewNormalMatrix;
 
+#if defined(EYE_POSITION) || defined(NORMALMAP)
+uniform mat4 u_ModelViewMatrix;
+#endif
+
+#ifdef EYE_POSITION
 varying vec3 v_EyeFragPos;
-varying vec3 v_EyeNormal;
-// varying vec2 v_Texcoord;
+#endif
 
 void main() {
-    gl_Position = u_MVPMatrix * vec4(Position, 1.0);
+#ifdef SKINNED
+    mat4 skin =
+        u_BoneMatrices[int(Indices.x)] * Weight.x +
+        u_BoneMatrices[int(Indices.y)] * Weight.y +
+        u_BoneMatrices[int(Indices.z)] * Weight.z +
+        u_BoneMatrices[int(Indices.w)] * Weight.w;
+#else
+    mat4 skin = mat4(1.0);
+#endif
+
+    vec4 position = skin * vec4(Position, 1.0);
+    gl_Position = u_MVPMatrix * position;
 
-    vec4 eyePos = u_ModelViewMatrix * vec4(Position, 1.0);
+#ifdef EYE_POSITION
+    vec4 eyePos = u_ModelViewMatrix * position;
     v_EyeFragPos = eyePos.xyz / eyePos.w;
-    v_EyeNormal = vec3(u_ViewNormalMatrix * vec4(Normal, 0.0));
-    // v_Texcoord = Texcoord;
+#endif
+
+#ifdef EYE_NORMAL
+    v_EyeNormal = vec3(u_ViewNormalMatrix * (skin * vec4(Normal, 0.0)));
+#endif
+
+#ifdef NORMALMAP
+    v_Texcoord = Texcoord0;
+    v_EyeTangent = vec4(vec3(u_ModelViewMatrix * (skin * vec4(Tangent.xyz, 0.0))), Tangent.w);
+#endif
+
+#ifdef LIGHTMAP
+    v_LightmapCoord = Texcoord1 * u_LightmapScaleOffset.xy + u_LightmapScaleOffset.zw;
+#endif
 }
\ No newline at end of file
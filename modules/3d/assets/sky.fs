@@ -0,0 +1,29 @@
+varying vec3 v_ViewRay;
+
+uniform vec3 u_SunDir;
+uniform vec3 u_SunColor;
+uniform float u_SunSize;
+
+uniform vec3 u_ZenithColor;
+uniform vec3 u_HorizonColor;
+uniform vec3 u_GroundColor;
+
+void main()
+{
+    vec3 dir = normalize(v_ViewRay);
+
+    vec3 color;
+    if (dir.y > 0.0) {
+        color = mix(u_HorizonColor, u_ZenithColor, sqrt(dir.y));
+    } else {
+        color = mix(u_HorizonColor, u_GroundColor, sqrt(-dir.y));
+    }
+
+    // sun disk and its glow.
+    float cosine = dot(dir, u_SunDir);
+    float disk = smoothstep(cos(u_SunSize), cos(u_SunSize * 0.8), cosine);
+    float glow = pow(max(cosine, 0.0), 64.0) * 0.5;
+    color += u_SunColor * (disk + glow);
+
+    gl_FragColor = vec4(color, 1.0);
+}
@@ -0,0 +1,19 @@
+#ifdef CROSS_FADE
+uniform sampler2D u_From;
+uniform sampler2D u_To;
+uniform float u_Factor;
+#else
+uniform vec4 u_Color;
+#endif
+
+varying vec2 v_Texcoord;
+
+void main() {
+#ifdef CROSS_FADE
+    vec3 from = texture2D(u_From, v_Texcoord).rgb;
+    vec3 to = texture2D(u_To, v_Texcoord).rgb;
+    gl_FragColor = vec4(mix(from, to, u_Factor), 1.0);
+#else
+    gl_FragColor = u_Color;
+#endif
+}
@@ -0,0 +1,8 @@
+attribute vec2 Position;
+
+varying vec2 v_Texcoord;
+
+void main() {
+    gl_Position = vec4(Position, 0.0, 1.0);
+    v_Texcoord = Position * 0.5 + 0.5;
+}
@@ -0,0 +1,33 @@
+uniform sampler2D u_Source;
+uniform vec2 u_UVScale;
+uniform vec2 u_TexelSize;
+uniform float u_Sharpness;
+
+varying vec2 v_Texcoord;
+
+vec3 Fetch(vec2 uv) {
+    // Clamps to the valid region, so the garbages outside never bleed in.
+    return texture2D(u_Source, clamp(uv, 0.5 * u_TexelSize, u_UVScale - 0.5 * u_TexelSize)).rgb;
+}
+
+void main() {
+    vec3 c = Fetch(v_Texcoord);
+
+    if (u_Sharpness > 0.0) {
+        vec3 n = Fetch(v_Texcoord + vec2(0.0, u_TexelSize.y));
+        vec3 s = Fetch(v_Texcoord - vec2(0.0, u_TexelSize.y));
+        vec3 e = Fetch(v_Texcoord + vec2(u_TexelSize.x, 0.0));
+        vec3 w = Fetch(v_Texcoord - vec2(u_TexelSize.x, 0.0));
+
+        // Contrast adaptive sharpening, which sharpens less on the edges with high
+        // contrast to avoid ringing.
+        vec3 mn = min(c, min(min(n, s), min(e, w)));
+        vec3 mx = max(c, max(max(n, s), max(e, w)));
+        vec3 amp = sqrt(clamp(min(mn, 1.0 - mx) / max(mx, vec3(0.0001)), 0.0, 1.0));
+        vec3 weight = -amp * mix(0.125, 0.2, u_Sharpness);
+
+        c = (c + (n + s + e + w) * weight) / (1.0 + 4.0 * weight);
+    }
+
+    gl_FragColor = vec4(c, 1.0);
+}
@@ -0,0 +1,12 @@
+attribute vec2 Position;
+
+uniform vec2 u_UVScale;
+
+varying vec2 v_Texcoord;
+
+void main() {
+    gl_Position = vec4(Position, 0.0, 1.0);
+
+    // Only the scaled region of intermediate target is valid.
+    v_Texcoord = (Position * 0.5 + 0.5) * u_UVScale;
+}
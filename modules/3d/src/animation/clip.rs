@@ -0,0 +1,152 @@
+//! Animation clips, which are the keyframes of bones and the events along the timeline.
+
+use crayon::errors::*;
+
+use scene::Transform;
+
+/// The transform of bone at a point of time.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy)]
+pub struct Keyframe {
+    /// The time in seconds.
+    pub time: f32,
+    pub transform: Transform,
+}
+
+/// The keyframes of a bone, which are sorted by time.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct Track {
+    pub bone: String,
+    keys: Vec<Keyframe>,
+}
+
+impl Track {
+    #[inline]
+    pub fn keys(&self) -> &[Keyframe] {
+        &self.keys
+    }
+
+    /// Samples the local transform of bone at `time`, the keyframes are interpolated
+    /// linearly.
+    pub fn sample(&self, time: f32) -> Option<Transform> {
+        let (first, last) = match (self.keys.first(), self.keys.last()) {
+            (Some(first), Some(last)) => (first, last),
+            _ => return None,
+        };
+
+        if time <= first.time {
+            return Some(first.transform);
+        }
+
+        if time >= last.time {
+            return Some(last.transform);
+        }
+
+        let next = self.keys.iter().position(|v| v.time > time).unwrap();
+        let (lhs, rhs) = (&self.keys[next - 1], &self.keys[next]);
+        let t = (time - lhs.time) / (rhs.time - lhs.time);
+        Some(lhs.transform.lerp(&rhs.transform, t))
+    }
+}
+
+/// An event which is emitted when the playback crosses its time, e.g. footsteps, hit
+/// frames and visual effects.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct AnimationEvent {
+    /// The normalized time ranging from 0.0 to 1.0.
+    pub time: f32,
+    pub name: String,
+    pub payload: String,
+}
+
+/// `AnimationClip` is a piece of animation, which could be played by `Animator`s.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct AnimationClip {
+    /// The length in seconds.
+    pub duration: f32,
+    tracks: Vec<Track>,
+    events: Vec<AnimationEvent>,
+}
+
+impl AnimationClip {
+    pub fn new(duration: f32) -> Self {
+        AnimationClip {
+            duration: duration,
+            tracks: Vec::new(),
+            events: Vec::new(),
+        }
+    }
+
+    /// Adds the keyframes of bone, which must be sorted by time.
+    pub fn add_track<T: Into<String>>(&mut self, bone: T, keys: Vec<Keyframe>) -> Result<()> {
+        let bone = bone.into();
+        for w in keys.windows(2) {
+            if w[0].time > w[1].time {
+                bail!("The keyframes of bone {} are not sorted by time.", bone);
+            }
+        }
+
+        self.tracks.retain(|v| v.bone != bone);
+        self.tracks.push(Track {
+            bone: bone,
+            keys: keys,
+        });
+
+        Ok(())
+    }
+
+    /// Adds an event at normalized `time`. The events at the same time are emitted in
+    /// the order they are added.
+    pub fn add_event<T1, T2>(&mut self, time: f32, name: T1, payload: T2) -> Result<()>
+    where
+        T1: Into<String>,
+        T2: Into<String>,
+    {
+        if time < 0.0 || time > 1.0 {
+            bail!("The normalized time {} of event is out of range.", time);
+        }
+
+        let index = self.events
+            .iter()
+            .position(|v| v.time > time)
+            .unwrap_or(self.events.len());
+
+        self.events.insert(
+            index,
+            AnimationEvent {
+                time: time,
+                name: name.into(),
+                payload: payload.into(),
+            },
+        );
+
+        Ok(())
+    }
+
+    #[inline]
+    pub fn tracks(&self) -> &[Track] {
+        &self.tracks
+    }
+
+    /// Gets the events sorted by time.
+    #[inline]
+    pub fn events(&self) -> &[AnimationEvent] {
+        &self.events
+    }
+
+    /// Collects the events in normalized range `[from, to)`, or `[from, to]` if
+    /// `inclusive`.
+    pub(crate) fn collect_events(
+        &self,
+        from: f32,
+        to: f32,
+        inclusive: bool,
+        events: &mut Vec<AnimationEvent>,
+    ) {
+        for v in &self.events {
+            let before = if inclusive { v.time <= to } else { v.time < to };
+            if v.time >= from && before {
+                events.push(v.clone());
+            }
+        }
+    }
+}
@@ -0,0 +1,236 @@
+//! Inverse kinematics, which adjusts the animated pose so the feet could be planted on
+//! terrain and the heads could track targets.
+//!
+//! The chains of bones are configured per `Skeleton`, and the targets are set per
+//! `Animator`. The chains are solved in the order they are added, after the clips are
+//! sampled, and blended with the animated pose by the weights of targets.
+
+use crayon::math::{self, InnerSpace, Rotation3};
+
+use scene::Transform;
+
+/// The solvers of inverse kinematics.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+pub enum IkSolver {
+    /// Rotates `upper` and `lower`, so the `end` reaches the target, e.g. arms and legs.
+    /// The `lower` must be the child of `upper`, and the `end` must be the child of
+    /// `lower`.
+    TwoBone {
+        upper: usize,
+        lower: usize,
+        end: usize,
+    },
+    /// Rotates the bone so its local `forward` axis points at the target, e.g. heads and
+    /// weapons. The rotation is clamped to `limit` in radians.
+    LookAt {
+        bone: usize,
+        forward: math::Vector3<f32>,
+        limit: f32,
+    },
+}
+
+/// A named chain of bones which is solved with `solver`.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct IkChain {
+    pub name: String,
+    pub solver: IkSolver,
+}
+
+/// The goal of an IK chain.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct IkTarget {
+    /// The position in world space.
+    pub position: math::Vector3<f32>,
+    /// The position in world space which the middle joint of two-bone chains bends
+    /// towards. The bending plane of animated pose is kept if its `None`.
+    pub pole: Option<math::Vector3<f32>>,
+    /// The weight of solved pose, which is blended with the animated pose.
+    pub weight: f32,
+}
+
+impl IkTarget {
+    pub fn new<T: Into<math::Vector3<f32>>>(position: T) -> Self {
+        IkTarget {
+            position: position.into(),
+            pole: None,
+            weight: 1.0,
+        }
+    }
+}
+
+const EPSILON: f32 = 1e-5;
+
+/// Solves the chain. The target is in the space of entity, and the `bones` are the
+/// transforms of bones relative to the entity from `locals`, which are updated.
+pub(crate) fn solve(
+    solver: &IkSolver,
+    target: &IkTarget,
+    locals: &mut [Transform],
+    bones: &[Transform],
+) {
+    let weight = target.weight.max(0.0).min(1.0);
+    if weight <= 0.0 {
+        return;
+    }
+
+    match *solver {
+        IkSolver::TwoBone { upper, lower, end } => {
+            let (a, b, c) = (bones[upper], bones[lower], bones[end]);
+            let t = target.position;
+
+            let lab = (b.position - a.position).magnitude();
+            let lcb = (c.position - b.position).magnitude();
+            if lab < EPSILON || lcb < EPSILON {
+                return;
+            }
+
+            let ac = c.position - a.position;
+            let at = t - a.position;
+            let lat = at.magnitude().max(EPSILON).min(lab + lcb - EPSILON);
+            if ac.magnitude() < EPSILON || at.magnitude() < EPSILON {
+                return;
+            }
+
+            // The interior angles of current and solved triangles.
+            let ac_ab_0 = angle(ac, b.position - a.position);
+            let ba_bc_0 = angle(a.position - b.position, c.position - b.position);
+            let ac_at_0 = angle(ac, at);
+            let ac_ab_1 = acos((lcb * lcb - lab * lab - lat * lat) / (-2.0 * lab * lat));
+            let ba_bc_1 = acos((lat * lat - lab * lab - lcb * lcb) / (-2.0 * lab * lcb));
+
+            let bend = bending_axis(ac, b.position - a.position, t, &a);
+            let r0 = math::Quaternion::from_axis_angle(bend, math::Rad(ac_ab_1 - ac_ab_0));
+            let r1 = math::Quaternion::from_axis_angle(bend, math::Rad(ba_bc_1 - ba_bc_0));
+            let r2 = match normalize(ac.cross(at)) {
+                Some(axis) => math::Quaternion::from_axis_angle(axis, math::Rad(ac_at_0)),
+                None => math::Quaternion::from_arc(ac.normalize(), at.normalize(), None),
+            };
+
+            let mut ra = r2 * r0;
+
+            // Twists the chain around the target direction, so the middle joint bends
+            // towards the pole.
+            if let Some(pole) = target.pole {
+                let axis = at.normalize();
+                let mid = ra * (b.position - a.position);
+                let lhs = mid - axis * mid.dot(axis);
+                let rhs = (pole - a.position) - axis * (pole - a.position).dot(axis);
+
+                if let (Some(lhs), Some(rhs)) = (normalize(lhs), normalize(rhs)) {
+                    let sign = if lhs.cross(rhs).dot(axis) < 0.0 {
+                        -1.0
+                    } else {
+                        1.0
+                    };
+
+                    let twist = math::Rad(sign * angle(lhs, rhs));
+                    ra = math::Quaternion::from_axis_angle(axis, twist) * ra;
+                }
+            }
+
+            rotate(&mut locals[upper], &a, ra, weight);
+            rotate(&mut locals[lower], &b, r1, weight);
+        }
+
+        IkSolver::LookAt {
+            bone,
+            forward,
+            limit,
+        } => {
+            let v = bones[bone];
+            let current = match normalize(v.rotation * forward) {
+                Some(dir) => dir,
+                None => return,
+            };
+
+            let desired = match normalize(target.position - v.position) {
+                Some(dir) => dir,
+                None => return,
+            };
+
+            let theta = angle(current, desired);
+            if theta < EPSILON {
+                return;
+            }
+
+            // Rotates by `limit` at most.
+            let theta = math::Rad(theta.min(limit.max(0.0)));
+            let r = match normalize(current.cross(desired)) {
+                Some(axis) => math::Quaternion::from_axis_angle(axis, theta),
+                None => math::Quaternion::from_arc(current, desired, None),
+            };
+
+            rotate(&mut locals[bone], &v, r, weight);
+        }
+    }
+}
+
+/// Transforms the position in world space into the space of entity.
+pub(crate) fn to_local(world: &Transform, v: math::Vector3<f32>) -> math::Vector3<f32> {
+    let scale = if world.scale.abs() > EPSILON {
+        world.scale
+    } else {
+        1.0
+    };
+
+    world.rotation.conjugate() * (v - world.position) / scale
+}
+
+// Applies the rotation `r` in the space of entity to the bone, whose transform relative
+// to entity is `v`.
+fn rotate(local: &mut Transform, v: &Transform, r: math::Quaternion<f32>, weight: f32) {
+    // The parent rotation P = V * L^-1, so the local rotation is L' = P^-1 * R * V.
+    let solved = local.rotation * v.rotation.conjugate() * r * v.rotation;
+    local.rotation = if weight >= 1.0 {
+        solved
+    } else {
+        let mut solved = solved;
+        if local.rotation.dot(solved) < 0.0 {
+            solved = -solved;
+        }
+
+        local.rotation.nlerp(solved, weight)
+    };
+}
+
+// The axis to bend the chain around, which is perpendicular to the plane of chain.
+fn bending_axis(
+    ac: math::Vector3<f32>,
+    ab: math::Vector3<f32>,
+    t: math::Vector3<f32>,
+    a: &Transform,
+) -> math::Vector3<f32> {
+    if let Some(axis) = normalize(ac.cross(ab)) {
+        return axis;
+    }
+
+    // The chain is straight, bends it in any plane which contains the target.
+    let at = t - a.position;
+    normalize(ac.cross(at))
+        .or_else(|| normalize(ac.cross(math::Vector3::new(1.0, 0.0, 0.0))))
+        .or_else(|| normalize(ac.cross(math::Vector3::new(0.0, 1.0, 0.0))))
+        .unwrap()
+}
+
+#[inline]
+fn normalize(v: math::Vector3<f32>) -> Option<math::Vector3<f32>> {
+    let len = v.magnitude();
+    if len > EPSILON {
+        Some(v / len)
+    } else {
+        None
+    }
+}
+
+#[inline]
+fn acos(v: f32) -> f32 {
+    v.max(-1.0).min(1.0).acos()
+}
+
+#[inline]
+fn angle(lhs: math::Vector3<f32>, rhs: math::Vector3<f32>) -> f32 {
+    match (normalize(lhs), normalize(rhs)) {
+        (Some(lhs), Some(rhs)) => acos(lhs.dot(rhs)),
+        _ => 0.0,
+    }
+}
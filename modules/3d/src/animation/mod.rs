@@ -0,0 +1,759 @@
+//! Skeletal animations, and the sockets on bones, e.g. the hands which hold weapons
+//! and props.
+//!
+//! `Animator` keeps the local pose of a skeleton, which is sampled from the playing
+//! `AnimationClip` or written by users. `Animators::advance` resolves the bones and
+//! sockets after that every frame. The entities attached to a socket follow it, so they
+//! move with the animated character.
+//!
+//! The events of clips are emitted when the playback crosses them, and they should be
+//! taken with `Animators::take_events` every frame. The root motion could be extracted
+//! with `Animator::enable_root_motion`, which is applied by character controllers.
+//!
+//! The IK chains of skeleton are solved after sampling, towards the targets set with
+//! `Animator::set_ik_target`, e.g. to plant feet on the terrain.
+//!
+//! ```rust,ignore
+//! let mut animator = Animator::new(skeleton);
+//! animator.add_socket("hand_r", "RightHand", Transform::default())?;
+//! animator.play(walk, true);
+//! world.animators.add(character, animator);
+//! world.animators.attach(&mut world.scene, character, "hand_r", sword)?;
+//!
+//! // Every frame.
+//! world.animators.advance(&mut world.scene, ctx.time.frame_delta());
+//! for (ent, event) in world.animators.take_events() {
+//!     if event.name == "footstep" {
+//!         play_sound(ent, &event.payload);
+//!     }
+//! }
+//! ```
+
+pub mod clip;
+pub use self::clip::{AnimationClip, AnimationEvent, Keyframe, Track};
+
+pub mod root_motion;
+pub use self::root_motion::RootMotion;
+
+pub mod ik;
+pub use self::ik::{IkChain, IkSolver, IkTarget};
+
+use std::hash::Hash;
+use std::mem;
+use std::sync::Arc;
+use std::time::Duration;
+
+use crayon::errors::*;
+use crayon::math;
+use crayon::utils::Checksum;
+
+use entity_ref::EntityRemap;
+use scene::{SceneGraph, Transform};
+use {Component, Entity};
+
+/// A bone of skeleton.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct Bone {
+    pub name: String,
+    /// The index of parent bone, which always comes before its children.
+    pub parent: Option<usize>,
+    /// The transform relative to parent in bind pose.
+    pub local: Transform,
+}
+
+/// The hierarchy of bones, which could be shared by animators.
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+pub struct Skeleton {
+    bones: Vec<Bone>,
+    ik: Vec<IkChain>,
+}
+
+impl Skeleton {
+    pub fn new() -> Self {
+        Skeleton {
+            bones: Vec::new(),
+            ik: Vec::new(),
+        }
+    }
+
+    /// Adds a bone and returns its index. The parent must be added before its children.
+    pub fn add<T: Into<String>>(
+        &mut self,
+        name: T,
+        parent: Option<usize>,
+        local: Transform,
+    ) -> Result<usize> {
+        let name = name.into();
+        if self.find(&name).is_some() {
+            bail!("Bone {} already exists in skeleton.", name);
+        }
+
+        if let Some(parent) = parent {
+            if parent >= self.bones.len() {
+                bail!("The parent of bone {} is out of bounds.", name);
+            }
+        }
+
+        self.bones.push(Bone {
+            name: name,
+            parent: parent,
+            local: local,
+        });
+
+        Ok(self.bones.len() - 1)
+    }
+
+    /// Finds the index of bone by name.
+    pub fn find(&self, name: &str) -> Option<usize> {
+        self.bones.iter().position(|v| v.name == name)
+    }
+
+    /// Gets the transforms of bones relative to the entity in bind pose.
+    pub fn bind_pose(&self) -> Vec<Transform> {
+        let mut pose: Vec<Transform> = Vec::with_capacity(self.bones.len());
+        for bone in &self.bones {
+            let v = match bone.parent {
+                Some(parent) => concat(&pose[parent], &bone.local),
+                None => bone.local,
+            };
+
+            pose.push(v);
+        }
+
+        pose
+    }
+
+    /// Adds a two-bone IK chain, e.g. arms and legs. The `lower` must be the child of
+    /// `upper`, and the `end` must be the child of `lower`.
+    pub fn add_two_bone_ik<T: Into<String>>(
+        &mut self,
+        name: T,
+        upper: &str,
+        lower: &str,
+        end: &str,
+    ) -> Result<()> {
+        let upper = self.bone_index(upper)?;
+        let lower = self.bone_index(lower)?;
+        let end = self.bone_index(end)?;
+
+        if self.bones[lower].parent != Some(upper) || self.bones[end].parent != Some(lower) {
+            bail!("The bones of two-bone IK chain are not connected.");
+        }
+
+        let solver = IkSolver::TwoBone {
+            upper: upper,
+            lower: lower,
+            end: end,
+        };
+
+        self.add_ik(name.into(), solver);
+        Ok(())
+    }
+
+    /// Adds a look-at IK chain, which rotates the local `forward` axis of bone towards the
+    /// target by `limit` radians at most, e.g. heads.
+    pub fn add_look_at_ik<T1, T2>(
+        &mut self,
+        name: T1,
+        bone: &str,
+        forward: T2,
+        limit: f32,
+    ) -> Result<()>
+    where
+        T1: Into<String>,
+        T2: Into<math::Vector3<f32>>,
+    {
+        let solver = IkSolver::LookAt {
+            bone: self.bone_index(bone)?,
+            forward: forward.into(),
+            limit: limit,
+        };
+
+        self.add_ik(name.into(), solver);
+        Ok(())
+    }
+
+    /// Finds the index of IK chain by name.
+    pub fn find_ik(&self, name: &str) -> Option<usize> {
+        self.ik.iter().position(|v| v.name == name)
+    }
+
+    /// Gets the IK chains in the order they are solved.
+    #[inline]
+    pub fn ik_chains(&self) -> &[IkChain] {
+        &self.ik
+    }
+
+    #[inline]
+    pub fn bones(&self) -> &[Bone] {
+        &self.bones
+    }
+
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.bones.len()
+    }
+
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.bones.is_empty()
+    }
+
+    fn bone_index(&self, name: &str) -> Result<usize> {
+        match self.find(name) {
+            Some(v) => Ok(v),
+            None => bail!("Bone {} does not exist in skeleton.", name),
+        }
+    }
+
+    fn add_ik(&mut self, name: String, solver: IkSolver) {
+        self.ik.retain(|v| v.name != name);
+        self.ik.push(IkChain {
+            name: name,
+            solver: solver,
+        });
+    }
+}
+
+/// A named attachment point, which is placed relative to a bone.
+#[derive(Debug, Clone)]
+struct Socket {
+    name: String,
+    bone: usize,
+    offset: Transform,
+    world: Transform,
+}
+
+/// The state of playing clip.
+#[derive(Debug, Clone)]
+struct Playback {
+    clip: Arc<AnimationClip>,
+    // The bones of tracks.
+    bindings: Vec<Option<usize>>,
+    // The elapsed time in seconds.
+    time: f32,
+    looping: bool,
+    finished: bool,
+}
+
+/// The setup of root motion extraction.
+#[derive(Debug, Clone, Copy)]
+struct RootMotionSetup {
+    bone: usize,
+    remove: bool,
+}
+
+/// `Animator` keeps the pose of skeleton on entity.
+#[derive(Debug, Clone)]
+pub struct Animator {
+    skeleton: Arc<Skeleton>,
+    pose: Vec<Transform>,
+    // The local pose with IK applied.
+    locals: Vec<Transform>,
+    // The transforms of bones relative to the entity.
+    bones: Vec<Transform>,
+    // The targets of IK chains in skeleton.
+    targets: Vec<Option<IkTarget>>,
+    sockets: Vec<Socket>,
+    playback: Option<Playback>,
+    speed: f32,
+    root_motion: Option<RootMotionSetup>,
+    motion: RootMotion,
+}
+
+impl Animator {
+    /// Creates a new `Animator` in the bind pose of skeleton.
+    pub fn new(skeleton: Arc<Skeleton>) -> Self {
+        let pose: Vec<_> = skeleton.bones.iter().map(|v| v.local).collect();
+        let targets = vec![None; skeleton.ik.len()];
+        let mut animator = Animator {
+            skeleton: skeleton,
+            locals: pose.clone(),
+            bones: pose.clone(),
+            pose: pose,
+            targets: targets,
+            sockets: Vec::new(),
+            playback: None,
+            speed: 1.0,
+            root_motion: None,
+            motion: RootMotion::default(),
+        };
+
+        animator.resolve();
+        animator
+    }
+
+    #[inline]
+    pub fn skeleton(&self) -> &Arc<Skeleton> {
+        &self.skeleton
+    }
+
+    /// Gets the local transform of bone in current pose.
+    #[inline]
+    pub fn local_pose(&self, bone: usize) -> Option<Transform> {
+        self.pose.get(bone).cloned()
+    }
+
+    /// Sets the local transform of bone, which is usually sampled from animations.
+    #[inline]
+    pub fn set_local_pose(&mut self, bone: usize, transform: Transform) {
+        if let Some(v) = self.pose.get_mut(bone) {
+            *v = transform;
+        }
+    }
+
+    /// Resets the pose into the bind pose of skeleton.
+    pub fn reset_pose(&mut self) {
+        for (v, bone) in self.pose.iter_mut().zip(self.skeleton.bones.iter()) {
+            *v = bone.local;
+        }
+    }
+
+    /// Plays the clip from the beginning, the tracks of bones which do not exist in
+    /// skeleton are ignored.
+    pub fn play(&mut self, clip: Arc<AnimationClip>, looping: bool) {
+        let bindings = clip.tracks()
+            .iter()
+            .map(|v| self.skeleton.find(&v.bone))
+            .collect();
+
+        self.playback = Some(Playback {
+            clip: clip,
+            bindings: bindings,
+            time: 0.0,
+            looping: looping,
+            finished: false,
+        });
+    }
+
+    /// Stops the playback, the current pose is kept.
+    #[inline]
+    pub fn stop(&mut self) {
+        self.playback = None;
+    }
+
+    /// Returns true if a clip is playing. A clip which is not looping stops at its end.
+    #[inline]
+    pub fn is_playing(&self) -> bool {
+        self.playback.as_ref().map(|v| !v.finished).unwrap_or(false)
+    }
+
+    /// Gets the normalized time of playing clip.
+    pub fn normalized_time(&self) -> Option<f32> {
+        self.playback.as_ref().map(|v| {
+            if v.clip.duration > 0.0 {
+                v.time / v.clip.duration
+            } else {
+                1.0
+            }
+        })
+    }
+
+    #[inline]
+    pub fn speed(&self) -> f32 {
+        self.speed
+    }
+
+    /// Sets the playback speed, which must be non-negative.
+    #[inline]
+    pub fn set_speed(&mut self, speed: f32) {
+        self.speed = speed.max(0.0);
+    }
+
+    /// Extracts the motion of root bone from the playing clips. If `remove` is true, the
+    /// motion is removed from the pose, so the root stays in place until the extracted
+    /// motion is applied to the entity.
+    pub fn enable_root_motion(&mut self, bone: &str, remove: bool) -> Result<()> {
+        let bone = match self.skeleton.find(bone) {
+            Some(v) => v,
+            None => bail!("Bone {} does not exist in skeleton.", bone),
+        };
+
+        self.root_motion = Some(RootMotionSetup {
+            bone: bone,
+            remove: remove,
+        });
+
+        Ok(())
+    }
+
+    #[inline]
+    pub fn disable_root_motion(&mut self) {
+        self.root_motion = None;
+        self.motion = RootMotion::default();
+    }
+
+    /// Gets the root motion extracted during last `Animators::advance`.
+    #[inline]
+    pub fn root_motion(&self) -> RootMotion {
+        self.motion
+    }
+
+    /// Sets the target of IK chain, which is solved every `Animators::advance` until
+    /// it is cleared.
+    pub fn set_ik_target(&mut self, name: &str, target: IkTarget) -> Result<()> {
+        match self.skeleton.find_ik(name) {
+            Some(v) => self.targets[v] = Some(target),
+            None => bail!("IK chain {} does not exist in skeleton.", name),
+        }
+
+        Ok(())
+    }
+
+    /// Clears the target of IK chain, so the bones follow the animated pose.
+    pub fn clear_ik_target(&mut self, name: &str) {
+        if let Some(v) = self.skeleton.find_ik(name) {
+            self.targets[v] = None;
+        }
+    }
+
+    /// Gets the transform of bone relative to the entity, as of last `Animators::advance`.
+    pub fn bone(&self, name: &str) -> Option<Transform> {
+        self.skeleton.find(name).map(|v| self.bones[v])
+    }
+
+    /// Gets the skinning matrices of bones in current pose, which transform the vertices
+    /// from bind pose, e.g. for `SimpleRenderer::set_skin`.
+    pub fn skin_matrices(&self) -> Vec<math::Matrix4<f32>> {
+        use crayon::math::SquareMatrix;
+
+        self.skeleton
+            .bind_pose()
+            .iter()
+            .zip(self.bones.iter())
+            .map(|(bind, bone)| {
+                let inverse = bind.matrix().invert().unwrap_or(math::Matrix4::identity());
+                bone.matrix() * inverse
+            })
+            .collect()
+    }
+
+    /// Adds a socket placed at `offset` relative to bone.
+    pub fn add_socket<T: Into<String>>(
+        &mut self,
+        name: T,
+        bone: &str,
+        offset: Transform,
+    ) -> Result<()> {
+        let name = name.into();
+        let bone = match self.skeleton.find(bone) {
+            Some(v) => v,
+            None => bail!("Bone {} does not exist in skeleton.", bone),
+        };
+
+        self.sockets.retain(|v| v.name != name);
+        self.sockets.push(Socket {
+            name: name,
+            bone: bone,
+            offset: offset,
+            world: Transform::default(),
+        });
+
+        Ok(())
+    }
+
+    /// Removes the socket.
+    pub fn remove_socket(&mut self, name: &str) {
+        self.sockets.retain(|v| v.name != name);
+    }
+
+    /// Gets the world transform of socket, which is updated by `Animators::advance`
+    /// every frame.
+    pub fn socket(&self, name: &str) -> Option<Transform> {
+        self.sockets.iter().find(|v| v.name == name).map(|v| v.world)
+    }
+
+    // Moves the playback forward by `dt` seconds, and samples the pose. The events
+    // crossed are collected into `events`.
+    fn tick(&mut self, dt: f32, events: &mut Vec<AnimationEvent>) {
+        let speed = self.speed;
+        self.motion = RootMotion::default();
+
+        let v = match self.playback {
+            Some(ref mut v) => v,
+            None => return,
+        };
+
+        if v.finished {
+            return;
+        }
+
+        let (duration, prev) = (v.clip.duration, v.time);
+        // The number of times the looping clip passes through its end.
+        let mut wraps = 0;
+
+        if duration <= 0.0 {
+            v.clip.collect_events(0.0, 1.0, true, events);
+            v.finished = true;
+        } else {
+            let from = v.time / duration;
+            let to = from + dt * speed / duration;
+
+            if v.looping {
+                // Emits the events of one cycle at most for a long step.
+                let (mut start, mut end) = (from, to.min(from + 1.0));
+                while end >= 1.0 {
+                    v.clip.collect_events(start, 1.0, true, events);
+                    start = 0.0;
+                    end -= 1.0;
+                }
+
+                v.clip.collect_events(start, end, false, events);
+                v.time = to.fract() * duration;
+                wraps = to.max(0.0).floor() as u32;
+            } else if to >= 1.0 {
+                v.clip.collect_events(from, 1.0, true, events);
+                v.time = duration;
+                v.finished = true;
+            } else {
+                v.clip.collect_events(from, to, false, events);
+                v.time = to * duration;
+            }
+        }
+
+        for (track, &bone) in v.clip.tracks().iter().zip(v.bindings.iter()) {
+            if let (Some(bone), Some(transform)) = (bone, track.sample(v.time)) {
+                self.pose[bone] = transform;
+            }
+        }
+
+        if let Some(setup) = self.root_motion {
+            let track = v.bindings
+                .iter()
+                .position(|&b| b == Some(setup.bone))
+                .map(|i| &v.clip.tracks()[i]);
+
+            if let Some(track) = track {
+                let sample = |time| track.sample(time).unwrap_or_default();
+
+                // The motion through the end of looping clip is the motion to the end,
+                // followed by the whole cycles passed in a long step, and the one from
+                // the beginning.
+                self.motion = if wraps > 0 {
+                    let cycle = RootMotion::between(&sample(0.0), &sample(duration));
+                    let mut motion = RootMotion::between(&sample(prev), &sample(duration));
+                    for _ in 1..wraps {
+                        motion = motion.then(&cycle);
+                    }
+
+                    let rhs = RootMotion::between(&sample(0.0), &sample(v.time));
+                    motion.then(&rhs)
+                } else {
+                    RootMotion::between(&sample(prev), &sample(v.time))
+                };
+
+                if setup.remove {
+                    root_motion::remove(&mut self.pose[setup.bone]);
+                }
+            }
+        }
+    }
+
+    // Computes the transforms of bones relative to the entity from the local pose.
+    fn resolve(&mut self) {
+        self.locals.clone_from(&self.pose);
+        self.compose();
+    }
+
+    // Solves the IK chains which have targets, in the order of skeleton. The `world` is
+    // the world transform of entity.
+    fn solve_ik(&mut self, world: &Transform) {
+        let skeleton = self.skeleton.clone();
+        for (i, chain) in skeleton.ik.iter().enumerate() {
+            if let Some(mut target) = self.targets[i] {
+                target.position = ik::to_local(world, target.position);
+                target.pole = target.pole.map(|v| ik::to_local(world, v));
+
+                ik::solve(&chain.solver, &target, &mut self.locals, &self.bones);
+                self.compose();
+            }
+        }
+    }
+
+    fn compose(&mut self) {
+        for (i, bone) in self.skeleton.bones.iter().enumerate() {
+            self.bones[i] = match bone.parent {
+                Some(parent) => concat(&self.bones[parent], &self.locals[i]),
+                None => self.locals[i],
+            };
+        }
+    }
+}
+
+/// The entity which follows a socket.
+#[derive(Clone)]
+struct Attachment {
+    owner: Entity,
+    socket: String,
+    child: Entity,
+}
+
+/// The collection of `Animator`s in world.
+#[derive(Clone)]
+pub struct Animators {
+    animators: Component<Animator>,
+    attachments: Vec<Attachment>,
+    events: Vec<(Entity, AnimationEvent)>,
+}
+
+impl Animators {
+    pub fn new() -> Self {
+        Animators {
+            animators: Component::new(),
+            attachments: Vec::new(),
+            events: Vec::new(),
+        }
+    }
+
+    #[inline]
+    pub fn add(&mut self, ent: Entity, animator: Animator) {
+        self.animators.add(ent, animator);
+    }
+
+    /// Removes the animator of entity, and detaches the entities from its sockets. The
+    /// entity itself is detached if its attached to a socket.
+    pub fn remove(&mut self, ent: Entity) {
+        self.animators.remove(ent);
+        self.attachments.retain(|v| v.owner != ent && v.child != ent);
+    }
+
+    #[inline]
+    pub fn get(&self, ent: Entity) -> Option<&Animator> {
+        self.animators.get(ent)
+    }
+
+    #[inline]
+    pub fn get_mut(&mut self, ent: Entity) -> Option<&mut Animator> {
+        self.animators.get_mut(ent)
+    }
+
+    /// Attaches `child` to the socket of animator on `owner`. The child is parented to
+    /// the owner in scene, and its world transform follows the socket.
+    pub fn attach(
+        &mut self,
+        scene: &mut SceneGraph,
+        owner: Entity,
+        socket: &str,
+        child: Entity,
+    ) -> Result<()> {
+        let world = match self.animators.get(owner) {
+            Some(animator) => match animator.socket(socket) {
+                Some(v) => v,
+                None => bail!("Socket {} does not exist on {:?}.", socket, owner),
+            },
+            None => bail!("{:?} does not have an animator.", owner),
+        };
+
+        if child == owner || scene.is_ancestor(owner, child) {
+            bail!("Can not attach {:?} to the socket of its descendant.", child);
+        }
+
+        scene.set_parent(child, owner, false)?;
+        self.detach(child);
+        self.attachments.push(Attachment {
+            owner: owner,
+            socket: socket.to_owned(),
+            child: child,
+        });
+
+        set_world_transform(scene, child, &world);
+        Ok(())
+    }
+
+    /// Stops following the socket, the entity stays a child of the owner in scene.
+    pub fn detach(&mut self, child: Entity) {
+        self.attachments.retain(|v| v.child != child);
+    }
+
+    /// Takes the events emitted since last call, in the order they are crossed.
+    #[inline]
+    pub fn take_events(&mut self) -> Vec<(Entity, AnimationEvent)> {
+        mem::replace(&mut self.events, Vec::new())
+    }
+
+    /// Replaces the entities with the new ones in `remap`, including the owners and
+    /// children of attachments.
+    pub fn remap_entities(&mut self, remap: &EntityRemap) {
+        let map = |v: Entity| remap.get(v).unwrap_or(v);
+
+        self.animators.remap_entities(remap);
+
+        for v in &mut self.attachments {
+            v.owner = map(v.owner);
+            v.child = map(v.child);
+        }
+
+        for v in &mut self.events {
+            v.0 = map(v.0);
+        }
+    }
+
+    /// Writes the poses, playbacks and root motions of animators into `checksum`. The
+    /// resolved bones are derived from the poses, so they are skipped.
+    pub fn checksum(&self, checksum: &mut Checksum) {
+        self.animators.checksum(checksum, |v, checksum| {
+            for w in v.pose.iter().chain(&v.locals) {
+                w.checksum(checksum);
+            }
+
+            if let Some(ref playback) = v.playback {
+                checksum.write_f32(playback.time);
+                playback.looping.hash(checksum);
+                playback.finished.hash(checksum);
+            }
+
+            let (t, r) = (v.motion.translation, v.motion.rotation);
+            checksum.write_f32s(&[v.speed, t.x, t.y, t.z]);
+            checksum.write_f32s(&[r.s, r.v.x, r.v.y, r.v.z]);
+        });
+    }
+
+    /// Moves the playing clips forward, resolves the bones from current poses and IK
+    /// targets, updates the sockets, and moves the attached entities. This should be
+    /// called every frame, after the poses are modified by users.
+    pub fn advance(&mut self, scene: &mut SceneGraph, delta: Duration) {
+        let dt = delta.as_secs() as f32 + delta.subsec_nanos() as f32 * 1e-9;
+
+        let mut events = Vec::new();
+        for (&ent, animator) in self.animators
+            .entities
+            .iter()
+            .zip(self.animators.data.iter_mut())
+        {
+            animator.tick(dt, &mut events);
+            for v in events.drain(..) {
+                self.events.push((ent, v));
+            }
+
+            let world = scene.transform(ent).unwrap_or_default();
+            animator.resolve();
+            animator.solve_ik(&world);
+
+            for v in &mut animator.sockets {
+                let local = concat(&animator.bones[v.bone], &v.offset);
+                v.world = concat(&world, &local);
+            }
+        }
+
+        for v in &self.attachments {
+            if let Some(world) = self.animators.get(v.owner).and_then(|a| a.socket(&v.socket)) {
+                set_world_transform(scene, v.child, &world);
+            }
+        }
+    }
+}
+
+// Composes the transform of child relative to its parent.
+fn concat(parent: &Transform, child: &Transform) -> Transform {
+    Transform {
+        scale: parent.scale * child.scale,
+        position: parent.transform_point(child.position),
+        rotation: parent.rotation * child.rotation,
+    }
+}
+
+fn set_world_transform(scene: &mut SceneGraph, ent: Entity, transform: &Transform) {
+    scene.set_position(ent, transform.position);
+    scene.set_rotation(ent, transform.rotation);
+    scene.set_scale(ent, transform.scale);
+}
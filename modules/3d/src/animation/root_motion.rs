@@ -0,0 +1,77 @@
+//! The motion of root bone, which is extracted from animations so characters are moved
+//! by the authored locomotion instead of sliding.
+//!
+//! Only the motion on the ground is extracted, which is the translation along the
+//! horizontal plane and the rotation around the up axis. The vertical movements, e.g.
+//! the bobbing of walk cycles, stay in the pose.
+
+use crayon::math::{self, One, Rotation3};
+
+use scene::Transform;
+
+/// The motion of root bone during last frame.
+///
+/// The translation is relative to the heading of root at the beginning of frame, so it
+/// should be applied in the local space of entity before the rotation, which is what
+/// `RootMotion::apply` does.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RootMotion {
+    pub translation: math::Vector3<f32>,
+    /// The rotation around the up axis.
+    pub rotation: math::Quaternion<f32>,
+}
+
+impl Default for RootMotion {
+    fn default() -> Self {
+        RootMotion {
+            translation: math::Vector3::new(0.0, 0.0, 0.0),
+            rotation: math::Quaternion::one(),
+        }
+    }
+}
+
+impl RootMotion {
+    /// Gets the motion between two local transforms of root bone.
+    pub fn between(from: &Transform, to: &Transform) -> Self {
+        let (lhs, rhs) = (heading(from), heading(to));
+        let offset = to.position - from.position;
+
+        RootMotion {
+            translation: yaw(-lhs) * math::Vector3::new(offset.x, 0.0, offset.z),
+            rotation: yaw(rhs - lhs),
+        }
+    }
+
+    /// Gets the motion of `self` followed by `rhs`.
+    pub fn then(&self, rhs: &RootMotion) -> Self {
+        RootMotion {
+            translation: self.translation + self.rotation * rhs.translation,
+            rotation: self.rotation * rhs.rotation,
+        }
+    }
+
+    /// Moves the world transform of entity.
+    pub fn apply(&self, transform: &mut Transform) {
+        transform.position += transform.rotation * (self.translation * transform.scale);
+        transform.rotation = transform.rotation * self.rotation;
+    }
+}
+
+/// Removes the motion on the ground from the local transform of root bone, so it
+/// stays at the origin of entity and faces forward.
+pub(crate) fn remove(transform: &mut Transform) {
+    transform.rotation = yaw(-heading(transform)) * transform.rotation;
+    transform.position.x = 0.0;
+    transform.position.z = 0.0;
+}
+
+// The angle between the forward vector projected on the ground and the positive z-axis.
+fn heading(transform: &Transform) -> f32 {
+    let forward = transform.forward();
+    forward.x.atan2(forward.z)
+}
+
+#[inline]
+fn yaw(angle: f32) -> math::Quaternion<f32> {
+    math::Quaternion::from_angle_y(math::Rad(angle))
+}
@@ -4,11 +4,25 @@ pub use self::prefab::{Prefab, PrefabHandle};
 pub mod prefab_loader;
 pub use self::prefab_loader::PrefabLoader;
 
-use std::sync::{Arc, RwLock};
+pub mod pipeline;
+pub use self::pipeline::{
+    RenderPassKind, RenderPassSetup, RenderPipelineHandle, RenderPipelineSetup, RenderTargetSetup,
+};
 
+pub mod pipeline_graph;
+pub use self::pipeline_graph::RenderTargetLifetime;
+
+pub mod pipeline_loader;
+pub use self::pipeline_loader::RenderPipelineLoader;
+
+use std::sync::Arc;
+
+use crayon::application::time::TimeSystemShared;
 use crayon::application::Engine;
 use crayon::errors::*;
+use crayon::res::ResourceSystemShared;
 use crayon::utils::object_pool::ObjectPool;
+use crayon::utils::snapshot::Snapshot;
 
 pub struct WorldResources {
     shared: Arc<WorldResourcesShared>,
@@ -16,10 +30,14 @@ pub struct WorldResources {
 
 impl WorldResources {
     pub fn new(engine: &mut Engine) -> Self {
-        let shared = Arc::new(WorldResourcesShared::new());
+        let time = engine.context().time.clone();
+        let shared = Arc::new(WorldResourcesShared::new(engine.res.shared(), time));
         let loader = PrefabLoader::new(engine.res.shared(), shared.clone());
         engine.res.register(loader);
 
+        let loader = RenderPipelineLoader::new(shared.clone());
+        engine.res.register(loader);
+
         WorldResources { shared: shared }
     }
 
@@ -28,28 +46,41 @@ impl WorldResources {
     }
 }
 
+#[derive(Clone)]
 enum AsyncState<T> {
     Ok(T),
     NotReady,
 }
 
+/// The loaded prefabs and pipelines, which are shared between loaders and worlds.
+///
+/// The assets are kept in immutable snapshots, so looking them up on the render path is
+/// lock free. The loaders publish a new snapshot for every change, which are rare.
 pub struct WorldResourcesShared {
-    prefabs: RwLock<ObjectPool<AsyncState<Arc<Prefab>>>>,
+    prefabs: Snapshot<ObjectPool<AsyncState<Arc<Prefab>>>>,
+    pipelines: Snapshot<ObjectPool<AsyncState<Arc<RenderPipelineSetup>>>>,
+    res: Arc<ResourceSystemShared>,
+    time: Arc<TimeSystemShared>,
 }
 
 impl WorldResourcesShared {
-    fn new() -> Self {
+    pub(crate) fn new(res: Arc<ResourceSystemShared>, time: Arc<TimeSystemShared>) -> Self {
         WorldResourcesShared {
-            prefabs: RwLock::new(ObjectPool::new()),
+            prefabs: Snapshot::new(ObjectPool::new()),
+            pipelines: Snapshot::new(ObjectPool::new()),
+            res: res,
+            time: time,
         }
     }
 
+    /// Gets the time system of engine, which drives the fixed updates of worlds.
+    #[inline]
+    pub(crate) fn time(&self) -> Arc<TimeSystemShared> {
+        self.time.clone()
+    }
+
     pub(crate) fn create_prefab_async(&self) -> PrefabHandle {
-        self.prefabs
-            .write()
-            .unwrap()
-            .create(AsyncState::NotReady)
-            .into()
+        self.prefabs.update(|v| v.create(AsyncState::NotReady)).into()
     }
 
     pub(crate) fn update_prefab_async(
@@ -59,28 +90,71 @@ impl WorldResourcesShared {
     ) -> Result<Option<Prefab>> {
         prefab.validate()?;
 
-        if let Some(v) = self.prefabs.write().unwrap().get_mut(handle) {
-            *v = AsyncState::Ok(Arc::new(prefab));
-            Ok(None)
-        } else {
-            Ok(Some(prefab))
-        }
+        let mut prefab = Some(prefab);
+        self.prefabs.update(|v| {
+            if let Some(v) = v.get_mut(handle) {
+                *v = AsyncState::Ok(Arc::new(prefab.take().unwrap()));
+            }
+        });
+
+        Ok(prefab)
     }
 
     pub(crate) fn delete_prefab_async(&self, handle: PrefabHandle) -> Option<Arc<Prefab>> {
         self.prefabs
-            .write()
-            .unwrap()
-            .free(handle)
+            .update(|v| v.free(handle))
             .and_then(|v| match v {
                 AsyncState::Ok(prefab) => Some(prefab),
                 _ => None,
             })
     }
 
+    /// Gets the result of loading prefab without blocking, returns `None` if its not
+    /// finished yet. The error is returned only once, see `ResourceSystemShared::poll`.
+    #[inline]
+    pub fn poll_prefab(&self, handle: PrefabHandle) -> Option<Result<()>> {
+        self.res.poll(handle)
+    }
+
     #[inline]
     pub fn prefab(&self, handle: PrefabHandle) -> Option<Arc<Prefab>> {
-        if let Some(AsyncState::Ok(v)) = self.prefabs.read().unwrap().get(handle) {
+        if let Some(AsyncState::Ok(v)) = self.prefabs.load().get(handle) {
+            Some(v.clone())
+        } else {
+            None
+        }
+    }
+}
+
+impl WorldResourcesShared {
+    pub(crate) fn create_pipeline_async(&self) -> RenderPipelineHandle {
+        self.pipelines.update(|v| v.create(AsyncState::NotReady)).into()
+    }
+
+    pub(crate) fn update_pipeline_async(
+        &self,
+        handle: RenderPipelineHandle,
+        setup: RenderPipelineSetup,
+    ) -> Result<()> {
+        setup.validate()?;
+
+        let setup = Arc::new(setup);
+        self.pipelines.update(|v| {
+            if let Some(v) = v.get_mut(handle) {
+                *v = AsyncState::Ok(setup);
+            }
+        });
+
+        Ok(())
+    }
+
+    pub(crate) fn delete_pipeline_async(&self, handle: RenderPipelineHandle) {
+        self.pipelines.update(|v| v.free(handle));
+    }
+
+    #[inline]
+    pub fn pipeline(&self, handle: RenderPipelineHandle) -> Option<Arc<RenderPipelineSetup>> {
+        if let Some(AsyncState::Ok(v)) = self.pipelines.load().get(handle) {
             Some(v.clone())
         } else {
             None
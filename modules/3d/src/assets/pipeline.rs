@@ -0,0 +1,126 @@
+use std::collections::HashSet;
+
+use crayon::errors::*;
+use crayon::math;
+use crayon::video::assets::texture::RenderTextureFormat;
+use crayon::video::MAX_FRAMEBUFFER_ATTACHMENTS;
+
+impl_handle!(RenderPipelineHandle);
+
+/// The serializable description of a camera render pipeline, which consists of
+/// ordered passes and the wiring between their inputs and outputs.
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+pub struct RenderPipelineSetup {
+    /// The ordered passes of this pipeline.
+    pub passes: Vec<RenderPassSetup>,
+}
+
+/// The description of a single pass in render pipeline.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct RenderPassSetup {
+    /// The unique name of this pass.
+    pub name: String,
+    /// How the drawcalls of this pass are generated.
+    pub kind: RenderPassKind,
+    /// The name of the registered shader which would be used in `Fullscreen` passes.
+    pub shader: Option<String>,
+    /// The `(uniform, target)` pairs, which binds the output target of previous passes
+    /// to uniform variables of shader.
+    pub inputs: Vec<(String, String)>,
+    /// The color targets this pass rendered into. The default framebuffer would be
+    /// used if there is no color and depth targets.
+    pub colors: Vec<RenderTargetSetup>,
+    /// The optional depth target this pass rendered into.
+    pub depth: Option<RenderTargetSetup>,
+    /// The clear color of this pass.
+    pub clear_color: Option<[f32; 4]>,
+    /// The clear depth of this pass.
+    pub clear_depth: Option<f32>,
+}
+
+/// The kinds of render pass.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RenderPassKind {
+    /// Draws the visible objects of camera into targets.
+    Scene,
+    /// Draws a fullscreen triangle with shader and inputs, it is usually used by
+    /// post-processing effects.
+    Fullscreen,
+}
+
+/// The description of a render target.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct RenderTargetSetup {
+    /// The unique name of this target, which could be referenced by the inputs of
+    /// following passes.
+    pub name: String,
+    /// The format of this target.
+    pub format: RenderTextureFormat,
+    /// The scale of dimensions relative to the window.
+    pub scale: f32,
+}
+
+impl RenderTargetSetup {
+    /// Gets the dimensions of this target with the dimensions of window.
+    pub fn dimensions(&self, window: math::Vector2<u32>) -> math::Vector2<u32> {
+        math::Vector2::new(
+            ((window.x as f32 * self.scale) as u32).max(1),
+            ((window.y as f32 * self.scale) as u32).max(1),
+        )
+    }
+}
+
+impl RenderPipelineSetup {
+    pub fn validate(&self) -> Result<()> {
+        let mut passes = HashSet::new();
+        let mut targets = HashSet::new();
+
+        for pass in &self.passes {
+            if !passes.insert(pass.name.as_str()) {
+                bail!("Pass {} is declared more than once.", pass.name);
+            }
+
+            if pass.kind == RenderPassKind::Fullscreen && pass.shader.is_none() {
+                bail!("Fullscreen pass {} requires a shader.", pass.name);
+            }
+
+            if pass.colors.len() >= MAX_FRAMEBUFFER_ATTACHMENTS {
+                bail!("Pass {} has too many color targets.", pass.name);
+            }
+
+            for &(_, ref target) in &pass.inputs {
+                if !targets.contains(target.as_str()) {
+                    bail!(
+                        "Input {} of pass {} is not the output of previous passes.",
+                        target,
+                        pass.name
+                    );
+                }
+            }
+
+            for v in &pass.colors {
+                if !v.format.is_color() {
+                    bail!("Color target {} has a non-color format.", v.name);
+                }
+            }
+
+            if let Some(ref v) = pass.depth {
+                if v.format.is_color() {
+                    bail!("Depth target {} has a color format.", v.name);
+                }
+            }
+
+            for v in pass.colors.iter().chain(pass.depth.iter()) {
+                if v.scale <= 0.0 {
+                    bail!("Target {} has non-positive scale.", v.name);
+                }
+
+                if !targets.insert(v.name.as_str()) {
+                    bail!("Target {} is declared more than once.", v.name);
+                }
+            }
+        }
+
+        Ok(())
+    }
+}
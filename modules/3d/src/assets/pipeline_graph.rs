@@ -0,0 +1,372 @@
+//! Exports the `RenderPipelineSetup` as a graph of passes and targets, which helps to
+//! reason about the ordering of passes and the aliasing of render targets in complex
+//! pipelines.
+//!
+//! The graph could be written in GraphViz dot format, which could be rendered with
+//! `dot -Tsvg pipeline.dot -o pipeline.svg`, or as a standalone HTML page which shows
+//! the passes and the lifetimes of targets without any external tools.
+//!
+//! ```rust,ignore
+//! if let Some(setup) = world.res.pipeline(handle) {
+//!     let dimensions = ctx.window.dimensions();
+//!     fs::write("pipeline.dot", setup.to_dot(Some(dimensions)))?;
+//!     fs::write("pipeline.html", setup.to_html(Some(dimensions)))?;
+//! }
+//! ```
+//!
+//! The jobs of `sched` which the passes are waiting for could be exported in the same
+//! way with `crayon::sched::graph::TaskGraph`.
+//!
+//! ```rust,ignore
+//! fs::write("tasks.dot", ctx.sched.task_graph().to_dot())?;
+//! ```
+
+use std::fmt::{self, Write};
+
+use crayon::math;
+
+use super::pipeline::{RenderPassKind, RenderPassSetup, RenderPipelineSetup, RenderTargetSetup};
+
+/// The lifetime of a render target in the indices of passes, from the pass which
+/// renders into it to the last pass which reads it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RenderTargetLifetime {
+    pub first: usize,
+    pub last: usize,
+}
+
+impl RenderTargetLifetime {
+    /// Returns true if both targets are alive during some passes.
+    #[inline]
+    pub fn overlaps(&self, rhs: &RenderTargetLifetime) -> bool {
+        self.first <= rhs.last && rhs.first <= self.last
+    }
+
+    /// Returns true if the target is alive during pass with index.
+    #[inline]
+    pub fn contains(&self, pass: usize) -> bool {
+        self.first <= pass && pass <= self.last
+    }
+}
+
+impl RenderPipelineSetup {
+    /// Gets the targets and their lifetimes in declared order.
+    pub fn target_lifetimes(&self) -> Vec<(&RenderTargetSetup, RenderTargetLifetime)> {
+        let mut lifetimes: Vec<(&RenderTargetSetup, RenderTargetLifetime)> = Vec::new();
+
+        for (i, pass) in self.passes.iter().enumerate() {
+            for &(_, ref target) in &pass.inputs {
+                if let Some(v) = lifetimes.iter_mut().find(|v| v.0.name == *target) {
+                    v.1.last = i;
+                }
+            }
+
+            for v in pass.colors.iter().chain(pass.depth.iter()) {
+                lifetimes.push((v, RenderTargetLifetime { first: i, last: i }));
+            }
+        }
+
+        lifetimes
+    }
+
+    /// Gets the pairs of targets which could share the same memory, since they have
+    /// the same format and scale, and are never alive at the same time.
+    ///
+    /// Notes that the targets which are read outside of pipeline, e.g. with
+    /// `RenderPipeline::target`, should not be aliased.
+    pub fn aliasable_targets(&self) -> Vec<(&str, &str)> {
+        let lifetimes = self.target_lifetimes();
+        let mut pairs = Vec::new();
+
+        for (i, lhs) in lifetimes.iter().enumerate() {
+            for rhs in &lifetimes[i + 1..] {
+                if lhs.0.format == rhs.0.format
+                    && (lhs.0.scale - rhs.0.scale).abs() < ::std::f32::EPSILON
+                    && !lhs.1.overlaps(&rhs.1)
+                {
+                    pairs.push((lhs.0.name.as_str(), rhs.0.name.as_str()));
+                }
+            }
+        }
+
+        pairs
+    }
+
+    /// Writes the graph in GraphViz dot format. The sizes of targets are included if
+    /// the `dimensions` of window is given.
+    pub fn write_dot<W: Write>(
+        &self,
+        w: &mut W,
+        dimensions: Option<math::Vector2<u32>>,
+    ) -> fmt::Result {
+        writeln!(w, "digraph RenderPipeline {{")?;
+        writeln!(w, "    rankdir=LR;")?;
+        writeln!(w, "    node [fontname=\"Helvetica\", fontsize=10];")?;
+        writeln!(w, "    edge [fontname=\"Helvetica\", fontsize=9];")?;
+
+        for (i, pass) in self.passes.iter().enumerate() {
+            let mut label = format!("#{} {}\n{:?}", i, pass.name, pass.kind);
+            if let Some(ref shader) = pass.shader {
+                label.push_str(&format!("\n{}", shader));
+            }
+
+            let color = match pass.kind {
+                RenderPassKind::Scene => "lightblue",
+                RenderPassKind::Fullscreen => "lightyellow",
+            };
+
+            writeln!(
+                w,
+                "    \"pass:{}\" [shape=box, style=filled, fillcolor={}, label=\"{}\"];",
+                escape_dot(&pass.name),
+                color,
+                escape_dot(&label)
+            )?;
+        }
+
+        for (target, lifetime) in self.target_lifetimes() {
+            let label = format!(
+                "{}\n{:?} {}\npasses {}..{}",
+                target.name,
+                target.format,
+                target_size(target, dimensions),
+                lifetime.first,
+                lifetime.last
+            );
+
+            writeln!(
+                w,
+                "    \"target:{}\" [shape=ellipse, label=\"{}\"];",
+                escape_dot(&target.name),
+                escape_dot(&label)
+            )?;
+        }
+
+        if self.passes.iter().any(is_framebuffer) {
+            writeln!(w, "    \"framebuffer\" [shape=doubleoctagon];")?;
+        }
+
+        for pass in &self.passes {
+            let name = escape_dot(&pass.name);
+
+            for &(ref uniform, ref target) in &pass.inputs {
+                writeln!(
+                    w,
+                    "    \"target:{}\" -> \"pass:{}\" [label=\"{}\"];",
+                    escape_dot(target),
+                    name,
+                    escape_dot(uniform)
+                )?;
+            }
+
+            for v in pass.colors.iter().chain(pass.depth.iter()) {
+                writeln!(
+                    w,
+                    "    \"pass:{}\" -> \"target:{}\";",
+                    name,
+                    escape_dot(&v.name)
+                )?;
+            }
+
+            if is_framebuffer(pass) {
+                writeln!(w, "    \"pass:{}\" -> \"framebuffer\";", name)?;
+            }
+        }
+
+        // The submission order of passes.
+        for (lhs, rhs) in self.passes.iter().zip(self.passes.iter().skip(1)) {
+            writeln!(
+                w,
+                "    \"pass:{}\" -> \"pass:{}\" [style=dotted, color=gray];",
+                escape_dot(&lhs.name),
+                escape_dot(&rhs.name)
+            )?;
+        }
+
+        for (lhs, rhs) in self.aliasable_targets() {
+            writeln!(
+                w,
+                "    \"target:{}\" -> \"target:{}\" [style=dashed, dir=none, color=blue, \
+                 label=\"alias\", constraint=false];",
+                escape_dot(lhs),
+                escape_dot(rhs)
+            )?;
+        }
+
+        writeln!(w, "}}")
+    }
+
+    /// Gets the graph in GraphViz dot format, see `write_dot` for details.
+    pub fn to_dot(&self, dimensions: Option<math::Vector2<u32>>) -> String {
+        let mut dot = String::new();
+        self.write_dot(&mut dot, dimensions).unwrap();
+        dot
+    }
+
+    /// Writes the graph as a standalone HTML page, which lists the passes in order, the
+    /// lifetimes of targets and the targets which could be aliased. The dot source is
+    /// embedded at the end of page.
+    pub fn write_html<W: Write>(
+        &self,
+        w: &mut W,
+        dimensions: Option<math::Vector2<u32>>,
+    ) -> fmt::Result {
+        writeln!(w, "<!DOCTYPE html>")?;
+        writeln!(w, "<html>")?;
+        writeln!(w, "<head>")?;
+        writeln!(w, "<meta charset=\"utf-8\">")?;
+        writeln!(w, "<title>RenderPipeline</title>")?;
+        writeln!(w, "<style>")?;
+        writeln!(
+            w,
+            "body {{ font-family: Helvetica, sans-serif; font-size: 13px; }}"
+        )?;
+        writeln!(
+            w,
+            "table {{ border-collapse: collapse; margin-bottom: 16px; }}"
+        )?;
+        writeln!(w, "th, td {{ border: 1px solid #ccc; padding: 4px 8px; }}")?;
+        writeln!(w, "td.alive {{ background: #b3d9ff; text-align: center; }}")?;
+        writeln!(w, "pre {{ background: #f4f4f4; padding: 8px; }}")?;
+        writeln!(w, "</style>")?;
+        writeln!(w, "</head>")?;
+        writeln!(w, "<body>")?;
+
+        writeln!(w, "<h2>Passes</h2>")?;
+        writeln!(w, "<table>")?;
+        writeln!(
+            w,
+            "<tr><th>#</th><th>Name</th><th>Kind</th><th>Shader</th>\
+             <th>Inputs</th><th>Outputs</th></tr>"
+        )?;
+
+        for (i, pass) in self.passes.iter().enumerate() {
+            let inputs: Vec<_> = pass.inputs
+                .iter()
+                .map(|&(ref uniform, ref target)| {
+                    format!("{} &larr; {}", escape_html(uniform), escape_html(target))
+                })
+                .collect();
+
+            let mut outputs: Vec<_> = pass.colors
+                .iter()
+                .chain(pass.depth.iter())
+                .map(|v| escape_html(&v.name))
+                .collect();
+
+            if is_framebuffer(pass) {
+                outputs.push("framebuffer".into());
+            }
+
+            writeln!(
+                w,
+                "<tr><td>{}</td><td>{}</td><td>{:?}</td><td>{}</td><td>{}</td><td>{}</td></tr>",
+                i,
+                escape_html(&pass.name),
+                pass.kind,
+                escape_html(pass.shader.as_ref().map(|v| v.as_str()).unwrap_or("")),
+                inputs.join("<br>"),
+                outputs.join("<br>")
+            )?;
+        }
+
+        writeln!(w, "</table>")?;
+
+        writeln!(w, "<h2>Targets</h2>")?;
+        writeln!(w, "<table>")?;
+        write!(w, "<tr><th>Name</th><th>Format</th><th>Size</th>")?;
+        for pass in &self.passes {
+            write!(w, "<th>{}</th>", escape_html(&pass.name))?;
+        }
+        writeln!(w, "</tr>")?;
+
+        for (target, lifetime) in self.target_lifetimes() {
+            write!(
+                w,
+                "<tr><td>{}</td><td>{:?}</td><td>{}</td>",
+                escape_html(&target.name),
+                target.format,
+                target_size(target, dimensions)
+            )?;
+
+            for (i, pass) in self.passes.iter().enumerate() {
+                if !lifetime.contains(i) {
+                    write!(w, "<td></td>")?;
+                    continue;
+                }
+
+                // W if the pass renders into the target, and R if the pass reads it.
+                let access = if i == lifetime.first {
+                    "W"
+                } else if pass.inputs.iter().any(|v| v.1 == target.name) {
+                    "R"
+                } else {
+                    ""
+                };
+
+                write!(w, "<td class=\"alive\">{}</td>", access)?;
+            }
+
+            writeln!(w, "</tr>")?;
+        }
+
+        writeln!(w, "</table>")?;
+
+        writeln!(w, "<h2>Aliasing</h2>")?;
+        let aliases = self.aliasable_targets();
+        if aliases.is_empty() {
+            writeln!(w, "<p>None of the targets could be aliased.</p>")?;
+        } else {
+            writeln!(w, "<ul>")?;
+            for (lhs, rhs) in aliases {
+                writeln!(
+                    w,
+                    "<li>{} &harr; {}</li>",
+                    escape_html(lhs),
+                    escape_html(rhs)
+                )?;
+            }
+            writeln!(w, "</ul>")?;
+        }
+
+        writeln!(w, "<h2>Dot</h2>")?;
+        writeln!(w, "<pre>{}</pre>", escape_html(&self.to_dot(dimensions)))?;
+        writeln!(w, "</body>")?;
+        writeln!(w, "</html>")
+    }
+
+    /// Gets the graph as a standalone HTML page, see `write_html` for details.
+    pub fn to_html(&self, dimensions: Option<math::Vector2<u32>>) -> String {
+        let mut html = String::new();
+        self.write_html(&mut html, dimensions).unwrap();
+        html
+    }
+}
+
+/// The passes without any targets render into the default framebuffer.
+fn is_framebuffer(pass: &RenderPassSetup) -> bool {
+    pass.colors.is_empty() && pass.depth.is_none()
+}
+
+fn target_size(target: &RenderTargetSetup, dimensions: Option<math::Vector2<u32>>) -> String {
+    match dimensions {
+        Some(dimensions) => {
+            let v = target.dimensions(dimensions);
+            format!("{}x{} ({}KB)", v.x, v.y, target.format.size(v) / 1024)
+        }
+        None => format!("x{}", target.scale),
+    }
+}
+
+fn escape_dot(v: &str) -> String {
+    v.replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace('\n', "\\n")
+}
+
+fn escape_html(v: &str) -> String {
+    v.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
@@ -0,0 +1,66 @@
+use std::io::Read;
+use std::sync::Arc;
+
+use crayon::bincode;
+use crayon::errors::*;
+use crayon::res::format::{AssetFormat, Version};
+use crayon::res::{ResourceHandle, ResourceLoader};
+
+use super::pipeline::*;
+use super::WorldResourcesShared;
+
+pub const FORMAT: AssetFormat = AssetFormat {
+    name: "RenderPipelineLoader",
+    magic: [b'P', b'I', b'P', b'E'],
+    version: Version {
+        major: 0,
+        minor: 0,
+        patch: 1,
+    },
+};
+
+pub struct RenderPipelineLoader {
+    world_resources: Arc<WorldResourcesShared>,
+}
+
+impl RenderPipelineLoader {
+    pub fn new(world_resources: Arc<WorldResourcesShared>) -> Self {
+        RenderPipelineLoader {
+            world_resources: world_resources,
+        }
+    }
+}
+
+impl ResourceHandle for RenderPipelineHandle {
+    type Loader = RenderPipelineLoader;
+}
+
+impl ResourceLoader for RenderPipelineLoader {
+    type Handle = RenderPipelineHandle;
+
+    fn create(&self) -> Result<Self::Handle> {
+        let handle = self.world_resources.create_pipeline_async();
+        info!("[RenderPipelineLoader] creates {:?}.", handle);
+        Ok(handle)
+    }
+
+    fn load(&self, handle: Self::Handle, file: &mut dyn Read) -> Result<()> {
+        let data: RenderPipelineSetup =
+            FORMAT.load(file, &[], |mut file| Ok(bincode::deserialize_from(&mut file)?))?;
+
+        info!(
+            "[RenderPipelineLoader] loads {:?}. (Passes: {})",
+            handle,
+            data.passes.len()
+        );
+
+        self.world_resources.update_pipeline_async(handle, data)?;
+        Ok(())
+    }
+
+    fn delete(&self, handle: Self::Handle) -> Result<()> {
+        info!("[RenderPipelineLoader] deletes {:?}.", handle);
+        self.world_resources.delete_pipeline_async(handle);
+        Ok(())
+    }
+}
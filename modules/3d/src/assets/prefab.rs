@@ -1,6 +1,7 @@
 use crayon::errors::*;
 use crayon::uuid::Uuid;
 use crayon::video::assets::mesh::MeshHandle;
+use crayon::video::assets::texture::TextureHandle;
 
 use scene::Transform;
 
@@ -11,9 +12,13 @@ pub struct Prefab {
     ///
     pub nodes: Vec<PrefabNode>,
     pub universe_meshes: Vec<Uuid>,
+    /// The externally baked lightmap atlases.
+    pub universe_lightmaps: Vec<Uuid>,
 
     #[serde(skip)]
     pub meshes: Vec<MeshHandle>,
+    #[serde(skip)]
+    pub lightmaps: Vec<TextureHandle>,
 }
 
 #[derive(Serialize, Deserialize, Debug, Clone)]
@@ -28,6 +33,18 @@ pub struct PrefabNode {
     pub next_sib: Option<usize>,
     /// The optional mesh renderer.
     pub mesh_renderer: Option<usize>,
+    /// The optional lightmap region of mesh renderer.
+    pub lightmap: Option<PrefabLightmap>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+pub struct PrefabLightmap {
+    /// The lightmap atlas index.
+    pub index: usize,
+    /// The scale of region in atlas.
+    pub scale: [f32; 2],
+    /// The offset of region in atlas.
+    pub offset: [f32; 2],
 }
 
 #[derive(Serialize, Deserialize, Debug, Clone, Copy)]
@@ -44,6 +61,18 @@ pub struct PrefabMeshRenderer {
 
 impl Prefab {
     pub fn validate(&self) -> Result<()> {
+        for v in &self.nodes {
+            if let Some(lightmap) = v.lightmap {
+                if lightmap.index >= self.universe_lightmaps.len() {
+                    bail!(
+                        "The lightmap index {} of node {} is out of bounds.",
+                        lightmap.index,
+                        v.name
+                    );
+                }
+            }
+        }
+
         Ok(())
     }
 }
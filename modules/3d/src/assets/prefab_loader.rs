@@ -3,15 +3,86 @@ use std::sync::Arc;
 
 use crayon::bincode;
 use crayon::errors::*;
+use crayon::res::format::{AssetFormat, Migration, Version};
 use crayon::res::location::Location;
 use crayon::res::{ResourceHandle, ResourceLoader, ResourceSystemShared};
+use crayon::uuid::Uuid;
+use crayon::video::assets::texture::TextureHandle;
 
 use super::prefab::*;
 use super::WorldResourcesShared;
 
-pub const MAGIC: [u8; 8] = [
-    'P' as u8, 'R' as u8, 'E' as u8, 'B' as u8, ' ' as u8, 0, 0, 1,
-];
+pub const FORMAT: AssetFormat = AssetFormat {
+    name: "PrefabLoader",
+    magic: [b'P', b'R', b'E', b'B'],
+    version: Version {
+        major: 0,
+        minor: 1,
+        patch: 0,
+    },
+};
+
+/// The layout of prefabs before lightmaps are supported.
+mod legacy_0_0 {
+    use super::*;
+    use scene::Transform;
+
+    #[derive(Deserialize)]
+    struct PrefabNode {
+        name: String,
+        local_transform: Transform,
+        first_child: Option<usize>,
+        next_sib: Option<usize>,
+        mesh_renderer: Option<usize>,
+    }
+
+    #[derive(Deserialize)]
+    struct Prefab {
+        nodes: Vec<PrefabNode>,
+        universe_meshes: Vec<Uuid>,
+    }
+
+    pub fn migrate(mut file: &mut dyn Read) -> Result<super::Prefab> {
+        let v: Prefab = bincode::deserialize_from(&mut file)?;
+
+        let nodes = v
+            .nodes
+            .into_iter()
+            .map(|n| super::PrefabNode {
+                name: n.name,
+                local_transform: n.local_transform,
+                first_child: n.first_child,
+                next_sib: n.next_sib,
+                mesh_renderer: n.mesh_renderer,
+                lightmap: None,
+            })
+            .collect();
+
+        Ok(super::Prefab {
+            nodes: nodes,
+            universe_meshes: v.universe_meshes,
+            universe_lightmaps: Vec::new(),
+            meshes: Vec::new(),
+            lightmaps: Vec::new(),
+        })
+    }
+}
+
+const MIGRATIONS: &[Migration<Prefab>] = &[(
+    Version {
+        major: 0,
+        minor: 0,
+        patch: 0,
+    },
+    legacy_0_0::migrate,
+)];
+
+/// Decodes the prefab from `file`, the outdated ones are migrated to current version.
+pub fn decode(file: &mut dyn Read) -> Result<Prefab> {
+    FORMAT.load(file, MIGRATIONS, |mut file| {
+        Ok(bincode::deserialize_from(&mut file)?)
+    })
+}
 
 pub struct PrefabLoader {
     world_resources: Arc<WorldResourcesShared>,
@@ -40,29 +111,32 @@ impl ResourceLoader for PrefabLoader {
         Ok(handle)
     }
 
-    fn load(&self, handle: Self::Handle, mut file: &mut dyn Read) -> Result<()> {
-        let mut buf = [0; 8];
-        file.read_exact(&mut buf[0..8])?;
+    fn load(&self, handle: Self::Handle, file: &mut dyn Read) -> Result<()> {
+        let mut data = decode(file)?;
 
-        // magic: [u8; 8]
-        if &buf[0..8] != &MAGIC[..] {
-            bail!("[PrefabLoader] MAGIC number not match.");
-        }
-
-        let mut data: Prefab = bincode::deserialize_from(&mut file)?;
         for v in &data.universe_meshes {
             data.meshes.push(self.res.load_from(Location::from(*v))?);
         }
 
+        for v in &data.universe_lightmaps {
+            let texture: TextureHandle = self.res.load_from(Location::from(*v))?;
+            data.lightmaps.push(texture);
+        }
+
         for &v in &data.meshes {
             self.res.wait(v)?;
         }
 
+        for &v in &data.lightmaps {
+            self.res.wait(v)?;
+        }
+
         info!(
-            "[PrefabLoader] loads {:?}. (Nodes: {}, Meshes: {})",
+            "[PrefabLoader] loads {:?}. (Nodes: {}, Meshes: {}, Lightmaps: {})",
             handle,
             data.nodes.len(),
-            data.meshes.len()
+            data.meshes.len(),
+            data.lightmaps.len()
         );
 
         // The prefab handle might already been freed.
@@ -70,6 +144,10 @@ impl ResourceLoader for PrefabLoader {
             for v in prefab.meshes {
                 self.res.unload(v)?;
             }
+
+            for v in prefab.lightmaps {
+                self.res.unload(v)?;
+            }
         }
 
         Ok(())
@@ -82,8 +160,91 @@ impl ResourceLoader for PrefabLoader {
             for &v in &prefab.meshes {
                 self.res.unload(v)?;
             }
+
+            for &v in &prefab.lightmaps {
+                self.res.unload(v)?;
+            }
         }
 
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use scene::Transform;
+
+    fn uuid(v: u8) -> Uuid {
+        Uuid::from_bytes(&[v; 16]).unwrap()
+    }
+
+    fn node(name: &str, mesh_renderer: Option<usize>) -> PrefabNode {
+        PrefabNode {
+            name: name.into(),
+            local_transform: Transform::default(),
+            first_child: None,
+            next_sib: None,
+            mesh_renderer: mesh_renderer,
+            lightmap: None,
+        }
+    }
+
+    #[test]
+    fn lightmaps() {
+        let mut prefab = Prefab {
+            nodes: vec![node("floor", Some(0))],
+            universe_meshes: vec![uuid(1)],
+            universe_lightmaps: vec![uuid(2)],
+            meshes: Vec::new(),
+            lightmaps: Vec::new(),
+        };
+
+        prefab.nodes[0].lightmap = Some(PrefabLightmap {
+            index: 0,
+            scale: [0.5, 0.5],
+            offset: [0.5, 0.0],
+        });
+
+        let mut bytes = FORMAT.header().to_vec();
+        bytes.extend(bincode::serialize(&prefab).unwrap());
+
+        let v = decode(&mut &bytes[..]).unwrap();
+        assert!(v.validate().is_ok());
+        assert_eq!(v.universe_lightmaps, prefab.universe_lightmaps);
+        assert_eq!(v.nodes[0].lightmap, prefab.nodes[0].lightmap);
+
+        // The lightmap indices are validated.
+        prefab.universe_lightmaps.clear();
+        assert!(prefab.validate().is_err());
+    }
+
+    #[test]
+    fn migrate_0_0() {
+        // The fields of legacy nodes, which are encoded in the same way as tuples.
+        type Index = Option<usize>;
+        type LegacyNode = (String, Transform, Index, Index, Index);
+
+        let t = Transform::default();
+        let nodes: Vec<LegacyNode> = vec![
+            ("floor".to_owned(), t, Some(1), None, Some(0)),
+            ("box".to_owned(), t, None, None, None),
+        ];
+
+        let meshes = vec![uuid(1)];
+
+        // The prefabs written before lightmaps are supported have no lightmaps.
+        let mut bytes = b"PREB \x00\x00\x01".to_vec();
+        bytes.extend(bincode::serialize(&(nodes, meshes.clone())).unwrap());
+
+        let v = decode(&mut &bytes[..]).unwrap();
+        assert_eq!(v.nodes.len(), 2);
+        assert_eq!(v.nodes[0].name, "floor");
+        assert_eq!(v.nodes[0].first_child, Some(1));
+        assert_eq!(v.nodes[0].mesh_renderer, Some(0));
+        assert_eq!(v.nodes[1].mesh_renderer, None);
+        assert!(v.nodes.iter().all(|n| n.lightmap.is_none()));
+        assert_eq!(v.universe_meshes, meshes);
+        assert!(v.universe_lightmaps.is_empty());
+    }
+}
@@ -0,0 +1,241 @@
+//! Two-phase instantiation of large prefabs.
+//!
+//! Instantiating prefabs with thousands of nodes on the main thread might cause hitches.
+//! Instead, the node and component data could be prepared on the workers of `sched`
+//! into a `CommandBuffer` first, which is then merged into `World` in one go at a safe
+//! sync point, e.g. the beginning of next frame.
+//!
+//! ```rust,ignore
+//! let job = world.instantiate_async(&ctx.sched, prefab)?;
+//!
+//! // Every frame, updates the loading screen and merges if its ready.
+//! loading.set_progress(job.progress());
+//! if let Some(commands) = job.take() {
+//!     let (root, remap) = world.apply(commands?)?;
+//! }
+//! ```
+
+use std::any::Any;
+use std::panic::{self, AssertUnwindSafe};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+
+use crayon::errors::*;
+use crayon::sched::ScheduleSystemShared;
+
+use assets::Prefab;
+use renderers::{Lightmap, MeshRenderer};
+use scene::Transform;
+
+/// The number of nodes prepared in one job.
+const NODES_PER_JOB: usize = 256;
+
+/// The prepared data of a prefab node.
+#[derive(Debug, Clone)]
+pub struct PreparedNode {
+    /// The index of node in prefab.
+    pub node: usize,
+    /// The position of parent in `CommandBuffer`, which always precedes its children.
+    pub parent: Option<usize>,
+    pub name: String,
+    pub local_transform: Transform,
+    pub mesh_renderer: Option<MeshRenderer>,
+}
+
+/// `CommandBuffer` holds the prepared nodes of a prefab in depth-first order, which
+/// could be applied to `World` without touching the prefab anymore.
+#[derive(Debug, Clone, Default)]
+pub struct CommandBuffer {
+    pub(crate) nodes: Vec<PreparedNode>,
+}
+
+impl CommandBuffer {
+    /// Prepares the nodes of prefab on current thread.
+    pub fn prepare(prefab: &Prefab) -> Self {
+        let order = CommandBuffer::order(prefab);
+        let nodes = order
+            .iter()
+            .map(|&(parent, idx)| CommandBuffer::prepare_node(prefab, parent, idx))
+            .collect();
+
+        CommandBuffer { nodes: nodes }
+    }
+
+    /// Prepares the nodes of prefab on the workers of `sched`. The `progress` is
+    /// increased by one for every prepared node.
+    pub fn prepare_parallel(
+        sched: &ScheduleSystemShared,
+        prefab: &Prefab,
+        progress: &AtomicUsize,
+    ) -> Self {
+        let order = CommandBuffer::order(prefab);
+        let mut nodes: Vec<Option<PreparedNode>> = order.iter().map(|_| None).collect();
+
+        {
+            let order = &order;
+            let chunks = nodes.chunks_mut(NODES_PER_JOB);
+            sched.scope(|s| {
+                for (i, chunk) in chunks.enumerate() {
+                    s.spawn(move |_| {
+                        let offset = i * NODES_PER_JOB;
+                        for (j, v) in chunk.iter_mut().enumerate() {
+                            let (parent, idx) = order[offset + j];
+                            *v = Some(CommandBuffer::prepare_node(prefab, parent, idx));
+                            progress.fetch_add(1, Ordering::Relaxed);
+                        }
+                    });
+                }
+            });
+        }
+
+        CommandBuffer {
+            nodes: nodes.into_iter().map(|v| v.unwrap()).collect(),
+        }
+    }
+
+    /// Gets the number of prepared nodes.
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.nodes.len()
+    }
+
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.nodes.is_empty()
+    }
+
+    #[inline]
+    pub fn nodes(&self) -> &[PreparedNode] {
+        &self.nodes
+    }
+
+    // Flattens the hierarchy of prefab into (parent position, node index) pairs.
+    fn order(prefab: &Prefab) -> Vec<(Option<usize>, usize)> {
+        let mut order = Vec::with_capacity(prefab.nodes.len());
+        if prefab.nodes.is_empty() {
+            return order;
+        }
+
+        let mut nodes = Vec::new();
+        nodes.push((None, 0));
+
+        while let Some((parent, idx)) = nodes.pop() {
+            let n = &prefab.nodes[idx];
+            let position = order.len();
+            order.push((parent, idx));
+
+            if let Some(sib) = n.next_sib {
+                nodes.push((parent, sib));
+            }
+
+            if let Some(child) = n.first_child {
+                nodes.push((Some(position), child));
+            }
+        }
+
+        order
+    }
+
+    fn prepare_node(prefab: &Prefab, parent: Option<usize>, idx: usize) -> PreparedNode {
+        let n = &prefab.nodes[idx];
+
+        let mesh_renderer = n.mesh_renderer.map(|mesh| {
+            let mut mr = MeshRenderer::default();
+            mr.mesh = prefab.meshes[mesh];
+
+            if let Some(v) = n.lightmap {
+                if let Some(&texture) = prefab.lightmaps.get(v.index) {
+                    mr.lightmap = Some(Lightmap::with_region(texture, v.scale, v.offset));
+                }
+            }
+
+            mr
+        });
+
+        PreparedNode {
+            node: idx,
+            parent: parent,
+            name: n.name.clone(),
+            local_transform: n.local_transform,
+            mesh_renderer: mesh_renderer,
+        }
+    }
+}
+
+struct InstantiateState {
+    total: usize,
+    prepared: AtomicUsize,
+    commands: Mutex<Option<Result<CommandBuffer>>>,
+}
+
+/// The handle of an asynchronous instantiation, which could be used to query the
+/// progress and take the prepared `CommandBuffer`.
+#[derive(Clone)]
+pub struct InstantiateJob {
+    state: Arc<InstantiateState>,
+}
+
+impl InstantiateJob {
+    /// Spawns a job which prepares the nodes of prefab on the workers of `sched`.
+    pub fn spawn(sched: Arc<ScheduleSystemShared>, prefab: Arc<Prefab>) -> Self {
+        let state = Arc::new(InstantiateState {
+            total: prefab.nodes.len(),
+            prepared: AtomicUsize::new(0),
+            commands: Mutex::new(None),
+        });
+
+        let job_state = state.clone();
+        let job_sched = sched.clone();
+        sched.spawn(move || {
+            let commands = if prefab.nodes.is_empty() {
+                Err(format_err!("The prefab has no nodes."))
+            } else {
+                // The panics of jobs are propagated out of the scope, they are caught
+                // here so the job is still finished, e.g. with malformed prefabs.
+                let prepared = &job_state.prepared;
+                let prepare = || CommandBuffer::prepare_parallel(&job_sched, &prefab, prepared);
+
+                panic::catch_unwind(AssertUnwindSafe(prepare)).map_err(|err| {
+                    let message = panic_message(&*err);
+                    format_err!("The preparation of prefab panicked. {}", message)
+                })
+            };
+
+            *job_state.commands.lock().unwrap() = Some(commands);
+        });
+
+        InstantiateJob { state: state }
+    }
+
+    /// Gets the ratio of prepared nodes, ranging from 0.0 to 1.0.
+    pub fn progress(&self) -> f32 {
+        if self.state.total == 0 {
+            return 1.0;
+        }
+
+        let prepared = self.state.prepared.load(Ordering::Relaxed);
+        prepared as f32 / self.state.total as f32
+    }
+
+    /// Returns true if the `CommandBuffer` is ready to take, or the preparation failed.
+    #[inline]
+    pub fn is_finished(&self) -> bool {
+        self.state.commands.lock().unwrap().is_some()
+    }
+
+    /// Takes the prepared `CommandBuffer` if its finished. Returns `None` if its still
+    /// being prepared or has been taken already.
+    pub fn take(&self) -> Option<Result<CommandBuffer>> {
+        self.state.commands.lock().unwrap().take()
+    }
+}
+
+fn panic_message(err: &(Any + Send)) -> &str {
+    if let Some(v) = err.downcast_ref::<&str>() {
+        v
+    } else if let Some(v) = err.downcast_ref::<String>() {
+        v
+    } else {
+        "Unknown panic."
+    }
+}
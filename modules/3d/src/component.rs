@@ -1,7 +1,13 @@
 use std::collections::HashMap;
+use std::hash::{Hash, Hasher};
 
+use crayon::sched::ScheduleSystemShared;
+use crayon::utils::Checksum;
+
+use entity_ref::EntityRemap;
 use Entity;
 
+#[derive(Clone)]
 pub struct Component<T> {
     pub remap: HashMap<Entity, usize>,
     pub entities: Vec<Entity>,
@@ -58,4 +64,83 @@ impl<T> Component<T> {
         let data = &mut self.data;
         self.remap.get(&ent).map(move |&index| &mut data[index])
     }
+
+    /// Visits the components in chunks of `chunk_size` on the workers of `sched`, and
+    /// blocks until all the chunks are visited.
+    pub fn par_chunks_mut<F>(&mut self, sched: &ScheduleSystemShared, chunk_size: usize, func: F)
+    where
+        T: Send,
+        F: Fn(&[Entity], &mut [T]) + Sync,
+    {
+        assert!(chunk_size > 0, "The chunk size must be positive.");
+
+        let func = &func;
+        let entities = self.entities.chunks(chunk_size);
+        let data = self.data.chunks_mut(chunk_size);
+        sched.scope(|s| {
+            for (ents, v) in entities.zip(data) {
+                s.spawn(move |_| func(ents, v));
+            }
+        });
+    }
+
+    /// Visits the components joined with the ones of the same entities in `other`, in
+    /// chunks of `chunk_size` on the workers of `sched`. The entities without `U` are
+    /// skipped.
+    pub fn par_join_mut<U, F>(
+        &mut self,
+        other: &Component<U>,
+        sched: &ScheduleSystemShared,
+        chunk_size: usize,
+        func: F,
+    ) where
+        T: Send,
+        U: Sync,
+        F: Fn(Entity, &mut T, &U) + Sync,
+    {
+        self.par_chunks_mut(sched, chunk_size, |ents, data| {
+            for (&ent, v) in ents.iter().zip(data.iter_mut()) {
+                if let Some(rhs) = other.get(ent) {
+                    func(ent, v, rhs);
+                }
+            }
+        });
+    }
+
+    /// Writes the entities and their components with `func` into `checksum`. They are
+    /// visited in the order of entities, so the checksum does not depend on the order
+    /// of insertions and removals.
+    pub fn checksum<F>(&self, checksum: &mut Checksum, func: F)
+    where
+        F: Fn(&T, &mut Checksum),
+    {
+        let mut entities = self.entities.clone();
+        entities.sort();
+
+        checksum.write_usize(entities.len());
+        for ent in entities {
+            ent.hash(checksum);
+            func(&self.data[self.remap[&ent]], checksum);
+        }
+    }
+
+    /// Replaces the entities with the new ones in `remap`, and reorders the data by the
+    /// indices of entities.
+    pub fn remap_entities(&mut self, remap: &EntityRemap) {
+        let mut items: Vec<_> = self
+            .entities
+            .drain(..)
+            .map(|v| remap.get(v).unwrap_or(v))
+            .zip(self.data.drain(..))
+            .collect();
+
+        items.sort_by_key(|v| v.0.index());
+
+        self.remap.clear();
+        for (i, (ent, v)) in items.into_iter().enumerate() {
+            self.remap.insert(ent, i);
+            self.entities.push(ent);
+            self.data.push(v);
+        }
+    }
 }
@@ -0,0 +1,315 @@
+//! Deferred structural changes of `World`.
+//!
+//! The systems which iterate the components could not create or remove entities, or
+//! add and remove their components safely in the middle of iteration. Instead, they
+//! record the changes into an `EntityCommandBuffer`, which is flushed into the world
+//! with `World::apply_commands` at frame boundaries.
+//!
+//! The entities created by command buffer are placeholders, which could be referenced
+//! by the following commands in the same buffer, and are mapped to the real entities
+//! when applied:
+//!
+//! ```rust,ignore
+//! let mut commands = EntityCommandBuffer::new();
+//! for v in &mut bullets {
+//!     if v.hit {
+//!         commands.remove(v.ent);
+//!
+//!         let spark = commands.create();
+//!         commands.add(spark, Transform { position: v.position, ..Default::default() });
+//!         commands.add(spark, spark_renderer);
+//!     }
+//! }
+//!
+//! let remap = world.apply_commands(commands)?;
+//! ```
+//!
+//! The command buffers are `Send`, so every job on the workers of `sched` could record
+//! into its own buffer, which are merged with `EntityCommandBuffer::append` in a fixed
+//! order afterwards to keep the results deterministic.
+
+use animation::Animator;
+use path::PathFollow;
+use physics::{CharacterController, Collider, TriggerVolume};
+use renderers::{BlobShadow, Camera, LineRenderer, Lit, MeshRenderer, TrailRenderer};
+use scene::Transform;
+use ticks::TickRate;
+use Entity;
+
+/// The components that could be added with `EntityCommandBuffer`. The large ones are
+/// boxed to keep the commands small.
+#[derive(Debug, Clone)]
+pub enum EntityComponent {
+    Name(String),
+    Transform(Transform),
+    /// Attaches the entity to parent, the local transform is kept.
+    Parent(Entity),
+    TickRate(TickRate),
+    Camera(Box<Camera>),
+    Lit(Box<Lit>),
+    MeshRenderer(Box<MeshRenderer>),
+    LineRenderer(Box<LineRenderer>),
+    TrailRenderer(Box<TrailRenderer>),
+    BlobShadow(Box<BlobShadow>),
+    Collider(Box<Collider>),
+    TriggerVolume(Box<TriggerVolume>),
+    CharacterController(Box<CharacterController>),
+    PathFollow(Box<PathFollow>),
+    Animator(Box<Animator>),
+}
+
+/// The kinds of `EntityComponent`, which are used to remove components.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum EntityComponentKind {
+    Name,
+    /// Resets the local transform to identity.
+    Transform,
+    /// Detaches the entity from its parent.
+    Parent,
+    TickRate,
+    Camera,
+    Lit,
+    MeshRenderer,
+    LineRenderer,
+    TrailRenderer,
+    BlobShadow,
+    Collider,
+    TriggerVolume,
+    CharacterController,
+    PathFollow,
+    Animator,
+}
+
+impl EntityComponent {
+    /// Gets the kind of this component.
+    pub fn kind(&self) -> EntityComponentKind {
+        match *self {
+            EntityComponent::Name(_) => EntityComponentKind::Name,
+            EntityComponent::Transform(_) => EntityComponentKind::Transform,
+            EntityComponent::Parent(_) => EntityComponentKind::Parent,
+            EntityComponent::TickRate(_) => EntityComponentKind::TickRate,
+            EntityComponent::Camera(_) => EntityComponentKind::Camera,
+            EntityComponent::Lit(_) => EntityComponentKind::Lit,
+            EntityComponent::MeshRenderer(_) => EntityComponentKind::MeshRenderer,
+            EntityComponent::LineRenderer(_) => EntityComponentKind::LineRenderer,
+            EntityComponent::TrailRenderer(_) => EntityComponentKind::TrailRenderer,
+            EntityComponent::BlobShadow(_) => EntityComponentKind::BlobShadow,
+            EntityComponent::Collider(_) => EntityComponentKind::Collider,
+            EntityComponent::TriggerVolume(_) => EntityComponentKind::TriggerVolume,
+            EntityComponent::CharacterController(_) => EntityComponentKind::CharacterController,
+            EntityComponent::PathFollow(_) => EntityComponentKind::PathFollow,
+            EntityComponent::Animator(_) => EntityComponentKind::Animator,
+        }
+    }
+}
+
+macro_rules! impl_from_component {
+    ($name:ident) => {
+        impl From<$name> for EntityComponent {
+            fn from(v: $name) -> Self {
+                EntityComponent::$name(Box::new(v))
+            }
+        }
+    };
+}
+
+impl_from_component!(Camera);
+impl_from_component!(Lit);
+impl_from_component!(MeshRenderer);
+impl_from_component!(LineRenderer);
+impl_from_component!(TrailRenderer);
+impl_from_component!(BlobShadow);
+impl_from_component!(Collider);
+impl_from_component!(TriggerVolume);
+impl_from_component!(CharacterController);
+impl_from_component!(PathFollow);
+impl_from_component!(Animator);
+
+impl From<Transform> for EntityComponent {
+    fn from(v: Transform) -> Self {
+        EntityComponent::Transform(v)
+    }
+}
+
+impl From<TickRate> for EntityComponent {
+    fn from(v: TickRate) -> Self {
+        EntityComponent::TickRate(v)
+    }
+}
+
+impl<'a> From<&'a str> for EntityComponent {
+    fn from(v: &'a str) -> Self {
+        EntityComponent::Name(v.to_owned())
+    }
+}
+
+impl From<String> for EntityComponent {
+    fn from(v: String) -> Self {
+        EntityComponent::Name(v)
+    }
+}
+
+#[derive(Debug, Clone)]
+pub(crate) enum EntityCommand {
+    Create(Entity),
+    Remove(Entity),
+    Add(Entity, EntityComponent),
+    RemoveComponent(Entity, EntityComponentKind),
+}
+
+/// `EntityCommandBuffer` records the structural changes of `World` in order.
+#[derive(Debug, Clone, Default)]
+pub struct EntityCommandBuffer {
+    pub(crate) commands: Vec<EntityCommand>,
+    created: usize,
+}
+
+impl EntityCommandBuffer {
+    pub fn new() -> Self {
+        EntityCommandBuffer::default()
+    }
+
+    /// Creates a placeholder entity, which is mapped to a new entity when applied.
+    pub fn create(&mut self) -> Entity {
+        let ent = Entity::placeholder(self.created);
+        self.created += 1;
+        self.commands.push(EntityCommand::Create(ent));
+        ent
+    }
+
+    /// Removes a entity and all of its descendants.
+    #[inline]
+    pub fn remove(&mut self, ent: Entity) {
+        self.commands.push(EntityCommand::Remove(ent));
+    }
+
+    /// Adds a component to entity, or replaces the existing one.
+    #[inline]
+    pub fn add<T: Into<EntityComponent>>(&mut self, ent: Entity, component: T) {
+        self.commands
+            .push(EntityCommand::Add(ent, component.into()));
+    }
+
+    /// Removes a component from entity.
+    #[inline]
+    pub fn remove_component(&mut self, ent: Entity, kind: EntityComponentKind) {
+        self.commands
+            .push(EntityCommand::RemoveComponent(ent, kind));
+    }
+
+    /// Moves all the commands of `other` after the commands of this buffer. The
+    /// placeholders of `other` are renumbered.
+    pub fn append(&mut self, other: EntityCommandBuffer) {
+        let offset = self.created;
+        let remap = |ent: Entity| {
+            if ent.is_placeholder() {
+                Entity::placeholder(ent.index() as usize + offset)
+            } else {
+                ent
+            }
+        };
+
+        for v in other.commands {
+            let v = match v {
+                EntityCommand::Create(ent) => EntityCommand::Create(remap(ent)),
+                EntityCommand::Remove(ent) => EntityCommand::Remove(remap(ent)),
+                EntityCommand::Add(ent, EntityComponent::Parent(parent)) => {
+                    EntityCommand::Add(remap(ent), EntityComponent::Parent(remap(parent)))
+                }
+                EntityCommand::Add(ent, component) => EntityCommand::Add(remap(ent), component),
+                EntityCommand::RemoveComponent(ent, kind) => {
+                    EntityCommand::RemoveComponent(remap(ent), kind)
+                }
+            };
+
+            self.commands.push(v);
+        }
+
+        self.created += other.created;
+    }
+
+    /// Gets the number of recorded commands.
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.commands.len()
+    }
+
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.commands.is_empty()
+    }
+
+    /// Gets the number of created placeholders.
+    #[inline]
+    pub fn created(&self) -> usize {
+        self.created
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crayon::utils::Handle;
+
+    #[test]
+    fn append() {
+        let ent: Entity = Handle::new(3, 1).into();
+
+        let mut lhs = EntityCommandBuffer::new();
+        let e1 = lhs.create();
+        lhs.add(e1, "e1");
+        assert!(e1.is_placeholder());
+
+        let mut rhs = EntityCommandBuffer::new();
+        let e2 = rhs.create();
+        rhs.add(e2, EntityComponent::Parent(ent));
+        rhs.add(ent, EntityComponent::Parent(e2));
+        rhs.remove_component(e2, EntityComponentKind::Name);
+        assert_eq!(e1, e2);
+
+        lhs.append(rhs);
+        assert_eq!(lhs.len(), 6);
+        assert_eq!(lhs.created(), 2);
+
+        let e2 = Entity::placeholder(1);
+        match lhs.commands[2] {
+            EntityCommand::Create(v) => assert_eq!(v, e2),
+            _ => unreachable!(),
+        }
+
+        match lhs.commands[3] {
+            EntityCommand::Add(v, EntityComponent::Parent(p)) => assert_eq!((v, p), (e2, ent)),
+            _ => unreachable!(),
+        }
+
+        match lhs.commands[4] {
+            EntityCommand::Add(v, EntityComponent::Parent(p)) => assert_eq!((v, p), (ent, e2)),
+            _ => unreachable!(),
+        }
+
+        match lhs.commands[5] {
+            EntityCommand::RemoveComponent(v, kind) => {
+                assert_eq!((v, kind), (e2, EntityComponentKind::Name))
+            }
+            _ => unreachable!(),
+        }
+    }
+
+    #[test]
+    fn kinds() {
+        let ent = Entity::placeholder(0);
+        let mut commands = EntityCommandBuffer::new();
+        commands.add(ent, Transform::default());
+        commands.add(ent, TickRate::Half);
+
+        match commands.commands[0] {
+            EntityCommand::Add(_, ref v) => assert_eq!(v.kind(), EntityComponentKind::Transform),
+            _ => unreachable!(),
+        }
+
+        match commands.commands[1] {
+            EntityCommand::Add(_, ref v) => assert_eq!(v.kind(), EntityComponentKind::TickRate),
+            _ => unreachable!(),
+        }
+    }
+}
@@ -0,0 +1,148 @@
+//! Entity-to-entity references which could be stored inside components safely.
+
+use std::collections::HashMap;
+
+use crayon::utils::HandlePool;
+
+use component::Component;
+use Entity;
+
+/// A reference to another entity. Unlike raw `Entity` fields, it resolves to `None` once
+/// the target entity has been removed from the world, even if its slot is reused, and
+/// could be remapped when the entities are instantiated or cloned.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct EntityRef(Option<Entity>);
+
+impl EntityRef {
+    /// Creates a reference to `ent`.
+    #[inline]
+    pub fn new(ent: Entity) -> Self {
+        EntityRef(Some(ent))
+    }
+
+    /// Creates a null reference.
+    #[inline]
+    pub fn none() -> Self {
+        EntityRef(None)
+    }
+
+    /// Returns true if this reference is null.
+    #[inline]
+    pub fn is_none(&self) -> bool {
+        self.0.is_none()
+    }
+
+    /// Gets the referenced entity without checking if its still alive.
+    #[inline]
+    pub fn raw(&self) -> Option<Entity> {
+        self.0
+    }
+
+    /// Resets this reference to null.
+    #[inline]
+    pub fn clear(&mut self) {
+        self.0 = None;
+    }
+
+    /// Gets the referenced entity if its still alive.
+    #[inline]
+    pub(crate) fn get(&self, entities: &HandlePool) -> Option<Entity> {
+        self.0.and_then(|v| if entities.is_alive(v) { Some(v) } else { None })
+    }
+
+    /// Remaps the reference with `remap`, the references to entities which are not
+    /// covered by `remap` are kept untouched.
+    pub fn remap(&mut self, remap: &EntityRemap) {
+        if let Some(v) = self.0 {
+            if let Some(&to) = remap.0.get(&v) {
+                self.0 = Some(to);
+            }
+        }
+    }
+}
+
+impl From<Entity> for EntityRef {
+    fn from(ent: Entity) -> Self {
+        EntityRef::new(ent)
+    }
+}
+
+/// The mappings from source entities to the new ones.
+#[derive(Debug, Default, Clone)]
+pub struct EntityRemap(HashMap<Entity, Entity>);
+
+impl EntityRemap {
+    pub fn new() -> Self {
+        EntityRemap(HashMap::new())
+    }
+
+    /// Maps `from` to `to`.
+    #[inline]
+    pub fn insert(&mut self, from: Entity, to: Entity) {
+        self.0.insert(from, to);
+    }
+
+    /// Gets the new entity of `from`.
+    #[inline]
+    pub fn get(&self, from: Entity) -> Option<Entity> {
+        self.0.get(&from).cloned()
+    }
+
+    /// Returns the number of mappings.
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.0.len()
+    }
+
+    /// Checks if there is no mapping.
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+}
+
+/// Components which hold `EntityRef`s should implement this trait, so their references
+/// could be fixed up automatically.
+pub trait VisitEntityRefs {
+    /// Visits all the `EntityRef`s of this component.
+    fn visit_entity_refs<F: FnMut(&mut EntityRef)>(&mut self, visitor: F);
+}
+
+/// The stores of components which are kept outside of `World`, e.g. the components of
+/// games or the bodies of physics. Their entities are fixed up by `World::compact_with`.
+pub trait EntityStore {
+    /// Replaces the entities with the new ones in `remap`.
+    fn remap_entities(&mut self, remap: &EntityRemap);
+}
+
+/// The components are reordered by the new entities, and the `EntityRef`s held by
+/// them are remapped too. The components without `EntityRef`s could implement
+/// `VisitEntityRefs` with nothing to visit.
+impl<T: VisitEntityRefs> EntityStore for Component<T> {
+    fn remap_entities(&mut self, remap: &EntityRemap) {
+        Component::remap_entities(self, remap);
+        self.remap_entity_refs(remap);
+    }
+}
+
+impl<T: VisitEntityRefs> Component<T> {
+    /// Remaps all the `EntityRef`s in this component.
+    pub fn remap_entity_refs(&mut self, remap: &EntityRemap) {
+        for v in &mut self.data {
+            v.visit_entity_refs(|r| r.remap(remap));
+        }
+    }
+
+    /// Resets all the `EntityRef`s which refer to `deletions` to null.
+    pub fn clear_entity_refs(&mut self, deletions: &[Entity]) {
+        for v in &mut self.data {
+            v.visit_entity_refs(|r| {
+                if let Some(ent) = r.raw() {
+                    if deletions.contains(&ent) {
+                        r.clear();
+                    }
+                }
+            });
+        }
+    }
+}
@@ -0,0 +1,374 @@
+//! The parent-child relationships between entities.
+//!
+//! The `Hierarchy` only keeps the tree structures, the transforms are composed along it
+//! by `SceneGraph`. Other systems could rely on it to propagate their own states from
+//! parents to children, e.g. the visibilities or layers.
+//!
+//! ```rust,ignore
+//! world.set_parent(wheel, car)?;
+//!
+//! for child in world.scene.hierarchy().children(car) {
+//!     // ...
+//! }
+//! ```
+//!
+//! # Dirty Propagation
+//!
+//! Changing the parent or the local transform of a node marks it and all of its
+//! descendants as dirty, so the systems that cache derived states only need to update
+//! the dirty nodes. The dirty flags are cleared after `World::advance`.
+
+use std::collections::btree_set;
+use std::collections::BTreeSet;
+
+use entity_ref::EntityRemap;
+use scene::{Error, Result};
+use {Component, Entity};
+
+/// The links of a node. Every node keeps its parent and siblings, so the children
+/// could be iterated without any allocations.
+#[derive(Default, Debug, Clone, Copy)]
+struct Node {
+    parent: Option<Entity>,
+    next_sib: Option<Entity>,
+    prev_sib: Option<Entity>,
+    first_child: Option<Entity>,
+}
+
+#[derive(Clone)]
+pub struct Hierarchy {
+    nodes: Component<Node>,
+    roots: BTreeSet<Entity>,
+    dirty: BTreeSet<Entity>,
+}
+
+impl Hierarchy {
+    pub fn new() -> Self {
+        Hierarchy {
+            nodes: Component::new(),
+            roots: BTreeSet::new(),
+            dirty: BTreeSet::new(),
+        }
+    }
+
+    /// Adds a root node.
+    pub fn add(&mut self, ent: Entity) {
+        assert!(!self.nodes.has(ent), "Ent already has a node in Hierarchy.");
+
+        self.nodes.add(ent, Node::default());
+        self.roots.insert(ent);
+        self.dirty.insert(ent);
+    }
+
+    /// Removes a node and all of its descendants, the node is unlinked from its parent
+    /// and siblings first. Returns the removed entities in tree order.
+    pub fn remove(&mut self, ent: Entity) -> Option<Vec<Entity>> {
+        if !self.nodes.has(ent) {
+            return None;
+        }
+
+        self.remove_from_parent(ent).unwrap();
+
+        let removes: Vec<_> = ::std::iter::once(ent)
+            .chain(self.descendants(ent))
+            .collect();
+
+        for &v in &removes {
+            self.nodes.remove(v);
+            self.roots.remove(&v);
+            self.dirty.remove(&v);
+        }
+
+        Some(removes)
+    }
+
+    /// Returns true if the entity has a node.
+    #[inline]
+    pub fn has(&self, ent: Entity) -> bool {
+        self.nodes.has(ent)
+    }
+
+    /// Gets the parent node.
+    #[inline]
+    pub fn parent(&self, ent: Entity) -> Option<Entity> {
+        self.nodes.get(ent).and_then(|v| v.parent)
+    }
+
+    /// Returns ture if this is the leaf of a hierarchy, aka. has no child.
+    #[inline]
+    pub fn is_leaf(&self, ent: Entity) -> bool {
+        self.nodes
+            .get(ent)
+            .map(|v| v.first_child.is_none())
+            .unwrap_or(false)
+    }
+
+    /// Returns ture if this is the root of a hierarchy, aka. has no parent.
+    #[inline]
+    pub fn is_root(&self, ent: Entity) -> bool {
+        self.nodes
+            .get(ent)
+            .map(|v| v.parent.is_none())
+            .unwrap_or(false)
+    }
+
+    /// Returns an iterator of the root nodes, in the order of entities.
+    #[inline]
+    pub fn roots(&self) -> btree_set::Iter<Entity> {
+        self.roots.iter()
+    }
+
+    /// Attachs a new child to parent, before existing children. The child is detached
+    /// from its previous parent first, or becomes a root if `parent` is `None`.
+    pub fn set_parent<T>(&mut self, child: Entity, parent: T) -> Result<()>
+    where
+        T: Into<Option<Entity>>,
+    {
+        let parent = parent.into();
+        if !self.nodes.has(child) {
+            return Err(Error::NonNodeFound(child));
+        }
+
+        if let Some(parent) = parent {
+            if parent == child {
+                return Err(Error::CanNotAttachSelfAsParent);
+            }
+
+            if !self.nodes.has(parent) {
+                return Err(Error::NonNodeFound(parent));
+            }
+
+            if self.is_ancestor(parent, child) {
+                return Err(Error::CanNotAttachDescendantAsParent(child, parent));
+            }
+        }
+
+        self.remove_from_parent(child)?;
+
+        if let Some(parent) = parent {
+            let next_sib = {
+                let node = self.nodes.get_mut(parent).unwrap();
+                ::std::mem::replace(&mut node.first_child, Some(child))
+            };
+
+            if let Some(next_sib) = next_sib {
+                self.nodes.get_mut(next_sib).unwrap().prev_sib = Some(child);
+            }
+
+            {
+                let node = self.nodes.get_mut(child).unwrap();
+                node.parent = Some(parent);
+                node.next_sib = next_sib;
+            }
+
+            self.roots.remove(&child);
+        }
+
+        self.set_dirty(child);
+        Ok(())
+    }
+
+    /// Detaches a node from its parent and siblings, and makes it a root. Children are
+    /// not affected.
+    pub fn remove_from_parent(&mut self, child: Entity) -> Result<()> {
+        let (parent, next_sib, prev_sib) = {
+            let node = self
+                .nodes
+                .get_mut(child)
+                .ok_or(Error::NonNodeFound(child))?;
+
+            (
+                node.parent.take(),
+                node.next_sib.take(),
+                node.prev_sib.take(),
+            )
+        };
+
+        if parent.is_none() {
+            return Ok(());
+        }
+
+        if let Some(next_sib) = next_sib {
+            self.nodes.get_mut(next_sib).unwrap().prev_sib = prev_sib;
+        }
+
+        if let Some(prev_sib) = prev_sib {
+            self.nodes.get_mut(prev_sib).unwrap().next_sib = next_sib;
+        } else if let Some(parent) = parent {
+            // Take the next sibling as the first child of parent if there is no previous
+            // sibling.
+            self.nodes.get_mut(parent).unwrap().first_child = next_sib;
+        }
+
+        self.roots.insert(child);
+        self.set_dirty(child);
+        Ok(())
+    }
+
+    /// Returns an iterator of references to its ancestors.
+    #[inline]
+    pub fn ancestors(&self, ent: Entity) -> Ancestors {
+        Ancestors {
+            cursor: self.parent(ent),
+            hierarchy: self,
+        }
+    }
+
+    /// Return true if rhs is one of the ancestor of `lhs`.
+    #[inline]
+    pub fn is_ancestor(&self, lhs: Entity, rhs: Entity) -> bool {
+        self.ancestors(lhs).any(|v| v == rhs)
+    }
+
+    /// Returns an iterator of references to its children. The children are ordered from
+    /// the most recently attached one.
+    #[inline]
+    pub fn children(&self, ent: Entity) -> Children {
+        Children {
+            cursor: self.nodes.get(ent).and_then(|v| v.first_child),
+            hierarchy: self,
+        }
+    }
+
+    /// Returns an iterator of references to its descendants in tree order.
+    #[inline]
+    pub fn descendants(&self, ent: Entity) -> Descendants {
+        Descendants {
+            root: ent,
+            cursor: self.nodes.get(ent).and_then(|v| v.first_child),
+            hierarchy: self,
+        }
+    }
+
+    /// Replaces the entities with the new ones in `remap`, the tree structures and
+    /// dirty flags are kept.
+    pub fn remap_entities(&mut self, remap: &EntityRemap) {
+        let map = |v: Option<Entity>| v.map(|v| remap.get(v).unwrap_or(v));
+
+        self.nodes.remap_entities(remap);
+        for v in &mut self.nodes.data {
+            v.parent = map(v.parent);
+            v.next_sib = map(v.next_sib);
+            v.prev_sib = map(v.prev_sib);
+            v.first_child = map(v.first_child);
+        }
+
+        let roots = ::std::mem::replace(&mut self.roots, BTreeSet::new());
+        self.roots = roots.into_iter().filter_map(|v| map(Some(v))).collect();
+
+        let dirty = ::std::mem::replace(&mut self.dirty, BTreeSet::new());
+        self.dirty = dirty.into_iter().filter_map(|v| map(Some(v))).collect();
+    }
+}
+
+impl Hierarchy {
+    /// Marks the node and all of its descendants as dirty.
+    pub fn set_dirty(&mut self, ent: Entity) {
+        // The descendants of a dirty node are always dirty.
+        if !self.nodes.has(ent) || self.dirty.contains(&ent) {
+            return;
+        }
+
+        let descendants: Vec<_> = self.descendants(ent).collect();
+        self.dirty.insert(ent);
+        self.dirty.extend(descendants);
+    }
+
+    /// Returns true if the node, or any of its ancestors, has been changed since last
+    /// `clear_dirty`.
+    #[inline]
+    pub fn is_dirty(&self, ent: Entity) -> bool {
+        self.dirty.contains(&ent)
+    }
+
+    /// Returns an iterator of the dirty nodes, in the order of entities.
+    #[inline]
+    pub fn dirty(&self) -> btree_set::Iter<Entity> {
+        self.dirty.iter()
+    }
+
+    /// Clears the dirty flags of all nodes.
+    #[inline]
+    pub fn clear_dirty(&mut self) {
+        self.dirty.clear();
+    }
+}
+
+/// An iterator of references to its ancestors.
+pub struct Ancestors<'a> {
+    hierarchy: &'a Hierarchy,
+    cursor: Option<Entity>,
+}
+
+impl<'a> Iterator for Ancestors<'a> {
+    type Item = Entity;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if let Some(ent) = self.cursor {
+            let parent = self.hierarchy.parent(ent);
+            ::std::mem::replace(&mut self.cursor, parent)
+        } else {
+            None
+        }
+    }
+}
+
+/// An iterator of references to its children.
+pub struct Children<'a> {
+    hierarchy: &'a Hierarchy,
+    cursor: Option<Entity>,
+}
+
+impl<'a> Iterator for Children<'a> {
+    type Item = Entity;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if let Some(ent) = self.cursor {
+            let next_sib = self.hierarchy.nodes.get(ent).and_then(|v| v.next_sib);
+            ::std::mem::replace(&mut self.cursor, next_sib)
+        } else {
+            None
+        }
+    }
+}
+
+/// An iterator of references to its descendants, in tree order.
+pub struct Descendants<'a> {
+    hierarchy: &'a Hierarchy,
+    root: Entity,
+    cursor: Option<Entity>,
+}
+
+impl<'a> Iterator for Descendants<'a> {
+    type Item = Entity;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if let Some(ent) = self.cursor {
+            let nodes = &self.hierarchy.nodes;
+            let mut v = *nodes.get(ent).unwrap();
+
+            // Deep first search when iterating children recursively.
+            if v.first_child.is_some() {
+                return ::std::mem::replace(&mut self.cursor, v.first_child);
+            }
+
+            if v.next_sib.is_some() {
+                return ::std::mem::replace(&mut self.cursor, v.next_sib);
+            }
+
+            // Travel back when we reach leaf-node.
+            while let Some(parent) = v.parent {
+                if parent == self.root {
+                    break;
+                }
+
+                v = *nodes.get(parent).unwrap();
+                if v.next_sib.is_some() {
+                    return ::std::mem::replace(&mut self.cursor, v.next_sib);
+                }
+            }
+        }
+
+        ::std::mem::replace(&mut self.cursor, None)
+    }
+}
@@ -5,20 +5,43 @@ extern crate failure;
 #[macro_use]
 extern crate serde;
 
+pub mod animation;
 pub mod assets;
+pub mod commands;
+pub mod hierarchy;
+pub mod path;
+pub mod physics;
 pub mod renderers;
 pub mod scene;
 pub mod tags;
+pub mod ticks;
+pub mod transitions;
 
 mod component;
-use self::component::Component;
+pub use self::component::Component;
 
 mod world;
-pub use self::world::{world_impl, Entity, World};
+pub use self::world::{world_impl, Entity, World, WorldId, WorldState};
+
+mod entity_ref;
+pub use self::entity_ref::{EntityRef, EntityRemap, EntityStore, VisitEntityRefs};
+
+pub mod entity_commands;
 
 pub mod prelude {
+    pub use animation::{AnimationClip, Animator, RootMotion, Skeleton};
     pub use assets::{Prefab, WorldResources};
-    pub use renderers::{Camera, Lit, MeshRenderer, SimpleMaterial, SimpleRenderer};
+    pub use commands::{CommandBuffer, InstantiateJob};
+    pub use entity_commands::{EntityCommandBuffer, EntityComponent, EntityComponentKind};
+    pub use hierarchy::Hierarchy;
+    pub use path::{PathEnd, PathFollow};
+    pub use physics::{CharacterController, Collider, TriggerShape, TriggerVolume};
+    pub use renderers::{
+        BlobShadow, Camera, DebugDrawMode, LineRenderer, Lit, MeshRenderer, PickingService,
+        ShaderKeywords, ShadowQuality, SimpleMaterial, SimpleRenderer, TrailRenderer,
+    };
     pub use scene::{SceneGraph, Transform};
-    pub use world::{Entity, World};
+    pub use ticks::TickRate;
+    pub use world::{Entity, World, WorldId};
+    pub use {EntityRef, VisitEntityRefs};
 }
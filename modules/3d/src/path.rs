@@ -0,0 +1,196 @@
+//! Moves entities along splines, e.g. camera rails, moving platforms and projectiles.
+//!
+//! ```rust,ignore
+//! let rail = Arc::new(math::Spline::catmull_rom(points)?);
+//! world.paths.add(camera, PathFollow::new(rail, 2.0));
+//!
+//! // Every frame.
+//! world.paths.advance(&mut world.scene, ctx.time.frame_delta());
+//! ```
+
+use std::hash::{Hash, Hasher};
+use std::sync::Arc;
+use std::time::Duration;
+
+use crayon::math::{self, InnerSpace, QuaternionExt};
+use crayon::utils::Checksum;
+
+use entity_ref::EntityRemap;
+use scene::SceneGraph;
+use {Component, Entity};
+
+/// What happens when a follower reaches the end of path.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum PathEnd {
+    /// Stops at the end.
+    Clamp,
+    /// Restarts from the beginning.
+    Loop,
+    /// Moves back and forth.
+    PingPong,
+}
+
+/// `PathFollow` moves the transform of entity along a spline in world space.
+#[derive(Debug, Clone)]
+pub struct PathFollow {
+    pub spline: Arc<math::Spline>,
+    /// The speed in units per second.
+    pub speed: f32,
+    /// The current distance along the spline.
+    pub distance: f32,
+    pub end: PathEnd,
+    /// Rotates the entity so its forward vector is aligned with the tangent of spline.
+    pub align: bool,
+    /// The up vector used when aligning.
+    pub up: math::Vector3<f32>,
+    reversed: bool,
+}
+
+impl PathFollow {
+    pub fn new(spline: Arc<math::Spline>, speed: f32) -> Self {
+        PathFollow {
+            spline: spline,
+            speed: speed,
+            distance: 0.0,
+            end: PathEnd::Clamp,
+            align: true,
+            up: math::Vector3::new(0.0, 1.0, 0.0),
+            reversed: false,
+        }
+    }
+
+    /// Returns true if the follower has stopped at the end of a clamped path.
+    pub fn is_finished(&self) -> bool {
+        self.end == PathEnd::Clamp && self.distance >= self.spline.length()
+    }
+
+    /// Moves forward by `dt` seconds.
+    pub fn advance(&mut self, dt: f32) {
+        let length = self.spline.length();
+        let step = self.speed * dt;
+
+        match self.end {
+            PathEnd::Clamp => {
+                self.distance = (self.distance + step).max(0.0).min(length);
+            }
+            PathEnd::Loop => {
+                self.distance = if length > 0.0 {
+                    (self.distance + step) % length
+                } else {
+                    0.0
+                };
+
+                if self.distance < 0.0 {
+                    self.distance += length;
+                }
+            }
+            PathEnd::PingPong => {
+                let mut v = if self.reversed {
+                    self.distance - step
+                } else {
+                    self.distance + step
+                };
+
+                // Reflects at the ends, a long step might bounce multiple times.
+                while length > 0.0 && (v < 0.0 || v > length) {
+                    v = if v < 0.0 { -v } else { 2.0 * length - v };
+                    self.reversed = !self.reversed;
+                }
+
+                self.distance = v.max(0.0).min(length);
+            }
+        }
+    }
+
+    /// Gets the position at current distance.
+    #[inline]
+    pub fn position(&self) -> math::Vector3<f32> {
+        self.spline.evaluate_at_distance(self.distance)
+    }
+
+    /// Gets the rotation whose forward vector is aligned with the moving direction.
+    pub fn rotation(&self) -> Option<math::Quaternion<f32>> {
+        let mut dir = self.spline.tangent_at_distance(self.distance);
+        if self.reversed {
+            dir = -dir;
+        }
+
+        if dir.magnitude2() <= 0.0 || self.up.cross(dir).magnitude2() <= 0.0 {
+            return None;
+        }
+
+        Some(math::Quaternion::look_rotation(dir, self.up))
+    }
+}
+
+/// The collection of `PathFollow`s in world.
+#[derive(Clone)]
+pub struct PathFollowers {
+    followers: Component<PathFollow>,
+}
+
+impl PathFollowers {
+    pub fn new() -> Self {
+        PathFollowers {
+            followers: Component::new(),
+        }
+    }
+
+    #[inline]
+    pub fn add(&mut self, ent: Entity, follow: PathFollow) {
+        self.followers.add(ent, follow);
+    }
+
+    #[inline]
+    pub fn remove(&mut self, ent: Entity) {
+        self.followers.remove(ent);
+    }
+
+    #[inline]
+    pub fn get(&self, ent: Entity) -> Option<&PathFollow> {
+        self.followers.get(ent)
+    }
+
+    #[inline]
+    pub fn get_mut(&mut self, ent: Entity) -> Option<&mut PathFollow> {
+        self.followers.get_mut(ent)
+    }
+
+    /// Replaces the entities with the new ones in `remap`.
+    #[inline]
+    pub fn remap_entities(&mut self, remap: &EntityRemap) {
+        self.followers.remap_entities(remap);
+    }
+
+    /// Writes the states of followers into `checksum`. The splines are shared and not
+    /// simulated, so only their lengths are written.
+    pub fn checksum(&self, checksum: &mut Checksum) {
+        self.followers.checksum(checksum, |v, checksum| {
+            checksum.write_f32s(&[v.spline.length(), v.speed, v.distance]);
+            checksum.write_f32s(&[v.up.x, v.up.y, v.up.z]);
+            checksum.write_u8(v.end as u8);
+            v.align.hash(checksum);
+            v.reversed.hash(checksum);
+        });
+    }
+
+    /// Moves all the followers forward, and updates the transforms of entities.
+    pub fn advance(&mut self, scene: &mut SceneGraph, delta: Duration) {
+        let dt = delta.as_secs() as f32 + delta.subsec_nanos() as f32 * 1e-9;
+
+        for (&ent, follow) in self.followers
+            .entities
+            .iter()
+            .zip(self.followers.data.iter_mut())
+        {
+            follow.advance(dt);
+            scene.set_position(ent, follow.position());
+
+            if follow.align {
+                if let Some(rotation) = follow.rotation() {
+                    scene.set_rotation(ent, rotation);
+                }
+            }
+        }
+    }
+}
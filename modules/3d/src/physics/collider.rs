@@ -0,0 +1,220 @@
+//! The static collision geometry of scene.
+
+use std::hash::Hasher;
+use std::iter::Zip;
+use std::slice;
+
+use crayon::math::{self, InnerSpace};
+use crayon::utils::Checksum;
+
+use super::geometry::{closest_on_box, closest_on_segment, direction, ray_box, ray_sphere};
+use entity_ref::EntityRemap;
+use scene::SceneGraph;
+use {Component, Entity};
+
+/// The shape of collider, which is placed with the world transform of entity.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+pub enum Collider {
+    /// An oriented box with the half extents.
+    Box(math::Vector3<f32>),
+    /// A sphere with the radius.
+    Sphere(f32),
+}
+
+/// The contact between a capsule and collider.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Contact {
+    pub ent: Entity,
+    /// The closest point on the surface of collider.
+    pub point: math::Vector3<f32>,
+    /// The direction which pushes the capsule out of collider.
+    pub normal: math::Vector3<f32>,
+    /// The normal of surface at `point`, which is `None` at the edges and corners.
+    pub surface: Option<math::Vector3<f32>>,
+    /// The penetration depth, which is negative if the capsule is separated.
+    pub depth: f32,
+}
+
+/// The closest hit of raycast against colliders.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RaycastHit {
+    pub ent: Entity,
+    /// The point on the surface of collider.
+    pub point: math::Vector3<f32>,
+    /// The normal of surface at `point`.
+    pub normal: math::Vector3<f32>,
+    /// The distance from the origin of ray to `point`.
+    pub distance: f32,
+}
+
+/// The collection of `Collider`s in world.
+#[derive(Clone)]
+pub struct Colliders {
+    colliders: Component<Collider>,
+}
+
+impl Colliders {
+    pub fn new() -> Self {
+        Colliders {
+            colliders: Component::new(),
+        }
+    }
+
+    #[inline]
+    pub fn add(&mut self, ent: Entity, collider: Collider) {
+        self.colliders.add(ent, collider);
+    }
+
+    #[inline]
+    pub fn remove(&mut self, ent: Entity) {
+        self.colliders.remove(ent);
+    }
+
+    #[inline]
+    pub fn get(&self, ent: Entity) -> Option<&Collider> {
+        self.colliders.get(ent)
+    }
+
+    /// Gets the iterator of entities and their colliders.
+    #[inline]
+    pub fn iter(&self) -> Zip<slice::Iter<Entity>, slice::Iter<Collider>> {
+        self.colliders
+            .entities
+            .iter()
+            .zip(self.colliders.data.iter())
+    }
+
+    /// Replaces the entities with the new ones in `remap`.
+    #[inline]
+    pub fn remap_entities(&mut self, remap: &EntityRemap) {
+        self.colliders.remap_entities(remap);
+    }
+
+    /// Writes the shapes of colliders into `checksum`.
+    pub fn checksum(&self, checksum: &mut Checksum) {
+        self.colliders.checksum(checksum, |v, checksum| match *v {
+            Collider::Box(e) => {
+                checksum.write_u8(0);
+                checksum.write_f32s(&[e.x, e.y, e.z]);
+            }
+            Collider::Sphere(r) => {
+                checksum.write_u8(1);
+                checksum.write_f32(r);
+            }
+        });
+    }
+
+    /// Casts a ray from `origin` along `dir`, and returns the closest hit within
+    /// `max_distance`. The collider on entity `ignore` is skipped, and the colliders
+    /// which contain the origin are never hit.
+    pub fn raycast(
+        &self,
+        scene: &SceneGraph,
+        ignore: Entity,
+        origin: math::Vector3<f32>,
+        dir: math::Vector3<f32>,
+        max_distance: f32,
+    ) -> Option<RaycastHit> {
+        let dir = direction(dir)?;
+        let mut result: Option<RaycastHit> = None;
+
+        for (&ent, collider) in self.colliders.entities.iter().zip(&self.colliders.data) {
+            if ent == ignore {
+                continue;
+            }
+
+            let transform = match scene.transform(ent) {
+                Some(v) => v,
+                None => continue,
+            };
+
+            let hit = match *collider {
+                Collider::Sphere(r) => {
+                    let center = transform.position;
+                    ray_sphere(origin, dir, center, r * transform.scale).map(|t| {
+                        let point = origin + dir * t;
+                        let n = direction(point - center).unwrap_or(-dir);
+                        (t, n)
+                    })
+                }
+
+                Collider::Box(extents) => {
+                    let inv = transform.rotation.conjugate();
+                    let scale = transform.scale.abs().max(::std::f32::EPSILON);
+                    let lo = inv * (origin - transform.position) / scale;
+                    let ld = inv * dir / scale;
+                    ray_box(lo, ld, extents).map(|(t, n)| (t, transform.rotation * n))
+                }
+            };
+
+            if let Some((t, normal)) = hit {
+                if t <= max_distance && result.map(|v| t < v.distance).unwrap_or(true) {
+                    result = Some(RaycastHit {
+                        ent: ent,
+                        point: origin + dir * t,
+                        normal: normal,
+                        distance: t,
+                    });
+                }
+            }
+        }
+
+        result
+    }
+
+    /// Collects the contacts with the capsule between `a` and `b` in world space, which
+    /// are closer than `offset`. The collider on entity `ignore` is skipped.
+    pub(crate) fn capsule_contacts(
+        &self,
+        scene: &SceneGraph,
+        ignore: Entity,
+        (a, b): (math::Vector3<f32>, math::Vector3<f32>),
+        radius: f32,
+        offset: f32,
+        contacts: &mut Vec<Contact>,
+    ) {
+        for (&ent, collider) in self.colliders.entities.iter().zip(&self.colliders.data) {
+            if ent == ignore {
+                continue;
+            }
+
+            let transform = match scene.transform(ent) {
+                Some(v) => v,
+                None => continue,
+            };
+
+            let (point, normal, surface, distance) = match *collider {
+                Collider::Sphere(r) => {
+                    let center = transform.position;
+                    let p = closest_on_segment(a, b, center);
+                    let r = r * transform.scale;
+                    let n = direction(p - center).unwrap_or(math::Vector3::unit_y());
+                    (center + n * r, n, Some(n), (p - center).magnitude() - r)
+                }
+
+                Collider::Box(extents) => {
+                    // Finds the closest points in the local space of box.
+                    let inv = transform.rotation.conjugate();
+                    let scale = transform.scale.abs().max(::std::f32::EPSILON);
+                    let la = inv * (a - transform.position) / scale;
+                    let lb = inv * (b - transform.position) / scale;
+                    let (q, n, face, d) = closest_on_box(la, lb, extents);
+
+                    let point = transform.rotation * (q * scale) + transform.position;
+                    let surface = face.map(|v| transform.rotation * v);
+                    (point, transform.rotation * n, surface, d * scale)
+                }
+            };
+
+            if distance < radius + offset {
+                contacts.push(Contact {
+                    ent: ent,
+                    point: point,
+                    normal: normal,
+                    surface: surface,
+                    depth: radius - distance,
+                });
+            }
+        }
+    }
+}
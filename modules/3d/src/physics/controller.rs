@@ -0,0 +1,368 @@
+//! Kinematic character controllers, which move upright capsules against the colliders.
+
+use std::hash::{Hash, Hasher};
+use std::time::Duration;
+
+use crayon::errors::*;
+use crayon::math::{self, InnerSpace};
+use crayon::utils::Checksum;
+
+use super::collider::{Colliders, Contact};
+use entity_ref::EntityRemap;
+use scene::SceneGraph;
+use {Component, Entity};
+
+/// The sides of capsule which collided during last move.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub struct CollisionFlags {
+    pub sides: bool,
+    pub above: bool,
+    pub below: bool,
+}
+
+/// `CharacterController` moves the entity as an upright capsule, whose bottom is at the
+/// position of entity. It slides along walls, climbs stairs lower than `step_offset`
+/// and slopes less steep than `slope_limit`.
+#[derive(Debug, Clone)]
+pub struct CharacterController {
+    pub radius: f32,
+    /// The total height including the hemispheres.
+    pub height: f32,
+    /// The maximum height of stairs which could be climbed.
+    pub step_offset: f32,
+    /// The maximum angle of walkable slopes in radians.
+    pub slope_limit: f32,
+    /// The distance to the ground which is still considered as grounded.
+    pub skin: f32,
+    /// The downward acceleration in units per second squared.
+    pub gravity: f32,
+    /// The velocity in units per second, which is moved by `CharacterControllers::advance`.
+    pub velocity: math::Vector3<f32>,
+    flags: CollisionFlags,
+    ground: Option<math::Vector3<f32>>,
+}
+
+// The maximum depenetration iterations of every substep.
+const MAX_ITERATIONS: usize = 4;
+
+// The colliders which the capsule of `ent` moves against.
+struct Surroundings<'a> {
+    scene: &'a SceneGraph,
+    colliders: &'a Colliders,
+    ent: Entity,
+}
+
+impl CharacterController {
+    pub fn new(radius: f32, height: f32) -> Self {
+        CharacterController {
+            radius: radius,
+            height: height,
+            step_offset: 0.3,
+            slope_limit: ::std::f32::consts::PI / 4.0,
+            skin: 0.02,
+            gravity: 9.81,
+            velocity: math::Vector3::new(0.0, 0.0, 0.0),
+            flags: CollisionFlags::default(),
+            ground: None,
+        }
+    }
+
+    /// Returns true if the capsule is standing on walkable ground after last move.
+    #[inline]
+    pub fn is_grounded(&self) -> bool {
+        self.ground.is_some()
+    }
+
+    /// Gets the normal of walkable ground after last move.
+    #[inline]
+    pub fn ground_normal(&self) -> Option<math::Vector3<f32>> {
+        self.ground
+    }
+
+    /// Gets the sides of capsule which collided during last move.
+    #[inline]
+    pub fn collision_flags(&self) -> CollisionFlags {
+        self.flags
+    }
+
+    // Moves the capsule at `position` by `motion`, and returns the new position.
+    fn move_and_slide(
+        &mut self,
+        env: &Surroundings,
+        mut position: math::Vector3<f32>,
+        motion: math::Vector3<f32>,
+    ) -> math::Vector3<f32> {
+        let up = math::Vector3::unit_y();
+        let horizontal = math::Vector3::new(motion.x, 0.0, motion.z);
+
+        let mut flags = CollisionFlags::default();
+        let mut contacts = Vec::new();
+
+        // Lifts the capsule by `step_offset` before moving horizontally on the ground,
+        // and puts it down afterwards, so it walks over the stairs.
+        let lift = if self.ground.is_some() && horizontal.magnitude2() > 0.0 {
+            self.step_offset.max(0.0)
+        } else {
+            0.0
+        };
+
+        let base = position.y;
+        let rise = up * (lift + motion.y.max(0.0));
+        position = self.slide(env, position, rise, base, false, &mut flags);
+
+        // The lifted height might be less than `step_offset` under the ceiling.
+        let lifted = (position.y - base - motion.y.max(0.0)).max(0.0).min(lift);
+        position = self.slide(env, position, horizontal, base, false, &mut flags);
+
+        let fall = up * -(lifted + (-motion.y).max(0.0));
+        position = self.slide(env, position, fall, base, true, &mut flags);
+
+        // Probes the ground under the capsule unless its moving up.
+        self.ground = None;
+        if motion.y <= 0.0 {
+            self.contacts(env, position, self.skin, &mut contacts);
+
+            for v in &contacts {
+                if self.is_walkable(v, position.y) {
+                    let normal = v.surface.unwrap_or(v.normal);
+                    if self.ground.map(|n| normal.y > n.y).unwrap_or(true) {
+                        self.ground = Some(normal);
+                    }
+                }
+            }
+        }
+
+        flags.below = flags.below || self.ground.is_some();
+        self.flags = flags;
+        position
+    }
+
+    // Returns true if the contact could be stood on by the capsule whose bottom is at
+    // `base`. The edges lower than `step_offset` are stairs, which are always walkable.
+    fn is_walkable(&self, contact: &Contact, base: f32) -> bool {
+        match contact.surface {
+            Some(n) => n.y >= self.slope_limit.cos(),
+            None => contact.normal.y > 0.0 && contact.point.y <= base + self.step_offset,
+        }
+    }
+
+    // Moves the capsule in substeps, and pushes it out of colliders after each of them.
+    // If `descending`, the capsule is pushed upwards from walkable ground, so it does
+    // not slide down the slopes.
+    fn slide(
+        &self,
+        env: &Surroundings,
+        mut position: math::Vector3<f32>,
+        motion: math::Vector3<f32>,
+        base: f32,
+        descending: bool,
+        flags: &mut CollisionFlags,
+    ) -> math::Vector3<f32> {
+        let distance = motion.magnitude();
+        if distance <= 0.0 {
+            return position;
+        }
+
+        let limit = self.slope_limit.cos();
+        let steps = (distance / (self.radius * 0.5).max(1e-3)).ceil().max(1.0) as usize;
+        let step = motion / steps as f32;
+        let mut contacts = Vec::new();
+
+        for _ in 0..steps {
+            position += step;
+
+            for _ in 0..MAX_ITERATIONS {
+                contacts.clear();
+                self.contacts(env, position, 0.0, &mut contacts);
+
+                let mut deepest: Option<&Contact> = None;
+                for v in &contacts {
+                    if v.depth > 0.0 && deepest.map(|w| v.depth > w.depth).unwrap_or(true) {
+                        deepest = Some(v);
+                    }
+                }
+
+                let v = match deepest {
+                    Some(v) => v,
+                    None => break,
+                };
+
+                let (n, depth) = (v.normal, v.depth);
+                position += if self.is_walkable(v, base) {
+                    flags.below = true;
+
+                    // Stands on the ground instead of sliding down.
+                    if descending {
+                        math::Vector3::new(0.0, depth / n.y, 0.0)
+                    } else {
+                        n * depth
+                    }
+                } else if n.y <= -limit {
+                    flags.above = true;
+                    n * depth
+                } else {
+                    flags.sides = true;
+
+                    // Steep slopes are walls, which could not be climbed.
+                    let flat = math::Vector3::new(n.x, 0.0, n.z);
+                    if n.y > 0.0 && !descending && flat.magnitude2() > 0.0 {
+                        flat * (depth / flat.magnitude2())
+                    } else {
+                        n * depth
+                    }
+                };
+            }
+        }
+
+        position
+    }
+
+    // Collects the contacts of capsule at `position`, which are closer than `offset`.
+    fn contacts(
+        &self,
+        env: &Surroundings,
+        position: math::Vector3<f32>,
+        offset: f32,
+        contacts: &mut Vec<Contact>,
+    ) {
+        // The segment between the centers of hemispheres.
+        let top = (self.height - self.radius).max(self.radius);
+        let segment = (
+            position + math::Vector3::new(0.0, self.radius, 0.0),
+            position + math::Vector3::new(0.0, top, 0.0),
+        );
+
+        env.colliders
+            .capsule_contacts(env.scene, env.ent, segment, self.radius, offset, contacts);
+    }
+}
+
+/// The collection of `CharacterController`s in world.
+#[derive(Clone)]
+pub struct CharacterControllers {
+    controllers: Component<CharacterController>,
+}
+
+impl CharacterControllers {
+    pub fn new() -> Self {
+        CharacterControllers {
+            controllers: Component::new(),
+        }
+    }
+
+    #[inline]
+    pub fn add(&mut self, ent: Entity, controller: CharacterController) {
+        self.controllers.add(ent, controller);
+    }
+
+    #[inline]
+    pub fn remove(&mut self, ent: Entity) {
+        self.controllers.remove(ent);
+    }
+
+    #[inline]
+    pub fn get(&self, ent: Entity) -> Option<&CharacterController> {
+        self.controllers.get(ent)
+    }
+
+    #[inline]
+    pub fn get_mut(&mut self, ent: Entity) -> Option<&mut CharacterController> {
+        self.controllers.get_mut(ent)
+    }
+
+    /// Replaces the entities with the new ones in `remap`.
+    #[inline]
+    pub fn remap_entities(&mut self, remap: &EntityRemap) {
+        self.controllers.remap_entities(remap);
+    }
+
+    /// Writes the shapes, velocities and grounded states of controllers into `checksum`.
+    pub fn checksum(&self, checksum: &mut Checksum) {
+        self.controllers.checksum(checksum, |v, checksum| {
+            checksum.write_f32s(&[v.radius, v.height, v.step_offset, v.slope_limit]);
+            checksum.write_f32s(&[v.skin, v.gravity]);
+            checksum.write_f32s(&[v.velocity.x, v.velocity.y, v.velocity.z]);
+            v.flags.hash(checksum);
+
+            match v.ground {
+                Some(n) => {
+                    checksum.write_u8(1);
+                    checksum.write_f32s(&[n.x, n.y, n.z]);
+                }
+                None => checksum.write_u8(0),
+            }
+        });
+    }
+
+    /// Moves the entity by `motion` in world space, and returns the sides collided.
+    pub fn move_and_slide<T>(
+        &mut self,
+        scene: &mut SceneGraph,
+        colliders: &Colliders,
+        ent: Entity,
+        motion: T,
+    ) -> Result<CollisionFlags>
+    where
+        T: Into<math::Vector3<f32>>,
+    {
+        let position = match scene.position(ent) {
+            Some(v) => v,
+            None => bail!("{:?} does not exist in scene.", ent),
+        };
+
+        let controller = match self.controllers.get_mut(ent) {
+            Some(v) => v,
+            None => bail!("{:?} does not have a character controller.", ent),
+        };
+
+        let position = {
+            let env = Surroundings {
+                scene: scene,
+                colliders: colliders,
+                ent: ent,
+            };
+
+            controller.move_and_slide(&env, position, motion.into())
+        };
+
+        scene.set_position(ent, position);
+        Ok(controller.flags)
+    }
+
+    /// Moves the controllers by their velocities, which are accelerated by gravity. This
+    /// should be called in `Application::on_fixed_update` with the fixed timestep, after
+    /// the velocities are set by users.
+    pub fn advance(&mut self, scene: &mut SceneGraph, colliders: &Colliders, delta: Duration) {
+        let dt = delta.as_secs() as f32 + delta.subsec_nanos() as f32 * 1e-9;
+
+        for (&ent, v) in self.controllers
+            .entities
+            .iter()
+            .zip(self.controllers.data.iter_mut())
+        {
+            let position = match scene.position(ent) {
+                Some(position) => position,
+                None => continue,
+            };
+
+            v.velocity.y -= v.gravity * dt;
+            let motion = v.velocity * dt;
+            let position = {
+                let env = Surroundings {
+                    scene: scene,
+                    colliders: colliders,
+                    ent: ent,
+                };
+
+                v.move_and_slide(&env, position, motion)
+            };
+
+            scene.set_position(ent, position);
+
+            // Stops falling on the ground, and stops rising when hitting the ceiling.
+            if (v.flags.below && v.velocity.y < 0.0) || (v.flags.above && v.velocity.y > 0.0) {
+                v.velocity.y = 0.0;
+            }
+        }
+    }
+}
@@ -0,0 +1,285 @@
+//! The closest points and ray intersections between primitives.
+
+use crayon::math::{self, InnerSpace};
+
+/// The closest point on the segment between `a` and `b` to `p`.
+pub fn closest_on_segment(
+    a: math::Vector3<f32>,
+    b: math::Vector3<f32>,
+    p: math::Vector3<f32>,
+) -> math::Vector3<f32> {
+    let ab = b - a;
+    let len2 = ab.magnitude2();
+    if len2 <= 0.0 {
+        return a;
+    }
+
+    let t = ((p - a).dot(ab) / len2).max(0.0).min(1.0);
+    a + ab * t
+}
+
+/// The closest point on the surface of box centered at origin to the segment, the
+/// normal, the normal of face if the point is not on edges, and the signed distance
+/// between them, which is negative if the segment intersects with the box.
+pub fn closest_on_box(
+    a: math::Vector3<f32>,
+    b: math::Vector3<f32>,
+    extents: math::Vector3<f32>,
+) -> (
+    math::Vector3<f32>,
+    math::Vector3<f32>,
+    Option<math::Vector3<f32>>,
+    f32,
+) {
+    let clamp = |v: math::Vector3<f32>| {
+        math::Vector3::new(
+            v.x.max(-extents.x).min(extents.x),
+            v.y.max(-extents.y).min(extents.y),
+            v.z.max(-extents.z).min(extents.z),
+        )
+    };
+
+    // Projects alternately between the segment and box, which converges to the
+    // closest points of convex shapes.
+    let mut p = closest_on_segment(a, b, math::Vector3::new(0.0, 0.0, 0.0));
+    let mut q = clamp(p);
+    for _ in 0..8 {
+        p = closest_on_segment(a, b, q);
+        q = clamp(p);
+    }
+
+    if let Some(n) = direction(p - q) {
+        // The point is on a face if the segment is outside along only one axis.
+        let mut face = math::Vector3::new(0.0, 0.0, 0.0);
+        let mut axes = 0;
+        for i in 0..3 {
+            if (p[i] - q[i]).abs() > 1e-6 {
+                face[i] = n[i].signum();
+                axes += 1;
+            }
+        }
+
+        let face = if axes == 1 { Some(face) } else { None };
+        return (q, n, face, (p - q).magnitude());
+    }
+
+    // The segment is inside, pushes it out through the nearest face.
+    let gaps = [
+        extents.x - p.x.abs(),
+        extents.y - p.y.abs(),
+        extents.z - p.z.abs(),
+    ];
+
+    let mut axis = 0;
+    for (i, &v) in gaps.iter().enumerate() {
+        if v < gaps[axis] {
+            axis = i;
+        }
+    }
+
+    let mut n = math::Vector3::new(0.0, 0.0, 0.0);
+    n[axis] = if p[axis] < 0.0 { -1.0 } else { 1.0 };
+    q[axis] = extents[axis] * n[axis];
+    (q, n, Some(n), -gaps[axis])
+}
+
+/// Normalizes the vector, or returns `None` if its too short.
+#[inline]
+pub fn direction(v: math::Vector3<f32>) -> Option<math::Vector3<f32>> {
+    let len = v.magnitude();
+    if len > 1e-6 {
+        Some(v / len)
+    } else {
+        None
+    }
+}
+
+/// The closest points between the segment `(p1, q1)` and `(p2, q2)`.
+pub fn closest_between_segments(
+    (p1, q1): (math::Vector3<f32>, math::Vector3<f32>),
+    (p2, q2): (math::Vector3<f32>, math::Vector3<f32>),
+) -> (math::Vector3<f32>, math::Vector3<f32>) {
+    const EPSILON: f32 = 1e-6;
+
+    let clamp = |v: f32| v.max(0.0).min(1.0);
+    let (d1, d2, r) = (q1 - p1, q2 - p2, p1 - p2);
+    let (a, e, f) = (d1.dot(d1), d2.dot(d2), d2.dot(r));
+
+    if a <= EPSILON && e <= EPSILON {
+        return (p1, p2);
+    }
+
+    let (s, t) = if a <= EPSILON {
+        (0.0, clamp(f / e))
+    } else if e <= EPSILON {
+        (clamp(-d1.dot(r) / a), 0.0)
+    } else {
+        let (b, c) = (d1.dot(d2), d1.dot(r));
+        let denom = a * e - b * b;
+        let s = if denom > EPSILON {
+            clamp((b * f - c * e) / denom)
+        } else {
+            0.0
+        };
+
+        let t = (b * s + f) / e;
+        if t < 0.0 {
+            (clamp(-c / a), 0.0)
+        } else if t > 1.0 {
+            (clamp((b - c) / a), 1.0)
+        } else {
+            (s, t)
+        }
+    };
+
+    (p1 + d1 * s, p2 + d2 * t)
+}
+
+/// An oriented box.
+#[derive(Debug, Clone, Copy)]
+pub struct Obb {
+    pub center: math::Vector3<f32>,
+    pub axes: [math::Vector3<f32>; 3],
+    pub extents: math::Vector3<f32>,
+}
+
+impl Obb {
+    pub fn new(
+        center: math::Vector3<f32>,
+        rotation: math::Quaternion<f32>,
+        extents: math::Vector3<f32>,
+    ) -> Self {
+        Obb {
+            center: center,
+            axes: [
+                rotation * math::Vector3::unit_x(),
+                rotation * math::Vector3::unit_y(),
+                rotation * math::Vector3::unit_z(),
+            ],
+            extents: extents,
+        }
+    }
+
+    /// The closest point inside the box to `p`.
+    pub fn clamp(&self, p: math::Vector3<f32>) -> math::Vector3<f32> {
+        let mut v = self.center;
+        for (i, &axis) in self.axes.iter().enumerate() {
+            let d = (p - self.center).dot(axis);
+            v += axis * d.max(-self.extents[i]).min(self.extents[i]);
+        }
+
+        v
+    }
+
+    // The half length of projection onto `axis`.
+    fn project(&self, axis: math::Vector3<f32>) -> f32 {
+        self.axes
+            .iter()
+            .enumerate()
+            .fold(0.0, |acc, (i, v)| acc + self.extents[i] * v.dot(axis).abs())
+    }
+}
+
+/// Tests the boxes with separating axes, and returns the normal which pushes `lhs` out
+/// of `rhs` and the penetration depth if they overlap.
+pub fn box_box(lhs: &Obb, rhs: &Obb) -> Option<(math::Vector3<f32>, f32)> {
+    let mut axes = Vec::with_capacity(15);
+    axes.extend_from_slice(&lhs.axes);
+    axes.extend_from_slice(&rhs.axes);
+    for u in &lhs.axes {
+        for v in &rhs.axes {
+            if let Some(axis) = direction(u.cross(*v)) {
+                axes.push(axis);
+            }
+        }
+    }
+
+    let offset = lhs.center - rhs.center;
+    let mut result: Option<(math::Vector3<f32>, f32)> = None;
+
+    for axis in axes {
+        let distance = offset.dot(axis);
+        let depth = lhs.project(axis) + rhs.project(axis) - distance.abs();
+        if depth < 0.0 {
+            return None;
+        }
+
+        if result.map(|v| depth < v.1).unwrap_or(true) {
+            let normal = if distance < 0.0 { -axis } else { axis };
+            result = Some((normal, depth));
+        }
+    }
+
+    result
+}
+
+/// The distance along the ray to the sphere, the ray starting inside is not counted.
+pub fn ray_sphere(
+    origin: math::Vector3<f32>,
+    dir: math::Vector3<f32>,
+    center: math::Vector3<f32>,
+    radius: f32,
+) -> Option<f32> {
+    let m = origin - center;
+    let (a, b, c) = (dir.dot(dir), m.dot(dir), m.dot(m) - radius * radius);
+    if c <= 0.0 || a <= 0.0 {
+        return None;
+    }
+
+    let discr = b * b - a * c;
+    if discr < 0.0 {
+        return None;
+    }
+
+    let t = (-b - discr.sqrt()) / a;
+    if t >= 0.0 {
+        Some(t)
+    } else {
+        None
+    }
+}
+
+/// The distance along the ray to the box centered at origin, and the normal of face
+/// hit by it. The ray starting inside is not counted.
+pub fn ray_box(
+    origin: math::Vector3<f32>,
+    dir: math::Vector3<f32>,
+    extents: math::Vector3<f32>,
+) -> Option<(f32, math::Vector3<f32>)> {
+    let (mut near, mut far) = (::std::f32::MIN, ::std::f32::MAX);
+    let mut axis = None;
+
+    // Clips the ray with slabs of each axis.
+    for i in 0..3 {
+        if dir[i].abs() <= 1e-6 {
+            if origin[i].abs() > extents[i] {
+                return None;
+            }
+
+            continue;
+        }
+
+        let t0 = (-extents[i] - origin[i]) / dir[i];
+        let t1 = (extents[i] - origin[i]) / dir[i];
+        let (t0, t1) = if t0 < t1 { (t0, t1) } else { (t1, t0) };
+
+        if t0 > near {
+            near = t0;
+            axis = Some(i);
+        }
+
+        far = far.min(t1);
+        if near > far {
+            return None;
+        }
+    }
+
+    match axis {
+        Some(i) if near >= 0.0 => {
+            let mut n = math::Vector3::new(0.0, 0.0, 0.0);
+            n[i] = -dir[i].signum();
+            Some((near, n))
+        }
+        _ => None,
+    }
+}
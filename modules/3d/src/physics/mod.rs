@@ -0,0 +1,36 @@
+//! Kinematic character controllers, which move capsules against the static colliders
+//! of scene, e.g. walls, floors, stairs and slopes. And trigger volumes, which report
+//! the overlaps between each other, e.g. the player entering a zone.
+//!
+//! The controllers and triggers should be advanced with the fixed timestep, so the
+//! results are consistent regardless of the frame rate. The transforms of scene could
+//! be rendered with interpolation between the fixed updates.
+//!
+//! ```rust,ignore
+//! world.colliders.add(floor, Collider::Box(math::Vector3::new(10.0, 0.5, 10.0)));
+//! world.controllers.add(player, CharacterController::new(0.5, 2.0));
+//!
+//! // In `Application::on_fixed_update`.
+//! world.controllers.get_mut(player).unwrap().velocity.x = input.x * speed;
+//! world.controllers.advance(&mut world.scene, &world.colliders, ctx.time.fixed_timestep());
+//! world.triggers.advance(&world.scene);
+//!
+//! for v in world.triggers.take_events() {
+//!     if v.phase == TriggerPhase::Enter && v.rhs == player {
+//!         pickup(v.lhs);
+//!     }
+//! }
+//! ```
+
+mod geometry;
+
+pub mod collider;
+pub use self::collider::{Collider, Colliders, Contact, RaycastHit};
+
+pub mod controller;
+pub use self::controller::{CharacterController, CharacterControllers, CollisionFlags};
+
+pub mod trigger;
+pub use self::trigger::{
+    TriggerContact, TriggerEvent, TriggerPhase, TriggerShape, TriggerVolume, Triggers,
+};
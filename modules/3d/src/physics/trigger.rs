@@ -0,0 +1,359 @@
+//! Trigger volumes, which report the overlaps between each other without collision
+//! responses, e.g. pickups, zones and scripted triggers.
+
+use std::collections::HashMap;
+use std::hash::{Hash, Hasher};
+use std::mem;
+
+use crayon::math::{self, InnerSpace};
+use crayon::utils::Checksum;
+
+use super::geometry::{self, Obb};
+use entity_ref::EntityRemap;
+use scene::{SceneGraph, Transform};
+use {Component, Entity};
+
+/// The shape of trigger volume, which is centered at the world transform of entity.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+pub enum TriggerShape {
+    /// An oriented box with the half extents.
+    Box(math::Vector3<f32>),
+    /// A sphere with the radius.
+    Sphere(f32),
+    /// A capsule along the up axis of entity, the height includes the hemispheres.
+    Capsule { radius: f32, height: f32 },
+}
+
+/// `TriggerVolume` overlaps with the volumes whose `layers` match its `mask`.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+pub struct TriggerVolume {
+    pub shape: TriggerShape,
+    /// The layers which this volume belongs to.
+    pub layers: u32,
+    /// The layers which this volume overlaps with.
+    pub mask: u32,
+}
+
+impl TriggerVolume {
+    pub fn new(shape: TriggerShape) -> Self {
+        TriggerVolume {
+            shape: shape,
+            layers: 1,
+            mask: !0,
+        }
+    }
+}
+
+/// The phase of overlap.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TriggerPhase {
+    /// The volumes start to overlap.
+    Enter,
+    /// The volumes are still overlapping.
+    Stay,
+    /// The volumes stop overlapping, or one of them has been removed.
+    Exit,
+}
+
+/// The approximate contact between two volumes.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct TriggerContact {
+    pub point: math::Vector3<f32>,
+    /// The direction which pushes `lhs` out of `rhs`.
+    pub normal: math::Vector3<f32>,
+    pub depth: f32,
+}
+
+/// The overlap event between two volumes, the `lhs` always comes before `rhs`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct TriggerEvent {
+    pub phase: TriggerPhase,
+    pub lhs: Entity,
+    pub rhs: Entity,
+    /// The contact of overlapping volumes, which is `None` for `TriggerPhase::Exit`.
+    pub contact: Option<TriggerContact>,
+}
+
+/// The collection of `TriggerVolume`s in world.
+#[derive(Clone)]
+pub struct Triggers {
+    volumes: Component<TriggerVolume>,
+    overlaps: HashMap<(Entity, Entity), TriggerContact>,
+    events: Vec<TriggerEvent>,
+}
+
+impl Triggers {
+    pub fn new() -> Self {
+        Triggers {
+            volumes: Component::new(),
+            overlaps: HashMap::new(),
+            events: Vec::new(),
+        }
+    }
+
+    #[inline]
+    pub fn add(&mut self, ent: Entity, volume: TriggerVolume) {
+        self.volumes.add(ent, volume);
+    }
+
+    /// Removes the volume of entity, and emits the exit events of its overlaps.
+    pub fn remove(&mut self, ent: Entity) {
+        self.volumes.remove(ent);
+
+        let mut exits: Vec<_> = self.overlaps
+            .keys()
+            .filter(|v| v.0 == ent || v.1 == ent)
+            .cloned()
+            .collect();
+
+        exits.sort();
+        for (lhs, rhs) in exits {
+            self.overlaps.remove(&(lhs, rhs));
+            self.events.push(TriggerEvent {
+                phase: TriggerPhase::Exit,
+                lhs: lhs,
+                rhs: rhs,
+                contact: None,
+            });
+        }
+    }
+
+    #[inline]
+    pub fn get(&self, ent: Entity) -> Option<&TriggerVolume> {
+        self.volumes.get(ent)
+    }
+
+    #[inline]
+    pub fn get_mut(&mut self, ent: Entity) -> Option<&mut TriggerVolume> {
+        self.volumes.get_mut(ent)
+    }
+
+    /// Returns true if the volumes of two entities were overlapping in last `advance`.
+    pub fn is_overlapping(&self, lhs: Entity, rhs: Entity) -> bool {
+        let key = if lhs < rhs { (lhs, rhs) } else { (rhs, lhs) };
+        self.overlaps.contains_key(&key)
+    }
+
+    /// Takes the events emitted since last call.
+    #[inline]
+    pub fn take_events(&mut self) -> Vec<TriggerEvent> {
+        mem::replace(&mut self.events, Vec::new())
+    }
+
+    /// Replaces the entities with the new ones in `remap`. The pairs of overlaps and
+    /// pending events are swapped if `lhs` comes after `rhs` afterwards.
+    pub fn remap_entities(&mut self, remap: &EntityRemap) {
+        let map = |lhs: Entity, rhs: Entity, contact: Option<TriggerContact>| {
+            let lhs = remap.get(lhs).unwrap_or(lhs);
+            let rhs = remap.get(rhs).unwrap_or(rhs);
+            if lhs < rhs {
+                (lhs, rhs, contact)
+            } else {
+                let contact = contact.map(|mut v| {
+                    v.normal = -v.normal;
+                    v
+                });
+
+                (rhs, lhs, contact)
+            }
+        };
+
+        self.volumes.remap_entities(remap);
+
+        let overlaps = mem::replace(&mut self.overlaps, HashMap::new());
+        for ((lhs, rhs), contact) in overlaps {
+            let (lhs, rhs, contact) = map(lhs, rhs, Some(contact));
+            self.overlaps.insert((lhs, rhs), contact.unwrap());
+        }
+
+        for v in &mut self.events {
+            let (lhs, rhs, contact) = map(v.lhs, v.rhs, v.contact);
+            v.lhs = lhs;
+            v.rhs = rhs;
+            v.contact = contact;
+        }
+    }
+
+    /// Writes the volumes and the overlapping pairs into `checksum`. The pending events
+    /// are skipped, since they are taken every frame.
+    pub fn checksum(&self, checksum: &mut Checksum) {
+        self.volumes.checksum(checksum, |v, checksum| {
+            match v.shape {
+                TriggerShape::Box(e) => {
+                    checksum.write_u8(0);
+                    checksum.write_f32s(&[e.x, e.y, e.z]);
+                }
+                TriggerShape::Sphere(r) => {
+                    checksum.write_u8(1);
+                    checksum.write_f32(r);
+                }
+                TriggerShape::Capsule { radius, height } => {
+                    checksum.write_u8(2);
+                    checksum.write_f32s(&[radius, height]);
+                }
+            }
+
+            checksum.write_u32(v.layers);
+            checksum.write_u32(v.mask);
+        });
+
+        let mut overlaps: Vec<_> = self.overlaps.iter().collect();
+        overlaps.sort_by_key(|v| *v.0);
+
+        checksum.write_usize(overlaps.len());
+        for (&(lhs, rhs), v) in overlaps {
+            lhs.hash(checksum);
+            rhs.hash(checksum);
+            checksum.write_f32s(&[v.point.x, v.point.y, v.point.z, v.depth]);
+            checksum.write_f32s(&[v.normal.x, v.normal.y, v.normal.z]);
+        }
+    }
+
+    /// Tests the overlaps between volumes, and emits the events. This should be called in
+    /// `Application::on_fixed_update`, after the entities are moved.
+    pub fn advance(&mut self, scene: &SceneGraph) {
+        let mut shapes: Vec<_> = self.volumes
+            .entities
+            .iter()
+            .zip(self.volumes.data.iter())
+            .filter_map(|(&ent, v)| {
+                let transform = scene.transform(ent)?;
+                Some((ent, *v, Shape::new(v, &transform)))
+            })
+            .collect();
+
+        // Keeps the order of events stable.
+        shapes.sort_by_key(|v| v.0);
+
+        let mut overlaps = HashMap::new();
+        for (i, lhs) in shapes.iter().enumerate() {
+            for rhs in &shapes[i + 1..] {
+                if lhs.1.mask & rhs.1.layers == 0 || rhs.1.mask & lhs.1.layers == 0 {
+                    continue;
+                }
+
+                if let Some(contact) = lhs.2.overlap(&rhs.2) {
+                    let phase = if self.overlaps.contains_key(&(lhs.0, rhs.0)) {
+                        TriggerPhase::Stay
+                    } else {
+                        TriggerPhase::Enter
+                    };
+
+                    overlaps.insert((lhs.0, rhs.0), contact);
+                    self.events.push(TriggerEvent {
+                        phase: phase,
+                        lhs: lhs.0,
+                        rhs: rhs.0,
+                        contact: Some(contact),
+                    });
+                }
+            }
+        }
+
+        let mut exits: Vec<_> = self.overlaps
+            .keys()
+            .filter(|v| !overlaps.contains_key(v))
+            .cloned()
+            .collect();
+
+        exits.sort();
+        for (lhs, rhs) in exits {
+            self.events.push(TriggerEvent {
+                phase: TriggerPhase::Exit,
+                lhs: lhs,
+                rhs: rhs,
+                contact: None,
+            });
+        }
+
+        self.overlaps = overlaps;
+    }
+}
+
+// The volume in world space.
+enum Shape {
+    // The segment and radius, which is a sphere if the segment is degenerated.
+    Capsule(math::Vector3<f32>, math::Vector3<f32>, f32),
+    Box(Obb),
+}
+
+impl Shape {
+    fn new(volume: &TriggerVolume, transform: &Transform) -> Self {
+        let (position, scale) = (transform.position, transform.scale.abs());
+
+        match volume.shape {
+            TriggerShape::Box(extents) => {
+                Shape::Box(Obb::new(position, transform.rotation, extents * scale))
+            }
+            TriggerShape::Sphere(radius) => Shape::Capsule(position, position, radius * scale),
+            TriggerShape::Capsule { radius, height } => {
+                let half = (height * 0.5 - radius).max(0.0) * scale;
+                let up = transform.rotation * math::Vector3::new(0.0, half, 0.0);
+                Shape::Capsule(position - up, position + up, radius * scale)
+            }
+        }
+    }
+
+    fn overlap(&self, rhs: &Shape) -> Option<TriggerContact> {
+        match (self, rhs) {
+            (&Shape::Capsule(a1, b1, r1), &Shape::Capsule(a2, b2, r2)) => {
+                let (p, q) = geometry::closest_between_segments((a1, b1), (a2, b2));
+                let distance = (p - q).magnitude();
+                if distance >= r1 + r2 {
+                    return None;
+                }
+
+                let normal = geometry::direction(p - q).unwrap_or(math::Vector3::unit_y());
+                Some(TriggerContact {
+                    point: ((p - normal * r1) + (q + normal * r2)) * 0.5,
+                    normal: normal,
+                    depth: r1 + r2 - distance,
+                })
+            }
+
+            (&Shape::Capsule(a, b, r), &Shape::Box(ref obb)) => capsule_box((a, b), r, obb),
+
+            (&Shape::Box(ref obb), &Shape::Capsule(a, b, r)) => {
+                capsule_box((a, b), r, obb).map(|mut v| {
+                    v.normal = -v.normal;
+                    v
+                })
+            }
+
+            (&Shape::Box(ref lhs), &Shape::Box(ref rhs)) => {
+                geometry::box_box(lhs, rhs).map(|(normal, depth)| TriggerContact {
+                    point: (rhs.clamp(lhs.center) + lhs.clamp(rhs.center)) * 0.5,
+                    normal: normal,
+                    depth: depth,
+                })
+            }
+        }
+    }
+}
+
+fn capsule_box(
+    (a, b): (math::Vector3<f32>, math::Vector3<f32>),
+    radius: f32,
+    obb: &Obb,
+) -> Option<TriggerContact> {
+    // Transforms the segment into the space of box.
+    let local = |v: math::Vector3<f32>| {
+        let d = v - obb.center;
+        math::Vector3::new(d.dot(obb.axes[0]), d.dot(obb.axes[1]), d.dot(obb.axes[2]))
+    };
+
+    let (q, n, _, distance) = geometry::closest_on_box(local(a), local(b), obb.extents);
+    if distance >= radius {
+        return None;
+    }
+
+    let world = |v: math::Vector3<f32>| {
+        obb.axes[0] * v.x + obb.axes[1] * v.y + obb.axes[2] * v.z
+    };
+
+    Some(TriggerContact {
+        point: obb.center + world(q),
+        normal: world(n),
+        depth: radius - distance,
+    })
+}
@@ -0,0 +1,148 @@
+//! Blob shadows, which are the cheap alternative of shadow mapping on low-end targets.
+//!
+//! A rounded quad is projected onto the colliders right beneath the entity, so the
+//! characters don't float visually. The shadow gets larger and fainter as the entity
+//! rises above the ground.
+//!
+//! ```rust,ignore
+//! world.renderables.add_blob_shadow(player, BlobShadow::new(0.4));
+//!
+//! // Every frame, after the entities are moved.
+//! world.renderables.update_blob_shadows(&world.scene, &world.colliders);
+//! ```
+
+use crayon::math::{self, InnerSpace};
+
+use physics::{Colliders, RaycastHit};
+use scene::{SceneGraph, Transform};
+use Entity;
+
+/// The settings of blob shadow.
+#[derive(Debug, Clone, Copy)]
+pub struct BlobShadow {
+    /// The half extents of shadow along the right and forward directions of entity.
+    pub extents: math::Vector2<f32>,
+    /// The radius of corners relative to the smaller extent, 0 makes a sharp rectangle
+    /// and 1 makes a ellipse-like shape.
+    pub roundness: f32,
+    /// The width of blurry edges relative to the smaller extent.
+    pub softness: f32,
+    /// The opacity when the entity stands on the ground.
+    pub opacity: f32,
+    /// The height above the ground where the shadow fades out completely.
+    pub max_height: f32,
+    /// The scale of extents at `max_height`.
+    pub far_scale: f32,
+    /// The distance which lifts the ray above the entity, so it starts outside of the
+    /// ground which the entity stands on.
+    pub ray_offset: f32,
+    /// The distance which lifts the shadow above the ground to avoid z-fighting.
+    pub depth_offset: f32,
+    /// Is this shadow visible.
+    pub visible: bool,
+    projection: Option<BlobQuad>,
+}
+
+/// The projected quad of blob shadow in world space.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct BlobQuad {
+    pub center: math::Vector3<f32>,
+    /// The normal of ground.
+    pub normal: math::Vector3<f32>,
+    /// The direction of first extent on the ground.
+    pub tangent: math::Vector3<f32>,
+    /// The scaled half extents.
+    pub extents: math::Vector2<f32>,
+    /// The radius of corners in world units.
+    pub radius: f32,
+    /// The width of blurry edges in world units.
+    pub softness: f32,
+    /// The faded opacity.
+    pub opacity: f32,
+}
+
+impl BlobQuad {
+    /// Gets the corners in order around the quad.
+    pub fn corners(&self) -> [math::Vector3<f32>; 4] {
+        let u = self.tangent * self.extents.x;
+        let v = self.tangent.cross(self.normal) * self.extents.y;
+        let c = self.center;
+        [c - u - v, c + u - v, c + u + v, c - u + v]
+    }
+}
+
+impl BlobShadow {
+    /// Creates a round shadow with radius.
+    pub fn new(radius: f32) -> Self {
+        BlobShadow {
+            extents: math::Vector2::new(radius, radius),
+            roundness: 1.0,
+            softness: 0.5,
+            opacity: 0.6,
+            max_height: 5.0,
+            far_scale: 1.5,
+            ray_offset: 0.5,
+            depth_offset: 0.01,
+            visible: true,
+            projection: None,
+        }
+    }
+
+    /// Gets the quad projected by the last update, returns `None` if there is no
+    /// ground within `max_height`.
+    #[inline]
+    pub fn projection(&self) -> Option<BlobQuad> {
+        self.projection
+    }
+
+    /// Projects the shadow of entity with transform onto the ground which is hit by ray.
+    pub fn project(&mut self, transform: &Transform, hit: Option<RaycastHit>) {
+        self.projection = hit.and_then(|hit| {
+            let height = (hit.distance - self.ray_offset).max(0.0);
+            let t = height / self.max_height.max(1e-4);
+            if t >= 1.0 {
+                return None;
+            }
+
+            // Aligns the shadow with the facing of entity.
+            let n = hit.normal;
+            let tangent = [transform.right(), transform.forward()]
+                .iter()
+                .map(|&v| v - n * v.dot(n))
+                .find(|v| v.magnitude2() > 1e-6)
+                .map(|v| v.normalize())?;
+
+            let scale = 1.0 + (self.far_scale - 1.0) * t;
+            let extents = self.extents * (scale * transform.scale);
+            let size = extents.x.min(extents.y);
+
+            Some(BlobQuad {
+                center: hit.point + n * self.depth_offset,
+                normal: n,
+                tangent: tangent,
+                extents: extents,
+                radius: size * self.roundness.max(0.0).min(1.0),
+                softness: size * self.softness.max(0.0),
+                opacity: self.opacity * (1.0 - t),
+            })
+        });
+    }
+
+    /// Casts a ray down from the entity, and projects the shadow onto the closest
+    /// collider. The collider of entity itself is skipped.
+    pub fn update(&mut self, scene: &SceneGraph, colliders: &Colliders, ent: Entity) {
+        let transform = match scene.transform(ent) {
+            Some(v) => v,
+            None => {
+                self.projection = None;
+                return;
+            }
+        };
+
+        let origin = transform.position + math::Vector3::unit_y() * self.ray_offset;
+        let down = -math::Vector3::unit_y();
+        let max_distance = self.max_height + self.ray_offset;
+        let hit = colliders.raycast(scene, ent, origin, down, max_distance);
+        self.project(&transform, hit);
+    }
+}
@@ -0,0 +1,230 @@
+//! The extension points of renderers, which let middlewares (e.g. custom effects or
+//! third-party renderers) inject drawcalls at the named stages of camera passes.
+//!
+//! ```rust,ignore
+//! let handle = renderer.add_render_callback(RenderStage::AfterOpaque, move |recorder| {
+//!     let mvp = recorder.projection_matrix() * recorder.view_matrix();
+//!     let mut dc = DrawCall::new(shader, mesh);
+//!     dc.set_uniform_variable("u_MVPMatrix", mvp);
+//!     recorder.draw(dc);
+//! });
+//! ```
+
+use crayon::math;
+use crayon::utils::HandlePool;
+use crayon::video::prelude::*;
+
+impl_handle!(RenderCallbackHandle);
+
+/// The named stages of camera pass where callbacks are invoked.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum RenderStage {
+    /// After the opaque meshes are drawn, and before the ground plane.
+    AfterOpaque,
+    /// After the ground plane and blob shadows, and before the translucent lines and
+    /// trails.
+    BeforeTransparents,
+    /// After the scene is upscaled into the destination surface of camera.
+    AfterPost,
+}
+
+/// The callback invoked at a stage of camera pass.
+pub type RenderCallback = Box<FnMut(&mut RenderRecorder) + Send>;
+
+/// The restricted drawcall recorder passed to callbacks, which only draws into the
+/// view of current camera.
+pub struct RenderRecorder<'a> {
+    video: &'a VideoSystemShared,
+    surface: SurfaceHandle,
+    stage: RenderStage,
+    view_matrix: math::Matrix4<f32>,
+    projection_matrix: math::Matrix4<f32>,
+    eye: math::Vector3<f32>,
+    drawcalls: u32,
+}
+
+impl<'a> RenderRecorder<'a> {
+    pub(crate) fn new(
+        video: &'a VideoSystemShared,
+        surface: SurfaceHandle,
+        stage: RenderStage,
+        view_matrix: math::Matrix4<f32>,
+        projection_matrix: math::Matrix4<f32>,
+        eye: math::Vector3<f32>,
+    ) -> Self {
+        RenderRecorder {
+            video: video,
+            surface: surface,
+            stage: stage,
+            view_matrix: view_matrix,
+            projection_matrix: projection_matrix,
+            eye: eye,
+            drawcalls: 0,
+        }
+    }
+
+    /// Gets the stage which is being recorded.
+    #[inline]
+    pub fn stage(&self) -> RenderStage {
+        self.stage
+    }
+
+    /// Gets the view matrix of camera.
+    #[inline]
+    pub fn view_matrix(&self) -> math::Matrix4<f32> {
+        self.view_matrix
+    }
+
+    /// Gets the projection matrix of camera.
+    #[inline]
+    pub fn projection_matrix(&self) -> math::Matrix4<f32> {
+        self.projection_matrix
+    }
+
+    /// Gets the position of camera in world space.
+    #[inline]
+    pub fn eye(&self) -> math::Vector3<f32> {
+        self.eye
+    }
+
+    /// Gets the number of drawcalls recorded so far.
+    #[inline]
+    pub fn drawcalls(&self) -> u32 {
+        self.drawcalls
+    }
+
+    /// Draws into the view of camera, after everything drawn before this stage.
+    pub fn draw(&mut self, dc: DrawCall) {
+        self.video.draw(self.surface, dc);
+        self.drawcalls += 1;
+    }
+}
+
+/// The callbacks registered to a renderer, which are invoked in the order they are
+/// added.
+#[derive(Default)]
+pub struct RenderCallbacks {
+    handles: HandlePool,
+    callbacks: Vec<(RenderCallbackHandle, RenderStage, RenderCallback)>,
+}
+
+impl RenderCallbacks {
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    /// Adds a callback which is invoked at `stage` of every camera pass.
+    pub fn add<F>(&mut self, stage: RenderStage, callback: F) -> RenderCallbackHandle
+    where
+        F: FnMut(&mut RenderRecorder) + Send + 'static,
+    {
+        let handle = self.handles.create().into();
+        self.callbacks.push((handle, stage, Box::new(callback)));
+        handle
+    }
+
+    /// Removes the callback, returns false if it does not exist.
+    pub fn remove(&mut self, handle: RenderCallbackHandle) -> bool {
+        if !self.handles.free(handle) {
+            return false;
+        }
+
+        self.callbacks.retain(|v| v.0 != handle);
+        true
+    }
+
+    /// Returns true if there are no callbacks at `stage`.
+    pub fn is_empty(&self, stage: RenderStage) -> bool {
+        self.callbacks.iter().all(|v| v.1 != stage)
+    }
+
+    /// Invokes the callbacks at the stage of recorder.
+    pub fn invoke(&mut self, recorder: &mut RenderRecorder) {
+        for v in &mut self.callbacks {
+            if v.1 == recorder.stage {
+                (v.2)(recorder);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::sync::{Arc, Mutex};
+
+    use super::*;
+    use crayon::prelude::*;
+
+    #[test]
+    fn callbacks() {
+        let mut settings = Settings::default();
+        settings.headless = true;
+        let engine = Engine::new_with(&settings).unwrap();
+        let video = engine.context().video.clone();
+        let surface = video.create_surface(SurfaceParams::default()).unwrap();
+
+        let invoked = Arc::new(Mutex::new(Vec::new()));
+        let mut callbacks = RenderCallbacks::new();
+        assert!(callbacks.is_empty(RenderStage::AfterOpaque));
+
+        let record = |name: &'static str| {
+            let invoked = invoked.clone();
+            move |recorder: &mut RenderRecorder| {
+                let entry = (name, recorder.stage(), recorder.eye());
+                invoked.lock().unwrap().push(entry);
+            }
+        };
+
+        let first = callbacks.add(RenderStage::AfterOpaque, record("first"));
+        let post = callbacks.add(RenderStage::AfterPost, record("post"));
+        let second = callbacks.add(RenderStage::AfterOpaque, record("second"));
+        assert!(!callbacks.is_empty(RenderStage::AfterOpaque));
+        assert!(callbacks.is_empty(RenderStage::BeforeTransparents));
+        assert!(!callbacks.is_empty(RenderStage::AfterPost));
+
+        let eye = math::Vector3::new(1.0, 2.0, 3.0);
+        let invoke = |callbacks: &mut RenderCallbacks, stage| {
+            let identity = math::Matrix4::from_scale(1.0);
+            let mut recorder = RenderRecorder::new(&video, surface, stage, identity, identity, eye);
+            callbacks.invoke(&mut recorder);
+            assert_eq!(recorder.drawcalls(), 0);
+        };
+
+        // Only the callbacks at the stage are invoked, in the order they are added.
+        invoke(&mut callbacks, RenderStage::AfterOpaque);
+        assert_eq!(
+            *invoked.lock().unwrap(),
+            [
+                ("first", RenderStage::AfterOpaque, eye),
+                ("second", RenderStage::AfterOpaque, eye),
+            ]
+        );
+
+        invoked.lock().unwrap().clear();
+        invoke(&mut callbacks, RenderStage::BeforeTransparents);
+        assert!(invoked.lock().unwrap().is_empty());
+
+        invoke(&mut callbacks, RenderStage::AfterPost);
+        assert_eq!(
+            *invoked.lock().unwrap(),
+            [("post", RenderStage::AfterPost, eye)]
+        );
+
+        // The removed callbacks are never invoked again.
+        assert!(callbacks.remove(first));
+        assert!(!callbacks.remove(first));
+        assert!(callbacks.remove(post));
+        assert!(callbacks.is_empty(RenderStage::AfterPost));
+
+        invoked.lock().unwrap().clear();
+        invoke(&mut callbacks, RenderStage::AfterOpaque);
+        invoke(&mut callbacks, RenderStage::AfterPost);
+        assert_eq!(
+            *invoked.lock().unwrap(),
+            [("second", RenderStage::AfterOpaque, eye)]
+        );
+
+        assert!(callbacks.remove(second));
+        assert!(callbacks.is_empty(RenderStage::AfterOpaque));
+    }
+}
@@ -3,16 +3,49 @@
 use crayon::math;
 use crayon::video::assets::surface::SurfaceHandle;
 
+use super::RenderScale;
 use scene::Transform;
+use {Entity, WorldId};
+
+/// The debug visualization modes which could be used to diagnose content issues.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum DebugDrawMode {
+    /// Draws objects with their materials and lits as usual.
+    Shaded,
+    /// Draws shaded objects with a wireframe overlay.
+    Wireframe,
+    /// Visualizes the view space normals of objects.
+    Normals,
+    /// Visualizes how many times a pixel is drawn, brighter means more overdraws.
+    Overdraw,
+    /// Tints objects with the estimated mipmap level of their textures.
+    MipmapLevel,
+    /// Draws objects with white materials, so only the lighting is visible.
+    LightingOnly,
+}
+
+impl Default for DebugDrawMode {
+    fn default() -> Self {
+        DebugDrawMode::Shaded
+    }
+}
 
 /// A `Camera` is a device through which the player views the world.
 #[derive(Debug, Clone, Copy)]
 pub struct Camera {
     frustum: math::Frustum<f32>,
     surface: Option<SurfaceHandle>,
+    debug_mode: DebugDrawMode,
+    render_scale: RenderScale,
+    culling_mask: u32,
+    world: Option<WorldId>,
 
     #[doc(hidden)]
     pub transform: Transform,
+    #[doc(hidden)]
+    pub ent: Entity,
+    #[doc(hidden)]
+    pub owner: Option<WorldId>,
 }
 
 impl Default for Camera {
@@ -34,7 +67,13 @@ impl Camera {
         Camera {
             frustum: math::Frustum::new(projection),
             surface: None,
+            debug_mode: DebugDrawMode::default(),
+            render_scale: RenderScale::default(),
+            culling_mask: !0,
+            world: None,
             transform: Transform::default(),
+            ent: Entity::default(),
+            owner: None,
         }
     }
 
@@ -79,6 +118,69 @@ impl Camera {
         self.surface
     }
 
+    /// Sets the debug visualization mode, which could be changed at runtime.
+    #[inline]
+    pub fn set_debug_mode(&mut self, mode: DebugDrawMode) {
+        self.debug_mode = mode;
+    }
+
+    /// Gets the debug visualization mode.
+    #[inline]
+    pub fn debug_mode(&self) -> DebugDrawMode {
+        self.debug_mode
+    }
+
+    /// Sets the render scale, the scene will be rendered into an intermediate target
+    /// at the scaled resolution, and then upscaled to the drawing surface.
+    ///
+    /// Notes that render scale only works with the default surface right now.
+    #[inline]
+    pub fn set_render_scale(&mut self, render_scale: RenderScale) {
+        self.render_scale = render_scale;
+    }
+
+    /// Gets the render scale settings.
+    #[inline]
+    pub fn render_scale(&self) -> RenderScale {
+        self.render_scale
+    }
+
+    /// Sets the layers which are visible to this camera, the objects whose `layers`
+    /// don't match the mask are culled. All the layers are visible by default.
+    #[inline]
+    pub fn set_culling_mask(&mut self, mask: u32) {
+        self.culling_mask = mask;
+    }
+
+    /// Gets the layers which are visible to this camera.
+    #[inline]
+    pub fn culling_mask(&self) -> u32 {
+        self.culling_mask
+    }
+
+    /// Gets the id of camera in the culling statistics of `VideoFrameInfo`, which is
+    /// made of the world and entity of camera.
+    pub fn view_id(&self) -> u64 {
+        let world = self.owner.map(|v| v.index() as u64 + 1).unwrap_or(0);
+        (world << 32) | u64::from(self.ent.index())
+    }
+
+    /// Selects the world drawn by this camera, e.g. the editor camera which previews
+    /// another world. The world this camera belongs to is drawn if none is selected.
+    /// See `World::draw_cameras_of` for details.
+    pub fn set_world<T>(&mut self, world: T)
+    where
+        T: Into<Option<WorldId>>,
+    {
+        self.world = world.into();
+    }
+
+    /// Gets the selected world.
+    #[inline]
+    pub fn world(&self) -> Option<WorldId> {
+        self.world
+    }
+
     /// Sets the near/far clipping plane distances.
     #[inline]
     pub fn set_clip_plane(&mut self, near: f32, far: f32) {
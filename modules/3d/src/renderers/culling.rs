@@ -0,0 +1,229 @@
+//! Culls the objects which are invisible to a camera before drawing, and gathers the
+//! statistics which are reported into `VideoFrameInfo::culling`.
+//!
+//! The results of frustum tests could be kept in a `VisibilityCache` across frames.
+//! They are reused as long as both the camera and the object stay still, which saves
+//! most of the culling work of static cameras on big scenes.
+
+use std::collections::HashMap;
+use std::mem;
+use std::time::Instant;
+
+use crayon::math::{self, simd, PlaneRelation};
+use crayon::video::prelude::*;
+
+use super::{Camera, MeshRenderer};
+use scene::Transform;
+use {Entity, WorldId};
+
+/// The maximum number of cameras whose results are kept in `VisibilityCache`. The
+/// least recently used one is evicted when there are more.
+pub const MAX_CACHED_VIEWS: usize = 8;
+
+/// The results of frustum tests of cameras in last frame.
+#[derive(Default)]
+pub struct VisibilityCache {
+    // The cameras of different worlds could have the same Entity, so they are keyed
+    // with the worlds they belong to.
+    views: HashMap<(Option<WorldId>, Entity), CachedView>,
+    ticks: u64,
+}
+
+// The bounds of mesh are part of the state, so the results are discarded once the mesh
+// is reloaded or edited in place.
+type CachedResult = (MeshHandle, Transform, Option<math::Aabb3<f32>>, bool);
+
+struct CachedView {
+    view_matrix: math::Matrix4<f32>,
+    projection_matrix: math::Matrix4<f32>,
+    // The objects tested with their states, and whether they pass the frustum test.
+    results: HashMap<Entity, CachedResult>,
+    scratch: HashMap<Entity, CachedResult>,
+    ticks: u64,
+}
+
+impl VisibilityCache {
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    /// Discards the results of camera. The cuts and teleports of cameras, and the
+    /// changes of mesh bounds are detected automatically.
+    pub fn invalidate(&mut self, camera: &Camera) {
+        self.views.remove(&(camera.owner, camera.ent));
+    }
+
+    /// Discards all the results.
+    pub fn clear(&mut self) {
+        self.views.clear();
+    }
+
+    // Gets the results of camera, which are discarded if the camera has changed in any
+    // way since last frame.
+    fn view(&mut self, camera: &Camera) -> &mut CachedView {
+        self.ticks += 1;
+
+        let key = (camera.owner, camera.ent);
+        if !self.views.contains_key(&key) && self.views.len() >= MAX_CACHED_VIEWS {
+            let lru = self
+                .views
+                .iter()
+                .min_by_key(|v| (v.1).ticks)
+                .map(|v| *v.0)
+                .unwrap();
+
+            self.views.remove(&lru);
+        }
+
+        let view_matrix = camera.transform.view_matrix();
+        let projection_matrix = camera.frustum().to_matrix();
+
+        let view = self.views.entry(key).or_insert_with(|| CachedView {
+            view_matrix: view_matrix,
+            projection_matrix: projection_matrix,
+            results: HashMap::new(),
+            scratch: HashMap::new(),
+            ticks: 0,
+        });
+
+        if view.view_matrix != view_matrix || view.projection_matrix != projection_matrix {
+            view.view_matrix = view_matrix;
+            view.projection_matrix = projection_matrix;
+            view.results.clear();
+        }
+
+        view.ticks = self.ticks;
+        view
+    }
+}
+
+/// Pushes the visible meshes of camera into `visible`, and returns the statistics.
+/// The meshes are tested by the culling mask of camera, and then the view frustum
+/// with their bounds from `aabb`. The meshes without bounds are never culled by
+/// frustum.
+///
+/// There are no occluders yet, so `CullingInfo::occlusion_culled` is always zero.
+pub fn cull<F>(
+    camera: &Camera,
+    meshes: &[MeshRenderer],
+    aabb: F,
+    visible: &mut Vec<MeshRenderer>,
+) -> CullingInfo
+where
+    F: Fn(MeshHandle) -> Option<math::Aabb3<f32>>,
+{
+    cull_impl(None, camera, meshes, aabb, visible)
+}
+
+/// Culls like `cull`, but reuses the results of frustum tests in last frame if
+/// neither the camera nor the mesh has moved since then. The cache of camera is
+/// updated with the results of this frame.
+pub fn cull_cached<F>(
+    cache: &mut VisibilityCache,
+    camera: &Camera,
+    meshes: &[MeshRenderer],
+    aabb: F,
+    visible: &mut Vec<MeshRenderer>,
+) -> CullingInfo
+where
+    F: Fn(MeshHandle) -> Option<math::Aabb3<f32>>,
+{
+    cull_impl(Some(cache.view(camera)), camera, meshes, aabb, visible)
+}
+
+enum FrustumTest {
+    Done(bool),
+    // The index of bounds to be tested.
+    Pending(usize),
+}
+
+fn cull_impl<F>(
+    mut cache: Option<&mut CachedView>,
+    camera: &Camera,
+    meshes: &[MeshRenderer],
+    aabb: F,
+    visible: &mut Vec<MeshRenderer>,
+) -> CullingInfo
+where
+    F: Fn(MeshHandle) -> Option<math::Aabb3<f32>>,
+{
+    let ts = Instant::now();
+    let mut info = CullingInfo::default();
+
+    let frustum = camera.frustum();
+    let view_matrix = camera.transform.view_matrix();
+    let mask = camera.culling_mask();
+
+    // The results of meshes which are not tested in this frame are dropped.
+    let mut results = cache
+        .as_mut()
+        .map(|v| mem::replace(&mut v.scratch, HashMap::new()))
+        .unwrap_or_default();
+    results.clear();
+
+    // The bounds of meshes which are not reused are gathered in view space first, and
+    // then tested against the frustum planes in one batch.
+    let mut tests = Vec::new();
+    let mut bounds = Vec::new();
+
+    for mesh in meshes.iter().filter(|v| v.visible) {
+        info.tested += 1;
+
+        if mesh.layers & mask == 0 {
+            info.layer_culled += 1;
+            continue;
+        }
+
+        let local = aabb(mesh.mesh).filter(|v| *v != math::Aabb3::zero());
+        let reused = cache
+            .as_ref()
+            .and_then(|v| v.results.get(&mesh.ent))
+            .filter(|v| v.0 == mesh.mesh && v.1 == mesh.transform && v.2 == local)
+            .map(|v| v.3);
+
+        let test = match reused {
+            Some(v) => {
+                info.reused += 1;
+                FrustumTest::Done(v)
+            }
+            None => match local {
+                Some(v) => {
+                    let mv = simd::mul_matrix4(&view_matrix, &mesh.transform.matrix());
+                    bounds.push(simd::transform_aabb(&mv, &v));
+                    FrustumTest::Pending(bounds.len() - 1)
+                }
+                None => FrustumTest::Done(true),
+            },
+        };
+
+        tests.push((mesh, local, test));
+    }
+
+    let mut relations = vec![PlaneRelation::Out; bounds.len()];
+    simd::contains_aabbs(&frustum, &bounds, &mut relations);
+
+    for (mesh, local, test) in tests {
+        let inside = match test {
+            FrustumTest::Done(v) => v,
+            FrustumTest::Pending(i) => relations[i] != PlaneRelation::Out,
+        };
+
+        if cache.is_some() {
+            results.insert(mesh.ent, (mesh.mesh, mesh.transform, local, inside));
+        }
+
+        if !inside {
+            info.frustum_culled += 1;
+            continue;
+        }
+
+        visible.push(*mesh);
+    }
+
+    if let Some(v) = cache {
+        v.scratch = mem::replace(&mut v.results, results);
+    }
+
+    info.duration = Instant::now() - ts;
+    info
+}
@@ -0,0 +1,65 @@
+//! Global settings which affect how the objects are rendered.
+
+use crayon::math;
+
+/// The equations used to compute the fog factor with the distance to eye.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FogMode {
+    /// Fog is disabled.
+    Nothing,
+    /// The fog grows linearly between the `start` and `end` distances.
+    Linear,
+    /// The fog grows exponentially with `density`.
+    Exp,
+    /// The fog grows exponentially with the square of `density * distance`.
+    Exp2,
+}
+
+/// The global fog settings.
+#[derive(Debug, Clone, Copy)]
+pub struct Fog {
+    /// The equation of fog.
+    pub mode: FogMode,
+    /// The color of fog.
+    pub color: math::Color<f32>,
+    /// The density of `Exp` and `Exp2` fog.
+    pub density: f32,
+    /// The distance where `Linear` fog starts.
+    pub start: f32,
+    /// The distance where `Linear` fog completely hides objects.
+    pub end: f32,
+}
+
+impl Default for Fog {
+    fn default() -> Self {
+        Fog {
+            mode: FogMode::Nothing,
+            color: math::Color::gray(),
+            density: 0.01,
+            start: 0.0,
+            end: 100.0,
+        }
+    }
+}
+
+impl Fog {
+    /// Encodes the fog settings into `(mode, density, start, end)`, which is passed to
+    /// shaders as `u_FogParams` uniform.
+    pub fn encode(&self) -> math::Vector4<f32> {
+        let mode = match self.mode {
+            FogMode::Nothing => 0.0,
+            FogMode::Linear => 1.0,
+            FogMode::Exp => 2.0,
+            FogMode::Exp2 => 3.0,
+        };
+
+        math::Vector4::new(mode, self.density, self.start, self.end)
+    }
+}
+
+/// The global environment settings of renderer.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct RenderEnvironment {
+    /// The fog settings.
+    pub fog: Fog,
+}
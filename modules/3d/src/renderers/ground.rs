@@ -0,0 +1,143 @@
+//! An infinite ground plane, which is drawn with a grid projected from camera.
+//!
+//! Instead of a huge mesh, a grid which covers the screen is projected onto the plane
+//! every frame. So the vertices are dense near the camera and sparse at distance, and
+//! the plane always stretches to the horizon (or the far clip plane).
+
+use crayon::math::{self, SquareMatrix};
+use crayon::video::assets::texture::TextureHandle;
+
+/// The settings of infinite ground plane.
+#[derive(Debug, Clone, Copy)]
+pub struct GroundPlane {
+    /// The height of plane in world space.
+    pub height: f32,
+    /// The number of vertices of projected grid along the x and y axes of screen.
+    pub resolution: math::Vector2<u32>,
+    /// The texture which is tiled over the plane, it should be created with
+    /// `TextureWrap::Repeat`. The plane is drawn with `color` only if none.
+    pub texture: Option<TextureHandle>,
+    /// The color which is multiplied with texture.
+    pub color: math::Color<f32>,
+    /// The size of a tile in world units.
+    pub tile_size: math::Vector2<f32>,
+    /// The offset of tiles in world units, which could be animated to scroll the
+    /// texture, e.g. the waves of ocean.
+    pub tile_offset: math::Vector2<f32>,
+    /// The distance to eye where the plane starts fading out.
+    pub fade_start: f32,
+    /// The distance to eye where the plane fades out completely.
+    pub fade_end: f32,
+}
+
+impl Default for GroundPlane {
+    fn default() -> Self {
+        GroundPlane {
+            height: 0.0,
+            resolution: math::Vector2::new(64, 64),
+            texture: None,
+            color: math::Color::gray(),
+            tile_size: math::Vector2::new(1.0, 1.0),
+            tile_offset: math::Vector2::new(0.0, 0.0),
+            fade_start: 50.0,
+            fade_end: 100.0,
+        }
+    }
+}
+
+impl GroundPlane {
+    /// The maximum number of vertices along each axis, so the indices fit into `u16`.
+    pub const MAX_RESOLUTION: u32 = 256;
+
+    /// Creates a new ground plane at height.
+    pub fn new(height: f32) -> Self {
+        GroundPlane {
+            height: height,
+            ..Default::default()
+        }
+    }
+
+    /// Gets the resolution clamped into valid range.
+    pub fn clamped_resolution(&self) -> math::Vector2<u32> {
+        let f = |v: u32| v.max(2).min(Self::MAX_RESOLUTION);
+        math::Vector2::new(f(self.resolution.x), f(self.resolution.y))
+    }
+
+    /// Projects the grid from the view onto the plane, and returns the vertices in
+    /// world space row by row, from the bottom of screen to the top.
+    ///
+    /// The rays which never hit the plane within the far clip plane are stopped at the
+    /// far end, right above or below it.
+    pub fn project(
+        &self,
+        view_matrix: math::Matrix4<f32>,
+        projection_matrix: math::Matrix4<f32>,
+    ) -> Vec<math::Vector3<f32>> {
+        let view_proj = projection_matrix * view_matrix;
+        let inv = view_proj.invert().unwrap_or_else(math::Matrix4::identity);
+        let unproject = |x: f32, y: f32, z: f32| {
+            let v = inv * math::Vector4::new(x, y, z, 1.0);
+            v.truncate() / v.w
+        };
+
+        let resolution = self.clamped_resolution();
+        let (w, h) = (resolution.x as f32 - 1.0, resolution.y as f32 - 1.0);
+
+        let mut vertices = Vec::with_capacity((resolution.x * resolution.y) as usize);
+        for j in 0..resolution.y {
+            for i in 0..resolution.x {
+                let x = i as f32 / w * 2.0 - 1.0;
+                let y = j as f32 / h * 2.0 - 1.0;
+
+                let near = unproject(x, y, -1.0);
+                let far = unproject(x, y, 1.0);
+                let dir = far - near;
+
+                let t = if dir.y.abs() > ::std::f32::EPSILON {
+                    (self.height - near.y) / dir.y
+                } else {
+                    -1.0
+                };
+
+                let mut v = if t >= 0.0 && t <= 1.0 {
+                    near + dir * t
+                } else {
+                    far
+                };
+                v.y = self.height;
+                vertices.push(v);
+            }
+        }
+
+        vertices
+    }
+
+    /// Gets the indices of triangles of the projected grid.
+    pub fn indices(&self) -> Vec<u16> {
+        let resolution = self.clamped_resolution();
+        let (w, h) = (resolution.x, resolution.y);
+
+        let mut indices = Vec::with_capacity(((w - 1) * (h - 1) * 6) as usize);
+        for j in 0..(h - 1) {
+            for i in 0..(w - 1) {
+                let v = (j * w + i) as u16;
+                let up = v + w as u16;
+                indices.extend_from_slice(&[v, v + 1, up + 1, v, up + 1, up]);
+            }
+        }
+
+        indices
+    }
+
+    /// Encodes the tiling into `(1 / size, offset / size)`, which is passed to shaders
+    /// as `u_TilingOffset` uniform.
+    pub fn encode_tiling(&self) -> math::Vector4<f32> {
+        let (sx, sy) = (self.tile_size.x.max(1e-4), self.tile_size.y.max(1e-4));
+        math::Vector4::new(
+            1.0 / sx,
+            1.0 / sy,
+            self.tile_offset.x / sx,
+            self.tile_offset.y / sy,
+        )
+    }
+}
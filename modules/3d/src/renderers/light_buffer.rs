@@ -0,0 +1,290 @@
+//! Light sources gathered once per frame, and shared by all the drawcalls.
+
+use std::sync::Arc;
+
+use crayon::math::{self, MetricSpace};
+use crayon::video::assets::shader::{UniformVariableLayoutBuilder, UniformVariableType};
+use crayon::video::assets::uniform_buffer::*;
+use crayon::video::batch::DrawCall;
+use crayon::video::errors::Result;
+use crayon::video::VideoSystemShared;
+
+use super::{Lit, LitSource};
+
+type DirLit = (math::Vector3<f32>, math::Vector3<f32>);
+type PointLit = (math::Vector3<f32>, math::Vector3<f32>, math::Vector3<f32>);
+
+// The uniform buffer which holds the gathered lights, and its CPU-side block.
+struct SharedBuffer {
+    video: Arc<VideoSystemShared>,
+    buffer: UniformBufferHandle,
+    block: UniformBlock,
+}
+
+/// A `LightBuffer` gathers all the enabled light sources once per frame and transforms
+/// them into view space, so drawcalls could share the same uniform arrays instead of
+/// computing lights per-object.
+///
+/// The lights of a `LightBuffer` created with `LightBuffer::shared` are uploaded into
+/// a uniform buffer once, which is read by all the drawcalls. Otherwise, they have to
+/// be bound into every drawcall with `LightBuffer::bind`, e.g. on the devices without
+/// uniform buffers.
+///
+/// The attenuation of point lights is packed into the `w` of their positions and
+/// colors, so every point light takes two `vec4` uniforms only.
+pub struct LightBuffer {
+    dir_names: Vec<(String, String)>,
+    point_names: Vec<(String, String)>,
+
+    dirs: Vec<DirLit>,
+    points: Vec<PointLit>,
+    shared: Option<SharedBuffer>,
+}
+
+impl LightBuffer {
+    /// Creates a new `LightBuffer` with the maximum number of directional and point lights.
+    pub fn new(max_dir_lits: usize, max_point_lits: usize) -> Self {
+        let mut dir_names = Vec::new();
+        for i in 0..max_dir_lits {
+            dir_names.push((
+                format!("u_DirLitViewDir[{0}]", i),
+                format!("u_DirLitColor[{0}]", i),
+            ));
+        }
+
+        let mut point_names = Vec::new();
+        for i in 0..max_point_lits {
+            point_names.push((
+                format!("u_PointLitViewPos[{0}]", i),
+                format!("u_PointLitColor[{0}]", i),
+            ));
+        }
+
+        LightBuffer {
+            dir_names: dir_names,
+            point_names: point_names,
+            dirs: Vec::new(),
+            points: Vec::new(),
+            shared: None,
+        }
+    }
+
+    /// Creates a new `LightBuffer` which uploads the gathered lights into a uniform
+    /// buffer, with the layout of `declare_block`. It fails if uniform buffers are not
+    /// supported by the device.
+    pub fn shared(
+        video: &Arc<VideoSystemShared>,
+        max_dir_lits: usize,
+        max_point_lits: usize,
+    ) -> Result<Self> {
+        let mut lights = LightBuffer::new(max_dir_lits, max_point_lits);
+        let layout = lights.declare_block(UniformBlockLayout::build()).finish()?;
+
+        let mut params = UniformBufferParams::default();
+        params.size = layout.size();
+        let buffer = video.create_uniform_buffer(params, None)?;
+
+        lights.shared = Some(SharedBuffer {
+            video: video.clone(),
+            buffer: buffer,
+            block: UniformBlock::new(layout),
+        });
+
+        Ok(lights)
+    }
+
+    /// Gets the uniform buffer which holds the lights, or `None` if the lights are not
+    /// shared.
+    #[inline]
+    pub fn uniform_buffer(&self) -> Option<UniformBufferHandle> {
+        self.shared.as_ref().map(|v| v.buffer)
+    }
+
+    /// Gets the uniform block of the last upload, or `None` if the lights are not
+    /// shared.
+    #[inline]
+    pub fn block(&self) -> Option<&UniformBlock> {
+        self.shared.as_ref().map(|v| &v.block)
+    }
+
+    /// Declares the uniform arrays of directional lights.
+    pub fn declare_dir_lits(
+        &self,
+        mut builder: UniformVariableLayoutBuilder,
+    ) -> UniformVariableLayoutBuilder {
+        for v in &self.dir_names {
+            builder = builder
+                .with(v.0.as_ref(), UniformVariableType::Vector3f)
+                .with(v.1.as_ref(), UniformVariableType::Vector3f);
+        }
+
+        builder
+    }
+
+    /// Declares the uniform arrays of point lights.
+    pub fn declare_point_lits(
+        &self,
+        mut builder: UniformVariableLayoutBuilder,
+    ) -> UniformVariableLayoutBuilder {
+        for v in &self.point_names {
+            builder = builder
+                .with(v.0.as_ref(), UniformVariableType::Vector4f)
+                .with(v.1.as_ref(), UniformVariableType::Vector4f);
+        }
+
+        builder
+    }
+
+    /// Declares the arrays of lights as the members of uniform block, in the same order
+    /// as `u_DirLit*` and `u_PointLit*` are declared in the blocks of shaders.
+    pub fn declare_block(&self, builder: UniformBlockLayoutBuilder) -> UniformBlockLayoutBuilder {
+        let dirs = self.dir_names.len();
+        let points = self.point_names.len();
+
+        builder
+            .with_array("u_DirLitViewDir", UniformVariableType::Vector3f, dirs)
+            .with_array("u_DirLitColor", UniformVariableType::Vector3f, dirs)
+            .with_array("u_PointLitViewPos", UniformVariableType::Vector4f, points)
+            .with_array("u_PointLitColor", UniformVariableType::Vector4f, points)
+    }
+
+    /// Gathers the enabled lights for a view. Point lights are prioritized by the
+    /// distances between their bounding sphere and the eye.
+    pub fn build(
+        &mut self,
+        view_matrix: math::Matrix4<f32>,
+        eye: math::Vector3<f32>,
+        lits: &[Lit],
+    ) {
+        self.dirs.clear();
+        self.points.clear();
+
+        let mut points = Vec::new();
+        for lit in lits {
+            if !lit.enable {
+                continue;
+            }
+
+            match lit.source {
+                LitSource::Dir => {
+                    if self.dirs.len() < self.dir_names.len() {
+                        let dir = view_matrix * lit.transform.forward().extend(0.0);
+                        self.dirs.push((dir.truncate(), lit.color.rgb().into()));
+                    }
+                }
+                LitSource::Point { radius, .. } => {
+                    let distance = lit.transform.position.distance(eye) - radius;
+                    points.push((distance, lit));
+                }
+            }
+        }
+
+        points.sort_by(|lhs, rhs| {
+            lhs.0
+                .partial_cmp(&rhs.0)
+                .unwrap_or(::std::cmp::Ordering::Equal)
+        });
+
+        for &(_, lit) in points.iter().take(self.point_names.len()) {
+            if let LitSource::Point { radius, smoothness } = lit.source {
+                let pos = view_matrix * lit.transform.position.extend(1.0);
+                let attenuation = math::Vector3::new(
+                    1.0,
+                    -1.0 / (radius + smoothness * radius * radius),
+                    -smoothness / (radius + smoothness * radius * radius),
+                );
+
+                self.points
+                    .push((pos.truncate(), lit.color.rgb().into(), attenuation));
+            }
+        }
+    }
+
+    /// Binds the gathered lights into drawcall. Unused slots are filled with black lights.
+    ///
+    /// The point lights are bound as `(position, linear)` and `(color, quadratic)`,
+    /// the constant factor of attenuation is always 1.
+    pub fn bind(&self, dc: &mut DrawCall) {
+        let zero = math::Vector3::new(0.0, 0.0, 0.0);
+
+        for (i, names) in self.dir_names.iter().enumerate() {
+            let v = self.dirs.get(i).cloned().unwrap_or((zero, zero));
+            dc.set_uniform_variable(&names.0, v.0);
+            dc.set_uniform_variable(&names.1, v.1);
+        }
+
+        for (i, names) in self.point_names.iter().enumerate() {
+            let v = self.points.get(i).cloned().unwrap_or((zero, zero, zero));
+            dc.set_uniform_variable(&names.0, v.0.extend(v.2.y));
+            dc.set_uniform_variable(&names.1, v.1.extend(v.2.z));
+        }
+    }
+
+    /// Uploads the gathered lights into the shared uniform buffer once, and binds it to
+    /// `slot`. The drawcalls submitted after this call read the lights of last `build`.
+    /// It does nothing if the lights are not shared.
+    pub fn upload(&mut self, slot: usize) -> Result<()> {
+        if let Some(ref mut v) = self.shared {
+            let (dirs, points) = (self.dir_names.len(), self.point_names.len());
+            encode(&self.dirs, &self.points, (dirs, points), &mut v.block)?;
+
+            v.video
+                .update_uniform_buffer(v.buffer, 0, v.block.as_bytes())?;
+            v.video.bind_uniform_buffer(slot, v.buffer)?;
+        }
+
+        Ok(())
+    }
+
+    /// Encodes the gathered lights into uniform block which is declared by
+    /// `declare_block`, the same way as `bind` does.
+    pub fn encode(&self, block: &mut UniformBlock) -> Result<()> {
+        let (dirs, points) = (self.dir_names.len(), self.point_names.len());
+        encode(&self.dirs, &self.points, (dirs, points), block)
+    }
+
+    /// Returns the number of gathered directional lights.
+    #[inline]
+    pub fn num_dir_lits(&self) -> usize {
+        self.dirs.len()
+    }
+
+    /// Returns the number of gathered point lights.
+    #[inline]
+    pub fn num_point_lits(&self) -> usize {
+        self.points.len()
+    }
+}
+
+impl Drop for LightBuffer {
+    fn drop(&mut self) {
+        if let Some(ref v) = self.shared {
+            v.video.delete_uniform_buffer(v.buffer);
+        }
+    }
+}
+
+// Encodes the lights into all the slots of block, the unused ones are filled with black
+// lights.
+fn encode(
+    dirs: &[DirLit],
+    points: &[PointLit],
+    slots: (usize, usize),
+    block: &mut UniformBlock,
+) -> Result<()> {
+    let zero = math::Vector3::new(0.0, 0.0, 0.0);
+
+    for i in 0..slots.0 {
+        let v = dirs.get(i).cloned().unwrap_or((zero, zero));
+        block.set_element("u_DirLitViewDir", i, v.0)?;
+        block.set_element("u_DirLitColor", i, v.1)?;
+    }
+
+    for i in 0..slots.1 {
+        let v = points.get(i).cloned().unwrap_or((zero, zero, zero));
+        block.set_element("u_PointLitViewPos", i, v.0.extend(v.2.y))?;
+        block.set_element("u_PointLitColor", i, v.1.extend(v.2.z))?;
+    }
+
+    Ok(())
+}
@@ -6,8 +6,13 @@ use scene::Transform;
 pub struct Lit {
     /// Is this light enable.
     pub enable: bool,
-    /// Is this light casting shadow.
+    /// Is this light casting shadow. Only the first enabled directional light casts
+    /// shadows, this is ignored by the other lights.
     pub shadow_caster: bool,
+    /// The resolution of shadow map, which overrides the one of `ShadowSettings`.
+    pub shadow_resolution: Option<u32>,
+    /// The maximum distance to the camera where this light casts shadows.
+    pub shadow_distance: f32,
     /// Color of the light.
     pub color: math::Color<f32>,
     /// Brightness of the light source, in lumens.
@@ -38,6 +43,8 @@ impl Default for Lit {
         Lit {
             enable: true,
             shadow_caster: false,
+            shadow_resolution: None,
+            shadow_distance: ::std::f32::MAX,
             color: math::Color::white(),
             intensity: 1.0,
             source: LitSource::Dir,
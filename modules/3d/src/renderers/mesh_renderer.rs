@@ -1,5 +1,8 @@
+use crayon::math;
+use crayon::video::assets::texture::TextureHandle;
 use crayon::video::prelude::*;
 
+use super::MaterialPropertyBlock;
 use scene::Transform;
 use Entity;
 
@@ -13,6 +16,13 @@ pub struct MeshRenderer {
     pub shadow_receiver: bool,
     /// Is this renderer visible.
     pub visible: bool,
+    /// The layers which this object belongs to, it's only drawn by the cameras whose
+    /// culling mask matches.
+    pub layers: u32,
+    /// The baked lightmap of static object.
+    pub lightmap: Option<Lightmap>,
+    /// The per-object overrides which are layered over the shared materials.
+    pub properties: MaterialPropertyBlock,
 
     #[doc(hidden)]
     pub transform: Transform,
@@ -27,8 +37,39 @@ impl Default for MeshRenderer {
             shadow_caster: false,
             shadow_receiver: false,
             visible: true,
+            layers: 1,
+            lightmap: None,
+            properties: MaterialPropertyBlock::default(),
             transform: Transform::default(),
             ent: Entity::default(),
         }
     }
 }
+
+/// The region of lightmap atlas which is assigned to a object. The second texcoords
+/// (`Texcoord1`) of mesh are transformed into atlas with `uv * scale + offset`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Lightmap {
+    /// The lightmap atlas.
+    pub texture: TextureHandle,
+    /// The scale (xy) and offset (zw) of region in atlas.
+    pub scale_offset: math::Vector4<f32>,
+}
+
+impl Lightmap {
+    /// Creates a lightmap which uses the whole texture.
+    pub fn new(texture: TextureHandle) -> Self {
+        Lightmap {
+            texture: texture,
+            scale_offset: math::Vector4::new(1.0, 1.0, 0.0, 0.0),
+        }
+    }
+
+    /// Creates a lightmap with the region in atlas.
+    pub fn with_region(texture: TextureHandle, scale: [f32; 2], offset: [f32; 2]) -> Self {
+        Lightmap {
+            texture: texture,
+            scale_offset: math::Vector4::new(scale[0], scale[1], offset[0], offset[1]),
+        }
+    }
+}
@@ -1,26 +1,104 @@
 mod camera;
-pub use self::camera::Camera;
+pub use self::camera::{Camera, DebugDrawMode};
 
 mod lit;
 pub use self::lit::{Lit, LitSource};
 
+mod light_buffer;
+pub use self::light_buffer::LightBuffer;
+
+mod property_block;
+pub use self::property_block::{MaterialPropertyBlock, MAX_MATERIAL_PROPERTIES};
+
 mod mesh_renderer;
-pub use self::mesh_renderer::MeshRenderer;
+pub use self::mesh_renderer::{Lightmap, MeshRenderer};
+
+mod culling;
+pub use self::culling::{cull, cull_cached, VisibilityCache, MAX_CACHED_VIEWS};
+
+mod callbacks;
+pub use self::callbacks::{RenderCallback, RenderCallbackHandle, RenderCallbacks};
+pub use self::callbacks::{RenderRecorder, RenderStage};
+
+pub mod pipeline;
+pub use self::pipeline::{RenderPass, RenderPipeline};
+
+mod sky;
+pub use self::sky::{Sky, SkySun};
+
+mod render_scale;
+pub use self::render_scale::{RenderScale, RenderScaleController, RenderScaleMode, UpscaleFilter};
+
+mod probes;
+pub use self::probes::{ProbeGrid, ShIrradiance};
+
+mod ground;
+pub use self::ground::GroundPlane;
+
+mod trail;
+pub use self::trail::{ColorGradient, LineRenderer, LineTextureMode, TrailRenderer, WidthCurve};
+pub use self::trail::{Ribbon, RibbonPoint, RibbonVertex};
+
+mod shadows;
+pub use self::shadows::{atlas_tile, fit_cascades, split_distances};
+pub use self::shadows::{ShadowCascade, ShadowQuality, ShadowSettings, MAX_SHADOW_CASCADES};
+
+mod blob_shadow;
+pub use self::blob_shadow::{BlobQuad, BlobShadow};
+
+mod reflection;
+pub use self::reflection::{PlanarReflection, WaterDistortion};
+
+mod picking;
+pub use self::picking::{decode_picking_id, encode_picking_id, MAX_PICKING_OBJECTS};
+pub use self::picking::{PickingFrame, PickingParams, PickingService};
+
+mod environment;
+pub use self::environment::{Fog, FogMode, RenderEnvironment};
+
+mod variants;
+pub use self::variants::{ShaderKeywords, ShaderVariantBuilder, ShaderVariants};
 
 pub mod simple;
 pub use self::simple::{SimpleMaterial, SimpleRenderer};
 
+use std::time::Duration;
+
+use crayon::errors::*;
+
+use entity_ref::EntityRemap;
+use physics::Colliders;
 use scene::SceneGraph;
-use {Component, Entity};
+use {Component, Entity, WorldId};
 
 pub trait Renderer {
-    fn submit(&mut self, camera: &Camera, lits: &[Lit], meshes: &[MeshRenderer]);
+    /// Draws the meshes with lits by the camera. The drawcalls which could not be
+    /// validated, e.g. with undefined uniforms, are dropped instead of aborting the
+    /// camera, and the first error is returned after the rest are submitted.
+    fn submit(&mut self, camera: &Camera, lits: &[Lit], meshes: &[MeshRenderer]) -> Result<()>;
+
+    /// Sets the ribbons of lines and trails, which are drawn by the cameras submitted
+    /// afterwards. They are ignored by default.
+    fn set_ribbons(&mut self, _: Vec<Ribbon>) {}
+
+    /// Sets the projected blob shadows, which are drawn by the cameras submitted
+    /// afterwards. They are ignored by default.
+    fn set_blob_shadows(&mut self, _: Vec<BlobQuad>) {}
+
+    /// Replaces the entities with the new ones in `remap`, which is called after the
+    /// entities of world are compacted. The renderers which keep states of entities
+    /// should update them.
+    fn remap_entities(&mut self, _: &EntityRemap) {}
 }
 
+#[derive(Clone)]
 pub struct Renderable {
     cameras: Component<Camera>,
     lits: Component<Lit>,
     meshes: Component<MeshRenderer>,
+    lines: Component<LineRenderer>,
+    trails: Component<TrailRenderer>,
+    blob_shadows: Component<BlobShadow>,
 }
 
 impl Renderable {
@@ -29,6 +107,9 @@ impl Renderable {
             cameras: Component::new(),
             lits: Component::new(),
             meshes: Component::new(),
+            lines: Component::new(),
+            trails: Component::new(),
+            blob_shadows: Component::new(),
         }
     }
 
@@ -91,31 +172,192 @@ impl Renderable {
     pub fn remove_mesh(&mut self, ent: Entity) {
         self.meshes.remove(ent);
     }
+
+    /// Gets all the meshes, with the transforms of last `draw`.
+    #[inline]
+    pub fn meshes(&self) -> &[MeshRenderer] {
+        &self.meshes.data
+    }
+
+    #[inline]
+    pub fn add_line(&mut self, ent: Entity, line: LineRenderer) {
+        self.lines.add(ent, line);
+    }
+
+    #[inline]
+    pub fn line(&self, ent: Entity) -> Option<&LineRenderer> {
+        self.lines.get(ent)
+    }
+
+    #[inline]
+    pub fn line_mut(&mut self, ent: Entity) -> Option<&mut LineRenderer> {
+        self.lines.get_mut(ent)
+    }
+
+    #[inline]
+    pub fn remove_line(&mut self, ent: Entity) {
+        self.lines.remove(ent);
+    }
+
+    #[inline]
+    pub fn add_trail(&mut self, ent: Entity, trail: TrailRenderer) {
+        self.trails.add(ent, trail);
+    }
+
+    #[inline]
+    pub fn trail(&self, ent: Entity) -> Option<&TrailRenderer> {
+        self.trails.get(ent)
+    }
+
+    #[inline]
+    pub fn trail_mut(&mut self, ent: Entity) -> Option<&mut TrailRenderer> {
+        self.trails.get_mut(ent)
+    }
+
+    #[inline]
+    pub fn remove_trail(&mut self, ent: Entity) {
+        self.trails.remove(ent);
+    }
+
+    /// Records the positions of entities into their trails, which should be called
+    /// once per frame.
+    pub fn advance_trails(&mut self, scene: &SceneGraph, delta: Duration) {
+        for (&ent, trail) in self.trails.entities.iter().zip(self.trails.data.iter_mut()) {
+            if let Some(transform) = scene.transform(ent) {
+                trail.advance(transform.position, delta);
+            }
+        }
+    }
+
+    #[inline]
+    pub fn add_blob_shadow(&mut self, ent: Entity, shadow: BlobShadow) {
+        self.blob_shadows.add(ent, shadow);
+    }
+
+    #[inline]
+    pub fn blob_shadow(&self, ent: Entity) -> Option<&BlobShadow> {
+        self.blob_shadows.get(ent)
+    }
+
+    #[inline]
+    pub fn blob_shadow_mut(&mut self, ent: Entity) -> Option<&mut BlobShadow> {
+        self.blob_shadows.get_mut(ent)
+    }
+
+    #[inline]
+    pub fn remove_blob_shadow(&mut self, ent: Entity) {
+        self.blob_shadows.remove(ent);
+    }
+
+    /// Projects the blob shadows onto the colliders beneath entities, which should be
+    /// called once per frame after the entities are moved.
+    pub fn update_blob_shadows(&mut self, scene: &SceneGraph, colliders: &Colliders) {
+        for (&ent, shadow) in self.blob_shadows
+            .entities
+            .iter()
+            .zip(self.blob_shadows.data.iter_mut())
+        {
+            shadow.update(scene, colliders, ent);
+        }
+    }
+
+    /// Replaces the entities with the new ones in `remap`.
+    pub fn remap_entities(&mut self, remap: &EntityRemap) {
+        self.cameras.remap_entities(remap);
+        self.lits.remap_entities(remap);
+        self.meshes.remap_entities(remap);
+        self.lines.remap_entities(remap);
+        self.trails.remap_entities(remap);
+        self.blob_shadows.remap_entities(remap);
+
+        for (i, v) in self.cameras.data.iter_mut().enumerate() {
+            v.ent = self.cameras.entities[i];
+        }
+
+        for (i, v) in self.meshes.data.iter_mut().enumerate() {
+            v.ent = self.meshes.entities[i];
+        }
+    }
 }
 
 impl Renderable {
-    pub fn draw(&mut self, pipeline: &mut Renderer, scene: &SceneGraph) {
+    /// Draws with the transforms which are interpolated by `alpha`, see
+    /// `SceneGraph::interpolated_transform` for details. The cameras which select
+    /// other worlds than `world` are skipped.
+    ///
+    /// Every camera is submitted even if some of them failed, and the first error is
+    /// returned.
+    pub fn draw(
+        &mut self,
+        pipeline: &mut Renderer,
+        scene: &SceneGraph,
+        alpha: f32,
+        world: WorldId,
+    ) -> Result<()> {
         for (i, v) in self.cameras.data.iter_mut().enumerate() {
-            if let Some(transform) = scene.transform(self.cameras.entities[i]) {
+            v.owner = Some(world);
+            if let Some(transform) = scene.interpolated_transform(self.cameras.entities[i], alpha) {
                 v.transform = transform;
+                v.ent = self.cameras.entities[i];
             }
         }
 
         for (i, v) in self.lits.data.iter_mut().enumerate() {
-            if let Some(transform) = scene.transform(self.lits.entities[i]) {
+            if let Some(transform) = scene.interpolated_transform(self.lits.entities[i], alpha) {
                 v.transform = transform;
             }
         }
 
         for (i, v) in self.meshes.data.iter_mut().enumerate() {
-            if let Some(transform) = scene.transform(self.meshes.entities[i]) {
+            if let Some(transform) = scene.interpolated_transform(self.meshes.entities[i], alpha) {
                 v.transform = transform;
                 v.ent = self.meshes.entities[i];
             }
         }
 
+        let lines = self.lines.data.iter().filter(|v| v.visible);
+        let trails = self.trails.data.iter().filter(|v| v.visible);
+        let ribbons = lines
+            .filter_map(|v| v.ribbon())
+            .chain(trails.filter_map(|v| v.ribbon()))
+            .collect();
+        pipeline.set_ribbons(ribbons);
+
+        let shadows = self.blob_shadows
+            .data
+            .iter()
+            .filter(|v| v.visible)
+            .filter_map(|v| v.projection())
+            .collect();
+        pipeline.set_blob_shadows(shadows);
+
+        let mut result = Ok(());
         for v in &self.cameras.data {
-            pipeline.submit(&v, &self.lits.data, &self.meshes.data);
+            if v.world().map_or(true, |v| v == world) {
+                let submitted = pipeline.submit(&v, &self.lits.data, &self.meshes.data);
+                result = result.and(submitted);
+            }
+        }
+
+        result
+    }
+
+    /// Submits the cameras of `views` which select `world`, with the lits and meshes
+    /// prepared by the last `draw`.
+    pub fn draw_cameras_of(
+        &self,
+        pipeline: &mut Renderer,
+        views: &Renderable,
+        world: WorldId,
+    ) -> Result<()> {
+        let mut result = Ok(());
+        for v in &views.cameras.data {
+            if v.world() == Some(world) {
+                let submitted = pipeline.submit(&v, &self.lits.data, &self.meshes.data);
+                result = result.and(submitted);
+            }
         }
+
+        result
     }
 }
@@ -0,0 +1,361 @@
+//! GPU picking, which finds the objects under the cursor with an ID pass.
+//!
+//! The meshes seen by registered cameras are drawn with their indices as colors into
+//! a target at reduced resolution every few frames, and the pixels are read back
+//! asynchronously. The queries are answered with the latest finished pass, so they
+//! never stall the video thread, but the results are a few frames old.
+//!
+//! It complements the ray casts against colliders, since the dense meshes have no
+//! colliders usually.
+//!
+//! ```rust,ignore
+//! let mut picking = PickingService::new(ctx, PickingParams::default())?;
+//! picking.register(camera);
+//!
+//! // Every frame, after the renderables are drawn.
+//! picking.advance(&world.renderables);
+//! if let Some(ent) = picking.pick(camera, ctx.input.mouse_position()) {
+//!     // ...
+//! }
+//! ```
+
+use std::sync::Arc;
+
+use crayon::application::window::WindowShared;
+use crayon::application::Context;
+use crayon::errors::*;
+use crayon::math;
+use crayon::video::assets::prelude::*;
+use crayon::video::prelude::*;
+
+use super::{cull, Renderable};
+use Entity;
+
+/// The maximum number of objects in a ID pass, which are encoded into 24 bits.
+pub const MAX_PICKING_OBJECTS: usize = (1 << 24) - 1;
+
+/// The settings of `PickingService`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct PickingParams {
+    /// The resolution of ID pass relative to the window.
+    pub resolution: f32,
+    /// The ID pass is drawn once every `interval` frames.
+    pub interval: u32,
+}
+
+impl Default for PickingParams {
+    fn default() -> Self {
+        PickingParams {
+            resolution: 0.25,
+            interval: 4,
+        }
+    }
+}
+
+/// Encodes the index of object into the color of ID pass. The index 0 is reserved
+/// for the background.
+pub fn encode_picking_id(index: usize) -> math::Color<f32> {
+    let id = (index + 1) as u32;
+    let channel = |shift: u32| ((id >> shift) & 0xFF) as f32 / 255.0;
+    math::Color::new(channel(0), channel(8), channel(16), 1.0)
+}
+
+/// Decodes the index of object from the pixel of ID pass.
+pub fn decode_picking_id(pixel: [u8; 4]) -> Option<usize> {
+    let id = u32::from(pixel[0]) | (u32::from(pixel[1]) << 8) | (u32::from(pixel[2]) << 16);
+    if id == 0 {
+        None
+    } else {
+        Some(id as usize - 1)
+    }
+}
+
+/// The pixels of a finished ID pass, and the objects drawn into it.
+#[derive(Debug, Clone)]
+pub struct PickingFrame {
+    readback: Readback,
+    entities: Vec<Entity>,
+}
+
+impl PickingFrame {
+    pub fn new(readback: Readback, entities: Vec<Entity>) -> Self {
+        PickingFrame {
+            readback: readback,
+            entities: entities,
+        }
+    }
+
+    /// Gets the object at the position, which is normalized into `[0, 1]` and starts
+    /// from the bottom-left corner.
+    pub fn pick(&self, position: math::Vector2<f32>) -> Option<Entity> {
+        if position.x < 0.0 || position.x > 1.0 || position.y < 0.0 || position.y > 1.0 {
+            return None;
+        }
+
+        let dim = self.readback.area.dim();
+        let x = ((position.x * dim.x as f32) as u32).min(dim.x.max(1) - 1);
+        let y = ((position.y * dim.y as f32) as u32).min(dim.y.max(1) - 1);
+
+        let pixel = self.readback.pixel(x, y)?;
+        decode_picking_id(pixel).and_then(|i| self.entities.get(i).cloned())
+    }
+}
+
+/// Renders the ID passes of registered cameras, and answers the picking queries.
+pub struct PickingService {
+    params: PickingParams,
+    video: Arc<VideoSystemShared>,
+    window: Arc<WindowShared>,
+    shader: ShaderHandle,
+    frames: u64,
+    views: Vec<PickingView>,
+}
+
+struct PickingView {
+    camera: Entity,
+    target: Option<PickingTarget>,
+    // The read in flight, and the objects drawn into it.
+    pending: Option<(ReadbackHandle, Vec<Entity>)>,
+    latest: Option<PickingFrame>,
+}
+
+struct PickingTarget {
+    surface: SurfaceHandle,
+    color: RenderTextureHandle,
+    depth: RenderTextureHandle,
+    dimensions: math::Vector2<u32>,
+}
+
+impl PickingTarget {
+    fn delete(&self, video: &VideoSystemShared) {
+        video.delete_surface(self.surface);
+        video.delete_render_texture(self.color);
+        video.delete_render_texture(self.depth);
+    }
+}
+
+impl PickingService {
+    pub fn new(ctx: &Context, params: PickingParams) -> Result<Self> {
+        let attributes = AttributeLayout::build()
+            .with(Attribute::Position, 3)
+            .finish();
+
+        let uniforms = UniformVariableLayout::build()
+            .with("u_MVPMatrix", UniformVariableType::Matrix4f)
+            .with("u_Id", UniformVariableType::Vector4f)
+            .finish();
+
+        let mut params_ = ShaderParams::default();
+        params_.state.depth_write = true;
+        params_.state.depth_test = Comparison::Less;
+        params_.attributes = attributes;
+        params_.uniforms = uniforms;
+
+        let vs = format!(
+            "
+            #version 100
+            precision highp float;
+            {0}
+            ",
+            include_str!("../../assets/picking.vs")
+        );
+
+        let fs = format!(
+            "
+            #version 100
+            precision mediump float;
+            {0}
+            ",
+            include_str!("../../assets/picking.fs")
+        );
+
+        let shader = ctx.video.create_shader(params_, vs, fs)?;
+
+        Ok(PickingService {
+            params: params,
+            video: ctx.video.clone(),
+            window: ctx.window.clone(),
+            shader: shader,
+            frames: 0,
+            views: Vec::new(),
+        })
+    }
+
+    /// Starts drawing the ID pass of camera.
+    pub fn register(&mut self, camera: Entity) {
+        if self.views.iter().all(|v| v.camera != camera) {
+            self.views.push(PickingView {
+                camera: camera,
+                target: None,
+                pending: None,
+                latest: None,
+            });
+        }
+    }
+
+    /// Stops drawing the ID pass of camera, and releases its resources.
+    pub fn unregister(&mut self, camera: Entity) {
+        if let Some(i) = self.views.iter().position(|v| v.camera == camera) {
+            let view = self.views.swap_remove(i);
+            self.release(&view);
+        }
+    }
+
+    /// Gets the object under the position of camera in pixels, which starts from the
+    /// bottom-left corner of window like `InputSystemShared::mouse_position`.
+    pub fn pick(&self, camera: Entity, position: math::Vector2<f32>) -> Option<Entity> {
+        let window = self.window.dimensions();
+        let view = self.views.iter().find(|v| v.camera == camera)?;
+        let uv = math::Vector2::new(
+            position.x / window.x.max(1) as f32,
+            position.y / window.y.max(1) as f32,
+        );
+
+        view.latest.as_ref().and_then(|v| v.pick(uv))
+    }
+
+    /// Gets the latest finished ID pass of camera.
+    pub fn latest(&self, camera: Entity) -> Option<&PickingFrame> {
+        self.views
+            .iter()
+            .find(|v| v.camera == camera)
+            .and_then(|v| v.latest.as_ref())
+    }
+
+    /// Polls the finished reads, and draws the ID passes if its time to. This should
+    /// be called once per frame after the renderables are drawn, so the transforms of
+    /// meshes are up to date.
+    pub fn advance(&mut self, renderables: &Renderable) {
+        for view in &mut self.views {
+            let finished = match view.pending {
+                Some((handle, _)) => self.video.readback(handle),
+                None => None,
+            };
+
+            if let Some(readback) = finished {
+                let (_, entities) = view.pending.take().unwrap();
+                view.latest = Some(PickingFrame::new(readback, entities));
+            }
+        }
+
+        self.frames += 1;
+        if self.frames % u64::from(self.params.interval.max(1)) != 0 {
+            return;
+        }
+
+        for i in 0..self.views.len() {
+            // Skips the views whose last read is still in flight.
+            if self.views[i].pending.is_some() {
+                continue;
+            }
+
+            if let Err(err) = self.draw(i, renderables) {
+                warn!("Failed to draw the ID pass of picking: {}", err);
+            }
+        }
+    }
+
+    fn draw(&mut self, index: usize, renderables: &Renderable) -> Result<()> {
+        let camera = match renderables.camera(self.views[index].camera) {
+            Some(v) => *v,
+            None => return Ok(()),
+        };
+
+        let (surface, dimensions) = self.prepare_target(index)?;
+
+        let mut visible = Vec::new();
+        cull(
+            &camera,
+            renderables.meshes(),
+            |v| self.video.mesh_aabb(v),
+            &mut visible,
+        );
+
+        let view_projection = camera.frustum().to_matrix() * camera.transform.view_matrix();
+        let mut entities = Vec::with_capacity(visible.len());
+        for mesh in visible.iter().take(MAX_PICKING_OBJECTS) {
+            let id = encode_picking_id(entities.len());
+            entities.push(mesh.ent);
+
+            let mut dc = DrawCall::new(self.shader, mesh.mesh);
+            dc.set_uniform_variable("u_MVPMatrix", view_projection * mesh.transform.matrix());
+            dc.set_uniform_variable("u_Id", math::Vector4::new(id.r, id.g, id.b, id.a));
+            self.video.draw(surface, dc);
+        }
+
+        let area = math::Aabb2::new(
+            math::Point2::new(0, 0),
+            math::Point2::new(dimensions.x, dimensions.y),
+        );
+
+        let handle = self.video.read_pixels(surface, area)?;
+        self.views[index].pending = Some((handle, entities));
+        Ok(())
+    }
+
+    // Prepares the target of ID pass with the size of window scaled by resolution.
+    fn prepare_target(&mut self, index: usize) -> Result<(SurfaceHandle, math::Vector2<u32>)> {
+        let window = self.window.dimensions();
+        let scale = self.params.resolution.max(0.01).min(1.0);
+        let dimensions = math::Vector2::new(
+            ((window.x as f32 * scale) as u32).max(1),
+            ((window.y as f32 * scale) as u32).max(1),
+        );
+
+        let recreate = match self.views[index].target {
+            Some(ref v) => v.dimensions != dimensions,
+            None => true,
+        };
+
+        if recreate {
+            if let Some(v) = self.views[index].target.take() {
+                v.delete(&self.video);
+            }
+
+            let mut params = RenderTextureParams::default();
+            params.format = RenderTextureFormat::RGBA8;
+            params.dimensions = dimensions;
+            let color = self.video.create_render_texture(params)?;
+
+            params.format = RenderTextureFormat::Depth16;
+            params.sampler = false;
+            let depth = self.video.create_render_texture(params)?;
+
+            // The background is cleared to the reserved id 0.
+            let mut params = SurfaceParams::default();
+            params.set_attachments(&[color], depth)?;
+            params.set_clear(math::Color::transparent(), 1.0, None);
+            let surface = self.video.create_surface(params)?;
+
+            self.views[index].target = Some(PickingTarget {
+                surface: surface,
+                color: color,
+                depth: depth,
+                dimensions: dimensions,
+            });
+        }
+
+        let target = self.views[index].target.as_ref().unwrap();
+        Ok((target.surface, target.dimensions))
+    }
+
+    fn release(&self, view: &PickingView) {
+        if let Some(ref v) = view.target {
+            v.delete(&self.video);
+        }
+
+        if let Some((handle, _)) = view.pending {
+            self.video.delete_readback(handle);
+        }
+    }
+}
+
+impl Drop for PickingService {
+    fn drop(&mut self) {
+        for view in &self.views {
+            self.release(view);
+        }
+
+        self.video.delete_shader(self.shader);
+    }
+}
@@ -0,0 +1,201 @@
+//! Instantiates a `RenderPipelineSetup` into video resources.
+
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use crayon::errors::*;
+use crayon::math;
+use crayon::video::assets::prelude::*;
+use crayon::video::prelude::*;
+
+use assets::{RenderPassKind, RenderPipelineSetup, RenderTargetSetup};
+
+impl_vertex!{
+    FullscreenVertex {
+        position => [Position; Float; 2; false],
+    }
+}
+
+/// The instantiated render pass.
+#[derive(Debug, Clone)]
+pub struct RenderPass {
+    /// The name of this pass.
+    pub name: String,
+    /// The kind of this pass.
+    pub kind: RenderPassKind,
+    /// The surface that this pass rendered into.
+    pub surface: SurfaceHandle,
+    /// The shader of `Fullscreen` pass.
+    pub shader: Option<ShaderHandle>,
+    /// The render textures bound to uniform variables.
+    pub inputs: Vec<(String, RenderTextureHandle)>,
+}
+
+impl RenderPass {
+    /// Binds the input textures of this pass into drawcall.
+    pub fn bind(&self, dc: &mut DrawCall) {
+        for &(ref name, texture) in &self.inputs {
+            dc.set_uniform_variable(name.as_str(), texture);
+        }
+    }
+}
+
+/// A `RenderPipeline` owns the surfaces and render targets described by the data-driven
+/// `RenderPipelineSetup`.
+///
+/// The camera that renders the scene should draw into the surfaces of `Scene` passes, and
+/// the `Fullscreen` passes are submitted in order with `RenderPipeline::submit`.
+pub struct RenderPipeline {
+    video: Arc<VideoSystemShared>,
+    passes: Vec<RenderPass>,
+    targets: HashMap<String, RenderTextureHandle>,
+    mesh: MeshHandle,
+}
+
+impl RenderPipeline {
+    /// Creates the video resources of pipeline. The `shaders` are used to resolve the
+    /// shader references by name.
+    pub fn new(
+        video: Arc<VideoSystemShared>,
+        setup: &RenderPipelineSetup,
+        dimensions: math::Vector2<u32>,
+        shaders: &HashMap<String, ShaderHandle>,
+    ) -> Result<Self> {
+        setup.validate()?;
+
+        let mesh = create_fullscreen_mesh(&video)?;
+
+        let mut pipeline = RenderPipeline {
+            video: video,
+            passes: Vec::new(),
+            targets: HashMap::new(),
+            mesh: mesh,
+        };
+
+        for v in &setup.passes {
+            let mut colors = Vec::new();
+            for target in &v.colors {
+                colors.push(pipeline.create_target(target, dimensions)?);
+            }
+
+            let depth = match v.depth {
+                Some(ref target) => Some(pipeline.create_target(target, dimensions)?),
+                None => None,
+            };
+
+            let mut params = SurfaceParams::default();
+            params.set_attachments(&colors, depth)?;
+            params.set_clear(
+                v.clear_color.map(|c| math::Color::new(c[0], c[1], c[2], c[3])),
+                v.clear_depth,
+                None,
+            );
+
+            let surface = pipeline.video.create_surface(params)?;
+
+            let shader = match v.shader {
+                Some(ref name) => Some(
+                    *shaders
+                        .get(name)
+                        .ok_or_else(|| format_err!("Shader {} is not registered.", name))?,
+                ),
+                None => None,
+            };
+
+            let mut inputs = Vec::new();
+            for &(ref uniform, ref target) in &v.inputs {
+                inputs.push((uniform.clone(), pipeline.targets[target]));
+            }
+
+            pipeline.passes.push(RenderPass {
+                name: v.name.clone(),
+                kind: v.kind,
+                surface: surface,
+                shader: shader,
+                inputs: inputs,
+            });
+        }
+
+        Ok(pipeline)
+    }
+
+    /// Gets the pass by name.
+    pub fn pass(&self, name: &str) -> Option<&RenderPass> {
+        self.passes.iter().find(|v| v.name == name)
+    }
+
+    /// Gets all the passes in order.
+    #[inline]
+    pub fn passes(&self) -> &[RenderPass] {
+        &self.passes
+    }
+
+    /// Gets the render texture of target by name.
+    #[inline]
+    pub fn target(&self, name: &str) -> Option<RenderTextureHandle> {
+        self.targets.get(name).cloned()
+    }
+
+    /// Submits all the `Fullscreen` passes in order.
+    pub fn submit(&self) -> Result<()> {
+        for v in &self.passes {
+            if let (RenderPassKind::Fullscreen, Some(shader)) = (v.kind, v.shader) {
+                let mut dc = DrawCall::new(shader, self.mesh);
+                v.bind(&mut dc);
+                self.video.draw(v.surface, dc);
+            }
+        }
+
+        Ok(())
+    }
+
+    fn create_target(
+        &mut self,
+        setup: &RenderTargetSetup,
+        dimensions: math::Vector2<u32>,
+    ) -> Result<RenderTextureHandle> {
+        let mut params = RenderTextureParams::default();
+        params.format = setup.format;
+        params.dimensions = setup.dimensions(dimensions);
+
+        let handle = self.video.create_render_texture(params)?;
+        self.targets.insert(setup.name.clone(), handle);
+        Ok(handle)
+    }
+}
+
+impl Drop for RenderPipeline {
+    fn drop(&mut self) {
+        for v in &self.passes {
+            self.video.delete_surface(v.surface);
+        }
+
+        for (_, &v) in &self.targets {
+            self.video.delete_render_texture(v);
+        }
+
+        self.video.delete_mesh(self.mesh);
+    }
+}
+
+/// Creates a triangle that covers the whole screen in normalized device coordinates.
+pub(crate) fn create_fullscreen_mesh(video: &VideoSystemShared) -> Result<MeshHandle> {
+    let verts: [FullscreenVertex; 3] = [
+        FullscreenVertex::new([-1.0, -1.0]),
+        FullscreenVertex::new([3.0, -1.0]),
+        FullscreenVertex::new([-1.0, 3.0]),
+    ];
+    let idxes: [u16; 3] = [0, 1, 2];
+
+    let mut params = MeshParams::default();
+    params.num_verts = 3;
+    params.num_idxes = 3;
+    params.layout = FullscreenVertex::layout();
+
+    let data = MeshData {
+        vptr: FullscreenVertex::encode(&verts[..]).into(),
+        iptr: IndexFormat::encode(&idxes).into(),
+    };
+
+    Ok(video.create_mesh(params, Some(data))?)
+}
@@ -0,0 +1,293 @@
+//! Irradiance probes which supply the ambient lighting of dynamic objects in baked scenes.
+
+use crayon::math::{self, InnerSpace};
+use crayon::video::assets::shader::{UniformVariableLayoutBuilder, UniformVariableType};
+use crayon::video::batch::DrawCall;
+
+// The 27 floats of coefficients are packed tightly into 7 `vec4`s.
+const SH_UNIFORMS: [&str; 7] = [
+    "u_SH[0]", "u_SH[1]", "u_SH[2]", "u_SH[3]", "u_SH[4]", "u_SH[5]", "u_SH[6]",
+];
+
+/// The radiance around a point projected onto the first three bands of spherical
+/// harmonics, in the order of `L00, L1-1, L10, L11, L2-2, L2-1, L20, L21, L22`.
+///
+/// Shaders evaluate the irradiance with the normal of fragment in world space, and
+/// divide it by `PI`, so a constant radiance results in the same ambient color.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ShIrradiance {
+    pub coefficients: [math::Vector3<f32>; 9],
+}
+
+impl Default for ShIrradiance {
+    fn default() -> Self {
+        ShIrradiance {
+            coefficients: [math::Vector3::new(0.0, 0.0, 0.0); 9],
+        }
+    }
+}
+
+impl ShIrradiance {
+    /// Creates the spherical harmonics of a constant radiance from all directions.
+    pub fn from_color(color: math::Color<f32>) -> Self {
+        // The projection of constant radiance onto `Y00` is `color * 2 * sqrt(PI)`.
+        let mut sh = ShIrradiance::default();
+        sh.coefficients[0] = math::Vector3::new(color.r, color.g, color.b) * 3.544_908;
+        sh
+    }
+
+    /// Accumulates the radiance sample from `dir` with the solid angle `weight`.
+    pub fn add_sample(&mut self, dir: math::Vector3<f32>, color: math::Color<f32>, weight: f32) {
+        let d = dir.normalize();
+        let c = math::Vector3::new(color.r, color.g, color.b) * weight;
+
+        let basis = [
+            0.282_095,
+            0.488_603 * d.y,
+            0.488_603 * d.z,
+            0.488_603 * d.x,
+            1.092_548 * d.x * d.y,
+            1.092_548 * d.y * d.z,
+            0.315_392 * (3.0 * d.z * d.z - 1.0),
+            1.092_548 * d.x * d.z,
+            0.546_274 * (d.x * d.x - d.y * d.y),
+        ];
+
+        for (v, y) in self.coefficients.iter_mut().zip(basis.iter()) {
+            *v += c * *y;
+        }
+    }
+
+    /// Evaluates the irradiance divided by `PI` at surface with normal `n`. This is
+    /// the same equations used by shaders.
+    pub fn evaluate(&self, n: math::Vector3<f32>) -> math::Color<f32> {
+        let n = n.normalize();
+        let l = &self.coefficients;
+        let (c1, c2, c3, c4, c5) = (0.429_043, 0.511_664, 0.743_125, 0.886_227, 0.247_708);
+
+        let v = l[8] * (c1 * (n.x * n.x - n.y * n.y))
+            + l[6] * (c3 * n.z * n.z)
+            + l[0] * c4
+            - l[6] * c5
+            + (l[4] * (n.x * n.y) + l[7] * (n.x * n.z) + l[5] * (n.y * n.z)) * (2.0 * c1)
+            + (l[3] * n.x + l[1] * n.y + l[2] * n.z) * (2.0 * c2);
+
+        let v = v / ::std::f32::consts::PI;
+        math::Color::new(v.x.max(0.0), v.y.max(0.0), v.z.max(0.0), 1.0)
+    }
+
+    /// Linear interpolation between two spherical harmonics.
+    pub fn lerp(&self, rhs: &ShIrradiance, t: f32) -> ShIrradiance {
+        let mut sh = *self;
+        for (v, r) in sh.coefficients.iter_mut().zip(rhs.coefficients.iter()) {
+            *v += (*r - *v) * t;
+        }
+
+        sh
+    }
+
+    /// Declares the `u_SH` uniform array, which has the coefficients packed into 7
+    /// `vec4`s.
+    pub fn declare(mut builder: UniformVariableLayoutBuilder) -> UniformVariableLayoutBuilder {
+        for v in &SH_UNIFORMS {
+            builder = builder.with(*v, UniformVariableType::Vector4f);
+        }
+
+        builder
+    }
+
+    /// Packs the coefficients tightly, e.g. the first `vec4` holds `L00` and the red
+    /// channel of `L1-1`.
+    pub fn pack(&self) -> [math::Vector4<f32>; 7] {
+        let mut floats = [0.0; 28];
+        for (i, v) in self.coefficients.iter().enumerate() {
+            floats[i * 3] = v.x;
+            floats[i * 3 + 1] = v.y;
+            floats[i * 3 + 2] = v.z;
+        }
+
+        let mut packed = [math::Vector4::new(0.0, 0.0, 0.0, 0.0); 7];
+        for (i, v) in packed.iter_mut().enumerate() {
+            let f = &floats[i * 4..];
+            *v = math::Vector4::new(f[0], f[1], f[2], f[3]);
+        }
+
+        packed
+    }
+
+    /// Binds the packed coefficients into drawcall.
+    pub fn bind(&self, dc: &mut DrawCall) {
+        for (name, v) in SH_UNIFORMS.iter().zip(self.pack().iter()) {
+            dc.set_uniform_variable(*name, *v);
+        }
+    }
+}
+
+/// A regular 3D grid of irradiance probes. Objects interpolate the nearest eight
+/// probes with their positions, positions outside the grid are clamped to the bounds.
+#[derive(Debug, Clone)]
+pub struct ProbeGrid {
+    origin: math::Vector3<f32>,
+    spacing: math::Vector3<f32>,
+    dimensions: math::Vector3<u32>,
+    probes: Vec<ShIrradiance>,
+}
+
+impl ProbeGrid {
+    /// Creates a new grid with `dimensions` probes starting at `origin`, the probes
+    /// are `spacing` apart along each axis. All the probes are black initially.
+    pub fn new<T1, T2, T3>(origin: T1, spacing: T2, dimensions: T3) -> Self
+    where
+        T1: Into<math::Vector3<f32>>,
+        T2: Into<math::Vector3<f32>>,
+        T3: Into<math::Vector3<u32>>,
+    {
+        let spacing = spacing.into();
+        let dimensions = dimensions.into();
+        let dimensions = math::Vector3::new(
+            dimensions.x.max(1),
+            dimensions.y.max(1),
+            dimensions.z.max(1),
+        );
+
+        let len = (dimensions.x * dimensions.y * dimensions.z) as usize;
+        ProbeGrid {
+            origin: origin.into(),
+            spacing: math::Vector3::new(
+                spacing.x.max(::std::f32::EPSILON),
+                spacing.y.max(::std::f32::EPSILON),
+                spacing.z.max(::std::f32::EPSILON),
+            ),
+            dimensions: dimensions,
+            probes: vec![ShIrradiance::default(); len],
+        }
+    }
+
+    /// Gets the number of probes along each axis.
+    #[inline]
+    pub fn dimensions(&self) -> math::Vector3<u32> {
+        self.dimensions
+    }
+
+    /// Gets the number of probes.
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.probes.len()
+    }
+
+    /// Checks if the grid has no probes, which would never happen actually.
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.probes.is_empty()
+    }
+
+    /// Gets the index of probe at grid coordinates.
+    #[inline]
+    pub fn index(&self, x: u32, y: u32, z: u32) -> Option<usize> {
+        let d = self.dimensions;
+        if x < d.x && y < d.y && z < d.z {
+            Some((x + y * d.x + z * d.x * d.y) as usize)
+        } else {
+            None
+        }
+    }
+
+    /// Gets the position of probe in world space.
+    pub fn position(&self, index: usize) -> Option<math::Vector3<f32>> {
+        if index >= self.probes.len() {
+            return None;
+        }
+
+        let d = self.dimensions;
+        let i = index as u32;
+        let (x, y, z) = (i % d.x, (i / d.x) % d.y, i / (d.x * d.y));
+        Some(
+            self.origin + math::Vector3::new(
+                x as f32 * self.spacing.x,
+                y as f32 * self.spacing.y,
+                z as f32 * self.spacing.z,
+            ),
+        )
+    }
+
+    /// Gets the spherical harmonics of probe.
+    #[inline]
+    pub fn probe(&self, index: usize) -> Option<&ShIrradiance> {
+        self.probes.get(index)
+    }
+
+    /// Updates the spherical harmonics of probe, e.g. with the coefficients baked by
+    /// an external tool.
+    #[inline]
+    pub fn set_probe(&mut self, index: usize, sh: ShIrradiance) {
+        if let Some(v) = self.probes.get_mut(index) {
+            *v = sh;
+        }
+    }
+
+    /// Bakes the probe at `index` by sampling the radiance in `samples` directions
+    /// distributed uniformly over the sphere. The `radiance` closure returns the
+    /// incoming light at the probe position from the direction.
+    pub fn bake_probe<F>(&mut self, index: usize, samples: usize, mut radiance: F)
+    where
+        F: FnMut(math::Vector3<f32>, math::Vector3<f32>) -> math::Color<f32>,
+    {
+        let position = match self.position(index) {
+            Some(v) => v,
+            None => return,
+        };
+
+        let samples = samples.max(1);
+        let weight = 4.0 * ::std::f32::consts::PI / samples as f32;
+        let golden = ::std::f32::consts::PI * (3.0 - 5.0f32.sqrt());
+
+        let mut sh = ShIrradiance::default();
+        for i in 0..samples {
+            // Fibonacci sphere.
+            let y = 1.0 - (i as f32 + 0.5) / samples as f32 * 2.0;
+            let r = (1.0 - y * y).max(0.0).sqrt();
+            let phi = golden * i as f32;
+            let dir = math::Vector3::new(phi.cos() * r, y, phi.sin() * r);
+            sh.add_sample(dir, radiance(position, dir), weight);
+        }
+
+        self.probes[index] = sh;
+    }
+
+    /// Bakes all the probes in grid. It could be expensive with complex `radiance`
+    /// closure, use `bake_probe` to spread the updates over frames instead.
+    pub fn bake<F>(&mut self, samples: usize, mut radiance: F)
+    where
+        F: FnMut(math::Vector3<f32>, math::Vector3<f32>) -> math::Color<f32>,
+    {
+        for i in 0..self.probes.len() {
+            self.bake_probe(i, samples, &mut radiance);
+        }
+    }
+
+    /// Interpolates the spherical harmonics at `position` trilinearly.
+    pub fn sample(&self, position: math::Vector3<f32>) -> ShIrradiance {
+        let d = self.dimensions;
+        let local = position - self.origin;
+
+        let axis = |v: f32, spacing: f32, n: u32| {
+            let v = (v / spacing).max(0.0).min((n - 1) as f32);
+            let i = (v.floor() as u32).min(n.saturating_sub(2));
+            (i, (i + 1).min(n - 1), v - i as f32)
+        };
+
+        let (x0, x1, tx) = axis(local.x, self.spacing.x, d.x);
+        let (y0, y1, ty) = axis(local.y, self.spacing.y, d.y);
+        let (z0, z1, tz) = axis(local.z, self.spacing.z, d.z);
+
+        let at = |x, y, z| self.probes[self.index(x, y, z).unwrap()];
+        let y0z0 = at(x0, y0, z0).lerp(&at(x1, y0, z0), tx);
+        let y1z0 = at(x0, y1, z0).lerp(&at(x1, y1, z0), tx);
+        let y0z1 = at(x0, y0, z1).lerp(&at(x1, y0, z1), tx);
+        let y1z1 = at(x0, y1, z1).lerp(&at(x1, y1, z1), tx);
+
+        let z0 = y0z0.lerp(&y1z0, ty);
+        let z1 = y0z1.lerp(&y1z1, ty);
+        z0.lerp(&z1, tz)
+    }
+}
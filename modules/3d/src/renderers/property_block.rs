@@ -0,0 +1,116 @@
+//! Per-object overrides of the shared materials.
+
+use crayon::utils::HashValue;
+use crayon::video::assets::shader::{UniformVariable, UniformVariableLayout};
+use crayon::video::batch::DrawCall;
+
+/// The maximum number of properties in a `MaterialPropertyBlock`.
+pub const MAX_MATERIAL_PROPERTIES: usize = 8;
+
+/// `MaterialPropertyBlock` is a small set of uniform variables which are layered over
+/// the material when drawing an object, e.g. thousands of objects could share the same
+/// material while varying their colors.
+#[derive(Debug, Clone, Copy)]
+pub struct MaterialPropertyBlock {
+    properties: [(HashValue<str>, UniformVariable); MAX_MATERIAL_PROPERTIES],
+    len: usize,
+}
+
+impl Default for MaterialPropertyBlock {
+    fn default() -> Self {
+        let nil = (HashValue::zero(), UniformVariable::I32(0));
+        MaterialPropertyBlock {
+            properties: [nil; MAX_MATERIAL_PROPERTIES],
+            len: 0,
+        }
+    }
+}
+
+impl MaterialPropertyBlock {
+    /// Creates a new and empty property block.
+    #[inline]
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    /// Overrides the named uniform variable.
+    ///
+    /// # Panics
+    ///
+    /// Panics if there are already `MAX_MATERIAL_PROPERTIES` different properties.
+    pub fn set<F, V>(&mut self, field: F, variable: V)
+    where
+        F: Into<HashValue<str>>,
+        V: Into<UniformVariable>,
+    {
+        let field = field.into();
+        let variable = variable.into();
+
+        for i in 0..self.len {
+            if self.properties[i].0 == field {
+                self.properties[i].1 = variable;
+                return;
+            }
+        }
+
+        assert!(self.len < MAX_MATERIAL_PROPERTIES);
+        self.properties[self.len] = (field, variable);
+        self.len += 1;
+    }
+
+    /// Gets the overridden uniform variable.
+    pub fn get<F>(&self, field: F) -> Option<UniformVariable>
+    where
+        F: Into<HashValue<str>>,
+    {
+        let field = field.into();
+        self.iter().find(|v| v.0 == field).map(|v| v.1)
+    }
+
+    /// Removes the overridden uniform variable.
+    pub fn remove<F>(&mut self, field: F)
+    where
+        F: Into<HashValue<str>>,
+    {
+        let field = field.into();
+        let index = self.iter().position(|v| v.0 == field);
+        if let Some(i) = index {
+            self.properties[i] = self.properties[self.len - 1];
+            self.len -= 1;
+        }
+    }
+
+    #[inline]
+    pub fn clear(&mut self) {
+        self.len = 0;
+    }
+
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Returns an iterator over the overridden properties.
+    #[inline]
+    pub fn iter(&self) -> ::std::slice::Iter<(HashValue<str>, UniformVariable)> {
+        self.properties[0..self.len].iter()
+    }
+
+    /// Applies the properties into drawcall. The properties which are undeclared in
+    /// `layout`, or mismatched with the declarations are skipped.
+    pub fn apply(&self, layout: &UniformVariableLayout, dc: &mut DrawCall) {
+        for &(field, variable) in self.iter() {
+            match layout.variable_type(field) {
+                Some(v) if v.is_compatible(variable.variable_type()) => {
+                    dc.set_uniform_variable(field, variable);
+                }
+                _ => {}
+            }
+        }
+    }
+}
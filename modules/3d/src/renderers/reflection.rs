@@ -0,0 +1,118 @@
+//! Planar reflections, which are usually used to render water surfaces.
+//!
+//! The scene above the plane is drawn by a mirrored camera into the reflection
+//! texture, and the scene below the plane is drawn into the refraction texture. Both
+//! passes clip the objects on the other side of plane. The materials with
+//! `ShaderKeywords::WATER` sample these textures as `u_ReflectionTexture` and
+//! `u_RefractionTexture`, and distort them with `u_WaterParams`.
+
+use std::time::Duration;
+
+use crayon::math::{self, InnerSpace};
+
+use scene::Transform;
+
+/// The ripples which distort the reflection and refraction textures.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct WaterDistortion {
+    /// The offset of texture coordinates at the crest of ripples.
+    pub strength: f32,
+    /// The number of ripples per unit in world space.
+    pub frequency: f32,
+    /// The speed of ripples in radians per second.
+    pub speed: f32,
+    /// The power of fresnel term, larger values show less reflection when looking down
+    /// onto the surface.
+    pub fresnel: f32,
+}
+
+impl Default for WaterDistortion {
+    fn default() -> Self {
+        WaterDistortion {
+            strength: 0.02,
+            frequency: 2.0,
+            speed: 1.0,
+            fresnel: 2.0,
+        }
+    }
+}
+
+/// The settings of planar reflection.
+#[derive(Debug, Clone, Copy)]
+pub struct PlanarReflection {
+    /// A point on the plane in world space.
+    pub origin: math::Vector3<f32>,
+    /// The normal of plane, which points to the reflected side.
+    pub normal: math::Vector3<f32>,
+    /// The resolution of reflection and refraction textures relative to the window.
+    pub resolution: f32,
+    /// The distance which pushes the clip planes across the plane, so there are no
+    /// gaps where objects intersect the surface.
+    pub clip_offset: f32,
+    /// The distortion settings.
+    pub distortion: WaterDistortion,
+    phase: f32,
+}
+
+impl PlanarReflection {
+    /// Creates a new planar reflection with the plane.
+    pub fn new<T1, T2>(origin: T1, normal: T2) -> Self
+    where
+        T1: Into<math::Vector3<f32>>,
+        T2: Into<math::Vector3<f32>>,
+    {
+        PlanarReflection {
+            origin: origin.into(),
+            normal: normal.into(),
+            resolution: 0.5,
+            clip_offset: 0.05,
+            distortion: WaterDistortion::default(),
+            phase: 0.0,
+        }
+    }
+
+    /// Advances the ripples, which should be called once per frame.
+    pub fn advance(&mut self, delta: Duration) {
+        let delta = delta.as_secs() as f32 + delta.subsec_nanos() as f32 * 1e-9;
+        let tau = 2.0 * ::std::f32::consts::PI;
+        self.phase = (self.phase + delta * self.distortion.speed) % tau;
+    }
+
+    /// Gets the transform of mirrored camera.
+    ///
+    /// The mirror of a transform is a reflection which can't be represented by rotation,
+    /// so the right direction of mirrored camera is flipped. The reflection texture
+    /// should be sampled with `(1 - u, v)` accordingly.
+    pub fn mirror(&self, transform: &Transform) -> Transform {
+        let n = self.normal.normalize();
+        let reflect = |v: math::Vector3<f32>| v - n * (2.0 * v.dot(n));
+
+        let mut mirror = *transform;
+        mirror.position = self.origin + reflect(transform.position - self.origin);
+
+        let forward = reflect(transform.forward());
+        let target = mirror.position + forward;
+        mirror.look_at(target, reflect(transform.up()));
+        mirror
+    }
+
+    /// Gets the clip plane in view space, the fragments which have negative distances
+    /// to it are discarded. The plane keeps the reflected side if `above` is true,
+    /// otherwise the other side.
+    pub fn clip_plane(&self, view_matrix: math::Matrix4<f32>, above: bool) -> math::Vector4<f32> {
+        let n = self.normal.normalize();
+        let n = if above { n } else { -n };
+
+        let origin = self.origin - n * self.clip_offset;
+        let n = (view_matrix * n.extend(0.0)).truncate();
+        let origin = (view_matrix * origin.extend(1.0)).truncate();
+        n.extend(-n.dot(origin))
+    }
+
+    /// Encodes the distortion into `(strength, frequency, phase, fresnel)`, which is
+    /// passed to shaders as `u_WaterParams` uniform.
+    pub fn encode(&self) -> math::Vector4<f32> {
+        let v = self.distortion;
+        math::Vector4::new(v.strength, v.frequency, self.phase, v.fresnel)
+    }
+}
@@ -0,0 +1,152 @@
+//! Dynamic resolution, which renders the scene into an intermediate target with a
+//! scale factor, and then upscales it to the destination surface.
+
+use std::time::Duration;
+
+/// The filter used to upscale the intermediate target to destination.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum UpscaleFilter {
+    /// Plain bilinear filtering.
+    Bilinear,
+    /// Bilinear filtering followed by a contrast adaptive sharpening, which restores
+    /// some details lost in the lower resolution. The payload is the strength of
+    /// sharpening ranging from 0.0 to 1.0.
+    Sharpen(f32),
+}
+
+/// How the scale factor is decided.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum RenderScaleMode {
+    /// Always renders at the specified scale.
+    Fixed(f32),
+    /// Adjusts the scale between `min` and `max` automatically to keep the GPU time of
+    /// camera within the `budget`. The GPU time is measured with timer queries, so it
+    /// requires `VideoParams::gpu_timings` to be enabled.
+    Dynamic {
+        budget: Duration,
+        min: f32,
+        max: f32,
+    },
+}
+
+/// The render scale settings of a camera.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RenderScale {
+    pub mode: RenderScaleMode,
+    pub filter: UpscaleFilter,
+}
+
+impl Default for RenderScale {
+    fn default() -> Self {
+        RenderScale {
+            mode: RenderScaleMode::Fixed(1.0),
+            filter: UpscaleFilter::Bilinear,
+        }
+    }
+}
+
+impl RenderScale {
+    /// Renders at a fixed scale.
+    pub fn fixed(scale: f32, filter: UpscaleFilter) -> Self {
+        RenderScale {
+            mode: RenderScaleMode::Fixed(scale),
+            filter: filter,
+        }
+    }
+
+    /// Adjusts the scale automatically with GPU time budget in milliseconds.
+    pub fn dynamic(budget: u32, min: f32, max: f32, filter: UpscaleFilter) -> Self {
+        RenderScale {
+            mode: RenderScaleMode::Dynamic {
+                budget: Duration::from_millis(u64::from(budget)),
+                min: min,
+                max: max,
+            },
+            filter: filter,
+        }
+    }
+
+    /// Gets the largest scale this setting could reach, which decides the size of
+    /// intermediate target.
+    pub fn max_scale(&self) -> f32 {
+        let v = match self.mode {
+            RenderScaleMode::Fixed(v) => v,
+            RenderScaleMode::Dynamic { min, max, .. } => min.max(max),
+        };
+
+        clamp_scale(v)
+    }
+
+    /// Returns true if the scene could be drawn into destination directly.
+    pub fn is_native(&self) -> bool {
+        match self.mode {
+            RenderScaleMode::Fixed(v) => (clamp_scale(v) - 1.0).abs() < ::std::f32::EPSILON,
+            RenderScaleMode::Dynamic { .. } => false,
+        }
+    }
+}
+
+#[inline]
+fn clamp_scale(v: f32) -> f32 {
+    v.max(0.1).min(2.0)
+}
+
+/// The controller of dynamic resolution, which adjusts the scale with the GPU time
+/// feedbacks of previous frames. Each camera has its own controller.
+#[derive(Debug, Clone, Copy)]
+pub struct RenderScaleController {
+    scale: f32,
+}
+
+impl Default for RenderScaleController {
+    fn default() -> Self {
+        RenderScaleController { scale: 1.0 }
+    }
+}
+
+impl RenderScaleController {
+    // The scale steps taken when the frame is over or well under budget.
+    const DECREASE_STEP: f32 = 0.1;
+    const INCREASE_STEP: f32 = 0.05;
+    // The ratio of budget below which the scale starts to increase.
+    const HEADROOM: f32 = 0.85;
+
+    /// Gets the current scale factor.
+    #[inline]
+    pub fn scale(&self) -> f32 {
+        self.scale
+    }
+
+    /// Advances with the GPU time of last frame, and returns the scale factor which
+    /// should be used for the next frame. The scale is kept if the GPU time is not
+    /// available.
+    pub fn advance(&mut self, setup: &RenderScale, gpu_time: Option<Duration>) -> f32 {
+        self.scale = match setup.mode {
+            RenderScaleMode::Fixed(v) => clamp_scale(v),
+            RenderScaleMode::Dynamic { budget, min, max } => {
+                let (min, max) = (clamp_scale(min.min(max)), clamp_scale(min.max(max)));
+                let ratio = gpu_time
+                    .map(|v| duration_to_secs(v) / duration_to_secs(budget).max(1e-6))
+                    .unwrap_or(Self::HEADROOM);
+
+                let v = if ratio > 1.0 {
+                    // Decreases faster if we are far away from the budget.
+                    self.scale - Self::DECREASE_STEP * ratio.min(2.0)
+                } else if ratio < Self::HEADROOM {
+                    self.scale + Self::INCREASE_STEP
+                } else {
+                    self.scale
+                };
+
+                v.max(min).min(max)
+            }
+        };
+
+        self.scale
+    }
+}
+
+#[inline]
+fn duration_to_secs(v: Duration) -> f32 {
+    v.as_secs() as f32 + v.subsec_nanos() as f32 * 1e-9
+}
@@ -0,0 +1,296 @@
+//! Cascaded shadow maps of the directional light. Only the first enabled directional
+//! light casts shadows, if it's a `shadow_caster`.
+//!
+//! The view frustum of camera is split into slices along the view direction, and every
+//! slice is covered by an orthographic projection from the light. The depths of shadow
+//! casters are drawn into the tiles of a shared atlas, and sampled by the receivers with
+//! `ShaderKeywords::SHADOWS`.
+//!
+//! The settings could be picked from a `ShadowQuality` tier, which scales from mobile
+//! to desktop targets.
+
+use crayon::math::{self, InnerSpace, QuaternionExt, SquareMatrix};
+
+use super::{Camera, Lit};
+use scene::Transform;
+
+/// The maximum number of cascades.
+pub const MAX_SHADOW_CASCADES: usize = 4;
+
+/// The quality tiers of shadows.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ShadowQuality {
+    /// No shadows are drawn.
+    Disabled,
+    /// Two cascades with low resolution and hard edges.
+    Low,
+    /// Three cascades with soft edges.
+    Medium,
+    /// Four cascades with high resolution and soft edges.
+    High,
+}
+
+impl ShadowQuality {
+    /// Gets the settings of this tier.
+    pub fn settings(&self) -> ShadowSettings {
+        let (cascades, resolution, distance, soft) = match *self {
+            ShadowQuality::Disabled => (0, 512, 0.0, false),
+            ShadowQuality::Low => (2, 512, 30.0, false),
+            ShadowQuality::Medium => (3, 1024, 60.0, true),
+            ShadowQuality::High => (4, 2048, 100.0, true),
+        };
+
+        ShadowSettings {
+            cascades: cascades,
+            resolution: resolution,
+            distance: distance,
+            split_lambda: 0.75,
+            stabilize: true,
+            depth_bias: 0.002,
+            soft: soft,
+        }
+    }
+}
+
+/// The settings of shadows.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ShadowSettings {
+    /// The number of cascades of directional light, which is clamped to
+    /// `MAX_SHADOW_CASCADES`. Shadows are disabled with 0.
+    pub cascades: usize,
+    /// The resolution of each cascade, which could be overridden by lights.
+    pub resolution: u32,
+    /// The maximum distance to the camera where shadows are drawn.
+    pub distance: f32,
+    /// The blend between uniform (0) and logarithmic (1) splits. The logarithmic splits
+    /// spend more texels close to the camera.
+    pub split_lambda: f32,
+    /// Keeps the size of cascades constant and snaps them to texels, so the edges of
+    /// shadows don't shimmer while the camera moves or rotates. It wastes some texels.
+    pub stabilize: bool,
+    /// The offset of depths which avoids self-shadowing artifacts.
+    pub depth_bias: f32,
+    /// Filters the edges of shadows with multiple samples.
+    pub soft: bool,
+}
+
+impl Default for ShadowSettings {
+    fn default() -> Self {
+        ShadowQuality::Medium.settings()
+    }
+}
+
+/// A cascade which covers a slice of view frustum.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ShadowCascade {
+    /// The view matrix of light.
+    pub view_matrix: math::Matrix4<f32>,
+    /// The orthographic projection of light.
+    pub projection_matrix: math::Matrix4<f32>,
+    /// The distance along the view direction of camera where this cascade ends.
+    pub split: f32,
+}
+
+impl ShadowCascade {
+    /// Gets the matrix from world space into the clip space of light.
+    #[inline]
+    pub fn view_projection(&self) -> math::Matrix4<f32> {
+        self.projection_matrix * self.view_matrix
+    }
+
+    /// Gets the matrix from world space into the tile of atlas with index, where the
+    /// texture coordinates and depths are in range `[0, 1]`. The atlas is split into
+    /// 2x2 tiles if there are more than one cascade.
+    pub fn atlas_matrix(&self, index: usize, cascades: usize) -> math::Matrix4<f32> {
+        let (scale, offset) = atlas_tile(index, cascades);
+        let bias = math::Matrix4::new(
+            0.5 * scale,
+            0.0,
+            0.0,
+            0.0,
+            0.0,
+            0.5 * scale,
+            0.0,
+            0.0,
+            0.0,
+            0.0,
+            0.5,
+            0.0,
+            offset.x + 0.5 * scale,
+            offset.y + 0.5 * scale,
+            0.5,
+            1.0,
+        );
+
+        bias * self.view_projection()
+    }
+}
+
+/// Gets the scale and offset of the tile with index in atlas.
+pub fn atlas_tile(index: usize, cascades: usize) -> (f32, math::Vector2<f32>) {
+    if cascades <= 1 {
+        (1.0, math::Vector2::new(0.0, 0.0))
+    } else {
+        let (x, y) = (index % 2, index / 2);
+        (0.5, math::Vector2::new(x as f32 * 0.5, y as f32 * 0.5))
+    }
+}
+
+/// Splits the range between `near` and `far` into `count` slices, and returns the
+/// `count + 1` distances of their boundaries.
+pub fn split_distances(near: f32, far: f32, count: usize, lambda: f32) -> Vec<f32> {
+    let near = near.max(1e-4);
+    let far = far.max(near);
+    let lambda = lambda.max(0.0).min(1.0);
+
+    (0..(count + 1))
+        .map(|i| {
+            let t = i as f32 / count.max(1) as f32;
+            let log = near * (far / near).powf(t);
+            let uniform = near + (far - near) * t;
+            uniform + (log - uniform) * lambda
+        })
+        .collect()
+}
+
+/// Fits the cascades of directional light to the view frustum of camera. Returns an
+/// empty set if the light casts no shadows.
+pub fn fit_cascades(camera: &Camera, lit: &Lit, settings: &ShadowSettings) -> Vec<ShadowCascade> {
+    let count = settings.cascades.min(MAX_SHADOW_CASCADES);
+    if count == 0 || !lit.enable || !lit.shadow_caster {
+        return Vec::new();
+    }
+
+    let (near, far) = (camera.near_clip_plane(), camera.far_clip_plane());
+    let distance = settings.distance.min(lit.shadow_distance).min(far);
+    if distance <= near {
+        return Vec::new();
+    }
+
+    let resolution = lit.shadow_resolution.unwrap_or(settings.resolution).max(1);
+
+    // The corners of view frustum in world space.
+    let view_proj = camera.frustum().to_matrix() * camera.transform.view_matrix();
+    let inv = view_proj.invert().unwrap_or_else(math::Matrix4::identity);
+    let unproject = |x: f32, y: f32, z: f32| {
+        let v = inv * math::Vector4::new(x, y, z, 1.0);
+        v.truncate() / v.w
+    };
+
+    let rays: Vec<_> = [(-1.0, -1.0), (1.0, -1.0), (-1.0, 1.0), (1.0, 1.0)]
+        .iter()
+        .map(|&(x, y)| {
+            let n = unproject(x, y, -1.0);
+            (n, unproject(x, y, 1.0) - n)
+        })
+        .collect();
+
+    let dir = lit.transform.forward().normalize();
+    let up = if dir.y.abs() > 0.99 {
+        math::Vector3::unit_z()
+    } else {
+        math::Vector3::unit_y()
+    };
+
+    let mut transform = Transform::default();
+    transform.rotation = math::Quaternion::look_rotation(dir, up);
+    let view_matrix = transform.view_matrix();
+
+    let splits = split_distances(near, distance, count, settings.split_lambda);
+    splits
+        .windows(2)
+        .map(|v| {
+            let mut corners = Vec::with_capacity(8);
+            for &d in v {
+                let t = (d - near) / (far - near);
+                corners.extend(rays.iter().map(|&(o, ray)| o + ray * t));
+            }
+
+            let (min, max) = if settings.stabilize {
+                stable_bounds(&corners, view_matrix, resolution)
+            } else {
+                bounds(&corners, view_matrix)
+            };
+
+            // Pulls the near plane back to the light, so the casters between the light
+            // and the slice are drawn.
+            let min = math::Vector3::new(min.x, min.y, min.z - distance);
+
+            ShadowCascade {
+                view_matrix: view_matrix,
+                projection_matrix: ortho(min, max),
+                split: v[1],
+            }
+        })
+        .collect()
+}
+
+// The bounds of points in light space.
+fn bounds(
+    corners: &[math::Vector3<f32>],
+    view_matrix: math::Matrix4<f32>,
+) -> (math::Vector3<f32>, math::Vector3<f32>) {
+    let mut min = math::Vector3::new(::std::f32::MAX, ::std::f32::MAX, ::std::f32::MAX);
+    let mut max = -min;
+
+    for &v in corners {
+        let v = (view_matrix * v.extend(1.0)).truncate();
+        for i in 0..3 {
+            min[i] = min[i].min(v[i]);
+            max[i] = max[i].max(v[i]);
+        }
+    }
+
+    (min, max)
+}
+
+// The bounds of the sphere around points in light space, which keeps the same size
+// when the camera rotates. The center is snapped to texels.
+fn stable_bounds(
+    corners: &[math::Vector3<f32>],
+    view_matrix: math::Matrix4<f32>,
+    resolution: u32,
+) -> (math::Vector3<f32>, math::Vector3<f32>) {
+    let sum = corners
+        .iter()
+        .fold(math::Vector3::new(0.0, 0.0, 0.0), |acc, &v| acc + v);
+    let center = sum / corners.len() as f32;
+
+    let radius = corners
+        .iter()
+        .fold(0.0f32, |acc, &v| acc.max((v - center).magnitude()));
+    let radius = (radius * 16.0).ceil() / 16.0;
+
+    let texel = 2.0 * radius / resolution as f32;
+    let mut center = (view_matrix * center.extend(1.0)).truncate();
+    center.x = (center.x / texel).floor() * texel;
+    center.y = (center.y / texel).floor() * texel;
+
+    let extents = math::Vector3::new(radius, radius, radius);
+    (center - extents, center + extents)
+}
+
+// The orthographic projection of box in view space.
+fn ortho(min: math::Vector3<f32>, max: math::Vector3<f32>) -> math::Matrix4<f32> {
+    let size = max - min;
+    let size = math::Vector3::new(size.x.max(1e-4), size.y.max(1e-4), size.z.max(1e-4));
+
+    math::Matrix4::new(
+        2.0 / size.x,
+        0.0,
+        0.0,
+        0.0,
+        0.0,
+        2.0 / size.y,
+        0.0,
+        0.0,
+        0.0,
+        0.0,
+        2.0 / size.z,
+        0.0,
+        -(max.x + min.x) / size.x,
+        -(max.y + min.y) / size.y,
+        -(max.z + min.z) / size.z,
+        1.0,
+    )
+}
@@ -0,0 +1,256 @@
+use crayon::video::assets::prelude::*;
+use crayon::video::prelude::{VideoApi, VideoCapabilities, VideoProfile};
+
+use renderers::{DebugDrawMode, LightBuffer, ShIrradiance, ShaderKeywords, MAX_SHADOW_CASCADES};
+
+use super::{CAMERA_BLOCK_SLOT, LIGHTS_BLOCK_SLOT, MAX_DIR_LITS, MAX_POINT_LITS, MAX_SKIN_BONES};
+
+/// Gets the GLSL version which the variants are compiled as, when the camera and
+/// lights are read from the uniform block. Returns `None` if uniform buffers are not
+/// available, and the variants are compiled as GLSL ES 1.00 with plain uniforms.
+pub fn glsl_version(capabilities: &VideoCapabilities) -> Option<&'static str> {
+    if !capabilities.uniform_buffer || capabilities.profile != VideoProfile::Standard {
+        return None;
+    }
+
+    match capabilities.api {
+        VideoApi::ES(major, _) if major >= 3 => Some("300 es"),
+        // The uniform blocks are only in core GLSL since 1.40.
+        VideoApi::GL(3, 0) => None,
+        VideoApi::GL(3, minor) if minor < 3 => Some("140"),
+        VideoApi::GL(major, _) if major >= 3 => Some("330"),
+        VideoApi::Headless => Some("330"),
+        _ => None,
+    }
+}
+
+/// The inputs of a variant of simple shader, which are derived from its keywords and
+/// debug mode.
+///
+/// The GL backend refuses to create shaders with the uniforms or attributes which
+/// are optimized out by the GLSL compiler, so only the ones which are actually used
+/// are declared. The sources are compiled with the same flags as defines, so the
+/// declarations always match the programs.
+#[derive(Debug, Copy, Clone)]
+pub struct ShaderInputs {
+    pub keywords: ShaderKeywords,
+    pub mode: DebugDrawMode,
+    // Whether the camera and lights are read from the uniform block.
+    uniform_buffer: bool,
+    // Whether the lights are evaluated.
+    lit: bool,
+    // Whether the view space position is passed to fragment shader.
+    eye_position: bool,
+    // Whether the view space normal is passed to fragment shader.
+    eye_normal: bool,
+}
+
+impl ShaderInputs {
+    /// Creates the inputs of variant, the keywords which make no difference in the
+    /// debug mode are stripped.
+    pub fn new(mut keywords: ShaderKeywords, mode: DebugDrawMode, uniform_buffer: bool) -> Self {
+        let lit = match mode {
+            DebugDrawMode::Shaded | DebugDrawMode::MipmapLevel | DebugDrawMode::LightingOnly => {
+                true
+            }
+            _ => false,
+        };
+
+        if !lit {
+            keywords.disable(ShaderKeywords::FOG);
+            keywords.disable(ShaderKeywords::LIGHTMAP);
+            keywords.disable(ShaderKeywords::WATER);
+            keywords.disable(ShaderKeywords::SHADOWS);
+        }
+
+        // The lightmaps have the shadows of directional light baked already.
+        if keywords.contains(ShaderKeywords::LIGHTMAP) {
+            keywords.disable(ShaderKeywords::SHADOWS);
+        }
+
+        let eye_normal = lit || mode == DebugDrawMode::Normals;
+        if !eye_normal {
+            keywords.disable(ShaderKeywords::NORMALMAP);
+        }
+
+        ShaderInputs {
+            keywords: keywords,
+            mode: mode,
+            uniform_buffer: uniform_buffer,
+            lit: lit,
+            eye_position: lit || keywords.contains(ShaderKeywords::CLIP_PLANE),
+            eye_normal: eye_normal,
+        }
+    }
+
+    /// Gets the preprocessor defines of variant.
+    pub fn defines(&self) -> String {
+        let mut defines = self.keywords.defines();
+
+        let mode = match self.mode {
+            DebugDrawMode::Shaded => None,
+            DebugDrawMode::Wireframe => Some("DEBUG_WIREFRAME"),
+            DebugDrawMode::Normals => Some("DEBUG_NORMALS"),
+            DebugDrawMode::Overdraw => Some("DEBUG_OVERDRAW"),
+            DebugDrawMode::MipmapLevel => Some("DEBUG_MIPMAP"),
+            DebugDrawMode::LightingOnly => Some("DEBUG_LIGHTING_ONLY"),
+        };
+
+        let flags = [
+            (mode.is_some(), mode.unwrap_or("")),
+            (self.uniform_buffer, "UNIFORM_BUFFER"),
+            (self.lit, "LIT"),
+            (self.eye_position, "EYE_POSITION"),
+            (self.eye_normal, "EYE_NORMAL"),
+        ];
+
+        for &(enabled, name) in &flags {
+            if enabled {
+                defines.push_str("#define ");
+                defines.push_str(name);
+                defines.push('\n');
+            }
+        }
+
+        defines
+    }
+
+    /// Gets the vertex attributes which are read by variant.
+    pub fn attributes(&self) -> AttributeLayout {
+        let mut attributes = AttributeLayout::build().with(Attribute::Position, 3);
+
+        if self.eye_normal {
+            attributes = attributes.with(Attribute::Normal, 3);
+        }
+
+        if self.keywords.contains(ShaderKeywords::NORMALMAP) {
+            attributes = attributes
+                .with(Attribute::Texcoord0, 2)
+                .with(Attribute::Tangent, 4);
+        }
+
+        if self.keywords.contains(ShaderKeywords::SKINNED) {
+            attributes = attributes
+                .with(Attribute::Indices, 4)
+                .with(Attribute::Weight, 4);
+        }
+
+        if self.keywords.contains(ShaderKeywords::LIGHTMAP) {
+            attributes = attributes.with(Attribute::Texcoord1, 2);
+        }
+
+        attributes.finish()
+    }
+
+    /// Gets the uniform blocks which are used by variant, and the slots they are bound
+    /// to. Only the lit variants read the blocks of camera and lights.
+    pub fn uniform_blocks(&self) -> Vec<(String, usize)> {
+        if self.uniform_buffer && self.lit {
+            vec![
+                ("Camera".to_owned(), CAMERA_BLOCK_SLOT),
+                ("Lights".to_owned(), LIGHTS_BLOCK_SLOT),
+            ]
+        } else {
+            Vec::new()
+        }
+    }
+
+    /// Gets the uniforms which are used by variant, the ones in uniform block are
+    /// excluded.
+    pub fn uniforms(&self) -> UniformVariableLayout {
+        let keywords = self.keywords;
+        let lightmap = keywords.contains(ShaderKeywords::LIGHTMAP);
+        let lighting_only = self.mode == DebugDrawMode::LightingOnly;
+
+        let mut uniforms =
+            UniformVariableLayout::build().with("u_MVPMatrix", UniformVariableType::Matrix4f);
+
+        if self.eye_position || keywords.contains(ShaderKeywords::NORMALMAP) {
+            uniforms = uniforms.with("u_ModelViewMatrix", UniformVariableType::Matrix4f);
+        }
+
+        if self.eye_normal {
+            uniforms = uniforms.with("u_ViewNormalMatrix", UniformVariableType::Matrix4f);
+        }
+
+        if keywords.contains(ShaderKeywords::NORMALMAP) {
+            uniforms = uniforms.with("u_NormalMap", UniformVariableType::Texture);
+        }
+
+        if keywords.contains(ShaderKeywords::SKINNED) {
+            for i in 0..MAX_SKIN_BONES {
+                let name = format!("u_BoneMatrices[{0}]", i);
+                uniforms = uniforms.with(name, UniformVariableType::Matrix4f);
+            }
+        }
+
+        if keywords.contains(ShaderKeywords::CLIP_PLANE) {
+            uniforms = uniforms.with("u_ClipPlane", UniformVariableType::Vector4f);
+        }
+
+        if !self.lit {
+            return uniforms.finish();
+        }
+
+        let lights = LightBuffer::new(MAX_DIR_LITS, MAX_POINT_LITS);
+        if !self.uniform_buffer {
+            uniforms = lights.declare_point_lits(uniforms);
+        }
+
+        uniforms = uniforms
+            .with("u_Specular", UniformVariableType::Vector3f)
+            .with("u_Shininess", UniformVariableType::F32);
+
+        if !lighting_only {
+            uniforms = uniforms.with("u_Diffuse", UniformVariableType::Vector3f);
+        }
+
+        if lightmap {
+            uniforms = uniforms
+                .with("u_Lightmap", UniformVariableType::Texture)
+                .with("u_LightmapScaleOffset", UniformVariableType::Vector4f);
+        } else {
+            if !self.uniform_buffer {
+                uniforms = lights.declare_dir_lits(uniforms);
+            }
+
+            uniforms = ShIrradiance::declare(uniforms);
+            if !lighting_only {
+                uniforms = uniforms.with("u_Ambient", UniformVariableType::Vector3f);
+            }
+        }
+
+        // The world space is required by the ambient, shadows and water ripples.
+        if !self.uniform_buffer && (!lightmap || keywords.contains(ShaderKeywords::WATER)) {
+            uniforms = uniforms.with("u_InvViewMatrix", UniformVariableType::Matrix4f);
+        }
+
+        if keywords.contains(ShaderKeywords::FOG) {
+            uniforms = uniforms
+                .with("u_FogParams", UniformVariableType::Vector4f)
+                .with("u_FogColor", UniformVariableType::Vector3f);
+        }
+
+        if keywords.contains(ShaderKeywords::WATER) {
+            uniforms = uniforms
+                .with("u_ReflectionTexture", UniformVariableType::RenderTexture)
+                .with("u_RefractionTexture", UniformVariableType::RenderTexture)
+                .with("u_WaterParams", UniformVariableType::Vector4f)
+                .with("u_ScreenSize", UniformVariableType::Vector2f);
+        }
+
+        if keywords.contains(ShaderKeywords::SHADOWS) {
+            for i in 0..MAX_SHADOW_CASCADES {
+                let name = format!("u_ShadowMatrices[{0}]", i);
+                uniforms = uniforms.with(name, UniformVariableType::Matrix4f);
+            }
+
+            uniforms = uniforms
+                .with("u_ShadowMap", UniformVariableType::RenderTexture)
+                .with("u_ShadowSplits", UniformVariableType::Vector4f)
+                .with("u_ShadowParams", UniformVariableType::Vector4f);
+        }
+
+        uniforms.finish()
+    }
+}
@@ -1,6 +1,8 @@
 use crayon::math;
 use crayon::video::assets::texture::TextureHandle;
 
+use renderers::ShaderKeywords;
+
 #[derive(Debug, Copy, Clone)]
 pub struct SimpleMaterial {
     pub texture: TextureHandle,
@@ -8,6 +10,14 @@ pub struct SimpleMaterial {
     pub diffuse: math::Color<f32>,
     pub specular: math::Color<f32>,
     pub shininess: f32,
+    /// The normals in tangent space, which are sampled with the first texcoords. It's
+    /// only used by the meshes which have `Texcoord0` and `Tangent` attributes.
+    pub normal_map: Option<TextureHandle>,
+    /// Whether this material is affected by the global fog.
+    pub fog: bool,
+    /// The additional shader keywords, the renderer picks the variant with these
+    /// keywords enabled.
+    pub keywords: ShaderKeywords,
 }
 
 impl Default for SimpleMaterial {
@@ -18,6 +28,9 @@ impl Default for SimpleMaterial {
             diffuse: math::Color::white(),
             specular: math::Color::black(),
             shininess: 0.0,
+            normal_map: None,
+            fog: true,
+            keywords: ShaderKeywords::empty(),
         }
     }
 }
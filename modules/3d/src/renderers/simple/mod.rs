@@ -1,136 +1,1211 @@
+mod inputs;
 mod material;
 pub use self::material::SimpleMaterial;
 
+use crayon::application::diagnostics::Profiler;
+use crayon::application::window::WindowShared;
 use crayon::application::Context;
 use crayon::errors::*;
 use crayon::math;
 use crayon::video::assets::prelude::*;
 use crayon::video::prelude::*;
 
+use std::collections::HashMap;
 use std::sync::Arc;
 
-use super::{Camera, Lit, LitSource, MeshRenderer};
-use {Component, Entity};
+use self::inputs::ShaderInputs;
+use super::pipeline::create_fullscreen_mesh;
+use super::{atlas_tile, fit_cascades, ShadowQuality, ShadowSettings, MAX_SHADOW_CASCADES};
+use super::{cull_cached, VisibilityCache};
+use super::{BlobQuad, GroundPlane, PlanarReflection, ProbeGrid, RenderScaleController, Ribbon};
+use super::{Camera, DebugDrawMode, LightBuffer, Lit, MeshRenderer, RenderEnvironment, Sky};
+use super::{LitSource, MaterialPropertyBlock, ShIrradiance, UpscaleFilter};
+use super::{RenderCallbackHandle, RenderCallbacks, RenderRecorder, RenderStage};
+use super::{ShaderKeywords, ShaderVariants};
+use entity_ref::EntityRemap;
+use {Component, Entity, WorldId};
 
 pub const MAX_DIR_LITS: usize = 1;
 pub const MAX_POINT_LITS: usize = 4;
+/// The maximum number of bones which could affect a skinned mesh.
+pub const MAX_SKIN_BONES: usize = 16;
+/// The slot of uniform buffer which holds the camera of current pass.
+pub const CAMERA_BLOCK_SLOT: usize = 0;
+/// The slot of uniform buffer which holds the lights of current pass.
+pub const LIGHTS_BLOCK_SLOT: usize = 1;
+
+const GLSL3_VS_COMPAT: &'static str = "
+    #define attribute in
+    #define varying out
+";
+
+const GLSL3_FS_COMPAT: &'static str = "
+    #define varying in
+    #define texture2D texture
+    out vec4 o_FragColor;
+    #define gl_FragColor o_FragColor
+";
+
+impl_vertex!{
+    GroundVertex {
+        position => [Position; Float; 3; false],
+    }
+}
+
+impl_vertex!{
+    RibbonVertex {
+        position => [Position; Float; 3; false],
+        texcoord => [Texcoord0; Float; 2; false],
+        color => [Color0; UByte; 4; true],
+    }
+}
+
+impl_vertex!{
+    BlobVertex {
+        position => [Position; Float; 3; false],
+        local => [Texcoord0; Float; 2; false],
+        shape => [Texcoord1; Float; 4; false],
+        color => [Color0; UByte; 4; true],
+    }
+}
 
 /// A simple renderer that draws some color into mesh objects.
 pub struct SimpleRenderer {
-    materials: Component<SimpleMaterial>,
+    materials: Component<Vec<SimpleMaterial>>,
+    skins: Component<Vec<math::Matrix4<f32>>>,
+
+    surface: SurfaceHandle,
+    variants: ShaderVariants,
+    debug_variants: HashMap<DebugDrawMode, ShaderVariants>,
+    // The uniforms of compiled variants, the inactive ones are stripped from drawcalls
+    // with them.
+    layouts: HashMap<ShaderHandle, UniformVariableLayout>,
+    video: Arc<VideoSystemShared>,
+    // The meshes which pass the culling of current camera.
+    visible: Vec<MeshRenderer>,
+    visibility: VisibilityCache,
+    drawcalls: OrderDrawBatch<DrawOrder>,
+    overlay_drawcalls: OrderDrawBatch<DrawOrder>,
+
+    lights: LightBuffer,
+    // The GLSL version of variants, which is `None` if they are compiled as GLSL ES
+    // 1.00 without the uniform block of camera.
+    glsl: Option<&'static str>,
+    // The uniform buffer of camera and lights, which is updated once per pass.
+    camera_block: Option<(UniformBufferHandle, UniformBlock)>,
+
+    environment: RenderEnvironment,
+    sky: Option<Sky>,
+    probes: Option<ProbeGrid>,
+    sky_shader: ShaderHandle,
+    sky_mesh: MeshHandle,
+
+    ground: Option<GroundPlane>,
+    ground_shader: ShaderHandle,
+    ground_mesh: Option<(MeshHandle, math::Vector2<u32>)>,
+    white_texture: TextureHandle,
+
+    ribbons: Vec<Ribbon>,
+    ribbon_shader: ShaderHandle,
+    ribbon_mesh: Option<(MeshHandle, usize, usize)>,
+
+    blob_shadows: Vec<BlobQuad>,
+    blob_shader: ShaderHandle,
+    blob_mesh: Option<(MeshHandle, usize, usize)>,
+
+    shadow_settings: ShadowSettings,
+    shadow_shader: ShaderHandle,
+    shadow_target: Option<ShadowTarget>,
+
+    window: Arc<WindowShared>,
+    profiler: Arc<Profiler>,
+    scaled_targets: HashMap<(Option<WorldId>, Entity), ScaledTarget>,
+    scaled_ticks: u64,
+    upscale_shader: ShaderHandle,
+
+    reflection: Option<PlanarReflection>,
+    reflection_target: Option<ReflectionTarget>,
+
+    callbacks: RenderCallbacks,
+}
+
+/// The maximum number of cameras whose intermediate targets of render scale are kept.
+/// The least recently used one is deleted when there are more.
+pub const MAX_SCALED_TARGETS: usize = 4;
+
+// The intermediate target of dynamic resolution of a camera, which has the size of
+// window scaled by the maximum render scale. Only the viewport at current scale is
+// drawn.
+struct ScaledTarget {
+    surface: SurfaceHandle,
+    color: RenderTextureHandle,
+    depth: RenderTextureHandle,
+    dimensions: math::Vector2<u32>,
+    controller: RenderScaleController,
+    ticks: u64,
+}
+
+impl ScaledTarget {
+    fn delete(&self, video: &VideoSystemShared) {
+        video.delete_surface(self.surface);
+        video.delete_render_texture(self.color);
+        video.delete_render_texture(self.depth);
+    }
+}
+
+// The render targets of planar reflection, which have the size of window scaled by
+// the resolution of reflection.
+struct ReflectionTarget {
+    reflection: ColorTarget,
+    refraction: ColorTarget,
+    dimensions: math::Vector2<u32>,
+}
+
+// The atlas of shadow cascades, which has a tile of resolution for every cascade.
+struct ShadowTarget {
+    target: ColorTarget,
+    dimensions: math::Vector2<u32>,
+}
+
+#[derive(Debug, Copy, Clone)]
+struct ColorTarget {
+    surface: SurfaceHandle,
+    color: RenderTextureHandle,
+    depth: RenderTextureHandle,
+}
+
+impl ColorTarget {
+    fn new(
+        video: &VideoSystemShared,
+        dimensions: math::Vector2<u32>,
+        clear: math::Color<f32>,
+    ) -> Result<Self> {
+        let mut params = RenderTextureParams::default();
+        params.format = RenderTextureFormat::RGBA8;
+        params.dimensions = dimensions;
+        let color = video.create_render_texture(params)?;
+
+        params.format = RenderTextureFormat::Depth24;
+        params.sampler = false;
+        let depth = video.create_render_texture(params)?;
+
+        let mut params = SurfaceParams::default();
+        params.set_attachments(&[color], depth)?;
+        params.set_clear(clear, 1.0, None);
+        let surface = video.create_surface(params)?;
+
+        Ok(ColorTarget {
+            surface: surface,
+            color: color,
+            depth: depth,
+        })
+    }
+
+    fn delete(&self, video: &VideoSystemShared) {
+        video.delete_surface(self.surface);
+        video.delete_render_texture(self.color);
+        video.delete_render_texture(self.depth);
+    }
+}
+
+impl SimpleRenderer {
+    /// Creates a new `SimpleRenderer`.
+    pub fn new(ctx: &Context) -> Result<Self> {
+        let glsl = inputs::glsl_version(ctx.video.capabilities());
+        let mut variants = SimpleRenderer::create_variants(ctx, DebugDrawMode::Shaded, glsl);
+        // The default materials are fogged.
+        variants.prewarm(&[ShaderKeywords::FOG])?;
+
+        let mut debug_variants = HashMap::new();
+        for &mode in &[
+            DebugDrawMode::Wireframe,
+            DebugDrawMode::Normals,
+            DebugDrawMode::Overdraw,
+            DebugDrawMode::MipmapLevel,
+            DebugDrawMode::LightingOnly,
+        ] {
+            debug_variants.insert(mode, SimpleRenderer::create_variants(ctx, mode, glsl));
+        }
+
+        let params = SurfaceParams::default();
+        let surface = ctx.video.create_surface(params)?;
+
+        let (lights, camera_block) = match glsl {
+            Some(_) => (
+                LightBuffer::shared(&ctx.video, MAX_DIR_LITS, MAX_POINT_LITS)?,
+                Some(SimpleRenderer::create_camera_block(ctx)?),
+            ),
+            None => (LightBuffer::new(MAX_DIR_LITS, MAX_POINT_LITS), None),
+        };
+
+        let (sky_shader, sky_mesh) = SimpleRenderer::create_sky(ctx)?;
+        let upscale_shader = SimpleRenderer::create_upscale(ctx)?;
+        let (ground_shader, white_texture) = SimpleRenderer::create_ground(ctx)?;
+        let ribbon_shader = SimpleRenderer::create_ribbon(ctx)?;
+        let blob_shader = SimpleRenderer::create_blob_shadow(ctx)?;
+        let shadow_shader = SimpleRenderer::create_shadow(ctx)?;
+
+        Ok(SimpleRenderer {
+            materials: Component::new(),
+            skins: Component::new(),
+            video: ctx.video.clone(),
+            surface: surface,
+            variants: variants,
+            debug_variants: debug_variants,
+            layouts: HashMap::new(),
+            visible: Vec::new(),
+            visibility: VisibilityCache::new(),
+            drawcalls: OrderDrawBatch::new(),
+            overlay_drawcalls: OrderDrawBatch::new(),
+            lights: lights,
+            glsl: glsl,
+            camera_block: camera_block,
+            environment: RenderEnvironment::default(),
+            sky: None,
+            probes: None,
+            sky_shader: sky_shader,
+            sky_mesh: sky_mesh,
+            ground: None,
+            ground_shader: ground_shader,
+            ground_mesh: None,
+            white_texture: white_texture,
+            ribbons: Vec::new(),
+            ribbon_shader: ribbon_shader,
+            ribbon_mesh: None,
+            blob_shadows: Vec::new(),
+            blob_shader: blob_shader,
+            blob_mesh: None,
+            shadow_settings: ShadowSettings::default(),
+            shadow_shader: shadow_shader,
+            shadow_target: None,
+            window: ctx.window.clone(),
+            profiler: ctx.profiler.clone(),
+            scaled_targets: HashMap::new(),
+            scaled_ticks: 0,
+            upscale_shader: upscale_shader,
+            reflection: None,
+            reflection_target: None,
+            callbacks: RenderCallbacks::new(),
+        })
+    }
+
+    // Creates the uniform buffer which holds the camera of pass, its layout matches the
+    // `Camera` block of simple shaders.
+    fn create_camera_block(ctx: &Context) -> Result<(UniformBufferHandle, UniformBlock)> {
+        let layout = UniformBlockLayout::build()
+            .with("u_InvViewMatrix", UniformVariableType::Matrix4f)
+            .finish()?;
+
+        let mut params = UniformBufferParams::default();
+        params.size = layout.size();
+        let buffer = ctx.video.create_uniform_buffer(params, None)?;
+        Ok((buffer, UniformBlock::new(layout)))
+    }
+
+    fn create_upscale(ctx: &Context) -> Result<ShaderHandle> {
+        let attributes = AttributeLayout::build()
+            .with(Attribute::Position, 2)
+            .finish();
+
+        let uniforms = UniformVariableLayout::build()
+            .with("u_Source", UniformVariableType::RenderTexture)
+            .with("u_UVScale", UniformVariableType::Vector2f)
+            .with("u_TexelSize", UniformVariableType::Vector2f)
+            .with("u_Sharpness", UniformVariableType::F32)
+            .finish();
+
+        let mut params = ShaderParams::default();
+        params.state.depth_write = false;
+        params.state.depth_test = Comparison::Always;
+        params.attributes = attributes;
+        params.uniforms = uniforms;
+
+        let vs = format!(
+            "
+            #version 100
+            precision mediump float;
+            {0}
+            ",
+            include_str!("../../../assets/upscale.vs")
+        );
+
+        let fs = format!(
+            "
+            #version 100
+            precision mediump float;
+            {0}
+            ",
+            include_str!("../../../assets/upscale.fs")
+        );
+
+        Ok(ctx.video.create_shader(params, vs, fs)?)
+    }
+
+    /// Gets the render scale of camera used in last frame.
+    pub fn current_render_scale(&self, camera: &Camera) -> f32 {
+        self.scaled_targets
+            .get(&(camera.owner, camera.ent))
+            .map(|v| v.controller.scale())
+            .unwrap_or(1.0)
+    }
+
+    // Prepares the intermediate target with the render scale of camera, returns the
+    // surface to draw into and the scaled viewport. The scale is adjusted with the GPU
+    // time spent on the target of camera in last frame.
+    fn prepare_scaled_target(
+        &mut self,
+        camera: &Camera,
+    ) -> Result<Option<(SurfaceHandle, SurfaceViewport)>> {
+        let key = (camera.owner, camera.ent);
+        let setup = camera.render_scale();
+        if setup.is_native() || camera.surface().is_some() {
+            if let Some(v) = self.scaled_targets.remove(&key) {
+                v.delete(&self.video);
+            }
+
+            return Ok(None);
+        }
+
+        let window = self.window.dimensions();
+        let max_scale = setup.max_scale();
+        let dimensions = math::Vector2::new(
+            ((window.x as f32 * max_scale) as u32).max(1),
+            ((window.y as f32 * max_scale) as u32).max(1),
+        );
+
+        let recreate = match self.scaled_targets.get(&key) {
+            Some(v) => v.dimensions != dimensions,
+            None => true,
+        };
+
+        if recreate {
+            let mut controller = RenderScaleController::default();
+            if let Some(v) = self.scaled_targets.remove(&key) {
+                controller = v.controller;
+                v.delete(&self.video);
+            } else if self.scaled_targets.len() >= MAX_SCALED_TARGETS {
+                let lru = self
+                    .scaled_targets
+                    .iter()
+                    .min_by_key(|v| (v.1).ticks)
+                    .map(|v| *v.0)
+                    .unwrap();
+
+                if let Some(v) = self.scaled_targets.remove(&lru) {
+                    v.delete(&self.video);
+                }
+            }
+
+            let mut params = RenderTextureParams::default();
+            params.format = RenderTextureFormat::RGBA8;
+            params.dimensions = dimensions;
+            let color = self.video.create_render_texture(params)?;
+
+            params.format = RenderTextureFormat::Depth24;
+            params.sampler = false;
+            let depth = self.video.create_render_texture(params)?;
+
+            let mut params = SurfaceParams::default();
+            params.set_attachments(&[color], depth)?;
+            let surface = self.video.create_surface(params)?;
+
+            self.scaled_targets.insert(
+                key,
+                ScaledTarget {
+                    surface: surface,
+                    color: color,
+                    depth: depth,
+                    dimensions: dimensions,
+                    controller: controller,
+                    ticks: 0,
+                },
+            );
+        }
+
+        self.scaled_ticks += 1;
+        let target = self.scaled_targets.get_mut(&key).unwrap();
+        target.ticks = self.scaled_ticks;
+
+        // The surface of a recreated target has not been drawn yet.
+        let gpu_time = if recreate {
+            None
+        } else {
+            self.profiler.last_gpu_time(target.surface)
+        };
+
+        let scale = target.controller.advance(&setup, gpu_time) / max_scale;
+        let viewport = SurfaceViewport {
+            position: math::Vector2::new(0, 0),
+            size: math::Vector2::new(
+                ((dimensions.x as f32 * scale) as u32).max(1),
+                ((dimensions.y as f32 * scale) as u32).max(1),
+            ),
+        };
+
+        Ok(Some((target.surface, viewport)))
+    }
+
+    // Prepares the render targets of planar reflection.
+    fn prepare_reflection_target(
+        &mut self,
+        setup: &PlanarReflection,
+    ) -> Result<(ColorTarget, ColorTarget)> {
+        let window = self.window.dimensions();
+        let scale = setup.resolution.max(0.1).min(1.0);
+        let dimensions = math::Vector2::new(
+            ((window.x as f32 * scale) as u32).max(1),
+            ((window.y as f32 * scale) as u32).max(1),
+        );
+
+        let recreate = match self.reflection_target {
+            Some(ref v) => v.dimensions != dimensions,
+            None => true,
+        };
+
+        if recreate {
+            if let Some(v) = self.reflection_target.take() {
+                v.reflection.delete(&self.video);
+                v.refraction.delete(&self.video);
+            }
+
+            let black = math::Color::black();
+            let reflection = ColorTarget::new(&self.video, dimensions, black)?;
+            let refraction = match ColorTarget::new(&self.video, dimensions, black) {
+                Ok(v) => v,
+                Err(err) => {
+                    reflection.delete(&self.video);
+                    return Err(err);
+                }
+            };
+
+            self.reflection_target = Some(ReflectionTarget {
+                reflection: reflection,
+                refraction: refraction,
+                dimensions: dimensions,
+            });
+        }
+
+        let target = self.reflection_target.as_ref().unwrap();
+        Ok((target.reflection, target.refraction))
+    }
+
+    // Upscales the scaled viewport of intermediate target into `surface`.
+    fn upscale(&self, camera: &Camera, surface: SurfaceHandle, viewport: SurfaceViewport) {
+        let target = match self.scaled_targets.get(&(camera.owner, camera.ent)) {
+            Some(v) => v,
+            None => return,
+        };
+
+        let sharpness = match camera.render_scale().filter {
+            UpscaleFilter::Bilinear => 0.0,
+            UpscaleFilter::Sharpen(v) => v.max(0.0).min(1.0),
+        };
+
+        let (w, h) = (target.dimensions.x as f32, target.dimensions.y as f32);
+        let uv_scale = math::Vector2::new(viewport.size.x as f32 / w, viewport.size.y as f32 / h);
+
+        let mut dc = DrawCall::new(self.upscale_shader, self.sky_mesh);
+        dc.set_uniform_variable("u_Source", target.color);
+        dc.set_uniform_variable("u_UVScale", uv_scale);
+        dc.set_uniform_variable("u_TexelSize", math::Vector2::new(1.0 / w, 1.0 / h));
+        dc.set_uniform_variable("u_Sharpness", sharpness);
+        self.video.draw(surface, dc);
+    }
+
+    // Creates the variants of objects in debug mode, which only support the keywords
+    // making difference in that mode.
+    fn create_variants(
+        ctx: &Context,
+        mode: DebugDrawMode,
+        glsl: Option<&'static str>,
+    ) -> ShaderVariants {
+        let mut params = ShaderParams::default();
+        params.state.depth_write = true;
+        params.state.depth_test = Comparison::Less;
+
+        let supported = match mode {
+            DebugDrawMode::Shaded => {
+                ShaderKeywords::FOG
+                    | ShaderKeywords::LIGHTMAP
+                    | ShaderKeywords::CLIP_PLANE
+                    | ShaderKeywords::WATER
+                    | ShaderKeywords::SHADOWS
+                    | ShaderKeywords::NORMALMAP
+                    | ShaderKeywords::SKINNED
+            }
+            DebugDrawMode::MipmapLevel | DebugDrawMode::LightingOnly => {
+                ShaderKeywords::LIGHTMAP | ShaderKeywords::NORMALMAP | ShaderKeywords::SKINNED
+            }
+            DebugDrawMode::Normals => ShaderKeywords::NORMALMAP | ShaderKeywords::SKINNED,
+            DebugDrawMode::Overdraw => {
+                // Accumulates the color of every fragments without depth test.
+                params.state.depth_write = false;
+                params.state.depth_test = Comparison::Always;
+                params.state.color_blend =
+                    Some((Equation::Add, BlendFactor::One, BlendFactor::One));
+                ShaderKeywords::SKINNED
+            }
+            DebugDrawMode::Wireframe => {
+                // Draws the edges of polygons on top of shaded ones, the polygon offset
+                // pulls lines towards the camera to avoid z-fighting.
+                params.state.depth_write = false;
+                params.state.depth_test = Comparison::LessOrEqual;
+                params.state.depth_write_offset = Some((-1.0, -1.0));
+                params.state.polygon_mode = PolygonMode::Line;
+                ShaderKeywords::SKINNED
+            }
+        };
+
+        ShaderVariants::new(ctx.video.clone(), supported, move |keywords| {
+            let inputs = ShaderInputs::new(keywords, mode, glsl.is_some());
+            let mut p = params.clone();
+            p.attributes = inputs.attributes();
+            p.uniforms = inputs.uniforms();
+            p.uniform_blocks = inputs.uniform_blocks();
+
+            let (vs, fs) = SimpleRenderer::sources(&inputs.defines(), glsl);
+            (p, vs, fs)
+        })
+    }
+
+    // Gets the variant of objects with keywords in debug mode, and caches the layout of
+    // its uniforms.
+    fn variant(&mut self, mode: DebugDrawMode, keywords: ShaderKeywords) -> Result<ShaderHandle> {
+        let (shader, keywords) = {
+            let variants = match mode {
+                DebugDrawMode::Shaded => &mut self.variants,
+                _ => self
+                    .debug_variants
+                    .get_mut(&mode)
+                    .ok_or_else(|| format_err!("{:?} has no shader variants.", mode))?,
+            };
+
+            let shader = variants.get(keywords)?;
+            (shader, keywords.intersection(variants.supported()))
+        };
+
+        if !self.layouts.contains_key(&shader) {
+            let uniforms = ShaderInputs::new(keywords, mode, self.glsl.is_some()).uniforms();
+            self.layouts.insert(shader, uniforms);
+        }
+
+        Ok(shader)
+    }
+
+    fn create_sky(ctx: &Context) -> Result<(ShaderHandle, MeshHandle)> {
+        let attributes = AttributeLayout::build()
+            .with(Attribute::Position, 2)
+            .finish();
+
+        let uniforms = UniformVariableLayout::build()
+            .with("u_InvViewProjMatrix", UniformVariableType::Matrix4f)
+            .with("u_SunDir", UniformVariableType::Vector3f)
+            .with("u_SunColor", UniformVariableType::Vector3f)
+            .with("u_SunSize", UniformVariableType::F32)
+            .with("u_ZenithColor", UniformVariableType::Vector3f)
+            .with("u_HorizonColor", UniformVariableType::Vector3f)
+            .with("u_GroundColor", UniformVariableType::Vector3f)
+            .finish();
+
+        // Draws at far plane without writing depth, so it would be covered by any objects.
+        let mut params = ShaderParams::default();
+        params.state.depth_write = false;
+        params.state.depth_test = Comparison::LessOrEqual;
+        params.attributes = attributes;
+        params.uniforms = uniforms;
+
+        let vs = format!(
+            "
+            #version 100
+            precision mediump float;
+            {0}
+            ",
+            include_str!("../../../assets/sky.vs")
+        );
+
+        let fs = format!(
+            "
+            #version 100
+            precision mediump float;
+            {0}
+            ",
+            include_str!("../../../assets/sky.fs")
+        );
+
+        let shader = ctx.video.create_shader(params, vs, fs)?;
+        let mesh = create_fullscreen_mesh(&ctx.video)?;
+        Ok((shader, mesh))
+    }
+
+    fn create_ground(ctx: &Context) -> Result<(ShaderHandle, TextureHandle)> {
+        let attributes = AttributeLayout::build()
+            .with(Attribute::Position, 3)
+            .finish();
+
+        let uniforms = UniformVariableLayout::build()
+            .with("u_ViewMatrix", UniformVariableType::Matrix4f)
+            .with("u_ProjMatrix", UniformVariableType::Matrix4f)
+            .with("u_Texture", UniformVariableType::Texture)
+            .with("u_Color", UniformVariableType::Vector4f)
+            .with("u_TilingOffset", UniformVariableType::Vector4f)
+            .with("u_Fade", UniformVariableType::Vector2f)
+            .with("u_ClipPlane", UniformVariableType::Vector4f)
+            .finish();
+
+        // Fades out at distance with alpha blending.
+        let mut params = ShaderParams::default();
+        params.state.depth_write = true;
+        params.state.depth_test = Comparison::Less;
+        params.state.color_blend = Some((
+            Equation::Add,
+            BlendFactor::Value(BlendValue::SourceAlpha),
+            BlendFactor::OneMinusValue(BlendValue::SourceAlpha),
+        ));
+        params.attributes = attributes;
+        params.uniforms = uniforms;
+
+        let vs = format!(
+            "
+            #version 100
+            precision highp float;
+            {0}
+            ",
+            include_str!("../../../assets/ground.vs")
+        );
+
+        let fs = format!(
+            "
+            #version 100
+            precision mediump float;
+            {0}
+            ",
+            include_str!("../../../assets/ground.fs")
+        );
+
+        let shader = ctx.video.create_shader(params, vs, fs)?;
+
+        // The plane without texture samples this instead.
+        let mut params = TextureParams::default();
+        params.dimensions = math::Vector2::new(1, 1);
+        let data = TextureData {
+            bytes: vec![vec![255, 255, 255, 255].into_boxed_slice()],
+        };
+
+        let texture = ctx.video.create_texture(params, data)?;
+        Ok((shader, texture))
+    }
+
+    // Projects the grid of ground plane from the view of pass, and draws it.
+    fn draw_ground(&mut self, pass: &ScenePass, ground: &GroundPlane) -> Result<()> {
+        let vertices: Vec<_> = ground
+            .project(pass.view_matrix, pass.projection_matrix)
+            .into_iter()
+            .map(|v| GroundVertex::new(v.into()))
+            .collect();
+
+        let resolution = ground.clamped_resolution();
+        let mesh = match self.ground_mesh {
+            Some((mesh, v)) if v == resolution => {
+                let bytes = GroundVertex::encode(&vertices);
+                self.video.update_vertex_buffer(mesh, 0, bytes)?;
+                mesh
+            }
+            _ => {
+                if let Some((mesh, _)) = self.ground_mesh.take() {
+                    self.video.delete_mesh(mesh);
+                }
+
+                let idxes = ground.indices();
+
+                let mut params = MeshParams::default();
+                params.hint = MeshHint::Stream;
+                params.layout = GroundVertex::layout();
+                params.index_format = IndexFormat::from_vertex_count(vertices.len());
+                params.primitive = MeshPrimitive::Triangles;
+                params.num_verts = vertices.len();
+                params.num_idxes = idxes.len();
+
+                let iptr = params.index_format.encode_u16(&idxes).into_owned();
+                let data = MeshData {
+                    vptr: GroundVertex::encode(&vertices).into(),
+                    iptr: iptr.into(),
+                };
+
+                let mesh = self.video.create_mesh(params, data)?;
+                self.ground_mesh = Some((mesh, resolution));
+                mesh
+            }
+        };
+
+        let texture = ground.texture.unwrap_or(self.white_texture);
+        let fade = math::Vector2::new(ground.fade_start, ground.fade_end);
+        let clip = pass
+            .clip
+            .unwrap_or_else(|| math::Vector4::new(0.0, 0.0, 0.0, 1.0));
+
+        let mut dc = DrawCall::new(self.ground_shader, mesh);
+        dc.set_uniform_variable("u_ViewMatrix", pass.view_matrix);
+        dc.set_uniform_variable("u_ProjMatrix", pass.projection_matrix);
+        dc.set_uniform_variable("u_Texture", texture);
+        dc.set_uniform_variable("u_Color", math::Vector4::from(ground.color.rgba()));
+        dc.set_uniform_variable("u_TilingOffset", ground.encode_tiling());
+        dc.set_uniform_variable("u_Fade", fade);
+        dc.set_uniform_variable("u_ClipPlane", clip);
+        self.video.draw(pass.surface, dc);
+        Ok(())
+    }
+
+    fn create_ribbon(ctx: &Context) -> Result<ShaderHandle> {
+        let attributes = AttributeLayout::build()
+            .with(Attribute::Position, 3)
+            .with(Attribute::Texcoord0, 2)
+            .with(Attribute::Color0, 4)
+            .finish();
+
+        let uniforms = UniformVariableLayout::build()
+            .with("u_ViewMatrix", UniformVariableType::Matrix4f)
+            .with("u_ProjMatrix", UniformVariableType::Matrix4f)
+            .with("u_Texture", UniformVariableType::Texture)
+            .with("u_ClipPlane", UniformVariableType::Vector4f)
+            .finish();
+
+        // The ribbons are translucent, and visible from both sides.
+        let mut params = ShaderParams::default();
+        params.state.depth_write = false;
+        params.state.depth_test = Comparison::Less;
+        params.state.color_blend = Some((
+            Equation::Add,
+            BlendFactor::Value(BlendValue::SourceAlpha),
+            BlendFactor::OneMinusValue(BlendValue::SourceAlpha),
+        ));
+        params.attributes = attributes;
+        params.uniforms = uniforms;
+
+        let vs = format!(
+            "
+            #version 100
+            precision highp float;
+            {0}
+            ",
+            include_str!("../../../assets/ribbon.vs")
+        );
+
+        let fs = format!(
+            "
+            #version 100
+            precision mediump float;
+            {0}
+            ",
+            include_str!("../../../assets/ribbon.fs")
+        );
+
+        ctx.video.create_shader(params, vs, fs)
+    }
+
+    // Extrudes the ribbons towards the eye of pass, and draws them with a streaming
+    // mesh shared by all of them.
+    fn draw_ribbons(&mut self, pass: &ScenePass) -> Result<()> {
+        let mut vertices = Vec::new();
+        let mut indices = Vec::new();
+        let mut ranges = Vec::with_capacity(self.ribbons.len());
+
+        for ribbon in &self.ribbons {
+            let extruded = ribbon.extrude(pass.eye);
+            if vertices.len() + extruded.len() > ::std::u16::MAX as usize {
+                warn!("Too many vertices of lines and trails, some of them are not drawn.");
+                break;
+            }
+
+            let start = indices.len();
+            indices.extend(ribbon.indices(vertices.len() as u16));
+            ranges.push((ribbon.texture, start, indices.len() - start));
+
+            for v in extruded {
+                let color: [u8; 4] = v.color.into();
+                vertices.push(RibbonVertex::new(
+                    v.position.into(),
+                    v.texcoord.into(),
+                    color,
+                ));
+            }
+        }
+
+        if indices.is_empty() {
+            return Ok(());
+        }
+
+        let mesh = stream_mesh(
+            &self.video,
+            &mut self.ribbon_mesh,
+            RibbonVertex::layout(),
+            (RibbonVertex::encode(&vertices), vertices.len()),
+            &indices,
+        )?;
+
+        let clip = pass
+            .clip
+            .unwrap_or_else(|| math::Vector4::new(0.0, 0.0, 0.0, 1.0));
+
+        for (texture, start, len) in ranges {
+            let mut dc = DrawCall::new(self.ribbon_shader, mesh);
+            dc.mesh_index = MeshIndex::Ptr(start, len);
+            dc.set_uniform_variable("u_ViewMatrix", pass.view_matrix);
+            dc.set_uniform_variable("u_ProjMatrix", pass.projection_matrix);
+            dc.set_uniform_variable("u_Texture", texture.unwrap_or(self.white_texture));
+            dc.set_uniform_variable("u_ClipPlane", clip);
+            self.video.draw(pass.surface, dc);
+        }
+
+        Ok(())
+    }
+
+    fn create_blob_shadow(ctx: &Context) -> Result<ShaderHandle> {
+        let attributes = AttributeLayout::build()
+            .with(Attribute::Position, 3)
+            .with(Attribute::Texcoord0, 2)
+            .with(Attribute::Texcoord1, 4)
+            .with(Attribute::Color0, 4)
+            .finish();
+
+        let uniforms = UniformVariableLayout::build()
+            .with("u_ViewMatrix", UniformVariableType::Matrix4f)
+            .with("u_ProjMatrix", UniformVariableType::Matrix4f)
+            .with("u_ClipPlane", UniformVariableType::Vector4f)
+            .finish();
+
+        // The shadows are lifted slightly above the ground, and darken it with blending.
+        let mut params = ShaderParams::default();
+        params.state.depth_write = false;
+        params.state.depth_test = Comparison::LessOrEqual;
+        params.state.color_blend = Some((
+            Equation::Add,
+            BlendFactor::Value(BlendValue::SourceAlpha),
+            BlendFactor::OneMinusValue(BlendValue::SourceAlpha),
+        ));
+        params.attributes = attributes;
+        params.uniforms = uniforms;
+
+        let vs = format!(
+            "
+            #version 100
+            precision highp float;
+            {0}
+            ",
+            include_str!("../../../assets/blob_shadow.vs")
+        );
+
+        let fs = format!(
+            "
+            #version 100
+            precision mediump float;
+            {0}
+            ",
+            include_str!("../../../assets/blob_shadow.fs")
+        );
+
+        ctx.video.create_shader(params, vs, fs)
+    }
+
+    // Draws all the blob shadows with one drawcall.
+    fn draw_blob_shadows(&mut self, pass: &ScenePass) -> Result<()> {
+        let max_quads = ::std::u16::MAX as usize / 4;
+        if self.blob_shadows.len() > max_quads {
+            warn!("Too many blob shadows, some of them are not drawn.");
+        }
+
+        let len = self.blob_shadows.len().min(max_quads);
+        let mut vertices = Vec::with_capacity(len * 4);
+        let mut indices = Vec::with_capacity(len * 6);
+
+        for quad in &self.blob_shadows[0..len] {
+            let (ex, ey) = (quad.extents.x, quad.extents.y);
+            let shape = [ex, ey, quad.radius, quad.softness];
+            let color = [0, 0, 0, (quad.opacity.max(0.0).min(1.0) * 255.0) as u8];
+            let locals = [[-ex, -ey], [ex, -ey], [ex, ey], [-ex, ey]];
+
+            let v = vertices.len() as u16;
+            indices.extend_from_slice(&[v, v + 1, v + 2, v, v + 2, v + 3]);
+
+            for (p, &local) in quad.corners().iter().zip(&locals) {
+                vertices.push(BlobVertex::new((*p).into(), local, shape, color));
+            }
+        }
+
+        if indices.is_empty() {
+            return Ok(());
+        }
+
+        let mesh = stream_mesh(
+            &self.video,
+            &mut self.blob_mesh,
+            BlobVertex::layout(),
+            (BlobVertex::encode(&vertices), vertices.len()),
+            &indices,
+        )?;
+
+        let clip = pass
+            .clip
+            .unwrap_or_else(|| math::Vector4::new(0.0, 0.0, 0.0, 1.0));
+
+        let mut dc = DrawCall::new(self.blob_shader, mesh);
+        dc.mesh_index = MeshIndex::Ptr(0, indices.len());
+        dc.set_uniform_variable("u_ViewMatrix", pass.view_matrix);
+        dc.set_uniform_variable("u_ProjMatrix", pass.projection_matrix);
+        dc.set_uniform_variable("u_ClipPlane", clip);
+        self.video.draw(pass.surface, dc);
+        Ok(())
+    }
+
+    fn create_shadow(ctx: &Context) -> Result<ShaderHandle> {
+        let attributes = AttributeLayout::build()
+            .with(Attribute::Position, 3)
+            .finish();
+
+        let uniforms = UniformVariableLayout::build()
+            .with("u_MVPMatrix", UniformVariableType::Matrix4f)
+            .finish();
+
+        let mut params = ShaderParams::default();
+        params.state.depth_write = true;
+        params.state.depth_test = Comparison::Less;
+        params.attributes = attributes;
+        params.uniforms = uniforms;
+
+        let vs = format!(
+            "
+            #version 100
+            precision highp float;
+            {0}
+            ",
+            include_str!("../../../assets/shadow.vs")
+        );
+
+        let fs = format!(
+            "
+            #version 100
+            precision highp float;
+            {0}
+            ",
+            include_str!("../../../assets/shadow.fs")
+        );
 
-    surface: SurfaceHandle,
-    shader: ShaderHandle,
-    video: Arc<VideoSystemShared>,
-    drawcalls: OrderDrawBatch<DrawOrder>,
+        ctx.video.create_shader(params, vs, fs)
+    }
 
-    dir_lits: Vec<(String, String)>,
-    point_lits: Vec<(String, String, String)>,
-}
+    /// Sets the settings of shadows with a quality tier.
+    #[inline]
+    pub fn set_shadow_quality(&mut self, quality: ShadowQuality) {
+        self.shadow_settings = quality.settings();
+    }
 
-impl SimpleRenderer {
-    /// Creates a new `SimpleRenderer`.
-    pub fn new(ctx: &Context) -> Result<Self> {
-        // Create shader state.
-        let attributes = AttributeLayout::build()
-            .with(Attribute::Position, 3)
-            .with(Attribute::Normal, 3)
-            .finish();
+    /// Sets the settings of shadows.
+    ///
+    /// Only the first enabled directional light casts shadows, and only if it's a
+    /// `shadow_caster`. The other directional lights and all the point lights never
+    /// cast shadows.
+    #[inline]
+    pub fn set_shadow_settings(&mut self, settings: ShadowSettings) {
+        self.shadow_settings = settings;
+    }
 
-        let mut uniforms = UniformVariableLayout::build()
-            .with("u_ModelViewMatrix", UniformVariableType::Matrix4f)
-            .with("u_MVPMatrix", UniformVariableType::Matrix4f)
-            .with("u_ViewNormalMatrix", UniformVariableType::Matrix4f)
-            .with("u_Ambient", UniformVariableType::Vector3f)
-            .with("u_Diffuse", UniformVariableType::Vector3f)
-            .with("u_Specular", UniformVariableType::Vector3f)
-            .with("u_Shininess", UniformVariableType::F32);
-        // .with("u_Texture", UniformVariableType::Texture);
-
-        let mut dir_lits = Vec::new();
-        let mut point_lits = Vec::new();
-
-        for i in 0..MAX_DIR_LITS {
-            let name = (
-                format!("u_DirLitViewDir[{0}]", i),
-                format!("u_DirLitColor[{0}]", i),
-            );
+    /// Gets the settings of shadows.
+    #[inline]
+    pub fn shadow_settings(&self) -> &ShadowSettings {
+        &self.shadow_settings
+    }
 
-            uniforms = uniforms
-                .with(name.0.as_ref(), UniformVariableType::Matrix4f)
-                .with(name.1.as_ref(), UniformVariableType::Matrix4f);
+    /// Sets the global environment settings.
+    #[inline]
+    pub fn set_environment(&mut self, environment: RenderEnvironment) {
+        self.environment = environment;
+    }
 
-            dir_lits.push(name);
-        }
+    /// Gets the global environment settings.
+    #[inline]
+    pub fn environment(&self) -> &RenderEnvironment {
+        &self.environment
+    }
 
-        for i in 0..MAX_POINT_LITS {
-            let name = (
-                format!("u_PointLitViewPos[{0}]", i),
-                format!("u_PointLitColor[{0}]", i),
-                format!("u_PointLitAttenuation[{0}]", i),
-            );
+    /// Sets the procedural sky which would be drawn as background. The ambient
+    /// lighting would be estimated from the sky also.
+    #[inline]
+    pub fn set_sky<T>(&mut self, sky: T)
+    where
+        T: Into<Option<Sky>>,
+    {
+        self.sky = sky.into();
+    }
 
-            uniforms = uniforms
-                .with(name.0.as_ref(), UniformVariableType::Matrix4f)
-                .with(name.1.as_ref(), UniformVariableType::Matrix4f)
-                .with(name.2.as_ref(), UniformVariableType::Matrix4f);
+    /// Gets the procedural sky.
+    #[inline]
+    pub fn sky(&self) -> Option<&Sky> {
+        self.sky.as_ref()
+    }
 
-            point_lits.push(name);
-        }
+    /// Sets the irradiance probe grid, which supplies the ambient lighting of objects
+    /// by their positions instead of the uniform ambient of environment.
+    #[inline]
+    pub fn set_probe_grid<T>(&mut self, probes: T)
+    where
+        T: Into<Option<ProbeGrid>>,
+    {
+        self.probes = probes.into();
+    }
 
-        let mut params = ShaderParams::default();
-        params.state.depth_write = true;
-        params.state.depth_test = Comparison::Less;
-        params.attributes = attributes;
-        params.uniforms = uniforms.finish();
+    /// Gets the irradiance probe grid.
+    #[inline]
+    pub fn probe_grid(&self) -> Option<&ProbeGrid> {
+        self.probes.as_ref()
+    }
+
+    /// Gets the mutable irradiance probe grid, which could be used to update the
+    /// probes at runtime.
+    #[inline]
+    pub fn probe_grid_mut(&mut self) -> Option<&mut ProbeGrid> {
+        self.probes.as_mut()
+    }
+
+    /// Sets the planar reflection, which draws the reflection and refraction textures
+    /// of water surfaces before the cameras which draw into the default surface. The
+    /// materials with `ShaderKeywords::WATER` sample these textures.
+    #[inline]
+    pub fn set_planar_reflection<T>(&mut self, reflection: T)
+    where
+        T: Into<Option<PlanarReflection>>,
+    {
+        self.reflection = reflection.into();
+    }
+
+    /// Gets the planar reflection.
+    #[inline]
+    pub fn planar_reflection(&self) -> Option<&PlanarReflection> {
+        self.reflection.as_ref()
+    }
+
+    /// Gets the mutable planar reflection, which could be used to move the plane or
+    /// advance the ripples at runtime.
+    #[inline]
+    pub fn planar_reflection_mut(&mut self) -> Option<&mut PlanarReflection> {
+        self.reflection.as_mut()
+    }
+
+    /// Sets the infinite ground plane, which is drawn after the objects of every
+    /// camera.
+    #[inline]
+    pub fn set_ground<T>(&mut self, ground: T)
+    where
+        T: Into<Option<GroundPlane>>,
+    {
+        self.ground = ground.into();
+    }
+
+    /// Gets the infinite ground plane.
+    #[inline]
+    pub fn ground(&self) -> Option<&GroundPlane> {
+        self.ground.as_ref()
+    }
+
+    /// Gets the mutable infinite ground plane, which could be used to scroll the tiles
+    /// at runtime.
+    #[inline]
+    pub fn ground_mut(&mut self) -> Option<&mut GroundPlane> {
+        self.ground.as_mut()
+    }
+
+    /// Adds a callback which is invoked at `stage` of every camera pass, it records
+    /// drawcalls into the view of camera.
+    pub fn add_render_callback<F>(
+        &mut self,
+        stage: RenderStage,
+        callback: F,
+    ) -> RenderCallbackHandle
+    where
+        F: FnMut(&mut RenderRecorder) + Send + 'static,
+    {
+        self.callbacks.add(stage, callback)
+    }
+
+    /// Removes the render callback.
+    pub fn remove_render_callback(&mut self, handle: RenderCallbackHandle) -> bool {
+        self.callbacks.remove(handle)
+    }
+
+    /// Gets the shader variants of objects, which could be used to prewarm the
+    /// permutations before loading scenes.
+    #[inline]
+    pub fn shader_variants_mut(&mut self) -> &mut ShaderVariants {
+        &mut self.variants
+    }
+
+    // Gets the sources of variant. They are written in GLSL ES 1.00, and compiled as
+    // `glsl` with the qualifiers and functions which are renamed since GLSL 1.30
+    // redefined by macros, when the uniform block of camera is used.
+    fn sources(defines: &str, glsl: Option<&str>) -> (String, String) {
+        let (version, vs_compat, fs_compat) = match glsl {
+            Some(version) => (version, GLSL3_VS_COMPAT, GLSL3_FS_COMPAT),
+            None => ("100", "", ""),
+        };
 
         let vs = format!(
             "
-            #version 100
+            #version {0}
             precision lowp float;
-
-            #define MAX_DIR_LITS {0}
-            #define MAX_POINT_LITS {1}
-            {2}
+            {1}
+            #define MAX_DIR_LITS {2}
+            #define MAX_POINT_LITS {3}
+            #define MAX_SKIN_BONES {4}
+            {5}
+            {6}
             ",
+            version,
+            vs_compat,
             MAX_DIR_LITS,
             MAX_POINT_LITS,
+            MAX_SKIN_BONES,
+            defines,
             include_str!("../../../assets/simple.vs")
         );
 
         let fs = format!(
             "
-            #version 100
+            #version {0}
             precision lowp float;
-
-            #define MAX_DIR_LITS {0}
-            #define MAX_POINT_LITS {1}
-            {2}
+            {1}
+            #define MAX_DIR_LITS {2}
+            #define MAX_POINT_LITS {3}
+            #define MAX_SKIN_BONES {4}
+            {5}
+            {6}
             ",
+            version,
+            fs_compat,
             MAX_DIR_LITS,
             MAX_POINT_LITS,
+            MAX_SKIN_BONES,
+            defines,
             include_str!("../../../assets/simple.fs")
         );
 
-        let shader = ctx.video.create_shader(params, vs, fs)?;
-
-        let params = SurfaceParams::default();
-        let surface = ctx.video.create_surface(params)?;
-
-        Ok(SimpleRenderer {
-            materials: Component::new(),
-            video: ctx.video.clone(),
-            surface: surface,
-            shader: shader,
-            drawcalls: OrderDrawBatch::new(),
-            dir_lits: dir_lits,
-            point_lits: point_lits,
-        })
+        (vs, fs)
     }
 
+    /// Sets the material of the first section of mesh.
     #[inline]
     pub fn add(&mut self, ent: Entity, material: SimpleMaterial) -> Option<SimpleMaterial> {
-        self.materials.add(ent, material)
+        self.add_sections(ent, vec![material])
+            .and_then(|v| v.into_iter().next())
+    }
+
+    /// Sets the materials of mesh sections. The material at `i`-th slot would be used
+    /// to draw the `i`-th sub-mesh, and sections without a slot fallback to the first
+    /// one.
+    #[inline]
+    pub fn add_sections(
+        &mut self,
+        ent: Entity,
+        materials: Vec<SimpleMaterial>,
+    ) -> Option<Vec<SimpleMaterial>> {
+        self.materials.add(ent, materials)
     }
 
     #[inline]
@@ -140,92 +1215,681 @@ impl SimpleRenderer {
 
     #[inline]
     pub fn material(&self, ent: Entity) -> Option<&SimpleMaterial> {
-        self.materials.get(ent)
+        self.section_material(ent, 0)
     }
 
     #[inline]
     pub fn material_mut(&mut self, ent: Entity) -> Option<&mut SimpleMaterial> {
-        self.materials.get_mut(ent)
+        self.section_material_mut(ent, 0)
+    }
+
+    #[inline]
+    pub fn section_material(&self, ent: Entity, slot: usize) -> Option<&SimpleMaterial> {
+        self.materials.get(ent).and_then(|v| v.get(slot))
+    }
+
+    #[inline]
+    pub fn section_material_mut(
+        &mut self,
+        ent: Entity,
+        slot: usize,
+    ) -> Option<&mut SimpleMaterial> {
+        self.materials.get_mut(ent).and_then(|v| v.get_mut(slot))
     }
 
     #[inline]
     pub fn remove(&mut self, ent: Entity) {
         self.materials.remove(ent)
     }
+
+    /// Sets the matrices of bones which deform the skinned mesh, e.g. the
+    /// `Animator::skin_matrices`. The meshes with `Indices` and `Weight` attributes are
+    /// drawn with the `SKINNED` variants if they have skins, and only the first
+    /// `MAX_SKIN_BONES` bones are used.
+    pub fn set_skin(&mut self, ent: Entity, matrices: &[math::Matrix4<f32>]) {
+        let len = matrices.len().min(MAX_SKIN_BONES);
+        self.skins.add(ent, matrices[0..len].to_vec());
+    }
+
+    #[inline]
+    pub fn remove_skin(&mut self, ent: Entity) {
+        self.skins.remove(ent)
+    }
 }
 
-impl super::Renderer for SimpleRenderer {
-    fn submit(&mut self, camera: &Camera, lits: &[Lit], meshes: &[MeshRenderer]) {
-        use crayon::math::{Matrix, MetricSpace, SquareMatrix};
+// The view and destination of a pass which draws the scene.
+struct ScenePass {
+    view_matrix: math::Matrix4<f32>,
+    projection_matrix: math::Matrix4<f32>,
+    eye: math::Vector3<f32>,
+    surface: SurfaceHandle,
+    mode: DebugDrawMode,
+    // The clip plane in view space.
+    clip: Option<math::Vector4<f32>>,
+    // Whether this pass draws into the textures of planar reflection, which skips the
+    // water surfaces.
+    reflected: bool,
+    // The textures of planar reflection, which are sampled by water surfaces.
+    water: Option<WaterTextures>,
+    // The cascaded shadow map, which is sampled by shadow receivers.
+    shadows: Option<ShadowMaps>,
+}
+
+#[derive(Debug, Copy, Clone)]
+struct WaterTextures {
+    reflection: RenderTextureHandle,
+    refraction: RenderTextureHandle,
+    params: math::Vector4<f32>,
+    screen_size: math::Vector2<f32>,
+}
+
+#[derive(Debug, Copy, Clone)]
+struct ShadowMaps {
+    texture: RenderTextureHandle,
+    matrices: [math::Matrix4<f32>; MAX_SHADOW_CASCADES],
+    splits: math::Vector4<f32>,
+    params: math::Vector4<f32>,
+}
+
+impl SimpleRenderer {
+    // Draws the reflection and refraction textures of water surfaces with camera. The
+    // textures are still usable if some drawcalls failed, so the first error of passes
+    // is returned along with them.
+    fn draw_planar_reflection(
+        &mut self,
+        camera: &Camera,
+        lits: &[Lit],
+        meshes: &[MeshRenderer],
+        screen_size: math::Vector2<u32>,
+    ) -> (Option<WaterTextures>, Result<()>) {
+        let reflection = match self.reflection {
+            Some(v) => v,
+            None => return (None, Ok(())),
+        };
+
+        let (reflected, refracted) = match self.prepare_reflection_target(&reflection) {
+            Ok(v) => v,
+            Err(err) => {
+                warn!("Failed to prepare the planar reflection target: {}", err);
+                return (None, Ok(()));
+            }
+        };
 
-        let view_matrix = camera.transform.view_matrix();
         let projection_matrix = camera.frustum().to_matrix();
-        let mut lits = Vec::from(lits);
 
-        for mesh in meshes {
+        // Draws the scene above the plane with mirrored camera.
+        let mirror = reflection.mirror(&camera.transform);
+        let view_matrix = mirror.view_matrix();
+        let pass = ScenePass {
+            view_matrix: view_matrix,
+            projection_matrix: projection_matrix,
+            eye: mirror.position,
+            surface: reflected.surface,
+            mode: DebugDrawMode::Shaded,
+            clip: Some(reflection.clip_plane(view_matrix, true)),
+            reflected: true,
+            water: None,
+            shadows: None,
+        };
+
+        let result = self.draw_scene(&pass, lits, meshes);
+
+        // Grabs the scene below the plane with camera itself.
+        let view_matrix = camera.transform.view_matrix();
+        let pass = ScenePass {
+            view_matrix: view_matrix,
+            projection_matrix: projection_matrix,
+            eye: camera.transform.position,
+            surface: refracted.surface,
+            mode: DebugDrawMode::Shaded,
+            clip: Some(reflection.clip_plane(view_matrix, false)),
+            reflected: true,
+            water: None,
+            shadows: None,
+        };
+
+        let result = result.and(self.draw_scene(&pass, lits, meshes));
+
+        let water = WaterTextures {
+            reflection: reflected.color,
+            refraction: refracted.color,
+            params: reflection.encode(),
+            screen_size: math::Vector2::new(screen_size.x as f32, screen_size.y as f32),
+        };
+
+        (Some(water), result)
+    }
+
+    // Prepares the atlas of shadow cascades.
+    fn prepare_shadow_target(&mut self, resolution: u32, cascades: usize) -> Result<ColorTarget> {
+        let size = if cascades > 1 {
+            resolution * 2
+        } else {
+            resolution
+        };
+
+        let dimensions = math::Vector2::new(size, size);
+        let recreate = match self.shadow_target {
+            Some(ref v) => v.dimensions != dimensions,
+            None => true,
+        };
+
+        if recreate {
+            if let Some(v) = self.shadow_target.take() {
+                v.target.delete(&self.video);
+            }
+
+            // Clears to the farthest depth.
+            let target = ColorTarget::new(&self.video, dimensions, math::Color::white())?;
+            self.shadow_target = Some(ShadowTarget {
+                target: target,
+                dimensions: dimensions,
+            });
+        }
+
+        Ok(self.shadow_target.as_ref().unwrap().target)
+    }
+
+    // Draws the depths of shadow casters into the cascades of the first directional
+    // light.
+    fn draw_shadows(
+        &mut self,
+        camera: &Camera,
+        lits: &[Lit],
+        meshes: &[MeshRenderer],
+    ) -> Option<ShadowMaps> {
+        use crayon::math::SquareMatrix;
+
+        // The shader applies the shadows to the first directional light in buffer, so
+        // the other ones never cast shadows even if they are shadow casters.
+        let lit = lits.iter().find(|v| match v.source {
+            LitSource::Dir => v.enable,
+            _ => false,
+        })?;
+
+        if !lit.shadow_caster {
+            return None;
+        }
+
+        let settings = self.shadow_settings;
+        let cascades = fit_cascades(camera, lit, &settings);
+        if cascades.is_empty() {
+            return None;
+        }
+
+        let resolution = lit.shadow_resolution.unwrap_or(settings.resolution).max(1);
+        let target = match self.prepare_shadow_target(resolution, cascades.len()) {
+            Ok(v) => v,
+            Err(err) => {
+                warn!("Failed to prepare the shadow map: {}", err);
+                return None;
+            }
+        };
+
+        let size = self.shadow_target.as_ref().unwrap().dimensions.x as f32;
+        let mut maps = ShadowMaps {
+            texture: target.color,
+            matrices: [math::Matrix4::identity(); MAX_SHADOW_CASCADES],
+            splits: math::Vector4::new(0.0, 0.0, 0.0, 0.0),
+            params: math::Vector4::new(
+                cascades.len() as f32,
+                1.0 / size,
+                settings.depth_bias,
+                if settings.soft { 1.0 } else { 0.0 },
+            ),
+        };
+
+        for (i, cascade) in cascades.iter().enumerate() {
+            // Every cascade is drawn into its own tile of atlas.
+            let (scale, offset) = atlas_tile(i, cascades.len());
+            let (offset, tile) = (offset * size, (scale * size) as u32);
+            let viewport = SurfaceViewport {
+                position: math::Vector2::new(offset.x as u32, offset.y as u32),
+                size: math::Vector2::new(tile, tile),
+            };
+
+            self.video.update_viewport(target.surface, viewport);
+
+            let view_projection = cascade.view_projection();
+            for mesh in meshes.iter().filter(|v| v.visible && v.shadow_caster) {
+                let mut dc = DrawCall::new(self.shadow_shader, mesh.mesh);
+                dc.set_uniform_variable("u_MVPMatrix", view_projection * mesh.transform.matrix());
+                self.video.draw(target.surface, dc);
+            }
+
+            maps.matrices[i] = cascade.atlas_matrix(i, cascades.len());
+            maps.splits[i] = cascade.split;
+        }
+
+        Some(maps)
+    }
+
+    // Uploads the camera and lights of pass into uniform buffers once, which are shared
+    // by all the drawcalls of pass. The drawcalls are submitted after binding, so they
+    // read the blocks of this pass even if there are several passes in a frame.
+    fn update_camera_block(&mut self, inv_view_matrix: math::Matrix4<f32>) -> Result<()> {
+        if let Some((buffer, ref mut block)) = self.camera_block {
+            block.set("u_InvViewMatrix", inv_view_matrix)?;
+            self.video
+                .update_uniform_buffer(buffer, 0, block.as_bytes())?;
+            self.video.bind_uniform_buffer(CAMERA_BLOCK_SLOT, buffer)?;
+        }
+
+        self.lights.upload(LIGHTS_BLOCK_SLOT)?;
+        Ok(())
+    }
+
+    // Draws the meshes in pass. The invalid drawcalls are dropped, and the first error is
+    // returned after the rest of scene is drawn.
+    fn draw_scene(
+        &mut self,
+        pass: &ScenePass,
+        lits: &[Lit],
+        meshes: &[MeshRenderer],
+    ) -> Result<()> {
+        use crayon::math::{Matrix, MetricSpace, SquareMatrix};
+
+        let view_matrix = pass.view_matrix;
+        let projection_matrix = pass.projection_matrix;
+        let surface = pass.surface;
+
+        // The objects are shaded as usual under the wireframe overlay.
+        let mode = pass.mode;
+        let shading = match mode {
+            DebugDrawMode::Wireframe => DebugDrawMode::Shaded,
+            v => v,
+        };
+
+        self.lights.build(view_matrix, pass.eye, lits);
+        let inv_view_matrix = view_matrix.invert().unwrap_or(math::Matrix4::identity());
+
+        if let Err(err) = self.update_camera_block(inv_view_matrix) {
+            warn!("Failed to update the uniform block of camera: {}", err);
+        }
+
+        let fog = self.environment.fog.encode();
+
+        let mut env_ambient = math::Color::white();
+
+        if let Some(sky) = self.sky {
+            let sun_dir = sky.sun_dir(lits);
+            env_ambient = sky.ambient(sun_dir);
+
+            // Removes the translation of view, so only the directions of rays matter.
+            let mut rotation = view_matrix;
+            rotation.w = math::Vector4::new(0.0, 0.0, 0.0, 1.0);
+            let inv = (projection_matrix * rotation)
+                .invert()
+                .unwrap_or(math::Matrix4::identity());
+
+            let mut dc = DrawCall::new(self.sky_shader, self.sky_mesh);
+            dc.set_uniform_variable("u_InvViewProjMatrix", inv);
+            dc.set_uniform_variable("u_SunDir", sun_dir);
+            dc.set_uniform_variable("u_SunColor", sky.sun_color.rgb());
+            dc.set_uniform_variable("u_SunSize", sky.sun_size);
+            dc.set_uniform_variable("u_ZenithColor", sky.zenith.rgb());
+            dc.set_uniform_variable("u_HorizonColor", sky.horizon.rgb());
+            dc.set_uniform_variable("u_GroundColor", sky.ground.rgb());
+            self.video.draw(surface, dc);
+        }
+
+        // Objects outside of probe grid, or without grid at all, fallback to the
+        // uniform ambient of environment.
+        let env_sh = ShIrradiance::from_color(env_ambient);
+
+        for mesh in meshes.iter().filter(|v| v.visible) {
             let model_matrix = mesh.transform.matrix();
             let mv = view_matrix * model_matrix;
             let mvp = projection_matrix * mv;
             let vn = mv.invert().and_then(|v| Some(v.transpose())).unwrap_or(mv);
 
-            let mut dc = DrawCall::new(self.shader, mesh.mesh);
+            let layout = self.video.mesh_layout(mesh.mesh);
+            let has = |attribute, size| {
+                layout
+                    .as_ref()
+                    .and_then(|v| v.element(attribute))
+                    .map_or(false, |v| v.size >= size)
+            };
+
+            // Uses the lightmapped variant only if the mesh has the second texcoords.
+            let lightmap = match mesh.lightmap {
+                Some(v) if has(Attribute::Texcoord1, 2) => Some(v),
+                _ => None,
+            };
+
+            // The normal maps of materials are sampled only if the mesh has tangents.
+            let tangents = has(Attribute::Texcoord0, 2) && has(Attribute::Tangent, 4);
+
+            // The shader is picked by the material of every section below, and the
+            // uniforms which are inactive in it are stripped.
+            let mut dc = DrawCall::new(ShaderHandle::default(), mesh.mesh);
+            if let Some(v) = lightmap {
+                dc.set_uniform_variable("u_Lightmap", v.texture);
+                dc.set_uniform_variable("u_LightmapScaleOffset", v.scale_offset);
+            }
+
+            let skinned = match self.skins.get(mesh.ent) {
+                Some(v) if has(Attribute::Indices, 4) && has(Attribute::Weight, 4) => {
+                    for i in 0..MAX_SKIN_BONES {
+                        let m = v.get(i).cloned().unwrap_or(math::Matrix4::identity());
+                        dc.set_uniform_variable(format!("u_BoneMatrices[{0}]", i), m);
+                    }
+
+                    true
+                }
+                _ => false,
+            };
+
+            if let Some(v) = pass.clip {
+                dc.set_uniform_variable("u_ClipPlane", v);
+            }
+
+            // The lightmaps have the shadows of directional light baked already.
+            let shadows = match pass.shadows {
+                Some(v) if mesh.shadow_receiver && lightmap.is_none() => Some(v),
+                _ => None,
+            };
+
+            if let Some(v) = shadows {
+                dc.set_uniform_variable("u_ShadowMap", v.texture);
+                dc.set_uniform_variable("u_ShadowSplits", v.splits);
+                dc.set_uniform_variable("u_ShadowParams", v.params);
+                for (i, &m) in v.matrices.iter().enumerate() {
+                    dc.set_uniform_variable(format!("u_ShadowMatrices[{0}]", i), m);
+                }
+            }
+
             dc.set_uniform_variable("u_ModelViewMatrix", mv);
             dc.set_uniform_variable("u_MVPMatrix", mvp);
             dc.set_uniform_variable("u_ViewNormalMatrix", vn);
 
-            let mat = self.material(mesh.ent).cloned().unwrap_or_default();
-            dc.set_uniform_variable("u_Ambient", mat.ambient.rgb());
-            dc.set_uniform_variable("u_Diffuse", mat.diffuse.rgb());
-            dc.set_uniform_variable("u_Specular", mat.specular.rgb());
-            dc.set_uniform_variable("u_Shininess", mat.shininess);
-
-            lits.sort_by_key(|v| mesh.transform.position.distance2(v.transform.position) as u32);
-
-            let (mut dir_index, mut point_index) = (0, 0);
-            for lit in &lits {
-                match lit.source {
-                    LitSource::Dir => {
-                        if dir_index < self.dir_lits.len() {
-                            let names = &self.dir_lits[dir_index];
-                            let dir = view_matrix * lit.transform.forward().extend(1.0);
-                            dc.set_uniform_variable(&names.0, dir.truncate());
-                            dc.set_uniform_variable(&names.1, lit.color.rgb());
-                            dir_index += 1;
-                        }
+            dc.set_uniform_variable("u_FogColor", self.environment.fog.color.rgb());
+            if self.camera_block.is_none() {
+                dc.set_uniform_variable("u_InvViewMatrix", inv_view_matrix);
+            }
+
+            if self.lights.uniform_buffer().is_none() {
+                self.lights.bind(&mut dc);
+            }
+
+            match self.probes {
+                Some(ref probes) => probes.sample(mesh.transform.position).bind(&mut dc),
+                None => env_sh.bind(&mut dc),
+            }
+
+            let zorder = mesh.transform.position.distance2(pass.eye) as u32;
+
+            // Draws every sub-mesh section with its own material slot.
+            let sections = self.video.mesh_sub_meshes(mesh.mesh).unwrap_or(1);
+            for i in 0..sections {
+                let mat = self.section_material(mesh.ent, i)
+                    .or_else(|| self.material(mesh.ent))
+                    .cloned()
+                    .unwrap_or_default();
+
+                // The water surfaces never reflect themselves.
+                let water = mat.keywords.contains(ShaderKeywords::WATER);
+                if water && pass.reflected {
+                    continue;
+                }
+
+                // Picks the variant with keywords of material and mesh.
+                let mut keywords = mat.keywords;
+                keywords.set(ShaderKeywords::FOG, mat.fog);
+                keywords.set(ShaderKeywords::LIGHTMAP, lightmap.is_some());
+                keywords.set(ShaderKeywords::CLIP_PLANE, pass.clip.is_some());
+                keywords.set(ShaderKeywords::WATER, water && pass.water.is_some());
+                keywords.set(ShaderKeywords::SHADOWS, shadows.is_some());
+                keywords.set(
+                    ShaderKeywords::NORMALMAP,
+                    mat.normal_map.is_some() && tangents,
+                );
+                keywords.set(ShaderKeywords::SKINNED, skinned);
+
+                let shader = match self.variant(shading, keywords) {
+                    Ok(v) => v,
+                    Err(err) => {
+                        warn!("Failed to compile the shader variant: {}", err);
+                        continue;
                     }
-                    LitSource::Point { radius, smoothness } => {
-                        if point_index < self.point_lits.len() {
-                            let names = &self.point_lits[point_index];
-                            let pos = view_matrix * lit.transform.position.extend(1.0);
-                            let attenuation = math::Vector3::new(
-                                1.0,
-                                -1.0 / (radius + smoothness * radius * radius),
-                                -smoothness / (radius + smoothness * radius * radius),
-                            );
-                            dc.set_uniform_variable(&names.0, pos.truncate());
-                            dc.set_uniform_variable(&names.1, lit.color.rgb());
-                            dc.set_uniform_variable(&names.2, attenuation);
-
-                            point_index += 1;
+                };
+
+                let mut dc = dc;
+                if sections > 1 {
+                    dc.mesh_index = MeshIndex::SubMesh(i);
+                }
+
+                if let (true, Some(v)) = (water, pass.water) {
+                    dc.set_uniform_variable("u_ReflectionTexture", v.reflection);
+                    dc.set_uniform_variable("u_RefractionTexture", v.refraction);
+                    dc.set_uniform_variable("u_WaterParams", v.params);
+                    dc.set_uniform_variable("u_ScreenSize", v.screen_size);
+                }
+
+                if let Some(v) = mat.normal_map {
+                    dc.set_uniform_variable("u_NormalMap", v);
+                }
+
+                dc.set_uniform_variable("u_Ambient", mat.ambient.rgb());
+                dc.set_uniform_variable("u_Diffuse", mat.diffuse.rgb());
+                dc.set_uniform_variable("u_Specular", mat.specular.rgb());
+                dc.set_uniform_variable("u_Shininess", mat.shininess);
+                dc.set_uniform_variable("u_FogParams", fog);
+
+                let order = DrawOrder::new(shader, false, zorder);
+                let v = self.strip(dc, shader, &mesh.properties);
+                self.drawcalls.draw(order, v);
+
+                if mode == DebugDrawMode::Wireframe {
+                    match self.variant(DebugDrawMode::Wireframe, keywords) {
+                        Ok(wireframe) => {
+                            let v = self.strip(dc, wireframe, &mesh.properties);
+                            self.overlay_drawcalls.draw(order, v);
                         }
+                        Err(err) => warn!("Failed to compile the shader variant: {}", err),
                     }
                 }
             }
+        }
 
-            let order = DrawOrder::new(
-                self.shader,
-                false,
-                mesh.transform.position.distance2(camera.transform.position) as u32,
-            );
+        let drawn = self.drawcalls.submit(&self.video, surface);
+        let overlay = self.overlay_drawcalls.submit(&self.video, surface);
+        self.invoke_callbacks(RenderStage::AfterOpaque, pass, surface);
+
+        // The ground plane is blended with the objects behind it when fading out.
+        if let Some(ground) = self.ground {
+            if let Err(err) = self.draw_ground(pass, &ground) {
+                warn!("Failed to draw the ground plane: {}", err);
+            }
+        }
+
+        // The shadows darken the ground plane too.
+        if let Err(err) = self.draw_blob_shadows(pass) {
+            warn!("Failed to draw the blob shadows: {}", err);
+        }
+
+        self.invoke_callbacks(RenderStage::BeforeTransparents, pass, surface);
+
+        if let Err(err) = self.draw_ribbons(pass) {
+            warn!("Failed to draw the lines and trails: {}", err);
+        }
+
+        drawn.and(overlay)
+    }
+}
+
+impl SimpleRenderer {
+    // Binds the drawcall to the variant, strips the uniforms which are inactive in it
+    // and applies the property block.
+    fn strip(
+        &self,
+        mut dc: DrawCall,
+        shader: ShaderHandle,
+        properties: &MaterialPropertyBlock,
+    ) -> DrawCall {
+        dc.shader = shader;
+        if let Some(layout) = self.layouts.get(&shader) {
+            dc.retain_uniforms(|field| layout.variable_type(field).is_some());
+            properties.apply(layout, &mut dc);
+        }
+
+        dc
+    }
+
+    // Invokes the render callbacks at stage, which are skipped in the reflected passes.
+    fn invoke_callbacks(&mut self, stage: RenderStage, pass: &ScenePass, surface: SurfaceHandle) {
+        if pass.reflected || self.callbacks.is_empty(stage) {
+            return;
+        }
+
+        let mut recorder = RenderRecorder::new(
+            &self.video,
+            surface,
+            stage,
+            pass.view_matrix,
+            pass.projection_matrix,
+            pass.eye,
+        );
+
+        self.callbacks.invoke(&mut recorder);
+    }
+}
+
+impl super::Renderer for SimpleRenderer {
+    fn submit(&mut self, camera: &Camera, lits: &[Lit], meshes: &[MeshRenderer]) -> Result<()> {
+        let destination = camera.surface().unwrap_or(self.surface);
+        let scaled = match self.prepare_scaled_target(camera) {
+            Ok(v) => v,
+            Err(err) => {
+                warn!("Failed to prepare the render scale target: {}", err);
+                None
+            }
+        };
+
+        let (surface, screen_size) = match scaled {
+            Some((surface, viewport)) => (surface, viewport.size),
+            None => (destination, self.window.dimensions()),
+        };
+
+        // The planar reflection only works with the default surface, since the targets
+        // are shared by all the cameras.
+        let (water, result) = if camera.surface().is_none() {
+            self.draw_planar_reflection(camera, lits, meshes, screen_size)
+        } else {
+            (None, Ok(()))
+        };
+
+        let shadows = self.draw_shadows(camera, lits, meshes);
 
-            self.drawcalls.draw(order, dc);
+        // The viewport is set after the passes above, since binding their targets
+        // resets the viewport of this surface.
+        if let Some((surface, viewport)) = scaled {
+            self.video.update_viewport(surface, viewport);
         }
 
-        let surface = camera.surface().unwrap_or(self.surface);
-        self.drawcalls.submit(&self.video, surface).unwrap();
+        let pass = ScenePass {
+            view_matrix: camera.transform.view_matrix(),
+            projection_matrix: camera.frustum().to_matrix(),
+            eye: camera.transform.position,
+            surface: surface,
+            mode: camera.debug_mode(),
+            clip: None,
+            reflected: false,
+            water: water,
+            shadows: shadows,
+        };
+
+        // Only the scene of camera itself is culled, the shadow casters and reflected
+        // objects outside of its frustum might still be visible.
+        let mut visible = ::std::mem::replace(&mut self.visible, Vec::new());
+        visible.clear();
+
+        let culling = {
+            let video = &self.video;
+            cull_cached(
+                &mut self.visibility,
+                camera,
+                meshes,
+                |v| video.mesh_aabb(v),
+                &mut visible,
+            )
+        };
+        self.video.report_culling(camera.view_id(), culling);
+
+        let result = result.and(self.draw_scene(&pass, lits, &visible));
+        self.visible = visible;
+
+        if let Some((_, viewport)) = scaled {
+            self.upscale(camera, destination, viewport);
+        }
+
+        self.invoke_callbacks(RenderStage::AfterPost, &pass, destination);
+        result
+    }
+
+    fn set_ribbons(&mut self, ribbons: Vec<Ribbon>) {
+        self.ribbons = ribbons;
+    }
+
+    fn set_blob_shadows(&mut self, shadows: Vec<BlobQuad>) {
+        self.blob_shadows = shadows;
+    }
+
+    fn remap_entities(&mut self, remap: &EntityRemap) {
+        self.materials.remap_entities(remap);
+        self.skins.remap_entities(remap);
+        self.visibility.clear();
+
+        let targets = ::std::mem::replace(&mut self.scaled_targets, HashMap::new());
+        for ((owner, ent), v) in targets {
+            let ent = remap.get(ent).unwrap_or(ent);
+            self.scaled_targets.insert((owner, ent), v);
+        }
     }
 }
 
+// Uploads the vertices and indices into the streaming mesh in `slot`, which grows to
+// fit them. The unused parts at the end are never drawn.
+fn stream_mesh(
+    video: &VideoSystemShared,
+    slot: &mut Option<(MeshHandle, usize, usize)>,
+    layout: VertexLayout,
+    (vertices, num_verts): (&[u8], usize),
+    indices: &[u16],
+) -> Result<MeshHandle> {
+    let (mesh, verts) = match *slot {
+        Some((mesh, verts, idxes)) if verts >= num_verts && idxes >= indices.len() => {
+            (mesh, verts)
+        }
+        _ => {
+            if let Some((mesh, _, _)) = slot.take() {
+                video.delete_mesh(mesh);
+            }
+
+            let verts = num_verts.next_power_of_two().max(256);
+            let idxes = indices.len().next_power_of_two().max(256);
+
+            let mut params = MeshParams::default();
+            params.hint = MeshHint::Stream;
+            params.layout = layout;
+            params.index_format = IndexFormat::from_vertex_count(verts);
+            params.primitive = MeshPrimitive::Triangles;
+            params.num_verts = verts;
+            params.num_idxes = idxes;
+
+            let mesh = video.create_mesh(params, None)?;
+            *slot = Some((mesh, verts, idxes));
+            (mesh, verts)
+        }
+    };
+
+    // The index format is decided by the capacity of vertices.
+    let format = IndexFormat::from_vertex_count(verts);
+    video.update_vertex_buffer(mesh, 0, vertices)?;
+    video.update_index_buffer(mesh, 0, &format.encode_u16(indices))?;
+    Ok(mesh)
+}
+
 #[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord)]
 struct DrawOrder(u64);
 
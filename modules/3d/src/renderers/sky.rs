@@ -0,0 +1,83 @@
+//! Procedural sky which could be used as the background of cameras.
+
+use crayon::math::{self, InnerSpace};
+
+use super::{Lit, LitSource};
+
+/// The direction of sun.
+#[derive(Debug, Clone, Copy)]
+pub enum SkySun {
+    /// Follows the direction of the first enabled directional light.
+    Lit,
+    /// The direction towards the sun in world space.
+    Dir(math::Vector3<f32>),
+}
+
+/// A procedural sky with simple gradient and sun disk.
+#[derive(Debug, Clone, Copy)]
+pub struct Sky {
+    /// The color of sky right above the viewer.
+    pub zenith: math::Color<f32>,
+    /// The color of sky at horizon.
+    pub horizon: math::Color<f32>,
+    /// The color of ground below the horizon.
+    pub ground: math::Color<f32>,
+    /// The color of sun disk.
+    pub sun_color: math::Color<f32>,
+    /// The angular radius of sun disk, in radians.
+    pub sun_size: f32,
+    /// The direction of sun.
+    pub sun: SkySun,
+}
+
+impl Default for Sky {
+    fn default() -> Self {
+        Sky {
+            zenith: math::Color::new(0.15, 0.35, 0.75, 1.0),
+            horizon: math::Color::new(0.65, 0.75, 0.85, 1.0),
+            ground: math::Color::new(0.3, 0.28, 0.25, 1.0),
+            sun_color: math::Color::new(1.0, 0.95, 0.85, 1.0),
+            sun_size: 0.02,
+            sun: SkySun::Lit,
+        }
+    }
+}
+
+impl Sky {
+    /// Resolves the normalized direction towards the sun in world space.
+    pub fn sun_dir(&self, lits: &[Lit]) -> math::Vector3<f32> {
+        let dir = match self.sun {
+            SkySun::Dir(v) => v,
+            SkySun::Lit => lits
+                .iter()
+                .find(|v| match v.source {
+                    LitSource::Dir => v.enable,
+                    _ => false,
+                })
+                .map(|v| -v.transform.forward())
+                .unwrap_or_else(|| math::Vector3::new(0.0, 1.0, 0.0)),
+        };
+
+        if dir.magnitude2() > 0.0 {
+            dir.normalize()
+        } else {
+            math::Vector3::new(0.0, 1.0, 0.0)
+        }
+    }
+
+    /// Estimates the ambient irradiance of this sky, by integrating the gradient over
+    /// the upper hemisphere and adding the scattered sun light.
+    pub fn ambient(&self, sun_dir: math::Vector3<f32>) -> math::Color<f32> {
+        let elevation = sun_dir.y.max(0.0);
+        let f = |zenith: f32, horizon: f32, ground: f32, sun: f32| {
+            zenith * 0.45 + horizon * 0.4 + ground * 0.15 + sun * elevation * 0.1
+        };
+
+        math::Color::new(
+            f(self.zenith.r, self.horizon.r, self.ground.r, self.sun_color.r),
+            f(self.zenith.g, self.horizon.g, self.ground.g, self.sun_color.g),
+            f(self.zenith.b, self.horizon.b, self.ground.b, self.sun_color.b),
+            1.0,
+        )
+    }
+}
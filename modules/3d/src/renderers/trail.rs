@@ -0,0 +1,418 @@
+//! Lines and trails, which are extruded into ribbons facing the camera.
+//!
+//! A `LineRenderer` draws a fixed polyline in world space, e.g. the visualization of
+//! paths. A `TrailRenderer` records the positions of its entity over time and fades
+//! them out, e.g. the trails behind projectiles and sword swipes.
+//!
+//! ```rust,ignore
+//! world.renderables.add_trail(sword, TrailRenderer::new(Duration::from_millis(300)));
+//!
+//! // Every frame.
+//! world.renderables.advance_trails(&world.scene, ctx.time.frame_delta());
+//! ```
+
+use std::collections::VecDeque;
+use std::time::Duration;
+
+use crayon::math::{self, InnerSpace};
+use crayon::video::assets::texture::TextureHandle;
+
+/// A piecewise linear curve of widths, which is evaluated over the normalized length
+/// of lines, or the normalized age of trails.
+#[derive(Debug, Clone, PartialEq)]
+pub struct WidthCurve {
+    keys: Vec<(f32, f32)>,
+}
+
+impl WidthCurve {
+    /// Creates a curve with constant width.
+    pub fn constant(width: f32) -> Self {
+        WidthCurve {
+            keys: vec![(0.0, width)],
+        }
+    }
+
+    /// Creates a curve which goes from `start` to `end` linearly.
+    pub fn linear(start: f32, end: f32) -> Self {
+        WidthCurve {
+            keys: vec![(0.0, start), (1.0, end)],
+        }
+    }
+
+    /// Adds a key at normalized time `t`.
+    pub fn with_key(mut self, t: f32, width: f32) -> Self {
+        insert(&mut self.keys, t, width);
+        self
+    }
+
+    /// Evaluates the width at normalized time `t`.
+    pub fn evaluate(&self, t: f32) -> f32 {
+        sample(&self.keys, t, |lhs, rhs, t| lhs + (rhs - lhs) * t)
+    }
+}
+
+/// A piecewise linear gradient of colors, which is evaluated over the normalized length
+/// of lines, or the normalized age of trails.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ColorGradient {
+    keys: Vec<(f32, math::Color<f32>)>,
+}
+
+impl ColorGradient {
+    /// Creates a gradient with constant color.
+    pub fn constant(color: math::Color<f32>) -> Self {
+        ColorGradient {
+            keys: vec![(0.0, color)],
+        }
+    }
+
+    /// Creates a gradient which goes from `start` to `end` linearly.
+    pub fn linear(start: math::Color<f32>, end: math::Color<f32>) -> Self {
+        ColorGradient {
+            keys: vec![(0.0, start), (1.0, end)],
+        }
+    }
+
+    /// Adds a key at normalized time `t`.
+    pub fn with_key(mut self, t: f32, color: math::Color<f32>) -> Self {
+        insert(&mut self.keys, t, color);
+        self
+    }
+
+    /// Evaluates the color at normalized time `t`.
+    pub fn evaluate(&self, t: f32) -> math::Color<f32> {
+        sample(&self.keys, t, |lhs, rhs, t| {
+            let (lhs, rhs) = (lhs.rgba(), rhs.rgba());
+            let f = |i: usize| lhs[i] + (rhs[i] - lhs[i]) * t;
+            math::Color::new(f(0), f(1), f(2), f(3))
+        })
+    }
+}
+
+fn insert<T>(keys: &mut Vec<(f32, T)>, t: f32, v: T) {
+    let t = t.max(0.0).min(1.0);
+    let index = keys.iter().position(|k| k.0 > t).unwrap_or(keys.len());
+    keys.insert(index, (t, v));
+}
+
+fn sample<T, F>(keys: &[(f32, T)], t: f32, lerp: F) -> T
+where
+    T: Copy,
+    F: Fn(T, T, f32) -> T,
+{
+    let index = keys.iter().position(|k| k.0 > t).unwrap_or(keys.len());
+    if index == 0 {
+        return keys[0].1;
+    }
+
+    if index == keys.len() {
+        return keys[index - 1].1;
+    }
+
+    let (lhs, rhs) = (keys[index - 1], keys[index]);
+    lerp(lhs.1, rhs.1, (t - lhs.0) / (rhs.0 - lhs.0))
+}
+
+/// How the texture is mapped along ribbons.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LineTextureMode {
+    /// Stretches the texture once along the whole ribbon.
+    Stretch,
+    /// Repeats the texture every `tile_length` units in world space.
+    Tile,
+}
+
+/// The vertex of extruded ribbon.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RibbonVertex {
+    pub position: math::Vector3<f32>,
+    pub texcoord: math::Vector2<f32>,
+    pub color: math::Color<f32>,
+}
+
+/// A point of ribbon.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RibbonPoint {
+    pub position: math::Vector3<f32>,
+    pub width: f32,
+    pub color: math::Color<f32>,
+    /// The texture coordinate along the ribbon.
+    pub u: f32,
+}
+
+/// A polyline in world space, which is extruded towards the camera when drawing.
+#[derive(Debug, Clone)]
+pub struct Ribbon {
+    pub points: Vec<RibbonPoint>,
+    pub texture: Option<TextureHandle>,
+}
+
+impl Ribbon {
+    /// Extrudes the points into pairs of vertices facing the `eye`, the texture
+    /// coordinate across the ribbon goes from 0 to 1.
+    pub fn extrude(&self, eye: math::Vector3<f32>) -> Vec<RibbonVertex> {
+        let len = self.points.len();
+
+        let mut vertices = Vec::with_capacity(len * 2);
+        for (i, v) in self.points.iter().enumerate() {
+            let prev = self.points[i.saturating_sub(1)].position;
+            let next = self.points[(i + 1).min(len - 1)].position;
+            let tangent = next - prev;
+
+            let mut side = tangent.cross(eye - v.position);
+            if side.magnitude2() <= ::std::f32::EPSILON {
+                side = tangent.cross(math::Vector3::unit_y());
+            }
+
+            if side.magnitude2() <= ::std::f32::EPSILON {
+                side = math::Vector3::unit_x();
+            }
+
+            let side = side.normalize() * (v.width * 0.5);
+            for &(offset, t) in &[(-side, 0.0), (side, 1.0)] {
+                vertices.push(RibbonVertex {
+                    position: v.position + offset,
+                    texcoord: math::Vector2::new(v.u, t),
+                    color: v.color,
+                });
+            }
+        }
+
+        vertices
+    }
+
+    /// Gets the indices of triangles of the extruded vertices, which start at `base`.
+    pub fn indices(&self, base: u16) -> Vec<u16> {
+        let segments = self.points.len().saturating_sub(1) as u16;
+
+        let mut indices = Vec::with_capacity(segments as usize * 6);
+        for i in 0..segments {
+            let v = base + i * 2;
+            indices.extend_from_slice(&[v, v + 1, v + 3, v, v + 3, v + 2]);
+        }
+
+        indices
+    }
+}
+
+// Builds the ribbon with curves evaluated at the normalized `t` of positions.
+fn build<I>(
+    positions: I,
+    width: &WidthCurve,
+    color: &ColorGradient,
+    mode: LineTextureMode,
+    tile_length: f32,
+    texture: Option<TextureHandle>,
+) -> Option<Ribbon>
+where
+    I: Iterator<Item = (math::Vector3<f32>, f32)>,
+{
+    let mut points = Vec::new();
+    let mut distance = 0.0;
+
+    for (position, t) in positions {
+        if let Some(last) = points.last().map(|v: &RibbonPoint| v.position) {
+            distance += (position - last).magnitude();
+        }
+
+        points.push(RibbonPoint {
+            position: position,
+            width: width.evaluate(t),
+            color: color.evaluate(t),
+            u: distance,
+        });
+    }
+
+    if points.len() < 2 {
+        return None;
+    }
+
+    let scale = match mode {
+        LineTextureMode::Stretch => 1.0 / distance.max(1e-4),
+        LineTextureMode::Tile => 1.0 / tile_length.max(1e-4),
+    };
+
+    for v in &mut points {
+        v.u *= scale;
+    }
+
+    Some(Ribbon {
+        points: points,
+        texture: texture,
+    })
+}
+
+/// Draws a polyline in world space.
+#[derive(Debug, Clone)]
+pub struct LineRenderer {
+    /// The points in world space.
+    pub points: Vec<math::Vector3<f32>>,
+    /// The width over the length of line.
+    pub width: WidthCurve,
+    /// The color over the length of line.
+    pub color: ColorGradient,
+    /// The texture which is multiplied with colors. Its drawn with colors only if none.
+    pub texture: Option<TextureHandle>,
+    pub texture_mode: LineTextureMode,
+    /// The length of a tile in world units, if `texture_mode` is `Tile`.
+    pub tile_length: f32,
+    /// Is this renderer visible.
+    pub visible: bool,
+}
+
+impl LineRenderer {
+    /// Creates a new line with points in world space.
+    pub fn new(points: Vec<math::Vector3<f32>>) -> Self {
+        LineRenderer {
+            points: points,
+            width: WidthCurve::constant(0.1),
+            color: ColorGradient::constant(math::Color::white()),
+            texture: None,
+            texture_mode: LineTextureMode::Stretch,
+            tile_length: 1.0,
+            visible: true,
+        }
+    }
+
+    /// Gets the ribbon of this line, returns `None` if there are less than two points.
+    pub fn ribbon(&self) -> Option<Ribbon> {
+        let mut distances = Vec::with_capacity(self.points.len());
+        let mut distance = 0.0f32;
+        for (i, &v) in self.points.iter().enumerate() {
+            distance += (v - self.points[i.saturating_sub(1)]).magnitude();
+            distances.push(distance);
+        }
+
+        let length = distance.max(1e-4);
+        let positions = self.points
+            .iter()
+            .zip(distances)
+            .map(|(&v, distance)| (v, distance / length));
+
+        build(
+            positions,
+            &self.width,
+            &self.color,
+            self.texture_mode,
+            self.tile_length,
+            self.texture,
+        )
+    }
+}
+
+/// Leaves a trail behind the entity, which fades out over time.
+#[derive(Debug, Clone)]
+pub struct TrailRenderer {
+    /// The time which a point lives for.
+    pub lifetime: Duration,
+    /// The minimum distance between the points of trail. The newest point follows the
+    /// entity until its far enough from the previous one.
+    pub min_vertex_distance: f32,
+    /// The width over the age of points.
+    pub width: WidthCurve,
+    /// The color over the age of points, which fades out to transparent by default.
+    pub color: ColorGradient,
+    /// The texture which is multiplied with colors. Its drawn with colors only if none.
+    pub texture: Option<TextureHandle>,
+    pub texture_mode: LineTextureMode,
+    /// The length of a tile in world units, if `texture_mode` is `Tile`.
+    pub tile_length: f32,
+    /// Adds new points if true, the existing points keep fading out otherwise.
+    pub emitting: bool,
+    /// Is this renderer visible.
+    pub visible: bool,
+    points: VecDeque<(math::Vector3<f32>, f32)>,
+}
+
+impl TrailRenderer {
+    /// Creates a new trail whose points live for `lifetime`.
+    pub fn new(lifetime: Duration) -> Self {
+        TrailRenderer {
+            lifetime: lifetime,
+            min_vertex_distance: 0.1,
+            width: WidthCurve::linear(0.1, 0.0),
+            color: ColorGradient::linear(
+                math::Color::white(),
+                math::Color::new(1.0, 1.0, 1.0, 0.0),
+            ),
+            texture: None,
+            texture_mode: LineTextureMode::Stretch,
+            tile_length: 1.0,
+            emitting: true,
+            visible: true,
+            points: VecDeque::new(),
+        }
+    }
+
+    /// Gets the number of points.
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.points.len()
+    }
+
+    /// Returns true if there are no points.
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.points.is_empty()
+    }
+
+    /// Gets the points and their ages in seconds, from the newest to the oldest.
+    #[inline]
+    pub fn points(&self) -> &VecDeque<(math::Vector3<f32>, f32)> {
+        &self.points
+    }
+
+    /// Removes all the points.
+    #[inline]
+    pub fn clear(&mut self) {
+        self.points.clear();
+    }
+
+    /// Ages the points by `delta`, and records the current `position` of entity.
+    pub fn advance(&mut self, position: math::Vector3<f32>, delta: Duration) {
+        let dt = delta.as_secs() as f32 + delta.subsec_nanos() as f32 * 1e-9;
+        let lifetime = self.lifetime();
+
+        for v in &mut self.points {
+            v.1 += dt;
+        }
+
+        while self.points.back().map(|v| v.1 >= lifetime).unwrap_or(false) {
+            self.points.pop_back();
+        }
+
+        if !self.emitting {
+            return;
+        }
+
+        let follows = self.points
+            .get(1)
+            .map(|v| (position - v.0).magnitude() < self.min_vertex_distance)
+            .unwrap_or(false);
+
+        if follows {
+            self.points[0] = (position, 0.0);
+        } else {
+            self.points.push_front((position, 0.0));
+        }
+    }
+
+    /// Gets the ribbon of this trail, returns `None` if there are less than two points.
+    pub fn ribbon(&self) -> Option<Ribbon> {
+        let lifetime = self.lifetime().max(1e-4);
+        let positions = self.points.iter().map(|&(v, age)| (v, age / lifetime));
+
+        build(
+            positions,
+            &self.width,
+            &self.color,
+            self.texture_mode,
+            self.tile_length,
+            self.texture,
+        )
+    }
+
+    fn lifetime(&self) -> f32 {
+        self.lifetime.as_secs() as f32 + self.lifetime.subsec_nanos() as f32 * 1e-9
+    }
+}
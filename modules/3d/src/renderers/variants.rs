@@ -0,0 +1,201 @@
+//! Shader variants, which are the permutations of a shader with keywords enabled
+//! or disabled.
+//!
+//! Every keyword maps to a preprocessor define, e.g. `ShaderKeywords::FOG` compiles
+//! the shader with `#define FOG`. The variants are compiled on demand and cached by
+//! `ShaderVariants`, or could be prewarmed with a declared set to avoid hitches.
+
+use std::collections::HashMap;
+use std::ops::{BitOr, BitOrAssign};
+use std::sync::Arc;
+
+use crayon::errors::*;
+use crayon::video::assets::prelude::*;
+use crayon::video::prelude::*;
+
+/// A set of shader keywords.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash, PartialOrd, Ord, Default)]
+pub struct ShaderKeywords(u32);
+
+impl ShaderKeywords {
+    /// Samples the normal map.
+    pub const NORMALMAP: ShaderKeywords = ShaderKeywords(1 << 0);
+    /// Applies skeleton skinning to vertices.
+    pub const SKINNED: ShaderKeywords = ShaderKeywords(1 << 1);
+    /// Applies the global fog.
+    pub const FOG: ShaderKeywords = ShaderKeywords(1 << 2);
+    /// Samples the baked lightmap with the second texcoords.
+    pub const LIGHTMAP: ShaderKeywords = ShaderKeywords(1 << 3);
+    /// Discards the fragments behind the clip plane.
+    pub const CLIP_PLANE: ShaderKeywords = ShaderKeywords(1 << 4);
+    /// Shades as water surface with the textures of planar reflection.
+    pub const WATER: ShaderKeywords = ShaderKeywords(1 << 5);
+    /// Receives the cascaded shadows of directional light.
+    pub const SHADOWS: ShaderKeywords = ShaderKeywords(1 << 6);
+
+    /// The keywords and their preprocessor defines.
+    pub const NAMES: [(ShaderKeywords, &'static str); 7] = [
+        (ShaderKeywords::NORMALMAP, "NORMALMAP"),
+        (ShaderKeywords::SKINNED, "SKINNED"),
+        (ShaderKeywords::FOG, "FOG"),
+        (ShaderKeywords::LIGHTMAP, "LIGHTMAP"),
+        (ShaderKeywords::CLIP_PLANE, "CLIP_PLANE"),
+        (ShaderKeywords::WATER, "WATER"),
+        (ShaderKeywords::SHADOWS, "SHADOWS"),
+    ];
+
+    /// Creates an empty set of keywords.
+    #[inline]
+    pub fn empty() -> Self {
+        ShaderKeywords(0)
+    }
+
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.0 == 0
+    }
+
+    /// Returns true if all the keywords in `other` are enabled.
+    #[inline]
+    pub fn contains(&self, other: ShaderKeywords) -> bool {
+        (self.0 & other.0) == other.0
+    }
+
+    #[inline]
+    pub fn enable(&mut self, other: ShaderKeywords) {
+        self.0 |= other.0;
+    }
+
+    #[inline]
+    pub fn disable(&mut self, other: ShaderKeywords) {
+        self.0 &= !other.0;
+    }
+
+    /// Enables or disables the keywords in `other`.
+    #[inline]
+    pub fn set(&mut self, other: ShaderKeywords, enabled: bool) {
+        if enabled {
+            self.enable(other);
+        } else {
+            self.disable(other);
+        }
+    }
+
+    /// Gets the keywords enabled in both sets.
+    #[inline]
+    pub fn intersection(&self, other: ShaderKeywords) -> ShaderKeywords {
+        ShaderKeywords(self.0 & other.0)
+    }
+
+    /// Gets the preprocessor defines of enabled keywords, one per line.
+    pub fn defines(&self) -> String {
+        let mut defines = String::new();
+        for &(keyword, name) in &ShaderKeywords::NAMES {
+            if self.contains(keyword) {
+                defines.push_str("#define ");
+                defines.push_str(name);
+                defines.push('\n');
+            }
+        }
+
+        defines
+    }
+}
+
+impl BitOr for ShaderKeywords {
+    type Output = ShaderKeywords;
+
+    #[inline]
+    fn bitor(self, rhs: ShaderKeywords) -> ShaderKeywords {
+        ShaderKeywords(self.0 | rhs.0)
+    }
+}
+
+impl BitOrAssign for ShaderKeywords {
+    #[inline]
+    fn bitor_assign(&mut self, rhs: ShaderKeywords) {
+        self.0 |= rhs.0;
+    }
+}
+
+/// Builds the shader params, vertex and fragment sources of a variant.
+pub type ShaderVariantBuilder = Fn(ShaderKeywords) -> (ShaderParams, String, String);
+
+/// The cache of compiled shader variants.
+pub struct ShaderVariants {
+    video: Arc<VideoSystemShared>,
+    supported: ShaderKeywords,
+    builder: Box<ShaderVariantBuilder>,
+    variants: HashMap<ShaderKeywords, ShaderHandle>,
+}
+
+impl ShaderVariants {
+    /// Creates a new cache. Only the `supported` keywords would make difference, the
+    /// others are stripped before looking up, so they never produce redundant
+    /// permutations.
+    pub fn new<F>(video: Arc<VideoSystemShared>, supported: ShaderKeywords, builder: F) -> Self
+    where
+        F: Fn(ShaderKeywords) -> (ShaderParams, String, String) + 'static,
+    {
+        ShaderVariants {
+            video: video,
+            supported: supported,
+            builder: Box::new(builder),
+            variants: HashMap::new(),
+        }
+    }
+
+    /// Gets the keywords which are supported by this shader.
+    #[inline]
+    pub fn supported(&self) -> ShaderKeywords {
+        self.supported
+    }
+
+    /// Gets the variant with keywords, compiles it if it's not cached yet.
+    pub fn get(&mut self, keywords: ShaderKeywords) -> Result<ShaderHandle> {
+        let keywords = keywords.intersection(self.supported);
+        if let Some(&shader) = self.variants.get(&keywords) {
+            return Ok(shader);
+        }
+
+        let (params, vs, fs) = (self.builder)(keywords);
+        let shader = self.video.create_shader(params, vs, fs)?;
+        self.variants.insert(keywords, shader);
+        Ok(shader)
+    }
+
+    /// Compiles the declared set of variants ahead of time.
+    pub fn prewarm(&mut self, variants: &[ShaderKeywords]) -> Result<()> {
+        for &v in variants {
+            self.get(v)?;
+        }
+
+        Ok(())
+    }
+
+    /// Returns true if the variant with keywords has been compiled.
+    #[inline]
+    pub fn contains(&self, keywords: ShaderKeywords) -> bool {
+        self.variants
+            .contains_key(&keywords.intersection(self.supported))
+    }
+
+    /// Gets the number of compiled variants.
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.variants.len()
+    }
+
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.variants.is_empty()
+    }
+}
+
+impl Drop for ShaderVariants {
+    fn drop(&mut self) {
+        for (_, v) in self.variants.drain() {
+            self.video.delete_shader(v);
+        }
+    }
+}
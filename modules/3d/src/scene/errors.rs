@@ -6,6 +6,8 @@ pub enum Error {
     CanNotInverseTransform(::Entity),
     #[fail(display = "Node can not set self as parent.")]
     CanNotAttachSelfAsParent,
+    #[fail(display = "Ent({:?}) can not be attached to its descendant({:?}).", _0, _1)]
+    CanNotAttachDescendantAsParent(::Entity, ::Entity),
 }
 
 pub type Result<T> = ::std::result::Result<T, Error>;
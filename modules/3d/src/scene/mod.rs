@@ -1,30 +1,36 @@
-mod node;
-use self::node::Node;
-
 mod transform;
 pub use self::transform::Transform;
 
 mod errors;
 pub use self::errors::{Error, Result};
 
-use std::collections::{HashMap, HashSet};
-use std::iter;
+use std::collections::HashMap;
+use std::hash::Hash;
+use std::sync::Arc;
 
+use crayon::application::time::TimeSystemShared;
 use crayon::math::{self, One};
+use crayon::utils::Checksum;
 
+use entity_ref::EntityRemap;
+use hierarchy::Hierarchy;
 use Entity;
 
+pub use hierarchy::{Ancestors, Children, Descendants};
+
 /// A simple scene graph that used to tore and manipulate the postiion, rotation and scale
 /// of the object. We do also keeps a tree relationships betweens object in scene graph, so
 /// you can access properties of transformation in both local and world space.
+#[derive(Clone)]
 pub struct SceneGraph {
     remap: HashMap<Entity, usize>,
     entities: Vec<Entity>,
-    nodes: Vec<Node>,
+    hierarchy: Hierarchy,
     local_transforms: Vec<Transform>,
     world_transforms: Vec<Transform>,
-
-    pub(crate) roots: HashSet<Entity>,
+    previous_transforms: Vec<Option<Transform>>,
+    time: Option<Arc<TimeSystemShared>>,
+    snapshot_frame: u64,
 }
 
 impl SceneGraph {
@@ -32,10 +38,12 @@ impl SceneGraph {
         SceneGraph {
             remap: HashMap::new(),
             entities: Vec::new(),
-            nodes: Vec::new(),
+            hierarchy: Hierarchy::new(),
             local_transforms: Vec::new(),
             world_transforms: Vec::new(),
-            roots: HashSet::new(),
+            previous_transforms: Vec::new(),
+            time: None,
+            snapshot_frame: 0,
         }
     }
 
@@ -48,25 +56,22 @@ impl SceneGraph {
 
         self.remap.insert(ent, self.entities.len());
         self.entities.push(ent);
-        self.nodes.push(Node::default());
         self.local_transforms.push(Transform::default());
         self.world_transforms.push(Transform::default());
-        self.roots.insert(ent);
+        self.previous_transforms.push(None);
+        self.hierarchy.add(ent);
     }
 
     /// Removes a node and all of its descendants from SceneGraph.
     pub fn remove(&mut self, ent: Entity) -> Option<Vec<Entity>> {
         if self.remap.contains_key(&ent) {
-            self.remove_from_parent(ent, false).unwrap();
-            self.roots.remove(&ent);
-
-            let removes: Vec<_> = iter::once(ent).chain(self.descendants(ent)).collect();
+            let removes = self.hierarchy.remove(ent).unwrap();
             for w in removes.iter() {
                 let index = self.remap.remove(w).unwrap();
                 self.entities.swap_remove(index);
-                self.nodes.swap_remove(index);
                 self.local_transforms.swap_remove(index);
                 self.world_transforms.swap_remove(index);
+                self.previous_transforms.swap_remove(index);
 
                 if self.entities.len() != index {
                     *self.remap.get_mut(&self.entities[index]).unwrap() = index;
@@ -79,6 +84,34 @@ impl SceneGraph {
         }
     }
 
+    /// Replaces the entities with the new ones in `remap`, and reorders the transforms
+    /// by the indices of entities.
+    pub fn remap_entities(&mut self, remap: &EntityRemap) {
+        let mut order: Vec<_> = self
+            .entities
+            .iter()
+            .map(|&v| remap.get(v).unwrap_or(v))
+            .enumerate()
+            .collect();
+
+        order.sort_by_key(|v| (v.1).index());
+
+        self.remap.clear();
+        self.entities = order.iter().map(|v| v.1).collect();
+        for (i, &ent) in self.entities.iter().enumerate() {
+            self.remap.insert(ent, i);
+        }
+
+        self.local_transforms = order.iter().map(|v| self.local_transforms[v.0]).collect();
+        self.world_transforms = order.iter().map(|v| self.world_transforms[v.0]).collect();
+        self.previous_transforms = order
+            .iter()
+            .map(|v| self.previous_transforms[v.0])
+            .collect();
+
+        self.hierarchy.remap_entities(remap);
+    }
+
     #[inline]
     fn index(&self, ent: Entity) -> Result<usize> {
         self.remap
@@ -94,28 +127,34 @@ impl SceneGraph {
 }
 
 impl SceneGraph {
+    /// Gets the parent-child relationships between nodes.
+    #[inline]
+    pub fn hierarchy(&self) -> &Hierarchy {
+        &self.hierarchy
+    }
+
+    /// Clears the dirty flags of all nodes, see `Hierarchy::is_dirty` for details.
+    #[inline]
+    pub fn clear_dirty(&mut self) {
+        self.hierarchy.clear_dirty();
+    }
+
     /// Gets the parent node.
     #[inline]
     pub fn parent(&self, ent: Entity) -> Option<Entity> {
-        self.remap.get(&ent).and_then(|v| self.nodes[*v].parent)
+        self.hierarchy.parent(ent)
     }
 
     /// Returns ture if this is the leaf of a hierarchy, aka. has no child.
     #[inline]
     pub fn is_leaf(&self, ent: Entity) -> bool {
-        self.remap
-            .get(&ent)
-            .map(|v| self.nodes[*v].first_child.is_none())
-            .unwrap_or(false)
+        self.hierarchy.is_leaf(ent)
     }
 
     /// Returns ture if this is the root of a hierarchy, aka. has no parent.
     #[inline]
     pub fn is_root(&self, ent: Entity) -> bool {
-        self.remap
-            .get(&ent)
-            .map(|v| self.nodes[*v].parent.is_none())
-            .unwrap_or(false)
+        self.hierarchy.is_root(ent)
     }
 
     /// Attachs a new child to parent transform, before existing children.
@@ -123,214 +162,60 @@ impl SceneGraph {
     where
         T: Into<Option<Entity>>,
     {
-        unsafe {
-            let child_index = self.index(child)?;
-            let position = if keep_world_pose {
-                self.position(child).unwrap()
-            } else {
-                self.local_transforms[child_index].position
-            };
-
-            self.remove_from_parent(child, false)?;
-
-            if let Some(parent) = parent.into() {
-                if parent != child {
-                    let parent_index = self.index(parent)?;
-                    let next_sib = {
-                        let node = self.nodes.get_unchecked_mut(parent_index);
-                        ::std::mem::replace(&mut node.first_child, Some(child))
-                    };
-
-                    let child = self.nodes.get_unchecked_mut(child_index);
-                    child.parent = Some(parent);
-                    child.next_sib = next_sib;
-                } else {
-                    return Err(Error::CanNotAttachSelfAsParent);
-                }
-
-                self.roots.remove(&child);
-            }
+        self.begin_change();
+        let child_index = self.index(child)?;
+        let position = if keep_world_pose {
+            self.position(child).unwrap()
+        } else {
+            self.local_transforms[child_index].position
+        };
 
-            if keep_world_pose {
-                self.set_position(child, position);
-            }
+        self.hierarchy.set_parent(child, parent)?;
 
-            Ok(())
+        if keep_world_pose {
+            self.set_position(child, position);
         }
+
+        Ok(())
     }
 
     /// Detach a transform from its parent and siblings. Children are not affected.
     pub fn remove_from_parent(&mut self, child: Entity, keep_world_pose: bool) -> Result<()> {
-        unsafe {
-            let child_index = self.index(child)?;
-            let position = if keep_world_pose {
-                self.position(child).unwrap()
-            } else {
-                self.local_transforms[child_index].position
-            };
-
-            let (parent, next_sib, prev_sib) = {
-                let node = self.nodes.get_unchecked_mut(child_index);
-
-                (
-                    node.parent.take(),
-                    node.next_sib.take(),
-                    node.prev_sib.take(),
-                )
-            };
-
-            if let Some(next_sib) = next_sib {
-                let nsi = self.index_unchecked(next_sib);
-                self.nodes[nsi].prev_sib = prev_sib;
-            }
-
-            if let Some(prev_sib) = prev_sib {
-                let psi = self.index_unchecked(prev_sib);
-                self.nodes[psi].next_sib = next_sib;
-            } else if let Some(parent) = parent {
-                // Take this transform as the first child of parent if there is no previous sibling.
-                let pi = self.index_unchecked(parent);
-                self.nodes[pi].first_child = next_sib;
-            }
+        self.begin_change();
+        let child_index = self.index(child)?;
+        let position = if keep_world_pose {
+            self.position(child).unwrap()
+        } else {
+            self.local_transforms[child_index].position
+        };
 
-            self.local_transforms[child_index].position = position;
-            self.roots.insert(child);
-            Ok(())
-        }
+        self.hierarchy.remove_from_parent(child)?;
+        self.local_transforms[child_index].position = position;
+        Ok(())
     }
 
     /// Returns an iterator of references to its ancestors.
     #[inline]
     pub fn ancestors(&self, ent: Entity) -> Ancestors {
-        Ancestors {
-            cursor: self.parent(ent),
-            scene: self,
-        }
+        self.hierarchy.ancestors(ent)
     }
 
     /// Return true if rhs is one of the ancestor of this `Node`.
     #[inline]
     pub fn is_ancestor(&self, lhs: Entity, rhs: Entity) -> bool {
-        for v in self.ancestors(lhs) {
-            if v == rhs {
-                return true;
-            }
-        }
-
-        false
+        self.hierarchy.is_ancestor(lhs, rhs)
     }
 
     /// Returns an iterator of references to this transform's children.
     #[inline]
     pub fn children(&self, ent: Entity) -> Children {
-        let first_child = self.remap
-            .get(&ent)
-            .and_then(|v| self.nodes[*v].first_child);
-
-        Children {
-            cursor: first_child,
-            scene: self,
-        }
+        self.hierarchy.children(ent)
     }
 
     /// Returns an iterator of references to this transform's descendants in tree order.
     #[inline]
     pub fn descendants(&self, ent: Entity) -> Descendants {
-        let first_child = self.remap
-            .get(&ent)
-            .and_then(|v| self.nodes[*v].first_child);
-
-        Descendants {
-            root: ent,
-            cursor: first_child,
-            scene: self,
-        }
-    }
-}
-
-/// An iterator of references to its ancestors.
-pub struct Ancestors<'a> {
-    scene: &'a SceneGraph,
-    cursor: Option<Entity>,
-}
-
-impl<'a> Iterator for Ancestors<'a> {
-    type Item = Entity;
-
-    fn next(&mut self) -> Option<Self::Item> {
-        unsafe {
-            if let Some(ent) = self.cursor {
-                let index = self.scene.index_unchecked(ent);
-                ::std::mem::replace(&mut self.cursor, self.scene.nodes[index].parent)
-            } else {
-                None
-            }
-        }
-    }
-}
-
-/// An iterator of references to its children.
-pub struct Children<'a> {
-    scene: &'a SceneGraph,
-    cursor: Option<Entity>,
-}
-
-impl<'a> Iterator for Children<'a> {
-    type Item = Entity;
-
-    fn next(&mut self) -> Option<Self::Item> {
-        unsafe {
-            if let Some(ent) = self.cursor {
-                let index = self.scene.index_unchecked(ent);
-                ::std::mem::replace(&mut self.cursor, self.scene.nodes[index].next_sib)
-            } else {
-                None
-            }
-        }
-    }
-}
-
-/// An iterator of references to its descendants, in tree order.
-pub struct Descendants<'a> {
-    scene: &'a SceneGraph,
-    root: Entity,
-    cursor: Option<Entity>,
-}
-
-impl<'a> Iterator for Descendants<'a> {
-    type Item = Entity;
-
-    fn next(&mut self) -> Option<Self::Item> {
-        unsafe {
-            if let Some(ent) = self.cursor {
-                let index = self.scene.index_unchecked(ent);
-                let mut v = *self.scene.nodes.get_unchecked(index);
-
-                // Deep first search when iterating children recursively.
-                if v.first_child.is_some() {
-                    return ::std::mem::replace(&mut self.cursor, v.first_child);
-                }
-
-                if v.next_sib.is_some() {
-                    return ::std::mem::replace(&mut self.cursor, v.next_sib);
-                }
-
-                // Travel back when we reach leaf-node.
-                while let Some(parent) = v.parent {
-                    if parent == self.root {
-                        break;
-                    }
-
-                    let parent_index = self.scene.index_unchecked(parent);
-                    v = self.scene.nodes[parent_index];
-                    if v.next_sib.is_some() {
-                        return ::std::mem::replace(&mut self.cursor, v.next_sib);
-                    }
-                }
-            }
-
-            ::std::mem::replace(&mut self.cursor, None)
-        }
+        self.hierarchy.descendants(ent)
     }
 }
 
@@ -347,6 +232,21 @@ impl SceneGraph {
         })
     }
 
+    /// Writes the world matrices of entities into `dst`, which are composed in batches
+    /// with `math::batch`. The entities not in scene graph get identities.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `ents` and `dst` have different lengths.
+    pub fn matrices(&self, ents: &[Entity], dst: &mut [math::Matrix4<f32>]) {
+        assert_eq!(ents.len(), dst.len());
+
+        math::batch::compose_trs_with(dst, |i| {
+            let v = self.transform(ents[i]).unwrap_or_default();
+            (v.position, v.rotation, v.scale)
+        });
+    }
+
     /// Gets the transform in local space.
     #[inline]
     pub fn local_transform(&self, ent: Entity) -> Option<Transform> {
@@ -358,8 +258,120 @@ impl SceneGraph {
     /// Sets the transform in local space.
     #[inline]
     pub fn set_local_transform(&mut self, ent: Entity, transform: Transform) {
+        self.begin_change();
         if let Some(&index) = self.remap.get(&ent) {
             self.local_transforms[index] = transform;
+            self.hierarchy.set_dirty(ent);
+        }
+    }
+}
+
+impl SceneGraph {
+    /// Writes the hierarchies and local transforms into `checksum`. The nodes are
+    /// visited in the order of entities, so the checksum does not depend on the order
+    /// of insertions and removals.
+    pub fn checksum(&self, checksum: &mut Checksum) {
+        let mut entities = self.entities.clone();
+        entities.sort();
+
+        for ent in entities {
+            let index = self.remap[&ent];
+            ent.hash(checksum);
+            self.hierarchy.parent(ent).hash(checksum);
+
+            self.local_transforms[index].checksum(checksum);
+        }
+    }
+}
+
+/// The interpolation of transforms between fixed updates. Rendering at display rate
+/// with gameplay updated at fixed timestep would stutter, since the frames land at
+/// arbitrary points between two fixed updates. So the local transforms are saved with
+/// `snapshot` before each fixed update, and renderers query the transforms between the
+/// saved and the latest states with `TimeSystemShared::fixed_alpha`.
+///
+/// The scene graphs of `World` take the snapshots automatically, see `set_time`.
+impl SceneGraph {
+    /// Takes the snapshot automatically before the first change of transforms in each
+    /// fixed update, which is counted by `TimeSystemShared::fixed_frames`. The nodes are
+    /// not interpolated if nothing changed in the latest fixed update.
+    pub fn set_time(&mut self, time: Arc<TimeSystemShared>) {
+        self.snapshot_frame = time.fixed_frames();
+        self.time = Some(time);
+    }
+
+    /// Saves the local transforms as the previous states of interpolation, which should
+    /// be called before each fixed update.
+    pub fn snapshot(&mut self) {
+        for (i, v) in self.local_transforms.iter().enumerate() {
+            self.previous_transforms[i] = Some(*v);
+        }
+    }
+
+    /// Discards the previous state of node, so it would not be interpolated from its
+    /// old place after teleported.
+    pub fn reset_interpolation(&mut self, ent: Entity) {
+        if let Some(&index) = self.remap.get(&ent) {
+            self.previous_transforms[index] = None;
+        }
+    }
+
+    /// Gets the transform in world space, which is interpolated between the previous
+    /// and current states with `alpha`. Nodes added after last `snapshot` are not
+    /// interpolated.
+    pub fn interpolated_transform(&self, ent: Entity, alpha: f32) -> Option<Transform> {
+        if alpha >= 1.0 {
+            return self.transform(ent);
+        }
+
+        self.remap.get(&ent).map(|&index| unsafe {
+            self.ancestors(ent)
+                .map(|v| self.index_unchecked(v))
+                .fold(self.interpolated_local(index, alpha), |acc, rhs| {
+                    acc * self.interpolated_local(rhs, alpha)
+                })
+        })
+    }
+
+    /// Gets the interpolated world matrix, see `interpolated_transform` for details.
+    #[inline]
+    pub fn interpolated_matrix(&self, ent: Entity, alpha: f32) -> Option<math::Matrix4<f32>> {
+        self.interpolated_transform(ent, alpha).map(|v| v.matrix())
+    }
+
+    #[inline]
+    fn interpolated_local(&self, index: usize, alpha: f32) -> Transform {
+        let current = self.local_transforms[index];
+        if !self.is_interpolating() {
+            return current;
+        }
+
+        match self.previous_transforms[index] {
+            Some(previous) => previous.lerp(&current, alpha),
+            None => current,
+        }
+    }
+
+    // Returns false if the transforms have not been changed in the latest fixed update.
+    #[inline]
+    fn is_interpolating(&self) -> bool {
+        match self.time {
+            Some(ref time) => time.fixed_frames() == self.snapshot_frame,
+            None => true,
+        }
+    }
+
+    // Takes the snapshot if this is the first change in current fixed update.
+    #[inline]
+    fn begin_change(&mut self) {
+        let frame = match self.time {
+            Some(ref time) => time.fixed_frames(),
+            None => return,
+        };
+
+        if frame != self.snapshot_frame {
+            self.snapshot();
+            self.snapshot_frame = frame;
         }
     }
 }
@@ -370,8 +382,10 @@ impl SceneGraph {
     where
         T: Into<math::Vector3<f32>>,
     {
+        self.begin_change();
         if let Some(&index) = self.remap.get(&ent) {
             self.local_transforms[index].position += translation.into();
+            self.hierarchy.set_dirty(ent);
         }
     }
 
@@ -391,6 +405,7 @@ impl SceneGraph {
     where
         T: Into<math::Vector3<f32>>,
     {
+        self.begin_change();
         unsafe {
             if let Some(&index) = self.remap.get(&ent) {
                 let ancestor_position = self.ancestors(ent)
@@ -400,6 +415,7 @@ impl SceneGraph {
                     });
 
                 self.local_transforms[index].position = position.into() - ancestor_position;
+                self.hierarchy.set_dirty(ent);
             }
         }
     }
@@ -416,8 +432,10 @@ impl SceneGraph {
     where
         T: Into<math::Vector3<f32>>,
     {
+        self.begin_change();
         if let Some(&index) = self.remap.get(&ent) {
             self.local_transforms[index].position = position.into();
+            self.hierarchy.set_dirty(ent);
         }
     }
 }
@@ -428,9 +446,11 @@ impl SceneGraph {
     where
         T: Into<math::Quaternion<f32>>,
     {
+        self.begin_change();
         if let Some(&index) = self.remap.get(&ent) {
             self.local_transforms[index].rotation =
                 rotation.into() * self.local_transforms[index].rotation;
+            self.hierarchy.set_dirty(ent);
         }
     }
 
@@ -440,17 +460,11 @@ impl SceneGraph {
         T1: Into<math::Vector3<f32>>,
         T2: Into<math::Vector3<f32>>,
     {
-        use crayon::math::InnerSpace;
+        self.begin_change();
+        use crayon::math::QuaternionExt;
 
         if let Some(eye) = self.position(ent) {
-            let center = center.into();
-            let up = up.into();
-
-            let dir = (center - eye).normalize();
-            let side = up.cross(dir).normalize();
-            let up = dir.cross(side).normalize();
-            let rotation: math::Quaternion<f32> = math::Matrix3::from_cols(side, up, dir).into();
-
+            let rotation = math::Quaternion::look_rotation(center.into() - eye, up.into());
             self.set_rotation(ent, rotation);
         }
     }
@@ -471,6 +485,7 @@ impl SceneGraph {
     where
         T: Into<math::Quaternion<f32>>,
     {
+        self.begin_change();
         use crayon::math::Rotation;
         unsafe {
             if let Some(&index) = self.remap.get(&ent) {
@@ -482,6 +497,7 @@ impl SceneGraph {
 
                 self.local_transforms[index].rotation =
                     rotation.into() * ancestor_rotation.invert();
+                self.hierarchy.set_dirty(ent);
             }
         }
     }
@@ -498,8 +514,10 @@ impl SceneGraph {
     where
         T: Into<math::Quaternion<f32>>,
     {
+        self.begin_change();
         if let Some(&index) = self.remap.get(&ent) {
             self.local_transforms[index].rotation = rotation.into();
+            self.hierarchy.set_dirty(ent);
         }
     }
 }
@@ -518,6 +536,7 @@ impl SceneGraph {
 
     /// Sets scale of the transform in world space.
     pub fn set_scale(&mut self, ent: Entity, scale: f32) {
+        self.begin_change();
         unsafe {
             if let Some(&index) = self.remap.get(&ent) {
                 let ancestor_scale = self.ancestors(ent)
@@ -529,6 +548,8 @@ impl SceneGraph {
                 } else {
                     self.local_transforms[index].scale = scale;
                 }
+
+                self.hierarchy.set_dirty(ent);
             }
         }
     }
@@ -542,8 +563,10 @@ impl SceneGraph {
 
     /// Sets scale of the transform in local space.
     pub fn set_local_scale(&mut self, ent: Entity, scale: f32) {
+        self.begin_change();
         if let Some(&index) = self.remap.get(&ent) {
             self.local_transforms[index].scale = scale;
+            self.hierarchy.set_dirty(ent);
         }
     }
 }
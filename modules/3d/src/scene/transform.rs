@@ -1,8 +1,9 @@
 use crayon::math::{self, One};
+use crayon::utils::Checksum;
 
 /// `Transform` is used to store and manipulate the postiion, rotation and scale
 /// of the object. We use a left handed, y-up world coordinate system.
-#[derive(Serialize, Deserialize, Debug, Clone, Copy)]
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
 pub struct Transform {
     pub scale: f32,
     pub position: math::Vector3<f32>,
@@ -94,6 +95,55 @@ impl Transform {
         ir * it
     }
 
+    /// Rotates the transform so the forward vector points at `target`.
+    pub fn look_at<T1, T2>(&mut self, target: T1, up: T2)
+    where
+        T1: Into<math::Vector3<f32>>,
+        T2: Into<math::Vector3<f32>>,
+    {
+        use crayon::math::QuaternionExt;
+
+        let dir = target.into() - self.position;
+        self.rotation = math::Quaternion::look_rotation(dir, up.into());
+    }
+
+    /// Decomposes the matrix into transform. The scale of `Transform` is uniform, so the
+    /// average of scales along axes is used.
+    pub fn from_matrix(m: &math::Matrix4<f32>) -> Transform {
+        let (position, rotation, scale) = math::decompose(m);
+        Transform {
+            scale: (scale.x + scale.y + scale.z) / 3.0,
+            position: position,
+            rotation: rotation,
+        }
+    }
+
+    /// Interpolates between two transforms, the rotations are interpolated along the
+    /// shortest arc.
+    pub fn lerp(&self, rhs: &Transform, t: f32) -> Transform {
+        use crayon::math::InnerSpace;
+
+        let rotation = if self.rotation.dot(rhs.rotation) < 0.0 {
+            -rhs.rotation
+        } else {
+            rhs.rotation
+        };
+
+        Transform {
+            scale: self.scale + (rhs.scale - self.scale) * t,
+            position: self.position + (rhs.position - self.position) * t,
+            rotation: self.rotation.nlerp(rotation, t),
+        }
+    }
+
+    /// Writes the position, rotation and scale into `checksum`.
+    pub fn checksum(&self, checksum: &mut Checksum) {
+        let (p, r) = (self.position, self.rotation);
+        checksum.write_f32s(&[p.x, p.y, p.z]);
+        checksum.write_f32s(&[r.s, r.v.x, r.v.y, r.v.z]);
+        checksum.write_f32(self.scale);
+    }
+
     /// Returns the matrix representation.
     #[inline]
     pub fn matrix(&self) -> math::Matrix4<f32> {
@@ -103,4 +153,19 @@ impl Transform {
         m.w = self.position.extend(1.0);
         m
     }
+
+    /// Writes the matrix representations of transforms into `dst`, which goes through
+    /// the batched paths in `math::batch` instead of composing them one by one.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `src` and `dst` have different lengths.
+    pub fn matrices(src: &[Transform], dst: &mut [math::Matrix4<f32>]) {
+        assert_eq!(src.len(), dst.len());
+
+        math::batch::compose_trs_with(dst, |i| {
+            let v = &src[i];
+            (v.position, v.rotation, v.scale)
+        });
+    }
 }
@@ -1,5 +1,11 @@
+use std::hash::Hash;
+
+use crayon::utils::Checksum;
+
+use entity_ref::EntityRemap;
 use {Component, Entity};
 
+#[derive(Clone)]
 pub struct Tags {
     names: Component<String>,
 }
@@ -25,4 +31,16 @@ impl Tags {
     pub fn name(&self, ent: Entity) -> Option<&str> {
         self.names.get(ent).map(|v| v.as_ref())
     }
+
+    /// Replaces the entities with the new ones in `remap`.
+    #[inline]
+    pub fn remap_entities(&mut self, remap: &EntityRemap) {
+        self.names.remap_entities(remap);
+    }
+
+    /// Writes the names of entities into `checksum`.
+    pub fn checksum(&self, checksum: &mut Checksum) {
+        self.names
+            .checksum(checksum, |v, checksum| v.hash(checksum));
+    }
 }
@@ -0,0 +1,179 @@
+//! Tick groups which update entities at reduced rates, e.g. the AI and animations of
+//! distant entities.
+//!
+//! The entities of `TickRate::Half` and `TickRate::Quarter` are spread round-robin over
+//! consecutive frames, so only a fraction of them are due in each frame. The delta time
+//! of due entities is the elapsed time since their last tick.
+//!
+//! ```rust,ignore
+//! world.ticks.set(ent, TickRate::Quarter);
+//!
+//! // Every frame.
+//! world.ticks.advance(ctx.time.frame_delta());
+//! for (ent, dt) in world.ticks.due() {
+//!     update_ai(ent, dt);
+//! }
+//! ```
+
+use std::hash::Hash;
+use std::time::Duration;
+
+use crayon::utils::Checksum;
+
+use entity_ref::EntityRemap;
+use {Component, Entity};
+
+/// The update frequency of entities or systems.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum TickRate {
+    /// Updates every frame.
+    Full,
+    /// Updates every two frames.
+    Half,
+    /// Updates every four frames.
+    Quarter,
+}
+
+impl Default for TickRate {
+    fn default() -> Self {
+        TickRate::Full
+    }
+}
+
+impl TickRate {
+    /// Gets the number of frames between two ticks.
+    #[inline]
+    pub fn interval(&self) -> usize {
+        match *self {
+            TickRate::Full => 1,
+            TickRate::Half => 2,
+            TickRate::Quarter => 4,
+        }
+    }
+
+    #[inline]
+    fn offset(&self) -> usize {
+        match *self {
+            TickRate::Full => 0,
+            TickRate::Half => 1,
+            TickRate::Quarter => 3,
+        }
+    }
+}
+
+// The buckets of all rates, which are indexed by `TickRate::offset + phase`.
+const BUCKETS: usize = 7;
+
+/// `TickGroups` assigns entities into buckets of update rates.
+#[derive(Clone)]
+pub struct TickGroups {
+    rates: Component<(TickRate, usize)>,
+    assigned: [usize; BUCKETS],
+    elapsed: [Duration; BUCKETS],
+    deltas: [Option<Duration>; BUCKETS],
+    frame: usize,
+}
+
+impl TickGroups {
+    pub fn new() -> Self {
+        TickGroups {
+            rates: Component::new(),
+            assigned: [0; BUCKETS],
+            elapsed: [Duration::from_secs(0); BUCKETS],
+            deltas: [None; BUCKETS],
+            frame: 0,
+        }
+    }
+
+    /// Sets the update rate of entity, which is assigned to the least used phase. The
+    /// entities of `TickRate::Full` are not tracked, it's the same as `remove`.
+    pub fn set(&mut self, ent: Entity, rate: TickRate) {
+        self.remove(ent);
+        if rate == TickRate::Full {
+            return;
+        }
+
+        let offset = rate.offset();
+        let mut phase = 0;
+        for i in 1..rate.interval() {
+            if self.assigned[offset + i] < self.assigned[offset + phase] {
+                phase = i;
+            }
+        }
+
+        self.assigned[offset + phase] += 1;
+        self.rates.add(ent, (rate, phase));
+    }
+
+    /// Resets the update rate of entity to `TickRate::Full`.
+    pub fn remove(&mut self, ent: Entity) {
+        if let Some(&(rate, phase)) = self.rates.get(ent) {
+            self.assigned[rate.offset() + phase] -= 1;
+            self.rates.remove(ent);
+        }
+    }
+
+    /// Gets the update rate of entity.
+    #[inline]
+    pub fn rate(&self, ent: Entity) -> TickRate {
+        self.rates.get(ent).map(|v| v.0).unwrap_or_default()
+    }
+
+    /// Replaces the entities with the new ones in `remap`, the assigned phases are kept.
+    #[inline]
+    pub fn remap_entities(&mut self, remap: &EntityRemap) {
+        self.rates.remap_entities(remap);
+    }
+
+    /// Writes the rates and phases of entities, and the elapsed time of phases into
+    /// `checksum`.
+    pub fn checksum(&self, checksum: &mut Checksum) {
+        self.rates
+            .checksum(checksum, |v, checksum| v.hash(checksum));
+        self.elapsed.hash(checksum);
+        self.deltas.hash(checksum);
+        self.frame.hash(checksum);
+    }
+
+    /// Advances to next frame with the delta time of last frame.
+    pub fn advance(&mut self, delta: Duration) {
+        for i in 0..BUCKETS {
+            self.elapsed[i] += delta;
+            self.deltas[i] = None;
+        }
+
+        for &rate in &[TickRate::Full, TickRate::Half, TickRate::Quarter] {
+            let index = rate.offset() + self.frame % rate.interval();
+            self.deltas[index] = Some(self.elapsed[index]);
+            self.elapsed[index] = Duration::from_secs(0);
+        }
+
+        self.frame += 1;
+    }
+
+    /// Returns the compensated delta time if the entity is due in this frame.
+    pub fn delta(&self, ent: Entity) -> Option<Duration> {
+        let (rate, phase) = self.rates.get(ent).cloned().unwrap_or((TickRate::Full, 0));
+        self.deltas[rate.offset() + phase]
+    }
+
+    /// Returns the compensated delta time if the systems of `rate` are due in this frame.
+    /// Systems always run in the first phase of rate.
+    #[inline]
+    pub fn system_delta(&self, rate: TickRate) -> Option<Duration> {
+        self.deltas[rate.offset()]
+    }
+
+    /// Lists the entities with reduced rates which are due in this frame. Notes that
+    /// the entities of `TickRate::Full` are not listed.
+    pub fn due(&self) -> Vec<(Entity, Duration)> {
+        self.rates
+            .entities
+            .iter()
+            .zip(self.rates.data.iter())
+            .filter_map(|(&ent, &(rate, phase))| {
+                self.deltas[rate.offset() + phase].map(|dt| (ent, dt))
+            })
+            .collect()
+    }
+}
@@ -0,0 +1,456 @@
+//! Screen fades and cross-fades, which are coordinated with scene loading so level
+//! changes don't pop.
+//!
+//! ```rust,ignore
+//! // Fades the screen to black, loads the next level once the screen is covered, and
+//! // fades back in after the level is ready.
+//! transitions
+//!     .fade_out(0.5)
+//!     .then_unload(level)
+//!     .then_load(next_level)
+//!     .fade_in(0.5);
+//!
+//! // Every frame.
+//! transitions.advance(&mut world, ctx.time.frame_delta())?;
+//! world.advance()?;
+//! renderer.draw(transitions.overlay());
+//! ```
+
+use std::collections::VecDeque;
+use std::sync::Arc;
+use std::time::Duration;
+
+use crayon::application::Context;
+use crayon::errors::*;
+use crayon::math;
+use crayon::video::assets::prelude::*;
+use crayon::video::prelude::*;
+
+use assets::PrefabHandle;
+use renderers::pipeline::create_fullscreen_mesh;
+use renderers::Renderer;
+use world::{Entity, World};
+
+/// The easing functions which shape the progress of transitions.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Easing {
+    Linear,
+    EaseIn,
+    EaseOut,
+    EaseInOut,
+}
+
+impl Easing {
+    /// Applies the easing to `t` ranging from 0.0 to 1.0.
+    pub fn apply(&self, t: f32) -> f32 {
+        let t = t.max(0.0).min(1.0);
+        match *self {
+            Easing::Linear => t,
+            Easing::EaseIn => t * t,
+            Easing::EaseOut => t * (2.0 - t),
+            Easing::EaseInOut => t * t * (3.0 - 2.0 * t),
+        }
+    }
+}
+
+/// The scenes which could be loaded or unloaded by transitions. Its implemented by
+/// `World`.
+pub trait TransitionScene {
+    /// Instantiates the prefab, returns `None` if its not ready yet, or an error if it
+    /// failed to load.
+    fn load(&mut self, prefab: PrefabHandle) -> Result<Option<Entity>>;
+    /// Removes the entity and all of its descendants.
+    fn unload(&mut self, ent: Entity);
+}
+
+impl<T: Renderer> TransitionScene for World<T> {
+    fn load(&mut self, prefab: PrefabHandle) -> Result<Option<Entity>> {
+        match self.res.poll_prefab(prefab) {
+            Some(result) => result?,
+            None => return Ok(None),
+        }
+
+        Ok(Some(self.instantiate(prefab)?))
+    }
+
+    fn unload(&mut self, ent: Entity) {
+        self.remove(ent);
+    }
+}
+
+/// What should be drawn on top of the scene.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum TransitionOverlay {
+    Nothing,
+    /// Covers the screen with color, the alpha is the opacity of fade.
+    Fade(math::Color<f32>),
+    /// Blends two rendered scenes, e.g. the render textures drawn by two cameras.
+    CrossFade {
+        from: RenderTextureHandle,
+        to: RenderTextureHandle,
+        factor: f32,
+    },
+}
+
+#[derive(Debug, Clone, Copy)]
+enum Step {
+    Fade {
+        from: Option<f32>,
+        to: f32,
+        duration: f32,
+        easing: Easing,
+    },
+    CrossFade {
+        from: RenderTextureHandle,
+        to: RenderTextureHandle,
+        duration: f32,
+        easing: Easing,
+    },
+    Wait(f32),
+    Load(PrefabHandle),
+    Unload(Entity),
+}
+
+/// A queue of transition steps which are performed in order.
+pub struct Transitions {
+    steps: VecDeque<Step>,
+    elapsed: f32,
+    color: math::Color<f32>,
+    easing: Easing,
+    opacity: f32,
+    overlay: TransitionOverlay,
+    loaded: Vec<(PrefabHandle, Entity)>,
+}
+
+impl Default for Transitions {
+    fn default() -> Self {
+        Transitions::new()
+    }
+}
+
+impl Transitions {
+    pub fn new() -> Self {
+        Transitions {
+            steps: VecDeque::new(),
+            elapsed: 0.0,
+            color: math::Color::black(),
+            easing: Easing::EaseInOut,
+            opacity: 0.0,
+            overlay: TransitionOverlay::Nothing,
+            loaded: Vec::new(),
+        }
+    }
+
+    /// Sets the color of fades. The alpha component is ignored.
+    #[inline]
+    pub fn set_color(&mut self, color: math::Color<f32>) -> &mut Self {
+        self.color = color;
+        self
+    }
+
+    /// Sets the easing of the steps pushed after this.
+    #[inline]
+    pub fn set_easing(&mut self, easing: Easing) -> &mut Self {
+        self.easing = easing;
+        self
+    }
+
+    /// Fades the screen to color in `duration` seconds. The screen stays covered until
+    /// `fade_in`.
+    pub fn fade_out(&mut self, duration: f32) -> &mut Self {
+        self.push_fade(1.0, duration)
+    }
+
+    /// Fades the screen back from color in `duration` seconds.
+    pub fn fade_in(&mut self, duration: f32) -> &mut Self {
+        self.push_fade(0.0, duration)
+    }
+
+    /// Blends from one rendered scene to another in `duration` seconds.
+    pub fn cross_fade(
+        &mut self,
+        from: RenderTextureHandle,
+        to: RenderTextureHandle,
+        duration: f32,
+    ) -> &mut Self {
+        self.steps.push_back(Step::CrossFade {
+            from: from,
+            to: to,
+            duration: duration,
+            easing: self.easing,
+        });
+        self
+    }
+
+    /// Waits for `duration` seconds.
+    pub fn then_wait(&mut self, duration: f32) -> &mut Self {
+        self.steps.push_back(Step::Wait(duration));
+        self
+    }
+
+    /// Instantiates the prefab. The following steps are blocked until the prefab is
+    /// loaded, and cancelled if it failed to load.
+    pub fn then_load(&mut self, prefab: PrefabHandle) -> &mut Self {
+        self.steps.push_back(Step::Load(prefab));
+        self
+    }
+
+    /// Removes the entity (e.g. the root of previous level).
+    pub fn then_unload(&mut self, ent: Entity) -> &mut Self {
+        self.steps.push_back(Step::Unload(ent));
+        self
+    }
+
+    /// Returns true if there are pending steps.
+    #[inline]
+    pub fn is_running(&self) -> bool {
+        !self.steps.is_empty()
+    }
+
+    /// Cancels all the pending steps. The current overlay is kept.
+    pub fn clear(&mut self) {
+        self.steps.clear();
+        self.elapsed = 0.0;
+    }
+
+    /// Gets what should be drawn on top of the scene.
+    #[inline]
+    pub fn overlay(&self) -> TransitionOverlay {
+        self.overlay
+    }
+
+    /// Takes the roots of prefabs which have been loaded since last call.
+    pub fn take_loaded(&mut self) -> Vec<(PrefabHandle, Entity)> {
+        ::std::mem::replace(&mut self.loaded, Vec::new())
+    }
+
+    /// Advances the transitions with the time elapsed since last frame.
+    ///
+    /// If a step fails, e.g. the prefab could not be loaded, the pending steps are
+    /// cancelled and the error is returned. The current overlay is kept, so the screen
+    /// stays covered until the application recovers from it.
+    pub fn advance(&mut self, scene: &mut TransitionScene, dt: Duration) -> Result<()> {
+        let dt = dt.as_secs() as f32 + dt.subsec_nanos() as f32 * 1e-9;
+        self.elapsed += dt;
+
+        while let Some(step) = self.steps.front().cloned() {
+            let finished = match self.perform(scene, step) {
+                Ok(finished) => finished,
+                Err(err) => {
+                    self.clear();
+                    return Err(err);
+                }
+            };
+
+            if !finished {
+                break;
+            }
+
+            self.steps.pop_front();
+            self.elapsed = 0.0;
+        }
+
+        Ok(())
+    }
+
+    // Performs the step, returns true if its finished.
+    fn perform(&mut self, scene: &mut TransitionScene, step: Step) -> Result<bool> {
+        match step {
+            Step::Fade {
+                from,
+                to,
+                duration,
+                easing,
+            } => {
+                let start = from.unwrap_or(self.opacity);
+                if from.is_none() {
+                    // Remembers where the fade starts from.
+                    if let Some(&mut Step::Fade { ref mut from, .. }) = self.steps.front_mut() {
+                        *from = Some(start);
+                    }
+                }
+
+                let t = Self::progress(self.elapsed, duration);
+                self.set_opacity(start + (to - start) * easing.apply(t));
+                Ok(t >= 1.0)
+            }
+
+            Step::CrossFade {
+                from,
+                to,
+                duration,
+                easing,
+            } => {
+                let t = Self::progress(self.elapsed, duration);
+                self.overlay = if t >= 1.0 {
+                    TransitionOverlay::Nothing
+                } else {
+                    TransitionOverlay::CrossFade {
+                        from: from,
+                        to: to,
+                        factor: easing.apply(t),
+                    }
+                };
+
+                Ok(t >= 1.0)
+            }
+
+            Step::Wait(duration) => Ok(self.elapsed >= duration),
+
+            Step::Load(prefab) => {
+                if let Some(ent) = scene.load(prefab)? {
+                    self.loaded.push((prefab, ent));
+                    Ok(true)
+                } else {
+                    Ok(false)
+                }
+            }
+
+            Step::Unload(ent) => {
+                scene.unload(ent);
+                Ok(true)
+            }
+        }
+    }
+
+    fn push_fade(&mut self, to: f32, duration: f32) -> &mut Self {
+        self.steps.push_back(Step::Fade {
+            from: None,
+            to: to,
+            duration: duration,
+            easing: self.easing,
+        });
+        self
+    }
+
+    fn set_opacity(&mut self, opacity: f32) {
+        self.opacity = opacity.max(0.0).min(1.0);
+        self.overlay = if self.opacity > 0.0 {
+            let mut color = self.color;
+            color.a = self.opacity;
+            TransitionOverlay::Fade(color)
+        } else {
+            TransitionOverlay::Nothing
+        };
+    }
+
+    #[inline]
+    fn progress(elapsed: f32, duration: f32) -> f32 {
+        if duration <= 0.0 {
+            1.0
+        } else {
+            (elapsed / duration).min(1.0)
+        }
+    }
+}
+
+/// Draws the `TransitionOverlay` on top of the default surface.
+pub struct TransitionRenderer {
+    video: Arc<VideoSystemShared>,
+    surface: SurfaceHandle,
+    fade_shader: ShaderHandle,
+    cross_fade_shader: ShaderHandle,
+    mesh: MeshHandle,
+}
+
+impl TransitionRenderer {
+    pub fn new(ctx: &Context) -> Result<Self> {
+        // Draws on top of whatever has been drawn into the default framebuffer.
+        let mut params = SurfaceParams::default();
+        params.set_clear(None, None, None);
+        let surface = ctx.video.create_surface(params)?;
+
+        let attributes = AttributeLayout::build()
+            .with(Attribute::Position, 2)
+            .finish();
+
+        let mut params = ShaderParams::default();
+        params.state.depth_write = false;
+        params.state.depth_test = Comparison::Always;
+        params.attributes = attributes;
+
+        let mut p = params.clone();
+        p.state.color_blend = Some((
+            Equation::Add,
+            BlendFactor::Value(BlendValue::SourceAlpha),
+            BlendFactor::OneMinusValue(BlendValue::SourceAlpha),
+        ));
+        p.uniforms = UniformVariableLayout::build()
+            .with("u_Color", UniformVariableType::Vector4f)
+            .finish();
+        let fade_shader = Self::create_shader(ctx, p, "")?;
+
+        let mut p = params.clone();
+        p.uniforms = UniformVariableLayout::build()
+            .with("u_From", UniformVariableType::RenderTexture)
+            .with("u_To", UniformVariableType::RenderTexture)
+            .with("u_Factor", UniformVariableType::F32)
+            .finish();
+        let cross_fade_shader = Self::create_shader(ctx, p, "#define CROSS_FADE")?;
+
+        Ok(TransitionRenderer {
+            video: ctx.video.clone(),
+            surface: surface,
+            fade_shader: fade_shader,
+            cross_fade_shader: cross_fade_shader,
+            mesh: create_fullscreen_mesh(&ctx.video)?,
+        })
+    }
+
+    fn create_shader(ctx: &Context, params: ShaderParams, defines: &str) -> Result<ShaderHandle> {
+        let vs = format!(
+            "
+            #version 100
+            precision mediump float;
+            {0}
+            ",
+            include_str!("../assets/transition.vs")
+        );
+
+        let fs = format!(
+            "
+            #version 100
+            precision mediump float;
+            {0}
+            {1}
+            ",
+            defines,
+            include_str!("../assets/transition.fs")
+        );
+
+        Ok(ctx.video.create_shader(params, vs, fs)?)
+    }
+
+    /// Draws the overlay. This should be called after the scene is drawn.
+    pub fn draw(&self, overlay: TransitionOverlay) {
+        match overlay {
+            TransitionOverlay::Nothing => {}
+
+            TransitionOverlay::Fade(color) => {
+                let mut dc = DrawCall::new(self.fade_shader, self.mesh);
+                dc.set_uniform_variable(
+                    "u_Color",
+                    math::Vector4::new(color.r, color.g, color.b, color.a),
+                );
+                self.video.draw(self.surface, dc);
+            }
+
+            TransitionOverlay::CrossFade { from, to, factor } => {
+                let mut dc = DrawCall::new(self.cross_fade_shader, self.mesh);
+                dc.set_uniform_variable("u_From", from);
+                dc.set_uniform_variable("u_To", to);
+                dc.set_uniform_variable("u_Factor", factor);
+                self.video.draw(self.surface, dc);
+            }
+        }
+    }
+}
+
+impl Drop for TransitionRenderer {
+    fn drop(&mut self) {
+        self.video.delete_surface(self.surface);
+        self.video.delete_shader(self.fade_shader);
+        self.video.delete_shader(self.cross_fade_shader);
+        self.video.delete_mesh(self.mesh);
+    }
+}
@@ -1,18 +1,95 @@
+use std::collections::HashSet;
+use std::hash::Hasher;
+use std::sync::atomic::{AtomicUsize, Ordering, ATOMIC_USIZE_INIT};
 use std::sync::Arc;
 
 use crayon::errors::*;
-use crayon::utils::HandlePool;
+use crayon::sched::ScheduleSystemShared;
+use crayon::utils::{Checksum, Handle, HandleIndex, HandlePool};
 
+use animation::Animators;
 use assets::{PrefabHandle, WorldResourcesShared};
-use renderers::{MeshRenderer, Renderable, Renderer};
-use scene::SceneGraph;
+use commands::{CommandBuffer, InstantiateJob};
+use entity_commands::{EntityCommand, EntityCommandBuffer};
+use entity_commands::{EntityComponent, EntityComponentKind};
+use entity_ref::{EntityRef, EntityRemap, EntityStore};
+use path::PathFollowers;
+use physics::{CharacterControllers, Colliders, Triggers};
+use renderers::{Renderable, Renderer};
+use scene::{Children, SceneGraph};
 use tags::Tags;
+use ticks::{TickGroups, TickRate};
 
 impl_handle!(Entity);
 
+impl Entity {
+    /// Gets the placeholder Entity which refers to the node of prefab with index. The
+    /// `EntityRef`s in prefabs use these placeholders, and they are remapped when
+    /// instantiating.
+    #[inline]
+    pub fn prefab_node(index: usize) -> Self {
+        Entity::placeholder(index)
+    }
+
+    /// Returns true if this is a placeholder, which is never alive in worlds.
+    #[inline]
+    pub fn is_placeholder(&self) -> bool {
+        // The versions of alive handles are always odd.
+        self.version() == 0
+    }
+
+    #[inline]
+    pub(crate) fn placeholder(index: usize) -> Self {
+        Entity(Handle::new(index as HandleIndex, 0))
+    }
+}
+
+/// The unique identifier of `World`, which is never reused during the lifetime of
+/// process.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct WorldId(usize);
+
+static WORLD_COUNTER: AtomicUsize = ATOMIC_USIZE_INIT;
+
+impl WorldId {
+    fn next() -> Self {
+        WorldId(WORLD_COUNTER.fetch_add(1, Ordering::Relaxed))
+    }
+
+    #[inline]
+    pub(crate) fn index(&self) -> usize {
+        self.0
+    }
+}
+
+/// The snapshot of the entities and components of `World`, see `World::save`.
+#[derive(Clone)]
+pub struct WorldState {
+    entities: HandlePool,
+    tags: Tags,
+    ticks: TickGroups,
+    paths: PathFollowers,
+    animators: Animators,
+    colliders: Colliders,
+    controllers: CharacterControllers,
+    triggers: Triggers,
+    scene: SceneGraph,
+    renderables: Renderable,
+}
+
+/// Multiple worlds could coexist, e.g. the main scene, the UI and the preview of
+/// loading screen. Each of them draws with its own cameras, and a camera could also
+/// select another world to draw with `Camera::set_world`.
 pub struct World<T: Renderer> {
+    id: WorldId,
     entities: HandlePool,
     pub tags: Tags,
+    pub ticks: TickGroups,
+    pub paths: PathFollowers,
+    pub animators: Animators,
+    pub colliders: Colliders,
+    pub controllers: CharacterControllers,
+    pub triggers: Triggers,
     pub scene: SceneGraph,
     pub renderables: Renderable,
     pub renderer: T,
@@ -21,16 +98,32 @@ pub struct World<T: Renderer> {
 
 impl<T: Renderer> World<T> {
     pub fn new(res: Arc<WorldResourcesShared>, renderer: T) -> Self {
+        let mut scene = SceneGraph::new();
+        scene.set_time(res.time());
+
         World {
+            id: WorldId::next(),
             entities: HandlePool::new(),
             tags: Tags::new(),
-            scene: SceneGraph::new(),
+            ticks: TickGroups::new(),
+            paths: PathFollowers::new(),
+            animators: Animators::new(),
+            colliders: Colliders::new(),
+            controllers: CharacterControllers::new(),
+            triggers: Triggers::new(),
+            scene: scene,
             renderables: Renderable::new(),
             renderer: renderer,
             res: res,
         }
     }
 
+    /// Gets the unique identifier of this world.
+    #[inline]
+    pub fn id(&self) -> WorldId {
+        self.id
+    }
+
     /// Creates a new Entity.
     pub fn create(&mut self) -> Entity {
         world_impl::create(&mut self.entities, &mut self.scene)
@@ -38,13 +131,61 @@ impl<T: Renderer> World<T> {
 
     /// Removes a Entity and all of its descendants from this world.
     pub fn remove(&mut self, ent: Entity) -> Option<Vec<Entity>> {
-        world_impl::remove(
+        let deletions = world_impl::remove(
             &mut self.entities,
             &mut self.scene,
             &mut self.renderables,
             &mut self.tags,
             ent,
-        )
+        );
+
+        if let Some(ref deletions) = deletions {
+            for &v in deletions {
+                self.ticks.remove(v);
+                self.paths.remove(v);
+                self.animators.remove(v);
+                self.colliders.remove(v);
+                self.controllers.remove(v);
+                self.triggers.remove(v);
+            }
+        }
+
+        deletions
+    }
+
+    /// Returns true if the Entity is still alive in this world.
+    #[inline]
+    pub fn is_alive(&self, ent: Entity) -> bool {
+        self.entities.is_alive(ent)
+    }
+
+    /// Resolves the `EntityRef` into Entity, returns `None` if the referenced Entity
+    /// has been removed.
+    #[inline]
+    pub fn resolve(&self, v: EntityRef) -> Option<Entity> {
+        v.get(&self.entities)
+    }
+
+    /// Attachs `child` to `parent`, before its existing children. The local transform of
+    /// child is kept. Passing `None` as parent makes the child a root.
+    pub fn set_parent<P>(&mut self, child: Entity, parent: P) -> Result<()>
+    where
+        P: Into<Option<Entity>>,
+    {
+        self.scene.set_parent(child, parent, false)?;
+        Ok(())
+    }
+
+    /// Gets the parent of Entity.
+    #[inline]
+    pub fn parent(&self, ent: Entity) -> Option<Entity> {
+        self.scene.parent(ent)
+    }
+
+    /// Returns an iterator of the children of Entity.
+    #[inline]
+    pub fn children(&self, ent: Entity) -> Children {
+        self.scene.children(ent)
     }
 
     /// Finds a Entity by name and returns it.
@@ -58,6 +199,14 @@ impl<T: Renderer> World<T> {
 
     /// Instantiates a prefab into entities of this world.
     pub fn instantiate(&mut self, handle: PrefabHandle) -> Result<Entity> {
+        let (root, _) = self.instantiate_with_remap(handle)?;
+        Ok(root)
+    }
+
+    /// Instantiates a prefab into entities of this world, and returns the mappings from
+    /// the prefab nodes (as `Entity` with node index) to instantiated entities. Its
+    /// useful to fixup the `EntityRef`s which refer to prefab nodes.
+    pub fn instantiate_with_remap(&mut self, handle: PrefabHandle) -> Result<(Entity, EntityRemap)> {
         if let Some(prefab) = self.res.prefab(handle) {
             world_impl::instantiate(
                 &mut self.entities,
@@ -71,8 +220,439 @@ impl<T: Renderer> World<T> {
         }
     }
 
-    pub fn advance(&mut self) {
-        self.renderables.draw(&mut self.renderer, &self.scene);
+    /// Prepares the nodes of prefab on the workers of `sched` asynchronously. The
+    /// `CommandBuffer` taken from the job should be merged with `World::apply`.
+    pub fn instantiate_async(
+        &self,
+        sched: &Arc<ScheduleSystemShared>,
+        handle: PrefabHandle,
+    ) -> Result<InstantiateJob> {
+        if let Some(prefab) = self.res.prefab(handle) {
+            Ok(InstantiateJob::spawn(sched.clone(), prefab))
+        } else {
+            bail!("{:?} is not valid.", handle);
+        }
+    }
+
+    /// Merges the prepared nodes into entities of this world, and returns the root and
+    /// the mappings from the prefab nodes to instantiated entities.
+    pub fn apply(&mut self, commands: CommandBuffer) -> Result<(Entity, EntityRemap)> {
+        world_impl::apply(
+            &mut self.entities,
+            &mut self.scene,
+            &mut self.renderables,
+            &mut self.tags,
+            commands,
+        )
+    }
+
+    /// Applies the recorded commands in order, and returns the mappings from the
+    /// placeholders to created entities. The commands on the removed entities are
+    /// ignored.
+    ///
+    /// The commands are applied atomically, the world is left untouched if any of them
+    /// fails. The placeholders are checked before anything is applied, and only the
+    /// changes of hierarchy could fail halfway, which restore the world from a snapshot
+    /// taken beforehand.
+    pub fn apply_commands(&mut self, commands: EntityCommandBuffer) -> Result<EntityRemap> {
+        let mut created = HashSet::new();
+        let mut hierarchy = false;
+
+        for v in &commands.commands {
+            let (ent, parent) = match *v {
+                EntityCommand::Create(placeholder) => {
+                    created.insert(placeholder);
+                    continue;
+                }
+                EntityCommand::Remove(ent) => (ent, None),
+                EntityCommand::Add(ent, EntityComponent::Parent(parent)) => (ent, Some(parent)),
+                EntityCommand::Add(ent, _) => (ent, None),
+                EntityCommand::RemoveComponent(ent, kind) => {
+                    hierarchy |= kind == EntityComponentKind::Parent;
+                    (ent, None)
+                }
+            };
+
+            hierarchy |= parent.is_some();
+            for v in Some(ent).into_iter().chain(parent) {
+                if v.is_placeholder() && !created.contains(&v) {
+                    bail!("{:?} is not created by the command buffer.", v);
+                }
+            }
+        }
+
+        let state = if hierarchy { Some(self.save()) } else { None };
+        let result = self.apply_commands_in_order(commands);
+        if let (&Err(_), Some(state)) = (&result, state) {
+            self.restore(&state);
+        }
+
+        result
+    }
+
+    fn apply_commands_in_order(&mut self, commands: EntityCommandBuffer) -> Result<EntityRemap> {
+        let mut remap = EntityRemap::new();
+
+        for v in commands.commands {
+            match v {
+                EntityCommand::Create(placeholder) => {
+                    let ent = self.create();
+                    remap.insert(placeholder, ent);
+                }
+                EntityCommand::Remove(ent) => {
+                    let ent = Self::resolve_placeholder(&remap, ent)?;
+                    self.remove(ent);
+                }
+                EntityCommand::Add(ent, component) => {
+                    let ent = Self::resolve_placeholder(&remap, ent)?;
+                    if self.is_alive(ent) {
+                        self.add_component(&remap, ent, component)?;
+                    }
+                }
+                EntityCommand::RemoveComponent(ent, kind) => {
+                    let ent = Self::resolve_placeholder(&remap, ent)?;
+                    if self.is_alive(ent) {
+                        self.remove_component(ent, kind)?;
+                    }
+                }
+            }
+        }
+
+        Ok(remap)
+    }
+
+    fn resolve_placeholder(remap: &EntityRemap, ent: Entity) -> Result<Entity> {
+        if !ent.is_placeholder() {
+            return Ok(ent);
+        }
+
+        match remap.get(ent) {
+            Some(v) => Ok(v),
+            None => bail!("{:?} is not created by the command buffer.", ent),
+        }
+    }
+
+    fn add_component(
+        &mut self,
+        remap: &EntityRemap,
+        ent: Entity,
+        v: EntityComponent,
+    ) -> Result<()> {
+        match v {
+            EntityComponent::Name(v) => self.tags.add(ent, v),
+            EntityComponent::Transform(v) => self.scene.set_local_transform(ent, v),
+            EntityComponent::Parent(v) => {
+                let parent = Self::resolve_placeholder(remap, v)?;
+                if self.is_alive(parent) {
+                    self.scene.set_parent(ent, parent, false)?;
+                }
+            }
+            EntityComponent::TickRate(v) => self.ticks.set(ent, v),
+            EntityComponent::Camera(v) => self.renderables.add_camera(ent, *v),
+            EntityComponent::Lit(v) => self.renderables.add_lit(ent, *v),
+            EntityComponent::MeshRenderer(v) => self.renderables.add_mesh(ent, *v),
+            EntityComponent::LineRenderer(v) => self.renderables.add_line(ent, *v),
+            EntityComponent::TrailRenderer(v) => self.renderables.add_trail(ent, *v),
+            EntityComponent::BlobShadow(v) => self.renderables.add_blob_shadow(ent, *v),
+            EntityComponent::Collider(v) => self.colliders.add(ent, *v),
+            EntityComponent::TriggerVolume(v) => self.triggers.add(ent, *v),
+            EntityComponent::CharacterController(v) => self.controllers.add(ent, *v),
+            EntityComponent::PathFollow(v) => self.paths.add(ent, *v),
+            EntityComponent::Animator(v) => self.animators.add(ent, *v),
+        }
+
+        Ok(())
+    }
+
+    fn remove_component(&mut self, ent: Entity, kind: EntityComponentKind) -> Result<()> {
+        match kind {
+            EntityComponentKind::Name => self.tags.remove(ent),
+            EntityComponentKind::Transform => {
+                self.scene.set_local_transform(ent, Default::default())
+            }
+            EntityComponentKind::Parent => self.scene.remove_from_parent(ent, false)?,
+            EntityComponentKind::TickRate => self.ticks.remove(ent),
+            EntityComponentKind::Camera => self.renderables.remove_camera(ent),
+            EntityComponentKind::Lit => self.renderables.remove_lit(ent),
+            EntityComponentKind::MeshRenderer => self.renderables.remove_mesh(ent),
+            EntityComponentKind::LineRenderer => self.renderables.remove_line(ent),
+            EntityComponentKind::TrailRenderer => self.renderables.remove_trail(ent),
+            EntityComponentKind::BlobShadow => self.renderables.remove_blob_shadow(ent),
+            EntityComponentKind::Collider => self.colliders.remove(ent),
+            EntityComponentKind::TriggerVolume => self.triggers.remove(ent),
+            EntityComponentKind::CharacterController => self.controllers.remove(ent),
+            EntityComponentKind::PathFollow => self.paths.remove(ent),
+            EntityComponentKind::Animator => self.animators.remove(ent),
+        }
+
+        Ok(())
+    }
+
+    /// Gets the components of Entity, which could be added to the entities of any
+    /// world with `EntityCommandBuffer`. The runtime states which are not components,
+    /// e.g. the overlaps of triggers, are not included.
+    pub fn components(&self, ent: Entity) -> Vec<EntityComponent> {
+        let mut components = Vec::new();
+        if !self.is_alive(ent) {
+            return components;
+        }
+
+        if let Some(v) = self.tags.name(ent) {
+            components.push(v.to_owned().into());
+        }
+
+        if let Some(v) = self.scene.local_transform(ent) {
+            components.push(v.into());
+        }
+
+        if let Some(v) = self.scene.parent(ent) {
+            components.push(EntityComponent::Parent(v));
+        }
+
+        let rate = self.ticks.rate(ent);
+        if rate != TickRate::Full {
+            components.push(rate.into());
+        }
+
+        if let Some(v) = self.renderables.camera(ent) {
+            components.push((*v).into());
+        }
+
+        if let Some(v) = self.renderables.lit(ent) {
+            components.push((*v).into());
+        }
+
+        if let Some(v) = self.renderables.mesh(ent) {
+            components.push((*v).into());
+        }
+
+        if let Some(v) = self.renderables.line(ent) {
+            components.push(v.clone().into());
+        }
+
+        if let Some(v) = self.renderables.trail(ent) {
+            components.push(v.clone().into());
+        }
+
+        if let Some(v) = self.renderables.blob_shadow(ent) {
+            components.push((*v).into());
+        }
+
+        if let Some(v) = self.colliders.get(ent) {
+            components.push((*v).into());
+        }
+
+        if let Some(v) = self.triggers.get(ent) {
+            components.push((*v).into());
+        }
+
+        if let Some(v) = self.controllers.get(ent) {
+            components.push(v.clone().into());
+        }
+
+        if let Some(v) = self.paths.get(ent) {
+            components.push(v.clone().into());
+        }
+
+        if let Some(v) = self.animators.get(ent) {
+            components.push(v.clone().into());
+        }
+
+        components
+    }
+
+    /// Moves the Entity and all of its descendants into `other` world with their
+    /// components, and returns the mappings from the previous entities to the new ones.
+    /// The migrated Entity becomes a root of `other`, and its world transform is kept.
+    ///
+    /// The states of renderer, e.g. the materials of `SimpleRenderer`, are not migrated
+    /// since the worlds could use different renderers. The `EntityRef`s which refer to
+    /// the migrated entities should be fixed up with the returned mappings.
+    ///
+    /// The migration is atomic, both worlds are left untouched if it fails.
+    pub fn migrate<R: Renderer>(
+        &mut self,
+        ent: Entity,
+        other: &mut World<R>,
+    ) -> Result<EntityRemap> {
+        if !self.is_alive(ent) {
+            bail!("{:?} is not alive.", ent);
+        }
+
+        let mut entities = vec![ent];
+        entities.extend(self.scene.descendants(ent));
+
+        let mut remap = EntityRemap::new();
+        for &v in &entities {
+            remap.insert(v, other.create());
+        }
+
+        if let Err(err) = self.copy_into(&entities, &remap, other) {
+            for &v in &entities {
+                other.remove(remap.get(v).unwrap());
+            }
+
+            return Err(err);
+        }
+
+        self.remove(ent);
+        Ok(remap)
+    }
+
+    // Copies the components of `entities` into the entities of `other` in `remap`. The
+    // first one is the root, which keeps its world transform.
+    fn copy_into<R: Renderer>(
+        &self,
+        entities: &[Entity],
+        remap: &EntityRemap,
+        other: &mut World<R>,
+    ) -> Result<()> {
+        let root = entities[0];
+
+        let mut parents = Vec::new();
+        for &v in entities {
+            let to = remap.get(v).unwrap();
+            for component in self.components(v) {
+                match component {
+                    EntityComponent::Parent(parent) => {
+                        if v != root {
+                            parents.push((to, remap.get(parent).unwrap()));
+                        }
+                    }
+                    EntityComponent::Transform(_) if v == root => {
+                        let transform = self.scene.transform(root).unwrap();
+                        other.scene.set_local_transform(to, transform);
+                    }
+                    component => other.add_component(remap, to, component)?,
+                }
+            }
+        }
+
+        // The children are attached before the existing ones, so we attach them in
+        // reversed tree order to keep the order of siblings.
+        for (child, parent) in parents.into_iter().rev() {
+            other.scene.set_parent(child, parent, false)?;
+        }
+
+        Ok(())
+    }
+
+    /// Reassigns all the entities to continuous indices in tree order, and packs their
+    /// components densely in the same order. It restores the iteration performance
+    /// after heavy churn of creations and removals, and is usually called during the
+    /// loading screens since it touches every component.
+    ///
+    /// Returns the mappings from the previous entities to the new ones, which are never
+    /// alive again. The entities kept outside of this world should be fixed up with it,
+    /// or use `compact_with` to remap the stores of them at the same time.
+    pub fn compact(&mut self) -> EntityRemap {
+        self.compact_with(&mut [])
+    }
+
+    /// Compacts the entities like `compact`, and remaps the entities of `stores` which
+    /// are kept outside of this world, e.g. the `Component`s of game whose `EntityRef`s
+    /// are visited by `VisitEntityRefs`, and the bodies of physics.
+    pub fn compact_with(&mut self, stores: &mut [&mut EntityStore]) -> EntityRemap {
+        let remap = world_impl::compact(
+            &mut self.entities,
+            &mut self.scene,
+            &mut self.renderables,
+            &mut self.tags,
+        );
+
+        self.ticks.remap_entities(&remap);
+        self.paths.remap_entities(&remap);
+        self.animators.remap_entities(&remap);
+        self.colliders.remap_entities(&remap);
+        self.controllers.remap_entities(&remap);
+        self.triggers.remap_entities(&remap);
+        self.renderer.remap_entities(&remap);
+
+        for v in stores.iter_mut() {
+            v.remap_entities(&remap);
+        }
+
+        remap
+    }
+
+    /// Computes the checksum of simulation states, which is the same on every platform
+    /// if the states are the same. It's usually compared between peers every tick in
+    /// lockstep networking to detect desyncs.
+    ///
+    /// All the simulated components are hashed, the renderables are not.
+    pub fn checksum(&self) -> u64 {
+        let mut checksum = Checksum::new();
+        checksum.write_usize(self.entities.len());
+        self.tags.checksum(&mut checksum);
+        self.ticks.checksum(&mut checksum);
+        self.paths.checksum(&mut checksum);
+        self.animators.checksum(&mut checksum);
+        self.colliders.checksum(&mut checksum);
+        self.controllers.checksum(&mut checksum);
+        self.triggers.checksum(&mut checksum);
+        self.scene.checksum(&mut checksum);
+        checksum.finish()
+    }
+
+    /// Takes a snapshot of the entities and their components, which could be restored
+    /// with `restore` later. It's usually used as the `Simulation::State` of lockstep
+    /// simulations to roll back the mispredicted ticks.
+    ///
+    /// The states of renderer are not included, since they are not simulated.
+    pub fn save(&self) -> WorldState {
+        WorldState {
+            entities: self.entities.clone(),
+            tags: self.tags.clone(),
+            ticks: self.ticks.clone(),
+            paths: self.paths.clone(),
+            animators: self.animators.clone(),
+            colliders: self.colliders.clone(),
+            controllers: self.controllers.clone(),
+            triggers: self.triggers.clone(),
+            scene: self.scene.clone(),
+            renderables: self.renderables.clone(),
+        }
+    }
+
+    /// Restores the entities and their components from the snapshot. The entities
+    /// created after the snapshot was taken are removed, and the removed ones are
+    /// alive again with the same handles.
+    pub fn restore(&mut self, state: &WorldState) {
+        self.entities = state.entities.clone();
+        self.tags = state.tags.clone();
+        self.ticks = state.ticks.clone();
+        self.paths = state.paths.clone();
+        self.animators = state.animators.clone();
+        self.colliders = state.colliders.clone();
+        self.controllers = state.controllers.clone();
+        self.triggers = state.triggers.clone();
+        self.scene = state.scene.clone();
+        self.renderables = state.renderables.clone();
+    }
+
+    pub fn advance(&mut self) -> Result<()> {
+        self.advance_interpolated(1.0)
+    }
+
+    /// Draws the world with transforms interpolated between the last two fixed updates,
+    /// the `alpha` is usually `TimeSystemShared::fixed_alpha`.
+    ///
+    /// The invalid drawcalls are dropped by the renderer, and the first error is
+    /// returned after the whole world is drawn.
+    pub fn advance_interpolated(&mut self, alpha: f32) -> Result<()> {
+        let result = self
+            .renderables
+            .draw(&mut self.renderer, &self.scene, alpha, self.id);
+        self.scene.clear_dirty();
+        result
+    }
+
+    /// Draws this world with the cameras of `other` which select it with
+    /// `Camera::set_world`. It should be called after both worlds are advanced in
+    /// the current frame.
+    pub fn draw_cameras_of<R: Renderer>(&mut self, other: &World<R>) -> Result<()> {
+        self.renderables
+            .draw_cameras_of(&mut self.renderer, &other.renderables, self.id)
     }
 }
 
@@ -100,6 +680,9 @@ pub mod world_impl {
                 renderables.remove_mesh(v);
                 renderables.remove_lit(v);
                 renderables.remove_camera(v);
+                renderables.remove_line(v);
+                renderables.remove_trail(v);
+                renderables.remove_blob_shadow(v);
             }
 
             Some(deletions)
@@ -109,53 +692,79 @@ pub mod world_impl {
     }
 
     pub fn instantiate(
+        entities: &mut HandlePool,
+        scene: &mut SceneGraph,
+        renderables: &mut Renderable,
+        tags: &mut Tags,
+        prefab: &Prefab,
+    ) -> Result<(Entity, EntityRemap)> {
+        let commands = CommandBuffer::prepare(prefab);
+        apply(entities, scene, renderables, tags, commands)
+    }
+
+    pub fn apply(
         mut entities: &mut HandlePool,
         mut scene: &mut SceneGraph,
         renderables: &mut Renderable,
         tags: &mut Tags,
-        prefab: &Prefab,
-    ) -> Result<Entity> {
-        let mut root = None;
-        let mut nodes = Vec::new();
-        nodes.push((None, 0));
+        commands: CommandBuffer,
+    ) -> Result<(Entity, EntityRemap)> {
+        if commands.is_empty() {
+            bail!("The command buffer has no nodes.");
+        }
 
-        while let Some((parent, idx)) = nodes.pop() {
-            let n = &prefab.nodes[idx];
+        let mut remap = EntityRemap::new();
+        let mut created: Vec<Entity> = Vec::with_capacity(commands.len());
+
+        for n in commands.nodes {
             let e = create(&mut entities, &mut scene);
+            remap.insert(Entity::prefab_node(n.node), e);
 
             tags.add(e, &n.name);
             scene.set_local_transform(e, n.local_transform);
 
-            if let Some(parent) = parent {
-                scene.set_parent(e, parent, false).unwrap();
+            if let Some(parent) = n.parent {
+                scene.set_parent(e, created[parent], false).unwrap();
             }
 
-            if let Some(mesh) = n.mesh_renderer {
-                let mut mr = MeshRenderer::default();
-                mr.mesh = prefab.meshes[mesh];
+            if let Some(mr) = n.mesh_renderer {
                 renderables.add_mesh(e, mr);
             }
 
-            if let Some(sib) = n.next_sib {
-                nodes.push((parent, sib));
-            }
+            created.push(e);
+        }
 
-            if let Some(child) = n.first_child {
-                nodes.push((Some(e), child));
-            }
+        Ok((created[0], remap))
+    }
 
-            if root.is_none() {
-                root = Some(e);
-            }
+    pub fn compact(
+        entities: &mut HandlePool,
+        scene: &mut SceneGraph,
+        renderables: &mut Renderable,
+        tags: &mut Tags,
+    ) -> EntityRemap {
+        let mut order = Vec::with_capacity(entities.len());
+        for &v in scene.hierarchy().roots() {
+            order.push(v);
+            order.extend(scene.descendants(v));
         }
 
-        return Ok(root.unwrap());
+        let handles: Vec<Handle> = order.iter().map(|&v| v.into()).collect();
+        let mut remap = EntityRemap::new();
+        for (&from, to) in order.iter().zip(entities.compact(&handles)) {
+            remap.insert(from, to.into());
+        }
+
+        scene.remap_entities(&remap);
+        tags.remap_entities(&remap);
+        renderables.remap_entities(&remap);
+        remap
     }
 
     pub fn find<N: AsRef<str>>(scene: &SceneGraph, tags: &Tags, name: N) -> Option<Entity> {
         let mut components = name.as_ref().trim_left_matches('/').split('/');
         if let Some(first) = components.next() {
-            for &v in &scene.roots {
+            for &v in scene.hierarchy().roots() {
                 if let Some(n) = tags.name(v) {
                     if n == first {
                         let mut iter = v;
@@ -196,5 +805,4 @@ pub mod world_impl {
 
         None
     }
-
 }
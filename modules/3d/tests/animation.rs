@@ -0,0 +1,423 @@
+extern crate crayon;
+extern crate crayon_3d;
+
+use std::sync::Arc;
+use std::time::Duration;
+
+use crayon::math::{self, InnerSpace, Rotation3};
+use crayon::utils::handle_pool::HandlePool;
+
+use crayon_3d::animation::{AnimationClip, Animators, IkTarget, Keyframe, RootMotion};
+use crayon_3d::prelude::*;
+use crayon_3d::world_impl;
+
+fn close(lhs: math::Vector3<f32>, rhs: math::Vector3<f32>) -> bool {
+    (lhs - rhs).magnitude() < 1e-3
+}
+
+fn skeleton() -> Arc<Skeleton> {
+    let mut hips = Transform::default();
+    hips.position = math::Vector3::new(0.0, 1.0, 0.0);
+
+    let mut hand = Transform::default();
+    hand.position = math::Vector3::new(1.0, 0.0, 0.0);
+
+    let mut skeleton = Skeleton::new();
+    let root = skeleton.add("Hips", None, hips).unwrap();
+    skeleton.add("RightHand", Some(root), hand).unwrap();
+
+    assert!(skeleton.add("Hips", None, hips).is_err());
+    assert!(skeleton.add("LeftHand", Some(3), hand).is_err());
+    Arc::new(skeleton)
+}
+
+#[test]
+fn sockets() {
+    let mut animator = Animator::new(skeleton());
+    assert!(animator.add_socket("hand_l", "LeftHand", Transform::default()).is_err());
+
+    let mut offset = Transform::default();
+    offset.position = math::Vector3::new(0.0, 0.5, 0.0);
+    animator.add_socket("hand_r", "RightHand", offset).unwrap();
+
+    let hand = animator.bone("RightHand").unwrap();
+    assert!(close(hand.position, math::Vector3::new(1.0, 1.0, 0.0)));
+
+    let mut entities = HandlePool::new();
+    let mut scene = SceneGraph::new();
+    let mut animators = Animators::new();
+
+    let character = world_impl::create(&mut entities, &mut scene);
+    scene.set_position(character, math::Vector3::new(0.0, 0.0, 5.0));
+    animators.add(character, animator);
+    animators.advance(&mut scene, Duration::from_millis(16));
+
+    let socket = animators.get(character).unwrap().socket("hand_r").unwrap();
+    assert!(close(socket.position, math::Vector3::new(1.0, 1.5, 5.0)));
+    assert!(animators.get(character).unwrap().socket("hand_l").is_none());
+
+    // The sockets follow the animated pose.
+    let rotation = math::Quaternion::from_angle_y(math::Deg(90.0));
+    {
+        let animator = animators.get_mut(character).unwrap();
+        let mut hips = animator.local_pose(0).unwrap();
+        hips.rotation = rotation;
+        animator.set_local_pose(0, hips);
+    }
+
+    animators.advance(&mut scene, Duration::from_millis(16));
+    let socket = animators.get(character).unwrap().socket("hand_r").unwrap();
+    let expected = rotation * math::Vector3::new(1.0, 0.5, 0.0);
+    let expected = expected + math::Vector3::new(0.0, 1.0, 5.0);
+    assert!(close(socket.position, expected));
+}
+
+#[test]
+fn attach() {
+    let mut entities = HandlePool::new();
+    let mut scene = SceneGraph::new();
+    let mut animators = Animators::new();
+
+    let mut animator = Animator::new(skeleton());
+    animator.add_socket("hand_r", "RightHand", Transform::default()).unwrap();
+
+    let character = world_impl::create(&mut entities, &mut scene);
+    let sword = world_impl::create(&mut entities, &mut scene);
+    assert!(animators.attach(&mut scene, character, "hand_r", sword).is_err());
+
+    animators.add(character, animator);
+    animators.advance(&mut scene, Duration::from_millis(16));
+    assert!(animators.attach(&mut scene, character, "hand_l", sword).is_err());
+    assert!(animators.attach(&mut scene, character, "hand_r", character).is_err());
+
+    animators.attach(&mut scene, character, "hand_r", sword).unwrap();
+    assert_eq!(scene.parent(sword), Some(character));
+    assert!(close(scene.position(sword).unwrap(), math::Vector3::new(1.0, 1.0, 0.0)));
+
+    // Moves with the character.
+    scene.set_position(character, math::Vector3::new(2.0, 0.0, 0.0));
+    animators.advance(&mut scene, Duration::from_millis(16));
+    assert!(close(scene.position(sword).unwrap(), math::Vector3::new(3.0, 1.0, 0.0)));
+
+    // Moves as an ordinary child once detached.
+    animators.detach(sword);
+    animators.get_mut(character).unwrap().set_local_pose(0, Transform::default());
+    scene.set_position(character, math::Vector3::new(0.0, 0.0, 3.0));
+    animators.advance(&mut scene, Duration::from_millis(16));
+    assert!(close(scene.position(sword).unwrap(), math::Vector3::new(1.0, 1.0, 3.0)));
+}
+
+#[test]
+fn skin_matrices() {
+    let animator = Animator::new(skeleton());
+    let bind = animator.skeleton().bind_pose();
+    assert!(close(bind[1].position, math::Vector3::new(1.0, 1.0, 0.0)));
+
+    // The skinning matrices are identities in bind pose.
+    for m in animator.skin_matrices() {
+        let p = m * math::Vector4::new(1.0, 2.0, 3.0, 1.0);
+        assert!(close(p.truncate(), math::Vector3::new(1.0, 2.0, 3.0)));
+    }
+
+    let mut entities = HandlePool::new();
+    let mut scene = SceneGraph::new();
+    let mut animators = Animators::new();
+
+    let character = world_impl::create(&mut entities, &mut scene);
+    animators.add(character, animator);
+
+    let rotation = math::Quaternion::from_angle_y(math::Deg(90.0));
+    {
+        let animator = animators.get_mut(character).unwrap();
+        let mut hips = animator.local_pose(0).unwrap();
+        hips.rotation = rotation;
+        animator.set_local_pose(0, hips);
+    }
+
+    animators.advance(&mut scene, Duration::from_millis(16));
+
+    // The vertices at the hand in bind pose follow the hand.
+    let matrices = animators.get(character).unwrap().skin_matrices();
+    assert_eq!(matrices.len(), 2);
+    let p = matrices[1] * math::Vector4::new(1.0, 1.0, 0.0, 1.0);
+    let expected = rotation * math::Vector3::new(1.0, 0.0, 0.0);
+    assert!(close(p.truncate(), expected + math::Vector3::new(0.0, 1.0, 0.0)));
+}
+
+fn clip() -> Arc<AnimationClip> {
+    let mut clip = AnimationClip::new(2.0);
+
+    let mut lifted = Transform::default();
+    lifted.position = math::Vector3::new(0.0, 3.0, 0.0);
+    let keys = vec![
+        Keyframe {
+            time: 0.0,
+            transform: Transform::default(),
+        },
+        Keyframe {
+            time: 2.0,
+            transform: lifted,
+        },
+    ];
+
+    clip.add_track("Hips", keys.clone()).unwrap();
+    clip.add_track("Tail", keys.clone()).unwrap();
+    assert!(clip.add_track("Hips", keys.into_iter().rev().collect()).is_err());
+
+    clip.add_event(0.5, "footstep", "right").unwrap();
+    clip.add_event(0.0, "footstep", "left").unwrap();
+    clip.add_event(1.0, "hit", "").unwrap();
+    assert!(clip.add_event(1.5, "hit", "").is_err());
+    Arc::new(clip)
+}
+
+#[test]
+fn playback() {
+    let mut entities = HandlePool::new();
+    let mut scene = SceneGraph::new();
+    let mut animators = Animators::new();
+
+    let character = world_impl::create(&mut entities, &mut scene);
+    let mut animator = Animator::new(skeleton());
+    animator.play(clip(), false);
+    animators.add(character, animator);
+
+    animators.advance(&mut scene, Duration::from_millis(500));
+    let hips = animators.get(character).unwrap().bone("Hips").unwrap();
+    assert!(close(hips.position, math::Vector3::new(0.0, 0.75, 0.0)));
+
+    animators.advance(&mut scene, Duration::from_millis(5000));
+    let animator = animators.get(character).unwrap();
+    assert!(!animator.is_playing());
+    assert!((animator.normalized_time().unwrap() - 1.0).abs() < 1e-3);
+
+    let hips = animator.bone("Hips").unwrap();
+    assert!(close(hips.position, math::Vector3::new(0.0, 3.0, 0.0)));
+}
+
+#[test]
+fn events() {
+    let mut entities = HandlePool::new();
+    let mut scene = SceneGraph::new();
+    let mut animators = Animators::new();
+
+    let character = world_impl::create(&mut entities, &mut scene);
+    let mut animator = Animator::new(skeleton());
+    animator.play(clip(), true);
+    animators.add(character, animator);
+
+    let names = |animators: &mut Animators| -> Vec<String> {
+        animators
+            .take_events()
+            .into_iter()
+            .map(|(ent, v)| {
+                assert_eq!(ent, character);
+                format!("{}:{}", v.name, v.payload)
+            })
+            .collect()
+    };
+
+    animators.advance(&mut scene, Duration::from_millis(500));
+    assert_eq!(names(&mut animators), ["footstep:left"]);
+
+    // Every event is emitted once when crossed.
+    animators.advance(&mut scene, Duration::from_millis(500));
+    animators.advance(&mut scene, Duration::from_millis(500));
+    assert_eq!(names(&mut animators), ["footstep:right"]);
+    assert!(names(&mut animators).is_empty());
+
+    // Wraps around the end of looping clip.
+    animators.advance(&mut scene, Duration::from_millis(1000));
+    assert_eq!(names(&mut animators), ["hit:", "footstep:left"]);
+
+    // A long step emits the events of one cycle at most.
+    animators.advance(&mut scene, Duration::from_millis(9000));
+    assert_eq!(names(&mut animators), ["footstep:right", "hit:", "footstep:left"]);
+    let t = animators.get(character).unwrap().normalized_time().unwrap();
+    assert!((t - 0.75).abs() < 1e-3);
+
+    animators.get_mut(character).unwrap().stop();
+    animators.advance(&mut scene, Duration::from_millis(1000));
+    assert!(names(&mut animators).is_empty());
+}
+
+#[test]
+fn root_motion() {
+    let mut from = Transform::default();
+    from.rotation = math::Quaternion::from_angle_y(math::Deg(90.0));
+    let mut to = from;
+    to.position = math::Vector3::new(2.0, 0.5, 0.0);
+
+    // Moves forward while facing the positive x-axis.
+    let motion = RootMotion::between(&from, &to);
+    assert!(close(motion.translation, math::Vector3::new(0.0, 0.0, 2.0)));
+
+    let mut transform = from;
+    motion.apply(&mut transform);
+    assert!(close(transform.position, math::Vector3::new(2.0, 0.0, 0.0)));
+
+    // Walks 4 units forward in every cycle.
+    let mut clip = AnimationClip::new(2.0);
+    let mut end = Transform::default();
+    end.position = math::Vector3::new(0.0, 1.0, 4.0);
+    let keys = vec![
+        Keyframe {
+            time: 0.0,
+            transform: skeleton().bones()[0].local,
+        },
+        Keyframe {
+            time: 2.0,
+            transform: end,
+        },
+    ];
+    clip.add_track("Hips", keys).unwrap();
+
+    let mut entities = HandlePool::new();
+    let mut scene = SceneGraph::new();
+    let mut animators = Animators::new();
+
+    let character = world_impl::create(&mut entities, &mut scene);
+    let mut animator = Animator::new(skeleton());
+    assert!(animator.enable_root_motion("Root", true).is_err());
+    animator.enable_root_motion("Hips", true).unwrap();
+    animator.play(Arc::new(clip), true);
+    animators.add(character, animator);
+
+    animators.advance(&mut scene, Duration::from_millis(500));
+    let animator = animators.get(character).unwrap();
+    assert!(close(animator.root_motion().translation, math::Vector3::new(0.0, 0.0, 1.0)));
+
+    // The motion on the ground is removed from pose.
+    let hips = animator.bone("Hips").unwrap();
+    assert!(close(hips.position, math::Vector3::new(0.0, 1.0, 0.0)));
+
+    // Wraps around the end of clip.
+    animators.advance(&mut scene, Duration::from_millis(2000));
+    let animator = animators.get(character).unwrap();
+    assert!(close(animator.root_motion().translation, math::Vector3::new(0.0, 0.0, 4.0)));
+
+    // A step longer than the clip includes the motion of the whole cycles passed.
+    animators.advance(&mut scene, Duration::from_millis(5000));
+    let animator = animators.get(character).unwrap();
+    assert!(close(animator.root_motion().translation, math::Vector3::new(0.0, 0.0, 10.0)));
+
+    animators.get_mut(character).unwrap().stop();
+    animators.advance(&mut scene, Duration::from_millis(500));
+    let animator = animators.get(character).unwrap();
+    assert!(close(animator.root_motion().translation, math::Vector3::new(0.0, 0.0, 0.0)));
+}
+
+fn leg() -> Arc<Skeleton> {
+    let mut skeleton = Skeleton::new();
+
+    let mut local = Transform::default();
+    local.position = math::Vector3::new(0.0, 2.0, 0.0);
+    let hips = skeleton.add("Hips", None, local).unwrap();
+
+    local.position = math::Vector3::new(0.0, -1.0, 0.0);
+    let thigh = skeleton.add("Thigh", Some(hips), local).unwrap();
+    let shin = skeleton.add("Shin", Some(thigh), local).unwrap();
+    skeleton.add("Foot", Some(shin), local).unwrap();
+
+    local.position = math::Vector3::new(0.0, 1.0, 0.0);
+    skeleton.add("Head", Some(hips), local).unwrap();
+
+    assert!(skeleton.add_two_bone_ik("leg", "Hips", "Shin", "Foot").is_err());
+    skeleton.add_two_bone_ik("leg", "Thigh", "Shin", "Foot").unwrap();
+
+    let forward = math::Vector3::new(0.0, 0.0, 1.0);
+    assert!(skeleton.add_look_at_ik("look", "Neck", forward, 1.0).is_err());
+    skeleton.add_look_at_ik("look", "Head", forward, 1.0).unwrap();
+    Arc::new(skeleton)
+}
+
+#[test]
+fn two_bone_ik() {
+    let mut entities = HandlePool::new();
+    let mut scene = SceneGraph::new();
+    let mut animators = Animators::new();
+
+    let character = world_impl::create(&mut entities, &mut scene);
+    scene.set_position(character, math::Vector3::new(0.0, 0.0, 5.0));
+
+    let mut animator = Animator::new(leg());
+    assert!(animator.set_ik_target("arm", IkTarget::new([0.0, 0.0, 0.0])).is_err());
+
+    // Plants the foot on the raised terrain, and bends the knee forward.
+    let mut target = IkTarget::new([0.3, 0.5, 5.2]);
+    target.pole = Some(math::Vector3::new(0.0, 1.0, 10.0));
+    animator.set_ik_target("leg", target).unwrap();
+    animators.add(character, animator);
+    animators.advance(&mut scene, Duration::from_millis(16));
+
+    let animator = animators.get(character).unwrap();
+    let foot = animator.bone("Foot").unwrap();
+    assert!(close(foot.position, math::Vector3::new(0.3, 0.5, 0.2)));
+    assert!(animator.bone("Shin").unwrap().position.z > 0.5);
+    assert!(close(animator.local_pose(1).unwrap().position, math::Vector3::new(0.0, -1.0, 0.0)));
+
+    // The unreachable targets are approached as close as possible.
+    let thigh = animator.bone("Thigh").unwrap().position;
+    let target = IkTarget::new([0.0, -5.0, 5.0]);
+    animators.get_mut(character).unwrap().set_ik_target("leg", target).unwrap();
+    animators.advance(&mut scene, Duration::from_millis(16));
+
+    let foot = animators.get(character).unwrap().bone("Foot").unwrap();
+    assert!((foot.position - thigh).magnitude() < 2.0 + 1e-3);
+    assert!(foot.position.y < thigh.y - 1.99);
+
+    // Follows the animated pose once cleared.
+    animators.get_mut(character).unwrap().clear_ik_target("leg");
+    animators.advance(&mut scene, Duration::from_millis(16));
+
+    let foot = animators.get(character).unwrap().bone("Foot").unwrap();
+    assert!(close(foot.position, math::Vector3::new(0.0, 0.0, 0.0)));
+}
+
+#[test]
+fn look_at_ik() {
+    let mut entities = HandlePool::new();
+    let mut scene = SceneGraph::new();
+    let mut animators = Animators::new();
+
+    let character = world_impl::create(&mut entities, &mut scene);
+    let rotation = math::Quaternion::from_angle_y(math::Deg(90.0));
+    scene.set_rotation(character, rotation);
+
+    let forward = |animators: &Animators| {
+        let head = animators.get(character).unwrap().bone("Head").unwrap();
+        head.forward()
+    };
+
+    // Looks at the target in front of character, which is the positive x-axis in world.
+    let mut animator = Animator::new(leg());
+    animator.set_ik_target("look", IkTarget::new([10.0, 13.0, 0.0])).unwrap();
+    animators.add(character, animator);
+    animators.advance(&mut scene, Duration::from_millis(16));
+
+    let expected = math::Vector3::new(0.0, 1.0, 1.0).normalize();
+    assert!(close(forward(&animators), expected));
+
+    // The rotation is clamped to the limit.
+    let target = IkTarget::new([0.0, 3.0, -10.0]);
+    animators.get_mut(character).unwrap().set_ik_target("look", target).unwrap();
+    animators.advance(&mut scene, Duration::from_millis(16));
+
+    let v = forward(&animators);
+    assert!((v.dot(math::Vector3::new(0.0, 0.0, 1.0)) - 1.0f32.cos()).abs() < 1e-3);
+    assert!(v.x > 0.0);
+
+    // Blends with the animated pose by weight.
+    let mut target = IkTarget::new([10.0, 13.0, 0.0]);
+    target.weight = 0.0;
+    animators.get_mut(character).unwrap().set_ik_target("look", target).unwrap();
+    animators.advance(&mut scene, Duration::from_millis(16));
+    assert!(close(forward(&animators), math::Vector3::new(0.0, 0.0, 1.0)));
+
+    target.weight = 0.5;
+    animators.get_mut(character).unwrap().set_ik_target("look", target).unwrap();
+    animators.advance(&mut scene, Duration::from_millis(16));
+
+    let v = forward(&animators);
+    assert!(v.y > 0.1 && v.y < expected.y - 0.1);
+}
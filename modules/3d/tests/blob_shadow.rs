@@ -0,0 +1,97 @@
+extern crate crayon;
+extern crate crayon_3d;
+
+use crayon::math::{self, InnerSpace, Rotation3};
+use crayon::utils::handle_pool::HandlePool;
+
+use crayon_3d::physics::Colliders;
+use crayon_3d::prelude::*;
+use crayon_3d::renderers::Renderable;
+use crayon_3d::world_impl;
+
+fn approx(lhs: math::Vector3<f32>, rhs: [f32; 3]) -> bool {
+    (lhs - math::Vector3::from(rhs)).magnitude() < 1e-4
+}
+
+#[test]
+fn projection() {
+    let mut entities = HandlePool::new();
+    let mut scene = SceneGraph::new();
+    let mut colliders = Colliders::new();
+    let mut renderables = Renderable::new();
+
+    let floor = world_impl::create(&mut entities, &mut scene);
+    scene.set_position(floor, [0.0, -0.5, 0.0]);
+    colliders.add(floor, Collider::Box([10.0, 0.5, 10.0].into()));
+
+    // The collider of character itself is skipped.
+    let player = world_impl::create(&mut entities, &mut scene);
+    colliders.add(player, Collider::Sphere(1.0));
+    renderables.add_blob_shadow(player, BlobShadow::new(0.5));
+
+    renderables.update_blob_shadows(&scene, &colliders);
+    let quad = renderables
+        .blob_shadow(player)
+        .unwrap()
+        .projection()
+        .unwrap();
+    assert!(approx(quad.center, [0.0, 0.01, 0.0]));
+    assert!(approx(quad.normal, [0.0, 1.0, 0.0]));
+    assert!(approx(quad.tangent, [1.0, 0.0, 0.0]));
+    assert_eq!(quad.extents, math::Vector2::new(0.5, 0.5));
+    assert_eq!(quad.radius, 0.5);
+    assert_eq!(quad.softness, 0.25);
+    assert_eq!(quad.opacity, 0.6);
+
+    // Gets larger and fainter above the ground.
+    scene.set_position(player, [0.0, 2.5, 0.0]);
+    renderables.update_blob_shadows(&scene, &colliders);
+    let quad = renderables
+        .blob_shadow(player)
+        .unwrap()
+        .projection()
+        .unwrap();
+    assert!(approx(quad.center, [0.0, 0.01, 0.0]));
+    assert!((quad.extents.x - 0.625).abs() < 1e-4);
+    assert!((quad.opacity - 0.3).abs() < 1e-4);
+
+    // And disappears beyond the max height.
+    scene.set_position(player, [0.0, 6.0, 0.0]);
+    renderables.update_blob_shadows(&scene, &colliders);
+    assert!(renderables
+        .blob_shadow(player)
+        .unwrap()
+        .projection()
+        .is_none());
+
+    renderables.remove_blob_shadow(player);
+    assert!(renderables.blob_shadow(player).is_none());
+}
+
+#[test]
+fn orientation() {
+    let mut entities = HandlePool::new();
+    let mut scene = SceneGraph::new();
+    let mut colliders = Colliders::new();
+
+    let floor = world_impl::create(&mut entities, &mut scene);
+    scene.set_position(floor, [0.0, -0.5, 0.0]);
+    colliders.add(floor, Collider::Box([10.0, 0.5, 10.0].into()));
+
+    let player = world_impl::create(&mut entities, &mut scene);
+    scene.set_rotation(player, math::Quaternion::from_angle_y(math::Deg(90.0)));
+
+    // The shadow is aligned with the facing of entity.
+    let mut shadow = BlobShadow::new(0.5);
+    shadow.extents = math::Vector2::new(1.0, 0.5);
+    shadow.depth_offset = 0.0;
+    shadow.update(&scene, &colliders, player);
+
+    let quad = shadow.projection().unwrap();
+    assert!(approx(quad.tangent, [0.0, 0.0, -1.0]));
+    assert_eq!(quad.radius, 0.5);
+
+    let corners = quad.corners();
+    assert!(approx(corners[0], [-0.5, 0.0, 1.0]));
+    assert!(approx(corners[2], [0.5, 0.0, -1.0]));
+}
@@ -0,0 +1,78 @@
+//! The helpers shared by the integration tests.
+
+#![allow(dead_code)]
+
+use std::iter::{self, Repeat, Take};
+use std::time::Duration;
+
+use crayon::math;
+use crayon::utils::handle_pool::HandlePool;
+
+use crayon_3d::physics::{CharacterControllers, Colliders, Triggers};
+use crayon_3d::prelude::*;
+use crayon_3d::world_impl;
+
+/// Returns the fixed timesteps of 60 fps which last `seconds`.
+pub fn fixed_steps(seconds: f32) -> Take<Repeat<Duration>> {
+    iter::repeat(Duration::new(0, 16_666_667)).take((seconds * 60.0) as usize)
+}
+
+/// The scene with a floor, which moves characters against the static colliders.
+pub struct Fixture {
+    pub entities: HandlePool,
+    pub scene: SceneGraph,
+    pub colliders: Colliders,
+    pub controllers: CharacterControllers,
+    pub triggers: Triggers,
+}
+
+impl Fixture {
+    pub fn new() -> Self {
+        let mut fixture = Fixture {
+            entities: HandlePool::new(),
+            scene: SceneGraph::new(),
+            colliders: Colliders::new(),
+            controllers: CharacterControllers::new(),
+            triggers: Triggers::new(),
+        };
+
+        let floor = fixture.collider([0.0, -0.5, 0.0], Collider::Box([20.0, 0.5, 20.0].into()));
+        fixture.collider([0.0, 0.0, -20.0], Collider::Sphere(1.0));
+        assert!(fixture.colliders.get(floor).is_some());
+        fixture
+    }
+
+    pub fn collider(&mut self, position: [f32; 3], collider: Collider) -> Entity {
+        let ent = world_impl::create(&mut self.entities, &mut self.scene);
+        self.scene.set_position(ent, position);
+        self.colliders.add(ent, collider);
+        ent
+    }
+
+    pub fn character(&mut self, position: [f32; 3]) -> Entity {
+        let ent = world_impl::create(&mut self.entities, &mut self.scene);
+        self.scene.set_position(ent, position);
+        self.controllers
+            .add(ent, CharacterController::new(0.5, 2.0));
+        ent
+    }
+
+    pub fn volume(&mut self, position: [f32; 3], shape: TriggerShape) -> Entity {
+        let ent = world_impl::create(&mut self.entities, &mut self.scene);
+        self.scene.set_position(ent, position);
+        self.triggers.add(ent, TriggerVolume::new(shape));
+        ent
+    }
+
+    /// Advances the character controllers with the fixed timestep of 60 fps.
+    pub fn run(&mut self, seconds: f32) {
+        for dt in fixed_steps(seconds) {
+            self.controllers
+                .advance(&mut self.scene, &self.colliders, dt);
+        }
+    }
+
+    pub fn position(&self, ent: Entity) -> math::Vector3<f32> {
+        self.scene.position(ent).unwrap()
+    }
+}
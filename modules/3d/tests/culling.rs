@@ -0,0 +1,164 @@
+extern crate crayon;
+extern crate crayon_3d;
+
+use std::cell::Cell;
+
+use crayon::math;
+use crayon::video::prelude::MeshHandle;
+use crayon_3d::renderers::*;
+
+fn mesh(position: [f32; 3], layers: u32) -> MeshRenderer {
+    let mut mesh = MeshRenderer {
+        layers: layers,
+        ..Default::default()
+    };
+
+    mesh.transform.position = position.into();
+    mesh
+}
+
+#[test]
+fn cull_meshes() {
+    let mut camera = Camera::perspective(math::Deg(60.0), 1.0, 0.1, 100.0);
+    camera.set_culling_mask(0b011);
+
+    let mut hidden = mesh([0.0, 0.0, 10.0], 1);
+    hidden.visible = false;
+
+    let meshes = [
+        mesh([0.0, 0.0, 10.0], 1),
+        mesh([0.0, 0.0, -10.0], 1),
+        mesh([100.0, 0.0, 10.0], 2),
+        mesh([0.0, 0.0, 10.0], 4),
+        mesh([0.0, 0.0, 200.0], 2),
+        hidden,
+    ];
+
+    let aabb = math::Aabb3::new(
+        math::Point3::new(-1.0, -1.0, -1.0),
+        math::Point3::new(1.0, 1.0, 1.0),
+    );
+
+    let mut visible = Vec::new();
+    let info = cull(&camera, &meshes, |_| Some(aabb), &mut visible);
+    assert_eq!(info.tested, 5);
+    assert_eq!(info.layer_culled, 1);
+    assert_eq!(info.frustum_culled, 3);
+    assert_eq!(info.occlusion_culled, 0);
+    assert_eq!(info.visible(), 1);
+    assert_eq!(visible.len(), 1);
+    assert_eq!(visible[0].transform.position, meshes[0].transform.position);
+
+    // The meshes without bounds are never culled by frustum.
+    visible.clear();
+    let info = cull(&camera, &meshes, |_| None, &mut visible);
+    assert_eq!(info.frustum_culled, 0);
+    assert_eq!(visible.len(), 4);
+
+    // Moves the camera forward, leaves the near meshes behind.
+    camera.transform.position = math::Vector3::new(0.0, 0.0, 150.0);
+    visible.clear();
+    let info = cull(&camera, &meshes, |_| Some(aabb), &mut visible);
+    assert_eq!(info.frustum_culled, 3);
+    assert_eq!(visible[0].transform.position, meshes[4].transform.position);
+}
+
+#[test]
+fn cull_cached_meshes() {
+    let mut camera = Camera::perspective(math::Deg(60.0), 1.0, 0.1, 100.0);
+    let mut meshes = [mesh([0.0, 0.0, 10.0], 1), mesh([0.0, 0.0, -10.0], 1)];
+
+    let extents = Cell::new(1.0);
+    let aabb = |_: MeshHandle| {
+        let v = extents.get();
+        Some(math::Aabb3::new(
+            math::Point3::new(-v, -v, -v),
+            math::Point3::new(v, v, v),
+        ))
+    };
+
+    let mut cache = VisibilityCache::new();
+    let mut visible = Vec::new();
+    let info = cull_cached(&mut cache, &camera, &meshes, &aabb, &mut visible);
+    assert_eq!(info.reused, 0);
+    assert_eq!(info.frustum_culled, 1);
+
+    // Nothing moves, the results of last frame are reused.
+    visible.clear();
+    let info = cull_cached(&mut cache, &camera, &meshes, &aabb, &mut visible);
+    assert_eq!(info.reused, 2);
+    assert_eq!(info.frustum_culled, 1);
+    assert_eq!(visible.len(), 1);
+
+    // Only the moved mesh is tested again.
+    meshes[1].transform.position = math::Vector3::new(0.0, 0.0, 20.0);
+    visible.clear();
+    let info = cull_cached(&mut cache, &camera, &meshes, &aabb, &mut visible);
+    assert_eq!(info.reused, 1);
+    assert_eq!(info.visible(), 2);
+
+    // The camera teleports, everything is tested again.
+    camera.transform.position = math::Vector3::new(0.0, 0.0, 150.0);
+    visible.clear();
+    let info = cull_cached(&mut cache, &camera, &meshes, &aabb, &mut visible);
+    assert_eq!(info.reused, 0);
+    assert_eq!(info.frustum_culled, 2);
+
+    // The meshes are edited in place, and their bounds reach the camera now.
+    extents.set(140.0);
+    visible.clear();
+    let info = cull_cached(&mut cache, &camera, &meshes, &aabb, &mut visible);
+    assert_eq!(info.reused, 0);
+    assert_eq!(info.frustum_culled, 0);
+
+    cache.invalidate(&camera);
+    let info = cull_cached(&mut cache, &camera, &meshes, &aabb, &mut visible);
+    assert_eq!(info.reused, 0);
+}
+
+#[test]
+fn cull_cached_cameras_of_worlds() {
+    use crayon::errors::Result;
+    use crayon::prelude::{Engine, Settings};
+    use crayon_3d::assets::WorldResources;
+    use crayon_3d::World;
+
+    struct Nil;
+
+    impl Renderer for Nil {
+        fn submit(&mut self, _: &Camera, _: &[Lit], _: &[MeshRenderer]) -> Result<()> {
+            Ok(())
+        }
+    }
+
+    let mut settings = Settings::default();
+    settings.headless = true;
+    let mut engine = Engine::new_with(&settings).unwrap();
+    let res = WorldResources::new(&mut engine);
+
+    let w1 = World::new(res.shared(), Nil);
+    let w2 = World::new(res.shared(), Nil);
+
+    // The cameras of both worlds have the same Entity.
+    let mut c1 = Camera::perspective(math::Deg(60.0), 1.0, 0.1, 100.0);
+    c1.owner = Some(w1.id());
+    let mut c2 = c1;
+    c2.owner = Some(w2.id());
+
+    let meshes = [mesh([0.0, 0.0, 10.0], 1)];
+    let aabb = |_: MeshHandle| None;
+
+    let mut cache = VisibilityCache::new();
+    let mut visible = Vec::new();
+    cull_cached(&mut cache, &c1, &meshes, &aabb, &mut visible);
+
+    let info = cull_cached(&mut cache, &c2, &meshes, &aabb, &mut visible);
+    assert_eq!(info.reused, 0);
+
+    let info = cull_cached(&mut cache, &c1, &meshes, &aabb, &mut visible);
+    assert_eq!(info.reused, 1);
+
+    cache.invalidate(&c2);
+    let info = cull_cached(&mut cache, &c1, &meshes, &aabb, &mut visible);
+    assert_eq!(info.reused, 1);
+}
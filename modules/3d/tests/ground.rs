@@ -0,0 +1,75 @@
+extern crate crayon;
+extern crate crayon_3d;
+
+use crayon::math::{self, InnerSpace};
+use crayon_3d::prelude::*;
+use crayon_3d::renderers::*;
+
+fn approx(lhs: math::Vector3<f32>, rhs: [f32; 3]) -> bool {
+    (lhs - math::Vector3::from(rhs)).magnitude() < 0.01
+}
+
+#[test]
+fn top_down() {
+    let mut ground = GroundPlane::new(1.0);
+    ground.resolution = math::Vector2::new(3, 5);
+
+    let mut transform = Transform::default();
+    transform.position = math::Vector3::new(0.0, 10.0, 0.0);
+    transform.look_at([0.0, 0.0, 0.0], [0.0, 0.0, 1.0]);
+    let camera = Camera::ortho(20.0, 10.0, 0.1, 100.0);
+
+    let vertices = ground.project(transform.view_matrix(), camera.frustum().to_matrix());
+    assert_eq!(vertices.len(), 15);
+    assert!(approx(vertices[0], [-10.0, 1.0, -5.0]));
+    assert!(approx(vertices[7], [0.0, 1.0, 0.0]));
+    assert!(approx(vertices[14], [10.0, 1.0, 5.0]));
+
+    let indices = ground.indices();
+    assert_eq!(indices.len(), 2 * 4 * 6);
+    assert_eq!(&indices[0..6], &[0, 1, 4, 0, 4, 3]);
+    assert_eq!(indices.iter().max(), Some(&14));
+}
+
+#[test]
+fn horizon() {
+    let mut ground = GroundPlane::new(0.0);
+    ground.resolution = math::Vector2::new(5, 9);
+
+    let mut transform = Transform::default();
+    transform.position = math::Vector3::new(0.0, 2.0, 0.0);
+    let camera = Camera::perspective(math::Deg(60.0), 1.0, 0.1, 100.0);
+
+    let vertices = ground.project(transform.view_matrix(), camera.frustum().to_matrix());
+    assert!(vertices.iter().all(|v| v.y == 0.0));
+
+    // The bottom of screen hits the plane right in front of camera.
+    let distance = 2.0 / (30.0f32).to_radians().tan();
+    assert!(approx(vertices[2], [0.0, 0.0, distance]));
+
+    // The rays above horizon stop at the far clip plane.
+    assert!((vertices[42].z - 100.0).abs() < 0.1);
+    assert!((vertices[38].z - 100.0).abs() < 0.1);
+
+    // The grid gets sparser towards the horizon.
+    let step = |j: usize| vertices[(j + 1) * 5 + 2].z - vertices[j * 5 + 2].z;
+    assert!(step(0) < step(1) && step(1) < step(2));
+
+    // Too large resolutions are clamped, so the indices fit into `u16`.
+    ground.resolution = math::Vector2::new(1000, 1);
+    let resolution = ground.clamped_resolution();
+    assert_eq!(
+        resolution,
+        math::Vector2::new(GroundPlane::MAX_RESOLUTION, 2)
+    );
+}
+
+#[test]
+fn tiling() {
+    let mut ground = GroundPlane::default();
+    ground.tile_size = math::Vector2::new(2.0, 4.0);
+    ground.tile_offset = math::Vector2::new(1.0, 1.0);
+
+    let v = ground.encode_tiling();
+    assert_eq!(v, math::Vector4::new(0.5, 0.25, 0.5, 0.25));
+}
@@ -0,0 +1,124 @@
+extern crate crayon;
+extern crate crayon_3d;
+
+use crayon::utils::handle_pool::HandlePool;
+use crayon_3d::prelude::*;
+
+struct Testbed {
+    world: HandlePool,
+    hierarchy: Hierarchy,
+}
+
+impl Testbed {
+    fn new() -> Testbed {
+        Testbed {
+            world: HandlePool::new(),
+            hierarchy: Hierarchy::new(),
+        }
+    }
+
+    fn create(&mut self) -> Entity {
+        let ent = self.world.create().into();
+        self.hierarchy.add(ent);
+        ent
+    }
+}
+
+#[test]
+fn parent() {
+    let mut testbed = Testbed::new();
+    let e1 = testbed.create();
+    let e2 = testbed.create();
+    let e3 = testbed.create();
+
+    let hierarchy = &mut testbed.hierarchy;
+    assert_eq!(hierarchy.roots().cloned().collect::<Vec<_>>(), [e1, e2, e3]);
+
+    hierarchy.set_parent(e2, e1).unwrap();
+    hierarchy.set_parent(e3, e2).unwrap();
+    assert_eq!(hierarchy.parent(e3), Some(e2));
+    assert!(hierarchy.is_root(e1) && !hierarchy.is_root(e2));
+    assert!(hierarchy.is_leaf(e3) && !hierarchy.is_leaf(e1));
+    assert_eq!(hierarchy.roots().cloned().collect::<Vec<_>>(), [e1]);
+
+    assert!(hierarchy.set_parent(e1, e1).is_err());
+    assert!(hierarchy.set_parent(e1, e3).is_err());
+    assert_eq!(hierarchy.parent(e1), None);
+
+    hierarchy.set_parent(e3, None).unwrap();
+    assert_eq!(hierarchy.parent(e3), None);
+    assert!(hierarchy.is_leaf(e2));
+}
+
+#[test]
+fn children() {
+    let mut testbed = Testbed::new();
+    let e1 = testbed.create();
+    let e2 = testbed.create();
+    let e3 = testbed.create();
+    let e4 = testbed.create();
+
+    let hierarchy = &mut testbed.hierarchy;
+    hierarchy.set_parent(e2, e1).unwrap();
+    hierarchy.set_parent(e3, e1).unwrap();
+    hierarchy.set_parent(e4, e1).unwrap();
+    assert_eq!(hierarchy.children(e1).collect::<Vec<_>>(), [e4, e3, e2]);
+
+    // Unlinks the siblings in the middle and at the end.
+    hierarchy.remove_from_parent(e3).unwrap();
+    assert_eq!(hierarchy.children(e1).collect::<Vec<_>>(), [e4, e2]);
+    hierarchy.set_parent(e3, e1).unwrap();
+    hierarchy.remove_from_parent(e2).unwrap();
+    assert_eq!(hierarchy.children(e1).collect::<Vec<_>>(), [e3, e4]);
+
+    hierarchy.set_parent(e2, e4).unwrap();
+    assert_eq!(hierarchy.remove(e4), Some(vec![e4, e2]));
+    assert!(!hierarchy.has(e2));
+    assert_eq!(hierarchy.children(e1).collect::<Vec<_>>(), [e3]);
+    assert_eq!(hierarchy.remove(e4), None);
+}
+
+#[test]
+fn dirty() {
+    let mut testbed = Testbed::new();
+    let e1 = testbed.create();
+    let e2 = testbed.create();
+    let e3 = testbed.create();
+
+    let hierarchy = &mut testbed.hierarchy;
+    assert_eq!(hierarchy.dirty().count(), 3);
+
+    hierarchy.set_parent(e3, e2).unwrap();
+    hierarchy.clear_dirty();
+    assert!(!hierarchy.is_dirty(e3));
+
+    hierarchy.set_parent(e2, e1).unwrap();
+    assert!(!hierarchy.is_dirty(e1));
+    assert!(hierarchy.is_dirty(e2) && hierarchy.is_dirty(e3));
+
+    hierarchy.clear_dirty();
+    hierarchy.set_dirty(e1);
+    assert_eq!(hierarchy.dirty().cloned().collect::<Vec<_>>(), [e1, e2, e3]);
+}
+
+#[test]
+fn transforms() {
+    let mut world = HandlePool::new();
+    let mut scene = SceneGraph::new();
+
+    let e1 = world.create().into();
+    let e2 = world.create().into();
+    scene.add(e1);
+    scene.add(e2);
+    scene.set_parent(e2, e1, false).unwrap();
+    scene.clear_dirty();
+
+    scene.set_local_position(e1, [1.0, 0.0, 0.0]);
+    assert!(scene.hierarchy().is_dirty(e1));
+    assert!(scene.hierarchy().is_dirty(e2));
+
+    scene.clear_dirty();
+    scene.set_local_scale(e2, 2.0);
+    assert!(!scene.hierarchy().is_dirty(e1));
+    assert!(scene.hierarchy().is_dirty(e2));
+}
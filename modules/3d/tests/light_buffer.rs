@@ -0,0 +1,125 @@
+extern crate crayon;
+extern crate crayon_3d;
+
+use crayon::math;
+use crayon::prelude::*;
+use crayon::video::assets::uniform_buffer::*;
+use crayon_3d::renderers::*;
+
+fn headless_with(profile: VideoProfile) -> Engine {
+    let mut settings = Settings::default();
+    settings.headless = true;
+    settings.video.profile = profile;
+    Engine::new_with(&settings).unwrap()
+}
+
+fn point(x: f32) -> Lit {
+    let mut lit = Lit {
+        source: LitSource::Point {
+            radius: 1.0,
+            smoothness: 0.0,
+        },
+        ..Default::default()
+    };
+
+    lit.transform.position = math::Vector3::new(x, 0.0, 0.0);
+    lit
+}
+
+fn lits() -> Vec<Lit> {
+    let disabled = Lit {
+        enable: false,
+        ..Default::default()
+    };
+
+    vec![
+        disabled,
+        point(8.0),
+        Lit::default(),
+        point(-2.0),
+        point(4.0),
+    ]
+}
+
+// Reads the element of vec4 (or vec3) array from the std140 bytes of block.
+fn element(block: &UniformBlock, name: &str, index: usize) -> [f32; 4] {
+    let field = block.layout().field(name).unwrap();
+    let offset = field.offset + field.stride * index;
+    let bytes = &block.as_bytes()[offset..(offset + 16)];
+
+    let mut v = [0.0; 4];
+    for (i, b) in bytes.chunks(4).enumerate() {
+        let bits = (b[0] as u32) | (b[1] as u32) << 8 | (b[2] as u32) << 16 | (b[3] as u32) << 24;
+        v[i] = f32::from_bits(bits);
+    }
+
+    v
+}
+
+#[test]
+fn gather() {
+    let mut lights = LightBuffer::new(1, 2);
+    lights.build(math::Matrix4::from_scale(1.0), [0.0; 3].into(), &lits());
+    assert_eq!(lights.num_dir_lits(), 1);
+    assert_eq!(lights.num_point_lits(), 2);
+    assert!(lights.uniform_buffer().is_none());
+    assert!(lights.block().is_none());
+
+    let layout = lights.declare_block(UniformBlockLayout::build());
+    let mut block = UniformBlock::new(layout.finish().unwrap());
+    lights.encode(&mut block).unwrap();
+
+    // The nearest point lights are gathered, with the attenuation packed into w.
+    assert_eq!(element(&block, "u_DirLitViewDir", 0)[..3], [0.0, 0.0, 1.0]);
+    assert_eq!(element(&block, "u_DirLitColor", 0)[..3], [1.0, 1.0, 1.0]);
+    assert_eq!(
+        element(&block, "u_PointLitViewPos", 0),
+        [-2.0, 0.0, 0.0, -1.0]
+    );
+    assert_eq!(
+        element(&block, "u_PointLitViewPos", 1),
+        [4.0, 0.0, 0.0, -1.0]
+    );
+    assert_eq!(element(&block, "u_PointLitColor", 1), [1.0, 1.0, 1.0, 0.0]);
+
+    // The unused slots are filled with black lights.
+    lights.build(math::Matrix4::from_scale(1.0), [0.0; 3].into(), &[]);
+    lights.encode(&mut block).unwrap();
+    assert_eq!(element(&block, "u_DirLitColor", 0), [0.0; 4]);
+    assert_eq!(element(&block, "u_PointLitViewPos", 0), [0.0; 4]);
+}
+
+#[test]
+fn shared() {
+    let engine = headless_with(VideoProfile::Standard);
+    let video = engine.context().video.clone();
+
+    let mut lights = LightBuffer::shared(&video, 1, 4).unwrap();
+    let buffer = lights.uniform_buffer().unwrap();
+    let size = lights.block().unwrap().as_bytes().len();
+    assert_eq!(video.uniform_buffer(buffer).unwrap().size, size);
+
+    // The lights are encoded into the shared block once per upload.
+    lights.build(math::Matrix4::from_scale(1.0), [0.0; 3].into(), &lits());
+    lights.upload(1).unwrap();
+
+    let block = lights.block().unwrap();
+    assert_eq!(
+        element(block, "u_PointLitViewPos", 0),
+        [-2.0, 0.0, 0.0, -1.0]
+    );
+    assert_eq!(
+        element(block, "u_PointLitViewPos", 2),
+        [8.0, 0.0, 0.0, -1.0]
+    );
+    assert_eq!(element(block, "u_PointLitViewPos", 3), [0.0; 4]);
+
+    // The uniform buffer is deleted with the `LightBuffer`.
+    drop(lights);
+    assert!(video.uniform_buffer(buffer).is_none());
+
+    // Uniform buffers are not available with GLES2.
+    let engine = headless_with(VideoProfile::Gles2);
+    let video = engine.context().video.clone();
+    assert!(LightBuffer::shared(&video, 1, 4).is_err());
+}
@@ -0,0 +1,67 @@
+extern crate crayon;
+extern crate crayon_3d;
+
+use std::sync::Arc;
+use std::time::Duration;
+
+use crayon::math::{self, InnerSpace};
+use crayon::utils::handle_pool::HandlePool;
+
+use crayon_3d::path::PathFollowers;
+use crayon_3d::prelude::*;
+use crayon_3d::world_impl;
+
+fn rail() -> Arc<math::Spline> {
+    let points = vec![
+        math::Vector3::new(0.0, 0.0, 0.0),
+        math::Vector3::new(0.0, 0.0, 5.0),
+        math::Vector3::new(0.0, 0.0, 10.0),
+    ];
+
+    Arc::new(math::Spline::catmull_rom(points).unwrap())
+}
+
+fn close(lhs: math::Vector3<f32>, rhs: math::Vector3<f32>) -> bool {
+    (lhs - rhs).magnitude() < 1e-2
+}
+
+#[test]
+fn ends() {
+    let mut follow = PathFollow::new(rail(), 4.0);
+    follow.advance(1.0);
+    assert!(close(follow.position(), math::Vector3::new(0.0, 0.0, 4.0)));
+    follow.advance(2.0);
+    assert!(follow.is_finished());
+
+    follow.end = PathEnd::Loop;
+    follow.distance = 8.0;
+    follow.advance(1.0);
+    assert!((follow.distance - 2.0).abs() < 1e-3);
+
+    follow.end = PathEnd::PingPong;
+    follow.distance = 8.0;
+    follow.advance(1.0);
+    assert!((follow.distance - 8.0).abs() < 1e-3);
+    follow.advance(0.5);
+    assert!((follow.distance - 6.0).abs() < 1e-3);
+}
+
+#[test]
+fn follow() {
+    let mut entities = HandlePool::new();
+    let mut scene = SceneGraph::new();
+    let mut paths = PathFollowers::new();
+
+    let e1 = world_impl::create(&mut entities, &mut scene);
+    paths.add(e1, PathFollow::new(rail(), 5.0));
+    paths.advance(&mut scene, Duration::from_millis(1000));
+
+    let position = scene.position(e1).unwrap();
+    assert!(close(position, math::Vector3::new(0.0, 0.0, 5.0)));
+
+    let forward = scene.transform(e1).unwrap().forward();
+    assert!(close(forward, math::Vector3::new(0.0, 0.0, 1.0)));
+
+    paths.remove(e1);
+    assert!(paths.get(e1).is_none());
+}
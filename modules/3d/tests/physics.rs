@@ -0,0 +1,238 @@
+extern crate crayon;
+extern crate crayon_3d;
+
+mod common;
+
+use crayon::math::{self, InnerSpace, Rotation3};
+
+use crayon_3d::physics::{TriggerPhase, Triggers};
+use crayon_3d::prelude::*;
+use crayon_3d::world_impl;
+
+use common::Fixture;
+
+#[test]
+fn falling() {
+    let mut fixture = Fixture::new();
+    let character = fixture.character([0.0, 2.0, 0.0]);
+
+    fixture.run(0.1);
+    assert!(!fixture.controllers.get(character).unwrap().is_grounded());
+
+    fixture.run(1.0);
+    assert!(fixture.position(character).y.abs() < 0.02);
+
+    let controller = fixture.controllers.get(character).unwrap();
+    assert!(controller.is_grounded());
+    assert!(controller.collision_flags().below);
+    assert!(controller.velocity.y.abs() < 1.0);
+
+    // Jumps.
+    fixture.controllers.get_mut(character).unwrap().velocity.y = 5.0;
+    fixture.run(0.2);
+    assert!(fixture.position(character).y > 0.5);
+    assert!(!fixture.controllers.get(character).unwrap().is_grounded());
+}
+
+#[test]
+fn walls() {
+    let mut fixture = Fixture::new();
+    fixture.collider([3.0, 1.0, 0.0], Collider::Box([0.5, 2.0, 5.0].into()));
+
+    let character = fixture.character([0.0, 0.0, 0.0]);
+    fixture.run(0.1);
+
+    // Slides along the wall.
+    fixture.controllers.get_mut(character).unwrap().velocity = [4.0, 0.0, 2.0].into();
+    fixture.run(1.0);
+
+    let position = fixture.position(character);
+    assert!((position.x - 2.0).abs() < 0.02);
+    assert!((position.z - 2.0).abs() < 0.1);
+    assert!(position.y.abs() < 0.02);
+
+    let controller = fixture.controllers.get(character).unwrap();
+    assert!(controller.collision_flags().sides);
+    assert!(controller.is_grounded());
+
+    let stranger = world_impl::create(&mut fixture.entities, &mut fixture.scene);
+    let motion = [1.0, 0.0, 0.0];
+    assert!(fixture
+        .controllers
+        .move_and_slide(&mut fixture.scene, &fixture.colliders, stranger, motion)
+        .is_err());
+}
+
+#[test]
+fn stairs() {
+    let mut fixture = Fixture::new();
+    fixture.collider([0.0, 0.1, 3.0], Collider::Box([1.0, 0.1, 1.0].into()));
+    fixture.collider([5.0, 0.25, 3.0], Collider::Box([1.0, 0.25, 1.0].into()));
+
+    let low = fixture.character([0.0, 0.0, 0.0]);
+    let high = fixture.character([5.0, 0.0, 0.0]);
+    fixture.run(0.1);
+
+    for &v in &[low, high] {
+        fixture.controllers.get_mut(v).unwrap().velocity = [0.0, 0.0, 2.0].into();
+    }
+
+    fixture.run(1.5);
+
+    // Climbs the stairs lower than step offset.
+    let position = fixture.position(low);
+    assert!((position.z - 3.0).abs() < 0.1);
+    assert!((position.y - 0.2).abs() < 0.02);
+    assert!(fixture.controllers.get(low).unwrap().is_grounded());
+
+    let position = fixture.position(high);
+    assert!((position.z - 1.5).abs() < 0.02);
+    assert!(position.y.abs() < 0.02);
+}
+
+#[test]
+fn slopes() {
+    let mut fixture = Fixture::new();
+
+    // Places the ramps whose surfaces cross the floor at z = 2.
+    let ramp = |fixture: &mut Fixture, x: f32, degrees: f32| {
+        let rotation = math::Quaternion::from_angle_x(math::Deg(-degrees));
+        let normal = rotation * math::Vector3::new(0.0, 1.0, 0.0);
+        let center = math::Vector3::new(x, 0.0, 2.0) - normal * 0.5;
+
+        let ent = fixture.collider(center.into(), Collider::Box([1.0, 0.5, 4.0].into()));
+        fixture.scene.set_rotation(ent, rotation);
+    };
+
+    ramp(&mut fixture, 0.0, 30.0);
+    ramp(&mut fixture, 5.0, 60.0);
+
+    let gentle = fixture.character([0.0, 0.0, 0.0]);
+    let steep = fixture.character([5.0, 0.0, 0.0]);
+    fixture.run(0.1);
+
+    for &v in &[gentle, steep] {
+        fixture.controllers.get_mut(v).unwrap().velocity = [0.0, 0.0, 2.0].into();
+    }
+
+    fixture.run(2.0);
+
+    let position = fixture.position(gentle);
+    assert!(position.y > 1.0);
+    assert!(fixture.controllers.get(gentle).unwrap().is_grounded());
+
+    let position = fixture.position(steep);
+    assert!(position.y < 0.35);
+    assert!(position.z < 2.0);
+}
+
+#[test]
+fn triggers() {
+    let mut fixture = Fixture::new();
+    let capsule = TriggerShape::Capsule {
+        radius: 0.5,
+        height: 2.0,
+    };
+
+    let player = fixture.volume([0.0, 1.0, 0.0], capsule);
+    let coin = fixture.volume([0.0, 1.0, 2.8], TriggerShape::Sphere(0.5));
+    let zone = fixture.volume([0.0, 0.0, 5.0], TriggerShape::Box([2.0, 2.0, 2.0].into()));
+    let door = fixture.volume([0.0, 2.9, 7.5], TriggerShape::Box([1.0, 1.0, 1.0].into()));
+
+    // The coins are only collected by the volumes on the first layer.
+    fixture.triggers.get_mut(zone).unwrap().layers = 4;
+    fixture.triggers.get_mut(coin).unwrap().mask = 1;
+
+    let phases = |triggers: &mut Triggers| -> Vec<(TriggerPhase, Entity, Entity)> {
+        triggers
+            .take_events()
+            .into_iter()
+            .map(|v| (v.phase, v.lhs, v.rhs))
+            .collect()
+    };
+
+    fixture.triggers.advance(&fixture.scene);
+    let events = phases(&mut fixture.triggers);
+    assert_eq!(events, [(TriggerPhase::Enter, zone.min(door), zone.max(door))]);
+    assert!(fixture.triggers.is_overlapping(door, zone));
+
+    fixture.scene.set_position(player, [0.0, 1.0, 2.0]);
+    fixture.triggers.advance(&fixture.scene);
+
+    let events = fixture.triggers.take_events();
+    let enter = events.iter().find(|v| v.lhs != door && v.rhs != door).unwrap();
+    assert_eq!(enter.phase, TriggerPhase::Enter);
+    assert_eq!((enter.lhs, enter.rhs), (player.min(coin), player.max(coin)));
+
+    let contact = enter.contact.unwrap();
+    assert!((contact.depth - 0.2).abs() < 1e-3);
+    assert!(contact.normal.z.abs() > 0.99);
+
+    fixture.triggers.advance(&fixture.scene);
+    let events = phases(&mut fixture.triggers);
+    assert!(events.contains(&(TriggerPhase::Stay, player.min(coin), player.max(coin))));
+    assert!(!fixture.triggers.is_overlapping(player, zone));
+
+    // Walks into the zone, and collects the coin.
+    fixture.scene.set_position(player, [0.0, 1.0, 3.3]);
+    fixture.triggers.advance(&fixture.scene);
+    fixture.triggers.remove(coin);
+
+    let events = phases(&mut fixture.triggers);
+    assert!(events.contains(&(TriggerPhase::Enter, player.min(zone), player.max(zone))));
+    assert!(events.contains(&(TriggerPhase::Exit, player.min(coin), player.max(coin))));
+    assert!(!fixture.triggers.is_overlapping(coin, zone));
+
+    fixture.triggers.advance(&fixture.scene);
+    let events = phases(&mut fixture.triggers);
+    assert_eq!(events.len(), 2);
+    assert!(events.iter().all(|v| v.0 == TriggerPhase::Stay));
+
+    fixture.scene.set_position(player, [0.0, 1.0, -5.0]);
+    fixture.triggers.advance(&fixture.scene);
+    let events = phases(&mut fixture.triggers);
+    assert!(events.contains(&(TriggerPhase::Exit, player.min(zone), player.max(zone))));
+    assert!(!fixture.triggers.is_overlapping(player, zone));
+}
+
+#[test]
+fn raycasts() {
+    let fixture = Fixture::new();
+    let (scene, colliders) = (&fixture.scene, &fixture.colliders);
+    let down = math::Vector3::new(0.0, -1.0, 0.0);
+    let ignore = Entity::default();
+
+    let hit = colliders
+        .raycast(scene, ignore, [0.0, 5.0, 0.0].into(), down, 10.0)
+        .unwrap();
+    assert!((hit.distance - 5.0).abs() < 1e-4);
+    assert!((hit.point - math::Vector3::new(0.0, 0.0, 0.0)).magnitude() < 1e-4);
+    assert!((hit.normal - math::Vector3::unit_y()).magnitude() < 1e-4);
+
+    // The sphere above the floor is closer.
+    let hit = colliders
+        .raycast(scene, ignore, [0.0, 5.0, -20.0].into(), down, 10.0)
+        .unwrap();
+    assert!((hit.distance - 4.0).abs() < 1e-4);
+    assert_eq!(colliders.get(hit.ent), Some(&Collider::Sphere(1.0)));
+    assert!(colliders
+        .raycast(scene, ignore, [0.0, 5.0, -20.0].into(), down, 3.0)
+        .is_none());
+
+    let side = math::Vector3::new(1.0, 0.0, 0.0);
+    let hit = colliders
+        .raycast(scene, ignore, [-30.0, -0.5, 0.0].into(), side, 100.0)
+        .unwrap();
+    assert!((hit.distance - 10.0).abs() < 1e-4);
+    assert!((hit.normal + side).magnitude() < 1e-4);
+
+    // The rays starting inside colliders, or hitting the ignored ones, are discarded.
+    assert!(colliders
+        .raycast(scene, ignore, [0.0, -0.5, 0.0].into(), down, 10.0)
+        .is_none());
+
+    let floor = hit.ent;
+    assert!(colliders
+        .raycast(scene, floor, [0.0, 5.0, 0.0].into(), down, 10.0)
+        .is_none());
+}
@@ -0,0 +1,66 @@
+extern crate crayon;
+extern crate crayon_3d;
+
+use crayon::math;
+use crayon::utils::handle::Handle;
+use crayon::video::prelude::Readback;
+use crayon_3d::renderers::*;
+use crayon_3d::Entity;
+
+fn to_pixel(color: math::Color<f32>) -> [u8; 4] {
+    let channel = |v: f32| (v * 255.0).round() as u8;
+    [
+        channel(color.r),
+        channel(color.g),
+        channel(color.b),
+        channel(color.a),
+    ]
+}
+
+#[test]
+fn encode_ids() {
+    assert_eq!(to_pixel(encode_picking_id(0)), [1, 0, 0, 255]);
+    assert_eq!(to_pixel(encode_picking_id(0x0201ff)), [0, 2, 2, 255]);
+
+    for &i in &[0, 1, 254, 255, 256, 65535, 70000, MAX_PICKING_OBJECTS - 1] {
+        let pixel = to_pixel(encode_picking_id(i));
+        assert_eq!(decode_picking_id(pixel), Some(i));
+    }
+
+    // The background is cleared to zero.
+    assert_eq!(decode_picking_id([0, 0, 0, 0]), None);
+}
+
+#[test]
+fn pick_frame() {
+    let entities: Vec<Entity> = (0..2).map(|i| Handle::new(i, 1).into()).collect();
+
+    // A 2x2 ID pass, whose top-right pixel is the background.
+    let mut bytes = Vec::new();
+    for &id in &[Some(0), Some(1), Some(1), None] {
+        let pixel = match id {
+            Some(i) => to_pixel(encode_picking_id(i)),
+            None => [0, 0, 0, 0],
+        };
+
+        bytes.extend_from_slice(&pixel);
+    }
+
+    let readback = Readback {
+        area: math::Aabb2::new(math::Point2::new(0, 0), math::Point2::new(2, 2)),
+        bytes: bytes,
+    };
+
+    let frame = PickingFrame::new(readback, entities.clone());
+    let pick = |x, y| frame.pick(math::Vector2::new(x, y));
+    assert_eq!(pick(0.25, 0.25), Some(entities[0]));
+    assert_eq!(pick(0.75, 0.25), Some(entities[1]));
+    assert_eq!(pick(0.25, 0.75), Some(entities[1]));
+    assert_eq!(pick(0.75, 0.75), None);
+
+    // The edges are clamped into the pass, and the outsides are ignored.
+    assert_eq!(pick(0.0, 0.0), Some(entities[0]));
+    assert_eq!(pick(1.0, 0.0), Some(entities[1]));
+    assert_eq!(pick(1.5, 0.0), None);
+    assert_eq!(pick(0.0, -0.1), None);
+}
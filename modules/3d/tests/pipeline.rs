@@ -0,0 +1,147 @@
+extern crate crayon;
+extern crate crayon_3d;
+
+use crayon::math;
+use crayon::video::assets::texture::RenderTextureFormat;
+use crayon::video::MAX_FRAMEBUFFER_ATTACHMENTS;
+
+use crayon_3d::assets::*;
+
+fn target(name: &str, format: RenderTextureFormat) -> RenderTargetSetup {
+    RenderTargetSetup {
+        name: name.into(),
+        format: format,
+        scale: 1.0,
+    }
+}
+
+fn pass(name: &str, kind: RenderPassKind) -> RenderPassSetup {
+    RenderPassSetup {
+        name: name.into(),
+        kind: kind,
+        shader: None,
+        inputs: Vec::new(),
+        colors: Vec::new(),
+        depth: None,
+        clear_color: None,
+        clear_depth: None,
+    }
+}
+
+#[test]
+fn validate() {
+    let mut scene = pass("scene", RenderPassKind::Scene);
+    scene.colors.push(target("color", RenderTextureFormat::RGBA8));
+    scene.depth = Some(target("depth", RenderTextureFormat::Depth24));
+
+    let mut post = pass("post", RenderPassKind::Fullscreen);
+    post.shader = Some("tonemap".into());
+    post.inputs.push(("u_Texture".into(), "color".into()));
+
+    let setup = RenderPipelineSetup {
+        passes: vec![scene.clone(), post.clone()],
+    };
+    assert!(setup.validate().is_ok());
+
+    // Inputs must be produced by previous passes.
+    let setup = RenderPipelineSetup {
+        passes: vec![post.clone(), scene.clone()],
+    };
+    assert!(setup.validate().is_err());
+
+    // Fullscreen passes requires shader.
+    let mut invalid = post.clone();
+    invalid.shader = None;
+    let setup = RenderPipelineSetup {
+        passes: vec![scene.clone(), invalid],
+    };
+    assert!(setup.validate().is_err());
+
+    // Depth target must have depth format.
+    let mut invalid = scene.clone();
+    invalid.depth = Some(target("depth", RenderTextureFormat::RGBA8));
+    let setup = RenderPipelineSetup {
+        passes: vec![invalid],
+    };
+    assert!(setup.validate().is_err());
+
+    // Names of passes and targets must be unique.
+    let setup = RenderPipelineSetup {
+        passes: vec![scene.clone(), scene.clone()],
+    };
+    assert!(setup.validate().is_err());
+}
+
+#[test]
+fn attachments() {
+    let mut scene = pass("scene", RenderPassKind::Scene);
+    for i in 0..(MAX_FRAMEBUFFER_ATTACHMENTS - 1) {
+        let name = format!("color{}", i);
+        scene.colors.push(target(&name, RenderTextureFormat::RGBA8));
+    }
+
+    let setup = RenderPipelineSetup {
+        passes: vec![scene.clone()],
+    };
+    assert!(setup.validate().is_ok());
+
+    // Same as `SurfaceParams::set_attachments`.
+    let name = format!("color{}", MAX_FRAMEBUFFER_ATTACHMENTS);
+    scene.colors.push(target(&name, RenderTextureFormat::RGBA8));
+    let setup = RenderPipelineSetup {
+        passes: vec![scene],
+    };
+    assert!(setup.validate().is_err());
+}
+
+#[test]
+fn graph() {
+    let mut scene = pass("scene", RenderPassKind::Scene);
+    scene.colors.push(target("color", RenderTextureFormat::RGBA8));
+    scene.depth = Some(target("depth", RenderTextureFormat::Depth24));
+
+    let mut bright = pass("bright", RenderPassKind::Fullscreen);
+    bright.shader = Some("bright".into());
+    bright.inputs.push(("u_Texture".into(), "color".into()));
+    bright.colors.push(target("bright", RenderTextureFormat::RGBA8));
+
+    let mut blur = pass("blur", RenderPassKind::Fullscreen);
+    blur.shader = Some("blur".into());
+    blur.inputs.push(("u_Texture".into(), "bright".into()));
+    blur.colors.push(target("blurred", RenderTextureFormat::RGBA8));
+
+    let mut tonemap = pass("tonemap", RenderPassKind::Fullscreen);
+    tonemap.shader = Some("tonemap".into());
+    tonemap.inputs.push(("u_Texture".into(), "blurred".into()));
+
+    let setup = RenderPipelineSetup {
+        passes: vec![scene, bright, blur, tonemap],
+    };
+    assert!(setup.validate().is_ok());
+
+    let lifetimes: Vec<_> = setup
+        .target_lifetimes()
+        .iter()
+        .map(|&(v, lifetime)| (v.name.clone(), lifetime.first, lifetime.last))
+        .collect();
+
+    assert_eq!(lifetimes[0], ("color".into(), 0, 1));
+    assert_eq!(lifetimes[1], ("depth".into(), 0, 0));
+    assert_eq!(lifetimes[2], ("bright".into(), 1, 2));
+    assert_eq!(lifetimes[3], ("blurred".into(), 2, 3));
+
+    // The color target is never read after the bright pass.
+    assert_eq!(setup.aliasable_targets(), [("color", "blurred")]);
+
+    let dot = setup.to_dot(Some(math::Vector2::new(100, 50)));
+    assert!(dot.starts_with("digraph RenderPipeline {"));
+    assert!(dot.contains("\"target:color\" -> \"pass:bright\" [label=\"u_Texture\"];"));
+    assert!(dot.contains("\"pass:tonemap\" -> \"framebuffer\";"));
+    assert!(dot.contains("\"target:color\" -> \"target:blurred\""));
+    assert!(dot.contains("100x50 (19KB)"));
+
+    let html = setup.to_html(None);
+    assert!(html.starts_with("<!DOCTYPE html>"));
+    assert!(html.contains("<th>tonemap</th>"));
+    assert!(html.contains("color &harr; blurred"));
+}
@@ -0,0 +1,90 @@
+extern crate crayon;
+extern crate crayon_3d;
+
+use crayon::math;
+use crayon_3d::renderers::*;
+
+fn approx(lhs: math::Color<f32>, rhs: math::Color<f32>, epsilon: f32) -> bool {
+    (lhs.r - rhs.r).abs() < epsilon
+        && (lhs.g - rhs.g).abs() < epsilon
+        && (lhs.b - rhs.b).abs() < epsilon
+}
+
+#[test]
+fn constant() {
+    let color = math::Color::new(0.2, 0.4, 0.6, 1.0);
+    let sh = ShIrradiance::from_color(color);
+
+    let up = math::Vector3::new(0.0, 1.0, 0.0);
+    let left = math::Vector3::new(-1.0, 0.0, 0.0);
+    assert!(approx(sh.evaluate(up), color, 0.001));
+    assert!(approx(sh.evaluate(left), color, 0.001));
+
+    // Baking a constant radiance gives the same results.
+    let mut grid = ProbeGrid::new([0.0, 0.0, 0.0], [1.0, 1.0, 1.0], [1, 1, 1]);
+    grid.bake(256, |_, _| color);
+    let baked = grid.probe(0).unwrap();
+    assert!(approx(baked.evaluate(up), color, 0.01));
+    assert!(approx(baked.evaluate(left), color, 0.01));
+}
+
+#[test]
+fn directional() {
+    let mut grid = ProbeGrid::new([0.0, 0.0, 0.0], [1.0, 1.0, 1.0], [1, 1, 1]);
+    grid.bake(256, |_, dir| {
+        if dir.y > 0.0 {
+            math::Color::white()
+        } else {
+            math::Color::new(0.0, 0.0, 0.0, 1.0)
+        }
+    });
+
+    let sh = grid.probe(0).unwrap();
+    let up = sh.evaluate(math::Vector3::new(0.0, 1.0, 0.0));
+    let down = sh.evaluate(math::Vector3::new(0.0, -1.0, 0.0));
+    let side = sh.evaluate(math::Vector3::new(1.0, 0.0, 0.0));
+
+    assert!(up.r > side.r);
+    assert!(side.r > down.r);
+    assert!((side.r - 0.5).abs() < 0.05);
+}
+
+#[test]
+fn interpolation() {
+    let mut grid = ProbeGrid::new([0.0, 0.0, 0.0], [2.0, 2.0, 2.0], [2, 1, 1]);
+    assert_eq!(grid.len(), 2);
+    assert_eq!(grid.index(1, 0, 0), Some(1));
+    assert_eq!(grid.index(2, 0, 0), None);
+    assert_eq!(grid.position(1), Some(math::Vector3::new(2.0, 0.0, 0.0)));
+
+    let black = math::Color::new(0.0, 0.0, 0.0, 1.0);
+    grid.set_probe(0, ShIrradiance::from_color(black));
+    grid.set_probe(1, ShIrradiance::from_color(math::Color::white()));
+
+    let n = math::Vector3::new(0.0, 1.0, 0.0);
+    let half = math::Color::new(0.5, 0.5, 0.5, 1.0);
+    let v = grid.sample(math::Vector3::new(1.0, 0.0, 0.0)).evaluate(n);
+    assert!(approx(v, half, 0.001));
+
+    // Positions outside of grid are clamped.
+    let v = grid.sample(math::Vector3::new(-5.0, 3.0, 0.0)).evaluate(n);
+    assert!(approx(v, black, 0.001));
+    let v = grid.sample(math::Vector3::new(5.0, -3.0, 1.0)).evaluate(n);
+    assert!(approx(v, math::Color::white(), 0.001));
+}
+
+#[test]
+fn pack() {
+    let mut sh = ShIrradiance::default();
+    for (i, v) in sh.coefficients.iter_mut().enumerate() {
+        let f = (i * 3) as f32;
+        *v = math::Vector3::new(f, f + 1.0, f + 2.0);
+    }
+
+    let packed = sh.pack();
+    for (i, v) in packed.iter().enumerate() {
+        let f = (i * 4) as f32;
+        let w = if i == 6 { 0.0 } else { f + 3.0 };
+        assert_eq!(*v, math::Vector4::new(f, f + 1.0, f + 2.0, w));
+    }
+}
@@ -0,0 +1,46 @@
+extern crate crayon;
+extern crate crayon_3d;
+
+use crayon::math;
+use crayon::video::assets::shader::UniformVariable;
+use crayon_3d::renderers::*;
+
+#[test]
+fn basic() {
+    let mut block = MaterialPropertyBlock::new();
+    assert!(block.is_empty());
+
+    block.set("u_Diffuse", math::Vector3::new(1.0, 0.0, 0.0));
+    block.set("u_Shininess", 2.0f32);
+    assert_eq!(block.len(), 2);
+
+    match block.get("u_Shininess") {
+        Some(UniformVariable::F32(v)) => assert_eq!(v, 2.0),
+        _ => unreachable!(),
+    }
+
+    // Overrides the existing property.
+    block.set("u_Shininess", 4.0f32);
+    assert_eq!(block.len(), 2);
+    match block.get("u_Shininess") {
+        Some(UniformVariable::F32(v)) => assert_eq!(v, 4.0),
+        _ => unreachable!(),
+    }
+
+    block.remove("u_Diffuse");
+    assert_eq!(block.len(), 1);
+    assert!(block.get("u_Diffuse").is_none());
+    assert!(block.get("u_Shininess").is_some());
+
+    block.clear();
+    assert!(block.is_empty());
+}
+
+#[test]
+#[should_panic]
+fn overflow() {
+    let mut block = MaterialPropertyBlock::new();
+    for i in 0..(MAX_MATERIAL_PROPERTIES + 1) {
+        block.set(format!("u_Property{}", i).as_str(), 1.0f32);
+    }
+}
@@ -0,0 +1,81 @@
+extern crate crayon;
+extern crate crayon_3d;
+
+use std::time::Duration;
+
+use crayon::math::{self, InnerSpace};
+use crayon_3d::prelude::*;
+use crayon_3d::renderers::*;
+
+fn approx(lhs: math::Vector3<f32>, rhs: math::Vector3<f32>) -> bool {
+    (lhs - rhs).magnitude() < 0.001
+}
+
+fn distance(plane: math::Vector4<f32>, view_matrix: math::Matrix4<f32>, p: [f32; 3]) -> f32 {
+    let p = view_matrix * math::Vector3::from(p).extend(1.0);
+    plane.dot(p)
+}
+
+#[test]
+fn mirror() {
+    let reflection = PlanarReflection::new([0.0, 1.0, 0.0], [0.0, 1.0, 0.0]);
+
+    let mut camera = Transform::default();
+    camera.position = math::Vector3::new(1.0, 3.0, -5.0);
+    camera.look_at([1.0, 1.0, 0.0], [0.0, 1.0, 0.0]);
+
+    let mirror = reflection.mirror(&camera);
+    assert!(approx(mirror.position, math::Vector3::new(1.0, -1.0, -5.0)));
+
+    let (forward, up, right) = (camera.forward(), camera.up(), camera.right());
+    assert!(approx(
+        mirror.forward(),
+        [forward.x, -forward.y, forward.z].into()
+    ));
+    assert!(approx(mirror.up(), [up.x, -up.y, up.z].into()));
+
+    // The mirrored camera is flipped horizontally.
+    assert!(approx(mirror.right(), [-right.x, right.y, -right.z].into()));
+}
+
+#[test]
+fn clip_planes() {
+    let mut reflection = PlanarReflection::new([0.0, 1.0, 0.0], [0.0, 2.0, 0.0]);
+    reflection.clip_offset = 0.1;
+
+    let mut camera = Transform::default();
+    camera.position = math::Vector3::new(0.0, 3.0, -5.0);
+    camera.look_at([0.0, 1.0, 0.0], [0.0, 1.0, 0.0]);
+
+    // The reflection keeps the objects above the plane.
+    let view_matrix = reflection.mirror(&camera).view_matrix();
+    let plane = reflection.clip_plane(view_matrix, true);
+    assert!((distance(plane, view_matrix, [2.0, 2.0, 3.0]) - 1.1).abs() < 0.001);
+    assert!(distance(plane, view_matrix, [0.0, 0.95, 0.0]) > 0.0);
+    assert!(distance(plane, view_matrix, [0.0, 0.5, 0.0]) < 0.0);
+
+    // The refraction keeps the objects below the plane.
+    let view_matrix = camera.view_matrix();
+    let plane = reflection.clip_plane(view_matrix, false);
+    assert!(distance(plane, view_matrix, [2.0, 2.0, 3.0]) < 0.0);
+    assert!(distance(plane, view_matrix, [0.0, 1.05, 0.0]) > 0.0);
+    assert!((distance(plane, view_matrix, [0.0, 0.5, 0.0]) - 0.6).abs() < 0.001);
+}
+
+#[test]
+fn distortion() {
+    let mut reflection = PlanarReflection::new([0.0, 0.0, 0.0], [0.0, 1.0, 0.0]);
+    reflection.distortion.speed = 2.0;
+
+    let params = reflection.encode();
+    assert_eq!(params.x, reflection.distortion.strength);
+    assert_eq!(params.z, 0.0);
+
+    reflection.advance(Duration::from_millis(500));
+    assert!((reflection.encode().z - 1.0).abs() < 0.001);
+
+    // The phase wraps around, so the ripples keep continuous.
+    reflection.advance(Duration::from_secs(3));
+    let phase = 7.0 - 2.0 * ::std::f32::consts::PI;
+    assert!((reflection.encode().z - phase).abs() < 0.001);
+}
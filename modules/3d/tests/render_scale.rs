@@ -0,0 +1,52 @@
+extern crate crayon;
+extern crate crayon_3d;
+
+use std::time::Duration;
+
+use crayon_3d::renderers::*;
+
+#[test]
+fn fixed() {
+    let setup = RenderScale::fixed(0.5, UpscaleFilter::Bilinear);
+    assert!(!setup.is_native());
+    assert_eq!(setup.max_scale(), 0.5);
+
+    let mut controller = RenderScaleController::default();
+    let v = controller.advance(&setup, Some(Duration::from_millis(100)));
+    assert_eq!(v, 0.5);
+
+    assert!(RenderScale::default().is_native());
+}
+
+#[test]
+fn dynamic() {
+    let setup = RenderScale::dynamic(16, 0.5, 1.0, UpscaleFilter::Sharpen(0.5));
+    assert!(!setup.is_native());
+    assert_eq!(setup.max_scale(), 1.0);
+
+    let mut controller = RenderScaleController::default();
+
+    // Decreases until reaching the minimum if we are always over budget.
+    let mut last = controller.scale();
+    for _ in 0..16 {
+        let v = controller.advance(&setup, Some(Duration::from_millis(24)));
+        assert!(v <= last);
+        last = v;
+    }
+
+    assert_eq!(controller.scale(), 0.5);
+
+    // Keeps the scale if we are close to the budget.
+    let v = controller.advance(&setup, Some(Duration::from_millis(15)));
+    assert_eq!(v, 0.5);
+
+    // Keeps the scale if the GPU time is not available.
+    assert_eq!(controller.advance(&setup, None), 0.5);
+
+    // Increases until reaching the maximum if there is a lot of headroom.
+    for _ in 0..16 {
+        controller.advance(&setup, Some(Duration::from_millis(4)));
+    }
+
+    assert_eq!(controller.scale(), 1.0);
+}
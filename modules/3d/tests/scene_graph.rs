@@ -2,7 +2,13 @@ extern crate crayon;
 extern crate crayon_3d;
 extern crate rand;
 
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::Duration;
+
+use crayon::errors::Result;
 use crayon::math::*;
+use crayon::prelude::{Application, Context, Engine, Settings};
 use crayon::utils::handle_pool::HandlePool;
 use crayon_3d::prelude::*;
 
@@ -282,3 +288,170 @@ fn random_iteration() {
     let len = testbed.descendants(constructed[0]).count();
     assert_eq!(len, 254);
 }
+
+#[test]
+fn interpolation() {
+    let mut testbed = Testbed::new();
+    let e1 = testbed.create();
+    let e2 = testbed.create();
+    testbed.set_parent(e2, e1, false).unwrap();
+    testbed.set_local_position(e2, [0.0, 2.0, 0.0]);
+
+    // Nodes are not interpolated before the first snapshot.
+    let v = testbed.interpolated_transform(e2, 0.5).unwrap();
+    assert_ulps_eq!(v.position, Vector3::new(0.0, 2.0, 0.0));
+
+    testbed.scene.snapshot();
+    testbed.set_local_position(e1, [4.0, 0.0, 0.0]);
+    testbed.set_local_scale(e1, 3.0);
+
+    let v = testbed.interpolated_transform(e2, 0.5).unwrap();
+    assert_ulps_eq!(v.position, Vector3::new(2.0, 2.0, 0.0));
+    assert_ulps_eq!(v.scale, 2.0);
+
+    let v = testbed.interpolated_transform(e2, 1.0).unwrap();
+    assert_ulps_eq!(v.position, Vector3::new(4.0, 2.0, 0.0));
+
+    // Teleported nodes jump to the current states directly.
+    testbed.scene.reset_interpolation(e1);
+    let v = testbed.interpolated_transform(e1, 0.0).unwrap();
+    assert_ulps_eq!(v.position, Vector3::new(4.0, 0.0, 0.0));
+}
+
+struct Mover {
+    scene: Arc<Mutex<SceneGraph>>,
+    ent: Entity,
+}
+
+impl Application for Mover {
+    fn on_fixed_update(&mut self, _: &Context) -> Result<()> {
+        let mut scene = self.scene.lock().unwrap();
+        scene.translate(self.ent, [1.0, 0.0, 0.0]);
+        Ok(())
+    }
+}
+
+#[test]
+fn automatic_interpolation() {
+    let mut settings = Settings::default();
+    settings.headless = true;
+    settings.engine.fixed_fps = 100;
+    let mut engine = Engine::new_with(&settings).unwrap();
+
+    let mut testbed = Testbed::new();
+    let e1 = testbed.create();
+    testbed.scene.set_time(engine.context().time.clone());
+
+    let scene = Arc::new(Mutex::new(testbed.scene.clone()));
+    let mover = Mover {
+        scene: scene.clone(),
+        ent: e1,
+    };
+
+    engine.attach(mover).unwrap();
+    for _ in 0..3 {
+        thread::sleep(Duration::from_millis(15));
+        engine.step().unwrap();
+    }
+    engine.detach().unwrap();
+
+    // The snapshots are taken before each fixed update without calling `snapshot`.
+    let scene = scene.lock().unwrap();
+    let position = scene.position(e1).unwrap();
+    assert!(position.x >= 1.0);
+
+    let v = scene.interpolated_transform(e1, 0.5).unwrap();
+    assert_ulps_eq!(v.position, position - Vector3::new(0.5, 0.0, 0.0));
+}
+
+#[test]
+fn look_at_and_decompose() {
+    let mut transform = Transform::default();
+    transform.position = Vector3::new(1.0, 0.0, 1.0);
+    transform.look_at([1.0, 0.0, 5.0], [0.0, 1.0, 0.0]);
+    assert!((transform.forward() - Vector3::new(0.0, 0.0, 1.0)).magnitude() < 1e-4);
+
+    transform.look_at([5.0, 0.0, 1.0], [0.0, 1.0, 0.0]);
+    assert!((transform.forward() - Vector3::new(1.0, 0.0, 0.0)).magnitude() < 1e-4);
+    assert!((transform.right() - Vector3::new(0.0, 0.0, -1.0)).magnitude() < 1e-4);
+
+    transform.scale = 2.0;
+    let v = Transform::from_matrix(&transform.matrix());
+    assert!((v.scale - 2.0).abs() < 1e-4);
+    assert!((v.position - transform.position).magnitude() < 1e-4);
+    assert!(v.rotation.dot(transform.rotation).abs() > 1.0 - 1e-4);
+}
+
+#[test]
+fn matrices() {
+    let transforms: Vec<_> = (0..100)
+        .map(|i| {
+            let mut transform = Transform::default();
+            transform.position = Vector3::new(i as f32, 1.0, -(i as f32));
+            transform.rotation = Quaternion::from_angle_y(Deg(i as f32 * 3.0));
+            transform.scale = 1.0 + i as f32 * 0.1;
+            transform
+        })
+        .collect();
+
+    let mut dst = vec![Matrix4::one(); transforms.len()];
+    Transform::matrices(&transforms, &mut dst);
+
+    for (v, m) in transforms.iter().zip(&dst) {
+        let expected = v.matrix();
+        for i in 0..4 {
+            assert!((m[i] - expected[i]).magnitude() < 1e-3);
+        }
+    }
+}
+
+#[test]
+fn scene_matrices() {
+    let mut testbed = Testbed::new();
+    let ents: Vec<_> = (0..6).map(|_| testbed.create()).collect();
+    for (i, &ent) in ents.iter().enumerate() {
+        testbed.scene.set_position(ent, [i as f32, 0.0, 0.0]);
+        testbed.scene.set_scale(ent, 2.0);
+    }
+
+    testbed.scene.set_parent(ents[1], ents[0], false).unwrap();
+
+    let mut dst = vec![Matrix4::zero(); ents.len()];
+    testbed.scene.matrices(&ents, &mut dst);
+
+    for (ent, m) in ents.iter().zip(&dst) {
+        let expected = testbed.scene.transform(*ent).unwrap().matrix();
+        for i in 0..4 {
+            assert!((m[i] - expected[i]).magnitude() < 1e-3);
+        }
+    }
+}
+
+#[test]
+fn checksum() {
+    use std::hash::Hasher;
+
+    let checksum = |scene: &SceneGraph| {
+        let mut v = crayon::utils::Checksum::new();
+        scene.checksum(&mut v);
+        v.finish()
+    };
+
+    let mut lhs = Testbed::new();
+    let e1 = lhs.create();
+    let e2 = lhs.create();
+    lhs.scene.set_parent(e2, e1, false).unwrap();
+    lhs.scene.set_local_position(e2, [1.0, -0.0, 2.0]);
+
+    let mut rhs = Testbed::new();
+    let e1 = rhs.create();
+    let e2 = rhs.create();
+    rhs.scene.set_local_position(e2, [1.0, 0.0, 2.0]);
+    assert!(checksum(&lhs.scene) != checksum(&rhs.scene));
+
+    rhs.scene.set_parent(e2, e1, false).unwrap();
+    assert_eq!(checksum(&lhs.scene), checksum(&rhs.scene));
+
+    rhs.scene.translate(e1, [0.0, 0.001, 0.0]);
+    assert!(checksum(&lhs.scene) != checksum(&rhs.scene));
+}
@@ -0,0 +1,123 @@
+extern crate crayon;
+extern crate crayon_3d;
+
+use crayon::math::{self, QuaternionExt, Rotation3};
+use crayon_3d::renderers::*;
+
+fn sun() -> Lit {
+    let mut lit = Lit {
+        shadow_caster: true,
+        ..Default::default()
+    };
+
+    lit.transform.rotation =
+        math::Quaternion::look_rotation([1.0, -2.0, 0.5].into(), math::Vector3::unit_y());
+    lit
+}
+
+fn corners(near: f32, far: f32) -> Vec<math::Vector3<f32>> {
+    let tan = 30.0f32.to_radians().tan();
+    let mut corners = Vec::new();
+    for &d in &[near, far] {
+        for &(x, y) in &[(-1.0, -1.0), (1.0, -1.0), (-1.0, 1.0), (1.0, 1.0)] {
+            corners.push(math::Vector3::new(x * d * tan, y * d * tan, d));
+        }
+    }
+    corners
+}
+
+#[test]
+fn splits() {
+    let v = split_distances(1.0, 100.0, 2, 0.0);
+    assert_eq!(v.len(), 3);
+    assert!((v[1] - 50.5).abs() < 1e-4);
+
+    let v = split_distances(1.0, 100.0, 2, 1.0);
+    assert!((v[0] - 1.0).abs() < 1e-4);
+    assert!((v[1] - 10.0).abs() < 1e-4);
+    assert!((v[2] - 100.0).abs() < 1e-3);
+}
+
+#[test]
+fn quality() {
+    assert_eq!(ShadowQuality::Disabled.settings().cascades, 0);
+    assert!(!ShadowQuality::Low.settings().soft);
+    assert_eq!(ShadowQuality::High.settings().cascades, MAX_SHADOW_CASCADES);
+    assert_eq!(ShadowSettings::default(), ShadowQuality::Medium.settings());
+
+    assert_eq!(atlas_tile(0, 1), (1.0, math::Vector2::new(0.0, 0.0)));
+    assert_eq!(atlas_tile(3, 4), (0.5, math::Vector2::new(0.5, 0.5)));
+}
+
+#[test]
+fn fit() {
+    let camera = Camera::perspective(math::Deg(60.0), 1.0, 0.1, 100.0);
+    let mut settings = ShadowQuality::Medium.settings();
+    settings.stabilize = false;
+
+    let cascades = fit_cascades(&camera, &sun(), &settings);
+    assert_eq!(cascades.len(), 3);
+    assert!((cascades[2].split - 60.0).abs() < 1e-3);
+
+    // Every slice of view frustum is covered by its cascade.
+    let mut near = 0.1;
+    for (i, cascade) in cascades.iter().enumerate() {
+        for v in corners(near, cascade.split) {
+            let p = cascade.view_projection() * v.extend(1.0);
+            assert!(p.x.abs() <= 1.001 && p.y.abs() <= 1.001 && p.z.abs() <= 1.001);
+
+            // And mapped into its tile of atlas.
+            let (scale, offset) = atlas_tile(i, cascades.len());
+            let uv = cascade.atlas_matrix(i, cascades.len()) * v.extend(1.0);
+            assert!((uv.x - (offset.x + (p.x * 0.5 + 0.5) * scale)).abs() < 1e-4);
+            assert!((uv.y - (offset.y + (p.y * 0.5 + 0.5) * scale)).abs() < 1e-4);
+            assert!((uv.z - (p.z * 0.5 + 0.5)).abs() < 1e-4);
+        }
+
+        near = cascade.split;
+    }
+}
+
+#[test]
+fn stabilization() {
+    let mut camera = Camera::perspective(math::Deg(60.0), 1.0, 0.1, 100.0);
+    let settings = ShadowQuality::Low.settings();
+    let lit = sun();
+
+    let lhs = fit_cascades(&camera, &lit, &settings);
+    camera.transform.rotation = math::Quaternion::from_angle_y(math::Deg(37.0));
+    camera.transform.position = math::Vector3::new(3.3, 0.0, -1.7);
+    let rhs = fit_cascades(&camera, &lit, &settings);
+
+    // The size of cascades keeps the same when camera rotates.
+    for (lhs, rhs) in lhs.iter().zip(rhs.iter()) {
+        let (lhs, rhs) = (lhs.projection_matrix, rhs.projection_matrix);
+        assert!((lhs.x.x - rhs.x.x).abs() < 1e-6);
+        assert!((lhs.y.y - rhs.y.y).abs() < 1e-6);
+    }
+
+    // And the centers are snapped to texels.
+    let half = settings.resolution as f32 * 0.5;
+    for cascade in &rhs {
+        let texels = cascade.projection_matrix.w * half;
+        assert!((texels.x - texels.x.round()).abs() < 1e-2);
+        assert!((texels.y - texels.y.round()).abs() < 1e-2);
+    }
+}
+
+#[test]
+fn disabled() {
+    let camera = Camera::perspective(math::Deg(60.0), 1.0, 0.1, 100.0);
+    let settings = ShadowSettings::default();
+
+    let mut lit = sun();
+    lit.shadow_caster = false;
+    assert!(fit_cascades(&camera, &lit, &settings).is_empty());
+
+    let mut lit = sun();
+    lit.shadow_distance = 0.05;
+    assert!(fit_cascades(&camera, &lit, &settings).is_empty());
+
+    let settings = ShadowQuality::Disabled.settings();
+    assert!(fit_cascades(&camera, &sun(), &settings).is_empty());
+}
@@ -0,0 +1,145 @@
+extern crate crayon;
+extern crate crayon_3d;
+
+use crayon::prelude::*;
+use crayon_3d::renderers::simple::{CAMERA_BLOCK_SLOT, LIGHTS_BLOCK_SLOT};
+use crayon_3d::renderers::*;
+
+fn headless() -> Engine {
+    headless_with(VideoProfile::Standard)
+}
+
+fn headless_with(profile: VideoProfile) -> Engine {
+    let mut settings = Settings::default();
+    settings.headless = true;
+    settings.video.profile = profile;
+    Engine::new_with(&settings).unwrap()
+}
+
+// Gets all the combinations of supported keywords.
+fn permutations(supported: ShaderKeywords) -> Vec<ShaderKeywords> {
+    let keywords: Vec<_> = ShaderKeywords::NAMES
+        .iter()
+        .map(|v| v.0)
+        .filter(|&v| supported.contains(v))
+        .collect();
+
+    let mut permutations = Vec::new();
+    for mask in 0..(1 << keywords.len()) {
+        let mut v = ShaderKeywords::empty();
+        for (i, &keyword) in keywords.iter().enumerate() {
+            if mask & (1 << i) != 0 {
+                v |= keyword;
+            }
+        }
+
+        permutations.push(v);
+    }
+
+    permutations
+}
+
+#[test]
+fn prewarm() {
+    let engine = headless();
+    let mut renderer = SimpleRenderer::new(engine.context()).unwrap();
+
+    let variants = renderer.shader_variants_mut();
+    let permutations = permutations(variants.supported());
+    variants.prewarm(&permutations).unwrap();
+    assert_eq!(variants.len(), permutations.len());
+}
+
+#[test]
+fn keywords() {
+    // The lights are declared as plain uniforms without uniform buffers.
+    let engine = headless_with(VideoProfile::Gles2);
+    let video = engine.context().video.clone();
+    let mut renderer = SimpleRenderer::new(engine.context()).unwrap();
+
+    let variants = renderer.shader_variants_mut();
+    let declared = |variants: &mut ShaderVariants, keywords, field: &str| {
+        let shader = variants.get(keywords).unwrap();
+        let params = video.shader(shader).unwrap();
+        params.uniforms.variable_type(field).is_some()
+    };
+
+    for &(keyword, _) in &ShaderKeywords::NAMES {
+        assert!(variants.supported().contains(keyword));
+        variants.get(keyword).unwrap();
+        assert!(variants.contains(keyword));
+    }
+
+    let empty = ShaderKeywords::empty();
+    assert!(declared(variants, ShaderKeywords::FOG, "u_FogParams"));
+    assert!(!declared(variants, empty, "u_FogParams"));
+    assert!(!declared(variants, empty, "u_FogColor"));
+
+    assert!(declared(variants, ShaderKeywords::NORMALMAP, "u_NormalMap"));
+    assert!(!declared(variants, empty, "u_NormalMap"));
+    assert!(declared(variants, ShaderKeywords::SKINNED, "u_BoneMatrices[0]"));
+    assert!(!declared(variants, empty, "u_BoneMatrices[0]"));
+
+    // The lightmaps have the ambient, directional lights and shadows baked.
+    let lightmap = ShaderKeywords::LIGHTMAP | ShaderKeywords::SHADOWS;
+    assert!(declared(variants, lightmap, "u_Lightmap"));
+    assert!(!declared(variants, lightmap, "u_ShadowMap"));
+    assert!(!declared(variants, lightmap, "u_SH[0]"));
+    assert!(!declared(variants, lightmap, "u_DirLitColor[0]"));
+    assert!(declared(variants, lightmap, "u_PointLitColor[0]"));
+    assert!(declared(variants, ShaderKeywords::SHADOWS, "u_ShadowMap"));
+
+    let shader = variants
+        .get(ShaderKeywords::NORMALMAP | ShaderKeywords::SKINNED)
+        .unwrap();
+    let attributes: Vec<_> = video
+        .shader(shader)
+        .unwrap()
+        .attributes
+        .iter()
+        .map(|v| v.0)
+        .collect();
+    assert!(attributes.contains(&Attribute::Tangent));
+    assert!(attributes.contains(&Attribute::Indices));
+    assert!(attributes.contains(&Attribute::Weight));
+}
+
+#[test]
+fn camera_block() {
+    let engine = headless();
+    let video = engine.context().video.clone();
+    let mut renderer = SimpleRenderer::new(engine.context()).unwrap();
+
+    // The camera and lights are read from the uniform blocks of lit variants.
+    let variants = renderer.shader_variants_mut();
+    let shader = variants.get(ShaderKeywords::FOG).unwrap();
+    let params = video.shader(shader).unwrap();
+    assert_eq!(
+        params.uniform_blocks,
+        [
+            ("Camera".to_owned(), CAMERA_BLOCK_SLOT),
+            ("Lights".to_owned(), LIGHTS_BLOCK_SLOT),
+        ]
+    );
+    assert!(params
+        .uniforms
+        .variable_type("u_PointLitColor[0]")
+        .is_none());
+    assert!(params.uniforms.variable_type("u_InvViewMatrix").is_none());
+    assert!(params.uniforms.variable_type("u_FogParams").is_some());
+
+    let engine = headless_with(VideoProfile::Gles2);
+    let video = engine.context().video.clone();
+    let params = UniformBufferParams {
+        hint: MeshHint::Stream,
+        size: 64,
+    };
+    assert!(video.create_uniform_buffer(params, None).is_err());
+
+    let mut renderer = SimpleRenderer::new(engine.context()).unwrap();
+    let shader = renderer
+        .shader_variants_mut()
+        .get(ShaderKeywords::FOG)
+        .unwrap();
+    assert!(video.shader(shader).unwrap().uniform_blocks.is_empty());
+}
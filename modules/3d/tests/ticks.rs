@@ -0,0 +1,70 @@
+extern crate crayon;
+extern crate crayon_3d;
+
+use std::time::Duration;
+
+use crayon::utils::handle_pool::HandlePool;
+
+use crayon_3d::prelude::*;
+use crayon_3d::ticks::TickGroups;
+
+fn ms(v: u64) -> Duration {
+    Duration::from_millis(v)
+}
+
+#[test]
+fn round_robin() {
+    let mut entities = HandlePool::new();
+    let mut ticks = TickGroups::new();
+
+    let full: Entity = entities.create().into();
+    let halves: Vec<Entity> = (0..4).map(|_| entities.create().into()).collect();
+    let quarters: Vec<Entity> = (0..8).map(|_| entities.create().into()).collect();
+
+    for &v in &halves {
+        ticks.set(v, TickRate::Half);
+    }
+
+    for &v in &quarters {
+        ticks.set(v, TickRate::Quarter);
+    }
+
+    assert_eq!(ticks.rate(full), TickRate::Full);
+    assert_eq!(ticks.rate(halves[0]), TickRate::Half);
+
+    for frame in 0..8 {
+        ticks.advance(ms(10));
+        assert_eq!(ticks.delta(full), Some(ms(10)));
+
+        // Half of the halves and a quarter of the quarters are due in every frame.
+        let due = ticks.due();
+        assert_eq!(due.len(), 2 + 2);
+
+        for (ent, dt) in due {
+            let expected = if frame < ticks.rate(ent).interval() {
+                ms(10 * (frame as u64 + 1))
+            } else {
+                ms(10 * ticks.rate(ent).interval() as u64)
+            };
+
+            assert_eq!(dt, expected);
+        }
+    }
+
+    assert_eq!(ticks.system_delta(TickRate::Full), Some(ms(10)));
+    assert_eq!(ticks.system_delta(TickRate::Quarter), None);
+
+    ticks.remove(quarters[0]);
+    assert_eq!(ticks.rate(quarters[0]), TickRate::Full);
+
+    // The entities which go back to full rate are not listed as due.
+    ticks.set(halves[0], TickRate::Full);
+    ticks.set(full, TickRate::Full);
+    assert_eq!(ticks.rate(halves[0]), TickRate::Full);
+
+    for _ in 0..4 {
+        ticks.advance(ms(10));
+        assert_eq!(ticks.delta(halves[0]), Some(ms(10)));
+        assert!(ticks.due().iter().all(|v| v.0 != halves[0] && v.0 != full));
+    }
+}
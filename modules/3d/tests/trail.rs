@@ -0,0 +1,112 @@
+extern crate crayon;
+extern crate crayon_3d;
+
+use std::time::Duration;
+
+use crayon::math::{self, InnerSpace};
+use crayon_3d::renderers::*;
+
+fn approx(lhs: math::Vector3<f32>, rhs: [f32; 3]) -> bool {
+    (lhs - math::Vector3::from(rhs)).magnitude() < 0.001
+}
+
+#[test]
+fn curves() {
+    let width = WidthCurve::linear(1.0, 0.0).with_key(0.5, 1.0);
+    assert_eq!(width.evaluate(-1.0), 1.0);
+    assert_eq!(width.evaluate(0.25), 1.0);
+    assert_eq!(width.evaluate(0.75), 0.5);
+    assert_eq!(width.evaluate(2.0), 0.0);
+    assert_eq!(WidthCurve::constant(0.3).evaluate(0.7), 0.3);
+
+    let color = ColorGradient::linear(math::Color::white(), math::Color::transparent());
+    assert_eq!(color.evaluate(0.5).rgba(), [0.5, 0.5, 0.5, 0.5]);
+    assert_eq!(color.evaluate(1.0), math::Color::transparent());
+}
+
+#[test]
+fn lines() {
+    let points = vec![
+        math::Vector3::new(0.0, 0.0, 0.0),
+        math::Vector3::new(4.0, 0.0, 0.0),
+        math::Vector3::new(10.0, 0.0, 0.0),
+    ];
+
+    let mut line = LineRenderer::new(points);
+    line.width = WidthCurve::linear(1.0, 3.0);
+
+    let ribbon = line.ribbon().unwrap();
+    assert_eq!(ribbon.points.len(), 3);
+    assert_eq!(ribbon.points[1].u, 0.4);
+    assert!((ribbon.points[1].width - 1.8).abs() < 0.001);
+
+    // The ribbon faces the eye in front of it.
+    let vertices = ribbon.extrude([5.0, 0.0, -10.0].into());
+    assert_eq!(vertices.len(), 6);
+    assert!(approx(vertices[0].position, [0.0, -0.5, 0.0]));
+    assert!(approx(vertices[1].position, [0.0, 0.5, 0.0]));
+    assert!(approx(vertices[5].position, [10.0, 1.5, 0.0]));
+    assert_eq!(vertices[5].texcoord, math::Vector2::new(1.0, 1.0));
+
+    // And turns to face the eye above it.
+    let vertices = ribbon.extrude([5.0, 10.0, 0.0].into());
+    assert!(approx(vertices[0].position, [0.0, 0.0, -0.5]));
+
+    let indices = ribbon.indices(10);
+    assert_eq!(indices.len(), 12);
+    assert_eq!(&indices[6..12], &[12, 13, 15, 12, 15, 14]);
+
+    line.texture_mode = LineTextureMode::Tile;
+    line.tile_length = 2.0;
+    assert_eq!(line.ribbon().unwrap().points[2].u, 5.0);
+
+    line.points.truncate(1);
+    assert!(line.ribbon().is_none());
+}
+
+#[test]
+fn trails() {
+    let mut trail = TrailRenderer::new(Duration::from_millis(950));
+    let dt = Duration::from_millis(100);
+
+    for i in 0..15 {
+        trail.advance([i as f32, 0.0, 0.0].into(), dt);
+    }
+
+    // The points older than lifetime are removed.
+    assert_eq!(trail.len(), 10);
+    assert!(approx(trail.points()[0].0, [14.0, 0.0, 0.0]));
+    assert!(approx(trail.points()[9].0, [5.0, 0.0, 0.0]));
+
+    // The points fade out and get thinner over time.
+    let ribbon = trail.ribbon().unwrap();
+    assert_eq!(ribbon.points[0].color.rgba()[3], 1.0);
+    assert!(ribbon.points[9].color.rgba()[3] < 0.1);
+    assert!(ribbon.points[9].width < ribbon.points[5].width);
+
+    trail.emitting = false;
+    trail.advance([20.0, 0.0, 0.0].into(), dt);
+    assert_eq!(trail.len(), 9);
+
+    trail.advance([20.0, 0.0, 0.0].into(), Duration::from_secs(1));
+    assert!(trail.is_empty());
+    assert!(trail.ribbon().is_none());
+}
+
+#[test]
+fn min_vertex_distance() {
+    let mut trail = TrailRenderer::new(Duration::from_secs(10));
+    trail.min_vertex_distance = 0.5;
+
+    let dt = Duration::from_millis(100);
+    for i in 0..6 {
+        trail.advance([i as f32 * 0.2, 0.0, 0.0].into(), dt);
+    }
+
+    // The newest point follows the entity until its far enough from the previous one.
+    assert_eq!(trail.len(), 4);
+    assert!(approx(trail.points()[0].0, [1.0, 0.0, 0.0]));
+    assert!(approx(trail.points()[1].0, [0.8, 0.0, 0.0]));
+    assert!(approx(trail.points()[2].0, [0.4, 0.0, 0.0]));
+    assert!(approx(trail.points()[3].0, [0.0, 0.0, 0.0]));
+}
@@ -0,0 +1,131 @@
+extern crate crayon;
+extern crate crayon_3d;
+
+use std::io;
+use std::time::Duration;
+
+use crayon::errors::*;
+use crayon::math;
+
+use crayon_3d::assets::PrefabHandle;
+use crayon_3d::prelude::*;
+use crayon_3d::transitions::*;
+
+#[derive(Default)]
+struct Scene {
+    ready: bool,
+    failed: bool,
+    loaded: Vec<PrefabHandle>,
+    unloaded: Vec<Entity>,
+}
+
+impl TransitionScene for Scene {
+    fn load(&mut self, prefab: PrefabHandle) -> Result<Option<Entity>> {
+        if self.failed {
+            let err = io::Error::new(io::ErrorKind::InvalidData, "corrupted prefab");
+            return Err(err.into());
+        }
+
+        if !self.ready {
+            return Ok(None);
+        }
+
+        self.loaded.push(prefab);
+        Ok(Some(Entity::prefab_node(self.loaded.len())))
+    }
+
+    fn unload(&mut self, ent: Entity) {
+        self.unloaded.push(ent);
+    }
+}
+
+fn opacity(transitions: &Transitions) -> f32 {
+    match transitions.overlay() {
+        TransitionOverlay::Fade(color) => color.a,
+        _ => 0.0,
+    }
+}
+
+#[test]
+fn easing() {
+    for &v in &[Easing::Linear, Easing::EaseIn, Easing::EaseOut, Easing::EaseInOut] {
+        assert_eq!(v.apply(0.0), 0.0);
+        assert_eq!(v.apply(1.0), 1.0);
+        assert_eq!(v.apply(2.0), 1.0);
+    }
+
+    assert_eq!(Easing::Linear.apply(0.25), 0.25);
+    assert!(Easing::EaseIn.apply(0.25) < 0.25);
+    assert!(Easing::EaseOut.apply(0.25) > 0.25);
+}
+
+#[test]
+fn fade_and_load() {
+    let mut scene = Scene::default();
+    let prefab = PrefabHandle::default();
+    let level = Entity::prefab_node(0);
+    let frame = Duration::from_millis(250);
+
+    let mut transitions = Transitions::new();
+    transitions
+        .set_color(math::Color::white())
+        .set_easing(Easing::Linear)
+        .fade_out(0.5)
+        .then_unload(level)
+        .then_load(prefab)
+        .fade_in(0.5);
+
+    assert!(transitions.is_running());
+    assert_eq!(transitions.overlay(), TransitionOverlay::Nothing);
+
+    transitions.advance(&mut scene, frame).unwrap();
+    assert_eq!(opacity(&transitions), 0.5);
+
+    // The screen stays covered until the prefab is ready.
+    for _ in 0..4 {
+        transitions.advance(&mut scene, frame).unwrap();
+        assert_eq!(opacity(&transitions), 1.0);
+    }
+
+    assert_eq!(scene.unloaded, vec![level]);
+    assert!(scene.loaded.is_empty());
+    assert!(transitions.take_loaded().is_empty());
+
+    scene.ready = true;
+    transitions.advance(&mut scene, frame).unwrap();
+    assert_eq!(scene.loaded, vec![prefab]);
+    assert_eq!(transitions.take_loaded().len(), 1);
+
+    transitions.advance(&mut scene, frame).unwrap();
+    assert_eq!(opacity(&transitions), 0.5);
+
+    transitions.advance(&mut scene, frame).unwrap();
+    assert_eq!(transitions.overlay(), TransitionOverlay::Nothing);
+    assert!(!transitions.is_running());
+}
+
+#[test]
+fn load_failure() {
+    let mut scene = Scene::default();
+    let prefab = PrefabHandle::default();
+    let frame = Duration::from_millis(250);
+
+    let mut transitions = Transitions::new();
+    transitions
+        .set_easing(Easing::Linear)
+        .fade_out(0.25)
+        .then_load(prefab)
+        .fade_in(0.25);
+
+    transitions.advance(&mut scene, frame).unwrap();
+    assert_eq!(opacity(&transitions), 1.0);
+    assert!(transitions.is_running());
+
+    // The failure is propagated instead of waiting forever, and the screen stays
+    // covered.
+    scene.failed = true;
+    assert!(transitions.advance(&mut scene, frame).is_err());
+    assert!(!transitions.is_running());
+    assert_eq!(opacity(&transitions), 1.0);
+    assert!(transitions.take_loaded().is_empty());
+}
@@ -0,0 +1,28 @@
+extern crate crayon;
+extern crate crayon_3d;
+
+use crayon_3d::renderers::*;
+
+#[test]
+fn keywords() {
+    let mut keywords = ShaderKeywords::empty();
+    assert!(keywords.is_empty());
+    assert_eq!(keywords.defines(), "");
+
+    keywords.enable(ShaderKeywords::FOG);
+    keywords |= ShaderKeywords::NORMALMAP;
+    assert!(keywords.contains(ShaderKeywords::FOG));
+    assert!(keywords.contains(ShaderKeywords::FOG | ShaderKeywords::NORMALMAP));
+    assert!(!keywords.contains(ShaderKeywords::FOG | ShaderKeywords::SKINNED));
+    assert_eq!(keywords.defines(), "#define NORMALMAP\n#define FOG\n");
+
+    keywords.set(ShaderKeywords::FOG, false);
+    keywords.set(ShaderKeywords::LIGHTMAP, true);
+    assert_eq!(keywords, ShaderKeywords::NORMALMAP | ShaderKeywords::LIGHTMAP);
+
+    let supported = ShaderKeywords::FOG | ShaderKeywords::LIGHTMAP;
+    assert_eq!(keywords.intersection(supported), ShaderKeywords::LIGHTMAP);
+
+    keywords.disable(ShaderKeywords::NORMALMAP | ShaderKeywords::LIGHTMAP);
+    assert!(keywords.is_empty());
+}
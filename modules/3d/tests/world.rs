@@ -1,12 +1,16 @@
 extern crate crayon;
 extern crate crayon_3d;
 
+use crayon::errors::Result;
+use crayon::prelude::{Engine, Settings};
+use crayon::utils::handle::Handle;
 use crayon::utils::handle_pool::HandlePool;
 
 use crayon_3d::prelude::*;
-use crayon_3d::renderers::Renderable;
+use crayon_3d::renderers::{Renderable, Renderer};
 use crayon_3d::tags::Tags;
 use crayon_3d::world_impl;
+use crayon_3d::{Component, EntityRemap, EntityStore};
 
 struct Testbed {
     entities: HandlePool,
@@ -35,7 +39,7 @@ impl Testbed {
         world_impl::find(&self.scene, &self.tags, name)
     }
 
-    fn instantiate(&mut self, prefab: &Prefab) -> Option<Entity> {
+    fn instantiate(&mut self, prefab: &Prefab) -> Option<(Entity, EntityRemap)> {
         world_impl::instantiate(
             &mut self.entities,
             &mut self.scene,
@@ -73,14 +77,15 @@ fn find() {
     assert_eq!(testbed.find("room.obj/blahblah"), None);
 }
 
-#[test]
-fn instantiate() {
+fn room() -> Prefab {
     use crayon_3d::assets::prefab::PrefabNode;
 
     let mut prefab = Prefab {
         nodes: Vec::new(),
         universe_meshes: Vec::new(),
+        universe_lightmaps: Vec::new(),
         meshes: Vec::new(),
+        lightmaps: Vec::new(),
     };
 
     prefab.nodes.push(PrefabNode {
@@ -89,6 +94,7 @@ fn instantiate() {
         first_child: Some(1),
         next_sib: None,
         mesh_renderer: None,
+        lightmap: None,
     });
 
     prefab.nodes.push(PrefabNode {
@@ -97,6 +103,7 @@ fn instantiate() {
         first_child: Some(2),
         next_sib: None,
         mesh_renderer: None,
+        lightmap: None,
     });
 
     prefab.nodes.push(PrefabNode {
@@ -105,6 +112,7 @@ fn instantiate() {
         first_child: None,
         next_sib: Some(3),
         mesh_renderer: None,
+        lightmap: None,
     });
 
     prefab.nodes.push(PrefabNode {
@@ -113,12 +121,494 @@ fn instantiate() {
         first_child: None,
         next_sib: None,
         mesh_renderer: None,
+        lightmap: None,
     });
 
+    prefab
+}
+
+#[test]
+fn instantiate() {
+    let prefab = room();
     let mut testbed = Testbed::new();
-    let e1 = testbed.instantiate(&prefab).unwrap();
+    let (e1, remap) = testbed.instantiate(&prefab).unwrap();
 
     assert_eq!(testbed.entities.len(), 4);
     assert_eq!(testbed.find("room.obj"), Some(e1));
     assert!(testbed.find("room.obj/floor/tallBox").is_some());
+
+    assert_eq!(remap.len(), 4);
+    assert_eq!(remap.get(Entity::prefab_node(0)), Some(e1));
+
+    let mut v = EntityRef::new(Entity::prefab_node(3));
+    v.remap(&remap);
+    assert_eq!(v.raw(), testbed.find("room.obj/floor/shortBox"));
+
+    let e2 = testbed.create("other");
+    let mut v = EntityRef::new(e2);
+    v.remap(&remap);
+    assert_eq!(v.raw(), Some(e2));
+}
+
+#[test]
+fn instantiate_lightmaps() {
+    use crayon_3d::assets::prefab::PrefabLightmap;
+    use crayon_3d::renderers::Lightmap;
+
+    let mut prefab = room();
+    let mesh = Handle::new(1, 1).into();
+    let texture = Handle::new(1, 1).into();
+    prefab.meshes.push(mesh);
+    prefab.lightmaps.push(texture);
+
+    prefab.nodes[2].mesh_renderer = Some(0);
+    prefab.nodes[2].lightmap = Some(PrefabLightmap {
+        index: 0,
+        scale: [0.5, 0.25],
+        offset: [0.5, 0.0],
+    });
+
+    prefab.nodes[3].mesh_renderer = Some(0);
+
+    let mut testbed = Testbed::new();
+    testbed.instantiate(&prefab).unwrap();
+
+    // The region of atlas is assigned to the mesh renderer of node.
+    let tall_box = testbed.find("room.obj/floor/tallBox").unwrap();
+    let mr = testbed.renderables.mesh(tall_box).unwrap();
+    let lightmap = Lightmap::with_region(texture, [0.5, 0.25], [0.5, 0.0]);
+    assert_eq!(mr.mesh, mesh);
+    assert_eq!(mr.lightmap, Some(lightmap));
+    assert_eq!(lightmap.scale_offset, [0.5, 0.25, 0.5, 0.0].into());
+
+    let short_box = testbed.find("room.obj/floor/shortBox").unwrap();
+    let mr = testbed.renderables.mesh(short_box).unwrap();
+    assert_eq!(mr.lightmap, None);
+}
+
+#[test]
+fn instantiate_parallel() {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Arc;
+
+    use crayon::sched::ScheduleSystem;
+    use crayon_3d::commands::{CommandBuffer, InstantiateJob};
+
+    let sched = ScheduleSystem::new(2, None, None);
+    let prefab = room();
+
+    let progress = AtomicUsize::new(0);
+    let commands = CommandBuffer::prepare_parallel(&sched.shared(), &prefab, &progress);
+    assert_eq!(progress.load(Ordering::Relaxed), 4);
+    assert_eq!(commands.len(), 4);
+
+    let sequential = CommandBuffer::prepare(&prefab);
+    for (lhs, rhs) in commands.nodes().iter().zip(sequential.nodes()) {
+        assert_eq!(lhs.node, rhs.node);
+        assert_eq!(lhs.parent, rhs.parent);
+        assert_eq!(lhs.name, rhs.name);
+    }
+
+    let job = InstantiateJob::spawn(sched.shared(), Arc::new(prefab));
+    while !job.is_finished() {
+        std::thread::yield_now();
+    }
+
+    assert!(job.progress() >= 1.0);
+
+    let commands = job.take().unwrap().unwrap();
+    assert!(job.take().is_none());
+
+    let mut testbed = Testbed::new();
+    let (e1, remap) = world_impl::apply(
+        &mut testbed.entities,
+        &mut testbed.scene,
+        &mut testbed.renderables,
+        &mut testbed.tags,
+        commands,
+    ).unwrap();
+
+    assert_eq!(testbed.entities.len(), 4);
+    assert_eq!(remap.get(Entity::prefab_node(0)), Some(e1));
+    assert!(testbed.find("room.obj/floor/tallBox").is_some());
+    assert!(testbed.find("room.obj/floor/shortBox").is_some());
+
+    // The malformed prefab panics, which finishes the job with an error.
+    let mut prefab = room();
+    prefab.nodes[3].mesh_renderer = Some(5);
+    let job = InstantiateJob::spawn(sched.shared(), Arc::new(prefab));
+    while !job.is_finished() {
+        std::thread::yield_now();
+    }
+
+    assert!(job.take().unwrap().is_err());
+
+    sched.terminate();
+}
+
+#[test]
+fn compact() {
+    let mut testbed = Testbed::new();
+    let garbage: Vec<_> = (0..4).map(|_| testbed.create("garbage")).collect();
+    let e1 = testbed.create("room.obj");
+    let e2 = testbed.create("floor");
+    let e3 = testbed.create("tallBox");
+
+    testbed.scene.set_parent(e2, e1, false).unwrap();
+    testbed.scene.set_parent(e3, e2, false).unwrap();
+    testbed.scene.set_local_position(e3, [1.0, 2.0, 3.0]);
+
+    for v in garbage {
+        world_impl::remove(
+            &mut testbed.entities,
+            &mut testbed.scene,
+            &mut testbed.renderables,
+            &mut testbed.tags,
+            v,
+        );
+    }
+
+    let remap = world_impl::compact(
+        &mut testbed.entities,
+        &mut testbed.scene,
+        &mut testbed.renderables,
+        &mut testbed.tags,
+    );
+
+    assert_eq!(remap.len(), 3);
+    assert_eq!(testbed.entities.len(), 3);
+    assert!(!testbed.entities.is_alive(e1));
+
+    // The entities are packed in tree order.
+    let v: Vec<_> = [e1, e2, e3]
+        .iter()
+        .map(|&v| remap.get(v).unwrap())
+        .collect();
+    assert_eq!(v.iter().map(|v| v.index()).collect::<Vec<_>>(), [0, 1, 2]);
+    assert!(v.iter().all(|&v| testbed.entities.is_alive(v)));
+
+    assert_eq!(testbed.find("room.obj/floor/tallBox"), Some(v[2]));
+    assert_eq!(testbed.scene.parent(v[2]), Some(v[1]));
+    assert_eq!(
+        testbed.scene.local_position(v[2]),
+        Some([1.0, 2.0, 3.0].into())
+    );
+
+    let mut r = EntityRef::new(e3);
+    r.remap(&remap);
+    assert_eq!(r.raw(), Some(v[2]));
+}
+
+#[test]
+fn par_join() {
+    use crayon::sched::ScheduleSystem;
+
+    let sched = ScheduleSystem::new(2, None, None);
+    let mut entities = HandlePool::new();
+
+    let mut positions = Component::new();
+    let mut velocities = Component::new();
+    for i in 0..100 {
+        let ent: Entity = entities.create().into();
+        positions.add(ent, i as f32);
+        if i % 2 == 0 {
+            velocities.add(ent, 1.0f32);
+        }
+    }
+
+    positions.par_join_mut(&velocities, &sched.shared(), 7, |_, p, v| *p += *v);
+    for (i, &v) in positions.data.iter().enumerate() {
+        let expected = if i % 2 == 0 { i + 1 } else { i };
+        assert_eq!(v, expected as f32);
+    }
+
+    positions.par_chunks_mut(&sched.shared(), 16, |ents, data| {
+        assert_eq!(ents.len(), data.len());
+        assert!(data.len() <= 16);
+        for v in data {
+            *v = 0.0;
+        }
+    });
+
+    assert!(positions.data.iter().all(|&v| v == 0.0));
+}
+
+#[derive(Default)]
+struct Recorder {
+    cameras: Vec<Entity>,
+}
+
+impl Renderer for Recorder {
+    fn submit(&mut self, camera: &Camera, _: &[Lit], _: &[MeshRenderer]) -> Result<()> {
+        self.cameras.push(camera.ent);
+        Ok(())
+    }
+}
+
+fn worlds() -> (World<Recorder>, World<Recorder>) {
+    let mut settings = Settings::default();
+    settings.headless = true;
+    let mut engine = Engine::new_with(&settings).unwrap();
+    let res = WorldResources::new(&mut engine);
+
+    (
+        World::new(res.shared(), Recorder::default()),
+        World::new(res.shared(), Recorder::default()),
+    )
+}
+
+#[test]
+fn apply_commands() {
+    let (mut world, _) = worlds();
+    let e1 = world.create();
+    let e2 = world.create();
+    world.tags.add(e1, "e1");
+    world.set_parent(e2, e1).unwrap();
+
+    let mut commands = EntityCommandBuffer::new();
+    let spark = commands.create();
+    commands.add(spark, "spark");
+    commands.add(spark, EntityComponent::Parent(e1));
+    commands.remove(e2);
+
+    let remap = world.apply_commands(commands).unwrap();
+    let spark = remap.get(spark).unwrap();
+    assert_eq!(world.find("e1/spark"), Some(spark));
+    assert!(!world.is_alive(e2));
+
+    // The world is left untouched if any of commands fails, e.g. attaching an entity
+    // to its descendant.
+    let checksum = world.checksum();
+    let mut commands = EntityCommandBuffer::new();
+    let e3 = commands.create();
+    commands.add(e3, "e3");
+    commands.remove(spark);
+    commands.add(e1, EntityComponent::Parent(e3));
+    commands.add(e3, EntityComponent::Parent(e1));
+    assert!(world.apply_commands(commands).is_err());
+    assert_eq!(world.checksum(), checksum);
+    assert!(world.is_alive(spark));
+    assert!(world.find("e3").is_none());
+
+    // The placeholders are checked before anything is applied.
+    let mut commands = EntityCommandBuffer::new();
+    let e3 = EntityCommandBuffer::new().create();
+    commands.remove(spark);
+    commands.add(e3, "e3");
+    assert!(world.apply_commands(commands).is_err());
+    assert!(world.is_alive(spark));
+}
+
+struct Follow(EntityRef);
+
+impl VisitEntityRefs for Follow {
+    fn visit_entity_refs<F: FnMut(&mut EntityRef)>(&mut self, mut visitor: F) {
+        visitor(&mut self.0);
+    }
+}
+
+#[test]
+fn checksum() {
+    let (mut world, _) = worlds();
+    let e1 = world.create();
+    let checksum = world.checksum();
+
+    // The states of components besides the scene graph are hashed too.
+    world.tags.add(e1, "e1");
+    assert!(world.checksum() != checksum);
+
+    let checksum = world.checksum();
+    world.colliders.add(e1, Collider::Sphere(1.0));
+    assert!(world.checksum() != checksum);
+
+    let checksum = world.checksum();
+    world
+        .controllers
+        .add(e1, CharacterController::new(0.5, 2.0));
+    assert!(world.checksum() != checksum);
+
+    let checksum = world.checksum();
+    world.controllers.get_mut(e1).unwrap().velocity.x = 1.0;
+    assert!(world.checksum() != checksum);
+
+    let checksum = world.checksum();
+    world
+        .triggers
+        .add(e1, TriggerVolume::new(TriggerShape::Sphere(1.0)));
+    assert!(world.checksum() != checksum);
+
+    // The same states produce the same checksum.
+    let (state, checksum) = (world.save(), world.checksum());
+    world.controllers.get_mut(e1).unwrap().velocity.x = 2.0;
+    world.restore(&state);
+    assert_eq!(world.checksum(), checksum);
+}
+
+#[test]
+fn compact_with() {
+    let (mut world, _) = worlds();
+    let garbage = world.create();
+    let e1 = world.create();
+    let e2 = world.create();
+    world.remove(garbage);
+
+    let mut follows = Component::new();
+    follows.add(e2, Follow(EntityRef::new(e1)));
+
+    let remap = world.compact_with(&mut [&mut follows as &mut EntityStore]);
+    assert!(!world.is_alive(e1) && !world.is_alive(e2));
+
+    // Both the entities and references of components are remapped.
+    let (e1, e2) = (remap.get(e1).unwrap(), remap.get(e2).unwrap());
+    assert!(world.is_alive(e1) && world.is_alive(e2));
+    assert_eq!(follows.get(e2).and_then(|v| world.resolve(v.0)), Some(e1));
+}
+
+#[test]
+fn migrate() {
+    let (mut src, mut dst) = worlds();
+    assert!(src.id() != dst.id());
+
+    let e1 = src.create();
+    let e2 = src.create();
+    let e3 = src.create();
+    let e4 = src.create();
+    src.tags.add(e2, "room.obj");
+    src.tags.add(e3, "floor");
+    src.tags.add(e4, "tallBox");
+    src.set_parent(e2, e1).unwrap();
+    src.set_parent(e4, e2).unwrap();
+    src.set_parent(e3, e2).unwrap();
+    src.scene.set_local_position(e1, [1.0, 0.0, 0.0]);
+    src.scene.set_local_position(e2, [0.0, 2.0, 0.0]);
+    src.ticks.set(e3, TickRate::Half);
+    src.renderables.add_camera(e4, Camera::default());
+
+    let remap = src.migrate(e2, &mut dst).unwrap();
+    assert_eq!(remap.len(), 3);
+    assert!(src.is_alive(e1));
+    assert!(!src.is_alive(e2) && !src.is_alive(e3) && !src.is_alive(e4));
+
+    let v = dst.find("room.obj").unwrap();
+    assert_eq!(remap.get(e2), Some(v));
+    assert_eq!(dst.parent(v), None);
+    assert_eq!(dst.scene.position(v), Some([1.0, 2.0, 0.0].into()));
+
+    let children: Vec<_> = dst.children(v).collect();
+    assert_eq!(children, [remap.get(e3).unwrap(), remap.get(e4).unwrap()]);
+    assert_eq!(dst.ticks.rate(children[0]), TickRate::Half);
+    assert!(dst.renderables.camera(children[1]).is_some());
+    assert_eq!(dst.components(children[1]).len(), 4);
+
+    // The failed migrations leave both worlds untouched.
+    let (src_checksum, dst_checksum) = (src.checksum(), dst.checksum());
+    assert!(src.migrate(e2, &mut dst).is_err());
+    assert_eq!(src.checksum(), src_checksum);
+    assert_eq!(dst.checksum(), dst_checksum);
+}
+
+#[test]
+fn draw_cameras_of() {
+    let (mut main, mut preview) = worlds();
+
+    let e1 = main.create();
+    let e2 = main.create();
+    main.renderables.add_camera(e1, Camera::default());
+
+    let mut camera = Camera::default();
+    camera.set_world(preview.id());
+    main.renderables.add_camera(e2, camera);
+
+    main.advance().unwrap();
+    preview.advance().unwrap();
+    preview.draw_cameras_of(&main).unwrap();
+    assert_eq!(main.renderer.cameras, [e1]);
+    assert_eq!(preview.renderer.cameras, [e2]);
+}
+
+#[test]
+fn lockstep() {
+    use crayon::application::lockstep::{Lockstep, LockstepParams, Simulation};
+    use crayon::math;
+    use crayon_3d::WorldState;
+
+    struct Game {
+        world: World<Recorder>,
+        players: Vec<Entity>,
+    }
+
+    impl Simulation for Game {
+        type Input = i32;
+        type State = WorldState;
+
+        fn step(&mut self, _: u64, inputs: &[i32]) -> Result<()> {
+            for (&ent, &v) in self.players.iter().zip(inputs) {
+                let position = self.world.scene.local_position(ent).unwrap();
+                let delta = math::Vector3::new(v as f32, 0.0, 0.0);
+                self.world.scene.set_local_position(ent, position + delta);
+
+                if v == 9 {
+                    let spawned = self.world.create();
+                    self.world.tags.add(spawned, "spawned");
+                }
+            }
+
+            Ok(())
+        }
+
+        fn save(&self) -> WorldState {
+            self.world.save()
+        }
+
+        fn restore(&mut self, state: &WorldState) {
+            self.world.restore(state);
+        }
+    }
+
+    let game = || {
+        let (mut world, _) = worlds();
+        let players = vec![world.create(), world.create()];
+        Game {
+            world: world,
+            players: players,
+        }
+    };
+
+    let params = LockstepParams {
+        players: 2,
+        local: 0,
+        input_delay: 1,
+        max_rollback: 4,
+    };
+
+    let mut lockstep = Lockstep::new(params).unwrap();
+    let mut sim = game();
+
+    // The remote inputs are predicted as default.
+    for _ in 0..4 {
+        lockstep.add_local_input(1).unwrap();
+        assert!(lockstep.advance(&mut sim).unwrap());
+    }
+
+    assert!(sim.world.find("spawned").is_none());
+
+    for (tick, &v) in [2, 9, 2, 2].iter().enumerate() {
+        lockstep.add_remote_input(1, tick as u64 + 1, v).unwrap();
+    }
+
+    assert!(lockstep.advance(&mut sim).unwrap());
+    assert_eq!(lockstep.rollbacks(), 1);
+
+    let mut expected = game();
+    let inputs = [[0, 0], [1, 2], [1, 9], [1, 2], [1, 2]];
+    for (i, v) in inputs.iter().enumerate() {
+        expected.step(i as u64, v).unwrap();
+    }
+
+    assert!(sim.world.find("spawned").is_some());
+    assert_eq!(sim.world.checksum(), expected.world.checksum());
+    assert_eq!(
+        sim.world.scene.local_position(sim.players[1]),
+        Some([15.0, 0.0, 0.0].into())
+    );
 }
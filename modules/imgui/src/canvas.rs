@@ -1,16 +1,32 @@
 use std::ops::{Deref, DerefMut};
 
+use crayon::application::cursor::{Cursor, CursorIcon};
 use crayon::application::Context;
 use crayon::input::prelude::*;
+use crayon::math;
 use crayon::video::errors::*;
 use crayon::video::prelude::*;
+use cursor::SoftwareCursor;
 use imgui;
 use renderer::Renderer;
+use scaler::{CanvasLayout, CanvasScaler};
 
 pub struct FrameGuard<'a> {
     renderer: &'a mut Renderer,
     frame: Option<imgui::Ui<'a>>,
     surface: Option<SurfaceHandle>,
+    hidpi: f32,
+    cursor: Option<&'a mut SoftwareCursor>,
+    safe_area: math::RectF,
+}
+
+impl<'a> FrameGuard<'a> {
+    /// Gets the area in units which is not obscured by notches or system bars, the
+    /// widgets anchored to the edges of screen should be placed inside it.
+    #[inline]
+    pub fn safe_area(&self) -> math::RectF {
+        self.safe_area
+    }
 }
 
 impl<'a> Deref for FrameGuard<'a> {
@@ -30,7 +46,11 @@ impl<'a> DerefMut for FrameGuard<'a> {
 impl<'a> Drop for FrameGuard<'a> {
     fn drop(&mut self) {
         if let Some(ui) = self.frame.take() {
-            self.renderer.draw(self.surface, ui).unwrap();
+            self.renderer.draw(self.surface, self.hidpi, ui).unwrap();
+        }
+
+        if let Some(cursor) = self.cursor.take() {
+            cursor.draw().unwrap();
         }
     }
 }
@@ -38,6 +58,10 @@ impl<'a> Drop for FrameGuard<'a> {
 pub struct Canvas {
     ctx: imgui::ImGui,
     renderer: Renderer,
+    cursor: SoftwareCursor,
+    // Whether the cursor of window is changed by widgets.
+    cursor_changed: bool,
+    scaler: CanvasScaler,
 }
 
 impl Canvas {
@@ -46,34 +70,126 @@ impl Canvas {
         imgui.set_ini_filename(None);
 
         let renderer = Renderer::new(ctx, &mut imgui)?;
+        let cursor = SoftwareCursor::new(ctx)?;
 
         Self::bind_keycode(&mut imgui);
         Ok(Canvas {
             ctx: imgui,
             renderer: renderer,
+            cursor: cursor,
+            cursor_changed: false,
+            scaler: CanvasScaler::default(),
         })
     }
 
+    /// Sets the scaling policy of canvas.
+    #[inline]
+    pub fn set_scaler(&mut self, scaler: CanvasScaler) {
+        self.scaler = scaler;
+    }
+
+    #[inline]
+    pub fn scaler(&self) -> &CanvasScaler {
+        &self.scaler
+    }
+
+    /// Gets the layout of canvas with the current dimensions of window.
+    pub fn layout(&self, ctx: &Context) -> CanvasLayout {
+        let dimensions = ctx.window.dimensions();
+        let insets = ctx.window.safe_area_insets();
+        self.scaler.layout(dimensions, ctx.window.hidpi(), insets)
+    }
+
     pub fn frame<T>(&mut self, ctx: &Context, surface: T) -> FrameGuard
     where
         T: Into<Option<SurfaceHandle>>,
     {
+        let layout = self.layout(ctx);
+        let units = math::Vector2::new(
+            (layout.size.x.round() as u32).max(1),
+            (layout.size.y.round() as u32).max(1),
+        );
+
+        // The mouse position is in pixels relative to the lower-left hand corner.
+        let pos = ctx.input.mouse_position() / layout.scale;
+        let pointer = math::Vector2::new(pos.x, layout.size.y - pos.y);
+
+        self.update_cursor(ctx);
+        let mut frame = self.frame_with(
+            ctx,
+            surface.into(),
+            (units, ctx.window.dimensions(), layout.scale),
+            Some(pointer),
+            true,
+        );
+
+        frame.safe_area = layout.safe_area;
+        frame
+    }
+
+    /// Gets the software cursor which is drawn on top of UI.
+    #[inline]
+    pub fn software_cursor_mut(&mut self) -> &mut SoftwareCursor {
+        &mut self.cursor
+    }
+
+    /// Starts a frame with the (units, pixels, pixels per unit) `display` and the
+    /// `pointer` position in units, whose origin is at top-left. The software cursor is drawn
+    /// on top of UI if `cursor` is true.
+    pub(crate) fn frame_with(
+        &mut self,
+        ctx: &Context,
+        surface: Option<SurfaceHandle>,
+        display: (math::Vector2<u32>, math::Vector2<u32>, f32),
+        pointer: Option<math::Vector2<f32>>,
+        cursor: bool,
+    ) -> FrameGuard {
         // Update input device states.
-        Self::update_mouse_state(&mut self.ctx, &ctx);
+        Self::update_mouse_state(&mut self.ctx, ctx, pointer);
         Self::update_keycode_state(&mut self.ctx, &ctx.input);
 
         // Generates frame builder.
         let duration = ctx.time.frame_delta();
         let ts = duration.as_secs() as f32 + duration.subsec_nanos() as f32 / 1_000_000_000.0;
+        let (points, pixels, scale) = display;
 
         FrameGuard {
             renderer: &mut self.renderer,
-            frame: Some(self.ctx.frame(
-                ctx.window.dimensions_in_points().into(),
-                ctx.window.dimensions().into(),
-                ts,
-            )),
-            surface: surface.into(),
+            frame: Some(self.ctx.frame(points.into(), pixels.into(), ts)),
+            surface: surface,
+            hidpi: scale,
+            cursor: if cursor { Some(&mut self.cursor) } else { None },
+            safe_area: math::RectF::new(0.0, 0.0, points.x as f32, points.y as f32),
+        }
+    }
+
+    // Applies the cursor requested by the hovered widgets in last frame, and restores
+    // the default one once nothing requests it.
+    fn update_cursor(&mut self, ctx: &Context) {
+        let cursor = match self.ctx.mouse_cursor() {
+            imgui::ImGuiMouseCursor::None => Some(Cursor::Hidden),
+            imgui::ImGuiMouseCursor::Arrow => None,
+            imgui::ImGuiMouseCursor::TextInput => Some(Cursor::Icon(CursorIcon::Text)),
+            imgui::ImGuiMouseCursor::Move => Some(Cursor::Icon(CursorIcon::Move)),
+            imgui::ImGuiMouseCursor::ResizeNS => Some(Cursor::Icon(CursorIcon::ResizeVertical)),
+            imgui::ImGuiMouseCursor::ResizeEW => {
+                Some(Cursor::Icon(CursorIcon::ResizeHorizontal))
+            }
+            imgui::ImGuiMouseCursor::ResizeNESW => Some(Cursor::Icon(CursorIcon::ResizeNeSw)),
+            imgui::ImGuiMouseCursor::ResizeNWSE => Some(Cursor::Icon(CursorIcon::ResizeNwSe)),
+        };
+
+        match cursor {
+            Some(v) => {
+                ctx.window.set_cursor(v);
+                self.cursor_changed = true;
+            }
+            None => {
+                if self.cursor_changed {
+                    ctx.window.set_cursor(Cursor::default());
+                    self.cursor_changed = false;
+                }
+            }
         }
     }
 
@@ -139,10 +255,15 @@ impl Canvas {
         imgui.set_key_super(lwin || rwin);
     }
 
-    fn update_mouse_state(imgui: &mut imgui::ImGui, ctx: &Context) {
-        let dims = ctx.window.dimensions_in_points();
-        let pos = ctx.input.mouse_position_in_points();
-        imgui.set_mouse_pos(pos.x, dims.y as f32 - pos.y);
+    fn update_mouse_state(
+        imgui: &mut imgui::ImGui,
+        ctx: &Context,
+        pointer: Option<math::Vector2<f32>>,
+    ) {
+        // ImGui treats -FLT_MAX as the mouse is unavailable.
+        let unavailable = math::Vector2::new(-::std::f32::MAX, -::std::f32::MAX);
+        let pos = pointer.unwrap_or(unavailable);
+        imgui.set_mouse_pos(pos.x, pos.y);
 
         let l = ctx.input.is_mouse_down(MouseButton::Left);
         let r = ctx.input.is_mouse_down(MouseButton::Right);
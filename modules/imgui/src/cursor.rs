@@ -0,0 +1,168 @@
+//! The software cursor, which is rendered when the cursor requested with
+//! `WindowShared::set_cursor` is not supported by the platform.
+//!
+//! `Canvas::frame` draws it on top of UI automatically. Applications without UI could
+//! draw it at the end of every frame:
+//!
+//! ```rust,ignore
+//! let mut cursor = SoftwareCursor::new(&ctx)?;
+//! ctx.window.set_cursor(CustomCursor::new(&image, (4, 4).into()).unwrap());
+//!
+//! // Every frame, after everything else is drawn.
+//! cursor.draw()?;
+//! ```
+
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use crayon::application::cursor::{Cursor, CursorIcon, CustomCursor};
+use crayon::application::window::WindowShared;
+use crayon::application::Context;
+use crayon::input::prelude::*;
+use crayon::video::assets::prelude::*;
+use crayon::video::errors::*;
+use crayon::video::prelude::*;
+
+impl_vertex!{
+    CursorVertex {
+        position => [Position; Float; 2; false],
+        texcoord => [Texcoord0; Float; 2; false],
+        color => [Color0; UByte; 4; true],
+    }
+}
+
+/// `SoftwareCursor` draws the custom cursor as a textured quad at mouse position.
+pub struct SoftwareCursor {
+    video: Arc<VideoSystemShared>,
+    window: Arc<WindowShared>,
+    input: Arc<InputSystemShared>,
+
+    surface: SurfaceHandle,
+    shader: ShaderHandle,
+    mesh: MeshHandle,
+    texture: Option<(CustomCursor, TextureHandle)>,
+    icons: HashMap<CursorIcon, CustomCursor>,
+}
+
+impl SoftwareCursor {
+    pub fn new(ctx: &Context) -> Result<Self> {
+        let mut params = SurfaceParams::default();
+        params.set_clear(None, None, None);
+        let surface = ctx.video.create_surface(params)?;
+        let shader = ctx.video.builtin_shader(BuiltinShader::Ui)?;
+
+        // The unit quad whose origin is at top-left, it's placed by the matrix.
+        let white = [255, 255, 255, 255];
+        let verts = [
+            CursorVertex::new([0.0, 0.0], [0.0, 0.0], white),
+            CursorVertex::new([1.0, 0.0], [1.0, 0.0], white),
+            CursorVertex::new([1.0, -1.0], [1.0, 1.0], white),
+            CursorVertex::new([0.0, -1.0], [0.0, 1.0], white),
+        ];
+        let idxes: [u16; 6] = [0, 1, 2, 0, 2, 3];
+
+        let mut params = MeshParams::default();
+        params.layout = CursorVertex::layout();
+        params.index_format = IndexFormat::from_vertex_count(verts.len());
+        params.num_verts = verts.len();
+        params.num_idxes = idxes.len();
+
+        let iptr = params.index_format.encode_u16(&idxes).into_owned();
+        let data = MeshData {
+            vptr: CursorVertex::encode(&verts).into(),
+            iptr: iptr.into(),
+        };
+
+        let mesh = ctx.video.create_mesh(params, data)?;
+
+        Ok(SoftwareCursor {
+            video: ctx.video.clone(),
+            window: ctx.window.clone(),
+            input: ctx.input.clone(),
+            surface: surface,
+            shader: shader,
+            mesh: mesh,
+            texture: None,
+            icons: HashMap::new(),
+        })
+    }
+
+    /// Sets the image of standard icon, which is used if the icon is not supported by
+    /// the platform. The unsupported icons without image are not drawn.
+    #[inline]
+    pub fn set_icon(&mut self, icon: CursorIcon, cursor: CustomCursor) {
+        self.icons.insert(icon, cursor);
+    }
+
+    /// Draws the cursor if it should be rendered by software.
+    pub fn draw(&mut self) -> Result<()> {
+        let cursor = match self.window.software_cursor() {
+            Some(Cursor::Custom(v)) => v,
+            Some(Cursor::Icon(icon)) => match self.icons.get(&icon) {
+                Some(v) => v.clone(),
+                None => return Ok(()),
+            },
+            _ => return Ok(()),
+        };
+
+        let dimensions = self.window.dimensions();
+        if dimensions.x == 0 || dimensions.y == 0 {
+            return Ok(());
+        }
+
+        let texture = self.update_texture(&cursor)?;
+
+        // The mouse position is relative to the lower-left hand corner of the window.
+        let pos = self.input.mouse_position();
+        let size = cursor.image().dimensions();
+        let hotspot = cursor.hotspot();
+        let left = pos.x - hotspot.x as f32;
+        let top = pos.y + hotspot.y as f32;
+
+        let (w, h) = (dimensions.x as f32, dimensions.y as f32);
+        let matrix = UniformVariable::Matrix4f(
+            [
+                [2.0 * size.x as f32 / w, 0.0, 0.0, 0.0],
+                [0.0, 2.0 * size.y as f32 / h, 0.0, 0.0],
+                [0.0, 0.0, 1.0, 0.0],
+                [2.0 * left / w - 1.0, 2.0 * top / h - 1.0, 0.0, 1.0],
+            ],
+            false,
+        );
+
+        let mut dc = DrawCall::new(self.shader, self.mesh);
+        dc.set_uniform_variable("u_ProjMatrix", matrix);
+        dc.set_uniform_variable("u_Texture", texture);
+        self.video.draw(self.surface, dc);
+        Ok(())
+    }
+
+    fn update_texture(&mut self, cursor: &CustomCursor) -> Result<TextureHandle> {
+        if let Some((ref v, texture)) = self.texture {
+            if v == cursor {
+                return Ok(texture);
+            }
+        }
+
+        if let Some((_, texture)) = self.texture.take() {
+            self.video.delete_texture(texture);
+        }
+
+        let (mut params, data) = cursor.image().clone().into_texture(false);
+        params.filter = TextureFilter::Nearest;
+        let texture = self.video.create_texture(params, data)?;
+        self.texture = Some((cursor.clone(), texture));
+        Ok(texture)
+    }
+}
+
+impl Drop for SoftwareCursor {
+    fn drop(&mut self) {
+        if let Some((_, texture)) = self.texture.take() {
+            self.video.delete_texture(texture);
+        }
+
+        self.video.delete_mesh(self.mesh);
+        self.video.delete_surface(self.surface);
+    }
+}
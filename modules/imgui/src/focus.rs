@@ -0,0 +1,388 @@
+//! Focus and directional navigation, which makes menus usable without a mouse.
+//!
+//! The focusable items are registered with their layout rectangles, and the navigation
+//! graph is computed from the geometry, e.g. `NavDirection::Right` moves the focus to
+//! the nearest item on the right side.
+//!
+//! ```rust,ignore
+//! let mut nav = FocusNavigator::new();
+//! nav.add(START, start_rect);
+//! nav.add(OPTIONS, options_rect);
+//! nav.add(QUIT, quit_rect);
+//! nav.set_default(START);
+//! nav.set_cancel(QUIT);
+//!
+//! // Every frame.
+//! nav.update(&focus::keyboard_actions(&ctx.input));
+//! if nav.is_activated(START) {
+//!     ...
+//! }
+//!
+//! if let Some(rect) = nav.highlight(&FocusStyle::default()) {
+//!     // Draws the highlight around focused item.
+//! }
+//! ```
+//!
+//! There is no gamepad device in `crayon::input` yet, the actions of other devices could
+//! be fed into `FocusNavigator::update` directly.
+
+use crayon::input::prelude::*;
+use crayon::math;
+
+/// The identifier of focusable item.
+pub type FocusId = u32;
+
+/// The directions of navigation.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum NavDirection {
+    Up,
+    Down,
+    Left,
+    Right,
+}
+
+impl NavDirection {
+    pub const ALL: [NavDirection; 4] = [
+        NavDirection::Up,
+        NavDirection::Down,
+        NavDirection::Left,
+        NavDirection::Right,
+    ];
+
+    /// Gets the unit vector in UI coordinates, whose origin is at top-left.
+    fn vector(&self) -> math::Vector2<f32> {
+        match *self {
+            NavDirection::Up => math::Vector2::new(0.0, -1.0),
+            NavDirection::Down => math::Vector2::new(0.0, 1.0),
+            NavDirection::Left => math::Vector2::new(-1.0, 0.0),
+            NavDirection::Right => math::Vector2::new(1.0, 0.0),
+        }
+    }
+}
+
+/// The device independent inputs of navigation.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum NavAction {
+    /// Moves focus in direction.
+    Move(NavDirection),
+    /// Moves focus to the next item in the order of registration.
+    Next,
+    /// Moves focus to the previous item in the order of registration.
+    Previous,
+    /// Activates the focused item, or the default item if nothing is focused.
+    Activate,
+    /// Activates the cancel item if any.
+    Cancel,
+}
+
+/// The appearance of focus highlight.
+#[derive(Debug, Copy, Clone)]
+pub struct FocusStyle {
+    pub color: math::Color<f32>,
+    /// The thickness of outline in points.
+    pub thickness: f32,
+    /// The gap between item and outline in points.
+    pub padding: f32,
+}
+
+impl Default for FocusStyle {
+    fn default() -> Self {
+        FocusStyle {
+            color: math::Color::new(1.0, 0.8, 0.2, 1.0),
+            thickness: 2.0,
+            padding: 2.0,
+        }
+    }
+}
+
+impl FocusStyle {
+    /// Gets the outline rectangle around `rect`.
+    pub fn outline(&self, rect: math::RectF) -> math::RectF {
+        rect.inset(-(self.padding + self.thickness * 0.5))
+    }
+}
+
+#[derive(Debug, Copy, Clone)]
+struct FocusItem {
+    id: FocusId,
+    rect: math::RectF,
+    enabled: bool,
+}
+
+/// `FocusNavigator` keeps the focusable items, and routes navigation actions to them.
+#[derive(Debug, Clone, Default)]
+pub struct FocusNavigator {
+    items: Vec<FocusItem>,
+    focused: Option<FocusId>,
+    default: Option<FocusId>,
+    cancel: Option<FocusId>,
+    activated: Option<FocusId>,
+    cancelled: bool,
+}
+
+impl FocusNavigator {
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    /// Adds a focusable item, or updates its layout if its already added.
+    pub fn add(&mut self, id: FocusId, rect: math::RectF) {
+        match self.items.iter().position(|v| v.id == id) {
+            Some(i) => self.items[i].rect = rect,
+            None => self.items.push(FocusItem {
+                id: id,
+                rect: rect,
+                enabled: true,
+            }),
+        }
+    }
+
+    pub fn remove(&mut self, id: FocusId) {
+        self.items.retain(|v| v.id != id);
+
+        if self.focused == Some(id) {
+            self.focused = None;
+        }
+    }
+
+    /// Removes all the items.
+    pub fn clear(&mut self) {
+        self.items.clear();
+        self.focused = None;
+        self.default = None;
+        self.cancel = None;
+        self.activated = None;
+        self.cancelled = false;
+    }
+
+    /// Disabled items are skipped by navigation.
+    pub fn set_enabled(&mut self, id: FocusId, enabled: bool) {
+        if let Some(v) = self.items.iter_mut().find(|v| v.id == id) {
+            v.enabled = enabled;
+        }
+
+        if !enabled && self.focused == Some(id) {
+            self.focused = None;
+        }
+    }
+
+    /// Sets the default item, which gets focus initially and is activated if nothing
+    /// is focused.
+    #[inline]
+    pub fn set_default<T: Into<Option<FocusId>>>(&mut self, id: T) {
+        self.default = id.into();
+    }
+
+    /// Sets the item which is activated by `NavAction::Cancel`, e.g. the back button.
+    #[inline]
+    pub fn set_cancel<T: Into<Option<FocusId>>>(&mut self, id: T) {
+        self.cancel = id.into();
+    }
+
+    /// Moves the focus to item.
+    pub fn focus(&mut self, id: FocusId) {
+        if self.is_enabled(id) {
+            self.focused = Some(id);
+        }
+    }
+
+    /// Gets the focused item.
+    #[inline]
+    pub fn focused(&self) -> Option<FocusId> {
+        self.focused
+    }
+
+    #[inline]
+    pub fn is_focused(&self, id: FocusId) -> bool {
+        self.focused == Some(id)
+    }
+
+    /// Gets the item activated in last `update`.
+    #[inline]
+    pub fn activated(&self) -> Option<FocusId> {
+        self.activated
+    }
+
+    #[inline]
+    pub fn is_activated(&self, id: FocusId) -> bool {
+        self.activated == Some(id)
+    }
+
+    /// Returns true if `NavAction::Cancel` is received in last `update`.
+    #[inline]
+    pub fn is_cancelled(&self) -> bool {
+        self.cancelled
+    }
+
+    /// Gets the rectangle of focus highlight.
+    pub fn highlight(&self, style: &FocusStyle) -> Option<math::RectF> {
+        let id = self.focused?;
+        self.items
+            .iter()
+            .find(|v| v.id == id)
+            .map(|v| style.outline(v.rect))
+    }
+
+    /// Finds the nearest enabled item in direction. The candidates must be in front of
+    /// the center of `from`, and the ones aligned with `from` are preferred.
+    pub fn neighbor(&self, from: FocusId, dir: NavDirection) -> Option<FocusId> {
+        let src = self.items.iter().find(|v| v.id == from)?.rect;
+        let axis = dir.vector();
+        let center = src.center();
+
+        let mut best: Option<(f32, FocusId)> = None;
+        for v in &self.items {
+            if v.id == from || !v.enabled {
+                continue;
+            }
+
+            let delta = v.rect.center() - center;
+            let primary = delta.x * axis.x + delta.y * axis.y;
+            if primary <= 0.0 {
+                continue;
+            }
+
+            // The distance between the ranges on perpendicular axis, zero if overlapped.
+            let (lhs, rhs) = match dir {
+                NavDirection::Left | NavDirection::Right => {
+                    ((src.min().y, src.max().y), (v.rect.min().y, v.rect.max().y))
+                }
+                NavDirection::Up | NavDirection::Down => {
+                    ((src.min().x, src.max().x), (v.rect.min().x, v.rect.max().x))
+                }
+            };
+
+            let gap = (rhs.0 - lhs.1).max(lhs.0 - rhs.1).max(0.0);
+            let score = primary + gap * 2.0;
+            if best.map(|b| score < b.0).unwrap_or(true) {
+                best = Some((score, v.id));
+            }
+        }
+
+        best.map(|v| v.1)
+    }
+
+    /// Gets the navigation graph, which are the neighbors of every item in the order
+    /// of `NavDirection::ALL`.
+    pub fn graph(&self) -> Vec<(FocusId, [Option<FocusId>; 4])> {
+        self.items
+            .iter()
+            .map(|v| {
+                let mut neighbors = [None; 4];
+                for (n, dir) in neighbors.iter_mut().zip(NavDirection::ALL.iter()) {
+                    *n = self.neighbor(v.id, *dir);
+                }
+
+                (v.id, neighbors)
+            })
+            .collect()
+    }
+
+    /// Applies the navigation actions of this frame.
+    pub fn update(&mut self, actions: &[NavAction]) {
+        self.activated = None;
+        self.cancelled = false;
+
+        // Drops the focus of removed or disabled items.
+        if let Some(id) = self.focused {
+            if !self.is_enabled(id) {
+                self.focused = None;
+            }
+        }
+
+        if self.focused.is_none() {
+            self.focused = self.default.filter(|&v| self.is_enabled(v));
+        }
+
+        for &action in actions {
+            match action {
+                NavAction::Move(dir) => match self.focused {
+                    Some(id) => {
+                        if let Some(next) = self.neighbor(id, dir) {
+                            self.focused = Some(next);
+                        }
+                    }
+                    None => self.focused = self.first(),
+                },
+                NavAction::Next => self.focused = self.step(1),
+                NavAction::Previous => self.focused = self.step(-1),
+                NavAction::Activate => {
+                    self.activated = self.focused.or(self.default);
+                }
+                NavAction::Cancel => {
+                    self.cancelled = true;
+                    if self.cancel.map(|v| self.is_enabled(v)).unwrap_or(false) {
+                        self.activated = self.cancel;
+                    }
+                }
+            }
+        }
+    }
+
+    fn is_enabled(&self, id: FocusId) -> bool {
+        self.items.iter().any(|v| v.id == id && v.enabled)
+    }
+
+    fn first(&self) -> Option<FocusId> {
+        self.items.iter().find(|v| v.enabled).map(|v| v.id)
+    }
+
+    fn step(&self, offset: isize) -> Option<FocusId> {
+        let len = self.items.len() as isize;
+        let start = match self.focused {
+            Some(id) => self.items.iter().position(|v| v.id == id).unwrap() as isize,
+            None => return self.first(),
+        };
+
+        for i in 1..(len + 1) {
+            let index = ((start + offset * i) % len + len) % len;
+            let index = index as usize;
+            if self.items[index].enabled {
+                return Some(self.items[index].id);
+            }
+        }
+
+        self.focused
+    }
+}
+
+/// Maps the keyboard states into navigation actions. The arrow keys move focus, `Tab`
+/// and `Shift+Tab` cycle focus, `Return` and `Space` activate, and `Escape` cancels.
+pub fn keyboard_actions(input: &InputSystemShared) -> Vec<NavAction> {
+    let pressed = |key| input.is_key_press(key) || input.is_key_repeat(key);
+    let mut actions = Vec::new();
+
+    let moves = [
+        (KeyboardButton::Up, NavDirection::Up),
+        (KeyboardButton::Down, NavDirection::Down),
+        (KeyboardButton::Left, NavDirection::Left),
+        (KeyboardButton::Right, NavDirection::Right),
+    ];
+
+    for &(key, dir) in &moves {
+        if pressed(key) {
+            actions.push(NavAction::Move(dir));
+        }
+    }
+
+    if pressed(KeyboardButton::Tab) {
+        let shift = input.is_key_down(KeyboardButton::LShift)
+            || input.is_key_down(KeyboardButton::RShift);
+
+        actions.push(if shift {
+            NavAction::Previous
+        } else {
+            NavAction::Next
+        });
+    }
+
+    if input.is_key_press(KeyboardButton::Return) || input.is_key_press(KeyboardButton::Space) {
+        actions.push(NavAction::Activate);
+    }
+
+    if input.is_key_press(KeyboardButton::Escape) {
+        actions.push(NavAction::Cancel);
+    }
+
+    actions
+}
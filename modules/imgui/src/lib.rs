@@ -1,11 +1,24 @@
 #[macro_use]
 extern crate crayon;
 
+#[macro_use]
 extern crate imgui;
 #[doc(hidden)]
 pub use imgui::*;
 
 pub mod canvas;
+pub mod cursor;
+pub mod focus;
+pub mod profiler;
 mod renderer;
+pub mod scaler;
+pub mod stats;
+pub mod world;
 
 pub use self::canvas::Canvas;
+pub use self::cursor::SoftwareCursor;
+pub use self::focus::{FocusNavigator, FocusStyle, NavAction, NavDirection};
+pub use self::profiler::{ProfilerOverlay, ProfilerOverlayParams};
+pub use self::scaler::{CanvasLayout, CanvasScaler, ScaleMode};
+pub use self::stats::{StatsOverlay, StatsOverlayParams};
+pub use self::world::{WorldCanvas, WorldCanvasParams};
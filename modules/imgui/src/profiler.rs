@@ -0,0 +1,179 @@
+//! An overlay which shows the `FrameProfile` of engine, e.g. the time spent in each
+//! phase, the scoped systems of application and the GPU timings of surfaces.
+
+use std::collections::VecDeque;
+use std::time::Duration;
+
+use crayon::application::diagnostics::FrameProfile;
+use crayon::application::Context;
+use crayon::input::prelude::*;
+use imgui::{ImGuiCond, Ui};
+
+/// The setup parameters of `ProfilerOverlay`.
+#[derive(Debug, Clone, Copy)]
+pub struct ProfilerOverlayParams {
+    /// Shows the overlay initially.
+    pub visible: bool,
+    /// The hotkey which toggles the overlay.
+    pub toggle: Option<KeyboardButton>,
+    /// The number of frames shown in the frame time graph.
+    pub history: usize,
+    /// The maximum number of the most expensive GPU drawcalls shown.
+    pub drawcalls: usize,
+}
+
+impl Default for ProfilerOverlayParams {
+    fn default() -> Self {
+        ProfilerOverlayParams {
+            visible: false,
+            toggle: Some(KeyboardButton::F4),
+            history: 120,
+            drawcalls: 8,
+        }
+    }
+}
+
+/// `ProfilerOverlay` draws the `FrameProfile` of last frame with ImGui.
+pub struct ProfilerOverlay {
+    params: ProfilerOverlayParams,
+    visible: bool,
+    profile: FrameProfile,
+    frame_times: VecDeque<f32>,
+}
+
+impl ProfilerOverlay {
+    pub fn new(params: ProfilerOverlayParams) -> Self {
+        ProfilerOverlay {
+            params: params,
+            visible: params.visible,
+            profile: FrameProfile::default(),
+            frame_times: VecDeque::with_capacity(params.history),
+        }
+    }
+
+    #[inline]
+    pub fn set_visible(&mut self, visible: bool) {
+        self.visible = visible;
+    }
+
+    #[inline]
+    pub fn is_visible(&self) -> bool {
+        self.visible
+    }
+
+    /// Records the profile of last frame, which should be called in
+    /// `Application::on_post_update` with `Profiler::last_frame`.
+    pub fn update(&mut self, profile: &FrameProfile) {
+        self.profile = profile.clone();
+
+        while self.frame_times.len() >= self.params.history.max(1) {
+            self.frame_times.pop_front();
+        }
+
+        self.frame_times.push_back(to_ms(profile.duration));
+    }
+
+    /// Draws the overlay if its visible. The hotkey is handled here also.
+    pub fn draw(&mut self, ctx: &Context, ui: &Ui) {
+        if let Some(key) = self.params.toggle {
+            if ctx.input.is_key_press(key) {
+                self.visible = !self.visible;
+            }
+        }
+
+        if !self.visible {
+            return;
+        }
+
+        let profile = &self.profile;
+        let frame_times: Vec<f32> = self.frame_times.iter().cloned().collect();
+        let max = frame_times.iter().cloned().fold(16.7, f32::max);
+        let num_drawcalls = self.params.drawcalls;
+
+        ui.window(im_str!("Profiler"))
+            .movable(false)
+            .resizable(false)
+            .always_auto_resize(true)
+            .position((5.0, 160.0), ImGuiCond::FirstUseEver)
+            .build(|| {
+                ui.plot_lines(im_str!("##FrameTime"), &frame_times)
+                    .graph_size((240.0, 40.0))
+                    .scale_min(0.0)
+                    .scale_max(max)
+                    .overlay_text(im_str!("{:.2}ms", to_ms(profile.duration)))
+                    .build();
+
+                let phases = [
+                    ("Input", profile.input),
+                    ("Resources", profile.resources),
+                    ("Audio", profile.audio),
+                    ("Wait", profile.wait),
+                    ("Video", profile.video),
+                    ("Swap", profile.swap),
+                ];
+
+                for &(name, duration) in &phases {
+                    ui.text(im_str!("{}: {:.2}ms", name, to_ms(duration)));
+                }
+
+                let app = &profile.application;
+                ui.separator();
+                ui.text(im_str!("Application: {:.2}ms", to_ms(app.total())));
+                ui.text(im_str!(
+                    "  Pre: {:.2}ms, Fixed: {:.2}ms, Update: {:.2}ms, Late: {:.2}ms",
+                    to_ms(app.pre_update),
+                    to_ms(app.fixed_update),
+                    to_ms(app.update),
+                    to_ms(app.late_update)
+                ));
+
+                ui.text(im_str!(
+                    "  PreRender: {:.2}ms, Render: {:.2}ms",
+                    to_ms(app.pre_render),
+                    to_ms(app.render)
+                ));
+
+                for v in &profile.systems {
+                    ui.text(im_str!(
+                        "  {}: {:.2}ms ({:?})",
+                        v.name,
+                        to_ms(v.duration),
+                        v.calls
+                    ));
+                }
+
+                ui.separator();
+                let gpu = match profile.gpu {
+                    Some(ref gpu) => gpu,
+                    None => {
+                        ui.text(im_str!("GPU: N/A"));
+                        return;
+                    }
+                };
+
+                ui.text(im_str!("GPU: {:.2}ms", to_ms(gpu.duration)));
+                for v in &gpu.surfaces {
+                    ui.text(im_str!("  {:?}: {:.2}ms", v.surface, to_ms(v.duration)));
+                }
+
+                let mut drawcalls: Vec<_> = gpu.surfaces
+                    .iter()
+                    .flat_map(|v| v.drawcalls.iter())
+                    .collect();
+
+                drawcalls.sort_by(|lhs, rhs| rhs.duration.cmp(&lhs.duration));
+                for v in drawcalls.iter().take(num_drawcalls) {
+                    ui.text(im_str!(
+                        "  {:?} {:?}: {:.3}ms",
+                        v.shader,
+                        v.mesh,
+                        to_ms(v.duration)
+                    ));
+                }
+            });
+    }
+}
+
+fn to_ms(duration: Duration) -> f32 {
+    duration.as_secs() as f32 * 1000.0 + duration.subsec_nanos() as f32 / 1_000_000.0
+}
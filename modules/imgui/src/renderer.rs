@@ -2,7 +2,6 @@ use std::sync::Arc;
 
 use crayon::{application, math};
 
-use crayon::application::window;
 use crayon::video::assets::prelude::*;
 use crayon::video::errors::*;
 use crayon::video::prelude::*;
@@ -19,7 +18,6 @@ impl_vertex!{
 
 pub struct Renderer {
     video: Arc<VideoSystemShared>,
-    window: Arc<window::WindowShared>,
 
     surface: SurfaceHandle,
     shader: ShaderHandle,
@@ -79,7 +77,6 @@ impl Renderer {
 
         Ok(Renderer {
             video: ctx.video.clone(),
-            window: ctx.window.clone(),
 
             batch: Batch::new(),
             shader: shader,
@@ -89,15 +86,16 @@ impl Renderer {
         })
     }
 
-    pub fn draw(&mut self, surface: Option<SurfaceHandle>, ui: Ui) -> Result<()> {
+    pub fn draw(&mut self, surface: Option<SurfaceHandle>, hidpi: f32, ui: Ui) -> Result<()> {
         let surface = surface.unwrap_or(self.surface);
-        ui.render(|ui, dcs| self.render_draw_list(surface, ui, &dcs))?;
+        ui.render(|ui, dcs| self.render_draw_list(surface, hidpi, ui, &dcs))?;
         Ok(())
     }
 
     fn render_draw_list<'a>(
         &mut self,
         surface: SurfaceHandle,
+        hidpi: f32,
         ui: &'a Ui<'a>,
         tasks: &DrawList<'a>,
     ) -> Result<()> {
@@ -131,7 +129,6 @@ impl Renderer {
 
         let font_texture_id = **self.texture as usize;
         let mut idx_start = 0;
-        let hidpi = self.window.hidpi();
 
         for cmd in tasks.cmd_buffer {
             assert!(font_texture_id == cmd.texture_id as usize);
@@ -178,8 +175,9 @@ impl Renderer {
                 let slice = CanvasVertex::encode(verts);
                 self.batch.update_vertex_buffer(handle, 0, slice);
 
-                let slice = IndexFormat::encode(idxes);
-                self.batch.update_index_buffer(handle, 0, slice);
+                let format = IndexFormat::from_vertex_count(nv);
+                let slice = format.encode_u16(idxes);
+                self.batch.update_index_buffer(handle, 0, &slice);
                 return Ok(handle);
             }
 
@@ -199,14 +197,15 @@ impl Renderer {
         let mut params = MeshParams::default();
         params.hint = MeshHint::Stream;
         params.layout = CanvasVertex::layout();
-        params.index_format = IndexFormat::U16;
+        params.index_format = IndexFormat::from_vertex_count(nv);
         params.primitive = MeshPrimitive::Triangles;
         params.num_verts = nv;
         params.num_idxes = ni;
 
+        let iptr = params.index_format.encode_u16(idxes).into_owned();
         let data = MeshData {
             vptr: CanvasVertex::encode(verts).into(),
-            iptr: IndexFormat::encode(idxes).into(),
+            iptr: iptr.into(),
         };
 
         let mesh = self.video.create_mesh(params, data)?;
@@ -0,0 +1,117 @@
+//! Scaling policies of screen-space canvas, which make one UI layout works across
+//! different resolutions.
+//!
+//! The canvas is laid out in *units*, and `CanvasScaler` decides how many pixels a unit
+//! takes on current screen.
+//!
+//! ```rust,ignore
+//! // The layout is designed for 1920x1080, and scales with the height of screen.
+//! canvas.set_scaler(CanvasScaler::new(ScaleMode::ScaleWithHeight(1080.0)));
+//!
+//! let ui = canvas.frame(&ctx, None);
+//! let safe = ui.safe_area();
+//! ui.window(im_str!("HUD"))
+//!     .position((safe.min().x, safe.min().y), ImGuiCond::Always)
+//!     .build(|| {});
+//! ```
+
+use crayon::application::window::SafeAreaInsets;
+use crayon::math;
+
+/// The policies of canvas scaling.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum ScaleMode {
+    /// A unit takes constant size in *points*, e.g. `1.0` makes a unit the same as a
+    /// point, which has the same physical size regardless of the resolution.
+    ConstantPixelSize(f32),
+    /// Scales the canvas so its height matches the reference height in units.
+    ScaleWithHeight(f32),
+    /// Scales the canvas with the reference resolution in units. The `factor`
+    /// blends between matching width (0.0) and height (1.0) logarithmically, so the
+    /// scaling is symmetric when the aspect ratio differs from the reference.
+    MatchWidthOrHeight {
+        reference: math::Vector2<f32>,
+        factor: f32,
+    },
+}
+
+/// The setup of canvas scaling.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct CanvasScaler {
+    pub mode: ScaleMode,
+    /// The scale is clamped into [min_scale, max_scale], in pixels per unit.
+    pub min_scale: f32,
+    pub max_scale: f32,
+}
+
+impl Default for CanvasScaler {
+    fn default() -> Self {
+        CanvasScaler::new(ScaleMode::ConstantPixelSize(1.0))
+    }
+}
+
+/// The layout of canvas in current frame.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct CanvasLayout {
+    /// The size of canvas in units.
+    pub size: math::Vector2<f32>,
+    /// The number of pixels per unit.
+    pub scale: f32,
+    /// The area which is not obscured by notches or system bars, in units whose origin
+    /// is at top-left.
+    pub safe_area: math::RectF,
+}
+
+impl CanvasScaler {
+    pub fn new(mode: ScaleMode) -> Self {
+        CanvasScaler {
+            mode: mode,
+            min_scale: 0.1,
+            max_scale: 16.0,
+        }
+    }
+
+    /// Gets the number of pixels per unit with the `dimensions` in pixels of screen.
+    pub fn scale(&self, dimensions: math::Vector2<u32>, hidpi: f32) -> f32 {
+        let (w, h) = (dimensions.x as f32, dimensions.y as f32);
+
+        let scale = match self.mode {
+            ScaleMode::ConstantPixelSize(v) => v * hidpi,
+            ScaleMode::ScaleWithHeight(v) => h / v.max(1.0),
+            ScaleMode::MatchWidthOrHeight { reference, factor } => {
+                let sx = (w / reference.x.max(1.0)).max(::std::f32::EPSILON);
+                let sy = (h / reference.y.max(1.0)).max(::std::f32::EPSILON);
+                let t = factor.max(0.0).min(1.0);
+                (sx.ln() * (1.0 - t) + sy.ln() * t).exp()
+            }
+        };
+
+        scale.max(self.min_scale).min(self.max_scale)
+    }
+
+    /// Lays out the canvas with the `dimensions` in pixels of screen, and the safe area
+    /// `insets` in points.
+    pub fn layout(
+        &self,
+        dimensions: math::Vector2<u32>,
+        hidpi: f32,
+        insets: SafeAreaInsets,
+    ) -> CanvasLayout {
+        let scale = self.scale(dimensions, hidpi);
+        let size = math::Vector2::new(dimensions.x as f32, dimensions.y as f32) / scale;
+
+        // Converts the insets from points into units.
+        let ratio = hidpi / scale;
+        let min = math::Vector2::new(insets.left * ratio, insets.top * ratio);
+        let max = math::Vector2::new(
+            (size.x - insets.right * ratio).max(min.x),
+            (size.y - insets.bottom * ratio).max(min.y),
+        );
+
+        CanvasLayout {
+            size: size,
+            scale: scale,
+            safe_area: math::RectF::from_min_max(min, max),
+        }
+    }
+}
@@ -0,0 +1,184 @@
+//! An overlay which shows the statistics of engine, e.g. frame time, drawcalls and
+//! the utilization of schedule workers.
+
+use std::collections::VecDeque;
+use std::fmt::Display;
+use std::time::Duration;
+
+use crayon::application::{Context, FrameInfo};
+use crayon::input::prelude::*;
+use imgui::{ImGuiCond, Ui};
+
+/// The setup parameters of `StatsOverlay`.
+#[derive(Debug, Clone, Copy)]
+pub struct StatsOverlayParams {
+    /// Shows the overlay initially.
+    pub visible: bool,
+    /// The hotkey which toggles the overlay.
+    pub toggle: Option<KeyboardButton>,
+    /// The number of frames shown in the frame time graph.
+    pub history: usize,
+}
+
+impl Default for StatsOverlayParams {
+    fn default() -> Self {
+        StatsOverlayParams {
+            visible: false,
+            toggle: Some(KeyboardButton::F3),
+            history: 120,
+        }
+    }
+}
+
+/// `StatsOverlay` draws the collected `FrameInfo` of engine with ImGui.
+pub struct StatsOverlay {
+    params: StatsOverlayParams,
+    visible: bool,
+    info: FrameInfo,
+    frame_times: VecDeque<f32>,
+    counters: Vec<(String, String)>,
+}
+
+impl StatsOverlay {
+    pub fn new(params: StatsOverlayParams) -> Self {
+        StatsOverlay {
+            params: params,
+            visible: params.visible,
+            info: FrameInfo::default(),
+            frame_times: VecDeque::with_capacity(params.history),
+            counters: Vec::new(),
+        }
+    }
+
+    #[inline]
+    pub fn set_visible(&mut self, visible: bool) {
+        self.visible = visible;
+    }
+
+    #[inline]
+    pub fn is_visible(&self) -> bool {
+        self.visible
+    }
+
+    /// Records the information of last frame, which should be called in
+    /// `Application::on_post_update`.
+    pub fn update(&mut self, info: &FrameInfo) {
+        self.info = *info;
+
+        while self.frame_times.len() >= self.params.history.max(1) {
+            self.frame_times.pop_front();
+        }
+
+        self.frame_times.push_back(to_ms(info.duration));
+    }
+
+    /// Sets a custom counter which is shown at the bottom of overlay, e.g. the number
+    /// of entities in world.
+    pub fn set_counter<T: Display>(&mut self, name: &str, value: T) {
+        let value = value.to_string();
+        match self.counters.iter().position(|v| v.0 == name) {
+            Some(i) => self.counters[i].1 = value,
+            None => self.counters.push((name.to_owned(), value)),
+        }
+    }
+
+    /// Removes the custom counter.
+    pub fn remove_counter(&mut self, name: &str) {
+        self.counters.retain(|v| v.0 != name);
+    }
+
+    /// Draws the overlay if its visible. The hotkey is handled here also.
+    pub fn draw(&mut self, ctx: &Context, ui: &Ui) {
+        if let Some(key) = self.params.toggle {
+            if ctx.input.is_key_press(key) {
+                self.visible = !self.visible;
+            }
+        }
+
+        if !self.visible {
+            return;
+        }
+
+        let info = self.info;
+        let frame_times: Vec<f32> = self.frame_times.iter().cloned().collect();
+        let max = frame_times.iter().cloned().fold(16.7, f32::max);
+        let counters = &self.counters;
+
+        ui.window(im_str!("Stats"))
+            .movable(false)
+            .resizable(false)
+            .title_bar(false)
+            .always_auto_resize(true)
+            .position((5.0, 5.0), ImGuiCond::FirstUseEver)
+            .build(|| {
+                ui.text(im_str!(
+                    "FPS: {:?}, CPU: {:.2?}ms, GPU: {:.2?}ms",
+                    info.fps,
+                    to_ms(info.duration),
+                    to_ms(info.video.duration)
+                ));
+
+                ui.plot_lines(im_str!("##FrameTime"), &frame_times)
+                    .graph_size((240.0, 40.0))
+                    .scale_min(0.0)
+                    .scale_max(max)
+                    .overlay_text(im_str!("{:.1}ms", max))
+                    .build();
+
+                ui.text(im_str!(
+                    "DrawCalls: {:?}, Triangles: {:?}",
+                    info.video.drawcall,
+                    info.video.triangles
+                ));
+
+                ui.text(im_str!(
+                    "Textures: {:?} ({:.2}MB), Meshes: {:?}, Shaders: {:?}",
+                    info.video.alive_textures,
+                    info.video.texture_memory as f32 / (1024.0 * 1024.0),
+                    info.video.alive_meshes,
+                    info.video.alive_shaders
+                ));
+
+                let culling = info.video.culling.total();
+                ui.text(im_str!(
+                    "Visible: {:?}/{:?} (Frustum: -{:?}, Layer: -{:?}, Reused: {:?}), {:.2?}ms",
+                    culling.visible(),
+                    culling.tested,
+                    culling.frustum_culled,
+                    culling.layer_culled,
+                    culling.reused,
+                    to_ms(culling.duration)
+                ));
+
+                // Breaks down the statistics by camera if there are more than one.
+                let views = info.video.culling.views();
+                if views.len() > 1 {
+                    for &(id, ref v) in views {
+                        ui.text(im_str!(
+                            "  View {:x}: {:?}/{:?} (Frustum: -{:?}, Layer: -{:?}), {:.2?}ms",
+                            id,
+                            v.visible(),
+                            v.tested,
+                            v.frustum_culled,
+                            v.layer_culled,
+                            to_ms(v.duration)
+                        ));
+                    }
+                }
+
+                ui.text(im_str!(
+                    "Allocations: {:?}, Sched: {:.0}%",
+                    info.allocations,
+                    info.sched_utilization * 100.0
+                ));
+
+                for v in counters {
+                    ui.text(im_str!("{}: {}", v.0, v.1));
+                }
+            });
+    }
+}
+
+fn to_ms(duration: Duration) -> f32 {
+    duration.as_secs() as f32 * 1000.0 + duration.subsec_nanos() as f32 / 1_000_000.0
+}
@@ -0,0 +1,282 @@
+//! World-space canvas, which renders UI onto a flat or curved panel in 3D scene, e.g.
+//! diegetic interfaces and VR-style panels.
+//!
+//! The UI is drawn into a render texture, and the panel is a regular mesh which could
+//! be rendered by the scene with depth testing like any other objects. The pointer is
+//! mapped into canvas with raycasting.
+//!
+//! ```rust,ignore
+//! let mut params = WorldCanvasParams::default();
+//! params.curvature = 0.5;
+//! let mut panel = WorldCanvas::new(&ctx, params)?;
+//!
+//! // Draws `panel.mesh()` in scene with `panel.texture()` bound to `u_Texture`.
+//! let pointer = panel.raycast(&world_matrix, ray_origin, ray_dir);
+//! let ui = panel.frame(&ctx, pointer);
+//! ui.text(im_str!("Hello, world!"));
+//! ```
+
+use std::sync::Arc;
+
+use crayon::application::Context;
+use crayon::math::{self, InnerSpace, SquareMatrix};
+use crayon::video::errors::*;
+use crayon::video::prelude::*;
+
+use canvas::{Canvas, FrameGuard};
+
+impl_vertex!{
+    WorldCanvasVertex {
+        position => [Position; Float; 3; false],
+        normal => [Normal; Float; 3; false],
+        texcoord => [Texcoord0; Float; 2; false],
+    }
+}
+
+/// The setup parameters of `WorldCanvas`.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct WorldCanvasParams {
+    /// The resolution of canvas in pixels.
+    pub dimensions: math::Vector2<u32>,
+    /// The size of panel in world units.
+    pub size: math::Vector2<f32>,
+    /// The horizontal angle in radians the panel bends around its local y-axis. The
+    /// panel is flat if its zero, and it wraps towards the viewer otherwise.
+    pub curvature: f32,
+    /// The number of horizontal segments of curved panel.
+    pub segments: u32,
+}
+
+impl Default for WorldCanvasParams {
+    fn default() -> Self {
+        WorldCanvasParams {
+            dimensions: math::Vector2::new(512, 512),
+            size: math::Vector2::new(1.0, 1.0),
+            curvature: 0.0,
+            segments: 16,
+        }
+    }
+}
+
+impl WorldCanvasParams {
+    /// Gets the radius of curved panel.
+    #[inline]
+    fn radius(&self) -> Option<f32> {
+        if self.curvature > ::std::f32::EPSILON {
+            Some(self.size.x / self.curvature.min(2.0 * ::std::f32::consts::PI))
+        } else {
+            None
+        }
+    }
+
+    /// Casts a ray in world space against the front face of panel, whose local space
+    /// is described by `transform`. Returns the hit position in canvas pixels, whose
+    /// origin is at top-left.
+    ///
+    /// In local space, the panel is centered at origin and faces the negative z-axis.
+    pub fn raycast(
+        &self,
+        transform: &math::Matrix4<f32>,
+        origin: math::Vector3<f32>,
+        dir: math::Vector3<f32>,
+    ) -> Option<math::Vector2<f32>> {
+        let inv = transform.invert()?;
+        let o = (inv * origin.extend(1.0)).truncate();
+        let d = (inv * dir.extend(0.0)).truncate();
+        let half = self.size * 0.5;
+
+        // Finds the hit position along the arc, and the height.
+        let (x, y) = match self.radius() {
+            None => {
+                // Hits the front face only.
+                if d.z <= 0.0 {
+                    return None;
+                }
+
+                let t = -o.z / d.z;
+                if t < 0.0 {
+                    return None;
+                }
+
+                let p = o + d * t;
+                (p.x, p.y)
+            }
+            Some(r) => {
+                // The panel is a part of cylinder whose axis is parallel to y-axis and
+                // passes through (0, 0, -r).
+                let (ox, oz) = (o.x, o.z + r);
+                let a = d.x * d.x + d.z * d.z;
+                let b = 2.0 * (ox * d.x + oz * d.z);
+                let c = ox * ox + oz * oz - r * r;
+                let disc = b * b - 4.0 * a * c;
+
+                if a <= 0.0 || disc < 0.0 {
+                    return None;
+                }
+
+                let sqrt = disc.sqrt();
+                let roots = [(-b - sqrt) / (2.0 * a), (-b + sqrt) / (2.0 * a)];
+
+                let mut hit = None;
+                for &t in roots.iter().filter(|&&t| t >= 0.0) {
+                    let p = o + d * t;
+                    let theta = p.x.atan2(p.z + r);
+                    let normal = math::Vector3::new(-theta.sin(), 0.0, -theta.cos());
+                    let x = theta * r;
+
+                    if d.dot(normal) < 0.0 && x.abs() <= half.x && p.y.abs() <= half.y {
+                        hit = Some((x, p.y));
+                        break;
+                    }
+                }
+
+                hit?
+            }
+        };
+
+        if x.abs() > half.x || y.abs() > half.y {
+            return None;
+        }
+
+        let u = x / self.size.x + 0.5;
+        let v = 0.5 - y / self.size.y;
+        Some(math::Vector2::new(
+            u * self.dimensions.x as f32,
+            v * self.dimensions.y as f32,
+        ))
+    }
+
+    /// Builds the vertices and indices of panel.
+    pub fn vertices(&self) -> (Vec<WorldCanvasVertex>, Vec<u16>) {
+        let segments = if self.radius().is_some() {
+            self.segments.max(1)
+        } else {
+            1
+        };
+
+        let half = self.size * 0.5;
+        let mut verts = Vec::with_capacity((segments as usize + 1) * 2);
+        let mut idxes = Vec::with_capacity(segments as usize * 6);
+
+        for i in 0..(segments + 1) {
+            let s = i as f32 / segments as f32;
+            let x = (s - 0.5) * self.size.x;
+
+            let (position, normal) = match self.radius() {
+                Some(r) => {
+                    let theta = x / r;
+                    let (sin, cos) = theta.sin_cos();
+                    ([r * sin, 0.0, -r * (1.0 - cos)], [-sin, 0.0, -cos])
+                }
+                None => ([x, 0.0, 0.0], [0.0, 0.0, -1.0]),
+            };
+
+            // The render texture is upside down, since its origin is at bottom-left.
+            let (px, pz) = (position[0], position[2]);
+            verts.push(WorldCanvasVertex::new([px, -half.y, pz], normal, [s, 0.0]));
+            verts.push(WorldCanvasVertex::new([px, half.y, pz], normal, [s, 1.0]));
+
+            if i > 0 {
+                let base = (i as u16 - 1) * 2;
+                idxes.extend_from_slice(&[base, base + 2, base + 3, base, base + 3, base + 1]);
+            }
+        }
+
+        (verts, idxes)
+    }
+}
+
+/// `WorldCanvas` draws UI into a render texture which is mapped onto a panel in scene.
+pub struct WorldCanvas {
+    video: Arc<VideoSystemShared>,
+    canvas: Canvas,
+    params: WorldCanvasParams,
+    texture: RenderTextureHandle,
+    surface: SurfaceHandle,
+    mesh: MeshHandle,
+}
+
+impl WorldCanvas {
+    pub fn new(ctx: &Context, params: WorldCanvasParams) -> Result<Self> {
+        let canvas = Canvas::new(ctx)?;
+
+        let mut setup = RenderTextureParams::default();
+        setup.format = RenderTextureFormat::RGBA8;
+        setup.dimensions = params.dimensions;
+        let texture = ctx.video.create_render_texture(setup)?;
+
+        let mut setup = SurfaceParams::default();
+        setup.set_attachments(&[texture], None)?;
+        setup.set_clear(math::Color::transparent(), None, None);
+        let surface = ctx.video.create_surface(setup)?;
+
+        let (verts, idxes) = params.vertices();
+        let mut setup = MeshParams::default();
+        setup.layout = WorldCanvasVertex::layout();
+        setup.index_format = IndexFormat::from_vertex_count(verts.len());
+        setup.num_verts = verts.len();
+        setup.num_idxes = idxes.len();
+
+        let iptr = setup.index_format.encode_u16(&idxes).into_owned();
+        let data = MeshData {
+            vptr: WorldCanvasVertex::encode(&verts).into(),
+            iptr: iptr.into(),
+        };
+
+        let mesh = ctx.video.create_mesh(setup, data)?;
+
+        Ok(WorldCanvas {
+            video: ctx.video.clone(),
+            canvas: canvas,
+            params: params,
+            texture: texture,
+            surface: surface,
+            mesh: mesh,
+        })
+    }
+
+    #[inline]
+    pub fn params(&self) -> &WorldCanvasParams {
+        &self.params
+    }
+
+    /// Gets the render texture which the UI is drawn into.
+    #[inline]
+    pub fn texture(&self) -> RenderTextureHandle {
+        self.texture
+    }
+
+    /// Gets the mesh of panel, which has position, normal and texcoord attributes.
+    #[inline]
+    pub fn mesh(&self) -> MeshHandle {
+        self.mesh
+    }
+
+    /// Casts a ray in world space against the panel. See `WorldCanvasParams::raycast`.
+    #[inline]
+    pub fn raycast(
+        &self,
+        transform: &math::Matrix4<f32>,
+        origin: math::Vector3<f32>,
+        dir: math::Vector3<f32>,
+    ) -> Option<math::Vector2<f32>> {
+        self.params.raycast(transform, origin, dir)
+    }
+
+    /// Starts a frame with the `pointer` position in canvas pixels, which is usually
+    /// the result of `raycast`. The buttons and wheel are still read from mouse.
+    pub fn frame(&mut self, ctx: &Context, pointer: Option<math::Vector2<f32>>) -> FrameGuard {
+        let dimensions = self.params.dimensions;
+        let display = (dimensions, dimensions, 1.0);
+        self.canvas
+            .frame_with(ctx, Some(self.surface), display, pointer, false)
+    }
+}
+
+impl Drop for WorldCanvas {
+    fn drop(&mut self) {
+        self.video.delete_mesh(self.mesh);
+        self.video.delete_surface(self.surface);
+        self.video.delete_render_texture(self.texture);
+    }
+}
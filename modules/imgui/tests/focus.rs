@@ -0,0 +1,133 @@
+extern crate crayon;
+extern crate crayon_imgui;
+
+use crayon::math;
+use crayon_imgui::focus::*;
+
+const A: FocusId = 1;
+const B: FocusId = 2;
+const C: FocusId = 3;
+const D: FocusId = 4;
+
+// A B
+// C D
+fn grid() -> FocusNavigator {
+    let mut nav = FocusNavigator::new();
+    nav.add(A, math::RectF::new(0.0, 0.0, 10.0, 10.0));
+    nav.add(B, math::RectF::new(20.0, 0.0, 10.0, 10.0));
+    nav.add(C, math::RectF::new(0.0, 20.0, 10.0, 10.0));
+    nav.add(D, math::RectF::new(20.0, 20.0, 10.0, 10.0));
+    nav
+}
+
+#[test]
+fn neighbors() {
+    let nav = grid();
+    assert_eq!(nav.neighbor(A, NavDirection::Right), Some(B));
+    assert_eq!(nav.neighbor(A, NavDirection::Down), Some(C));
+    assert_eq!(nav.neighbor(A, NavDirection::Up), None);
+    assert_eq!(nav.neighbor(A, NavDirection::Left), None);
+    assert_eq!(nav.neighbor(D, NavDirection::Left), Some(C));
+    assert_eq!(nav.neighbor(D, NavDirection::Up), Some(B));
+
+    let graph = nav.graph();
+    assert_eq!(graph[0], (A, [None, Some(C), None, Some(B)]));
+    assert_eq!(graph[3], (D, [Some(B), None, Some(C), None]));
+}
+
+#[test]
+fn aligned() {
+    let mut nav = grid();
+
+    // The aligned items are preferred, even if they are a bit farther.
+    nav.add(5, math::RectF::new(15.0, 18.0, 10.0, 10.0));
+    nav.add(6, math::RectF::new(25.0, 0.0, 10.0, 10.0));
+    nav.remove(B);
+    assert_eq!(nav.neighbor(A, NavDirection::Right), Some(6));
+}
+
+#[test]
+fn disabled() {
+    let mut nav = grid();
+    nav.set_enabled(B, false);
+    assert_eq!(nav.neighbor(A, NavDirection::Right), Some(D));
+    assert_eq!(nav.neighbor(D, NavDirection::Up), Some(A));
+
+    nav.focus(B);
+    assert_eq!(nav.focused(), None);
+
+    nav.focus(A);
+    nav.update(&[NavAction::Next]);
+    assert_eq!(nav.focused(), Some(C));
+
+    // Disabling the focused item drops the focus.
+    nav.set_enabled(C, false);
+    assert_eq!(nav.focused(), None);
+
+    nav.update(&[NavAction::Move(NavDirection::Down)]);
+    assert_eq!(nav.focused(), Some(A));
+}
+
+#[test]
+fn wraparound() {
+    let mut nav = grid();
+    nav.focus(D);
+    nav.update(&[NavAction::Next]);
+    assert_eq!(nav.focused(), Some(A));
+
+    nav.update(&[NavAction::Previous]);
+    assert_eq!(nav.focused(), Some(D));
+
+    nav.update(&[NavAction::Previous, NavAction::Previous]);
+    assert_eq!(nav.focused(), Some(B));
+
+    // Stays if there are no other enabled items.
+    nav.set_enabled(A, false);
+    nav.set_enabled(C, false);
+    nav.set_enabled(D, false);
+    nav.update(&[NavAction::Next]);
+    assert_eq!(nav.focused(), Some(B));
+}
+
+#[test]
+fn default_and_cancel() {
+    let mut nav = grid();
+    nav.set_default(B);
+    nav.set_cancel(C);
+
+    nav.update(&[]);
+    assert_eq!(nav.focused(), Some(B));
+    assert_eq!(nav.activated(), None);
+
+    nav.update(&[NavAction::Move(NavDirection::Down), NavAction::Activate]);
+    assert!(nav.is_activated(D));
+
+    // The activation only lasts one update.
+    nav.update(&[]);
+    assert_eq!(nav.activated(), None);
+
+    nav.update(&[NavAction::Cancel]);
+    assert!(nav.is_cancelled());
+    assert!(nav.is_activated(C));
+    assert!(nav.is_focused(D));
+
+    nav.update(&[]);
+    assert!(!nav.is_cancelled());
+
+    // The disabled cancel item is not activated.
+    nav.set_enabled(C, false);
+    nav.update(&[NavAction::Cancel]);
+    assert!(nav.is_cancelled());
+    assert_eq!(nav.activated(), None);
+
+    // Falls back to the default item if nothing is focused.
+    nav.remove(D);
+    nav.set_default(None::<FocusId>);
+    nav.update(&[]);
+    assert_eq!(nav.focused(), None);
+
+    nav.set_default(A);
+    nav.update(&[NavAction::Activate]);
+    assert!(nav.is_activated(A));
+    assert!(nav.is_focused(A));
+}
@@ -0,0 +1,106 @@
+extern crate crayon;
+extern crate crayon_imgui;
+
+use crayon::application::window::SafeAreaInsets;
+use crayon::math;
+use crayon_imgui::scaler::*;
+
+fn approx(lhs: f32, rhs: f32) -> bool {
+    (lhs - rhs).abs() < 0.0001
+}
+
+#[test]
+fn constant_pixel_size() {
+    let scaler = CanvasScaler::new(ScaleMode::ConstantPixelSize(1.0));
+    let dimensions = math::Vector2::new(1920, 1080);
+    assert!(approx(scaler.scale(dimensions, 1.0), 1.0));
+    assert!(approx(scaler.scale(dimensions, 2.0), 2.0));
+
+    let layout = scaler.layout(dimensions, 2.0, SafeAreaInsets::default());
+    assert_eq!(layout.size, math::Vector2::new(960.0, 540.0));
+    assert_eq!(layout.safe_area, math::RectF::new(0.0, 0.0, 960.0, 540.0));
+}
+
+#[test]
+fn scale_with_height() {
+    let scaler = CanvasScaler::new(ScaleMode::ScaleWithHeight(1080.0));
+    let uhd = math::Vector2::new(3840, 2160);
+    assert!(approx(scaler.scale(uhd, 1.0), 2.0));
+
+    let hd = math::Vector2::new(1280, 720);
+    assert!(approx(scaler.scale(hd, 2.0), 2.0 / 3.0));
+
+    let layout = scaler.layout(math::Vector2::new(2560, 1440), 1.0, Default::default());
+    assert!(approx(layout.size.x, 1920.0));
+    assert!(approx(layout.size.y, 1080.0));
+}
+
+#[test]
+fn match_width_or_height() {
+    let mode = |factor| ScaleMode::MatchWidthOrHeight {
+        reference: math::Vector2::new(1920.0, 1080.0),
+        factor: factor,
+    };
+
+    // Twice as tall as the reference.
+    let dimensions = math::Vector2::new(1920, 2160);
+    let scale = |factor| CanvasScaler::new(mode(factor)).scale(dimensions, 1.0);
+    assert!(approx(scale(0.0), 1.0));
+    assert!(approx(scale(1.0), 2.0));
+    assert!(approx(scale(0.5), 2.0f32.sqrt()));
+
+    // The factor is clamped into [0, 1].
+    assert!(approx(scale(-1.0), 1.0));
+    assert!(approx(scale(2.0), 2.0));
+}
+
+#[test]
+fn clamp() {
+    let dimensions = math::Vector2::new(1920, 1080);
+
+    let scaler = CanvasScaler::new(ScaleMode::ConstantPixelSize(100.0));
+    assert!(approx(scaler.scale(dimensions, 1.0), 16.0));
+
+    let scaler = CanvasScaler::new(ScaleMode::ConstantPixelSize(0.0));
+    assert!(approx(scaler.scale(dimensions, 1.0), 0.1));
+
+    let mut scaler = CanvasScaler::new(ScaleMode::ScaleWithHeight(1080.0));
+    scaler.min_scale = 1.0;
+    scaler.max_scale = 1.5;
+    let (small, large) = (math::Vector2::new(640, 360), math::Vector2::new(3840, 2160));
+    assert!(approx(scaler.scale(small, 1.0), 1.0));
+    assert!(approx(scaler.scale(large, 1.0), 1.5));
+
+    // A degenerated reference never divides by zero.
+    let scaler = CanvasScaler::new(ScaleMode::ScaleWithHeight(0.0));
+    assert!(approx(scaler.scale(dimensions, 1.0), 16.0));
+}
+
+#[test]
+fn safe_area() {
+    let insets = SafeAreaInsets {
+        left: 10.0,
+        top: 20.0,
+        right: 30.0,
+        bottom: 40.0,
+    };
+
+    // The insets in points are converted into units.
+    let scaler = CanvasScaler::new(ScaleMode::ConstantPixelSize(1.0));
+    let layout = scaler.layout(math::Vector2::new(2000, 1000), 2.0, insets);
+    assert!(approx(layout.scale, 2.0));
+    assert_eq!(layout.safe_area.min(), math::Vector2::new(10.0, 20.0));
+    assert_eq!(layout.safe_area.max(), math::Vector2::new(970.0, 460.0));
+
+    let scaler = CanvasScaler::new(ScaleMode::ScaleWithHeight(500.0));
+    let layout = scaler.layout(math::Vector2::new(2000, 1000), 1.0, insets);
+    assert!(approx(layout.scale, 2.0));
+    assert_eq!(layout.safe_area.min(), math::Vector2::new(5.0, 10.0));
+    assert_eq!(layout.safe_area.max(), math::Vector2::new(985.0, 480.0));
+
+    // The safe area is empty instead of inverted if the insets overlap.
+    let scaler = CanvasScaler::new(ScaleMode::ConstantPixelSize(1.0));
+    let layout = scaler.layout(math::Vector2::new(30, 30), 1.0, insets);
+    assert!(layout.safe_area.is_empty());
+    assert_eq!(layout.safe_area.min(), math::Vector2::new(10.0, 20.0));
+}
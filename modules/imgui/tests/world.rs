@@ -0,0 +1,128 @@
+extern crate crayon;
+extern crate crayon_imgui;
+
+use crayon::math::{self, InnerSpace, SquareMatrix};
+use crayon_imgui::world::*;
+
+fn approx(lhs: Option<math::Vector2<f32>>, rhs: [f32; 2]) -> bool {
+    lhs.map(|v| (v - math::Vector2::from(rhs)).magnitude() < 0.01)
+        .unwrap_or(false)
+}
+
+fn flat() -> WorldCanvasParams {
+    let mut params = WorldCanvasParams::default();
+    params.dimensions = math::Vector2::new(512, 256);
+    params.size = math::Vector2::new(2.0, 1.0);
+    params
+}
+
+fn curved() -> WorldCanvasParams {
+    // The radius is 4.0, and the arc spans [-0.25, 0.25] radians.
+    let mut params = flat();
+    params.curvature = 0.5;
+    params
+}
+
+#[test]
+fn flat_hit() {
+    let params = flat();
+    let identity = math::Matrix4::identity();
+    let forward = math::Vector3::new(0.0, 0.0, 1.0);
+
+    let hit = params.raycast(&identity, math::Vector3::new(0.0, 0.0, -5.0), forward);
+    assert!(approx(hit, [256.0, 128.0]));
+
+    // The canvas origin is at top-left.
+    let hit = params.raycast(&identity, math::Vector3::new(-0.5, 0.25, -5.0), forward);
+    assert!(approx(hit, [128.0, 64.0]));
+
+    let hit = params.raycast(&identity, math::Vector3::new(0.99, -0.49, -5.0), forward);
+    assert!(approx(hit, [509.44, 253.44]));
+
+    // The ray is transformed into the local space of panel.
+    let transform = math::Matrix4::from_translation(math::Vector3::new(1.0, 2.0, 3.0));
+    let hit = params.raycast(&transform, math::Vector3::new(1.5, 2.0, 0.0), forward);
+    assert!(approx(hit, [384.0, 128.0]));
+
+    let transform = math::Matrix4::from_angle_y(math::Deg(90.0));
+    let dir = math::Vector3::new(1.0, 0.0, 0.0);
+    let hit = params.raycast(&transform, math::Vector3::new(-5.0, 0.0, 0.0), dir);
+    assert!(approx(hit, [256.0, 128.0]));
+}
+
+#[test]
+fn flat_miss() {
+    let params = flat();
+    let identity = math::Matrix4::identity();
+    let forward = math::Vector3::new(0.0, 0.0, 1.0);
+    let origin = math::Vector3::new(0.0, 0.0, -5.0);
+
+    // Outside of panel.
+    let v = math::Vector3::new(1.1, 0.0, -5.0);
+    assert!(params.raycast(&identity, v, forward).is_none());
+    let v = math::Vector3::new(0.0, -0.6, -5.0);
+    assert!(params.raycast(&identity, v, forward).is_none());
+
+    // Back face, parallel and opposite rays.
+    let v = math::Vector3::new(0.0, 0.0, 5.0);
+    assert!(params.raycast(&identity, v, -forward).is_none());
+    let dir = math::Vector3::new(1.0, 0.0, 0.0);
+    assert!(params.raycast(&identity, origin, dir).is_none());
+    assert!(params.raycast(&identity, origin, -forward).is_none());
+
+    // Behind the origin of ray.
+    let v = math::Vector3::new(0.0, 0.0, 1.0);
+    assert!(params.raycast(&identity, v, forward).is_none());
+
+    // Singular transform.
+    let transform = math::Matrix4::from_scale(0.0);
+    assert!(params.raycast(&transform, origin, forward).is_none());
+}
+
+#[test]
+fn curved_hit() {
+    let params = curved();
+    let identity = math::Matrix4::identity();
+    let forward = math::Vector3::new(0.0, 0.0, 1.0);
+
+    // From outside and inside of the cylinder.
+    let hit = params.raycast(&identity, math::Vector3::new(0.0, 0.0, -20.0), forward);
+    assert!(approx(hit, [256.0, 128.0]));
+
+    let hit = params.raycast(&identity, math::Vector3::new(0.0, 0.25, -1.0), forward);
+    assert!(approx(hit, [256.0, 64.0]));
+
+    // The horizontal position is mapped with the arc length, so the surface of panel
+    // is not stretched.
+    let r = 4.0f32;
+    for &(x, u) in &[(-0.5f32, 128.0f32), (0.5, 384.0), (0.9, 486.4)] {
+        let theta = x / r;
+        let normal = math::Vector3::new(-theta.sin(), 0.0, -theta.cos());
+        let p = math::Vector3::new(r * theta.sin(), 0.0, -r * (1.0 - theta.cos()));
+
+        let hit = params.raycast(&identity, p + normal, -normal);
+        assert!(approx(hit, [u, 128.0]));
+    }
+}
+
+#[test]
+fn curved_miss() {
+    let params = curved();
+    let identity = math::Matrix4::identity();
+    let forward = math::Vector3::new(0.0, 0.0, 1.0);
+
+    // Hits the cylinder outside of the arc.
+    let v = math::Vector3::new(2.0, 0.0, -20.0);
+    assert!(params.raycast(&identity, v, forward).is_none());
+    let v = math::Vector3::new(0.0, 0.6, -20.0);
+    assert!(params.raycast(&identity, v, forward).is_none());
+
+    // Back face, vertical and opposite rays.
+    let v = math::Vector3::new(0.0, 0.0, 5.0);
+    assert!(params.raycast(&identity, v, -forward).is_none());
+    let v = math::Vector3::new(0.0, 5.0, -1.0);
+    let up = math::Vector3::new(0.0, 1.0, 0.0);
+    assert!(params.raycast(&identity, v, -up).is_none());
+    let v = math::Vector3::new(0.0, 0.0, -1.0);
+    assert!(params.raycast(&identity, v, -forward).is_none());
+}
@@ -0,0 +1,83 @@
+//! The rigid body component.
+
+use crayon::math;
+
+use shape::Shape;
+
+/// The way how a body is moved.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BodyKind {
+    /// Moved by the simulation, and writes its transform back to the scene.
+    Dynamic,
+    /// Moved by users through the scene. It pushes the dynamic bodies with the velocity
+    /// derived from its movement, but is never pushed back.
+    Kinematic,
+    /// Never moves, e.g. the floors and walls.
+    Static,
+}
+
+/// `RigidBody` simulates the entity as a solid with `shape`. The bodies collide with
+/// the bodies whose `layers` match their `mask`.
+#[derive(Debug, Clone)]
+pub struct RigidBody {
+    pub kind: BodyKind,
+    pub shape: Shape,
+    /// The mass in kilograms, which is ignored unless the body is dynamic.
+    pub mass: f32,
+    pub friction: f32,
+    /// The bounciness between 0 and 1.
+    pub restitution: f32,
+    /// The ratio of linear velocity which is lost per second.
+    pub linear_damping: f32,
+    /// The ratio of angular velocity which is lost per second.
+    pub angular_damping: f32,
+    pub gravity_scale: f32,
+    /// The layers which this body belongs to.
+    pub layers: u32,
+    /// The layers which this body collides with.
+    pub mask: u32,
+    /// The velocity of center in units per second.
+    pub linear_velocity: math::Vector3<f32>,
+    /// The angular velocity in world space in radians per second.
+    pub angular_velocity: math::Vector3<f32>,
+}
+
+impl RigidBody {
+    pub fn new(kind: BodyKind, shape: Shape) -> Self {
+        RigidBody {
+            kind: kind,
+            shape: shape,
+            mass: 1.0,
+            friction: 0.5,
+            restitution: 0.0,
+            linear_damping: 0.01,
+            angular_damping: 0.05,
+            gravity_scale: 1.0,
+            layers: 1,
+            mask: !0,
+            linear_velocity: math::Vector3::new(0.0, 0.0, 0.0),
+            angular_velocity: math::Vector3::new(0.0, 0.0, 0.0),
+        }
+    }
+
+    /// Gets the inverse of mass, which is zero unless the body is dynamic.
+    pub(crate) fn inverse_mass(&self) -> f32 {
+        if self.kind == BodyKind::Dynamic && self.mass > 0.0 {
+            1.0 / self.mass
+        } else {
+            0.0
+        }
+    }
+
+    /// Gets the inverse of principal moments of inertia in local space.
+    pub(crate) fn inverse_inertia(&self) -> math::Vector3<f32> {
+        let inv = |v: f32| if v > 0.0 { 1.0 / v } else { 0.0 };
+
+        if self.inverse_mass() > 0.0 {
+            let v = self.shape.inertia(self.mass);
+            math::Vector3::new(inv(v.x), inv(v.y), inv(v.z))
+        } else {
+            math::Vector3::new(0.0, 0.0, 0.0)
+        }
+    }
+}
@@ -0,0 +1,252 @@
+// The persistent contact manifolds between pairs of bodies.
+
+use crayon::math::{self, InnerSpace, Rotation3};
+
+use gjk::{self, Convex, Pose};
+use shape::Shape;
+
+/// The distance within which the points are kept in manifolds.
+pub const THRESHOLD: f32 = 0.02;
+
+// The maximum number of points in manifold.
+const MAX_POINTS: usize = 4;
+// The angle in radians which the smaller shape is tilted by to find more points.
+const PERTURBATION: f32 = 0.1;
+
+/// A contact point found by the narrowphase.
+#[derive(Debug, Clone, Copy)]
+pub struct Candidate {
+    pub a: math::Vector3<f32>,
+    pub b: math::Vector3<f32>,
+    /// The direction from `lhs` to `rhs`.
+    pub normal: math::Vector3<f32>,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct ContactPoint {
+    // The points on surfaces of bodies in their local spaces.
+    pub local_a: math::Vector3<f32>,
+    pub local_b: math::Vector3<f32>,
+    pub a: math::Vector3<f32>,
+    pub b: math::Vector3<f32>,
+    pub normal: math::Vector3<f32>,
+    pub depth: f32,
+    // The accumulated impulses of last step, which warm start the solver.
+    pub impulse: f32,
+    pub tangent_impulse: [f32; 2],
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct Manifold {
+    pub points: Vec<ContactPoint>,
+}
+
+impl Manifold {
+    /// Updates the points with the poses of bodies, and removes the ones which have
+    /// been separated or drifted away.
+    pub fn refresh(&mut self, lhs: &Pose, rhs: &Pose) {
+        self.points.retain(|v| {
+            let (a, b) = (lhs.transform(v.local_a), rhs.transform(v.local_b));
+            let depth = (a - b).dot(v.normal);
+            let drift = (a - b) - v.normal * depth;
+            depth >= -THRESHOLD && drift.magnitude2() <= THRESHOLD * THRESHOLD
+        });
+
+        for v in &mut self.points {
+            v.a = lhs.transform(v.local_a);
+            v.b = rhs.transform(v.local_b);
+            v.depth = (v.a - v.b).dot(v.normal);
+        }
+    }
+
+    /// Adds a new point, which replaces the existing one nearby.
+    pub fn add(&mut self, lhs: &Pose, rhs: &Pose, candidate: &Candidate) {
+        let mut point = ContactPoint {
+            local_a: lhs.inverse_transform(candidate.a),
+            local_b: rhs.inverse_transform(candidate.b),
+            a: candidate.a,
+            b: candidate.b,
+            normal: candidate.normal,
+            depth: (candidate.a - candidate.b).dot(candidate.normal),
+            impulse: 0.0,
+            tangent_impulse: [0.0, 0.0],
+        };
+
+        let nearby = self
+            .points
+            .iter()
+            .position(|v| (v.local_a - point.local_a).magnitude2() < THRESHOLD * THRESHOLD);
+
+        if let Some(index) = nearby {
+            point.impulse = self.points[index].impulse;
+            point.tangent_impulse = self.points[index].tangent_impulse;
+            self.points[index] = point;
+            return;
+        }
+
+        self.points.push(point);
+        if self.points.len() > MAX_POINTS {
+            self.reduce();
+        }
+    }
+
+    /// Exchanges the bodies of manifold. The tangent impulses are dropped since the
+    /// tangents are derived from the flipped normal.
+    pub fn swap(&mut self) {
+        for v in &mut self.points {
+            ::std::mem::swap(&mut v.local_a, &mut v.local_b);
+            ::std::mem::swap(&mut v.a, &mut v.b);
+            v.normal = -v.normal;
+            v.tangent_impulse = [0.0, 0.0];
+        }
+    }
+
+    /// Gets the deepest point.
+    pub fn deepest(&self) -> Option<&ContactPoint> {
+        let mut deepest: Option<&ContactPoint> = None;
+        for v in &self.points {
+            if deepest.map(|w| v.depth > w.depth).unwrap_or(true) {
+                deepest = Some(v);
+            }
+        }
+
+        deepest
+    }
+
+    // Keeps the deepest point, and the points which cover the largest area.
+    fn reduce(&mut self) {
+        let points: Vec<_> = self.points.iter().map(|v| v.a).collect();
+        let area = |i: usize, j: usize, k: usize| {
+            (points[j] - points[i])
+                .cross(points[k] - points[i])
+                .magnitude()
+        };
+
+        let farthest = |score: &Fn(usize) -> f32, picked: &[usize]| {
+            let mut best: Option<(usize, f32)> = None;
+            for i in (0..points.len()).filter(|i| !picked.contains(i)) {
+                let v = score(i);
+                if best.map(|w| v > w.1).unwrap_or(true) {
+                    best = Some((i, v));
+                }
+            }
+
+            best.map(|v| v.0).unwrap_or(0)
+        };
+
+        let deepest = farthest(&|i| self.points[i].depth, &[]);
+        let mut picked = vec![deepest];
+
+        let i1 = farthest(&|i| (points[i] - points[deepest]).magnitude2(), &picked);
+        picked.push(i1);
+
+        let i2 = farthest(&|i| area(deepest, i1, i), &picked);
+        picked.push(i2);
+
+        let i3 = farthest(
+            &|i| area(i, deepest, i1) + area(i, i1, i2) + area(i, i2, deepest),
+            &picked,
+        );
+        picked.push(i3);
+
+        picked.sort();
+        let mut index = 0;
+        self.points.retain(|_| {
+            index += 1;
+            picked.contains(&(index - 1))
+        });
+    }
+}
+
+/// Finds the contact points between two shapes, which are closer than `threshold`.
+/// The points in the gap are speculative, which stop the bodies approaching before
+/// they penetrate.
+///
+/// If both of them have flat surfaces, the smaller one is tilted slightly in a few
+/// directions to find the points around, so the manifold is filled at once.
+pub fn collide(lhs: &Convex, rhs: &Convex, threshold: f32, candidates: &mut Vec<Candidate>) {
+    let (ma, mb) = (lhs.shape.margin(), rhs.shape.margin());
+    let closest = match gjk::closest(lhs, rhs) {
+        Some(v) => v,
+        None => return,
+    };
+
+    if closest.distance - ma - mb > threshold {
+        return;
+    }
+
+    let n = closest.normal;
+    candidates.push(Candidate {
+        a: closest.a + n * ma,
+        b: closest.b - n * mb,
+        normal: n,
+    });
+
+    let flat = |v: &Shape| match *v {
+        Shape::Box(_) | Shape::ConvexHull(_) => true,
+        _ => false,
+    };
+
+    if !flat(lhs.shape) || !flat(rhs.shape) {
+        return;
+    }
+
+    let perturb_lhs = lhs.shape.radius() <= rhs.shape.radius();
+    let (t1, t2) = tangents(n);
+
+    for &(c, s) in &[(1.0, 0.0), (0.0, 1.0), (-1.0, 0.0), (0.0, -1.0)] {
+        let axis = t1 * c + t2 * s;
+        let tilt = math::Quaternion::from_axis_angle(axis, math::Rad(PERTURBATION));
+
+        let tilted = |v: &Convex| Pose {
+            position: v.pose.position,
+            rotation: tilt * v.pose.rotation,
+        };
+
+        let (pa, pb) = if perturb_lhs {
+            (tilted(lhs), rhs.pose)
+        } else {
+            (lhs.pose, tilted(rhs))
+        };
+
+        let (a, b) = (
+            Convex {
+                shape: lhs.shape,
+                pose: pa,
+            },
+            Convex {
+                shape: rhs.shape,
+                pose: pb,
+            },
+        );
+
+        if let Some(v) = gjk::closest(&a, &b) {
+            // Moves the points back to the bodies which are not tilted.
+            let a = lhs
+                .pose
+                .transform(pa.inverse_transform(v.a + v.normal * ma));
+            let b = rhs
+                .pose
+                .transform(pb.inverse_transform(v.b - v.normal * mb));
+            if (a - b).dot(n) >= -threshold {
+                candidates.push(Candidate {
+                    a: a,
+                    b: b,
+                    normal: n,
+                });
+            }
+        }
+    }
+}
+
+/// Gets two directions which are perpendicular to `n` and each other.
+pub fn tangents(n: math::Vector3<f32>) -> (math::Vector3<f32>, math::Vector3<f32>) {
+    let axis = if n.x.abs() < 0.57 {
+        math::Vector3::unit_x()
+    } else {
+        math::Vector3::unit_y()
+    };
+
+    let t1 = n.cross(axis).normalize();
+    (t1, n.cross(t1))
+}
@@ -0,0 +1,434 @@
+// The closest points between convex shapes. The distance between the cores of shapes
+// is found with GJK, and the penetration of overlapping cores is found with EPA.
+
+use crayon::math::{self, InnerSpace};
+
+use shape::Shape;
+
+const MAX_ITERATIONS: usize = 64;
+const EPSILON: f32 = 1e-6;
+
+/// The position and rotation of body in world space.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Pose {
+    pub position: math::Vector3<f32>,
+    pub rotation: math::Quaternion<f32>,
+}
+
+impl Pose {
+    #[inline]
+    pub fn transform(&self, v: math::Vector3<f32>) -> math::Vector3<f32> {
+        self.rotation * v + self.position
+    }
+
+    #[inline]
+    pub fn inverse_transform(&self, v: math::Vector3<f32>) -> math::Vector3<f32> {
+        self.rotation.conjugate() * (v - self.position)
+    }
+}
+
+/// A shape placed in world space.
+pub struct Convex<'a> {
+    pub shape: &'a Shape,
+    pub pose: Pose,
+}
+
+impl<'a> Convex<'a> {
+    #[inline]
+    pub fn support(&self, dir: math::Vector3<f32>) -> math::Vector3<f32> {
+        let local = self.shape.support(self.pose.rotation.conjugate() * dir);
+        self.pose.transform(local)
+    }
+}
+
+/// The closest points between the cores of two shapes.
+#[derive(Debug, Clone, Copy)]
+pub struct Closest {
+    pub a: math::Vector3<f32>,
+    pub b: math::Vector3<f32>,
+    /// The direction from `a` to `b`, or the direction which separates `rhs` from `lhs`
+    /// if the cores are overlapping.
+    pub normal: math::Vector3<f32>,
+    /// The distance between cores, which is negative if they are overlapping.
+    pub distance: f32,
+}
+
+// A vertex of the Minkowski difference `lhs - rhs`.
+#[derive(Debug, Clone, Copy)]
+struct Vertex {
+    w: math::Vector3<f32>,
+    a: math::Vector3<f32>,
+    b: math::Vector3<f32>,
+}
+
+fn vertex(lhs: &Convex, rhs: &Convex, dir: math::Vector3<f32>) -> Vertex {
+    let a = lhs.support(dir);
+    let b = rhs.support(-dir);
+    Vertex {
+        w: a - b,
+        a: a,
+        b: b,
+    }
+}
+
+/// Finds the closest points between the cores of shapes. Returns `None` if the shapes
+/// are degenerated.
+pub fn closest(lhs: &Convex, rhs: &Convex) -> Option<Closest> {
+    let mut dir = lhs.pose.position - rhs.pose.position;
+    if dir.magnitude2() < EPSILON {
+        dir = math::Vector3::unit_x();
+    }
+
+    let mut simplex = vec![vertex(lhs, rhs, dir)];
+    let mut weights = vec![1.0];
+    let mut v = simplex[0].w;
+
+    for _ in 0..MAX_ITERATIONS {
+        let d2 = v.magnitude2();
+        if d2 < EPSILON * EPSILON {
+            return epa(lhs, rhs, simplex);
+        }
+
+        let s = vertex(lhs, rhs, -v);
+        let duplicated = simplex.iter().any(|p| (p.w - s.w).magnitude2() < EPSILON);
+        if duplicated || d2 - v.dot(s.w) <= d2 * 1e-4 {
+            break;
+        }
+
+        simplex.push(s);
+        match reduce(&mut simplex) {
+            Some(w) => {
+                v = simplex
+                    .iter()
+                    .zip(&w)
+                    .fold(zero(), |acc, (p, &t)| acc + p.w * t);
+                weights = w;
+            }
+            None => return epa(lhs, rhs, simplex),
+        }
+    }
+
+    Some(separated(&simplex, &weights))
+}
+
+fn separated(simplex: &[Vertex], weights: &[f32]) -> Closest {
+    let mut closest = Closest {
+        a: zero(),
+        b: zero(),
+        normal: math::Vector3::unit_y(),
+        distance: 0.0,
+    };
+
+    for (p, &t) in simplex.iter().zip(weights) {
+        closest.a += p.a * t;
+        closest.b += p.b * t;
+    }
+
+    let d = closest.b - closest.a;
+    closest.distance = d.magnitude();
+    if closest.distance > EPSILON {
+        closest.normal = d / closest.distance;
+    }
+
+    closest
+}
+
+// Reduces the simplex to the vertices of the feature which is closest to the origin,
+// and returns their barycentric weights. Returns `None` if the origin is inside.
+fn reduce(simplex: &mut Vec<Vertex>) -> Option<Vec<f32>> {
+    let weights = match simplex.len() {
+        1 => vec![1.0],
+        2 => {
+            let (a, b) = (simplex[0].w, simplex[1].w);
+            let t = segment(a, b);
+            vec![1.0 - t, t]
+        }
+        3 => triangle(simplex[0].w, simplex[1].w, simplex[2].w).to_vec(),
+        _ => {
+            let w: Vec<_> = simplex.iter().map(|v| v.w).collect();
+            let faces = [(0, 1, 2, 3), (0, 2, 3, 1), (0, 3, 1, 2), (1, 3, 2, 0)];
+
+            let mut best: Option<(f32, [f32; 4])> = None;
+            for &(i, j, k, l) in &faces {
+                let n = (w[j] - w[i]).cross(w[k] - w[i]);
+                let side = n.dot(w[l] - w[i]);
+
+                // Skips the faces whose outside does not contain the origin.
+                if side.abs() > EPSILON && (-w[i]).dot(n) * side >= 0.0 {
+                    continue;
+                }
+
+                let t = triangle(w[i], w[j], w[k]);
+                let p = w[i] * t[0] + w[j] * t[1] + w[k] * t[2];
+                if best.map(|v| p.magnitude2() < v.0).unwrap_or(true) {
+                    let mut weights = [0.0; 4];
+                    weights[i] = t[0];
+                    weights[j] = t[1];
+                    weights[k] = t[2];
+                    best = Some((p.magnitude2(), weights));
+                }
+            }
+
+            best?.1.to_vec()
+        }
+    };
+
+    let mut reduced = Vec::with_capacity(weights.len());
+    let mut i = 0;
+    simplex.retain(|_| {
+        i += 1;
+        if weights[i - 1] > 0.0 {
+            reduced.push(weights[i - 1]);
+            true
+        } else {
+            false
+        }
+    });
+
+    Some(reduced)
+}
+
+// Gets the parameter of point on segment `ab` which is closest to the origin.
+fn segment(a: math::Vector3<f32>, b: math::Vector3<f32>) -> f32 {
+    let ab = b - a;
+    let len2 = ab.magnitude2();
+    if len2 < EPSILON {
+        return 0.0;
+    }
+
+    (-a.dot(ab) / len2).max(0.0).min(1.0)
+}
+
+// Gets the barycentric weights of point on triangle `abc` which is closest to the
+// origin. The weights of vertices outside the closest feature are exactly zero.
+fn triangle(a: math::Vector3<f32>, b: math::Vector3<f32>, c: math::Vector3<f32>) -> [f32; 3] {
+    let (ab, ac) = (b - a, c - a);
+
+    let (d1, d2) = (-ab.dot(a), -ac.dot(a));
+    if d1 <= 0.0 && d2 <= 0.0 {
+        return [1.0, 0.0, 0.0];
+    }
+
+    let (d3, d4) = (-ab.dot(b), -ac.dot(b));
+    if d3 >= 0.0 && d4 <= d3 {
+        return [0.0, 1.0, 0.0];
+    }
+
+    let vc = d1 * d4 - d3 * d2;
+    if vc <= 0.0 && d1 >= 0.0 && d3 <= 0.0 {
+        let t = d1 / (d1 - d3);
+        return [1.0 - t, t, 0.0];
+    }
+
+    let (d5, d6) = (-ab.dot(c), -ac.dot(c));
+    if d6 >= 0.0 && d5 <= d6 {
+        return [0.0, 0.0, 1.0];
+    }
+
+    let vb = d5 * d2 - d1 * d6;
+    if vb <= 0.0 && d2 >= 0.0 && d6 <= 0.0 {
+        let t = d2 / (d2 - d6);
+        return [1.0 - t, 0.0, t];
+    }
+
+    let va = d3 * d6 - d5 * d4;
+    if va <= 0.0 && (d4 - d3) >= 0.0 && (d5 - d6) >= 0.0 {
+        let t = (d4 - d3) / ((d4 - d3) + (d5 - d6));
+        return [0.0, 1.0 - t, t];
+    }
+
+    let sum = va + vb + vc;
+    if sum < EPSILON * EPSILON {
+        // The triangle is degenerated, takes the closer edge.
+        let (s, t) = (segment(a, b), segment(a, c));
+        let (p, q) = (a + ab * s, a + ac * t);
+        return if p.magnitude2() <= q.magnitude2() {
+            [1.0 - s, s, 0.0]
+        } else {
+            [1.0 - t, 0.0, t]
+        };
+    }
+
+    let (v, w) = (vb / sum, vc / sum);
+    [1.0 - v - w, v, w]
+}
+
+// A face of polytope, whose normal points outwards.
+struct Face {
+    v: [usize; 3],
+    normal: math::Vector3<f32>,
+    distance: f32,
+}
+
+impl Face {
+    fn new(vertices: &[Vertex], v: [usize; 3], center: math::Vector3<f32>) -> Option<Face> {
+        let (a, b, c) = (vertices[v[0]].w, vertices[v[1]].w, vertices[v[2]].w);
+        let n = (b - a).cross(c - a);
+        let len = n.magnitude();
+        if len < EPSILON {
+            return None;
+        }
+
+        let (v, n) = if n.dot(a - center) < 0.0 {
+            ([v[0], v[2], v[1]], -n / len)
+        } else {
+            (v, n / len)
+        };
+
+        Some(Face {
+            v: v,
+            normal: n,
+            distance: n.dot(a),
+        })
+    }
+}
+
+// Expands the polytope from simplex until the face closest to the origin is found.
+fn epa(lhs: &Convex, rhs: &Convex, mut vertices: Vec<Vertex>) -> Option<Closest> {
+    if !blow_up(lhs, rhs, &mut vertices) {
+        return None;
+    }
+
+    let center = vertices.iter().fold(zero(), |acc, v| acc + v.w) * 0.25;
+    let mut faces = Vec::new();
+    for v in &[[0, 1, 2], [0, 3, 1], [0, 2, 3], [1, 3, 2]] {
+        faces.push(Face::new(&vertices, *v, center)?);
+    }
+
+    for _ in 0..MAX_ITERATIONS {
+        let closest = nearest(&faces);
+        let (normal, distance) = (faces[closest].normal, faces[closest].distance);
+        let s = vertex(lhs, rhs, normal);
+        if s.w.dot(normal) - distance < 1e-4 {
+            break;
+        }
+
+        // Removes the faces which could be seen from the new vertex, and connects the
+        // edges on horizon to it.
+        let mut horizon: Vec<(usize, usize)> = Vec::new();
+        faces.retain(|f| {
+            if f.normal.dot(s.w - vertices[f.v[0]].w) <= EPSILON {
+                return true;
+            }
+
+            for &(i, j) in &[(f.v[0], f.v[1]), (f.v[1], f.v[2]), (f.v[2], f.v[0])] {
+                if let Some(index) = horizon.iter().position(|&e| e == (j, i)) {
+                    horizon.swap_remove(index);
+                } else {
+                    horizon.push((i, j));
+                }
+            }
+
+            false
+        });
+
+        vertices.push(s);
+        let index = vertices.len() - 1;
+        for (i, j) in horizon {
+            if let Some(face) = Face::new(&vertices, [i, j, index], center) {
+                faces.push(face);
+            }
+        }
+
+        if faces.is_empty() {
+            return None;
+        }
+    }
+
+    let face = &faces[nearest(&faces)];
+    let (p0, p1, p2) = (
+        vertices[face.v[0]],
+        vertices[face.v[1]],
+        vertices[face.v[2]],
+    );
+
+    let t = barycentric(face.normal * face.distance, p0.w, p1.w, p2.w);
+    Some(Closest {
+        a: p0.a * t[0] + p1.a * t[1] + p2.a * t[2],
+        b: p0.b * t[0] + p1.b * t[1] + p2.b * t[2],
+        normal: face.normal,
+        distance: -face.distance,
+    })
+}
+
+fn nearest(faces: &[Face]) -> usize {
+    let mut nearest = 0;
+    for (i, v) in faces.iter().enumerate() {
+        if v.distance < faces[nearest].distance {
+            nearest = i;
+        }
+    }
+
+    nearest
+}
+
+// Grows the simplex into a tetrahedron, which is required by EPA.
+fn blow_up(lhs: &Convex, rhs: &Convex, vertices: &mut Vec<Vertex>) -> bool {
+    let axes = [
+        math::Vector3::unit_x(),
+        math::Vector3::unit_y(),
+        math::Vector3::unit_z(),
+    ];
+
+    if vertices.len() == 1 {
+        for &axis in &axes {
+            for &dir in &[axis, -axis] {
+                let s = vertex(lhs, rhs, dir);
+                if vertices.len() == 1 && (s.w - vertices[0].w).magnitude2() > EPSILON {
+                    vertices.push(s);
+                }
+            }
+        }
+    }
+
+    if vertices.len() == 2 {
+        let d = vertices[1].w - vertices[0].w;
+        for &axis in &axes {
+            let n = d.cross(axis);
+            for &dir in &[n, -n, n.cross(d), -n.cross(d)] {
+                let s = vertex(lhs, rhs, dir);
+                if vertices.len() == 2 && d.cross(s.w - vertices[0].w).magnitude2() > EPSILON {
+                    vertices.push(s);
+                }
+            }
+        }
+    }
+
+    if vertices.len() == 3 {
+        let a = vertices[0].w;
+        let n = (vertices[1].w - a).cross(vertices[2].w - a);
+        for &dir in &[n, -n] {
+            let s = vertex(lhs, rhs, dir);
+            if vertices.len() == 3 && n.dot(s.w - a).abs() > EPSILON {
+                vertices.push(s);
+            }
+        }
+    }
+
+    vertices.len() == 4
+}
+
+fn barycentric(
+    p: math::Vector3<f32>,
+    a: math::Vector3<f32>,
+    b: math::Vector3<f32>,
+    c: math::Vector3<f32>,
+) -> [f32; 3] {
+    let (v0, v1, v2) = (b - a, c - a, p - a);
+    let (d00, d01, d11) = (v0.dot(v0), v0.dot(v1), v1.dot(v1));
+    let (d20, d21) = (v2.dot(v0), v2.dot(v1));
+
+    let denom = d00 * d11 - d01 * d01;
+    if denom.abs() < EPSILON * EPSILON {
+        return [1.0, 0.0, 0.0];
+    }
+
+    let v = (d11 * d20 - d01 * d21) / denom;
+    let w = (d00 * d21 - d01 * d20) / denom;
+    [1.0 - v - w, v, w]
+}
+
+#[inline]
+fn zero() -> math::Vector3<f32> {
+    math::Vector3::new(0.0, 0.0, 0.0)
+}
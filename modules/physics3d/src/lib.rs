@@ -0,0 +1,79 @@
+//! A minimal rigid body physics module for `crayon-3d`.
+//!
+//! The `RigidBody`s are simulated as spheres, boxes, capsules or convex hulls. The
+//! contacts are found with GJK and EPA, kept in persistent manifolds, and resolved by
+//! a sequential impulse solver with friction and restitution.
+//!
+//! `PhysicsWorld` steps the simulation with a fixed timestep, and syncs the transforms
+//! of bodies with the `SceneGraph` of world:
+//!
+//! ```rust,ignore
+//! use crayon_physics3d::prelude::*;
+//!
+//! let mut physics = PhysicsWorld::new(PhysicsParams::default());
+//! let body = RigidBody::new(BodyKind::Dynamic, Shape::Box([0.5, 0.5, 0.5].into()));
+//! physics.add(&world.scene, ent, body)?;
+//! world.colliders.add(floor, Collider::Box([10.0, 0.5, 10.0].into()));
+//!
+//! // In `Application::on_fixed_update`.
+//! physics.advance(&ctx.sched, &mut world.scene, &world.colliders, ctx.time.fixed_timestep());
+//! world.controllers.advance(&mut world.scene, &world.colliders, ctx.time.fixed_timestep());
+//! world.triggers.advance(&world.scene);
+//!
+//! for v in physics.take_events() {
+//!     if v.phase == CollisionPhase::Enter {
+//!         info!("{:?} hits {:?}.", v.lhs, v.rhs);
+//!     }
+//! }
+//!
+//! let ground = physics.raycast(position, [0.0, -1.0, 0.0], 10.0, !0);
+//! ```
+//!
+//! A dynamic body could be driven as car by `Vehicles`, whose wheels are suspension
+//! rays with friction curves.
+//!
+//! # Working with `crayon_3d::physics`
+//!
+//! The `Collider`s of world are the shared static geometry. They are simulated as static
+//! bodies, so the same floors and walls block both the rigid bodies and the character
+//! controllers. An entity with both a `RigidBody` and `Collider` is simulated with its
+//! rigid body, while the controllers still slide against its collider, e.g. a moving
+//! platform with a kinematic body.
+//!
+//! The `TriggerVolume`s read the transforms from scene, so they see the bodies moved by
+//! `PhysicsWorld::advance` if it is called before `Triggers::advance`. The controllers
+//! are not seen by the rigid bodies, a kinematic capsule body could be added to the
+//! entity of controller to push the dynamic bodies.
+//!
+//! The bodies are stored as components of entities, which should be removed with
+//! `PhysicsWorld::remove` when their entities are deleted from world. Both
+//! `PhysicsWorld` and `Vehicles` are `EntityStore`s, which could be passed to
+//! `World::compact_with` to be remapped along with the world.
+
+extern crate crayon;
+extern crate crayon_3d;
+#[macro_use]
+extern crate failure;
+
+pub mod body;
+pub mod shape;
+pub mod vehicle;
+pub mod world;
+
+mod contact;
+mod gjk;
+mod solver;
+
+pub use self::body::{BodyKind, RigidBody};
+pub use self::shape::{ConvexHull, Shape};
+pub use self::vehicle::{FrictionCurve, Vehicle, Vehicles, Wheel, WheelHit};
+pub use self::world::{
+    CollisionContact, CollisionEvent, CollisionPhase, PhysicsParams, PhysicsWorld, RaycastHit,
+};
+
+pub mod prelude {
+    pub use body::{BodyKind, RigidBody};
+    pub use shape::Shape;
+    pub use vehicle::{Vehicle, Vehicles, Wheel};
+    pub use world::{CollisionPhase, PhysicsParams, PhysicsWorld};
+}
@@ -0,0 +1,149 @@
+//! The collision shapes of rigid bodies.
+
+use std::sync::Arc;
+
+use crayon::errors::*;
+use crayon::math::{self, InnerSpace};
+
+/// The margin of boxes and convex hulls. The boxes are shrunk by it and the convex
+/// hulls are inflated by it, so their edges are rounded and the contacts are stable.
+pub const MARGIN: f32 = 0.02;
+
+/// The shape of rigid body, which is centered at the world transform of entity. The
+/// scale of entity is not applied.
+#[derive(Debug, Clone)]
+pub enum Shape {
+    /// A sphere with the radius.
+    Sphere(f32),
+    /// An oriented box with the half extents.
+    Box(math::Vector3<f32>),
+    /// A capsule along the up axis of entity, the height includes the hemispheres.
+    Capsule { radius: f32, height: f32 },
+    /// A convex hull, which could be shared between bodies.
+    ConvexHull(Arc<ConvexHull>),
+}
+
+/// The convex hull of points in the space of entity.
+#[derive(Debug, Clone)]
+pub struct ConvexHull {
+    points: Vec<math::Vector3<f32>>,
+}
+
+impl ConvexHull {
+    pub fn new<T>(points: &[T]) -> Result<Self>
+    where
+        T: Into<math::Vector3<f32>> + Copy,
+    {
+        if points.len() < 4 {
+            bail!(
+                "A convex hull needs at least 4 points, but got {}.",
+                points.len()
+            );
+        }
+
+        Ok(ConvexHull {
+            points: points.iter().map(|&v| v.into()).collect(),
+        })
+    }
+
+    #[inline]
+    pub fn points(&self) -> &[math::Vector3<f32>] {
+        &self.points
+    }
+}
+
+impl Shape {
+    /// Gets the radius of rounded surface around the core of shape.
+    pub(crate) fn margin(&self) -> f32 {
+        match *self {
+            Shape::Sphere(radius) | Shape::Capsule { radius, .. } => radius.max(0.0),
+            Shape::Box(extents) => {
+                let min = extents.x.min(extents.y).min(extents.z).max(0.0);
+                MARGIN.min(min * 0.5)
+            }
+            Shape::ConvexHull(_) => MARGIN,
+        }
+    }
+
+    /// Gets the farthest point of the core of shape along `dir` in local space.
+    pub(crate) fn support(&self, dir: math::Vector3<f32>) -> math::Vector3<f32> {
+        let sign = |v: f32, extent: f32| if v < 0.0 { -extent } else { extent };
+
+        match *self {
+            Shape::Sphere(_) => math::Vector3::new(0.0, 0.0, 0.0),
+            Shape::Capsule { radius, height } => {
+                let half = (height * 0.5 - radius).max(0.0);
+                math::Vector3::new(0.0, sign(dir.y, half), 0.0)
+            }
+            Shape::Box(extents) => {
+                let m = self.margin();
+                math::Vector3::new(
+                    sign(dir.x, (extents.x - m).max(0.0)),
+                    sign(dir.y, (extents.y - m).max(0.0)),
+                    sign(dir.z, (extents.z - m).max(0.0)),
+                )
+            }
+            Shape::ConvexHull(ref hull) => {
+                let mut support = hull.points[0];
+                let mut max = support.dot(dir);
+                for &v in &hull.points[1..] {
+                    let d = v.dot(dir);
+                    if d > max {
+                        max = d;
+                        support = v;
+                    }
+                }
+
+                support
+            }
+        }
+    }
+
+    /// Gets the radius of bounding sphere.
+    pub(crate) fn radius(&self) -> f32 {
+        match *self {
+            Shape::Sphere(radius) => radius,
+            Shape::Capsule { radius, height } => (height * 0.5).max(radius),
+            Shape::Box(extents) => extents.magnitude(),
+            Shape::ConvexHull(ref hull) => {
+                let max = hull
+                    .points
+                    .iter()
+                    .fold(0.0f32, |a, v| a.max(v.magnitude2()));
+                max.sqrt() + MARGIN
+            }
+        }
+    }
+
+    /// Gets the principal moments of inertia with `mass`. The convex hulls are
+    /// approximated with their bounding boxes.
+    pub(crate) fn inertia(&self, mass: f32) -> math::Vector3<f32> {
+        let cuboid = |e: math::Vector3<f32>| {
+            let (x, y, z) = (e.x * e.x * 4.0, e.y * e.y * 4.0, e.z * e.z * 4.0);
+            math::Vector3::new(y + z, x + z, x + y) * (mass / 12.0)
+        };
+
+        match *self {
+            Shape::Sphere(radius) => {
+                let v = 0.4 * mass * radius * radius;
+                math::Vector3::new(v, v, v)
+            }
+            Shape::Box(extents) => cuboid(extents),
+            Shape::Capsule { radius, height } => {
+                let (r2, h2) = (radius * radius, height * height);
+                let v = mass * (3.0 * r2 + h2) / 12.0;
+                math::Vector3::new(v, 0.5 * mass * r2, v)
+            }
+            Shape::ConvexHull(ref hull) => {
+                let mut extents = math::Vector3::new(0.0, 0.0, 0.0);
+                for v in &hull.points {
+                    extents.x = extents.x.max(v.x.abs());
+                    extents.y = extents.y.max(v.y.abs());
+                    extents.z = extents.z.max(v.z.abs());
+                }
+
+                cuboid(extents + math::Vector3::new(MARGIN, MARGIN, MARGIN))
+            }
+        }
+    }
+}
@@ -0,0 +1,180 @@
+// The sequential impulse solver of contacts.
+
+use crayon::math::{self, InnerSpace};
+
+use contact::{self, Manifold};
+use gjk::Pose;
+
+// The ratio of penetration which is resolved per second.
+const BAUMGARTE: f32 = 0.2;
+// The penetration which is allowed to keep the contacts persistent.
+const SLOP: f32 = 0.005;
+// The minimum approaching speed which bounces.
+const BOUNCE_THRESHOLD: f32 = 1.0;
+
+/// The state of body during solving.
+#[derive(Debug, Clone, Copy)]
+pub struct SolverBody {
+    pub pose: Pose,
+    pub linear: math::Vector3<f32>,
+    pub angular: math::Vector3<f32>,
+    pub inverse_mass: f32,
+    pub inverse_inertia: math::Vector3<f32>,
+    pub friction: f32,
+    pub restitution: f32,
+}
+
+impl SolverBody {
+    /// Multiplies `v` with the inverse inertia tensor in world space.
+    pub fn inverse_inertia_world(&self, v: math::Vector3<f32>) -> math::Vector3<f32> {
+        let rotation = self.pose.rotation;
+        let local = rotation.conjugate() * v;
+        let i = self.inverse_inertia;
+        rotation * math::Vector3::new(local.x * i.x, local.y * i.y, local.z * i.z)
+    }
+
+    fn apply(&mut self, impulse: math::Vector3<f32>, r: math::Vector3<f32>) {
+        self.linear += impulse * self.inverse_mass;
+        self.angular += self.inverse_inertia_world(r.cross(impulse));
+    }
+}
+
+struct Constraint {
+    a: usize,
+    b: usize,
+    manifold: usize,
+    point: usize,
+    ra: math::Vector3<f32>,
+    rb: math::Vector3<f32>,
+    normal: math::Vector3<f32>,
+    tangents: [math::Vector3<f32>; 2],
+    normal_mass: f32,
+    tangent_mass: [f32; 2],
+    bias: f32,
+    friction: f32,
+    impulse: f32,
+    friction_impulse: [f32; 2],
+}
+
+impl Constraint {
+    fn velocity(&self, bodies: &[SolverBody]) -> math::Vector3<f32> {
+        let (a, b) = (&bodies[self.a], &bodies[self.b]);
+        (b.linear + b.angular.cross(self.rb)) - (a.linear + a.angular.cross(self.ra))
+    }
+
+    fn apply(&self, bodies: &mut [SolverBody], impulse: math::Vector3<f32>) {
+        bodies[self.a].apply(-impulse, self.ra);
+        bodies[self.b].apply(impulse, self.rb);
+    }
+
+    // Clamps the friction along the tangent by the normal impulse.
+    fn solve_friction(&mut self, bodies: &mut [SolverBody], k: usize) {
+        let limit = self.friction * self.impulse;
+        let vt = self.velocity(bodies).dot(self.tangents[k]);
+        let old = self.friction_impulse[k];
+        self.friction_impulse[k] = (old - vt * self.tangent_mass[k]).max(-limit).min(limit);
+
+        let impulse = self.tangents[k] * (self.friction_impulse[k] - old);
+        self.apply(bodies, impulse);
+    }
+}
+
+/// Solves the velocities of bodies with the contacts in `manifolds`, which are the
+/// indices of bodies and their manifold. The accumulated impulses are stored back
+/// into manifolds to warm start the next step.
+pub fn solve(
+    bodies: &mut [SolverBody],
+    manifolds: &mut [(usize, usize, &mut Manifold)],
+    dt: f32,
+    iterations: usize,
+) {
+    let mut constraints = Vec::new();
+
+    for (i, v) in manifolds.iter().enumerate() {
+        let (a, b) = (&bodies[v.0], &bodies[v.1]);
+        let friction = (a.friction * b.friction).max(0.0).sqrt();
+        let restitution = a.restitution.max(b.restitution);
+
+        for (j, p) in v.2.points.iter().enumerate() {
+            let ra = p.a - a.pose.position;
+            let rb = p.b - b.pose.position;
+            let n = p.normal;
+            let (t1, t2) = contact::tangents(n);
+
+            let mass = |d: math::Vector3<f32>| {
+                let (ca, cb) = (ra.cross(d), rb.cross(d));
+                let k = a.inverse_mass
+                    + b.inverse_mass
+                    + ca.dot(a.inverse_inertia_world(ca))
+                    + cb.dot(b.inverse_inertia_world(cb));
+
+                if k > 0.0 {
+                    1.0 / k
+                } else {
+                    0.0
+                }
+            };
+
+            let mut bias = if p.depth > SLOP {
+                BAUMGARTE / dt * (p.depth - SLOP)
+            } else if p.depth < 0.0 {
+                // Allows approaching until the gap is closed.
+                p.depth / dt
+            } else {
+                0.0
+            };
+
+            let approaching =
+                ((b.linear + b.angular.cross(rb)) - (a.linear + a.angular.cross(ra))).dot(n);
+
+            if restitution > 0.0 && -approaching > BOUNCE_THRESHOLD {
+                bias = bias.max(-restitution * approaching);
+            }
+
+            constraints.push(Constraint {
+                a: v.0,
+                b: v.1,
+                manifold: i,
+                point: j,
+                ra: ra,
+                rb: rb,
+                normal: n,
+                tangents: [t1, t2],
+                normal_mass: mass(n),
+                tangent_mass: [mass(t1), mass(t2)],
+                bias: bias,
+                friction: friction,
+                impulse: p.impulse,
+                friction_impulse: p.tangent_impulse,
+            });
+        }
+    }
+
+    // Warm starts with the impulses of last step.
+    for c in &constraints {
+        let impulse = c.normal * c.impulse
+            + c.tangents[0] * c.friction_impulse[0]
+            + c.tangents[1] * c.friction_impulse[1];
+        c.apply(bodies, impulse);
+    }
+
+    for _ in 0..iterations {
+        for c in &mut constraints {
+            c.solve_friction(bodies, 0);
+            c.solve_friction(bodies, 1);
+
+            let vn = c.velocity(bodies).dot(c.normal);
+            let old = c.impulse;
+            c.impulse = (old + (c.bias - vn) * c.normal_mass).max(0.0);
+
+            let impulse = c.normal * (c.impulse - old);
+            c.apply(bodies, impulse);
+        }
+    }
+
+    for c in &constraints {
+        let point = &mut manifolds[c.manifold].2.points[c.point];
+        point.impulse = c.impulse;
+        point.tangent_impulse = c.friction_impulse;
+    }
+}
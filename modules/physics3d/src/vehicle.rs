@@ -0,0 +1,414 @@
+//! Raycast vehicles, which hold the chassis bodies up with suspension rays and drive
+//! them with the friction of wheels.
+//!
+//! The chassis is a dynamic `RigidBody`, whose forward direction is the positive
+//! z-axis. The wheels are not simulated as bodies, they only push the chassis at the
+//! points where their suspension rays hit the ground.
+
+use std::collections::HashMap;
+use std::time::Duration;
+
+use crayon::errors::*;
+use crayon::math::{self, InnerSpace, Rotation3};
+use crayon_3d::scene::SceneGraph;
+use crayon_3d::{Entity, EntityRemap, EntityStore};
+
+use body::BodyKind;
+use world::PhysicsWorld;
+
+/// The friction coefficient of tyre as a function of the slip speed between tyre and
+/// ground. It rises from zero to the extremum, then falls to the asymptote, where the
+/// tyre skids.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct FrictionCurve {
+    pub extremum_slip: f32,
+    pub extremum_value: f32,
+    pub asymptote_slip: f32,
+    pub asymptote_value: f32,
+}
+
+impl FrictionCurve {
+    /// Evaluates the friction coefficient at slip speed in units per second.
+    pub fn evaluate(&self, slip: f32) -> f32 {
+        let slip = slip.abs();
+
+        if slip <= self.extremum_slip {
+            if self.extremum_slip > 0.0 {
+                self.extremum_value * slip / self.extremum_slip
+            } else {
+                self.extremum_value
+            }
+        } else if slip < self.asymptote_slip {
+            let t = (slip - self.extremum_slip) / (self.asymptote_slip - self.extremum_slip);
+            self.extremum_value + (self.asymptote_value - self.extremum_value) * t
+        } else {
+            self.asymptote_value
+        }
+    }
+}
+
+/// The ground under wheel.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct WheelHit {
+    pub ent: Entity,
+    pub point: math::Vector3<f32>,
+    pub normal: math::Vector3<f32>,
+    /// The force of suspension in newtons.
+    pub force: f32,
+}
+
+/// A wheel of vehicle, whose suspension casts a ray down from `anchor`.
+#[derive(Debug, Clone)]
+pub struct Wheel {
+    /// The top of suspension in the space of chassis.
+    pub anchor: math::Vector3<f32>,
+    pub radius: f32,
+    /// The mass of wheel in kilograms, which resists the spinning.
+    pub mass: f32,
+    /// The maximum length of suspension between the anchor and wheel center.
+    pub suspension_length: f32,
+    /// The stiffness of suspension spring in newtons per unit.
+    pub stiffness: f32,
+    /// The damping of suspension in newtons per unit per second.
+    pub damping: f32,
+    /// Turns with `Vehicle::steer`.
+    pub steering: bool,
+    /// Driven by `Vehicle::throttle`.
+    pub powered: bool,
+    pub forward_friction: FrictionCurve,
+    pub sideways_friction: FrictionCurve,
+    /// The entity of wheel mesh, whose transform is written by `Vehicles::advance`.
+    pub mesh: Option<Entity>,
+    length: f32,
+    steer_angle: f32,
+    angular_velocity: f32,
+    spin: f32,
+    hit: Option<WheelHit>,
+}
+
+impl Wheel {
+    pub fn new<T>(anchor: T, radius: f32) -> Self
+    where
+        T: Into<math::Vector3<f32>>,
+    {
+        Wheel {
+            anchor: anchor.into(),
+            radius: radius,
+            mass: 20.0,
+            suspension_length: 0.3,
+            stiffness: 20000.0,
+            damping: 2000.0,
+            steering: false,
+            powered: false,
+            forward_friction: FrictionCurve {
+                extremum_slip: 0.4,
+                extremum_value: 1.0,
+                asymptote_slip: 0.8,
+                asymptote_value: 0.5,
+            },
+            sideways_friction: FrictionCurve {
+                extremum_slip: 0.2,
+                extremum_value: 1.0,
+                asymptote_slip: 0.5,
+                asymptote_value: 0.75,
+            },
+            mesh: None,
+            length: 0.3,
+            steer_angle: 0.0,
+            angular_velocity: 0.0,
+            spin: 0.0,
+            hit: None,
+        }
+    }
+
+    /// Gets the ground under wheel after last advance.
+    #[inline]
+    pub fn hit(&self) -> Option<&WheelHit> {
+        self.hit.as_ref()
+    }
+
+    #[inline]
+    pub fn is_grounded(&self) -> bool {
+        self.hit.is_some()
+    }
+
+    /// Gets the length which the suspension is compressed by.
+    #[inline]
+    pub fn compression(&self) -> f32 {
+        self.suspension_length - self.length
+    }
+
+    /// Gets the steering angle in radians.
+    #[inline]
+    pub fn steer_angle(&self) -> f32 {
+        self.steer_angle
+    }
+
+    /// Gets the spinning speed in radians per second.
+    #[inline]
+    pub fn angular_velocity(&self) -> f32 {
+        self.angular_velocity
+    }
+
+    /// Gets the position and rotation of wheel center in the space of chassis.
+    pub fn local_transform(&self) -> (math::Vector3<f32>, math::Quaternion<f32>) {
+        let position = self.anchor - math::Vector3::unit_y() * self.length;
+        let steer = math::Quaternion::from_angle_y(math::Rad(self.steer_angle));
+        let spin = math::Quaternion::from_angle_x(math::Rad(self.spin));
+        (position, steer * spin)
+    }
+}
+
+/// `Vehicle` drives the rigid body of entity with its wheels.
+#[derive(Debug, Clone)]
+pub struct Vehicle {
+    pub wheels: Vec<Wheel>,
+    /// The maximum torque in newton meters, which is shared by the powered wheels.
+    pub motor_torque: f32,
+    /// The maximum braking torque of every wheel in newton meters.
+    pub brake_torque: f32,
+    /// The maximum steering angle in radians.
+    pub max_steer_angle: f32,
+    /// The layers which the suspension rays hit.
+    pub mask: u32,
+    /// The input between -1 (reverse) and 1 (forward).
+    pub throttle: f32,
+    /// The input between 0 and 1.
+    pub brake: f32,
+    /// The input between -1 (left) and 1 (right).
+    pub steer: f32,
+}
+
+impl Vehicle {
+    pub fn new(wheels: Vec<Wheel>) -> Self {
+        Vehicle {
+            wheels: wheels,
+            motor_torque: 1000.0,
+            brake_torque: 3000.0,
+            max_steer_angle: ::std::f32::consts::PI / 6.0,
+            mask: !0,
+            throttle: 0.0,
+            brake: 0.0,
+            steer: 0.0,
+        }
+    }
+
+    // Computes the forces of wheels, and applies them to the chassis of `ent`.
+    fn advance(&mut self, physics: &mut PhysicsWorld, scene: &SceneGraph, ent: Entity, dt: f32) {
+        let transform = match scene.transform(ent) {
+            Some(v) => v,
+            None => return,
+        };
+
+        let (mass, linear, angular) = match physics.get(ent) {
+            Some(v) if v.kind == BodyKind::Dynamic => {
+                (v.mass, v.linear_velocity, v.angular_velocity)
+            }
+            _ => return,
+        };
+
+        let up = transform.rotation * math::Vector3::unit_y();
+        let throttle = self.throttle.max(-1.0).min(1.0);
+        let brake = self.brake.max(0.0).min(1.0);
+        let steer = self.steer.max(-1.0).min(1.0) * self.max_steer_angle;
+
+        let hits: Vec<_> = self
+            .wheels
+            .iter()
+            .map(|v| {
+                let origin = transform.transform_point(v.anchor);
+                let length = v.suspension_length + v.radius;
+                physics.raycast_except(origin, -up, length, self.mask, Some(ent))
+            })
+            .collect();
+
+        let grounded = hits.iter().filter(|v| v.is_some()).count().max(1);
+        let powered = self.wheels.iter().filter(|v| v.powered).count().max(1);
+        let share = mass / grounded as f32;
+
+        let mut impulses = Vec::new();
+        for (wheel, hit) in self.wheels.iter_mut().zip(hits) {
+            let radius = wheel.radius.max(1e-3);
+            let inertia = (0.5 * wheel.mass * radius * radius).max(1e-3);
+
+            let drive = if wheel.powered {
+                throttle * self.motor_torque / powered as f32
+            } else {
+                0.0
+            };
+
+            wheel.steer_angle = if wheel.steering { steer } else { 0.0 };
+            let previous = wheel.length;
+
+            wheel.hit = None;
+            if let Some(hit) = hit {
+                wheel.length = (hit.distance - radius)
+                    .max(0.0)
+                    .min(wheel.suspension_length);
+
+                let compression = wheel.suspension_length - wheel.length;
+                let speed = (previous - wheel.length) / dt;
+                let load = (wheel.stiffness * compression + wheel.damping * speed).max(0.0);
+
+                // Projects the heading of wheel onto the ground.
+                let n = hit.normal;
+                let steer = math::Quaternion::from_angle_y(math::Rad(wheel.steer_angle));
+                let heading = transform.rotation * (steer * math::Vector3::unit_z());
+                let forward = heading - n * heading.dot(n);
+                let forward = if forward.magnitude2() > 1e-6 {
+                    forward.normalize()
+                } else {
+                    heading
+                };
+
+                let side = n.cross(forward);
+                let velocity = linear + angular.cross(hit.point - transform.position);
+                let (vf, vs) = (velocity.dot(forward), velocity.dot(side));
+
+                // The sideways friction is limited to stop the sliding in this step.
+                let limit = share * vs.abs() / dt;
+                let fy = -vs.signum() * (wheel.sideways_friction.evaluate(vs) * load).min(limit);
+
+                // The forward friction is limited to stop the spinning against ground.
+                let slip = wheel.angular_velocity * radius - vf;
+                let limit = slip.abs() * inertia / (radius * radius * dt);
+                let fx = slip.signum() * (wheel.forward_friction.evaluate(slip) * load).min(limit);
+                wheel.angular_velocity -= fx * radius / inertia * dt;
+
+                let force = up * load + forward * fx + side * fy;
+                impulses.push((hit.ent, force * dt, hit.point));
+
+                wheel.hit = Some(WheelHit {
+                    ent: hit.ent,
+                    point: hit.point,
+                    normal: hit.normal,
+                    force: load,
+                });
+            } else {
+                wheel.length = wheel.suspension_length;
+            }
+
+            wheel.angular_velocity += drive / inertia * dt;
+
+            let braking =
+                (brake * self.brake_torque / inertia * dt).min(wheel.angular_velocity.abs());
+            wheel.angular_velocity -= wheel.angular_velocity.signum() * braking;
+
+            let tau = 2.0 * ::std::f32::consts::PI;
+            wheel.spin = (wheel.spin + wheel.angular_velocity * dt) % tau;
+        }
+
+        for (ground, impulse, point) in impulses {
+            // The impulses are ignored by the bodies which are not dynamic, and the
+            // wheels whose bodies have been removed are skipped.
+            if physics.apply_impulse(ent, impulse, point).is_ok() {
+                let _ = physics.apply_impulse(ground, -impulse, point);
+            }
+        }
+    }
+}
+
+/// The collection of `Vehicle`s.
+pub struct Vehicles {
+    remap: HashMap<Entity, usize>,
+    entities: Vec<Entity>,
+    vehicles: Vec<Vehicle>,
+}
+
+impl Vehicles {
+    pub fn new() -> Self {
+        Vehicles {
+            remap: HashMap::new(),
+            entities: Vec::new(),
+            vehicles: Vec::new(),
+        }
+    }
+
+    /// Adds the vehicle to entity, which should have a dynamic rigid body as chassis.
+    pub fn add(&mut self, physics: &PhysicsWorld, ent: Entity, vehicle: Vehicle) -> Result<()> {
+        match physics.get(ent) {
+            Some(v) if v.kind == BodyKind::Dynamic => {}
+            _ => bail!("{:?} does not have a dynamic rigid body.", ent),
+        }
+
+        if let Some(&index) = self.remap.get(&ent) {
+            self.vehicles[index] = vehicle;
+        } else {
+            self.remap.insert(ent, self.vehicles.len());
+            self.entities.push(ent);
+            self.vehicles.push(vehicle);
+        }
+
+        Ok(())
+    }
+
+    pub fn remove(&mut self, ent: Entity) {
+        if let Some(index) = self.remap.remove(&ent) {
+            self.entities.swap_remove(index);
+            self.vehicles.swap_remove(index);
+
+            if index < self.entities.len() {
+                self.remap.insert(self.entities[index], index);
+            }
+        }
+    }
+
+    #[inline]
+    pub fn get(&self, ent: Entity) -> Option<&Vehicle> {
+        self.remap.get(&ent).map(|&index| &self.vehicles[index])
+    }
+
+    #[inline]
+    pub fn get_mut(&mut self, ent: Entity) -> Option<&mut Vehicle> {
+        let vehicles = &mut self.vehicles;
+        self.remap.get(&ent).map(move |&index| &mut vehicles[index])
+    }
+
+    /// Replaces the entities of chassis and wheel meshes with the new ones in `remap`.
+    pub fn remap_entities(&mut self, remap: &EntityRemap) {
+        let map = |v: Entity| remap.get(v).unwrap_or(v);
+
+        self.remap.clear();
+        for (i, (ent, v)) in self.entities.iter_mut().zip(&mut self.vehicles).enumerate() {
+            *ent = map(*ent);
+            self.remap.insert(*ent, i);
+
+            for wheel in &mut v.wheels {
+                wheel.mesh = wheel.mesh.map(&map);
+            }
+        }
+    }
+
+    /// Applies the forces of wheels to chassis, and writes the transforms of wheel
+    /// meshes. This should be called in `Application::on_fixed_update` right before
+    /// `PhysicsWorld::advance` with the same fixed timestep.
+    pub fn advance(&mut self, physics: &mut PhysicsWorld, scene: &mut SceneGraph, delta: Duration) {
+        let dt = delta.as_secs() as f32 + delta.subsec_nanos() as f32 * 1e-9;
+        if dt <= 0.0 {
+            return;
+        }
+
+        for (&ent, v) in self.entities.iter().zip(self.vehicles.iter_mut()) {
+            v.advance(physics, scene, ent, dt);
+
+            let transform = match scene.transform(ent) {
+                Some(transform) => transform,
+                None => continue,
+            };
+
+            for wheel in &v.wheels {
+                if let Some(mesh) = wheel.mesh {
+                    let (position, rotation) = wheel.local_transform();
+                    scene.set_position(mesh, transform.transform_point(position));
+                    scene.set_rotation(mesh, transform.rotation * rotation);
+                }
+            }
+        }
+    }
+}
+
+impl EntityStore for Vehicles {
+    fn remap_entities(&mut self, remap: &EntityRemap) {
+        Vehicles::remap_entities(self, remap);
+    }
+}
@@ -0,0 +1,748 @@
+//! The world of rigid bodies, which steps the simulation with a fixed timestep.
+
+use std::collections::BTreeMap;
+use std::mem;
+use std::time::Duration;
+
+use crayon::errors::*;
+use crayon::math::{self, InnerSpace, One};
+use crayon::sched::ScheduleSystemShared;
+use crayon_3d::physics::{Collider, Colliders};
+use crayon_3d::scene::SceneGraph;
+use crayon_3d::{Component, Entity, EntityRemap, EntityStore};
+
+use body::{BodyKind, RigidBody};
+use contact::{self, Candidate, Manifold};
+use gjk::{self, Convex, Pose};
+use shape::Shape;
+use solver::{self, SolverBody};
+
+// The number of pairs tested by every job in narrowphase.
+const PAIRS_PER_JOB: usize = 64;
+// The maximum iterations when casting rays against bodies.
+const MAX_RAY_ITERATIONS: usize = 64;
+
+/// The setup parameters of `PhysicsWorld`.
+#[derive(Debug, Clone, Copy)]
+pub struct PhysicsParams {
+    /// The acceleration of gravity in units per second squared.
+    pub gravity: math::Vector3<f32>,
+    /// The fixed timestep of simulation.
+    pub timestep: Duration,
+    /// The maximum steps per `advance`, the remaining time is dropped to catch up.
+    pub max_steps: usize,
+    /// The velocity iterations of solver per step.
+    pub iterations: usize,
+}
+
+impl Default for PhysicsParams {
+    fn default() -> Self {
+        PhysicsParams {
+            gravity: math::Vector3::new(0.0, -9.81, 0.0),
+            timestep: Duration::new(0, 16_666_667),
+            max_steps: 4,
+            iterations: 10,
+        }
+    }
+}
+
+/// The phase of collision.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CollisionPhase {
+    /// The bodies start to touch.
+    Enter,
+    /// The bodies are still touching.
+    Stay,
+    /// The bodies stop touching, or one of them has been removed.
+    Exit,
+}
+
+/// The deepest contact between two bodies.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct CollisionContact {
+    pub point: math::Vector3<f32>,
+    /// The direction which pushes `lhs` out of `rhs`.
+    pub normal: math::Vector3<f32>,
+    pub depth: f32,
+    /// The total impulse applied between bodies during the step.
+    pub impulse: f32,
+}
+
+/// The collision event between two bodies, the `lhs` always comes before `rhs`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct CollisionEvent {
+    pub phase: CollisionPhase,
+    pub lhs: Entity,
+    pub rhs: Entity,
+    /// The contact of touching bodies, which is `None` for `CollisionPhase::Exit`.
+    pub contact: Option<CollisionContact>,
+}
+
+/// The closest intersection of ray.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RaycastHit {
+    pub ent: Entity,
+    pub point: math::Vector3<f32>,
+    pub normal: math::Vector3<f32>,
+    pub distance: f32,
+}
+
+struct Body {
+    desc: RigidBody,
+    pose: Pose,
+    // The pose written to scene by last `advance`, which is used to find teleports.
+    synced: Option<Pose>,
+    // The pose of kinematic body which is reached at the end of steps.
+    target: Option<Pose>,
+    // Whether the body mirrors a `Collider` of scene.
+    collider: bool,
+}
+
+impl Body {
+    #[inline]
+    fn convex(&self) -> Convex {
+        Convex {
+            shape: &self.desc.shape,
+            pose: self.pose,
+        }
+    }
+
+    // Gets the maximum distance which the surface moves during `dt`.
+    fn sweep(&self, dt: f32) -> f32 {
+        let v = &self.desc;
+        let radius = v.shape.radius();
+        (v.linear_velocity.magnitude() + v.angular_velocity.magnitude() * radius) * dt
+    }
+
+    fn bounds(&self, dt: f32) -> (math::Vector3<f32>, math::Vector3<f32>) {
+        let convex = self.convex();
+        let margin = self.desc.shape.margin() + contact::THRESHOLD + self.sweep(dt);
+
+        let axes = [
+            math::Vector3::unit_x(),
+            math::Vector3::unit_y(),
+            math::Vector3::unit_z(),
+        ];
+
+        let mut min = math::Vector3::new(0.0, 0.0, 0.0);
+        let mut max = math::Vector3::new(0.0, 0.0, 0.0);
+        for (i, &axis) in axes.iter().enumerate() {
+            min[i] = convex.support(-axis)[i] - margin;
+            max[i] = convex.support(axis)[i] + margin;
+        }
+
+        (min, max)
+    }
+}
+
+/// `PhysicsWorld` simulates the `RigidBody`s of entities, and syncs their transforms
+/// with `SceneGraph`. The `Collider`s of scene take part in the simulation as static
+/// bodies.
+pub struct PhysicsWorld {
+    params: PhysicsParams,
+    bodies: Component<Body>,
+    manifolds: BTreeMap<(Entity, Entity), Manifold>,
+    events: Vec<CollisionEvent>,
+    accumulator: f32,
+}
+
+impl PhysicsWorld {
+    pub fn new(params: PhysicsParams) -> Self {
+        PhysicsWorld {
+            params: params,
+            bodies: Component::new(),
+            manifolds: BTreeMap::new(),
+            events: Vec::new(),
+            accumulator: 0.0,
+        }
+    }
+
+    #[inline]
+    pub fn params(&self) -> &PhysicsParams {
+        &self.params
+    }
+
+    #[inline]
+    pub fn params_mut(&mut self) -> &mut PhysicsParams {
+        &mut self.params
+    }
+
+    /// Adds the body to entity at its current transform, or replaces the existing one.
+    /// The body takes the place of the `Collider` of entity in simulation.
+    pub fn add(&mut self, scene: &SceneGraph, ent: Entity, body: RigidBody) -> Result<()> {
+        let pose = match scene.transform(ent) {
+            Some(v) => Pose {
+                position: v.position,
+                rotation: v.rotation,
+            },
+            None => bail!("{:?} does not exist in scene.", ent),
+        };
+
+        let body = Body {
+            desc: body,
+            pose: pose,
+            synced: Some(pose),
+            target: None,
+            collider: false,
+        };
+
+        self.bodies.add(ent, body);
+        Ok(())
+    }
+
+    /// Removes the body of entity, and emits the exit events of its collisions.
+    pub fn remove(&mut self, ent: Entity) {
+        self.bodies.remove(ent);
+
+        let exits: Vec<_> = self
+            .manifolds
+            .keys()
+            .filter(|v| v.0 == ent || v.1 == ent)
+            .cloned()
+            .collect();
+
+        for (lhs, rhs) in exits {
+            self.manifolds.remove(&(lhs, rhs));
+            self.events.push(CollisionEvent {
+                phase: CollisionPhase::Exit,
+                lhs: lhs,
+                rhs: rhs,
+                contact: None,
+            });
+        }
+    }
+
+    /// Gets the body of entity. The static bodies of `Collider`s are included, and their
+    /// shapes are refreshed from colliders in every `advance`.
+    #[inline]
+    pub fn get(&self, ent: Entity) -> Option<&RigidBody> {
+        self.bodies.get(ent).map(|v| &v.desc)
+    }
+
+    #[inline]
+    pub fn get_mut(&mut self, ent: Entity) -> Option<&mut RigidBody> {
+        self.bodies.get_mut(ent).map(|v| &mut v.desc)
+    }
+
+    /// Replaces the entities with the new ones in `remap`. The pairs of contacts are
+    /// swapped if `lhs` comes after `rhs` afterwards.
+    pub fn remap_entities(&mut self, remap: &EntityRemap) {
+        let map = |v: Entity| remap.get(v).unwrap_or(v);
+
+        self.bodies.remap_entities(remap);
+
+        let manifolds = mem::replace(&mut self.manifolds, BTreeMap::new());
+        for ((lhs, rhs), mut manifold) in manifolds {
+            let (lhs, rhs) = (map(lhs), map(rhs));
+            if lhs < rhs {
+                self.manifolds.insert((lhs, rhs), manifold);
+            } else {
+                manifold.swap();
+                self.manifolds.insert((rhs, lhs), manifold);
+            }
+        }
+
+        for v in &mut self.events {
+            let (lhs, rhs) = (map(v.lhs), map(v.rhs));
+            if lhs < rhs {
+                v.lhs = lhs;
+                v.rhs = rhs;
+            } else {
+                v.lhs = rhs;
+                v.rhs = lhs;
+                if let Some(ref mut contact) = v.contact {
+                    contact.normal = -contact.normal;
+                }
+            }
+        }
+    }
+
+    /// Applies the `impulse` at `point` in world space to the dynamic body of entity.
+    pub fn apply_impulse<T1, T2>(&mut self, ent: Entity, impulse: T1, point: T2) -> Result<()>
+    where
+        T1: Into<math::Vector3<f32>>,
+        T2: Into<math::Vector3<f32>>,
+    {
+        let body = match self.bodies.get_mut(ent) {
+            Some(v) => v,
+            None => bail!("{:?} does not have a rigid body.", ent),
+        };
+
+        let state = solver_body(body);
+        let r = point.into() - body.pose.position;
+        let impulse = impulse.into();
+
+        body.desc.linear_velocity += impulse * state.inverse_mass;
+        body.desc.angular_velocity += state.inverse_inertia_world(r.cross(impulse));
+        Ok(())
+    }
+
+    /// Returns true if the bodies of two entities were touching in last step.
+    pub fn is_colliding(&self, lhs: Entity, rhs: Entity) -> bool {
+        let key = if lhs < rhs { (lhs, rhs) } else { (rhs, lhs) };
+        self.manifolds.contains_key(&key)
+    }
+
+    /// Takes the events emitted since last call.
+    #[inline]
+    pub fn take_events(&mut self) -> Vec<CollisionEvent> {
+        mem::replace(&mut self.events, Vec::new())
+    }
+
+    /// Casts a ray against the bodies whose `layers` match `mask`, and returns the
+    /// closest hit within `max_distance`. The bodies which contain `origin` are ignored.
+    pub fn raycast<T1, T2>(
+        &self,
+        origin: T1,
+        direction: T2,
+        max_distance: f32,
+        mask: u32,
+    ) -> Option<RaycastHit>
+    where
+        T1: Into<math::Vector3<f32>>,
+        T2: Into<math::Vector3<f32>>,
+    {
+        self.raycast_except(origin.into(), direction.into(), max_distance, mask, None)
+    }
+
+    /// Casts a ray like `raycast`, but ignores the body of `except`.
+    pub(crate) fn raycast_except(
+        &self,
+        origin: math::Vector3<f32>,
+        direction: math::Vector3<f32>,
+        max_distance: f32,
+        mask: u32,
+        except: Option<Entity>,
+    ) -> Option<RaycastHit> {
+        if direction.magnitude2() <= 0.0 {
+            return None;
+        }
+
+        let direction = direction.normalize();
+        let mut hit: Option<RaycastHit> = None;
+
+        for (&ent, body) in self.bodies.entities.iter().zip(&self.bodies.data) {
+            if body.desc.layers & mask == 0 || Some(ent) == except {
+                continue;
+            }
+
+            let limit = hit.map(|v| v.distance).unwrap_or(max_distance);
+            if let Some((distance, normal)) = cast(body, origin, direction, limit) {
+                hit = Some(RaycastHit {
+                    ent: ent,
+                    point: origin + direction * distance,
+                    normal: normal,
+                    distance: distance,
+                });
+            }
+        }
+
+        hit
+    }
+
+    /// Advances the simulation by `delta` with the fixed timestep, and writes the
+    /// transforms of dynamic bodies back to the scene.
+    ///
+    /// The transforms of kinematic and static bodies are read from the scene, and the
+    /// dynamic bodies whose transforms are changed by users are teleported. The
+    /// `colliders` without rigid bodies are simulated as static bodies. The pairs of
+    /// bodies are tested on the workers of `sched`.
+    pub fn advance(
+        &mut self,
+        sched: &ScheduleSystemShared,
+        scene: &mut SceneGraph,
+        colliders: &Colliders,
+        delta: Duration,
+    ) {
+        self.sync_colliders(scene, colliders);
+
+        let timestep = seconds(self.params.timestep);
+        if timestep <= 0.0 {
+            return;
+        }
+
+        self.accumulator += seconds(delta);
+
+        let steps = (self.accumulator / timestep + 1e-3) as usize;
+        let steps = if steps > self.params.max_steps {
+            self.accumulator = 0.0;
+            self.params.max_steps
+        } else {
+            self.accumulator = (self.accumulator - steps as f32 * timestep).max(0.0);
+            steps
+        };
+
+        self.pull(scene, steps as f32 * timestep);
+        for _ in 0..steps {
+            self.step(sched, timestep);
+        }
+
+        self.push(scene);
+    }
+
+    // Mirrors the `Collider`s of scene as static bodies, unless the entities have their
+    // own rigid bodies.
+    fn sync_colliders(&mut self, scene: &SceneGraph, colliders: &Colliders) {
+        let stale: Vec<_> = self
+            .bodies
+            .entities
+            .iter()
+            .zip(&self.bodies.data)
+            .filter(|&(&ent, v)| v.collider && colliders.get(ent).is_none())
+            .map(|(&ent, _)| ent)
+            .collect();
+
+        for ent in stale {
+            self.remove(ent);
+        }
+
+        for (&ent, collider) in colliders.iter() {
+            let transform = match scene.transform(ent) {
+                Some(v) => v,
+                None => continue,
+            };
+
+            let scale = transform.scale.abs();
+            let shape = match *collider {
+                Collider::Box(extents) => Shape::Box(extents * scale),
+                Collider::Sphere(radius) => Shape::Sphere(radius * scale),
+            };
+
+            if let Some(body) = self.bodies.get_mut(ent) {
+                if body.collider {
+                    body.desc.shape = shape;
+                }
+
+                continue;
+            }
+
+            let pose = Pose {
+                position: transform.position,
+                rotation: transform.rotation,
+            };
+
+            let body = Body {
+                desc: RigidBody::new(BodyKind::Static, shape),
+                pose: pose,
+                synced: Some(pose),
+                target: None,
+                collider: true,
+            };
+
+            self.bodies.add(ent, body);
+        }
+    }
+
+    // Reads the transforms of bodies from scene.
+    fn pull(&mut self, scene: &SceneGraph, duration: f32) {
+        let bodies = &mut self.bodies;
+        for (&ent, body) in bodies.entities.iter().zip(bodies.data.iter_mut()) {
+            let pose = match scene.transform(ent) {
+                Some(v) => Pose {
+                    position: v.position,
+                    rotation: v.rotation,
+                },
+                None => continue,
+            };
+
+            match body.desc.kind {
+                BodyKind::Static => {
+                    body.pose = pose;
+                    body.desc.linear_velocity = math::Vector3::new(0.0, 0.0, 0.0);
+                    body.desc.angular_velocity = math::Vector3::new(0.0, 0.0, 0.0);
+                }
+
+                BodyKind::Kinematic => {
+                    if duration > 0.0 {
+                        // Moves towards the new transform during steps.
+                        let (linear, angular) = velocities(&body.pose, &pose, duration);
+                        body.desc.linear_velocity = linear;
+                        body.desc.angular_velocity = angular;
+                        body.target = Some(pose);
+                    }
+                }
+
+                BodyKind::Dynamic => {
+                    let teleported = body.synced.map(|v| !is_close(&v, &pose)).unwrap_or(true);
+
+                    if teleported {
+                        body.pose = pose;
+                    }
+                }
+            }
+        }
+    }
+
+    // Writes the transforms of dynamic bodies to scene.
+    fn push(&mut self, scene: &mut SceneGraph) {
+        let bodies = &mut self.bodies;
+        for (&ent, body) in bodies.entities.iter().zip(bodies.data.iter_mut()) {
+            match body.desc.kind {
+                BodyKind::Kinematic => {
+                    if let Some(target) = body.target.take() {
+                        body.pose = target;
+                    }
+                }
+
+                BodyKind::Dynamic => {
+                    scene.set_position(ent, body.pose.position);
+                    scene.set_rotation(ent, body.pose.rotation);
+
+                    body.synced = scene.transform(ent).map(|v| Pose {
+                        position: v.position,
+                        rotation: v.rotation,
+                    });
+                }
+
+                BodyKind::Static => {}
+            }
+        }
+    }
+
+    fn step(&mut self, sched: &ScheduleSystemShared, dt: f32) {
+        let gravity = self.params.gravity;
+        for body in &mut self.bodies.data {
+            let v = &mut body.desc;
+            if v.kind == BodyKind::Dynamic {
+                v.linear_velocity += gravity * (v.gravity_scale * dt);
+                v.linear_velocity = v.linear_velocity / (1.0 + dt * v.linear_damping.max(0.0));
+                v.angular_velocity = v.angular_velocity / (1.0 + dt * v.angular_damping.max(0.0));
+            }
+        }
+
+        let pairs = self.broadphase(dt);
+        let mut candidates: Vec<Vec<Candidate>> = vec![Vec::new(); pairs.len()];
+
+        {
+            let sweeps: Vec<_> = self.bodies.data.iter().map(|v| v.sweep(dt)).collect();
+            let (bodies, sweeps, pairs) = (&self.bodies.data, &sweeps, &pairs);
+            let chunks = candidates.chunks_mut(PAIRS_PER_JOB);
+            sched.scope(|s| {
+                for (i, chunk) in chunks.enumerate() {
+                    s.spawn(move |_| {
+                        let offset = i * PAIRS_PER_JOB;
+                        for (j, v) in chunk.iter_mut().enumerate() {
+                            let (a, b) = pairs[offset + j];
+                            let threshold = contact::THRESHOLD + sweeps[a] + sweeps[b];
+                            let (lhs, rhs) = (bodies[a].convex(), bodies[b].convex());
+                            contact::collide(&lhs, &rhs, threshold, v);
+                        }
+                    });
+                }
+            });
+        }
+
+        let previous: Vec<_> = self.manifolds.keys().cloned().collect();
+        let mut manifolds = BTreeMap::new();
+        for (&(a, b), found) in pairs.iter().zip(&candidates) {
+            let key = (self.bodies.entities[a], self.bodies.entities[b]);
+            let (pa, pb) = (self.bodies.data[a].pose, self.bodies.data[b].pose);
+
+            let mut manifold = self.manifolds.remove(&key).unwrap_or_default();
+            manifold.refresh(&pa, &pb);
+            for v in found {
+                manifold.add(&pa, &pb, v);
+            }
+
+            if !manifold.points.is_empty() {
+                manifolds.insert(key, manifold);
+            }
+        }
+
+        self.manifolds = manifolds;
+        let mut states: Vec<_> = self.bodies.data.iter().map(solver_body).collect();
+
+        {
+            let remap = &self.bodies.remap;
+            let mut manifolds: Vec<_> = self
+                .manifolds
+                .iter_mut()
+                .map(|(k, v)| (remap[&k.0], remap[&k.1], v))
+                .collect();
+
+            solver::solve(&mut states, &mut manifolds, dt, self.params.iterations);
+        }
+
+        for (body, state) in self.bodies.data.iter_mut().zip(states) {
+            if body.desc.kind == BodyKind::Dynamic {
+                body.desc.linear_velocity = state.linear;
+                body.desc.angular_velocity = state.angular;
+            }
+
+            if body.desc.kind != BodyKind::Static {
+                let v = &body.desc;
+                let spin = math::Quaternion::from_sv(0.0, v.angular_velocity) * body.pose.rotation;
+                body.pose.position += v.linear_velocity * dt;
+                body.pose.rotation = (body.pose.rotation + spin * (0.5 * dt)).normalize();
+            }
+        }
+
+        for (&(lhs, rhs), v) in &self.manifolds {
+            let phase = if previous.binary_search(&(lhs, rhs)).is_ok() {
+                CollisionPhase::Stay
+            } else {
+                CollisionPhase::Enter
+            };
+
+            let contact = v.deepest().map(|p| CollisionContact {
+                point: (p.a + p.b) * 0.5,
+                normal: -p.normal,
+                depth: p.depth,
+                impulse: v.points.iter().map(|w| w.impulse).sum(),
+            });
+
+            self.events.push(CollisionEvent {
+                phase: phase,
+                lhs: lhs,
+                rhs: rhs,
+                contact: contact,
+            });
+        }
+
+        for (lhs, rhs) in previous {
+            if !self.manifolds.contains_key(&(lhs, rhs)) {
+                self.events.push(CollisionEvent {
+                    phase: CollisionPhase::Exit,
+                    lhs: lhs,
+                    rhs: rhs,
+                    contact: None,
+                });
+            }
+        }
+    }
+
+    // Finds the pairs of bodies whose bounds overlap with sweep and prune. The pairs
+    // are sorted by entities, and the body of smaller entity comes first.
+    fn broadphase(&self, dt: f32) -> Vec<(usize, usize)> {
+        let bounds: Vec<_> = self.bodies.data.iter().map(|v| v.bounds(dt)).collect();
+        let mut order: Vec<_> = (0..self.bodies.data.len()).collect();
+        order.sort_by(|&a, &b| {
+            let (a, b) = (bounds[a].0.x, bounds[b].0.x);
+            a.partial_cmp(&b).unwrap_or(::std::cmp::Ordering::Equal)
+        });
+
+        let mut pairs = Vec::new();
+        for (i, &a) in order.iter().enumerate() {
+            for &b in &order[i + 1..] {
+                let (lhs, rhs) = (&bounds[a], &bounds[b]);
+                if rhs.0.x > lhs.1.x {
+                    break;
+                }
+
+                let separated = |i: usize| rhs.0[i] > lhs.1[i] || lhs.0[i] > rhs.1[i];
+                if separated(1) || separated(2) {
+                    continue;
+                }
+
+                let (da, db) = (&self.bodies.data[a].desc, &self.bodies.data[b].desc);
+                if da.kind != BodyKind::Dynamic && db.kind != BodyKind::Dynamic {
+                    continue;
+                }
+
+                if da.mask & db.layers == 0 || db.mask & da.layers == 0 {
+                    continue;
+                }
+
+                let entities = &self.bodies.entities;
+                if entities[a] < entities[b] {
+                    pairs.push((a, b));
+                } else {
+                    pairs.push((b, a));
+                }
+            }
+        }
+
+        let entities = &self.bodies.entities;
+        pairs.sort_by_key(|&(a, b)| (entities[a], entities[b]));
+        pairs
+    }
+}
+
+impl EntityStore for PhysicsWorld {
+    fn remap_entities(&mut self, remap: &EntityRemap) {
+        PhysicsWorld::remap_entities(self, remap);
+    }
+}
+
+fn solver_body(body: &Body) -> SolverBody {
+    SolverBody {
+        pose: body.pose,
+        linear: body.desc.linear_velocity,
+        angular: body.desc.angular_velocity,
+        inverse_mass: body.desc.inverse_mass(),
+        inverse_inertia: body.desc.inverse_inertia(),
+        friction: body.desc.friction,
+        restitution: body.desc.restitution,
+    }
+}
+
+// Casts the ray against body with conservative advancement, which steps along the ray
+// by the distance to the surface until it is reached.
+fn cast(
+    body: &Body,
+    origin: math::Vector3<f32>,
+    direction: math::Vector3<f32>,
+    max_distance: f32,
+) -> Option<(f32, math::Vector3<f32>)> {
+    let point = Shape::Sphere(0.0);
+    let target = body.convex();
+    let margin = body.desc.shape.margin();
+
+    let mut t = 0.0;
+    for _ in 0..MAX_RAY_ITERATIONS {
+        let probe = Convex {
+            shape: &point,
+            pose: Pose {
+                position: origin + direction * t,
+                rotation: math::Quaternion::one(),
+            },
+        };
+
+        let closest = gjk::closest(&target, &probe)?;
+        let gap = closest.distance - margin;
+        if gap < 1e-4 {
+            return if t > 0.0 || gap >= 0.0 {
+                Some((t, closest.normal))
+            } else {
+                None
+            };
+        }
+
+        t += gap;
+        if t > max_distance {
+            return None;
+        }
+    }
+
+    None
+}
+
+// Gets the velocities which move `from` to `to` in `duration`.
+fn velocities(from: &Pose, to: &Pose, duration: f32) -> (math::Vector3<f32>, math::Vector3<f32>) {
+    let linear = (to.position - from.position) / duration;
+
+    let mut delta = to.rotation * from.rotation.conjugate();
+    if delta.s < 0.0 {
+        delta = delta * -1.0;
+    }
+
+    let sin = delta.v.magnitude();
+    let angular = if sin > 1e-6 {
+        let angle = 2.0 * sin.atan2(delta.s);
+        delta.v * (angle / (sin * duration))
+    } else {
+        math::Vector3::new(0.0, 0.0, 0.0)
+    };
+
+    (linear, angular)
+}
+
+fn is_close(lhs: &Pose, rhs: &Pose) -> bool {
+    (lhs.position - rhs.position).magnitude2() < 1e-8
+        && lhs.rotation.dot(rhs.rotation).abs() > 1.0 - 1e-6
+}
+
+#[inline]
+fn seconds(v: Duration) -> f32 {
+    v.as_secs() as f32 + v.subsec_nanos() as f32 * 1e-9
+}
@@ -0,0 +1,89 @@
+//! The helpers shared by the integration tests.
+
+#![allow(dead_code)]
+
+use std::iter::{self, Repeat, Take};
+use std::time::Duration;
+
+use crayon::math;
+use crayon::sched::ScheduleSystem;
+use crayon::utils::handle_pool::HandlePool;
+
+use crayon_3d::physics::{Colliders, Triggers};
+use crayon_3d::prelude::*;
+use crayon_3d::world_impl;
+use crayon_physics3d::prelude::*;
+use crayon_physics3d::RigidBody;
+
+/// Returns the fixed timesteps of 60 fps which last `seconds`.
+pub fn fixed_steps(seconds: f32) -> Take<Repeat<Duration>> {
+    iter::repeat(Duration::new(0, 16_666_667)).take((seconds * 60.0) as usize)
+}
+
+/// The physics world with a static floor, whose top is at the height of 0.
+pub struct Fixture {
+    pub sched: ScheduleSystem,
+    pub entities: HandlePool,
+    pub scene: SceneGraph,
+    pub colliders: Colliders,
+    pub triggers: Triggers,
+    pub physics: PhysicsWorld,
+    pub floor: Entity,
+}
+
+impl Fixture {
+    pub fn new() -> Self {
+        Fixture::with_floor([20.0, 0.5, 20.0])
+    }
+
+    /// Creates the fixture whose floor has the half extents.
+    pub fn with_floor(half_extents: [f32; 3]) -> Self {
+        let mut fixture = Fixture {
+            sched: ScheduleSystem::new(2, None, None),
+            entities: HandlePool::new(),
+            scene: SceneGraph::new(),
+            colliders: Colliders::new(),
+            triggers: Triggers::new(),
+            physics: PhysicsWorld::new(PhysicsParams::default()),
+            floor: Entity::default(),
+        };
+
+        let position = [0.0, -half_extents[1], 0.0];
+        let floor = Shape::Box(half_extents.into());
+        fixture.floor = fixture.body(position, BodyKind::Static, floor);
+        fixture
+    }
+
+    pub fn body(&mut self, position: [f32; 3], kind: BodyKind, shape: Shape) -> Entity {
+        self.body_with(position, RigidBody::new(kind, shape))
+    }
+
+    pub fn body_with(&mut self, position: [f32; 3], body: RigidBody) -> Entity {
+        let ent = world_impl::create(&mut self.entities, &mut self.scene);
+        self.scene.set_position(ent, position);
+        self.physics.add(&self.scene, ent, body).unwrap();
+        ent
+    }
+
+    /// Advances the physics world and triggers by `dt`.
+    pub fn step(&mut self, dt: Duration) {
+        self.physics
+            .advance(&self.sched.shared(), &mut self.scene, &self.colliders, dt);
+        self.triggers.advance(&self.scene);
+    }
+
+    /// Advances with the fixed timestep of 60 fps.
+    pub fn run(&mut self, seconds: f32) {
+        for dt in fixed_steps(seconds) {
+            self.step(dt);
+        }
+    }
+
+    pub fn position(&self, ent: Entity) -> math::Vector3<f32> {
+        self.scene.position(ent).unwrap()
+    }
+
+    pub fn up(&self, ent: Entity) -> math::Vector3<f32> {
+        self.scene.rotation(ent).unwrap() * math::Vector3::unit_y()
+    }
+}
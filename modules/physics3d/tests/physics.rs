@@ -0,0 +1,398 @@
+extern crate crayon;
+extern crate crayon_3d;
+extern crate crayon_physics3d;
+
+mod common;
+
+use std::sync::Arc;
+
+use crayon::math::{self, InnerSpace, Rotation3};
+
+use crayon_3d::physics::TriggerPhase;
+use crayon_3d::prelude::*;
+use crayon_3d::world_impl;
+use crayon_physics3d::prelude::*;
+use crayon_physics3d::ConvexHull;
+
+use common::Fixture;
+
+#[test]
+fn resting() {
+    let mut fixture = Fixture::new();
+    let cube = Shape::Box([0.5, 0.5, 0.5].into());
+    let cube = fixture.body([0.0, 2.0, 0.0], BodyKind::Dynamic, cube);
+    let ball = fixture.body([3.0, 2.0, 0.0], BodyKind::Dynamic, Shape::Sphere(0.5));
+    let pill = fixture.body(
+        [-3.0, 2.0, 0.0],
+        BodyKind::Dynamic,
+        Shape::Capsule {
+            radius: 0.5,
+            height: 2.0,
+        },
+    );
+
+    // Lays the capsule down on its side.
+    fixture
+        .scene
+        .set_rotation(pill, math::Quaternion::from_angle_x(math::Deg(90.0)));
+
+    fixture.run(3.0);
+
+    assert!((fixture.position(cube).y - 0.5).abs() < 0.02);
+    assert!(fixture.up(cube).y > 0.999);
+    assert!(
+        fixture
+            .physics
+            .get(cube)
+            .unwrap()
+            .linear_velocity
+            .magnitude()
+            < 0.05
+    );
+
+    assert!((fixture.position(ball).y - 0.5).abs() < 0.02);
+    assert!((fixture.position(pill).y - 0.5).abs() < 0.02);
+    assert!(fixture.up(pill).y.abs() < 0.05);
+
+    let floor = fixture.floor;
+    assert!(fixture.physics.is_colliding(floor, cube));
+    assert!(!fixture.physics.is_colliding(cube, ball));
+}
+
+#[test]
+fn stacking() {
+    let mut fixture = Fixture::new();
+    let shape = Shape::Box([0.5, 0.5, 0.5].into());
+
+    let boxes: Vec<_> = (0..3)
+        .map(|i| {
+            let y = 0.5 + i as f32 * 1.05;
+            fixture.body([0.0, y, 0.0], BodyKind::Dynamic, shape.clone())
+        })
+        .collect();
+
+    fixture.run(4.0);
+
+    for (i, &v) in boxes.iter().enumerate() {
+        let position = fixture.position(v);
+        assert!((position.y - (0.5 + i as f32)).abs() < 0.05);
+        assert!(position.x.abs() < 0.05 && position.z.abs() < 0.05);
+        assert!(fixture.up(v).y > 0.99);
+    }
+}
+
+#[test]
+fn convex_hulls() {
+    assert!(ConvexHull::new(&[[0.0, 0.0, 0.0], [1.0, 0.0, 0.0], [0.0, 1.0, 0.0]]).is_err());
+
+    let mut points = Vec::new();
+    for &x in &[-0.5, 0.5] {
+        for &y in &[-0.5, 0.5] {
+            for &z in &[-0.5, 0.5] {
+                points.push([x, y, z]);
+            }
+        }
+    }
+
+    let hull = Arc::new(ConvexHull::new(&points).unwrap());
+    assert_eq!(hull.points().len(), 8);
+
+    let mut fixture = Fixture::new();
+    let rock = fixture.body([0.0, 2.0, 0.0], BodyKind::Dynamic, Shape::ConvexHull(hull));
+    fixture.run(3.0);
+
+    // The convex hulls are inflated by the margin.
+    let position = fixture.position(rock);
+    assert!((position.y - 0.52).abs() < 0.02);
+    assert!(fixture.up(rock).y > 0.99);
+}
+
+#[test]
+fn bouncing() {
+    let mut fixture = Fixture::new();
+    let ball = fixture.body([0.0, 3.0, 0.0], BodyKind::Dynamic, Shape::Sphere(0.5));
+    let clay = fixture.body([3.0, 3.0, 0.0], BodyKind::Dynamic, Shape::Sphere(0.5));
+    fixture.physics.get_mut(ball).unwrap().restitution = 0.8;
+
+    let mut peak = 0.0f32;
+    let mut bounced = false;
+    for _ in 0..120 {
+        fixture.run(1.0 / 60.0);
+
+        let v = fixture.physics.get(ball).unwrap().linear_velocity;
+        bounced = bounced || v.y > 1.0;
+        if bounced {
+            peak = peak.max(fixture.position(ball).y);
+        }
+
+        assert!(fixture.position(clay).y > 0.45);
+    }
+
+    assert!(peak > 1.5);
+    assert!((fixture.position(clay).y - 0.5).abs() < 0.02);
+}
+
+#[test]
+fn friction() {
+    let mut fixture = Fixture::new();
+    let shape = Shape::Box([0.5, 0.5, 0.5].into());
+    let rough = fixture.body([0.0, 0.5, 0.0], BodyKind::Dynamic, shape.clone());
+    let slick = fixture.body([0.0, 0.5, 5.0], BodyKind::Dynamic, shape);
+    fixture.physics.get_mut(slick).unwrap().friction = 0.0;
+
+    fixture.run(0.5);
+    for &v in &[rough, slick] {
+        fixture.physics.get_mut(v).unwrap().linear_velocity = [4.0, 0.0, 0.0].into();
+    }
+
+    fixture.run(1.0);
+
+    // The rough box stops after sliding `v * v / (2 * g * u)` units.
+    let position = fixture.position(rough);
+    assert!((position.x - 1.63).abs() < 0.2);
+    assert!(
+        fixture
+            .physics
+            .get(rough)
+            .unwrap()
+            .linear_velocity
+            .magnitude()
+            < 0.05
+    );
+
+    let position = fixture.position(slick);
+    assert!(position.x > 3.5);
+}
+
+#[test]
+fn kinematics() {
+    let mut fixture = Fixture::new();
+    let pusher = Shape::Box([0.5, 1.0, 2.0].into());
+    let pusher = fixture.body([-2.0, 1.0, 0.0], BodyKind::Kinematic, pusher);
+    let ball = fixture.body([0.0, 0.5, 0.0], BodyKind::Dynamic, Shape::Sphere(0.5));
+
+    for i in 0..60 {
+        let x = -2.0 + i as f32 * 0.05;
+        fixture.scene.set_position(pusher, [x, 1.0, 0.0]);
+        fixture.run(1.0 / 60.0);
+
+        // The kinematic bodies follow the scene, and are never pushed back.
+        let position = fixture.position(pusher);
+        assert!((position.x - x).abs() < 1e-4);
+        assert!((position.y - 1.0).abs() < 1e-4);
+    }
+
+    let velocity = fixture.physics.get(pusher).unwrap().linear_velocity;
+    assert!((velocity.x - 3.0).abs() < 0.01);
+    assert!(fixture.position(ball).x > 1.3);
+
+    // Teleports the dynamic body by setting its transform.
+    fixture.scene.set_position(ball, [0.0, 0.5, 10.0]);
+    fixture.run(0.1);
+    assert!((fixture.position(ball).z - 10.0).abs() < 0.01);
+}
+
+#[test]
+fn events() {
+    let mut fixture = Fixture::new();
+    let floor = fixture.floor;
+    let ball = fixture.body([0.0, 1.0, 0.0], BodyKind::Dynamic, Shape::Sphere(0.5));
+    let ghost = fixture.body([5.0, 1.0, 0.0], BodyKind::Dynamic, Shape::Sphere(0.5));
+    fixture.physics.get_mut(ghost).unwrap().mask = 0;
+
+    let phases = |physics: &mut PhysicsWorld| -> Vec<(CollisionPhase, Entity, Entity)> {
+        physics
+            .take_events()
+            .into_iter()
+            .map(|v| (v.phase, v.lhs, v.rhs))
+            .collect()
+    };
+
+    fixture.run(0.1);
+    assert!(phases(&mut fixture.physics).is_empty());
+
+    fixture.run(1.0 / 60.0);
+    while !fixture.physics.is_colliding(floor, ball) {
+        assert!(phases(&mut fixture.physics).is_empty());
+        fixture.run(1.0 / 60.0);
+    }
+
+    let events = fixture.physics.take_events();
+    assert_eq!(events.len(), 1);
+    assert_eq!(events[0].phase, CollisionPhase::Enter);
+    assert_eq!(
+        (events[0].lhs, events[0].rhs),
+        (floor.min(ball), floor.max(ball))
+    );
+
+    let contact = events[0].contact.unwrap();
+    assert!(contact.impulse > 0.0);
+    assert!(contact.normal.y.abs() > 0.99);
+
+    fixture.run(1.0 / 60.0);
+    let events = phases(&mut fixture.physics);
+    assert_eq!(
+        events,
+        [(CollisionPhase::Stay, floor.min(ball), floor.max(ball))]
+    );
+
+    // The ghost falls through the floor.
+    fixture.run(0.5);
+    assert!(fixture.position(ghost).y < 0.0);
+    assert!(phases(&mut fixture.physics)
+        .iter()
+        .all(|v| v.0 == CollisionPhase::Stay));
+
+    fixture.physics.remove(ball);
+    let events = phases(&mut fixture.physics);
+    assert_eq!(
+        events,
+        [(CollisionPhase::Exit, floor.min(ball), floor.max(ball))]
+    );
+    assert!(!fixture.physics.is_colliding(floor, ball));
+    assert!(fixture.physics.get(ball).is_none());
+    assert!(fixture.physics.get(ghost).is_some());
+
+    let stranger = world_impl::create(&mut fixture.entities, &mut fixture.scene);
+    assert!(fixture
+        .physics
+        .apply_impulse(stranger, [1.0, 0.0, 0.0], [0.0, 0.0, 0.0])
+        .is_err());
+}
+
+#[test]
+fn raycasts() {
+    let mut fixture = Fixture::new();
+    let floor = fixture.floor;
+    let ball = fixture.body([0.0, 1.0, 0.0], BodyKind::Static, Shape::Sphere(0.5));
+    let pillar = Shape::Box([0.5, 1.0, 0.5].into());
+    let pillar = fixture.body([3.0, 1.0, 0.0], BodyKind::Static, pillar);
+    fixture
+        .scene
+        .set_rotation(pillar, math::Quaternion::from_angle_z(math::Deg(30.0)));
+    fixture.physics.get_mut(ball).unwrap().layers = 2;
+    fixture.run(1.0 / 60.0);
+
+    let hit = fixture
+        .physics
+        .raycast([0.0, 5.0, 0.0], [0.0, -2.0, 0.0], 10.0, !0)
+        .unwrap();
+    assert_eq!(hit.ent, ball);
+    assert!((hit.distance - 3.5).abs() < 1e-3);
+    assert!((hit.point.y - 1.5).abs() < 1e-3);
+    assert!(hit.normal.y > 0.999);
+
+    // Ignores the bodies on other layers.
+    let hit = fixture
+        .physics
+        .raycast([0.0, 5.0, 0.0], [0.0, -1.0, 0.0], 10.0, 1)
+        .unwrap();
+    assert_eq!(hit.ent, floor);
+    assert!((hit.distance - 5.0).abs() < 1e-3);
+
+    // Hits the side of rotated box.
+    let hit = fixture
+        .physics
+        .raycast([6.0, 1.0, 0.0], [-1.0, 0.0, 0.0], 10.0, !0)
+        .unwrap();
+    assert_eq!(hit.ent, pillar);
+    let angle = 30f32.to_radians();
+    let normal = math::Vector3::new(angle.cos(), angle.sin(), 0.0);
+    assert!(hit.normal.dot(normal) > 0.99);
+    assert!((hit.distance - (3.0 - 0.5 / angle.cos())).abs() < 1e-2);
+
+    assert!(fixture
+        .physics
+        .raycast([0.0, 5.0, 0.0], [0.0, 1.0, 0.0], 10.0, !0)
+        .is_none());
+    assert!(fixture
+        .physics
+        .raycast([0.0, 5.0, 0.0], [0.0, -1.0, 0.0], 3.0, !0)
+        .is_none());
+
+    // The bodies which contain the origin are ignored.
+    let hit = fixture
+        .physics
+        .raycast([0.0, 1.0, 0.0], [0.0, -1.0, 0.0], 10.0, !0)
+        .unwrap();
+    assert_eq!(hit.ent, floor);
+    assert!((hit.distance - 1.0).abs() < 1e-3);
+}
+
+#[test]
+fn colliders() {
+    let mut fixture = Fixture::new();
+    let ball = fixture.body([0.0, 4.0, 5.0], BodyKind::Dynamic, Shape::Sphere(0.5));
+
+    // A ledge made of collider, which is scaled by its entity.
+    let ledge = world_impl::create(&mut fixture.entities, &mut fixture.scene);
+    fixture.scene.set_position(ledge, [0.0, 2.0, 5.0]);
+    fixture.scene.set_scale(ledge, 2.0);
+    fixture
+        .colliders
+        .add(ledge, Collider::Box([1.0, 0.25, 1.0].into()));
+
+    fixture.run(2.0);
+    assert!((fixture.position(ball).y - 3.0).abs() < 0.02);
+    assert!(fixture.physics.is_colliding(ledge, ball));
+    assert_eq!(fixture.physics.get(ledge).unwrap().kind, BodyKind::Static);
+
+    let hit = fixture
+        .physics
+        .raycast([1.5, 5.0, 5.0], [0.0, -1.0, 0.0], 10.0, !0)
+        .unwrap();
+    assert_eq!(hit.ent, ledge);
+    assert!((hit.point.y - 2.5).abs() < 1e-3);
+
+    // The ball falls onto the floor once the collider is removed.
+    fixture.colliders.remove(ledge);
+    fixture.physics.take_events();
+    fixture.run(1.0 / 60.0);
+    assert!(fixture.physics.get(ledge).is_none());
+    assert!(fixture
+        .physics
+        .take_events()
+        .iter()
+        .any(|v| v.phase == CollisionPhase::Exit && v.lhs == ledge.min(ball)));
+
+    fixture.run(2.0);
+    assert!((fixture.position(ball).y - 0.5).abs() < 0.02);
+
+    // The rigid body of entity takes the place of its collider.
+    fixture.colliders.add(ball, Collider::Sphere(4.0));
+    fixture.run(0.5);
+    assert_eq!(fixture.physics.get(ball).unwrap().kind, BodyKind::Dynamic);
+    assert!((fixture.position(ball).y - 0.5).abs() < 0.02);
+}
+
+#[test]
+fn triggers() {
+    let mut fixture = Fixture::new();
+    let ball = fixture.body([0.0, 5.0, 0.0], BodyKind::Dynamic, Shape::Sphere(0.5));
+
+    let zone = world_impl::create(&mut fixture.entities, &mut fixture.scene);
+    fixture.scene.set_position(zone, [0.0, 2.0, 0.0]);
+    let volume = TriggerVolume::new(TriggerShape::Box([1.0, 0.5, 1.0].into()));
+    fixture.triggers.add(zone, volume);
+
+    let sphere = TriggerVolume::new(TriggerShape::Sphere(0.5));
+    fixture.triggers.add(ball, sphere);
+
+    fixture.run(2.0);
+    let phases: Vec<_> = fixture
+        .triggers
+        .take_events()
+        .into_iter()
+        .filter(|v| v.phase != TriggerPhase::Stay)
+        .map(|v| (v.phase, v.lhs, v.rhs))
+        .collect();
+
+    let pair = (zone.min(ball), zone.max(ball));
+    assert_eq!(
+        phases,
+        [
+            (TriggerPhase::Enter, pair.0, pair.1),
+            (TriggerPhase::Exit, pair.0, pair.1),
+        ]
+    );
+}
@@ -0,0 +1,211 @@
+extern crate crayon;
+extern crate crayon_3d;
+extern crate crayon_physics3d;
+
+mod common;
+
+use crayon::math::{self, InnerSpace};
+
+use crayon_3d::prelude::*;
+use crayon_3d::world_impl;
+use crayon_3d::EntityRemap;
+use crayon_physics3d::prelude::*;
+use crayon_physics3d::FrictionCurve;
+
+struct Fixture {
+    world: common::Fixture,
+    vehicles: Vehicles,
+    car: Entity,
+    meshes: Vec<Entity>,
+}
+
+impl Fixture {
+    fn new() -> Self {
+        let mut world = common::Fixture::with_floor([100.0, 0.5, 100.0]);
+
+        let mut body = RigidBody::new(BodyKind::Dynamic, Shape::Box([1.0, 0.3, 2.0].into()));
+        body.mass = 1000.0;
+        let car = world.body_with([0.0, 1.0, 0.0], body);
+
+        let mut meshes = Vec::new();
+        let mut wheels = Vec::new();
+        for &(x, z) in &[(-0.9, 1.5), (0.9, 1.5), (-0.9, -1.5), (0.9, -1.5)] {
+            let mesh = world_impl::create(&mut world.entities, &mut world.scene);
+            meshes.push(mesh);
+
+            let mut wheel = Wheel::new([x, -0.2, z], 0.35);
+            wheel.steering = z > 0.0;
+            wheel.powered = z < 0.0;
+            wheel.mesh = Some(mesh);
+            wheels.push(wheel);
+        }
+
+        let mut vehicles = Vehicles::new();
+        vehicles
+            .add(&world.physics, car, Vehicle::new(wheels))
+            .unwrap();
+
+        Fixture {
+            world: world,
+            vehicles: vehicles,
+            car: car,
+            meshes: meshes,
+        }
+    }
+
+    // Advances the vehicles before every step of physics world.
+    fn run(&mut self, seconds: f32) {
+        for dt in common::fixed_steps(seconds) {
+            self.vehicles
+                .advance(&mut self.world.physics, &mut self.world.scene, dt);
+            self.world.step(dt);
+        }
+    }
+
+    fn vehicle(&mut self) -> &mut Vehicle {
+        self.vehicles.get_mut(self.car).unwrap()
+    }
+
+    fn position(&self) -> math::Vector3<f32> {
+        self.world.position(self.car)
+    }
+
+    fn velocity(&self) -> math::Vector3<f32> {
+        self.world.physics.get(self.car).unwrap().linear_velocity
+    }
+}
+
+#[test]
+fn friction_curves() {
+    let curve = FrictionCurve {
+        extremum_slip: 0.5,
+        extremum_value: 1.0,
+        asymptote_slip: 1.0,
+        asymptote_value: 0.5,
+    };
+
+    assert_eq!(curve.evaluate(0.0), 0.0);
+    assert_eq!(curve.evaluate(0.25), 0.5);
+    assert_eq!(curve.evaluate(-0.5), 1.0);
+    assert_eq!(curve.evaluate(0.75), 0.75);
+    assert_eq!(curve.evaluate(5.0), 0.5);
+}
+
+#[test]
+fn suspension() {
+    let mut fixture = Fixture::new();
+    fixture.run(3.0);
+
+    // The load of 2450 newtons compresses every spring by about 0.12.
+    let position = fixture.position();
+    assert!((position.y - 0.73).abs() < 0.05, "{:?}", position);
+    assert!(fixture.velocity().magnitude() < 0.05);
+
+    let vehicle = fixture.vehicles.get(fixture.car).unwrap();
+    for wheel in &vehicle.wheels {
+        assert!(wheel.is_grounded());
+        assert!((wheel.compression() - 0.12).abs() < 0.03);
+        assert!((wheel.hit().unwrap().force - 2450.0).abs() < 100.0);
+    }
+}
+
+#[test]
+fn throttle_and_brake() {
+    let mut fixture = Fixture::new();
+    fixture.run(1.0);
+
+    fixture.vehicle().throttle = 1.0;
+    fixture.run(2.0);
+
+    let position = fixture.position();
+    assert!(position.z > 2.0, "{:?}", position);
+    assert!(position.x.abs() < 0.1, "{:?}", position);
+    assert!(fixture.velocity().z > 2.0);
+
+    let vehicle = fixture.vehicles.get(fixture.car).unwrap();
+    assert!(vehicle.wheels.iter().all(|v| v.angular_velocity() > 0.0));
+
+    fixture.vehicle().throttle = 0.0;
+    fixture.vehicle().brake = 1.0;
+    fixture.run(3.0);
+    assert!(fixture.velocity().magnitude() < 0.1);
+
+    fixture.vehicle().brake = 0.0;
+    fixture.vehicle().throttle = -1.0;
+    fixture.run(1.0);
+    assert!(fixture.velocity().z < -0.5);
+}
+
+#[test]
+fn steering() {
+    let mut fixture = Fixture::new();
+    fixture.run(1.0);
+
+    fixture.vehicle().throttle = 1.0;
+    fixture.vehicle().steer = 1.0;
+    fixture.run(3.0);
+
+    let position = fixture.position();
+    assert!(position.x > 1.0, "{:?}", position);
+
+    let forward = fixture.world.scene.rotation(fixture.car).unwrap() * math::Vector3::unit_z();
+    assert!(forward.x > 0.2, "{:?}", forward);
+
+    let vehicle = fixture.vehicles.get(fixture.car).unwrap();
+    assert!(vehicle.wheels[0].steer_angle() > 0.0);
+    assert_eq!(vehicle.wheels[2].steer_angle(), 0.0);
+}
+
+#[test]
+fn wheel_meshes() {
+    let mut fixture = Fixture::new();
+    fixture.run(1.0);
+
+    let transform = fixture.world.scene.transform(fixture.car).unwrap();
+    let vehicle = fixture.vehicles.get(fixture.car).unwrap();
+    for (wheel, &mesh) in vehicle.wheels.iter().zip(&fixture.meshes) {
+        let center = transform.transform_point(wheel.local_transform().0);
+        let position = fixture.world.scene.position(mesh).unwrap();
+        assert!((position - center).magnitude() < 1e-3);
+
+        // The wheels stand on the floor.
+        assert!((position.y - 0.35).abs() < 0.02, "{:?}", position);
+    }
+
+    fixture.vehicles.remove(fixture.car);
+    assert!(fixture.vehicles.get(fixture.car).is_none());
+}
+
+#[test]
+fn removed_bodies() {
+    let mut fixture = Fixture::new();
+    fixture.run(1.0);
+
+    // The vehicle falls without the ground.
+    let floor = fixture.world.floor;
+    fixture.world.physics.remove(floor);
+    fixture.run(0.5);
+    assert!(fixture.position().y < 0.0);
+
+    // The vehicle without chassis is skipped.
+    fixture.world.physics.remove(fixture.car);
+    fixture.run(0.5);
+    assert!(fixture.vehicles.get(fixture.car).is_some());
+}
+
+#[test]
+fn remap_entities() {
+    let mut fixture = Fixture::new();
+    let car = fixture.world.entities.create().into();
+    let mesh = fixture.world.entities.create().into();
+
+    let mut remap = EntityRemap::new();
+    remap.insert(fixture.car, car);
+    remap.insert(fixture.meshes[0], mesh);
+    fixture.vehicles.remap_entities(&remap);
+
+    assert!(fixture.vehicles.get(fixture.car).is_none());
+    let vehicle = fixture.vehicles.get(car).unwrap();
+    assert_eq!(vehicle.wheels[0].mesh, Some(mesh));
+    assert_eq!(vehicle.wheels[1].mesh, Some(fixture.meshes[1]));
+}
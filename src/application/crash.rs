@@ -0,0 +1,340 @@
+//! Configurable panic handling which writes a crash dump of engine state.
+//!
+//! ```rust,ignore
+//! let mut crash = CrashHandler::new(CrashParams::default());
+//! crash.install_logger(None).unwrap();
+//! crash.add_section("world", move || format!("{:?}", snapshot));
+//! crash.set_upload(|path| upload_to_server(path));
+//!
+//! engine.set_crash_handler(crash);
+//! ```
+//!
+//! The dump is a plain text file which contains the panic message, the index of frame,
+//! the `FrameInfo` and `FrameProfile` of last frame, the profiled scopes of current
+//! frame, the list of loaded resources, the recent log lines and all the custom
+//! sections. Every crash is written into a new file, which is named after the time and
+//! the process id, e.g. `crash-1539760000-4242.txt`.
+
+use std::collections::VecDeque;
+use std::fmt::Write as FmtWrite;
+use std::fs;
+use std::io::{self, Write};
+use std::panic::{self, PanicInfo};
+use std::path::{Path, PathBuf};
+use std::process;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use log::{self, LevelFilter, Log, Metadata, Record};
+
+use errors::*;
+use res::ResourceSystemShared;
+
+use super::diagnostics::Profiler;
+use super::FrameInfo;
+
+/// The setup parameters of `CrashHandler`.
+#[derive(Debug, Clone)]
+pub struct CrashParams {
+    /// The directory where crash dumps are written into.
+    pub directory: PathBuf,
+    /// The number of recent log lines kept in crash dumps.
+    pub log_lines: usize,
+    /// The maximum level of captured logs.
+    pub log_level: LevelFilter,
+}
+
+impl Default for CrashParams {
+    fn default() -> Self {
+        CrashParams {
+            directory: "crashes".into(),
+            log_lines: 64,
+            log_level: LevelFilter::Info,
+        }
+    }
+}
+
+type Section = (String, Box<Fn() -> String + Send + Sync>);
+type Upload = Box<Fn(&Path) + Send + Sync>;
+
+/// `CrashHandler` collects the state of engine, which is written into a crash dump when
+/// panicking. It should be installed with `Engine::set_crash_handler`.
+pub struct CrashHandler {
+    params: CrashParams,
+    logs: Arc<Mutex<VecDeque<String>>>,
+    sections: Vec<Section>,
+    upload: Option<Upload>,
+}
+
+impl CrashHandler {
+    pub fn new(params: CrashParams) -> Self {
+        CrashHandler {
+            params: params,
+            logs: Arc::new(Mutex::new(VecDeque::new())),
+            sections: Vec::new(),
+            upload: None,
+        }
+    }
+
+    /// Installs a global logger which keeps the recent log lines for crash dumps, the
+    /// records are forwarded to `inner` (e.g. `env_logger`) if any. This fails if a
+    /// logger has been installed already.
+    pub fn install_logger(&self, inner: Option<Box<Log>>) -> Result<()> {
+        let logger = Box::new(CrashLogger {
+            inner: inner,
+            level: self.params.log_level,
+            capacity: self.params.log_lines,
+            logs: self.logs.clone(),
+        });
+
+        if log::set_logger(Box::leak(logger)).is_err() {
+            bail!("A logger has been installed already.");
+        }
+
+        log::set_max_level(self.params.log_level);
+        Ok(())
+    }
+
+    /// Adds a custom section into crash dumps, e.g. a snapshot of world. Notes that
+    /// the closure is called while panicking, it should avoid taking locks which might
+    /// be held by the panicked thread.
+    pub fn add_section<F>(&mut self, name: &str, func: F)
+    where
+        F: Fn() -> String + Send + Sync + 'static,
+    {
+        self.sections.push((name.to_owned(), Box::new(func)));
+    }
+
+    /// Sets the callback which is called with the path of crash dump after its written,
+    /// e.g. uploading it to a server.
+    pub fn set_upload<F>(&mut self, func: F)
+    where
+        F: Fn(&Path) + Send + Sync + 'static,
+    {
+        self.upload = Some(Box::new(func));
+    }
+
+    fn write(&self, state: &CrashState, panic: &PanicInfo) -> Result<PathBuf> {
+        let mut dump = String::new();
+
+        let message = if let Some(v) = panic.payload().downcast_ref::<&str>() {
+            (*v).to_owned()
+        } else if let Some(v) = panic.payload().downcast_ref::<String>() {
+            v.clone()
+        } else {
+            "Box<Any>".to_owned()
+        };
+
+        writeln!(dump, "panic: {}", message)?;
+        if let Some(location) = panic.location() {
+            writeln!(dump, "location: {}:{}", location.file(), location.line())?;
+        }
+
+        let thread = thread::current();
+        writeln!(dump, "thread: {}", thread.name().unwrap_or("<unnamed>"))?;
+        writeln!(dump, "frame: {}", state.frame.load(Ordering::Relaxed))?;
+
+        // The locks might be held by the panicked thread, so they are never blocked on.
+        if let Ok(info) = state.info.try_lock() {
+            writeln!(dump, "\n== Frame ==\n{:#?}", *info)?;
+        }
+
+        let (last_frame, systems) = state.profiler.try_snapshot();
+        if let Some(profile) = last_frame {
+            writeln!(dump, "\n== Profiler ==\n{:#?}", profile)?;
+        }
+
+        if let Some(systems) = systems {
+            writeln!(dump, "\n== Profiler (Current Frame) ==")?;
+            for v in systems {
+                writeln!(dump, "{}: {:?} ({} calls)", v.name, v.duration, v.calls)?;
+            }
+        }
+
+        if let Some(resources) = state.res.try_loaded_resources() {
+            writeln!(dump, "\n== Resources ==")?;
+            for (uuid, rc) in resources {
+                writeln!(dump, "{:X} (rc: {})", uuid.simple(), rc)?;
+            }
+        }
+
+        if let Ok(logs) = self.logs.try_lock() {
+            writeln!(dump, "\n== Logs ==")?;
+            for v in logs.iter() {
+                writeln!(dump, "{}", v)?;
+            }
+        }
+
+        for &(ref name, ref func) in &self.sections {
+            writeln!(dump, "\n== {} ==\n{}", name, func())?;
+        }
+
+        save(&self.params.directory, &dump)
+    }
+}
+
+/// Writes the dump into a new file in `directory`, which never overwrites the dumps of
+/// other crashes in the same second, or of other processes.
+fn save(directory: &Path, dump: &str) -> Result<PathBuf> {
+    let ts = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|v| v.as_secs())
+        .unwrap_or(0);
+
+    fs::create_dir_all(directory)?;
+    let name = format!("crash-{}-{}", ts, process::id());
+    for i in 0.. {
+        let path = if i == 0 {
+            directory.join(format!("{}.txt", name))
+        } else {
+            directory.join(format!("{}-{}.txt", name, i))
+        };
+
+        let mut options = fs::OpenOptions::new();
+        match options.write(true).create_new(true).open(&path) {
+            Ok(mut file) => {
+                file.write_all(dump.as_bytes())?;
+                return Ok(path);
+            }
+            Err(ref err) if err.kind() == io::ErrorKind::AlreadyExists => continue,
+            Err(err) => return Err(err.into()),
+        }
+    }
+
+    unreachable!();
+}
+
+/// The engine state which is shared with panic hook.
+pub(crate) struct CrashState {
+    pub frame: AtomicUsize,
+    pub info: Mutex<FrameInfo>,
+    res: Arc<ResourceSystemShared>,
+    profiler: Arc<Profiler>,
+}
+
+impl CrashState {
+    pub fn new(res: Arc<ResourceSystemShared>, profiler: Arc<Profiler>) -> Self {
+        CrashState {
+            frame: AtomicUsize::new(0),
+            info: Mutex::new(FrameInfo::default()),
+            res: res,
+            profiler: profiler,
+        }
+    }
+
+    /// Records the information of last frame.
+    pub fn advance(&self, info: &FrameInfo) {
+        self.frame.fetch_add(1, Ordering::Relaxed);
+        *self.info.lock().unwrap() = *info;
+    }
+}
+
+/// Installs the panic hook, which writes crash dump and then calls the previous hook.
+pub(crate) fn install(handler: CrashHandler, state: Arc<CrashState>) {
+    let previous = panic::take_hook();
+    panic::set_hook(Box::new(move |panic| {
+        match handler.write(&state, panic) {
+            Ok(path) => {
+                eprintln!("Crash dump is written into {:?}.", path);
+                if let Some(ref upload) = handler.upload {
+                    upload(&path);
+                }
+            }
+            Err(err) => eprintln!("Failed to write crash dump. {}", err),
+        }
+
+        previous(panic);
+    }));
+}
+
+struct CrashLogger {
+    inner: Option<Box<Log>>,
+    level: LevelFilter,
+    capacity: usize,
+    logs: Arc<Mutex<VecDeque<String>>>,
+}
+
+impl Log for CrashLogger {
+    fn enabled(&self, metadata: &Metadata) -> bool {
+        metadata.level() <= self.level
+    }
+
+    fn log(&self, record: &Record) {
+        if let Some(ref inner) = self.inner {
+            if inner.enabled(record.metadata()) {
+                inner.log(record);
+            }
+        }
+
+        if self.capacity == 0 || !self.enabled(record.metadata()) {
+            return;
+        }
+
+        // Skips the record instead of blocking, in case of logging while panicking.
+        if let Ok(mut logs) = self.logs.try_lock() {
+            while logs.len() >= self.capacity {
+                logs.pop_front();
+            }
+
+            let line = format!("[{}] {}: {}", record.level(), record.target(), record.args());
+            logs.push_back(line);
+        }
+    }
+
+    fn flush(&self) {
+        if let Some(ref inner) = self.inner {
+            inner.flush();
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use log::Level;
+    use std::env;
+
+    #[test]
+    fn recent_logs() {
+        let logs = Arc::new(Mutex::new(VecDeque::new()));
+        let logger = CrashLogger {
+            inner: None,
+            level: LevelFilter::Info,
+            capacity: 2,
+            logs: logs.clone(),
+        };
+
+        for (i, level) in [Level::Info, Level::Debug, Level::Warn, Level::Error]
+            .iter()
+            .enumerate()
+        {
+            logger.log(&Record::builder()
+                .args(format_args!("{}", i))
+                .level(*level)
+                .target("crash")
+                .build());
+        }
+
+        let logs = logs.lock().unwrap();
+        assert_eq!(logs.len(), 2);
+        assert_eq!(logs[0], "[WARN] crash: 2");
+        assert_eq!(logs[1], "[ERROR] crash: 3");
+    }
+
+    #[test]
+    fn unique_dumps() {
+        let directory = env::temp_dir().join("crayon_crashes");
+        let _ = fs::remove_dir_all(&directory);
+
+        // The crashes in the same second are written into different files.
+        let first = save(&directory, "first").unwrap();
+        let second = save(&directory, "second").unwrap();
+        assert!(first != second);
+        assert_eq!(fs::read_to_string(&first).unwrap(), "first");
+        assert_eq!(fs::read_to_string(&second).unwrap(), "second");
+
+        let _ = fs::remove_dir_all(&directory);
+    }
+}
@@ -0,0 +1,146 @@
+//! The appearance of mouse cursor.
+//!
+//! The cursor could be changed at any time with `WindowShared::set_cursor`, usually every
+//! frame based on what is hovered. It's applied by the window at the start of next frame.
+//!
+//! ```rust,ignore
+//! let cursor = if hovered {
+//!     Cursor::Icon(CursorIcon::Hand)
+//! } else {
+//!     Cursor::default()
+//! };
+//!
+//! ctx.window.set_cursor(cursor);
+//! ```
+//!
+//! # Platform-specific
+//!
+//! The custom cursors are not supported by the windowing backend yet, the hardware cursor
+//! is hidden instead and `WindowShared::software_cursor` returns the cursor which should
+//! be rendered by the engine, e.g. the `crayon-imgui` module draws it on top of UI.
+
+use std::sync::Arc;
+
+use math;
+use video::assets::image::Image;
+use video::assets::texture::TextureFormat;
+
+/// The standard cursor icons of system.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum CursorIcon {
+    /// The platform-dependent default cursor, usually an arrow.
+    Default,
+    /// Indicates a link.
+    Hand,
+    /// Indicates text that may be selected or edited.
+    Text,
+    /// Indicates something is to be moved.
+    Move,
+    /// Indicates a precise selection.
+    Crosshair,
+    /// Indicates the program is busy.
+    Wait,
+    /// Indicates help is available.
+    Help,
+    /// Indicates the requested action will not be carried out.
+    NotAllowed,
+    /// Indicates a left-right resizing.
+    ResizeHorizontal,
+    /// Indicates an up-down resizing.
+    ResizeVertical,
+    /// Indicates a resizing along the bottom-left to top-right diagonal.
+    ResizeNeSw,
+    /// Indicates a resizing along the top-left to bottom-right diagonal.
+    ResizeNwSe,
+}
+
+/// The custom cursor made from a RGBA image.
+#[derive(Debug, Clone)]
+pub struct CustomCursor {
+    image: Arc<Image>,
+    hotspot: math::Vector2<u32>,
+}
+
+impl CustomCursor {
+    /// Creates a custom cursor with the `hotspot` in pixels, whose origin is at the
+    /// top-left of image. Returns `None` if the image could not be converted into RGBA8.
+    pub fn new(image: &Image, hotspot: math::Vector2<u32>) -> Option<Self> {
+        let image = image.convert(TextureFormat::RGBA8).ok()?;
+        let dimensions = image.dimensions();
+
+        Some(CustomCursor {
+            image: Arc::new(image),
+            hotspot: math::Vector2::new(
+                hotspot.x.min(dimensions.x.saturating_sub(1)),
+                hotspot.y.min(dimensions.y.saturating_sub(1)),
+            ),
+        })
+    }
+
+    /// Gets the RGBA8 image of cursor.
+    #[inline]
+    pub fn image(&self) -> &Image {
+        &self.image
+    }
+
+    /// Gets the hotspot in pixels, whose origin is at the top-left of image.
+    #[inline]
+    pub fn hotspot(&self) -> math::Vector2<u32> {
+        self.hotspot
+    }
+}
+
+impl PartialEq for CustomCursor {
+    fn eq(&self, rhs: &Self) -> bool {
+        Arc::ptr_eq(&self.image, &rhs.image) && self.hotspot == rhs.hotspot
+    }
+}
+
+/// The appearance of mouse cursor.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Cursor {
+    /// The standard system cursor.
+    Icon(CursorIcon),
+    /// The custom cursor.
+    Custom(CustomCursor),
+    /// Hides the cursor when its over the window.
+    Hidden,
+}
+
+impl Default for Cursor {
+    fn default() -> Self {
+        Cursor::Icon(CursorIcon::Default)
+    }
+}
+
+impl From<CursorIcon> for Cursor {
+    fn from(icon: CursorIcon) -> Self {
+        Cursor::Icon(icon)
+    }
+}
+
+impl From<CustomCursor> for Cursor {
+    fn from(cursor: CustomCursor) -> Self {
+        Cursor::Custom(cursor)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn custom() {
+        let image = Image::new(TextureFormat::RGB8, math::Vector2::new(16, 8)).unwrap();
+        let cursor = CustomCursor::new(&image, math::Vector2::new(32, 2)).unwrap();
+        assert_eq!(cursor.image().format(), TextureFormat::RGBA8);
+        assert_eq!(cursor.hotspot(), math::Vector2::new(15, 2));
+
+        // Compares by the identity of image.
+        assert_eq!(Cursor::from(cursor.clone()), Cursor::Custom(cursor.clone()));
+        let other = CustomCursor::new(&image, math::Vector2::new(15, 2)).unwrap();
+        assert!(cursor != other);
+
+        assert_eq!(Cursor::default(), Cursor::Icon(CursorIcon::Default));
+    }
+}
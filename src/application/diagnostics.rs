@@ -0,0 +1,260 @@
+//! Frame profiler which records the time spent in each phase of engine, the named
+//! scopes of application and optionally the GPU timings.
+//!
+//! The CPU timings of engine phases are collected every frame, and the systems of
+//! application, e.g. the animations or physics of world, could be measured with
+//! `Profiler::scope`. The GPU timings are measured with timer queries if it's enabled
+//! with `VideoParams::gpu_timings`.
+//!
+//! ```rust,ignore
+//! fn on_update(&mut self, ctx: &Context) -> Result<()> {
+//!     {
+//!         let _scope = ctx.profiler.scope("Physics");
+//!         self.world.triggers.advance(&self.world.scene);
+//!     }
+//!
+//!     let _scope = ctx.profiler.scope("Animation");
+//!     let delta = ctx.time.frame_delta();
+//!     self.world.animators.advance(&mut self.world.scene, delta);
+//!     Ok(())
+//! }
+//!
+//! fn on_post_update(&mut self, ctx: &Context, _: &FrameInfo) -> Result<()> {
+//!     if let Some(profile) = ctx.profiler.last_frame() {
+//!         self.overlay.update(&profile);
+//!     }
+//!
+//!     Ok(())
+//! }
+//! ```
+
+use std::sync::{Mutex, RwLock};
+use std::time::{Duration, Instant};
+
+use video::assets::surface::SurfaceHandle;
+use video::timing::GpuFrameTimings;
+
+/// The time spent in each phase of `Application`, which are executed on the worker
+/// thread.
+#[derive(Debug, Copy, Clone, Default)]
+pub struct ApplicationTimings {
+    pub pre_update: Duration,
+    /// The sum of all the fixed steps.
+    pub fixed_update: Duration,
+    pub update: Duration,
+    pub late_update: Duration,
+    pub pre_render: Duration,
+    pub render: Duration,
+}
+
+impl ApplicationTimings {
+    /// Gets the time spent in all the phases.
+    pub fn total(&self) -> Duration {
+        self.pre_update + self.fixed_update + self.update + self.late_update
+            + self.pre_render + self.render
+    }
+}
+
+/// The aggregated time of a named scope during a frame.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct SystemTiming {
+    pub name: &'static str,
+    pub duration: Duration,
+    /// The number of times this scope is entered.
+    pub calls: u32,
+}
+
+/// The timings of a frame.
+#[derive(Debug, Clone, Default)]
+pub struct FrameProfile {
+    /// The wall time of the whole frame on main thread.
+    pub duration: Duration,
+    /// The time spent on polling and dispatching events, and advancing input.
+    pub input: Duration,
+    /// The time spent on advancing the schedule and resource systems.
+    pub resources: Duration,
+    /// The time spent on advancing the time and audio systems.
+    pub audio: Duration,
+    /// The time main thread spent on waiting for the updates of application.
+    pub wait: Duration,
+    /// The time spent in each phase of application.
+    pub application: ApplicationTimings,
+    /// The time spent on submitting video commands, which includes waiting for the GPU.
+    pub video: Duration,
+    /// The time spent on swapping buffers.
+    pub swap: Duration,
+    /// The scopes recorded with `Profiler::scope`, in the order of first entered.
+    pub systems: Vec<SystemTiming>,
+    /// The GPU timings, which is `None` if it's disabled or not supported.
+    pub gpu: Option<GpuFrameTimings>,
+}
+
+/// `Profiler` collects the timings of the frame being updated, and keeps the
+/// `FrameProfile` of last frame.
+#[derive(Default)]
+pub struct Profiler {
+    systems: Mutex<Vec<SystemTiming>>,
+    last_frame: RwLock<Option<FrameProfile>>,
+}
+
+impl Profiler {
+    pub fn new() -> Self {
+        Profiler::default()
+    }
+
+    /// Starts a named scope, the time is recorded when the returned guard is dropped.
+    /// The scopes with the same name are aggregated, and nested scopes are recorded
+    /// separately, so the outer one includes the inner ones.
+    #[inline]
+    pub fn scope(&self, name: &'static str) -> ProfileScope {
+        ProfileScope {
+            profiler: self,
+            name: name,
+            ts: Instant::now(),
+        }
+    }
+
+    /// Records the duration of a named scope directly.
+    pub fn record(&self, name: &'static str, duration: Duration) {
+        let mut systems = self.systems.lock().unwrap();
+        if let Some(v) = systems.iter_mut().find(|v| v.name == name) {
+            v.duration += duration;
+            v.calls += 1;
+            return;
+        }
+
+        systems.push(SystemTiming {
+            name: name,
+            duration: duration,
+            calls: 1,
+        });
+    }
+
+    /// Gets the `FrameProfile` of last frame.
+    pub fn last_frame(&self) -> Option<FrameProfile> {
+        self.last_frame.read().unwrap().clone()
+    }
+
+    /// Gets the GPU time spent on `surface` in last frame, which sums up all the times
+    /// it's binded. Returns `None` if the GPU timings are not available, or the surface
+    /// is not drawn.
+    pub fn last_gpu_time(&self, surface: SurfaceHandle) -> Option<Duration> {
+        let last_frame = self.last_frame.read().unwrap();
+        let timings = last_frame.as_ref().and_then(|v| v.gpu.as_ref())?;
+
+        timings
+            .surfaces
+            .iter()
+            .filter(|v| v.surface == surface)
+            .fold(None, |acc, v| Some(acc.unwrap_or_default() + v.duration))
+    }
+
+    /// Gets the `FrameProfile` of last frame and the scopes recorded in current frame
+    /// without blocking, e.g. while panicking. The parts whose locks are held by other
+    /// threads are `None`.
+    pub(crate) fn try_snapshot(&self) -> (Option<FrameProfile>, Option<Vec<SystemTiming>>) {
+        let last_frame = self.last_frame.try_read().ok().and_then(|v| v.clone());
+        let systems = self.systems.try_lock().ok().map(|v| v.clone());
+        (last_frame, systems)
+    }
+
+    /// Takes the scopes recorded since last call.
+    pub(crate) fn take_systems(&self) -> Vec<SystemTiming> {
+        ::std::mem::replace(&mut *self.systems.lock().unwrap(), Vec::new())
+    }
+
+    pub(crate) fn finish(&self, profile: FrameProfile) {
+        *self.last_frame.write().unwrap() = Some(profile);
+    }
+}
+
+/// A guard which records the time elapsed since created into `Profiler`.
+pub struct ProfileScope<'a> {
+    profiler: &'a Profiler,
+    name: &'static str,
+    ts: Instant,
+}
+
+impl<'a> Drop for ProfileScope<'a> {
+    fn drop(&mut self) {
+        self.profiler.record(self.name, Instant::now() - self.ts);
+    }
+}
+
+/// Returns the time elapsed since `timepoint`, and resets it to now.
+pub(crate) fn lap(timepoint: &mut Instant) -> Duration {
+    let now = Instant::now();
+    let elapsed = now - *timepoint;
+    *timepoint = now;
+    elapsed
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use utils::handle::Handle;
+    use video::timing::GpuSurfaceTiming;
+
+    #[test]
+    fn scopes() {
+        let profiler = Profiler::new();
+        assert!(profiler.last_frame().is_none());
+
+        {
+            let _scope = profiler.scope("Physics");
+        }
+
+        profiler.record("Animation", Duration::from_millis(2));
+        profiler.record("Physics", Duration::from_millis(1));
+        profiler.record("Animation", Duration::from_millis(3));
+
+        let systems = profiler.take_systems();
+        assert_eq!(systems.len(), 2);
+        assert_eq!(systems[0].name, "Physics");
+        assert_eq!(systems[0].calls, 2);
+        assert!(systems[0].duration >= Duration::from_millis(1));
+        assert_eq!(systems[1].name, "Animation");
+        assert_eq!(systems[1].calls, 2);
+        assert_eq!(systems[1].duration, Duration::from_millis(5));
+        assert!(profiler.take_systems().is_empty());
+
+        let mut profile = FrameProfile::default();
+        profile.application.update = Duration::from_millis(4);
+        profile.application.render = Duration::from_millis(1);
+        profile.systems = systems;
+        profiler.finish(profile);
+
+        let profile = profiler.last_frame().unwrap();
+        assert_eq!(profile.application.total(), Duration::from_millis(5));
+        assert_eq!(profile.systems.len(), 2);
+    }
+
+    #[test]
+    fn gpu_time() {
+        let surface: SurfaceHandle = Handle::new(0, 1).into();
+        let other: SurfaceHandle = Handle::new(1, 1).into();
+
+        let profiler = Profiler::new();
+        assert!(profiler.last_gpu_time(surface).is_none());
+
+        let mut timings = GpuFrameTimings::default();
+        for &(v, millis) in &[(surface, 2), (other, 1), (surface, 3)] {
+            timings.surfaces.push(GpuSurfaceTiming {
+                surface: v,
+                duration: Duration::from_millis(millis),
+                drawcalls: Vec::new(),
+            });
+        }
+
+        let mut profile = FrameProfile::default();
+        profile.gpu = Some(timings);
+        profiler.finish(profile);
+
+        let unused: SurfaceHandle = Handle::new(2, 1).into();
+        let v = profiler.last_gpu_time(surface);
+        assert_eq!(v, Some(Duration::from_millis(5)));
+        let v = profiler.last_gpu_time(other);
+        assert_eq!(v, Some(Duration::from_millis(1)));
+        assert!(profiler.last_gpu_time(unused).is_none());
+    }
+}
@@ -1,10 +1,16 @@
-use std::sync::{Arc, RwLock};
-use std::time::{Duration, Instant};
+use std::sync::{Arc, Mutex, RwLock};
+use std::time::{Instant, SystemTime, UNIX_EPOCH};
 
+use super::diagnostics::{self, ApplicationTimings, FrameProfile};
 use super::*;
+use audio;
+use capabilities;
 use input;
+use math;
 use res;
 use sched;
+use utils::alloc::{self, AllocCategory};
+use utils::Random;
 use video;
 
 type Result<T> = ::std::result::Result<T, ::failure::Error>;
@@ -12,6 +18,69 @@ type Result<T> = ::std::result::Result<T, ::failure::Error>;
 #[derive(Default, Copy, Clone)]
 struct ContextData {
     shutdown: bool,
+    idle: bool,
+    deterministic: bool,
+    seed: u64,
+}
+
+type MainThreadTask = Box<FnMut(&window::Window) + Send>;
+
+// The idle and background states of main loop, which are driven by the window events
+// and the idle requests of application.
+#[derive(Debug, Clone, Copy)]
+struct LoopState {
+    idle_policy: settings::IdlePolicy,
+    background_policy: settings::BackgroundPolicy,
+    background_resources: settings::BackgroundResourcePolicy,
+    idle: bool,
+    suspended: bool,
+    focused: bool,
+}
+
+impl LoopState {
+    fn new(params: &settings::EngineParams) -> Self {
+        LoopState {
+            idle_policy: params.idle_policy,
+            background_policy: params.background_policy,
+            background_resources: params.background_resources,
+            idle: false,
+            suspended: false,
+            focused: true,
+        }
+    }
+
+    // Returns true if the main loop should block until new events arrive.
+    fn should_wait(&self) -> bool {
+        (self.idle && self.idle_policy == settings::IdlePolicy::WaitEvents) || self.suspended
+    }
+
+    fn on_event(&mut self, v: event::ApplicationEvent) {
+        match v {
+            event::ApplicationEvent::GainFocus => self.focused = true,
+            event::ApplicationEvent::LostFocus => self.focused = false,
+            event::ApplicationEvent::Suspended => {
+                self.suspended = self.background_policy == settings::BackgroundPolicy::Pause;
+            }
+            event::ApplicationEvent::Resumed => self.suspended = false,
+            _ => {}
+        }
+    }
+
+    // Updates the idle state with the explicit request of application, and returns the
+    // event to notify if it's changed.
+    fn update_idle(&mut self, requested: bool) -> Option<event::ApplicationEvent> {
+        let idle = !self.focused || requested;
+        if idle == self.idle {
+            return None;
+        }
+
+        self.idle = idle;
+        if idle {
+            Some(event::ApplicationEvent::Idle)
+        } else {
+            Some(event::ApplicationEvent::Active)
+        }
+    }
 }
 
 /// The context of sub-systems that could be accessed from multi-thread environments safely.
@@ -21,22 +90,82 @@ pub struct Context {
     pub input: Arc<input::InputSystemShared>,
     pub time: Arc<time::TimeSystemShared>,
     pub video: Arc<video::VideoSystemShared>,
+    pub audio: Arc<audio::AudioSystemShared>,
     pub window: Arc<window::WindowShared>,
     pub sched: Arc<sched::ScheduleSystemShared>,
+    pub profiler: Arc<diagnostics::Profiler>,
 
     data: Arc<RwLock<ContextData>>,
+    main_tasks: Arc<Mutex<Vec<MainThreadTask>>>,
+    waker: proxy::Waker,
 }
 
 impl Context {
     /// Shutdown the whole application at the end of this frame.
     pub fn shutdown(&self) {
         self.data.write().unwrap().shutdown = true;
+        self.waker.wakeup();
     }
 
     /// Returns true if we are going to shutdown the application at the end of this frame.
     pub fn is_shutdown(&self) -> bool {
         self.data.read().unwrap().shutdown
     }
+
+    /// Requests the engine to enter (or leave) the idle mode explicitly, which throttles
+    /// the main loop or blocks on events according to the `IdlePolicy`. The main loop is
+    /// woken up when leaving the idle mode.
+    pub fn set_idle(&self, idle: bool) {
+        let wakeup = {
+            let mut data = self.data.write().unwrap();
+            let wakeup = data.idle && !idle;
+            data.idle = idle;
+            wakeup
+        };
+
+        if wakeup {
+            self.waker.wakeup();
+        }
+    }
+
+    /// Returns true if the idle mode is requested explicitly.
+    pub fn is_idle_requested(&self) -> bool {
+        self.data.read().unwrap().idle
+    }
+
+    /// Returns true if the engine runs in determinism mode.
+    pub fn is_deterministic(&self) -> bool {
+        self.data.read().unwrap().deterministic
+    }
+
+    /// Gets the global seed of random streams.
+    pub fn seed(&self) -> u64 {
+        self.data.read().unwrap().seed
+    }
+
+    /// Creates the named random stream derived from the global seed. The streams with
+    /// the same name always produce the same sequence with the same seed.
+    pub fn random(&self, stream: &str) -> Random {
+        Random::from_stream(self.seed(), stream)
+    }
+
+    /// Schedules a closure which will be executed on the main thread, which owns the
+    /// window and OpenGL context, at the start of next frame (right after the events
+    /// polling). The main loop is woken up if its blocking on events.
+    pub fn run_on_main<F>(&self, func: F)
+    where
+        F: FnOnce(&window::Window) + Send + 'static,
+    {
+        let mut func = Some(func);
+        let task = move |window: &window::Window| {
+            if let Some(func) = func.take() {
+                func(window);
+            }
+        };
+
+        self.main_tasks.lock().unwrap().push(Box::new(task));
+        self.waker.wakeup();
+    }
 }
 
 /// `Engine` is the root object of the game application. It binds various sub-systems in
@@ -46,12 +175,20 @@ pub struct Engine {
     pub window: window::Window,
     pub input: input::InputSystem,
     pub video: video::VideoSystem,
+    pub audio: audio::AudioSystem,
     pub res: res::ResourceSystem,
     pub time: time::TimeSystem,
     pub sched: sched::ScheduleSystem,
+    pub watchdog: watchdog::Watchdog,
 
     context: Context,
+    user_events: Arc<Mutex<Vec<proxy::UserEvent>>>,
     headless: bool,
+    state: LoopState,
+    application: Option<Arc<RwLock<Application + Send + Sync>>>,
+    latch: Arc<sched::latch::LockLatch<Result<ApplicationTimings>>>,
+    crash: Option<Arc<crash::CrashState>>,
+    injected_inputs: Vec<event::InputDeviceEvent>,
 }
 
 impl Engine {
@@ -64,6 +201,7 @@ impl Engine {
     pub fn new_with(settings: &Settings) -> Result<Self> {
         let sched = sched::ScheduleSystem::new(6, None, None);
         let sched_shared = sched.shared();
+        sched_shared.set_sequential(settings.engine.deterministic);
 
         let input = input::InputSystem::new(settings.input);
         let input_shared = input.shared();
@@ -74,17 +212,50 @@ impl Engine {
             window::Window::new(settings.window.clone())?
         };
 
-        let res = res::ResourceSystem::new(sched_shared.clone())?;
+        let mut res = res::ResourceSystem::new(sched_shared.clone())?;
+        res.set_variant_policy(settings.res.variants.clone());
+        res.set_cipher_key(settings.res.key);
+        res.set_hot_reload(settings.res.hot_reload);
         let res_shared = res.shared();
 
         let video = if settings.headless {
-            video::VideoSystem::headless()
+            video::VideoSystem::headless_with(settings.video.profile)
         } else {
-            video::VideoSystem::new(&window)?
+            video::VideoSystem::new(&window, settings.video.profile)?
         };
 
         let video_shared = video.shared();
+        video_shared.set_params(settings.video);
+
+        // The games should still run on the machines without audio devices.
+        let audio = if settings.headless {
+            audio::AudioSystem::headless()
+        } else {
+            audio::AudioSystem::new().unwrap_or_else(|err| {
+                warn!("Failed to open audio device, plays nothing instead. {}", err);
+                audio::AudioSystem::headless()
+            })
+        };
 
+        let audio_shared = audio.shared();
+
+        // Wakes up the main loop blocking on events once background jobs complete.
+        let waker = proxy::Waker::with_proxy(window.create_proxy());
+        {
+            let waker = waker.clone();
+            sched_shared.set_background_waker(move || waker.wakeup());
+        }
+
+        capabilities::register(capabilities::Capabilities {
+            version: capabilities::VERSION,
+            platform: capabilities::Platform::current(),
+            workers: sched_shared.workers(),
+            background_workers: sched_shared.background_workers(),
+            headless: settings.headless,
+            video: video_shared.capabilities().clone(),
+        });
+
+        let watchdog = watchdog::Watchdog::new(settings.engine.hitch_threshold);
         let time = time::TimeSystem::new(settings.engine);
         let time_shared = time.shared();
 
@@ -96,26 +267,53 @@ impl Engine {
             video_shared.clone(),
         ));
 
+        res.register(audio::assets::clip_loader::AudioClipLoader::new(
+            audio_shared.clone(),
+        ));
+
+        res.register(audio::assets::stream_loader::AudioStreamLoader::new(
+            audio_shared.clone(),
+        ));
+
+        let data = ContextData {
+            shutdown: false,
+            idle: false,
+            deterministic: settings.engine.deterministic,
+            seed: settings.engine.seed.unwrap_or_else(clock_seed),
+        };
+
         let context = Context {
             res: res_shared,
             input: input_shared,
             time: time_shared,
             video: video_shared,
+            audio: audio_shared,
             window: window.shared(),
             sched: sched_shared,
-            data: Arc::new(RwLock::new(ContextData::default())),
+            profiler: Arc::new(diagnostics::Profiler::new()),
+            data: Arc::new(RwLock::new(data)),
+            main_tasks: Arc::new(Mutex::new(Vec::new())),
+            waker: waker,
         };
 
         Ok(Engine {
             input: input,
             window: window,
             video: video,
+            audio: audio,
             res: res,
             time: time,
             sched: sched,
+            watchdog: watchdog,
 
             context: context,
+            user_events: Arc::new(Mutex::new(Vec::new())),
             headless: settings.headless,
+            state: LoopState::new(&settings.engine),
+            application: None,
+            latch: Arc::new(sched::latch::LockLatch::new()),
+            crash: None,
+            injected_inputs: Vec::new(),
         })
     }
 
@@ -129,100 +327,403 @@ impl Engine {
     where
         T: Application + Send + Sync + 'static,
     {
-        let application = Arc::new(RwLock::new(application));
+        self.attach(application)?;
+        while self.step()? {}
+        self.detach()?;
+        Ok(self)
+    }
+
+    /// Creates a cloneable and thread-safe sender, which could be used to inject custom
+    /// events into the main loop from other threads (e.g. networking).
+    pub fn create_event_proxy(&self) -> proxy::EventProxy {
+        proxy::EventProxy::new(self.user_events.clone(), self.window.create_proxy())
+    }
+
+    /// Sets the behaviour of main loop when the engine is idle.
+    #[inline]
+    pub fn set_idle_policy(&mut self, policy: settings::IdlePolicy) {
+        self.state.idle_policy = policy;
+    }
+
+    /// Installs the panic hook of `CrashHandler`, which writes a crash dump of engine
+    /// state when panicking. The previous panic hook is still called after that.
+    pub fn set_crash_handler(&mut self, handler: crash::CrashHandler) {
+        let state = Arc::new(crash::CrashState::new(
+            self.context.res.clone(),
+            self.context.profiler.clone(),
+        ));
+        crash::install(handler, state.clone());
+        self.crash = Some(state);
+    }
+
+    /// Returns true if the engine is idle.
+    #[inline]
+    pub fn is_idle(&self) -> bool {
+        self.state.idle
+    }
+
+    /// Attaches the application to `Engine`, and starts to update its first frame. The
+    /// frames could be performed manually with `Engine::step` after that.
+    pub fn attach<T>(&mut self, application: T) -> Result<()>
+    where
+        T: Application + Send + Sync + 'static,
+    {
+        if self.application.is_some() {
+            bail!("An application has been attached to the engine already.");
+        }
 
         let dir = ::std::env::current_dir()?;
         info!("CWD: {:?}.", dir);
 
-        let latch = Arc::new(sched::latch::LockLatch::new());
-        Self::execute_frame(&self.context, latch.clone(), application.clone());
+        let application: Arc<RwLock<Application + Send + Sync>> =
+            Arc::new(RwLock::new(application));
+
+        Self::execute_frame(&self.context, self.latch.clone(), application.clone());
+        self.application = Some(application);
+        Ok(())
+    }
+
+    /// Performs exactly one frame, which polls events, updates and renders the attached
+    /// application, and swaps the buffers. Returns false if the application is going to
+    /// exit, `Engine::detach` should be called then.
+    ///
+    /// This is useful for host applications (e.g. editors, test harnesses) which drive
+    /// the frames by themselves.
+    pub fn step(&mut self) -> Result<bool> {
+        let application = match self.application {
+            Some(ref application) => application.clone(),
+            None => bail!("There is no application attached to the engine."),
+        };
+
+        let ts = Instant::now();
+        let mut timepoint = ts;
+        let mut profile = FrameProfile::default();
+
+        self.watchdog.start();
+        self.input.advance(self.window.hidpi());
+
+        // Poll any possible events first, or blocks until new events arrive if we are
+        // idle or paused in background.
+        let wait = self.state.should_wait();
+        let events = if wait {
+            let events = self.window.wait_and_advance();
+            self.watchdog.reset();
+            events
+        } else {
+            self.window.advance()
+        };
 
+        let suspended = self.state.suspended;
         let mut alive = true;
-        while alive {
-            self.input.advance(self.window.hidpi());
-
-            // Poll any possible events first.
-            for v in self.window.advance() {
-                match *v {
-                    event::Event::Application(value) => {
-                        {
-                            let mut application = application.write().unwrap();
-                            application.on_receive_event(&self.context, value)?;
-                        }
-
-                        if let event::ApplicationEvent::Closed = value {
-                            alive = false;
-                        }
+        for v in events {
+            match *v {
+                event::Event::Application(value) => {
+                    {
+                        let mut application = application.write().unwrap();
+                        application.on_receive_event(&self.context, value)?;
                     }
 
-                    event::Event::InputDevice(value) => self.input.update_with(value),
+                    if let event::ApplicationEvent::Closed = value {
+                        alive = false;
+                    }
+
+                    self.state.on_event(value);
                 }
+
+                event::Event::InputDevice(value) => self.input.update_with(value),
             }
+        }
+
+        for v in self.injected_inputs.drain(..) {
+            self.input.update_with(v);
+        }
+
+        let user_events: Vec<_> = self.user_events.lock().unwrap().drain(..).collect();
+        for v in user_events {
+            let mut application = application.write().unwrap();
+            application.on_user_event(&self.context, v)?;
+        }
 
-            alive = alive && !self.context.is_shutdown();
-            if !alive {
-                break;
+        alive = alive && !self.context.is_shutdown();
+        if !alive {
+            return Ok(false);
+        }
+
+        if let Some(value) = self.state.update_idle(self.context.is_idle_requested()) {
+            self.time.set_idle(self.state.idle);
+
+            let mut application = application.write().unwrap();
+            application.on_receive_event(&self.context, value)?;
+        }
+
+        // Nothing touches the OpenGL context until resumed, and the time spent in
+        // background is not simulated. The frame which enters background is finished
+        // to dispatch the deletions of transient resources.
+        if self.state.suspended && suspended {
+            return Ok(true);
+        }
+
+        let suspending = self.state.suspended;
+        if suspending {
+            self.audio.set_paused(true);
+
+            let policy = self.state.background_resources;
+            if policy == settings::BackgroundResourcePolicy::ReleaseTransient {
+                self.video.release_transient();
             }
+        }
+
+        if suspended {
+            self.audio.set_paused(false);
+            self.time.reset();
+            self.watchdog.reset();
+        }
+
+        // Executes the closures scheduled with `Context::run_on_main`.
+        let tasks: Vec<_> = self.context.main_tasks.lock().unwrap().drain(..).collect();
+        for mut v in tasks {
+            v(&self.window);
+        }
+
+        profile.input = diagnostics::lap(&mut timepoint);
+        self.sched.advance();
 
+        {
+            let _scope = alloc::scope(AllocCategory::Resource);
             self.res.advance();
-            self.time.advance();
-            self.video.swap_frames();
-
-            let (video_info, duration) = {
-                let duration = latch.wait_and_take()?;
-
-                // Perform update and render submitting for frame [x], and drawing
-                // frame [x-1] at the same time.
-                Self::execute_frame(&self.context, latch.clone(), application.clone());
-                // This will block the main-thread until all the video commands is finished by GPU.
-                let video_info = self.video.advance(&self.window)?;
-                (video_info, duration)
-            };
+        }
+
+        profile.resources = diagnostics::lap(&mut timepoint);
+        self.time.advance();
+        // The throttling of idle mode is not a hitch.
+        if self.state.idle {
+            self.watchdog.reset();
+        }
 
-            self.window.swap_buffers()?;
+        self.audio.advance(self.time.shared().frame_delta())?;
+        profile.audio = diagnostics::lap(&mut timepoint);
+        self.video.swap_frames();
+
+        let video_info = {
+            profile.application = self.latch.wait_and_take()?;
+            profile.wait = diagnostics::lap(&mut timepoint);
+            // The scopes recorded during the updates of frame [x].
+            profile.systems = self.context.profiler.take_systems();
+
+            // Perform update and render submitting for frame [x], and drawing
+            // frame [x-1] at the same time.
+            Self::execute_frame(&self.context, self.latch.clone(), application.clone());
+
+            let _scope = alloc::scope(AllocCategory::Video);
+            // This will block the main-thread until all the video commands is finished by GPU.
+            let video_info = self.video.advance(&self.window)?;
+            if suspending {
+                self.video.finish()?;
+            }
+
+            profile.video = diagnostics::lap(&mut timepoint);
+            profile.gpu = self.video.take_gpu_timings();
+            video_info
+        };
 
-            {
-                let info = FrameInfo {
-                    video: video_info,
-                    duration: duration,
-                    fps: self.time.shared().get_fps(),
-                };
+        self.window.swap_buffers()?;
+        profile.swap = diagnostics::lap(&mut timepoint);
+
+        {
+            profile.duration = timepoint - ts;
+            let info = FrameInfo {
+                video: video_info,
+                duration: profile.application.total(),
+                fps: self.time.shared().get_fps(),
+                allocations: alloc::advance(),
+                sched_utilization: self.sched.utilization(),
+            };
 
-                let mut application = application.write().unwrap();
-                application.on_post_update(&self.context, &info)?;
+            if let Some(ref crash) = self.crash {
+                crash.advance(&info);
             }
 
-            alive = alive && !self.context.is_shutdown() && !self.headless;
+            self.context.profiler.finish(profile);
+
+            let mut application = application.write().unwrap();
+            application.on_post_update(&self.context, &info)?;
+
+            if let Some(report) = self.watchdog.advance(&info) {
+                warn!("Hitch detected: {:?}.", report);
+                application.on_hitch(&self.context, &report)?;
+            }
         }
 
-        {
+        Ok(!self.context.is_shutdown() && !self.headless)
+    }
+
+    /// Injects an input event, which is handled in the next `Engine::step` as if it
+    /// comes from the window. Its useful to replay or script the inputs.
+    pub fn inject_input(&mut self, event: event::InputDeviceEvent) {
+        self.injected_inputs.push(event);
+    }
+
+    /// Blocks until the update of the latest frame is finished. The updates are
+    /// performed on worker thread in parallel with the drawing of previous frame, so
+    /// the states of application might be not updated yet after `Engine::step`.
+    pub fn sync(&self) {
+        use sched::latch::LatchWaitProbe;
+        self.latch.wait();
+    }
+
+    /// Detaches the application, and calls `Application::on_exit`. The sub-systems are
+    /// terminated after that.
+    pub fn detach(&mut self) -> Result<()> {
+        if let Some(application) = self.application.take() {
             let mut application = application.write().unwrap();
             application.on_exit(&self.context)?;
         }
 
+        // Deletes the retired video objects before the context is destroyed.
+        self.video.finish()?;
         self.sched.terminate();
         self.sched.wait_until_terminated();
-        Ok(self)
+
+        alloc::log_offenders();
+        Ok(())
     }
 
-    fn execute_frame<T>(
+    fn execute_frame(
         ctx: &Context,
-        latch: Arc<sched::latch::LockLatch<Result<Duration>>>,
-        app: Arc<RwLock<T>>,
-    ) where
-        T: Application + Send + Sync + 'static,
-    {
-        let run = |ctx, app: Arc<RwLock<T>>| {
-            let ts = Instant::now();
+        latch: Arc<sched::latch::LockLatch<Result<ApplicationTimings>>>,
+        app: Arc<RwLock<Application + Send + Sync>>,
+    ) {
+        let run = |ctx: Context, app: Arc<RwLock<Application + Send + Sync>>| {
+            let mut timings = ApplicationTimings::default();
+            let mut ts = Instant::now();
+            let _scope = alloc::scope(AllocCategory::Application);
 
             let mut application = app.write().unwrap();
+            application.on_pre_update(&ctx)?;
+            timings.pre_update = diagnostics::lap(&mut ts);
+
+            let deterministic = ctx.is_deterministic();
+            for _ in 0..ctx.time.fixed_steps() {
+                if deterministic {
+                    math::float::reset_float_env();
+                }
+
+                ctx.time.begin_fixed_update();
+                application.on_fixed_update(&ctx)?;
+            }
+
+            timings.fixed_update = diagnostics::lap(&mut ts);
             application.on_update(&ctx)?;
+            timings.update = diagnostics::lap(&mut ts);
+            application.on_late_update(&ctx)?;
+            timings.late_update = diagnostics::lap(&mut ts);
+            application.on_pre_render(&ctx)?;
+            timings.pre_render = diagnostics::lap(&mut ts);
             application.on_render(&ctx)?;
+            timings.render = diagnostics::lap(&mut ts);
 
-            Ok(Instant::now() - ts)
+            Ok(timings)
         };
 
         let ctx_clone = ctx.clone();
         ctx.sched.spawn(move || latch.set(run(ctx_clone, app)));
     }
 }
+
+fn clock_seed() -> u64 {
+    let now = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default();
+
+    now.as_secs() ^ u64::from(now.subsec_nanos()).rotate_left(32)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn is_idle_event(v: Option<event::ApplicationEvent>) -> bool {
+        match v {
+            Some(event::ApplicationEvent::Idle) => true,
+            _ => false,
+        }
+    }
+
+    fn is_active_event(v: Option<event::ApplicationEvent>) -> bool {
+        match v {
+            Some(event::ApplicationEvent::Active) => true,
+            _ => false,
+        }
+    }
+
+    #[test]
+    fn idle() {
+        let mut params = settings::EngineParams::default();
+        params.idle_policy = settings::IdlePolicy::WaitEvents;
+
+        let mut state = LoopState::new(&params);
+        assert!(!state.idle);
+        assert!(!state.should_wait());
+        assert!(state.update_idle(false).is_none());
+
+        assert!(is_idle_event(state.update_idle(true)));
+        assert!(state.idle);
+        assert!(state.should_wait());
+        assert!(state.update_idle(true).is_none());
+
+        assert!(is_active_event(state.update_idle(false)));
+        assert!(!state.idle);
+        assert!(!state.should_wait());
+
+        state.idle_policy = settings::IdlePolicy::Throttle;
+        assert!(is_idle_event(state.update_idle(true)));
+        assert!(!state.should_wait());
+    }
+
+    #[test]
+    fn focus() {
+        let mut params = settings::EngineParams::default();
+        params.idle_policy = settings::IdlePolicy::WaitEvents;
+
+        let mut state = LoopState::new(&params);
+        state.on_event(event::ApplicationEvent::LostFocus);
+        assert!(!state.focused);
+        assert!(is_idle_event(state.update_idle(false)));
+        assert!(state.should_wait());
+
+        // The explicit requests can't make an unfocused engine active.
+        assert!(state.update_idle(true).is_none());
+        assert!(state.update_idle(false).is_none());
+
+        state.on_event(event::ApplicationEvent::GainFocus);
+        assert!(state.focused);
+        assert!(is_active_event(state.update_idle(false)));
+        assert!(!state.should_wait());
+
+        state.on_event(event::ApplicationEvent::GainFocus);
+        assert!(is_idle_event(state.update_idle(true)));
+    }
+
+    #[test]
+    fn suspend() {
+        let mut params = settings::EngineParams::default();
+        params.background_policy = settings::BackgroundPolicy::Pause;
+
+        let mut state = LoopState::new(&params);
+        state.on_event(event::ApplicationEvent::Suspended);
+        assert!(state.suspended);
+        assert!(state.should_wait());
+
+        state.on_event(event::ApplicationEvent::Resumed);
+        assert!(!state.suspended);
+        assert!(!state.should_wait());
+
+        state.background_policy = settings::BackgroundPolicy::Continue;
+        state.on_event(event::ApplicationEvent::Suspended);
+        assert!(!state.suspended);
+        assert!(!state.should_wait());
+
+        state.on_event(event::ApplicationEvent::Resized(1, 1));
+        assert!(!state.suspended && state.focused && !state.idle);
+    }
+}
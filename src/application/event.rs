@@ -1,7 +1,9 @@
 //! Responsible for converting window messages to input state and internal events.
 
 use glutin;
+pub use glutin::DeviceId;
 pub use glutin::MouseButton;
+pub use glutin::ScanCode;
 pub use glutin::VirtualKeyCode as KeyboardButton;
 
 use math;
@@ -25,6 +27,10 @@ pub enum ApplicationEvent {
     Resized(u32, u32),
     /// The position of window has changed.
     Moved(u32, u32),
+    /// The engine entered the idle mode.
+    Idle,
+    /// The engine left the idle mode.
+    Active,
 }
 
 /// Input device event, supports mouse and keyboard only.
@@ -40,11 +46,23 @@ pub enum InputDeviceEvent {
     MouseReleased { button: MouseButton },
     /// A mouse wheel movement or touchpad scroll occurred.
     MouseWheel { delta: (f32, f32) },
+    /// The raw relative motion of a mouse device, which is not affected by the cursor
+    /// acceleration or clamped to the window. The delta is measured in device units,
+    /// and the y axis points upward.
+    MouseRawMoved { device: DeviceId, delta: (f32, f32) },
 
-    /// Pressed event on keyboard has been received.
-    KeyboardPressed { key: KeyboardButton },
+    /// Pressed event on keyboard has been received. The `scancode` identifies the
+    /// physical key, regardless of the keyboard layout. The `key` is `None` if the
+    /// key has no virtual keycode.
+    KeyboardPressed {
+        key: Option<KeyboardButton>,
+        scancode: ScanCode,
+    },
     /// Released event from keyboard has been received.
-    KeyboardReleased { key: KeyboardButton },
+    KeyboardReleased {
+        key: Option<KeyboardButton>,
+        scancode: ScanCode,
+    },
     /// Received a unicode character.
     ReceivedCharacter { character: char },
 
@@ -79,7 +97,20 @@ pub(crate) fn from_event(source: glutin::Event, dimensions: math::Vector2<u32>)
             Some(Event::Application(ApplicationEvent::Resumed))
         },
 
-        glutin::Event::DeviceEvent { .. } => None,
+        glutin::Event::DeviceEvent { device_id, event } => from_device_event(device_id, &event),
+    }
+}
+
+fn from_device_event(device: glutin::DeviceId, source: &glutin::DeviceEvent) -> Option<Event> {
+    match *source {
+        glutin::DeviceEvent::MouseMotion { delta } => {
+            Some(Event::InputDevice(InputDeviceEvent::MouseRawMoved {
+                device: device,
+                delta: (delta.0 as f32, -delta.1 as f32),
+            }))
+        }
+
+        _ => None,
     }
 }
 
@@ -135,24 +166,28 @@ fn from_window_event(
             input:
                 glutin::KeyboardInput {
                     state: glutin::ElementState::Pressed,
-                    virtual_keycode: Some(key),
+                    virtual_keycode: key,
+                    scancode,
                     ..
                 },
             ..
         } => Some(Event::InputDevice(InputDeviceEvent::KeyboardPressed {
             key,
+            scancode,
         })),
 
         glutin::WindowEvent::KeyboardInput {
             input:
                 glutin::KeyboardInput {
                     state: glutin::ElementState::Released,
-                    virtual_keycode: Some(key),
+                    virtual_keycode: key,
+                    scancode,
                     ..
                 },
             ..
         } => Some(Event::InputDevice(InputDeviceEvent::KeyboardReleased {
             key,
+            scancode,
         })),
 
         glutin::WindowEvent::ReceivedCharacter(character) => Some(Event::InputDevice(
@@ -164,6 +199,7 @@ fn from_window_event(
                 id: touch.id as u8,
                 state: from_touch_state(touch.phase),
                 position: (touch.location.x as f32, touch.location.y as f32).into(),
+                pressure: touch_pressure(&touch),
             };
 
             Some(Event::InputDevice(InputDeviceEvent::Touch(evt)))
@@ -173,6 +209,21 @@ fn from_window_event(
     }
 }
 
+#[cfg(target_os = "ios")]
+fn touch_pressure(touch: &glutin::Touch) -> f32 {
+    let released = match touch.phase {
+        glutin::TouchPhase::Ended | glutin::TouchPhase::Cancelled => true,
+        _ => false,
+    };
+
+    super::ios::touch_force(touch.id, released)
+}
+
+#[cfg(not(target_os = "ios"))]
+fn touch_pressure(_: &glutin::Touch) -> f32 {
+    1.0
+}
+
 fn from_touch_state(state: glutin::TouchPhase) -> TouchState {
     match state {
         glutin::TouchPhase::Started => TouchState::Start,
@@ -196,6 +247,9 @@ pub struct TouchEvent {
     pub id: u8,
     pub state: TouchState,
     pub position: math::Vector2<f32>,
+    /// The normalized force of touch, where 1.0 is the force of an average touch. The
+    /// platforms which can't sense force always report 1.0.
+    pub pressure: f32,
 }
 
 impl Default for TouchEvent {
@@ -204,6 +258,7 @@ impl Default for TouchEvent {
             id: 0,
             state: TouchState::End,
             position: math::Vector2::new(0.0, 0.0),
+            pressure: 1.0,
         }
     }
 }
@@ -0,0 +1,182 @@
+//! The UIKit specific parts of window on iOS, which are not covered by glutin.
+//!
+//! The `UIView` created by glutin is backed by a `CAEAGLLayer`, and the OpenGL ES
+//! context draws into it directly. These helpers query and tweak the view with the
+//! Objective-C runtime.
+
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::os::raw::c_void;
+use std::sync::{Once, ONCE_INIT};
+
+use objc::declare::ClassDecl;
+use objc::runtime::{Class, Object, Sel, BOOL, NO};
+
+use super::window::SafeAreaInsets;
+
+const UI_TOUCH_PHASE_ENDED: i64 = 3;
+const UI_TOUCH_PHASE_CANCELLED: i64 = 4;
+const UI_GESTURE_RECOGNIZER_STATE_FAILED: i64 = 5;
+
+thread_local! {
+    // The forces of touches, keyed by the addresses of `UITouch` which glutin uses as
+    // the ids of touch events too.
+    static FORCES: RefCell<HashMap<u64, f32>> = RefCell::new(HashMap::new());
+}
+
+#[repr(C)]
+#[derive(Debug, Clone, Copy)]
+struct UIEdgeInsets {
+    top: f64,
+    left: f64,
+    bottom: f64,
+    right: f64,
+}
+
+/// Makes the layer of view render at the native resolution of retina screen, and
+/// returns the scale factor from points to pixels.
+pub unsafe fn set_native_scale(view: *mut c_void) -> f32 {
+    let view = view as *mut Object;
+    let screen: *mut Object = msg_send![class!(UIScreen), mainScreen];
+    let scale: f64 = msg_send![screen, nativeScale];
+    let _: () = msg_send![view, setContentScaleFactor: scale];
+    scale as f32
+}
+
+/// Gets the scale factor from points to pixels of view.
+pub unsafe fn content_scale(view: *mut c_void) -> f32 {
+    let view = view as *mut Object;
+    let scale: f64 = msg_send![view, contentScaleFactor];
+    scale as f32
+}
+
+/// Gets the insets of view which are obscured by the notches, rounded corners or
+/// home indicator. They are always zero before iOS 11.
+pub unsafe fn safe_area_insets(view: *mut c_void) -> SafeAreaInsets {
+    let view = view as *mut Object;
+    let supported: BOOL = msg_send![view, respondsToSelector: sel!(safeAreaInsets)];
+    if supported == NO {
+        return SafeAreaInsets::default();
+    }
+
+    let insets: UIEdgeInsets = msg_send![view, safeAreaInsets];
+    SafeAreaInsets {
+        left: insets.left as f32,
+        top: insets.top as f32,
+        right: insets.right as f32,
+        bottom: insets.bottom as f32,
+    }
+}
+
+/// Installs a gesture recognizer on view, which records the forces of touches without
+/// recognizing anything, so the touch events are still delivered to glutin as usual.
+pub unsafe fn observe_touch_forces(view: *mut c_void) {
+    let view = view as *mut Object;
+    let recognizer: *mut Object = msg_send![touch_force_recognizer(), alloc];
+    let recognizer: *mut Object = msg_send![recognizer, init];
+    let _: () = msg_send![recognizer, setCancelsTouchesInView: NO];
+    let _: () = msg_send![recognizer, setDelaysTouchesBegan: NO];
+    let _: () = msg_send![recognizer, setDelaysTouchesEnded: NO];
+    let _: () = msg_send![view, addGestureRecognizer: recognizer];
+    let _: () = msg_send![recognizer, release];
+}
+
+/// Gets the normalized force of touch `id`, where 1.0 is the force of an average
+/// touch. The record is forgotten once the touch is `released`. It's always 1.0 on the
+/// devices without 3D Touch.
+pub fn touch_force(id: u64, released: bool) -> f32 {
+    FORCES.with(|forces| {
+        let mut forces = forces.borrow_mut();
+        let force = forces.get(&id).cloned().unwrap_or(1.0);
+        if released {
+            forces.remove(&id);
+        }
+
+        force
+    })
+}
+
+fn touch_force_recognizer() -> &'static Class {
+    static REGISTER: Once = ONCE_INIT;
+
+    REGISTER.call_once(|| {
+        let superclass = class!(UIGestureRecognizer);
+        let mut decl = ClassDecl::new("CrayonTouchForceRecognizer", superclass).unwrap();
+
+        unsafe {
+            let began: extern "C" fn(&mut Object, Sel, *mut Object, *mut Object) = touches_began;
+            let moved: extern "C" fn(&mut Object, Sel, *mut Object, *mut Object) = touches_moved;
+            let ended: extern "C" fn(&mut Object, Sel, *mut Object, *mut Object) = touches_ended;
+            decl.add_method(sel!(touchesBegan:withEvent:), began);
+            decl.add_method(sel!(touchesMoved:withEvent:), moved);
+            decl.add_method(sel!(touchesEnded:withEvent:), ended);
+            decl.add_method(sel!(touchesCancelled:withEvent:), ended);
+        }
+
+        decl.register();
+    });
+
+    Class::get("CrayonTouchForceRecognizer").unwrap()
+}
+
+extern "C" fn touches_began(this: &mut Object, _: Sel, touches: *mut Object, event: *mut Object) {
+    unsafe {
+        let superclass = class!(UIGestureRecognizer);
+        let _: () = msg_send![super(this, superclass), touchesBegan: touches withEvent: event];
+        record_touch_forces(touches);
+    }
+}
+
+extern "C" fn touches_moved(this: &mut Object, _: Sel, touches: *mut Object, event: *mut Object) {
+    unsafe {
+        let superclass = class!(UIGestureRecognizer);
+        let _: () = msg_send![super(this, superclass), touchesMoved: touches withEvent: event];
+        record_touch_forces(touches);
+    }
+}
+
+extern "C" fn touches_ended(this: &mut Object, _: Sel, touches: *mut Object, event: *mut Object) {
+    unsafe {
+        record_touch_forces(touches);
+
+        // The recognizer never succeeds, it fails after all the touches are released to
+        // get reset for the next sequence of touches.
+        let all: *mut Object = msg_send![event, allTouches];
+        let all: *mut Object = msg_send![all, allObjects];
+        let len: usize = msg_send![all, count];
+
+        let released = (0..len).all(|i| {
+            let touch: *mut Object = msg_send![all, objectAtIndex: i];
+            let phase: i64 = msg_send![touch, phase];
+            phase == UI_TOUCH_PHASE_ENDED || phase == UI_TOUCH_PHASE_CANCELLED
+        });
+
+        if released {
+            let _: () = msg_send![this, setState: UI_GESTURE_RECOGNIZER_STATE_FAILED];
+        }
+    }
+}
+
+unsafe fn record_touch_forces(touches: *mut Object) {
+    let touches: *mut Object = msg_send![touches, allObjects];
+    let len: usize = msg_send![touches, count];
+
+    for i in 0..len {
+        let touch: *mut Object = msg_send![touches, objectAtIndex: i];
+        let supported: BOOL = msg_send![touch, respondsToSelector: sel!(force)];
+        let force = if supported == NO {
+            1.0
+        } else {
+            let max: f64 = msg_send![touch, maximumPossibleForce];
+            let force: f64 = msg_send![touch, force];
+            // Devices without 3D Touch report zero for both.
+            if max > 0.0 {
+                force as f32
+            } else {
+                1.0
+            }
+        };
+
+        FORCES.with(|forces| forces.borrow_mut().insert(touch as u64, force));
+    }
+}
@@ -0,0 +1,503 @@
+//! Lockstep simulations with input delay and rollbacks, which are usually used by the
+//! fighting games and RTSs to synchronize peers by exchanging inputs only.
+//!
+//! The simulation is advanced at fixed ticks with the inputs of all players. The local
+//! inputs are scheduled `input_delay` ticks later to hide the latencies of network, and
+//! the missing remote inputs are predicted by repeating their last known inputs. When a
+//! remote input arrives and differs from the prediction, the state is restored to the
+//! snapshot before that tick and the following ticks are simulated again.
+//!
+//! The simulation must be deterministic, see `EngineParams::deterministic`.
+//!
+//! ```rust,ignore
+//! fn on_fixed_update(&mut self, ctx: &Context) -> Result<()> {
+//!     if let Some(tick) = self.lockstep.add_local_input(self.sample_input(ctx)) {
+//!         self.socket.send(tick, self.lockstep.local_input(tick));
+//!     }
+//!
+//!     for (player, tick, input) in self.socket.receive() {
+//!         self.lockstep.add_remote_input(player, tick, input)?;
+//!     }
+//!
+//!     self.lockstep.advance(&mut self.game)?;
+//!     Ok(())
+//! }
+//! ```
+
+use std::collections::VecDeque;
+
+use errors::*;
+
+/// The simulation driven by `Lockstep`.
+pub trait Simulation {
+    /// The input of a player in one tick.
+    type Input: Clone + PartialEq + Default;
+    /// The snapshot of simulation states.
+    type State;
+
+    /// Advances the simulation by one tick with the inputs of all players.
+    fn step(&mut self, tick: u64, inputs: &[Self::Input]) -> Result<()>;
+
+    /// Takes a snapshot of current states.
+    fn save(&self) -> Self::State;
+
+    /// Restores the states from snapshot.
+    fn restore(&mut self, state: &Self::State);
+
+    /// `on_rollback` is called after restoring the states of `tick`, before the ticks
+    /// are simulated again. It could be used to cancel the predicted effects like
+    /// sounds or particles.
+    fn on_rollback(&mut self, _tick: u64) {}
+}
+
+/// The setup parameters of `Lockstep`. All the peers must use the same parameters
+/// except `local`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct LockstepParams {
+    /// The number of players.
+    pub players: usize,
+    /// The index of local player.
+    pub local: usize,
+    /// The number of ticks the local inputs are delayed.
+    pub input_delay: u32,
+    /// The maximum number of ticks could be predicted ahead of the confirmed inputs. A
+    /// value of 0 waits for the inputs of all players before every tick, which is the
+    /// classic lockstep without rollbacks.
+    pub max_rollback: u32,
+}
+
+impl Default for LockstepParams {
+    fn default() -> Self {
+        LockstepParams {
+            players: 2,
+            local: 0,
+            input_delay: 2,
+            max_rollback: 8,
+        }
+    }
+}
+
+struct Frame<T: Simulation> {
+    // The confirmed inputs of players.
+    inputs: Vec<Option<T::Input>>,
+    // The inputs which this tick has been simulated with.
+    simulated: Vec<T::Input>,
+    // The states before this tick is simulated.
+    state: Option<T::State>,
+}
+
+pub struct Lockstep<T: Simulation> {
+    params: LockstepParams,
+    frames: VecDeque<Frame<T>>,
+    // The tick of `frames[0]`.
+    base: u64,
+    // The next tick to simulate.
+    tick: u64,
+    // The first tick whose inputs are not all confirmed.
+    confirmed: u64,
+    // The next tick of local input.
+    local_tick: u64,
+    // The inputs of the last discarded tick, which are used to predict the ticks
+    // without any known inputs.
+    last: Vec<T::Input>,
+    // The earliest tick that has been simulated with mispredicted inputs.
+    rollback: Option<u64>,
+    rollbacks: u32,
+}
+
+impl<T: Simulation> Lockstep<T> {
+    pub fn new(params: LockstepParams) -> Result<Self> {
+        if params.local >= params.players {
+            bail!(
+                "The local player {} is out of {} players.",
+                params.local,
+                params.players
+            );
+        }
+
+        Ok(Lockstep {
+            params: params,
+            frames: VecDeque::new(),
+            base: 0,
+            tick: 0,
+            confirmed: 0,
+            local_tick: u64::from(params.input_delay),
+            last: vec![T::Input::default(); params.players],
+            rollback: None,
+            rollbacks: 0,
+        })
+    }
+
+    /// Gets the next tick to simulate.
+    #[inline]
+    pub fn tick(&self) -> u64 {
+        self.tick
+    }
+
+    /// Gets the first tick whose inputs are not all confirmed. The ticks before it
+    /// would never be rolled back.
+    #[inline]
+    pub fn confirmed_tick(&self) -> u64 {
+        self.confirmed
+    }
+
+    /// Gets the number of rollbacks since created.
+    #[inline]
+    pub fn rollbacks(&self) -> u32 {
+        self.rollbacks
+    }
+
+    /// Schedules the input of local player at `input_delay` ticks later, and returns
+    /// the tick it's scheduled, which should be sent to the other peers.
+    ///
+    /// Returns `None` if the local inputs are scheduled `max_rollback + input_delay`
+    /// ticks ahead of the confirmed inputs already, which happens when `advance` keeps
+    /// waiting for the other peers. The input should be discarded in this case.
+    pub fn add_local_input(&mut self, input: T::Input) -> Option<u64> {
+        if self.local_tick > self.confirmed + self.window() {
+            return None;
+        }
+
+        let tick = self.local_tick;
+        self.local_tick += 1;
+
+        let local = self.params.local;
+        self.add_input(local, tick, input);
+        Some(tick)
+    }
+
+    /// Gets the input of local player at `tick`, if it has not been discarded.
+    pub fn local_input(&self, tick: u64) -> Option<&T::Input> {
+        let local = self.params.local;
+        self.frame(tick).and_then(|v| v.inputs[local].as_ref())
+    }
+
+    /// Adds the input of remote player at `tick`. If the tick has been simulated with a
+    /// different prediction, it will be simulated again in next `advance`.
+    ///
+    /// The peers could have confirmed the ticks whose inputs are still on the way here,
+    /// so the remote inputs could be scheduled ahead of the local confirmed inputs by
+    /// up to twice of `max_rollback + input_delay` ticks. The inputs beyond that could
+    /// never be sent by the peers running with the same parameters, and are rejected.
+    pub fn add_remote_input(&mut self, player: usize, tick: u64, input: T::Input) -> Result<()> {
+        if player >= self.params.players || player == self.params.local {
+            bail!("Player {} is not a remote player.", player);
+        }
+
+        if tick < self.base {
+            bail!(
+                "The input of player {} at tick {} arrives too late.",
+                player,
+                tick
+            );
+        }
+
+        if tick > self.remote_horizon() {
+            bail!(
+                "The input of player {} at tick {} is too far ahead of the confirmed tick {}.",
+                player,
+                tick,
+                self.confirmed
+            );
+        }
+
+        self.add_input(player, tick, input);
+        Ok(())
+    }
+
+    /// Advances the simulation by one tick, the mispredicted ticks are simulated again
+    /// first. Returns false if it has to wait for the inputs of other peers.
+    pub fn advance(&mut self, sim: &mut T) -> Result<bool> {
+        if let Some(tick) = self.rollback.take() {
+            sim.restore(self.frame(tick).and_then(|v| v.state.as_ref()).unwrap());
+            sim.on_rollback(tick);
+            self.rollbacks += 1;
+
+            for v in tick..self.tick {
+                self.simulate(sim, v)?;
+            }
+        }
+
+        let ahead = self.tick + 1 > self.confirmed + u64::from(self.params.max_rollback);
+        if ahead {
+            return Ok(false);
+        }
+
+        let tick = self.tick;
+        self.simulate(sim, tick)?;
+        self.tick += 1;
+
+        // The confirmed ticks would never be rolled back, so its safe to discard them.
+        let discard = self.confirmed.min(self.tick);
+        while self.base < discard {
+            // The confirmed ticks have the inputs of all players.
+            if let Some(frame) = self.frames.pop_front() {
+                self.last = frame
+                    .inputs
+                    .into_iter()
+                    .map(|v| v.unwrap_or_default())
+                    .collect();
+            }
+
+            self.base += 1;
+        }
+
+        Ok(true)
+    }
+
+    fn simulate(&mut self, sim: &mut T, tick: u64) -> Result<()> {
+        let state = sim.save();
+        let inputs = self.predict(tick);
+
+        {
+            let frame = self.frame_mut(tick);
+            frame.state = Some(state);
+            frame.simulated = inputs.clone();
+        }
+
+        sim.step(tick, &inputs)
+    }
+
+    // Gets the inputs at `tick`, the missing ones are predicted with the last known
+    // inputs of players.
+    fn predict(&self, tick: u64) -> Vec<T::Input> {
+        (0..self.params.players)
+            .map(|player| {
+                (self.base..tick + 1)
+                    .rev()
+                    .filter_map(|v| self.frame(v).and_then(|v| v.inputs[player].clone()))
+                    .next()
+                    .unwrap_or_else(|| self.last[player].clone())
+            })
+            .collect()
+    }
+
+    fn add_input(&mut self, player: usize, tick: u64, input: T::Input) {
+        let mispredicted = {
+            let simulated = tick < self.tick;
+            let frame = self.frame_mut(tick);
+            if frame.inputs[player].is_some() {
+                return;
+            }
+
+            let mispredicted = simulated && frame.simulated[player] != input;
+            frame.inputs[player] = Some(input);
+            mispredicted
+        };
+
+        if mispredicted {
+            self.rollback = Some(self.rollback.map_or(tick, |v| v.min(tick)));
+        }
+
+        while self
+            .frame(self.confirmed)
+            .map(|v| v.inputs.iter().all(|v| v.is_some()))
+            .unwrap_or(false)
+        {
+            self.confirmed += 1;
+        }
+    }
+
+    // Gets the number of ticks the local inputs could be scheduled ahead of the
+    // confirmed inputs.
+    #[inline]
+    fn window(&self) -> u64 {
+        u64::from(self.params.max_rollback) + u64::from(self.params.input_delay)
+    }
+
+    // Gets the last tick which could be scheduled by the peers.
+    #[inline]
+    fn remote_horizon(&self) -> u64 {
+        self.confirmed + self.window() * 2 + 1
+    }
+
+    fn frame(&self, tick: u64) -> Option<&Frame<T>> {
+        if tick < self.base {
+            return None;
+        }
+
+        self.frames.get((tick - self.base) as usize)
+    }
+
+    fn frame_mut(&mut self, tick: u64) -> &mut Frame<T> {
+        debug_assert!(tick <= self.remote_horizon());
+
+        let players = self.params.players;
+        let delay = u64::from(self.params.input_delay);
+
+        while self.base + (self.frames.len() as u64) <= tick {
+            // Nobody has inputs in the first `input_delay` ticks.
+            let v = self.base + self.frames.len() as u64;
+            let inputs = if v < delay {
+                vec![Some(T::Input::default()); players]
+            } else {
+                vec![None; players]
+            };
+
+            self.frames.push_back(Frame {
+                inputs: inputs,
+                simulated: Vec::new(),
+                state: None,
+            });
+        }
+
+        &mut self.frames[(tick - self.base) as usize]
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    struct Counter {
+        values: [i32; 2],
+        rollbacks: Vec<u64>,
+    }
+
+    impl Simulation for Counter {
+        type Input = i32;
+        type State = [i32; 2];
+
+        fn step(&mut self, _: u64, inputs: &[i32]) -> Result<()> {
+            for (i, v) in inputs.iter().enumerate() {
+                self.values[i] = self.values[i] * 3 + v;
+            }
+
+            Ok(())
+        }
+
+        fn save(&self) -> [i32; 2] {
+            self.values
+        }
+
+        fn restore(&mut self, state: &[i32; 2]) {
+            self.values = *state;
+        }
+
+        fn on_rollback(&mut self, tick: u64) {
+            self.rollbacks.push(tick);
+        }
+    }
+
+    fn counter() -> Counter {
+        Counter {
+            values: [0, 0],
+            rollbacks: Vec::new(),
+        }
+    }
+
+    fn params(max_rollback: u32) -> LockstepParams {
+        LockstepParams {
+            players: 2,
+            local: 0,
+            input_delay: 1,
+            max_rollback: max_rollback,
+        }
+    }
+
+    #[test]
+    fn lockstep() {
+        let mut lockstep = Lockstep::new(params(0)).unwrap();
+        let mut sim = counter();
+
+        // The first tick has no inputs.
+        assert_eq!(lockstep.add_local_input(1), Some(1));
+        assert_eq!(lockstep.local_input(1), Some(&1));
+        assert!(lockstep.advance(&mut sim).unwrap());
+        assert_eq!(sim.values, [0, 0]);
+
+        // Waits for the remote input.
+        assert!(!lockstep.advance(&mut sim).unwrap());
+        lockstep.add_remote_input(1, 1, 2).unwrap();
+        assert!(lockstep.advance(&mut sim).unwrap());
+        assert_eq!(sim.values, [1, 2]);
+        assert_eq!(lockstep.confirmed_tick(), 2);
+
+        assert!(lockstep.add_remote_input(1, 0, 2).is_err());
+        assert!(lockstep.add_remote_input(0, 3, 2).is_err());
+
+        let params = LockstepParams {
+            local: 2,
+            ..params(0)
+        };
+
+        assert!(Lockstep::<Counter>::new(params).is_err());
+    }
+
+    #[test]
+    fn rollback() {
+        let mut lockstep = Lockstep::new(params(4)).unwrap();
+        let mut sim = counter();
+
+        for i in 1..5 {
+            lockstep.add_local_input(i);
+            assert!(lockstep.advance(&mut sim).unwrap());
+        }
+
+        // The remote inputs are predicted as default.
+        assert_eq!(sim.values, [18, 0]);
+        assert_eq!(lockstep.confirmed_tick(), 1);
+
+        // Could not predict more than 4 ticks ahead of the confirmed inputs.
+        assert!(lockstep.advance(&mut sim).unwrap());
+        assert!(!lockstep.advance(&mut sim).unwrap());
+        assert_eq!(lockstep.tick(), 5);
+
+        // The predictions fail since tick 2.
+        for tick in 1..5 {
+            let input = if tick < 2 { 0 } else { 5 };
+            lockstep.add_remote_input(1, tick, input).unwrap();
+        }
+
+        assert!(lockstep.advance(&mut sim).unwrap());
+        assert_eq!(sim.rollbacks, [2]);
+        assert_eq!(lockstep.rollbacks(), 1);
+        assert_eq!(lockstep.confirmed_tick(), 5);
+
+        // Simulates the same inputs without predictions, the tick 5 is predicted with
+        // the last known inputs.
+        let mut expected = counter();
+        let inputs = [[0, 0], [1, 0], [2, 5], [3, 5], [4, 5], [4, 5]];
+        for (i, v) in inputs.iter().enumerate() {
+            expected.step(i as u64, v).unwrap();
+        }
+
+        assert_eq!(sim.values, expected.values);
+    }
+
+    #[test]
+    fn horizon() {
+        let mut lockstep = Lockstep::<Counter>::new(params(0)).unwrap();
+
+        // The first tick has no inputs, so it's confirmed already.
+        assert_eq!(lockstep.add_local_input(1), Some(1));
+        assert_eq!(lockstep.confirmed_tick(), 1);
+        assert_eq!(lockstep.add_local_input(1), Some(2));
+        assert_eq!(lockstep.add_local_input(1), None);
+
+        assert!(lockstep.add_remote_input(1, 4, 0).is_ok());
+        assert!(lockstep.add_remote_input(1, 5, 0).is_err());
+    }
+
+    #[test]
+    fn predict_after_discarded() {
+        let mut lockstep = Lockstep::new(params(4)).unwrap();
+        let mut sim = counter();
+
+        lockstep.add_local_input(1);
+        lockstep.add_remote_input(1, 1, 2).unwrap();
+        assert!(lockstep.advance(&mut sim).unwrap());
+        assert!(lockstep.advance(&mut sim).unwrap());
+        assert_eq!(lockstep.confirmed_tick(), 2);
+        assert_eq!(sim.values, [1, 2]);
+
+        // The confirmed ticks are discarded, but their inputs are still predicted.
+        assert!(lockstep.advance(&mut sim).unwrap());
+        assert_eq!(sim.values, [4, 8]);
+
+        lockstep.add_local_input(1);
+        lockstep.add_remote_input(1, 2, 2).unwrap();
+        assert!(lockstep.advance(&mut sim).unwrap());
+        assert_eq!(lockstep.rollbacks(), 0);
+    }
+}
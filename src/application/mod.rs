@@ -25,17 +25,45 @@
 //! `Engine` mentioned above is the most fundamental module in crayon. It binds various
 //! essential systems in a central place, and responsible for running the main loop.
 //!
+//! Host applications like editors or test harnesses could also drive the frames manually:
+//!
+//! ```rust,ignore
+//! let mut engine = Engine::new().unwrap();
+//! engine.attach(window).unwrap();
+//! while engine.step().unwrap() {
+//!     // Interleaves your own works here.
+//! }
+//! engine.detach().unwrap();
+//! ```
+//!
 
+pub mod crash;
+pub mod cursor;
+pub mod diagnostics;
 pub mod event;
+#[cfg(target_os = "ios")]
+mod ios;
+pub mod lockstep;
+pub mod proxy;
+pub mod script;
 pub mod settings;
 pub mod time;
+pub mod watchdog;
 pub mod window;
-pub use self::settings::Settings;
+pub use self::settings::{
+    BackbufferParams, BackgroundPolicy, BackgroundResourcePolicy, DepthFormat, IdlePolicy, Settings,
+};
 
 mod engine;
 pub use self::engine::{Context, Engine};
 
 pub mod prelude {
+    pub use super::crash::{CrashHandler, CrashParams};
+    pub use super::cursor::{Cursor, CursorIcon, CustomCursor};
+    pub use super::diagnostics::{FrameProfile, Profiler};
+    pub use super::proxy::{EventProxy, UserEvent};
+    pub use super::script::InputScript;
+    pub use super::watchdog::HitchReport;
     pub use super::FrameInfo;
     pub use super::{Application, Context, Engine, Settings};
     pub use errors::Result;
@@ -52,17 +80,62 @@ pub struct FrameInfo {
     pub video: VideoFrameInfo,
     pub duration: Duration,
     pub fps: u32,
+    /// The number of heap allocations during last frame, which is always zero if the
+    /// `utils::alloc::TrackingAllocator` is not installed.
+    pub allocations: usize,
+    /// The ratio of time the workers of `sched` spent on executing jobs during last
+    /// frame, in range `[0, 1]`.
+    pub sched_utilization: f32,
 }
 
 /// `Application` is a user-friendly facade to build application, which consists of
 /// several event functions that get executed in a pre-determined order.
+///
+/// In each frame, the events of window are dispatched with `on_receive_event` and
+/// `on_user_event` first, and the input, time and resource systems are advanced. And
+/// then the per-frame phases are executed in order:
+///
+/// 1. `on_pre_update`
+/// 2. `on_fixed_update`, zero or more times.
+/// 3. `on_update`
+/// 4. `on_late_update`
+/// 5. `on_pre_render`
+/// 6. `on_render`
+///
+/// The phases run on a worker thread while the video commands of last frame are
+/// being drawn, and `on_post_update` is called on main thread after both finished.
 pub trait Application {
+    /// `Application::on_pre_update` is called every frame before any updates, e.g.
+    /// gathering the inputs into game commands.
+    fn on_pre_update(&mut self, _: &Context) -> Result<()> {
+        Ok(())
+    }
+
     /// `Application::on_update` is called every frame. Its the main workhorse
     /// function for frame updates.
     fn on_update(&mut self, _: &Context) -> Result<()> {
         Ok(())
     }
 
+    /// `Application::on_late_update` is called after `on_update`, when all the
+    /// animations and physics have been updated, e.g. a camera which follows others.
+    fn on_late_update(&mut self, _: &Context) -> Result<()> {
+        Ok(())
+    }
+
+    /// `Application::on_pre_render` is called after the cameras are finalized, and
+    /// before any draw calls are submitted, e.g. culling and sorting.
+    fn on_pre_render(&mut self, _: &Context) -> Result<()> {
+        Ok(())
+    }
+
+    /// `Application::on_fixed_update` is called zero or more times before `on_update`,
+    /// with the fixed timestep of `EngineParams::fixed_fps`. Its useful for gameplay
+    /// and physics which require deterministic steps.
+    fn on_fixed_update(&mut self, _: &Context) -> Result<()> {
+        Ok(())
+    }
+
     /// `Application::on_render` is called before we starts rendering the scene.
     fn on_render(&mut self, _: &Context) -> Result<()> {
         Ok(())
@@ -73,11 +146,22 @@ pub trait Application {
         Ok(())
     }
 
+    /// `Application::on_hitch` is called after a frame which exceeds the time budget.
+    fn on_hitch(&mut self, _: &Context, _: &watchdog::HitchReport) -> Result<()> {
+        Ok(())
+    }
+
     /// `Application::on_update` is called when receiving application event.
     fn on_receive_event(&mut self, _: &Context, _: event::ApplicationEvent) -> Result<()> {
         Ok(())
     }
 
+    /// `Application::on_user_event` is called when receiving custom event sent with
+    /// `EventProxy`.
+    fn on_user_event(&mut self, _: &Context, _: proxy::UserEvent) -> Result<()> {
+        Ok(())
+    }
+
     /// `Application::on_exit` is called when exiting.
     fn on_exit(&mut self, _: &Context) -> Result<()> {
         Ok(())
@@ -0,0 +1,170 @@
+//! Thread-safe sender which injects custom user events into the main loop.
+
+use std::any::Any;
+use std::fmt;
+use std::sync::{Arc, Mutex};
+
+use glutin;
+
+use errors::*;
+
+/// A type-erased custom event, which could carry any `Send` payload (e.g. a `Vec<u8>`
+/// received from network).
+pub struct UserEvent(Box<Any + Send>);
+
+impl UserEvent {
+    pub fn new<T: Any + Send>(payload: T) -> Self {
+        UserEvent(Box::new(payload))
+    }
+
+    /// Returns true if the payload is of type `T`.
+    #[inline]
+    pub fn is<T: Any + Send>(&self) -> bool {
+        self.0.is::<T>()
+    }
+
+    /// Returns a reference to the payload if its of type `T`.
+    #[inline]
+    pub fn downcast_ref<T: Any + Send>(&self) -> Option<&T> {
+        self.0.downcast_ref::<T>()
+    }
+
+    /// Takes the payload if its of type `T`, returns the event itself otherwise.
+    pub fn downcast<T: Any + Send>(self) -> ::std::result::Result<T, UserEvent> {
+        match self.0.downcast::<T>() {
+            Ok(v) => Ok(*v),
+            Err(v) => Err(UserEvent(v)),
+        }
+    }
+}
+
+impl fmt::Debug for UserEvent {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "UserEvent")
+    }
+}
+
+/// A cloneable and thread-safe sender of `UserEvent`s, which is created by `Engine::
+/// create_event_proxy`. The events are delivered to `Application::on_user_event` on
+/// the main thread at the start of next frame, and the main loop will be woken up if
+/// its blocking on window events.
+#[derive(Clone)]
+pub struct EventProxy {
+    queue: Arc<Mutex<Vec<UserEvent>>>,
+    waker: Option<glutin::EventsLoopProxy>,
+}
+
+impl EventProxy {
+    pub(crate) fn new(
+        queue: Arc<Mutex<Vec<UserEvent>>>,
+        waker: Option<glutin::EventsLoopProxy>,
+    ) -> Self {
+        EventProxy {
+            queue: queue,
+            waker: waker,
+        }
+    }
+
+    /// Sends a custom event with `payload` to the main loop.
+    pub fn send<T: Any + Send>(&self, payload: T) -> Result<()> {
+        self.send_event(UserEvent::new(payload))
+    }
+
+    /// Sends a `UserEvent` to the main loop. Returns error if the main loop has been
+    /// terminated.
+    pub fn send_event(&self, event: UserEvent) -> Result<()> {
+        self.queue.lock().unwrap().push(event);
+
+        if let Some(ref waker) = self.waker {
+            if waker.wakeup().is_err() {
+                bail!("The main loop has been terminated.");
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// Wakes up the main loop if its blocking on window events, so the requests from
+/// other threads (e.g. `Context::run_on_main`) are handled without waiting for input.
+#[derive(Clone)]
+pub(crate) struct Waker(Option<Arc<Fn() + Send + Sync>>);
+
+impl Waker {
+    pub fn new<F: Fn() + Send + Sync + 'static>(func: F) -> Self {
+        Waker(Some(Arc::new(func)))
+    }
+
+    /// Creates a waker with the proxy of events loop, which does nothing if there is
+    /// no events loop, e.g. in headless mode.
+    pub fn with_proxy(proxy: Option<glutin::EventsLoopProxy>) -> Self {
+        match proxy {
+            Some(proxy) => {
+                let proxy = Mutex::new(proxy);
+                Waker::new(move || {
+                    // Fails only if the main loop has been terminated.
+                    let _ = proxy.lock().unwrap().wakeup();
+                })
+            }
+            None => Waker(None),
+        }
+    }
+
+    #[inline]
+    pub fn wakeup(&self) {
+        if let Some(ref func) = self.0 {
+            func();
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    #[test]
+    fn waker() {
+        Waker::with_proxy(None).wakeup();
+
+        let counter = Arc::new(AtomicUsize::new(0));
+        let waker = {
+            let counter = counter.clone();
+            Waker::new(move || {
+                counter.fetch_add(1, Ordering::SeqCst);
+            })
+        };
+
+        let waker_clone = waker.clone();
+        ::std::thread::spawn(move || waker_clone.wakeup())
+            .join()
+            .unwrap();
+
+        waker.wakeup();
+        assert_eq!(counter.load(Ordering::SeqCst), 2);
+    }
+
+    #[test]
+    fn send() {
+        let queue = Arc::new(Mutex::new(Vec::new()));
+        let proxy = EventProxy::new(queue.clone(), None);
+
+        let proxy_clone = proxy.clone();
+        ::std::thread::spawn(move || proxy_clone.send(vec![1u8, 2, 3]).unwrap())
+            .join()
+            .unwrap();
+
+        proxy.send(7u32).unwrap();
+
+        let mut events: Vec<_> = queue.lock().unwrap().drain(..).collect();
+        assert_eq!(events.len(), 2);
+
+        let v = events.remove(0);
+        assert!(v.is::<Vec<u8>>());
+        assert!(v.downcast_ref::<u32>().is_none());
+        assert_eq!(v.downcast::<Vec<u8>>().unwrap(), vec![1, 2, 3]);
+
+        let v = events.remove(0).downcast::<String>().unwrap_err();
+        assert_eq!(v.downcast::<u32>().unwrap(), 7);
+    }
+}
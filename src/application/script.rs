@@ -0,0 +1,219 @@
+//! Scripted input sequences which drive an application for automated tests.
+//!
+//! ```rust,ignore
+//! let mut settings = Settings::default();
+//! settings.headless = true;
+//!
+//! InputScript::new()
+//!     .key_press(KeyboardButton::W)
+//!     .wait_frames(10)
+//!     .assert("moves forward", |_, game: &Game| game.position.z > 0.0)
+//!     .key_release(KeyboardButton::W)
+//!     .run(Engine::new_with(&settings)?, Game::new())?;
+//! ```
+
+use std::sync::{Arc, RwLock};
+
+use errors::*;
+
+use super::event::{self, InputDeviceEvent, KeyboardButton, MouseButton};
+use super::watchdog::HitchReport;
+use super::{Application, Context, Engine, FrameInfo};
+
+enum Step<T> {
+    Input(InputDeviceEvent),
+    Wait(u32),
+    Execute(Box<FnMut(&Context, &mut T)>),
+    Assert(String, Box<FnMut(&Context, &T) -> bool>),
+}
+
+/// `InputScript` is a sequence of input events, frame waits and assertions, which are
+/// performed in order with `Engine`.
+pub struct InputScript<T> {
+    steps: Vec<Step<T>>,
+}
+
+impl<T> InputScript<T>
+where
+    T: Application + Send + Sync + 'static,
+{
+    pub fn new() -> Self {
+        InputScript { steps: Vec::new() }
+    }
+
+    /// Feeds an input event, which is handled in the next frame.
+    pub fn input(mut self, event: InputDeviceEvent) -> Self {
+        self.steps.push(Step::Input(event));
+        self
+    }
+
+    pub fn key_press(self, key: KeyboardButton) -> Self {
+        self.input(InputDeviceEvent::KeyboardPressed {
+            key: Some(key),
+            scancode: 0,
+        })
+    }
+
+    pub fn key_release(self, key: KeyboardButton) -> Self {
+        self.input(InputDeviceEvent::KeyboardReleased {
+            key: Some(key),
+            scancode: 0,
+        })
+    }
+
+    /// Feeds the characters of text.
+    pub fn text(mut self, text: &str) -> Self {
+        for v in text.chars() {
+            self = self.input(InputDeviceEvent::ReceivedCharacter { character: v });
+        }
+
+        self
+    }
+
+    /// Moves the cursor to the position in pixels relative to the top-left corner.
+    pub fn mouse_move(self, position: (f32, f32)) -> Self {
+        self.input(InputDeviceEvent::MouseMoved { position: position })
+    }
+
+    pub fn mouse_press(self, button: MouseButton) -> Self {
+        self.input(InputDeviceEvent::MousePressed { button: button })
+    }
+
+    pub fn mouse_release(self, button: MouseButton) -> Self {
+        self.input(InputDeviceEvent::MouseReleased { button: button })
+    }
+
+    /// Presses and releases the mouse button at position in two frames.
+    pub fn click(self, position: (f32, f32), button: MouseButton) -> Self {
+        self.mouse_move(position)
+            .mouse_press(button)
+            .wait_frames(1)
+            .mouse_release(button)
+            .wait_frames(1)
+    }
+
+    pub fn touch(self, touch: event::TouchEvent) -> Self {
+        self.input(InputDeviceEvent::Touch(touch))
+    }
+
+    /// Runs `frames` frames. The fed inputs are handled in the first one.
+    pub fn wait_frames(mut self, frames: u32) -> Self {
+        self.steps.push(Step::Wait(frames));
+        self
+    }
+
+    /// Modifies the application directly, e.g. teleports the player.
+    pub fn execute<F>(mut self, func: F) -> Self
+    where
+        F: FnMut(&Context, &mut T) + 'static,
+    {
+        self.steps.push(Step::Execute(Box::new(func)));
+        self
+    }
+
+    /// Checks the states of application, the script fails with `name` if it returns
+    /// false.
+    pub fn assert<F>(mut self, name: &str, func: F) -> Self
+    where
+        F: FnMut(&Context, &T) -> bool + 'static,
+    {
+        self.steps.push(Step::Assert(name.to_owned(), Box::new(func)));
+        self
+    }
+
+    /// Attaches the application to engine and performs the script. The application is
+    /// detached after that, and the first failed assertion is returned as error.
+    pub fn run(self, mut engine: Engine, application: T) -> Result<()> {
+        let application = Arc::new(RwLock::new(application));
+        engine.attach(Scripted(application.clone()))?;
+
+        let result = self.perform(&mut engine, &application);
+        engine.detach()?;
+        result
+    }
+
+    fn perform(self, engine: &mut Engine, application: &Arc<RwLock<T>>) -> Result<()> {
+        let mut frame = 0;
+        engine.sync();
+
+        for v in self.steps {
+            match v {
+                Step::Input(event) => engine.inject_input(event),
+
+                Step::Wait(frames) => {
+                    for _ in 0..frames {
+                        if !engine.step()? {
+                            bail!("Application exited at frame {} of script.", frame);
+                        }
+
+                        engine.sync();
+                        frame += 1;
+                    }
+                }
+
+                Step::Execute(mut func) => {
+                    let mut application = application.write().unwrap();
+                    func(engine.context(), &mut *application);
+                }
+
+                Step::Assert(name, mut func) => {
+                    let application = application.read().unwrap();
+                    if !func(engine.context(), &*application) {
+                        bail!("Assertion {:?} failed at frame {} of script.", name, frame);
+                    }
+                }
+            }
+        }
+
+        Ok(())
+    }
+}
+
+// Forwards the events to application which is shared with script.
+struct Scripted<T>(Arc<RwLock<T>>);
+
+impl<T: Application> Application for Scripted<T> {
+    fn on_pre_update(&mut self, ctx: &Context) -> Result<()> {
+        self.0.write().unwrap().on_pre_update(ctx)
+    }
+
+    fn on_update(&mut self, ctx: &Context) -> Result<()> {
+        self.0.write().unwrap().on_update(ctx)
+    }
+
+    fn on_late_update(&mut self, ctx: &Context) -> Result<()> {
+        self.0.write().unwrap().on_late_update(ctx)
+    }
+
+    fn on_pre_render(&mut self, ctx: &Context) -> Result<()> {
+        self.0.write().unwrap().on_pre_render(ctx)
+    }
+
+    fn on_fixed_update(&mut self, ctx: &Context) -> Result<()> {
+        self.0.write().unwrap().on_fixed_update(ctx)
+    }
+
+    fn on_render(&mut self, ctx: &Context) -> Result<()> {
+        self.0.write().unwrap().on_render(ctx)
+    }
+
+    fn on_post_update(&mut self, ctx: &Context, info: &FrameInfo) -> Result<()> {
+        self.0.write().unwrap().on_post_update(ctx, info)
+    }
+
+    fn on_hitch(&mut self, ctx: &Context, report: &HitchReport) -> Result<()> {
+        self.0.write().unwrap().on_hitch(ctx, report)
+    }
+
+    fn on_receive_event(&mut self, ctx: &Context, v: event::ApplicationEvent) -> Result<()> {
+        self.0.write().unwrap().on_receive_event(ctx, v)
+    }
+
+    fn on_user_event(&mut self, ctx: &Context, v: super::proxy::UserEvent) -> Result<()> {
+        self.0.write().unwrap().on_user_event(ctx, v)
+    }
+
+    fn on_exit(&mut self, ctx: &Context) -> Result<()> {
+        self.0.write().unwrap().on_exit(ctx)
+    }
+}
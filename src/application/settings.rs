@@ -2,6 +2,8 @@
 
 use input;
 use math;
+use res;
+use video;
 
 /// A structure containing configuration data for the game engine, which are
 /// used to specify hardware setup stuff to create the window and other
@@ -11,15 +13,71 @@ pub struct Settings {
     pub engine: EngineParams,
     pub window: WindowParams,
     pub input: input::InputParams,
+    pub res: res::ResourceParams,
+    pub video: video::VideoParams,
     pub headless: bool,
 }
 
+/// The behaviour of main loop when the engine is idle, which happens when the window
+/// is unfocused or the idle mode is requested explicitly.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum IdlePolicy {
+    /// Throttles the main loop to `EngineParams::max_inactive_fps`.
+    Throttle,
+    /// Blocks the main loop until new events arrive.
+    WaitEvents,
+}
+
+/// The behaviour of engine when the application is sent to background, which happens
+/// on mobile platforms.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BackgroundPolicy {
+    /// Pauses the main loop until the application is resumed. This is required on
+    /// iOS, since the processes which issue OpenGL ES commands in background are
+    /// terminated.
+    Pause,
+    /// Keeps running the main loop.
+    Continue,
+}
+
+/// What happens to the GPU resources when the main loop is paused in background.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BackgroundResourcePolicy {
+    /// Keeps all the resources, so the first frame after resuming is not slowed down.
+    Keep,
+    /// Deletes the pooled temporary render targets, which are recreated on demand
+    /// after resuming. It makes the application less likely to be terminated by the
+    /// system for memory while in background.
+    ReleaseTransient,
+}
+
 #[derive(Debug, Clone, Copy)]
 pub struct EngineParams {
     pub min_fps: u32,
     pub max_fps: u32,
     pub max_inactive_fps: u32,
     pub time_smooth_step: u32,
+    /// The time budget of a single frame in milliseconds, any frame exceeding it will be
+    /// reported as a hitch. A value of 0 disables the hitch reporting.
+    pub hitch_threshold: u32,
+    /// The behaviour of main loop when the engine is idle.
+    pub idle_policy: IdlePolicy,
+    /// The frequency of `Application::on_fixed_update`. A value of 0 disables the fixed
+    /// updates.
+    pub fixed_fps: u32,
+    /// The maximum number of fixed updates in one frame, the remaining time is dropped
+    /// so a slow frame would not spiral down.
+    pub max_fixed_steps: u32,
+    /// The behaviour of main loop when the application is sent to background.
+    pub background_policy: BackgroundPolicy,
+    /// What happens to the GPU resources when the main loop is paused in background.
+    pub background_resources: BackgroundResourcePolicy,
+    /// Enables the determinism mode, which resets the floating point environment before
+    /// every fixed update and executes the scoped jobs sequentially, so the simulations
+    /// are reproducible with the same seed and inputs.
+    pub deterministic: bool,
+    /// The global seed of `Context::random`. It's derived from clock if `None`.
+    pub seed: Option<u64>,
 }
 
 impl Default for EngineParams {
@@ -29,6 +87,14 @@ impl Default for EngineParams {
             max_fps: 30,
             max_inactive_fps: 0,
             time_smooth_step: 0,
+            hitch_threshold: 100,
+            idle_policy: IdlePolicy::Throttle,
+            fixed_fps: 0,
+            max_fixed_steps: 5,
+            background_policy: BackgroundPolicy::Pause,
+            background_resources: BackgroundResourcePolicy::ReleaseTransient,
+            deterministic: false,
+            seed: None,
         }
     }
 }
@@ -44,6 +110,20 @@ pub struct WindowParams {
     pub multisample: u16,
     /// Specifies whether should we have vsync.
     pub vsync: bool,
+    /// The formats of backbuffer to request.
+    pub backbuffer: BackbufferParams,
+    /// Sets the initial position in *points* of the window relative to the top-left
+    /// corner of the desktop. The position is decided by platform if `None`.
+    pub position: Option<math::Vector2<i32>>,
+    /// Creates an undecorated window which covers the whole primary monitor, instead of
+    /// switching the video mode of monitor. The `size` and `position` are ignored.
+    pub borderless_fullscreen: bool,
+    /// Keeps the window above all the other windows.
+    pub always_on_top: bool,
+    /// Requests a transparent framebuffer, so the desktop behind the window is visible
+    /// where the alpha of backbuffer is less than 1.0. This can't be changed after the
+    /// window has been created.
+    pub transparent: bool,
 }
 
 impl Default for WindowParams {
@@ -53,6 +133,96 @@ impl Default for WindowParams {
             size: math::Vector2::new(640, 320),
             multisample: 2,
             vsync: false,
+            backbuffer: BackbufferParams::default(),
+            position: None,
+            borderless_fullscreen: false,
+            always_on_top: false,
+            transparent: false,
+        }
+    }
+}
+
+/// The precision of depth buffer.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DepthFormat {
+    /// No depth buffer at all.
+    Nothing,
+    /// 16 bits integer.
+    D16,
+    /// 24 bits integer.
+    D24,
+    /// 32 bits floating point.
+    D32F,
+}
+
+impl DepthFormat {
+    /// Gets the number of bits per pixel.
+    pub fn bits(&self) -> u8 {
+        match *self {
+            DepthFormat::Nothing => 0,
+            DepthFormat::D16 => 16,
+            DepthFormat::D24 => 24,
+            DepthFormat::D32F => 32,
         }
     }
 }
+
+/// The formats of default framebuffer. If the platform could not satisfy them, the
+/// closest weaker formats are used with warnings, unless `strict` is set.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BackbufferParams {
+    /// The bits of RGB color channels. The platform decides if `None`.
+    pub color_bits: Option<u8>,
+    /// The bits of alpha channel. The platform decides if `None`.
+    pub alpha_bits: Option<u8>,
+    /// The precision of depth buffer. Notes that some GLES devices will not create a
+    /// depth buffer unless its requested explicitly.
+    pub depth: DepthFormat,
+    /// The bits of stencil buffer, 0 means no stencil buffer.
+    pub stencil_bits: u8,
+    /// Requests a sRGB-capable backbuffer.
+    pub srgb: bool,
+    /// Fails the window creation if the formats are not satisfied exactly. Notes that
+    /// the default framebuffer never has floating point depth, so `DepthFormat::D32F`
+    /// always fails in strict mode.
+    pub strict: bool,
+}
+
+impl Default for BackbufferParams {
+    fn default() -> Self {
+        BackbufferParams {
+            color_bits: None,
+            alpha_bits: None,
+            depth: DepthFormat::D24,
+            stencil_bits: 8,
+            srgb: false,
+            strict: false,
+        }
+    }
+}
+
+impl BackbufferParams {
+    /// Gets the closest formats which are one step weaker than these, or `None` if
+    /// nothing could be given up any more. The floating point depth is replaced by 24
+    /// bits integer first, then the sRGB, the explicit color bits, the depth precision
+    /// and the stencil buffer are given up in order.
+    pub fn relax(&self) -> Option<BackbufferParams> {
+        let mut v = *self;
+        if self.depth == DepthFormat::D32F {
+            v.depth = DepthFormat::D24;
+        } else if self.srgb {
+            v.srgb = false;
+        } else if self.color_bits.is_some() || self.alpha_bits.is_some() {
+            v.color_bits = None;
+            v.alpha_bits = None;
+        } else if self.depth == DepthFormat::D24 {
+            v.depth = DepthFormat::D16;
+        } else if self.stencil_bits > 0 {
+            v.stencil_bits = 0;
+        } else {
+            return None;
+        }
+
+        Some(v)
+    }
+}
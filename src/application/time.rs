@@ -13,9 +13,13 @@ pub struct TimeSystem {
     max_fps: u32,
     max_inactive_fps: u32,
     smoothing_step: usize,
+    fixed_fps: u32,
+    max_fixed_steps: u32,
+    idle: bool,
 
     timestep: Duration,
     previous_timesteps: VecDeque<Duration>,
+    accumulator: Duration,
     last_frame_timepoint: Instant,
     shared: Arc<TimeSystemShared>,
 }
@@ -29,7 +33,11 @@ impl TimeSystem {
             max_fps: setup.max_fps,
             max_inactive_fps: setup.max_inactive_fps,
             smoothing_step: setup.time_smooth_step as usize,
+            fixed_fps: setup.fixed_fps,
+            max_fixed_steps: setup.max_fixed_steps,
+            idle: false,
             previous_timesteps: VecDeque::new(),
+            accumulator: Duration::new(0, 0),
             timestep: Duration::new(0, 0),
             last_frame_timepoint: Instant::now(),
             shared: Arc::new(shared),
@@ -41,17 +49,35 @@ impl TimeSystem {
         self.shared.clone()
     }
 
+    /// Sets whether the engine is idle, the `max_inactive_fps` is used if its idle.
+    pub(crate) fn set_idle(&mut self, idle: bool) {
+        self.idle = idle;
+    }
+
+    /// Restarts the timing of current frame, so the time elapsed since last frame is
+    /// not counted, e.g. after the application is resumed from background.
+    pub(crate) fn reset(&mut self) {
+        self.last_frame_timepoint = Instant::now();
+    }
+
     pub(crate) fn advance(&mut self) -> Duration {
         // Synchonize with configurations.
         self.min_fps = *self.shared.min_fps.read().unwrap();
         self.max_fps = *self.shared.max_fps.read().unwrap();
         self.max_inactive_fps = *self.shared.max_inactive_fps.read().unwrap();
         self.smoothing_step = *self.shared.smoothing_step.read().unwrap();
+        self.fixed_fps = *self.shared.fixed_fps.read().unwrap();
+
+        let max_fps = if self.idle && self.max_inactive_fps > 0 {
+            self.max_inactive_fps
+        } else {
+            self.max_fps
+        };
 
         // Perform waiting loop if maximum fps set, cooperatively gives up
         // a timeslice to the OS scheduler.
-        if self.max_fps > 0 {
-            let td = Duration::from_millis(u64::from(1000 / self.max_fps));
+        if max_fps > 0 {
+            let td = Duration::from_millis(u64::from(1000 / max_fps));
             while self.last_frame_timepoint.elapsed() <= td {
                 if (self.last_frame_timepoint.elapsed() + Duration::from_millis(2)) < td {
                     std::thread::sleep(Duration::from_millis(1));
@@ -91,8 +117,41 @@ impl TimeSystem {
         }
 
         *self.shared.timestep.write().unwrap() = self.timestep;
+        self.advance_fixed_steps();
         self.timestep
     }
+
+    // Accumulates the timestep, and splits it into fixed steps. The remaining time is
+    // kept as the interpolation factor between the last two fixed steps.
+    fn advance_fixed_steps(&mut self) {
+        let (steps, alpha) = if self.fixed_fps > 0 {
+            let step = 1_000_000_000 / u64::from(self.fixed_fps);
+            let elapsed = to_nanos(self.accumulator + self.timestep);
+
+            let steps = (elapsed / step).min(u64::from(self.max_fixed_steps)) as u32;
+            let remains = elapsed - u64::from(steps) * step;
+            let remains = if remains >= step {
+                // Drops the time which exceeds `max_fixed_steps`.
+                remains % step
+            } else {
+                remains
+            };
+
+            let nanos = (remains % 1_000_000_000) as u32;
+            self.accumulator = Duration::new(remains / 1_000_000_000, nanos);
+            (steps, remains as f32 / step as f32)
+        } else {
+            self.accumulator = Duration::new(0, 0);
+            (0, 1.0)
+        };
+
+        *self.shared.fixed_steps.write().unwrap() = steps;
+        *self.shared.fixed_alpha.write().unwrap() = alpha;
+    }
+}
+
+fn to_nanos(duration: Duration) -> u64 {
+    duration.as_secs() * 1_000_000_000 + u64::from(duration.subsec_nanos())
 }
 
 /// The multi-thread friendly parts of `TimeSystem`.
@@ -102,6 +161,10 @@ pub struct TimeSystemShared {
     max_inactive_fps: RwLock<u32>,
     smoothing_step: RwLock<usize>,
     timestep: RwLock<Duration>,
+    fixed_fps: RwLock<u32>,
+    fixed_steps: RwLock<u32>,
+    fixed_frames: RwLock<u64>,
+    fixed_alpha: RwLock<f32>,
 }
 
 impl TimeSystemShared {
@@ -112,6 +175,10 @@ impl TimeSystemShared {
             max_inactive_fps: RwLock::new(setup.max_inactive_fps),
             smoothing_step: RwLock::new(setup.time_smooth_step as usize),
             timestep: RwLock::new(Duration::new(0, 0)),
+            fixed_fps: RwLock::new(setup.fixed_fps),
+            fixed_steps: RwLock::new(0),
+            fixed_frames: RwLock::new(0),
+            fixed_alpha: RwLock::new(1.0),
         }
     }
 
@@ -159,4 +226,47 @@ impl TimeSystemShared {
     pub fn frame_delta(&self) -> Duration {
         *self.timestep.read().unwrap()
     }
+
+    /// Set the frequency of `Application::on_fixed_update`. A value of 0 disables the
+    /// fixed updates.
+    #[inline]
+    pub fn set_fixed_fps(&self, fps: u32) {
+        *self.fixed_fps.write().unwrap() = fps;
+    }
+
+    /// Gets the duration of a fixed update, which is zero if fixed updates are disabled.
+    #[inline]
+    pub fn fixed_timestep(&self) -> Duration {
+        let fps = *self.fixed_fps.read().unwrap();
+        if fps == 0 {
+            Duration::new(0, 0)
+        } else {
+            Duration::new(0, 1_000_000_000 / fps)
+        }
+    }
+
+    /// Gets the number of fixed updates in current frame.
+    #[inline]
+    pub fn fixed_steps(&self) -> u32 {
+        *self.fixed_steps.read().unwrap()
+    }
+
+    /// Gets the number of fixed updates since the engine started, including the one
+    /// which is running in `Application::on_fixed_update`.
+    #[inline]
+    pub fn fixed_frames(&self) -> u64 {
+        *self.fixed_frames.read().unwrap()
+    }
+
+    pub(crate) fn begin_fixed_update(&self) {
+        *self.fixed_frames.write().unwrap() += 1;
+    }
+
+    /// Gets the interpolation factor in `[0, 1)` between the previous and the latest
+    /// fixed updates, which should be used to render the states smoothly at display
+    /// rate. Its always 1.0 if fixed updates are disabled.
+    #[inline]
+    pub fn fixed_alpha(&self) -> f32 {
+        *self.fixed_alpha.read().unwrap()
+    }
 }
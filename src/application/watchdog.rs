@@ -0,0 +1,172 @@
+//! Frame-time budget watchdog which reports hitches.
+
+use std::time::{Duration, Instant};
+
+use super::FrameInfo;
+use video::VideoFrameInfo;
+
+/// The changes of alive video resources during a frame.
+#[derive(Debug, Copy, Clone, Default, PartialEq, Eq)]
+pub struct VideoResourceDelta {
+    pub surfaces: i32,
+    pub shaders: i32,
+    pub meshes: i32,
+    pub textures: i32,
+}
+
+impl VideoResourceDelta {
+    fn between(previous: &VideoFrameInfo, current: &VideoFrameInfo) -> Self {
+        let diff = |a: u32, b: u32| b as i32 - a as i32;
+
+        VideoResourceDelta {
+            surfaces: diff(previous.alive_surfaces, current.alive_surfaces),
+            shaders: diff(previous.alive_shaders, current.alive_shaders),
+            meshes: diff(previous.alive_meshes, current.alive_meshes),
+            textures: diff(previous.alive_textures, current.alive_textures),
+        }
+    }
+}
+
+/// A structured report of frame that exceeds the time budget.
+#[derive(Debug, Copy, Clone)]
+pub struct HitchReport {
+    /// The index of frame.
+    pub frame: u64,
+    /// The time budget of a single frame.
+    pub budget: Duration,
+    /// The wall time of the whole frame.
+    pub duration: Duration,
+    /// The time spent in `Application::on_update` and `Application::on_render`.
+    pub update: Duration,
+    /// The video statistics of this frame.
+    pub video: VideoFrameInfo,
+    /// The video resources created or destroyed during this frame.
+    pub resources: VideoResourceDelta,
+}
+
+/// `Watchdog` records any frame which exceeds the time budget.
+pub struct Watchdog {
+    budget: Option<Duration>,
+    frame: u64,
+    last_frame_timepoint: Option<Instant>,
+    last_video_info: VideoFrameInfo,
+}
+
+impl Watchdog {
+    /// Creates a new `Watchdog` with time budget in milliseconds. A value of 0 disables
+    /// the hitch reporting.
+    pub fn new(budget: u32) -> Self {
+        let mut watchdog = Watchdog {
+            budget: None,
+            frame: 0,
+            last_frame_timepoint: None,
+            last_video_info: VideoFrameInfo::default(),
+        };
+
+        watchdog.set_budget(budget);
+        watchdog
+    }
+
+    /// Sets the time budget of a single frame in milliseconds. A value of 0 disables
+    /// the hitch reporting.
+    pub fn set_budget(&mut self, budget: u32) {
+        self.budget = if budget > 0 {
+            Some(Duration::from_millis(u64::from(budget)))
+        } else {
+            None
+        };
+    }
+
+    /// Starts measuring the first frame, does nothing if it has been started already.
+    pub fn start(&mut self) {
+        if self.last_frame_timepoint.is_none() {
+            self.reset();
+        }
+    }
+
+    /// Restarts measuring the current frame, so the time spent in blocking on events or
+    /// in background is not reported as a hitch.
+    pub fn reset(&mut self) {
+        self.last_frame_timepoint = Some(Instant::now());
+    }
+
+    /// Advances a frame, returns a `HitchReport` if the time elapsed since last frame
+    /// exceeds the budget.
+    pub fn advance(&mut self, info: &FrameInfo) -> Option<HitchReport> {
+        let now = Instant::now();
+        let elapsed = self
+            .last_frame_timepoint
+            .map(|v| now - v)
+            .unwrap_or_default();
+
+        self.last_frame_timepoint = Some(now);
+        self.check(elapsed, info)
+    }
+
+    pub(crate) fn check(&mut self, elapsed: Duration, info: &FrameInfo) -> Option<HitchReport> {
+        let resources = VideoResourceDelta::between(&self.last_video_info, &info.video);
+        let frame = self.frame;
+
+        self.frame += 1;
+        self.last_video_info = info.video;
+
+        let budget = self.budget?;
+        if elapsed <= budget {
+            return None;
+        }
+
+        Some(HitchReport {
+            frame: frame,
+            budget: budget,
+            duration: elapsed,
+            update: info.duration,
+            video: info.video,
+            resources: resources,
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn hitch() {
+        let mut watchdog = Watchdog::new(0);
+        let mut info = FrameInfo::default();
+        assert!(watchdog.check(Duration::from_secs(1), &info).is_none());
+
+        watchdog.set_budget(20);
+        assert!(watchdog.check(Duration::from_millis(10), &info).is_none());
+
+        info.video.alive_textures = 2;
+        let report = watchdog
+            .check(Duration::from_millis(30), &info)
+            .unwrap();
+
+        assert_eq!(report.frame, 2);
+        assert_eq!(report.budget, Duration::from_millis(20));
+        assert_eq!(report.duration, Duration::from_millis(30));
+        assert_eq!(report.resources.textures, 2);
+        assert_eq!(report.resources.meshes, 0);
+    }
+
+    #[test]
+    fn timepoint() {
+        let mut watchdog = Watchdog::new(1);
+        let info = FrameInfo::default();
+
+        // The time before the first frame is not measured.
+        ::std::thread::sleep(Duration::from_millis(5));
+        assert!(watchdog.advance(&info).is_none());
+
+        watchdog.start();
+        ::std::thread::sleep(Duration::from_millis(5));
+        watchdog.start();
+        assert!(watchdog.advance(&info).is_some());
+
+        ::std::thread::sleep(Duration::from_millis(5));
+        watchdog.reset();
+        assert!(watchdog.advance(&info).is_none());
+    }
+}
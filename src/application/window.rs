@@ -1,15 +1,29 @@
 //! An OpenGL context and the environment around it.
 
+use std::cell::Cell;
 use std::slice::Iter;
 use std::sync::{Arc, RwLock};
 
 use glutin;
 use glutin::GlContext;
+#[cfg(target_os = "ios")]
+use glutin::os::ios::WindowExt;
 
 use math;
 
+use super::cursor::{Cursor, CursorIcon};
 use super::event::*;
-use super::settings::WindowParams;
+use super::settings::{BackbufferParams, DepthFormat, WindowParams};
+
+// The versions of context to request in order. iOS only supports OpenGL ES, and the
+// old devices fall back to OpenGL ES 2.0.
+#[cfg(not(target_os = "ios"))]
+const GL_REQUESTS: &[glutin::GlRequest] = &[glutin::GlRequest::Latest];
+#[cfg(target_os = "ios")]
+const GL_REQUESTS: &[glutin::GlRequest] = &[
+    glutin::GlRequest::Specific(glutin::Api::OpenGlEs, (3, 0)),
+    glutin::GlRequest::Specific(glutin::Api::OpenGlEs, (2, 0)),
+];
 
 #[derive(Debug, Fail)]
 pub enum Error {
@@ -17,6 +31,19 @@ pub enum Error {
     Context(String),
     #[fail(display = "[GLUTIN] {}", _0)]
     Creation(String),
+    #[fail(display = "[GLUTIN] Backbuffer format not supported: {}", _0)]
+    Format(String),
+}
+
+impl Error {
+    /// Gets the category of this error.
+    pub fn kind(&self) -> ::errors::ErrorKind {
+        match *self {
+            Error::Context(_) => ::errors::ErrorKind::DriverLost,
+            Error::Creation(_) => ::errors::ErrorKind::Unsupported,
+            Error::Format(_) => ::errors::ErrorKind::Unsupported,
+        }
+    }
 }
 
 impl From<glutin::CreationError> for Error {
@@ -33,6 +60,72 @@ impl From<glutin::ContextError> for Error {
 
 pub type Result<T> = ::std::result::Result<T, Error>;
 
+/// The insets in *points* from the edges of window, which are obscured by the notches,
+/// rounded corners or system bars on some devices.
+#[derive(Debug, Copy, Clone, PartialEq, Default)]
+pub struct SafeAreaInsets {
+    pub left: f32,
+    pub top: f32,
+    pub right: f32,
+    pub bottom: f32,
+}
+
+// The formats of default framebuffer which are actually created.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+struct PixelFormat {
+    color_bits: u8,
+    alpha_bits: u8,
+    depth_bits: u8,
+    stencil_bits: u8,
+    srgb: bool,
+}
+
+impl<'a> From<&'a glutin::PixelFormat> for PixelFormat {
+    fn from(v: &'a glutin::PixelFormat) -> Self {
+        PixelFormat {
+            color_bits: v.color_bits,
+            alpha_bits: v.alpha_bits,
+            depth_bits: v.depth_bits,
+            stencil_bits: v.stencil_bits,
+            srgb: v.srgb,
+        }
+    }
+}
+
+impl PixelFormat {
+    // Some platforms will fallback to a weaker pixel format silently, so we have to
+    // check the format we actually got. Returns the descriptions of the requested
+    // formats which are not satisfied.
+    fn shortfalls(&self, requested: &BackbufferParams) -> Vec<String> {
+        let mut shortfalls = Vec::new();
+        let mut check = |what: &str, requested: u8, actual: u8| {
+            if actual < requested {
+                shortfalls.push(format!(
+                    "Requested {} {} bits, but got {} bits.",
+                    what, requested, actual
+                ));
+            }
+        };
+
+        if let Some(bits) = requested.color_bits {
+            check("color", bits, self.color_bits);
+        }
+
+        if let Some(bits) = requested.alpha_bits {
+            check("alpha", bits, self.alpha_bits);
+        }
+
+        check("depth", requested.depth.bits(), self.depth_bits);
+        check("stencil", requested.stencil_bits, self.stencil_bits);
+
+        if requested.srgb && !self.srgb {
+            shortfalls.push("Requested a sRGB-capable backbuffer.".into());
+        }
+
+        shortfalls
+    }
+}
+
 /// Represents an OpenGL context and the window or environment around it, its just
 /// simple wrappers to [glutin](https://github.com/tomaka/glutin) right now.
 pub struct Window {
@@ -40,56 +133,175 @@ pub struct Window {
     visitor: Box<Visitor>,
     events: Vec<Event>,
     shared: Arc<WindowShared>,
+    cursor: Option<Cursor>,
 }
 
 impl Window {
     /// Creates a new `Window` and initalize OpenGL context.
     pub fn new(params: WindowParams) -> Result<Self> {
+        let events_loop = glutin::EventsLoop::new();
+
+        let mut size = glutin::dpi::LogicalSize::new(params.size.x as f64, params.size.y as f64);
+        let mut position = params
+            .position
+            .map(|v| glutin::dpi::LogicalPosition::new(v.x as f64, v.y as f64));
+
+        if params.borderless_fullscreen {
+            let monitor = events_loop.get_primary_monitor();
+            let hidpi = monitor.get_hidpi_factor();
+            size = monitor.get_dimensions().to_logical(hidpi);
+            position = Some(monitor.get_position().to_logical(hidpi));
+        }
+
         let builder = glutin::WindowBuilder::new()
-            .with_title(params.title)
-            .with_dimensions(glutin::dpi::LogicalSize::new(
-                params.size.x as f64,
-                params.size.y as f64,
-            ))
+            .with_title(params.title.clone())
+            .with_dimensions(size)
+            .with_decorations(!params.borderless_fullscreen)
+            .with_always_on_top(params.always_on_top)
+            .with_transparency(params.transparent)
             .with_multitouch();
 
-        let context = glutin::ContextBuilder::new()
-            .with_multisampling(params.multisample as u16)
-            .with_gl_profile(glutin::GlProfile::Core)
-            .with_gl(glutin::GlRequest::Latest)
-            .with_vsync(params.vsync);
+        // Falls back to the closest weaker formats if the requested ones are not
+        // available, unless the backbuffer is strict.
+        let requested = params.backbuffer;
+        let mut backbuffer = requested;
+        let device = loop {
+            match Self::create_device(&builder, &params, &backbuffer, &events_loop) {
+                Err(Error::Format(err)) => match backbuffer.relax() {
+                    Some(v) if !requested.strict => {
+                        warn!("{} Falls back to {:?}.", err, v);
+                        backbuffer = v;
+                    }
+                    _ => return Err(Error::Format(err)),
+                },
+                v => break v?,
+            }
+        };
 
-        let events_loop = glutin::EventsLoop::new();
-        let device = glutin::GlWindow::new(builder, context, &events_loop)?;
         unsafe {
             device.make_current()?;
         }
 
+        let format = PixelFormat::from(&device.get_pixel_format());
+        let shortfalls = format.shortfalls(&requested);
+        if requested.strict && !shortfalls.is_empty() {
+            return Err(Error::Format(shortfalls.join(" ")));
+        }
+
+        for v in &shortfalls {
+            warn!("{} Uses the closest format instead.", v);
+        }
+
+        info!("Created backbuffer with {:?}.", format);
+
+        if let Some(position) = position {
+            device.set_position(position);
+        }
+
+        // Renders at the native resolution of retina screens.
+        #[cfg(target_os = "ios")]
+        let _ = unsafe { super::ios::set_native_scale(device.get_uiview()) };
+
+        // Records the forces of touches, which are not reported by glutin.
+        #[cfg(target_os = "ios")]
+        unsafe {
+            super::ios::observe_touch_forces(device.get_uiview())
+        };
+
+        let visitor = GlutinVisitor {
+            window: device,
+            borderless: Cell::new(Borderless::default()),
+        };
+
+        if params.borderless_fullscreen {
+            let pos = params.position.unwrap_or_else(|| math::Vector2::new(0, 0));
+            let mut borderless = Borderless::default();
+            borderless.enter(Some((
+                glutin::dpi::LogicalPosition::new(pos.x as f64, pos.y as f64),
+                glutin::dpi::LogicalSize::new(params.size.x as f64, params.size.y as f64),
+            )));
+            visitor.borderless.set(borderless);
+        }
+
         let window = Window {
-            visitor: Box::new(GlutinVisitor(device)),
+            visitor: Box::new(visitor),
             events_loop: Some(events_loop),
             events: Vec::new(),
-            shared: Arc::new(WindowShared {
-                dimensions: RwLock::new(math::Vector2::new(0, 0)),
-                dimensions_in_points: RwLock::new(math::Vector2::new(0, 0)),
-                hidpi: RwLock::new(1.0),
-            }),
+            shared: Arc::new(WindowShared::new()),
+            cursor: None,
         };
 
         Ok(window)
     }
 
+    // Creates the window and context with the formats of backbuffer, the versions of
+    // context in `GL_REQUESTS` are tried in order.
+    fn create_device(
+        builder: &glutin::WindowBuilder,
+        params: &WindowParams,
+        backbuffer: &BackbufferParams,
+        events_loop: &glutin::EventsLoop,
+    ) -> Result<glutin::GlWindow> {
+        // The default framebuffer has no floating point depth, which is reported as a
+        // format error, so the closest integer precision could be tried instead.
+        if backbuffer.depth == DepthFormat::D32F {
+            return Err(Error::Format(
+                "32 bits floating point depth buffer is not supported by default framebuffer, \
+                 uses a render texture instead."
+                    .into(),
+            ));
+        }
+
+        let mut context = glutin::ContextBuilder::new()
+            .with_multisampling(params.multisample as u16)
+            .with_vsync(params.vsync)
+            .with_depth_buffer(backbuffer.depth.bits())
+            .with_stencil_buffer(backbuffer.stencil_bits)
+            .with_srgb(backbuffer.srgb);
+
+        if backbuffer.color_bits.is_some() || backbuffer.alpha_bits.is_some() {
+            context = context.with_pixel_format(
+                backbuffer.color_bits.unwrap_or(24),
+                backbuffer.alpha_bits.unwrap_or(8),
+            );
+        }
+
+        if cfg!(not(target_os = "ios")) {
+            context = context.with_gl_profile(glutin::GlProfile::Core);
+        }
+
+        let mut result = None;
+        for (i, &request) in GL_REQUESTS.iter().enumerate() {
+            let context = context.clone().with_gl(request);
+            match glutin::GlWindow::new(builder.clone(), context, events_loop) {
+                Err(ref err) if i + 1 < GL_REQUESTS.len() => {
+                    warn!("Failed to create context with {:?}. {}", request, err);
+                }
+                v => {
+                    result = Some(v);
+                    break;
+                }
+            }
+        }
+
+        match result.unwrap() {
+            Ok(device) => Ok(device),
+            Err(glutin::CreationError::NoAvailablePixelFormat) => Err(Error::Format(format!(
+                "No available pixel format satisfies {:?}.",
+                backbuffer
+            ))),
+            Err(err) => Err(err.into()),
+        }
+    }
+
     /// Creates a new `Window` with headless context.
     pub fn headless() -> Self {
         Window {
             visitor: Box::new(HeadlessVisitor {}),
             events_loop: None,
             events: Vec::new(),
-            shared: Arc::new(WindowShared {
-                dimensions: RwLock::new(math::Vector2::new(0, 0)),
-                dimensions_in_points: RwLock::new(math::Vector2::new(0, 0)),
-                hidpi: RwLock::new(1.0),
-            }),
+            shared: Arc::new(WindowShared::new()),
+            cursor: None,
         }
     }
 
@@ -118,6 +330,37 @@ impl Window {
         self.visitor.hide();
     }
 
+    /// Modifies the position in *points* of the window relative to the top-left corner
+    /// of the desktop.
+    ///
+    /// # Platform-specific
+    ///
+    /// Has no effect on mobile platform.
+    #[inline]
+    pub fn set_position(&self, position: math::Vector2<i32>) {
+        self.visitor.set_position(position);
+    }
+
+    /// Turns the window decorations (title bar and borders) on or off.
+    #[inline]
+    pub fn set_decorations(&self, decorations: bool) {
+        self.visitor.set_decorations(decorations);
+    }
+
+    /// Keeps the window above all the other windows or not.
+    #[inline]
+    pub fn set_always_on_top(&self, always_on_top: bool) {
+        self.visitor.set_always_on_top(always_on_top);
+    }
+
+    /// Switches to or leaves the borderless fullscreen, which is an undecorated window
+    /// that covers the whole monitor the window currently resides on. The previous
+    /// position and size will be restored when leaving.
+    #[inline]
+    pub fn set_borderless_fullscreen(&self, enabled: bool) {
+        self.visitor.set_borderless_fullscreen(enabled);
+    }
+
     /// Set the context as the active context in this thread.
     #[inline]
     pub fn make_current(&self) -> Result<()> {
@@ -130,11 +373,35 @@ impl Window {
         self.visitor.is_current()
     }
 
+    /// Creates a proxy which could wake up the events loop from other threads. Returns
+    /// `None` if the window is headless.
+    pub(crate) fn create_proxy(&self) -> Option<glutin::EventsLoopProxy> {
+        self.events_loop.as_ref().map(|v| v.create_proxy())
+    }
+
     /// Polls events from window, and returns the iterator over them.
     pub fn advance(&mut self) -> Iter<Event> {
+        self.advance_with(false)
+    }
+
+    /// Blocks until new events arrive, and returns the iterator over them.
+    pub fn wait_and_advance(&mut self) -> Iter<Event> {
+        self.advance_with(true)
+    }
+
+    fn advance_with(&mut self, wait: bool) -> Iter<Event> {
         *self.shared.dimensions_in_points.write().unwrap() = self.dimensions_in_points();
         *self.shared.dimensions.write().unwrap() = self.dimensions();
         *self.shared.hidpi.write().unwrap() = self.hidpi();
+        *self.shared.safe_area.write().unwrap() = self.visitor.safe_area_insets();
+
+        // Applies the cursor requested during last frame.
+        let cursor = self.shared.cursor();
+        if self.cursor.as_ref() != Some(&cursor) {
+            let hardware = self.visitor.set_cursor(&cursor);
+            *self.shared.software_cursor.write().unwrap() = !hardware;
+            self.cursor = Some(cursor);
+        }
 
         self.events.clear();
 
@@ -143,6 +410,17 @@ impl Window {
             let events = &mut self.events;
 
             if let Some(ref mut events_loop) = self.events_loop {
+                if wait {
+                    events_loop.run_forever(|evt| {
+                        if let Some(v) = from_event(evt, dims) {
+                            events.push(v);
+                            glutin::ControlFlow::Break
+                        } else {
+                            glutin::ControlFlow::Continue
+                        }
+                    });
+                }
+
                 events_loop.poll_events(|evt| {
                     if let Some(v) = from_event(evt, dims) {
                         events.push(v);
@@ -221,9 +499,66 @@ pub struct WindowShared {
     dimensions_in_points: RwLock<math::Vector2<u32>>,
     dimensions: RwLock<math::Vector2<u32>>,
     hidpi: RwLock<f32>,
+    cursor: RwLock<Cursor>,
+    software_cursor: RwLock<bool>,
+    safe_area: RwLock<SafeAreaInsets>,
+    simulated_safe_area: RwLock<Option<SafeAreaInsets>>,
 }
 
 impl WindowShared {
+    fn new() -> Self {
+        WindowShared {
+            dimensions: RwLock::new(math::Vector2::new(0, 0)),
+            dimensions_in_points: RwLock::new(math::Vector2::new(0, 0)),
+            hidpi: RwLock::new(1.0),
+            cursor: RwLock::new(Cursor::default()),
+            software_cursor: RwLock::new(false),
+            safe_area: RwLock::new(SafeAreaInsets::default()),
+            simulated_safe_area: RwLock::new(None),
+        }
+    }
+
+    /// Gets the safe area insets of window, which are queried from the platform or
+    /// simulated with `WindowShared::simulate_safe_area_insets`.
+    #[inline]
+    pub fn safe_area_insets(&self) -> SafeAreaInsets {
+        match *self.simulated_safe_area.read().unwrap() {
+            Some(v) => v,
+            None => *self.safe_area.read().unwrap(),
+        }
+    }
+
+    /// Overrides the safe area insets of platform, which is useful to preview the
+    /// layouts of devices with notches on desktop.
+    #[inline]
+    pub fn simulate_safe_area_insets<T: Into<Option<SafeAreaInsets>>>(&self, insets: T) {
+        *self.simulated_safe_area.write().unwrap() = insets.into();
+    }
+
+    /// Sets the appearance of mouse cursor, which will be applied at the start of
+    /// next frame.
+    #[inline]
+    pub fn set_cursor<T: Into<Cursor>>(&self, cursor: T) {
+        *self.cursor.write().unwrap() = cursor.into();
+    }
+
+    /// Gets the requested appearance of mouse cursor.
+    #[inline]
+    pub fn cursor(&self) -> Cursor {
+        self.cursor.read().unwrap().clone()
+    }
+
+    /// Gets the cursor which is not supported by the platform, and should be rendered
+    /// by software. The hardware cursor is hidden in that case.
+    #[inline]
+    pub fn software_cursor(&self) -> Option<Cursor> {
+        if *self.software_cursor.read().unwrap() {
+            Some(self.cursor())
+        } else {
+            None
+        }
+    }
+
     /// Returns the size in *points* of the client area of the window.
     ///
     /// The client area is the content of the window, excluding the title bar and borders. These are
@@ -253,7 +588,14 @@ pub trait Visitor {
     fn position(&self) -> math::Vector2<i32>;
     fn dimensions(&self) -> math::Vector2<u32>;
     fn hidpi(&self) -> f32;
+    fn safe_area_insets(&self) -> SafeAreaInsets;
     fn resize(&self, dimensions: math::Vector2<u32>);
+    fn set_position(&self, position: math::Vector2<i32>);
+    fn set_decorations(&self, decorations: bool);
+    fn set_always_on_top(&self, always_on_top: bool);
+    fn set_borderless_fullscreen(&self, enabled: bool);
+    /// Sets the hardware cursor, returns false if the cursor is not supported.
+    fn set_cursor(&self, cursor: &Cursor) -> bool;
 
     fn is_current(&self) -> bool;
     fn make_current(&self) -> Result<()>;
@@ -261,64 +603,196 @@ pub trait Visitor {
     fn get_proc_address(&self, addr: &str) -> *const ();
 }
 
-pub struct GlutinVisitor(glutin::GlWindow);
+type Placement = (glutin::dpi::LogicalPosition, glutin::dpi::LogicalSize);
+
+// The borderless fullscreen state, which is tracked apart from the placement to
+// restore since the placement of window might be unavailable when entering.
+#[derive(Debug, Default, Clone, Copy)]
+struct Borderless {
+    enabled: bool,
+    restore: Option<Placement>,
+}
+
+impl Borderless {
+    // Returns false if it is in borderless fullscreen already.
+    fn enter(&mut self, restore: Option<Placement>) -> bool {
+        if self.enabled {
+            return false;
+        }
+
+        self.enabled = true;
+        self.restore = restore;
+        true
+    }
+
+    // Returns `None` if it is not in borderless fullscreen, or the placement to
+    // restore otherwise.
+    fn leave(&mut self) -> Option<Option<Placement>> {
+        if !self.enabled {
+            return None;
+        }
+
+        self.enabled = false;
+        Some(self.restore.take())
+    }
+}
+
+pub struct GlutinVisitor {
+    window: glutin::GlWindow,
+    borderless: Cell<Borderless>,
+}
 
 impl Visitor for GlutinVisitor {
     #[inline]
     fn show(&self) {
-        self.0.show();
+        self.window.show();
     }
 
     #[inline]
     fn hide(&self) {
-        self.0.hide();
+        self.window.hide();
     }
 
     #[inline]
     fn position(&self) -> math::Vector2<i32> {
-        let pos = self.0.get_position().unwrap();
+        let pos = self.window.get_position().unwrap();
         math::Vector2::new(pos.x as i32, pos.y as i32)
     }
 
     #[inline]
     fn dimensions(&self) -> math::Vector2<u32> {
-        let size = self.0.get_inner_size().unwrap();
+        let size = self.window.get_inner_size().unwrap();
         math::Vector2::new(size.width as u32, size.height as u32)
     }
 
+    #[cfg(not(target_os = "ios"))]
+    #[inline]
+    fn hidpi(&self) -> f32 {
+        self.window.get_hidpi_factor() as f32
+    }
+
+    // The layer might be scaled differently from the screen of `UIWindow`.
+    #[cfg(target_os = "ios")]
     #[inline]
     fn hidpi(&self) -> f32 {
-        self.0.get_hidpi_factor() as f32
+        unsafe { super::ios::content_scale(self.window.get_uiview()) }
+    }
+
+    // The desktop windows are never obscured.
+    #[cfg(not(target_os = "ios"))]
+    #[inline]
+    fn safe_area_insets(&self) -> SafeAreaInsets {
+        SafeAreaInsets::default()
+    }
+
+    #[cfg(target_os = "ios")]
+    #[inline]
+    fn safe_area_insets(&self) -> SafeAreaInsets {
+        unsafe { super::ios::safe_area_insets(self.window.get_uiview()) }
     }
 
     #[inline]
     fn resize(&self, dimensions: math::Vector2<u32>) {
         let size = glutin::dpi::PhysicalSize::new(dimensions.x as f64, dimensions.y as f64);
-        self.0.resize(size)
+        self.window.resize(size)
+    }
+
+    #[inline]
+    fn set_position(&self, position: math::Vector2<i32>) {
+        let position = glutin::dpi::LogicalPosition::new(position.x as f64, position.y as f64);
+        self.window.set_position(position);
+    }
+
+    #[inline]
+    fn set_decorations(&self, decorations: bool) {
+        self.window.set_decorations(decorations);
+    }
+
+    #[inline]
+    fn set_always_on_top(&self, always_on_top: bool) {
+        self.window.set_always_on_top(always_on_top);
+    }
+
+    fn set_borderless_fullscreen(&self, enabled: bool) {
+        let mut borderless = self.borderless.get();
+
+        if enabled {
+            let restore = match (self.window.get_position(), self.window.get_inner_size()) {
+                (Some(position), Some(size)) => Some((position, size)),
+                _ => None,
+            };
+
+            if borderless.enter(restore) {
+                let monitor = self.window.get_current_monitor();
+                let hidpi = monitor.get_hidpi_factor();
+                self.window.set_decorations(false);
+                self.window
+                    .set_position(monitor.get_position().to_logical(hidpi));
+                self.window
+                    .set_inner_size(monitor.get_dimensions().to_logical(hidpi));
+            }
+        } else if let Some(restore) = borderless.leave() {
+            self.window.set_decorations(true);
+            if let Some((position, size)) = restore {
+                self.window.set_position(position);
+                self.window.set_inner_size(size);
+            }
+        }
+
+        self.borderless.set(borderless);
+    }
+
+    fn set_cursor(&self, cursor: &Cursor) -> bool {
+        let icon = match *cursor {
+            Cursor::Icon(icon) => icon,
+            Cursor::Custom(_) | Cursor::Hidden => {
+                self.window.hide_cursor(true);
+                return cursor == &Cursor::Hidden;
+            }
+        };
+
+        let icon = match icon {
+            CursorIcon::Default => glutin::MouseCursor::Default,
+            CursorIcon::Hand => glutin::MouseCursor::Hand,
+            CursorIcon::Text => glutin::MouseCursor::Text,
+            CursorIcon::Move => glutin::MouseCursor::Move,
+            CursorIcon::Crosshair => glutin::MouseCursor::Crosshair,
+            CursorIcon::Wait => glutin::MouseCursor::Wait,
+            CursorIcon::Help => glutin::MouseCursor::Help,
+            CursorIcon::NotAllowed => glutin::MouseCursor::NotAllowed,
+            CursorIcon::ResizeHorizontal => glutin::MouseCursor::EwResize,
+            CursorIcon::ResizeVertical => glutin::MouseCursor::NsResize,
+            CursorIcon::ResizeNeSw => glutin::MouseCursor::NeswResize,
+            CursorIcon::ResizeNwSe => glutin::MouseCursor::NwseResize,
+        };
+
+        self.window.set_cursor(icon);
+        self.window.hide_cursor(false);
+        true
     }
 
     #[inline]
     fn is_current(&self) -> bool {
-        self.0.is_current()
+        self.window.is_current()
     }
 
     #[inline]
     fn make_current(&self) -> Result<()> {
         unsafe {
-            self.0.make_current()?;
+            self.window.make_current()?;
             Ok(())
         }
     }
 
     #[inline]
     fn swap_buffers(&self) -> Result<()> {
-        self.0.swap_buffers()?;
+        self.window.swap_buffers()?;
         Ok(())
     }
 
     #[inline]
     fn get_proc_address(&self, addr: &str) -> *const () {
-        self.0.get_proc_address(addr)
+        self.window.get_proc_address(addr)
     }
 }
 
@@ -346,9 +820,31 @@ impl Visitor for HeadlessVisitor {
         1.0
     }
 
+    #[inline]
+    fn safe_area_insets(&self) -> SafeAreaInsets {
+        SafeAreaInsets::default()
+    }
+
     #[inline]
     fn resize(&self, _: math::Vector2<u32>) {}
 
+    #[inline]
+    fn set_position(&self, _: math::Vector2<i32>) {}
+
+    #[inline]
+    fn set_decorations(&self, _: bool) {}
+
+    #[inline]
+    fn set_always_on_top(&self, _: bool) {}
+
+    #[inline]
+    fn set_borderless_fullscreen(&self, _: bool) {}
+
+    #[inline]
+    fn set_cursor(&self, _: &Cursor) -> bool {
+        true
+    }
+
     #[inline]
     fn is_current(&self) -> bool {
         true
@@ -369,3 +865,78 @@ impl Visitor for HeadlessVisitor {
         ::std::ptr::null()
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn shortfalls() {
+        let format = PixelFormat {
+            color_bits: 24,
+            alpha_bits: 8,
+            depth_bits: 24,
+            stencil_bits: 8,
+            srgb: false,
+        };
+
+        let mut requested = BackbufferParams::default();
+        assert!(format.shortfalls(&requested).is_empty());
+
+        requested.color_bits = Some(30);
+        requested.depth = DepthFormat::D16;
+        requested.srgb = true;
+        let shortfalls = format.shortfalls(&requested);
+        assert_eq!(shortfalls.len(), 2);
+        assert_eq!(shortfalls[0], "Requested color 30 bits, but got 24 bits.");
+
+        requested = BackbufferParams::default();
+        requested.depth = DepthFormat::D32F;
+        assert_eq!(format.shortfalls(&requested).len(), 1);
+    }
+
+    #[test]
+    fn borderless() {
+        let placement = (
+            glutin::dpi::LogicalPosition::new(10.0, 20.0),
+            glutin::dpi::LogicalSize::new(640.0, 480.0),
+        );
+
+        let mut borderless = Borderless::default();
+        assert_eq!(borderless.leave(), None);
+
+        assert!(borderless.enter(Some(placement)));
+        assert!(!borderless.enter(None));
+        assert_eq!(borderless.leave(), Some(Some(placement)));
+        assert_eq!(borderless.leave(), None);
+
+        // Leaves the borderless fullscreen even if there is nothing to restore.
+        assert!(borderless.enter(None));
+        assert_eq!(borderless.leave(), Some(None));
+        assert!(borderless.enter(Some(placement)));
+    }
+
+    #[test]
+    fn relax() {
+        let mut requested = BackbufferParams::default();
+        requested.depth = DepthFormat::D32F;
+        requested.color_bits = Some(30);
+        requested.srgb = true;
+
+        let mut steps = Vec::new();
+        let mut v = requested;
+        while let Some(relaxed) = v.relax() {
+            steps.push(relaxed);
+            v = relaxed;
+        }
+
+        // The floating point depth is replaced by the closest integer precision first.
+        assert_eq!(steps[0].depth, DepthFormat::D24);
+        assert!(steps[0].srgb);
+        assert!(!steps[1].srgb);
+        assert_eq!(steps[2].color_bits, None);
+        assert_eq!(steps[3].depth, DepthFormat::D16);
+        assert_eq!(steps[4].stencil_bits, 0);
+        assert_eq!(steps.len(), 5);
+    }
+}
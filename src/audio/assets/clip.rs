@@ -0,0 +1,95 @@
+//! Decoded audio samples, which could be played by many sources at the same time.
+
+use errors::*;
+
+impl_handle!(AudioClipHandle);
+
+/// The decoded samples of a sound, in normalized floating points. The samples of
+/// channels are interleaved.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct AudioClip {
+    /// The number of channels, which is either 1 (mono) or 2 (stereo).
+    pub channels: u8,
+    /// The number of sample frames per second.
+    pub sample_rate: u32,
+    /// The interleaved samples in range `[-1, 1]`.
+    pub samples: Vec<f32>,
+}
+
+impl AudioClip {
+    /// Gets the number of sample frames.
+    #[inline]
+    pub fn frames(&self) -> usize {
+        self.samples.len() / usize::from(self.channels.max(1))
+    }
+
+    /// Gets the duration of clip in seconds.
+    #[inline]
+    pub fn duration(&self) -> f32 {
+        self.frames() as f32 / self.sample_rate.max(1) as f32
+    }
+
+    /// Gets the stereo samples of frame, the mono samples are duplicated into both
+    /// channels.
+    #[inline]
+    pub fn frame(&self, index: usize) -> (f32, f32) {
+        if self.channels == 1 {
+            let v = self.samples[index];
+            (v, v)
+        } else {
+            let i = index * 2;
+            (self.samples[i], self.samples[i + 1])
+        }
+    }
+
+    pub fn validate(&self) -> Result<()> {
+        if self.channels != 1 && self.channels != 2 {
+            bail!("Only mono or stereo audio clips are supported.");
+        }
+
+        if self.sample_rate == 0 {
+            bail!("The sample rate of audio clip must be positive.");
+        }
+
+        if self.samples.len() % usize::from(self.channels) != 0 {
+            bail!("The samples of audio clip are not aligned to channels.");
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn frames() {
+        let clip = AudioClip {
+            channels: 2,
+            sample_rate: 4,
+            samples: vec![0.0, 0.1, 0.2, 0.3],
+        };
+
+        assert!(clip.validate().is_ok());
+        assert_eq!(clip.frames(), 2);
+        assert_eq!(clip.duration(), 0.5);
+        assert_eq!(clip.frame(1), (0.2, 0.3));
+
+        let clip = AudioClip {
+            channels: 1,
+            sample_rate: 4,
+            samples: vec![0.0, 0.1, 0.2],
+        };
+
+        assert_eq!(clip.frame(1), (0.1, 0.1));
+
+        let mut invalid = clip.clone();
+        invalid.channels = 2;
+        assert!(invalid.validate().is_err());
+
+        let mut invalid = clip.clone();
+        invalid.sample_rate = 0;
+        assert!(invalid.validate().is_err());
+    }
+}
@@ -0,0 +1,66 @@
+use bincode;
+use std::io::Read;
+use std::sync::Arc;
+
+use errors::*;
+use res::format::{AssetFormat, Version};
+
+use super::super::AudioSystemShared;
+use super::clip::*;
+
+pub const FORMAT: AssetFormat = AssetFormat {
+    name: "AudioClipLoader",
+    magic: [b'A', b'C', b'L', b'P'],
+    version: Version {
+        major: 0,
+        minor: 0,
+        patch: 1,
+    },
+};
+
+pub struct AudioClipLoader {
+    audio: Arc<AudioSystemShared>,
+}
+
+impl AudioClipLoader {
+    pub fn new(audio: Arc<AudioSystemShared>) -> Self {
+        AudioClipLoader { audio: audio }
+    }
+}
+
+impl ::res::ResourceHandle for AudioClipHandle {
+    type Loader = AudioClipLoader;
+}
+
+impl ::res::ResourceLoader for AudioClipLoader {
+    type Handle = AudioClipHandle;
+
+    fn create(&self) -> Result<Self::Handle> {
+        let handle = self.audio.create_clip_async()?;
+        info!("[AudioClipLoader] creates {:?}.", handle);
+        Ok(handle)
+    }
+
+    fn load(&self, handle: Self::Handle, file: &mut dyn Read) -> Result<()> {
+        let clip: AudioClip = FORMAT.load(file, &[], |mut file| {
+            Ok(bincode::deserialize_from(&mut file)?)
+        })?;
+
+        info!(
+            "[AudioClipLoader] loads {:?} ({} channels, {}Hz, {:.2}s).",
+            handle,
+            clip.channels,
+            clip.sample_rate,
+            clip.duration()
+        );
+
+        self.audio.update_clip_async(handle, clip)?;
+        Ok(())
+    }
+
+    fn delete(&self, handle: Self::Handle) -> Result<()> {
+        self.audio.delete_clip(handle);
+        info!("[AudioClipLoader] deletes {:?}.", handle);
+        Ok(())
+    }
+}
@@ -0,0 +1,9 @@
+pub mod clip;
+pub mod clip_loader;
+pub mod stream;
+pub mod stream_loader;
+
+pub mod prelude {
+    pub use super::clip::{AudioClip, AudioClipHandle};
+    pub use super::stream::{AudioStream, AudioStreamFormat, AudioStreamHandle};
+}
@@ -0,0 +1,73 @@
+//! Compressed audio streams, which are decoded in chunks while playing.
+
+use std::sync::Arc;
+
+use errors::*;
+
+impl_handle!(AudioStreamHandle);
+
+/// The supported compressed formats of `AudioStream`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AudioStreamFormat {
+    /// Ogg Vorbis.
+    Ogg,
+    Mp3,
+}
+
+impl AudioStreamFormat {
+    /// Detects the format by the leading bytes of file.
+    pub fn detect(bytes: &[u8]) -> Option<Self> {
+        if bytes.starts_with(b"OggS") {
+            return Some(AudioStreamFormat::Ogg);
+        }
+
+        // The MP3 files start with either ID3 tags or the sync word of frame header.
+        if bytes.starts_with(b"ID3")
+            || (bytes.len() >= 2 && bytes[0] == 0xFF && bytes[1] & 0xE0 == 0xE0)
+        {
+            return Some(AudioStreamFormat::Mp3);
+        }
+
+        None
+    }
+}
+
+/// The compressed bytes of a long sound, e.g. music tracks. Unlike `AudioClip`, only
+/// the compressed bytes are kept in memory, and the samples are decoded in chunks on
+/// the streaming worker while playing.
+#[derive(Debug, Clone)]
+pub struct AudioStream {
+    pub format: AudioStreamFormat,
+    pub bytes: Arc<Vec<u8>>,
+}
+
+impl AudioStream {
+    /// Creates a stream from the bytes of OGG Vorbis or MP3 file.
+    pub fn new(bytes: Vec<u8>) -> Result<Self> {
+        let format = AudioStreamFormat::detect(&bytes)
+            .ok_or_else(|| format_err!("Only OGG Vorbis and MP3 audio streams are supported."))?;
+
+        Ok(AudioStream {
+            format: format,
+            bytes: Arc::new(bytes),
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn detect() {
+        let detect = AudioStreamFormat::detect;
+        assert_eq!(detect(b"OggS\x00\x02"), Some(AudioStreamFormat::Ogg));
+        assert_eq!(detect(b"ID3\x04\x00"), Some(AudioStreamFormat::Mp3));
+        assert_eq!(detect(&[0xFF, 0xFB, 0x90]), Some(AudioStreamFormat::Mp3));
+        assert_eq!(detect(b"RIFF"), None);
+        assert_eq!(detect(&[0xFF]), None);
+
+        assert!(AudioStream::new(b"OggS".to_vec()).is_ok());
+        assert!(AudioStream::new(Vec::new()).is_err());
+    }
+}
@@ -0,0 +1,59 @@
+use std::io::Read;
+use std::sync::Arc;
+
+use errors::*;
+
+use super::super::AudioSystemShared;
+use super::stream::*;
+
+/// Loads the OGG Vorbis or MP3 files as `AudioStream`s. The files are kept compressed
+/// in memory, so they are read as they are instead of being wrapped by `AssetFormat`.
+pub struct AudioStreamLoader {
+    audio: Arc<AudioSystemShared>,
+}
+
+impl AudioStreamLoader {
+    pub fn new(audio: Arc<AudioSystemShared>) -> Self {
+        AudioStreamLoader { audio: audio }
+    }
+}
+
+impl ::res::ResourceHandle for AudioStreamHandle {
+    type Loader = AudioStreamLoader;
+}
+
+impl ::res::ResourceLoader for AudioStreamLoader {
+    type Handle = AudioStreamHandle;
+
+    fn create(&self) -> Result<Self::Handle> {
+        let handle = self.audio.create_stream_async()?;
+        info!("[AudioStreamLoader] creates {:?}.", handle);
+        Ok(handle)
+    }
+
+    fn load(&self, handle: Self::Handle, file: &mut dyn Read) -> Result<()> {
+        let mut bytes = Vec::new();
+        file.read_to_end(&mut bytes)?;
+        self.load_bytes(handle, &bytes)
+    }
+
+    fn load_bytes(&self, handle: Self::Handle, bytes: &[u8]) -> Result<()> {
+        let stream = AudioStream::new(bytes.to_vec())?;
+
+        info!(
+            "[AudioStreamLoader] loads {:?} ({:?}, {} bytes).",
+            handle,
+            stream.format,
+            bytes.len()
+        );
+
+        self.audio.update_stream_async(handle, stream)?;
+        Ok(())
+    }
+
+    fn delete(&self, handle: Self::Handle) -> Result<()> {
+        self.audio.delete_stream(handle);
+        info!("[AudioStreamLoader] deletes {:?}.", handle);
+        Ok(())
+    }
+}
@@ -0,0 +1,179 @@
+use std::panic::{self, AssertUnwindSafe};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc;
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::Duration;
+
+use cpal;
+
+use errors::*;
+
+use super::super::mixer::Mixer;
+use super::Visitor;
+
+// The payload which unwinds the audio thread out of `EventLoop::run`.
+struct Terminated;
+
+/// The backend which plays on the default output device of system. The samples are
+/// pulled by the device on a dedicated thread.
+pub struct DeviceVisitor {
+    events: Arc<cpal::EventLoop>,
+    stream: cpal::StreamId,
+    terminated: Arc<AtomicBool>,
+    thread: Option<thread::JoinHandle<()>>,
+    finished: mpsc::Receiver<()>,
+}
+
+impl DeviceVisitor {
+    pub fn new(mixer: Arc<Mutex<Mixer>>) -> Result<Self> {
+        let device = cpal::default_output_device()
+            .ok_or_else(|| format_err!("Could not find any audio output device."))?;
+
+        let format = device
+            .default_output_format()
+            .map_err(|err| format_err!("{:?}", err))?;
+
+        let events = Arc::new(cpal::EventLoop::new());
+        let stream = events
+            .build_output_stream(&device, &format)
+            .map_err(|err| format_err!("{:?}", err))?;
+
+        events.play_stream(stream.clone());
+
+        let rate = format.sample_rate.0;
+        let channels = usize::from(format.channels.max(1));
+        let terminated = Arc::new(AtomicBool::new(false));
+        let (tx, finished) = mpsc::channel();
+
+        let thread = {
+            let events = events.clone();
+            let terminated = terminated.clone();
+            thread::Builder::new().name("Audio".into()).spawn(move || {
+                let id = thread::current().id();
+                let mut buf = Vec::new();
+
+                // `EventLoop::run` never returns, so the thread unwinds out of it once
+                // terminated. The callbacks invoked on the threads of system, e.g. by
+                // CoreAudio, could not unwind and output silence instead.
+                let _ = panic::catch_unwind(AssertUnwindSafe(|| {
+                    events.run(move |_, data| {
+                        let terminated = terminated.load(Ordering::Relaxed);
+                        if terminated && thread::current().id() == id {
+                            panic::resume_unwind(Box::new(Terminated));
+                        }
+
+                        if let cpal::StreamData::Output { buffer } = data {
+                            let mixer = if terminated { None } else { Some(&*mixer) };
+                            Self::write(mixer, &mut buf, rate, channels, buffer);
+                        }
+                    })
+                }));
+
+                let _ = tx.send(());
+            })?
+        };
+
+        info!(
+            "[DeviceVisitor] plays on {} ({} channels, {}Hz).",
+            device.name(),
+            format.channels,
+            rate
+        );
+
+        Ok(DeviceVisitor {
+            events: events,
+            stream: stream,
+            terminated: terminated,
+            thread: Some(thread),
+            finished: finished,
+        })
+    }
+
+    // Writes the mixed samples into output, or silence if `mixer` is `None`.
+    fn write(
+        mixer: Option<&Mutex<Mixer>>,
+        buf: &mut Vec<f32>,
+        rate: u32,
+        channels: usize,
+        output: cpal::UnknownTypeOutputBuffer,
+    ) {
+        let len = match output {
+            cpal::UnknownTypeOutputBuffer::F32(ref v) => v.len(),
+            cpal::UnknownTypeOutputBuffer::I16(ref v) => v.len(),
+            cpal::UnknownTypeOutputBuffer::U16(ref v) => v.len(),
+        };
+
+        buf.resize(len / channels * 2, 0.0);
+        match mixer {
+            Some(mixer) => mixer.lock().unwrap().mix(rate, buf),
+            None => {
+                for v in buf.iter_mut() {
+                    *v = 0.0;
+                }
+            }
+        }
+
+        // Maps the stereo samples into the channels of device, the extra channels
+        // are left silent.
+        let sample = |i: usize| {
+            let (frame, channel) = (i / channels, i % channels);
+            match (channels, channel) {
+                (1, _) => (buf[frame * 2] + buf[frame * 2 + 1]) * 0.5,
+                (_, 0) | (_, 1) => buf[frame * 2 + channel],
+                _ => 0.0,
+            }
+        };
+
+        match output {
+            cpal::UnknownTypeOutputBuffer::F32(mut v) => {
+                for (i, v) in v.iter_mut().enumerate() {
+                    *v = sample(i);
+                }
+            }
+            cpal::UnknownTypeOutputBuffer::I16(mut v) => {
+                for (i, v) in v.iter_mut().enumerate() {
+                    *v = (sample(i) * f32::from(::std::i16::MAX)) as i16;
+                }
+            }
+            cpal::UnknownTypeOutputBuffer::U16(mut v) => {
+                for (i, v) in v.iter_mut().enumerate() {
+                    *v = ((sample(i) * 0.5 + 0.5) * f32::from(::std::u16::MAX)) as u16;
+                }
+            }
+        }
+    }
+}
+
+impl Visitor for DeviceVisitor {
+    fn advance(&mut self, _: Duration) -> Result<()> {
+        Ok(())
+    }
+
+    fn set_paused(&mut self, paused: bool) {
+        if paused {
+            self.events.pause_stream(self.stream.clone());
+        } else {
+            self.events.play_stream(self.stream.clone());
+        }
+    }
+}
+
+impl Drop for DeviceVisitor {
+    fn drop(&mut self) {
+        self.terminated.store(true, Ordering::Relaxed);
+
+        // The stream keeps pulling until the next callback unwinds the audio thread,
+        // it's detached if the device stops calling back.
+        if let Some(thread) = self.thread.take() {
+            self.events.play_stream(self.stream.clone());
+            if self.finished.recv_timeout(Duration::from_secs(1)).is_ok() {
+                let _ = thread.join();
+            } else {
+                warn!("[DeviceVisitor] the audio thread does not stop, detaching it.");
+            }
+        }
+
+        self.events.destroy_stream(self.stream.clone());
+    }
+}
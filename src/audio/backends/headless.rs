@@ -0,0 +1,55 @@
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use errors::*;
+
+use super::super::mixer::Mixer;
+use super::Visitor;
+
+pub const HEADLESS_SAMPLE_RATE: u32 = 44100;
+
+/// The backend without any output device. The samples are mixed and discarded as
+/// time goes by, so the playbacks still progress and finish.
+pub struct HeadlessVisitor {
+    mixer: Arc<Mutex<Mixer>>,
+    buf: Vec<f32>,
+    // The fractional sample frames which are not mixed yet.
+    remainder: f64,
+    paused: bool,
+}
+
+impl HeadlessVisitor {
+    pub fn new(mixer: Arc<Mutex<Mixer>>) -> Self {
+        HeadlessVisitor {
+            mixer: mixer,
+            buf: Vec::new(),
+            remainder: 0.0,
+            paused: false,
+        }
+    }
+}
+
+impl Visitor for HeadlessVisitor {
+    fn advance(&mut self, delta: Duration) -> Result<()> {
+        if self.paused {
+            return Ok(());
+        }
+
+        let secs = delta.as_secs() as f64 + f64::from(delta.subsec_nanos()) * 1e-9;
+        self.remainder += secs * f64::from(HEADLESS_SAMPLE_RATE);
+
+        let frames = self.remainder as usize;
+        self.remainder -= frames as f64;
+
+        self.buf.resize(frames * 2, 0.0);
+        self.mixer
+            .lock()
+            .unwrap()
+            .mix(HEADLESS_SAMPLE_RATE, &mut self.buf);
+        Ok(())
+    }
+
+    fn set_paused(&mut self, paused: bool) {
+        self.paused = paused;
+    }
+}
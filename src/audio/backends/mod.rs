@@ -0,0 +1,18 @@
+//! The backend of audio system, which pulls the mixed samples into the output device.
+
+pub mod device;
+pub mod headless;
+
+use std::time::Duration;
+
+use errors::*;
+
+pub trait Visitor {
+    /// Advance one frame, it will be called every frames. The devices which pull the
+    /// samples on their own threads could simply ignore it.
+    fn advance(&mut self, delta: Duration) -> Result<()>;
+
+    /// Pauses (or resumes) pulling the samples, the playbacks do not progress while
+    /// paused.
+    fn set_paused(&mut self, paused: bool);
+}
@@ -0,0 +1,165 @@
+//! The mixing graph. Every source is routed into a bus, and the child buses are
+//! processed with their effects and summed into the master bus.
+
+/// The buses of mixing graph.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum AudioBus {
+    /// The final bus, which all the other buses are routed into.
+    Master = 0,
+    Music,
+    Sfx,
+}
+
+/// The number of `AudioBus`.
+pub const NUM_BUSES: usize = 3;
+
+impl AudioBus {
+    /// All the buses.
+    pub const ALL: [AudioBus; NUM_BUSES] = [AudioBus::Master, AudioBus::Music, AudioBus::Sfx];
+}
+
+/// The cutoff frequency in Hz at or above which the low-pass filter is bypassed.
+pub const LOW_PASS_BYPASS: f32 = 20_000.0;
+
+/// The parameters of a bus.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct AudioBusParams {
+    /// The volume scale of bus.
+    pub volume: f32,
+    /// The cutoff frequency in Hz of low-pass filter, which muffles the bus. It's
+    /// bypassed at `LOW_PASS_BYPASS` or above the Nyquist frequency of output.
+    pub low_pass: f32,
+    /// The amount of signal sent into the shared reverb, in range `[0, 1]`. It's
+    /// ignored by the master bus.
+    pub reverb_send: f32,
+    /// The threshold of soft limiter in range `[0, 1]`, the peaks above it are
+    /// compressed smoothly instead of being clipped. A value of 1.0 disables it.
+    pub limit: f32,
+}
+
+impl Default for AudioBusParams {
+    fn default() -> Self {
+        AudioBusParams {
+            volume: 1.0,
+            low_pass: LOW_PASS_BYPASS,
+            reverb_send: 0.0,
+            limit: 1.0,
+        }
+    }
+}
+
+impl AudioBusParams {
+    /// Interpolates between the parameters. The cutoff frequency is interpolated
+    /// logarithmically, so the sweeps of filter sound even.
+    pub fn lerp(&self, to: &Self, t: f32) -> Self {
+        if t <= 0.0 {
+            return *self;
+        }
+
+        if t >= 1.0 {
+            return *to;
+        }
+
+        let lerp = |a: f32, b: f32| a + (b - a) * t;
+
+        // Keeps the cutoff exact if unchanged, the round trip through logarithm
+        // would engage a bypassed filter.
+        let low_pass = if self.low_pass == to.low_pass {
+            self.low_pass
+        } else {
+            lerp(self.low_pass.max(1.0).ln(), to.low_pass.max(1.0).ln()).exp()
+        };
+
+        AudioBusParams {
+            volume: lerp(self.volume, to.volume),
+            low_pass: low_pass,
+            reverb_send: lerp(self.reverb_send, to.reverb_send),
+            limit: lerp(self.limit, to.limit),
+        }
+    }
+}
+
+/// A set of parameters of all the buses, which could be transitioned to smoothly,
+/// e.g. muffles the music and sound effects when the pause menu shows up.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct AudioMixerSnapshot {
+    pub buses: [AudioBusParams; NUM_BUSES],
+}
+
+impl AudioMixerSnapshot {
+    #[inline]
+    pub fn bus(&self, bus: AudioBus) -> &AudioBusParams {
+        &self.buses[bus as usize]
+    }
+
+    #[inline]
+    pub fn bus_mut(&mut self, bus: AudioBus) -> &mut AudioBusParams {
+        &mut self.buses[bus as usize]
+    }
+
+    /// Interpolates between the snapshots.
+    pub fn lerp(&self, to: &Self, t: f32) -> Self {
+        let mut v = *self;
+        for (i, bus) in v.buses.iter_mut().enumerate() {
+            *bus = self.buses[i].lerp(&to.buses[i], t);
+        }
+
+        v
+    }
+}
+
+/// Lowers the volume of `target` bus while the `trigger` bus is playing, e.g. ducks
+/// the music under the dialogues.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct AudioDucking {
+    pub trigger: AudioBus,
+    pub target: AudioBus,
+    /// The peak level of trigger above which the target is ducked.
+    pub threshold: f32,
+    /// The volume scale of target when its fully ducked.
+    pub volume: f32,
+    /// The time in seconds to duck the target.
+    pub attack: f32,
+    /// The time in seconds to restore the target after the trigger falls silent.
+    pub release: f32,
+}
+
+impl AudioDucking {
+    pub fn new(trigger: AudioBus, target: AudioBus) -> Self {
+        AudioDucking {
+            trigger: trigger,
+            target: target,
+            threshold: 0.01,
+            volume: 0.3,
+            attack: 0.05,
+            release: 0.5,
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn lerp() {
+        let from = AudioMixerSnapshot::default();
+        let mut to = from;
+        to.bus_mut(AudioBus::Music).volume = 0.0;
+        to.bus_mut(AudioBus::Music).low_pass = 200.0;
+        to.bus_mut(AudioBus::Sfx).reverb_send = 1.0;
+
+        assert_eq!(from.lerp(&to, 0.0), from);
+        assert_eq!(from.lerp(&to, -1.0), from);
+
+        let v = from.lerp(&to, 0.5);
+        assert_eq!(v.bus(AudioBus::Music).volume, 0.5);
+        assert!((v.bus(AudioBus::Music).low_pass - 2000.0).abs() < 0.1);
+        assert_eq!(v.bus(AudioBus::Sfx).reverb_send, 0.5);
+        assert_eq!(*v.bus(AudioBus::Master), AudioBusParams::default());
+
+        let v = from.lerp(&to, 1.0);
+        assert_eq!(v.bus(AudioBus::Music).volume, 0.0);
+        assert!((v.bus(AudioBus::Music).low_pass - 200.0).abs() < 0.01);
+    }
+}
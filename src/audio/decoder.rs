@@ -0,0 +1,199 @@
+//! The decoders of compressed audio streams, which produce the interleaved samples in
+//! chunks.
+
+use std::io::Cursor;
+use std::sync::Arc;
+
+use lewton::inside_ogg::OggStreamReader;
+use minimp3;
+
+use errors::*;
+
+use super::assets::prelude::*;
+
+pub trait Decoder: Send {
+    /// Gets the number of channels, which is either 1 (mono) or 2 (stereo).
+    fn channels(&self) -> u8;
+
+    /// Gets the number of sample frames per second.
+    fn sample_rate(&self) -> u32;
+
+    /// Decodes the next chunk of interleaved samples, and appends them into `buf`.
+    /// Returns false if it has reached the end of stream.
+    fn decode(&mut self, buf: &mut Vec<f32>) -> Result<bool>;
+
+    /// Moves to the sample frame, the next chunk is decoded from there.
+    fn seek(&mut self, frame: u64) -> Result<()>;
+}
+
+/// Opens the decoder of stream, the headers are parsed immediately.
+pub fn open(stream: &AudioStream) -> Result<Box<Decoder>> {
+    let decoder: Box<Decoder> = match stream.format {
+        AudioStreamFormat::Ogg => Box::new(OggDecoder::new(stream.bytes.clone())?),
+        AudioStreamFormat::Mp3 => Box::new(Mp3Decoder::new(stream.bytes.clone())?),
+    };
+
+    if decoder.channels() != 1 && decoder.channels() != 2 {
+        bail!("Only mono or stereo audio streams are supported.");
+    }
+
+    if decoder.sample_rate() == 0 {
+        bail!("The sample rate of audio stream must be positive.");
+    }
+
+    Ok(decoder)
+}
+
+// Shares the compressed bytes between the decoders of the same stream.
+struct Bytes(Arc<Vec<u8>>);
+
+impl AsRef<[u8]> for Bytes {
+    fn as_ref(&self) -> &[u8] {
+        &self.0
+    }
+}
+
+#[inline]
+fn extend_i16(buf: &mut Vec<f32>, samples: &[i16]) {
+    buf.extend(samples.iter().map(|&v| f32::from(v) / 32768.0));
+}
+
+pub struct OggDecoder {
+    reader: OggStreamReader<Cursor<Bytes>>,
+}
+
+impl OggDecoder {
+    pub fn new(bytes: Arc<Vec<u8>>) -> Result<Self> {
+        let reader = OggStreamReader::new(Cursor::new(Bytes(bytes)))
+            .map_err(|err| format_err!("{:?}", err))?;
+
+        Ok(OggDecoder { reader: reader })
+    }
+}
+
+impl Decoder for OggDecoder {
+    fn channels(&self) -> u8 {
+        self.reader.ident_hdr.audio_channels
+    }
+
+    fn sample_rate(&self) -> u32 {
+        self.reader.ident_hdr.audio_sample_rate
+    }
+
+    fn decode(&mut self, buf: &mut Vec<f32>) -> Result<bool> {
+        loop {
+            let packet = self
+                .reader
+                .read_dec_packet_itl()
+                .map_err(|err| format_err!("{:?}", err))?;
+
+            match packet {
+                // The first packet after (re)starting holds no samples.
+                Some(ref v) if v.is_empty() => continue,
+                Some(v) => {
+                    extend_i16(buf, &v);
+                    return Ok(true);
+                }
+                None => return Ok(false),
+            }
+        }
+    }
+
+    /// Moves to the page containing the sample frame, so it's only accurate to the
+    /// granularity of Ogg pages.
+    fn seek(&mut self, frame: u64) -> Result<()> {
+        self.reader
+            .seek_absgp_pg(frame)
+            .map_err(|err| format_err!("{:?}", err))?;
+        Ok(())
+    }
+}
+
+pub struct Mp3Decoder {
+    bytes: Arc<Vec<u8>>,
+    decoder: minimp3::Decoder<Cursor<Bytes>>,
+    channels: u8,
+    sample_rate: u32,
+    // The samples decoded in advance, e.g. the first frame which is used to probe the
+    // format of stream, and the rest of frame after seeking.
+    pending: Vec<f32>,
+}
+
+impl Mp3Decoder {
+    pub fn new(bytes: Arc<Vec<u8>>) -> Result<Self> {
+        let mut decoder = minimp3::Decoder::new(Cursor::new(Bytes(bytes.clone())));
+        let mut pending = Vec::new();
+
+        // MP3 has no header of stream, so the format is probed from the first frame.
+        let (channels, sample_rate) = match Self::next(&mut decoder, &mut pending)? {
+            Some(v) => v,
+            None => bail!("Could not find any frame in MP3 stream."),
+        };
+
+        Ok(Mp3Decoder {
+            bytes: bytes,
+            decoder: decoder,
+            channels: channels,
+            sample_rate: sample_rate,
+            pending: pending,
+        })
+    }
+
+    // Decodes the next frame, returns its channels and sample rate.
+    fn next(
+        decoder: &mut minimp3::Decoder<Cursor<Bytes>>,
+        buf: &mut Vec<f32>,
+    ) -> Result<Option<(u8, u32)>> {
+        loop {
+            match decoder.next_frame() {
+                Ok(frame) => {
+                    extend_i16(buf, &frame.data);
+                    return Ok(Some((frame.channels as u8, frame.sample_rate as u32)));
+                }
+                Err(minimp3::Error::SkippedData) => continue,
+                Err(minimp3::Error::Eof) => return Ok(None),
+                Err(err) => bail!("{:?}", err),
+            }
+        }
+    }
+}
+
+impl Decoder for Mp3Decoder {
+    fn channels(&self) -> u8 {
+        self.channels
+    }
+
+    fn sample_rate(&self) -> u32 {
+        self.sample_rate
+    }
+
+    fn decode(&mut self, buf: &mut Vec<f32>) -> Result<bool> {
+        if !self.pending.is_empty() {
+            buf.append(&mut self.pending);
+            return Ok(true);
+        }
+
+        Ok(Self::next(&mut self.decoder, buf)?.is_some())
+    }
+
+    /// Restarts from the beginning and skips the frames before `frame`, since the MP3
+    /// frames have no index of positions.
+    fn seek(&mut self, frame: u64) -> Result<()> {
+        self.decoder = minimp3::Decoder::new(Cursor::new(Bytes(self.bytes.clone())));
+        self.pending.clear();
+
+        let mut skip = frame * u64::from(self.channels);
+        let mut buf = Vec::new();
+        while Self::next(&mut self.decoder, &mut buf)?.is_some() {
+            if (buf.len() as u64) > skip {
+                self.pending = buf.split_off(skip as usize);
+                break;
+            }
+
+            skip -= buf.len() as u64;
+            buf.clear();
+        }
+
+        Ok(())
+    }
+}
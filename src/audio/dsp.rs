@@ -0,0 +1,248 @@
+//! The basic effects of mixing graph, which process the stereo frames one by one.
+
+use std::f32::consts::PI;
+
+/// The one-pole low-pass filter.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct LowPass {
+    state: (f32, f32),
+}
+
+impl LowPass {
+    /// Gets the smoothing coefficient, or `None` if the filter should be bypassed.
+    pub fn coefficient(cutoff: f32, bypass: f32, sample_rate: u32) -> Option<f32> {
+        let sample_rate = sample_rate.max(1) as f32;
+        if cutoff >= bypass || cutoff >= sample_rate * 0.5 {
+            return None;
+        }
+
+        Some(1.0 - (-2.0 * PI * cutoff.max(0.0) / sample_rate).exp())
+    }
+
+    pub fn process(&mut self, coefficient: Option<f32>, v: (f32, f32)) -> (f32, f32) {
+        match coefficient {
+            Some(a) => {
+                self.state.0 += (v.0 - self.state.0) * a;
+                self.state.1 += (v.1 - self.state.1) * a;
+                self.state
+            }
+            None => {
+                self.state = v;
+                v
+            }
+        }
+    }
+}
+
+/// Compresses the peaks above `threshold` smoothly into `[-1, 1]`.
+pub fn soft_limit(v: f32, threshold: f32) -> f32 {
+    let threshold = threshold.max(0.0).min(1.0);
+    let a = v.abs();
+    if threshold >= 1.0 || a <= threshold {
+        return v;
+    }
+
+    let knee = 1.0 - threshold;
+    (threshold + knee * ((a - threshold) / knee).tanh()) * v.signum()
+}
+
+/// Gets the coefficient of envelope follower, which reaches about 63% of the target
+/// in `secs`.
+pub fn envelope_coefficient(secs: f32, sample_rate: u32) -> f32 {
+    let frames = secs * sample_rate.max(1) as f32;
+    if frames <= 1.0 {
+        1.0
+    } else {
+        1.0 - (-1.0 / frames).exp()
+    }
+}
+
+// The tunings of Freeverb at 44100Hz.
+const COMBS: [usize; 4] = [1116, 1188, 1277, 1356];
+const ALLPASSES: [usize; 2] = [556, 441];
+const STEREO_SPREAD: usize = 23;
+const FEEDBACK: f32 = 0.84;
+const DAMPING: f32 = 0.2;
+const INPUT_GAIN: f32 = 0.015;
+const WET_GAIN: f32 = 3.0;
+
+struct Comb {
+    buf: Vec<f32>,
+    index: usize,
+    filter: f32,
+}
+
+impl Comb {
+    fn process(&mut self, v: f32) -> f32 {
+        let output = self.buf[self.index];
+        self.filter = output * (1.0 - DAMPING) + self.filter * DAMPING;
+        self.buf[self.index] = v + self.filter * FEEDBACK;
+        self.index = (self.index + 1) % self.buf.len();
+        output
+    }
+}
+
+struct AllPass {
+    buf: Vec<f32>,
+    index: usize,
+}
+
+impl AllPass {
+    fn process(&mut self, v: f32) -> f32 {
+        let delayed = self.buf[self.index];
+        self.buf[self.index] = v + delayed * 0.5;
+        self.index = (self.index + 1) % self.buf.len();
+        delayed - v
+    }
+}
+
+struct ReverbChannel {
+    combs: Vec<Comb>,
+    allpasses: Vec<AllPass>,
+}
+
+impl ReverbChannel {
+    fn new(sample_rate: u32, spread: usize) -> Self {
+        let scale = |v: usize| {
+            let len = (v + spread) as f32 * sample_rate.max(1) as f32 / 44100.0;
+            (len as usize).max(1)
+        };
+
+        ReverbChannel {
+            combs: COMBS
+                .iter()
+                .map(|&v| Comb {
+                    buf: vec![0.0; scale(v)],
+                    index: 0,
+                    filter: 0.0,
+                })
+                .collect(),
+            allpasses: ALLPASSES
+                .iter()
+                .map(|&v| AllPass {
+                    buf: vec![0.0; scale(v)],
+                    index: 0,
+                })
+                .collect(),
+        }
+    }
+
+    fn process(&mut self, v: f32) -> f32 {
+        let mut output = 0.0;
+        for comb in &mut self.combs {
+            output += comb.process(v);
+        }
+
+        for allpass in &mut self.allpasses {
+            output = allpass.process(output);
+        }
+
+        output
+    }
+}
+
+/// The Schroeder reverberator shared by all the buses, which returns the wet signal
+/// only.
+pub struct Reverb {
+    sample_rate: u32,
+    channels: Option<(ReverbChannel, ReverbChannel)>,
+}
+
+impl Default for Reverb {
+    fn default() -> Self {
+        Reverb {
+            sample_rate: 0,
+            channels: None,
+        }
+    }
+}
+
+impl Reverb {
+    pub fn process(&mut self, sample_rate: u32, v: (f32, f32)) -> (f32, f32) {
+        // The delay lines are allocated lazily, and reallocated if the sample rate of
+        // output changes.
+        if self.channels.is_none() || self.sample_rate != sample_rate {
+            self.sample_rate = sample_rate;
+            self.channels = Some((
+                ReverbChannel::new(sample_rate, 0),
+                ReverbChannel::new(sample_rate, STEREO_SPREAD),
+            ));
+        }
+
+        let (ref mut left, ref mut right) = *self.channels.as_mut().unwrap();
+        let input = (v.0 + v.1) * INPUT_GAIN;
+        (
+            left.process(input) * WET_GAIN,
+            right.process(input) * WET_GAIN,
+        )
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn low_pass() {
+        assert!(LowPass::coefficient(20000.0, 20000.0, 48000).is_none());
+        assert!(LowPass::coefficient(10000.0, 20000.0, 16000).is_none());
+
+        let a = LowPass::coefficient(1000.0, 20000.0, 48000);
+        assert!(a.is_some());
+
+        // Converges to DC, and attenuates the alternating signal.
+        let mut filter = LowPass::default();
+        for _ in 0..1000 {
+            filter.process(a, (1.0, -1.0));
+        }
+
+        let v = filter.process(a, (1.0, -1.0));
+        assert!((v.0 - 1.0).abs() < 1e-4 && (v.1 + 1.0).abs() < 1e-4);
+
+        let mut filter = LowPass::default();
+        let mut peak = 0.0f32;
+        for i in 0..1000 {
+            let x = if i % 2 == 0 { 1.0 } else { -1.0 };
+            peak = peak.max(filter.process(a, (x, x)).0.abs());
+        }
+
+        assert!(peak < 0.2);
+        assert_eq!(filter.process(None, (0.5, 0.25)), (0.5, 0.25));
+    }
+
+    #[test]
+    fn limit() {
+        assert_eq!(soft_limit(0.5, 0.8), 0.5);
+        assert_eq!(soft_limit(-0.8, 0.8), -0.8);
+        assert_eq!(soft_limit(3.0, 1.0), 3.0);
+
+        let v = soft_limit(1.5, 0.8);
+        assert!(v > 0.8 && v < 1.0);
+        assert_eq!(soft_limit(-1.5, 0.8), -v);
+        assert!(soft_limit(100.0, 0.0) <= 1.0);
+    }
+
+    #[test]
+    fn reverb() {
+        let mut reverb = Reverb::default();
+        assert_eq!(reverb.process(44100, (0.0, 0.0)), (0.0, 0.0));
+
+        // The impulse is delayed, and decays afterwards.
+        let mut energy = Vec::new();
+        let (l, r) = reverb.process(44100, (1.0, 1.0));
+        assert_eq!((l, r), (0.0, 0.0));
+
+        for _ in 0..8 {
+            let mut sum = 0.0;
+            for _ in 0..4410 {
+                let (l, r) = reverb.process(44100, (0.0, 0.0));
+                sum += l * l + r * r;
+            }
+
+            energy.push(sum);
+        }
+
+        assert!(energy[0] > 0.0);
+        assert!(energy[7] < energy[1]);
+    }
+}
@@ -0,0 +1,938 @@
+//! The software mixer, which resamples the playing sources into the buses of mixing
+//! graph, and mixes the buses into the stereo output of device.
+
+use std::collections::VecDeque;
+use std::sync::Arc;
+
+use utils::object_pool::ObjectPool;
+
+use super::assets::prelude::*;
+use super::bus::*;
+use super::dsp::{self, LowPass, Reverb};
+use super::source::*;
+use super::streaming::StreamBuffer;
+
+enum Voice {
+    Clip(Playback),
+    Stream(StreamPlayback),
+}
+
+struct Playback {
+    clip: Arc<AudioClip>,
+    params: AudioSource,
+    // The position in sample frames of clip.
+    cursor: f64,
+    // The number of times the clip has been played through.
+    played: u32,
+    fade: Option<Fade>,
+}
+
+struct StreamPlayback {
+    buffer: Arc<StreamBuffer>,
+    params: AudioStreamSource,
+    // The frames pulled from the buffer but not played yet.
+    frames: VecDeque<(f32, f32)>,
+    // The position in sample frames relative to the first one of `frames`.
+    cursor: f64,
+    fade: Option<Fade>,
+}
+
+impl Drop for StreamPlayback {
+    fn drop(&mut self) {
+        // Drops the decoder on the worker as well.
+        self.buffer.stop();
+    }
+}
+
+// The volume envelope of fading in or out.
+#[derive(Debug, Clone, Copy)]
+struct Fade {
+    from: f32,
+    to: f32,
+    // The duration and elapsed time in seconds.
+    duration: f32,
+    elapsed: f32,
+    // Stops the playback once faded.
+    stop: bool,
+}
+
+impl Fade {
+    fn gain(&self) -> f32 {
+        if self.elapsed >= self.duration {
+            return self.to;
+        }
+
+        self.from + (self.to - self.from) * (self.elapsed / self.duration)
+    }
+
+    // Returns the gain of current sample frame, and advances by `dt` seconds.
+    fn advance(&mut self, dt: f32) -> f32 {
+        let gain = self.gain();
+        self.elapsed += dt;
+        gain
+    }
+
+    fn is_stopped(&self) -> bool {
+        self.stop && self.elapsed >= self.duration
+    }
+}
+
+struct Transition {
+    from: AudioMixerSnapshot,
+    to: AudioMixerSnapshot,
+    // The duration and elapsed time in seconds.
+    duration: f32,
+    elapsed: f32,
+}
+
+#[derive(Default)]
+struct Bus {
+    buf: Vec<f32>,
+    low_pass: LowPass,
+}
+
+/// Mixes the playing sources into the interleaved stereo samples.
+pub struct Mixer {
+    listener: AudioListener,
+    playbacks: ObjectPool<Voice>,
+    snapshot: AudioMixerSnapshot,
+    transition: Option<Transition>,
+    // The ducking rules with their envelopes.
+    duckings: Vec<(AudioDucking, f32)>,
+    buses: Vec<Bus>,
+    reverb: Reverb,
+}
+
+impl Default for Mixer {
+    fn default() -> Self {
+        Mixer {
+            listener: AudioListener::default(),
+            playbacks: ObjectPool::new(),
+            snapshot: AudioMixerSnapshot::default(),
+            transition: None,
+            duckings: Vec::new(),
+            buses: (0..NUM_BUSES).map(|_| Bus::default()).collect(),
+            reverb: Reverb::default(),
+        }
+    }
+}
+
+impl Mixer {
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    #[inline]
+    pub fn set_listener(&mut self, listener: AudioListener) {
+        self.listener = listener;
+    }
+
+    /// Gets the current parameters of all the buses.
+    #[inline]
+    pub fn snapshot(&self) -> AudioMixerSnapshot {
+        self.snapshot
+    }
+
+    /// Sets the parameters of bus. It also overrides the bus in the ongoing transition,
+    /// so the bus stays at `params` while the others keep transitioning.
+    pub fn set_bus(&mut self, bus: AudioBus, params: AudioBusParams) {
+        *self.snapshot.bus_mut(bus) = params;
+
+        if let Some(ref mut v) = self.transition {
+            *v.from.bus_mut(bus) = params;
+            *v.to.bus_mut(bus) = params;
+        }
+    }
+
+    /// Transitions to the snapshot in `duration` seconds of the mixed output.
+    pub fn transition(&mut self, snapshot: AudioMixerSnapshot, duration: f32) {
+        if duration <= 0.0 {
+            self.snapshot = snapshot;
+            self.transition = None;
+            return;
+        }
+
+        self.transition = Some(Transition {
+            from: self.snapshot,
+            to: snapshot,
+            duration: duration,
+            elapsed: 0.0,
+        });
+    }
+
+    /// Replaces the ducking rules.
+    pub fn set_duckings(&mut self, duckings: &[AudioDucking]) {
+        self.duckings = duckings.iter().map(|&v| (v, 0.0)).collect();
+    }
+
+    pub fn play(&mut self, clip: Arc<AudioClip>, params: AudioSource) -> AudioSourceHandle {
+        let playback = Playback {
+            clip: clip,
+            params: params,
+            cursor: 0.0,
+            played: 0,
+            fade: None,
+        };
+
+        self.playbacks.create(Voice::Clip(playback)).into()
+    }
+
+    /// Plays the samples fed into `buffer`, and fades in within `fade_in` seconds.
+    pub fn play_stream(
+        &mut self,
+        buffer: Arc<StreamBuffer>,
+        params: AudioStreamSource,
+        fade_in: f32,
+    ) -> AudioSourceHandle {
+        let fade = if fade_in > 0.0 {
+            Some(Fade {
+                from: 0.0,
+                to: 1.0,
+                duration: fade_in,
+                elapsed: 0.0,
+                stop: false,
+            })
+        } else {
+            None
+        };
+
+        let playback = StreamPlayback {
+            buffer: buffer,
+            params: params,
+            frames: VecDeque::new(),
+            cursor: 0.0,
+            fade: fade,
+        };
+
+        self.playbacks.create(Voice::Stream(playback)).into()
+    }
+
+    pub fn stop(&mut self, handle: AudioSourceHandle) {
+        self.playbacks.free(handle);
+    }
+
+    /// Fades out the source within `duration` seconds, and stops it afterwards.
+    pub fn fade_out(&mut self, handle: AudioSourceHandle, duration: f32) {
+        if duration <= 0.0 {
+            self.stop(handle);
+            return;
+        }
+
+        let fade = match self.playbacks.get_mut(handle) {
+            Some(&mut Voice::Clip(ref mut v)) => &mut v.fade,
+            Some(&mut Voice::Stream(ref mut v)) => &mut v.fade,
+            None => return,
+        };
+
+        *fade = Some(Fade {
+            from: fade.map(|v| v.gain()).unwrap_or(1.0),
+            to: 0.0,
+            duration: duration,
+            elapsed: 0.0,
+            stop: true,
+        });
+    }
+
+    /// Moves the source to the position in seconds.
+    pub fn seek(&mut self, handle: AudioSourceHandle, secs: f32) {
+        let secs = f64::from(secs.max(0.0));
+        match self.playbacks.get_mut(handle) {
+            Some(&mut Voice::Clip(ref mut v)) => {
+                let frames = v.clip.frames().saturating_sub(1);
+                v.cursor = (secs * f64::from(v.clip.sample_rate)).min(frames as f64);
+            }
+            Some(&mut Voice::Stream(ref mut v)) => {
+                let frame = secs * f64::from(v.buffer.sample_rate());
+                v.buffer.seek(frame as u64);
+                v.frames.clear();
+                v.cursor = 0.0;
+            }
+            None => {}
+        }
+    }
+
+    /// Stops all the sources playing the clip.
+    pub fn stop_clip(&mut self, clip: AudioClipHandle) {
+        let predicate = |v: &Voice| match *v {
+            Voice::Clip(ref v) => v.params.clip == clip,
+            Voice::Stream(_) => false,
+        };
+
+        for _ in self.playbacks.free_if(predicate) {}
+    }
+
+    /// Stops all the sources playing the stream.
+    pub fn stop_stream(&mut self, stream: AudioStreamHandle) {
+        let predicate = |v: &Voice| match *v {
+            Voice::Clip(_) => false,
+            Voice::Stream(ref v) => v.params.stream == stream,
+        };
+
+        for _ in self.playbacks.free_if(predicate) {}
+    }
+
+    #[inline]
+    pub fn is_playing(&self, handle: AudioSourceHandle) -> bool {
+        self.playbacks.is_alive(handle)
+    }
+
+    /// Gets the parameters of a playing clip.
+    pub fn source_mut(&mut self, handle: AudioSourceHandle) -> Option<&mut AudioSource> {
+        match self.playbacks.get_mut(handle) {
+            Some(&mut Voice::Clip(ref mut v)) => Some(&mut v.params),
+            _ => None,
+        }
+    }
+
+    /// Sets the volume of a playing clip or stream.
+    pub fn set_volume(&mut self, handle: AudioSourceHandle, volume: f32) {
+        match self.playbacks.get_mut(handle) {
+            Some(&mut Voice::Clip(ref mut v)) => v.params.volume = volume,
+            Some(&mut Voice::Stream(ref mut v)) => v.params.volume = volume,
+            None => {}
+        }
+    }
+
+    /// Sets the pitch of a playing clip or stream.
+    pub fn set_pitch(&mut self, handle: AudioSourceHandle, pitch: f32) {
+        match self.playbacks.get_mut(handle) {
+            Some(&mut Voice::Clip(ref mut v)) => v.params.pitch = pitch,
+            Some(&mut Voice::Stream(ref mut v)) => v.params.pitch = pitch,
+            None => {}
+        }
+    }
+
+    /// Mixes the sources into the interleaved stereo samples at `sample_rate`, the
+    /// sources finished playing are removed.
+    pub fn mix(&mut self, sample_rate: u32, output: &mut [f32]) {
+        self.advance_transition(output.len() / 2, sample_rate);
+
+        for v in &mut self.buses {
+            v.buf.clear();
+            v.buf.resize(output.len(), 0.0);
+        }
+
+        let handles: Vec<_> = self.playbacks.iter().collect();
+        for handle in handles {
+            let finished = match *self.playbacks.get_mut(handle).unwrap() {
+                Voice::Clip(ref mut playback) => {
+                    let buf = &mut self.buses[playback.params.bus as usize].buf;
+                    Self::mix_playback(sample_rate, &self.listener, playback, buf)
+                }
+                Voice::Stream(ref mut playback) => {
+                    let buf = &mut self.buses[playback.params.bus as usize].buf;
+                    Self::mix_stream(sample_rate, playback, buf)
+                }
+            };
+
+            if finished {
+                self.playbacks.free(handle);
+            }
+        }
+
+        self.mix_buses(sample_rate, output);
+    }
+
+    fn advance_transition(&mut self, frames: usize, sample_rate: u32) {
+        if let Some(mut v) = self.transition.take() {
+            v.elapsed += frames as f32 / sample_rate.max(1) as f32;
+            let t = (v.elapsed / v.duration).min(1.0);
+            self.snapshot = v.from.lerp(&v.to, t);
+
+            if t < 1.0 {
+                self.transition = Some(v);
+            }
+        }
+    }
+
+    // Processes the buses with their effects, and sums them into the master bus.
+    fn mix_buses(&mut self, sample_rate: u32, output: &mut [f32]) {
+        let mut low_passes = [None; NUM_BUSES];
+        for (i, v) in self.snapshot.buses.iter().enumerate() {
+            low_passes[i] = LowPass::coefficient(v.low_pass, LOW_PASS_BYPASS, sample_rate);
+        }
+
+        let envelopes: Vec<_> = self
+            .duckings
+            .iter()
+            .map(|&(ref v, _)| {
+                let attack = dsp::envelope_coefficient(v.attack, sample_rate);
+                let release = dsp::envelope_coefficient(v.release, sample_rate);
+                (attack, release)
+            })
+            .collect();
+
+        let snapshot = &self.snapshot;
+        for (i, v) in output.chunks_mut(2).enumerate() {
+            let mut frames = [(0.0, 0.0); NUM_BUSES];
+            for (j, bus) in self.buses.iter_mut().enumerate() {
+                let frame = (
+                    bus.buf[i * 2],
+                    bus.buf.get(i * 2 + 1).cloned().unwrap_or(0.0),
+                );
+
+                // The master bus is processed after summing.
+                frames[j] = if j == AudioBus::Master as usize {
+                    frame
+                } else {
+                    let frame = bus.low_pass.process(low_passes[j], frame);
+                    scale(frame, snapshot.buses[j].volume)
+                };
+            }
+
+            for (&mut (ref rule, ref mut envelope), &(attack, release)) in
+                self.duckings.iter_mut().zip(&envelopes)
+            {
+                let (l, r) = frames[rule.trigger as usize];
+                let (target, coefficient) = if l.abs().max(r.abs()) > rule.threshold {
+                    (1.0, attack)
+                } else {
+                    (0.0, release)
+                };
+
+                *envelope += (target - *envelope) * coefficient;
+                let gain = 1.0 - (1.0 - rule.volume.max(0.0).min(1.0)) * *envelope;
+                let target = rule.target as usize;
+                frames[target] = scale(frames[target], gain);
+            }
+
+            let (mut sum, mut send) = (frames[AudioBus::Master as usize], (0.0, 0.0));
+            for bus in &AudioBus::ALL[1..] {
+                let params = snapshot.bus(*bus);
+                let frame = limit(frames[*bus as usize], params.limit);
+                sum = add(sum, frame);
+                send = add(send, scale(frame, params.reverb_send));
+            }
+
+            sum = add(sum, self.reverb.process(sample_rate, send));
+
+            let params = snapshot.bus(AudioBus::Master);
+            let master = &mut self.buses[AudioBus::Master as usize];
+            let sum = master.low_pass.process(low_passes[0], sum);
+            let (l, r) = limit(scale(sum, params.volume), params.limit);
+
+            v[0] = l.max(-1.0).min(1.0);
+            if v.len() > 1 {
+                v[1] = r.max(-1.0).min(1.0);
+            }
+        }
+    }
+
+    // Mixes the playback into output, returns true if it has finished.
+    fn mix_playback(
+        sample_rate: u32,
+        listener: &AudioListener,
+        playback: &mut Playback,
+        output: &mut [f32],
+    ) -> bool {
+        let frames = playback.clip.frames();
+        if frames == 0 {
+            return true;
+        }
+
+        let volume = playback.params.volume.max(0.0);
+        let (left, right) = match playback.params.attenuation {
+            Some(ref v) => listener.spatialize(v),
+            None => (1.0, 1.0),
+        };
+
+        let step = f64::from(playback.params.pitch.max(0.0)) * f64::from(playback.clip.sample_rate)
+            / f64::from(sample_rate.max(1));
+        let dt = 1.0 / sample_rate.max(1) as f32;
+
+        for v in output.chunks_mut(2) {
+            if playback.fade.map_or(false, |v| v.is_stopped()) {
+                return true;
+            }
+
+            // The last frame is interpolated with the first one only if the clip
+            // loops again, otherwise it's held until the end.
+            let index = playback.cursor as usize;
+            let last = match playback.params.wrap {
+                AudioSourceWrap::Repeat(n) => playback.played + 1 >= n,
+                AudioSourceWrap::Infinite => false,
+            };
+
+            let next = if index + 1 < frames {
+                index + 1
+            } else if last {
+                index
+            } else {
+                0
+            };
+            let t = (playback.cursor - index as f64) as f32;
+            let gain = volume * playback.fade.as_mut().map_or(1.0, |v| v.advance(dt));
+
+            // Interpolates linearly between the neighbouring frames.
+            let (l0, r0) = playback.clip.frame(index);
+            let (l1, r1) = playback.clip.frame(next);
+            v[0] += (l0 + (l1 - l0) * t) * gain * left;
+            if v.len() > 1 {
+                v[1] += (r0 + (r1 - r0) * t) * gain * right;
+            }
+
+            playback.cursor += step;
+            while playback.cursor >= frames as f64 {
+                playback.cursor -= frames as f64;
+                playback.played += 1;
+
+                if let AudioSourceWrap::Repeat(n) = playback.params.wrap {
+                    if playback.played >= n {
+                        return true;
+                    }
+                }
+            }
+        }
+
+        false
+    }
+
+    // Mixes the frames decoded ahead into output, returns true if it has finished.
+    fn mix_stream(sample_rate: u32, playback: &mut StreamPlayback, output: &mut [f32]) -> bool {
+        let step = f64::from(playback.params.pitch.max(0.0))
+            * f64::from(playback.buffer.sample_rate())
+            / f64::from(sample_rate.max(1));
+        let dt = 1.0 / sample_rate.max(1) as f32;
+
+        // Pulls the frames of this mix, and the next one to interpolate with.
+        let frames = output.len() / 2 + output.len() % 2;
+        let needed = (playback.cursor + frames as f64 * step) as usize + 2;
+        let finished = if playback.frames.len() < needed {
+            let n = needed - playback.frames.len();
+            playback.buffer.pop(n, &mut playback.frames)
+        } else {
+            false
+        };
+
+        let volume = playback.params.volume.max(0.0);
+        for v in output.chunks_mut(2) {
+            while playback.cursor >= 1.0 && !playback.frames.is_empty() {
+                playback.frames.pop_front();
+                playback.cursor -= 1.0;
+            }
+
+            if playback.fade.map_or(false, |v| v.is_stopped()) {
+                return true;
+            }
+
+            // Leaves the rest silent if the worker falls behind, the playback resumes
+            // from where it stops once the frames are decoded.
+            let ((l0, r0), (l1, r1)) = match (playback.frames.front(), playback.frames.get(1)) {
+                (Some(&v), Some(&next)) => (v, next),
+                (Some(&v), None) if finished => (v, v),
+                _ => return finished,
+            };
+
+            let t = playback.cursor as f32;
+            let gain = volume * playback.fade.as_mut().map_or(1.0, |v| v.advance(dt));
+
+            v[0] += (l0 + (l1 - l0) * t) * gain;
+            if v.len() > 1 {
+                v[1] += (r0 + (r1 - r0) * t) * gain;
+            }
+
+            playback.cursor += step;
+        }
+
+        false
+    }
+}
+
+#[inline]
+fn add(lhs: (f32, f32), rhs: (f32, f32)) -> (f32, f32) {
+    (lhs.0 + rhs.0, lhs.1 + rhs.1)
+}
+
+#[inline]
+fn scale(v: (f32, f32), s: f32) -> (f32, f32) {
+    (v.0 * s, v.1 * s)
+}
+
+#[inline]
+fn limit(v: (f32, f32), threshold: f32) -> (f32, f32) {
+    (
+        dsp::soft_limit(v.0, threshold),
+        dsp::soft_limit(v.1, threshold),
+    )
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use utils::handle::Handle;
+
+    use super::super::streaming::StreamBuffer;
+
+    fn clip(samples: Vec<f32>) -> Arc<AudioClip> {
+        Arc::new(AudioClip {
+            channels: 1,
+            sample_rate: 4,
+            samples: samples,
+        })
+    }
+
+    #[test]
+    fn repeat() {
+        let handle: AudioClipHandle = Handle::new(1, 1).into();
+        let mut mixer = Mixer::new();
+
+        let mut params = AudioSource::new(handle);
+        params.wrap = AudioSourceWrap::Repeat(2);
+        params.volume = 0.5;
+        let source = mixer.play(clip(vec![1.0, 1.0]), params);
+
+        let mut output = [0.0; 6];
+        mixer.mix(4, &mut output);
+        assert_eq!(output, [0.5; 6]);
+        assert!(mixer.is_playing(source));
+
+        mixer.mix(4, &mut output);
+        assert_eq!(output, [0.5, 0.5, 0.0, 0.0, 0.0, 0.0]);
+        assert!(!mixer.is_playing(source));
+    }
+
+    #[test]
+    fn infinite() {
+        let handle: AudioClipHandle = Handle::new(1, 1).into();
+        let mut mixer = Mixer::new();
+
+        let mut params = AudioSource::new(handle);
+        params.wrap = AudioSourceWrap::Infinite;
+        let source = mixer.play(clip(vec![0.5]), params);
+
+        let mut output = [0.0; 32];
+        mixer.mix(4, &mut output);
+        assert_eq!(output, [0.5; 32]);
+        assert!(mixer.is_playing(source));
+
+        mixer.stop_clip(handle);
+        assert!(!mixer.is_playing(source));
+        assert!(mixer.playbacks.is_empty());
+    }
+
+    #[test]
+    fn pitch() {
+        let handle: AudioClipHandle = Handle::new(1, 1).into();
+        let mut mixer = Mixer::new();
+
+        let mut params = AudioSource::new(handle);
+        params.pitch = 2.0;
+        let source = mixer.play(clip(vec![0.0, 1.0, 0.0, 1.0]), params);
+
+        let mut output = [0.0; 6];
+        mixer.mix(4, &mut output);
+        assert_eq!(output, [0.0, 0.0, 0.0, 0.0, 0.0, 0.0]);
+        assert!(!mixer.is_playing(source));
+
+        // Half speed interpolates between frames.
+        params.pitch = 0.5;
+        mixer.play(clip(vec![0.0, 1.0, 0.0, 1.0]), params);
+        mixer.mix(4, &mut output);
+        assert_eq!(output, [0.0, 0.0, 0.5, 0.5, 1.0, 1.0]);
+
+        // The last frame is held unless the clip loops again.
+        let source = mixer.play(clip(vec![0.0, 1.0]), params);
+        let mut output = [0.0; 8];
+        mixer.mix(4, &mut output);
+        assert_eq!(output, [0.0, 0.0, 0.5, 0.5, 1.0, 1.0, 1.0, 1.0]);
+        assert!(!mixer.is_playing(source));
+
+        params.wrap = AudioSourceWrap::Infinite;
+        mixer.play(clip(vec![0.0, 1.0]), params);
+        mixer.mix(4, &mut output);
+        assert_eq!(output, [0.0, 0.0, 0.5, 0.5, 1.0, 1.0, 0.5, 0.5]);
+    }
+
+    #[test]
+    fn attenuation() {
+        let handle: AudioClipHandle = Handle::new(1, 1).into();
+        let mut mixer = Mixer::new();
+
+        let mut params = AudioSource::new(handle);
+        params.wrap = AudioSourceWrap::Infinite;
+        params.attenuation = Some(AudioSourceAttenuation::new([20.0, 0.0, 0.0], 1.0, 10.0));
+        let source = mixer.play(clip(vec![1.0]), params);
+
+        let mut output = [0.0; 2];
+        mixer.mix(4, &mut output);
+        assert_eq!(output, [0.0, 0.0]);
+
+        // Hard panned to the right.
+        mixer.source_mut(source).unwrap().attenuation =
+            Some(AudioSourceAttenuation::new([1.0, 0.0, 0.0], 1.0, 10.0));
+        mixer.mix(4, &mut output);
+        assert!(output[0].abs() < 1e-6);
+        assert!((output[1] - 1.0).abs() < 1e-6);
+
+        let attenuation = AudioSourceAttenuation::new([0.0, 0.0, 5.5], 1.0, 10.0);
+        assert_eq!(attenuation.gain(5.5), 0.5);
+        let (l, r) = AudioListener::default().spatialize(&attenuation);
+        assert!((l - r).abs() < 1e-6);
+        assert!((l * l + r * r - 0.25).abs() < 1e-6);
+    }
+
+    fn play(mixer: &mut Mixer, bus: AudioBus, samples: Vec<f32>) -> AudioSourceHandle {
+        let mut params = AudioSource::new(Handle::new(1, 1).into());
+        params.wrap = AudioSourceWrap::Infinite;
+        params.bus = bus;
+        mixer.play(clip(samples), params)
+    }
+
+    fn approx(lhs: &[f32], rhs: &[f32]) -> bool {
+        lhs.len() == rhs.len() && lhs.iter().zip(rhs).all(|(a, b)| (a - b).abs() < 1e-5)
+    }
+
+    #[test]
+    fn buses() {
+        let mut mixer = Mixer::new();
+        play(&mut mixer, AudioBus::Music, vec![0.25]);
+        play(&mut mixer, AudioBus::Sfx, vec![0.5]);
+        play(&mut mixer, AudioBus::Master, vec![0.125]);
+
+        let mut output = [0.0; 4];
+        mixer.mix(4, &mut output);
+        assert_eq!(output, [0.875; 4]);
+
+        let mut params = AudioBusParams::default();
+        params.volume = 0.0;
+        mixer.set_bus(AudioBus::Music, params);
+        mixer.mix(4, &mut output);
+        assert_eq!(output, [0.625; 4]);
+
+        params.volume = 0.5;
+        mixer.set_bus(AudioBus::Master, params);
+        mixer.mix(4, &mut output);
+        assert_eq!(output, [0.3125; 4]);
+    }
+
+    #[test]
+    fn transition() {
+        let mut mixer = Mixer::new();
+        play(&mut mixer, AudioBus::Sfx, vec![0.5]);
+
+        let mut snapshot = mixer.snapshot();
+        snapshot.bus_mut(AudioBus::Sfx).volume = 0.0;
+        snapshot.bus_mut(AudioBus::Music).volume = 0.0;
+        mixer.transition(snapshot, 1.0);
+
+        // The transition progresses with the mixed samples.
+        let mut output = [0.0; 4];
+        mixer.mix(4, &mut output);
+        assert!(approx(&output, &[0.25; 4]));
+        assert_eq!(mixer.snapshot().bus(AudioBus::Sfx).volume, 0.5);
+
+        // The buses set explicitly are excluded from the transition.
+        mixer.set_bus(AudioBus::Music, AudioBusParams::default());
+        mixer.mix(4, &mut output);
+        assert_eq!(output, [0.0; 4]);
+        assert_eq!(mixer.snapshot().bus(AudioBus::Music).volume, 1.0);
+        assert!(mixer.transition.is_none());
+
+        mixer.transition(AudioMixerSnapshot::default(), 0.0);
+        mixer.mix(4, &mut output);
+        assert_eq!(output, [0.5; 4]);
+    }
+
+    #[test]
+    fn low_pass() {
+        let handle: AudioClipHandle = Handle::new(1, 1).into();
+        let mut mixer = Mixer::new();
+
+        let mut params = AudioSource::new(handle);
+        params.wrap = AudioSourceWrap::Infinite;
+        params.bus = AudioBus::Music;
+        let samples = (0..100)
+            .map(|i| if i % 2 == 0 { 0.5 } else { -0.5 })
+            .collect();
+        let clip = Arc::new(AudioClip {
+            channels: 1,
+            sample_rate: 8000,
+            samples: samples,
+        });
+
+        mixer.play(clip, params);
+
+        let mut output = vec![0.0; 200];
+        mixer.mix(8000, &mut output);
+        assert_eq!((output[196], output[198]), (0.5, -0.5));
+
+        // Muffles the high frequencies.
+        let mut bus = AudioBusParams::default();
+        bus.low_pass = 200.0;
+        mixer.set_bus(AudioBus::Music, bus);
+        mixer.mix(8000, &mut output);
+        assert!(output[100..].iter().all(|v| v.abs() < 0.05));
+    }
+
+    #[test]
+    fn ducking() {
+        let handle: AudioClipHandle = Handle::new(1, 1).into();
+        let mut mixer = Mixer::new();
+        play(&mut mixer, AudioBus::Music, vec![0.5]);
+
+        let mut rule = AudioDucking::new(AudioBus::Sfx, AudioBus::Music);
+        rule.volume = 0.2;
+        rule.attack = 0.0;
+        rule.release = 0.0;
+        mixer.set_duckings(&[rule]);
+
+        let mut output = [0.0; 6];
+        mixer.mix(4, &mut output);
+        assert_eq!(output, [0.5; 6]);
+
+        // The music is ducked while the sound effect is playing.
+        mixer.play(clip(vec![0.1]), AudioSource::new(handle));
+        mixer.mix(4, &mut output);
+        assert!(approx(&output, &[0.2, 0.2, 0.5, 0.5, 0.5, 0.5]));
+    }
+
+    #[test]
+    fn reverb() {
+        let handle: AudioClipHandle = Handle::new(1, 1).into();
+        let mut mixer = Mixer::new();
+
+        let mut bus = AudioBusParams::default();
+        bus.reverb_send = 1.0;
+        mixer.set_bus(AudioBus::Sfx, bus);
+
+        let clip = Arc::new(AudioClip {
+            channels: 1,
+            sample_rate: 44100,
+            samples: vec![1.0],
+        });
+
+        mixer.play(clip, AudioSource::new(handle));
+
+        // The tail rings after the source finishes.
+        let mut output = vec![0.0; 44100];
+        mixer.mix(44100, &mut output);
+        assert_eq!(output[0], 1.0);
+        assert!(output[2..].iter().any(|v| v.abs() > 1e-4));
+    }
+
+    #[test]
+    fn limit() {
+        let mut mixer = Mixer::new();
+        play(&mut mixer, AudioBus::Music, vec![0.75]);
+        play(&mut mixer, AudioBus::Sfx, vec![0.75]);
+
+        let mut output = [0.0; 2];
+        mixer.mix(4, &mut output);
+        assert_eq!(output, [1.0; 2]);
+
+        let mut bus = AudioBusParams::default();
+        bus.limit = 0.5;
+        mixer.set_bus(AudioBus::Master, bus);
+        mixer.mix(4, &mut output);
+        assert!(output[0] > 0.5 && output[0] < 1.0);
+        assert_eq!(output[0], output[1]);
+    }
+
+    #[test]
+    fn stream() {
+        let handle: AudioStreamHandle = Handle::new(1, 1).into();
+        let mut mixer = Mixer::new();
+
+        let buffer = Arc::new(StreamBuffer::new(1, 4));
+        buffer.push(0, &[0.0, 0.25, 0.5], false);
+        let source = mixer.play_stream(buffer.clone(), handle.into(), 0.0);
+
+        // Waits silently for the worker, the next frame is needed to interpolate.
+        let mut output = [0.0; 8];
+        mixer.mix(4, &mut output);
+        assert_eq!(output, [0.0, 0.0, 0.25, 0.25, 0.0, 0.0, 0.0, 0.0]);
+        assert!(mixer.is_playing(source));
+
+        buffer.push(0, &[0.75, 1.0], true);
+        mixer.mix(4, &mut output);
+        assert_eq!(output, [0.5, 0.5, 0.75, 0.75, 1.0, 1.0, 0.0, 0.0]);
+        assert!(!mixer.is_playing(source));
+
+        // Resamples the stream at the rate of output.
+        let buffer = Arc::new(StreamBuffer::new(1, 8));
+        buffer.push(0, &[0.0, 0.1, 0.2, 0.3, 0.4, 0.5], true);
+        mixer.play_stream(buffer, handle.into(), 0.0);
+
+        let mut output = [0.0; 4];
+        mixer.mix(4, &mut output);
+        assert_eq!(output, [0.0, 0.0, 0.2, 0.2]);
+
+        // Speeds up with the pitch.
+        let buffer = Arc::new(StreamBuffer::new(1, 4));
+        buffer.push(0, &[0.0, 0.25, 0.5, 0.75, 1.0], true);
+        let source = mixer.play_stream(buffer, handle.into(), 0.0);
+        mixer.set_pitch(source, 2.0);
+
+        let mut output = [0.0; 6];
+        mixer.mix(4, &mut output);
+        assert_eq!(output, [0.0, 0.0, 0.5, 0.5, 1.0, 1.0]);
+    }
+
+    #[test]
+    fn cross_fade() {
+        let handle: AudioStreamHandle = Handle::new(1, 1).into();
+        let mut mixer = Mixer::new();
+
+        let from = play(&mut mixer, AudioBus::Music, vec![0.5]);
+        mixer.fade_out(from, 1.0);
+
+        let mut output = [0.0; 12];
+        mixer.mix(4, &mut output);
+        let expected = [
+            0.5, 0.5, 0.375, 0.375, 0.25, 0.25, 0.125, 0.125, 0.0, 0.0, 0.0, 0.0,
+        ];
+        assert_eq!(output, expected);
+        assert!(!mixer.is_playing(from));
+
+        // The fading are synchronized, so the sum stays the same.
+        let from = play(&mut mixer, AudioBus::Music, vec![0.5]);
+        let buffer = Arc::new(StreamBuffer::new(1, 4));
+        buffer.push(0, &[0.5; 8], false);
+
+        mixer.fade_out(from, 1.0);
+        let to = mixer.play_stream(buffer, handle.into(), 1.0);
+        mixer.mix(4, &mut output);
+        assert!(approx(&output, &[0.5; 12]));
+        assert!(!mixer.is_playing(from));
+        assert!(mixer.is_playing(to));
+
+        mixer.fade_out(to, 0.0);
+        assert!(!mixer.is_playing(to));
+    }
+
+    #[test]
+    fn seek() {
+        let handle: AudioStreamHandle = Handle::new(1, 1).into();
+        let mut mixer = Mixer::new();
+
+        let source = play(&mut mixer, AudioBus::Sfx, vec![0.0, 0.25, 0.5, 0.75]);
+        mixer.seek(source, 0.5);
+
+        let mut output = [0.0; 4];
+        mixer.mix(4, &mut output);
+        assert_eq!(output, [0.5, 0.5, 0.75, 0.75]);
+
+        mixer.seek(source, 10.0);
+        mixer.mix(4, &mut output);
+        assert_eq!(output, [0.75, 0.75, 0.0, 0.0]);
+        mixer.stop(source);
+
+        // The buffered frames are discarded, and decoded from the position again.
+        let buffer = Arc::new(StreamBuffer::new(1, 4));
+        buffer.push(0, &[0.5; 4], false);
+        let source = mixer.play_stream(buffer.clone(), handle.into(), 0.0);
+
+        mixer.seek(source, 1.0);
+        mixer.mix(4, &mut output);
+        assert_eq!(output, [0.0; 4]);
+
+        // The samples decoded after seeking.
+        buffer.push(1, &[0.25; 3], false);
+        mixer.mix(4, &mut output);
+        assert_eq!(output, [0.25; 4]);
+
+        mixer.stop_stream(handle);
+        assert!(!mixer.is_playing(source));
+        assert!(mixer.playbacks.is_empty());
+    }
+}
@@ -0,0 +1,482 @@
+//! A software mixed audio system with spatialized playback.
+//!
+//! # Overview
+//!
+//! The audio system follows the same layered design as `video`. The `AudioSystem` owns
+//! the backend which pulls the mixed samples into the output device, and the multi-thread
+//! friendly parts are shared as `AudioSystemShared` in the `Context`.
+//!
+//! # Audio Clip
+//!
+//! An `AudioClip` holds the decoded samples of a sound. It could be created from
+//! samples in memory, or loaded asynchronously from the filesystem through `res` like
+//! textures and meshes:
+//!
+//! ```rust,ignore
+//! let clip: AudioClipHandle = ctx.res.load("res:sfx/explosion.clip")?;
+//! ```
+//!
+//! # Audio Source
+//!
+//! Playing a clip creates a source. The sources stop themselves after the clip is
+//! played through, so the handles could be ignored for the fire-and-forget sounds:
+//!
+//! ```rust,ignore
+//! ctx.audio.play(clip)?;
+//!
+//! let mut params = AudioSource::new(music);
+//! params.wrap = AudioSourceWrap::Infinite;
+//! params.volume = 0.5;
+//! let source = ctx.audio.play(params)?;
+//! ```
+//!
+//! The volume and pitch of a playing source could be changed with its handle. And
+//! the sources with `AudioSourceAttenuation` fade out by their distances to the
+//! listener, and are panned by their directions relative to it:
+//!
+//! ```rust,ignore
+//! ctx.audio.set_listener(camera.position, camera.rotation);
+//! ctx.audio.set_position(source, enemy.position);
+//! ```
+//!
+//! # Audio Stream
+//!
+//! The long sounds like music tracks could be loaded as `AudioStream`s from OGG Vorbis
+//! or MP3 files. Only the compressed bytes are kept in memory, the samples are decoded
+//! in chunks on a dedicated IO worker slightly ahead of the mixer. The streams loop
+//! seamlessly, and could be sought or cross-faded into each other:
+//!
+//! ```rust,ignore
+//! let stream: AudioStreamHandle = ctx.res.load("res:music/field.ogg")?;
+//! ctx.res.wait(stream)?;
+//!
+//! let mut params = AudioStreamSource::new(stream);
+//! params.wrap = AudioSourceWrap::Infinite;
+//! let music = ctx.audio.play_stream(params)?;
+//! ctx.audio.seek(music, Duration::from_secs(30));
+//!
+//! let music = ctx.audio.cross_fade(music, battle, Duration::from_secs(2))?;
+//! ```
+//!
+//! # Mixer
+//!
+//! The sources are routed into the `AudioBus::Sfx` by default, or the `AudioBus::Music`
+//! for background music. Every bus has its volume, low-pass filter, reverb send and
+//! soft limiter, and the child buses are summed into the `AudioBus::Master`. A bus could
+//! also be ducked by the others, e.g. lowers the music while sound effects are playing.
+//!
+//! The parameters of all buses are captured as `AudioMixerSnapshot`, which could be
+//! transitioned to smoothly at runtime:
+//!
+//! ```rust,ignore
+//! let mut paused = ctx.audio.snapshot();
+//! paused.bus_mut(AudioBus::Music).low_pass = 800.0;
+//! paused.bus_mut(AudioBus::Sfx).volume = 0.2;
+//! ctx.audio.transition(paused, Duration::from_millis(300));
+//! ```
+
+pub mod assets;
+pub mod backends;
+
+mod bus;
+mod decoder;
+mod dsp;
+mod mixer;
+mod source;
+mod streaming;
+
+pub mod prelude {
+    pub use super::assets::prelude::*;
+    pub use super::bus::{AudioBus, AudioBusParams, AudioDucking, AudioMixerSnapshot};
+    pub use super::source::{AudioListener, AudioSource, AudioSourceAttenuation};
+    pub use super::source::{AudioSourceHandle, AudioSourceWrap, AudioStreamSource};
+    pub use super::{AudioSystem, AudioSystemShared};
+}
+
+use std::sync::{Arc, Mutex, RwLock};
+use std::time::Duration;
+
+use errors::*;
+use math;
+use utils::object_pool::ObjectPool;
+
+use self::assets::prelude::*;
+use self::backends::Visitor;
+use self::bus::*;
+use self::mixer::Mixer;
+use self::source::*;
+use self::streaming::{StreamBuffer, Streamer};
+
+/// The centralized management of audio sub-system.
+pub struct AudioSystem {
+    visitor: Box<Visitor>,
+    shared: Arc<AudioSystemShared>,
+}
+
+impl AudioSystem {
+    /// Creates a new `AudioSystem` which plays on the default output device.
+    pub fn new() -> Result<Self> {
+        let mixer = Arc::new(Mutex::new(Mixer::new()));
+        let visitor = backends::device::DeviceVisitor::new(mixer.clone())?;
+
+        Ok(AudioSystem {
+            visitor: Box::new(visitor),
+            shared: Arc::new(AudioSystemShared::new(mixer)),
+        })
+    }
+
+    /// Creates a new headless `AudioSystem`, which plays nothing.
+    pub fn headless() -> Self {
+        let mixer = Arc::new(Mutex::new(Mixer::new()));
+        let visitor = backends::headless::HeadlessVisitor::new(mixer.clone());
+
+        AudioSystem {
+            visitor: Box::new(visitor),
+            shared: Arc::new(AudioSystemShared::new(mixer)),
+        }
+    }
+
+    /// Returns the multi-thread friendly parts of `AudioSystem`.
+    pub fn shared(&self) -> Arc<AudioSystemShared> {
+        self.shared.clone()
+    }
+
+    /// Advance to next frame.
+    pub fn advance(&mut self, delta: Duration) -> Result<()> {
+        self.visitor.advance(delta)
+    }
+
+    /// Pauses (or resumes) the output, e.g. while the application is in background.
+    /// The playbacks do not progress while paused.
+    pub fn set_paused(&mut self, paused: bool) {
+        self.visitor.set_paused(paused);
+    }
+}
+
+/// The multi-thread friendly parts of `AudioSystem`.
+pub struct AudioSystemShared {
+    clips: RwLock<ObjectPool<Option<Arc<AudioClip>>>>,
+    streams: RwLock<ObjectPool<Option<AudioStream>>>,
+    mixer: Arc<Mutex<Mixer>>,
+    streamer: Streamer,
+}
+
+impl AudioSystemShared {
+    fn new(mixer: Arc<Mutex<Mixer>>) -> Self {
+        AudioSystemShared {
+            clips: RwLock::new(ObjectPool::new()),
+            streams: RwLock::new(ObjectPool::new()),
+            mixer: mixer,
+            streamer: Streamer::new(),
+        }
+    }
+
+    /// Creates a clip object from the decoded samples.
+    pub fn create_clip(&self, clip: AudioClip) -> Result<AudioClipHandle> {
+        clip.validate()?;
+        let handle = self.clips.write().unwrap().create(Some(Arc::new(clip)));
+        Ok(handle.into())
+    }
+
+    /// Gets the clip if it has been loaded.
+    pub fn clip(&self, handle: AudioClipHandle) -> Option<Arc<AudioClip>> {
+        self.clips
+            .read()
+            .unwrap()
+            .get(handle)
+            .and_then(|v| v.clone())
+    }
+
+    /// Deletes the clip object, and stops all the sources playing it.
+    pub fn delete_clip(&self, handle: AudioClipHandle) {
+        if self.clips.write().unwrap().free(handle).is_some() {
+            self.mixer.lock().unwrap().stop_clip(handle);
+        }
+    }
+
+    pub(crate) fn create_clip_async(&self) -> Result<AudioClipHandle> {
+        let handle = self.clips.write().unwrap().create(None);
+        Ok(handle.into())
+    }
+
+    pub(crate) fn update_clip_async(&self, handle: AudioClipHandle, clip: AudioClip) -> Result<()> {
+        clip.validate()?;
+
+        if let Some(v) = self.clips.write().unwrap().get_mut(handle) {
+            *v = Some(Arc::new(clip));
+        }
+
+        // Its ok since the clip might be freed before this call.
+        Ok(())
+    }
+
+    /// Creates a stream object from the bytes of OGG Vorbis or MP3 file.
+    pub fn create_stream(&self, bytes: Vec<u8>) -> Result<AudioStreamHandle> {
+        let stream = AudioStream::new(bytes)?;
+        let handle = self.streams.write().unwrap().create(Some(stream));
+        Ok(handle.into())
+    }
+
+    /// Deletes the stream object, and stops all the sources playing it.
+    pub fn delete_stream(&self, handle: AudioStreamHandle) {
+        if self.streams.write().unwrap().free(handle).is_some() {
+            self.mixer.lock().unwrap().stop_stream(handle);
+        }
+    }
+
+    pub(crate) fn create_stream_async(&self) -> Result<AudioStreamHandle> {
+        let handle = self.streams.write().unwrap().create(None);
+        Ok(handle.into())
+    }
+
+    pub(crate) fn update_stream_async(
+        &self,
+        handle: AudioStreamHandle,
+        stream: AudioStream,
+    ) -> Result<()> {
+        if let Some(v) = self.streams.write().unwrap().get_mut(handle) {
+            *v = Some(stream);
+        }
+
+        // Its ok since the stream might be freed before this call.
+        Ok(())
+    }
+
+    /// Starts playing a source. The clip must have been loaded.
+    pub fn play<T>(&self, params: T) -> Result<AudioSourceHandle>
+    where
+        T: Into<AudioSource>,
+    {
+        let params = params.into();
+        let clip = self
+            .clip(params.clip)
+            .ok_or_else(|| format_err!("{:?} is invalid or not ready.", params.clip))?;
+
+        Ok(self.mixer.lock().unwrap().play(clip, params))
+    }
+
+    /// Starts streaming a source. The stream must have been loaded, and the samples
+    /// are decoded on the IO worker while playing.
+    pub fn play_stream<T>(&self, params: T) -> Result<AudioSourceHandle>
+    where
+        T: Into<AudioStreamSource>,
+    {
+        let params = params.into();
+        let buffer = self.open_stream(&params)?;
+        Ok(self.mixer.lock().unwrap().play_stream(buffer, params, 0.0))
+    }
+
+    /// Starts streaming a source which fades in within `duration`, and fades out the
+    /// playing source `from` at the same time. The fading are synchronized to the
+    /// sample frames of the mixed output.
+    pub fn cross_fade<T>(
+        &self,
+        from: AudioSourceHandle,
+        to: T,
+        duration: Duration,
+    ) -> Result<AudioSourceHandle>
+    where
+        T: Into<AudioStreamSource>,
+    {
+        let params = to.into();
+        let buffer = self.open_stream(&params)?;
+
+        let mut mixer = self.mixer.lock().unwrap();
+        mixer.fade_out(from, secs(duration));
+        Ok(mixer.play_stream(buffer, params, secs(duration)))
+    }
+
+    fn open_stream(&self, params: &AudioStreamSource) -> Result<Arc<StreamBuffer>> {
+        let streams = self.streams.read().unwrap();
+        let stream = streams
+            .get(params.stream)
+            .and_then(|v| v.as_ref())
+            .ok_or_else(|| format_err!("{:?} is invalid or not ready.", params.stream))?;
+
+        self.streamer.open(stream, params.wrap)
+    }
+
+    /// Stops a playing source.
+    pub fn stop(&self, handle: AudioSourceHandle) {
+        self.mixer.lock().unwrap().stop(handle);
+    }
+
+    /// Fades out a playing source within `duration`, and stops it afterwards.
+    pub fn fade_out(&self, handle: AudioSourceHandle, duration: Duration) {
+        self.mixer.lock().unwrap().fade_out(handle, secs(duration));
+    }
+
+    /// Moves a playing source to the position. The streams are decoded from there on
+    /// the IO worker, and the Ogg streams are only accurate to the granularity of pages.
+    pub fn seek(&self, handle: AudioSourceHandle, position: Duration) {
+        self.mixer.lock().unwrap().seek(handle, secs(position));
+        self.streamer.notify();
+    }
+
+    /// Returns true if the source is still playing.
+    pub fn is_playing(&self, handle: AudioSourceHandle) -> bool {
+        self.mixer.lock().unwrap().is_playing(handle)
+    }
+
+    /// Sets the volume of a playing source.
+    pub fn set_volume(&self, handle: AudioSourceHandle, volume: f32) {
+        self.mixer.lock().unwrap().set_volume(handle, volume);
+    }
+
+    /// Sets the pitch of a playing clip or stream.
+    pub fn set_pitch(&self, handle: AudioSourceHandle, pitch: f32) {
+        self.mixer.lock().unwrap().set_pitch(handle, pitch);
+    }
+
+    /// Moves a positional source, it's ignored if the source has no attenuation.
+    pub fn set_position<T>(&self, handle: AudioSourceHandle, position: T)
+    where
+        T: Into<math::Vector3<f32>>,
+    {
+        if let Some(v) = self.mixer.lock().unwrap().source_mut(handle) {
+            if let Some(ref mut attenuation) = v.attenuation {
+                attenuation.position = position.into();
+            }
+        }
+    }
+
+    /// Sets the master volume of all sources.
+    pub fn set_master_volume(&self, volume: f32) {
+        let mut params = self.bus(AudioBus::Master);
+        params.volume = volume;
+        self.set_bus(AudioBus::Master, params);
+    }
+
+    /// Gets the current parameters of bus.
+    pub fn bus(&self, bus: AudioBus) -> AudioBusParams {
+        *self.mixer.lock().unwrap().snapshot().bus(bus)
+    }
+
+    /// Sets the parameters of bus immediately. The bus is excluded from the ongoing
+    /// transition if any.
+    pub fn set_bus(&self, bus: AudioBus, params: AudioBusParams) {
+        self.mixer.lock().unwrap().set_bus(bus, params);
+    }
+
+    /// Gets the current parameters of all the buses.
+    pub fn snapshot(&self) -> AudioMixerSnapshot {
+        self.mixer.lock().unwrap().snapshot()
+    }
+
+    /// Transitions the buses to the snapshot smoothly in `duration`.
+    pub fn transition(&self, snapshot: AudioMixerSnapshot, duration: Duration) {
+        self.mixer
+            .lock()
+            .unwrap()
+            .transition(snapshot, secs(duration));
+    }
+
+    /// Replaces the ducking rules between buses.
+    pub fn set_duckings(&self, duckings: &[AudioDucking]) {
+        self.mixer.lock().unwrap().set_duckings(duckings);
+    }
+
+    /// Sets the position and rotation of listener in world space.
+    pub fn set_listener<T>(&self, position: T, rotation: math::Quaternion<f32>)
+    where
+        T: Into<math::Vector3<f32>>,
+    {
+        let listener = AudioListener {
+            position: position.into(),
+            rotation: rotation,
+        };
+
+        self.mixer.lock().unwrap().set_listener(listener);
+    }
+}
+
+#[inline]
+fn secs(duration: Duration) -> f32 {
+    duration.as_secs() as f32 + duration.subsec_nanos() as f32 * 1e-9
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn play() {
+        let mut audio = AudioSystem::headless();
+        let shared = audio.shared();
+
+        let clip = AudioClip {
+            channels: 1,
+            sample_rate: 10,
+            samples: vec![0.0; 10],
+        };
+
+        let clip = shared.create_clip(clip).unwrap();
+        let source = shared.play(clip).unwrap();
+        assert!(shared.is_playing(source));
+
+        audio.advance(Duration::from_millis(500)).unwrap();
+        assert!(shared.is_playing(source));
+
+        // The playbacks do not progress while paused.
+        audio.set_paused(true);
+        audio.advance(Duration::from_secs(1)).unwrap();
+        assert!(shared.is_playing(source));
+        audio.set_paused(false);
+
+        audio.advance(Duration::from_millis(600)).unwrap();
+        assert!(!shared.is_playing(source));
+
+        let mut params = AudioSource::new(clip);
+        params.wrap = AudioSourceWrap::Infinite;
+        let source = shared.play(params).unwrap();
+        audio.advance(Duration::from_secs(2)).unwrap();
+        assert!(shared.is_playing(source));
+
+        shared.delete_clip(clip);
+        assert!(!shared.is_playing(source));
+        assert!(shared.play(clip).is_err());
+    }
+
+    #[test]
+    fn async_clip() {
+        let shared = AudioSystem::headless().shared();
+        let clip = shared.create_clip_async().unwrap();
+        assert!(shared.clip(clip).is_none());
+        assert!(shared.play(clip).is_err());
+
+        let data = AudioClip {
+            channels: 2,
+            sample_rate: 10,
+            samples: vec![0.0; 3],
+        };
+
+        assert!(shared.update_clip_async(clip, data).is_err());
+
+        let data = AudioClip {
+            channels: 2,
+            sample_rate: 10,
+            samples: vec![0.0; 4],
+        };
+
+        shared.update_clip_async(clip, data.clone()).unwrap();
+        assert_eq!(shared.clip(clip).as_ref().map(|v| &**v), Some(&data));
+        assert!(shared.play(clip).is_ok());
+    }
+
+    #[test]
+    fn stream() {
+        let shared = AudioSystem::headless().shared();
+        assert!(shared.create_stream(b"RIFF".to_vec()).is_err());
+
+        let stream = shared.create_stream_async().unwrap();
+        assert!(shared.play_stream(stream).is_err());
+
+        // The headers are parsed before playing.
+        let data = AudioStream::new(b"OggS".to_vec()).unwrap();
+        shared.update_stream_async(stream, data).unwrap();
+        assert!(shared.play_stream(stream).is_err());
+
+        shared.delete_stream(stream);
+        assert!(shared.play_stream(stream).is_err());
+    }
+}
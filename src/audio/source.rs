@@ -0,0 +1,159 @@
+//! The playbacks of audio clips.
+
+use math;
+use math::prelude::*;
+
+use super::assets::prelude::{AudioClipHandle, AudioStreamHandle};
+use super::bus::AudioBus;
+
+impl_handle!(AudioSourceHandle);
+
+/// How many times the clip is played by a source.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AudioSourceWrap {
+    /// Plays the clip for a number of times, and stops afterwards.
+    Repeat(u32),
+    /// Loops the clip until the source is stopped.
+    Infinite,
+}
+
+/// The distance attenuation of a positional source.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct AudioSourceAttenuation {
+    /// The position of source in world space.
+    pub position: math::Vector3<f32>,
+    /// The source is heard at full volume within this distance.
+    pub min_distance: f32,
+    /// The source fades out linearly, and becomes inaudible beyond this distance.
+    pub max_distance: f32,
+}
+
+impl AudioSourceAttenuation {
+    pub fn new<T>(position: T, min_distance: f32, max_distance: f32) -> Self
+    where
+        T: Into<math::Vector3<f32>>,
+    {
+        AudioSourceAttenuation {
+            position: position.into(),
+            min_distance: min_distance,
+            max_distance: max_distance,
+        }
+    }
+
+    /// Gets the volume scale at the distance from listener.
+    pub fn gain(&self, distance: f32) -> f32 {
+        if distance <= self.min_distance {
+            return 1.0;
+        }
+
+        if distance >= self.max_distance {
+            return 0.0;
+        }
+
+        1.0 - (distance - self.min_distance) / (self.max_distance - self.min_distance)
+    }
+}
+
+/// The parameters of playback.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct AudioSource {
+    pub clip: AudioClipHandle,
+    /// The volume scale in range `[0, 1]`.
+    pub volume: f32,
+    /// The playback speed, which also shifts the pitch. 1.0 is the original speed.
+    pub pitch: f32,
+    pub wrap: AudioSourceWrap,
+    /// The positional attenuation, the source is heard the same everywhere if `None`.
+    pub attenuation: Option<AudioSourceAttenuation>,
+    /// The bus which the source is routed into.
+    pub bus: AudioBus,
+}
+
+impl AudioSource {
+    pub fn new(clip: AudioClipHandle) -> Self {
+        AudioSource {
+            clip: clip,
+            volume: 1.0,
+            pitch: 1.0,
+            wrap: AudioSourceWrap::Repeat(1),
+            attenuation: None,
+            bus: AudioBus::Sfx,
+        }
+    }
+}
+
+impl From<AudioClipHandle> for AudioSource {
+    fn from(clip: AudioClipHandle) -> Self {
+        AudioSource::new(clip)
+    }
+}
+
+/// The parameters of streaming playback, which is usually used for music tracks.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct AudioStreamSource {
+    pub stream: AudioStreamHandle,
+    /// The volume scale in range `[0, 1]`.
+    pub volume: f32,
+    /// The playback speed, which also shifts the pitch. 1.0 is the original speed.
+    pub pitch: f32,
+    /// How many times the stream is played, the loops are seamless.
+    pub wrap: AudioSourceWrap,
+    /// The bus which the source is routed into.
+    pub bus: AudioBus,
+}
+
+impl AudioStreamSource {
+    pub fn new(stream: AudioStreamHandle) -> Self {
+        AudioStreamSource {
+            stream: stream,
+            volume: 1.0,
+            pitch: 1.0,
+            wrap: AudioSourceWrap::Repeat(1),
+            bus: AudioBus::Music,
+        }
+    }
+}
+
+impl From<AudioStreamHandle> for AudioStreamSource {
+    fn from(stream: AudioStreamHandle) -> Self {
+        AudioStreamSource::new(stream)
+    }
+}
+
+/// The ears in world space, which the positional sources are heard from.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct AudioListener {
+    pub position: math::Vector3<f32>,
+    pub rotation: math::Quaternion<f32>,
+}
+
+impl Default for AudioListener {
+    fn default() -> Self {
+        AudioListener {
+            position: math::Vector3::zero(),
+            rotation: math::Quaternion::one(),
+        }
+    }
+}
+
+impl AudioListener {
+    /// Gets the volume scales of left and right channels of the positional source.
+    /// The source is panned by its direction relative to the listener, with the
+    /// constant power law, so the sources right in front are about 3dB quieter than
+    /// the non-positional ones.
+    pub fn spatialize(&self, attenuation: &AudioSourceAttenuation) -> (f32, f32) {
+        let offset = attenuation.position - self.position;
+        let distance = offset.magnitude();
+        let gain = attenuation.gain(distance);
+
+        if distance <= ::std::f32::EPSILON {
+            let v = gain * ::std::f32::consts::FRAC_1_SQRT_2;
+            return (v, v);
+        }
+
+        let local = self.rotation.invert().rotate_vector(offset);
+        let pan = (local.x / distance).max(-1.0).min(1.0);
+        let angle = (pan + 1.0) * ::std::f32::consts::FRAC_PI_4;
+        (gain * angle.cos(), gain * angle.sin())
+    }
+}
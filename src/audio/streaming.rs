@@ -0,0 +1,410 @@
+//! Streaming playbacks. The compressed streams are decoded in chunks on a dedicated IO
+//! worker, and fed into the mixer through ring buffers, so the long music tracks are
+//! never decompressed into memory as a whole.
+
+use std::collections::VecDeque;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Condvar, Mutex};
+use std::thread;
+use std::time::Duration;
+
+use errors::*;
+
+use super::assets::prelude::*;
+use super::decoder::{self, Decoder};
+use super::source::AudioSourceWrap;
+
+/// The length in seconds of samples which are decoded ahead of the mixer.
+pub const STREAM_BUFFER_SECS: f32 = 0.5;
+
+// The interval to check the buffers while the worker is idle, which should be way
+// shorter than `STREAM_BUFFER_SECS`.
+const POLL_INTERVAL_MS: u64 = 10;
+
+struct State {
+    samples: VecDeque<f32>,
+    // The frame requested by seeking, which is handled on the worker.
+    seek: Option<u64>,
+    // Increases on every seek, so the chunks decoded before seeking are discarded.
+    generation: u64,
+    // The decoder has reached the end of last loop.
+    finished: bool,
+    // The playback has been stopped, so the decoder could be dropped.
+    stopped: bool,
+}
+
+/// The ring buffer of decoded samples between the IO worker and the mixer.
+pub struct StreamBuffer {
+    channels: u8,
+    sample_rate: u32,
+    // The maximum number of samples decoded ahead.
+    capacity: usize,
+    state: Mutex<State>,
+}
+
+impl StreamBuffer {
+    pub fn new(channels: u8, sample_rate: u32) -> Self {
+        let frames = (sample_rate as f32 * STREAM_BUFFER_SECS) as usize;
+        let capacity = frames.max(1) * usize::from(channels.max(1));
+
+        StreamBuffer {
+            channels: channels,
+            sample_rate: sample_rate,
+            capacity: capacity,
+            state: Mutex::new(State {
+                samples: VecDeque::with_capacity(capacity),
+                seek: None,
+                generation: 0,
+                finished: false,
+                stopped: false,
+            }),
+        }
+    }
+
+    #[inline]
+    pub fn sample_rate(&self) -> u32 {
+        self.sample_rate
+    }
+
+    /// Pops at most `frames` stereo frames into `dst`, the mono samples are duplicated
+    /// into both channels. Returns true if the stream has been played through.
+    pub fn pop(&self, frames: usize, dst: &mut VecDeque<(f32, f32)>) -> bool {
+        let mut state = self.state.lock().unwrap();
+        let channels = usize::from(self.channels.max(1));
+
+        for _ in 0..frames.min(state.samples.len() / channels) {
+            let l = state.samples.pop_front().unwrap();
+            let r = if channels == 1 {
+                l
+            } else {
+                state.samples.pop_front().unwrap()
+            };
+
+            dst.push_back((l, r));
+        }
+
+        state.finished && state.samples.len() < channels
+    }
+
+    /// Discards the buffered samples, and decodes from `frame` afterwards.
+    pub fn seek(&self, frame: u64) {
+        let mut state = self.state.lock().unwrap();
+        state.samples.clear();
+        state.seek = Some(frame);
+        state.generation += 1;
+        state.finished = false;
+    }
+
+    /// Stops decoding, the decoder is dropped on the worker.
+    pub fn stop(&self) {
+        self.state.lock().unwrap().stopped = true;
+    }
+
+    /// Pushes the samples decoded since `generation`, the ones decoded before seeking
+    /// are discarded.
+    pub fn push(&self, generation: u64, samples: &[f32], finished: bool) {
+        let mut state = self.state.lock().unwrap();
+        if state.generation == generation {
+            state.samples.extend(samples);
+            state.finished = finished;
+        }
+    }
+}
+
+struct Streaming {
+    buffer: Arc<StreamBuffer>,
+    decoder: Box<Decoder>,
+    wrap: AudioSourceWrap,
+    // The number of times the stream has been decoded through.
+    played: u32,
+    // Any samples have been decoded since (re)starting, so empty streams would not
+    // loop forever.
+    decoded: bool,
+}
+
+impl Streaming {
+    fn is_stopped(&self) -> bool {
+        self.buffer.state.lock().unwrap().stopped
+    }
+
+    // Decodes a chunk if the buffer is not full, returns true if anything is decoded.
+    fn pump(&mut self, chunk: &mut Vec<f32>) -> Result<bool> {
+        let (generation, seek) = {
+            let mut state = self.buffer.state.lock().unwrap();
+            if state.finished || state.samples.len() >= self.buffer.capacity {
+                return Ok(false);
+            }
+
+            (state.generation, state.seek.take())
+        };
+
+        if let Some(frame) = seek {
+            self.decoder.seek(frame)?;
+            self.decoded = true;
+        }
+
+        chunk.clear();
+        let mut finished = false;
+        if self.decoder.decode(chunk)? {
+            self.decoded = true;
+        } else {
+            self.played += 1;
+            let repeat = match self.wrap {
+                AudioSourceWrap::Repeat(n) => self.played < n,
+                AudioSourceWrap::Infinite => true,
+            };
+
+            if repeat && self.decoded {
+                // Loops seamlessly by decoding from the beginning right after the end,
+                // the mixer never sees the gap.
+                self.decoder.seek(0)?;
+                self.decoded = false;
+            } else {
+                finished = true;
+            }
+        }
+
+        self.buffer.push(generation, chunk, finished);
+        Ok(true)
+    }
+}
+
+struct StreamerShared {
+    // The newly opened streams, which are taken by the worker.
+    pending: Mutex<Vec<Streaming>>,
+    condvar: Condvar,
+    quit: AtomicBool,
+}
+
+/// The IO worker which decodes the playing streams ahead of the mixer.
+pub struct Streamer {
+    shared: Arc<StreamerShared>,
+}
+
+impl Streamer {
+    pub fn new() -> Self {
+        let shared = Arc::new(StreamerShared {
+            pending: Mutex::new(Vec::new()),
+            condvar: Condvar::new(),
+            quit: AtomicBool::new(false),
+        });
+
+        let worker = shared.clone();
+        thread::Builder::new()
+            .name("AudioStream".into())
+            .spawn(move || Streamer::main_loop(&worker))
+            .unwrap();
+
+        Streamer { shared: shared }
+    }
+
+    /// Opens the decoder of stream, and starts decoding it ahead on the worker.
+    pub fn open(&self, stream: &AudioStream, wrap: AudioSourceWrap) -> Result<Arc<StreamBuffer>> {
+        let decoder = decoder::open(stream)?;
+        let buffer = Arc::new(StreamBuffer::new(decoder.channels(), decoder.sample_rate()));
+
+        let streaming = Streaming {
+            buffer: buffer.clone(),
+            decoder: decoder,
+            wrap: wrap,
+            played: 0,
+            decoded: false,
+        };
+
+        self.shared.pending.lock().unwrap().push(streaming);
+        self.notify();
+        Ok(buffer)
+    }
+
+    /// Wakes up the worker, e.g. after seeking.
+    #[inline]
+    pub fn notify(&self) {
+        self.shared.condvar.notify_one();
+    }
+
+    fn main_loop(shared: &StreamerShared) {
+        let mut streams = Vec::new();
+        let mut chunk = Vec::new();
+
+        while !shared.quit.load(Ordering::Relaxed) {
+            streams.extend(shared.pending.lock().unwrap().drain(..));
+
+            if !Self::pump(&mut streams, &mut chunk) {
+                let pending = shared.pending.lock().unwrap();
+                if pending.is_empty() && !shared.quit.load(Ordering::Relaxed) {
+                    let timeout = Duration::from_millis(POLL_INTERVAL_MS);
+                    let _ = shared.condvar.wait_timeout(pending, timeout).unwrap();
+                }
+            }
+        }
+    }
+
+    // Decodes a chunk of every stream, and drops the stopped ones. Returns true if any
+    // chunk has been decoded.
+    fn pump(streams: &mut Vec<Streaming>, chunk: &mut Vec<f32>) -> bool {
+        let mut busy = false;
+        let mut i = 0;
+
+        while i < streams.len() {
+            if streams[i].is_stopped() {
+                streams.swap_remove(i);
+                continue;
+            }
+
+            match streams[i].pump(chunk) {
+                Ok(v) => busy |= v,
+                Err(err) => {
+                    // Ends the playback gracefully with what has been decoded.
+                    warn!("[Streamer] failed to decode audio stream: {}", err);
+                    streams[i].buffer.state.lock().unwrap().finished = true;
+                    streams.swap_remove(i);
+                    continue;
+                }
+            }
+
+            i += 1;
+        }
+
+        busy
+    }
+}
+
+impl Drop for Streamer {
+    fn drop(&mut self) {
+        self.shared.quit.store(true, Ordering::Relaxed);
+        self.shared.condvar.notify_one();
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    // Produces `len` frames of the ramp 0, 1, 2... in chunks of `chunk` frames.
+    struct Ramp {
+        len: u64,
+        chunk: u64,
+        cursor: u64,
+    }
+
+    impl Decoder for Ramp {
+        fn channels(&self) -> u8 {
+            1
+        }
+
+        fn sample_rate(&self) -> u32 {
+            8
+        }
+
+        fn decode(&mut self, buf: &mut Vec<f32>) -> Result<bool> {
+            if self.cursor >= self.len {
+                return Ok(false);
+            }
+
+            let end = (self.cursor + self.chunk).min(self.len);
+            buf.extend((self.cursor..end).map(|v| v as f32));
+            self.cursor = end;
+            Ok(true)
+        }
+
+        fn seek(&mut self, frame: u64) -> Result<()> {
+            self.cursor = frame;
+            Ok(())
+        }
+    }
+
+    fn streaming(len: u64, wrap: AudioSourceWrap) -> Streaming {
+        let decoder = Ramp {
+            len: len,
+            chunk: 3,
+            cursor: 0,
+        };
+
+        Streaming {
+            buffer: Arc::new(StreamBuffer::new(1, 8)),
+            decoder: Box::new(decoder),
+            wrap: wrap,
+            played: 0,
+            decoded: false,
+        }
+    }
+
+    fn pop(buffer: &StreamBuffer, frames: usize) -> (Vec<f32>, bool) {
+        let mut dst = VecDeque::new();
+        let finished = buffer.pop(frames, &mut dst);
+        (dst.into_iter().map(|v| v.0).collect(), finished)
+    }
+
+    #[test]
+    fn capacity() {
+        let mut chunk = Vec::new();
+        let mut v = streaming(100, AudioSourceWrap::Repeat(1));
+
+        // Decodes ahead until the buffer of 0.5 seconds is full.
+        assert!(v.pump(&mut chunk).unwrap());
+        assert!(v.pump(&mut chunk).unwrap());
+        assert!(!v.pump(&mut chunk).unwrap());
+        assert_eq!(pop(&v.buffer, 4), (vec![0.0, 1.0, 2.0, 3.0], false));
+
+        assert!(v.pump(&mut chunk).unwrap());
+        assert_eq!(pop(&v.buffer, 8), (vec![4.0, 5.0, 6.0, 7.0, 8.0], false));
+    }
+
+    #[test]
+    fn repeat() {
+        let mut chunk = Vec::new();
+        let mut v = streaming(2, AudioSourceWrap::Repeat(2));
+
+        for _ in 0..3 {
+            assert!(v.pump(&mut chunk).unwrap());
+        }
+
+        // Loops seamlessly, and finishes after the last loop.
+        assert!(!v.pump(&mut chunk).unwrap());
+        assert_eq!(pop(&v.buffer, 3), (vec![0.0, 1.0, 0.0], false));
+        assert!(v.pump(&mut chunk).unwrap());
+        assert!(!v.pump(&mut chunk).unwrap());
+        assert_eq!(pop(&v.buffer, 3), (vec![1.0], true));
+
+        let mut v = streaming(0, AudioSourceWrap::Infinite);
+        assert!(v.pump(&mut chunk).unwrap());
+        assert!(!v.pump(&mut chunk).unwrap());
+        assert_eq!(pop(&v.buffer, 1), (vec![], true));
+    }
+
+    #[test]
+    fn seek() {
+        let mut chunk = Vec::new();
+        let mut v = streaming(100, AudioSourceWrap::Repeat(1));
+        v.pump(&mut chunk).unwrap();
+
+        v.buffer.seek(50);
+        assert_eq!(pop(&v.buffer, 1), (vec![], false));
+        v.pump(&mut chunk).unwrap();
+        assert_eq!(pop(&v.buffer, 4), (vec![50.0, 51.0, 52.0], false));
+
+        // Seeks from the end of stream.
+        v.buffer.seek(99);
+        v.pump(&mut chunk).unwrap();
+        v.pump(&mut chunk).unwrap();
+        assert_eq!(pop(&v.buffer, 4), (vec![99.0], true));
+
+        v.buffer.seek(0);
+        v.pump(&mut chunk).unwrap();
+        assert_eq!(pop(&v.buffer, 4), (vec![0.0, 1.0, 2.0], false));
+    }
+
+    #[test]
+    fn worker() {
+        let mut streams = vec![streaming(100, AudioSourceWrap::Infinite)];
+        let buffer = streams[0].buffer.clone();
+
+        let mut chunk = Vec::new();
+        assert!(Streamer::pump(&mut streams, &mut chunk));
+        assert_eq!(streams.len(), 1);
+
+        buffer.stop();
+        assert!(!Streamer::pump(&mut streams, &mut chunk));
+        assert!(streams.is_empty());
+    }
+}
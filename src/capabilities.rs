@@ -0,0 +1,99 @@
+//! Queries the version of crayon and the capabilities of running device, which helps to
+//! adapt quality settings or pick the right variants of assets at startup.
+//!
+//! ```rust,ignore
+//! let engine = Engine::new()?;
+//!
+//! let caps = crayon::capabilities();
+//! if caps.video.has_texture_format(TextureFormat::Etc2RGBA8BPP) {
+//!     // Loads the ETC2 variants of textures.
+//! }
+//! ```
+
+use std::sync::{Once, RwLock, ONCE_INIT};
+
+use video::capabilities::VideoCapabilities;
+
+/// The version of crayon.
+pub const VERSION: &'static str = env!("CARGO_PKG_VERSION");
+
+/// The platform which crayon is running on.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Platform {
+    Windows,
+    MacOS,
+    Linux,
+    IOS,
+    Android,
+    Unknown,
+}
+
+impl Platform {
+    /// Gets the platform which crayon is compiled for.
+    pub fn current() -> Self {
+        if cfg!(target_os = "windows") {
+            Platform::Windows
+        } else if cfg!(target_os = "macos") {
+            Platform::MacOS
+        } else if cfg!(target_os = "linux") {
+            Platform::Linux
+        } else if cfg!(target_os = "ios") {
+            Platform::IOS
+        } else if cfg!(target_os = "android") {
+            Platform::Android
+        } else {
+            Platform::Unknown
+        }
+    }
+}
+
+/// The structured information about crayon and running device.
+#[derive(Debug, Clone)]
+pub struct Capabilities {
+    pub version: &'static str,
+    pub platform: Platform,
+    /// The number of threads which execute the per-frame jobs.
+    pub workers: u32,
+    /// The number of threads which execute the background jobs.
+    pub background_workers: u32,
+    /// Whether the engine is running without window and video device.
+    pub headless: bool,
+    pub video: VideoCapabilities,
+}
+
+impl Default for Capabilities {
+    fn default() -> Self {
+        Capabilities {
+            version: VERSION,
+            platform: Platform::current(),
+            workers: 0,
+            background_workers: 0,
+            headless: true,
+            video: VideoCapabilities::default(),
+        }
+    }
+}
+
+/// Gets the capabilities of the most recently created `Engine`. Only the version and
+/// platform are available if there is no engine created yet.
+pub fn capabilities() -> Capabilities {
+    shared().read().unwrap().clone()
+}
+
+/// Records the capabilities of a newly created `Engine`.
+pub(crate) fn register(caps: Capabilities) {
+    *shared().write().unwrap() = caps;
+}
+
+fn shared() -> &'static RwLock<Capabilities> {
+    static INIT: Once = ONCE_INIT;
+    static mut SHARED: *const RwLock<Capabilities> = 0 as *const _;
+
+    unsafe {
+        INIT.call_once(|| {
+            SHARED = Box::into_raw(Box::new(RwLock::new(Capabilities::default())));
+        });
+
+        &*SHARED
+    }
+}
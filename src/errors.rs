@@ -1 +1,225 @@
-pub type Result<T> = ::std::result::Result<T, ::failure::Error>;
+//! Error handling with categories and structured diagnostics.
+//!
+//! All the sub-systems report errors with `failure::Error`. To make it possible for user
+//! code to handle errors without parsing the messages, every error could be classified
+//! into an `ErrorKind` with `errors::kind`, and carries a chain of `Diagnostic` contexts
+//! (e.g. handles, resource locations) which are attached with `ResultExt`.
+//!
+//! ```rust,ignore
+//! use crayon::errors::*;
+//!
+//! let v = res.load_from::<MeshHandle>(location).with_location(uri);
+//! if let Err(err) = v {
+//!     match kind(&err) {
+//!         ErrorKind::NotReady => {}
+//!         ErrorKind::Io => warn!("{}", report(&err)),
+//!         _ => return Err(err),
+//!     }
+//! }
+//! ```
+
+use std::fmt;
+use std::io;
+
+use failure::{self, Fail};
+
+use application::window;
+use utils::handle::Handle;
+use video;
+
+pub type Result<T> = ::std::result::Result<T, failure::Error>;
+
+/// The categories of errors.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum ErrorKind {
+    /// The resource is not ready yet.
+    NotReady,
+    /// The handle is invalid or has been freed.
+    InvalidHandle,
+    /// The data (e.g. shader sources, asset contents) is malformed.
+    InvalidData,
+    /// An index or range is out of bounds.
+    OutOfBounds,
+    /// The operation is not supported by current platform or device.
+    Unsupported,
+    /// An I/O error.
+    Io,
+    /// The underlying video driver or context has been lost.
+    DriverLost,
+    /// Everything else.
+    Other,
+}
+
+impl fmt::Display for ErrorKind {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let v = match *self {
+            ErrorKind::NotReady => "Not ready",
+            ErrorKind::InvalidHandle => "Invalid handle",
+            ErrorKind::InvalidData => "Invalid data",
+            ErrorKind::OutOfBounds => "Out of bounds",
+            ErrorKind::Unsupported => "Unsupported",
+            ErrorKind::Io => "I/O error",
+            ErrorKind::DriverLost => "Driver lost",
+            ErrorKind::Other => "Other",
+        };
+
+        write!(f, "{}", v)
+    }
+}
+
+/// The structured context which could be attached to errors.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Diagnostic {
+    /// The handle of object which the error is related to.
+    Handle(Handle),
+    /// The location of resource.
+    Location(String),
+    /// The handle of surface (view) which the error occurs in.
+    Surface(Handle),
+    /// The name of sub-system.
+    Subsystem(&'static str),
+}
+
+impl fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Diagnostic::Handle(v) => write!(f, "Handle: {:?}", v),
+            Diagnostic::Location(ref v) => write!(f, "Location: {}", v),
+            Diagnostic::Surface(v) => write!(f, "Surface: {:?}", v),
+            Diagnostic::Subsystem(v) => write!(f, "Subsystem: {}", v),
+        }
+    }
+}
+
+/// Extension methods to attach categories and diagnostics to errors.
+pub trait ResultExt<T> {
+    /// Classifies the error with `kind`.
+    fn with_kind(self, kind: ErrorKind) -> Result<T>;
+    /// Attaches a diagnostic to the error.
+    fn with_diagnostic(self, diagnostic: Diagnostic) -> Result<T>;
+
+    /// Attaches the handle of object to the error.
+    fn with_handle<H: Into<Handle>>(self, handle: H) -> Result<T>
+    where
+        Self: Sized,
+    {
+        self.with_diagnostic(Diagnostic::Handle(handle.into()))
+    }
+
+    /// Attaches the location of resource to the error.
+    fn with_location<L: Into<String>>(self, location: L) -> Result<T>
+    where
+        Self: Sized,
+    {
+        self.with_diagnostic(Diagnostic::Location(location.into()))
+    }
+}
+
+impl<T, E> ResultExt<T> for ::std::result::Result<T, E>
+where
+    E: Into<failure::Error>,
+{
+    fn with_kind(self, kind: ErrorKind) -> Result<T> {
+        self.map_err(|err| err.into().context(kind).into())
+    }
+
+    fn with_diagnostic(self, diagnostic: Diagnostic) -> Result<T> {
+        self.map_err(|err| err.into().context(diagnostic).into())
+    }
+}
+
+/// Gets the category of error. The outermost categorized error in the chain of causes
+/// is used.
+pub fn kind(err: &failure::Error) -> ErrorKind {
+    let mut cause: Option<&Fail> = Some(err.as_fail());
+    while let Some(v) = cause {
+        if let Some(ctx) = v.downcast_ref::<failure::Context<ErrorKind>>() {
+            return *ctx.get_context();
+        }
+
+        if let Some(e) = v.downcast_ref::<video::errors::Error>() {
+            return e.kind();
+        }
+
+        if let Some(e) = v.downcast_ref::<window::Error>() {
+            return e.kind();
+        }
+
+        if v.downcast_ref::<io::Error>().is_some() {
+            return ErrorKind::Io;
+        }
+
+        cause = v.cause();
+    }
+
+    ErrorKind::Other
+}
+
+/// Gets all the diagnostics attached to the error, from outermost to innermost.
+pub fn diagnostics(err: &failure::Error) -> Vec<Diagnostic> {
+    let mut diagnostics = Vec::new();
+    let mut cause: Option<&Fail> = Some(err.as_fail());
+    while let Some(v) = cause {
+        if let Some(ctx) = v.downcast_ref::<failure::Context<Diagnostic>>() {
+            diagnostics.push(ctx.get_context().clone());
+        }
+
+        cause = v.cause();
+    }
+
+    diagnostics
+}
+
+/// Formats the error with its chain of causes. The backtrace is also included in debug
+/// builds if available.
+pub fn report(err: &failure::Error) -> String {
+    use std::fmt::Write;
+
+    let mut buf = String::new();
+    let _ = write!(buf, "[{}] {}", kind(err), err);
+
+    let mut cause = err.as_fail().cause();
+    while let Some(v) = cause {
+        let _ = write!(buf, "\n  caused by: {}", v);
+        cause = v.cause();
+    }
+
+    if cfg!(debug_assertions) {
+        let backtrace = format!("{}", err.backtrace());
+        if !backtrace.is_empty() {
+            let _ = write!(buf, "\n{}", backtrace);
+        }
+    }
+
+    buf
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn chain() {
+        let handle = Handle::new(3, 1);
+        let v: Result<()> = Err(format_err!("Oops"));
+        let err = v
+            .with_kind(ErrorKind::NotReady)
+            .with_handle(handle)
+            .with_location("res:a.png")
+            .unwrap_err();
+
+        assert_eq!(kind(&err), ErrorKind::NotReady);
+        assert_eq!(
+            diagnostics(&err),
+            vec![
+                Diagnostic::Location("res:a.png".into()),
+                Diagnostic::Handle(handle),
+            ]
+        );
+
+        let v: ::std::result::Result<(), io::Error> =
+            Err(io::Error::new(io::ErrorKind::NotFound, "Missing"));
+        let err = v.with_location("res:b.png").unwrap_err();
+        assert_eq!(kind(&err), ErrorKind::Io);
+    }
+}
@@ -1,7 +1,7 @@
 use std::collections::{HashMap, HashSet};
 use std::time::{Duration, Instant};
 
-pub use application::event::KeyboardButton;
+pub use application::event::{KeyboardButton, ScanCode};
 
 /// The setup parameters of keyboard device.
 #[derive(Debug, Clone, Copy)]
@@ -33,6 +33,9 @@ pub struct Keyboard {
     downs: HashMap<KeyboardButton, KeyDownState>,
     presses: HashSet<KeyboardButton>,
     releases: HashSet<KeyboardButton>,
+    scancode_downs: HashSet<ScanCode>,
+    scancode_presses: HashSet<ScanCode>,
+    scancode_releases: HashSet<ScanCode>,
     chars: Vec<char>,
     setup: KeyboardParams,
     now: Instant,
@@ -44,6 +47,9 @@ impl Keyboard {
             downs: HashMap::new(),
             presses: HashSet::new(),
             releases: HashSet::new(),
+            scancode_downs: HashSet::new(),
+            scancode_presses: HashSet::new(),
+            scancode_releases: HashSet::new(),
             chars: Vec::with_capacity(setup.max_chars),
             setup: setup,
             now: Instant::now(),
@@ -55,6 +61,9 @@ impl Keyboard {
         self.downs.clear();
         self.presses.clear();
         self.releases.clear();
+        self.scancode_downs.clear();
+        self.scancode_presses.clear();
+        self.scancode_releases.clear();
         self.chars.clear();
     }
 
@@ -62,6 +71,8 @@ impl Keyboard {
     pub fn advance(&mut self) {
         self.presses.clear();
         self.releases.clear();
+        self.scancode_presses.clear();
+        self.scancode_releases.clear();
         self.chars.clear();
 
         let last_frame_ts = self.now;
@@ -82,17 +93,28 @@ impl Keyboard {
     }
 
     #[inline]
-    pub fn on_key_pressed(&mut self, key: KeyboardButton) {
-        if !self.downs.contains_key(&key) {
-            self.presses.insert(key);
-            self.downs.insert(key, KeyDownState::Start(self.now));
+    pub fn on_key_pressed(&mut self, key: Option<KeyboardButton>, scancode: ScanCode) {
+        if let Some(key) = key {
+            if !self.downs.contains_key(&key) {
+                self.presses.insert(key);
+                self.downs.insert(key, KeyDownState::Start(self.now));
+            }
+        }
+
+        if self.scancode_downs.insert(scancode) {
+            self.scancode_presses.insert(scancode);
         }
     }
 
     #[inline]
-    pub fn on_key_released(&mut self, key: KeyboardButton) {
-        self.downs.remove(&key);
-        self.releases.insert(key);
+    pub fn on_key_released(&mut self, key: Option<KeyboardButton>, scancode: ScanCode) {
+        if let Some(key) = key {
+            self.downs.remove(&key);
+            self.releases.insert(key);
+        }
+
+        self.scancode_downs.remove(&scancode);
+        self.scancode_releases.insert(scancode);
     }
 
     #[inline]
@@ -128,8 +150,229 @@ impl Keyboard {
         }
     }
 
+    #[inline]
+    pub fn is_scancode_down(&self, scancode: ScanCode) -> bool {
+        self.scancode_downs.contains(&scancode)
+    }
+
+    #[inline]
+    pub fn is_scancode_press(&self, scancode: ScanCode) -> bool {
+        self.scancode_presses.contains(&scancode)
+    }
+
+    #[inline]
+    pub fn is_scancode_release(&self, scancode: ScanCode) -> bool {
+        self.scancode_releases.contains(&scancode)
+    }
+
     #[inline]
     pub fn captured_chars(&self) -> &[char] {
         &self.chars
     }
 }
+
+/// Gets the scancode of the physical key, which is labeled with `key` on a standard
+/// US QWERTY keyboard. E.g. `scancode_of(KeyboardButton::W)` always refers to the key
+/// right above `S`, even on AZERTY or Dvorak layouts.
+///
+/// Notes that only the main block and arrow keys are covered.
+pub fn scancode_of(key: KeyboardButton) -> Option<ScanCode> {
+    SCANCODES.iter().find(|v| v.1 == key).map(|v| v.0)
+}
+
+/// Gets the key labeled on the physical key with `scancode` on a standard US QWERTY
+/// keyboard.
+pub fn key_of(scancode: ScanCode) -> Option<KeyboardButton> {
+    SCANCODES.iter().find(|v| v.0 == scancode).map(|v| v.1)
+}
+
+// Linux (evdev) and Windows (set 1) share the same scancodes of main block.
+#[cfg(not(target_os = "macos"))]
+const SCANCODES: &[(ScanCode, KeyboardButton)] = &[
+    (0x01, KeyboardButton::Escape),
+    (0x02, KeyboardButton::Key1),
+    (0x03, KeyboardButton::Key2),
+    (0x04, KeyboardButton::Key3),
+    (0x05, KeyboardButton::Key4),
+    (0x06, KeyboardButton::Key5),
+    (0x07, KeyboardButton::Key6),
+    (0x08, KeyboardButton::Key7),
+    (0x09, KeyboardButton::Key8),
+    (0x0A, KeyboardButton::Key9),
+    (0x0B, KeyboardButton::Key0),
+    (0x0C, KeyboardButton::Minus),
+    (0x0D, KeyboardButton::Equals),
+    (0x0E, KeyboardButton::Back),
+    (0x0F, KeyboardButton::Tab),
+    (0x10, KeyboardButton::Q),
+    (0x11, KeyboardButton::W),
+    (0x12, KeyboardButton::E),
+    (0x13, KeyboardButton::R),
+    (0x14, KeyboardButton::T),
+    (0x15, KeyboardButton::Y),
+    (0x16, KeyboardButton::U),
+    (0x17, KeyboardButton::I),
+    (0x18, KeyboardButton::O),
+    (0x19, KeyboardButton::P),
+    (0x1A, KeyboardButton::LBracket),
+    (0x1B, KeyboardButton::RBracket),
+    (0x1C, KeyboardButton::Return),
+    (0x1D, KeyboardButton::LControl),
+    (0x1E, KeyboardButton::A),
+    (0x1F, KeyboardButton::S),
+    (0x20, KeyboardButton::D),
+    (0x21, KeyboardButton::F),
+    (0x22, KeyboardButton::G),
+    (0x23, KeyboardButton::H),
+    (0x24, KeyboardButton::J),
+    (0x25, KeyboardButton::K),
+    (0x26, KeyboardButton::L),
+    (0x27, KeyboardButton::Semicolon),
+    (0x28, KeyboardButton::Apostrophe),
+    (0x29, KeyboardButton::Grave),
+    (0x2A, KeyboardButton::LShift),
+    (0x2B, KeyboardButton::Backslash),
+    (0x2C, KeyboardButton::Z),
+    (0x2D, KeyboardButton::X),
+    (0x2E, KeyboardButton::C),
+    (0x2F, KeyboardButton::V),
+    (0x30, KeyboardButton::B),
+    (0x31, KeyboardButton::N),
+    (0x32, KeyboardButton::M),
+    (0x33, KeyboardButton::Comma),
+    (0x34, KeyboardButton::Period),
+    (0x35, KeyboardButton::Slash),
+    (0x36, KeyboardButton::RShift),
+    (0x38, KeyboardButton::LAlt),
+    (0x39, KeyboardButton::Space),
+    (0x3A, KeyboardButton::Capital),
+    #[cfg(target_os = "windows")]
+    (0x48, KeyboardButton::Up),
+    #[cfg(target_os = "windows")]
+    (0x4B, KeyboardButton::Left),
+    #[cfg(target_os = "windows")]
+    (0x4D, KeyboardButton::Right),
+    #[cfg(target_os = "windows")]
+    (0x50, KeyboardButton::Down),
+    #[cfg(not(target_os = "windows"))]
+    (0x67, KeyboardButton::Up),
+    #[cfg(not(target_os = "windows"))]
+    (0x69, KeyboardButton::Left),
+    #[cfg(not(target_os = "windows"))]
+    (0x6A, KeyboardButton::Right),
+    #[cfg(not(target_os = "windows"))]
+    (0x6C, KeyboardButton::Down),
+];
+
+#[cfg(target_os = "macos")]
+const SCANCODES: &[(ScanCode, KeyboardButton)] = &[
+    (0x00, KeyboardButton::A),
+    (0x01, KeyboardButton::S),
+    (0x02, KeyboardButton::D),
+    (0x03, KeyboardButton::F),
+    (0x04, KeyboardButton::H),
+    (0x05, KeyboardButton::G),
+    (0x06, KeyboardButton::Z),
+    (0x07, KeyboardButton::X),
+    (0x08, KeyboardButton::C),
+    (0x09, KeyboardButton::V),
+    (0x0B, KeyboardButton::B),
+    (0x0C, KeyboardButton::Q),
+    (0x0D, KeyboardButton::W),
+    (0x0E, KeyboardButton::E),
+    (0x0F, KeyboardButton::R),
+    (0x10, KeyboardButton::Y),
+    (0x11, KeyboardButton::T),
+    (0x12, KeyboardButton::Key1),
+    (0x13, KeyboardButton::Key2),
+    (0x14, KeyboardButton::Key3),
+    (0x15, KeyboardButton::Key4),
+    (0x16, KeyboardButton::Key6),
+    (0x17, KeyboardButton::Key5),
+    (0x18, KeyboardButton::Equals),
+    (0x19, KeyboardButton::Key9),
+    (0x1A, KeyboardButton::Key7),
+    (0x1B, KeyboardButton::Minus),
+    (0x1C, KeyboardButton::Key8),
+    (0x1D, KeyboardButton::Key0),
+    (0x1E, KeyboardButton::RBracket),
+    (0x1F, KeyboardButton::O),
+    (0x20, KeyboardButton::U),
+    (0x21, KeyboardButton::LBracket),
+    (0x22, KeyboardButton::I),
+    (0x23, KeyboardButton::P),
+    (0x24, KeyboardButton::Return),
+    (0x25, KeyboardButton::L),
+    (0x26, KeyboardButton::J),
+    (0x27, KeyboardButton::Apostrophe),
+    (0x28, KeyboardButton::K),
+    (0x29, KeyboardButton::Semicolon),
+    (0x2A, KeyboardButton::Backslash),
+    (0x2B, KeyboardButton::Comma),
+    (0x2C, KeyboardButton::Slash),
+    (0x2D, KeyboardButton::N),
+    (0x2E, KeyboardButton::M),
+    (0x2F, KeyboardButton::Period),
+    (0x30, KeyboardButton::Tab),
+    (0x31, KeyboardButton::Space),
+    (0x32, KeyboardButton::Grave),
+    (0x33, KeyboardButton::Back),
+    (0x35, KeyboardButton::Escape),
+    (0x38, KeyboardButton::LShift),
+    (0x39, KeyboardButton::Capital),
+    (0x3A, KeyboardButton::LAlt),
+    (0x3B, KeyboardButton::LControl),
+    (0x3C, KeyboardButton::RShift),
+    (0x7B, KeyboardButton::Left),
+    (0x7C, KeyboardButton::Right),
+    (0x7D, KeyboardButton::Down),
+    (0x7E, KeyboardButton::Up),
+];
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn scancodes() {
+        for &(scancode, key) in SCANCODES {
+            assert_eq!(scancode_of(key), Some(scancode));
+            assert_eq!(key_of(scancode), Some(key));
+        }
+
+        let mut kb = Keyboard::new(KeyboardParams::default());
+        let w = scancode_of(KeyboardButton::W).unwrap();
+
+        // The key labeled `Z` on AZERTY layout is located at `W` of QWERTY.
+        kb.on_key_pressed(Some(KeyboardButton::Z), w);
+        assert!(kb.is_scancode_down(w));
+        assert!(kb.is_scancode_press(w));
+        assert!(!kb.is_key_down(KeyboardButton::W));
+
+        kb.advance();
+        assert!(kb.is_scancode_down(w));
+        assert!(!kb.is_scancode_press(w));
+
+        kb.on_key_released(Some(KeyboardButton::Z), w);
+        assert!(!kb.is_scancode_down(w));
+        assert!(kb.is_scancode_release(w));
+    }
+
+    #[test]
+    fn unknown_keys() {
+        let mut kb = Keyboard::new(KeyboardParams::default());
+
+        // Keys without virtual keycodes are still reported with their scancodes.
+        kb.on_key_pressed(None, 0x60);
+        assert!(kb.is_scancode_down(0x60));
+        assert!(kb.is_scancode_press(0x60));
+        assert!(kb.downs.is_empty());
+        assert!(kb.presses.is_empty());
+
+        kb.advance();
+        kb.on_key_released(None, 0x60);
+        assert!(!kb.is_scancode_down(0x60));
+        assert!(kb.is_scancode_release(0x60));
+        assert!(kb.releases.is_empty());
+    }
+}
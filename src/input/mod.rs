@@ -33,6 +33,19 @@
 //! that the key code used here, are virtual keycode of physical keys, they don't
 //! necessarily represent what's actually printed on the key cap.
 //!
+//! To make bindings like `WASD` work on AZERTY or Dvorak layouts, the physical keys
+//! could be queried by their scancodes. The `_physical_` series functions take the
+//! key at the same position on a standard US QWERTY keyboard:
+//!
+//! ```rust
+//! use crayon::input::prelude::*;
+//! let input = InputSystem::new(InputParams::default()).shared();
+//!
+//! // Checks if the physical key labeled `W` on QWERTY keyboard is held down, which
+//! // is labeled `Z` on AZERTY keyboard.
+//! input.is_physical_key_down(KeyboardButton::W);
+//! ```
+//!
 //! It's useful to get converted character input instead of raw key codes, to capture
 //! entered text in last frame, you can call:
 //!
@@ -89,6 +102,20 @@
 //! input.mouse_scroll();
 //! ```
 //!
+//! For FPS-style aiming, the raw relative motion of mouse devices, which is not
+//! affected by the cursor acceleration of OS, could be enabled with `InputParams::
+//! use_raw_mouse`. All the samples between two frames are accumulated:
+//!
+//! ```rust
+//! use crayon::input::prelude::*;
+//! let mut params = InputParams::default();
+//! params.use_raw_mouse = true;
+//! let input = InputSystem::new(params).shared();
+//!
+//! // Gets the raw motion of all mouse devices in device units since last frame.
+//! input.mouse_raw_movement();
+//! ```
+//!
 //! Mouse positions and movement are reported in pixel coordinates which makes it
 //! difficult to derive useful movement information out of it. It might changes in
 //! the future versions (dividing by the framebuffer resolution is a simple but very
@@ -175,8 +202,8 @@ pub mod touchpad;
 pub const MAX_TOUCHES: usize = 4;
 
 pub mod prelude {
-    pub use super::keyboard::{KeyboardButton, KeyboardParams};
-    pub use super::mouse::{MouseButton, MouseParams};
+    pub use super::keyboard::{KeyboardButton, KeyboardParams, ScanCode};
+    pub use super::mouse::{DeviceId, MouseButton, MouseParams};
     pub use super::touchpad::{GesturePan, GestureTap, TouchPadParams};
     pub use super::{InputParams, InputSystem, InputSystemShared};
 }
@@ -192,6 +219,8 @@ pub struct InputParams {
     pub keyboard: keyboard::KeyboardParams,
     pub mouse: mouse::MouseParams,
     pub touchpad: touchpad::TouchPadParams,
+    /// Enables the raw relative motion of mouse devices, which is discarded otherwise.
+    pub use_raw_mouse: bool,
 }
 
 /// The `InputSystem` struct are used to manage all the events and corresponding
@@ -199,6 +228,7 @@ pub struct InputParams {
 pub struct InputSystem {
     touch_emulation: bool,
     touch_emulation_button: Option<MouseButton>,
+    use_raw_mouse: bool,
     shared: Arc<InputSystemShared>,
 }
 
@@ -210,6 +240,7 @@ impl InputSystem {
             shared: shared,
             touch_emulation: false,
             touch_emulation_button: None,
+            use_raw_mouse: setup.use_raw_mouse,
         }
     }
 
@@ -232,6 +263,12 @@ impl InputSystem {
         self
     }
 
+    /// Enables or disables the raw relative motion of mouse devices.
+    pub fn set_raw_mouse(&mut self, enabled: bool) -> &Self {
+        self.use_raw_mouse = enabled;
+        self
+    }
+
     pub(crate) fn advance(&mut self, hidpi: f32) {
         *self.shared.hidpi.write().unwrap() = hidpi;
         self.shared.mouse.write().unwrap().advance();
@@ -247,6 +284,7 @@ impl InputSystem {
                         id: 255,
                         state: event::TouchState::Move,
                         position: self.shared.mouse.read().unwrap().position(),
+                        pressure: 1.0,
                     };
 
                     self.shared.touchpad.write().unwrap().on_touch(touch);
@@ -263,6 +301,7 @@ impl InputSystem {
                         id: 255,
                         state: event::TouchState::Start,
                         position: self.shared.mouse.read().unwrap().position(),
+                        pressure: 1.0,
                     };
 
                     self.shared.touchpad.write().unwrap().on_touch(touch);
@@ -279,6 +318,7 @@ impl InputSystem {
                         id: 255,
                         state: event::TouchState::End,
                         position: self.shared.mouse.read().unwrap().position(),
+                        pressure: 1.0,
                     };
 
                     self.shared.touchpad.write().unwrap().on_touch(touch);
@@ -295,13 +335,29 @@ impl InputSystem {
                 self.shared.mouse.write().unwrap().on_wheel_scroll(delta)
             }
 
-            event::InputDeviceEvent::KeyboardPressed { key } => {
-                self.shared.keyboard.write().unwrap().on_key_pressed(key)
+            event::InputDeviceEvent::MouseRawMoved { device, delta } => {
+                if self.use_raw_mouse {
+                    self.shared
+                        .mouse
+                        .write()
+                        .unwrap()
+                        .on_raw_move(device, delta)
+                }
             }
 
-            event::InputDeviceEvent::KeyboardReleased { key } => {
-                self.shared.keyboard.write().unwrap().on_key_released(key)
-            }
+            event::InputDeviceEvent::KeyboardPressed { key, scancode } => self
+                .shared
+                .keyboard
+                .write()
+                .unwrap()
+                .on_key_pressed(key, scancode),
+
+            event::InputDeviceEvent::KeyboardReleased { key, scancode } => self
+                .shared
+                .keyboard
+                .write()
+                .unwrap()
+                .on_key_released(key, scancode),
 
             event::InputDeviceEvent::ReceivedCharacter { character } => {
                 self.shared.keyboard.write().unwrap().on_char(character)
@@ -369,6 +425,47 @@ impl InputSystemShared {
         self.keyboard.read().unwrap().is_key_repeat(key)
     }
 
+    /// Checks if the physical key with `scancode` is currently held down.
+    #[inline]
+    pub fn is_scancode_down(&self, scancode: keyboard::ScanCode) -> bool {
+        self.keyboard.read().unwrap().is_scancode_down(scancode)
+    }
+
+    /// Checks if the physical key with `scancode` has been pressed down during the last
+    /// frame.
+    #[inline]
+    pub fn is_scancode_press(&self, scancode: keyboard::ScanCode) -> bool {
+        self.keyboard.read().unwrap().is_scancode_press(scancode)
+    }
+
+    /// Checks if the physical key with `scancode` has been released during the last
+    /// frame.
+    #[inline]
+    pub fn is_scancode_release(&self, scancode: keyboard::ScanCode) -> bool {
+        self.keyboard.read().unwrap().is_scancode_release(scancode)
+    }
+
+    /// Checks if the physical key, which is labeled with `key` on a US QWERTY keyboard,
+    /// is currently held down.
+    #[inline]
+    pub fn is_physical_key_down(&self, key: KeyboardButton) -> bool {
+        keyboard::scancode_of(key).map_or(false, |v| self.is_scancode_down(v))
+    }
+
+    /// Checks if the physical key, which is labeled with `key` on a US QWERTY keyboard,
+    /// has been pressed down during the last frame.
+    #[inline]
+    pub fn is_physical_key_press(&self, key: KeyboardButton) -> bool {
+        keyboard::scancode_of(key).map_or(false, |v| self.is_scancode_press(v))
+    }
+
+    /// Checks if the physical key, which is labeled with `key` on a US QWERTY keyboard,
+    /// has been released during the last frame.
+    #[inline]
+    pub fn is_physical_key_release(&self, key: KeyboardButton) -> bool {
+        keyboard::scancode_of(key).map_or(false, |v| self.is_scancode_release(v))
+    }
+
     /// Gets captured text during the last frame.
     #[inline]
     pub fn text(&self) -> String {
@@ -451,6 +548,31 @@ impl InputSystemShared {
     pub fn mouse_scroll_in_points(&self) -> math::Vector2<f32> {
         self.mouse.read().unwrap().scroll()
     }
+
+    /// Gets the accumulated raw motion of all mouse devices since last frame, measured
+    /// in device units. It's always zero unless `InputParams::use_raw_mouse` is enabled.
+    #[inline]
+    pub fn mouse_raw_movement(&self) -> math::Vector2<f32> {
+        self.mouse.read().unwrap().raw_movement()
+    }
+
+    /// Gets the accumulated raw motion of specified mouse device since last frame.
+    #[inline]
+    pub fn mouse_raw_movement_of(&self, device: mouse::DeviceId) -> math::Vector2<f32> {
+        self.mouse.read().unwrap().raw_movement_of(device)
+    }
+
+    /// Gets the number of raw motion samples received since last frame.
+    #[inline]
+    pub fn mouse_raw_samples(&self) -> u32 {
+        self.mouse.read().unwrap().raw_samples()
+    }
+
+    /// Gets the identifiers of mouse devices which have reported raw motions.
+    #[inline]
+    pub fn mouse_raw_devices(&self) -> Vec<mouse::DeviceId> {
+        self.mouse.read().unwrap().raw_devices()
+    }
 }
 
 impl InputSystemShared {
@@ -480,6 +602,13 @@ impl InputSystemShared {
         self.touchpad.read().unwrap().position(n).map(|v| v * hidpi)
     }
 
+    /// Gets the normalized force of the `n`th touched finger, where 1.0 is the force of
+    /// an average touch.
+    #[inline]
+    pub fn finger_pressure(&self, n: usize) -> Option<f32> {
+        self.touchpad.read().unwrap().pressure(n)
+    }
+
     /// Gets the tap gesture in pixels.
     #[inline]
     pub fn finger_tap(&self) -> touchpad::GestureTap {
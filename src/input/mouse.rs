@@ -4,7 +4,7 @@ use std::time::{Duration, Instant};
 use math;
 use math::MetricSpace;
 
-pub use application::event::MouseButton;
+pub use application::event::{DeviceId, MouseButton};
 
 /// The setup parameters of mouse device.
 ///
@@ -37,6 +37,9 @@ pub struct Mouse {
     last_position: math::Vector2<f32>,
     position: math::Vector2<f32>,
     scrol: math::Vector2<f32>,
+    raw_movement: math::Vector2<f32>,
+    raw_devices: HashMap<DeviceId, math::Vector2<f32>>,
+    raw_samples: u32,
     click_detectors: HashMap<MouseButton, ClickDetector>,
     params: MouseParams,
 }
@@ -50,6 +53,9 @@ impl Mouse {
             last_position: math::Vector2::new(0.0, 0.0),
             position: math::Vector2::new(0.0, 0.0),
             scrol: math::Vector2::new(0.0, 0.0),
+            raw_movement: math::Vector2::new(0.0, 0.0),
+            raw_devices: HashMap::new(),
+            raw_samples: 0,
             click_detectors: HashMap::new(),
             params: params,
         }
@@ -63,6 +69,9 @@ impl Mouse {
         self.last_position = math::Vector2::new(0.0, 0.0);
         self.position = math::Vector2::new(0.0, 0.0);
         self.scrol = math::Vector2::new(0.0, 0.0);
+        self.raw_movement = math::Vector2::new(0.0, 0.0);
+        self.raw_devices.clear();
+        self.raw_samples = 0;
 
         for v in self.click_detectors.values_mut() {
             v.reset();
@@ -75,6 +84,12 @@ impl Mouse {
         self.releases.clear();
         self.scrol = math::Vector2::new(0.0, 0.0);
         self.last_position = self.position;
+        self.raw_movement = math::Vector2::new(0.0, 0.0);
+        self.raw_samples = 0;
+
+        for v in self.raw_devices.values_mut() {
+            *v = math::Vector2::new(0.0, 0.0);
+        }
 
         for v in self.click_detectors.values_mut() {
             v.advance();
@@ -86,6 +101,20 @@ impl Mouse {
         self.position = position.into();
     }
 
+    /// Accumulates the raw relative motion of device. There might be lots of samples
+    /// between two frames with high polling rate mouses.
+    #[inline]
+    pub fn on_raw_move(&mut self, device: DeviceId, delta: (f32, f32)) {
+        let delta: math::Vector2<f32> = delta.into();
+        self.raw_movement += delta;
+        self.raw_samples += 1;
+
+        *self
+            .raw_devices
+            .entry(device)
+            .or_insert_with(|| math::Vector2::new(0.0, 0.0)) += delta;
+    }
+
     #[inline]
     pub fn on_button_pressed(&mut self, button: MouseButton) {
         if !self.downs.contains(&button) {
@@ -170,6 +199,29 @@ impl Mouse {
     pub fn scroll(&self) -> math::Vector2<f32> {
         self.scrol
     }
+
+    #[inline]
+    pub fn raw_movement(&self) -> math::Vector2<f32> {
+        self.raw_movement
+    }
+
+    #[inline]
+    pub fn raw_movement_of(&self, device: DeviceId) -> math::Vector2<f32> {
+        self.raw_devices
+            .get(&device)
+            .cloned()
+            .unwrap_or_else(|| math::Vector2::new(0.0, 0.0))
+    }
+
+    #[inline]
+    pub fn raw_samples(&self) -> u32 {
+        self.raw_samples
+    }
+
+    #[inline]
+    pub fn raw_devices(&self) -> Vec<DeviceId> {
+        self.raw_devices.keys().cloned().collect()
+    }
 }
 
 struct ClickDetector {
@@ -247,3 +299,34 @@ impl ClickDetector {
         self.frame_clicks
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn raw_movement() {
+        let mut mouse = Mouse::new(MouseParams::default());
+        let device = unsafe { DeviceId::dummy() };
+
+        // Samples between two frames are accumulated.
+        mouse.on_raw_move(device, (1.0, 2.0));
+        mouse.on_raw_move(device, (3.0, -1.0));
+        assert_eq!(mouse.raw_movement(), math::Vector2::new(4.0, 1.0));
+        assert_eq!(mouse.raw_movement_of(device), math::Vector2::new(4.0, 1.0));
+        assert_eq!(mouse.raw_samples(), 2);
+        assert_eq!(mouse.raw_devices(), vec![device]);
+
+        // The cursor position is not affected.
+        assert_eq!(mouse.movement(), math::Vector2::new(0.0, 0.0));
+
+        mouse.advance();
+        assert_eq!(mouse.raw_movement(), math::Vector2::new(0.0, 0.0));
+        assert_eq!(mouse.raw_movement_of(device), math::Vector2::new(0.0, 0.0));
+        assert_eq!(mouse.raw_samples(), 0);
+        assert_eq!(mouse.raw_devices(), vec![device]);
+
+        mouse.reset();
+        assert!(mouse.raw_devices().is_empty());
+    }
+}
@@ -101,6 +101,11 @@ impl TouchPad {
         self.record.position(index)
     }
 
+    #[inline]
+    pub fn pressure(&self, index: usize) -> Option<f32> {
+        self.record.pressure(index)
+    }
+
     #[inline]
     pub fn pan(&self) -> GesturePan {
         self.pan
@@ -426,6 +431,14 @@ impl TouchesRecord {
         }
     }
 
+    fn pressure(&self, index: usize) -> Option<f32> {
+        if self.len > index {
+            Some(self.touches[index].1.pressure)
+        } else {
+            None
+        }
+    }
+
     fn update_touch(&mut self, touch: TouchEvent) {
         let mut found = false;
         for i in 0..self.len {
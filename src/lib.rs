@@ -9,6 +9,7 @@
 //! - Run on macOS, Linux, Windows, iOS, Android from the same source;
 //! - Built from the ground up to focus on multi-thread friendly with a work-stealing job scheduler;
 //! - Stateless, layered, multithread render system with OpenGL(ES) 3.0 backends;
+//! - Software mixed audio with spatialized playback;
 //! - Simplified assets workflow and asynchronous data loading from various filesystem;
 //! - Unified interfaces for handling input devices across platforms;
 //! - etc.
@@ -23,11 +24,29 @@
 //! cargo run --example modules_3d_prefab
 //! ```
 
+// The platform layer is built on glutin, OS threads and blocking file systems, none of
+// which exist on the web. Fails early with a readable message instead of the errors of
+// dependencies.
+#[cfg(target_arch = "wasm32")]
+compile_error!("The wasm32 targets are not supported, see `Platform Support` in README.md.");
+
 extern crate crossbeam_deque;
 #[macro_use]
 extern crate cgmath;
+extern crate cpal;
 extern crate gl;
 extern crate glutin;
+extern crate lewton;
+extern crate memmap;
+extern crate minimp3;
+extern crate rand;
+#[cfg(target_os = "ios")]
+#[macro_use]
+extern crate objc;
+#[cfg(unix)]
+extern crate libc;
+#[cfg(windows)]
+extern crate winapi;
 
 #[macro_use]
 extern crate failure;
@@ -54,6 +73,8 @@ pub mod errors;
 #[macro_use]
 pub mod utils;
 pub mod application;
+pub mod audio;
+pub mod capabilities;
 #[macro_use]
 pub mod video;
 pub mod input;
@@ -61,3 +82,5 @@ pub mod math;
 pub mod prelude;
 pub mod res;
 pub mod sched;
+
+pub use capabilities::capabilities;
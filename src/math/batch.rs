@@ -0,0 +1,169 @@
+//! Batched math on contiguous slices, e.g. updating the world matrices of thousands
+//! of objects every frame.
+//!
+//! Every element is computed independently, so the slices could be split into chunks
+//! and processed on the `sched` workers in parallel, while each chunk still goes
+//! through the fast paths in `math::simd`:
+//!
+//! ```rust,ignore
+//! let chunks = positions
+//!     .chunks(N)
+//!     .zip(rotations.chunks(N))
+//!     .zip(scales.chunks(N))
+//!     .zip(matrices.chunks_mut(N));
+//!
+//! sched.scope(|s| {
+//!     for (((positions, rotations), scales), dst) in chunks {
+//!         s.spawn(move |_| batch::compose_trs_batch(positions, rotations, scales, dst));
+//!     }
+//! });
+//! ```
+
+use cgmath::{Matrix4, Quaternion, Vector3};
+
+use super::simd;
+
+/// Transforms the points with affine matrix in place.
+#[inline]
+pub fn transform_points(m: &Matrix4<f32>, points: &mut [Vector3<f32>]) {
+    simd::transform_points_in_place(m, points);
+}
+
+/// Composes the matrices from translations, rotations and uniform scales into `dst`,
+/// the same as `T * R * S` of every element.
+///
+/// # Panics
+///
+/// Panics if the lengths of slices are different.
+pub fn compose_trs_batch(
+    translations: &[Vector3<f32>],
+    rotations: &[Quaternion<f32>],
+    scales: &[f32],
+    dst: &mut [Matrix4<f32>],
+) {
+    assert_eq!(translations.len(), dst.len());
+    assert_eq!(rotations.len(), dst.len());
+    assert_eq!(scales.len(), dst.len());
+
+    simd::compose_trs(dst, |i| (translations[i], rotations[i], scales[i]));
+}
+
+/// Composes the matrices into `dst` like `compose_trs_batch`, but reads the translation,
+/// rotation and uniform scale of the i-th element with `f(i)`. So they could be taken
+/// from arrays of structures directly, without being copied into separate slices.
+#[inline]
+pub fn compose_trs_with<F>(dst: &mut [Matrix4<f32>], f: F)
+where
+    F: Fn(usize) -> (Vector3<f32>, Quaternion<f32>, f32),
+{
+    simd::compose_trs(dst, f);
+}
+
+/// Multiplies the matrices in `lhs` and `rhs` pairwise into `dst`, e.g. the world
+/// matrices of parents and the local matrices of children.
+///
+/// # Panics
+///
+/// Panics if the lengths of slices are different.
+pub fn mul_matrix4_batch(lhs: &[Matrix4<f32>], rhs: &[Matrix4<f32>], dst: &mut [Matrix4<f32>]) {
+    assert_eq!(lhs.len(), dst.len());
+    assert_eq!(rhs.len(), dst.len());
+
+    for (i, m) in dst.iter_mut().enumerate() {
+        *m = simd::mul_matrix4(&lhs[i], &rhs[i]);
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use cgmath::prelude::*;
+    use cgmath::{Deg, Matrix3, Point3};
+
+    fn assert_close(lhs: &[f32], rhs: &[f32]) {
+        assert_eq!(lhs.len(), rhs.len());
+        for (a, b) in lhs.iter().zip(rhs) {
+            assert!(
+                (a - b).abs() <= 1e-4 * a.abs().max(1.0),
+                "{:?} != {:?}",
+                lhs,
+                rhs
+            );
+        }
+    }
+
+    fn trs(i: usize) -> (Vector3<f32>, Quaternion<f32>, f32) {
+        let axis = Vector3::new(1.0, i as f32, -2.0).normalize();
+        let rotation = Quaternion::from_axis_angle(axis, Deg(i as f32 * 25.0));
+        let translation = Vector3::new(i as f32, -3.0, 0.5 * i as f32);
+        (translation, rotation, 0.5 + i as f32)
+    }
+
+    fn matrix(t: Vector3<f32>, r: Quaternion<f32>, s: f32) -> Matrix4<f32> {
+        let m: Matrix3<_> = r.into();
+        let mut m: Matrix4<_> = (m * s).into();
+        m.w = t.extend(1.0);
+        m
+    }
+
+    #[test]
+    fn compose() {
+        let (mut translations, mut rotations, mut scales) = (Vec::new(), Vec::new(), Vec::new());
+        for i in 0..9 {
+            let (t, r, s) = trs(i);
+            translations.push(t);
+            rotations.push(r);
+            scales.push(s);
+        }
+
+        let mut dst = vec![Matrix4::zero(); 9];
+        compose_trs_batch(&translations, &rotations, &scales, &mut dst);
+
+        for (i, v) in dst.iter().enumerate() {
+            let expected = matrix(translations[i], rotations[i], scales[i]);
+            let lhs: &[f32; 16] = v.as_ref();
+            let rhs: &[f32; 16] = expected.as_ref();
+            assert_close(lhs, rhs);
+        }
+
+        let mut matrices = vec![Matrix4::zero(); 9];
+        compose_trs_with(&mut matrices, trs);
+        assert_eq!(matrices, dst);
+
+        let parents = dst.clone();
+        let mut worlds = vec![Matrix4::zero(); 9];
+        mul_matrix4_batch(&parents, &dst, &mut worlds);
+
+        for (i, v) in worlds.iter().enumerate() {
+            let expected = parents[i] * dst[i];
+            let lhs: &[f32; 16] = v.as_ref();
+            let rhs: &[f32; 16] = expected.as_ref();
+            assert_close(lhs, rhs);
+        }
+    }
+
+    #[test]
+    fn points() {
+        let (t, r, s) = trs(3);
+        let m = matrix(t, r, s);
+
+        let src: Vec<_> = (0..5)
+            .map(|i| Vector3::new(i as f32, 1.0, -(i as f32)))
+            .collect();
+
+        let mut points = src.clone();
+        transform_points(&m, &mut points);
+
+        for (p, v) in src.iter().zip(&points) {
+            let expected = m.transform_point(Point3::from_vec(*p));
+            assert_close(&[v.x, v.y, v.z], &[expected.x, expected.y, expected.z]);
+        }
+    }
+
+    #[test]
+    #[should_panic]
+    fn mismatch() {
+        let mut dst = vec![Matrix4::zero(); 2];
+        compose_trs_batch(&[Vector3::zero()], &[Quaternion::one()], &[1.0], &mut dst);
+    }
+}
@@ -0,0 +1,96 @@
+//! The floating point environment of deterministic simulations.
+//!
+//! The basic float operations are exactly rounded by IEEE-754, so the same code on
+//! the same inputs produces the same bits everywhere, as long as every thread runs
+//! with the same rounding mode and denormals are not flushed to zero. Those flags are
+//! per thread and might be changed by drivers or third-party libraries, so the engine
+//! resets them before every fixed update in determinism mode.
+//!
+//! Notes that the transcendental functions like `sin` and `exp` are implemented by the
+//! platform's libm, and their results might still differ between platforms.
+
+/// Resets the floating point environment of current thread to the IEEE-754 defaults:
+/// rounding to nearest, and denormals are preserved.
+#[inline]
+pub fn reset_float_env() {
+    imp::reset_float_env();
+}
+
+/// Returns true if the floating point environment of current thread is the IEEE-754
+/// defaults.
+#[inline]
+pub fn is_default_float_env() -> bool {
+    imp::is_default_float_env()
+}
+
+#[cfg(all(any(target_arch = "x86", target_arch = "x86_64"), target_feature = "sse"))]
+mod imp {
+    #[cfg(target_arch = "x86")]
+    use std::arch::x86::*;
+    #[cfg(target_arch = "x86_64")]
+    use std::arch::x86_64::*;
+
+    // All exceptions masked, rounding to nearest, no flush-to-zero and
+    // denormals-are-zero.
+    const MXCSR_DEFAULT: u32 = 0x1f80;
+    // The exception flags are sticky and do not affect results.
+    const MXCSR_FLAGS: u32 = 0x3f;
+
+    pub fn reset_float_env() {
+        unsafe { _mm_setcsr(MXCSR_DEFAULT) }
+    }
+
+    pub fn is_default_float_env() -> bool {
+        unsafe { (_mm_getcsr() & !MXCSR_FLAGS) == MXCSR_DEFAULT }
+    }
+}
+
+#[cfg(not(all(any(target_arch = "x86", target_arch = "x86_64"), target_feature = "sse")))]
+mod imp {
+    // The other targets do not expose their float control registers in `std::arch`,
+    // and they default to rounding to nearest without flushing denormals.
+    pub fn reset_float_env() {}
+
+    pub fn is_default_float_env() -> bool {
+        true
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    // Divides at runtime, since the constant folding of compiler does not respect the
+    // floating point environment.
+    fn halve(v: f32) -> f32 {
+        let divisor = 2.0f32;
+        v / unsafe { ::std::ptr::read_volatile(&divisor) }
+    }
+
+    #[test]
+    fn reset() {
+        // Flushes denormals to zero.
+        #[cfg(all(
+            any(target_arch = "x86", target_arch = "x86_64"),
+            target_feature = "sse"
+        ))]
+        unsafe {
+            #[cfg(target_arch = "x86")]
+            use std::arch::x86::*;
+            #[cfg(target_arch = "x86_64")]
+            use std::arch::x86_64::*;
+
+            _mm_setcsr(_mm_getcsr() | 0x8040);
+            assert!(!is_default_float_env());
+            assert_eq!(halve(::std::f32::MIN_POSITIVE), 0.0);
+        }
+
+        reset_float_env();
+        assert!(is_default_float_env());
+
+        // The denormals survive.
+        let v = halve(::std::f32::MIN_POSITIVE);
+        assert!(v > 0.0);
+        assert_eq!(v, ::std::f32::MIN_POSITIVE / 2.0);
+    }
+}
@@ -13,3 +13,18 @@ pub use self::frustum::{Frustum, FrustumPoints, Projection};
 
 pub mod color;
 pub use self::color::Color;
+
+pub mod spline;
+pub use self::spline::{Spline, SplineKind};
+
+pub mod quaternion;
+pub use self::quaternion::{decompose, QuaternionExt};
+
+pub mod rect;
+pub use self::rect::{AspectFit, Rect, RectF, RectI, ShelfPacker};
+
+pub mod simd;
+
+pub mod batch;
+
+pub mod float;
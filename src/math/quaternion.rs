@@ -0,0 +1,126 @@
+//! Rotation conveniences which are missing in `cgmath`.
+//!
+//! We use a left handed, y-up coordinate system, where the forward direction is the
+//! positive z-axis. The euler angles are applied in the order of roll (around z-axis),
+//! pitch (around x-axis) and yaw (around y-axis), e.g. `R = Ry(yaw) * Rx(pitch) * Rz(roll)`.
+
+use cgmath::prelude::*;
+use cgmath::{BaseFloat, Matrix3, Matrix4, Quaternion, Rad, Vector3};
+
+/// Extensions of `Quaternion`.
+pub trait QuaternionExt<S> {
+    /// Creates a rotation whose forward vector (positive z-axis) points to `dir`.
+    ///
+    /// Notes that `Rotation::look_at` of `cgmath` returns the inverse, which transforms
+    /// from world space to view space.
+    fn look_rotation(dir: Vector3<S>, up: Vector3<S>) -> Self;
+
+    /// Creates a rotation from euler angles. See the module document for order.
+    fn from_euler<A: Into<Rad<S>>>(pitch: A, yaw: A, roll: A) -> Self;
+
+    /// Decomposes the rotation into euler angles `(pitch, yaw, roll)`, the pitch is
+    /// ranging from -PI/2 to PI/2. See the module document for order.
+    fn to_euler(&self) -> (Rad<S>, Rad<S>, Rad<S>);
+}
+
+impl<S: BaseFloat> QuaternionExt<S> for Quaternion<S> {
+    fn look_rotation(dir: Vector3<S>, up: Vector3<S>) -> Self {
+        let dir = dir.normalize();
+        let side = up.cross(dir).normalize();
+        let up = dir.cross(side).normalize();
+        Matrix3::from_cols(side, up, dir).into()
+    }
+
+    fn from_euler<A: Into<Rad<S>>>(pitch: A, yaw: A, roll: A) -> Self {
+        Quaternion::from_angle_y(yaw) * Quaternion::from_angle_x(pitch)
+            * Quaternion::from_angle_z(roll)
+    }
+
+    fn to_euler(&self) -> (Rad<S>, Rad<S>, Rad<S>) {
+        let m: Matrix3<S> = (*self).into();
+        // The element at row `r` and column `c` is `m[c][r]` in column-major.
+        let sin_pitch = -m[2][1];
+
+        if sin_pitch.abs() >= S::one() - S::from(1e-6).unwrap() {
+            // Gimbal lock, the roll is merged into yaw.
+            let pitch = Rad::asin(sin_pitch.max(-S::one()).min(S::one()));
+            let yaw = Rad::atan2(-m[0][2], m[0][0]);
+            (pitch, yaw, Rad(S::zero()))
+        } else {
+            let pitch = Rad::asin(sin_pitch);
+            let yaw = Rad::atan2(m[2][0], m[2][2]);
+            let roll = Rad::atan2(m[0][1], m[1][1]);
+            (pitch, yaw, roll)
+        }
+    }
+}
+
+/// Decomposes the affine matrix into translation, rotation and scale, which satisfy
+/// `M = T * R * S`. The matrix should not contain any skews.
+pub fn decompose<S: BaseFloat>(m: &Matrix4<S>) -> (Vector3<S>, Quaternion<S>, Vector3<S>) {
+    let translation = m.w.truncate();
+
+    let mut x = m.x.truncate();
+    let y = m.y.truncate();
+    let z = m.z.truncate();
+    let mut scale = Vector3::new(x.magnitude(), y.magnitude(), z.magnitude());
+
+    // Flips one axis if the matrix is mirrored.
+    if x.cross(y).dot(z) < S::zero() {
+        scale.x = -scale.x;
+        x = -x;
+    }
+
+    let rotation = if scale.x.abs() > S::zero() && scale.y > S::zero() && scale.z > S::zero() {
+        let x = x / scale.x.abs();
+        Matrix3::from_cols(x, y / scale.y, z / scale.z).into()
+    } else {
+        Quaternion::one()
+    };
+
+    (translation, rotation, scale)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use cgmath::Deg;
+
+    fn close(lhs: Rad<f32>, rhs: Deg<f32>) -> bool {
+        (lhs - Rad::from(rhs)).0.abs() < 1e-4
+    }
+
+    #[test]
+    fn euler() {
+        let q: Quaternion<f32> = Quaternion::from_euler(Deg(30.0), Deg(45.0), Deg(60.0));
+        let (pitch, yaw, roll) = q.to_euler();
+        assert!(close(pitch, Deg(30.0)));
+        assert!(close(yaw, Deg(45.0)));
+        assert!(close(roll, Deg(60.0)));
+
+        let q: Quaternion<f32> = Quaternion::from_euler(Deg(0.0), Deg(90.0), Deg(0.0));
+        let v = q.rotate_vector(Vector3::new(0.0, 0.0, 1.0));
+        assert!((v - Vector3::new(1.0, 0.0, 0.0)).magnitude() < 1e-4);
+    }
+
+    #[test]
+    fn look_rotation() {
+        let dir = Vector3::new(1.0, 0.0, 1.0);
+        let q: Quaternion<f32> = Quaternion::look_rotation(dir, Vector3::unit_y());
+        let v = q.rotate_vector(Vector3::unit_z());
+        assert!((v - dir.normalize()).magnitude() < 1e-4);
+    }
+
+    #[test]
+    fn decomposition() {
+        let rotation: Quaternion<f32> = Quaternion::from_euler(Deg(10.0), Deg(20.0), Deg(30.0));
+        let m = Matrix4::from_translation(Vector3::new(1.0, 2.0, 3.0))
+            * Matrix4::from(rotation)
+            * Matrix4::from_nonuniform_scale(2.0, 3.0, 4.0);
+
+        let (t, r, s) = decompose(&m);
+        assert!((t - Vector3::new(1.0, 2.0, 3.0)).magnitude() < 1e-4);
+        assert!((s - Vector3::new(2.0, 3.0, 4.0)).magnitude() < 1e-4);
+        assert!(r.dot(rotation).abs() > 1.0 - 1e-4);
+    }
+}
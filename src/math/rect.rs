@@ -0,0 +1,383 @@
+//! Axis-aligned rectangles with layout and UV helpers, which are shared by viewports,
+//! atlases and sprites.
+//!
+//! The `position` is the corner with minimum coordinates, so its the bottom-left corner
+//! in y-up spaces (e.g. UV) and the top-left corner in y-down spaces (e.g. windows).
+
+use std::cmp::Ordering;
+
+use cgmath::{BaseFloat, BaseNum, Point2, Vector2};
+
+use math::Aabb2;
+
+/// An axis-aligned rectangle.
+#[derive(Serialize, Deserialize, Debug, Copy, Clone, PartialEq)]
+pub struct Rect<S> {
+    pub position: Vector2<S>,
+    pub size: Vector2<S>,
+}
+
+pub type RectF = Rect<f32>;
+pub type RectI = Rect<i32>;
+
+impl<S: BaseNum> Rect<S> {
+    #[inline]
+    pub fn new(x: S, y: S, width: S, height: S) -> Self {
+        Rect {
+            position: Vector2::new(x, y),
+            size: Vector2::new(width, height),
+        }
+    }
+
+    /// Creates a rectangle from the corners with minimum and maximum coordinates.
+    #[inline]
+    pub fn from_min_max(min: Vector2<S>, max: Vector2<S>) -> Self {
+        Rect {
+            position: min,
+            size: max - min,
+        }
+    }
+
+    #[inline]
+    pub fn min(&self) -> Vector2<S> {
+        self.position
+    }
+
+    #[inline]
+    pub fn max(&self) -> Vector2<S> {
+        self.position + self.size
+    }
+
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.size.x <= S::zero() || self.size.y <= S::zero()
+    }
+
+    #[inline]
+    pub fn area(&self) -> S {
+        self.size.x * self.size.y
+    }
+
+    /// Returns true if the point is inside, the maximum edges are exclusive.
+    pub fn contains(&self, point: Vector2<S>) -> bool {
+        let hi = self.max();
+        point.x >= self.position.x && point.y >= self.position.y && point.x < hi.x
+            && point.y < hi.y
+    }
+
+    /// Returns true if the `other` is entirely inside.
+    pub fn contains_rect(&self, other: &Self) -> bool {
+        let (hi, other_hi) = (self.max(), other.max());
+        other.position.x >= self.position.x && other.position.y >= self.position.y
+            && other_hi.x <= hi.x && other_hi.y <= hi.y
+    }
+
+    /// Returns true if two rectangles have an overlapped area.
+    #[inline]
+    pub fn intersects(&self, other: &Self) -> bool {
+        self.intersection(other).is_some()
+    }
+
+    /// Gets the overlapped area of two rectangles.
+    pub fn intersection(&self, other: &Self) -> Option<Self> {
+        let (lhs, rhs) = (self.max(), other.max());
+        let lo = Vector2::new(
+            max(self.position.x, other.position.x),
+            max(self.position.y, other.position.y),
+        );
+        let hi = Vector2::new(min(lhs.x, rhs.x), min(lhs.y, rhs.y));
+
+        if lo.x < hi.x && lo.y < hi.y {
+            Some(Rect::from_min_max(lo, hi))
+        } else {
+            None
+        }
+    }
+
+    /// Gets the smallest rectangle which contains both rectangles.
+    pub fn union(&self, other: &Self) -> Self {
+        let (lhs, rhs) = (self.max(), other.max());
+        let lo = Vector2::new(
+            min(self.position.x, other.position.x),
+            min(self.position.y, other.position.y),
+        );
+        let hi = Vector2::new(max(lhs.x, rhs.x), max(lhs.y, rhs.y));
+        Rect::from_min_max(lo, hi)
+    }
+
+    /// Shrinks the rectangle by `v` on each side, or expands if its negative.
+    pub fn inset(&self, v: S) -> Self {
+        let two = S::one() + S::one();
+        Rect {
+            position: self.position + Vector2::new(v, v),
+            size: self.size - Vector2::new(v * two, v * two),
+        }
+    }
+}
+
+impl<S: BaseFloat> Rect<S> {
+    #[inline]
+    pub fn center(&self) -> Vector2<S> {
+        let half = S::one() / (S::one() + S::one());
+        self.position + self.size * half
+    }
+
+    /// Converts the normalized coordinates (ranging from 0 to 1) inside into absolute
+    /// coordinates.
+    #[inline]
+    pub fn denormalize(&self, v: Vector2<S>) -> Vector2<S> {
+        Vector2::new(
+            self.position.x + v.x * self.size.x,
+            self.position.y + v.y * self.size.y,
+        )
+    }
+
+    /// Converts the absolute coordinates into normalized coordinates inside.
+    #[inline]
+    pub fn normalize(&self, v: Vector2<S>) -> Vector2<S> {
+        Vector2::new(
+            (v.x - self.position.x) / self.size.x,
+            (v.y - self.position.y) / self.size.y,
+        )
+    }
+
+    /// Lays out a child rectangle with anchors and offsets, just like the `RectTransform`
+    /// in most UI systems. The anchors are normalized coordinates in `self`, and the
+    /// offsets are added to the anchored corners.
+    ///
+    /// For example, a child stretched to fill with margin `m` is anchored at `(0, 0)` and
+    /// `(1, 1)` with offsets `(m, m)` and `(-m, -m)`.
+    pub fn anchored(
+        &self,
+        anchor_min: Vector2<S>,
+        anchor_max: Vector2<S>,
+        offset_min: Vector2<S>,
+        offset_max: Vector2<S>,
+    ) -> Self {
+        let min = self.denormalize(anchor_min) + offset_min;
+        let max = self.denormalize(anchor_max) + offset_max;
+        Rect::from_min_max(min, max)
+    }
+
+    /// Remaps the UV from this region into `to`, e.g. from the full texture into a
+    /// sub-region of atlas.
+    #[inline]
+    pub fn remap(&self, uv: Vector2<S>, to: &Self) -> Vector2<S> {
+        to.denormalize(self.normalize(uv))
+    }
+
+    /// Gets the UV scale (xy) and offset (zw) which map the full UV space into this
+    /// region, i.e. `uv * scale + offset`.
+    #[inline]
+    pub fn scale_offset(&self) -> [S; 4] {
+        [self.size.x, self.size.y, self.position.x, self.position.y]
+    }
+
+    /// Fits content with `aspect` (width / height) into this rectangle, the result is
+    /// centered.
+    pub fn fit(&self, aspect: S, mode: AspectFit) -> Self {
+        if mode == AspectFit::Stretch || self.size.y <= S::zero() || aspect <= S::zero() {
+            return *self;
+        }
+
+        let container = self.size.x / self.size.y;
+        let wider = aspect > container;
+
+        let size = match (mode, wider) {
+            (AspectFit::Letterbox, true) | (AspectFit::Crop, false) => {
+                Vector2::new(self.size.x, self.size.x / aspect)
+            }
+            _ => Vector2::new(self.size.y * aspect, self.size.y),
+        };
+
+        let half = S::one() / (S::one() + S::one());
+        Rect {
+            position: self.center() - size * half,
+            size: size,
+        }
+    }
+}
+
+impl<S: BaseNum> From<Aabb2<S>> for Rect<S> {
+    fn from(v: Aabb2<S>) -> Self {
+        Rect::from_min_max(
+            Vector2::new(v.min.x, v.min.y),
+            Vector2::new(v.max.x, v.max.y),
+        )
+    }
+}
+
+impl<S: BaseNum> From<Rect<S>> for Aabb2<S> {
+    fn from(v: Rect<S>) -> Self {
+        let hi = v.max();
+        Aabb2::new(
+            Point2::new(v.position.x, v.position.y),
+            Point2::new(hi.x, hi.y),
+        )
+    }
+}
+
+/// How to fit content into a rectangle with different aspect ratio.
+#[derive(Serialize, Deserialize, Debug, Copy, Clone, PartialEq, Eq)]
+pub enum AspectFit {
+    /// Scales non-uniformly to fill the rectangle.
+    Stretch,
+    /// Scales uniformly to fit inside, leaving bars on two sides.
+    Letterbox,
+    /// Scales uniformly to cover the rectangle, the overflowed parts are cropped.
+    Crop,
+}
+
+/// `ShelfPacker` allocates rectangles in rows (shelves) inside a fixed area, e.g. the
+/// glyphs or sprites in an atlas. Its fast and works well for items with similar heights.
+#[derive(Debug, Clone)]
+pub struct ShelfPacker {
+    size: Vector2<u32>,
+    padding: u32,
+    // The (y, height, used width) of shelves, including the paddings after entries.
+    shelves: Vec<(u32, u32, u32)>,
+}
+
+impl ShelfPacker {
+    /// Creates a packer with the area of `size`, and `padding` pixels between items.
+    ///
+    /// Every item is surrounded by `padding` pixels of space on all sides, including the
+    /// borders of area, so the items never bleed into each other (or the items on the
+    /// opposite side when wrapped) under filtering.
+    pub fn new(size: Vector2<u32>, padding: u32) -> Self {
+        ShelfPacker {
+            size: size,
+            padding: padding,
+            shelves: Vec::new(),
+        }
+    }
+
+    /// Allocates a rectangle with size, returns `None` if there is no enough space.
+    pub fn pack(&mut self, size: Vector2<u32>) -> Option<Rect<u32>> {
+        // The paddings before the first shelf and the first entry of every shelf are
+        // reserved, and every entry (or shelf) is followed by paddings.
+        let p = self.padding;
+        let (w, h) = (size.x + p, size.y + p);
+        if p + w > self.size.x {
+            return None;
+        }
+
+        // Finds the shelf which wastes least height.
+        let mut best: Option<usize> = None;
+        for (i, &(_, height, used)) in self.shelves.iter().enumerate() {
+            let fits = height >= h && p + used + w <= self.size.x;
+            if fits && best.map(|v| height < self.shelves[v].1).unwrap_or(true) {
+                best = Some(i);
+            }
+        }
+
+        let index = match best {
+            Some(v) => v,
+            None => {
+                let y = self.shelves.last().map(|v| v.0 + v.1).unwrap_or(p);
+                if y + h > self.size.y {
+                    return None;
+                }
+
+                self.shelves.push((y, h, 0));
+                self.shelves.len() - 1
+            }
+        };
+
+        let (y, _, used) = self.shelves[index];
+        self.shelves[index].2 += w;
+        Some(Rect::new(p + used, y, size.x, size.y))
+    }
+
+    /// Removes all the allocations.
+    #[inline]
+    pub fn clear(&mut self) {
+        self.shelves.clear();
+    }
+}
+
+fn min<S: PartialOrd + Copy>(lhs: S, rhs: S) -> S {
+    match lhs.partial_cmp(&rhs) {
+        Some(Ordering::Less) | Some(Ordering::Equal) | None => lhs,
+        _ => rhs,
+    }
+}
+
+fn max<S: PartialOrd + Copy>(lhs: S, rhs: S) -> S {
+    match lhs.partial_cmp(&rhs) {
+        Some(Ordering::Greater) | Some(Ordering::Equal) | None => lhs,
+        _ => rhs,
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn intersection() {
+        let lhs = RectI::new(0, 0, 10, 10);
+        let rhs = RectI::new(5, 5, 10, 10);
+
+        assert_eq!(lhs.intersection(&rhs), Some(RectI::new(5, 5, 5, 5)));
+        assert_eq!(lhs.union(&rhs), RectI::new(0, 0, 15, 15));
+        assert!(lhs.contains(Vector2::new(9, 9)));
+        assert!(!lhs.contains(Vector2::new(10, 9)));
+        assert!(!lhs.intersects(&RectI::new(10, 0, 5, 5)));
+        assert!(lhs.contains_rect(&lhs.inset(2)));
+    }
+
+    #[test]
+    fn layout() {
+        let screen = RectF::new(0.0, 0.0, 200.0, 100.0);
+
+        let letterbox = screen.fit(1.0, AspectFit::Letterbox);
+        assert_eq!(letterbox, RectF::new(50.0, 0.0, 100.0, 100.0));
+
+        let crop = screen.fit(1.0, AspectFit::Crop);
+        assert_eq!(crop, RectF::new(0.0, -50.0, 200.0, 200.0));
+
+        let child = screen.anchored(
+            Vector2::new(0.0, 0.0),
+            Vector2::new(1.0, 0.5),
+            Vector2::new(10.0, 10.0),
+            Vector2::new(-10.0, -10.0),
+        );
+        assert_eq!(child, RectF::new(10.0, 10.0, 180.0, 30.0));
+
+        let full = RectF::new(0.0, 0.0, 1.0, 1.0);
+        let region = RectF::new(0.5, 0.25, 0.5, 0.25);
+        assert_eq!(full.remap(Vector2::new(0.5, 0.5), &region), Vector2::new(0.75, 0.375));
+        assert_eq!(region.scale_offset(), [0.5, 0.25, 0.5, 0.25]);
+    }
+
+    #[test]
+    fn packing() {
+        let mut packer = ShelfPacker::new(Vector2::new(16, 16), 0);
+        let v1 = packer.pack(Vector2::new(8, 8)).unwrap();
+        let v2 = packer.pack(Vector2::new(8, 4)).unwrap();
+        let v3 = packer.pack(Vector2::new(16, 4)).unwrap();
+        let v4 = packer.pack(Vector2::new(4, 4)).unwrap();
+
+        assert_eq!(v1, Rect::new(0, 0, 8, 8));
+        assert_eq!(v2, Rect::new(8, 0, 8, 4));
+        assert_eq!(v3, Rect::new(0, 8, 16, 4));
+        assert_eq!(v4, Rect::new(0, 12, 4, 4));
+        assert!(packer.pack(Vector2::new(16, 1)).is_none());
+        assert!(packer.pack(Vector2::new(17, 1)).is_none());
+
+        // The items are apart from each other and the borders by paddings.
+        let mut packer = ShelfPacker::new(Vector2::new(16, 16), 1);
+        let v1 = packer.pack(Vector2::new(6, 6)).unwrap();
+        let v2 = packer.pack(Vector2::new(7, 4)).unwrap();
+        let v3 = packer.pack(Vector2::new(14, 2)).unwrap();
+        let v4 = packer.pack(Vector2::new(3, 4)).unwrap();
+
+        assert_eq!(v1, Rect::new(1, 1, 6, 6));
+        assert_eq!(v2, Rect::new(8, 1, 7, 4));
+        assert_eq!(v3, Rect::new(1, 8, 14, 2));
+        assert_eq!(v4, Rect::new(1, 11, 3, 4));
+        assert!(packer.pack(Vector2::new(15, 1)).is_none());
+        assert!(packer.pack(Vector2::new(1, 5)).is_none());
+    }
+}
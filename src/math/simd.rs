@@ -0,0 +1,761 @@
+//! SIMD fast paths of the hottest math in `f32`, e.g. the transforms of points and
+//! bounding boxes and the frustum culling, which are usually math-bound at scale.
+//!
+//! The SSE instructions are used on x86 and x86_64, and the NEON instructions on
+//! aarch64 (e.g. iOS and most of Android devices). The other targets fall back to the
+//! scalar implementations which produce the same results.
+//!
+//! All the transforms here assume the matrices are affine, which is the case for the
+//! model and view matrices. Use `Transform::transform_point` for projections.
+//!
+//! ```rust,ignore
+//! let mv = simd::mul_matrix4(&view_matrix, &model_matrix);
+//! let aabb = simd::transform_aabb(&mv, &aabb);
+//! let visible = simd::contains_aabb(&frustum, &aabb) != PlaneRelation::Out;
+//! ```
+
+use cgmath::{Matrix4, Point3, Quaternion, Vector3};
+use math::{Aabb3, Frustum, Plane, PlaneRelation};
+
+// The translation, rotation and uniform scale of a transform.
+type Trs = (Vector3<f32>, Quaternion<f32>, f32);
+
+/// Multiplies two matrices, it's the same as `a * b`.
+#[inline]
+pub fn mul_matrix4(a: &Matrix4<f32>, b: &Matrix4<f32>) -> Matrix4<f32> {
+    imp::mul_matrix4(a, b)
+}
+
+/// Transforms the point with affine matrix.
+#[inline]
+pub fn transform_point(m: &Matrix4<f32>, p: Point3<f32>) -> Point3<f32> {
+    imp::transform_point(m, p)
+}
+
+/// Transforms the points in `src` with affine matrix into `dst`.
+///
+/// # Panics
+///
+/// Panics if the lengths of `src` and `dst` are different.
+pub fn transform_points(m: &Matrix4<f32>, src: &[Point3<f32>], dst: &mut [Point3<f32>]) {
+    assert_eq!(src.len(), dst.len());
+    imp::transform_points(m, src, dst);
+}
+
+/// Transforms the points stored as vectors with affine matrix in place.
+pub fn transform_points_in_place(m: &Matrix4<f32>, points: &mut [Vector3<f32>]) {
+    imp::transform_points_in_place(m, points);
+}
+
+/// Composes the matrices of `T * R * S` into `dst`, with the translation, rotation and
+/// uniform scale of the i-th element from `f(i)`. Four matrices are composed at a time.
+pub fn compose_trs<F>(dst: &mut [Matrix4<f32>], f: F)
+where
+    F: Fn(usize) -> (Vector3<f32>, Quaternion<f32>, f32),
+{
+    imp::compose_trs(dst, f);
+}
+
+/// Transforms the bounding box with affine matrix, and returns a new conservative
+/// bound. It's the same as `Aabb3::transform` but without transforming the corners
+/// one by one.
+#[inline]
+pub fn transform_aabb(m: &Matrix4<f32>, aabb: &Aabb3<f32>) -> Aabb3<f32> {
+    imp::transform_aabb(m, aabb)
+}
+
+#[inline]
+fn compose_trs_one((t, r, s): Trs) -> Matrix4<f32> {
+    let (x2, y2, z2) = (r.v.x + r.v.x, r.v.y + r.v.y, r.v.z + r.v.z);
+
+    let (xx2, xy2, xz2) = (x2 * r.v.x, y2 * r.v.x, z2 * r.v.x);
+    let (yy2, yz2, zz2) = (y2 * r.v.y, z2 * r.v.y, z2 * r.v.z);
+    let (sx2, sy2, sz2) = (x2 * r.s, y2 * r.s, z2 * r.s);
+
+    Matrix4::new(
+        (1.0 - yy2 - zz2) * s,
+        (xy2 + sz2) * s,
+        (xz2 - sy2) * s,
+        0.0,
+        (xy2 - sz2) * s,
+        (1.0 - xx2 - zz2) * s,
+        (yz2 + sx2) * s,
+        0.0,
+        (xz2 + sy2) * s,
+        (yz2 - sx2) * s,
+        (1.0 - xx2 - yy2) * s,
+        0.0,
+        t.x,
+        t.y,
+        t.z,
+        1.0,
+    )
+}
+
+/// The planes of frustum in the structure-of-arrays layout, which are tested four
+/// at a time. The two extra planes always contain everything.
+struct FrustumPlanes {
+    n: [[[f32; 4]; 3]; 2],
+    d: [[f32; 4]; 2],
+}
+
+impl FrustumPlanes {
+    fn new(frustum: &Frustum<f32>) -> Self {
+        let padding = Plane::from_abcd(0.0, 0.0, 0.0, -1.0);
+        let planes = [
+            frustum.left,
+            frustum.right,
+            frustum.top,
+            frustum.bottom,
+            frustum.near,
+            frustum.far,
+            padding,
+            padding,
+        ];
+
+        let mut v = FrustumPlanes {
+            n: [[[0.0; 4]; 3]; 2],
+            d: [[0.0; 4]; 2],
+        };
+
+        for (i, plane) in planes.iter().enumerate() {
+            v.n[i / 4][0][i % 4] = plane.n.x;
+            v.n[i / 4][1][i % 4] = plane.n.y;
+            v.n[i / 4][2][i % 4] = plane.n.z;
+            v.d[i / 4][i % 4] = plane.d;
+        }
+
+        v
+    }
+}
+
+/// Finds the spatial relation of a bounding box inside the frustum, it's the same as
+/// `Frustum::contains`.
+#[inline]
+pub fn contains_aabb(frustum: &Frustum<f32>, aabb: &Aabb3<f32>) -> PlaneRelation {
+    imp::contains_aabb(&FrustumPlanes::new(frustum), aabb)
+}
+
+/// Finds the spatial relations of bounding boxes inside the frustum into `dst`.
+///
+/// # Panics
+///
+/// Panics if the lengths of `src` and `dst` are different.
+pub fn contains_aabbs(frustum: &Frustum<f32>, src: &[Aabb3<f32>], dst: &mut [PlaneRelation]) {
+    assert_eq!(src.len(), dst.len());
+
+    let planes = FrustumPlanes::new(frustum);
+    for (v, relation) in src.iter().zip(dst.iter_mut()) {
+        *relation = imp::contains_aabb(&planes, v);
+    }
+}
+
+#[cfg(all(any(target_arch = "x86", target_arch = "x86_64"), target_feature = "sse"))]
+mod imp {
+    #[cfg(target_arch = "x86")]
+    use std::arch::x86::*;
+    #[cfg(target_arch = "x86_64")]
+    use std::arch::x86_64::*;
+
+    use cgmath::prelude::*;
+    use cgmath::{Matrix4, Point3, Quaternion, Vector3};
+    use math::{Aabb3, PlaneRelation};
+
+    use super::{compose_trs_one, FrustumPlanes, Trs};
+
+    #[inline]
+    unsafe fn columns(m: &Matrix4<f32>) -> [__m128; 4] {
+        let m: &[f32; 16] = m.as_ref();
+        [
+            _mm_loadu_ps(m.as_ptr()),
+            _mm_loadu_ps(m.as_ptr().offset(4)),
+            _mm_loadu_ps(m.as_ptr().offset(8)),
+            _mm_loadu_ps(m.as_ptr().offset(12)),
+        ]
+    }
+
+    // Computes `c[0] * x + c[1] * y + c[2] * z + c[3] * w`.
+    #[inline]
+    unsafe fn combine(c: &[__m128; 4], x: f32, y: f32, z: f32, w: __m128) -> __m128 {
+        let xy = _mm_add_ps(
+            _mm_mul_ps(c[0], _mm_set1_ps(x)),
+            _mm_mul_ps(c[1], _mm_set1_ps(y)),
+        );
+
+        let zw = _mm_add_ps(_mm_mul_ps(c[2], _mm_set1_ps(z)), _mm_mul_ps(c[3], w));
+        _mm_add_ps(xy, zw)
+    }
+
+    #[inline]
+    unsafe fn to_point3(v: __m128) -> Point3<f32> {
+        let mut dst = [0.0; 4];
+        _mm_storeu_ps(dst.as_mut_ptr(), v);
+        Point3::new(dst[0], dst[1], dst[2])
+    }
+
+    // Gathers a component of four elements into lanes.
+    #[inline]
+    unsafe fn gather(v: &[Trs; 4], f: fn(&Trs) -> f32) -> __m128 {
+        _mm_set_ps(f(&v[3]), f(&v[2]), f(&v[1]), f(&v[0]))
+    }
+
+    #[inline]
+    unsafe fn transpose(a: __m128, b: __m128, c: __m128, d: __m128) -> [__m128; 4] {
+        let ab_lo = _mm_unpacklo_ps(a, b);
+        let cd_lo = _mm_unpacklo_ps(c, d);
+        let ab_hi = _mm_unpackhi_ps(a, b);
+        let cd_hi = _mm_unpackhi_ps(c, d);
+
+        [
+            _mm_movelh_ps(ab_lo, cd_lo),
+            _mm_movehl_ps(cd_lo, ab_lo),
+            _mm_movelh_ps(ab_hi, cd_hi),
+            _mm_movehl_ps(cd_hi, ab_hi),
+        ]
+    }
+
+    pub(super) fn mul_matrix4(a: &Matrix4<f32>, b: &Matrix4<f32>) -> Matrix4<f32> {
+        unsafe {
+            let c = columns(a);
+            let b: &[f32; 16] = b.as_ref();
+
+            let mut m = Matrix4::zero();
+            {
+                let dst: &mut [f32; 16] = m.as_mut();
+                for i in 0..4 {
+                    let w = _mm_set1_ps(b[i * 4 + 3]);
+                    let v = combine(&c, b[i * 4], b[i * 4 + 1], b[i * 4 + 2], w);
+                    _mm_storeu_ps(dst.as_mut_ptr().offset(i as isize * 4), v);
+                }
+            }
+
+            m
+        }
+    }
+
+    #[inline]
+    pub(super) fn transform_point(m: &Matrix4<f32>, p: Point3<f32>) -> Point3<f32> {
+        unsafe {
+            let c = columns(m);
+            to_point3(combine(&c, p.x, p.y, p.z, _mm_set1_ps(1.0)))
+        }
+    }
+
+    pub(super) fn transform_points(
+        m: &Matrix4<f32>,
+        src: &[Point3<f32>],
+        dst: &mut [Point3<f32>],
+    ) {
+        unsafe {
+            let c = columns(m);
+            let one = _mm_set1_ps(1.0);
+
+            for (p, v) in src.iter().zip(dst.iter_mut()) {
+                *v = to_point3(combine(&c, p.x, p.y, p.z, one));
+            }
+        }
+    }
+
+    pub(super) fn transform_points_in_place(m: &Matrix4<f32>, points: &mut [Vector3<f32>]) {
+        unsafe {
+            let c = columns(m);
+            let one = _mm_set1_ps(1.0);
+
+            for v in points.iter_mut() {
+                *v = to_point3(combine(&c, v.x, v.y, v.z, one)).to_vec();
+            }
+        }
+    }
+
+    // The rotation matrices of four quaternions are computed in the lanes, and then
+    // transposed into the columns of every element.
+    pub(super) fn compose_trs<F>(dst: &mut [Matrix4<f32>], f: F)
+    where
+        F: Fn(usize) -> (Vector3<f32>, Quaternion<f32>, f32),
+    {
+        let len = dst.len() - dst.len() % 4;
+        let (head, tail) = dst.split_at_mut(len);
+
+        unsafe {
+            let one = _mm_set1_ps(1.0);
+            let zero = _mm_setzero_ps();
+
+            for (i, chunk) in head.chunks_mut(4).enumerate() {
+                let i = i * 4;
+                let v = [f(i), f(i + 1), f(i + 2), f(i + 3)];
+
+                let x = gather(&v, |v| (v.1).v.x);
+                let y = gather(&v, |v| (v.1).v.y);
+                let z = gather(&v, |v| (v.1).v.z);
+                let w = gather(&v, |v| (v.1).s);
+                let s = gather(&v, |v| v.2);
+
+                let (x2, y2, z2) = (_mm_add_ps(x, x), _mm_add_ps(y, y), _mm_add_ps(z, z));
+                let (xx2, xy2, xz2) = (_mm_mul_ps(x2, x), _mm_mul_ps(y2, x), _mm_mul_ps(z2, x));
+                let (yy2, yz2, zz2) = (_mm_mul_ps(y2, y), _mm_mul_ps(z2, y), _mm_mul_ps(z2, z));
+                let (sx2, sy2, sz2) = (_mm_mul_ps(x2, w), _mm_mul_ps(y2, w), _mm_mul_ps(z2, w));
+
+                let c0 = transpose(
+                    _mm_mul_ps(_mm_sub_ps(_mm_sub_ps(one, yy2), zz2), s),
+                    _mm_mul_ps(_mm_add_ps(xy2, sz2), s),
+                    _mm_mul_ps(_mm_sub_ps(xz2, sy2), s),
+                    zero,
+                );
+
+                let c1 = transpose(
+                    _mm_mul_ps(_mm_sub_ps(xy2, sz2), s),
+                    _mm_mul_ps(_mm_sub_ps(_mm_sub_ps(one, xx2), zz2), s),
+                    _mm_mul_ps(_mm_add_ps(yz2, sx2), s),
+                    zero,
+                );
+
+                let c2 = transpose(
+                    _mm_mul_ps(_mm_add_ps(xz2, sy2), s),
+                    _mm_mul_ps(_mm_sub_ps(yz2, sx2), s),
+                    _mm_mul_ps(_mm_sub_ps(_mm_sub_ps(one, xx2), yy2), s),
+                    zero,
+                );
+
+                for (k, m) in chunk.iter_mut().enumerate() {
+                    {
+                        let dst: &mut [f32; 16] = m.as_mut();
+                        _mm_storeu_ps(dst.as_mut_ptr(), c0[k]);
+                        _mm_storeu_ps(dst.as_mut_ptr().offset(4), c1[k]);
+                        _mm_storeu_ps(dst.as_mut_ptr().offset(8), c2[k]);
+                    }
+
+                    m.w = (v[k].0).extend(1.0);
+                }
+            }
+        }
+
+        for (i, m) in tail.iter_mut().enumerate() {
+            *m = compose_trs_one(f(len + i));
+        }
+    }
+
+    pub(super) fn transform_aabb(m: &Matrix4<f32>, aabb: &Aabb3<f32>) -> Aabb3<f32> {
+        unsafe {
+            let c = columns(m);
+            let center = aabb.center();
+            let extents = aabb.dim() * 0.5;
+
+            let center = combine(&c, center.x, center.y, center.z, _mm_set1_ps(1.0));
+
+            // The extents are projected onto the axes with the absolute values of
+            // matrix, see _Real Time Collision Detection_, p. 86.
+            let sign = _mm_set1_ps(-0.0);
+            let abs = [
+                _mm_andnot_ps(sign, c[0]),
+                _mm_andnot_ps(sign, c[1]),
+                _mm_andnot_ps(sign, c[2]),
+                _mm_setzero_ps(),
+            ];
+
+            let extents = combine(&abs, extents.x, extents.y, extents.z, _mm_setzero_ps());
+
+            Aabb3::new(
+                to_point3(_mm_sub_ps(center, extents)),
+                to_point3(_mm_add_ps(center, extents)),
+            )
+        }
+    }
+
+    pub(super) fn contains_aabb(planes: &FrustumPlanes, aabb: &Aabb3<f32>) -> PlaneRelation {
+        unsafe {
+            let center = aabb.center();
+            let extents = aabb.dim() * 0.5;
+            let sign = _mm_set1_ps(-0.0);
+            let zero = _mm_setzero_ps();
+
+            let (mut out, mut cross) = (0, 0);
+            for i in 0..2 {
+                let nx = _mm_loadu_ps(planes.n[i][0].as_ptr());
+                let ny = _mm_loadu_ps(planes.n[i][1].as_ptr());
+                let nz = _mm_loadu_ps(planes.n[i][2].as_ptr());
+                let d = _mm_loadu_ps(planes.d[i].as_ptr());
+
+                // The signed distance of center, and the projected radius of extents.
+                let s = _mm_add_ps(
+                    _mm_mul_ps(nx, _mm_set1_ps(center.x)),
+                    _mm_add_ps(
+                        _mm_mul_ps(ny, _mm_set1_ps(center.y)),
+                        _mm_mul_ps(nz, _mm_set1_ps(center.z)),
+                    ),
+                );
+                let s = _mm_sub_ps(s, d);
+
+                let r = _mm_add_ps(
+                    _mm_mul_ps(_mm_andnot_ps(sign, nx), _mm_set1_ps(extents.x)),
+                    _mm_add_ps(
+                        _mm_mul_ps(_mm_andnot_ps(sign, ny), _mm_set1_ps(extents.y)),
+                        _mm_mul_ps(_mm_andnot_ps(sign, nz), _mm_set1_ps(extents.z)),
+                    ),
+                );
+
+                out |= _mm_movemask_ps(_mm_cmplt_ps(_mm_add_ps(s, r), zero));
+                cross |= _mm_movemask_ps(_mm_cmple_ps(_mm_sub_ps(s, r), zero));
+            }
+
+            if out != 0 {
+                PlaneRelation::Out
+            } else if cross != 0 {
+                PlaneRelation::Cross
+            } else {
+                PlaneRelation::In
+            }
+        }
+    }
+}
+
+#[cfg(all(target_arch = "aarch64", target_feature = "neon"))]
+mod imp {
+    use std::arch::aarch64::*;
+
+    use cgmath::prelude::*;
+    use cgmath::{Matrix4, Point3, Quaternion, Vector3};
+    use math::{Aabb3, PlaneRelation};
+
+    use super::{compose_trs_one, FrustumPlanes};
+
+    #[inline]
+    unsafe fn columns(m: &Matrix4<f32>) -> [float32x4_t; 4] {
+        let m: &[f32; 16] = m.as_ref();
+        [
+            vld1q_f32(m.as_ptr()),
+            vld1q_f32(m.as_ptr().offset(4)),
+            vld1q_f32(m.as_ptr().offset(8)),
+            vld1q_f32(m.as_ptr().offset(12)),
+        ]
+    }
+
+    // Computes `c[0] * x + c[1] * y + c[2] * z + c[3] * w`. The fused multiply-adds
+    // are avoided to produce the same results as the scalar implementations.
+    #[inline]
+    unsafe fn combine(c: &[float32x4_t; 4], x: f32, y: f32, z: f32, w: float32x4_t) -> float32x4_t {
+        let xy = vaddq_f32(vmulq_n_f32(c[0], x), vmulq_n_f32(c[1], y));
+        let zw = vaddq_f32(vmulq_n_f32(c[2], z), vmulq_f32(c[3], w));
+        vaddq_f32(xy, zw)
+    }
+
+    #[inline]
+    unsafe fn to_point3(v: float32x4_t) -> Point3<f32> {
+        let mut dst = [0.0; 4];
+        vst1q_f32(dst.as_mut_ptr(), v);
+        Point3::new(dst[0], dst[1], dst[2])
+    }
+
+    pub(super) fn mul_matrix4(a: &Matrix4<f32>, b: &Matrix4<f32>) -> Matrix4<f32> {
+        unsafe {
+            let c = columns(a);
+            let b: &[f32; 16] = b.as_ref();
+
+            let mut m = Matrix4::zero();
+            {
+                let dst: &mut [f32; 16] = m.as_mut();
+                for i in 0..4 {
+                    let w = vdupq_n_f32(b[i * 4 + 3]);
+                    let v = combine(&c, b[i * 4], b[i * 4 + 1], b[i * 4 + 2], w);
+                    vst1q_f32(dst.as_mut_ptr().offset(i as isize * 4), v);
+                }
+            }
+
+            m
+        }
+    }
+
+    #[inline]
+    pub(super) fn transform_point(m: &Matrix4<f32>, p: Point3<f32>) -> Point3<f32> {
+        unsafe {
+            let c = columns(m);
+            to_point3(combine(&c, p.x, p.y, p.z, vdupq_n_f32(1.0)))
+        }
+    }
+
+    pub(super) fn transform_points(
+        m: &Matrix4<f32>,
+        src: &[Point3<f32>],
+        dst: &mut [Point3<f32>],
+    ) {
+        unsafe {
+            let c = columns(m);
+            let one = vdupq_n_f32(1.0);
+
+            for (p, v) in src.iter().zip(dst.iter_mut()) {
+                *v = to_point3(combine(&c, p.x, p.y, p.z, one));
+            }
+        }
+    }
+
+    pub(super) fn transform_points_in_place(m: &Matrix4<f32>, points: &mut [Vector3<f32>]) {
+        unsafe {
+            let c = columns(m);
+            let one = vdupq_n_f32(1.0);
+
+            for v in points.iter_mut() {
+                *v = to_point3(combine(&c, v.x, v.y, v.z, one)).to_vec();
+            }
+        }
+    }
+
+    pub(super) fn compose_trs<F>(dst: &mut [Matrix4<f32>], f: F)
+    where
+        F: Fn(usize) -> (Vector3<f32>, Quaternion<f32>, f32),
+    {
+        for (i, m) in dst.iter_mut().enumerate() {
+            *m = compose_trs_one(f(i));
+        }
+    }
+
+    pub(super) fn transform_aabb(m: &Matrix4<f32>, aabb: &Aabb3<f32>) -> Aabb3<f32> {
+        unsafe {
+            let c = columns(m);
+            let center = aabb.center();
+            let extents = aabb.dim() * 0.5;
+
+            let center = combine(&c, center.x, center.y, center.z, vdupq_n_f32(1.0));
+
+            // The extents are projected onto the axes with the absolute values of
+            // matrix, see _Real Time Collision Detection_, p. 86.
+            let zero = vdupq_n_f32(0.0);
+            let abs = [vabsq_f32(c[0]), vabsq_f32(c[1]), vabsq_f32(c[2]), zero];
+            let extents = combine(&abs, extents.x, extents.y, extents.z, zero);
+
+            Aabb3::new(
+                to_point3(vsubq_f32(center, extents)),
+                to_point3(vaddq_f32(center, extents)),
+            )
+        }
+    }
+
+    pub(super) fn contains_aabb(planes: &FrustumPlanes, aabb: &Aabb3<f32>) -> PlaneRelation {
+        unsafe {
+            let center = aabb.center();
+            let extents = aabb.dim() * 0.5;
+            let zero = vdupq_n_f32(0.0);
+
+            let (mut out, mut cross) = (vdupq_n_u32(0), vdupq_n_u32(0));
+            for i in 0..2 {
+                let nx = vld1q_f32(planes.n[i][0].as_ptr());
+                let ny = vld1q_f32(planes.n[i][1].as_ptr());
+                let nz = vld1q_f32(planes.n[i][2].as_ptr());
+                let d = vld1q_f32(planes.d[i].as_ptr());
+
+                // The signed distance of center, and the projected radius of extents.
+                let s = vaddq_f32(
+                    vmulq_n_f32(nx, center.x),
+                    vaddq_f32(vmulq_n_f32(ny, center.y), vmulq_n_f32(nz, center.z)),
+                );
+                let s = vsubq_f32(s, d);
+
+                let r = vaddq_f32(
+                    vmulq_n_f32(vabsq_f32(nx), extents.x),
+                    vaddq_f32(
+                        vmulq_n_f32(vabsq_f32(ny), extents.y),
+                        vmulq_n_f32(vabsq_f32(nz), extents.z),
+                    ),
+                );
+
+                out = vorrq_u32(out, vcltq_f32(vaddq_f32(s, r), zero));
+                cross = vorrq_u32(cross, vcleq_f32(vsubq_f32(s, r), zero));
+            }
+
+            if vmaxvq_u32(out) != 0 {
+                PlaneRelation::Out
+            } else if vmaxvq_u32(cross) != 0 {
+                PlaneRelation::Cross
+            } else {
+                PlaneRelation::In
+            }
+        }
+    }
+}
+
+#[cfg(not(any(
+    all(any(target_arch = "x86", target_arch = "x86_64"), target_feature = "sse"),
+    all(target_arch = "aarch64", target_feature = "neon")
+)))]
+mod imp {
+    use cgmath::prelude::*;
+    use cgmath::{Matrix4, Point3, Quaternion, Vector3};
+    use math::{Aabb3, PlaneRelation};
+
+    use super::{compose_trs_one, FrustumPlanes};
+
+    #[inline]
+    pub(super) fn mul_matrix4(a: &Matrix4<f32>, b: &Matrix4<f32>) -> Matrix4<f32> {
+        a * b
+    }
+
+    #[inline]
+    pub(super) fn transform_point(m: &Matrix4<f32>, p: Point3<f32>) -> Point3<f32> {
+        Point3::from_homogeneous(m * p.to_homogeneous())
+    }
+
+    pub(super) fn transform_points(
+        m: &Matrix4<f32>,
+        src: &[Point3<f32>],
+        dst: &mut [Point3<f32>],
+    ) {
+        for (p, v) in src.iter().zip(dst.iter_mut()) {
+            *v = transform_point(m, *p);
+        }
+    }
+
+    pub(super) fn transform_points_in_place(m: &Matrix4<f32>, points: &mut [Vector3<f32>]) {
+        for v in points.iter_mut() {
+            *v = transform_point(m, Point3::from_vec(*v)).to_vec();
+        }
+    }
+
+    pub(super) fn compose_trs<F>(dst: &mut [Matrix4<f32>], f: F)
+    where
+        F: Fn(usize) -> (Vector3<f32>, Quaternion<f32>, f32),
+    {
+        for (i, m) in dst.iter_mut().enumerate() {
+            *m = compose_trs_one(f(i));
+        }
+    }
+
+    pub(super) fn transform_aabb(m: &Matrix4<f32>, aabb: &Aabb3<f32>) -> Aabb3<f32> {
+        let center = transform_point(m, aabb.center());
+        let extents = aabb.dim() * 0.5;
+
+        // The extents are projected onto the axes with the absolute values of matrix,
+        // see _Real Time Collision Detection_, p. 86.
+        let mut radius = [0.0; 3];
+        for (i, v) in radius.iter_mut().enumerate() {
+            *v = m.x[i].abs() * extents.x + m.y[i].abs() * extents.y + m.z[i].abs() * extents.z;
+        }
+
+        let radius: Vector3<f32> = radius.into();
+        Aabb3::new(center - radius, center + radius)
+    }
+
+    pub(super) fn contains_aabb(planes: &FrustumPlanes, aabb: &Aabb3<f32>) -> PlaneRelation {
+        let center = aabb.center();
+        let extents = aabb.dim() * 0.5;
+
+        let mut relation = PlaneRelation::In;
+        for i in 0..8 {
+            let n = [
+                planes.n[i / 4][0][i % 4],
+                planes.n[i / 4][1][i % 4],
+                planes.n[i / 4][2][i % 4],
+            ];
+
+            let s = n[0] * center.x + n[1] * center.y + n[2] * center.z - planes.d[i / 4][i % 4];
+            let r = n[0].abs() * extents.x + n[1].abs() * extents.y + n[2].abs() * extents.z;
+
+            if s + r < 0.0 {
+                return PlaneRelation::Out;
+            }
+
+            if s - r <= 0.0 {
+                relation = PlaneRelation::Cross;
+            }
+        }
+
+        relation
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use cgmath::prelude::*;
+    use cgmath::{Deg, Quaternion, Vector3};
+    use math::Projection;
+
+    fn affine(position: [f32; 3], angle: f32, scale: f32) -> Matrix4<f32> {
+        let axis = Vector3::new(1.0, 2.0, 3.0).normalize();
+        let rotation = Quaternion::from_axis_angle(axis, Deg(angle));
+        Matrix4::from_translation(position.into())
+            * Matrix4::from(rotation)
+            * Matrix4::from_scale(scale)
+    }
+
+    fn assert_close(lhs: &[f32], rhs: &[f32]) {
+        assert_eq!(lhs.len(), rhs.len());
+        for (a, b) in lhs.iter().zip(rhs) {
+            assert!(
+                (a - b).abs() <= 1e-4 * a.abs().max(1.0),
+                "{:?} != {:?}",
+                lhs,
+                rhs
+            );
+        }
+    }
+
+    #[test]
+    fn matrix() {
+        let a = affine([1.0, -2.0, 3.0], 30.0, 2.0);
+        let b = affine([-5.0, 0.5, 4.0], 75.0, 0.5);
+
+        let (lhs, rhs) = (mul_matrix4(&a, &b), a * b);
+        let lhs: &[f32; 16] = lhs.as_ref();
+        let rhs: &[f32; 16] = rhs.as_ref();
+        assert_close(lhs, rhs);
+    }
+
+    #[test]
+    fn points() {
+        let m = affine([1.0, -2.0, 3.0], 30.0, 2.0);
+        let src: Vec<_> = (0..7)
+            .map(|i| Point3::new(i as f32, -2.0 * i as f32, 0.5))
+            .collect();
+
+        let mut dst = vec![Point3::origin(); src.len()];
+        transform_points(&m, &src, &mut dst);
+
+        for (p, v) in src.iter().zip(&dst) {
+            let expected = m.transform_point(*p);
+            assert_close(&[v.x, v.y, v.z], &[expected.x, expected.y, expected.z]);
+
+            let v = transform_point(&m, *p);
+            assert_close(&[v.x, v.y, v.z], &[expected.x, expected.y, expected.z]);
+        }
+    }
+
+    #[test]
+    fn aabb() {
+        let m = affine([1.0, -2.0, 3.0], 30.0, 2.0);
+        let aabb = Aabb3::new(Point3::new(-1.0, 0.0, 2.0), Point3::new(3.0, 1.0, 5.0));
+
+        let lhs = transform_aabb(&m, &aabb);
+        let rhs = aabb.transform(&m);
+        assert_close(
+            &[
+                lhs.min.x, lhs.min.y, lhs.min.z, lhs.max.x, lhs.max.y, lhs.max.z,
+            ],
+            &[
+                rhs.min.x, rhs.min.y, rhs.min.z, rhs.max.x, rhs.max.y, rhs.max.z,
+            ],
+        );
+    }
+
+    #[test]
+    fn culling() {
+        let frustum = Frustum::new(Projection::perspective(Deg(60.0).into(), 1.5, 0.1, 100.0));
+
+        let mut aabbs = Vec::new();
+        for x in -10..10 {
+            for z in -2..20 {
+                let min = Point3::new(x as f32 * 5.0, 0.5, z as f32 * 5.0);
+                aabbs.push(Aabb3::new(min, min + Vector3::new(2.0, 1.0, 3.0)));
+            }
+        }
+
+        let mut relations = vec![PlaneRelation::Out; aabbs.len()];
+        contains_aabbs(&frustum, &aabbs, &mut relations);
+
+        for (v, relation) in aabbs.iter().zip(&relations) {
+            assert_eq!(*relation, frustum.contains(v));
+            assert_eq!(contains_aabb(&frustum, v), frustum.contains(v));
+        }
+
+        assert!(relations.contains(&PlaneRelation::In));
+        assert!(relations.contains(&PlaneRelation::Cross));
+        assert!(relations.contains(&PlaneRelation::Out));
+    }
+}
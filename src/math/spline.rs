@@ -0,0 +1,298 @@
+//! Piecewise cubic splines with arc-length parameterization.
+//!
+//! The splines are evaluated with parameter `t` ranging from 0 to 1, which is not
+//! proportional to the distance along the curve. The `*_at_distance` methods use a
+//! precomputed arc-length table, so its possible to move along the spline at constant
+//! speed, e.g. camera rails or moving platforms.
+
+use cgmath::prelude::*;
+use cgmath::Vector3;
+
+use errors::*;
+
+/// The number of samples per segment in the arc-length table.
+const SAMPLES_PER_SEGMENT: usize = 16;
+
+/// The basis of splines.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum SplineKind {
+    /// Passes through all the control points.
+    CatmullRom,
+    /// Cubic bezier curves joined end to end, the number of control points must be
+    /// `3 * segments + 1`. Passes through every third control points.
+    Bezier,
+    /// Uniform cubic b-spline, which is smooth but passes through none of the control
+    /// points in general.
+    BSpline,
+}
+
+impl SplineKind {
+    fn weights(&self, u: f32) -> [f32; 4] {
+        let u2 = u * u;
+        let u3 = u2 * u;
+        let v = 1.0 - u;
+
+        match *self {
+            SplineKind::CatmullRom => [
+                0.5 * (-u + 2.0 * u2 - u3),
+                0.5 * (2.0 - 5.0 * u2 + 3.0 * u3),
+                0.5 * (u + 4.0 * u2 - 3.0 * u3),
+                0.5 * (-u2 + u3),
+            ],
+            SplineKind::Bezier => [v * v * v, 3.0 * v * v * u, 3.0 * v * u2, u3],
+            SplineKind::BSpline => [
+                v * v * v / 6.0,
+                (3.0 * u3 - 6.0 * u2 + 4.0) / 6.0,
+                (-3.0 * u3 + 3.0 * u2 + 3.0 * u + 1.0) / 6.0,
+                u3 / 6.0,
+            ],
+        }
+    }
+
+    fn derivative_weights(&self, u: f32) -> [f32; 4] {
+        let u2 = u * u;
+        let v = 1.0 - u;
+
+        match *self {
+            SplineKind::CatmullRom => [
+                0.5 * (-1.0 + 4.0 * u - 3.0 * u2),
+                0.5 * (-10.0 * u + 9.0 * u2),
+                0.5 * (1.0 + 8.0 * u - 9.0 * u2),
+                0.5 * (-2.0 * u + 3.0 * u2),
+            ],
+            SplineKind::Bezier => [
+                -3.0 * v * v,
+                3.0 * v * v - 6.0 * v * u,
+                6.0 * v * u - 3.0 * u2,
+                3.0 * u2,
+            ],
+            SplineKind::BSpline => [
+                -0.5 * v * v,
+                0.5 * (3.0 * u2 - 4.0 * u),
+                0.5 * (-3.0 * u2 + 2.0 * u + 1.0),
+                0.5 * u2,
+            ],
+        }
+    }
+}
+
+/// A piecewise cubic spline in 3d space.
+#[derive(Debug, Clone)]
+pub struct Spline {
+    kind: SplineKind,
+    points: Vec<Vector3<f32>>,
+    // The accumulated distances of uniform samples of parameter.
+    lengths: Vec<f32>,
+}
+
+impl Spline {
+    /// Creates a spline with control points, fails if there are not enough points for
+    /// at least one segment.
+    pub fn new(kind: SplineKind, points: Vec<Vector3<f32>>) -> Result<Self> {
+        let valid = match kind {
+            SplineKind::CatmullRom => points.len() >= 2,
+            SplineKind::Bezier => points.len() >= 4 && (points.len() - 1) % 3 == 0,
+            SplineKind::BSpline => points.len() >= 4,
+        };
+
+        if !valid {
+            bail!("{} control points is invalid for {:?}.", points.len(), kind);
+        }
+
+        let mut spline = Spline {
+            kind: kind,
+            points: points,
+            lengths: Vec::new(),
+        };
+
+        spline.build_lengths();
+        Ok(spline)
+    }
+
+    #[inline]
+    pub fn catmull_rom(points: Vec<Vector3<f32>>) -> Result<Self> {
+        Spline::new(SplineKind::CatmullRom, points)
+    }
+
+    #[inline]
+    pub fn bezier(points: Vec<Vector3<f32>>) -> Result<Self> {
+        Spline::new(SplineKind::Bezier, points)
+    }
+
+    #[inline]
+    pub fn bspline(points: Vec<Vector3<f32>>) -> Result<Self> {
+        Spline::new(SplineKind::BSpline, points)
+    }
+
+    #[inline]
+    pub fn kind(&self) -> SplineKind {
+        self.kind
+    }
+
+    #[inline]
+    pub fn points(&self) -> &[Vector3<f32>] {
+        &self.points
+    }
+
+    /// Gets the number of cubic segments.
+    pub fn segments(&self) -> usize {
+        match self.kind {
+            SplineKind::CatmullRom => self.points.len() - 1,
+            SplineKind::Bezier => (self.points.len() - 1) / 3,
+            SplineKind::BSpline => self.points.len() - 3,
+        }
+    }
+
+    /// Gets the approximated length of spline.
+    #[inline]
+    pub fn length(&self) -> f32 {
+        *self.lengths.last().unwrap()
+    }
+
+    /// Evaluates the position at parameter `t`, ranging from 0 to 1.
+    pub fn evaluate(&self, t: f32) -> Vector3<f32> {
+        let (segment, u) = self.locate(t);
+        let weights = self.kind.weights(u);
+        self.combine(segment, &weights)
+    }
+
+    /// Evaluates the normalized tangent at parameter `t`, ranging from 0 to 1.
+    pub fn tangent(&self, t: f32) -> Vector3<f32> {
+        let (segment, u) = self.locate(t);
+        let weights = self.kind.derivative_weights(u);
+        let v = self.combine(segment, &weights);
+
+        if v.magnitude2() > 0.0 {
+            v.normalize()
+        } else {
+            v
+        }
+    }
+
+    /// Gets the parameter at `distance` along the spline, the distance is clamped into
+    /// `[0, length]`.
+    pub fn parameter_at_distance(&self, distance: f32) -> f32 {
+        let distance = distance.max(0.0).min(self.length());
+
+        // Finds the last sample whose distance is not greater than `distance`.
+        let (mut lo, mut hi) = (0, self.lengths.len() - 1);
+        while hi - lo > 1 {
+            let mid = (lo + hi) / 2;
+            if self.lengths[mid] <= distance {
+                lo = mid;
+            } else {
+                hi = mid;
+            }
+        }
+
+        let span = self.lengths[hi] - self.lengths[lo];
+        let frac = if span > 0.0 {
+            (distance - self.lengths[lo]) / span
+        } else {
+            0.0
+        };
+
+        (lo as f32 + frac) / (self.lengths.len() - 1) as f32
+    }
+
+    /// Evaluates the position at `distance` along the spline.
+    #[inline]
+    pub fn evaluate_at_distance(&self, distance: f32) -> Vector3<f32> {
+        self.evaluate(self.parameter_at_distance(distance))
+    }
+
+    /// Evaluates the normalized tangent at `distance` along the spline.
+    #[inline]
+    pub fn tangent_at_distance(&self, distance: f32) -> Vector3<f32> {
+        self.tangent(self.parameter_at_distance(distance))
+    }
+
+    fn locate(&self, t: f32) -> (usize, f32) {
+        let segments = self.segments();
+        let t = t.max(0.0).min(1.0) * segments as f32;
+        let segment = (t as usize).min(segments - 1);
+        (segment, t - segment as f32)
+    }
+
+    fn control_point(&self, index: isize) -> Vector3<f32> {
+        // The end points are duplicated for the first and last segments of catmull-rom.
+        let index = index.max(0).min(self.points.len() as isize - 1);
+        self.points[index as usize]
+    }
+
+    fn combine(&self, segment: usize, weights: &[f32; 4]) -> Vector3<f32> {
+        let first = match self.kind {
+            SplineKind::CatmullRom => segment as isize - 1,
+            SplineKind::Bezier => segment as isize * 3,
+            SplineKind::BSpline => segment as isize,
+        };
+
+        let mut v = Vector3::new(0.0, 0.0, 0.0);
+        for (i, &w) in weights.iter().enumerate() {
+            v += self.control_point(first + i as isize) * w;
+        }
+
+        v
+    }
+
+    fn build_lengths(&mut self) {
+        let samples = self.segments() * SAMPLES_PER_SEGMENT;
+        let mut lengths = Vec::with_capacity(samples + 1);
+        let mut last = self.evaluate(0.0);
+        let mut total = 0.0;
+        lengths.push(0.0);
+
+        for i in 1..(samples + 1) {
+            let v = self.evaluate(i as f32 / samples as f32);
+            total += (v - last).magnitude();
+            lengths.push(total);
+            last = v;
+        }
+
+        self.lengths = lengths;
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn close(lhs: Vector3<f32>, rhs: Vector3<f32>) -> bool {
+        (lhs - rhs).magnitude() < 1e-3
+    }
+
+    #[test]
+    fn catmull_rom() {
+        let points = vec![
+            Vector3::new(0.0, 0.0, 0.0),
+            Vector3::new(1.0, 1.0, 0.0),
+            Vector3::new(2.0, 0.0, 0.0),
+        ];
+
+        let spline = Spline::catmull_rom(points).unwrap();
+        assert_eq!(spline.segments(), 2);
+        assert!(close(spline.evaluate(0.0), Vector3::new(0.0, 0.0, 0.0)));
+        assert!(close(spline.evaluate(0.5), Vector3::new(1.0, 1.0, 0.0)));
+        assert!(close(spline.evaluate(1.0), Vector3::new(2.0, 0.0, 0.0)));
+        assert!(close(spline.tangent(0.5), Vector3::new(1.0, 0.0, 0.0)));
+    }
+
+    #[test]
+    fn arc_length() {
+        let points = vec![
+            Vector3::new(0.0, 0.0, 0.0),
+            Vector3::new(4.0, 0.0, 0.0),
+            Vector3::new(6.0, 0.0, 0.0),
+            Vector3::new(10.0, 0.0, 0.0),
+        ];
+
+        let spline = Spline::bezier(points).unwrap();
+        assert!((spline.length() - 10.0).abs() < 1e-3);
+        assert!(close(spline.evaluate_at_distance(5.0), Vector3::new(5.0, 0.0, 0.0)));
+        assert!(close(spline.evaluate_at_distance(20.0), Vector3::new(10.0, 0.0, 0.0)));
+        assert!(close(spline.tangent_at_distance(3.0), Vector3::new(1.0, 0.0, 0.0)));
+
+        assert!(Spline::bezier(vec![Vector3::new(0.0, 0.0, 0.0); 5]).is_err());
+        assert!(Spline::bspline(vec![Vector3::new(0.0, 0.0, 0.0); 4]).is_ok());
+    }
+}
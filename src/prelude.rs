@@ -1,10 +1,13 @@
+pub use capabilities::{capabilities, Capabilities, Platform};
 pub use math;
 pub use math::prelude::{Angle, EuclideanSpace, InnerSpace, Matrix, One, SquareMatrix, Zero};
+pub use math::QuaternionExt;
 
 pub use application;
 pub use application::{event, time};
 pub use application::{Application, Context, Engine, FrameInfo, Settings};
 
+pub use audio::prelude::*;
 pub use input::prelude::*;
 pub use res::prelude::*;
 pub use video::prelude::*;
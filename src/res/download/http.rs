@@ -0,0 +1,376 @@
+//! A minimal HTTP/1.1 client which supports the range requests. Only plain `http` is
+//! supported, the contents should be authenticated with checksums.
+
+use std::cmp;
+use std::fmt;
+use std::io::{self, BufRead, BufReader, Read, Write};
+use std::net::{TcpStream, ToSocketAddrs};
+use std::time::Duration;
+
+use errors::*;
+
+const MAX_REDIRECTS: usize = 5;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Url {
+    pub host: String,
+    pub port: u16,
+    pub path: String,
+}
+
+impl Url {
+    pub fn parse(url: &str) -> Result<Url> {
+        if url.starts_with("https://") {
+            bail!("[HTTP] Url {:?} is not supported, only plain http is available.", url);
+        }
+
+        if !url.starts_with("http://") {
+            bail!("[HTTP] Url {:?} is invalid.", url);
+        }
+
+        let rest = &url["http://".len()..];
+        let (authority, path) = match rest.find('/') {
+            Some(i) => (&rest[..i], &rest[i..]),
+            None => (rest, "/"),
+        };
+
+        // The IPv6 addresses are enclosed in brackets, e.g. `[::1]:8080`.
+        let (host, port) = if authority.starts_with('[') {
+            match authority.find(']') {
+                Some(i) => match &authority[i + 1..] {
+                    "" => (&authority[1..i], 80),
+                    v if v.starts_with(':') => (&authority[1..i], v[1..].parse()?),
+                    _ => bail!("[HTTP] Url {:?} is invalid.", url),
+                },
+                None => bail!("[HTTP] Url {:?} is invalid.", url),
+            }
+        } else {
+            match authority.rfind(':') {
+                Some(i) => (&authority[..i], authority[i + 1..].parse()?),
+                None => (authority, 80),
+            }
+        };
+
+        if host.is_empty() {
+            bail!("[HTTP] Url {:?} has no host.", url);
+        }
+
+        Ok(Url {
+            host: host.to_owned(),
+            port: port,
+            path: path.to_owned(),
+        })
+    }
+
+    /// Resolves the location of redirection.
+    pub fn join(&self, location: &str) -> Result<Url> {
+        if location.contains("://") {
+            return Url::parse(location);
+        }
+
+        let mut url = self.clone();
+        if location.starts_with('/') {
+            url.path = location.to_owned();
+        } else {
+            let dir = match self.path.rfind('/') {
+                Some(i) => &self.path[..i + 1],
+                None => "/",
+            };
+
+            url.path = format!("{}{}", dir, location);
+        }
+
+        Ok(url)
+    }
+
+    /// Gets the host and port which are sent with `Host` header, the default port is
+    /// omitted.
+    pub fn authority(&self) -> String {
+        let host = if self.host.contains(':') {
+            format!("[{}]", self.host)
+        } else {
+            self.host.clone()
+        };
+
+        if self.port == 80 {
+            host
+        } else {
+            format!("{}:{}", host, self.port)
+        }
+    }
+}
+
+impl fmt::Display for Url {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "http://{}{}", self.authority(), self.path)
+    }
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+enum Body {
+    /// The remaining bytes of body.
+    Length(u64),
+    /// The remaining bytes of current chunk, zero if the size of next chunk should be
+    /// read.
+    Chunked(u64),
+    /// The body ends when the connection is closed.
+    Close,
+}
+
+/// The response whose body is read on demand.
+pub struct Response {
+    pub status: u16,
+    headers: Vec<(String, String)>,
+    body: Body,
+    reader: BufReader<TcpStream>,
+}
+
+impl Response {
+    /// Gets the value of header, the name is case insensitive.
+    pub fn header(&self, name: &str) -> Option<&str> {
+        self.headers
+            .iter()
+            .find(|v| v.0.eq_ignore_ascii_case(name))
+            .map(|v| v.1.as_str())
+    }
+
+    pub fn content_length(&self) -> Option<u64> {
+        self.header("Content-Length").and_then(|v| v.parse().ok())
+    }
+
+    /// Gets the total size of resource, which is parsed from `Content-Range` of partial
+    /// contents, e.g. `bytes 100-999/1000`.
+    pub fn total(&self) -> Option<u64> {
+        if self.status != 206 {
+            return self.content_length();
+        }
+
+        self.header("Content-Range")
+            .and_then(|v| v.rsplit('/').next())
+            .and_then(|v| v.trim().parse().ok())
+    }
+
+    /// Gets the first byte position of partial contents, which is parsed from
+    /// `Content-Range`, e.g. `100` of `bytes 100-999/1000`.
+    pub fn range_start(&self) -> Option<u64> {
+        self.header("Content-Range")
+            .and_then(|v| v.trim().trim_left_matches("bytes").split('-').next())
+            .and_then(|v| v.trim().parse().ok())
+    }
+}
+
+impl Read for Response {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        if buf.is_empty() {
+            return Ok(0);
+        }
+
+        let body = self.body;
+        let limit = match body {
+            Body::Length(v) => v,
+            Body::Chunked(0) => {
+                let size = chunk_size(&mut self.reader)?;
+                if size == 0 {
+                    // Skips the trailers.
+                    while read_line(&mut self.reader)?.is_some() {}
+                    self.body = Body::Length(0);
+                    return Ok(0);
+                }
+
+                size
+            }
+            Body::Chunked(v) => v,
+            Body::Close => buf.len() as u64,
+        };
+
+        let max = cmp::min(buf.len() as u64, limit) as usize;
+        if max == 0 {
+            return Ok(0);
+        }
+
+        let n = self.reader.read(&mut buf[..max])?;
+        if n == 0 && self.body != Body::Close {
+            let err = "The connection is closed before the end of body.";
+            return Err(io::Error::new(io::ErrorKind::UnexpectedEof, err));
+        }
+
+        self.body = match body {
+            Body::Length(_) => Body::Length(limit - n as u64),
+            Body::Chunked(_) => {
+                let remaining = limit - n as u64;
+                if remaining == 0 {
+                    // Every chunk is followed by CRLF.
+                    read_line(&mut self.reader)?;
+                }
+
+                Body::Chunked(remaining)
+            }
+            Body::Close => Body::Close,
+        };
+
+        Ok(n)
+    }
+}
+
+/// Sends a GET request, the contents are requested from `offset` with `Range` header.
+/// The redirections are followed.
+pub fn get(url: &Url, offset: u64, timeout: Duration) -> Result<Response> {
+    let mut url = url.clone();
+    for _ in 0..MAX_REDIRECTS {
+        let response = request(&url, offset, timeout)?;
+        match response.status {
+            301 | 302 | 303 | 307 | 308 => {
+                url = match response.header("Location") {
+                    Some(v) => url.join(v)?,
+                    None => bail!("[HTTP] Redirection without location from {}.", url),
+                };
+            }
+            _ => return Ok(response),
+        }
+    }
+
+    bail!("[HTTP] Too many redirections from {}.", url);
+}
+
+// Connects to the resolved addresses in order, each one with `timeout`.
+fn connect(url: &Url, timeout: Duration) -> Result<TcpStream> {
+    let mut last = None;
+    for addr in (url.host.as_str(), url.port).to_socket_addrs()? {
+        match TcpStream::connect_timeout(&addr, timeout) {
+            Ok(stream) => return Ok(stream),
+            Err(err) => last = Some(err),
+        }
+    }
+
+    match last {
+        Some(err) => Err(err.into()),
+        None => bail!("[HTTP] Could not resolve the host of {}.", url),
+    }
+}
+
+fn request(url: &Url, offset: u64, timeout: Duration) -> Result<Response> {
+    let stream = connect(url, timeout)?;
+    stream.set_read_timeout(Some(timeout))?;
+    stream.set_write_timeout(Some(timeout))?;
+
+    let mut head = format!(
+        "GET {} HTTP/1.1\r\nHost: {}\r\nConnection: close\r\nAccept-Encoding: identity\r\n",
+        url.path,
+        url.authority()
+    );
+
+    if offset > 0 {
+        head.push_str(&format!("Range: bytes={}-\r\n", offset));
+    }
+
+    head.push_str("\r\n");
+    (&stream).write_all(head.as_bytes())?;
+
+    let mut reader = BufReader::new(stream);
+    // e.g. `HTTP/1.1 206 Partial Content`.
+    let line = read_line(&mut reader)?.unwrap_or_default();
+    let status = if line.starts_with("HTTP/") {
+        line.split_whitespace().nth(1).and_then(|v| v.parse().ok())
+    } else {
+        None
+    };
+
+    let status: u16 = match status {
+        Some(v) => v,
+        None => bail!("[HTTP] Malformed response from {}.", url),
+    };
+
+    let mut headers = Vec::new();
+    while let Some(line) = read_line(&mut reader)? {
+        if let Some(i) = line.find(':') {
+            let value = line[i + 1..].trim().to_owned();
+            headers.push((line[..i].trim().to_owned(), value));
+        }
+    }
+
+    let mut response = Response {
+        status: status,
+        headers: headers,
+        body: Body::Close,
+        reader: reader,
+    };
+
+    let chunked = response
+        .header("Transfer-Encoding")
+        .map(|v| v.eq_ignore_ascii_case("chunked"))
+        .unwrap_or(false);
+
+    response.body = if status == 204 || status == 304 || status / 100 == 1 {
+        Body::Length(0)
+    } else if chunked {
+        Body::Chunked(0)
+    } else if let Some(len) = response.content_length() {
+        Body::Length(len)
+    } else {
+        Body::Close
+    };
+
+    Ok(response)
+}
+
+// Reads a line without CRLF, returns `None` at the empty line or the end of stream.
+fn read_line<R: BufRead>(reader: &mut R) -> io::Result<Option<String>> {
+    let mut line = String::new();
+    if reader.read_line(&mut line)? == 0 {
+        return Ok(None);
+    }
+
+    let line = line.trim_right_matches(|c| c == '\r' || c == '\n');
+    if line.is_empty() {
+        Ok(None)
+    } else {
+        Ok(Some(line.to_owned()))
+    }
+}
+
+fn chunk_size<R: BufRead>(reader: &mut R) -> io::Result<u64> {
+    let line = read_line(reader)?.unwrap_or_default();
+    let size = line.split(';').next().unwrap_or("").trim();
+    u64::from_str_radix(size, 16).map_err(|_| {
+        let err = format!("Malformed chunk size {:?}.", line);
+        io::Error::new(io::ErrorKind::InvalidData, err)
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn url() {
+        let url = Url::parse("http://cdn.example.com:8080/bundles/1.0.patch").unwrap();
+        assert_eq!(url.host, "cdn.example.com");
+        assert_eq!(url.port, 8080);
+        assert_eq!(url.path, "/bundles/1.0.patch");
+        assert_eq!(url.to_string(), "http://cdn.example.com:8080/bundles/1.0.patch");
+        assert_eq!(url.authority(), "cdn.example.com:8080");
+
+        let url = Url::parse("http://cdn.example.com").unwrap();
+        assert_eq!((url.port, url.path.as_str()), (80, "/"));
+        assert_eq!(url.authority(), "cdn.example.com");
+
+        let url = Url::parse("http://[::1]:8080/file.bin").unwrap();
+        assert_eq!((url.host.as_str(), url.port), ("::1", 8080));
+        assert_eq!(url.authority(), "[::1]:8080");
+        assert_eq!(url.to_string(), "http://[::1]:8080/file.bin");
+
+        let url = Url::parse("http://[fe80::1]/").unwrap();
+        assert_eq!((url.host.as_str(), url.port), ("fe80::1", 80));
+        assert!(Url::parse("http://[::1/").is_err());
+        assert!(Url::parse("http://[::1]x/").is_err());
+
+        assert!(Url::parse("https://cdn.example.com/").is_err());
+        assert!(Url::parse("ftp://cdn.example.com/").is_err());
+        assert!(Url::parse("http://:80/").is_err());
+
+        let url = Url::parse("http://cdn.example.com/bundles/1.0.patch").unwrap();
+        assert_eq!(url.join("/mirror/a").unwrap().path, "/mirror/a");
+        assert_eq!(url.join("1.1.patch").unwrap().path, "/bundles/1.1.patch");
+        assert_eq!(url.join("http://mirror/").unwrap().host, "mirror");
+    }
+}
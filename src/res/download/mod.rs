@@ -0,0 +1,735 @@
+//! Resumable downloads of the contents which are streamed after installation, e.g. the
+//! asset bundles and their patches.
+//!
+//! The `DownloadManager` transfers files over HTTP on its own threads, with limited number
+//! of parallel connections and optional bandwidth throttling. The interrupted transfers
+//! are kept as `<path>.download`, and resumed with range requests the next time the same
+//! file is requested.
+//!
+//! ```rust,ignore
+//! let downloads = DownloadManager::new(DownloadParams::default());
+//!
+//! let mut request = DownloadRequest::new("http://cdn.example.com/1.0-1.1.patch", path);
+//! request.checksum = Some(0x8a3f_12c4_9e07_55d1);
+//! request.progress = Some(Box::new(|received, total| info!("{} / {:?}", received, total)));
+//!
+//! downloads.download(request, |result| match result {
+//!     Ok(path) => info!("Downloaded {:?}.", path),
+//!     Err(err) => warn!("{}", err),
+//! });
+//!
+//! // Every frame, the callbacks are delivered on the main thread.
+//! downloads.advance();
+//! let group = downloads.progress();
+//! ```
+//!
+//! The checksum is the `res::patch::checksum` of the whole file, which is computed while
+//! the contents are being written, so the downloaded files are never loaded into memory.
+//!
+//! The `res` module has no progress API of loading groups, so the progress of downloads
+//! is reported with `DownloadProgress` of its own, which could be merged into the
+//! loading screens with the progress of other resources.
+
+pub mod http;
+
+use std::collections::VecDeque;
+use std::fs;
+use std::io::{Read, Write};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Condvar, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use errors::*;
+use res::patch;
+
+/// The callback of progress, which is called with the received bytes and the total size
+/// of file if its known.
+pub type ProgressCallback = FnMut(u64, Option<u64>) + Send;
+
+/// The setup parameters of `DownloadManager`.
+#[derive(Debug, Clone, Copy)]
+pub struct DownloadParams {
+    /// The maximum number of parallel connections.
+    pub max_connections: u32,
+    /// The maximum bytes per second of all the transfers, or `None` if unlimited.
+    pub bandwidth: Option<u64>,
+    /// The timeout of connections.
+    pub timeout: Duration,
+    /// The number of retries if a transfer fails, the retries are resumed from where the
+    /// transfer is interrupted.
+    pub retries: u32,
+}
+
+impl Default for DownloadParams {
+    fn default() -> Self {
+        DownloadParams {
+            max_connections: 4,
+            bandwidth: None,
+            timeout: Duration::from_secs(30),
+            retries: 3,
+        }
+    }
+}
+
+/// The request of downloading a file at `url` into `path`.
+pub struct DownloadRequest {
+    pub url: String,
+    pub path: PathBuf,
+    /// The expected checksum of file, the file is downloaded again if its mismatched.
+    pub checksum: Option<u64>,
+    /// The progress callback which is called on the main thread.
+    pub progress: Option<Box<ProgressCallback>>,
+}
+
+impl DownloadRequest {
+    pub fn new<T1: Into<String>, T2: Into<PathBuf>>(url: T1, path: T2) -> Self {
+        DownloadRequest {
+            url: url.into(),
+            path: path.into(),
+            checksum: None,
+            progress: None,
+        }
+    }
+}
+
+/// The progress of a group of downloads.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct DownloadProgress {
+    /// The bytes received.
+    pub received: u64,
+    /// The total bytes of the downloads whose sizes are known.
+    pub total: u64,
+    /// The number of downloads which are being transferred or waiting.
+    pub pending: usize,
+}
+
+impl DownloadProgress {
+    /// Gets the ratio of received bytes, ranging from 0.0 to 1.0.
+    pub fn ratio(&self) -> f32 {
+        if self.total == 0 {
+            if self.pending == 0 {
+                1.0
+            } else {
+                0.0
+            }
+        } else {
+            (self.received as f64 / self.total as f64).min(1.0) as f32
+        }
+    }
+}
+
+struct DownloadState {
+    // The received bytes and total size, which are kept behind a lock since the 64-bit
+    // atomics are not available on every target, and the files might be larger than 4GB.
+    progress: Mutex<(u64, Option<u64>)>,
+    cancelled: AtomicBool,
+    finished: AtomicBool,
+}
+
+impl DownloadState {
+    fn new() -> Self {
+        DownloadState {
+            progress: Mutex::new((0, None)),
+            cancelled: AtomicBool::new(false),
+            finished: AtomicBool::new(false),
+        }
+    }
+
+    fn received(&self) -> u64 {
+        self.progress.lock().unwrap().0
+    }
+
+    fn total(&self) -> Option<u64> {
+        self.progress.lock().unwrap().1
+    }
+
+    fn set_received(&self, received: u64) {
+        self.progress.lock().unwrap().0 = received;
+    }
+
+    fn set_total(&self, total: Option<u64>) {
+        self.progress.lock().unwrap().1 = total;
+    }
+}
+
+/// The handle of a download, which could be used to query the progress or cancel the
+/// transfer.
+#[derive(Clone)]
+pub struct Download {
+    state: Arc<DownloadState>,
+}
+
+impl Download {
+    /// Gets the bytes received, including the ones received before resuming.
+    #[inline]
+    pub fn received(&self) -> u64 {
+        self.state.received()
+    }
+
+    /// Gets the total size of file, or `None` if its unknown yet.
+    #[inline]
+    pub fn total(&self) -> Option<u64> {
+        self.state.total()
+    }
+
+    /// Cancels the transfer, the received contents are kept so it could be resumed
+    /// later.
+    #[inline]
+    pub fn cancel(&self) {
+        self.state.cancelled.store(true, Ordering::Relaxed);
+    }
+
+    #[inline]
+    pub fn is_cancelled(&self) -> bool {
+        self.state.cancelled.load(Ordering::Relaxed)
+    }
+
+    /// Returns true if the transfer has been finished, succeeded or not.
+    #[inline]
+    pub fn is_finished(&self) -> bool {
+        self.state.finished.load(Ordering::Acquire)
+    }
+}
+
+type Completion = Box<FnMut(Result<PathBuf>) + Send>;
+
+struct Task {
+    url: String,
+    path: PathBuf,
+    checksum: Option<u64>,
+    state: Arc<DownloadState>,
+    completion: Completion,
+}
+
+struct Watcher {
+    state: Arc<DownloadState>,
+    progress: Option<Box<ProgressCallback>>,
+    received: u64,
+}
+
+struct Queue {
+    tasks: Mutex<VecDeque<Task>>,
+    condvar: Condvar,
+}
+
+struct Shared {
+    params: DownloadParams,
+    queue: Queue,
+    completions: Mutex<Vec<(Arc<DownloadState>, Completion, Result<PathBuf>)>>,
+    throttle: Option<Throttle>,
+    terminated: AtomicBool,
+}
+
+/// The manager of downloads.
+pub struct DownloadManager {
+    shared: Arc<Shared>,
+    watchers: Mutex<Vec<Watcher>>,
+    workers: Vec<thread::JoinHandle<()>>,
+}
+
+impl DownloadManager {
+    pub fn new(params: DownloadParams) -> Self {
+        let shared = Arc::new(Shared {
+            params: params,
+            queue: Queue {
+                tasks: Mutex::new(VecDeque::new()),
+                condvar: Condvar::new(),
+            },
+            completions: Mutex::new(Vec::new()),
+            throttle: params.bandwidth.map(Throttle::new),
+            terminated: AtomicBool::new(false),
+        });
+
+        let workers = (0..params.max_connections.max(1))
+            .map(|i| {
+                let shared = shared.clone();
+                thread::Builder::new()
+                    .name(format!("Download #{}", i))
+                    .spawn(move || shared.main_loop())
+                    .unwrap()
+            })
+            .collect();
+
+        DownloadManager {
+            shared: shared,
+            watchers: Mutex::new(Vec::new()),
+            workers: workers,
+        }
+    }
+
+    /// Downloads the file. The `completion` will be called with the path of file on the
+    /// main thread in `DownloadManager::advance`.
+    pub fn download<F>(&self, request: DownloadRequest, completion: F) -> Download
+    where
+        F: FnOnce(Result<PathBuf>) + Send + 'static,
+    {
+        let state = Arc::new(DownloadState::new());
+
+        let mut completion = Some(completion);
+        let task = Task {
+            url: request.url,
+            path: request.path,
+            checksum: request.checksum,
+            state: state.clone(),
+            completion: Box::new(move |v| (completion.take().unwrap())(v)),
+        };
+
+        self.watchers.lock().unwrap().push(Watcher {
+            state: state.clone(),
+            progress: request.progress,
+            received: 0,
+        });
+
+        self.shared.queue.tasks.lock().unwrap().push_back(task);
+        self.shared.queue.condvar.notify_one();
+
+        Download { state: state }
+    }
+
+    /// Gets the progress of all the downloads which have not been delivered yet.
+    pub fn progress(&self) -> DownloadProgress {
+        let mut progress = DownloadProgress::default();
+        for v in self.watchers.lock().unwrap().iter() {
+            progress.received += v.state.received();
+            progress.total += v.state.total().unwrap_or(0);
+            progress.pending += 1;
+        }
+
+        progress
+    }
+
+    /// Delivers the progress and completion callbacks. This should be called on the main
+    /// thread every frame.
+    pub fn advance(&self) {
+        {
+            let mut watchers = self.watchers.lock().unwrap();
+            for v in watchers.iter_mut() {
+                let received = v.state.received();
+                if received != v.received {
+                    v.received = received;
+                    if let Some(ref mut progress) = v.progress {
+                        progress(received, v.state.total());
+                    }
+                }
+            }
+        }
+
+        let completions: Vec<_> = self.shared.completions.lock().unwrap().drain(..).collect();
+        for (state, mut completion, result) in completions {
+            self.watchers
+                .lock()
+                .unwrap()
+                .retain(|v| !Arc::ptr_eq(&v.state, &state));
+
+            completion(result);
+        }
+    }
+}
+
+impl Drop for DownloadManager {
+    /// Interrupts the transfers and waits for the worker threads, the received contents
+    /// are kept.
+    fn drop(&mut self) {
+        {
+            // Holds the lock so the workers can't miss the notification between checking
+            // `terminated` and waiting.
+            let _tasks = self.shared.queue.tasks.lock().unwrap();
+            self.shared.terminated.store(true, Ordering::Relaxed);
+            self.shared.queue.condvar.notify_all();
+        }
+
+        for v in self.workers.drain(..) {
+            if v.join().is_err() {
+                warn!("[Download] The worker thread panicked.");
+            }
+        }
+    }
+}
+
+impl Shared {
+    fn main_loop(&self) {
+        loop {
+            let task = {
+                let mut tasks = self.queue.tasks.lock().unwrap();
+                loop {
+                    if self.terminated.load(Ordering::Relaxed) {
+                        return;
+                    }
+
+                    if let Some(task) = tasks.pop_front() {
+                        break task;
+                    }
+
+                    tasks = self.queue.condvar.wait(tasks).unwrap();
+                }
+            };
+
+            let result = self.run(&task);
+            task.state.finished.store(true, Ordering::Release);
+
+            let Task {
+                state, completion, ..
+            } = task;
+
+            self.completions
+                .lock()
+                .unwrap()
+                .push((state, completion, result));
+        }
+    }
+
+    fn is_cancelled(&self, task: &Task) -> bool {
+        task.state.cancelled.load(Ordering::Relaxed) || self.terminated.load(Ordering::Relaxed)
+    }
+
+    fn run(&self, task: &Task) -> Result<PathBuf> {
+        let mut retries = 0;
+        loop {
+            if self.is_cancelled(task) {
+                bail!("[Download] The transfer of {} is cancelled.", task.url);
+            }
+
+            match self.transfer(task) {
+                Ok(()) => return Ok(task.path.clone()),
+                Err(err) => {
+                    if self.is_cancelled(task) || retries >= self.params.retries {
+                        return Err(err).with_location(task.url.as_str());
+                    }
+
+                    retries += 1;
+                    warn!(
+                        "[Download] Retries {} ({}/{}). {}",
+                        task.url, retries, self.params.retries, err
+                    );
+
+                    thread::sleep(Duration::from_millis(500 * u64::from(retries)));
+                }
+            }
+        }
+    }
+
+    fn transfer(&self, task: &Task) -> Result<()> {
+        let mut partial = task.path.clone().into_os_string();
+        partial.push(".download");
+        let partial = PathBuf::from(partial);
+
+        if let Some(parent) = partial.parent() {
+            fs::create_dir_all(parent)?;
+        }
+
+        let url = http::Url::parse(&task.url)?;
+        let offset = fs::metadata(&partial).map(|v| v.len()).unwrap_or(0);
+        let mut response = http::get(&url, offset, self.params.timeout)?;
+
+        // The checksum of contents is computed while they are being written.
+        let mut hasher = task.checksum.map(|_| patch::Checksum::new());
+
+        let (mut file, mut received, total) = match response.status {
+            200 => (fs::File::create(&partial)?, 0, response.total()),
+            206 => {
+                // The server might ignore the requested range and send the contents from
+                // some other place, which would corrupt the partial file if appended. It's
+                // discarded, so the retry starts from the beginning.
+                if response.range_start() != Some(offset) {
+                    fs::remove_file(&partial)?;
+                    let err = format_err!(
+                        "[Download] Mismatched Content-Range {:?} from {}, expects {}.",
+                        response.header("Content-Range"),
+                        url,
+                        offset
+                    );
+
+                    return Err(err).with_kind(ErrorKind::InvalidData);
+                }
+
+                if let Some(ref mut hasher) = hasher {
+                    hash_file(&partial, hasher)?;
+                }
+
+                let file = fs::OpenOptions::new().append(true).open(&partial)?;
+                (file, offset, response.total())
+            }
+            // The partial file has been completed already.
+            416 if offset > 0 => {
+                if let Some(ref mut hasher) = hasher {
+                    hash_file(&partial, hasher)?;
+                }
+
+                return self.finish(task, &partial, hasher);
+            }
+            v => bail!("[Download] Unexpected status {} from {}.", v, url),
+        };
+
+        task.state.set_received(received);
+        task.state.set_total(total);
+
+        let mut buf = [0; 16 * 1024];
+        loop {
+            if self.is_cancelled(task) {
+                file.sync_all()?;
+                bail!("[Download] The transfer of {} is cancelled.", url);
+            }
+
+            let n = response.read(&mut buf)?;
+            if n == 0 {
+                break;
+            }
+
+            if let Some(ref throttle) = self.throttle {
+                throttle.acquire(n);
+            }
+
+            file.write_all(&buf[..n])?;
+            if let Some(ref mut hasher) = hasher {
+                hasher.update(&buf[..n]);
+            }
+
+            received += n as u64;
+            task.state.set_received(received);
+        }
+
+        file.sync_all()?;
+        if let Some(total) = total {
+            if received < total {
+                bail!("[Download] The transfer of {} is interrupted.", url);
+            }
+        }
+
+        self.finish(task, &partial, hasher)
+    }
+
+    // Validates the checksum of partial file, and moves it into place.
+    fn finish(&self, task: &Task, partial: &Path, hasher: Option<patch::Checksum>) -> Result<()> {
+        if let (Some(expected), Some(hasher)) = (task.checksum, hasher) {
+            if hasher.finish() != expected {
+                fs::remove_file(partial)?;
+                return Err(format_err!("[Download] Checksum of {:?} mismatched.", task.path))
+                    .with_kind(ErrorKind::InvalidData);
+            }
+        }
+
+        fs::rename(partial, &task.path)?;
+        Ok(())
+    }
+}
+
+// Hashes the contents of file piece by piece.
+fn hash_file(path: &Path, hasher: &mut patch::Checksum) -> Result<()> {
+    let mut file = fs::File::open(path)?;
+    let mut buf = [0; 16 * 1024];
+    loop {
+        let n = file.read(&mut buf)?;
+        if n == 0 {
+            return Ok(());
+        }
+
+        hasher.update(&buf[..n]);
+    }
+}
+
+// The token bucket which limits the bandwidth of all the transfers.
+struct Throttle {
+    rate: f64,
+    bucket: Mutex<(Instant, f64)>,
+}
+
+impl Throttle {
+    fn new(rate: u64) -> Self {
+        Throttle {
+            rate: rate.max(1) as f64,
+            bucket: Mutex::new((Instant::now(), 0.0)),
+        }
+    }
+
+    // Consumes `n` bytes from the bucket, blocks current thread until they are available.
+    fn acquire(&self, n: usize) {
+        let wait = {
+            let mut bucket = self.bucket.lock().unwrap();
+            let now = Instant::now();
+            let elapsed = now - bucket.0;
+            let elapsed = elapsed.as_secs() as f64 + f64::from(elapsed.subsec_nanos()) * 1e-9;
+
+            // At most one second of bandwidth could be accumulated.
+            bucket.0 = now;
+            bucket.1 = (bucket.1 + elapsed * self.rate).min(self.rate) - n as f64;
+            -bucket.1 / self.rate
+        };
+
+        if wait > 0.0 {
+            let nanos = (wait.fract() * 1e9) as u32;
+            thread::sleep(Duration::new(wait as u64, nanos));
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::env;
+    use std::io::{BufRead, BufReader};
+    use std::net::TcpListener;
+
+    // Serves the contents with range requests for `n` connections. The misbehaving server
+    // answers the range requests with partial contents from the beginning.
+    fn serve(contents: Vec<u8>, n: usize, misbehaving: bool) -> String {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+
+        thread::spawn(move || {
+            for stream in listener.incoming().take(n) {
+                let mut stream = stream.unwrap();
+                let mut offset = 0;
+
+                {
+                    let mut reader = BufReader::new(&stream);
+                    let mut line = String::new();
+                    while reader.read_line(&mut line).unwrap() > 2 {
+                        if line.starts_with("Range: bytes=") {
+                            let v = line["Range: bytes=".len()..].trim().trim_right_matches('-');
+                            offset = v.parse().unwrap();
+                        }
+
+                        line.clear();
+                    }
+                }
+
+                if misbehaving {
+                    offset = 0;
+                }
+
+                let body = &contents[offset..];
+                let head = if offset > 0 || misbehaving {
+                    format!(
+                        "HTTP/1.1 206 Partial Content\r\nContent-Range: bytes {}-{}/{}\r\n\
+                         Content-Length: {}\r\n\r\n",
+                        offset,
+                        contents.len() - 1,
+                        contents.len(),
+                        body.len()
+                    )
+                } else {
+                    format!("HTTP/1.1 200 OK\r\nContent-Length: {}\r\n\r\n", body.len())
+                };
+
+                stream.write_all(head.as_bytes()).unwrap();
+                stream.write_all(body).unwrap();
+            }
+        });
+
+        format!("http://{}/file.bin", addr)
+    }
+
+    fn fetch(downloads: &DownloadManager, request: DownloadRequest) -> Result<PathBuf> {
+        let result = Arc::new(Mutex::new(None));
+        let v = result.clone();
+        downloads.download(request, move |r| *v.lock().unwrap() = Some(r));
+
+        loop {
+            downloads.advance();
+            if let Some(v) = result.lock().unwrap().take() {
+                return v;
+            }
+
+            thread::sleep(Duration::from_millis(5));
+        }
+    }
+
+    #[test]
+    fn resume() {
+        let contents: Vec<u8> = (0..100_000).map(|v| (v * 31) as u8).collect();
+        let url = serve(contents.clone(), 2, false);
+
+        let root = env::temp_dir().join("crayon_download");
+        let _ = fs::remove_dir_all(&root);
+        fs::create_dir_all(&root).unwrap();
+
+        // Simulates an interrupted transfer.
+        let path = root.join("file.bin");
+        let partial = root.join("file.bin.download");
+        fs::File::create(&partial)
+            .unwrap()
+            .write_all(&contents[..40_000])
+            .unwrap();
+
+        let mut params = DownloadParams::default();
+        params.retries = 0;
+        let downloads = DownloadManager::new(params);
+
+        let received = Arc::new(Mutex::new((0, None)));
+        let v = received.clone();
+
+        let mut request = DownloadRequest::new(url.as_str(), path.as_path());
+        request.checksum = Some(patch::checksum(&contents));
+        request.progress = Some(Box::new(move |r, total| *v.lock().unwrap() = (r, total)));
+
+        assert_eq!(fetch(&downloads, request).unwrap(), path);
+        assert_eq!(*received.lock().unwrap(), (100_000, Some(100_000)));
+        assert_eq!(downloads.progress().pending, 0);
+        assert!(!partial.exists());
+
+        let mut bytes = Vec::new();
+        fs::File::open(&path)
+            .unwrap()
+            .read_to_end(&mut bytes)
+            .unwrap();
+        assert_eq!(bytes, contents);
+
+        // The mismatched file is discarded.
+        let mut request = DownloadRequest::new(url.as_str(), root.join("other.bin"));
+        request.checksum = Some(0);
+        assert!(fetch(&downloads, request).is_err());
+        assert!(!root.join("other.bin").exists());
+        assert!(!root.join("other.bin.download").exists());
+
+        let _ = fs::remove_dir_all(&root);
+    }
+
+    #[test]
+    fn mismatched_range() {
+        let contents: Vec<u8> = (0..100_000).map(|v| (v * 17) as u8).collect();
+        let url = serve(contents.clone(), 2, true);
+
+        let root = env::temp_dir().join("crayon_download_range");
+        let _ = fs::remove_dir_all(&root);
+        fs::create_dir_all(&root).unwrap();
+
+        let path = root.join("file.bin");
+        let partial = root.join("file.bin.download");
+        fs::File::create(&partial)
+            .unwrap()
+            .write_all(&contents[..40_000])
+            .unwrap();
+
+        // The partial file is discarded instead of appending the contents from a wrong
+        // position, and the retry downloads the whole file.
+        let mut params = DownloadParams::default();
+        params.retries = 1;
+        let downloads = DownloadManager::new(params);
+
+        let mut request = DownloadRequest::new(url.as_str(), path.as_path());
+        request.checksum = Some(patch::checksum(&contents));
+        assert_eq!(fetch(&downloads, request).unwrap(), path);
+
+        let mut bytes = Vec::new();
+        fs::File::open(&path)
+            .unwrap()
+            .read_to_end(&mut bytes)
+            .unwrap();
+        assert_eq!(bytes, contents);
+
+        // The worker threads are joined.
+        drop(downloads);
+        let _ = fs::remove_dir_all(&root);
+    }
+
+    #[test]
+    fn large_progress() {
+        let state = DownloadState::new();
+        state.set_received(5 << 30);
+        state.set_total(Some(6 << 30));
+        assert_eq!(state.received(), 5 << 30);
+        assert_eq!(state.total(), Some(6 << 30));
+    }
+}
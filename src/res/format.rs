@@ -0,0 +1,186 @@
+//! Versioned headers of binary asset formats.
+//!
+//! Every binary asset starts with a 8 bytes header, which consists of a 4 bytes magic
+//! number, a reserved byte, and the `major.minor.patch` version of format. Assets with
+//! different patch versions are always compatible, while assets written with previous
+//! major or minor versions are loaded through the migration hooks.
+
+use std::fmt;
+use std::io::{Read, Write};
+
+use errors::*;
+
+/// The semantic version of a binary format.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Version {
+    pub major: u8,
+    pub minor: u8,
+    pub patch: u8,
+}
+
+impl Version {
+    pub fn new(major: u8, minor: u8, patch: u8) -> Self {
+        Version {
+            major: major,
+            minor: minor,
+            patch: patch,
+        }
+    }
+
+    /// Returns true if the binary layouts of two versions are the same.
+    #[inline]
+    pub fn is_compatible_with(&self, rhs: Version) -> bool {
+        self.major == rhs.major && self.minor == rhs.minor
+    }
+}
+
+impl fmt::Display for Version {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}.{}.{}", self.major, self.minor, self.patch)
+    }
+}
+
+/// A hook which reads the assets written in an outdated version, and migrates it to
+/// the current representation.
+pub type Migration<T> = (Version, fn(&mut dyn Read) -> Result<T>);
+
+/// The description of a versioned binary asset format.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct AssetFormat {
+    /// The name of format, which is used in error messages.
+    pub name: &'static str,
+    /// The magic number which identifies the format.
+    pub magic: [u8; 4],
+    /// The current version of format supported by the engine.
+    pub version: Version,
+}
+
+impl AssetFormat {
+    /// Encodes the header of current version.
+    pub fn header(&self) -> [u8; 8] {
+        [
+            self.magic[0],
+            self.magic[1],
+            self.magic[2],
+            self.magic[3],
+            b' ',
+            self.version.major,
+            self.version.minor,
+            self.version.patch,
+        ]
+    }
+
+    /// Writes the header of current version into `file`.
+    pub fn write_header(&self, file: &mut dyn Write) -> Result<()> {
+        file.write_all(&self.header())?;
+        Ok(())
+    }
+
+    /// Reads and validates the header from `file`, returns the version which the asset
+    /// is written with.
+    pub fn read_header(&self, file: &mut dyn Read) -> Result<Version> {
+        let mut buf = [0; 8];
+        file.read_exact(&mut buf)?;
+
+        if buf[0..4] != self.magic[..] {
+            bail!("[{}] MAGIC number not match.", self.name);
+        }
+
+        let version = Version::new(buf[5], buf[6], buf[7]);
+        if (version.major, version.minor) > (self.version.major, self.version.minor) {
+            bail!(
+                "[{}] The asset version {} is newer than the supported version {}.",
+                self.name,
+                version,
+                self.version
+            );
+        }
+
+        Ok(version)
+    }
+
+    /// Reads the header from `file`, and then reads the asset with `current` if its
+    /// written in a compatible version. Otherwise, the migration hook registered with
+    /// the asset version will be used.
+    pub fn load<T, F>(&self, file: &mut dyn Read, migrations: &[Migration<T>], current: F) -> Result<T>
+    where
+        F: FnOnce(&mut dyn Read) -> Result<T>,
+    {
+        let version = self.read_header(file)?;
+        if version.is_compatible_with(self.version) {
+            return current(file);
+        }
+
+        for &(v, migration) in migrations {
+            if version.is_compatible_with(v) {
+                info!(
+                    "[{}] Migrates asset from version {} to {}.",
+                    self.name, version, self.version
+                );
+
+                return migration(file);
+            }
+        }
+
+        bail!(
+            "[{}] The asset version {} is outdated, and there is no migration to {}.",
+            self.name,
+            version,
+            self.version
+        );
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    const FORMAT: AssetFormat = AssetFormat {
+        name: "Test",
+        magic: [b'T', b'E', b'S', b'T'],
+        version: Version {
+            major: 0,
+            minor: 2,
+            patch: 1,
+        },
+    };
+
+    fn read_u8(file: &mut dyn Read) -> Result<u8> {
+        let mut buf = [0; 1];
+        file.read_exact(&mut buf)?;
+        Ok(buf[0])
+    }
+
+    fn migrate_from_0_1(file: &mut dyn Read) -> Result<u8> {
+        Ok(read_u8(file)? * 2)
+    }
+
+    #[test]
+    fn header() {
+        let mut buf = Vec::new();
+        FORMAT.write_header(&mut buf).unwrap();
+        assert_eq!(&buf[..], b"TEST \x00\x02\x01");
+
+        let version = FORMAT.read_header(&mut &buf[..]).unwrap();
+        assert_eq!(version, FORMAT.version);
+
+        assert!(FORMAT.read_header(&mut &b"TSET \x00\x02\x01"[..]).is_err());
+        assert!(FORMAT.read_header(&mut &b"TEST \x00\x03\x00"[..]).is_err());
+        assert!(FORMAT.read_header(&mut &b"TEST \x01\x00\x00"[..]).is_err());
+        assert!(FORMAT.read_header(&mut &b"TEST \x00\x02\x09"[..]).is_ok());
+    }
+
+    #[test]
+    fn migration() {
+        let migrations: &[Migration<u8>] = &[(Version::new(0, 1, 0), migrate_from_0_1)];
+
+        let v = FORMAT.load(&mut &b"TEST \x00\x02\x00\x03"[..], migrations, read_u8);
+        assert_eq!(v.unwrap(), 3);
+
+        let v = FORMAT.load(&mut &b"TEST \x00\x01\x05\x03"[..], migrations, read_u8);
+        assert_eq!(v.unwrap(), 6);
+
+        let v = FORMAT.load(&mut &b"TEST \x00\x00\x01\x03"[..], migrations, read_u8);
+        assert!(v.is_err());
+    }
+}
@@ -1,9 +1,11 @@
-use std::path::{Path, PathBuf};
+use std::path::PathBuf;
 use uuid::Uuid;
 
 use errors::*;
 use utils::hash_value::HashValue;
 
+use super::vfs;
+
 #[derive(Debug)]
 pub enum Location {
     Uuid(Uuid),
@@ -11,11 +13,23 @@ pub enum Location {
 }
 
 impl Location {
+    /// Parses location like `res:textures/grass.png`, or the URI style with mount
+    /// prefix like `save://settings.toml`. The path part is normalized with
+    /// `vfs::normalize`.
     pub fn from_str(v: &str) -> Result<Location> {
         if let Some(idx) = v.find(':') {
             let (fs, file) = v.split_at(idx);
-            let path: &Path = (&file[1..]).as_ref();
-            Ok(Location::Name(fs.into(), path.into()))
+            if fs.is_empty() {
+                bail!("Malformated location {:?}.", v);
+            }
+
+            let file = if file.starts_with("://") {
+                &file[3..]
+            } else {
+                &file[1..]
+            };
+
+            Ok(Location::Name(fs.into(), vfs::normalize(file)?))
         } else {
             bail!("Malformated location {:?}.", v);
         }
@@ -25,3 +39,27 @@ impl Location {
         Location::Uuid(v)
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn parse(v: &str) -> (HashValue<str>, PathBuf) {
+        match Location::from_str(v).unwrap() {
+            Location::Name(fs, file) => (fs, file),
+            _ => unreachable!(),
+        }
+    }
+
+    #[test]
+    fn prefix() {
+        let file: PathBuf = ["textures", "grass.png"].iter().collect();
+        assert_eq!(parse("res:textures/grass.png"), ("res".into(), file.clone()));
+        assert_eq!(parse("assets://textures/grass.png"), ("assets".into(), file.clone()));
+        assert_eq!(parse("save://./textures\\grass.png"), ("save".into(), file.clone()));
+
+        assert!(Location::from_str("textures/grass.png").is_err());
+        assert!(Location::from_str(":textures/grass.png").is_err());
+        assert!(Location::from_str("res://../grass.png").is_err());
+    }
+}
@@ -5,12 +5,19 @@ use bincode;
 use uuid;
 
 use errors::*;
+use res::format::{AssetFormat, Migration, Version};
 use utils::hash_value::HashValue;
 
 pub const NAME: &'static str = ".MANIFEST";
-pub const MAGIC: [u8; 8] = [
-    'M' as u8, 'N' as u8, 'F' as u8, 'T' as u8, ' ' as u8, 0, 0, 1,
-];
+pub const FORMAT: AssetFormat = AssetFormat {
+    name: "ManifestLoader",
+    magic: [b'M', b'N', b'F', b'T'],
+    version: Version {
+        major: 0,
+        minor: 1,
+        patch: 0,
+    },
+};
 
 #[derive(Serialize, Deserialize, Clone, Copy, Debug, Eq, Hash, PartialEq)]
 pub struct ManifestItem {
@@ -18,26 +25,42 @@ pub struct ManifestItem {
     pub uuid: uuid::Uuid,
 }
 
+/// A variant of resource, which is loaded instead of the resource `uuid` if its `tags`
+/// are selected by the `VariantPolicy`.
+#[derive(Serialize, Deserialize, Clone, Debug, Eq, Hash, PartialEq)]
+pub struct ManifestVariant {
+    pub uuid: uuid::Uuid,
+    pub tags: Vec<String>,
+    pub variant: uuid::Uuid,
+}
+
 #[derive(Serialize, Deserialize, Clone, Debug)]
 pub struct Manifest {
     pub items: Vec<ManifestItem>,
+    pub variants: Vec<ManifestVariant>,
 }
 
 impl Manifest {
     pub fn new() -> Self {
-        Manifest { items: Vec::new() }
+        Manifest {
+            items: Vec::new(),
+            variants: Vec::new(),
+        }
     }
 
-    pub fn load(mut file: &mut dyn Read) -> Result<Manifest> {
-        let mut buf = [0; 16];
-        file.read_exact(&mut buf[0..8])?;
-
-        // MAGIC: [u8; 8]
-        if &buf[0..8] != &MAGIC[..] {
-            bail!("[ManifestLoader] MAGIC number not match.");
-        }
+    pub fn load(file: &mut dyn Read) -> Result<Manifest> {
+        let migrations: &[Migration<Manifest>] = &[(Version::new(0, 0, 0), Self::migrate_0_0)];
+        FORMAT.load(file, migrations, |mut file| {
+            Ok(bincode::deserialize_from(&mut file)?)
+        })
+    }
 
-        let manifest = bincode::deserialize_from(&mut file)?;
-        Ok(manifest)
+    // The manifests without variants.
+    fn migrate_0_0(mut file: &mut dyn Read) -> Result<Manifest> {
+        let items: Vec<ManifestItem> = bincode::deserialize_from(&mut file)?;
+        Ok(Manifest {
+            items: items,
+            variants: Vec::new(),
+        })
     }
 }
@@ -13,13 +13,35 @@
 //!
 //! In some systems, actual resource objects are private and opaque, application will usually
 //! not have direct access to a resource object in form of reference.
+//!
+//! # Hot-reload
+//!
+//! With `ResourceParams::hot_reload`, the loaded resources are reloaded into their
+//! handles once the files are modified on disk, and the video objects like textures
+//! are recreated transparently. The plain files could be watched too, e.g. to
+//! recompile a shader when its sources are edited:
+//!
+//! ```rust,ignore
+//! let video = ctx.video.clone();
+//! ctx.res.watch("res:shaders/color.fs", move |bytes| {
+//!     let fs = String::from_utf8_lossy(bytes).into_owned();
+//!     video.update_shader(shader, vs.clone(), fs)
+//! })?;
+//! ```
 
+pub mod download;
+pub mod format;
 pub mod location;
 pub mod manifest;
+pub mod patch;
+pub mod variant;
 pub mod vfs;
 
 pub mod prelude {
-    pub use super::vfs::DiskFS;
+    pub use super::download::{DownloadManager, DownloadParams, DownloadRequest};
+    pub use super::patch::Patch;
+    pub use super::variant::VariantPolicy;
+    pub use super::vfs::{CipherFS, CipherKey, DiskFS, KnownDirectory, OverlayFS};
     pub use super::{ResourceHandle, ResourceLoader, ResourceSystem, ResourceSystemShared};
 }
 
@@ -28,14 +50,34 @@ mod registery;
 use std::any::{Any, TypeId};
 use std::collections::HashMap;
 use std::io::Read;
+use std::path::PathBuf;
 use std::sync::{Arc, RwLock};
+use uuid::Uuid;
 
+use sched::latch::LatchProbe;
 use sched::ScheduleSystemShared;
 use utils::handle::Handle;
+use utils::hash_value::HashValue;
 
-use self::vfs::VFS;
+use self::location::Location;
+use self::variant::VariantPolicy;
+use self::vfs::{CipherFS, CipherKey, DiskFS, FileBytes, FileSlice, KnownDirectory, VFS};
 use errors::*;
 
+/// The setup parameters of resource system.
+#[derive(Debug, Clone, Default)]
+pub struct ResourceParams {
+    /// The policy which selects the platform or quality specific variants of resources.
+    pub variants: VariantPolicy,
+    /// The key of encrypted assets, the `assets` directory is mounted with `CipherFS`
+    /// by `ResourceSystem::mount_known_directories` if it's set.
+    pub key: Option<CipherKey>,
+    /// Reloads the resources and the watched files once they are modified on disk,
+    /// which is useful to iterate shaders and textures without restarting. The files
+    /// are polled periodically, so it's usually enabled in development only.
+    pub hot_reload: bool,
+}
+
 pub trait ResourceHandle: Into<Handle> + From<Handle> + Copy + Send + 'static {
     type Loader: ResourceLoader<Handle = Self>;
 }
@@ -45,6 +87,29 @@ pub trait ResourceLoader: Send + Sync + Sized + 'static {
 
     fn create(&self) -> Result<Self::Handle>;
     fn load(&self, handle: Self::Handle, file: &mut dyn Read) -> Result<()>;
+
+    /// Loads the resource from the whole contents of file, which might be memory-mapped.
+    /// The first byte of `bytes` is aligned to `vfs::FILE_BYTES_ALIGNMENT` at least.
+    ///
+    /// Loaders could override this to avoid copying large binary data.
+    fn load_bytes(&self, handle: Self::Handle, bytes: &[u8]) -> Result<()> {
+        self.load(handle, &mut &bytes[..])
+    }
+
+    /// Loads the resource from the shared contents of file, which is what the resource
+    /// system calls. Loaders could keep ranges of `bytes` alive (e.g. hand them to the
+    /// GPU upload path) instead of copying. It falls back to `load_bytes` by default.
+    fn load_shared(&self, handle: Self::Handle, bytes: FileSlice) -> Result<()> {
+        self.load_bytes(handle, &bytes)
+    }
+
+    /// Loads the modified resource into the existing handle in hot-reload mode, the
+    /// dependent objects are expected to be recreated transparently. The resource is
+    /// loaded again with `load_bytes` by default.
+    fn reload(&self, handle: Self::Handle, bytes: &[u8]) -> Result<()> {
+        self.load_bytes(handle, bytes)
+    }
+
     fn delete(&self, handle: Self::Handle) -> Result<()>;
 }
 
@@ -52,6 +117,7 @@ pub struct ResourceSystem {
     loaders: Arc<RwLock<HashMap<TypeId, Arc<Any + Send + Sync>>>>,
     registery: Arc<RwLock<registery::Registery>>,
     shared: Arc<ResourceSystemShared>,
+    key: Option<CipherKey>,
 }
 
 impl ResourceSystem {
@@ -69,6 +135,7 @@ impl ResourceSystem {
             shared: shared,
             loaders: loaders,
             registery: registery,
+            key: None,
         })
     }
 
@@ -89,11 +156,57 @@ impl ResourceSystem {
         self.registery.write().unwrap().mount(name, vfs)
     }
 
+    /// Mounts the well-known directories of application `app` with their conventional
+    /// names, e.g. `save://settings.toml`. The writable directories are created if
+    /// missing, and `assets` is only mounted if it exists. The `assets` are decrypted
+    /// if the key is set with `ResourceSystem::set_cipher_key`.
+    pub fn mount_known_directories(&mut self, app: &str) -> Result<()> {
+        for &v in &KnownDirectory::ALL {
+            let path = v.resolve(app)?;
+            let fs = match v {
+                KnownDirectory::Assets if !path.is_dir() => {
+                    warn!("Assets directory {:?} does not exist.", path);
+                    continue;
+                }
+                KnownDirectory::Assets => DiskFS::new(path)?,
+                _ => DiskFS::create(path)?,
+            };
+
+            match (v, self.key) {
+                (KnownDirectory::Assets, Some(key)) => {
+                    self.mount(v.name(), CipherFS::new(fs, key))?
+                }
+                _ => self.mount(v.name(), fs)?,
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Sets the key of encrypted assets.
+    #[inline]
+    pub fn set_cipher_key<T: Into<Option<CipherKey>>>(&mut self, key: T) {
+        self.key = key.into();
+    }
+
+    /// Sets the policy which selects the variants of resources, it only affects the
+    /// resources loaded afterwards.
+    pub fn set_variant_policy(&self, policy: VariantPolicy) {
+        self.registery.write().unwrap().set_variant_policy(policy);
+    }
+
+    /// Enables the hot-reload mode, it only affects the resources loaded afterwards.
+    pub fn set_hot_reload(&self, enable: bool) {
+        self.registery.write().unwrap().set_hot_reload(enable);
+    }
+
     pub fn shared(&self) -> Arc<ResourceSystemShared> {
         self.shared.clone()
     }
 
-    pub fn advance(&self) {}
+    pub fn advance(&self) {
+        self.registery.write().unwrap().advance();
+    }
 }
 
 pub struct ResourceSystemShared {
@@ -108,7 +221,7 @@ impl ResourceSystemShared {
     where
         T: ResourceHandle + 'static,
     {
-        self.load_from(location::Location::from_str(uri)?)
+        self.load_from(Location::from_str(uri)?)
     }
 
     pub fn load_from<T>(&self, location: location::Location) -> Result<T>
@@ -120,6 +233,77 @@ impl ResourceSystemShared {
         self.registery.write().unwrap().load_from(loader, location)
     }
 
+    /// Reads the whole contents of plain file at location, e.g. `save://settings.toml`.
+    pub fn read(&self, uri: &str) -> Result<FileBytes> {
+        let (fs, file) = Self::parse_file(uri)?;
+        let registery = self.registery.read().unwrap();
+        registery.driver().read_bytes(fs, &file)
+    }
+
+    /// Writes the whole contents of plain file at location, the file system must be
+    /// writable, e.g. the ones which are mounted with `DiskFS`.
+    pub fn write(&self, uri: &str, bytes: &[u8]) -> Result<()> {
+        let (fs, file) = Self::parse_file(uri)?;
+        let registery = self.registery.read().unwrap();
+        registery.driver().write(fs, &file, bytes)
+    }
+
+    /// Removes the plain file at location.
+    pub fn remove(&self, uri: &str) -> Result<()> {
+        let (fs, file) = Self::parse_file(uri)?;
+        let registery = self.registery.read().unwrap();
+        registery.driver().remove(fs, &file)
+    }
+
+    /// Watches the plain file at location, `reload` is called with its contents on
+    /// the `sched` workers once it's modified in hot-reload mode. This is useful for
+    /// the files which are not resources, e.g. the sources of shaders.
+    pub fn watch<F>(&self, uri: &str, reload: F) -> Result<()>
+    where
+        F: Fn(&[u8]) -> Result<()> + Send + Sync + 'static,
+    {
+        let (fs, file) = Self::parse_file(uri)?;
+        let mut registery = self.registery.write().unwrap();
+        registery.watch(fs, file, Arc::new(reload))
+    }
+
+    /// Stops watching the plain file at location.
+    pub fn unwatch(&self, uri: &str) -> Result<()> {
+        let (fs, file) = Self::parse_file(uri)?;
+        self.registery.write().unwrap().unwatch(fs, &file);
+        Ok(())
+    }
+
+    /// Checks if the plain file at location exists.
+    pub fn exists(&self, uri: &str) -> Result<bool> {
+        let (fs, file) = Self::parse_file(uri)?;
+        let registery = self.registery.read().unwrap();
+        registery.driver().exists(fs, &file)
+    }
+
+    fn parse_file(uri: &str) -> Result<(HashValue<str>, PathBuf)> {
+        match Location::from_str(uri)? {
+            Location::Name(fs, file) => Ok((fs, file)),
+            Location::Uuid(_) => bail!("Location {:?} is not a plain file.", uri),
+        }
+    }
+
+    /// Gets the policy which selects the variants of resources.
+    pub fn variant_policy(&self) -> VariantPolicy {
+        self.registery.read().unwrap().variant_policy().clone()
+    }
+
+    /// Lists the uuids and reference counts of loaded resources.
+    pub fn loaded_resources(&self) -> Vec<(Uuid, u32)> {
+        self.registery.read().unwrap().loaded()
+    }
+
+    /// Lists the loaded resources without blocking, returns `None` if the registery is
+    /// locked or poisoned.
+    pub(crate) fn try_loaded_resources(&self) -> Option<Vec<(Uuid, u32)>> {
+        self.registery.try_read().ok().map(|v| v.loaded())
+    }
+
     /// Blocks current thread until loader is finished.
     pub fn wait<T>(&self, handle: T) -> Result<()>
     where
@@ -128,12 +312,26 @@ impl ResourceSystemShared {
         let v = self.registery.read().unwrap().promise(handle);
         if let Some(promise) = v {
             self.sched.wait_until(promise.as_ref());
-            promise.take()
+            promise.take().with_handle(handle)
         } else {
             Ok(())
         }
     }
 
+    /// Gets the result of loader without blocking, returns `None` if its not finished
+    /// yet. Like `wait`, the error is returned only once.
+    pub fn poll<T>(&self, handle: T) -> Option<Result<()>>
+    where
+        T: ResourceHandle,
+    {
+        let v = self.registery.read().unwrap().promise(handle);
+        match v {
+            Some(ref promise) if !promise.is_set() => None,
+            Some(promise) => Some(promise.take().with_handle(handle)),
+            None => Some(Ok(())),
+        }
+    }
+
     /// Unloads a resource when associated with `Handle`.
     pub fn unload<T>(&self, handle: T) -> Result<()>
     where
@@ -0,0 +1,762 @@
+//! Incremental patches of asset bundles.
+//!
+//! A bundle is a file system with `.MANIFEST`, which contains the manifest and the files
+//! of resources named by their uuids. Instead of downloading the whole bundle again, the
+//! asset pipeline generates a `Patch` between two versions of bundle with `Patch::diff`,
+//! which only carries the binary differences of changed files.
+//!
+//! The patch is applied at runtime into a writable directory (usually a sub-directory of
+//! `KnownDirectory::Cache`), and the cache is mounted on top of the shipped bundle with
+//! `OverlayFS`.
+//!
+//! ```rust,ignore
+//! // In the asset pipeline.
+//! let patch = Patch::diff(&DiskFS::new("bundles/1.0")?, &DiskFS::new("bundles/1.1")?)?;
+//! patch.save(&mut fs::File::create("bundles/1.0-1.1.patch")?)?;
+//!
+//! // At runtime, with the downloaded patch.
+//! let assets = DiskFS::new(KnownDirectory::Assets.resolve(app)?)?;
+//! let cache = DiskFS::create(KnownDirectory::Cache.resolve(app)?.join("assets"))?;
+//! patch::recover(&cache)?;
+//!
+//! let patch = Patch::load(&mut fs::File::open(downloaded)?)?;
+//! patch.apply(&assets, &cache)?;
+//! res.mount("assets", OverlayFS::new(cache, assets))?;
+//! ```
+//!
+//! The digest of the bundle being patched is verified first. Every patched file is
+//! staged and validated with its hash before anything in cache is changed. The removed
+//! files are hidden from the shipped bundle with the whiteout markers of `OverlayFS`. The changes are committed with a journal, so the cache is rolled back to the
+//! previous version if the commit fails. If the application crashes in the middle, the
+//! rollback happens in `patch::recover` at the next launch.
+
+use std::collections::HashMap;
+use std::io::{Read, Write};
+use std::path::{Path, PathBuf};
+
+use bincode;
+
+use errors::*;
+use res::format::{AssetFormat, Migration, Version};
+use res::manifest::{self, Manifest};
+use res::vfs::overlay::whiteout;
+use res::vfs::{FileBytes, VFS};
+
+pub const FORMAT: AssetFormat = AssetFormat {
+    name: "Patch",
+    magic: [b'P', b'T', b'C', b'H'],
+    version: Version {
+        major: 0,
+        minor: 1,
+        patch: 0,
+    },
+};
+
+/// The file which records the version of bundle in cache.
+pub const VERSION: &'static str = ".VERSION";
+/// The file which records the changes being committed.
+pub const JOURNAL: &'static str = ".JOURNAL";
+
+const STAGING: &'static str = ".staging";
+const BACKUP: &'static str = ".backup";
+
+// The size of blocks which are matched between versions of file.
+const BLOCK_SIZE: usize = 16;
+
+/// The instruction which reconstructs a part of file.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+pub enum Delta {
+    /// Copies `len` bytes at `offset` from the previous version of file.
+    Copy(u64, u64),
+    /// Inserts the bytes.
+    Insert(Vec<u8>),
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+pub enum PatchOp {
+    /// Removes the file.
+    Remove,
+    /// Replaces the file with the contents.
+    Replace(Vec<u8>),
+    /// Reconstructs the file from its previous version, whose hash is `base`.
+    Delta { base: u64, deltas: Vec<Delta> },
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+pub struct PatchEntry {
+    pub location: String,
+    /// The hash of patched file.
+    pub hash: u64,
+    pub op: PatchOp,
+}
+
+/// The differences between two versions of bundle.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+pub struct Patch {
+    /// The digest of bundle which this patch applies to.
+    pub from: u64,
+    /// The digest of patched bundle.
+    pub to: u64,
+    pub entries: Vec<PatchEntry>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+struct Journal {
+    // The locations being changed, and whether they have backups.
+    entries: Vec<(String, bool)>,
+}
+
+impl Patch {
+    /// Generates the patch which updates bundle `prev` to `next`.
+    pub fn diff(prev: &VFS, next: &VFS) -> Result<Patch> {
+        let prev_files = files(prev)?;
+        let next_files = files(next)?;
+
+        let mut entries = Vec::new();
+        for v in &next_files {
+            let bytes = next.read_bytes(v.as_ref())?;
+            let hash = checksum(&bytes);
+
+            let op = if prev_files.contains(v) {
+                let base = prev.read_bytes(v.as_ref())?;
+                if base[..] == bytes[..] {
+                    continue;
+                }
+
+                let deltas = diff(&base, &bytes);
+                if cost(&deltas) < bytes.len() {
+                    PatchOp::Delta {
+                        base: checksum(&base),
+                        deltas: deltas,
+                    }
+                } else {
+                    PatchOp::Replace(bytes.to_vec())
+                }
+            } else {
+                PatchOp::Replace(bytes.to_vec())
+            };
+
+            entries.push(PatchEntry {
+                location: v.clone(),
+                hash: hash,
+                op: op,
+            });
+        }
+
+        for v in prev_files.iter().filter(|v| !next_files.contains(v)) {
+            entries.push(PatchEntry {
+                location: v.clone(),
+                hash: 0,
+                op: PatchOp::Remove,
+            });
+        }
+
+        // The manifest is committed at last, so the new resources are always available
+        // when they are referenced.
+        entries.sort_by_key(|v| v.location == manifest::NAME);
+
+        Ok(Patch {
+            from: digest(prev)?,
+            to: digest(next)?,
+            entries: entries,
+        })
+    }
+
+    pub fn load(file: &mut dyn Read) -> Result<Patch> {
+        let migrations: &[Migration<Patch>] = &[];
+        FORMAT.load(file, migrations, |mut file| {
+            Ok(bincode::deserialize_from(&mut file)?)
+        })
+    }
+
+    pub fn save(&self, mut file: &mut dyn Write) -> Result<()> {
+        FORMAT.write_header(file)?;
+        bincode::serialize_into(&mut file, self)?;
+        Ok(())
+    }
+
+    /// Applies the patch to the bundle `base`, and writes the patched files into `cache`.
+    /// Returns false if the patch has been applied already.
+    pub fn apply(&self, base: &VFS, cache: &VFS) -> Result<bool> {
+        recover(cache)?;
+
+        match version(cache)? {
+            Some(v) if v == self.to => return Ok(false),
+            Some(v) if v != self.from => bail!(
+                "[{}] The patch applies to bundle {:X}, but the cache is {:X}.",
+                FORMAT.name,
+                self.from,
+                v
+            ),
+            _ => {}
+        }
+
+        let current = digest_with(|v| read(base, cache, v))?;
+        if current != self.from {
+            bail!(
+                "[{}] The patch applies to bundle {:X}, but the bundle is {:X}.",
+                FORMAT.name,
+                self.from,
+                current
+            );
+        }
+
+        info!(
+            "[{}] Patches bundle {:X} to {:X} with {} files.",
+            FORMAT.name,
+            self.from,
+            self.to,
+            self.entries.len()
+        );
+
+        if let Err(err) = self.stage(base, cache) {
+            self.clean(cache, STAGING);
+            return Err(err);
+        }
+
+        self.commit(cache)?;
+        Ok(true)
+    }
+
+    // Writes the validated files into the staging directory.
+    fn stage(&self, base: &VFS, cache: &VFS) -> Result<()> {
+        for v in &self.entries {
+            let bytes = match v.op {
+                PatchOp::Remove => continue,
+                PatchOp::Replace(ref bytes) => bytes.clone(),
+                PatchOp::Delta {
+                    base: hash,
+                    ref deltas,
+                } => {
+                    let src = read(base, cache, &v.location)?;
+                    if checksum(&src) != hash {
+                        bail!(
+                            "[{}] The file {:?} does not match the patch.",
+                            FORMAT.name,
+                            v.location
+                        );
+                    }
+
+                    patch(&src, deltas)?
+                }
+            };
+
+            if checksum(&bytes) != v.hash {
+                bail!("[{}] The patch of {:?} is corrupted.", FORMAT.name, v.location);
+            }
+
+            cache.write(&Path::new(STAGING).join(&v.location), &bytes)?;
+        }
+
+        Ok(())
+    }
+
+    fn commit(&self, cache: &VFS) -> Result<()> {
+        let mut journal = Journal {
+            entries: Vec::new(),
+        };
+
+        for v in self.locations() {
+            let exists = cache.exists(&v);
+            if exists {
+                let bytes = cache.read_bytes(&v)?;
+                cache.write(&Path::new(BACKUP).join(&v), &bytes)?;
+            }
+
+            let location = v.to_string_lossy().into_owned();
+            journal.entries.push((location, exists));
+        }
+
+        cache.write(JOURNAL.as_ref(), &bincode::serialize(&journal)?)?;
+
+        if let Err(err) = self.replace(cache) {
+            error!("[{}] Failed to commit patch, rolls back. {}", FORMAT.name, err);
+            rollback(cache, &journal)?;
+            return Err(err);
+        }
+
+        cache.remove(JOURNAL.as_ref())?;
+        self.clean(cache, BACKUP);
+        Ok(())
+    }
+
+    fn replace(&self, cache: &VFS) -> Result<()> {
+        for v in &self.entries {
+            let location: &Path = v.location.as_ref();
+            let marker = whiteout(location);
+
+            // The removed files are hidden from the bundle with markers.
+            if let PatchOp::Remove = v.op {
+                if cache.exists(location) {
+                    cache.remove(location)?;
+                }
+
+                cache.write(&marker, &[])?;
+            } else {
+                let staged = Path::new(STAGING).join(location);
+                let bytes = cache.read_bytes(&staged)?;
+                cache.write(location, &bytes)?;
+                cache.remove(&staged)?;
+
+                if cache.exists(&marker) {
+                    cache.remove(&marker)?;
+                }
+            }
+        }
+
+        cache.write(VERSION.as_ref(), &encode_u64(self.to))
+    }
+
+    // Gets the locations in cache which could be changed by this patch.
+    fn locations(&self) -> Vec<PathBuf> {
+        let mut locations = Vec::new();
+        for v in &self.entries {
+            let location: &Path = v.location.as_ref();
+            locations.push(location.to_owned());
+            locations.push(whiteout(location));
+        }
+
+        locations.push(VERSION.into());
+        locations
+    }
+
+    // Removes the temporary files, the failures are ignored.
+    fn clean(&self, cache: &VFS, dir: &str) {
+        for v in self.locations() {
+            let path = Path::new(dir).join(v);
+            if cache.exists(&path) {
+                let _ = cache.remove(&path);
+            }
+        }
+    }
+}
+
+/// Rolls back the cache if the application crashed while committing patch. Returns true
+/// if there is an interrupted commit.
+pub fn recover(cache: &VFS) -> Result<bool> {
+    if !cache.exists(JOURNAL.as_ref()) {
+        return Ok(false);
+    }
+
+    warn!("[{}] Rolls back the interrupted patch.", FORMAT.name);
+    let bytes = cache.read_bytes(JOURNAL.as_ref())?;
+    let journal: Journal = bincode::deserialize(&bytes)?;
+    rollback(cache, &journal)?;
+    Ok(true)
+}
+
+/// Gets the digest of bundle in cache, or `None` if it has not been patched.
+pub fn version(cache: &VFS) -> Result<Option<u64>> {
+    if !cache.exists(VERSION.as_ref()) {
+        return Ok(None);
+    }
+
+    let bytes = cache.read_bytes(VERSION.as_ref())?;
+    if bytes.len() != 8 {
+        bail!("[{}] The version of cache is corrupted.", FORMAT.name);
+    }
+
+    Ok(Some(decode_u64(&bytes)))
+}
+
+/// Computes the digest of bundle, which identifies its version.
+pub fn digest(fs: &VFS) -> Result<u64> {
+    digest_with(|v| fs.read_bytes(v.as_ref()))
+}
+
+// Computes the digest of bundle whose files are read with `read`.
+fn digest_with<F>(read: F) -> Result<u64>
+where
+    F: Fn(&str) -> Result<FileBytes>,
+{
+    let mut hashes = Vec::new();
+    for v in files_with(&read)? {
+        let bytes = read(&v)?;
+        hashes.extend_from_slice(v.as_bytes());
+        hashes.extend_from_slice(&encode_u64(checksum(&bytes)));
+    }
+
+    Ok(checksum(&hashes))
+}
+
+/// Computes the 64 bits FNV-1a hash of bytes, which is stable across platforms and
+/// builds.
+pub fn checksum(bytes: &[u8]) -> u64 {
+    let mut hasher = Checksum::new();
+    hasher.update(bytes);
+    hasher.finish()
+}
+
+/// The incremental version of `checksum`, which hashes the contents piece by piece,
+/// e.g. while they are being streamed into a file.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Checksum {
+    hash: u64,
+}
+
+impl Default for Checksum {
+    fn default() -> Self {
+        Checksum {
+            hash: 0xcbf2_9ce4_8422_2325,
+        }
+    }
+}
+
+impl Checksum {
+    #[inline]
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    pub fn update(&mut self, bytes: &[u8]) {
+        for &v in bytes {
+            self.hash ^= u64::from(v);
+            self.hash = self.hash.wrapping_mul(0x0100_0000_01b3);
+        }
+    }
+
+    #[inline]
+    pub fn finish(&self) -> u64 {
+        self.hash
+    }
+}
+
+/// Generates the deltas which reconstruct `next` from `prev`.
+pub fn diff(prev: &[u8], next: &[u8]) -> Vec<Delta> {
+    let mut blocks = HashMap::new();
+    let mut offset = 0;
+    while offset + BLOCK_SIZE <= prev.len() {
+        let hash = checksum(&prev[offset..offset + BLOCK_SIZE]);
+        blocks.entry(hash).or_insert(offset);
+        offset += BLOCK_SIZE;
+    }
+
+    let mut deltas = Vec::new();
+    let mut pending = Vec::new();
+    let mut i = 0;
+
+    while i < next.len() {
+        let found = if i + BLOCK_SIZE <= next.len() {
+            let block = &next[i..i + BLOCK_SIZE];
+            match blocks.get(&checksum(block)) {
+                Some(&v) if &prev[v..v + BLOCK_SIZE] == block => Some(v),
+                _ => None,
+            }
+        } else {
+            None
+        };
+
+        let offset = match found {
+            Some(v) => v,
+            None => {
+                pending.push(next[i]);
+                i += 1;
+                continue;
+            }
+        };
+
+        // Extends the match in both directions.
+        let mut start = offset;
+        while start > 0 && pending.last() == Some(&prev[start - 1]) {
+            pending.pop();
+            start -= 1;
+        }
+
+        let mut end = offset + BLOCK_SIZE;
+        i += BLOCK_SIZE;
+        while end < prev.len() && i < next.len() && prev[end] == next[i] {
+            end += 1;
+            i += 1;
+        }
+
+        if !pending.is_empty() {
+            deltas.push(Delta::Insert(pending.split_off(0)));
+        }
+
+        // Merges with the adjacent copy.
+        let (start, len) = (start as u64, (end - start) as u64);
+        if let Some(&mut Delta::Copy(o, ref mut l)) = deltas.last_mut() {
+            if o + *l == start {
+                *l += len;
+                continue;
+            }
+        }
+
+        deltas.push(Delta::Copy(start, len));
+    }
+
+    if !pending.is_empty() {
+        deltas.push(Delta::Insert(pending));
+    }
+
+    deltas
+}
+
+/// Reconstructs the file from its previous version with deltas.
+pub fn patch(prev: &[u8], deltas: &[Delta]) -> Result<Vec<u8>> {
+    let mut bytes = Vec::new();
+    for v in deltas {
+        match *v {
+            Delta::Copy(offset, len) => {
+                let (offset, len) = (offset as usize, len as usize);
+                if offset + len > prev.len() {
+                    return Err(format_err!("[{}] Copies out of bounds.", FORMAT.name))
+                        .with_kind(ErrorKind::OutOfBounds);
+                }
+
+                bytes.extend_from_slice(&prev[offset..offset + len]);
+            }
+            Delta::Insert(ref v) => bytes.extend_from_slice(v),
+        }
+    }
+
+    Ok(bytes)
+}
+
+// The approximated size of deltas when serialized.
+fn cost(deltas: &[Delta]) -> usize {
+    deltas
+        .iter()
+        .map(|v| match *v {
+            Delta::Copy(_, _) => 20,
+            Delta::Insert(ref v) => v.len() + 12,
+        })
+        .sum()
+}
+
+// Lists the files of bundle in order, the manifest is always included.
+fn files(fs: &VFS) -> Result<Vec<String>> {
+    files_with(&|v: &str| fs.read_bytes(v.as_ref()))
+}
+
+fn files_with<F>(read: &F) -> Result<Vec<String>>
+where
+    F: Fn(&str) -> Result<FileBytes>,
+{
+    let bytes = read(manifest::NAME)?;
+    let man = Manifest::load(&mut &bytes[..])?;
+
+    let uuids = man.items.iter().map(|v| v.uuid);
+    let variants = man.variants.iter().map(|v| v.variant);
+
+    let mut files: Vec<_> = uuids
+        .chain(variants)
+        .map(|v| format!("{:X}", v.simple()))
+        .collect();
+
+    files.push(manifest::NAME.to_owned());
+    files.sort();
+    files.dedup();
+    Ok(files)
+}
+
+// Reads the file from cache if its patched before, the removed files are read from
+// cache too, where they do not exist.
+fn read(base: &VFS, cache: &VFS, location: &str) -> Result<FileBytes> {
+    let location: &Path = location.as_ref();
+    if cache.exists(location) || cache.exists(&whiteout(location)) {
+        cache.read_bytes(location)
+    } else {
+        base.read_bytes(location)
+    }
+}
+
+fn rollback(cache: &VFS, journal: &Journal) -> Result<()> {
+    for &(ref v, backup) in &journal.entries {
+        let location: &Path = v.as_ref();
+        if backup {
+            let path = Path::new(BACKUP).join(location);
+            let bytes = cache.read_bytes(&path)?;
+            cache.write(location, &bytes)?;
+            cache.remove(&path)?;
+        } else if cache.exists(location) {
+            cache.remove(location)?;
+        }
+    }
+
+    cache.remove(JOURNAL.as_ref())
+}
+
+fn encode_u64(v: u64) -> [u8; 8] {
+    let mut bytes = [0; 8];
+    for (i, b) in bytes.iter_mut().enumerate() {
+        *b = (v >> (i * 8)) as u8;
+    }
+
+    bytes
+}
+
+fn decode_u64(bytes: &[u8]) -> u64 {
+    bytes
+        .iter()
+        .enumerate()
+        .fold(0, |acc, (i, &b)| acc | (u64::from(b) << (i * 8)))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::env;
+    use std::fs;
+
+    use res::manifest::ManifestItem;
+    use res::vfs::{DiskFS, OverlayFS};
+    use uuid::Uuid;
+
+    fn bundle(name: &str, files: &[(u8, &[u8])]) -> DiskFS {
+        let root = env::temp_dir().join("crayon_patch").join(name);
+        let _ = fs::remove_dir_all(&root);
+        let disk = DiskFS::create(&root).unwrap();
+
+        let mut man = Manifest::new();
+        for &(id, bytes) in files {
+            let uuid = Uuid::from_bytes(&[id; 16]).unwrap();
+            let location = format!("{:X}", uuid.simple());
+            disk.write(location.as_ref(), bytes).unwrap();
+            man.items.push(ManifestItem {
+                location: Path::new(&location).into(),
+                uuid: uuid,
+            });
+        }
+
+        let mut bytes = Vec::new();
+        manifest::FORMAT.write_header(&mut bytes).unwrap();
+        bincode::serialize_into(&mut bytes, &man).unwrap();
+        disk.write(manifest::NAME.as_ref(), &bytes).unwrap();
+        disk
+    }
+
+    fn location(id: u8) -> String {
+        format!("{:X}", Uuid::from_bytes(&[id; 16]).unwrap().simple())
+    }
+
+    #[test]
+    fn incremental_checksum() {
+        let bytes: Vec<u8> = (0..1000).map(|v| (v * 7) as u8).collect();
+        let mut hasher = Checksum::new();
+        for v in bytes.chunks(33) {
+            hasher.update(v);
+        }
+
+        assert_eq!(hasher.finish(), checksum(&bytes));
+        assert_eq!(Checksum::new().finish(), checksum(&[]));
+    }
+
+    #[test]
+    fn delta() {
+        let prev: Vec<u8> = (0..255).collect();
+        let mut next = prev.clone();
+        next[100] = 0;
+
+        let deltas = diff(&prev, &next);
+        assert_eq!(
+            deltas,
+            vec![
+                Delta::Copy(0, 100),
+                Delta::Insert(vec![0]),
+                Delta::Copy(101, 154),
+            ]
+        );
+
+        assert_eq!(patch(&prev, &deltas).unwrap(), next);
+        assert_eq!(patch(&prev, &diff(&prev, &prev)).unwrap(), prev);
+        assert_eq!(patch(&prev, &diff(&[], &next)).unwrap(), next);
+        assert!(patch(&prev, &[Delta::Copy(250, 10)]).is_err());
+    }
+
+    #[test]
+    fn apply() {
+        let large: Vec<u8> = (0..1024).map(|v| (v * 7) as u8).collect();
+        let mut changed = large.clone();
+        changed[512] ^= 0xff;
+
+        let prev = bundle("prev", &[(1, &large), (2, b"removed"), (3, b"same")]);
+        let next = bundle("next", &[(1, &changed), (3, b"same"), (4, b"added")]);
+
+        let v = Patch::diff(&prev, &next).unwrap();
+        let mut bytes = Vec::new();
+        v.save(&mut bytes).unwrap();
+        let v = Patch::load(&mut &bytes[..]).unwrap();
+
+        assert_eq!(v.entries.len(), 4);
+        assert_eq!(v.entries.last().unwrap().location, manifest::NAME);
+        match v.entries[0].op {
+            PatchOp::Delta { .. } => {}
+            _ => panic!("The changed file should be patched with deltas."),
+        }
+
+        let cache = bundle("cache", &[]);
+        cache.remove(manifest::NAME.as_ref()).unwrap();
+
+        // The patch is rejected if the bundle is not the one it applies to.
+        assert!(v.apply(&next, &cache).is_err());
+        assert_eq!(version(&cache).unwrap(), None);
+
+        // The corrupted patches are rejected without touching the cache.
+        let mut corrupted = v.clone();
+        corrupted.entries[1].op = PatchOp::Replace(b"corrupted".to_vec());
+        assert!(corrupted.apply(&prev, &cache).is_err());
+        assert!(!cache.exists(location(4).as_ref()));
+        assert_eq!(version(&cache).unwrap(), None);
+
+        assert!(v.apply(&prev, &cache).unwrap());
+        assert!(!v.apply(&prev, &cache).unwrap());
+        assert_eq!(version(&cache).unwrap(), Some(v.to));
+        assert!(!cache.exists(JOURNAL.as_ref()));
+
+        let patched = OverlayFS::new(cache, prev);
+        assert_eq!(digest(&patched).unwrap(), digest(&next).unwrap());
+        let bytes = patched.read_bytes(location(1).as_ref()).unwrap();
+        assert_eq!(&bytes[..], &changed[..]);
+
+        // The removed file is hidden from the shipped bundle.
+        assert!(patched.lower().exists(location(2).as_ref()));
+        assert!(!patched.exists(location(2).as_ref()));
+        assert!(patched.read_bytes(location(2).as_ref()).is_err());
+    }
+
+    #[test]
+    fn whiteout() {
+        let lower = bundle("whiteout_lower", &[(1, b"lower")]);
+        let upper = bundle("whiteout_upper", &[]);
+        let fs = OverlayFS::new(upper, lower);
+        let path = location(1);
+        let path: &Path = path.as_ref();
+
+        fs.remove(path).unwrap();
+        assert!(!fs.exists(path));
+        assert!(fs.lower().exists(path));
+        assert!(fs.remove(path).is_err());
+
+        fs.write(path, b"upper").unwrap();
+        assert_eq!(&fs.read_bytes(path).unwrap()[..], b"upper");
+
+        // Removes the file in both layers.
+        fs.remove(path).unwrap();
+        assert!(!fs.exists(path));
+        assert!(!fs.upper().exists(path));
+    }
+
+    #[test]
+    fn rollback() {
+        let cache = bundle("rollback", &[(1, b"prev")]);
+        let journal = Journal {
+            entries: vec![(location(1), true), (location(2), false)],
+        };
+
+        // Simulates a crash in the middle of commit.
+        let path = Path::new(BACKUP).join(location(1));
+        cache.write(&path, b"prev").unwrap();
+        cache.write(JOURNAL.as_ref(), &bincode::serialize(&journal).unwrap()).unwrap();
+        cache.write(location(1).as_ref(), b"next").unwrap();
+        cache.write(location(2).as_ref(), b"next").unwrap();
+
+        assert!(recover(&cache).unwrap());
+        assert!(!recover(&cache).unwrap());
+
+        let bytes = cache.read_bytes(location(1).as_ref()).unwrap();
+        assert_eq!(&bytes[..], b"prev");
+        assert!(!cache.exists(location(2).as_ref()));
+        assert!(!cache.exists(&path));
+    }
+}
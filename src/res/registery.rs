@@ -1,7 +1,8 @@
 use std::any::{Any, TypeId};
 use std::collections::HashMap;
-use std::path::Path;
+use std::path::{Path, PathBuf};
 use std::sync::{Arc, Condvar, Mutex};
+use std::time::{Duration, Instant, SystemTime};
 use uuid::Uuid;
 
 use errors::*;
@@ -12,6 +13,7 @@ use utils::hash_value::HashValue;
 
 use super::location::Location;
 use super::manifest;
+use super::variant::VariantPolicy;
 use super::vfs::{VFSDriver, VFS};
 use super::{ResourceHandle, ResourceLoader};
 
@@ -86,6 +88,21 @@ struct Entry {
     latch: Arc<PromiseLatch>,
 }
 
+/// The interval between the checks of watched files in hot-reload mode.
+const HOT_RELOAD_INTERVAL_MS: u64 = 250;
+
+type Reload = Arc<Fn(&[u8]) -> Result<()> + Send + Sync>;
+
+// The file which is reloaded once it's modified in hot-reload mode. The modification
+// time of file is compared with the one seen last time instead of the wall clock, so
+// the files replaced with older ones or touched within the same tick are picked up.
+struct Watch {
+    fs: HashValue<str>,
+    path: PathBuf,
+    mtime: Option<SystemTime>,
+    reload: Reload,
+}
+
 pub struct Registery {
     sched: Arc<ScheduleSystemShared>,
     locs: HashMap<Uuid, SchemaHandle>,
@@ -94,6 +111,13 @@ pub struct Registery {
     driver: VFSDriver,
     manifest: HashMap<Uuid, HashValue<str>>,
     remaps: HashMap<HashValue<Path>, Uuid>,
+    variants: HashMap<Uuid, Vec<(Vec<String>, Uuid)>>,
+    policy: VariantPolicy,
+
+    hot_reload: bool,
+    last_poll: Instant,
+    watches: HashMap<SchemaHandle, Watch>,
+    files: HashMap<(HashValue<str>, HashValue<Path>), Watch>,
 }
 
 impl Registery {
@@ -105,7 +129,41 @@ impl Registery {
             driver: VFSDriver::new(),
             manifest: HashMap::new(),
             remaps: HashMap::new(),
+            variants: HashMap::new(),
+            policy: VariantPolicy::default(),
+            hot_reload: false,
+            last_poll: Instant::now(),
+            watches: HashMap::new(),
+            files: HashMap::new(),
+        }
+    }
+
+    /// Enables the hot-reload mode, it only affects the resources loaded afterwards.
+    #[inline]
+    pub fn set_hot_reload(&mut self, enable: bool) {
+        self.hot_reload = enable;
+    }
+
+    #[inline]
+    pub fn set_variant_policy(&mut self, policy: VariantPolicy) {
+        self.policy = policy;
+    }
+
+    #[inline]
+    pub fn variant_policy(&self) -> &VariantPolicy {
+        &self.policy
+    }
+
+    /// Resolves the uuid of resource into the variant selected by policy.
+    pub fn resolve(&self, uuid: Uuid) -> Uuid {
+        if let Some(variants) = self.variants.get(&uuid) {
+            let candidates = variants.iter().map(|v| v.0.as_slice());
+            if let Some(i) = self.policy.select(candidates) {
+                return variants[i].1;
+            }
         }
+
+        uuid
     }
 
     pub fn mount<F>(&mut self, name: &str, vfs: F) -> Result<()>
@@ -114,16 +172,35 @@ impl Registery {
     {
         info!("Mounts virtual file system {}.", name);
 
-        let mut file = vfs.read(manifest::NAME.as_ref())?;
-        let name = name.into();
+        let hash = name.into();
 
-        let man = manifest::Manifest::load(&mut file)?;
-        for v in &man.items {
-            self.manifest.insert(v.uuid, name);
-            self.remaps.insert(v.location, v.uuid);
+        // File systems without manifest (e.g. save directories) could only be accessed
+        // as plain files.
+        if vfs.exists(manifest::NAME.as_ref()) {
+            let mut file = vfs.read(manifest::NAME.as_ref())?;
+            let man = manifest::Manifest::load(&mut file)?;
+            for v in &man.items {
+                self.manifest.insert(v.uuid, hash);
+                self.remaps.insert(v.location, v.uuid);
+            }
+
+            for v in &man.variants {
+                self.manifest.insert(v.variant, hash);
+                self.variants
+                    .entry(v.uuid)
+                    .or_insert_with(Vec::new)
+                    .push((v.tags.clone(), v.variant));
+            }
+        } else {
+            info!("Virtual file system {} has no manifest.", name);
         }
 
-        self.driver.mount(name, vfs)
+        self.driver.mount(hash, vfs)
+    }
+
+    #[inline]
+    pub fn driver(&self) -> &VFSDriver {
+        &self.driver
     }
 
     pub fn load_from<T>(&mut self, loader: Arc<Any + Send + Sync>, location: Location) -> Result<T>
@@ -135,7 +212,8 @@ impl Registery {
                 if let Some(fs) = self.manifest.get(&uuid) {
                     (*fs, uuid)
                 } else {
-                    bail!("Uuid {:X} not found.", uuid);
+                    return Err(format_err!("Uuid {:X} not found.", uuid))
+                        .with_kind(ErrorKind::Io);
                 }
             }
 
@@ -144,11 +222,15 @@ impl Registery {
                 if let Some(uuid) = self.remaps.get(&hash) {
                     (fs, *uuid)
                 } else {
-                    bail!("File {:?} not found.", file);
+                    return Err(format_err!("File {:?} not found.", file))
+                        .with_kind(ErrorKind::Io);
                 }
             }
         };
 
+        let uuid = self.resolve(uuid);
+        let fs = self.manifest.get(&uuid).cloned().unwrap_or(fs);
+
         if let Some(k) = self.locs.get(&uuid) {
             let v = self.entries.get_mut(k).unwrap();
             v.rc += 1;
@@ -176,16 +258,48 @@ impl Registery {
         self.entries.insert(sh, v);
 
         let path = format!("{:X}", uuid.simple());
-        let mut file = self.driver.read(fs, path.as_ref())?;
+        let vfs = self.driver.get(fs)?;
+
+        if self.hot_reload {
+            let loader = loader.clone();
+            let reload = move |bytes: &[u8]| {
+                let dc: &T::Loader = (loader.as_ref() as &Any).downcast_ref().unwrap();
+                dc.reload(sh.handle.into(), bytes)
+            };
+
+            let watch = Watch {
+                fs: fs,
+                path: path.clone().into(),
+                mtime: vfs.modified(path.as_ref()),
+                reload: Arc::new(reload),
+            };
 
+            self.watches.insert(sh, watch);
+        }
+
+        // The file is read (or mapped) on the workers, so large files would not stall
+        // the main thread.
         self.sched.spawn(move || {
             let dc: &T::Loader = (loader.as_ref() as &Any).downcast_ref().unwrap();
-            latch.set(dc.load(handle, &mut file));
+            let result = vfs
+                .read_encoded(path.as_ref())
+                .and_then(|bytes| vfs.decode(path.as_ref(), bytes))
+                .and_then(|bytes| dc.load_shared(handle, bytes.into()));
+
+            latch.set(result.with_location(path));
         });
 
         Ok(handle)
     }
 
+    /// Lists the uuids and reference counts of loaded resources.
+    pub fn loaded(&self) -> Vec<(Uuid, u32)> {
+        self.locs
+            .iter()
+            .filter_map(|(uuid, sh)| self.entries.get(sh).map(|v| (*uuid, v.rc)))
+            .collect()
+    }
+
     pub fn promise<T>(&self, handle: T) -> Option<Arc<PromiseLatch>>
     where
         T: ResourceHandle,
@@ -210,6 +324,7 @@ impl Registery {
         if let Some(v) = self.entries.get_mut(&sh) {
             v.rc -= 1;
             if v.rc <= 0 {
+                self.watches.remove(&sh);
                 let dc: &T::Loader = (loader.as_ref() as &Any).downcast_ref().unwrap();
                 return dc.delete(handle);
             }
@@ -217,4 +332,157 @@ impl Registery {
 
         Ok(())
     }
+
+    /// Watches the plain file, `reload` is called with its contents on the `sched`
+    /// workers when it's modified in hot-reload mode.
+    pub fn watch(&mut self, fs: HashValue<str>, file: PathBuf, reload: Reload) -> Result<()> {
+        if !self.driver.exists(fs, &file)? {
+            bail!("File {:?} not found.", file);
+        }
+
+        let watch = Watch {
+            fs: fs,
+            mtime: self.driver.modified(fs, &file)?,
+            path: file,
+            reload: reload,
+        };
+
+        self.files.insert((fs, (&watch.path).into()), watch);
+        Ok(())
+    }
+
+    /// Stops watching the plain file.
+    pub fn unwatch(&mut self, fs: HashValue<str>, file: &Path) {
+        self.files.remove(&(fs, file.into()));
+    }
+
+    /// Checks the watched files periodically in hot-reload mode, and reloads the
+    /// modified ones.
+    pub fn advance(&mut self) {
+        let interval = Duration::from_millis(HOT_RELOAD_INTERVAL_MS);
+        if !self.hot_reload || self.last_poll.elapsed() < interval {
+            return;
+        }
+
+        self.last_poll = Instant::now();
+
+        let driver = &self.driver;
+        let sched = &self.sched;
+        let entries = &self.entries;
+
+        // The resources are not reloaded until they have been loaded at least once.
+        let watches = self
+            .watches
+            .iter_mut()
+            .filter(|&(k, _)| entries.get(k).map_or(false, |v| v.latch.is_set()))
+            .map(|(_, v)| v)
+            .chain(self.files.values_mut());
+
+        for v in watches {
+            let mtime = driver.modified(v.fs, &v.path).unwrap_or(None);
+            if mtime.is_none() || mtime == v.mtime {
+                continue;
+            }
+
+            v.mtime = mtime;
+            info!("Hot-reloads {:?}.", v.path);
+
+            let vfs = match driver.get(v.fs) {
+                Ok(vfs) => vfs,
+                Err(err) => {
+                    warn!("Failed to reload {:?}. {}", v.path, err);
+                    continue;
+                }
+            };
+
+            let path = v.path.clone();
+            let reload = v.reload.clone();
+            sched.spawn(move || {
+                let result = vfs
+                    .read_encoded(&path)
+                    .and_then(|bytes| vfs.decode(&path, bytes))
+                    .and_then(|bytes| reload(&bytes));
+                if let Err(err) = result {
+                    warn!("Failed to reload {:?}. {}", path, err);
+                }
+            });
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use res::vfs::DiskFS;
+    use sched::ScheduleSystem;
+    use std::env;
+    use std::fs;
+    use std::thread;
+    use std::time::UNIX_EPOCH;
+
+    // Polls the watched files immediately, and waits for the reloads on workers.
+    fn advance(registery: &mut Registery, reloads: &Mutex<Vec<Vec<u8>>>, n: usize) {
+        registery.last_poll = Instant::now() - Duration::from_millis(HOT_RELOAD_INTERVAL_MS);
+        registery.advance();
+
+        for _ in 0..100 {
+            if reloads.lock().unwrap().len() >= n {
+                break;
+            }
+
+            thread::sleep(Duration::from_millis(10));
+        }
+    }
+
+    #[test]
+    fn watch() {
+        let root = env::temp_dir().join("crayon_registery_watch");
+        let _ = fs::remove_dir_all(&root);
+
+        let disk = DiskFS::create(&root).unwrap();
+        let location: &Path = "settings.toml".as_ref();
+        disk.write(location, b"v0").unwrap();
+
+        let sched = ScheduleSystem::new(2, None, None);
+        let mut registery = Registery::new(sched.shared());
+        registery.mount("save", disk).unwrap();
+
+        let reloads = Arc::new(Mutex::new(Vec::new()));
+        let reload: Reload = {
+            let reloads = reloads.clone();
+            Arc::new(move |bytes: &[u8]| {
+                reloads.lock().unwrap().push(bytes.to_vec());
+                Ok(())
+            })
+        };
+
+        let name = "save".into();
+        let missing = "missing.toml".into();
+        assert!(registery.watch(name, missing, reload.clone()).is_err());
+        registery.watch(name, location.into(), reload).unwrap();
+
+        // The files are only checked in hot-reload mode.
+        registery.driver.write(name, location, b"v1").unwrap();
+        registery.files.values_mut().next().unwrap().mtime = Some(UNIX_EPOCH);
+        advance(&mut registery, &reloads, 1);
+        assert!(reloads.lock().unwrap().is_empty());
+
+        // Any change of modification time triggers reloading, even if it goes back.
+        registery.set_hot_reload(true);
+        advance(&mut registery, &reloads, 1);
+        assert_eq!(*reloads.lock().unwrap(), [b"v1".to_vec()]);
+
+        // The unchanged files are not reloaded again.
+        advance(&mut registery, &reloads, 2);
+        assert_eq!(reloads.lock().unwrap().len(), 1);
+
+        registery.unwatch(name, location);
+        assert!(registery.files.is_empty());
+        registery.driver.write(name, location, b"v2").unwrap();
+        advance(&mut registery, &reloads, 2);
+        assert_eq!(reloads.lock().unwrap().len(), 1);
+
+        sched.terminate();
+        let _ = fs::remove_dir_all(&root);
+    }
 }
@@ -0,0 +1,172 @@
+//! Platform and quality specific variants of resources.
+//!
+//! A resource in manifest could have several variants, e.g. `texture.astc` for mobile
+//! devices and `texture.dxt` for desktop, or half-res textures on low tier devices. Every
+//! variant is tagged, and the `VariantPolicy` configured at startup decides which one is
+//! loaded when the resource is requested by its location or uuid.
+//!
+//! ```rust,ignore
+//! let mut settings = Settings::default();
+//! settings.res.variants = VariantPolicy::platform().with("low");
+//! ```
+
+/// The set of enabled variant tags, in the order of preference.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct VariantPolicy {
+    tags: Vec<String>,
+}
+
+impl Default for VariantPolicy {
+    fn default() -> Self {
+        VariantPolicy::platform()
+    }
+}
+
+impl VariantPolicy {
+    /// Creates a policy without any tags, which always loads the resources themselves.
+    pub fn new() -> Self {
+        VariantPolicy { tags: Vec::new() }
+    }
+
+    /// Creates a policy with the tags of current platform, which are the name of
+    /// operating system (e.g. `android`), `mobile` or `desktop`, and the preferred
+    /// texture compression (`astc` or `dxt`).
+    pub fn platform() -> Self {
+        let os = if cfg!(target_os = "android") {
+            "android"
+        } else if cfg!(target_os = "ios") {
+            "ios"
+        } else if cfg!(target_os = "windows") {
+            "windows"
+        } else if cfg!(target_os = "macos") {
+            "macos"
+        } else if cfg!(target_os = "linux") {
+            "linux"
+        } else {
+            "unknown"
+        };
+
+        let mobile = cfg!(any(target_os = "android", target_os = "ios"));
+        let policy = VariantPolicy::new().with(os);
+
+        if mobile {
+            policy.with("mobile").with("astc")
+        } else {
+            policy.with("desktop").with("dxt")
+        }
+    }
+
+    /// Enables the tag with lower preference than the existing ones.
+    pub fn with<T: Into<String>>(mut self, tag: T) -> Self {
+        self.enable(tag);
+        self
+    }
+
+    /// Enables the tag with lower preference than the existing ones.
+    pub fn enable<T: Into<String>>(&mut self, tag: T) {
+        let tag = tag.into();
+        if !self.contains(&tag) {
+            self.tags.push(tag);
+        }
+    }
+
+    pub fn disable(&mut self, tag: &str) {
+        self.tags.retain(|v| v != tag);
+    }
+
+    #[inline]
+    pub fn contains(&self, tag: &str) -> bool {
+        self.tags.iter().any(|v| v == tag)
+    }
+
+    /// Gets the enabled tags in the order of preference.
+    #[inline]
+    pub fn tags(&self) -> &[String] {
+        &self.tags
+    }
+
+    /// Selects the variant from the tags of candidates, returns its index. A variant is
+    /// only eligible if all of its tags are enabled, and the one with most tags wins,
+    /// the ties are broken by the preference of tags.
+    pub fn select<'a, I>(&self, candidates: I) -> Option<usize>
+    where
+        I: IntoIterator<Item = &'a [String]>,
+    {
+        let mut best: Option<(usize, usize, usize)> = None;
+
+        for (i, tags) in candidates.into_iter().enumerate() {
+            let mut rank = 0;
+            let mut eligible = true;
+
+            for tag in tags {
+                match self.tags.iter().position(|v| v == tag) {
+                    Some(v) => rank += v,
+                    None => {
+                        eligible = false;
+                        break;
+                    }
+                }
+            }
+
+            if !eligible {
+                continue;
+            }
+
+            let better = match best {
+                Some((_, len, r)) => tags.len() > len || (tags.len() == len && rank < r),
+                None => true,
+            };
+
+            if better {
+                best = Some((i, tags.len(), rank));
+            }
+        }
+
+        best.map(|v| v.0)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn tags(v: &[&str]) -> Vec<String> {
+        v.iter().map(|v| v.to_string()).collect()
+    }
+
+    #[test]
+    fn select() {
+        let candidates = vec![
+            tags(&["astc"]),
+            tags(&["dxt"]),
+            tags(&["dxt", "low"]),
+            tags(&["etc"]),
+        ];
+
+        let select = |policy: &VariantPolicy| {
+            policy.select(candidates.iter().map(|v| v.as_slice()))
+        };
+
+        assert_eq!(select(&VariantPolicy::new()), None);
+        assert_eq!(select(&VariantPolicy::new().with("astc")), Some(0));
+        assert_eq!(select(&VariantPolicy::new().with("dxt")), Some(1));
+
+        // The most specific variant wins.
+        let mut policy = VariantPolicy::new().with("dxt").with("low");
+        assert_eq!(select(&policy), Some(2));
+
+        policy.disable("low");
+        assert_eq!(select(&policy), Some(1));
+
+        // Ties are broken by preference.
+        assert_eq!(select(&VariantPolicy::new().with("etc").with("astc")), Some(3));
+        assert_eq!(select(&VariantPolicy::new().with("astc").with("etc")), Some(0));
+    }
+
+    #[test]
+    fn platform() {
+        let policy = VariantPolicy::platform();
+        assert!(policy.contains("mobile") != policy.contains("desktop"));
+        assert!(policy.contains("astc") || policy.contains("dxt"));
+    }
+}
@@ -0,0 +1,230 @@
+use std::fmt;
+use std::io::Read;
+use std::mem;
+use std::ops::{Deref, DerefMut, Range};
+use std::slice;
+use std::sync::Arc;
+
+use memmap::Mmap;
+
+use errors::*;
+
+/// The minimum alignment of the first byte in `FileBytes`.
+pub const FILE_BYTES_ALIGNMENT: usize = 8;
+
+/// The contents of a file, which is either memory-mapped or read into the memory.
+///
+/// The first byte is always aligned to `FILE_BYTES_ALIGNMENT` (the memory-mapped files
+/// are page aligned), so the data at aligned offsets could be reinterpreted and handed to
+/// the GPU upload path without an intermediate copy.
+pub enum FileBytes {
+    Mapped(Mmap),
+    Owned(Vec<u64>, usize),
+}
+
+impl FileBytes {
+    /// Reads all the bytes from `file` into a properly aligned buffer.
+    pub fn read(file: &mut dyn Read) -> Result<Self> {
+        let mut bytes = Vec::new();
+        file.read_to_end(&mut bytes)?;
+        Ok(FileBytes::from_slice(&bytes))
+    }
+
+    /// Copies the bytes into a properly aligned buffer.
+    pub fn from_slice(bytes: &[u8]) -> Self {
+        let len = bytes.len();
+        let mut buf = vec![0u64; (len + mem::size_of::<u64>() - 1) / mem::size_of::<u64>()];
+
+        unsafe {
+            let dst = slice::from_raw_parts_mut(buf.as_mut_ptr() as *mut u8, len);
+            dst.copy_from_slice(bytes);
+        }
+
+        FileBytes::Owned(buf, len)
+    }
+}
+
+impl Deref for FileBytes {
+    type Target = [u8];
+
+    fn deref(&self) -> &[u8] {
+        match *self {
+            FileBytes::Mapped(ref v) => &v[..],
+            FileBytes::Owned(ref v, len) => unsafe {
+                slice::from_raw_parts(v.as_ptr() as *const u8, len)
+            },
+        }
+    }
+}
+
+impl DerefMut for FileBytes {
+    /// Gets the mutable contents, the memory-mapped file is copied into memory first.
+    fn deref_mut(&mut self) -> &mut [u8] {
+        if let FileBytes::Mapped(_) = *self {
+            let bytes = FileBytes::from_slice(&**self);
+            *self = bytes;
+        }
+
+        match *self {
+            FileBytes::Owned(ref mut v, len) => unsafe {
+                slice::from_raw_parts_mut(v.as_mut_ptr() as *mut u8, len)
+            },
+            FileBytes::Mapped(_) => unreachable!(),
+        }
+    }
+}
+
+impl AsMut<[u8]> for FileBytes {
+    fn as_mut(&mut self) -> &mut [u8] {
+        self
+    }
+}
+
+impl AsRef<[u8]> for FileBytes {
+    fn as_ref(&self) -> &[u8] {
+        self
+    }
+}
+
+/// A shared range of `FileBytes`, which keeps the whole contents (e.g. the memory
+/// mapping) alive until the last slice is dropped. It could be passed to other threads,
+/// e.g. the GPU upload path, without copying the underlying bytes.
+#[derive(Clone)]
+pub struct FileSlice {
+    bytes: Arc<FileBytes>,
+    range: Range<usize>,
+}
+
+impl FileSlice {
+    /// Creates a slice with `range` of `bytes`.
+    pub fn new(bytes: Arc<FileBytes>, range: Range<usize>) -> Result<Self> {
+        if range.start > range.end || range.end > bytes.len() {
+            bail!("The range {:?} is out of bounds ({}).", range, bytes.len());
+        }
+
+        Ok(FileSlice {
+            bytes: bytes,
+            range: range,
+        })
+    }
+
+    /// Gets the slice with `range` of this slice.
+    pub fn slice(&self, range: Range<usize>) -> Result<Self> {
+        let range = (range.start + self.range.start)..(range.end + self.range.start);
+        if range.end > self.range.end {
+            bail!("The range {:?} is out of bounds ({:?}).", range, self.range);
+        }
+
+        FileSlice::new(self.bytes.clone(), range)
+    }
+}
+
+impl From<FileBytes> for FileSlice {
+    fn from(bytes: FileBytes) -> Self {
+        let len = bytes.len();
+        FileSlice {
+            bytes: Arc::new(bytes),
+            range: 0..len,
+        }
+    }
+}
+
+impl Deref for FileSlice {
+    type Target = [u8];
+
+    fn deref(&self) -> &[u8] {
+        &self.bytes[self.range.clone()]
+    }
+}
+
+impl AsRef<[u8]> for FileSlice {
+    fn as_ref(&self) -> &[u8] {
+        self
+    }
+}
+
+impl fmt::Debug for FileSlice {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "FileSlice({:?})", self.range)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::env;
+    use std::fs;
+
+    fn is_aligned(bytes: &[u8]) -> bool {
+        bytes.as_ptr() as usize % FILE_BYTES_ALIGNMENT == 0
+    }
+
+    #[test]
+    fn owned() {
+        let src: Vec<u8> = (0..33).collect();
+
+        for len in 0..src.len() {
+            let bytes = FileBytes::from_slice(&src[..len]);
+            assert_eq!(&bytes[..], &src[..len]);
+            assert!(is_aligned(&bytes));
+
+            // Copies from an unaligned source.
+            let bytes = FileBytes::from_slice(&src[1..len.max(1)]);
+            assert_eq!(&bytes[..], &src[1..len.max(1)]);
+            assert!(is_aligned(&bytes));
+        }
+
+        let bytes = FileBytes::read(&mut &src[3..]).unwrap();
+        assert_eq!(&bytes[..], &src[3..]);
+        assert!(is_aligned(&bytes));
+
+        let mut bytes = FileBytes::from_slice(&src[..5]);
+        bytes[4] = 0xFF;
+        assert_eq!(&bytes[..], &[0u8, 1, 2, 3, 0xFF]);
+    }
+
+    #[test]
+    fn mapped() {
+        let path = env::temp_dir().join("crayon_file_bytes_mapped");
+        fs::write(&path, b"crayon").unwrap();
+
+        {
+            let file = fs::File::open(&path).unwrap();
+            let mut bytes = FileBytes::Mapped(unsafe { Mmap::map(&file).unwrap() });
+            assert_eq!(&bytes[..], b"crayon");
+            assert!(is_aligned(&bytes));
+
+            // Writes go to a private copy instead of the file.
+            bytes[0] = b'C';
+            match bytes {
+                FileBytes::Owned(_, len) => assert_eq!(len, 6),
+                FileBytes::Mapped(_) => panic!("mapped bytes must be copied before writing."),
+            }
+
+            assert_eq!(&bytes[..], b"Crayon");
+            assert!(is_aligned(&bytes));
+        }
+
+        assert_eq!(&fs::read(&path).unwrap()[..], b"crayon");
+        let _ = fs::remove_file(&path);
+    }
+
+    #[test]
+    fn shared() {
+        let src: Vec<u8> = (0..16).collect();
+        let bytes = FileSlice::from(FileBytes::from_slice(&src));
+        assert_eq!(&bytes[..], &src[..]);
+        assert!(is_aligned(&bytes));
+
+        // The sub-slices share the same contents without copying.
+        let v = bytes.slice(2..10).unwrap();
+        let w = v.slice(4..6).unwrap();
+        assert_eq!(&v[..], &src[2..10]);
+        assert_eq!(&w[..], &src[6..8]);
+        assert_eq!(w.as_ptr(), bytes[6..].as_ptr());
+
+        assert!(v.slice(4..9).is_err());
+        assert!(FileSlice::new(Arc::new(FileBytes::from_slice(&src)), 8..17).is_err());
+        assert_eq!(format!("{:?}", w), "FileSlice(6..8)");
+    }
+}
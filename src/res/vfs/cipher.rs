@@ -0,0 +1,598 @@
+//! Encrypted virtual file system, which protects the shipped assets from casual
+//! extraction and tampering.
+//!
+//! Every file is encrypted with XChaCha20-Poly1305 and prefixed with a nonce taken from
+//! the random number generator of OS, the location of file is authenticated also, so
+//! files could not be swapped with each other.
+//!
+//! ```rust,ignore
+//! let key = CipherKey::new(*include_bytes!("../secret.key"));
+//! res.mount("res", CipherFS::new(DiskFS::new("packages/res")?, key))?;
+//! ```
+//!
+//! The contents are read as is when requested by resource loaders, and decrypted by the
+//! `sched` workers right before loading. The packaging tools could produce the encrypted
+//! files with `CipherFS::encrypt`.
+
+use std::fmt;
+use std::io::{Cursor, Read};
+use std::path::Path;
+use std::time::SystemTime;
+
+use rand::rngs::OsRng;
+use rand::RngCore;
+
+use errors::*;
+use res::format::{AssetFormat, Version};
+
+use super::{FileBytes, VFS};
+
+pub const FORMAT: AssetFormat = AssetFormat {
+    name: "CipherFS",
+    magic: [b'C', b'R', b'Y', b'E'],
+    version: Version {
+        major: 0,
+        minor: 1,
+        patch: 0,
+    },
+};
+
+/// The size of nonce in bytes.
+pub const NONCE_SIZE: usize = 24;
+/// The size of authentication tag in bytes.
+pub const TAG_SIZE: usize = 16;
+
+/// The 256 bits secret key.
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub struct CipherKey([u8; 32]);
+
+impl CipherKey {
+    pub fn new(bytes: [u8; 32]) -> Self {
+        CipherKey(bytes)
+    }
+}
+
+// Never prints the secret into logs.
+impl fmt::Debug for CipherKey {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "CipherKey(..)")
+    }
+}
+
+/// The wrapper of file system whose files are encrypted.
+pub struct CipherFS<F: VFS> {
+    fs: F,
+    key: CipherKey,
+}
+
+impl<F: VFS> CipherFS<F> {
+    pub fn new(fs: F, key: CipherKey) -> Self {
+        CipherFS { fs: fs, key: key }
+    }
+
+    /// Encrypts the `plain` contents of file at location with `nonce`, which must be
+    /// unique for every encryption with the same key.
+    pub fn encrypt(
+        key: &CipherKey,
+        location: &Path,
+        nonce: [u8; NONCE_SIZE],
+        plain: &[u8],
+    ) -> Vec<u8> {
+        let header = FORMAT.header();
+        let mut bytes = Vec::with_capacity(header.len() + NONCE_SIZE + plain.len() + TAG_SIZE);
+        bytes.extend_from_slice(&header);
+        bytes.extend_from_slice(&nonce);
+
+        let offset = bytes.len();
+        bytes.extend_from_slice(plain);
+
+        let aad = Self::aad(location);
+        let tag = seal(&key.0, &nonce, aad.as_bytes(), &mut bytes[offset..]);
+        bytes.extend_from_slice(&tag);
+        bytes
+    }
+
+    /// Authenticates and decrypts the contents of file at location.
+    pub fn decrypt(key: &CipherKey, location: &Path, bytes: &[u8]) -> Result<FileBytes> {
+        let version = FORMAT.read_header(&mut &bytes[..])?;
+        if !version.is_compatible_with(FORMAT.version) {
+            bail!("[{}] Unsupported version {}.", FORMAT.name, version);
+        }
+
+        let header = FORMAT.header().len();
+        if bytes.len() < header + NONCE_SIZE + TAG_SIZE {
+            bail!("[{}] File {:?} is truncated.", FORMAT.name, location);
+        }
+
+        let mut nonce = [0; NONCE_SIZE];
+        nonce.copy_from_slice(&bytes[header..header + NONCE_SIZE]);
+
+        let body = &bytes[header + NONCE_SIZE..];
+        let (cipher, tag) = body.split_at(body.len() - TAG_SIZE);
+        let mut file = FileBytes::from_slice(cipher);
+
+        let aad = Self::aad(location);
+        if !open(&key.0, &nonce, aad.as_bytes(), file.as_mut(), tag) {
+            bail!("[{}] File {:?} is corrupted or tampered.", FORMAT.name, location);
+        }
+
+        Ok(file)
+    }
+
+    // The location is authenticated with separators unified.
+    fn aad(location: &Path) -> String {
+        location.to_string_lossy().replace('\\', "/")
+    }
+
+    /// Generates a random nonce with the random number generator of OS. The 192 bits
+    /// nonce of XChaCha20 is large enough to be chosen randomly without collisions.
+    pub fn nonce() -> Result<[u8; NONCE_SIZE]> {
+        let mut nonce = [0; NONCE_SIZE];
+        OsRng::new()
+            .and_then(|mut rng| rng.try_fill_bytes(&mut nonce))
+            .map_err(|err| format_err!("[{}] Failed to generate nonce, {}.", FORMAT.name, err))?;
+        Ok(nonce)
+    }
+}
+
+impl<F: VFS> VFS for CipherFS<F> {
+    fn read(&self, location: &Path) -> Result<Box<Read + Send>> {
+        let bytes = self.read_bytes(location)?;
+        Ok(Box::new(Cursor::new(bytes.to_vec())))
+    }
+
+    fn read_bytes(&self, location: &Path) -> Result<FileBytes> {
+        let bytes = self.read_encoded(location)?;
+        self.decode(location, bytes)
+    }
+
+    fn read_encoded(&self, location: &Path) -> Result<FileBytes> {
+        self.fs.read_bytes(location)
+    }
+
+    fn decode(&self, location: &Path, bytes: FileBytes) -> Result<FileBytes> {
+        Self::decrypt(&self.key, location, &bytes)
+    }
+
+    #[inline]
+    fn is_dir(&self, location: &Path) -> bool {
+        self.fs.is_dir(location)
+    }
+
+    #[inline]
+    fn exists(&self, location: &Path) -> bool {
+        self.fs.exists(location)
+    }
+
+    #[inline]
+    fn modified_since(&self, location: &Path, ts: SystemTime) -> bool {
+        self.fs.modified_since(location, ts)
+    }
+
+    #[inline]
+    fn modified(&self, location: &Path) -> Option<SystemTime> {
+        self.fs.modified(location)
+    }
+
+    fn write(&self, location: &Path, plain: &[u8]) -> Result<()> {
+        let nonce = Self::nonce()?;
+        let bytes = Self::encrypt(&self.key, location, nonce, plain);
+        self.fs.write(location, &bytes)
+    }
+
+    #[inline]
+    fn remove(&self, location: &Path) -> Result<()> {
+        self.fs.remove(location)
+    }
+}
+
+#[inline]
+fn u32_from_le(v: &[u8]) -> u32 {
+    u32::from(v[0]) | (u32::from(v[1]) << 8) | (u32::from(v[2]) << 16) | (u32::from(v[3]) << 24)
+}
+
+#[inline]
+fn u32_to_le(v: u32) -> [u8; 4] {
+    [v as u8, (v >> 8) as u8, (v >> 16) as u8, (v >> 24) as u8]
+}
+
+fn key_words(key: &[u8; 32]) -> [u32; 8] {
+    let mut words = [0; 8];
+    for (i, v) in words.iter_mut().enumerate() {
+        *v = u32_from_le(&key[i * 4..]);
+    }
+
+    words
+}
+
+#[inline]
+fn quarter_round(s: &mut [u32; 16], a: usize, b: usize, c: usize, d: usize) {
+    s[a] = s[a].wrapping_add(s[b]);
+    s[d] = (s[d] ^ s[a]).rotate_left(16);
+    s[c] = s[c].wrapping_add(s[d]);
+    s[b] = (s[b] ^ s[c]).rotate_left(12);
+    s[a] = s[a].wrapping_add(s[b]);
+    s[d] = (s[d] ^ s[a]).rotate_left(8);
+    s[c] = s[c].wrapping_add(s[d]);
+    s[b] = (s[b] ^ s[c]).rotate_left(7);
+}
+
+fn chacha20_rounds(s: &mut [u32; 16]) {
+    for _ in 0..10 {
+        quarter_round(s, 0, 4, 8, 12);
+        quarter_round(s, 1, 5, 9, 13);
+        quarter_round(s, 2, 6, 10, 14);
+        quarter_round(s, 3, 7, 11, 15);
+        quarter_round(s, 0, 5, 10, 15);
+        quarter_round(s, 1, 6, 11, 12);
+        quarter_round(s, 2, 7, 8, 13);
+        quarter_round(s, 3, 4, 9, 14);
+    }
+}
+
+fn chacha20_state(key: &[u32; 8], words: [u32; 4]) -> [u32; 16] {
+    let mut s = [0; 16];
+    s[0] = 0x6170_7865;
+    s[1] = 0x3320_646e;
+    s[2] = 0x7962_2d32;
+    s[3] = 0x6b20_6574;
+    s[4..12].copy_from_slice(key);
+    s[12..16].copy_from_slice(&words);
+    s
+}
+
+fn chacha20_block(key: &[u32; 8], counter: u32, nonce: &[u32; 3]) -> [u8; 64] {
+    let init = chacha20_state(key, [counter, nonce[0], nonce[1], nonce[2]]);
+    let mut s = init;
+    chacha20_rounds(&mut s);
+
+    let mut block = [0; 64];
+    for (i, (v, w)) in s.iter().zip(init.iter()).enumerate() {
+        block[i * 4..i * 4 + 4].copy_from_slice(&u32_to_le(v.wrapping_add(*w)));
+    }
+
+    block
+}
+
+// Derives the subkey of XChaCha20 from the first 16 bytes of nonce.
+fn hchacha20(key: &[u32; 8], nonce: &[u8]) -> [u32; 8] {
+    let words = [
+        u32_from_le(&nonce[0..]),
+        u32_from_le(&nonce[4..]),
+        u32_from_le(&nonce[8..]),
+        u32_from_le(&nonce[12..]),
+    ];
+
+    let mut s = chacha20_state(key, words);
+    chacha20_rounds(&mut s);
+
+    let mut subkey = [0; 8];
+    subkey[0..4].copy_from_slice(&s[0..4]);
+    subkey[4..8].copy_from_slice(&s[12..16]);
+    subkey
+}
+
+// Xors the keystream starting from block `counter` into `bytes`.
+fn chacha20_xor(key: &[u32; 8], counter: u32, nonce: &[u32; 3], bytes: &mut [u8]) {
+    for (i, chunk) in bytes.chunks_mut(64).enumerate() {
+        let block = chacha20_block(key, counter.wrapping_add(i as u32), nonce);
+        for (v, k) in chunk.iter_mut().zip(block.iter()) {
+            *v ^= *k;
+        }
+    }
+}
+
+fn poly1305(key: &[u8], msg: &[u8]) -> [u8; 16] {
+    const MASK: u32 = 0x3ff_ffff;
+
+    let r0 = u32_from_le(&key[0..]) & 0x3ff_ffff;
+    let r1 = (u32_from_le(&key[3..]) >> 2) & 0x3ff_ff03;
+    let r2 = (u32_from_le(&key[6..]) >> 4) & 0x3ff_c0ff;
+    let r3 = (u32_from_le(&key[9..]) >> 6) & 0x3f0_3fff;
+    let r4 = (u32_from_le(&key[12..]) >> 8) & 0x00f_ffff;
+    let (s1, s2, s3, s4) = (r1 * 5, r2 * 5, r3 * 5, r4 * 5);
+
+    let mut h = [0u32; 5];
+    for chunk in msg.chunks(16) {
+        let mut m = [0u8; 17];
+        m[..chunk.len()].copy_from_slice(chunk);
+        m[chunk.len()] = 1;
+
+        h[0] += u32_from_le(&m[0..]) & MASK;
+        h[1] += (u32_from_le(&m[3..]) >> 2) & MASK;
+        h[2] += (u32_from_le(&m[6..]) >> 4) & MASK;
+        h[3] += (u32_from_le(&m[9..]) >> 6) & MASK;
+        h[4] += (u32_from_le(&m[12..]) >> 8) | (u32::from(m[16]) << 24);
+
+        let mul = |a: u32, b: u32| u64::from(a) * u64::from(b);
+        let d0 = mul(h[0], r0) + mul(h[1], s4) + mul(h[2], s3) + mul(h[3], s2) + mul(h[4], s1);
+        let d1 = mul(h[0], r1) + mul(h[1], r0) + mul(h[2], s4) + mul(h[3], s3) + mul(h[4], s2);
+        let d2 = mul(h[0], r2) + mul(h[1], r1) + mul(h[2], r0) + mul(h[3], s4) + mul(h[4], s3);
+        let d3 = mul(h[0], r3) + mul(h[1], r2) + mul(h[2], r1) + mul(h[3], r0) + mul(h[4], s4);
+        let d4 = mul(h[0], r4) + mul(h[1], r3) + mul(h[2], r2) + mul(h[3], r1) + mul(h[4], r0);
+
+        let mut c = d0 >> 26;
+        h[0] = d0 as u32 & MASK;
+        let d1 = d1 + c;
+        c = d1 >> 26;
+        h[1] = d1 as u32 & MASK;
+        let d2 = d2 + c;
+        c = d2 >> 26;
+        h[2] = d2 as u32 & MASK;
+        let d3 = d3 + c;
+        c = d3 >> 26;
+        h[3] = d3 as u32 & MASK;
+        let d4 = d4 + c;
+        c = d4 >> 26;
+        h[4] = d4 as u32 & MASK;
+        h[0] += c as u32 * 5;
+        h[1] += h[0] >> 26;
+        h[0] &= MASK;
+    }
+
+    // Fully carries h.
+    let mut c = h[1] >> 26;
+    h[1] &= MASK;
+    for i in 2..5 {
+        h[i] += c;
+        c = h[i] >> 26;
+        h[i] &= MASK;
+    }
+
+    h[0] += c * 5;
+    c = h[0] >> 26;
+    h[0] &= MASK;
+    h[1] += c;
+
+    // Computes h - p, and selects it if there is no borrow.
+    let mut g = [0u32; 5];
+    g[0] = h[0] + 5;
+    c = g[0] >> 26;
+    g[0] &= MASK;
+    for i in 1..5 {
+        g[i] = h[i] + c;
+        c = g[i] >> 26;
+        g[i] &= MASK;
+    }
+    g[4] = g[4].wrapping_add(c << 26).wrapping_sub(1 << 26);
+
+    let select = (g[4] >> 31).wrapping_sub(1);
+    for (h, g) in h.iter_mut().zip(g.iter()) {
+        *h = (*h & !select) | (*g & select);
+    }
+
+    let h0 = h[0] | (h[1] << 26);
+    let h1 = (h[1] >> 6) | (h[2] << 20);
+    let h2 = (h[2] >> 12) | (h[3] << 14);
+    let h3 = (h[3] >> 18) | (h[4] << 8);
+
+    let mut tag = [0; 16];
+    let mut f = 0u64;
+    for (i, v) in [h0, h1, h2, h3].iter().enumerate() {
+        f = u64::from(*v) + u64::from(u32_from_le(&key[16 + i * 4..])) + (f >> 32);
+        tag[i * 4..i * 4 + 4].copy_from_slice(&u32_to_le(f as u32));
+    }
+
+    tag
+}
+
+fn aead_tag(poly_key: &[u8], aad: &[u8], cipher: &[u8]) -> [u8; 16] {
+    let pad = |len: usize| (16 - len % 16) % 16;
+
+    let mut msg = Vec::with_capacity(aad.len() + cipher.len() + 48);
+    msg.extend_from_slice(aad);
+    msg.extend(::std::iter::repeat(0).take(pad(aad.len())));
+    msg.extend_from_slice(cipher);
+    msg.extend(::std::iter::repeat(0).take(pad(cipher.len())));
+
+    for v in &[aad.len() as u64, cipher.len() as u64] {
+        msg.extend_from_slice(&u32_to_le(*v as u32));
+        msg.extend_from_slice(&u32_to_le((*v >> 32) as u32));
+    }
+
+    poly1305(poly_key, &msg)
+}
+
+fn xchacha20_setup(key: &[u8; 32], nonce: &[u8; NONCE_SIZE]) -> ([u32; 8], [u32; 3]) {
+    let subkey = hchacha20(&key_words(key), &nonce[..16]);
+    let nonce = [0, u32_from_le(&nonce[16..]), u32_from_le(&nonce[20..])];
+    (subkey, nonce)
+}
+
+// Encrypts `bytes` in place, and returns the authentication tag.
+fn seal(key: &[u8; 32], nonce: &[u8; NONCE_SIZE], aad: &[u8], bytes: &mut [u8]) -> [u8; 16] {
+    let (subkey, nonce) = xchacha20_setup(key, nonce);
+    let poly_key = chacha20_block(&subkey, 0, &nonce);
+    chacha20_xor(&subkey, 1, &nonce, bytes);
+    aead_tag(&poly_key[..32], aad, bytes)
+}
+
+// Authenticates and decrypts `bytes` in place, returns false if the tag mismatches.
+fn open(
+    key: &[u8; 32],
+    nonce: &[u8; NONCE_SIZE],
+    aad: &[u8],
+    bytes: &mut [u8],
+    tag: &[u8],
+) -> bool {
+    let (subkey, nonce) = xchacha20_setup(key, nonce);
+    let poly_key = chacha20_block(&subkey, 0, &nonce);
+    let expected = aead_tag(&poly_key[..32], aad, bytes);
+
+    // Compares in constant time.
+    let diff = expected.iter().zip(tag.iter()).fold(0, |acc, (a, b)| acc | (a ^ b));
+    if diff != 0 {
+        return false;
+    }
+
+    chacha20_xor(&subkey, 1, &nonce, bytes);
+    true
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn hex(v: &str) -> Vec<u8> {
+        (0..v.len() / 2)
+            .map(|i| u8::from_str_radix(&v[i * 2..i * 2 + 2], 16).unwrap())
+            .collect()
+    }
+
+    fn sequence_key() -> [u8; 32] {
+        let mut key = [0; 32];
+        for (i, v) in key.iter_mut().enumerate() {
+            *v = i as u8;
+        }
+
+        key
+    }
+
+    #[test]
+    fn chacha20() {
+        // RFC 8439, 2.3.2.
+        let key = key_words(&sequence_key());
+        let block = chacha20_block(&key, 1, &[0x0900_0000, 0x4a00_0000, 0]);
+        let expected = hex(
+            "10f1e7e4d13b5915500fdd1fa32071c4c7d1f4c733c068030422aa9ac3d46c4e\
+             d2826446079faa0914c2d705d98b02a2b5129cd1de164eb9cbd083e8a2503c4e",
+        );
+
+        assert_eq!(&block[..], &expected[..]);
+    }
+
+    #[test]
+    fn hchacha20_subkey() {
+        // draft-irtf-cfrg-xchacha, 2.2.1.
+        let key = key_words(&sequence_key());
+        let nonce = hex("000000090000004a0000000031415927");
+        let subkey = hchacha20(&key, &nonce);
+        assert_eq!(
+            subkey,
+            [
+                0x423b_4182, 0xfe7b_b227, 0x5042_0ed3, 0x737d_878a, 0xd5e4_f9a0, 0x53a8_748a,
+                0x13c4_2ec1, 0xdcec_d326,
+            ]
+        );
+    }
+
+    #[test]
+    fn poly1305_tag() {
+        // RFC 8439, 2.5.2.
+        let key = hex("85d6be7857556d337f4452fe42d506a80103808afb0db2fd4abff6af4149f51b");
+        let tag = poly1305(&key, b"Cryptographic Forum Research Group");
+        assert_eq!(&tag[..], &hex("a8061dc1305136c6c22b8baf0c0127a9")[..]);
+    }
+
+    #[test]
+    fn xchacha20_poly1305() {
+        // draft-irtf-cfrg-xchacha, A.3.1.
+        let mut key = [0; 32];
+        for (i, v) in key.iter_mut().enumerate() {
+            *v = 0x80 + i as u8;
+        }
+
+        let mut nonce = [0; NONCE_SIZE];
+        for (i, v) in nonce.iter_mut().enumerate() {
+            *v = 0x40 + i as u8;
+        }
+
+        let aad = hex("50515253c0c1c2c3c4c5c6c7");
+        let plain: &[u8] = b"Ladies and Gentlemen of the class of '99: If I could offer you \
+                             only one tip for the future, sunscreen would be it.";
+
+        let expected = hex(
+            "bd6d179d3e83d43b9576579493c0e939572a1700252bfaccbed2902c21396cbb\
+             731c7f1b0b4aa6440bf3a82f4eda7e39ae64c6708c54c216cb96b72e1213b452\
+             2f8c9ba40db5d945b11b69b982c1bb9e3f3fac2bc369488f76b2383565d3fff9\
+             21f9664c97637da9768812f615c68b13b52e",
+        );
+
+        let mut bytes = plain.to_vec();
+        let tag = seal(&key, &nonce, &aad, &mut bytes);
+        assert_eq!(&bytes[..], &expected[..]);
+        assert_eq!(&tag[..], &hex("c0875924c1c7987947deafd8780acf49")[..]);
+
+        assert!(open(&key, &nonce, &aad, &mut bytes, &tag));
+        assert_eq!(&bytes[..], plain);
+
+        let mut bytes = expected.clone();
+        assert!(!open(&key, &nonce, &aad[1..], &mut bytes, &tag));
+        assert_eq!(&bytes[..], &expected[..]);
+    }
+
+    #[test]
+    fn cipher_fs() {
+        use res::vfs::DiskFS;
+        use std::{env, fs};
+
+        let root = env::temp_dir().join("crayon_cipher_fs");
+        let _ = fs::remove_dir_all(&root);
+
+        let key = CipherKey::new(sequence_key());
+        let cipher = CipherFS::new(DiskFS::create(&root).unwrap(), key);
+        let location = Path::new("textures/grass.png");
+        cipher.write(location, b"crayon").unwrap();
+        cipher.write(Path::new("textures/sky.png"), b"sky").unwrap();
+
+        {
+            let bytes = cipher.read_bytes(location).unwrap();
+            assert_eq!(&bytes[..], b"crayon");
+
+            let mut buf = Vec::new();
+            cipher
+                .read(location)
+                .unwrap()
+                .read_to_end(&mut buf)
+                .unwrap();
+            assert_eq!(&buf[..], b"crayon");
+        }
+
+        // The plain contents are never written into file.
+        let raw = fs::read(root.join(location)).unwrap();
+        assert!(!raw.windows(6).any(|v| v == b"crayon"));
+
+        let other = CipherFS::new(DiskFS::new(&root).unwrap(), CipherKey::new([0; 32]));
+        assert!(other.read_bytes(location).is_err());
+
+        // Swaps the files, which are authenticated with their locations.
+        let sky = fs::read(root.join("textures/sky.png")).unwrap();
+        fs::write(root.join(location), &sky).unwrap();
+        assert!(cipher.read_bytes(location).is_err());
+
+        let mut tampered = raw.clone();
+        let last = tampered.len() - 1;
+        tampered[last] ^= 0x80;
+        fs::write(root.join(location), &tampered).unwrap();
+        assert!(cipher.read_bytes(location).is_err());
+
+        fs::write(root.join(location), &raw).unwrap();
+        assert!(cipher.read_bytes(location).is_ok());
+
+        let _ = fs::remove_dir_all(&root);
+    }
+
+    #[test]
+    fn roundtrip() {
+        let key = CipherKey::new(sequence_key());
+        let location = Path::new("textures/grass.png");
+        let plain: Vec<u8> = (0..200).map(|v| v as u8).collect();
+
+        type Fs = CipherFS<::res::vfs::DiskFS>;
+        let nonce = Fs::nonce().unwrap();
+        assert!(nonce != Fs::nonce().unwrap());
+        let bytes = Fs::encrypt(&key, location, nonce, &plain);
+        assert_eq!(bytes.len(), 8 + NONCE_SIZE + plain.len() + TAG_SIZE);
+
+        let file = Fs::decrypt(&key, location, &bytes).unwrap();
+        assert_eq!(&file[..], &plain[..]);
+
+        // Tampered contents, swapped location and wrong key are rejected.
+        let mut tampered = bytes.clone();
+        tampered[8 + NONCE_SIZE] ^= 1;
+        assert!(Fs::decrypt(&key, location, &tampered).is_err());
+        assert!(Fs::decrypt(&key, Path::new("textures/sky.png"), &bytes).is_err());
+        assert!(Fs::decrypt(&CipherKey::new([0; 32]), location, &bytes).is_err());
+        assert!(Fs::decrypt(&key, location, &bytes[..20]).is_err());
+    }
+}
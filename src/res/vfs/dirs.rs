@@ -0,0 +1,137 @@
+//! The well-known directories of application, which are resolved with the conventions
+//! of current platform.
+
+use std::env;
+use std::path::PathBuf;
+
+use errors::*;
+
+/// The well-known directories which are usually mounted as virtual file-systems.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum KnownDirectory {
+    /// The read-only resources shipped with application.
+    Assets,
+    /// The persistent data which could be regenerated, e.g. compiled shaders.
+    Cache,
+    /// The persistent user data, e.g. settings and saved games.
+    Save,
+    /// The temporary data which could be removed after application exits.
+    Temp,
+}
+
+impl KnownDirectory {
+    /// All the well-known directories.
+    pub const ALL: [KnownDirectory; 4] = [
+        KnownDirectory::Assets,
+        KnownDirectory::Cache,
+        KnownDirectory::Save,
+        KnownDirectory::Temp,
+    ];
+
+    /// Gets the conventional mount name of this directory, e.g. `save` for locations
+    /// like `save://settings.toml`.
+    pub fn name(&self) -> &'static str {
+        match *self {
+            KnownDirectory::Assets => "assets",
+            KnownDirectory::Cache => "cache",
+            KnownDirectory::Save => "save",
+            KnownDirectory::Temp => "temp",
+        }
+    }
+
+    /// Resolves the path of this directory for application `app`.
+    ///
+    /// * Assets: `resources` besides executable, or `Contents/Resources` of macOS bundle.
+    /// * Cache: `%LOCALAPPDATA%/<app>/Cache` on Windows, `~/Library/Caches/<app>` on
+    /// macOS and `$XDG_CACHE_HOME/<app>` on Linux.
+    /// * Save: `%APPDATA%/<app>` on Windows, `~/Library/Application Support/<app>` on
+    /// macOS and `$XDG_DATA_HOME/<app>` on Linux.
+    /// * Temp: `<app>` in the temporary directory of system.
+    ///
+    /// Platforms without these conventions (e.g. mobile devices) fallback to the sub
+    /// directories of temporary directory.
+    pub fn resolve(&self, app: &str) -> Result<PathBuf> {
+        let fallback = || env::temp_dir().join(app).join(self.name());
+
+        let path = match *self {
+            KnownDirectory::Assets => assets_dir()?,
+            KnownDirectory::Cache => cache_dir(app).unwrap_or_else(fallback),
+            KnownDirectory::Save => save_dir(app).unwrap_or_else(fallback),
+            KnownDirectory::Temp => env::temp_dir().join(app),
+        };
+
+        Ok(path)
+    }
+}
+
+fn assets_dir() -> Result<PathBuf> {
+    let exe = env::current_exe()?;
+    let dir = match exe.parent() {
+        Some(v) => v.to_owned(),
+        None => bail!("Failed to locate the directory of executable {:?}.", exe),
+    };
+
+    if cfg!(target_os = "macos") {
+        let bundle = dir.join("..").join("Resources");
+        if bundle.is_dir() {
+            return Ok(bundle);
+        }
+    }
+
+    Ok(dir.join("resources"))
+}
+
+#[cfg(target_os = "windows")]
+fn save_dir(app: &str) -> Option<PathBuf> {
+    env::var_os("APPDATA").map(|v| PathBuf::from(v).join(app))
+}
+
+#[cfg(target_os = "windows")]
+fn cache_dir(app: &str) -> Option<PathBuf> {
+    env::var_os("LOCALAPPDATA").map(|v| PathBuf::from(v).join(app).join("Cache"))
+}
+
+#[cfg(target_os = "macos")]
+fn save_dir(app: &str) -> Option<PathBuf> {
+    home_dir().map(|v| v.join("Library/Application Support").join(app))
+}
+
+#[cfg(target_os = "macos")]
+fn cache_dir(app: &str) -> Option<PathBuf> {
+    home_dir().map(|v| v.join("Library/Caches").join(app))
+}
+
+#[cfg(all(unix, not(target_os = "macos"), not(target_os = "android")))]
+fn save_dir(app: &str) -> Option<PathBuf> {
+    xdg_dir("XDG_DATA_HOME", ".local/share").map(|v| v.join(app))
+}
+
+#[cfg(all(unix, not(target_os = "macos"), not(target_os = "android")))]
+fn cache_dir(app: &str) -> Option<PathBuf> {
+    xdg_dir("XDG_CACHE_HOME", ".cache").map(|v| v.join(app))
+}
+
+#[cfg(any(target_os = "android", not(any(unix, windows))))]
+fn save_dir(_: &str) -> Option<PathBuf> {
+    None
+}
+
+#[cfg(any(target_os = "android", not(any(unix, windows))))]
+fn cache_dir(_: &str) -> Option<PathBuf> {
+    None
+}
+
+#[cfg(all(unix, not(target_os = "macos"), not(target_os = "android")))]
+fn xdg_dir(var: &str, default: &str) -> Option<PathBuf> {
+    match env::var_os(var).map(PathBuf::from) {
+        Some(ref v) if v.is_absolute() => Some(v.clone()),
+        _ => home_dir().map(|v| v.join(default)),
+    }
+}
+
+#[cfg(all(unix, not(target_os = "android")))]
+fn home_dir() -> Option<PathBuf> {
+    env::var_os("HOME")
+        .map(PathBuf::from)
+        .filter(|v| v.is_absolute())
+}
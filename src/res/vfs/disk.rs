@@ -1,14 +1,17 @@
 use std::fs;
-use std::io::Read;
+use std::io::{Read, Write};
 use std::path::{Path, PathBuf};
 use std::time::SystemTime;
 
+use memmap::Mmap;
+
 use errors::*;
 
-use super::VFS;
+use super::{FileBytes, VFS};
 
 pub struct DiskFS {
     root: PathBuf,
+    case_insensitive: bool,
 }
 
 impl DiskFS {
@@ -18,30 +21,206 @@ impl DiskFS {
 
         let metadata = fs::metadata(&root)?;
         if metadata.is_dir() {
-            Ok(DiskFS { root: root })
+            Ok(DiskFS {
+                root: root,
+                case_insensitive: false,
+            })
         } else {
             bail!("Disk file-system must be associated with a readable directory.");
         }
     }
+
+    /// Creates the root directory if its missing, and then mounts it. This is usually
+    /// used with writable directories, e.g. `KnownDirectory::Save`.
+    pub fn create<T: Into<PathBuf>>(root: T) -> Result<Self> {
+        let root = root.into();
+        fs::create_dir_all(&root)?;
+        DiskFS::new(root)
+    }
+
+    /// Matches the names of files and directories case-insensitively when the exact
+    /// path is not found, so locations which are authored on case-insensitive platforms
+    /// (e.g. Windows and macOS) still work on the others.
+    pub fn case_insensitive(mut self, enable: bool) -> Self {
+        self.case_insensitive = enable;
+        self
+    }
+
+    fn locate(&self, location: &Path) -> PathBuf {
+        let exact = self.root.join(location);
+        if !self.case_insensitive || exact.exists() {
+            return exact;
+        }
+
+        let mut path = self.root.clone();
+        for v in location.components() {
+            let next = path.join(v.as_os_str());
+            if next.exists() {
+                path = next;
+                continue;
+            }
+
+            let name = v.as_os_str().to_string_lossy().to_lowercase();
+            let found = fs::read_dir(&path).ok().and_then(|entries| {
+                entries
+                    .filter_map(|v| v.ok())
+                    .find(|v| v.file_name().to_string_lossy().to_lowercase() == name)
+            });
+
+            path = match found {
+                Some(v) => v.path(),
+                None => next,
+            };
+        }
+
+        path
+    }
 }
 
 impl VFS for DiskFS {
     fn read(&self, location: &Path) -> Result<Box<Read + Send>> {
-        let location = self.root.join(location);
+        let location = self.locate(location);
         let file = fs::File::open(&location)?;
         Ok(Box::new(file))
     }
 
+    fn read_bytes(&self, location: &Path) -> Result<FileBytes> {
+        let location = self.locate(location);
+        let mut file = fs::File::open(&location)?;
+
+        // Empty files could not be mapped.
+        if file.metadata()?.len() == 0 {
+            return FileBytes::read(&mut file);
+        }
+
+        let mmap = unsafe { Mmap::map(&file)? };
+        Ok(FileBytes::Mapped(mmap))
+    }
+
     fn is_dir(&self, location: &Path) -> bool {
-        self.root.join(location).is_dir()
+        self.locate(location).is_dir()
     }
 
     fn exists(&self, location: &Path) -> bool {
-        self.root.join(location).exists()
+        self.locate(location).exists()
     }
 
     fn modified_since(&self, location: &Path, ts: SystemTime) -> bool {
-        let metadata = self.root.join(location).metadata().unwrap();
-        ts == metadata.modified().unwrap()
+        self.locate(location)
+            .metadata()
+            .and_then(|v| v.modified())
+            .map(|v| v > ts)
+            .unwrap_or(false)
+    }
+
+    fn modified(&self, location: &Path) -> Option<SystemTime> {
+        self.locate(location)
+            .metadata()
+            .and_then(|v| v.modified())
+            .ok()
+    }
+
+    fn write(&self, location: &Path, bytes: &[u8]) -> Result<()> {
+        let location = self.locate(location);
+        if let Some(parent) = location.parent() {
+            fs::create_dir_all(parent)?;
+        }
+
+        // Writes into a temporary file and then renames it, so the previous contents
+        // would not be corrupted if we crashed in the middle.
+        let mut partial = location.clone().into_os_string();
+        partial.push(".partial");
+
+        {
+            let mut file = fs::File::create(&partial)?;
+            file.write_all(bytes)?;
+            file.sync_all()?;
+        }
+
+        fs::rename(&partial, &location)?;
+        Ok(())
+    }
+
+    fn remove(&self, location: &Path) -> Result<()> {
+        fs::remove_file(self.locate(location))?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::env;
+    use std::time::{Duration, UNIX_EPOCH};
+
+    #[test]
+    fn modified_since() {
+        let root = env::temp_dir().join("crayon_disk_fs_modified_since");
+        let _ = fs::remove_dir_all(&root);
+
+        let disk = DiskFS::create(&root).unwrap();
+        let location: &Path = "shader.fs".as_ref();
+        disk.write(location, b"crayon").unwrap();
+        assert!(disk.modified_since(location, UNIX_EPOCH));
+
+        let ts = SystemTime::now() + Duration::from_secs(60);
+        assert!(!disk.modified_since(location, ts));
+
+        assert!(disk.modified(location).is_some());
+        assert!(disk.modified("missing.fs".as_ref()).is_none());
+
+        let _ = fs::remove_dir_all(&root);
+    }
+
+    #[test]
+    fn write() {
+        let root = env::temp_dir().join("crayon_disk_fs_write");
+        let _ = fs::remove_dir_all(&root);
+
+        let disk = DiskFS::create(&root).unwrap().case_insensitive(true);
+        let location: &Path = "Saves/Slot0.bin".as_ref();
+        disk.write(location, b"crayon").unwrap();
+        assert!(disk.exists(location));
+        assert!(disk.exists("saves/slot0.BIN".as_ref()));
+
+        {
+            let bytes = disk.read_bytes("SAVES/slot0.bin".as_ref()).unwrap();
+            assert_eq!(&bytes[..], b"crayon");
+        }
+
+        disk.remove("saves/slot0.bin".as_ref()).unwrap();
+        assert!(!disk.exists(location));
+        assert!(!disk.modified_since(location, UNIX_EPOCH));
+
+        let _ = fs::remove_dir_all(&root);
+    }
+
+    #[test]
+    fn read() {
+        let root = env::temp_dir().join("crayon_disk_fs_read");
+        let _ = fs::remove_dir_all(&root);
+        fs::create_dir_all(root.join("shaders")).unwrap();
+        fs::write(root.join("shaders/color.fs"), b"crayon").unwrap();
+        fs::write(root.join("empty.bin"), b"").unwrap();
+
+        let disk = DiskFS::new(&root).unwrap();
+        assert!(disk.is_dir("shaders".as_ref()));
+
+        let mut buf = Vec::new();
+        let mut file = disk.read("shaders/color.fs".as_ref()).unwrap();
+        file.read_to_end(&mut buf).unwrap();
+        assert_eq!(&buf[..], b"crayon");
+
+        {
+            let bytes = disk.read_bytes("shaders/color.fs".as_ref()).unwrap();
+            assert_eq!(&bytes[..], b"crayon");
+
+            let bytes = disk.read_bytes("empty.bin".as_ref()).unwrap();
+            assert!(bytes.is_empty());
+        }
+
+        assert!(disk.read_bytes("missing.bin".as_ref()).is_err());
+
+        let _ = fs::remove_dir_all(&root);
     }
 }
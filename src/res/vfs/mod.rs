@@ -1,10 +1,19 @@
+pub mod bytes;
+pub mod cipher;
+pub mod dirs;
 pub mod disk;
+pub mod overlay;
 
+pub use self::bytes::{FileBytes, FileSlice, FILE_BYTES_ALIGNMENT};
+pub use self::cipher::{CipherFS, CipherKey};
+pub use self::dirs::KnownDirectory;
 pub use self::disk::DiskFS;
+pub use self::overlay::OverlayFS;
 
 use std::collections::HashMap;
 use std::io::Read;
-use std::path::Path;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
 use std::time::SystemTime;
 
 use errors::*;
@@ -14,6 +23,25 @@ pub trait VFS: Send + Sync {
     /// Opens a readable file at location.
     fn read(&self, location: &Path) -> Result<Box<Read + Send>>;
 
+    /// Reads the whole contents of file at location. File-systems which support memory
+    /// mapping should override this to avoid the copy.
+    fn read_bytes(&self, location: &Path) -> Result<FileBytes> {
+        let mut file = self.read(location)?;
+        FileBytes::read(&mut file)
+    }
+
+    /// Reads the contents of file at location as it's stored, which are decoded with
+    /// `VFS::decode` later. The resource loaders use this so the decoding happens on
+    /// the `sched` workers.
+    fn read_encoded(&self, location: &Path) -> Result<FileBytes> {
+        self.read_bytes(location)
+    }
+
+    /// Decodes the contents which are read with `VFS::read_encoded`, e.g. decryption.
+    fn decode(&self, _: &Path, bytes: FileBytes) -> Result<FileBytes> {
+        Ok(bytes)
+    }
+
     // /// Retrieves all file and directory entries in the given directory.
     // fn read_dir(&self, location: &Path) -> Result<Box<Iterator<Item = PathBuf>>>;
 
@@ -23,12 +51,50 @@ pub trait VFS: Send + Sync {
     /// Checks if the file exists.
     fn exists(&self, location: &Path) -> bool;
 
-    /// Returns true if the file has been modified since `ts`.
+    /// Returns true if the file has been modified since `ts`. It's false if the file
+    /// does not exist, or its modification time is unknown.
     fn modified_since(&self, location: &Path, ts: SystemTime) -> bool;
+
+    /// Gets the modification time of file. It's `None` if the file does not exist, or
+    /// its modification time is unknown.
+    fn modified(&self, _: &Path) -> Option<SystemTime> {
+        None
+    }
+
+    /// Writes the whole contents of file at location, the missing parent directories
+    /// are created. File-systems are read-only by default.
+    fn write(&self, location: &Path, _: &[u8]) -> Result<()> {
+        bail!("Could not write {:?} into read-only file system.", location);
+    }
+
+    /// Removes the file at location.
+    fn remove(&self, location: &Path) -> Result<()> {
+        bail!("Could not remove {:?} from read-only file system.", location);
+    }
+}
+
+/// Normalizes the relative path in virtual file system. Both `/` and `\` are accepted
+/// as separators, and the `.` and `..` components are resolved. Its an error if the
+/// path escapes from the root.
+pub fn normalize(path: &str) -> Result<PathBuf> {
+    let mut components = Vec::new();
+    for v in path.split(|c| c == '/' || c == '\\') {
+        match v {
+            "" | "." => {}
+            ".." => {
+                if components.pop().is_none() {
+                    bail!("Path {:?} escapes from the root of file system.", path);
+                }
+            }
+            _ => components.push(v),
+        }
+    }
+
+    Ok(components.iter().collect())
 }
 
 pub struct VFSDriver {
-    mounts: HashMap<HashValue<str>, Box<VFS>>,
+    mounts: HashMap<HashValue<str>, Arc<VFS>>,
 }
 
 impl VFSDriver {
@@ -53,10 +119,23 @@ impl VFSDriver {
             );
         }
 
-        self.mounts.insert(hash, Box::new(vfs));
+        self.mounts.insert(hash, Arc::new(vfs));
         Ok(())
     }
 
+    /// Gets the mounted file-system.
+    pub fn get<T>(&self, fs: T) -> Result<Arc<VFS>>
+    where
+        T: Into<HashValue<str>>,
+    {
+        let fs = fs.into();
+        if let Some(vfs) = self.mounts.get(&fs) {
+            Ok(vfs.clone())
+        } else {
+            bail!("Undefined virtual file system {:?}.", fs);
+        }
+    }
+
     pub fn read<T>(&self, fs: T, file: &Path) -> Result<Box<Read + Send>>
     where
         T: Into<HashValue<str>>,
@@ -69,6 +148,18 @@ impl VFSDriver {
         }
     }
 
+    pub fn read_bytes<T>(&self, fs: T, file: &Path) -> Result<FileBytes>
+    where
+        T: Into<HashValue<str>>,
+    {
+        let fs = fs.into();
+        if let Some(vfs) = self.mounts.get(&fs) {
+            vfs.read_bytes(file)
+        } else {
+            bail!("Undefined virtual file system {:?}.", fs);
+        }
+    }
+
     pub fn is_dir<T>(&self, fs: T, file: &Path) -> Result<bool>
     where
         T: Into<HashValue<str>>,
@@ -93,6 +184,30 @@ impl VFSDriver {
         }
     }
 
+    pub fn write<T>(&self, fs: T, file: &Path, bytes: &[u8]) -> Result<()>
+    where
+        T: Into<HashValue<str>>,
+    {
+        let fs = fs.into();
+        if let Some(vfs) = self.mounts.get(&fs) {
+            vfs.write(file, bytes)
+        } else {
+            bail!("Undefined virtual file system {:?}.", fs);
+        }
+    }
+
+    pub fn remove<T>(&self, fs: T, file: &Path) -> Result<()>
+    where
+        T: Into<HashValue<str>>,
+    {
+        let fs = fs.into();
+        if let Some(vfs) = self.mounts.get(&fs) {
+            vfs.remove(file)
+        } else {
+            bail!("Undefined virtual file system {:?}.", fs);
+        }
+    }
+
     pub fn modified_since<T>(&self, fs: T, file: &Path, ts: SystemTime) -> Result<bool>
     where
         T: Into<HashValue<str>>,
@@ -104,4 +219,34 @@ impl VFSDriver {
             bail!("Undefined virtual file system {:?}.", fs);
         }
     }
+
+    pub fn modified<T>(&self, fs: T, file: &Path) -> Result<Option<SystemTime>>
+    where
+        T: Into<HashValue<str>>,
+    {
+        let fs = fs.into();
+        if let Some(vfs) = self.mounts.get(&fs) {
+            Ok(vfs.modified(file))
+        } else {
+            bail!("Undefined virtual file system {:?}.", fs);
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn normalization() {
+        let expected: PathBuf = ["textures", "grass.png"].iter().collect();
+        assert_eq!(normalize("textures/grass.png").unwrap(), expected);
+        assert_eq!(normalize("/textures//grass.png").unwrap(), expected);
+        assert_eq!(normalize("textures\\grass.png").unwrap(), expected);
+        assert_eq!(normalize("./textures/../textures/./grass.png").unwrap(), expected);
+        assert_eq!(normalize("").unwrap(), PathBuf::new());
+
+        assert!(normalize("..").is_err());
+        assert!(normalize("textures/../../grass.png").is_err());
+    }
 }
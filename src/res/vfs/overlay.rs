@@ -0,0 +1,129 @@
+//! Layered virtual file system, which shadows the files of a read-only file system with
+//! the ones in a writable directory, e.g. the patched assets in cache.
+//!
+//! The files of the lower layer can't be removed, so the removal leaves a whiteout
+//! marker beside the location in the upper layer instead, which hides the file until
+//! it's written again.
+//!
+//! ```rust,ignore
+//! let assets = DiskFS::new(KnownDirectory::Assets.resolve(app)?)?;
+//! let cache = DiskFS::create(KnownDirectory::Cache.resolve(app)?.join("assets"))?;
+//! res.mount("assets", OverlayFS::new(cache, assets))?;
+//! ```
+
+use std::io::Read;
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+
+use errors::*;
+
+use super::{FileBytes, VFS};
+
+/// The prefix of the names of whiteout markers.
+pub const WHITEOUT: &'static str = ".wh.";
+
+/// Gets the location of the whiteout marker which hides the file at `location`.
+pub fn whiteout(location: &Path) -> PathBuf {
+    let name = location
+        .file_name()
+        .map(|v| v.to_string_lossy().into_owned())
+        .unwrap_or_default();
+
+    location.with_file_name(format!("{}{}", WHITEOUT, name))
+}
+
+/// The file system which reads from `upper` if the file exists there, and falls back to
+/// `lower` otherwise unless it's removed. All the writes go into `upper`.
+pub struct OverlayFS<U: VFS, L: VFS> {
+    upper: U,
+    lower: L,
+}
+
+impl<U: VFS, L: VFS> OverlayFS<U, L> {
+    pub fn new(upper: U, lower: L) -> Self {
+        OverlayFS {
+            upper: upper,
+            lower: lower,
+        }
+    }
+
+    #[inline]
+    pub fn upper(&self) -> &U {
+        &self.upper
+    }
+
+    #[inline]
+    pub fn lower(&self) -> &L {
+        &self.lower
+    }
+
+    // The files which are removed are looked up in `upper`, where they do not exist.
+    fn layer(&self, location: &Path) -> &VFS {
+        if self.upper.exists(location) || self.upper.exists(&whiteout(location)) {
+            &self.upper
+        } else {
+            &self.lower
+        }
+    }
+}
+
+impl<U: VFS, L: VFS> VFS for OverlayFS<U, L> {
+    fn read(&self, location: &Path) -> Result<Box<Read + Send>> {
+        self.layer(location).read(location)
+    }
+
+    fn read_bytes(&self, location: &Path) -> Result<FileBytes> {
+        self.layer(location).read_bytes(location)
+    }
+
+    fn read_encoded(&self, location: &Path) -> Result<FileBytes> {
+        self.layer(location).read_encoded(location)
+    }
+
+    fn decode(&self, location: &Path, bytes: FileBytes) -> Result<FileBytes> {
+        self.layer(location).decode(location, bytes)
+    }
+
+    fn is_dir(&self, location: &Path) -> bool {
+        self.upper.is_dir(location) || self.lower.is_dir(location)
+    }
+
+    fn exists(&self, location: &Path) -> bool {
+        self.layer(location).exists(location)
+    }
+
+    fn modified_since(&self, location: &Path, ts: SystemTime) -> bool {
+        self.layer(location).modified_since(location, ts)
+    }
+
+    fn modified(&self, location: &Path) -> Option<SystemTime> {
+        self.layer(location).modified(location)
+    }
+
+    fn write(&self, location: &Path, bytes: &[u8]) -> Result<()> {
+        self.upper.write(location, bytes)?;
+
+        let marker = whiteout(location);
+        if self.upper.exists(&marker) {
+            self.upper.remove(&marker)?;
+        }
+
+        Ok(())
+    }
+
+    fn remove(&self, location: &Path) -> Result<()> {
+        if !self.exists(location) {
+            return self.upper.remove(location);
+        }
+
+        if self.upper.exists(location) {
+            self.upper.remove(location)?;
+        }
+
+        if self.lower.exists(location) {
+            self.upper.write(&whiteout(location), &[])?;
+        }
+
+        Ok(())
+    }
+}
@@ -0,0 +1,516 @@
+//! Long-running background jobs (e.g. navmesh baking, texture compression) which might
+//! take multiple seconds.
+//!
+//! Background jobs are executed on dedicated threads instead of the work-stealing
+//! workers, so they will never block the per-frame tasks. The background threads run
+//! with a lower OS priority than the workers, so they only take the CPU time left by
+//! the per-frame tasks. Its possible to report the
+//! progress and check the cancellation token inside jobs, and the completion callbacks
+//! are delivered on the main thread. Every job is resolved exactly once, the completion
+//! receives `None` if the job is cancelled before it starts, skipped by the termination
+//! of scheduler, or panicked. Jobs spawned after the termination are resolved with
+//! `None` immediately.
+
+use std::collections::VecDeque;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::{Arc, Condvar, Mutex};
+use std::thread;
+
+use math;
+
+use super::graph::{BackgroundJobNode, BackgroundJobStatus};
+use super::unwind;
+
+type Task = Box<FnMut() + Send>;
+type Waker = Arc<Fn() + Send + Sync>;
+
+struct JobState {
+    id: usize,
+    progress: AtomicUsize,
+    started: AtomicBool,
+    cancelled: AtomicBool,
+    finished: AtomicBool,
+}
+
+/// The context of a running background job.
+pub struct BackgroundJobContext {
+    state: Arc<JobState>,
+    terminated: Arc<AtomicBool>,
+}
+
+impl BackgroundJobContext {
+    /// Reports the progress of this job, ranging from 0.0 to 1.0.
+    #[inline]
+    pub fn set_progress(&self, progress: f32) {
+        let v = progress.max(0.0).min(1.0).to_bits() as usize;
+        self.state.progress.store(v, Ordering::Relaxed);
+    }
+
+    /// Returns true if the job has been cancelled, or the scheduler is terminating. Jobs
+    /// should check this periodically and return as soon as possible.
+    #[inline]
+    pub fn is_cancelled(&self) -> bool {
+        self.state.cancelled.load(Ordering::Relaxed) || self.terminated.load(Ordering::Relaxed)
+    }
+}
+
+/// The handle of a background job, which could be used to query the progress or cancel
+/// the job.
+#[derive(Clone)]
+pub struct BackgroundJob {
+    state: Arc<JobState>,
+}
+
+impl BackgroundJob {
+    /// Gets the latest progress reported by job, ranging from 0.0 to 1.0.
+    #[inline]
+    pub fn progress(&self) -> f32 {
+        f32::from_bits(self.state.progress.load(Ordering::Relaxed) as u32)
+    }
+
+    /// Requests the cancellation of job. The job will be skipped if it has not been
+    /// started yet.
+    #[inline]
+    pub fn cancel(&self) {
+        self.state.cancelled.store(true, Ordering::Relaxed);
+    }
+
+    /// Returns true if the job has been cancelled.
+    #[inline]
+    pub fn is_cancelled(&self) -> bool {
+        self.state.cancelled.load(Ordering::Relaxed)
+    }
+
+    /// Returns true if the job has been finished or skipped.
+    #[inline]
+    pub fn is_finished(&self) -> bool {
+        self.state.finished.load(Ordering::Acquire)
+    }
+}
+
+struct Queue {
+    tasks: Mutex<VecDeque<Task>>,
+    condvar: Condvar,
+}
+
+/// The executor of background jobs.
+pub struct Background {
+    queue: Arc<Queue>,
+    // The jobs which have not finished yet.
+    jobs: Mutex<Vec<Arc<JobState>>>,
+    next: AtomicUsize,
+    completions: Arc<Mutex<Vec<Task>>>,
+    waker: Arc<Mutex<Option<Waker>>>,
+    terminated: Arc<AtomicBool>,
+    workers: u32,
+}
+
+impl Background {
+    pub fn new(num: u32) -> Self {
+        let queue = Arc::new(Queue {
+            tasks: Mutex::new(VecDeque::new()),
+            condvar: Condvar::new(),
+        });
+
+        let terminated = Arc::new(AtomicBool::new(false));
+
+        for i in 0..num {
+            let queue = queue.clone();
+            let terminated = terminated.clone();
+
+            thread::Builder::new()
+                .name(format!("Background #{}", i))
+                .spawn(move || Background::main_loop(&queue, &terminated))
+                .unwrap();
+        }
+
+        Background {
+            queue: queue,
+            jobs: Mutex::new(Vec::new()),
+            next: AtomicUsize::new(0),
+            completions: Arc::new(Mutex::new(Vec::new())),
+            waker: Arc::new(Mutex::new(None)),
+            terminated: terminated,
+            workers: num,
+        }
+    }
+
+    /// Gets the number of background threads.
+    #[inline]
+    pub fn workers(&self) -> u32 {
+        self.workers
+    }
+
+    /// Sets the closure which is called from background threads once there are new
+    /// completions to deliver, e.g. to wake up the main loop blocking on events.
+    pub fn set_waker<F>(&self, waker: F)
+    where
+        F: Fn() + Send + Sync + 'static,
+    {
+        *self.waker.lock().unwrap() = Some(Arc::new(waker));
+    }
+
+    /// Spawns a background job. The `completion` will be called with the result on
+    /// the main thread, or `None` if the job is cancelled before it starts. If the
+    /// scheduler has been terminated, the `completion` is called with `None` on the
+    /// calling thread immediately.
+    pub fn spawn<F, R, C>(&self, func: F, completion: C) -> BackgroundJob
+    where
+        F: FnOnce(&BackgroundJobContext) -> R + Send + 'static,
+        R: Send + 'static,
+        C: FnOnce(Option<R>) + Send + 'static,
+    {
+        let state = Arc::new(JobState {
+            id: self.next.fetch_add(1, Ordering::Relaxed),
+            progress: AtomicUsize::new(0.0f32.to_bits() as usize),
+            started: AtomicBool::new(false),
+            cancelled: AtomicBool::new(false),
+            finished: AtomicBool::new(false),
+        });
+
+        // Checks the termination with the queue locked, so the task is either drained
+        // by `terminate` or resolved here.
+        let mut tasks = self.queue.tasks.lock().unwrap();
+        if self.terminated.load(Ordering::Relaxed) {
+            drop(tasks);
+
+            completion(None);
+            state.finished.store(true, Ordering::Release);
+            return BackgroundJob { state: state };
+        }
+
+        let ctx = BackgroundJobContext {
+            state: state.clone(),
+            terminated: self.terminated.clone(),
+        };
+
+        let completions = self.completions.clone();
+        let waker = self.waker.clone();
+        let mut payload = Some((func, completion));
+
+        let task = move || {
+            let (func, completion) = payload.take().unwrap();
+
+            let result = if ctx.is_cancelled() {
+                None
+            } else {
+                ctx.state.started.store(true, Ordering::Relaxed);
+                match unwind::halt_unwinding(|| func(&ctx)) {
+                    Ok(result) => Some(result),
+                    Err(_) => {
+                        error!("Background job panicked.");
+                        None
+                    }
+                }
+            };
+
+            let mut resolved = Some((completion, result));
+            let cb = move || {
+                let (completion, result) = resolved.take().unwrap();
+                completion(result);
+            };
+
+            completions.lock().unwrap().push(Box::new(cb));
+            ctx.state.finished.store(true, Ordering::Release);
+
+            let waker = waker.lock().unwrap().clone();
+            if let Some(waker) = waker {
+                waker();
+            }
+        };
+
+        {
+            let mut jobs = self.jobs.lock().unwrap();
+            jobs.retain(|v| !v.finished.load(Ordering::Acquire));
+            jobs.push(state.clone());
+        }
+
+        tasks.push_back(Box::new(task));
+        drop(tasks);
+
+        self.queue.condvar.notify_one();
+        BackgroundJob { state: state }
+    }
+
+    /// Gets the jobs which are queued or running, ordered by their creation.
+    pub fn nodes(&self) -> Vec<BackgroundJobNode> {
+        let mut jobs = self.jobs.lock().unwrap();
+        jobs.retain(|v| !v.finished.load(Ordering::Acquire));
+
+        jobs.iter()
+            .map(|v| BackgroundJobNode {
+                id: v.id,
+                status: if v.started.load(Ordering::Relaxed) {
+                    BackgroundJobStatus::Running
+                } else {
+                    BackgroundJobStatus::Queued
+                },
+                progress: f32::from_bits(v.progress.load(Ordering::Relaxed) as u32),
+                cancelled: v.cancelled.load(Ordering::Relaxed),
+            })
+            .collect()
+    }
+
+    /// Delivers the completion callbacks of finished jobs. This should be called on the
+    /// main thread.
+    pub fn advance(&self) {
+        let completions: Vec<_> = self.completions.lock().unwrap().drain(..).collect();
+        for mut v in completions {
+            v();
+        }
+    }
+
+    /// Cancels all the jobs, the background threads will exit after their current jobs.
+    /// The pending jobs are resolved as cancelled, and the completions of finished jobs
+    /// are delivered. This should be called on the main thread.
+    pub fn terminate(&self) {
+        let tasks: Vec<_> = {
+            let mut tasks = self.queue.tasks.lock().unwrap();
+            self.terminated.store(true, Ordering::Relaxed);
+            tasks.drain(..).collect()
+        };
+
+        self.queue.condvar.notify_all();
+        for mut v in tasks {
+            v();
+        }
+
+        self.advance();
+    }
+
+    fn main_loop(queue: &Queue, terminated: &AtomicBool) {
+        math::float::reset_float_env();
+        lower_thread_priority();
+
+        loop {
+            let task = {
+                let mut tasks = queue.tasks.lock().unwrap();
+                loop {
+                    if terminated.load(Ordering::Relaxed) {
+                        return;
+                    }
+
+                    if let Some(task) = tasks.pop_front() {
+                        break task;
+                    }
+
+                    tasks = queue.condvar.wait(tasks).unwrap();
+                }
+            };
+
+            let mut task = task;
+            task();
+        }
+    }
+}
+
+/// Lowers the OS priority of current thread below the normal threads.
+#[cfg(any(target_os = "linux", target_os = "android"))]
+fn lower_thread_priority() {
+    // The nice value applies to the calling thread only on Linux.
+    unsafe {
+        if libc::setpriority(libc::PRIO_PROCESS, 0, 10) != 0 {
+            warn!("Failed to lower the priority of background thread.");
+        }
+    }
+}
+
+/// Lowers the OS priority of current thread below the normal threads.
+#[cfg(all(unix, not(any(target_os = "linux", target_os = "android"))))]
+fn lower_thread_priority() {
+    unsafe {
+        let mut policy = 0;
+        let mut param: libc::sched_param = ::std::mem::zeroed();
+        let thread = libc::pthread_self();
+
+        if libc::pthread_getschedparam(thread, &mut policy, &mut param) == 0 {
+            param.sched_priority = libc::sched_get_priority_min(policy);
+            if libc::pthread_setschedparam(thread, policy, &param) == 0 {
+                return;
+            }
+        }
+
+        warn!("Failed to lower the priority of background thread.");
+    }
+}
+
+/// Lowers the OS priority of current thread below the normal threads.
+#[cfg(windows)]
+fn lower_thread_priority() {
+    use winapi::um::processthreadsapi::{GetCurrentThread, SetThreadPriority};
+    use winapi::um::winbase::THREAD_PRIORITY_BELOW_NORMAL;
+
+    unsafe {
+        if SetThreadPriority(GetCurrentThread(), THREAD_PRIORITY_BELOW_NORMAL as i32) == 0 {
+            warn!("Failed to lower the priority of background thread.");
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::sync::mpsc;
+
+    fn wait(job: &BackgroundJob) {
+        while !job.is_finished() {
+            thread::yield_now();
+        }
+    }
+
+    #[test]
+    fn progress() {
+        let background = Background::new(1);
+        let (tx, rx) = mpsc::channel::<()>();
+
+        let job = background.spawn(
+            move |ctx| {
+                ctx.set_progress(0.5);
+                rx.recv().unwrap();
+                ctx.set_progress(2.0);
+            },
+            |_| {},
+        );
+
+        while job.progress() < 0.5 {
+            thread::yield_now();
+        }
+
+        assert_eq!(job.progress(), 0.5);
+        assert!(!job.is_finished());
+
+        tx.send(()).unwrap();
+        wait(&job);
+        assert_eq!(job.progress(), 1.0);
+        background.terminate();
+    }
+
+    #[test]
+    fn completion() {
+        let background = Background::new(1);
+        let main = thread::current().id();
+        let result = Arc::new(Mutex::new(None));
+
+        let job = {
+            let result = result.clone();
+            background.spawn(
+                |_| 42,
+                move |v| *result.lock().unwrap() = Some((v, thread::current().id())),
+            )
+        };
+
+        // The completions are delivered on the main thread only.
+        wait(&job);
+        assert!(result.lock().unwrap().is_none());
+
+        background.advance();
+        assert_eq!(*result.lock().unwrap(), Some((Some(42), main)));
+        background.terminate();
+    }
+
+    #[test]
+    fn cancel() {
+        let background = Background::new(1);
+        let (tx, rx) = mpsc::channel::<()>();
+        let results = Arc::new(Mutex::new(Vec::new()));
+
+        let spawn = |id: u32, rx: Option<mpsc::Receiver<()>>| {
+            let results = results.clone();
+            background.spawn(
+                move |ctx| {
+                    if let Some(rx) = rx {
+                        rx.recv().unwrap();
+                    }
+
+                    ctx.is_cancelled()
+                },
+                move |v| results.lock().unwrap().push((id, v)),
+            )
+        };
+
+        let blocking = spawn(0, Some(rx));
+        let pending = spawn(1, None);
+        pending.cancel();
+        assert!(pending.is_cancelled());
+
+        tx.send(()).unwrap();
+        wait(&blocking);
+        wait(&pending);
+
+        background.advance();
+        let v = results.lock().unwrap().clone();
+        assert_eq!(v, vec![(0, Some(false)), (1, None)]);
+        background.terminate();
+    }
+
+    #[test]
+    fn terminate() {
+        let background = Background::new(1);
+        let (tx, rx) = mpsc::channel::<()>();
+        let (started_tx, started_rx) = mpsc::channel::<()>();
+        let results = Arc::new(Mutex::new(Vec::new()));
+
+        let blocking = {
+            let results = results.clone();
+            background.spawn(
+                move |ctx| {
+                    started_tx.send(()).unwrap();
+                    rx.recv().unwrap();
+                    ctx.is_cancelled()
+                },
+                move |v| results.lock().unwrap().push((0, v)),
+            )
+        };
+
+        let pending = {
+            let results = results.clone();
+            background.spawn(|_| false, move |v| results.lock().unwrap().push((1, v)))
+        };
+
+        // The pending jobs are resolved as cancelled immediately.
+        started_rx.recv().unwrap();
+        background.terminate();
+        assert!(pending.is_finished());
+        assert_eq!(*results.lock().unwrap(), vec![(1, None)]);
+
+        tx.send(()).unwrap();
+        wait(&blocking);
+        background.advance();
+        assert_eq!(*results.lock().unwrap(), vec![(1, None), (0, Some(true))]);
+
+        // The jobs spawned after the termination are resolved immediately.
+        let late = {
+            let results = results.clone();
+            background.spawn(|_| false, move |v| results.lock().unwrap().push((2, v)))
+        };
+
+        assert!(late.is_finished());
+        assert_eq!(background.nodes().len(), 0);
+        assert_eq!(
+            *results.lock().unwrap(),
+            vec![(1, None), (0, Some(true)), (2, None)]
+        );
+    }
+
+    #[test]
+    fn waker() {
+        let background = Background::new(1);
+        let counter = Arc::new(AtomicUsize::new(0));
+
+        {
+            let counter = counter.clone();
+            background.set_waker(move || {
+                counter.fetch_add(1, Ordering::SeqCst);
+            });
+        }
+
+        let job = background.spawn(|_| {}, |_| {});
+        wait(&job);
+        while counter.load(Ordering::SeqCst) == 0 {
+            thread::yield_now();
+        }
+
+        assert_eq!(counter.load(Ordering::SeqCst), 1);
+        background.terminate();
+    }
+}
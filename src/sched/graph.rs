@@ -0,0 +1,326 @@
+//! Snapshots of the pending jobs in `ScheduleSystem` as a task graph, which helps to
+//! find out what the workers are waiting for, e.g. when a frame stalls.
+//!
+//! The fork-join scopes which have not completed yet are the nodes of graph, and a
+//! scope depends on the scopes which are created by its jobs, since it can't complete
+//! before them. The detached jobs and background jobs are listed without dependencies.
+//!
+//! The graph could be written in GraphViz dot format, or as a standalone HTML page.
+//!
+//! ```rust,ignore
+//! let graph = ctx.sched.task_graph();
+//! fs::write("tasks.dot", graph.to_dot())?;
+//! fs::write("tasks.html", graph.to_html())?;
+//! ```
+
+use std::fmt::{self, Write};
+
+/// A fork-join scope which has not completed yet.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ScopeNode {
+    pub id: usize,
+    /// The scope whose job created this scope, which is waiting for this one.
+    pub parent: Option<usize>,
+    /// The number of jobs in scope which are not finished, including the body of scope.
+    pub pending: usize,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BackgroundJobStatus {
+    Queued,
+    Running,
+}
+
+/// A background job which has not finished yet.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct BackgroundJobNode {
+    pub id: usize,
+    pub status: BackgroundJobStatus,
+    pub progress: f32,
+    pub cancelled: bool,
+}
+
+/// The pending jobs of `ScheduleSystem` at some moment.
+#[derive(Debug, Clone, Default)]
+pub struct TaskGraph {
+    pub workers: u32,
+    pub background_workers: u32,
+    /// The scopes which have not completed yet, ordered by their creation.
+    pub scopes: Vec<ScopeNode>,
+    /// The number of jobs from `spawn` which are not finished.
+    pub detached: usize,
+    /// The background jobs which are queued or running, ordered by their creation.
+    pub background: Vec<BackgroundJobNode>,
+}
+
+impl TaskGraph {
+    /// Writes the graph in GraphViz dot format.
+    pub fn write_dot<W: Write>(&self, w: &mut W) -> fmt::Result {
+        writeln!(w, "digraph TaskGraph {{")?;
+        writeln!(w, "    rankdir=LR;")?;
+        writeln!(w, "    node [fontname=\"Helvetica\", fontsize=10];")?;
+        writeln!(w, "    edge [fontname=\"Helvetica\", fontsize=9];")?;
+
+        writeln!(
+            w,
+            "    \"workers\" [shape=box3d, label=\"{} workers\\n{} detached jobs\"];",
+            self.workers, self.detached
+        )?;
+
+        for v in &self.scopes {
+            writeln!(
+                w,
+                "    \"scope:{}\" [shape=box, style=filled, fillcolor=lightblue, \
+                 label=\"scope #{}\\n{} pending\"];",
+                v.id, v.id, v.pending
+            )?;
+        }
+
+        for v in &self.scopes {
+            match v.parent {
+                Some(parent) => writeln!(
+                    w,
+                    "    \"scope:{}\" -> \"scope:{}\" [label=\"waits\"];",
+                    parent, v.id
+                )?,
+                None => writeln!(w, "    \"workers\" -> \"scope:{}\";", v.id)?,
+            }
+        }
+
+        if !self.background.is_empty() || self.background_workers > 0 {
+            writeln!(
+                w,
+                "    \"background\" [shape=box3d, label=\"{} background workers\"];",
+                self.background_workers
+            )?;
+        }
+
+        for v in &self.background {
+            let color = match v.status {
+                BackgroundJobStatus::Queued => "lightgray",
+                BackgroundJobStatus::Running => "lightyellow",
+            };
+
+            writeln!(
+                w,
+                "    \"background:{}\" [shape=ellipse, style=filled, fillcolor={}, \
+                 label=\"{}\"];",
+                v.id,
+                color,
+                background_label(v).replace('\n', "\\n")
+            )?;
+
+            writeln!(w, "    \"background\" -> \"background:{}\";", v.id)?;
+        }
+
+        writeln!(w, "}}")
+    }
+
+    /// Gets the graph in GraphViz dot format, see `write_dot` for details.
+    pub fn to_dot(&self) -> String {
+        let mut dot = String::new();
+        self.write_dot(&mut dot).unwrap();
+        dot
+    }
+
+    /// Writes the graph as a standalone HTML page, which lists the pending scopes and
+    /// background jobs. The dot source is embedded at the end of page.
+    pub fn write_html<W: Write>(&self, w: &mut W) -> fmt::Result {
+        writeln!(w, "<!DOCTYPE html>")?;
+        writeln!(w, "<html>")?;
+        writeln!(w, "<head>")?;
+        writeln!(w, "<meta charset=\"utf-8\">")?;
+        writeln!(w, "<title>TaskGraph</title>")?;
+        writeln!(w, "<style>")?;
+        writeln!(
+            w,
+            "body {{ font-family: Helvetica, sans-serif; font-size: 13px; }}"
+        )?;
+        writeln!(
+            w,
+            "table {{ border-collapse: collapse; margin-bottom: 16px; }}"
+        )?;
+        writeln!(w, "th, td {{ border: 1px solid #ccc; padding: 4px 8px; }}")?;
+        writeln!(w, "pre {{ background: #f4f4f4; padding: 8px; }}")?;
+        writeln!(w, "</style>")?;
+        writeln!(w, "</head>")?;
+        writeln!(w, "<body>")?;
+
+        writeln!(
+            w,
+            "<p>{} workers, {} background workers, {} detached jobs.</p>",
+            self.workers, self.background_workers, self.detached
+        )?;
+
+        writeln!(w, "<h2>Scopes</h2>")?;
+        writeln!(w, "<table>")?;
+        writeln!(w, "<tr><th>#</th><th>Parent</th><th>Pending</th></tr>")?;
+        for v in &self.scopes {
+            let parent = v.parent.map(|v| format!("#{}", v)).unwrap_or_default();
+            writeln!(
+                w,
+                "<tr><td>{}</td><td>{}</td><td>{}</td></tr>",
+                v.id, parent, v.pending
+            )?;
+        }
+        writeln!(w, "</table>")?;
+
+        writeln!(w, "<h2>Background Jobs</h2>")?;
+        writeln!(w, "<table>")?;
+        writeln!(
+            w,
+            "<tr><th>#</th><th>Status</th><th>Progress</th><th>Cancelled</th></tr>"
+        )?;
+        for v in &self.background {
+            writeln!(
+                w,
+                "<tr><td>{}</td><td>{:?}</td><td>{:.0}%</td><td>{}</td></tr>",
+                v.id,
+                v.status,
+                v.progress * 100.0,
+                v.cancelled
+            )?;
+        }
+        writeln!(w, "</table>")?;
+
+        writeln!(w, "<h2>Dot</h2>")?;
+        writeln!(w, "<pre>{}</pre>", escape_html(&self.to_dot()))?;
+        writeln!(w, "</body>")?;
+        writeln!(w, "</html>")
+    }
+
+    /// Gets the graph as a standalone HTML page, see `write_html` for details.
+    pub fn to_html(&self) -> String {
+        let mut html = String::new();
+        self.write_html(&mut html).unwrap();
+        html
+    }
+}
+
+fn background_label(v: &BackgroundJobNode) -> String {
+    let mut label = format!("job #{}\n{:?} {:.0}%", v.id, v.status, v.progress * 100.0);
+
+    if v.cancelled {
+        label.push_str("\ncancelled");
+    }
+
+    label
+}
+
+fn escape_html(v: &str) -> String {
+    v.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+#[cfg(test)]
+mod test {
+    use std::sync::mpsc;
+    use std::thread;
+
+    use super::super::ScheduleSystem;
+    use super::*;
+
+    #[test]
+    fn scopes() {
+        let sched = ScheduleSystem::new(2, None, None);
+        let shared = sched.shared();
+        let (tx, rx) = mpsc::channel::<()>();
+        let (entered_tx, entered_rx) = mpsc::channel::<()>();
+
+        let handle = {
+            let shared = shared.clone();
+            thread::spawn(move || {
+                let inner = shared.clone();
+                shared.scope(|s| {
+                    s.spawn(move |_| {
+                        inner.scope(|_| {
+                            entered_tx.send(()).unwrap();
+                            rx.recv().unwrap();
+                        });
+                    });
+                });
+            })
+        };
+
+        entered_rx.recv().unwrap();
+        let graph = shared.task_graph();
+        assert_eq!(graph.workers, 2);
+        assert_eq!(graph.scopes.len(), 2);
+
+        let (outer, inner) = (graph.scopes[0], graph.scopes[1]);
+        assert_eq!(outer.parent, None);
+        assert_eq!(inner.parent, Some(outer.id));
+        assert_eq!(outer.pending, 1);
+        assert_eq!(inner.pending, 1);
+
+        let dot = graph.to_dot();
+        assert!(dot.contains(&format!("\"scope:{}\" -> \"scope:{}\"", outer.id, inner.id)));
+        assert!(graph.to_html().contains("<h2>Scopes</h2>"));
+
+        tx.send(()).unwrap();
+        handle.join().unwrap();
+        assert!(shared.task_graph().scopes.is_empty());
+
+        sched.terminate();
+    }
+
+    #[test]
+    fn jobs() {
+        let sched = ScheduleSystem::new(1, None, None);
+        let shared = sched.shared();
+        let (tx, rx) = mpsc::channel::<()>();
+        let (started_tx, started_rx) = mpsc::channel::<()>();
+
+        let running = shared.spawn_background(
+            move |_| {
+                started_tx.send(()).unwrap();
+                rx.recv().unwrap();
+            },
+            |_| {},
+        );
+
+        let queued = shared.spawn_background(|_| {}, |_| {});
+        queued.cancel();
+
+        started_rx.recv().unwrap();
+        let graph = shared.task_graph();
+        assert_eq!(graph.background.len(), 2);
+        assert_eq!(graph.background[0].status, BackgroundJobStatus::Running);
+        assert_eq!(graph.background[1].status, BackgroundJobStatus::Queued);
+        assert!(graph.background[1].cancelled);
+        assert!(graph.to_dot().contains("cancelled"));
+
+        tx.send(()).unwrap();
+        while !running.is_finished() || !queued.is_finished() {
+            thread::yield_now();
+        }
+
+        assert!(shared.task_graph().background.is_empty());
+        sched.terminate();
+    }
+
+    #[test]
+    fn detached() {
+        let sched = ScheduleSystem::new(1, None, None);
+        let shared = sched.shared();
+        let (tx, rx) = mpsc::channel::<()>();
+        let (done_tx, done_rx) = mpsc::channel::<()>();
+
+        shared.spawn(move || {
+            rx.recv().unwrap();
+            done_tx.send(()).unwrap();
+        });
+
+        assert_eq!(shared.task_graph().detached, 1);
+        tx.send(()).unwrap();
+        done_rx.recv().unwrap();
+
+        while shared.task_graph().detached > 0 {
+            thread::yield_now();
+        }
+
+        sched.terminate();
+    }
+}
@@ -132,6 +132,12 @@ impl CountLatch {
         debug_assert!(!self.is_set());
         self.counter.fetch_add(1, Ordering::Relaxed);
     }
+
+    /// Gets the number of `set()` calls which are still required to set the latch.
+    #[inline]
+    pub fn pending(&self) -> usize {
+        self.counter.load(Ordering::Relaxed)
+    }
 }
 
 impl Latch for CountLatch {
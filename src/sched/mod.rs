@@ -1,3 +1,5 @@
+pub mod background;
+pub mod graph;
 pub mod latch;
 pub mod scope;
 
@@ -5,6 +7,7 @@ mod job;
 mod scheduler;
 mod unwind;
 
+use std::sync::atomic::{AtomicUsize, Ordering};
 use std::sync::Arc;
 
 /// The type for a panic handling closure. Note that this same closure
@@ -23,6 +26,8 @@ impl ScheduleSystem {
     ) -> Self {
         let shared = ScheduleSystemShared {
             scheduler: scheduler::Scheduler::new(num, stack_size, panic_handler),
+            background: background::Background::new(1),
+            detached: Arc::new(AtomicUsize::new(0)),
         };
 
         ScheduleSystem {
@@ -34,11 +39,26 @@ impl ScheduleSystem {
         self.shared.clone()
     }
 
+    /// Delivers the completion callbacks of finished background jobs. This should be
+    /// called on the main thread every frame.
+    #[inline]
+    pub fn advance(&self) {
+        self.shared.background.advance();
+    }
+
+    /// Gets the ratio of time the workers spent on executing jobs since last call,
+    /// which is sampled by engine every frame and reported in `FrameInfo`.
+    #[inline]
+    pub fn utilization(&self) -> f32 {
+        self.shared.scheduler.utilization()
+    }
+
     /// Signals that the thread-pool which owns this scheduler has been dropped.
     /// The worker threads will gradually terminate, once any extant work is
-    /// completed.
+    /// completed. The pending background jobs are cancelled.
     #[inline]
     pub fn terminate(&self) {
+        self.shared.background.terminate();
         self.shared.scheduler.terminate_dec();
     }
 
@@ -51,9 +71,49 @@ impl ScheduleSystem {
 
 pub struct ScheduleSystemShared {
     scheduler: Arc<scheduler::Scheduler>,
+    background: background::Background,
+    // The number of jobs from `spawn` which are not finished.
+    detached: Arc<AtomicUsize>,
 }
 
 impl ScheduleSystemShared {
+    /// Gets the number of threads which execute the per-frame jobs.
+    #[inline]
+    pub fn workers(&self) -> u32 {
+        self.scheduler.workers()
+    }
+
+    /// Gets the number of threads which execute the background jobs.
+    #[inline]
+    pub fn background_workers(&self) -> u32 {
+        self.background.workers()
+    }
+
+    /// Takes a snapshot of the pending scopes, detached jobs and background jobs.
+    pub fn task_graph(&self) -> graph::TaskGraph {
+        graph::TaskGraph {
+            workers: self.workers(),
+            background_workers: self.background_workers(),
+            scopes: self.scheduler.scopes().nodes(),
+            detached: self.detached.load(Ordering::Relaxed),
+            background: self.background.nodes(),
+        }
+    }
+
+    /// Runs the jobs spawned into scopes inline on the spawning thread in the order they
+    /// are spawned, so the side effects of jobs are reproducible. This is enabled in
+    /// determinism mode, and trades the parallelism for determinism.
+    #[inline]
+    pub fn set_sequential(&self, sequential: bool) {
+        self.scheduler.set_sequential(sequential);
+    }
+
+    /// Returns true if the jobs spawned into scopes are executed sequentially.
+    #[inline]
+    pub fn is_sequential(&self) -> bool {
+        self.scheduler.is_sequential()
+    }
+
     /// Blocks current thread until latch is set. Try to keep busy by popping and stealing jobs
     /// as necessary.
     #[inline]
@@ -73,9 +133,11 @@ impl ScheduleSystemShared {
             // Ensure that scheduler cannot terminate until this job has executed. This
             // ref is decremented at the (*) below.
             self.scheduler.terminate_inc();
+            self.detached.fetch_add(1, Ordering::Relaxed);
 
             let job = Box::new(job::HeapJob::new({
                 let sched = self.scheduler.clone();
+                let detached = self.detached.clone();
                 move || {
                     match unwind::halt_unwinding(func) {
                         Ok(()) => {}
@@ -84,6 +146,7 @@ impl ScheduleSystemShared {
                         }
                     }
 
+                    detached.fetch_sub(1, Ordering::Relaxed);
                     sched.terminate_dec(); // (*) permit registry to terminate now
                 }
             }));
@@ -92,6 +155,28 @@ impl ScheduleSystemShared {
         }
     }
 
+    /// Spawns a long-running job on the background threads, which has lower priority
+    /// than the per-frame jobs. The `completion` will be called with the result on the
+    /// main thread, or `None` if the job is cancelled before it starts. Jobs spawned
+    /// after the termination are resolved with `None` immediately.
+    pub fn spawn_background<F, R, C>(&self, func: F, completion: C) -> background::BackgroundJob
+    where
+        F: FnOnce(&background::BackgroundJobContext) -> R + Send + 'static,
+        R: Send + 'static,
+        C: FnOnce(Option<R>) + Send + 'static,
+    {
+        self.background.spawn(func, completion)
+    }
+
+    /// Sets the closure which is called from background threads once there are new
+    /// completions of background jobs to deliver.
+    pub fn set_background_waker<F>(&self, waker: F)
+    where
+        F: Fn() + Send + Sync + 'static,
+    {
+        self.background.set_waker(waker);
+    }
+
     /// Create a "fork-join" scope `s` and invokes the closure with a
     /// reference to `s`. This closure can then spawn asynchronous tasks
     /// into `s`. Those tasks may run asynchronously with respect to the
@@ -1,14 +1,18 @@
 use std::cell::Cell;
 use std::collections::hash_map::DefaultHasher;
 use std::hash::Hasher;
-use std::sync::atomic::{AtomicUsize, Ordering, ATOMIC_USIZE_INIT};
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering, ATOMIC_USIZE_INIT};
 use std::sync::{Arc, Condvar, Mutex};
+use std::time::{Duration, Instant};
 use std::{mem, thread};
 
 use crossbeam_deque as deque;
 
+use math;
+
 use super::job::{JobRef, StackJob};
 use super::latch::{CountLatch, Latch, LatchProbe, LatchWaitProbe, LockLatch};
+use super::scope::ScopeRegistry;
 use super::unwind::AbortIfPanic;
 use super::PanicHandler;
 
@@ -21,6 +25,9 @@ pub struct Scheduler {
     injector: Mutex<deque::Worker<JobRef>>,
 
     panic_handler: Option<Box<PanicHandler>>,
+    sampled: Mutex<Instant>,
+    sequential: AtomicBool,
+    scopes: ScopeRegistry,
 }
 
 impl Scheduler {
@@ -45,6 +52,7 @@ impl Scheduler {
                 stealer: v,
                 primed: LockLatch::new(),
                 terminated: LockLatch::new(),
+                busy: AtomicUsize::new(0),
             })
             .collect();
 
@@ -55,6 +63,9 @@ impl Scheduler {
             panic_handler: panic_handler,
             terminator: CountLatch::new(),
             watcher: Watcher(Mutex::new(()), Condvar::new()),
+            sampled: Mutex::new(Instant::now()),
+            sequential: AtomicBool::new(false),
+            scopes: ScopeRegistry::default(),
         });
 
         for (i, w) in workers.drain(..).enumerate() {
@@ -76,6 +87,12 @@ impl Scheduler {
         scheduler
     }
 
+    /// Gets the scopes which have not completed yet.
+    #[inline]
+    pub fn scopes(&self) -> &ScopeRegistry {
+        &self.scopes
+    }
+
     /// Push a job into the "external jobs" queue; it will be taken by whatever
     /// worker has nothing to do.
     pub fn inject(&self, job: JobRef) {
@@ -180,6 +197,43 @@ impl Scheduler {
         }
     }
 
+    /// Gets the number of worker threads.
+    #[inline]
+    pub fn workers(&self) -> u32 {
+        self.threads.len() as u32
+    }
+
+    /// Executes the jobs spawned into scopes inline and in spawn order, instead of
+    /// distributing them to the workers.
+    #[inline]
+    pub fn set_sequential(&self, sequential: bool) {
+        self.sequential.store(sequential, Ordering::SeqCst);
+    }
+
+    #[inline]
+    pub fn is_sequential(&self) -> bool {
+        self.sequential.load(Ordering::Relaxed)
+    }
+
+    /// Gets the ratio of time the workers spent on executing jobs since last call.
+    pub fn utilization(&self) -> f32 {
+        let mut sampled = self.sampled.lock().unwrap();
+        let elapsed = sampled.elapsed();
+        *sampled = Instant::now();
+
+        let mut busy = 0;
+        for v in &self.threads {
+            busy += v.busy.swap(0, Ordering::Relaxed);
+        }
+
+        let total = to_micros(elapsed) * self.threads.len();
+        if total == 0 {
+            0.0
+        } else {
+            (busy as f64 / total as f64).min(1.0) as f32
+        }
+    }
+
     #[inline]
     pub fn terminate_dec(&self) {
         self.terminator.set();
@@ -214,9 +268,11 @@ impl Scheduler {
             index: index,
             worker: worker,
             rand: XorShift64Star::new(),
+            depth: Cell::new(0),
         };
 
         WorkerThread::set_current(&worker_thread);
+        math::float::reset_float_env();
 
         worker_thread.scheduler.threads[index].primed.set(());
 
@@ -251,6 +307,7 @@ pub struct WorkerThread {
     index: usize,
     worker: deque::Worker<JobRef>,
     rand: XorShift64Star,
+    depth: Cell<u32>,
 }
 
 // This is a bit sketchy, but basically: the WorkerThread is allocated on the
@@ -296,8 +353,7 @@ impl WorkerThread {
                 .or_else(|| self.steal())
                 .or_else(|| self.scheduler.inject_stealer.steal())
             {
-                job.execute();
-                self.scheduler.watcher.notify_all();
+                self.execute(job);
             } else {
                 thread::yield_now();
             }
@@ -314,8 +370,7 @@ impl WorkerThread {
                 .or_else(|| self.steal())
                 .or_else(|| self.scheduler.inject_stealer.steal())
             {
-                job.execute();
-                self.scheduler.watcher.notify_all();
+                self.execute(job);
             } else {
                 self.scheduler.watcher.wait();
             }
@@ -324,6 +379,31 @@ impl WorkerThread {
         mem::forget(abort_guard);
     }
 
+    // Executes the job, and accumulates the busy time of this worker. Jobs executed
+    // while waiting inside of another job are already counted.
+    //
+    // The floating point environment is reset before every outermost job, in case the
+    // previous one changed it, so jobs produce the same results on every worker.
+    unsafe fn execute(&self, job: JobRef) {
+        let depth = self.depth.get();
+        let ts = Instant::now();
+
+        if depth == 0 {
+            math::float::reset_float_env();
+        }
+
+        self.depth.set(depth + 1);
+        job.execute();
+        self.depth.set(depth);
+
+        if depth == 0 {
+            let busy = &self.scheduler.threads[self.index].busy;
+            busy.fetch_add(to_micros(ts.elapsed()), Ordering::Relaxed);
+        }
+
+        self.scheduler.watcher.notify_all();
+    }
+
     /// Attempts to obtain a "local" job.
     #[inline]
     unsafe fn steal_local(&self) -> Option<JobRef> {
@@ -350,6 +430,12 @@ struct ThreadInfo {
     stealer: deque::Stealer<JobRef>,
     primed: LockLatch<()>,
     terminated: LockLatch<()>,
+    busy: AtomicUsize,
+}
+
+#[inline]
+fn to_micros(duration: Duration) -> usize {
+    duration.as_secs() as usize * 1_000_000 + duration.subsec_nanos() as usize / 1_000
 }
 
 /// [xorshift*] is a fast pseudorandom number generator which will even tolerate
@@ -391,3 +477,60 @@ impl XorShift64Star {
         (self.next() % n as u64) as usize
     }
 }
+
+#[cfg(test)]
+mod test {
+    use std::thread;
+    use std::time::Duration;
+
+    use super::super::ScheduleSystem;
+
+    #[test]
+    fn utilization() {
+        let sched = ScheduleSystem::new(2, None, None);
+        let shared = sched.shared();
+
+        // The workers which are waiting for jobs are not busy.
+        sched.utilization();
+        thread::sleep(Duration::from_millis(20));
+        assert_eq!(sched.utilization(), 0.0);
+
+        shared.scope(|s| {
+            for _ in 0..2 {
+                s.spawn(|_| thread::sleep(Duration::from_millis(50)));
+            }
+        });
+
+        let v = sched.utilization();
+        assert!(v > 0.25 && v <= 1.0, "{}", v);
+
+        // The busy time is reset after each sample.
+        thread::sleep(Duration::from_millis(20));
+        assert_eq!(sched.utilization(), 0.0);
+
+        sched.terminate();
+    }
+
+    #[test]
+    fn nested_utilization() {
+        let sched = ScheduleSystem::new(1, None, None);
+        let shared = sched.shared();
+
+        // The jobs executed while waiting inside of another job are not counted twice,
+        // so the worker is busy for about half of the time.
+        sched.utilization();
+        thread::sleep(Duration::from_millis(50));
+        let inner = shared.clone();
+        shared.scope(|s| {
+            s.spawn(move |_| {
+                inner.scope(|s| {
+                    s.spawn(|_| thread::sleep(Duration::from_millis(50)));
+                });
+            });
+        });
+
+        let v = sched.utilization();
+        assert!(v > 0.25 && v < 0.75, "{}", v);
+        sched.terminate();
+    }
+}
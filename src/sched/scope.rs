@@ -1,18 +1,68 @@
 use std::any::Any;
+use std::cell::Cell;
 use std::marker::PhantomData;
-use std::sync::atomic::{AtomicPtr, Ordering};
-use std::sync::Arc;
+use std::sync::atomic::{AtomicPtr, AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex, Weak};
 use std::{mem, ptr};
 
+use super::graph::ScopeNode;
 use super::job::HeapJob;
 use super::latch::{CountLatch, Latch};
 use super::scheduler::{Scheduler, WorkerThread};
 use super::unwind;
 
+thread_local! {
+    // The scope whose job is being executed on current thread.
+    static CURRENT: Cell<Option<usize>> = Cell::new(None);
+}
+
+/// The scopes which have not completed yet, which are exported with `TaskGraph`.
+#[derive(Default)]
+pub struct ScopeRegistry {
+    next: AtomicUsize,
+    scopes: Mutex<Vec<(ScopeNode, Weak<CountLatch>)>>,
+}
+
+impl ScopeRegistry {
+    fn register(&self, latch: &Arc<CountLatch>) -> usize {
+        let id = self.next.fetch_add(1, Ordering::Relaxed);
+        let node = ScopeNode {
+            id: id,
+            parent: CURRENT.with(|v| v.get()),
+            pending: 0,
+        };
+
+        self.scopes
+            .lock()
+            .unwrap()
+            .push((node, Arc::downgrade(latch)));
+        id
+    }
+
+    fn unregister(&self, id: usize) {
+        self.scopes.lock().unwrap().retain(|v| v.0.id != id);
+    }
+
+    /// Gets the scopes with their pending jobs, ordered by their creation.
+    pub fn nodes(&self) -> Vec<ScopeNode> {
+        let scopes = self.scopes.lock().unwrap();
+        scopes
+            .iter()
+            .filter_map(|&(node, ref latch)| {
+                latch.upgrade().map(|v| ScopeNode {
+                    pending: v.pending(),
+                    ..node
+                })
+            })
+            .collect()
+    }
+}
+
 /// Represents a fork-join scope which can be used to spawn any number of tasks.
 pub struct Scope<'s> {
+    id: usize,
     scheduler: Arc<Scheduler>,
-    latch: CountLatch,
+    latch: Arc<CountLatch>,
     marker: PhantomData<Box<FnOnce(&Scope<'s>) + Send + Sync + 's>>,
     /// if some job panicked, the error is stored here; it will be
     /// propagated to the one who created the scope
@@ -21,9 +71,13 @@ pub struct Scope<'s> {
 
 impl<'s> Scope<'s> {
     pub fn new(scheduler: Arc<Scheduler>) -> Self {
+        let latch = Arc::new(CountLatch::new());
+        let id = scheduler.scopes().register(&latch);
+
         Scope {
+            id: id,
             scheduler: scheduler,
-            latch: CountLatch::new(),
+            latch: latch,
             marker: PhantomData::default(),
             panic: AtomicPtr::new(ptr::null_mut()),
         }
@@ -40,6 +94,11 @@ impl<'s> Scope<'s> {
         unsafe {
             self.latch.increment();
 
+            if self.scheduler.is_sequential() {
+                self.execute(func);
+                return;
+            }
+
             let job = Box::new(HeapJob::new(move || {
                 let _v = self.execute(func);
             })).as_job_ref();
@@ -56,7 +115,11 @@ impl<'s> Scope<'s> {
     where
         F: FnOnce(&Scope<'s>) -> R + 's,
     {
-        match unwind::halt_unwinding(move || func(self)) {
+        let parent = CURRENT.with(|v| v.replace(Some(self.id)));
+        let result = unwind::halt_unwinding(move || func(self));
+        CURRENT.with(|v| v.set(parent));
+
+        match result {
             Ok(r) => {
                 self.latch.set();
                 Some(r)
@@ -80,7 +143,7 @@ impl<'s> Scope<'s> {
 
     pub(crate) unsafe fn wait_until_completed(&self, worker: &WorkerThread) {
         // wait for job counter to reach 0:
-        worker.hot_wait_until(&self.latch);
+        worker.hot_wait_until(&*self.latch);
 
         // propagate panic, if any occurred; at this point, all outstanding jobs have completed,
         // so we can use a relaxed ordering:
@@ -91,3 +154,9 @@ impl<'s> Scope<'s> {
         }
     }
 }
+
+impl<'s> Drop for Scope<'s> {
+    fn drop(&mut self) {
+        self.scheduler.scopes().unregister(self.id);
+    }
+}
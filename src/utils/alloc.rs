@@ -0,0 +1,497 @@
+//! An optional allocator wrapper which tracks the heap allocations of sub-systems.
+//!
+//! The allocations are attributed to the `AllocCategory` of innermost `AllocScope` on
+//! current thread. To enable the tracking, installs the `TrackingAllocator` as global
+//! allocator in your application:
+//!
+//! ```rust,ignore
+//! use std::alloc::System;
+//! use crayon::utils::alloc::TrackingAllocator;
+//!
+//! #[global_allocator]
+//! static GLOBAL: TrackingAllocator<System> = TrackingAllocator(System);
+//! ```
+//!
+//! Notes that the statistics are always zero if `TrackingAllocator` is not installed.
+//!
+//! In debug builds, the call stacks of the largest allocations of every category are
+//! also captured, which could be retrieved with `offenders`.
+
+use std::alloc::{GlobalAlloc, Layout};
+use std::cell::Cell;
+use std::cmp;
+use std::mem;
+use std::ptr;
+use std::sync::atomic::{AtomicUsize, Ordering, ATOMIC_USIZE_INIT};
+
+/// The sub-systems which heap allocations will be attributed to.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum AllocCategory {
+    General = 0,
+    Application,
+    Video,
+    Resource,
+    Input,
+    Sched,
+    Module,
+}
+
+impl AllocCategory {
+    /// All the categories.
+    pub const ALL: [AllocCategory; NUM_CATEGORIES] = [
+        AllocCategory::General,
+        AllocCategory::Application,
+        AllocCategory::Video,
+        AllocCategory::Resource,
+        AllocCategory::Input,
+        AllocCategory::Sched,
+        AllocCategory::Module,
+    ];
+}
+
+/// The number of `AllocCategory`.
+pub const NUM_CATEGORIES: usize = 7;
+
+/// The allocations smaller than this are never traced as offenders.
+pub const MIN_TRACED_BYTES: usize = 64 * 1024;
+
+/// The allocation statistics of a category.
+#[derive(Debug, Copy, Clone, Default, PartialEq, Eq)]
+pub struct AllocCategoryStats {
+    /// The bytes currently allocated.
+    pub bytes: usize,
+    /// The peak value of `bytes`.
+    pub peak_bytes: usize,
+    /// The number of allocations since the start.
+    pub allocations: usize,
+    /// The size of largest single allocation.
+    pub largest: usize,
+}
+
+/// A snapshot of allocation statistics.
+#[derive(Debug, Copy, Clone, Default, PartialEq, Eq)]
+pub struct AllocStats {
+    pub categories: [AllocCategoryStats; NUM_CATEGORIES],
+}
+
+impl AllocStats {
+    /// Gets the statistics of specified category.
+    #[inline]
+    pub fn category(&self, category: AllocCategory) -> &AllocCategoryStats {
+        &self.categories[category as usize]
+    }
+
+    /// Gets the bytes currently allocated of all categories.
+    pub fn total_bytes(&self) -> usize {
+        self.categories.iter().map(|v| v.bytes).sum()
+    }
+}
+
+/// The call stack of the largest allocation in a category.
+#[derive(Debug, Clone)]
+pub struct AllocOffender {
+    pub category: AllocCategory,
+    /// The size of allocation in bytes.
+    pub size: usize,
+    /// The formatted call stack, which is empty if backtraces are not enabled.
+    pub backtrace: String,
+}
+
+struct Counters {
+    bytes: AtomicUsize,
+    peak_bytes: AtomicUsize,
+    allocations: AtomicUsize,
+    largest: AtomicUsize,
+}
+
+const COUNTERS_INIT: Counters = Counters {
+    bytes: ATOMIC_USIZE_INIT,
+    peak_bytes: ATOMIC_USIZE_INIT,
+    allocations: ATOMIC_USIZE_INIT,
+    largest: ATOMIC_USIZE_INIT,
+};
+
+static COUNTERS: [Counters; NUM_CATEGORIES] = [
+    COUNTERS_INIT,
+    COUNTERS_INIT,
+    COUNTERS_INIT,
+    COUNTERS_INIT,
+    COUNTERS_INIT,
+    COUNTERS_INIT,
+    COUNTERS_INIT,
+];
+
+static FRAME_ALLOCATIONS: AtomicUsize = ATOMIC_USIZE_INIT;
+
+thread_local! {
+    static CATEGORY: Cell<usize> = Cell::new(0);
+}
+
+fn fetch_max(v: &AtomicUsize, value: usize) {
+    let mut current = v.load(Ordering::Relaxed);
+    while current < value {
+        match v.compare_exchange_weak(current, value, Ordering::Relaxed, Ordering::Relaxed) {
+            Ok(_) => return,
+            Err(prev) => current = prev,
+        }
+    }
+}
+
+/// A scope guard which attributes the allocations on current thread to its category,
+/// the previous category will be restored when it goes out of scope.
+pub struct AllocScope {
+    previous: usize,
+}
+
+impl Drop for AllocScope {
+    fn drop(&mut self) {
+        let previous = self.previous;
+        let _ = CATEGORY.try_with(|v| v.set(previous));
+    }
+}
+
+/// Attributes the following allocations on current thread to `category`, until the
+/// returned `AllocScope` is dropped.
+pub fn scope(category: AllocCategory) -> AllocScope {
+    let previous = CATEGORY
+        .try_with(|v| v.replace(category as usize))
+        .unwrap_or(0);
+
+    AllocScope { previous: previous }
+}
+
+/// Gets a snapshot of allocation statistics.
+pub fn stats() -> AllocStats {
+    let mut stats = AllocStats::default();
+    for (i, v) in COUNTERS.iter().enumerate() {
+        stats.categories[i] = AllocCategoryStats {
+            bytes: v.bytes.load(Ordering::Relaxed),
+            peak_bytes: v.peak_bytes.load(Ordering::Relaxed),
+            allocations: v.allocations.load(Ordering::Relaxed),
+            largest: v.largest.load(Ordering::Relaxed),
+        };
+    }
+
+    stats
+}
+
+/// Gets the number of allocations since the start of this frame.
+#[inline]
+pub fn frame_allocations() -> usize {
+    FRAME_ALLOCATIONS.load(Ordering::Relaxed)
+}
+
+/// Gets the call stacks of the largest allocation (of `MIN_TRACED_BYTES` at least) in
+/// every category, ordered by their sizes. The call stacks are only captured in debug
+/// builds, and requires `RUST_BACKTRACE` to be set like the backtraces of errors.
+pub fn offenders() -> Vec<AllocOffender> {
+    stacks::collect()
+}
+
+/// Logs the call stacks of `offenders`.
+pub fn log_offenders() {
+    for v in offenders() {
+        info!(
+            "The largest allocation of {:?} is {} bytes.\n{}",
+            v.category, v.size, v.backtrace
+        );
+    }
+}
+
+/// Resets the per-frame counter, returns the number of allocations during last frame.
+#[inline]
+pub(crate) fn advance() -> usize {
+    FRAME_ALLOCATIONS.swap(0, Ordering::Relaxed)
+}
+
+/// The allocator wrapper which tracks the allocations with `AllocCategory`.
+pub struct TrackingAllocator<A: GlobalAlloc>(pub A);
+
+impl<A: GlobalAlloc> TrackingAllocator<A> {
+    // The category is stored in a header right before the allocated block, the header
+    // is padded to keep the alignment of block. Returns `None` if the padded size
+    // overflows.
+    #[inline]
+    fn header(layout: &Layout) -> Option<(Layout, usize)> {
+        let align = cmp::max(layout.align(), mem::size_of::<usize>());
+        let size = layout.size().checked_add(align)?;
+        let layout = Layout::from_size_align(size, align).ok()?;
+        Some((layout, align))
+    }
+}
+
+unsafe impl<A: GlobalAlloc> GlobalAlloc for TrackingAllocator<A> {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        let (padded, offset) = match Self::header(&layout) {
+            Some(v) => v,
+            None => return ptr::null_mut(),
+        };
+
+        let ptr = self.0.alloc(padded);
+        if ptr.is_null() {
+            return ptr;
+        }
+
+        let category = CATEGORY.try_with(|v| v.get()).unwrap_or(0);
+        *(ptr as *mut usize) = category;
+
+        let counters = &COUNTERS[category];
+        let bytes = counters.bytes.fetch_add(layout.size(), Ordering::Relaxed) + layout.size();
+        fetch_max(&counters.peak_bytes, bytes);
+        fetch_max(&counters.largest, layout.size());
+        counters.allocations.fetch_add(1, Ordering::Relaxed);
+        FRAME_ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+        stacks::record(category, layout.size());
+
+        ptr.offset(offset as isize)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        // The layout has been padded successfully when allocating.
+        let (padded, offset) = match Self::header(&layout) {
+            Some(v) => v,
+            None => return,
+        };
+
+        let ptr = ptr.offset(-(offset as isize));
+
+        let category = *(ptr as *const usize);
+        COUNTERS[category]
+            .bytes
+            .fetch_sub(layout.size(), Ordering::Relaxed);
+
+        self.0.dealloc(ptr, padded);
+    }
+}
+
+#[cfg(debug_assertions)]
+mod stacks {
+    use std::cell::Cell;
+    use std::sync::atomic::{AtomicUsize, Ordering, ATOMIC_USIZE_INIT};
+    use std::sync::{Mutex, Once, ONCE_INIT};
+
+    use failure::Backtrace;
+
+    use super::{AllocCategory, AllocOffender, MIN_TRACED_BYTES, NUM_CATEGORIES};
+
+    type Stacks = Mutex<Vec<(usize, usize, Backtrace)>>;
+
+    thread_local! {
+        // Capturing and formatting call stacks allocate too, which must not be traced
+        // recursively.
+        static TRACING: Cell<bool> = Cell::new(false);
+    }
+
+    static SIZES: [AtomicUsize; NUM_CATEGORIES] = [
+        ATOMIC_USIZE_INIT,
+        ATOMIC_USIZE_INIT,
+        ATOMIC_USIZE_INIT,
+        ATOMIC_USIZE_INIT,
+        ATOMIC_USIZE_INIT,
+        ATOMIC_USIZE_INIT,
+        ATOMIC_USIZE_INIT,
+    ];
+
+    static INIT: Once = ONCE_INIT;
+    static mut STACKS: *const Stacks = 0 as *const _;
+
+    fn stacks() -> &'static Stacks {
+        unsafe {
+            INIT.call_once(|| {
+                STACKS = Box::into_raw(Box::new(Mutex::new(Vec::new())));
+            });
+
+            &*STACKS
+        }
+    }
+
+    fn untraced<F: FnOnce()>(func: F) {
+        let _ = TRACING.try_with(|v| {
+            if !v.replace(true) {
+                func();
+                v.set(false);
+            }
+        });
+    }
+
+    pub fn record(category: usize, size: usize) {
+        // Only the allocations larger than the recorded one are traced, so the cost of
+        // capturing call stacks is paid rarely.
+        if size < MIN_TRACED_BYTES || size <= SIZES[category].load(Ordering::Relaxed) {
+            return;
+        }
+
+        untraced(|| {
+            let backtrace = Backtrace::new();
+            if let Ok(mut stacks) = stacks().lock() {
+                if size > SIZES[category].load(Ordering::Relaxed) {
+                    SIZES[category].store(size, Ordering::Relaxed);
+                    stacks.retain(|v| v.0 != category);
+                    stacks.push((category, size, backtrace));
+                }
+            }
+        });
+    }
+
+    pub fn collect() -> Vec<AllocOffender> {
+        let mut offenders = Vec::new();
+
+        untraced(|| {
+            if let Ok(stacks) = stacks().lock() {
+                for &(category, size, ref backtrace) in stacks.iter() {
+                    offenders.push(AllocOffender {
+                        category: AllocCategory::ALL[category],
+                        size: size,
+                        backtrace: format!("{}", backtrace),
+                    });
+                }
+            }
+        });
+
+        offenders.sort_by(|lhs, rhs| rhs.size.cmp(&lhs.size));
+        offenders
+    }
+}
+
+#[cfg(not(debug_assertions))]
+mod stacks {
+    use super::AllocOffender;
+
+    #[inline]
+    pub fn record(_: usize, _: usize) {}
+
+    #[inline]
+    pub fn collect() -> Vec<AllocOffender> {
+        Vec::new()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::alloc::System;
+
+    // The allocator is not installed globally in tests, so the counters of a category
+    // are only touched by the test which uses it.
+    #[test]
+    fn round_trip() {
+        let allocator = TrackingAllocator(System);
+        let _scope = scope(AllocCategory::Module);
+        let base = *stats().category(AllocCategory::Module);
+
+        unsafe {
+            let layout = Layout::from_size_align(64, 32).unwrap();
+            let ptr = allocator.alloc(layout);
+            assert!(!ptr.is_null());
+            assert_eq!(ptr as usize % 32, 0);
+
+            for i in 0..64 {
+                *ptr.offset(i) = i as u8;
+            }
+
+            let v = *stats().category(AllocCategory::Module);
+            assert_eq!(v.bytes, base.bytes + 64);
+            assert_eq!(v.allocations, base.allocations + 1);
+            assert!(v.largest >= 64);
+
+            let ptr = allocator.realloc(ptr, layout, 128);
+            assert!(!ptr.is_null());
+            assert_eq!(ptr as usize % 32, 0);
+
+            for i in 0..64 {
+                assert_eq!(*ptr.offset(i), i as u8);
+            }
+
+            let v = *stats().category(AllocCategory::Module);
+            assert_eq!(v.bytes, base.bytes + 128);
+            assert!(v.peak_bytes >= base.bytes + 128);
+
+            allocator.dealloc(ptr, Layout::from_size_align(128, 32).unwrap());
+            let v = *stats().category(AllocCategory::Module);
+            assert_eq!(v.bytes, base.bytes);
+        }
+    }
+
+    #[test]
+    fn overflow() {
+        let allocator = TrackingAllocator(System);
+        let size = ::std::isize::MAX as usize - 7;
+
+        unsafe {
+            let layout = Layout::from_size_align(size, 8).unwrap();
+            assert!(allocator.alloc(layout).is_null());
+        }
+    }
+
+    #[test]
+    fn categories() {
+        let allocator = TrackingAllocator(System);
+        let base = stats();
+
+        unsafe {
+            let layout = Layout::from_size_align(24, 8).unwrap();
+            let mut ptrs = Vec::new();
+
+            {
+                let _scope = scope(AllocCategory::Video);
+                ptrs.push(allocator.alloc(layout));
+
+                {
+                    let _scope = scope(AllocCategory::Input);
+                    ptrs.push(allocator.alloc(layout));
+                }
+
+                ptrs.push(allocator.alloc(layout));
+            }
+
+            let v = stats();
+            let video = base.category(AllocCategory::Video);
+            let input = base.category(AllocCategory::Input);
+            assert_eq!(v.category(AllocCategory::Video).bytes, video.bytes + 48);
+            assert_eq!(v.category(AllocCategory::Input).bytes, input.bytes + 24);
+
+            let allocations = v.category(AllocCategory::Video).allocations;
+            assert_eq!(allocations, video.allocations + 2);
+
+            // Deallocations are attributed to the category of allocation.
+            let _scope = scope(AllocCategory::Sched);
+            for ptr in ptrs {
+                allocator.dealloc(ptr, layout);
+            }
+
+            let v = stats();
+            assert_eq!(v.category(AllocCategory::Video).bytes, video.bytes);
+            assert_eq!(v.category(AllocCategory::Input).bytes, input.bytes);
+        }
+    }
+
+    #[test]
+    fn worst_offenders() {
+        let allocator = TrackingAllocator(System);
+        let _scope = scope(AllocCategory::Resource);
+
+        let sizes = || -> Vec<usize> {
+            offenders()
+                .iter()
+                .filter(|v| v.category == AllocCategory::Resource)
+                .map(|v| v.size)
+                .collect()
+        };
+
+        unsafe {
+            for &size in &[MIN_TRACED_BYTES - 1, MIN_TRACED_BYTES * 2, MIN_TRACED_BYTES] {
+                let layout = Layout::from_size_align(size, 8).unwrap();
+                let ptr = allocator.alloc(layout);
+                assert!(!ptr.is_null());
+                allocator.dealloc(ptr, layout);
+            }
+        }
+
+        // Only the largest allocation is recorded.
+        if cfg!(debug_assertions) {
+            assert_eq!(sizes(), vec![MIN_TRACED_BYTES * 2]);
+        } else {
+            assert!(sizes().is_empty());
+        }
+    }
+}
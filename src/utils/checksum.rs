@@ -0,0 +1,135 @@
+//! Platform independent hashing of simulation states, e.g. comparing the states of
+//! peers in lockstep networking to detect desyncs.
+
+use std::hash::Hasher;
+
+const FNV_OFFSET: u64 = 0xcbf2_9ce4_8422_2325;
+const FNV_PRIME: u64 = 0x0000_0100_0000_01b3;
+
+/// A [FNV-1a] hasher which writes integers in little-endian and `usize` as 64-bit,
+/// so the same states produce the same checksums on every platform, unlike the
+/// `DefaultHasher` which is randomly keyed and might change between releases.
+///
+/// [FNV-1a]: https://en.wikipedia.org/wiki/Fowler%E2%80%93Noll%E2%80%93Vo_hash_function
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Checksum(u64);
+
+impl Default for Checksum {
+    fn default() -> Self {
+        Checksum(FNV_OFFSET)
+    }
+}
+
+impl Checksum {
+    pub fn new() -> Self {
+        Checksum::default()
+    }
+
+    /// Writes the bits of float. The negative zero and NaNs are canonicalized, since
+    /// they compare equal but might be produced differently on different platforms.
+    #[inline]
+    pub fn write_f32(&mut self, v: f32) {
+        let bits = if v == 0.0 {
+            0
+        } else if v.is_nan() {
+            0x7fc0_0000
+        } else {
+            v.to_bits()
+        };
+
+        self.write_u32(bits);
+    }
+
+    #[inline]
+    pub fn write_f32s(&mut self, v: &[f32]) {
+        for &f in v {
+            self.write_f32(f);
+        }
+    }
+}
+
+impl Hasher for Checksum {
+    #[inline]
+    fn finish(&self) -> u64 {
+        self.0
+    }
+
+    #[inline]
+    fn write(&mut self, bytes: &[u8]) {
+        for &v in bytes {
+            self.0 ^= u64::from(v);
+            self.0 = self.0.wrapping_mul(FNV_PRIME);
+        }
+    }
+
+    #[inline]
+    fn write_u16(&mut self, v: u16) {
+        self.write(&[v as u8, (v >> 8) as u8]);
+    }
+
+    #[inline]
+    fn write_u32(&mut self, v: u32) {
+        self.write(&[v as u8, (v >> 8) as u8, (v >> 16) as u8, (v >> 24) as u8]);
+    }
+
+    #[inline]
+    fn write_u64(&mut self, v: u64) {
+        self.write_u32(v as u32);
+        self.write_u32((v >> 32) as u32);
+    }
+
+    #[inline]
+    fn write_usize(&mut self, v: usize) {
+        self.write_u64(v as u64);
+    }
+
+    #[inline]
+    fn write_i16(&mut self, v: i16) {
+        self.write_u16(v as u16);
+    }
+
+    #[inline]
+    fn write_i32(&mut self, v: i32) {
+        self.write_u32(v as u32);
+    }
+
+    #[inline]
+    fn write_i64(&mut self, v: i64) {
+        self.write_u64(v as u64);
+    }
+
+    #[inline]
+    fn write_isize(&mut self, v: isize) {
+        self.write_u64(v as u64);
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn stable() {
+        let mut hasher = Checksum::new();
+        assert_eq!(hasher.finish(), FNV_OFFSET);
+
+        hasher.write(b"a");
+        assert_eq!(hasher.finish(), 0xaf63_dc4c_8601_ec8c);
+
+        let (mut lhs, mut rhs) = (Checksum::new(), Checksum::new());
+        lhs.write_usize(0x0102_0304);
+        rhs.write(&[4, 3, 2, 1, 0, 0, 0, 0]);
+        assert_eq!(lhs.finish(), rhs.finish());
+    }
+
+    #[test]
+    fn floats() {
+        let (mut lhs, mut rhs) = (Checksum::new(), Checksum::new());
+        lhs.write_f32s(&[0.0, ::std::f32::NAN, 1.5]);
+        rhs.write_f32s(&[-0.0, -::std::f32::NAN, 1.5]);
+        assert_eq!(lhs.finish(), rhs.finish());
+
+        rhs.write_f32(1.0);
+        assert_ne!(lhs.finish(), rhs.finish());
+    }
+}
@@ -4,7 +4,7 @@ use std::collections::binary_heap::BinaryHeap;
 
 use super::{Handle, HandleIndex};
 
-#[derive(PartialEq, Eq)]
+#[derive(Clone, PartialEq, Eq)]
 struct InverseHandleIndex(HandleIndex);
 
 impl PartialOrd for InverseHandleIndex {
@@ -22,7 +22,7 @@ impl Ord for InverseHandleIndex {
 /// `HandlePool` manages the manipulations of a `Handle` collection, which are
 /// created with a continuous `index` field. It also have the ability to find
 /// out the current status of a specified `Handle`.
-#[derive(Default)]
+#[derive(Default, Clone)]
 pub struct HandlePool {
     versions: Vec<HandleIndex>,
     frees: BinaryHeap<InverseHandleIndex>,
@@ -104,6 +104,44 @@ impl HandlePool {
         }
     }
 
+    /// Reassigns the alive `handles` to the continuous indices `0..handles.len()` in
+    /// order, and returns the new handles. The alive handles that are not in `handles`
+    /// are freed.
+    ///
+    /// The versions of all indices are bumped, so none of the previous handles would
+    /// be alive again even if their indices are reused.
+    ///
+    /// # Panics
+    ///
+    /// Panics if any of `handles` is not alive, or is duplicated.
+    pub fn compact(&mut self, handles: &[Handle]) -> Vec<Handle> {
+        let mut compacted = vec![false; self.versions.len()];
+        for v in handles {
+            assert!(self.is_alive(v), "Compacting dead handle {:?}.", v);
+
+            let index = v.index() as usize;
+            assert!(!compacted[index], "Compacting duplicated handle {:?}.", v);
+            compacted[index] = true;
+        }
+
+        for v in &mut self.versions {
+            *v += *v & 0x1;
+        }
+
+        self.frees.clear();
+        for i in 0..self.versions.len() {
+            if i < handles.len() {
+                self.versions[i] += 1;
+            } else {
+                self.frees.push(InverseHandleIndex(i as HandleIndex));
+            }
+        }
+
+        (0..handles.len())
+            .map(|i| Handle::new(i as HandleIndex, self.versions[i]))
+            .collect()
+    }
+
     /// Clears the `HandlePool`, removing all versions. Keeps the allocated memory
     /// for reuse.
     pub fn clear(&mut self) {
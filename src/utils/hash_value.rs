@@ -1,8 +1,39 @@
+use std::fmt;
 use std::hash::{Hash, Hasher};
 use std::marker::PhantomData;
 use std::path::Path;
 
-#[derive(Serialize, Deserialize, Debug, Eq)]
+/// Computes the `HashValue<str>` of a string literal. The hash is computed only once
+/// for each call site, and cached for later uses. The literal is also registered for
+/// reverse lookup in debug builds.
+///
+/// Notes that the hash is NOT computed at compile time. `HashValue` is built on the
+/// `DefaultHasher` of std, which can't be evaluated in constant expressions, and its
+/// algorithm might change between compiler releases. So the first use of every call
+/// site hashes the literal at runtime, and the later uses cost a synchronized load only.
+///
+/// ```rust,ignore
+/// let v = hstr!("u_ModelViewMatrix");
+/// ```
+#[macro_export]
+macro_rules! hstr {
+    ($v: literal) => {{
+        use std::sync::{Once, ONCE_INIT};
+
+        static INIT: Once = ONCE_INIT;
+        static mut HASH: u64 = 0;
+
+        unsafe {
+            INIT.call_once(|| {
+                HASH = $crate::utils::hash_value::HashValue::<str>::register($v).value();
+            });
+
+            $crate::utils::hash_value::HashValue::<str>::from_value(HASH)
+        }
+    }};
+}
+
+#[derive(Serialize, Deserialize, Eq)]
 pub struct HashValue<T>(u64, PhantomData<T>)
 where
     T: Hash + ?Sized;
@@ -14,6 +45,61 @@ where
     pub fn zero() -> Self {
         HashValue(0, PhantomData)
     }
+
+    /// Creates a `HashValue` from the raw hash value.
+    #[inline]
+    pub fn from_value(v: u64) -> Self {
+        HashValue(v, PhantomData)
+    }
+
+    /// Gets the raw hash value.
+    #[inline]
+    pub fn value(&self) -> u64 {
+        self.0
+    }
+}
+
+impl HashValue<str> {
+    /// Computes the hash of `v`, and records `v` for reverse lookup in debug builds.
+    ///
+    /// Plain conversions with `From` are not recorded, so the lookup table only holds
+    /// the literals from `hstr!` and the names declared by assets.
+    pub fn register<T: AsRef<str>>(v: T) -> Self {
+        let v = v.as_ref();
+        let hash = hash(&v);
+        registery::register(hash, v);
+        HashValue(hash, PhantomData)
+    }
+
+    /// Gets the original string of this hash. The reverse lookup table is only available
+    /// in debug builds, so it always returns `None` in release builds.
+    pub fn name(&self) -> Option<String> {
+        registery::lookup(self.0)
+    }
+}
+
+impl fmt::Debug for HashValue<str> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.name() {
+            Some(name) => write!(f, "HashValue({:?})", name),
+            None => write!(f, "HashValue({:X})", self.0),
+        }
+    }
+}
+
+impl fmt::Display for HashValue<str> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.name() {
+            Some(name) => write!(f, "{}", name),
+            None => write!(f, "#{:X}", self.0),
+        }
+    }
+}
+
+impl fmt::Debug for HashValue<Path> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "HashValue({:X})", self.0)
+    }
 }
 
 impl<T> Clone for HashValue<T>
@@ -96,6 +182,65 @@ fn hash<T: Hash>(t: &T) -> u64 {
     s.finish()
 }
 
+#[cfg(debug_assertions)]
+mod registery {
+    use std::collections::HashMap;
+    use std::sync::{Once, RwLock, ONCE_INIT};
+
+    use utils::variant::VariantStr;
+
+    static INIT: Once = ONCE_INIT;
+    static mut REGISTERY: *const RwLock<HashMap<u64, VariantStr>> = 0 as *const _;
+
+    fn registery() -> &'static RwLock<HashMap<u64, VariantStr>> {
+        unsafe {
+            INIT.call_once(|| {
+                REGISTERY = Box::into_raw(Box::new(RwLock::new(HashMap::new())));
+            });
+
+            &*REGISTERY
+        }
+    }
+
+    pub fn register(hash: u64, v: &str) {
+        if let Some(name) = registery().read().unwrap().get(&hash) {
+            if name != v {
+                error!(
+                    "Hash collision detected between {:?} and {:?} ({:X}).",
+                    name, v, hash
+                );
+            }
+
+            return;
+        }
+
+        registery()
+            .write()
+            .unwrap()
+            .entry(hash)
+            .or_insert_with(|| v.into());
+    }
+
+    pub fn lookup(hash: u64) -> Option<String> {
+        registery()
+            .read()
+            .unwrap()
+            .get(&hash)
+            .map(|v| v.as_str().to_owned())
+    }
+}
+
+#[cfg(not(debug_assertions))]
+mod registery {
+    #[inline]
+    pub fn register(_: u64, _: &str) {}
+
+    #[inline]
+    pub fn lookup(_: u64) -> Option<String> {
+        None
+    }
+}
+
 #[cfg(test)]
 mod test {
     use super::*;
@@ -120,6 +265,28 @@ mod test {
         );
     }
 
+    #[test]
+    fn reverse_lookup() {
+        let v = HashValue::<str>::from("reverse_lookup");
+        assert_eq!(v.name(), None);
+
+        assert_eq!(hstr!("reverse_lookup"), v);
+        let w = HashValue::<str>::register(String::from("reverse_lookup_asset"));
+        assert_eq!(w, "reverse_lookup_asset");
+
+        if cfg!(debug_assertions) {
+            assert_eq!(v.name(), Some("reverse_lookup".to_owned()));
+            assert_eq!(format!("{}", v), "reverse_lookup");
+            assert_eq!(w.name(), Some("reverse_lookup_asset".to_owned()));
+        } else {
+            assert_eq!(v.name(), None);
+            assert_eq!(w.name(), None);
+        }
+
+        let u = HashValue::<str>::from(format!("reverse_lookup_{}", 2));
+        assert_eq!(u.name(), None);
+    }
+
     #[test]
     fn hash_path() {
         let h = HashValue::<Path>::from("str_path");
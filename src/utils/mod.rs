@@ -2,14 +2,25 @@
 
 #[macro_use]
 pub mod handle;
+pub mod alloc;
+pub mod checksum;
 pub mod data_buf;
 pub mod handle_pool;
+#[macro_use]
 pub mod hash_value;
+pub mod noise;
 pub mod object_pool;
+pub mod random;
+pub mod sharded_handle_pool;
+pub mod snapshot;
 pub mod variant;
 
+pub use self::checksum::Checksum;
 pub use self::data_buf::{DataBuffer, DataBufferPtr};
 pub use self::handle::{Handle, HandleIndex};
 pub use self::handle_pool::HandlePool;
 pub use self::hash_value::HashValue;
-pub use self::variant::{VariantChar, VariantStr};
+pub use self::random::Random;
+pub use self::sharded_handle_pool::ShardedHandlePool;
+pub use self::snapshot::Snapshot;
+pub use self::variant::{VariantChar, VariantStr, VariantStrBuilder};
@@ -0,0 +1,486 @@
+//! Seedable and deterministic coherent noises, e.g. terrains, clouds and procedural
+//! materials.
+//!
+//! ```rust,ignore
+//! let noise = Noise::new(42);
+//! let fbm = Fbm::default();
+//!
+//! // Bakes a cloud texture with domain-warped fBm.
+//! let image = Image::bake((256, 256).into(), |x, y| {
+//!     let v = noise::warp2(
+//!         |x, y| fbm.sample2(|x, y| noise.simplex2(x, y), x, y),
+//!         |x, y| noise.perlin2(x, y),
+//!         x * 8.0,
+//!         y * 8.0,
+//!         1.5,
+//!     );
+//!     v * 0.5 + 0.5
+//! });
+//!
+//! let (params, data) = image.into_texture(false);
+//! let texture = video.create_texture(params, data)?;
+//! ```
+//!
+//! The perlin and simplex noises are ranging from -1 to 1 approximately, and the worley
+//! noise is the distance to nearest feature point, which is ranging from 0 to 1
+//! approximately.
+
+use math;
+
+/// The noise generator with permutation table shuffled by seed.
+#[derive(Clone)]
+pub struct Noise {
+    seed: u32,
+    perm: [u8; 512],
+}
+
+impl Noise {
+    pub fn new(seed: u32) -> Self {
+        let mut table = [0u8; 256];
+        for (i, v) in table.iter_mut().enumerate() {
+            *v = i as u8;
+        }
+
+        let mut state = seed ^ 0x2545_f491;
+        if state == 0 {
+            state = 1;
+        }
+
+        // Fisher-Yates shuffle with xorshift32.
+        for i in (1..256).rev() {
+            state ^= state << 13;
+            state ^= state >> 17;
+            state ^= state << 5;
+            table.swap(i, state as usize % (i + 1));
+        }
+
+        let mut perm = [0u8; 512];
+        for i in 0..512 {
+            perm[i] = table[i & 255];
+        }
+
+        Noise {
+            seed: seed,
+            perm: perm,
+        }
+    }
+
+    #[inline]
+    pub fn seed(&self) -> u32 {
+        self.seed
+    }
+
+    pub fn perlin1(&self, x: f32) -> f32 {
+        let xi = x.floor();
+        let x = x - xi;
+        let xi = (xi as i32 & 255) as usize;
+
+        let u = fade(x);
+        let n0 = grad1(self.perm[xi], x);
+        let n1 = grad1(self.perm[xi + 1], x - 1.0);
+        lerp(u, n0, n1) * 0.25
+    }
+
+    pub fn perlin2(&self, x: f32, y: f32) -> f32 {
+        let (xi, yi) = (x.floor(), y.floor());
+        let (x, y) = (x - xi, y - yi);
+        let (xi, yi) = ((xi as i32 & 255) as usize, (yi as i32 & 255) as usize);
+
+        let (u, v) = (fade(x), fade(y));
+        let p = &self.perm;
+        let a = p[xi] as usize + yi;
+        let b = p[xi + 1] as usize + yi;
+
+        lerp(
+            v,
+            lerp(u, grad2(p[a], x, y), grad2(p[b], x - 1.0, y)),
+            lerp(u, grad2(p[a + 1], x, y - 1.0), grad2(p[b + 1], x - 1.0, y - 1.0)),
+        )
+    }
+
+    pub fn perlin3(&self, x: f32, y: f32, z: f32) -> f32 {
+        let (xi, yi, zi) = (x.floor(), y.floor(), z.floor());
+        let (x, y, z) = (x - xi, y - yi, z - zi);
+        let xi = (xi as i32 & 255) as usize;
+        let yi = (yi as i32 & 255) as usize;
+        let zi = (zi as i32 & 255) as usize;
+
+        let (u, v, w) = (fade(x), fade(y), fade(z));
+        let p = &self.perm;
+        let a = p[xi] as usize + yi;
+        let aa = p[a] as usize + zi;
+        let ab = p[a + 1] as usize + zi;
+        let b = p[xi + 1] as usize + yi;
+        let ba = p[b] as usize + zi;
+        let bb = p[b + 1] as usize + zi;
+
+        let (x1, y1, z1) = (x - 1.0, y - 1.0, z - 1.0);
+        lerp(
+            w,
+            lerp(
+                v,
+                lerp(u, grad3(p[aa], x, y, z), grad3(p[ba], x1, y, z)),
+                lerp(u, grad3(p[ab], x, y1, z), grad3(p[bb], x1, y1, z)),
+            ),
+            lerp(
+                v,
+                lerp(u, grad3(p[aa + 1], x, y, z1), grad3(p[ba + 1], x1, y, z1)),
+                lerp(u, grad3(p[ab + 1], x, y1, z1), grad3(p[bb + 1], x1, y1, z1)),
+            ),
+        )
+    }
+
+    pub fn simplex2(&self, x: f32, y: f32) -> f32 {
+        const F2: f32 = 0.366_025_4;
+        const G2: f32 = 0.211_324_87;
+
+        let s = (x + y) * F2;
+        let (i, j) = ((x + s).floor(), (y + s).floor());
+        let t = (i + j) * G2;
+        let (x0, y0) = (x - (i - t), y - (j - t));
+
+        let (i1, j1) = if x0 > y0 { (1, 0) } else { (0, 1) };
+        let (x1, y1) = (x0 - i1 as f32 + G2, y0 - j1 as f32 + G2);
+        let (x2, y2) = (x0 - 1.0 + 2.0 * G2, y0 - 1.0 + 2.0 * G2);
+
+        let p = &self.perm;
+        let ii = (i as i32 & 255) as usize;
+        let jj = (j as i32 & 255) as usize;
+        let g0 = p[ii + p[jj] as usize];
+        let g1 = p[ii + i1 + p[jj + j1] as usize];
+        let g2 = p[ii + 1 + p[jj + 1] as usize];
+
+        let corner = |g: u8, x: f32, y: f32| {
+            let t = 0.5 - x * x - y * y;
+            if t < 0.0 {
+                0.0
+            } else {
+                t * t * t * t * grad2(g, x, y)
+            }
+        };
+
+        70.0 * (corner(g0, x0, y0) + corner(g1, x1, y1) + corner(g2, x2, y2))
+    }
+
+    pub fn simplex3(&self, x: f32, y: f32, z: f32) -> f32 {
+        const F3: f32 = 1.0 / 3.0;
+        const G3: f32 = 1.0 / 6.0;
+
+        let s = (x + y + z) * F3;
+        let (i, j, k) = ((x + s).floor(), (y + s).floor(), (z + s).floor());
+        let t = (i + j + k) * G3;
+        let (x0, y0, z0) = (x - (i - t), y - (j - t), z - (k - t));
+
+        // Determines which simplex we are in.
+        let (i1, j1, k1, i2, j2, k2) = if x0 >= y0 {
+            if y0 >= z0 {
+                (1, 0, 0, 1, 1, 0)
+            } else if x0 >= z0 {
+                (1, 0, 0, 1, 0, 1)
+            } else {
+                (0, 0, 1, 1, 0, 1)
+            }
+        } else if y0 < z0 {
+            (0, 0, 1, 0, 1, 1)
+        } else if x0 < z0 {
+            (0, 1, 0, 0, 1, 1)
+        } else {
+            (0, 1, 0, 1, 1, 0)
+        };
+
+        let x1 = x0 - i1 as f32 + G3;
+        let y1 = y0 - j1 as f32 + G3;
+        let z1 = z0 - k1 as f32 + G3;
+        let x2 = x0 - i2 as f32 + 2.0 * G3;
+        let y2 = y0 - j2 as f32 + 2.0 * G3;
+        let z2 = z0 - k2 as f32 + 2.0 * G3;
+        let x3 = x0 - 1.0 + 3.0 * G3;
+        let y3 = y0 - 1.0 + 3.0 * G3;
+        let z3 = z0 - 1.0 + 3.0 * G3;
+
+        let p = &self.perm;
+        let ii = (i as i32 & 255) as usize;
+        let jj = (j as i32 & 255) as usize;
+        let kk = (k as i32 & 255) as usize;
+        let g0 = p[ii + p[jj + p[kk] as usize] as usize];
+        let g1 = p[ii + i1 + p[jj + j1 + p[kk + k1] as usize] as usize];
+        let g2 = p[ii + i2 + p[jj + j2 + p[kk + k2] as usize] as usize];
+        let g3 = p[ii + 1 + p[jj + 1 + p[kk + 1] as usize] as usize];
+
+        let corner = |g: u8, x: f32, y: f32, z: f32| {
+            let t = 0.6 - x * x - y * y - z * z;
+            if t < 0.0 {
+                0.0
+            } else {
+                t * t * t * t * grad3(g, x, y, z)
+            }
+        };
+
+        32.0 * (corner(g0, x0, y0, z0) + corner(g1, x1, y1, z1) + corner(g2, x2, y2, z2)
+            + corner(g3, x3, y3, z3))
+    }
+
+    /// Gets the distance to nearest feature point, there is one feature point in every
+    /// unit cell.
+    pub fn worley2(&self, x: f32, y: f32) -> f32 {
+        let (xi, yi) = (x.floor() as i32, y.floor() as i32);
+        let mut nearest = ::std::f32::MAX;
+
+        for dy in -1..2 {
+            for dx in -1..2 {
+                let (cx, cy) = (xi + dx, yi + dy);
+                let h = self.hash(cx, cy, 0);
+                let px = cx as f32 + unit(h) - x;
+                let py = cy as f32 + unit(h >> 16) - y;
+                nearest = nearest.min(px * px + py * py);
+            }
+        }
+
+        nearest.sqrt()
+    }
+
+    /// Gets the distance to nearest feature point, there is one feature point in every
+    /// unit cell.
+    pub fn worley3(&self, x: f32, y: f32, z: f32) -> f32 {
+        let (xi, yi, zi) = (x.floor() as i32, y.floor() as i32, z.floor() as i32);
+        let mut nearest = ::std::f32::MAX;
+
+        for dz in -1..2 {
+            for dy in -1..2 {
+                for dx in -1..2 {
+                    let (cx, cy, cz) = (xi + dx, yi + dy, zi + dz);
+                    let h = self.hash(cx, cy, cz);
+                    let px = cx as f32 + unit(h) - x;
+                    let py = cy as f32 + unit(h >> 16) - y;
+                    let pz = cz as f32 + unit(mix(h ^ 0x9e37_79b9)) - z;
+                    nearest = nearest.min(px * px + py * py + pz * pz);
+                }
+            }
+        }
+
+        nearest.sqrt()
+    }
+
+    fn hash(&self, x: i32, y: i32, z: i32) -> u32 {
+        mix(self.seed
+            ^ (x as u32).wrapping_mul(0x8da6_b343)
+            ^ (y as u32).wrapping_mul(0xd816_3841)
+            ^ (z as u32).wrapping_mul(0xcb1a_b31f))
+    }
+}
+
+/// The fractal brownian motion, which sums octaves of noise with increasing frequencies
+/// and decreasing amplitudes.
+#[derive(Debug, Clone, Copy)]
+pub struct Fbm {
+    pub octaves: u32,
+    /// The frequency multiplier between octaves.
+    pub lacunarity: f32,
+    /// The amplitude multiplier between octaves.
+    pub gain: f32,
+}
+
+impl Default for Fbm {
+    fn default() -> Self {
+        Fbm {
+            octaves: 5,
+            lacunarity: 2.0,
+            gain: 0.5,
+        }
+    }
+}
+
+impl Fbm {
+    /// Samples the fBm of `func`, the result is normalized into the range of `func`.
+    pub fn sample2<F>(&self, func: F, x: f32, y: f32) -> f32
+    where
+        F: Fn(f32, f32) -> f32,
+    {
+        self.sum(|frequency| func(x * frequency, y * frequency))
+    }
+
+    /// Samples the fBm of `func`, the result is normalized into the range of `func`.
+    pub fn sample3<F>(&self, func: F, x: f32, y: f32, z: f32) -> f32
+    where
+        F: Fn(f32, f32, f32) -> f32,
+    {
+        self.sum(|frequency| func(x * frequency, y * frequency, z * frequency))
+    }
+
+    fn sum<F: Fn(f32) -> f32>(&self, octave: F) -> f32 {
+        let (mut frequency, mut amplitude) = (1.0, 1.0);
+        let (mut sum, mut total) = (0.0, 0.0);
+
+        for _ in 0..self.octaves {
+            sum += octave(frequency) * amplitude;
+            total += amplitude;
+            frequency *= self.lacunarity;
+            amplitude *= self.gain;
+        }
+
+        if total > 0.0 {
+            sum / total
+        } else {
+            0.0
+        }
+    }
+}
+
+/// Samples `func` at the coordinates displaced by `warp`, which makes the patterns
+/// swirly.
+pub fn warp2<F, W>(func: F, warp: W, x: f32, y: f32, strength: f32) -> f32
+where
+    F: Fn(f32, f32) -> f32,
+    W: Fn(f32, f32) -> f32,
+{
+    let dx = warp(x, y);
+    let dy = warp(x + 5.2, y + 1.3);
+    func(x + dx * strength, y + dy * strength)
+}
+
+/// Samples `func` at the coordinates displaced by `warp`, which makes the patterns
+/// swirly.
+pub fn warp3<F, W>(func: F, warp: W, x: f32, y: f32, z: f32, strength: f32) -> f32
+where
+    F: Fn(f32, f32, f32) -> f32,
+    W: Fn(f32, f32, f32) -> f32,
+{
+    let dx = warp(x, y, z);
+    let dy = warp(x + 5.2, y + 1.3, z + 2.8);
+    let dz = warp(x + 1.7, y + 9.2, z + 4.1);
+    func(x + dx * strength, y + dy * strength, z + dz * strength)
+}
+
+/// Evaluates `func` at the normalized coordinates of every pixel, and returns the
+/// grayscale `RGBA8` bytes. The results of `func` are clamped into `[0, 1]`.
+pub fn bake<F>(dimensions: math::Vector2<u32>, func: F) -> Vec<u8>
+where
+    F: Fn(f32, f32) -> f32,
+{
+    let (w, h) = (dimensions.x as usize, dimensions.y as usize);
+    let mut bytes = Vec::with_capacity(w * h * 4);
+
+    for y in 0..h {
+        for x in 0..w {
+            let u = (x as f32 + 0.5) / w as f32;
+            let v = (y as f32 + 0.5) / h as f32;
+            let c = (func(u, v).max(0.0).min(1.0) * 255.0).round() as u8;
+            bytes.extend_from_slice(&[c, c, c, 255]);
+        }
+    }
+
+    bytes
+}
+
+#[inline]
+fn fade(t: f32) -> f32 {
+    t * t * t * (t * (t * 6.0 - 15.0) + 10.0)
+}
+
+#[inline]
+fn lerp(t: f32, a: f32, b: f32) -> f32 {
+    a + t * (b - a)
+}
+
+#[inline]
+fn grad1(hash: u8, x: f32) -> f32 {
+    let g = 1.0 + (hash & 7) as f32;
+    if hash & 8 == 0 {
+        g * x
+    } else {
+        -g * x
+    }
+}
+
+#[inline]
+fn grad2(hash: u8, x: f32, y: f32) -> f32 {
+    match hash & 7 {
+        0 => x + y,
+        1 => -x + y,
+        2 => x - y,
+        3 => -x - y,
+        4 => x,
+        5 => -x,
+        6 => y,
+        _ => -y,
+    }
+}
+
+#[inline]
+fn grad3(hash: u8, x: f32, y: f32, z: f32) -> f32 {
+    let h = hash & 15;
+    let u = if h < 8 { x } else { y };
+    let v = if h < 4 {
+        y
+    } else if h == 12 || h == 14 {
+        x
+    } else {
+        z
+    };
+
+    (if h & 1 == 0 { u } else { -u }) + (if h & 2 == 0 { v } else { -v })
+}
+
+#[inline]
+fn mix(mut h: u32) -> u32 {
+    h ^= h >> 16;
+    h = h.wrapping_mul(0x7feb_352d);
+    h ^= h >> 15;
+    h = h.wrapping_mul(0x846c_a68b);
+    h ^= h >> 16;
+    h
+}
+
+#[inline]
+fn unit(h: u32) -> f32 {
+    (h & 0xffff) as f32 / 65_535.0
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn deterministic() {
+        let (lhs, rhs) = (Noise::new(7), Noise::new(7));
+        let other = Noise::new(8);
+
+        let mut different = false;
+        for i in 0..64 {
+            let (x, y, z) = (i as f32 * 0.37, i as f32 * 0.71, i as f32 * 0.13);
+            assert_eq!(lhs.perlin3(x, y, z), rhs.perlin3(x, y, z));
+            assert_eq!(lhs.simplex2(x, y), rhs.simplex2(x, y));
+            assert_eq!(lhs.worley3(x, y, z), rhs.worley3(x, y, z));
+            different |= lhs.perlin2(x, y) != other.perlin2(x, y);
+        }
+
+        assert!(different);
+    }
+
+    #[test]
+    fn ranges() {
+        let noise = Noise::new(0);
+        let fbm = Fbm::default();
+
+        for i in 0..256 {
+            let (x, y, z) = (i as f32 * 0.173, i as f32 * 0.311, i as f32 * 0.059);
+            for &v in &[
+                noise.perlin1(x),
+                noise.perlin2(x, y),
+                noise.perlin3(x, y, z),
+                noise.simplex2(x, y),
+                noise.simplex3(x, y, z),
+                fbm.sample2(|x, y| noise.perlin2(x, y), x, y),
+            ] {
+                assert!(v >= -1.1 && v <= 1.1);
+            }
+
+            let v = noise.worley2(x, y);
+            assert!(v >= 0.0 && v <= 1.5);
+        }
+
+        // The perlin noise is zero at integer lattice.
+        assert_eq!(noise.perlin3(3.0, 4.0, 5.0), 0.0);
+        assert_eq!(bake((2, 2).into(), |_, _| 2.0), vec![255; 16]);
+    }
+}
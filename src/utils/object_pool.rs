@@ -1,33 +1,131 @@
 use std::borrow::Borrow;
+use std::fmt;
+use std::hash::{Hash, Hasher};
+use std::marker::PhantomData;
+use std::sync::atomic::{AtomicUsize, Ordering, ATOMIC_USIZE_INIT};
 
 use super::handle::Handle;
 use super::handle_pool::{HandlePool, Iter};
 
+static POOL_IDENTIFIER: AtomicUsize = ATOMIC_USIZE_INIT;
+
+/// A weak reference to the object in `ObjectPool`, which is created with
+/// `ObjectPool::downgrade`.
+///
+/// Its safe to keep weak references across systems (e.g. targets, parents), it always
+/// returns `None` when upgrading after the object is freed, even if the slot has been
+/// reused. Besides, upgrading a weak reference with other pools also returns `None`.
+pub struct WeakHandle<T> {
+    pool: usize,
+    handle: Handle,
+    _phantom: PhantomData<fn() -> T>,
+}
+
+impl<T> Clone for WeakHandle<T> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<T> Copy for WeakHandle<T> {}
+
+impl<T> PartialEq for WeakHandle<T> {
+    fn eq(&self, rhs: &Self) -> bool {
+        self.pool == rhs.pool && self.handle == rhs.handle
+    }
+}
+
+impl<T> Eq for WeakHandle<T> {}
+
+impl<T> Hash for WeakHandle<T> {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.pool.hash(state);
+        self.handle.hash(state);
+    }
+}
+
+impl<T> fmt::Debug for WeakHandle<T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "WeakHandle({}, {:?})", self.pool, self.handle)
+    }
+}
+
 /// A named object collections. Every time u create or free a handle, a
 /// attached instance `T` will be created/ freed.
-#[derive(Default)]
+///
+/// The clones of pool share the same identity, so the weak references are valid in
+/// both of them.
+#[derive(Clone)]
 pub struct ObjectPool<T: Sized> {
+    identifier: usize,
     handles: HandlePool,
     entries: Vec<Option<T>>,
 }
 
+impl<T: Sized> Default for ObjectPool<T> {
+    fn default() -> Self {
+        ObjectPool::new()
+    }
+}
+
 impl<T: Sized> ObjectPool<T> {
     /// Constructs a new, empty `ObjectPool`.
     pub fn new() -> Self {
-        ObjectPool {
-            handles: HandlePool::new(),
-            entries: Vec::new(),
-        }
+        ObjectPool::with_capacity(0)
     }
 
     /// Constructs a new `ObjectPool` with the specified capacity.
     pub fn with_capacity(capacity: usize) -> Self {
         ObjectPool {
+            identifier: POOL_IDENTIFIER.fetch_add(1, Ordering::Relaxed) + 1,
             handles: HandlePool::with_capacity(capacity),
             entries: Vec::with_capacity(capacity),
         }
     }
 
+    /// Creates a weak reference to the object with name `Handle`. Returns `None` if the
+    /// handle is not alive.
+    pub fn downgrade<H>(&self, handle: H) -> Option<WeakHandle<T>>
+    where
+        H: Borrow<Handle>,
+    {
+        let handle = *handle.borrow();
+        if self.handles.is_alive(handle) {
+            Some(WeakHandle {
+                pool: self.identifier,
+                handle: handle,
+                _phantom: PhantomData,
+            })
+        } else {
+            None
+        }
+    }
+
+    /// Upgrades the weak reference to `Handle` if the object is still alive.
+    #[inline]
+    pub fn upgrade(&self, weak: WeakHandle<T>) -> Option<Handle> {
+        if weak.pool == self.identifier && self.handles.is_alive(weak.handle) {
+            Some(weak.handle)
+        } else {
+            None
+        }
+    }
+
+    /// Returns immutable reference to the object of weak reference if its still alive.
+    #[inline]
+    pub fn get_weak(&self, weak: WeakHandle<T>) -> Option<&T> {
+        self.upgrade(weak).and_then(move |v| self.get(v))
+    }
+
+    /// Returns mutable reference to the object of weak reference if its still alive.
+    #[inline]
+    pub fn get_weak_mut(&mut self, weak: WeakHandle<T>) -> Option<&mut T> {
+        match self.upgrade(weak) {
+            Some(v) => self.get_mut(v),
+            None => None,
+        }
+    }
+
     /// Creates a `T` and named it with `Handle`.
     pub fn create(&mut self, value: T) -> Handle {
         let handle = self.handles.create();
@@ -183,4 +281,28 @@ mod test {
         assert_eq!(set.free(e1), None);
         assert_eq!(set.len(), 0);
     }
+
+    #[test]
+    fn weak() {
+        let mut set = ObjectPool::<i32>::new();
+        let mut other = ObjectPool::<i32>::new();
+
+        let e1 = set.create(3);
+        let w1 = set.downgrade(e1).unwrap();
+        assert_eq!(set.upgrade(w1), Some(e1));
+        assert_eq!(set.get_weak(w1), Some(&3));
+        *set.get_weak_mut(w1).unwrap() = 4;
+        assert_eq!(set.get(e1), Some(&4));
+
+        let o1 = other.create(5);
+        assert_eq!(o1, e1);
+        assert_eq!(other.upgrade(w1), None);
+
+        set.free(e1);
+        let e2 = set.create(6);
+        assert_eq!(e1.index(), e2.index());
+        assert_eq!(set.upgrade(w1), None);
+        assert_eq!(set.get_weak(w1), None);
+        assert!(set.downgrade(e1).is_none());
+    }
 }
@@ -0,0 +1,134 @@
+//! Seedable pseudorandom numbers which are reproducible across platforms and runs.
+//!
+//! Every system that needs randomness in a deterministic simulation should take its
+//! own stream derived from the global seed, so adding random calls in one system does
+//! not shift the sequences of the others:
+//!
+//! ```rust,ignore
+//! let mut rng = ctx.random("spawner");
+//! let position = math::Vector3::new(rng.range(-1.0, 1.0), 0.0, rng.range(-1.0, 1.0));
+//! ```
+
+use std::hash::Hasher;
+
+use super::checksum::Checksum;
+
+/// A [xorshift*] generator with 64-bit state.
+///
+/// [xorshift*]: https://en.wikipedia.org/wiki/Xorshift#xorshift*
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Random {
+    state: u64,
+}
+
+impl Random {
+    /// Creates a generator from seed. Any seed will do, including zero.
+    pub fn new(seed: u64) -> Self {
+        Random {
+            state: mix(seed) | 1,
+        }
+    }
+
+    /// Creates a generator of the named stream, which is independent from the other
+    /// streams with the same seed.
+    pub fn from_stream(seed: u64, name: &str) -> Self {
+        let mut hasher = Checksum::new();
+        hasher.write(name.as_bytes());
+        Random::new(seed ^ hasher.finish())
+    }
+
+    /// Gets the internal state, which could be saved and restored with `from_state`.
+    #[inline]
+    pub fn state(&self) -> u64 {
+        self.state
+    }
+
+    /// Restores a generator from a saved state.
+    #[inline]
+    pub fn from_state(state: u64) -> Self {
+        Random { state: state | 1 }
+    }
+
+    pub fn next_u64(&mut self) -> u64 {
+        let mut x = self.state;
+        x ^= x >> 12;
+        x ^= x << 25;
+        x ^= x >> 27;
+        self.state = x;
+        x.wrapping_mul(0x2545_f491_4f6c_dd1d)
+    }
+
+    #[inline]
+    pub fn next_u32(&mut self) -> u32 {
+        (self.next_u64() >> 32) as u32
+    }
+
+    /// Returns a number ranging from 0 to 1, exclusive.
+    #[inline]
+    pub fn next_f32(&mut self) -> f32 {
+        // The mantissa of f32 has 24 bits.
+        (self.next_u64() >> 40) as f32 / (1u64 << 24) as f32
+    }
+
+    /// Returns a number ranging from `min` to `max`, exclusive.
+    #[inline]
+    pub fn range(&mut self, min: f32, max: f32) -> f32 {
+        min + (max - min) * self.next_f32()
+    }
+
+    /// Returns an integer ranging from 0 to `n`, exclusive.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `n` is zero.
+    #[inline]
+    pub fn below(&mut self, n: u32) -> u32 {
+        assert!(n > 0);
+        ((u64::from(self.next_u32()) * u64::from(n)) >> 32) as u32
+    }
+}
+
+#[inline]
+fn mix(mut h: u64) -> u64 {
+    // The finalizer of splitmix64.
+    h = h.wrapping_add(0x9e37_79b9_7f4a_7c15);
+    h = (h ^ (h >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+    h = (h ^ (h >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+    h ^ (h >> 31)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn deterministic() {
+        let (mut lhs, mut rhs) = (Random::new(7), Random::new(7));
+        for _ in 0..64 {
+            assert_eq!(lhs.next_u64(), rhs.next_u64());
+        }
+
+        let mut restored = Random::from_state(lhs.state());
+        assert_eq!(restored.next_u32(), lhs.next_u32());
+
+        let mut zero = Random::new(0);
+        assert_ne!(zero.next_u64(), zero.next_u64());
+
+        let (mut a, mut b) = (Random::from_stream(7, "a"), Random::from_stream(7, "b"));
+        assert_ne!(a.next_u64(), b.next_u64());
+    }
+
+    #[test]
+    fn ranges() {
+        let mut rng = Random::new(42);
+        for _ in 0..1024 {
+            let v = rng.next_f32();
+            assert!(v >= 0.0 && v < 1.0);
+
+            let v = rng.range(-2.0, 3.0);
+            assert!(v >= -2.0 && v < 3.0);
+
+            assert!(rng.below(5) < 5);
+        }
+    }
+}
@@ -0,0 +1,273 @@
+//! A thread-safe `HandlePool` for high-churn objects which are created and freed from
+//! many workers every frame, e.g. transient drawcalls and particles.
+//!
+//! The indices are split into shards, every thread allocates from its own shard so
+//! the threads rarely contend on the same lock. A shard owns the indices which equal
+//! to its position modulo the number of shards.
+//!
+//! The freed indices are double-buffered. They are not reused until `reconcile` is
+//! called, which is usually done once per frame at a sync point. So the handles that
+//! are freed in current frame never alias the new ones, even if some systems are still
+//! holding them until the end of frame.
+//!
+//! ```rust,ignore
+//! let pool = Arc::new(ShardedHandlePool::new(ctx.sched.workers() as usize));
+//!
+//! ctx.sched.scope(|s| {
+//!     for chunk in emitters.chunks_mut(64) {
+//!         let pool = &pool;
+//!         s.spawn(move |_| {
+//!             for v in chunk {
+//!                 v.particles.push(pool.create());
+//!             }
+//!         });
+//!     }
+//! });
+//!
+//! // At the end of frame.
+//! pool.reconcile();
+//! ```
+
+use std::borrow::Borrow;
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicUsize, Ordering, ATOMIC_USIZE_INIT};
+use std::sync::Mutex;
+
+use super::{Handle, HandleIndex};
+
+#[derive(Default)]
+struct Shard {
+    versions: Vec<HandleIndex>,
+    frees: Vec<HandleIndex>,
+    // The indices freed since last reconciliation.
+    pending: Vec<HandleIndex>,
+}
+
+// The ids of pools, which are never reused.
+static POOLS: AtomicUsize = ATOMIC_USIZE_INIT;
+
+thread_local! {
+    // The shards assigned to current thread, keyed by the ids of pools.
+    static SHARDS: RefCell<HashMap<usize, usize>> = RefCell::new(HashMap::new());
+}
+
+/// The thread-safe `HandlePool` with per-thread shards.
+pub struct ShardedHandlePool {
+    id: usize,
+    shards: Vec<Mutex<Shard>>,
+    // The number of threads that have been assigned a shard.
+    threads: AtomicUsize,
+    len: AtomicUsize,
+}
+
+impl ShardedHandlePool {
+    /// Constructs a new, empty `ShardedHandlePool` with `shards` shards, which is usually
+    /// the number of threads that allocate handles.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `shards` is zero.
+    pub fn new(shards: usize) -> Self {
+        assert!(shards > 0, "ShardedHandlePool requires at least one shard.");
+
+        ShardedHandlePool {
+            id: POOLS.fetch_add(1, Ordering::Relaxed),
+            shards: (0..shards).map(|_| Mutex::new(Shard::default())).collect(),
+            threads: AtomicUsize::new(0),
+            len: AtomicUsize::new(0),
+        }
+    }
+
+    /// Gets the number of shards.
+    #[inline]
+    pub fn shards(&self) -> usize {
+        self.shards.len()
+    }
+
+    /// Creates a unused `Handle` from the shard of current thread.
+    pub fn create(&self) -> Handle {
+        let n = self.shards.len();
+        let i = self.thread_shard();
+        let mut shard = self.shards[i].lock().unwrap();
+
+        let local = if let Some(local) = shard.frees.pop() {
+            shard.versions[local as usize] += 1;
+            local
+        } else {
+            shard.versions.push(1);
+            shard.versions.len() as HandleIndex - 1
+        };
+
+        self.len.fetch_add(1, Ordering::Relaxed);
+
+        let version = shard.versions[local as usize];
+        Handle::new(local * n as HandleIndex + i as HandleIndex, version)
+    }
+
+    /// Returns true if the `Handle` is alive.
+    pub fn is_alive<T>(&self, handle: T) -> bool
+    where
+        T: Borrow<Handle>,
+    {
+        let handle = handle.borrow();
+        let (i, local) = self.locate(handle.index());
+        let shard = self.shards[i].lock().unwrap();
+
+        shard
+            .versions
+            .get(local)
+            .map(|&v| v == handle.version() && (v & 0x1) == 1)
+            .unwrap_or(false)
+    }
+
+    /// Frees the `Handle`, and returns true if it was alive. The index would not be
+    /// reused until next `reconcile`.
+    pub fn free<T>(&self, handle: T) -> bool
+    where
+        T: Borrow<Handle>,
+    {
+        let handle = handle.borrow();
+        let (i, local) = self.locate(handle.index());
+        let mut shard = self.shards[i].lock().unwrap();
+
+        let alive = shard
+            .versions
+            .get(local)
+            .map(|&v| v == handle.version() && (v & 0x1) == 1)
+            .unwrap_or(false);
+
+        if alive {
+            shard.versions[local] += 1;
+            shard.pending.push(local as HandleIndex);
+            self.len.fetch_sub(1, Ordering::Relaxed);
+        }
+
+        alive
+    }
+
+    /// Makes the indices freed since last call available for reuse.
+    pub fn reconcile(&self) {
+        for v in &self.shards {
+            let mut shard = v.lock().unwrap();
+            let shard = &mut *shard;
+            shard.frees.extend(shard.pending.drain(..));
+        }
+    }
+
+    /// Returns the number of alive handles.
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.len.load(Ordering::Relaxed)
+    }
+
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    // Assigns the shards to threads in the order they first allocate from this pool,
+    // so the first `shards` threads never share a shard no matter how many threads
+    // have used the other pools.
+    fn thread_shard(&self) -> usize {
+        SHARDS.with(|v| {
+            *v.borrow_mut()
+                .entry(self.id)
+                .or_insert_with(|| self.threads.fetch_add(1, Ordering::Relaxed) % self.shards.len())
+        })
+    }
+
+    #[inline]
+    fn locate(&self, index: HandleIndex) -> (usize, usize) {
+        let n = self.shards.len();
+        (index as usize % n, index as usize / n)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::collections::HashSet;
+    use std::sync::Arc;
+    use std::thread;
+
+    #[test]
+    fn basic() {
+        let pool = ShardedHandlePool::new(3);
+        let e1 = pool.create();
+        assert!(pool.is_alive(e1));
+        assert_eq!(pool.len(), 1);
+
+        assert!(pool.free(e1));
+        assert!(!pool.free(e1));
+        assert!(!pool.is_alive(e1));
+        assert!(pool.is_empty());
+
+        // The freed index is not reused until reconciliation.
+        let e2 = pool.create();
+        assert!(e2.index() != e1.index());
+
+        pool.reconcile();
+        let e3 = pool.create();
+        assert_eq!(e3.index(), e1.index());
+        assert!(e3.version() != e1.version());
+        assert!(!pool.is_alive(e1) && pool.is_alive(e3));
+    }
+
+    #[test]
+    fn threads() {
+        let pool = Arc::new(ShardedHandlePool::new(4));
+
+        let jobs: Vec<_> = (0..4)
+            .map(|_| {
+                let pool = pool.clone();
+                thread::spawn(move || {
+                    let handles: Vec<_> = (0..100).map(|_| pool.create()).collect();
+                    for v in handles.chunks(2) {
+                        assert!(pool.free(v[0]));
+                    }
+
+                    handles
+                })
+            })
+            .collect();
+
+        let mut set = HashSet::new();
+        for v in jobs {
+            for handle in v.join().unwrap() {
+                assert!(set.insert(handle));
+            }
+        }
+
+        assert_eq!(pool.len(), 200);
+        assert_eq!(set.iter().filter(|v| pool.is_alive(**v)).count(), 200);
+    }
+
+    #[test]
+    fn shards() {
+        // Spreads the threads of a pool across its shards, even if they have already
+        // allocated from other pools.
+        let other = Arc::new(ShardedHandlePool::new(3));
+        let pool = Arc::new(ShardedHandlePool::new(4));
+
+        let jobs: Vec<_> = (0..4)
+            .map(|i| {
+                let (other, pool) = (other.clone(), pool.clone());
+                thread::spawn(move || {
+                    for _ in 0..i {
+                        other.create();
+                    }
+
+                    pool.create()
+                })
+            })
+            .collect();
+
+        let shards: HashSet<_> = jobs
+            .into_iter()
+            .map(|v| v.join().unwrap().index() % 4)
+            .collect();
+
+        assert_eq!(shards.len(), 4);
+    }
+}
@@ -0,0 +1,229 @@
+//! Immutable snapshots which are swapped atomically, so the readers never block.
+//!
+//! `Snapshot<T>` is a shared slot of `Arc<T>`. The readers get the current value with
+//! `Snapshot::load`, which never takes locks and is never blocked by writers. The
+//! writers build a new value (usually with `Snapshot::update`, which clones the current
+//! one and applies the changes) and publish it atomically, the readers which are holding
+//! the previous snapshot are not affected.
+//!
+//! The writers are serialized with each other. A replaced snapshot is released after
+//! all the readers which might be observing it have left their (very short) critical
+//! sections, which are tracked by a two-phases epoch counter.
+//!
+//! ```rust,ignore
+//! let prefabs = Snapshot::new(HashMap::new());
+//!
+//! // Render thread, lock free.
+//! let prefab = prefabs.load().get(&handle).cloned();
+//!
+//! // Loader threads.
+//! prefabs.update(|v| v.insert(handle, Arc::new(prefab)));
+//! ```
+
+use std::fmt;
+use std::mem;
+use std::sync::atomic::{AtomicPtr, AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread;
+
+/// The atomically swappable `Arc<T>`.
+pub struct Snapshot<T> {
+    ptr: AtomicPtr<T>,
+    epoch: AtomicUsize,
+    // The number of readers in critical sections, indexed by the parity of epoch.
+    readers: [AtomicUsize; 2],
+    writer: Mutex<()>,
+}
+
+unsafe impl<T: Send + Sync> Send for Snapshot<T> {}
+unsafe impl<T: Send + Sync> Sync for Snapshot<T> {}
+
+impl<T> Snapshot<T> {
+    pub fn new(value: T) -> Self {
+        Snapshot::from_arc(Arc::new(value))
+    }
+
+    pub fn from_arc(value: Arc<T>) -> Self {
+        Snapshot {
+            ptr: AtomicPtr::new(Arc::into_raw(value) as *mut T),
+            epoch: AtomicUsize::new(0),
+            readers: [AtomicUsize::new(0), AtomicUsize::new(0)],
+            writer: Mutex::new(()),
+        }
+    }
+
+    /// Gets the current snapshot.
+    pub fn load(&self) -> Arc<T> {
+        let parity = self.enter();
+
+        // The snapshot could not be released until we leave, so its safe to increase
+        // the reference count.
+        let ptr = self.ptr.load(Ordering::SeqCst);
+        let current = unsafe { Arc::from_raw(ptr) };
+        let snapshot = current.clone();
+        mem::forget(current);
+
+        self.readers[parity].fetch_sub(1, Ordering::SeqCst);
+        snapshot
+    }
+
+    /// Publishes the value, returns the previous snapshot.
+    pub fn swap(&self, value: Arc<T>) -> Arc<T> {
+        let _guard = self.writer.lock().unwrap();
+        self.publish(value)
+    }
+
+    #[inline]
+    pub fn store(&self, value: T) {
+        self.swap(Arc::new(value));
+    }
+
+    /// Applies the changes on a copy of current value and publishes it. The updates from
+    /// multiple threads are applied in order, none of them will be lost.
+    pub fn update<F, R>(&self, func: F) -> R
+    where
+        T: Clone,
+        F: FnOnce(&mut T) -> R,
+    {
+        let _guard = self.writer.lock().unwrap();
+
+        // The current snapshot is only replaced by writers.
+        let mut value = unsafe { (*self.ptr.load(Ordering::SeqCst)).clone() };
+        let result = func(&mut value);
+        self.publish(Arc::new(value));
+        result
+    }
+
+    // Registers the reader in current epoch, returns its parity.
+    fn enter(&self) -> usize {
+        loop {
+            let epoch = self.epoch.load(Ordering::SeqCst);
+            let parity = epoch & 1;
+            self.readers[parity].fetch_add(1, Ordering::SeqCst);
+
+            // The epoch has been advanced, we might be unnoticed by the writer.
+            if self.epoch.load(Ordering::SeqCst) == epoch {
+                return parity;
+            }
+
+            self.readers[parity].fetch_sub(1, Ordering::SeqCst);
+        }
+    }
+
+    // Replaces the current snapshot, this must be called with the writer lock.
+    fn publish(&self, value: Arc<T>) -> Arc<T> {
+        let prev = self.ptr.swap(Arc::into_raw(value) as *mut T, Ordering::SeqCst);
+
+        // The readers entered after this point always observe the new snapshot, so we
+        // only have to wait for the ones in previous epoch.
+        let epoch = self.epoch.fetch_add(1, Ordering::SeqCst);
+        while self.readers[epoch & 1].load(Ordering::SeqCst) != 0 {
+            thread::yield_now();
+        }
+
+        unsafe { Arc::from_raw(prev) }
+    }
+}
+
+impl<T> Drop for Snapshot<T> {
+    fn drop(&mut self) {
+        unsafe {
+            drop(Arc::from_raw(*self.ptr.get_mut()));
+        }
+    }
+}
+
+impl<T: Default> Default for Snapshot<T> {
+    fn default() -> Self {
+        Snapshot::new(T::default())
+    }
+}
+
+impl<T: fmt::Debug> fmt::Debug for Snapshot<T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Snapshot({:?})", self.load())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::sync::atomic::AtomicBool;
+
+    struct Counted(Arc<AtomicUsize>, usize);
+
+    impl Clone for Counted {
+        fn clone(&self) -> Self {
+            self.0.fetch_add(1, Ordering::SeqCst);
+            Counted(self.0.clone(), self.1)
+        }
+    }
+
+    impl Drop for Counted {
+        fn drop(&mut self) {
+            self.0.fetch_sub(1, Ordering::SeqCst);
+        }
+    }
+
+    #[test]
+    fn basic() {
+        let v = Snapshot::new(vec![1, 2]);
+        let prev = v.load();
+        v.update(|v| v.push(3));
+        assert_eq!(*prev, [1, 2]);
+        assert_eq!(*v.load(), [1, 2, 3]);
+
+        let prev = v.swap(Arc::new(vec![4]));
+        assert_eq!(*prev, [1, 2, 3]);
+        assert_eq!(*v.load(), [4]);
+    }
+
+    #[test]
+    fn concurrent() {
+        let alive = Arc::new(AtomicUsize::new(1));
+        let v = Arc::new(Snapshot::new(Counted(alive.clone(), 0)));
+        let stop = Arc::new(AtomicBool::new(false));
+
+        let readers: Vec<_> = (0..4)
+            .map(|_| {
+                let v = v.clone();
+                let stop = stop.clone();
+                thread::spawn(move || {
+                    let mut last = 0;
+                    while !stop.load(Ordering::SeqCst) {
+                        // The published values are monotonic.
+                        let current = v.load().1;
+                        assert!(current >= last);
+                        last = current;
+                    }
+                })
+            })
+            .collect();
+
+        let writers: Vec<_> = (0..2)
+            .map(|_| {
+                let v = v.clone();
+                thread::spawn(move || {
+                    for _ in 0..1000 {
+                        v.update(|v| v.1 += 1);
+                    }
+                })
+            })
+            .collect();
+
+        for v in writers {
+            v.join().unwrap();
+        }
+
+        stop.store(true, Ordering::SeqCst);
+        for v in readers {
+            v.join().unwrap();
+        }
+
+        assert_eq!(v.load().1, 2000);
+        assert_eq!(alive.load(Ordering::SeqCst), 1);
+
+        drop(v);
+        assert_eq!(alive.load(Ordering::SeqCst), 0);
+    }
+}
@@ -1,9 +1,12 @@
 use std::borrow::Borrow;
+use std::cmp::Ordering;
+use std::fmt;
+use std::hash::{Hash, Hasher};
 use std::ops::Deref;
 use std::ptr;
 use std::str;
 
-#[derive(Debug, PartialEq, Eq)]
+#[derive(Debug, Clone)]
 enum _VariantStr {
     Size8(u8, [u8; 6]),
     Size16(u8, [u8; 14]),
@@ -13,7 +16,10 @@ enum _VariantStr {
 
 /// UTF-8 encoded owned str with varient length. It will store short string in place
 /// instead of another heap space.
-#[derive(Debug, PartialEq, Eq)]
+///
+/// All the methods of `str` (e.g. `chars`, `char_indices`) are available through `Deref`,
+/// and it could be built with `write!` since `fmt::Write` is implemented.
+#[derive(Debug, Clone)]
 pub struct VariantStr(_VariantStr);
 
 impl<T> From<T> for VariantStr
@@ -57,6 +63,66 @@ impl Default for VariantStr {
 }
 
 impl VariantStr {
+    /// Creates an empty `VariantStr` which could hold `capacity` bytes without
+    /// reallocation.
+    pub fn with_capacity(capacity: usize) -> Self {
+        if capacity <= 6 {
+            VariantStr(_VariantStr::Size8(0, [0; 6]))
+        } else if capacity <= 14 {
+            VariantStr(_VariantStr::Size16(0, [0; 14]))
+        } else if capacity <= 30 {
+            VariantStr(_VariantStr::Size32(0, [0; 30]))
+        } else {
+            VariantStr(_VariantStr::Unconstraint(String::with_capacity(capacity)))
+        }
+    }
+
+    /// Gets the number of bytes this `VariantStr` could hold without reallocation.
+    pub fn capacity(&self) -> usize {
+        match self.0 {
+            _VariantStr::Size8(_, ref v) => v.len(),
+            _VariantStr::Size16(_, ref v) => v.len(),
+            _VariantStr::Size32(_, ref v) => v.len(),
+            _VariantStr::Unconstraint(ref v) => v.capacity(),
+        }
+    }
+
+    /// Appends a given string slice onto the end of this `VariantStr`.
+    pub fn push_str(&mut self, v: &str) {
+        let len = self.len();
+        let new_len = len + v.len();
+
+        if let _VariantStr::Unconstraint(ref mut dst) = self.0 {
+            dst.push_str(v);
+            return;
+        }
+
+        if new_len > self.capacity() {
+            let mut dst = VariantStr::with_capacity(new_len);
+            dst.push_str(self.as_str());
+            dst.push_str(v);
+            *self = dst;
+            return;
+        }
+
+        let (dst_len, dst) = match self.0 {
+            _VariantStr::Size8(ref mut len, ref mut dst) => (len, &mut dst[..]),
+            _VariantStr::Size16(ref mut len, ref mut dst) => (len, &mut dst[..]),
+            _VariantStr::Size32(ref mut len, ref mut dst) => (len, &mut dst[..]),
+            _VariantStr::Unconstraint(_) => unreachable!(),
+        };
+
+        dst[len..new_len].copy_from_slice(v.as_bytes());
+        *dst_len = new_len as u8;
+    }
+
+    /// Appends the given char to the end of this `VariantStr`.
+    #[inline]
+    pub fn push(&mut self, c: char) {
+        let v = VariantChar::from(c);
+        self.push_str(unsafe { str::from_utf8_unchecked(v.as_slice()) });
+    }
+
     /// Gets the len of str.
     pub fn len(&self) -> usize {
         match self.0 {
@@ -96,6 +162,136 @@ impl VariantStr {
     }
 }
 
+impl AsRef<str> for VariantStr {
+    fn as_ref(&self) -> &str {
+        self.as_str()
+    }
+}
+
+impl From<VariantStr> for String {
+    fn from(v: VariantStr) -> Self {
+        match v.0 {
+            _VariantStr::Unconstraint(v) => v,
+            _ => String::from(v.as_str()),
+        }
+    }
+}
+
+impl fmt::Display for VariantStr {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        fmt::Display::fmt(self.as_str(), f)
+    }
+}
+
+impl fmt::Write for VariantStr {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        self.push_str(s);
+        Ok(())
+    }
+}
+
+impl Hash for VariantStr {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.as_str().hash(state);
+    }
+}
+
+impl PartialEq for VariantStr {
+    fn eq(&self, rhs: &Self) -> bool {
+        self.as_str() == rhs.as_str()
+    }
+}
+
+impl Eq for VariantStr {}
+
+impl PartialEq<str> for VariantStr {
+    fn eq(&self, rhs: &str) -> bool {
+        self.as_str() == rhs
+    }
+}
+
+impl<'a> PartialEq<&'a str> for VariantStr {
+    fn eq(&self, rhs: &&'a str) -> bool {
+        self.as_str() == *rhs
+    }
+}
+
+impl PartialEq<String> for VariantStr {
+    fn eq(&self, rhs: &String) -> bool {
+        self.as_str() == rhs.as_str()
+    }
+}
+
+impl PartialOrd for VariantStr {
+    fn partial_cmp(&self, rhs: &Self) -> Option<Ordering> {
+        Some(self.cmp(rhs))
+    }
+}
+
+impl Ord for VariantStr {
+    fn cmp(&self, rhs: &Self) -> Ordering {
+        self.as_str().cmp(rhs.as_str())
+    }
+}
+
+/// A builder which writes formatted strings into `VariantStr` within a capacity budget.
+/// Anything exceeds the budget will be truncated at char boundary.
+///
+/// ```rust,ignore
+/// use std::fmt::Write;
+///
+/// let mut builder = VariantStrBuilder::new(14);
+/// write!(builder, "Mesh#{}", 32).unwrap();
+/// let name = builder.build();
+/// ```
+pub struct VariantStrBuilder {
+    buf: VariantStr,
+    budget: usize,
+    truncated: bool,
+}
+
+impl VariantStrBuilder {
+    /// Creates a new builder which writes `budget` bytes at most.
+    pub fn new(budget: usize) -> Self {
+        VariantStrBuilder {
+            buf: VariantStr::with_capacity(budget),
+            budget: budget,
+            truncated: false,
+        }
+    }
+
+    /// Returns true if some contents have been truncated.
+    #[inline]
+    pub fn is_truncated(&self) -> bool {
+        self.truncated
+    }
+
+    /// Consumes the builder and returns the `VariantStr`.
+    #[inline]
+    pub fn build(self) -> VariantStr {
+        self.buf
+    }
+}
+
+impl fmt::Write for VariantStrBuilder {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        let remains = self.budget - self.buf.len();
+        if s.len() <= remains {
+            self.buf.push_str(s);
+        } else {
+            let mut end = remains;
+            while !s.is_char_boundary(end) {
+                end -= 1;
+            }
+
+            self.buf.push_str(&s[0..end]);
+            self.truncated = true;
+        }
+
+        Ok(())
+    }
+}
+
 const TAG_CONT: u8 = 0b1000_0000;
 const TAG_TWO_B: u8 = 0b1100_0000;
 const TAG_THREE_B: u8 = 0b1110_0000;
@@ -166,6 +362,42 @@ mod test {
         assert_eq!(v.as_bytes(), &bytes[0..9]);
     }
 
+    #[test]
+    fn push() {
+        let mut v = VariantStr::default();
+        v.push_str("12345");
+        assert_eq!(v.capacity(), 6);
+
+        v.push('β');
+        assert_eq!(v, "12345β");
+        assert_eq!(v.capacity(), 14);
+        assert_eq!(v.chars().count(), 6);
+
+        v.push_str("0123456789012345678901234");
+        assert_eq!(v.len(), 32);
+        assert_eq!(String::from(v.clone()), v.as_str());
+        assert!(v > VariantStr::from("1"));
+    }
+
+    #[test]
+    fn format() {
+        use std::fmt::Write;
+
+        let mut v = VariantStr::default();
+        write!(v, "{}-{}", "mesh", 3).unwrap();
+        assert_eq!(v, "mesh-3");
+        assert_eq!(format!("{}", v), "mesh-3");
+
+        let mut builder = VariantStrBuilder::new(7);
+        write!(builder, "mesh-{}", "β").unwrap();
+        assert!(!builder.is_truncated());
+
+        let mut builder = VariantStrBuilder::new(6);
+        write!(builder, "mesh-{}", "ββ").unwrap();
+        assert!(builder.is_truncated());
+        assert_eq!(builder.build(), "mesh-");
+    }
+
     #[test]
     fn layout() {
         assert_eq!(::std::mem::size_of::<VariantStr>(), 32);
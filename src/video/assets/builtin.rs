@@ -0,0 +1,265 @@
+//! The built-in shaders which are shipped with engine, so prototypes could draw the
+//! basic things without carrying their own shader sources.
+//!
+//! All the built-in shaders follow the same naming conventions of uniforms:
+//!
+//! * `u_MVPMatrix`, `u_ModelViewMatrix` and `u_ViewNormalMatrix` transform vertices
+//! and normals into clip and view space.
+//! * `u_Color` is the base color of material, which multiplies the texture if any.
+//! * `u_LitViewDir` and `u_LitColor` describe a directional light in view space.
+
+use std::str::FromStr;
+
+use super::shader::*;
+
+/// The shaders provided by engine.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum BuiltinShader {
+    /// Draws meshes with solid `u_Color`.
+    UnlitColor,
+    /// Draws meshes with `u_Texture` tinted by `u_Color`.
+    UnlitTextured,
+    /// Draws meshes with the interpolated `Color0` of vertices.
+    VertexColor,
+    /// Blinn-Phong lighting with one directional light.
+    BlinnPhong,
+    /// Metallic-roughness physically based lighting with one directional light.
+    Pbr,
+    /// Gradient sky drawn with a fullscreen triangle at far plane.
+    Skybox,
+    /// Alpha blended textured and colored 2D vertices, e.g. UI elements and text.
+    Ui,
+    /// Alpha blended 2D vertices sampling the signed distance field in `u_Texture`,
+    /// e.g. crisp text and icons at any scale, with optional outline and shadow.
+    UiSdf,
+    /// Visualizes the normals of meshes in view space.
+    DebugNormals,
+}
+
+impl BuiltinShader {
+    /// All the built-in shaders.
+    pub const ALL: [BuiltinShader; 9] = [
+        BuiltinShader::UnlitColor,
+        BuiltinShader::UnlitTextured,
+        BuiltinShader::VertexColor,
+        BuiltinShader::BlinnPhong,
+        BuiltinShader::Pbr,
+        BuiltinShader::Skybox,
+        BuiltinShader::Ui,
+        BuiltinShader::UiSdf,
+        BuiltinShader::DebugNormals,
+    ];
+
+    /// Gets the name of this shader, which could be used to find it in video system.
+    pub fn name(&self) -> &'static str {
+        match *self {
+            BuiltinShader::UnlitColor => "builtin/unlit_color",
+            BuiltinShader::UnlitTextured => "builtin/unlit_textured",
+            BuiltinShader::VertexColor => "builtin/vertex_color",
+            BuiltinShader::BlinnPhong => "builtin/blinn_phong",
+            BuiltinShader::Pbr => "builtin/pbr",
+            BuiltinShader::Skybox => "builtin/skybox",
+            BuiltinShader::Ui => "builtin/ui",
+            BuiltinShader::UiSdf => "builtin/ui_sdf",
+            BuiltinShader::DebugNormals => "builtin/debug_normals",
+        }
+    }
+
+    /// Gets the vertex and fragment shader sources.
+    pub fn sources(&self) -> (&'static str, &'static str) {
+        match *self {
+            BuiltinShader::UnlitColor => (
+                include_str!("../../../assets/shaders/unlit_color.vs"),
+                include_str!("../../../assets/shaders/unlit_color.fs"),
+            ),
+            BuiltinShader::UnlitTextured => (
+                include_str!("../../../assets/shaders/unlit_textured.vs"),
+                include_str!("../../../assets/shaders/unlit_textured.fs"),
+            ),
+            BuiltinShader::VertexColor => (
+                include_str!("../../../assets/shaders/vertex_color.vs"),
+                include_str!("../../../assets/shaders/vertex_color.fs"),
+            ),
+            BuiltinShader::BlinnPhong => (
+                include_str!("../../../assets/shaders/lit.vs"),
+                include_str!("../../../assets/shaders/blinn_phong.fs"),
+            ),
+            BuiltinShader::Pbr => (
+                include_str!("../../../assets/shaders/lit.vs"),
+                include_str!("../../../assets/shaders/pbr.fs"),
+            ),
+            BuiltinShader::Skybox => (
+                include_str!("../../../assets/shaders/skybox.vs"),
+                include_str!("../../../assets/shaders/skybox.fs"),
+            ),
+            BuiltinShader::Ui => (
+                include_str!("../../../assets/shaders/ui.vs"),
+                include_str!("../../../assets/shaders/ui.fs"),
+            ),
+            BuiltinShader::UiSdf => (
+                include_str!("../../../assets/shaders/ui.vs"),
+                include_str!("../../../assets/shaders/ui_sdf.fs"),
+            ),
+            BuiltinShader::DebugNormals => (
+                include_str!("../../../assets/shaders/lit.vs"),
+                include_str!("../../../assets/shaders/debug_normals.fs"),
+            ),
+        }
+    }
+
+    /// Gets the attributes, uniforms and render state of this shader.
+    pub fn params(&self) -> ShaderParams {
+        let mut params = ShaderParams::default();
+        params.state.depth_write = true;
+        params.state.depth_test = Comparison::Less;
+
+        let mvp = UniformVariableLayout::build().with("u_MVPMatrix", UniformVariableType::Matrix4f);
+
+        match *self {
+            BuiltinShader::UnlitColor => {
+                params.attributes = AttributeLayout::build()
+                    .with(Attribute::Position, 3)
+                    .finish();
+
+                params.uniforms = mvp.with("u_Color", UniformVariableType::Vector4f)
+                    .finish();
+            }
+
+            BuiltinShader::UnlitTextured => {
+                params.attributes = AttributeLayout::build()
+                    .with(Attribute::Position, 3)
+                    .with(Attribute::Texcoord0, 2)
+                    .finish();
+
+                params.uniforms = mvp.with("u_Color", UniformVariableType::Vector4f)
+                    .with("u_Texture", UniformVariableType::Texture)
+                    .finish();
+            }
+
+            BuiltinShader::VertexColor => {
+                params.attributes = AttributeLayout::build()
+                    .with(Attribute::Position, 3)
+                    .with(Attribute::Color0, 4)
+                    .finish();
+
+                params.uniforms = mvp.finish();
+            }
+
+            BuiltinShader::BlinnPhong | BuiltinShader::Pbr => {
+                params.attributes = AttributeLayout::build()
+                    .with(Attribute::Position, 3)
+                    .with(Attribute::Normal, 3)
+                    .finish();
+
+                let uniforms = mvp.with("u_ModelViewMatrix", UniformVariableType::Matrix4f)
+                    .with("u_ViewNormalMatrix", UniformVariableType::Matrix4f)
+                    .with("u_LitViewDir", UniformVariableType::Vector3f)
+                    .with("u_LitColor", UniformVariableType::Vector3f)
+                    .with("u_Ambient", UniformVariableType::Vector3f)
+                    .with("u_Color", UniformVariableType::Vector4f);
+
+                params.uniforms = if *self == BuiltinShader::Pbr {
+                    uniforms
+                        .with("u_Metallic", UniformVariableType::F32)
+                        .with("u_Roughness", UniformVariableType::F32)
+                        .finish()
+                } else {
+                    uniforms
+                        .with("u_Specular", UniformVariableType::Vector3f)
+                        .with("u_Shininess", UniformVariableType::F32)
+                        .finish()
+                };
+            }
+
+            BuiltinShader::Skybox => {
+                params.state.depth_write = false;
+                params.state.depth_test = Comparison::LessOrEqual;
+
+                params.attributes = AttributeLayout::build()
+                    .with(Attribute::Position, 2)
+                    .finish();
+
+                params.uniforms = UniformVariableLayout::build()
+                    .with("u_InvViewProjMatrix", UniformVariableType::Matrix4f)
+                    .with("u_ZenithColor", UniformVariableType::Vector3f)
+                    .with("u_HorizonColor", UniformVariableType::Vector3f)
+                    .with("u_GroundColor", UniformVariableType::Vector3f)
+                    .finish();
+            }
+
+            BuiltinShader::Ui | BuiltinShader::UiSdf => {
+                params.state.depth_write = false;
+                params.state.depth_test = Comparison::Always;
+                params.state.color_blend = Some((
+                    Equation::Add,
+                    BlendFactor::Value(BlendValue::SourceAlpha),
+                    BlendFactor::OneMinusValue(BlendValue::SourceAlpha),
+                ));
+
+                params.attributes = AttributeLayout::build()
+                    .with(Attribute::Position, 2)
+                    .with(Attribute::Texcoord0, 2)
+                    .with(Attribute::Color0, 4)
+                    .finish();
+
+                let uniforms = UniformVariableLayout::build()
+                    .with("u_ProjMatrix", UniformVariableType::Matrix4f)
+                    .with("u_Texture", UniformVariableType::Texture);
+
+                params.uniforms = if *self == BuiltinShader::UiSdf {
+                    uniforms
+                        .with("u_Softness", UniformVariableType::F32)
+                        .with("u_OutlineWidth", UniformVariableType::F32)
+                        .with("u_OutlineColor", UniformVariableType::Vector4f)
+                        .with("u_ShadowOffset", UniformVariableType::Vector2f)
+                        .with("u_ShadowSoftness", UniformVariableType::F32)
+                        .with("u_ShadowColor", UniformVariableType::Vector4f)
+                        .finish()
+                } else {
+                    uniforms.finish()
+                };
+            }
+
+            BuiltinShader::DebugNormals => {
+                params.attributes = AttributeLayout::build()
+                    .with(Attribute::Position, 3)
+                    .with(Attribute::Normal, 3)
+                    .finish();
+
+                params.uniforms = mvp.with("u_ViewNormalMatrix", UniformVariableType::Matrix4f)
+                    .finish();
+            }
+        }
+
+        params
+    }
+}
+
+impl FromStr for BuiltinShader {
+    type Err = ();
+
+    fn from_str(name: &str) -> ::std::result::Result<Self, Self::Err> {
+        BuiltinShader::ALL
+            .iter()
+            .find(|v| v.name() == name)
+            .cloned()
+            .ok_or(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn names() {
+        for v in &BuiltinShader::ALL {
+            assert_eq!(v.name().parse::<BuiltinShader>(), Ok(*v));
+
+            let (vs, fs) = v.sources();
+            assert!(v.params().validate(vs, fs).is_ok());
+        }
+
+        assert!("builtin/unknown".parse::<BuiltinShader>().is_err());
+    }
+}
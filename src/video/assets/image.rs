@@ -0,0 +1,432 @@
+//! CPU-side image of uncompressed 8-bit texture data, which could be manipulated before
+//! uploading, e.g. procedural generations and mipmap chains.
+//!
+//! ```rust,ignore
+//! let mut image = Image::from_bytes(TextureFormat::RGBA8, (512, 512).into(), bytes)?;
+//! image.flip_vertical();
+//! image.premultiply_alpha();
+//!
+//! let image = image.resize((256, 256).into(), ImageFilter::Bilinear);
+//! let (params, data) = image.into_texture(true);
+//! let texture = video.create_texture(params, data)?;
+//! ```
+
+use math;
+use utils::noise;
+use video::errors::{Error, Result};
+
+use super::texture::{TextureData, TextureFormat, TextureParams};
+
+/// The filter used when resizing images.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum ImageFilter {
+    /// Averages all the source pixels covered by destination pixel, which is preferred
+    /// when downsampling.
+    Box,
+    /// Interpolates the four nearest source pixels.
+    Bilinear,
+}
+
+/// The source of a channel when swizzling.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum ImageChannel {
+    R,
+    G,
+    B,
+    A,
+    Zero,
+    One,
+}
+
+/// CPU-side image with one of `R8`, `RG8`, `RGB8` or `RGBA8` format.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Image {
+    format: TextureFormat,
+    dimensions: math::Vector2<u32>,
+    bytes: Vec<u8>,
+}
+
+impl Image {
+    /// Creates an image with all bytes zeroed.
+    pub fn new(format: TextureFormat, dimensions: math::Vector2<u32>) -> Result<Self> {
+        let len = format.size(dimensions) as usize;
+        Image::from_bytes(format, dimensions, vec![0; len])
+    }
+
+    /// Creates an image with tightly packed rows of pixels.
+    pub fn from_bytes(
+        format: TextureFormat,
+        dimensions: math::Vector2<u32>,
+        bytes: Vec<u8>,
+    ) -> Result<Self> {
+        if !Image::is_supported(format) {
+            return Err(Error::ImageFormatUnsupported(format));
+        }
+
+        if bytes.len() != format.size(dimensions) as usize {
+            return Err(Error::OutOfBounds);
+        }
+
+        Ok(Image {
+            format: format,
+            dimensions: dimensions,
+            bytes: bytes,
+        })
+    }
+
+    /// Creates a grayscale `RGBA8` image by evaluating `func` at the normalized
+    /// coordinates of every pixel, e.g. the noises of `utils::noise`. The results of
+    /// `func` are clamped into `[0, 1]`.
+    pub fn bake<F>(dimensions: math::Vector2<u32>, func: F) -> Self
+    where
+        F: Fn(f32, f32) -> f32,
+    {
+        Image {
+            format: TextureFormat::RGBA8,
+            dimensions: dimensions,
+            bytes: noise::bake(dimensions, func),
+        }
+    }
+
+    /// Returns true if the format could be manipulated as image.
+    pub fn is_supported(format: TextureFormat) -> bool {
+        match format {
+            TextureFormat::R8 | TextureFormat::RG8 | TextureFormat::RGB8 | TextureFormat::RGBA8 => {
+                true
+            }
+            _ => false,
+        }
+    }
+
+    #[inline]
+    pub fn format(&self) -> TextureFormat {
+        self.format
+    }
+
+    #[inline]
+    pub fn dimensions(&self) -> math::Vector2<u32> {
+        self.dimensions
+    }
+
+    #[inline]
+    pub fn bytes(&self) -> &[u8] {
+        &self.bytes
+    }
+
+    #[inline]
+    pub fn into_bytes(self) -> Vec<u8> {
+        self.bytes
+    }
+
+    /// Gets the pixel in RGBA order, returns `None` if it's out of bounds. The missing
+    /// color channels are zero, and the missing alpha channel is 255.
+    pub fn pixel(&self, x: u32, y: u32) -> Option<[u8; 4]> {
+        if x < self.dimensions.x && y < self.dimensions.y {
+            Some(self.texel(x, y))
+        } else {
+            None
+        }
+    }
+
+    /// Sets the pixel in RGBA order, the channels missing in format are discarded.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the pixel is out of bounds.
+    pub fn set_pixel(&mut self, x: u32, y: u32, v: [u8; 4]) {
+        let n = self.components();
+        let offset = self.offset(x, y);
+        self.bytes[offset..offset + n].copy_from_slice(&v[..n]);
+    }
+
+    /// Converts the image into another format.
+    pub fn convert(&self, format: TextureFormat) -> Result<Image> {
+        let mut image = Image::new(format, self.dimensions)?;
+        self.for_each(|x, y| image.set_pixel(x, y, self.texel(x, y)));
+        Ok(image)
+    }
+
+    /// Resizes the image with `filter`.
+    pub fn resize(&self, dimensions: math::Vector2<u32>, filter: ImageFilter) -> Image {
+        let mut image = Image::new(self.format, dimensions).unwrap();
+        if self.dimensions.x == 0 || self.dimensions.y == 0 {
+            return image;
+        }
+
+        let sx = self.dimensions.x as f32 / dimensions.x as f32;
+        let sy = self.dimensions.y as f32 / dimensions.y as f32;
+
+        image.for_each_mut(|x, y| match filter {
+            ImageFilter::Box => {
+                let x0 = (x as f32 * sx) as u32;
+                let y0 = (y as f32 * sy) as u32;
+                let x1 = (((x + 1) as f32 * sx).ceil() as u32).max(x0 + 1);
+                let y1 = (((y + 1) as f32 * sy).ceil() as u32).max(y0 + 1);
+                self.average(x0, y0, x1.min(self.dimensions.x), y1.min(self.dimensions.y))
+            }
+            ImageFilter::Bilinear => {
+                let u = (x as f32 + 0.5) * sx - 0.5;
+                let v = (y as f32 + 0.5) * sy - 0.5;
+                self.bilinear(u, v)
+            }
+        });
+
+        image
+    }
+
+    /// Flips the image upside down, e.g. converts between top-left and bottom-left origins.
+    pub fn flip_vertical(&mut self) {
+        let stride = self.stride();
+        let h = self.dimensions.y as usize;
+        for y in 0..h / 2 {
+            let (top, bottom) = self.bytes.split_at_mut((h - y - 1) * stride);
+            top[y * stride..(y + 1) * stride].swap_with_slice(&mut bottom[..stride]);
+        }
+    }
+
+    /// Flips the image left to right.
+    pub fn flip_horizontal(&mut self) {
+        let (w, h) = (self.dimensions.x, self.dimensions.y);
+        for y in 0..h {
+            for x in 0..w / 2 {
+                let (lhs, rhs) = (self.texel(x, y), self.texel(w - x - 1, y));
+                self.set_pixel(x, y, rhs);
+                self.set_pixel(w - x - 1, y, lhs);
+            }
+        }
+    }
+
+    /// Multiplies the color channels with alpha, does nothing if there is no alpha channel.
+    pub fn premultiply_alpha(&mut self) {
+        if self.format != TextureFormat::RGBA8 {
+            return;
+        }
+
+        for v in self.bytes.chunks_mut(4) {
+            let a = v[3] as u32;
+            for c in &mut v[..3] {
+                *c = ((*c as u32 * a + 127) / 255) as u8;
+            }
+        }
+    }
+
+    /// Rearranges the channels, the i-th channel of result is taken from `channels[i]`.
+    pub fn swizzle(&mut self, channels: [ImageChannel; 4]) {
+        let (w, h) = (self.dimensions.x, self.dimensions.y);
+        for y in 0..h {
+            for x in 0..w {
+                let src = self.texel(x, y);
+                let mut dst = [0; 4];
+                for (d, c) in dst.iter_mut().zip(channels.iter()) {
+                    *d = match *c {
+                        ImageChannel::R => src[0],
+                        ImageChannel::G => src[1],
+                        ImageChannel::B => src[2],
+                        ImageChannel::A => src[3],
+                        ImageChannel::Zero => 0,
+                        ImageChannel::One => 255,
+                    };
+                }
+
+                self.set_pixel(x, y, dst);
+            }
+        }
+    }
+
+    /// Generates the mipmap chain from largest size to smallest size (1x1), the first one
+    /// is a copy of this image.
+    pub fn mipmaps(&self) -> Vec<Image> {
+        let mut chain = vec![self.clone()];
+        loop {
+            let next = {
+                let last = chain.last().unwrap();
+                let (w, h) = (last.dimensions.x, last.dimensions.y);
+                if w <= 1 && h <= 1 {
+                    break;
+                }
+
+                let dimensions = math::Vector2::new((w / 2).max(1), (h / 2).max(1));
+                last.resize(dimensions, ImageFilter::Box)
+            };
+
+            chain.push(next);
+        }
+
+        chain
+    }
+
+    /// Converts the image into parameters and data of texture, with optional mipmaps.
+    pub fn into_texture(self, mipmaps: bool) -> (TextureParams, TextureData) {
+        let mut params = TextureParams::default();
+        params.format = self.format;
+        params.dimensions = self.dimensions;
+
+        let bytes = if mipmaps {
+            self.mipmaps()
+                .into_iter()
+                .map(|v| v.bytes.into_boxed_slice())
+                .collect()
+        } else {
+            vec![self.bytes.into_boxed_slice()]
+        };
+
+        (params, TextureData { bytes: bytes })
+    }
+
+    #[inline]
+    fn components(&self) -> usize {
+        self.format.components() as usize
+    }
+
+    #[inline]
+    fn stride(&self) -> usize {
+        self.dimensions.x as usize * self.components()
+    }
+
+    #[inline]
+    // Gets the pixel which is known to be in bounds.
+    fn texel(&self, x: u32, y: u32) -> [u8; 4] {
+        let n = self.components();
+        let offset = self.offset(x, y);
+        let mut v = [0, 0, 0, 255];
+        v[..n].copy_from_slice(&self.bytes[offset..offset + n]);
+        v
+    }
+
+    fn offset(&self, x: u32, y: u32) -> usize {
+        assert!(x < self.dimensions.x && y < self.dimensions.y);
+        y as usize * self.stride() + x as usize * self.components()
+    }
+
+    fn for_each<F: FnMut(u32, u32)>(&self, mut func: F) {
+        for y in 0..self.dimensions.y {
+            for x in 0..self.dimensions.x {
+                func(x, y);
+            }
+        }
+    }
+
+    fn for_each_mut<F: FnMut(u32, u32) -> [u8; 4]>(&mut self, mut func: F) {
+        for y in 0..self.dimensions.y {
+            for x in 0..self.dimensions.x {
+                let v = func(x, y);
+                self.set_pixel(x, y, v);
+            }
+        }
+    }
+
+    fn average(&self, x0: u32, y0: u32, x1: u32, y1: u32) -> [u8; 4] {
+        let mut sum = [0u32; 4];
+        for y in y0..y1 {
+            for x in x0..x1 {
+                for (s, v) in sum.iter_mut().zip(self.texel(x, y).iter()) {
+                    *s += *v as u32;
+                }
+            }
+        }
+
+        let n = (x1 - x0) * (y1 - y0);
+        let mut v = [0; 4];
+        for (d, s) in v.iter_mut().zip(sum.iter()) {
+            *d = ((*s + n / 2) / n) as u8;
+        }
+
+        v
+    }
+
+    fn bilinear(&self, u: f32, v: f32) -> [u8; 4] {
+        let (w, h) = (self.dimensions.x as f32, self.dimensions.y as f32);
+        let u = u.max(0.0).min(w - 1.0);
+        let v = v.max(0.0).min(h - 1.0);
+
+        let (x0, y0) = (u.floor() as u32, v.floor() as u32);
+        let x1 = (x0 + 1).min(self.dimensions.x - 1);
+        let y1 = (y0 + 1).min(self.dimensions.y - 1);
+        let (fx, fy) = (u - x0 as f32, v - y0 as f32);
+
+        let (p00, p10) = (self.texel(x0, y0), self.texel(x1, y0));
+        let (p01, p11) = (self.texel(x0, y1), self.texel(x1, y1));
+
+        let mut result = [0; 4];
+        for (i, v) in result.iter_mut().enumerate() {
+            let top = p00[i] as f32 + (p10[i] as f32 - p00[i] as f32) * fx;
+            let bottom = p01[i] as f32 + (p11[i] as f32 - p01[i] as f32) * fx;
+            *v = (top + (bottom - top) * fy).round() as u8;
+        }
+
+        result
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn checker() -> Image {
+        let bytes = vec![
+            0, 0, 0, 255, 255, 255, 255, 255, //
+            255, 255, 255, 255, 0, 0, 0, 255,
+        ];
+
+        Image::from_bytes(TextureFormat::RGBA8, (2, 2).into(), bytes).unwrap()
+    }
+
+    #[test]
+    fn conversion() {
+        let image = checker();
+        let rgb = image.convert(TextureFormat::RGB8).unwrap();
+        assert_eq!(rgb.bytes().len(), 12);
+        assert_eq!(rgb.pixel(1, 0), Some([255, 255, 255, 255]));
+
+        let r = rgb.convert(TextureFormat::R8).unwrap();
+        assert_eq!(r.bytes(), &[0, 255, 255, 0]);
+        assert_eq!(r.pixel(0, 0), Some([0, 0, 0, 255]));
+
+        assert!(image.convert(TextureFormat::Etc2RGB4BPP).is_err());
+        assert!(Image::from_bytes(TextureFormat::R8, (2, 2).into(), vec![0; 3]).is_err());
+    }
+
+    #[test]
+    fn manipulations() {
+        let mut image = checker();
+        image.flip_vertical();
+        assert_eq!(image.pixel(0, 0), Some([255, 255, 255, 255]));
+        image.flip_horizontal();
+        assert_eq!(image.pixel(0, 0), Some([0, 0, 0, 255]));
+
+        image.set_pixel(0, 0, [200, 100, 50, 128]);
+        image.premultiply_alpha();
+        assert_eq!(image.pixel(0, 0), Some([100, 50, 25, 128]));
+
+        use self::ImageChannel::*;
+        image.swizzle([B, G, R, One]);
+        assert_eq!(image.pixel(0, 0), Some([25, 50, 100, 255]));
+    }
+
+    #[test]
+    fn resize_and_mipmaps() {
+        let image = checker();
+        let small = image.resize((1, 1).into(), ImageFilter::Box);
+        assert_eq!(small.pixel(0, 0), Some([128, 128, 128, 255]));
+
+        let large = image.resize((4, 4).into(), ImageFilter::Bilinear);
+        assert_eq!(large.pixel(0, 0), Some([0, 0, 0, 255]));
+        assert_eq!(large.pixel(3, 0), Some([255, 255, 255, 255]));
+        assert_eq!(large.pixel(4, 0), None);
+        assert_eq!(large.pixel(0, 4), None);
+
+        let baked = Image::bake((2, 1).into(), |x, _| x);
+        assert_eq!(baked.format(), TextureFormat::RGBA8);
+        assert_eq!(baked.pixel(0, 0), Some([64, 64, 64, 255]));
+        assert_eq!(baked.pixel(1, 0), Some([191, 191, 191, 255]));
+
+        let image = Image::new(TextureFormat::RGBA8, (8, 4).into()).unwrap();
+        let chain = image.mipmaps();
+        assert_eq!(chain.len(), 4);
+        assert_eq!(chain[3].dimensions(), (1, 1).into());
+
+        let (params, data) = image.into_texture(true);
+        assert_eq!(params.dimensions, (8, 4).into());
+        assert_eq!(data.bytes.len(), 4);
+    }
+}
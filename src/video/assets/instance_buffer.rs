@@ -0,0 +1,85 @@
+//! Immutable or dynamic per-instance vertex data.
+
+use video::assets::mesh::{MeshHint, VertexLayout};
+use video::errors::{Error, Result};
+
+impl_handle!(InstanceBufferHandle);
+
+/// The setup parameters of instance buffer object. The attributes of instance buffer
+/// advance once per `divisor` instances instead of once per vertex, e.g. the model
+/// matrices or colors of instanced draws.
+#[derive(Serialize, Deserialize, Debug, Copy, Clone, PartialEq)]
+pub struct InstanceBufferParams {
+    /// Usage hints.
+    pub hint: MeshHint,
+    /// How a single instance structure looks like. The attributes which could not be
+    /// found in the vertex layout of mesh are read from instance buffer.
+    pub layout: VertexLayout,
+    /// The number of instances drawn with the same element before advancing.
+    pub divisor: u32,
+    /// The number of elements in this buffer.
+    pub num: usize,
+}
+
+impl Default for InstanceBufferParams {
+    fn default() -> Self {
+        InstanceBufferParams {
+            hint: MeshHint::Stream,
+            layout: VertexLayout::default(),
+            divisor: 1,
+            num: 0,
+        }
+    }
+}
+
+impl InstanceBufferParams {
+    pub fn validate(&self, data: Option<&[u8]>) -> Result<()> {
+        if let Some(v) = data {
+            if v.len() > self.buffer_len() {
+                return Err(Error::OutOfBounds);
+            }
+        }
+
+        if self.divisor == 0 {
+            return Err(Error::OutOfBounds);
+        }
+
+        Ok(())
+    }
+
+    #[inline]
+    pub fn buffer_len(&self) -> usize {
+        self.num * self.layout.stride() as usize
+    }
+
+    /// Returns the number of instances that could be drawn with this buffer.
+    #[inline]
+    pub fn max_instances(&self) -> usize {
+        self.num * self.divisor as usize
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use video::assets::mesh::VertexFormat;
+    use video::assets::shader::Attribute;
+
+    #[test]
+    fn validate() {
+        let mut params = InstanceBufferParams::default();
+        params.layout = VertexLayout::build()
+            .with(Attribute::Texcoord1, VertexFormat::Float, 4, false)
+            .finish();
+        params.num = 4;
+        assert_eq!(params.buffer_len(), 64);
+        assert!(params.validate(Some(&[0; 64][..])).is_ok());
+        assert!(params.validate(Some(&[0; 65][..])).is_err());
+
+        params.divisor = 2;
+        assert_eq!(params.max_instances(), 8);
+
+        params.divisor = 0;
+        assert!(params.validate(None).is_err());
+    }
+}
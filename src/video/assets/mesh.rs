@@ -1,5 +1,7 @@
 //! Immutable or dynamic vertex and index data.
 
+use std::borrow::Cow;
+
 use math;
 use video::assets::shader::Attribute;
 use video::errors::{Error, Result};
@@ -64,10 +66,17 @@ impl MeshParams {
             }
         }
 
+        let mut last = 0;
         for v in &self.sub_mesh_offsets {
-            if *v >= self.num_idxes {
+            if *v >= self.num_idxes || *v < last {
                 return Err(Error::OutOfBounds);
             }
+
+            last = *v;
+        }
+
+        if self.num_verts > self.index_format.max_vertices() {
+            return Err(Error::OutOfBounds);
         }
 
         Ok(())
@@ -82,9 +91,59 @@ impl MeshParams {
     pub fn index_buffer_len(&self) -> usize {
         self.num_idxes * self.index_format.stride() as usize
     }
+
+    /// Returns the number of sub-mesh sections. A mesh without explicit offsets is
+    /// treated as one section that covers all the indices.
+    #[inline]
+    pub fn num_sub_meshes(&self) -> usize {
+        ::std::cmp::max(self.sub_mesh_offsets.len(), 1)
+    }
+
+    /// Returns the index range `(from, len)` of the specified sub-mesh section.
+    pub fn sub_mesh(&self, index: usize) -> Option<(usize, usize)> {
+        if self.sub_mesh_offsets.is_empty() {
+            return if index == 0 {
+                Some((0, self.num_idxes))
+            } else {
+                None
+            };
+        }
+
+        let from = *self.sub_mesh_offsets.get(index)?;
+        let to = self.sub_mesh_offsets
+            .get(index + 1)
+            .cloned()
+            .unwrap_or(self.num_idxes);
+
+        Some((from, to - from))
+    }
+
+    /// Returns the index range `(from, len)` that `MeshIndex` refers to.
+    pub fn index_range(&self, index: MeshIndex) -> Option<(usize, usize)> {
+        match index {
+            MeshIndex::Ptr(from, len) => if (from + len) > self.num_idxes {
+                None
+            } else {
+                Some((from, len))
+            },
+            MeshIndex::SubMesh(index) => self.sub_mesh(index),
+            MeshIndex::All => Some((0, self.num_idxes)),
+        }
+    }
+
+    /// Returns the index value which restarts the primitive assembly of strips, `None`
+    /// if the primitive does not support restarting.
+    #[inline]
+    pub fn primitive_restart_index(&self) -> Option<u32> {
+        if self.primitive.is_strip() {
+            Some(self.index_format.restart_index())
+        } else {
+            None
+        }
+    }
 }
 
-/// Mesh index.
+/// Mesh index, which specifies the range of indices to be drawn.
 #[derive(Debug, PartialEq, Eq, Clone, Copy)]
 pub enum MeshIndex {
     SubMesh(usize),
@@ -140,11 +199,25 @@ impl MeshPrimitive {
             MeshPrimitive::TriangleStrip => indices - 2,
         }
     }
+
+    /// Returns true if the primitives are assembled as strips, which could be
+    /// splitted with the primitive restart index in single submission.
+    #[inline]
+    pub fn is_strip(&self) -> bool {
+        match *self {
+            MeshPrimitive::LineStrip | MeshPrimitive::TriangleStrip => true,
+            _ => false,
+        }
+    }
 }
 
 /// Vertex indices can be either 16- or 32-bit. You should always prefer
 /// 16-bit indices over 32-bit indices, since the latter may have performance
 /// penalties on some platforms, and they take up twice as much memory.
+///
+/// The maximum value of each format (`0xFFFF` and `0xFFFF_FFFF`) is reserved as
+/// the primitive restart index of strips, so it should never be used to address
+/// a vertex.
 #[derive(Serialize, Deserialize, Debug, PartialEq, Eq, Clone, Copy)]
 pub enum IndexFormat {
     U16,
@@ -152,6 +225,31 @@ pub enum IndexFormat {
 }
 
 impl IndexFormat {
+    /// Selects the smallest index format that could address `num_verts` vertices.
+    #[inline]
+    pub fn from_vertex_count(num_verts: usize) -> Self {
+        if num_verts <= IndexFormat::U16.max_vertices() {
+            IndexFormat::U16
+        } else {
+            IndexFormat::U32
+        }
+    }
+
+    /// Returns the maximum number of vertices that could be addressed with this format.
+    #[inline]
+    pub fn max_vertices(&self) -> usize {
+        self.restart_index() as usize
+    }
+
+    /// Returns the reserved index value that restarts primitive assembly.
+    #[inline]
+    pub fn restart_index(&self) -> u32 {
+        match *self {
+            IndexFormat::U16 => 0xFFFF,
+            IndexFormat::U32 => 0xFFFF_FFFF,
+        }
+    }
+
     pub fn stride(&self) -> usize {
         match *self {
             IndexFormat::U16 => 2,
@@ -166,6 +264,18 @@ impl IndexFormat {
         let len = values.len() * ::std::mem::size_of::<T>();
         unsafe { ::std::slice::from_raw_parts(values.as_ptr() as *const u8, len) }
     }
+
+    /// Encodes the 16-bit indices into the bytes of this format, the indices are widened
+    /// if the format is `U32`. Notes that the restart index of `U16` is not preserved.
+    pub fn encode_u16(&self, values: &[u16]) -> Cow<[u8]> {
+        match *self {
+            IndexFormat::U16 => Cow::Borrowed(IndexFormat::encode(values)),
+            IndexFormat::U32 => {
+                let values: Vec<u32> = values.iter().map(|&v| u32::from(v)).collect();
+                Cow::Owned(IndexFormat::encode(&values).to_vec())
+            }
+        }
+    }
 }
 
 /// The data type in the vertex component.
@@ -367,6 +477,58 @@ mod test {
         assert_eq!(element.normalized, true);
         assert_eq!(layout.element(Attribute::Normal), None);
     }
+
+    #[test]
+    fn index_format() {
+        assert_eq!(IndexFormat::from_vertex_count(0), IndexFormat::U16);
+        assert_eq!(IndexFormat::from_vertex_count(0xFFFF), IndexFormat::U16);
+        assert_eq!(IndexFormat::from_vertex_count(0x10000), IndexFormat::U32);
+
+        let mut params = MeshParams::default();
+        params.num_verts = 0x10000;
+        assert!(params.validate(None).is_err());
+
+        params.index_format = IndexFormat::from_vertex_count(params.num_verts);
+        assert!(params.validate(None).is_ok());
+        assert_eq!(params.primitive_restart_index(), None);
+
+        params.primitive = MeshPrimitive::TriangleStrip;
+        assert_eq!(params.primitive_restart_index(), Some(0xFFFF_FFFF));
+
+        let idxes: [u16; 3] = [0, 1, 0xFFFE];
+        let bytes = IndexFormat::U16.encode_u16(&idxes);
+        assert_eq!(&*bytes, IndexFormat::encode(&idxes));
+
+        let widened: [u32; 3] = [0, 1, 0xFFFE];
+        let bytes = IndexFormat::U32.encode_u16(&idxes);
+        assert_eq!(&*bytes, IndexFormat::encode(&widened));
+    }
+
+    #[test]
+    fn sub_mesh() {
+        let mut params = MeshParams::default();
+        params.num_verts = 4;
+        params.num_idxes = 12;
+        assert_eq!(params.num_sub_meshes(), 1);
+        assert_eq!(params.sub_mesh(0), Some((0, 12)));
+        assert_eq!(params.sub_mesh(1), None);
+
+        params.sub_mesh_offsets = vec![0, 3, 9];
+        assert!(params.validate(None).is_ok());
+        assert_eq!(params.num_sub_meshes(), 3);
+        assert_eq!(params.sub_mesh(0), Some((0, 3)));
+        assert_eq!(params.sub_mesh(1), Some((3, 6)));
+        assert_eq!(params.sub_mesh(2), Some((9, 3)));
+        assert_eq!(params.sub_mesh(3), None);
+
+        assert_eq!(params.index_range(MeshIndex::SubMesh(1)), Some((3, 6)));
+        assert_eq!(params.index_range(MeshIndex::Ptr(6, 6)), Some((6, 6)));
+        assert_eq!(params.index_range(MeshIndex::Ptr(6, 7)), None);
+        assert_eq!(params.index_range(MeshIndex::All), Some((0, 12)));
+
+        params.sub_mesh_offsets = vec![0, 9, 3];
+        assert!(params.validate(None).is_err());
+    }
 }
 
 #[macro_use]
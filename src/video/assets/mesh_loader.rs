@@ -1,15 +1,24 @@
 use bincode;
 use std::io::Read;
+use std::mem;
 use std::sync::Arc;
 
 use errors::*;
+use res::format::{AssetFormat, Version};
+use res::vfs::FileSlice;
 
 use super::super::VideoSystemShared;
 use super::mesh::*;
 
-pub const MAGIC: [u8; 8] = [
-    'V' as u8, 'M' as u8, 'S' as u8, 'H' as u8, ' ' as u8, 0, 0, 1,
-];
+pub const FORMAT: AssetFormat = AssetFormat {
+    name: "MeshLoader",
+    magic: [b'V', b'M', b'S', b'H'],
+    version: Version {
+        major: 0,
+        minor: 0,
+        patch: 1,
+    },
+};
 
 pub struct MeshLoader {
     video: Arc<VideoSystemShared>,
@@ -19,6 +28,16 @@ impl MeshLoader {
     pub fn new(video: Arc<VideoSystemShared>) -> Self {
         MeshLoader { video: video }
     }
+
+    fn update(&self, handle: MeshHandle, params: MeshParams, data: MeshData) -> Result<()> {
+        info!(
+            "[MeshLoader] loads {:?}. (Verts: {}, Indxes: {})",
+            handle, params.num_verts, params.num_idxes
+        );
+
+        self.video.update_mesh_async(handle, params, data)?;
+        Ok(())
+    }
 }
 
 impl ::res::ResourceHandle for MeshHandle {
@@ -34,24 +53,53 @@ impl ::res::ResourceLoader for MeshLoader {
         Ok(handle)
     }
 
-    fn load(&self, handle: Self::Handle, mut file: &mut dyn Read) -> Result<()> {
-        let mut buf = [0; 8];
-        file.read_exact(&mut buf[0..8])?;
+    fn load(&self, handle: Self::Handle, file: &mut dyn Read) -> Result<()> {
+        let (params, data): (MeshParams, MeshData) = FORMAT.load(file, &[], |mut file| {
+            let params = bincode::deserialize_from(&mut file)?;
+            let data = bincode::deserialize_from(&mut file)?;
+            Ok((params, data))
+        })?;
+
+        self.update(handle, params, data)
+    }
+
+    fn load_bytes(&self, handle: Self::Handle, bytes: &[u8]) -> Result<()> {
+        let version = FORMAT.read_header(&mut &bytes[..])?;
+        if !version.is_compatible_with(FORMAT.version) {
+            return self.load(handle, &mut &bytes[..]);
+        }
+
+        // Deserializes straight from the (possibly memory-mapped) contents, instead of
+        // pulling them through an intermediate reader.
+        let bytes = &bytes[FORMAT.header().len()..];
+        let params: MeshParams = bincode::deserialize(bytes)?;
+        let offset = bincode::serialized_size(&params)? as usize;
+        let data: MeshData = bincode::deserialize(&bytes[offset..])?;
+        self.update(handle, params, data)
+    }
 
-        // MAGIC: [u8; 8]
-        if &buf[0..8] != &MAGIC[..] {
-            bail!("[MeshLoader] MAGIC number not match.");
+    fn load_shared(&self, handle: Self::Handle, bytes: FileSlice) -> Result<()> {
+        let version = FORMAT.read_header(&mut &bytes[..])?;
+        if !version.is_compatible_with(FORMAT.version) {
+            return self.load(handle, &mut &bytes[..]);
         }
 
-        let params: MeshParams = bincode::deserialize_from(&mut file)?;
-        let data = bincode::deserialize_from(&mut file)?;
+        // Only the params are deserialized. The vertices and indices are sliced from the
+        // (possibly memory-mapped) contents, and uploaded to GPU without any copy.
+        let mut offset = FORMAT.header().len();
+        let params: MeshParams = bincode::deserialize(&bytes[offset..])?;
+        offset += bincode::serialized_size(&params)? as usize;
+
+        let verts = slice_bytes(&bytes, &mut offset)?;
+        let idxes = slice_bytes(&bytes, &mut offset)?;
 
         info!(
             "[MeshLoader] loads {:?}. (Verts: {}, Indxes: {})",
             handle, params.num_verts, params.num_idxes
         );
 
-        self.video.update_mesh_async(handle, params, data)?;
+        self.video
+            .update_mesh_shared(handle, params, verts, idxes)?;
         Ok(())
     }
 
@@ -61,3 +109,67 @@ impl ::res::ResourceLoader for MeshLoader {
         Ok(())
     }
 }
+
+// Slices the length-prefixed bytes at `offset`, which is how bincode encodes `Box<[u8]>`.
+fn slice_bytes(bytes: &FileSlice, offset: &mut usize) -> Result<FileSlice> {
+    let len: u64 = bincode::deserialize(&bytes[*offset..])?;
+    let start = *offset + mem::size_of::<u64>();
+    let end = start
+        .checked_add(len as usize)
+        .ok_or_else(|| format_err!("The length of bytes {} is out of bounds.", len))?;
+
+    *offset = end;
+    bytes.slice(start..end)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use res::vfs::FileBytes;
+    use res::ResourceLoader;
+    use video::backends::frame::Command;
+    use video::VideoSystem;
+
+    #[test]
+    fn load_shared() {
+        let video = VideoSystem::headless();
+        let loader = MeshLoader::new(video.shared());
+
+        let mut params = MeshParams::default();
+        params.layout = VertexLayout::build()
+            .with(Attribute::Position, VertexFormat::Float, 3, false)
+            .finish();
+        params.num_verts = 3;
+        params.num_idxes = 3;
+
+        let data = MeshData {
+            vptr: vec![1; 36].into_boxed_slice(),
+            iptr: vec![2; 6].into_boxed_slice(),
+        };
+
+        let mut file = FORMAT.header().to_vec();
+        file.extend(bincode::serialize(&params).unwrap());
+        file.extend(bincode::serialize(&data).unwrap());
+
+        let bytes = FileSlice::from(FileBytes::from_slice(&file));
+        let handle = loader.create().unwrap();
+        loader.load_shared(handle, bytes.clone()).unwrap();
+        assert_eq!(video.shared().mesh_layout(handle), Some(params.layout));
+
+        // The command refers to the contents of file, instead of a copy.
+        match video.shared().frames.front().cmds.last() {
+            Some(Command::CreateMeshShared(v, _, verts, idxes)) => {
+                assert_eq!(*v, handle);
+                assert_eq!(&verts[..], &data.vptr[..]);
+                assert_eq!(&idxes[..], &data.iptr[..]);
+                assert_eq!(verts.as_ptr(), bytes[file.len() - 50..].as_ptr());
+                assert_eq!(idxes.as_ptr(), bytes[file.len() - 6..].as_ptr());
+            }
+            _ => panic!("the mesh must be created from the shared bytes."),
+        }
+
+        // Truncated files are rejected.
+        let bytes = FileSlice::from(FileBytes::from_slice(&file[..file.len() - 1]));
+        assert!(loader.load_shared(handle, bytes).is_err());
+    }
+}
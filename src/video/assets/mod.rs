@@ -1,3 +1,5 @@
+pub mod builtin;
+pub mod image;
 pub mod shader;
 pub mod surface;
 pub mod texture;
@@ -5,14 +7,21 @@ pub mod texture_loader;
 #[macro_use]
 pub mod mesh;
 pub mod mesh_loader;
+pub mod instance_buffer;
+pub mod uniform_buffer;
 
 pub mod prelude {
+    pub use super::builtin::BuiltinShader;
+
+    pub use super::image::{Image, ImageChannel, ImageFilter};
+
     pub use super::surface::{SurfaceHandle, SurfaceParams, SurfaceScissor, SurfaceViewport};
 
     pub use super::shader::{
-        Attribute, AttributeLayout, AttributeLayoutBuilder, BlendFactor, BlendValue, Comparison,
-        CullFace, Equation, FrontFaceOrder, RenderState, ShaderHandle, ShaderParams,
-        UniformVariable, UniformVariableLayout, UniformVariableLayoutBuilder, UniformVariableType,
+        Attribute, AttributeLayout, AttributeLayoutBuilder, BlendFactor, BlendValue, ColorBlend,
+        Comparison, CullFace, Equation, FrontFaceOrder, PolygonMode, RenderState, ShaderHandle,
+        ShaderParams, UniformVariable, UniformVariableLayout, UniformVariableLayoutBuilder,
+        UniformVariableType,
     };
 
     pub use super::texture::{
@@ -24,4 +33,11 @@ pub mod prelude {
         IndexFormat, MeshData, MeshHandle, MeshHint, MeshIndex, MeshParams, MeshPrimitive,
         VertexFormat, VertexLayout,
     };
+
+    pub use super::instance_buffer::{InstanceBufferHandle, InstanceBufferParams};
+
+    pub use super::uniform_buffer::{
+        UniformBlock, UniformBlockLayout, UniformBlockLayoutBuilder, UniformBufferHandle,
+        UniformBufferParams,
+    };
 }
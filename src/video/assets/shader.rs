@@ -9,7 +9,10 @@ use utils::HashValue;
 use video::assets::mesh::VertexLayout;
 use video::assets::texture::{RenderTextureHandle, TextureHandle};
 use video::errors::{Error, Result};
-use video::{MAX_UNIFORM_VARIABLES, MAX_VERTEX_ATTRIBUTES};
+use video::{
+    MAX_FRAMEBUFFER_ATTACHMENTS, MAX_UNIFORM_BUFFER_SLOTS, MAX_UNIFORM_VARIABLES,
+    MAX_VERTEX_ATTRIBUTES,
+};
 
 impl_handle!(ShaderHandle);
 
@@ -19,6 +22,10 @@ impl_handle!(ShaderHandle);
 pub struct ShaderParams {
     pub attributes: AttributeLayout,
     pub uniforms: UniformVariableLayout,
+    /// The `(name, slot)` pairs of the uniform blocks declared in shader sources. The
+    /// blocks read from the uniform buffers which are bound to their slots with
+    /// `VideoSystemShared::bind_uniform_buffer`.
+    pub uniform_blocks: Vec<(String, usize)>,
     pub state: RenderState,
 }
 
@@ -31,6 +38,22 @@ impl ShaderParams {
             )));
         }
 
+        for (i, &(ref name, slot)) in self.uniform_blocks.iter().enumerate() {
+            if slot >= MAX_UNIFORM_BUFFER_SLOTS {
+                return Err(Error::ShaderInvalid(format!(
+                    "The slot of uniform block {} is out of bounds (>= {:?}).",
+                    name, MAX_UNIFORM_BUFFER_SLOTS
+                )));
+            }
+
+            if self.uniform_blocks[..i].iter().any(|v| v.0 == *name) {
+                return Err(Error::ShaderInvalid(format!(
+                    "Uniform block {} is declared more than once.",
+                    name
+                )));
+            }
+        }
+
         if vs.is_empty() {
             return Err(Error::ShaderInvalid(
                 "Vertex shader is required to describe a proper render pipeline.".into(),
@@ -214,6 +237,21 @@ pub enum FrontFaceOrder {
     CounterClockwise,
 }
 
+/// Specify how polygons are rasterized.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum PolygonMode {
+    /// Rasterizes the interior of polygons.
+    Fill,
+    /// Rasterizes the boundary edges of polygons as line segments.
+    ///
+    /// Notes that this is not available on OpenGL ES, where polygons are always filled.
+    Line,
+    /// Rasterizes the vertices of polygons as points.
+    ///
+    /// Notes that this is not available on OpenGL ES, where polygons are always filled.
+    Point,
+}
+
 /// A pixel-wise comparison function.
 #[derive(Debug, PartialEq, Eq, Clone, Copy)]
 pub enum Comparison {
@@ -260,27 +298,62 @@ pub enum BlendFactor {
     OneMinusValue(BlendValue),
 }
 
+/// The blend equation and factors of source and destination.
+pub type ColorBlend = (Equation, BlendFactor, BlendFactor);
+
 /// A struct that encapsulate all the necessary render states.
 #[derive(Debug, PartialEq, Clone, Copy)]
 pub struct RenderState {
+    /// Specify whether front- or back-facing polygons can be culled.
     pub cull_face: CullFace,
+    /// The winding order of front-facing polygons.
     pub front_face_order: FrontFaceOrder,
+    /// How polygons are rasterized.
+    pub polygon_mode: PolygonMode,
+    /// The comparison function used in depth test.
     pub depth_test: Comparison,
+    /// Enable or disable writing into the depth buffer.
     pub depth_write: bool,
+    /// The `(factor, units)` of polygon offset, which would be added to the depth
+    /// values of fragments. It's useful to resolve z-fighting of decals, outlines or
+    /// to bias the shadow maps.
     pub depth_write_offset: Option<(f32, f32)>,
-    pub color_blend: Option<(Equation, BlendFactor, BlendFactor)>,
+    /// Clamps the depth values of fragments into the depth range instead of clipping
+    /// primitives with near and far planes. Notes that this is ignored if the
+    /// underlying implementation does not support it.
+    pub depth_clamp: bool,
+    /// The blend function of all the color attachments.
+    pub color_blend: Option<ColorBlend>,
+    /// Overrides the blend functions of each color attachment. Notes that only the
+    /// first blend function will be used if the underlying implementation does not
+    /// support independent blending.
+    pub attachment_color_blends: Option<[Option<ColorBlend>; MAX_FRAMEBUFFER_ATTACHMENTS]>,
+    /// Enable or disable writing of the red, green, blue and alpha channels.
     pub color_write: (bool, bool, bool, bool),
 }
 
+impl RenderState {
+    /// Returns the blend function of the color attachment at `index`.
+    pub fn attachment_color_blend(&self, index: usize) -> Option<ColorBlend> {
+        match self.attachment_color_blends {
+            Some(ref v) => v.get(index).cloned().unwrap_or(None),
+            None => self.color_blend,
+        }
+    }
+}
+
 impl Default for RenderState {
     fn default() -> Self {
         RenderState {
             cull_face: CullFace::Nothing,
             front_face_order: FrontFaceOrder::CounterClockwise,
+            polygon_mode: PolygonMode::Fill,
             depth_test: Comparison::Always, // no depth test,
             depth_write: false,             // no depth write,
             depth_write_offset: None,
+            depth_clamp: false,
             color_blend: None,
+            attachment_color_blends: None,
             color_write: (true, true, true, true),
         }
     }
@@ -301,6 +374,19 @@ pub enum UniformVariableType {
     Matrix4f,
 }
 
+impl UniformVariableType {
+    /// Checks if a variable of type `rhs` could be bound to uniform of this type. Both
+    /// textures and render textures could be bound to samplers.
+    pub fn is_compatible(&self, rhs: UniformVariableType) -> bool {
+        use self::UniformVariableType as T;
+
+        match (*self, rhs) {
+            (T::Texture, T::RenderTexture) | (T::RenderTexture, T::Texture) => true,
+            (lhs, rhs) => lhs == rhs,
+        }
+    }
+}
+
 /// Uniform variable for video program object. Each matrix based `UniformVariable`
 /// is assumed to be supplied in row major order with a optional transpose.
 #[derive(Debug, Copy, Clone)]
@@ -466,6 +552,32 @@ impl UniformVariableLayout {
     {
         self.variables.get(&field.into()).map(|v| v.0.as_ref())
     }
+
+    /// Checks if the `variable` matches the declaration of `field` in this layout. The
+    /// `shader` is only used to describe errors.
+    pub fn validate<T>(
+        &self,
+        shader: ShaderHandle,
+        field: T,
+        variable: &UniformVariable,
+    ) -> Result<()>
+    where
+        T: Into<HashValue<str>>,
+    {
+        let field = field.into();
+        let provided = variable.variable_type();
+
+        match self.variables.get(&field) {
+            Some(&(_, declared)) if declared.is_compatible(provided) => Ok(()),
+            Some(&(ref name, declared)) => Err(Error::UniformMismatch(
+                name.clone(),
+                format!("{:?}", shader),
+                declared,
+                provided,
+            )),
+            None => Err(Error::UniformUndefined(field.to_string(), format!("{:?}", shader))),
+        }
+    }
 }
 
 #[derive(Default)]
@@ -482,7 +594,7 @@ impl UniformVariableLayoutBuilder {
         T: Into<String>,
     {
         let field = field.into();
-        let hash = HashValue::from(&field);
+        let hash = HashValue::register(&field);
         self.0.variables.insert(hash, (field, v));
         self
     }
@@ -492,3 +604,54 @@ impl UniformVariableLayoutBuilder {
         self.0
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn validate() {
+        let layout = UniformVariableLayout::build()
+            .with("u_Time", UniformVariableType::F32)
+            .with("u_Texture", UniformVariableType::Texture)
+            .finish();
+
+        let shader = ShaderHandle::default();
+        let time = UniformVariable::F32(0.0);
+        assert!(layout.validate(shader, "u_Time", &time).is_ok());
+
+        let rt = UniformVariable::RenderTexture(RenderTextureHandle::default());
+        assert!(layout.validate(shader, "u_Texture", &rt).is_ok());
+
+        match layout.validate(shader, "u_Time", &UniformVariable::Vector3f([0.0; 3])) {
+            Err(Error::UniformMismatch(name, _, declared, provided)) => {
+                assert_eq!(name, "u_Time");
+                assert_eq!(declared, UniformVariableType::F32);
+                assert_eq!(provided, UniformVariableType::Vector3f);
+            }
+            _ => unreachable!(),
+        }
+
+        match layout.validate(shader, "u_Color", &time) {
+            Err(Error::UniformUndefined(_, _)) => {}
+            _ => unreachable!(),
+        }
+    }
+
+    #[test]
+    fn uniform_blocks() {
+        let mut params = ShaderParams::default();
+        params.uniform_blocks.push(("Camera".into(), 0));
+        params.uniform_blocks.push(("Lights".into(), 1));
+        assert!(params.validate("vs", "fs").is_ok());
+
+        params.uniform_blocks.push(("Camera".into(), 2));
+        assert!(params.validate("vs", "fs").is_err());
+
+        params.uniform_blocks.pop();
+        params
+            .uniform_blocks
+            .push(("Frame".into(), MAX_UNIFORM_BUFFER_SLOTS));
+        assert!(params.validate("vs", "fs").is_err());
+    }
+}
@@ -57,7 +57,7 @@ pub struct TextureData {
 /// be used as a render target. If the `sampler` field is true, it can also be ther
 /// source of a texture access from a __shader__.
 ///
-#[derive(Debug, Copy, Clone)]
+#[derive(Debug, Copy, Clone, PartialEq)]
 pub struct RenderTextureParams {
     pub format: RenderTextureFormat,
     pub wrap: TextureWrap,
@@ -129,7 +129,7 @@ pub enum TextureWrap {
 /// floating point, multiplies by the signed scale factor, adds the signed bias, and
 /// clamps to the range [0,1].
 #[repr(u8)]
-#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+#[derive(Serialize, Deserialize, Debug, PartialEq, Eq, Clone, Copy)]
 pub enum RenderTextureFormat {
     RGB8,
     RGBA4,
@@ -146,6 +146,18 @@ impl RenderTextureFormat {
             || *self == RenderTextureFormat::RGBA4
             || *self == RenderTextureFormat::RGBA8
     }
+
+    /// Returns the estimated bytes of render texture with dimensions.
+    pub fn size(&self, dimensions: math::Vector2<u32>) -> u32 {
+        let square = dimensions.x * dimensions.y;
+        match *self {
+            RenderTextureFormat::RGBA4 | RenderTextureFormat::Depth16 => 2 * square,
+            RenderTextureFormat::RGB8 | RenderTextureFormat::Depth24 => 3 * square,
+            RenderTextureFormat::RGBA8
+            | RenderTextureFormat::Depth32
+            | RenderTextureFormat::Depth24Stencil8 => 4 * square,
+        }
+    }
 }
 
 /// List of all the possible formats of input data when uploading to texture.
@@ -3,13 +3,20 @@ use std::io::Read;
 use std::sync::Arc;
 
 use errors::*;
+use res::format::{AssetFormat, Version};
 
 use super::super::VideoSystemShared;
 use super::texture::*;
 
-pub const MAGIC: [u8; 8] = [
-    'V' as u8, 'T' as u8, 'E' as u8, 'X' as u8, ' ' as u8, 0, 0, 1,
-];
+pub const FORMAT: AssetFormat = AssetFormat {
+    name: "TextureLoader",
+    magic: [b'V', b'T', b'E', b'X'],
+    version: Version {
+        major: 0,
+        minor: 0,
+        patch: 1,
+    },
+};
 
 pub struct TextureLoader {
     video: Arc<VideoSystemShared>,
@@ -19,6 +26,21 @@ impl TextureLoader {
     pub fn new(video: Arc<VideoSystemShared>) -> Self {
         TextureLoader { video: video }
     }
+
+    fn update(
+        &self,
+        handle: TextureHandle,
+        params: TextureParams,
+        data: TextureData,
+    ) -> Result<()> {
+        info!(
+            "[TextureLoader] loads {:?} ({}x{} - {:?}).",
+            handle, params.dimensions.x, params.dimensions.y, params.format
+        );
+
+        self.video.update_texture_async(handle, params, data)?;
+        Ok(())
+    }
 }
 
 impl ::res::ResourceHandle for TextureHandle {
@@ -34,25 +56,29 @@ impl ::res::ResourceLoader for TextureLoader {
         Ok(handle)
     }
 
-    fn load(&self, handle: Self::Handle, mut file: &mut dyn Read) -> Result<()> {
-        let mut buf = [0; 8];
-        file.read_exact(&mut buf[0..8])?;
+    fn load(&self, handle: Self::Handle, file: &mut dyn Read) -> Result<()> {
+        let (params, data): (TextureParams, TextureData) = FORMAT.load(file, &[], |mut file| {
+            let params = bincode::deserialize_from(&mut file)?;
+            let data = bincode::deserialize_from(&mut file)?;
+            Ok((params, data))
+        })?;
 
-        // magic: [u8; 8]
-        if &buf[0..8] != &MAGIC[..] {
-            bail!("[TextureLoader] MAGIC number not match.");
-        }
-
-        let params: TextureParams = bincode::deserialize_from(&mut file)?;
-        let data = bincode::deserialize_from(&mut file)?;
+        self.update(handle, params, data)
+    }
 
-        info!(
-            "[TextureLoader] loads {:?} ({}x{} - {:?}).",
-            handle, params.dimensions.x, params.dimensions.y, params.format
-        );
+    fn load_bytes(&self, handle: Self::Handle, bytes: &[u8]) -> Result<()> {
+        let version = FORMAT.read_header(&mut &bytes[..])?;
+        if !version.is_compatible_with(FORMAT.version) {
+            return self.load(handle, &mut &bytes[..]);
+        }
 
-        self.video.update_texture_async(handle, params, data)?;
-        Ok(())
+        // Deserializes straight from the (possibly memory-mapped) contents, instead of
+        // pulling them through an intermediate reader.
+        let bytes = &bytes[FORMAT.header().len()..];
+        let params: TextureParams = bincode::deserialize(bytes)?;
+        let offset = bincode::serialized_size(&params)? as usize;
+        let data: TextureData = bincode::deserialize(&bytes[offset..])?;
+        self.update(handle, params, data)
     }
 
     fn delete(&self, handle: Self::Handle) -> Result<()> {
@@ -0,0 +1,412 @@
+//! Uniform buffer objects which share the uniform variables, e.g. the view and
+//! projection matrices or light sources, between drawcalls and shaders.
+//!
+//! The variables of a `layout(std140) uniform` block are encoded into `UniformBlock`
+//! with the offsets described by `UniformBlockLayout`, and uploaded into a uniform
+//! buffer once per frame. The shaders declare the slots of their blocks in
+//! `ShaderParams::uniform_blocks`, and read from the buffers bound to these slots
+//! with `VideoSystemShared::bind_uniform_buffer`.
+
+use std::{mem, slice};
+
+use utils::HashValue;
+use video::assets::mesh::MeshHint;
+use video::assets::shader::{UniformVariable, UniformVariableType};
+use video::errors::{Error, Result};
+
+impl_handle!(UniformBufferHandle);
+
+/// The setup parameters of uniform buffer object.
+#[derive(Serialize, Deserialize, Debug, Copy, Clone, PartialEq)]
+pub struct UniformBufferParams {
+    /// Usage hints.
+    pub hint: MeshHint,
+    /// The size of buffer in bytes, which is usually the `size` of
+    /// `UniformBlockLayout`.
+    pub size: usize,
+}
+
+impl Default for UniformBufferParams {
+    fn default() -> Self {
+        UniformBufferParams {
+            hint: MeshHint::Stream,
+            size: 0,
+        }
+    }
+}
+
+impl UniformBufferParams {
+    pub fn validate(&self, data: Option<&[u8]>) -> Result<()> {
+        if let Some(v) = data {
+            if v.len() > self.size {
+                return Err(Error::OutOfBounds);
+            }
+        }
+
+        if self.size == 0 {
+            return Err(Error::OutOfBounds);
+        }
+
+        Ok(())
+    }
+}
+
+/// A variable of uniform block.
+#[derive(Debug, Clone, PartialEq)]
+pub struct UniformBlockField {
+    pub name: String,
+    pub variable_type: UniformVariableType,
+    /// The number of elements, which is `None` if the variable is not an array.
+    pub len: Option<usize>,
+    /// The offset of the first element in bytes.
+    pub offset: usize,
+    /// The bytes between the elements of array.
+    pub stride: usize,
+}
+
+/// The std140 layout of uniform block, which is shared by all the shaders which
+/// declare the block with the same variables in the same order.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct UniformBlockLayout {
+    fields: Vec<(HashValue<str>, UniformBlockField)>,
+    size: usize,
+}
+
+impl UniformBlockLayout {
+    pub fn build() -> UniformBlockLayoutBuilder {
+        UniformBlockLayoutBuilder::new()
+    }
+
+    /// Gets the size of block in bytes.
+    #[inline]
+    pub fn size(&self) -> usize {
+        self.size
+    }
+
+    /// Returns an iterator of the variables in declared order.
+    pub fn iter(&self) -> UniformBlockLayoutIter {
+        UniformBlockLayoutIter {
+            fields: self.fields.iter(),
+        }
+    }
+
+    /// Gets the variable by name.
+    pub fn field<T>(&self, field: T) -> Option<&UniformBlockField>
+    where
+        T: Into<HashValue<str>>,
+    {
+        let field = field.into();
+        self.fields.iter().find(|v| v.0 == field).map(|v| &v.1)
+    }
+}
+
+pub struct UniformBlockLayoutIter<'a> {
+    fields: slice::Iter<'a, (HashValue<str>, UniformBlockField)>,
+}
+
+impl<'a> Iterator for UniformBlockLayoutIter<'a> {
+    type Item = &'a UniformBlockField;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.fields.next().map(|v| &v.1)
+    }
+}
+
+#[derive(Default)]
+pub struct UniformBlockLayoutBuilder {
+    layout: UniformBlockLayout,
+    error: Option<Error>,
+}
+
+impl UniformBlockLayoutBuilder {
+    #[inline]
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    /// Appends a variable to the block. The textures could not be declared in blocks,
+    /// which is reported by `finish`.
+    pub fn with<T>(self, field: T, v: UniformVariableType) -> Self
+    where
+        T: Into<String>,
+    {
+        self.push(field.into(), v, None)
+    }
+
+    /// Appends an array of `len` variables to the block, whose elements are always
+    /// aligned to 16 bytes. The empty arrays are reported by `finish`.
+    pub fn with_array<T>(self, field: T, v: UniformVariableType, len: usize) -> Self
+    where
+        T: Into<String>,
+    {
+        self.push(field.into(), v, Some(len))
+    }
+
+    /// Finishes the layout, or returns the first error of declared variables.
+    pub fn finish(mut self) -> Result<UniformBlockLayout> {
+        if let Some(err) = self.error {
+            return Err(err);
+        }
+
+        // The size of block is rounded up to the alignment of vec4.
+        self.layout.size = align(self.layout.size, 16);
+        Ok(self.layout)
+    }
+
+    fn push(mut self, name: String, v: UniformVariableType, len: Option<usize>) -> Self {
+        if self.error.is_some() {
+            return self;
+        }
+
+        if len == Some(0) {
+            self.error = Some(Error::OutOfBounds);
+            return self;
+        }
+
+        let (alignment, size) = match v {
+            UniformVariableType::I32 | UniformVariableType::F32 => (4, 4),
+            UniformVariableType::Vector2f => (8, 8),
+            UniformVariableType::Vector3f => (16, 12),
+            UniformVariableType::Vector4f => (16, 16),
+            // Matrices are stored as arrays of column vectors.
+            UniformVariableType::Matrix2f => (16, 32),
+            UniformVariableType::Matrix3f => (16, 48),
+            UniformVariableType::Matrix4f => (16, 64),
+            _ => {
+                self.error = Some(Error::UniformBlockUnsupported(name, v));
+                return self;
+            }
+        };
+
+        let (alignment, stride) = match len {
+            Some(_) => (16, align(size, 16)),
+            None => (alignment, size),
+        };
+
+        let offset = align(self.layout.size, alignment);
+        self.layout.size = offset + stride * len.unwrap_or(1);
+
+        let hash = HashValue::register(&name);
+        let field = UniformBlockField {
+            name: name,
+            variable_type: v,
+            len: len,
+            offset: offset,
+            stride: stride,
+        };
+
+        self.layout.fields.push((hash, field));
+        self
+    }
+}
+
+/// The CPU side data of uniform block, which encodes the uniform variables with
+/// std140 layout. It's usually uploaded with `VideoSystemShared::update_uniform_buffer`
+/// once per frame.
+#[derive(Debug, Clone)]
+pub struct UniformBlock {
+    layout: UniformBlockLayout,
+    bytes: Vec<u8>,
+}
+
+impl UniformBlock {
+    /// Creates a new block with all the variables zeroed.
+    pub fn new(layout: UniformBlockLayout) -> Self {
+        let bytes = vec![0; layout.size()];
+        UniformBlock {
+            layout: layout,
+            bytes: bytes,
+        }
+    }
+
+    #[inline]
+    pub fn layout(&self) -> &UniformBlockLayout {
+        &self.layout
+    }
+
+    /// Sets the variable, or the first element of array.
+    #[inline]
+    pub fn set<F, V>(&mut self, field: F, variable: V) -> Result<()>
+    where
+        F: Into<HashValue<str>>,
+        V: Into<UniformVariable>,
+    {
+        self.set_element(field, 0, variable)
+    }
+
+    /// Sets the element of array with index.
+    pub fn set_element<F, V>(&mut self, field: F, index: usize, variable: V) -> Result<()>
+    where
+        F: Into<HashValue<str>>,
+        V: Into<UniformVariable>,
+    {
+        let field = field.into();
+        let variable = variable.into();
+
+        let offset = {
+            let v = self.layout.field(field).ok_or_else(|| {
+                Error::UniformUndefined(field.to_string(), "uniform block".into())
+            })?;
+
+            if v.variable_type != variable.variable_type() {
+                return Err(Error::UniformMismatch(
+                    v.name.clone(),
+                    "uniform block".into(),
+                    v.variable_type,
+                    variable.variable_type(),
+                ));
+            }
+
+            if index >= v.len.unwrap_or(1) {
+                return Err(Error::OutOfBounds);
+            }
+
+            v.offset + v.stride * index
+        };
+
+        match variable {
+            UniformVariable::I32(v) => self.write(offset, &[v]),
+            UniformVariable::F32(v) => self.write(offset, &[v]),
+            UniformVariable::Vector2f(v) => self.write(offset, &v),
+            UniformVariable::Vector3f(v) => self.write(offset, &v),
+            UniformVariable::Vector4f(v) => self.write(offset, &v),
+            UniformVariable::Matrix2f(v, transpose) => {
+                for c in 0..2 {
+                    let column = if transpose { [v[0][c], v[1][c]] } else { v[c] };
+                    self.write(offset + c * 16, &column);
+                }
+            }
+            UniformVariable::Matrix3f(v, transpose) => {
+                for c in 0..3 {
+                    let column = if transpose {
+                        [v[0][c], v[1][c], v[2][c]]
+                    } else {
+                        v[c]
+                    };
+
+                    self.write(offset + c * 16, &column);
+                }
+            }
+            UniformVariable::Matrix4f(v, transpose) => {
+                for c in 0..4 {
+                    let column = if transpose {
+                        [v[0][c], v[1][c], v[2][c], v[3][c]]
+                    } else {
+                        v[c]
+                    };
+
+                    self.write(offset + c * 16, &column);
+                }
+            }
+            UniformVariable::Texture(_) | UniformVariable::RenderTexture(_) => unreachable!(),
+        }
+
+        Ok(())
+    }
+
+    /// Gets the encoded bytes.
+    #[inline]
+    pub fn as_bytes(&self) -> &[u8] {
+        &self.bytes
+    }
+
+    fn write<T: Copy>(&mut self, offset: usize, v: &[T]) {
+        let len = v.len() * mem::size_of::<T>();
+        let bytes = unsafe { slice::from_raw_parts(v.as_ptr() as *const u8, len) };
+        self.bytes[offset..(offset + len)].copy_from_slice(bytes);
+    }
+}
+
+#[inline]
+fn align(v: usize, alignment: usize) -> usize {
+    (v + alignment - 1) / alignment * alignment
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn std140() {
+        let layout = UniformBlockLayout::build()
+            .with("u_Time", UniformVariableType::F32)
+            .with("u_Offset", UniformVariableType::Vector2f)
+            .with("u_Color", UniformVariableType::Vector3f)
+            .with("u_Intensity", UniformVariableType::F32)
+            .with("u_ViewMatrix", UniformVariableType::Matrix4f)
+            .with_array("u_Weights", UniformVariableType::F32, 3)
+            .with("u_Normal", UniformVariableType::Matrix3f)
+            .with("u_Frame", UniformVariableType::I32)
+            .finish()
+            .unwrap();
+
+        let offsets: Vec<_> = layout.iter().map(|v| (v.offset, v.stride)).collect();
+        assert_eq!(
+            offsets,
+            [
+                (0, 4),
+                (8, 8),
+                (16, 12),
+                (28, 4),
+                (32, 64),
+                (96, 16),
+                (144, 48),
+                (192, 4),
+            ]
+        );
+
+        assert_eq!(layout.size(), 208);
+        assert_eq!(layout.field("u_Weights").unwrap().len, Some(3));
+        assert!(layout.field("u_Missing").is_none());
+
+        let layout = UniformBlockLayout::build()
+            .with("u_Time", UniformVariableType::F32)
+            .with("u_Texture", UniformVariableType::Texture)
+            .finish();
+        assert!(layout.is_err());
+
+        let layout = UniformBlockLayout::build()
+            .with("u_Source", UniformVariableType::RenderTexture)
+            .with("u_Time", UniformVariableType::F32)
+            .finish();
+        match layout {
+            Err(Error::UniformBlockUnsupported(name, _)) => assert_eq!(name, "u_Source"),
+            _ => panic!("render textures are declared in uniform block."),
+        }
+
+        let layout = UniformBlockLayout::build()
+            .with_array("u_Weights", UniformVariableType::F32, 0)
+            .finish();
+        assert!(layout.is_err());
+    }
+
+    #[test]
+    fn encode() {
+        let layout = UniformBlockLayout::build()
+            .with("u_Time", UniformVariableType::F32)
+            .with("u_Matrix", UniformVariableType::Matrix2f)
+            .with_array("u_Colors", UniformVariableType::Vector3f, 2)
+            .finish()
+            .unwrap();
+
+        let mut block = UniformBlock::new(layout);
+        assert_eq!(block.as_bytes().len(), 80);
+
+        block.set("u_Time", 1.0f32).unwrap();
+        block.set("u_Matrix", [[1.0f32, 2.0], [3.0, 4.0]]).unwrap();
+        block
+            .set_element("u_Colors", 1, [5.0f32, 6.0, 7.0])
+            .unwrap();
+
+        let floats: &[f32] =
+            unsafe { slice::from_raw_parts(block.as_bytes().as_ptr() as *const f32, 20) };
+        assert_eq!(&floats[0..1], &[1.0]);
+        assert_eq!(&floats[4..6], &[1.0, 2.0]);
+        assert_eq!(&floats[8..10], &[3.0, 4.0]);
+        assert_eq!(&floats[16..19], &[5.0, 6.0, 7.0]);
+
+        assert!(block.set_element("u_Colors", 2, [0.0f32; 3]).is_err());
+        assert!(block.set("u_Time", [0.0f32; 3]).is_err());
+        assert!(block.set("u_Missing", 0.0f32).is_err());
+    }
+}
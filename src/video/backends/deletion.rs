@@ -0,0 +1,171 @@
+use std::collections::VecDeque;
+
+use errors::*;
+
+use super::super::lifetime::VideoObject;
+use super::Visitor;
+
+/// The number of attempts to delete a video object, before it's reported as leaked.
+pub const MAX_DELETION_ATTEMPTS: u32 = 3;
+
+/// `DeletionQueue` defers the deletions of video objects until the frames referencing
+/// them have completed. The objects retired in a frame are tagged with the fence which
+/// is inserted at the end of that frame, and deleted once the GPU has signaled it.
+#[derive(Default)]
+pub(crate) struct DeletionQueue {
+    // The objects retired in current frame with the number of failed deletions, which
+    // are waiting for the fence at the end of frame.
+    retired: Vec<(u32, VideoObject)>,
+    pending: VecDeque<(u64, u32, VideoObject)>,
+}
+
+impl DeletionQueue {
+    /// Retires the object, which would be deleted after the fence of current frame.
+    #[inline]
+    pub fn retire(&mut self, object: VideoObject) {
+        self.retired.push((0, object));
+    }
+
+    /// Tags the objects retired in current frame with `fence`, which should be inserted
+    /// after all the commands of frame.
+    pub fn fence(&mut self, fence: u64) {
+        for (attempts, object) in self.retired.drain(..) {
+            self.pending.push_back((fence, attempts, object));
+        }
+    }
+
+    /// Deletes the objects whose fences are signaled. The failed deletions are retried
+    /// after the fence of next frame, and the objects are reported as leaked after
+    /// `MAX_DELETION_ATTEMPTS` attempts.
+    pub unsafe fn collect(&mut self, visitor: &mut Visitor, signaled: u64) {
+        while self.pending.front().map_or(false, |v| v.0 <= signaled) {
+            let (_, attempts, object) = self.pending.pop_front().unwrap();
+            if let Err(err) = delete(visitor, object) {
+                self.fail(object, attempts + 1, &err);
+            }
+        }
+    }
+
+    /// Deletes all the objects waiting for deletion, which should only be called after
+    /// the GPU has completed all the frames.
+    pub unsafe fn drain(&mut self, visitor: &mut Visitor) {
+        self.fence(0);
+        self.collect(visitor, ::std::u64::MAX);
+    }
+
+    /// Gets the number of objects which are waiting for deletion.
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.pending.len() + self.retired.len()
+    }
+
+    fn fail(&mut self, object: VideoObject, attempts: u32, err: &Error) {
+        if attempts < MAX_DELETION_ATTEMPTS {
+            warn!("Failed to delete {:?}, it will be retried. {}", object, err);
+            self.retired.push((attempts, object));
+        } else {
+            error!(
+                "Failed to delete {:?} after {} attempts, it's leaked. {}",
+                object, attempts, err
+            );
+        }
+    }
+}
+
+/// Deletes the video object with visitor.
+pub(crate) unsafe fn delete(visitor: &mut Visitor, object: VideoObject) -> Result<()> {
+    match object {
+        VideoObject::Surface(handle) => visitor.delete_surface(handle),
+        VideoObject::Shader(handle) => visitor.delete_shader(handle),
+        VideoObject::Texture(handle) => visitor.delete_texture(handle),
+        VideoObject::RenderTexture(handle) => visitor.delete_render_texture(handle),
+        VideoObject::Mesh(handle) => visitor.delete_mesh(handle),
+        VideoObject::InstanceBuffer(handle) => visitor.delete_instance_buffer(handle),
+        VideoObject::UniformBuffer(handle) => visitor.delete_uniform_buffer(handle),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::super::super::assets::prelude::*;
+    use super::super::headless::HeadlessVisitor;
+    use super::*;
+    use utils::handle::Handle;
+
+    #[test]
+    fn fences() {
+        let mut visitor = HeadlessVisitor::new();
+        let mesh: MeshHandle = Handle::new(1, 1).into();
+        let texture: TextureHandle = Handle::new(1, 1).into();
+
+        let mut queue = DeletionQueue::default();
+        unsafe {
+            visitor
+                .create_mesh(mesh, MeshParams::default(), None)
+                .unwrap();
+            visitor
+                .create_texture(texture, TextureParams::default(), None)
+                .unwrap();
+
+            queue.retire(VideoObject::Mesh(mesh));
+            queue.fence(1);
+            queue.retire(VideoObject::Texture(texture));
+            queue.fence(2);
+            assert_eq!(queue.len(), 2);
+
+            // The objects are kept until the fences of their frames are signaled.
+            queue.collect(&mut visitor, 0);
+            assert_eq!(queue.len(), 2);
+            queue.collect(&mut visitor, 1);
+            assert_eq!(queue.len(), 1);
+            assert!(visitor.delete_mesh(mesh).is_err());
+            queue.collect(&mut visitor, 2);
+            assert_eq!(queue.len(), 0);
+            assert!(visitor.delete_texture(texture).is_err());
+
+            visitor
+                .create_mesh(mesh, MeshParams::default(), None)
+                .unwrap();
+            queue.retire(VideoObject::Mesh(mesh));
+            queue.drain(&mut visitor);
+            assert_eq!(queue.len(), 0);
+            assert!(visitor.delete_mesh(mesh).is_err());
+        }
+    }
+
+    #[test]
+    fn retries() {
+        let mut visitor = HeadlessVisitor::new();
+        let mesh: MeshHandle = Handle::new(1, 1).into();
+
+        let mut queue = DeletionQueue::default();
+        unsafe {
+            // The failed deletions are retried in the next frames, and given up after
+            // `MAX_DELETION_ATTEMPTS` attempts.
+            queue.retire(VideoObject::Mesh(mesh));
+            for i in 1..MAX_DELETION_ATTEMPTS {
+                queue.fence(u64::from(i));
+                queue.collect(&mut visitor, u64::from(i));
+                assert_eq!(queue.len(), 1);
+            }
+
+            queue.fence(u64::from(MAX_DELETION_ATTEMPTS));
+            queue.collect(&mut visitor, u64::from(MAX_DELETION_ATTEMPTS));
+            assert_eq!(queue.len(), 0);
+
+            // The object is deleted if the retry succeeds.
+            queue.retire(VideoObject::Mesh(mesh));
+            queue.fence(4);
+            queue.collect(&mut visitor, 4);
+            assert_eq!(queue.len(), 1);
+
+            visitor
+                .create_mesh(mesh, MeshParams::default(), None)
+                .unwrap();
+            queue.fence(5);
+            queue.collect(&mut visitor, 5);
+            assert_eq!(queue.len(), 0);
+            assert!(visitor.delete_mesh(mesh).is_err());
+        }
+    }
+}
@@ -2,9 +2,14 @@ use std::sync::{Mutex, MutexGuard, RwLock};
 
 use errors::*;
 use math;
+use res::vfs::FileSlice;
 use utils::{data_buf, hash_value};
 
 use super::super::assets::prelude::*;
+use super::super::lifetime::VideoObject;
+use super::super::readback::ReadbackHandle;
+use super::super::timing::{GpuTimer, TimerMark};
+use super::deletion::DeletionQueue;
 use super::Visitor;
 
 type VarsPtr = data_buf::DataBufferPtr<[(hash_value::HashValue<str>, UniformVariable)]>;
@@ -14,26 +19,47 @@ type BytesPtr = data_buf::DataBufferPtr<[u8]>;
 pub enum Command {
     Bind(SurfaceHandle),
     Draw(ShaderHandle, MeshHandle, MeshIndex, VarsPtr),
+    DrawInstanced(
+        ShaderHandle,
+        MeshHandle,
+        MeshIndex,
+        InstanceBufferHandle,
+        u32,
+        VarsPtr,
+    ),
     UpdateScissor(SurfaceScissor),
     UpdateViewport(SurfaceViewport),
+    ReadPixels(SurfaceHandle, math::Aabb2<u32>, ReadbackHandle),
 
     CreateSurface(SurfaceHandle, SurfaceParams),
     DeleteSurface(SurfaceHandle),
 
     CreateShader(ShaderHandle, ShaderParams, String, String),
+    UpdateShader(ShaderHandle, ShaderParams, String, String),
     DeleteShader(ShaderHandle),
 
     CreateTexture(TextureHandle, TextureParams, Option<TextureData>),
     UpdateTexture(TextureHandle, math::Aabb2<u32>, BytesPtr),
+    UploadTexture(TextureHandle, (u32, u32), math::Aabb2<u32>, BytesPtr),
     DeleteTexture(TextureHandle),
 
     CreateRenderTexture(RenderTextureHandle, RenderTextureParams),
     DeleteRenderTexture(RenderTextureHandle),
 
     CreateMesh(MeshHandle, MeshParams, Option<MeshData>),
+    CreateMeshShared(MeshHandle, MeshParams, FileSlice, FileSlice),
     UpdateVertexBuffer(MeshHandle, usize, BytesPtr),
     UpdateIndexBuffer(MeshHandle, usize, BytesPtr),
     DeleteMesh(MeshHandle),
+
+    CreateInstanceBuffer(InstanceBufferHandle, InstanceBufferParams, Option<BytesPtr>),
+    UpdateInstanceBuffer(InstanceBufferHandle, usize, BytesPtr),
+    DeleteInstanceBuffer(InstanceBufferHandle),
+
+    CreateUniformBuffer(UniformBufferHandle, UniformBufferParams, Option<BytesPtr>),
+    UpdateUniformBuffer(UniformBufferHandle, usize, BytesPtr),
+    DeleteUniformBuffer(UniformBufferHandle),
+    BindUniformBuffer(usize, Option<UniformBufferHandle>),
 }
 
 #[derive(Debug, Clone)]
@@ -54,10 +80,14 @@ impl Frame {
         }
     }
 
-    /// Dispatch frame tasks and draw calls to the backend context.
+    /// Dispatch frame tasks and draw calls to the backend context. The deleted objects
+    /// are retired into `deletions`, and deleted once the GPU has signaled the fence at
+    /// the end of frame.
     pub fn dispatch(
         &mut self,
         visitor: &mut Visitor,
+        deletions: &mut DeletionQueue,
+        timer: &mut GpuTimer,
         dimensions: math::Vector2<u32>,
     ) -> Result<(u32, u32)> {
         unsafe {
@@ -67,6 +97,7 @@ impl Frame {
             for v in self.cmds.drain(..) {
                 match v {
                     Command::Bind(surface) => {
+                        timer.mark(visitor, TimerMark::Surface(surface))?;
                         visitor.bind(surface, dimensions)?;
                     }
 
@@ -74,6 +105,15 @@ impl Frame {
                         let vars = self.bufs.as_slice(ptr);
                         dc += 1;
                         tris += visitor.draw(shader, mesh, mesh_index, vars)?;
+                        timer.mark(visitor, TimerMark::DrawCall(shader, mesh))?;
+                    }
+
+                    Command::DrawInstanced(shader, mesh, mesh_index, buffer, instances, ptr) => {
+                        let vars = self.bufs.as_slice(ptr);
+                        dc += 1;
+                        tris += visitor
+                            .draw_instanced(shader, mesh, mesh_index, buffer, instances, vars)?;
+                        timer.mark(visitor, TimerMark::DrawCall(shader, mesh))?;
                     }
 
                     Command::UpdateScissor(scissor) => {
@@ -84,20 +124,33 @@ impl Frame {
                         visitor.update_surface_viewport(view)?;
                     }
 
+                    Command::ReadPixels(surface, area, handle) => {
+                        visitor.bind(surface, dimensions)?;
+                        visitor.read_pixels(handle, area)?;
+                    }
+
                     Command::CreateSurface(handle, params) => {
                         visitor.create_surface(handle, params)?;
                     }
 
                     Command::DeleteSurface(handle) => {
-                        visitor.delete_surface(handle)?;
+                        deletions.retire(VideoObject::Surface(handle));
                     }
 
                     Command::CreateShader(handle, params, vs, fs) => {
                         visitor.create_shader(handle, params, &vs, &fs)?;
                     }
 
+                    Command::UpdateShader(handle, params, vs, fs) => {
+                        // The failures of recompilation are not fatal, the previous
+                        // program is kept instead.
+                        if let Err(err) = visitor.create_shader(handle, params, &vs, &fs) {
+                            warn!("Failed to update {:?}. {}", handle, err);
+                        }
+                    }
+
                     Command::DeleteShader(handle) => {
-                        visitor.delete_shader(handle)?;
+                        deletions.retire(VideoObject::Shader(handle));
                     }
 
                     Command::CreateTexture(handle, params, data) => {
@@ -109,8 +162,13 @@ impl Frame {
                         visitor.update_texture(handle, area, data)?;
                     }
 
+                    Command::UploadTexture(handle, (level, levels), area, ptr) => {
+                        let data = self.bufs.as_slice(ptr);
+                        visitor.upload_texture(handle, level, levels, area, data)?;
+                    }
+
                     Command::DeleteTexture(handle) => {
-                        visitor.delete_texture(handle)?;
+                        deletions.retire(VideoObject::Texture(handle));
                     }
 
                     Command::CreateRenderTexture(handle, params) => {
@@ -118,13 +176,18 @@ impl Frame {
                     }
 
                     Command::DeleteRenderTexture(handle) => {
-                        visitor.delete_render_texture(handle)?;
+                        deletions.retire(VideoObject::RenderTexture(handle));
                     }
 
                     Command::CreateMesh(handle, params, data) => {
+                        let data = data.as_ref().map(|v| (&v.vptr[..], &v.iptr[..]));
                         visitor.create_mesh(handle, params, data)?;
                     }
 
+                    Command::CreateMeshShared(handle, params, verts, idxes) => {
+                        visitor.create_mesh(handle, params, Some((&verts[..], &idxes[..])))?;
+                    }
+
                     Command::UpdateVertexBuffer(handle, offset, ptr) => {
                         let data = self.bufs.as_slice(ptr);
                         visitor.update_vertex_buffer(handle, offset, data)?;
@@ -136,11 +199,53 @@ impl Frame {
                     }
 
                     Command::DeleteMesh(handle) => {
-                        visitor.delete_mesh(handle)?;
+                        deletions.retire(VideoObject::Mesh(handle));
+                    }
+
+                    Command::CreateInstanceBuffer(handle, params, ptr) => {
+                        let bufs = &self.bufs;
+                        let data = ptr.map(|v| bufs.as_slice(v));
+                        visitor.create_instance_buffer(handle, params, data)?;
+                    }
+
+                    Command::UpdateInstanceBuffer(handle, offset, ptr) => {
+                        let data = self.bufs.as_slice(ptr);
+                        visitor.update_instance_buffer(handle, offset, data)?;
+                    }
+
+                    Command::DeleteInstanceBuffer(handle) => {
+                        deletions.retire(VideoObject::InstanceBuffer(handle));
+                    }
+
+                    Command::CreateUniformBuffer(handle, params, ptr) => {
+                        let bufs = &self.bufs;
+                        let data = ptr.map(|v| bufs.as_slice(v));
+                        visitor.create_uniform_buffer(handle, params, data)?;
+                    }
+
+                    Command::UpdateUniformBuffer(handle, offset, ptr) => {
+                        let data = self.bufs.as_slice(ptr);
+                        visitor.update_uniform_buffer(handle, offset, data)?;
+                    }
+
+                    Command::DeleteUniformBuffer(handle) => {
+                        deletions.retire(VideoObject::UniformBuffer(handle));
+                    }
+
+                    Command::BindUniformBuffer(slot, handle) => {
+                        visitor.bind_uniform_buffer(slot, handle)?;
                     }
                 }
             }
 
+            timer.mark(visitor, TimerMark::End)?;
+
+            // The objects retired in this frame are deleted once the GPU has completed
+            // all the commands before the fence.
+            deletions.fence(visitor.insert_fence()?);
+            let signaled = visitor.signaled_fence()?;
+            deletions.collect(visitor, signaled);
+
             visitor.flush()?;
             self.bufs.clear();
             Ok((dc, tris))
@@ -0,0 +1,188 @@
+//! The indexed blend functions, which specify the blend state of each color
+//! attachment independently.
+//!
+//! These are core functions since OpenGL 4.0 and OpenGL ES 3.2, and are exposed with
+//! the `ARB` or `OES` suffix by `GL_ARB_draw_buffers_blend` and
+//! `GL_OES_draw_buffers_indexed` on older contexts. The generated bindings only load
+//! the core entry points, so the suffixed ones are loaded here.
+
+use gl;
+use gl::types::*;
+use std::mem;
+
+type EnableFn = unsafe extern "system" fn(GLenum, GLuint);
+type BlendFuncFn = unsafe extern "system" fn(GLuint, GLenum, GLenum);
+type BlendEquationFn = unsafe extern "system" fn(GLuint, GLenum);
+
+/// The suffix of indexed blend functions.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum IndexedBlendApi {
+    /// OpenGL 4.0 or OpenGL ES 3.2.
+    Core,
+    /// `GL_ARB_draw_buffers_blend`, which relies on the `glEnablei` and `glDisablei` of
+    /// OpenGL 3.0.
+    ARB,
+    /// `GL_OES_draw_buffers_indexed`.
+    OES,
+}
+
+impl IndexedBlendApi {
+    fn names(self) -> [&'static str; 4] {
+        match self {
+            IndexedBlendApi::Core => [
+                "glEnablei",
+                "glDisablei",
+                "glBlendFunci",
+                "glBlendEquationi",
+            ],
+            IndexedBlendApi::ARB => [
+                "glEnablei",
+                "glDisablei",
+                "glBlendFunciARB",
+                "glBlendEquationiARB",
+            ],
+            IndexedBlendApi::OES => [
+                "glEnableiOES",
+                "glDisableiOES",
+                "glBlendFunciOES",
+                "glBlendEquationiOES",
+            ],
+        }
+    }
+}
+
+/// The loaded entry points of indexed blend functions.
+pub struct IndexedBlend {
+    api: IndexedBlendApi,
+    enable: EnableFn,
+    disable: EnableFn,
+    blend_func: BlendFuncFn,
+    blend_equation: BlendEquationFn,
+}
+
+impl IndexedBlend {
+    /// Loads the entry points of `api` with `loader`. Returns `None` if any of them
+    /// is not available.
+    pub unsafe fn load<F>(api: IndexedBlendApi, mut loader: F) -> Option<Self>
+    where
+        F: FnMut(&str) -> *const (),
+    {
+        let names = api.names();
+
+        let mut ptrs = [0 as *const (); 4];
+        for (ptr, name) in ptrs.iter_mut().zip(names.iter()) {
+            *ptr = loader(name);
+            if ptr.is_null() {
+                warn!("Failed to load {} of indexed blend functions.", name);
+                return None;
+            }
+        }
+
+        Some(IndexedBlend {
+            api: api,
+            enable: mem::transmute::<*const (), EnableFn>(ptrs[0]),
+            disable: mem::transmute::<*const (), EnableFn>(ptrs[1]),
+            blend_func: mem::transmute::<*const (), BlendFuncFn>(ptrs[2]),
+            blend_equation: mem::transmute::<*const (), BlendEquationFn>(ptrs[3]),
+        })
+    }
+
+    /// Returns the suffix of loaded entry points.
+    #[inline]
+    pub fn api(&self) -> IndexedBlendApi {
+        self.api
+    }
+
+    /// Enables blending of the color attachment at `index`.
+    #[inline]
+    pub unsafe fn enable(&self, index: GLuint) {
+        (self.enable)(gl::BLEND, index)
+    }
+
+    /// Disables blending of the color attachment at `index`.
+    #[inline]
+    pub unsafe fn disable(&self, index: GLuint) {
+        (self.disable)(gl::BLEND, index)
+    }
+
+    /// Specifies the blend factors of the color attachment at `index`.
+    #[inline]
+    pub unsafe fn blend_func(&self, index: GLuint, src: GLenum, dst: GLenum) {
+        (self.blend_func)(index, src, dst)
+    }
+
+    /// Specifies the blend equation of the color attachment at `index`.
+    #[inline]
+    pub unsafe fn blend_equation(&self, index: GLuint, mode: GLenum) {
+        (self.blend_equation)(index, mode)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    unsafe extern "system" fn dummy() {}
+
+    fn load(api: IndexedBlendApi, missing: Option<&str>) -> (Option<IndexedBlend>, Vec<String>) {
+        let mut requested = Vec::new();
+        let v = unsafe {
+            IndexedBlend::load(api, |name| {
+                requested.push(name.to_owned());
+                if Some(name) == missing {
+                    0 as *const ()
+                } else {
+                    dummy as *const ()
+                }
+            })
+        };
+
+        (v, requested)
+    }
+
+    #[test]
+    fn names() {
+        let (v, requested) = load(IndexedBlendApi::Core, None);
+        assert_eq!(v.unwrap().api(), IndexedBlendApi::Core);
+        assert_eq!(
+            requested,
+            [
+                "glEnablei",
+                "glDisablei",
+                "glBlendFunci",
+                "glBlendEquationi"
+            ]
+        );
+
+        let (v, requested) = load(IndexedBlendApi::ARB, None);
+        assert_eq!(v.unwrap().api(), IndexedBlendApi::ARB);
+        assert_eq!(
+            requested,
+            [
+                "glEnablei",
+                "glDisablei",
+                "glBlendFunciARB",
+                "glBlendEquationiARB",
+            ]
+        );
+
+        let (v, requested) = load(IndexedBlendApi::OES, None);
+        assert_eq!(v.unwrap().api(), IndexedBlendApi::OES);
+        assert_eq!(
+            requested,
+            [
+                "glEnableiOES",
+                "glDisableiOES",
+                "glBlendFunciOES",
+                "glBlendEquationiOES",
+            ]
+        );
+    }
+
+    #[test]
+    fn missing() {
+        let (v, requested) = load(IndexedBlendApi::OES, Some("glBlendFunciOES"));
+        assert!(v.is_none());
+        assert_eq!(requested.len(), 3);
+    }
+}
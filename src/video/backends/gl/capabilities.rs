@@ -6,6 +6,8 @@ use std::mem;
 
 use errors::*;
 
+use super::super::super::capabilities::{VideoApi, VideoCapabilities, VideoProfile};
+
 /// Describes the OpenGL context profile.
 #[derive(Debug, Copy, Clone)]
 pub enum Profile {
@@ -146,6 +148,17 @@ macro_rules! extensions {
 
                 Ok(extensions)
             }
+
+            /// Returns the names of supported extensions.
+            pub fn names(&self) -> Vec<&'static str> {
+                let mut names = Vec::new();
+                $(
+                    if self.$field {
+                        names.push($string);
+                    }
+                )+
+                names
+            }
         }
     }
 }
@@ -167,9 +180,20 @@ extensions! {
     "GL_OES_vertex_array_object" => gl_oes_vertex_array_object,
     "GL_IMG_texture_compression_pvrtc" => gl_img_texture_compression_pvrtc,
     "GL_EXT_texture_compression_s3tc" => gl_ext_texture_compression_s3tc,
+    "GL_ARB_ES2_compatibility" => gl_arb_es2_compatibility,
     "GL_ARB_ES3_compatibility" => gl_arb_es3_compatibility,
     "GL_OES_compressed_ETC2_RGB8_texture" => gl_oes_compressed_etc2_rgb8_texture,
     "GL_OES_compressed_ETC2_RGBA8_texture" => gl_oes_compressed_etc2_rgba8_texture,
+    "GL_OES_element_index_uint" => gl_oes_element_index_uint,
+    "GL_ARB_depth_clamp" => gl_arb_depth_clamp,
+    "GL_EXT_depth_clamp" => gl_ext_depth_clamp,
+    "GL_ARB_draw_buffers_blend" => gl_arb_draw_buffers_blend,
+    "GL_OES_draw_buffers_indexed" => gl_oes_draw_buffers_indexed,
+    "GL_ARB_timer_query" => gl_arb_timer_query,
+    "GL_ARB_sync" => gl_arb_sync,
+    "GL_ARB_texture_float" => gl_arb_texture_float,
+    "GL_OES_texture_float" => gl_oes_texture_float,
+    "GL_OES_texture_half_float" => gl_oes_texture_half_float,
 }
 
 #[derive(Debug)]
@@ -224,12 +248,24 @@ pub struct Capabilities {
 
     /// Maximum number of color attachment bind points.
     pub max_color_attachments: u32,
+
+    /// Maximum width and height of textures.
+    pub max_texture_size: u32,
+
+    /// Maximum number of samples of multisample render buffers, 0 if not supported.
+    pub max_samples: u32,
+
+    /// Only the features of GLES 2.0 are used, either it's the version of context or
+    /// forced by the `VideoProfile::Gles2`.
+    pub gles2: bool,
 }
 
 impl Capabilities {
-    pub unsafe fn parse() -> Result<Capabilities> {
+    pub unsafe fn parse(profile: VideoProfile) -> Result<Capabilities> {
         let version = Version::parse()?;
         let extensions = Extensions::parse(version)?;
+        let gles2 = profile == VideoProfile::Gles2
+            || (version >= Version::ES(2, 0) && version < Version::ES(3, 0));
 
         let (debug, forward_compatible) = if version >= Version::GL(3, 0) {
             let mut val = mem::uninitialized();
@@ -254,7 +290,18 @@ impl Capabilities {
             max_viewport_dims: Capabilities::parse_viewport_dims(),
             max_combined_texture_image_units: Capabilities::parse_texture_image_units(),
             max_indexed_uniform_buffer: Capabilities::parse_uniform_buffers(version, &extensions),
-            max_color_attachments: Capabilities::parse_color_attachments(version, &extensions),
+            max_color_attachments: if gles2 {
+                1
+            } else {
+                Capabilities::parse_color_attachments(version, &extensions)
+            },
+            max_texture_size: Capabilities::parse_texture_size(),
+            max_samples: if gles2 {
+                0
+            } else {
+                Capabilities::parse_samples(version, &extensions)
+            },
+            gles2: gles2,
         })
     }
 
@@ -270,6 +317,158 @@ impl Capabilities {
         }
     }
 
+    /// Summarizes the capabilities which are exposed to users.
+    pub fn summary(&self) -> VideoCapabilities {
+        VideoCapabilities {
+            api: match self.version {
+                Version::GL(major, minor) => VideoApi::GL(major, minor),
+                Version::ES(major, minor) => VideoApi::ES(major, minor),
+            },
+            profile: if self.gles2 {
+                VideoProfile::Gles2
+            } else {
+                VideoProfile::Standard
+            },
+            vendor: self.vendor.clone(),
+            renderer: self.renderer.clone(),
+            extensions: self.extensions.names().iter().map(|v| v.to_string()).collect(),
+            max_texture_size: self.max_texture_size,
+            max_samples: self.max_samples,
+            max_color_attachments: self.max_color_attachments,
+            u32_index: self.has_u32_index(),
+            vertex_array_object: self.has_vertex_array_object(),
+            uniform_buffer: self.has_uniform_buffer(),
+            instancing: self.has_instancing(),
+            timer_query: self.has_timer_query(),
+            half_float_texture: self.has_float_texture(true),
+            float_texture: self.has_float_texture(false),
+            etc2: self.has_compression(TextureCompression::ETC2),
+            pvrtc: self.has_compression(TextureCompression::PVRTC),
+            s3tc: self.has_compression(TextureCompression::S3TC),
+        }
+    }
+
+    /// Returns true if 32-bit indices could be used in element arrays.
+    pub fn has_u32_index(&self) -> bool {
+        let core = self.version >= Version::GL(1, 1) || self.version >= Version::ES(3, 0);
+        (core && !self.gles2) || self.extensions.gl_oes_element_index_uint
+    }
+
+    /// Returns true if the primitive restart with fixed index (the maximum value
+    /// of index type) is supported.
+    pub fn has_fixed_primitive_restart(&self) -> bool {
+        !self.gles2
+            && (self.version >= Version::GL(4, 3)
+                || self.version >= Version::ES(3, 0)
+                || self.extensions.gl_arb_es3_compatibility)
+    }
+
+    /// Returns true if the rasterization mode of polygons could be changed.
+    pub fn has_polygon_mode(&self) -> bool {
+        !self.gles2 && self.version >= Version::GL(1, 1)
+    }
+
+    /// Returns true if the depth values of fragments could be clamped instead of
+    /// clipping primitives.
+    pub fn has_depth_clamp(&self) -> bool {
+        !self.gles2
+            && (self.version >= Version::GL(3, 2)
+                || self.extensions.gl_arb_depth_clamp
+                || self.extensions.gl_ext_depth_clamp)
+    }
+
+    /// Returns true if the blend functions of each color attachment could be
+    /// specified independently.
+    pub fn has_independent_blend(&self) -> bool {
+        !self.gles2
+            && (self.version >= Version::GL(4, 0)
+                || self.version >= Version::ES(3, 2)
+                || (self.version >= Version::GL(3, 0) && self.extensions.gl_arb_draw_buffers_blend)
+                || self.extensions.gl_oes_draw_buffers_indexed)
+    }
+
+    /// Returns true if the primitive restart with user-specified index is supported.
+    pub fn has_primitive_restart(&self) -> bool {
+        !self.gles2 && self.version >= Version::GL(3, 1)
+    }
+
+    /// Returns true if vertex array objects could be used to bind vertex attributes.
+    pub fn has_vertex_array_object(&self) -> bool {
+        !self.gles2
+            && (self.version >= Version::GL(3, 0)
+                || self.version >= Version::ES(3, 0)
+                || self.extensions.gl_arb_vertex_array_object)
+    }
+
+    /// Returns true if a global vertex array object should be bound while the GLES 2.0
+    /// profile is forced. The core and forward compatible contexts draw nothing without
+    /// vertex array objects, so the attributes are bound per drawcall to a global one.
+    pub fn has_global_vertex_array_object(&self) -> bool {
+        self.gles2
+            && self.version >= Version::GL(3, 0)
+            && match self.profile {
+                Some(Profile::Core) => true,
+                _ => self.forward_compatible,
+            }
+    }
+
+    /// Returns true if the shaders written in GLSL ES 1.00 are accepted, which are
+    /// only supported by desktop contexts since GL 4.1 or with `ARB_ES2_compatibility`.
+    pub fn has_glsl100(&self) -> bool {
+        self.version >= Version::ES(2, 0)
+            || self.version >= Version::GL(4, 1)
+            || self.extensions.gl_arb_es2_compatibility
+    }
+
+    /// Returns true if uniform buffer objects are supported.
+    pub fn has_uniform_buffer(&self) -> bool {
+        !self.gles2 && self.max_indexed_uniform_buffer > 0
+    }
+
+    /// Returns true if instanced drawing is supported.
+    pub fn has_instancing(&self) -> bool {
+        !self.gles2 && (self.version >= Version::GL(3, 3) || self.version >= Version::ES(3, 0))
+    }
+
+    /// Returns true if the textures with floating point formats are available, which
+    /// are provided by the `OES_texture_float` and `OES_texture_half_float` extensions
+    /// on GLES 2.0.
+    pub fn has_float_texture(&self, half: bool) -> bool {
+        if self.gles2 {
+            return if half {
+                self.extensions.gl_oes_texture_half_float
+            } else {
+                self.extensions.gl_oes_texture_float
+            };
+        }
+
+        self.version >= Version::GL(3, 0)
+            || self.version >= Version::ES(3, 0)
+            || self.extensions.gl_arb_texture_float
+    }
+
+    /// Returns true if the GPU timestamps could be recorded with timer queries.
+    pub fn has_timer_query(&self) -> bool {
+        !self.gles2
+            && (self.version >= Version::GL(3, 3) || self.extensions.gl_arb_timer_query)
+            && gl::QueryCounter::is_loaded()
+    }
+
+    /// Returns true if the completion of commands could be polled with fences.
+    pub fn has_sync(&self) -> bool {
+        !self.gles2
+            && (self.version >= Version::GL(3, 2)
+                || self.version >= Version::ES(3, 0)
+                || self.extensions.gl_arb_sync)
+            && gl::FenceSync::is_loaded()
+    }
+
+    /// Returns true if the pixels could be read into pixel pack buffers, and polled with
+    /// fences instead of stalling until the GPU catches up.
+    pub fn has_async_readback(&self) -> bool {
+        self.has_sync() && gl::MapBufferRange::is_loaded()
+    }
+
     #[inline]
     unsafe fn parse_str(id: GLenum) -> Result<String> {
         let s = gl::GetString(gl::RENDERER);
@@ -313,9 +512,33 @@ impl Capabilities {
         val as u8
     }
 
+    #[inline]
+    unsafe fn parse_texture_size() -> u32 {
+        let mut val = 64;
+        gl::GetIntegerv(gl::MAX_TEXTURE_SIZE, &mut val);
+        val as u32
+    }
+
+    #[inline]
+    unsafe fn parse_samples(version: Version, exts: &Extensions) -> u32 {
+        if version >= Version::GL(3, 0)
+            || version >= Version::ES(3, 0)
+            || exts.gl_arb_framebuffer_object
+        {
+            let mut val = 0;
+            gl::GetIntegerv(gl::MAX_SAMPLES, &mut val);
+            val as u32
+        } else {
+            0
+        }
+    }
+
     #[inline]
     unsafe fn parse_uniform_buffers(version: Version, exts: &Extensions) -> u32 {
-        if version >= Version::GL(3, 1) || exts.gl_arb_uniform_buffer_object {
+        if version >= Version::GL(3, 1)
+            || version >= Version::ES(3, 0)
+            || exts.gl_arb_uniform_buffer_object
+        {
             let mut val = mem::uninitialized();
             gl::GetIntegerv(gl::MAX_UNIFORM_BUFFER_BINDINGS, &mut val);
             val as u32
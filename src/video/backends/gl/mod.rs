@@ -1,3 +1,5 @@
+pub mod blend;
 pub mod capabilities;
+pub mod translate;
 pub mod types;
 pub mod visitor;
@@ -0,0 +1,243 @@
+//! Translates the shaders written in GLSL 3.30 or GLSL ES 3.00 into GLSL ES 1.00,
+//! which is used by the GLES 2.0 profile.
+//!
+//! Only the common differences are handled: the version directive, the `in`/`out`
+//! qualifiers, the layout qualifiers, the outputs of fragment shader and the texture
+//! lookup functions. A default float precision is declared in fragment shaders which
+//! do not have one, since its required by GLSL ES 1.00. The sources without version
+//! directive or with `#version 100` are returned untouched.
+
+/// The stage of shader sources.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum ShaderStage {
+    Vertex,
+    Fragment,
+}
+
+/// Translates the shader sources into GLSL ES 1.00.
+pub fn to_glsl100(source: &str, stage: ShaderStage) -> String {
+    let version = source
+        .lines()
+        .map(|v| v.trim())
+        .find(|v| v.starts_with("#version"));
+
+    match version {
+        None => return source.to_owned(),
+        Some(v) if v.split_whitespace().nth(1) == Some("100") => return source.to_owned(),
+        _ => {}
+    }
+
+    // Desktop GLSL has no default precision of floats in fragment shaders.
+    let precision = stage == ShaderStage::Fragment
+        && !source.lines().any(|v| {
+            let mut words = v.split_whitespace();
+            words.next() == Some("precision") && words.nth(1) == Some("float;")
+        });
+
+    let mut cubes = Vec::new();
+    let mut outputs = Vec::new();
+    let mut lines = Vec::new();
+
+    for line in source.lines() {
+        let trimmed = line.trim();
+        if trimmed.starts_with("#version") {
+            lines.push("#version 100".to_owned());
+            if precision {
+                lines.push("precision mediump float;".to_owned());
+            }
+
+            continue;
+        }
+
+        let decl = strip_layout(trimmed);
+        let mut words = decl.split_whitespace();
+        let qualifier = words.next().unwrap_or("");
+
+        if qualifier == "uniform" && words.next() == Some("samplerCube") {
+            if let Some(name) = declared_name(decl) {
+                cubes.push(name.to_owned());
+            }
+        }
+
+        let translated = match (stage, qualifier) {
+            (ShaderStage::Vertex, "in") => format!("attribute{}", &decl[2..]),
+            (ShaderStage::Vertex, "out") => format!("varying{}", &decl[3..]),
+            (ShaderStage::Fragment, "in") => format!("varying{}", &decl[2..]),
+            (ShaderStage::Fragment, "out") => {
+                // The outputs of fragment shader are replaced by `gl_FragColor`.
+                if let Some(name) = declared_name(decl) {
+                    outputs.push(name.to_owned());
+                }
+
+                continue;
+            }
+            _ => line.to_owned(),
+        };
+
+        lines.push(translated);
+    }
+
+    let mut result = String::with_capacity(source.len());
+    for line in lines {
+        let mut line = replace_texture_calls(&line, &cubes);
+        for v in &outputs {
+            line = replace_ident(&line, v, "gl_FragColor");
+        }
+
+        result.push_str(&line);
+        result.push('\n');
+    }
+
+    result
+}
+
+// Removes the leading `layout(...)` qualifier of declaration.
+fn strip_layout(decl: &str) -> &str {
+    if decl.starts_with("layout") {
+        if let Some(end) = decl.find(')') {
+            return decl[(end + 1)..].trim_left();
+        }
+    }
+
+    decl
+}
+
+// Gets the name of a declaration like `out vec4 o_Color;`.
+fn declared_name(decl: &str) -> Option<&str> {
+    decl.trim_right_matches(|c: char| c == ';' || c.is_whitespace())
+        .split_whitespace()
+        .last()
+        .map(|v| v.split('[').next().unwrap())
+}
+
+fn is_ident_char(c: char) -> bool {
+    c.is_alphanumeric() || c == '_'
+}
+
+// Replaces the whole identifiers `from` in line with `to`.
+fn replace_ident(line: &str, from: &str, to: &str) -> String {
+    if from.is_empty() {
+        return line.to_owned();
+    }
+
+    let mut result = String::with_capacity(line.len());
+    let mut rest = line;
+
+    while let Some(i) = rest.find(from) {
+        let before = rest[..i].chars().next_back();
+        let after = rest[(i + from.len())..].chars().next();
+        let whole = !before.map_or(false, is_ident_char) && !after.map_or(false, is_ident_char);
+
+        result.push_str(&rest[..i]);
+        result.push_str(if whole { to } else { from });
+        rest = &rest[(i + from.len())..];
+    }
+
+    result.push_str(rest);
+    result
+}
+
+// Replaces `texture(sampler, ...)` with `texture2D` or `textureCube` by the type of
+// sampler.
+fn replace_texture_calls(line: &str, cubes: &[String]) -> String {
+    let mut result = String::with_capacity(line.len());
+    let mut rest = line;
+
+    while let Some(i) = rest.find("texture(") {
+        let before = rest[..i].chars().next_back();
+        result.push_str(&rest[..i]);
+        rest = &rest[(i + "texture".len())..];
+
+        if before.map_or(false, is_ident_char) {
+            result.push_str("texture");
+            continue;
+        }
+
+        let sampler = rest[1..]
+            .trim_left()
+            .split(|c: char| !is_ident_char(c))
+            .next()
+            .unwrap_or("");
+
+        if cubes.iter().any(|v| v == sampler) {
+            result.push_str("textureCube");
+        } else {
+            result.push_str("texture2D");
+        }
+    }
+
+    result.push_str(rest);
+    result
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn untouched() {
+        let src = "#version 100\nattribute vec3 Position;\nvoid main() {}\n";
+        assert_eq!(to_glsl100(src, ShaderStage::Vertex), src);
+
+        let src = "attribute vec3 Position;\n";
+        assert_eq!(to_glsl100(src, ShaderStage::Vertex), src);
+    }
+
+    #[test]
+    fn vertex() {
+        let src = "#version 300 es\n\
+                   layout(location = 0) in vec3 Position;\n\
+                   out vec2 v_Texcoord;\n\
+                   void main() { v_Texcoord = Position.xy; }\n";
+
+        let dst = "#version 100\n\
+                   attribute vec3 Position;\n\
+                   varying vec2 v_Texcoord;\n\
+                   void main() { v_Texcoord = Position.xy; }\n";
+
+        assert_eq!(to_glsl100(src, ShaderStage::Vertex), dst);
+    }
+
+    #[test]
+    fn fragment() {
+        let src = "#version 330\n\
+                   in vec2 v_Uv;\n\
+                   uniform sampler2D u_Main;\n\
+                   uniform samplerCube u_Sky;\n\
+                   out vec4 o_Color;\n\
+                   void main() {\n\
+                   o_Color = texture(u_Main, v_Uv) * texture( u_Sky, vec3(1.0));\n\
+                   o_Color.a = texture2D(u_Main, v_Uv).a + o_Colors;\n\
+                   }\n";
+
+        let dst = "#version 100\n\
+                   precision mediump float;\n\
+                   varying vec2 v_Uv;\n\
+                   uniform sampler2D u_Main;\n\
+                   uniform samplerCube u_Sky;\n\
+                   void main() {\n\
+                   gl_FragColor = texture2D(u_Main, v_Uv) * textureCube( u_Sky, vec3(1.0));\n\
+                   gl_FragColor.a = texture2D(u_Main, v_Uv).a + o_Colors;\n\
+                   }\n";
+
+        assert_eq!(to_glsl100(src, ShaderStage::Fragment), dst);
+    }
+
+    #[test]
+    fn precision() {
+        let src = "#version 300 es\n\
+                   precision highp float;\n\
+                   out vec4 o_Color;\n\
+                   void main() { o_Color = vec4(1.0); }\n";
+
+        let dst = "#version 100\n\
+                   precision highp float;\n\
+                   void main() { gl_FragColor = vec4(1.0); }\n";
+
+        assert_eq!(to_glsl100(src, ShaderStage::Fragment), dst);
+
+        let src = "#version 330\nvoid main() {}\n";
+        let dst = "#version 100\nvoid main() {}\n";
+        assert_eq!(to_glsl100(src, ShaderStage::Vertex), dst);
+    }
+}
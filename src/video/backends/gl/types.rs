@@ -1,17 +1,21 @@
 use gl;
 use gl::types::*;
 use std::borrow::Borrow;
+use std::collections::HashMap;
 
 use super::super::super::assets::prelude::*;
 use super::capabilities::{Capabilities, TextureCompression};
 use utils::handle;
 
+/// The backend objects keyed by their handles. The version of handle is a part of
+/// key, since the objects retired by `DeletionQueue` are still alive while their
+/// indices might have been reused by new objects.
 #[derive(Debug)]
 pub struct DataVec<T>
 where
     T: Sized + Clone,
 {
-    pub buf: Vec<Option<T>>,
+    pub buf: HashMap<handle::Handle, T>,
 }
 
 impl<T> DataVec<T>
@@ -19,53 +23,37 @@ where
     T: Sized + Clone,
 {
     pub fn new() -> Self {
-        DataVec { buf: Vec::new() }
+        DataVec {
+            buf: HashMap::new(),
+        }
     }
 
     pub fn get<H>(&self, handle: H) -> Option<&T>
     where
         H: Borrow<handle::Handle>,
     {
-        self.buf
-            .get(handle.borrow().index() as usize)
-            .and_then(|v| v.as_ref())
+        self.buf.get(handle.borrow())
     }
 
     pub fn get_mut<H>(&mut self, handle: H) -> Option<&mut T>
     where
         H: Borrow<handle::Handle>,
     {
-        self.buf
-            .get_mut(handle.borrow().index() as usize)
-            .and_then(|v| v.as_mut())
+        self.buf.get_mut(handle.borrow())
     }
 
     pub fn create<H>(&mut self, handle: H, value: T)
     where
         H: Borrow<handle::Handle>,
     {
-        let handle = handle.borrow();
-        let index = handle.index() as usize;
-
-        if self.buf.len() <= index {
-            self.buf.resize(index + 1, None);
-        }
-
-        self.buf[index] = Some(value);
+        self.buf.insert(*handle.borrow(), value);
     }
 
     pub fn free<H>(&mut self, handle: H) -> Option<T>
     where
         H: Borrow<handle::Handle>,
     {
-        let handle = handle.borrow();
-        if self.buf.len() <= handle.index() as usize {
-            None
-        } else {
-            let mut value = None;
-            ::std::mem::swap(&mut value, &mut self.buf[handle.index() as usize]);
-            value
-        }
+        self.buf.remove(handle.borrow())
     }
 }
 
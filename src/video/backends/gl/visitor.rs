@@ -1,7 +1,8 @@
 use gl;
 use gl::types::*;
 use std::cell::RefCell;
-use std::collections::{HashMap, HashSet};
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::{mem, ptr};
 
 use application::window::Window;
 use errors::*;
@@ -9,11 +10,29 @@ use math;
 use utils::hash_value;
 
 use super::super::super::assets::prelude::*;
-use super::super::super::MAX_UNIFORM_TEXTURE_SLOTS;
+use super::super::super::capabilities::{VideoCapabilities, VideoProfile};
+use super::super::super::readback::ReadbackHandle;
+use super::super::super::{
+    MAX_FRAMEBUFFER_ATTACHMENTS, MAX_UNIFORM_BUFFER_SLOTS, MAX_UNIFORM_TEXTURE_SLOTS,
+};
 use super::super::{UniformVar, Visitor};
+use super::blend::{IndexedBlend, IndexedBlendApi};
 use super::capabilities::{Capabilities, Version};
+use super::translate::{self, ShaderStage};
 use super::types::DataVec;
 
+// The number of frames which might be still in flight, which are assumed to be
+// completed after it if fences are not supported.
+const FRAMES_IN_FLIGHT: u64 = 2;
+
+// The pixels which are being copied into a pixel pack buffer by the GPU.
+struct GLReadback {
+    handle: ReadbackHandle,
+    pbo: GLuint,
+    fence: GLsync,
+    len: usize,
+}
+
 #[derive(Debug, Clone)]
 struct GLSurfaceFBO {
     id: GLuint,
@@ -79,6 +98,18 @@ struct GLMesh {
     params: MeshParams,
 }
 
+#[derive(Debug, Copy, Clone)]
+struct GLInstanceBuffer {
+    vbo: GLuint,
+    params: InstanceBufferParams,
+}
+
+#[derive(Debug, Copy, Clone)]
+struct GLUniformBuffer {
+    ubo: GLuint,
+    params: UniformBufferParams,
+}
+
 #[derive(Debug, Copy, Clone)]
 struct GLTexture {
     id: GLuint,
@@ -105,7 +136,13 @@ struct GLVisitorMutInternal {
     binded_shader: Option<GLuint>,
     binded_texture_index: usize,
     binded_textures: [Option<GLuint>; MAX_UNIFORM_TEXTURE_SLOTS],
-    vaos: HashMap<(GLuint, GLuint), GLuint>,
+    binded_uniform_buffers: [Option<GLuint>; MAX_UNIFORM_BUFFER_SLOTS],
+    primitive_restart: Option<u32>,
+    attachment_color_blends: [Option<ColorBlend>; MAX_FRAMEBUFFER_ATTACHMENTS],
+    // The vertex array objects of shader, vertex buffer and instance buffer (or 0).
+    vaos: HashMap<(GLuint, GLuint, GLuint), GLuint>,
+    // The enabled vertex attributes if vertex array objects are not used.
+    enabled_attributes: Vec<GLuint>,
 }
 
 pub struct GLVisitor {
@@ -113,19 +150,58 @@ pub struct GLVisitor {
     surfaces: DataVec<GLSurface>,
     shaders: DataVec<GLShader>,
     meshes: DataVec<GLMesh>,
+    instance_buffers: DataVec<GLInstanceBuffer>,
+    uniform_buffers: DataVec<GLUniformBuffer>,
     textures: DataVec<GLTexture>,
     render_textures: DataVec<GLRenderTexture>,
     capabilities: Capabilities,
+    // The entry points of indexed blend functions if independent blend is supported.
+    indexed_blend: Option<IndexedBlend>,
+    // The timestamp queries, and the number of ones used in this frame.
+    queries: Vec<GLuint>,
+    used_queries: usize,
+    // The pending reads in submission order, and the ones read synchronously if pixel
+    // pack buffers are not supported.
+    readbacks: VecDeque<GLReadback>,
+    finished_readbacks: Vec<(ReadbackHandle, Vec<u8>)>,
+    // The pending fences in submission order, the index of latest inserted one and the
+    // index of latest signaled one.
+    fences: VecDeque<(u64, GLsync)>,
+    fence: u64,
+    signaled_fence: u64,
 }
 
 impl GLVisitor {
-    pub unsafe fn new(window: &Window) -> Result<Self> {
+    pub unsafe fn new(window: &Window, profile: VideoProfile) -> Result<Self> {
         gl::load_with(|symbol| window.get_proc_address(symbol) as *const _);
 
-        let capabilities = Capabilities::parse()?;
+        let capabilities = Capabilities::parse(profile)?;
         info!("GLVisitor {:#?}", capabilities);
         check_capabilities(&capabilities)?;
 
+        let indexed_blend = if capabilities.has_independent_blend() {
+            let api = if capabilities.version >= Version::GL(4, 0)
+                || capabilities.version >= Version::ES(3, 2)
+            {
+                IndexedBlendApi::Core
+            } else if capabilities.extensions.gl_oes_draw_buffers_indexed {
+                IndexedBlendApi::OES
+            } else {
+                IndexedBlendApi::ARB
+            };
+
+            IndexedBlend::load(api, |symbol| window.get_proc_address(symbol))
+        } else {
+            None
+        };
+
+        if capabilities.has_global_vertex_array_object() {
+            let mut vao = 0;
+            gl::GenVertexArrays(1, &mut vao);
+            gl::BindVertexArray(vao);
+            check()?;
+        }
+
         let mutables = GLVisitorMutInternal {
             render_state: RenderState::default(),
             scissor: SurfaceScissor::Disable,
@@ -142,7 +218,11 @@ impl GLVisitor {
             binded_shader: None,
             binded_texture_index: 0,
             binded_textures: [None; MAX_UNIFORM_TEXTURE_SLOTS],
+            binded_uniform_buffers: [None; MAX_UNIFORM_BUFFER_SLOTS],
+            primitive_restart: None,
+            attachment_color_blends: [None; MAX_FRAMEBUFFER_ATTACHMENTS],
             vaos: HashMap::new(),
+            enabled_attributes: Vec::new(),
         };
 
         let visitor = GLVisitor {
@@ -150,9 +230,19 @@ impl GLVisitor {
             surfaces: DataVec::new(),
             shaders: DataVec::new(),
             meshes: DataVec::new(),
+            instance_buffers: DataVec::new(),
+            uniform_buffers: DataVec::new(),
             textures: DataVec::new(),
             render_textures: DataVec::new(),
             capabilities: capabilities,
+            indexed_blend: indexed_blend,
+            queries: Vec::new(),
+            used_queries: 0,
+            readbacks: VecDeque::new(),
+            finished_readbacks: Vec::new(),
+            fences: VecDeque::new(),
+            fence: 0,
+            signaled_fence: 0,
         };
 
         visitor.reset_render_state()?;
@@ -161,6 +251,10 @@ impl GLVisitor {
 }
 
 impl Visitor for GLVisitor {
+    fn capabilities(&self) -> VideoCapabilities {
+        self.capabilities.summary()
+    }
+
     unsafe fn advance(&mut self) -> Result<()> {
         {
             let mut mutables = self.mutables.borrow_mut();
@@ -277,8 +371,17 @@ impl Visitor for GLVisitor {
         vs: &str,
         fs: &str,
     ) -> Result<()> {
-        let vs = self.compile(gl::VERTEX_SHADER, vs)?;
-        let fs = self.compile(gl::FRAGMENT_SHADER, fs)?;
+        let (vs, fs) = if self.capabilities.gles2 {
+            (
+                translate::to_glsl100(vs, ShaderStage::Vertex),
+                translate::to_glsl100(fs, ShaderStage::Fragment),
+            )
+        } else {
+            (vs.to_owned(), fs.to_owned())
+        };
+
+        let vs = self.compile(gl::VERTEX_SHADER, &vs)?;
+        let fs = self.compile(gl::FRAGMENT_SHADER, &fs)?;
         let id = self.link(vs, fs)?;
 
         gl::DetachShader(id, vs);
@@ -311,6 +414,28 @@ impl Visitor for GLVisitor {
             }
         }
 
+        if !shader.params.uniform_blocks.is_empty() && !self.capabilities.has_uniform_buffer() {
+            self.delete_shader_intern(id)?;
+            bail!("The OpenGL implementation does not supports uniform buffer objects.");
+        }
+
+        for &(ref name, slot) in &shader.params.uniform_blocks {
+            let c_name = ::std::ffi::CString::new(name.as_bytes()).unwrap();
+            let index = gl::GetUniformBlockIndex(id, c_name.as_ptr());
+            if index == gl::INVALID_INDEX {
+                self.delete_shader_intern(id)?;
+                bail!("Uniform block({:?}) is undefined in shader sources.", name);
+            }
+
+            gl::UniformBlockBinding(id, index, slot as GLuint);
+            check()?;
+        }
+
+        // Replaces the previous program, e.g. when the shader is reloaded.
+        if self.shaders.get(handle).is_some() {
+            self.delete_shader(handle)?;
+        }
+
         self.shaders.create(handle, shader);
         Ok(())
     }
@@ -324,7 +449,7 @@ impl Visitor for GLVisitor {
         self.mutables
             .borrow_mut()
             .vaos
-            .retain(|&(sid, _), _| sid != shader.id);
+            .retain(|&(sid, _, _), _| sid != shader.id);
 
         self.delete_shader_intern(shader.id)
     }
@@ -403,6 +528,11 @@ impl Visitor for GLVisitor {
 
         check()?;
 
+        // Replaces the previous texture, e.g. when the texture is reloaded.
+        if self.textures.get(handle).is_some() {
+            self.delete_texture(handle)?;
+        }
+
         self.textures.create(
             handle,
             GLTexture {
@@ -477,6 +607,79 @@ impl Visitor for GLVisitor {
         check()
     }
 
+    unsafe fn upload_texture(
+        &mut self,
+        handle: TextureHandle,
+        level: u32,
+        levels: u32,
+        area: math::Aabb2<u32>,
+        data: &[u8],
+    ) -> Result<()> {
+        let texture = *self.textures
+            .get(handle)
+            .ok_or_else(|| format_err!("{:?} is invalid.", handle))?;
+
+        let (internal_format, format, pixel_type) = texture.params.format.into();
+        let is_compression = texture.params.format.is_compression();
+        let dims = (
+            (texture.params.dimensions.x >> level).max(1) as GLsizei,
+            (texture.params.dimensions.y >> level).max(1) as GLsizei,
+        );
+
+        self.bind_texture(0, texture.id)?;
+
+        if !texture.allocated {
+            let (wrap, filter) = (texture.params.wrap, texture.params.filter);
+            self.update_texture_params(texture.id, wrap, filter, levels)?;
+
+            // The compressed levels are specified as a whole when they arrive.
+            if !is_compression {
+                for i in 0..levels {
+                    gl::TexImage2D(
+                        gl::TEXTURE_2D,
+                        i as GLint,
+                        internal_format as GLint,
+                        (texture.params.dimensions.x >> i).max(1) as GLsizei,
+                        (texture.params.dimensions.y >> i).max(1) as GLsizei,
+                        0,
+                        format,
+                        pixel_type,
+                        ::std::ptr::null(),
+                    );
+                }
+            }
+
+            self.textures.get_mut(handle).unwrap().allocated = true;
+        }
+
+        if is_compression {
+            gl::CompressedTexImage2D(
+                gl::TEXTURE_2D,
+                level as GLint,
+                internal_format,
+                dims.0,
+                dims.1,
+                0,
+                data.len() as GLint,
+                &data[0] as *const u8 as *const ::std::os::raw::c_void,
+            );
+        } else {
+            gl::TexSubImage2D(
+                gl::TEXTURE_2D,
+                level as GLint,
+                area.min.x as i32,
+                area.min.y as i32,
+                area.dim().x as i32,
+                area.dim().y as i32,
+                format,
+                pixel_type,
+                &data[0] as *const u8 as *const ::std::os::raw::c_void,
+            );
+        }
+
+        check()
+    }
+
     unsafe fn delete_texture(&mut self, handle: TextureHandle) -> Result<()> {
         let texture = self.textures
             .free(handle)
@@ -563,22 +766,31 @@ impl Visitor for GLVisitor {
         &mut self,
         handle: MeshHandle,
         params: MeshParams,
-        data: Option<MeshData>,
+        data: Option<(&[u8], &[u8])>,
     ) -> Result<()> {
+        if params.index_format == IndexFormat::U32 && !self.capabilities.has_u32_index() {
+            bail!("The OpenGL implementation does not supports 32-bit indices.");
+        }
+
         let vbo = self.create_buffer_intern(
             gl::ARRAY_BUFFER,
             params.hint,
             params.vertex_buffer_len(),
-            data.as_ref().map(|v| v.vptr.as_ref()),
+            data.map(|v| v.0),
         )?;
 
         let ibo = self.create_buffer_intern(
             gl::ELEMENT_ARRAY_BUFFER,
             params.hint,
             params.index_buffer_len(),
-            data.as_ref().map(|v| v.iptr.as_ref()),
+            data.map(|v| v.1),
         )?;
 
+        // Replaces the previous buffers, e.g. when the mesh is reloaded.
+        if self.meshes.get(handle).is_some() {
+            self.delete_mesh(handle)?;
+        }
+
         self.meshes.create(
             handle,
             GLMesh {
@@ -644,13 +856,179 @@ impl Visitor for GLVisitor {
         self.mutables
             .borrow_mut()
             .vaos
-            .retain(|&(_, vbo), _| vbo != mesh.vbo);
+            .retain(|&(_, vbo, _), _| vbo != mesh.vbo);
 
         self.delete_buffer_intern(gl::ARRAY_BUFFER, mesh.vbo)?;
         self.delete_buffer_intern(gl::ELEMENT_ARRAY_BUFFER, mesh.ibo)?;
         Ok(())
     }
 
+    unsafe fn create_instance_buffer(
+        &mut self,
+        handle: InstanceBufferHandle,
+        params: InstanceBufferParams,
+        data: Option<&[u8]>,
+    ) -> Result<()> {
+        if !self.capabilities.has_instancing() {
+            bail!("The OpenGL implementation does not supports instanced drawing.");
+        }
+
+        let vbo =
+            self.create_buffer_intern(gl::ARRAY_BUFFER, params.hint, params.buffer_len(), data)?;
+
+        // Replaces the previous buffer, e.g. when the buffer is resized.
+        if self.instance_buffers.get(handle).is_some() {
+            self.delete_instance_buffer(handle)?;
+        }
+
+        self.instance_buffers.create(
+            handle,
+            GLInstanceBuffer {
+                vbo: vbo,
+                params: params,
+            },
+        );
+
+        Ok(())
+    }
+
+    unsafe fn update_instance_buffer(
+        &mut self,
+        handle: InstanceBufferHandle,
+        offset: usize,
+        data: &[u8],
+    ) -> Result<()> {
+        let vbo = {
+            let buffer = self.instance_buffers
+                .get(handle)
+                .ok_or_else(|| format_err!("{:?} is invalid.", handle))?;
+
+            if buffer.params.hint == MeshHint::Immutable {
+                bail!("Trying to update immutable buffer");
+            }
+
+            buffer.vbo
+        };
+
+        self.update_buffer_intern(gl::ARRAY_BUFFER, vbo, offset, data)?;
+        Ok(())
+    }
+
+    unsafe fn delete_instance_buffer(&mut self, handle: InstanceBufferHandle) -> Result<()> {
+        let buffer = self.instance_buffers
+            .free(handle)
+            .ok_or_else(|| format_err!("{:?} is invalid.", handle))?;
+
+        // Removes deprecated `VertexArrayObject`s.
+        self.mutables
+            .borrow_mut()
+            .vaos
+            .retain(|&(_, _, ivbo), _| ivbo != buffer.vbo);
+
+        self.delete_buffer_intern(gl::ARRAY_BUFFER, buffer.vbo)
+    }
+
+    unsafe fn create_uniform_buffer(
+        &mut self,
+        handle: UniformBufferHandle,
+        params: UniformBufferParams,
+        data: Option<&[u8]>,
+    ) -> Result<()> {
+        if !self.capabilities.has_uniform_buffer() {
+            bail!("The OpenGL implementation does not supports uniform buffer objects.");
+        }
+
+        let ubo = self.create_buffer_intern(gl::UNIFORM_BUFFER, params.hint, params.size, data)?;
+
+        // Replaces the previous buffer, e.g. when the buffer is resized.
+        if self.uniform_buffers.get(handle).is_some() {
+            self.delete_uniform_buffer(handle)?;
+        }
+
+        self.uniform_buffers.create(
+            handle,
+            GLUniformBuffer {
+                ubo: ubo,
+                params: params,
+            },
+        );
+
+        Ok(())
+    }
+
+    unsafe fn update_uniform_buffer(
+        &mut self,
+        handle: UniformBufferHandle,
+        offset: usize,
+        data: &[u8],
+    ) -> Result<()> {
+        let ubo = {
+            let buffer = self.uniform_buffers
+                .get(handle)
+                .ok_or_else(|| format_err!("{:?} is invalid.", handle))?;
+
+            if buffer.params.hint == MeshHint::Immutable {
+                bail!("Trying to update immutable buffer");
+            }
+
+            if offset + data.len() > buffer.params.size {
+                bail!("Trying to update uniform buffer out of bounds.");
+            }
+
+            buffer.ubo
+        };
+
+        self.update_buffer_intern(gl::UNIFORM_BUFFER, ubo, offset, data)?;
+        Ok(())
+    }
+
+    unsafe fn delete_uniform_buffer(&mut self, handle: UniformBufferHandle) -> Result<()> {
+        let buffer = self.uniform_buffers
+            .free(handle)
+            .ok_or_else(|| format_err!("{:?} is invalid.", handle))?;
+
+        for v in self.mutables.borrow_mut().binded_uniform_buffers.iter_mut() {
+            if *v == Some(buffer.ubo) {
+                *v = None;
+            }
+        }
+
+        self.delete_buffer_intern(gl::UNIFORM_BUFFER, buffer.ubo)
+    }
+
+    unsafe fn bind_uniform_buffer(
+        &mut self,
+        slot: usize,
+        handle: Option<UniformBufferHandle>,
+    ) -> Result<()> {
+        if slot >= MAX_UNIFORM_BUFFER_SLOTS
+            || slot >= self.capabilities.max_indexed_uniform_buffer as usize
+        {
+            bail!("Uniform buffer slot {} is out of bounds.", slot);
+        }
+
+        let ubo = match handle {
+            Some(handle) => {
+                self.uniform_buffers
+                    .get(handle)
+                    .ok_or_else(|| format_err!("{:?} is invalid.", handle))?
+                    .ubo
+            }
+            None => 0,
+        };
+
+        let mut mutables = self.mutables.borrow_mut();
+        if mutables.binded_uniform_buffers[slot] == Some(ubo) {
+            return Ok(());
+        }
+
+        // `glBindBufferBase` also binds the buffer to the generic binding point.
+        gl::BindBufferBase(gl::UNIFORM_BUFFER, slot as GLuint, ubo);
+        mutables.binded_uniform_buffers[slot] = Some(ubo);
+        mutables.binded_buffers.insert(gl::UNIFORM_BUFFER, ubo);
+        check()
+    }
+
     unsafe fn bind(&mut self, id: SurfaceHandle, dimensions: math::Vector2<u32>) -> Result<()> {
         if self.mutables.borrow().binded_surface == Some(id) {
             return Ok(());
@@ -706,88 +1084,160 @@ impl Visitor for GLVisitor {
         self.set_viewport(vp)
     }
 
-    unsafe fn draw(
-        &mut self,
-        shader: ShaderHandle,
-        mesh: MeshHandle,
-        mesh_index: MeshIndex,
-        uniforms: &[UniformVar],
-    ) -> Result<u32> {
-        let mesh = {
-            // Bind program and associated uniforms and textures.
-            let shader = self.shaders
-                .get(shader)
-                .ok_or_else(|| format_err!("{:?} is invalid.", shader))?;
-            self.bind_shader(&shader)?;
+    unsafe fn read_pixels(&mut self, handle: ReadbackHandle, area: math::Aabb2<u32>) -> Result<()> {
+        let dim = area.dim();
+        let len = (dim.x * dim.y * 4) as usize;
+
+        if !self.capabilities.has_async_readback() {
+            // Reads synchronously, but the pixels are still delivered in later frames.
+            let mut bytes = vec![0u8; len];
+
+            // The rows of RGBA8 pixels are always aligned to 4 bytes.
+            gl::ReadPixels(
+                area.min.x as GLint,
+                area.min.y as GLint,
+                dim.x as GLsizei,
+                dim.y as GLsizei,
+                gl::RGBA,
+                gl::UNSIGNED_BYTE,
+                bytes.as_mut_ptr() as *mut GLvoid,
+            );
 
-            let mut index = 0usize;
-            for &(field, variable) in uniforms {
-                let location = shader.hash_uniform_location(field).unwrap();
-                match variable {
-                    UniformVariable::Texture(handle) => {
-                        if let Some(texture) = self.textures.get(handle) {
-                            let v = UniformVariable::I32(index as i32);
-                            self.bind_uniform_variable(location, &v)?;
-                            self.bind_texture(index, texture.id)?;
-                            index += 1;
-                        }
-                    }
-                    UniformVariable::RenderTexture(handle) => {
-                        if let Some(texture) = self.render_textures.get(handle) {
-                            if !texture.params.sampler {
-                                bail!("The render buffer does not have a sampler.");
-                            }
+            check()?;
+            self.finished_readbacks.push((handle, bytes));
+            return Ok(());
+        }
 
-                            let v = UniformVariable::I32(index as i32);
-                            self.bind_uniform_variable(location, &v)?;
-                            self.bind_texture(index, texture.id)?;
-                            index += 1;
-                        }
-                    }
-                    _ => {
-                        self.bind_uniform_variable(location, &variable)?;
-                    }
-                }
-            }
+        let mut pbo = 0;
+        gl::GenBuffers(1, &mut pbo);
+        assert!(pbo != 0);
+
+        // The pixels are copied into the buffer by the GPU, and the fence is signaled
+        // once the copy is finished.
+        gl::BindBuffer(gl::PIXEL_PACK_BUFFER, pbo);
+        gl::BufferData(
+            gl::PIXEL_PACK_BUFFER,
+            len as GLsizeiptr,
+            ptr::null(),
+            gl::STREAM_READ,
+        );
 
-            // Bind vertex buffer and vertex array object.
-            let mesh = self.meshes
-                .get(mesh)
-                .ok_or_else(|| format_err!("{:?} is invalid.", mesh))?;
+        gl::ReadPixels(
+            area.min.x as GLint,
+            area.min.y as GLint,
+            dim.x as GLsizei,
+            dim.y as GLsizei,
+            gl::RGBA,
+            gl::UNSIGNED_BYTE,
+            ptr::null_mut(),
+        );
 
-            self.bind_buffer(gl::ARRAY_BUFFER, mesh.vbo)?;
-            self.bind_vao(&shader, &mesh)?;
-            mesh
-        };
+        gl::BindBuffer(gl::PIXEL_PACK_BUFFER, 0);
+        let fence = gl::FenceSync(gl::SYNC_GPU_COMMANDS_COMPLETE, 0);
 
-        // Bind index buffer object if available.
-        self.bind_buffer(gl::ELEMENT_ARRAY_BUFFER, mesh.ibo)?;
+        self.readbacks.push_back(GLReadback {
+            handle: handle,
+            pbo: pbo,
+            fence: fence,
+            len: len,
+        });
 
-        let (from, len) = match mesh_index {
-            MeshIndex::Ptr(from, len) => {
-                if (from + len) > mesh.params.num_idxes {
-                    bail!("MeshIndex is out of bounds");
+        check()
+    }
+
+    unsafe fn readbacks(&mut self) -> Result<Vec<(ReadbackHandle, Vec<u8>)>> {
+        let mut finished = mem::replace(&mut self.finished_readbacks, Vec::new());
+
+        // The fences are signaled in submission order, so stops at the first pending one.
+        while let Some(status) = self
+            .readbacks
+            .front()
+            .map(|v| gl::ClientWaitSync(v.fence, 0, 0))
+        {
+            match status {
+                gl::ALREADY_SIGNALED | gl::CONDITION_SATISFIED => {}
+                gl::TIMEOUT_EXPIRED => break,
+                _ => {
+                    check()?;
+                    bail!("[GL] Failed to wait for the fence of readback.");
                 }
+            }
+
+            let v = self.readbacks.pop_front().unwrap();
+            gl::DeleteSync(v.fence);
 
-                ((from * mesh.params.index_format.stride()), len)
+            gl::BindBuffer(gl::PIXEL_PACK_BUFFER, v.pbo);
+            let src = gl::MapBufferRange(
+                gl::PIXEL_PACK_BUFFER,
+                0,
+                v.len as GLsizeiptr,
+                gl::MAP_READ_BIT,
+            );
+
+            let mapped = !src.is_null();
+            if mapped {
+                let mut bytes = vec![0u8; v.len];
+                ptr::copy_nonoverlapping(src as *const u8, bytes.as_mut_ptr(), v.len);
+                gl::UnmapBuffer(gl::PIXEL_PACK_BUFFER);
+                finished.push((v.handle, bytes));
             }
-            MeshIndex::SubMesh(index) => {
-                let num = mesh.params.sub_mesh_offsets.len();
-                let from = mesh.params
-                    .sub_mesh_offsets
-                    .get(index)
-                    .ok_or_else(|| format_err!("MeshIndex is out of bounds"))?;
-
-                let to = if index == (num - 1) {
-                    mesh.params.num_idxes
-                } else {
-                    mesh.params.sub_mesh_offsets[index + 1]
-                };
 
-                ((from * mesh.params.index_format.stride()), (to - from))
+            gl::BindBuffer(gl::PIXEL_PACK_BUFFER, 0);
+            gl::DeleteBuffers(1, &v.pbo);
+            check()?;
+
+            if !mapped {
+                bail!("[GL] Failed to map the pixels of {:?}.", v.handle);
             }
-            MeshIndex::All => (0, mesh.params.num_idxes),
-        };
+        }
+
+        Ok(finished)
+    }
+
+    unsafe fn query_timestamp(&mut self) -> Result<()> {
+        if !self.capabilities.has_timer_query() {
+            return Ok(());
+        }
+
+        if self.used_queries == self.queries.len() {
+            let mut id = 0;
+            gl::GenQueries(1, &mut id);
+            assert!(id != 0);
+            self.queries.push(id);
+        }
+
+        gl::QueryCounter(self.queries[self.used_queries], gl::TIMESTAMP);
+        self.used_queries += 1;
+        check()
+    }
+
+    unsafe fn timestamps(&mut self) -> Result<Vec<u64>> {
+        // Waits for the GPU to reach the last timestamp, which only happens when the
+        // GPU timings are enabled.
+        let mut stamps = Vec::with_capacity(self.used_queries);
+        for &id in &self.queries[..self.used_queries] {
+            let mut v: GLuint64 = 0;
+            gl::GetQueryObjectui64v(id, gl::QUERY_RESULT, &mut v);
+            stamps.push(v);
+        }
+
+        self.used_queries = 0;
+        check()?;
+        Ok(stamps)
+    }
+
+    unsafe fn draw(
+        &mut self,
+        shader: ShaderHandle,
+        mesh: MeshHandle,
+        mesh_index: MeshIndex,
+        uniforms: &[UniformVar],
+    ) -> Result<u32> {
+        let mesh = self.bind_drawcall(shader, mesh, None, uniforms)?;
+        let (from, len) = mesh.params
+            .index_range(mesh_index)
+            .ok_or_else(|| format_err!("MeshIndex is out of bounds"))?;
+        let from = from * mesh.params.index_format.stride();
 
         gl::DrawElements(
             mesh.params.primitive.into(),
@@ -800,8 +1250,93 @@ impl Visitor for GLVisitor {
         Ok(mesh.params.primitive.assemble(len as u32))
     }
 
+    unsafe fn draw_instanced(
+        &mut self,
+        shader: ShaderHandle,
+        mesh: MeshHandle,
+        mesh_index: MeshIndex,
+        buffer: InstanceBufferHandle,
+        instances: u32,
+        uniforms: &[UniformVar],
+    ) -> Result<u32> {
+        {
+            let buffer = self.instance_buffers
+                .get(buffer)
+                .ok_or_else(|| format_err!("{:?} is invalid.", buffer))?;
+
+            if instances as usize > buffer.params.max_instances() {
+                bail!("Trying to draw instances out of bounds.");
+            }
+        }
+
+        let mesh = self.bind_drawcall(shader, mesh, Some(buffer), uniforms)?;
+        let (from, len) = mesh.params
+            .index_range(mesh_index)
+            .ok_or_else(|| format_err!("MeshIndex is out of bounds"))?;
+        let from = from * mesh.params.index_format.stride();
+
+        gl::DrawElementsInstanced(
+            mesh.params.primitive.into(),
+            len as i32,
+            mesh.params.index_format.into(),
+            from as *const u32 as *const ::std::os::raw::c_void,
+            instances as i32,
+        );
+
+        check()?;
+        Ok(mesh.params.primitive.assemble(len as u32) * instances)
+    }
+
+    unsafe fn insert_fence(&mut self) -> Result<u64> {
+        self.fence += 1;
+        if self.capabilities.has_sync() {
+            let sync = gl::FenceSync(gl::SYNC_GPU_COMMANDS_COMPLETE, 0);
+            self.fences.push_back((self.fence, sync));
+        }
+
+        check()?;
+        Ok(self.fence)
+    }
+
+    unsafe fn signaled_fence(&mut self) -> Result<u64> {
+        if !self.capabilities.has_sync() {
+            return Ok(self.fence.saturating_sub(FRAMES_IN_FLIGHT));
+        }
+
+        // The fences are signaled in submission order, so stops at the first pending one.
+        while let Some(status) = self.fences.front().map(|v| gl::ClientWaitSync(v.1, 0, 0)) {
+            match status {
+                gl::ALREADY_SIGNALED | gl::CONDITION_SATISFIED => {}
+                gl::TIMEOUT_EXPIRED => break,
+                _ => {
+                    check()?;
+                    bail!("[GL] Failed to wait for the fence of frame.");
+                }
+            }
+
+            let (fence, sync) = self.fences.pop_front().unwrap();
+            gl::DeleteSync(sync);
+            self.signaled_fence = fence;
+        }
+
+        Ok(self.signaled_fence)
+    }
+
     unsafe fn flush(&mut self) -> Result<()> {
+        // Only flushes the commands instead of waiting for them, the objects used by
+        // the frames in flight are kept alive by `DeletionQueue`.
+        gl::Flush();
+        check()
+    }
+
+    unsafe fn finish(&mut self) -> Result<()> {
         gl::Finish();
+
+        for (_, sync) in self.fences.drain(..) {
+            gl::DeleteSync(sync);
+        }
+
+        self.signaled_fence = self.fence;
         check()
     }
 }
@@ -862,9 +1397,22 @@ impl GLVisitor {
         let rs = shader.params.state;
         self.set_cull_face(rs.cull_face)?;
         self.set_front_face_order(rs.front_face_order)?;
+        self.set_polygon_mode(rs.polygon_mode)?;
         self.set_depth_test(rs.depth_write, rs.depth_test)?;
         self.set_depth_write_offset(rs.depth_write_offset)?;
-        self.set_color_blend(rs.color_blend)?;
+        self.set_depth_clamp(rs.depth_clamp)?;
+
+        if let Some(ref indexed) = self.indexed_blend {
+            let mut blends = [None; MAX_FRAMEBUFFER_ATTACHMENTS];
+            for (i, v) in blends.iter_mut().enumerate() {
+                *v = rs.attachment_color_blend(i);
+            }
+
+            self.set_attachment_color_blends(indexed, blends)?;
+        } else {
+            self.set_color_blend(rs.attachment_color_blend(0))?;
+        }
+
         self.set_color_write(rs.color_write)?;
 
         self.mutables.borrow_mut().binded_shader = Some(shader.id);
@@ -942,12 +1490,104 @@ impl GLVisitor {
         check()
     }
 
-    unsafe fn bind_vao(&self, shader: &GLShader, mesh: &GLMesh) -> Result<()> {
+    // Binds the program, uniform variables and buffers of drawcall, and returns the mesh.
+    unsafe fn bind_drawcall(
+        &self,
+        shader: ShaderHandle,
+        mesh: MeshHandle,
+        buffer: Option<InstanceBufferHandle>,
+        uniforms: &[UniformVar],
+    ) -> Result<&GLMesh> {
+        let mesh = {
+            // Bind program and associated uniforms and textures.
+            let shader = self.shaders
+                .get(shader)
+                .ok_or_else(|| format_err!("{:?} is invalid.", shader))?;
+            self.bind_shader(&shader)?;
+
+            let mut index = 0usize;
+            for &(field, variable) in uniforms {
+                let location = shader
+                    .hash_uniform_location(field)
+                    .ok_or_else(|| format_err!("Uniform({}) is undefined.", field))?;
+                match variable {
+                    UniformVariable::Texture(handle) => {
+                        if let Some(texture) = self.textures.get(handle) {
+                            let v = UniformVariable::I32(index as i32);
+                            self.bind_uniform_variable(location, &v)?;
+                            self.bind_texture(index, texture.id)?;
+                            index += 1;
+                        }
+                    }
+                    UniformVariable::RenderTexture(handle) => {
+                        if let Some(texture) = self.render_textures.get(handle) {
+                            if !texture.params.sampler {
+                                bail!("The render buffer does not have a sampler.");
+                            }
+
+                            let v = UniformVariable::I32(index as i32);
+                            self.bind_uniform_variable(location, &v)?;
+                            self.bind_texture(index, texture.id)?;
+                            index += 1;
+                        }
+                    }
+                    _ => {
+                        self.bind_uniform_variable(location, &variable)?;
+                    }
+                }
+            }
+
+            // Bind vertex buffer and vertex array object.
+            let mesh = self.meshes
+                .get(mesh)
+                .ok_or_else(|| format_err!("{:?} is invalid.", mesh))?;
+
+            let buffer = match buffer {
+                Some(v) => {
+                    let buffer = self.instance_buffers
+                        .get(v)
+                        .ok_or_else(|| format_err!("{:?} is invalid.", v))?;
+                    Some(buffer)
+                }
+                None => None,
+            };
+
+            self.bind_buffer(gl::ARRAY_BUFFER, mesh.vbo)?;
+            self.bind_vao(&shader, &mesh, buffer)?;
+            mesh
+        };
+
+        // Bind index buffer object if available.
+        self.bind_buffer(gl::ELEMENT_ARRAY_BUFFER, mesh.ibo)?;
+        self.set_primitive_restart(mesh.params.primitive_restart_index())?;
+        Ok(mesh)
+    }
+
+    unsafe fn bind_vao(
+        &self,
+        shader: &GLShader,
+        mesh: &GLMesh,
+        buffer: Option<&GLInstanceBuffer>,
+    ) -> Result<()> {
         let mut mutables = self.mutables.borrow_mut();
         assert!(mutables.binded_shader == Some(shader.id));
         assert!(*mutables.binded_buffers.get(&gl::ARRAY_BUFFER).unwrap() == mesh.vbo);
 
-        if let Some(vao) = mutables.vaos.get(&(shader.id, mesh.vbo)).cloned() {
+        // The attributes are bound for every drawcall without vertex array objects.
+        if !self.capabilities.has_vertex_array_object() {
+            let locations = self.bind_attributes(shader, mesh, buffer)?;
+            for &v in &mutables.enabled_attributes {
+                if !locations.contains(&v) {
+                    gl::DisableVertexAttribArray(v);
+                }
+            }
+
+            mutables.enabled_attributes = locations;
+            return check();
+        }
+
+        let key = (shader.id, mesh.vbo, buffer.map(|v| v.vbo).unwrap_or(0));
+        if let Some(vao) = mutables.vaos.get(&key).cloned() {
             if mutables.binded_vao == Some(vao) {
                 return Ok(());
             }
@@ -962,42 +1602,79 @@ impl GLVisitor {
         gl::BindVertexArray(vao);
         mutables.binded_vao = Some(vao);
 
+        self.bind_attributes(shader, mesh, buffer)?;
+        check()?;
+
+        mutables.vaos.insert(key, vao);
+        Ok(())
+    }
+
+    // Points the attributes of shader to the vertex buffer, or the instance buffer if they
+    // could not be found in the mesh, and returns their locations.
+    unsafe fn bind_attributes(
+        &self,
+        shader: &GLShader,
+        mesh: &GLMesh,
+        buffer: Option<&GLInstanceBuffer>,
+    ) -> Result<Vec<GLuint>> {
+        let mut locations = Vec::new();
         for (name, size) in shader.params.attributes.iter() {
-            if let Some(element) = mesh.params.layout.element(name) {
-                if element.size < size {
-                    bail!(
-                        "Vertex buffer has incompatible attribute `{:?}` [{:?} - {:?}].",
-                        name,
-                        element.size,
-                        size
-                    );
+            let (vbo, layout, divisor) = match buffer {
+                _ if mesh.params.layout.element(name).is_some() => {
+                    (mesh.vbo, &mesh.params.layout, 0)
                 }
-
-                let offset = mesh.params.layout.offset(name).unwrap();
-                let stride = mesh.params.layout.stride();
-
-                let location = shader.attribute_location(name.into())?;
-                gl::EnableVertexAttribArray(location as GLuint);
-                gl::VertexAttribPointer(
-                    location as GLuint,
-                    GLsizei::from(element.size),
-                    element.format.into(),
-                    element.normalized as u8,
-                    GLsizei::from(stride),
-                    offset as *const u8 as *const ::std::os::raw::c_void,
-                );
-            } else {
-                bail!(
+                Some(v) if v.params.layout.element(name).is_some() => {
+                    (v.vbo, &v.params.layout, v.params.divisor)
+                }
+                _ => bail!(
                     "Can't find attribute {:?} description in vertex buffer.",
                     name
+                ),
+            };
+
+            let element = layout.element(name).unwrap();
+            if element.size < size {
+                bail!(
+                    "Vertex buffer has incompatible attribute `{:?}` [{:?} - {:?}].",
+                    name,
+                    element.size,
+                    size
                 );
             }
+
+            let offset = layout.offset(name).unwrap();
+            let stride = layout.stride();
+
+            let location = shader.attribute_location(name.into())? as GLuint;
+            if buffer.is_some() {
+                gl::BindBuffer(gl::ARRAY_BUFFER, vbo);
+            }
+
+            gl::EnableVertexAttribArray(location);
+            gl::VertexAttribPointer(
+                location,
+                GLsizei::from(element.size),
+                element.format.into(),
+                element.normalized as u8,
+                GLsizei::from(stride),
+                offset as *const u8 as *const ::std::os::raw::c_void,
+            );
+
+            // Without VAOs the divisors are global states, so the ones left by previous
+            // instanced drawcalls must be reset for the attributes of mesh.
+            if self.capabilities.has_instancing() {
+                gl::VertexAttribDivisor(location, divisor);
+            }
+
+            locations.push(location);
         }
 
-        check()?;
+        // Restores the vertex buffer of mesh, which is tracked as the binded one.
+        if buffer.is_some() {
+            gl::BindBuffer(gl::ARRAY_BUFFER, mesh.vbo);
+        }
 
-        mutables.vaos.insert((shader.id, mesh.vbo), vao);
-        Ok(())
+        Ok(locations)
     }
 }
 
@@ -1011,16 +1688,30 @@ impl GLVisitor {
         gl::FrontFace(gl::CCW);
         mutables.render_state.front_face_order = FrontFaceOrder::CounterClockwise;
 
+        if self.capabilities.has_polygon_mode() {
+            gl::PolygonMode(gl::FRONT_AND_BACK, gl::FILL);
+        }
+        mutables.render_state.polygon_mode = PolygonMode::Fill;
+
         gl::Disable(gl::DEPTH_TEST);
         gl::DepthMask(gl::FALSE);
         mutables.render_state.depth_write = false;
         gl::DepthFunc(gl::ALWAYS);
         mutables.render_state.depth_test = Comparison::Always;
         gl::Disable(gl::POLYGON_OFFSET_FILL);
+        if self.capabilities.has_polygon_mode() {
+            gl::Disable(gl::POLYGON_OFFSET_LINE);
+        }
         mutables.render_state.depth_write_offset = None;
 
+        if self.capabilities.has_depth_clamp() {
+            gl::Disable(gl::DEPTH_CLAMP);
+        }
+        mutables.render_state.depth_clamp = false;
+
         gl::Disable(gl::BLEND);
         mutables.render_state.color_blend = None;
+        mutables.attachment_color_blends = [None; MAX_FRAMEBUFFER_ATTACHMENTS];
 
         gl::ColorMask(1, 1, 1, 1);
         mutables.render_state.color_write = (true, true, true, true);
@@ -1028,6 +1719,13 @@ impl GLVisitor {
         gl::Disable(gl::SCISSOR_TEST);
         mutables.scissor = SurfaceScissor::Disable;
 
+        if self.capabilities.has_fixed_primitive_restart() {
+            gl::Disable(gl::PRIMITIVE_RESTART_FIXED_INDEX);
+        } else if self.capabilities.has_primitive_restart() {
+            gl::Disable(gl::PRIMITIVE_RESTART);
+        }
+        mutables.primitive_restart = None;
+
         gl::PixelStorei(gl::UNPACK_ALIGNMENT, 1);
         gl::BindFramebuffer(gl::FRAMEBUFFER, 0);
 
@@ -1074,6 +1772,29 @@ impl GLVisitor {
         Ok(())
     }
 
+    /// Select the rasterization mode of polygons.
+    unsafe fn set_polygon_mode(&self, mode: PolygonMode) -> Result<()> {
+        let state = &mut self.mutables.borrow_mut().render_state;
+
+        if state.polygon_mode != mode {
+            if self.capabilities.has_polygon_mode() {
+                gl::PolygonMode(
+                    gl::FRONT_AND_BACK,
+                    match mode {
+                        PolygonMode::Fill => gl::FILL,
+                        PolygonMode::Line => gl::LINE,
+                        PolygonMode::Point => gl::POINT,
+                    },
+                );
+            }
+
+            state.polygon_mode = mode;
+            check()?;
+        }
+
+        Ok(())
+    }
+
     /// Enable or disable writing into the depth buffer and specify the value used for depth
     /// buffer comparisons.
     unsafe fn set_depth_test(&self, write: bool, comparsion: Comparison) -> Result<()> {
@@ -1114,12 +1835,19 @@ impl GLVisitor {
         let state = &mut self.mutables.borrow_mut().render_state;
 
         if state.depth_write_offset != offset {
-            if let Some(v) = offset {
-                if v.0 != 0.0 || v.1 != 0.0 {
+            match offset {
+                Some(v) if v.0 != 0.0 || v.1 != 0.0 => {
                     gl::Enable(gl::POLYGON_OFFSET_FILL);
+                    if self.capabilities.has_polygon_mode() {
+                        gl::Enable(gl::POLYGON_OFFSET_LINE);
+                    }
                     gl::PolygonOffset(v.0, v.1);
-                } else {
+                }
+                _ => {
                     gl::Disable(gl::POLYGON_OFFSET_FILL);
+                    if self.capabilities.has_polygon_mode() {
+                        gl::Disable(gl::POLYGON_OFFSET_LINE);
+                    }
                 }
             }
 
@@ -1130,11 +1858,60 @@ impl GLVisitor {
         Ok(())
     }
 
-    // Specifies how source and destination are combined.
-    unsafe fn set_color_blend(
+    /// Enable or disable clamping the depth values of fragments.
+    unsafe fn set_depth_clamp(&self, clamp: bool) -> Result<()> {
+        let state = &mut self.mutables.borrow_mut().render_state;
+
+        if state.depth_clamp != clamp {
+            if self.capabilities.has_depth_clamp() {
+                if clamp {
+                    gl::Enable(gl::DEPTH_CLAMP);
+                } else {
+                    gl::Disable(gl::DEPTH_CLAMP);
+                }
+            }
+
+            state.depth_clamp = clamp;
+            check()?;
+        }
+
+        Ok(())
+    }
+
+    // Specifies how source and destination are combined for each color attachment.
+    unsafe fn set_attachment_color_blends(
         &self,
-        blend: Option<(Equation, BlendFactor, BlendFactor)>,
+        indexed: &IndexedBlend,
+        blends: [Option<ColorBlend>; MAX_FRAMEBUFFER_ATTACHMENTS],
     ) -> Result<()> {
+        let mut mutables = self.mutables.borrow_mut();
+
+        for (i, &blend) in blends.iter().enumerate() {
+            if mutables.attachment_color_blends[i] == blend {
+                continue;
+            }
+
+            let index = i as GLuint;
+            if let Some((equation, src, dst)) = blend {
+                if mutables.attachment_color_blends[i] == None {
+                    indexed.enable(index);
+                }
+
+                indexed.blend_func(index, src.into(), dst.into());
+                indexed.blend_equation(index, equation.into());
+            } else {
+                indexed.disable(index);
+            }
+
+            mutables.attachment_color_blends[i] = blend;
+            check()?;
+        }
+
+        Ok(())
+    }
+
+    // Specifies how source and destination are combined.
+    unsafe fn set_color_blend(&self, blend: Option<ColorBlend>) -> Result<()> {
         let state = &mut self.mutables.borrow_mut().render_state;
 
         if state.color_blend != blend {
@@ -1169,6 +1946,37 @@ impl GLVisitor {
         Ok(())
     }
 
+    /// Enable or disable restarting the primitive assembly of strips when `index`
+    /// appears in the element array.
+    unsafe fn set_primitive_restart(&self, index: Option<u32>) -> Result<()> {
+        let mut mutables = self.mutables.borrow_mut();
+
+        if mutables.primitive_restart != index {
+            if self.capabilities.has_fixed_primitive_restart() {
+                // The restart index is always the maximum value of index type.
+                if index.is_some() {
+                    gl::Enable(gl::PRIMITIVE_RESTART_FIXED_INDEX);
+                } else {
+                    gl::Disable(gl::PRIMITIVE_RESTART_FIXED_INDEX);
+                }
+            } else if self.capabilities.has_primitive_restart() {
+                if let Some(v) = index {
+                    gl::Enable(gl::PRIMITIVE_RESTART);
+                    gl::PrimitiveRestartIndex(v);
+                } else {
+                    gl::Disable(gl::PRIMITIVE_RESTART);
+                }
+            } else {
+                return Ok(());
+            }
+
+            mutables.primitive_restart = index;
+            check()?;
+        }
+
+        Ok(())
+    }
+
     /// Set the scissor box relative to the top-lef corner of th window, in pixels.
     unsafe fn set_scissor(&self, scissor: SurfaceScissor) -> Result<()> {
         let mut mutables = self.mutables.borrow_mut();
@@ -1520,6 +2328,16 @@ unsafe fn check_capabilities(caps: &Capabilities) -> Result<()> {
         bail!("The OpenGL implementation does not supports blitting framebuffer.");
     }
 
+    // The GLES 2.0 profile uses neither uniform buffers nor vertex array objects, but
+    // the shaders are translated into GLSL ES 1.00.
+    if caps.gles2 {
+        if !caps.has_glsl100() {
+            bail!("The OpenGL implementation does not accept GLSL ES 1.00 shaders, which are required by the GLES 2.0 profile.");
+        }
+
+        return Ok(());
+    }
+
     if caps.version < Version::GL(3, 1)
         && caps.version < Version::ES(3, 0)
         && !caps.extensions.gl_arb_uniform_buffer_object
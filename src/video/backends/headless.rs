@@ -1,107 +1,374 @@
+//! The backend which runs without any OpenGL context, e.g. on the CI machines or
+//! servers without GPU or display.
+//!
+//! Nothing is rasterized, but the objects are tracked and validated the same way as
+//! the OpenGL backend does, so the misuses of video APIs are still reported, and the
+//! frame statistics like the number of drawcalls and triangles stay meaningful.
+
 use super::super::assets::prelude::*;
+use super::super::capabilities::{VideoCapabilities, VideoProfile};
+use super::super::readback::ReadbackHandle;
+use super::super::MAX_UNIFORM_BUFFER_SLOTS;
+use super::gl::types::DataVec;
 use super::{UniformVar, Visitor};
 
 use errors::*;
 use math;
 
-pub struct HeadlessVisitor {}
+pub struct HeadlessVisitor {
+    profile: VideoProfile,
+    surfaces: DataVec<SurfaceParams>,
+    shaders: DataVec<ShaderParams>,
+    meshes: DataVec<MeshParams>,
+    instance_buffers: DataVec<InstanceBufferParams>,
+    uniform_buffers: DataVec<UniformBufferParams>,
+    uniform_slots: [Option<UniformBufferHandle>; MAX_UNIFORM_BUFFER_SLOTS],
+    textures: DataVec<TextureParams>,
+    render_textures: DataVec<RenderTextureParams>,
+    readbacks: Vec<(ReadbackHandle, Vec<u8>)>,
+    fence: u64,
+}
 
 impl HeadlessVisitor {
     pub fn new() -> Self {
-        HeadlessVisitor {}
+        Self::with_profile(VideoProfile::Standard)
+    }
+
+    /// Creates a headless backend which reports the capabilities of `profile`, so the
+    /// fallbacks of GLES 2.0 devices could be checked without any context.
+    pub fn with_profile(profile: VideoProfile) -> Self {
+        HeadlessVisitor {
+            profile: profile,
+            surfaces: DataVec::new(),
+            shaders: DataVec::new(),
+            meshes: DataVec::new(),
+            instance_buffers: DataVec::new(),
+            uniform_buffers: DataVec::new(),
+            uniform_slots: [None; MAX_UNIFORM_BUFFER_SLOTS],
+            textures: DataVec::new(),
+            render_textures: DataVec::new(),
+            readbacks: Vec::new(),
+            fence: 0,
+        }
     }
 }
 
 impl Visitor for HeadlessVisitor {
-    unsafe fn create_surface(&mut self, _: SurfaceHandle, _: SurfaceParams) -> Result<()> {
+    fn capabilities(&self) -> VideoCapabilities {
+        let standard = self.profile == VideoProfile::Standard;
+
+        let mut capabilities = VideoCapabilities::default();
+        capabilities.profile = self.profile;
+        capabilities.uniform_buffer = standard;
+        capabilities.instancing = standard;
+        capabilities.half_float_texture = standard;
+        capabilities.float_texture = standard;
+        capabilities
+    }
+
+    unsafe fn create_surface(
+        &mut self,
+        handle: SurfaceHandle,
+        params: SurfaceParams,
+    ) -> Result<()> {
+        let mut dimensions = None;
+        let colors = params.colors.iter().filter_map(|v| v.map(|v| (v, true)));
+        for (v, color) in colors.chain(params.depth_stencil.map(|v| (v, false))) {
+            let rt = self
+                .render_textures
+                .get(v)
+                .ok_or_else(|| format_err!("RenderTexture handle {:?} is invalid.", v))?;
+
+            if rt.format.is_color() != color {
+                bail!(
+                    "Incompitable(mismatch format) attachments of SurfaceObject {:?}",
+                    handle
+                );
+            }
+
+            if dimensions.is_some() && dimensions != Some(rt.dimensions) {
+                bail!(
+                    "Incompitable(mismatch dimensions) attachments of SurfaceObject {:?}",
+                    handle
+                );
+            }
+
+            dimensions = Some(rt.dimensions);
+        }
+
+        self.surfaces.create(handle, params);
         Ok(())
     }
 
-    unsafe fn delete_surface(&mut self, _: SurfaceHandle) -> Result<()> {
+    unsafe fn delete_surface(&mut self, handle: SurfaceHandle) -> Result<()> {
+        self.surfaces
+            .free(handle)
+            .ok_or_else(|| format_err!("{:?} is invalid.", handle))?;
         Ok(())
     }
 
     unsafe fn create_shader(
         &mut self,
-        _: ShaderHandle,
-        _: ShaderParams,
+        handle: ShaderHandle,
+        params: ShaderParams,
         _: &str,
         _: &str,
     ) -> Result<()> {
+        self.shaders.create(handle, params);
         Ok(())
     }
 
-    unsafe fn delete_shader(&mut self, _: ShaderHandle) -> Result<()> {
+    unsafe fn delete_shader(&mut self, handle: ShaderHandle) -> Result<()> {
+        self.shaders
+            .free(handle)
+            .ok_or_else(|| format_err!("{:?} is invalid.", handle))?;
         Ok(())
     }
 
     unsafe fn create_texture(
         &mut self,
-        _: TextureHandle,
-        _: TextureParams,
+        handle: TextureHandle,
+        params: TextureParams,
         _: Option<TextureData>,
     ) -> Result<()> {
+        self.textures.create(handle, params);
         Ok(())
     }
 
     unsafe fn update_texture(
         &mut self,
-        _: TextureHandle,
+        handle: TextureHandle,
+        area: math::Aabb2<u32>,
+        data: &[u8],
+    ) -> Result<()> {
+        let params = self
+            .textures
+            .get(handle)
+            .ok_or_else(|| format_err!("{:?} is invalid.", handle))?;
+
+        if params.hint == TextureHint::Immutable {
+            bail!("Trying to update immutable texture.");
+        }
+
+        if params.format.is_compression() {
+            bail!("Trying to update compressed texture.");
+        }
+
+        if data.len() > area.volume() as usize
+            || area.min.x >= params.dimensions.x
+            || area.min.y >= params.dimensions.y
+        {
+            bail!("Trying to update texture data out of bounds.");
+        }
+
+        Ok(())
+    }
+
+    unsafe fn upload_texture(
+        &mut self,
+        handle: TextureHandle,
+        _: u32,
+        _: u32,
         _: math::Aabb2<u32>,
         _: &[u8],
     ) -> Result<()> {
+        self.textures
+            .get(handle)
+            .ok_or_else(|| format_err!("{:?} is invalid.", handle))?;
         Ok(())
     }
 
-    unsafe fn delete_texture(&mut self, _: TextureHandle) -> Result<()> {
+    unsafe fn delete_texture(&mut self, handle: TextureHandle) -> Result<()> {
+        self.textures
+            .free(handle)
+            .ok_or_else(|| format_err!("{:?} is invalid.", handle))?;
         Ok(())
     }
 
     unsafe fn create_render_texture(
         &mut self,
-        _: RenderTextureHandle,
-        _: RenderTextureParams,
+        handle: RenderTextureHandle,
+        params: RenderTextureParams,
     ) -> Result<()> {
+        self.render_textures.create(handle, params);
         Ok(())
     }
 
-    unsafe fn delete_render_texture(&mut self, _: RenderTextureHandle) -> Result<()> {
+    unsafe fn delete_render_texture(&mut self, handle: RenderTextureHandle) -> Result<()> {
+        self.render_textures
+            .free(handle)
+            .ok_or_else(|| format_err!("{:?} is invalid.", handle))?;
         Ok(())
     }
 
     unsafe fn create_mesh(
         &mut self,
-        _: MeshHandle,
-        _: MeshParams,
-        _: Option<MeshData>,
+        handle: MeshHandle,
+        params: MeshParams,
+        _: Option<(&[u8], &[u8])>,
+    ) -> Result<()> {
+        self.meshes.create(handle, params);
+        Ok(())
+    }
+
+    unsafe fn update_vertex_buffer(
+        &mut self,
+        handle: MeshHandle,
+        _: usize,
+        _: &[u8],
     ) -> Result<()> {
+        self.update_buffer(handle)
+    }
+
+    unsafe fn update_index_buffer(&mut self, handle: MeshHandle, _: usize, _: &[u8]) -> Result<()> {
+        self.update_buffer(handle)
+    }
+
+    unsafe fn delete_mesh(&mut self, handle: MeshHandle) -> Result<()> {
+        self.meshes
+            .free(handle)
+            .ok_or_else(|| format_err!("{:?} is invalid.", handle))?;
         Ok(())
     }
 
-    unsafe fn update_vertex_buffer(&mut self, _: MeshHandle, _: usize, _: &[u8]) -> Result<()> {
+    unsafe fn create_instance_buffer(
+        &mut self,
+        handle: InstanceBufferHandle,
+        params: InstanceBufferParams,
+        _: Option<&[u8]>,
+    ) -> Result<()> {
+        self.instance_buffers.create(handle, params);
         Ok(())
     }
 
-    unsafe fn update_index_buffer(&mut self, _: MeshHandle, _: usize, _: &[u8]) -> Result<()> {
+    unsafe fn update_instance_buffer(
+        &mut self,
+        handle: InstanceBufferHandle,
+        _: usize,
+        _: &[u8],
+    ) -> Result<()> {
+        let params = self
+            .instance_buffers
+            .get(handle)
+            .ok_or_else(|| format_err!("{:?} is invalid.", handle))?;
+
+        if params.hint == MeshHint::Immutable {
+            bail!("Trying to update immutable buffer");
+        }
+
         Ok(())
     }
 
-    unsafe fn delete_mesh(&mut self, _: MeshHandle) -> Result<()> {
+    unsafe fn delete_instance_buffer(&mut self, handle: InstanceBufferHandle) -> Result<()> {
+        self.instance_buffers
+            .free(handle)
+            .ok_or_else(|| format_err!("{:?} is invalid.", handle))?;
         Ok(())
     }
 
-    unsafe fn bind(&mut self, _: SurfaceHandle, _: math::Vector2<u32>) -> Result<()> {
+    unsafe fn create_uniform_buffer(
+        &mut self,
+        handle: UniformBufferHandle,
+        params: UniformBufferParams,
+        _: Option<&[u8]>,
+    ) -> Result<()> {
+        self.uniform_buffers.create(handle, params);
+        Ok(())
+    }
+
+    unsafe fn update_uniform_buffer(
+        &mut self,
+        handle: UniformBufferHandle,
+        offset: usize,
+        data: &[u8],
+    ) -> Result<()> {
+        let params = self
+            .uniform_buffers
+            .get(handle)
+            .ok_or_else(|| format_err!("{:?} is invalid.", handle))?;
+
+        if params.hint == MeshHint::Immutable {
+            bail!("Trying to update immutable buffer");
+        }
+
+        if offset + data.len() > params.size {
+            bail!("Trying to update uniform buffer out of bounds.");
+        }
+
+        Ok(())
+    }
+
+    unsafe fn delete_uniform_buffer(&mut self, handle: UniformBufferHandle) -> Result<()> {
+        self.uniform_buffers
+            .free(handle)
+            .ok_or_else(|| format_err!("{:?} is invalid.", handle))?;
+
+        for v in self.uniform_slots.iter_mut() {
+            if *v == Some(handle) {
+                *v = None;
+            }
+        }
+
+        Ok(())
+    }
+
+    unsafe fn bind_uniform_buffer(
+        &mut self,
+        slot: usize,
+        handle: Option<UniformBufferHandle>,
+    ) -> Result<()> {
+        if slot >= MAX_UNIFORM_BUFFER_SLOTS {
+            bail!("Uniform buffer slot {} is out of bounds.", slot);
+        }
+
+        if let Some(handle) = handle {
+            self.uniform_buffers
+                .get(handle)
+                .ok_or_else(|| format_err!("{:?} is invalid.", handle))?;
+        }
+
+        self.uniform_slots[slot] = handle;
+        Ok(())
+    }
+
+    unsafe fn bind(&mut self, handle: SurfaceHandle, _: math::Vector2<u32>) -> Result<()> {
+        self.surfaces
+            .get(handle)
+            .ok_or_else(|| format_err!("{:?} is invalid.", handle))?;
         Ok(())
     }
 
     unsafe fn draw(
         &mut self,
-        _: ShaderHandle,
-        _: MeshHandle,
-        _: MeshIndex,
-        _: &[UniformVar],
+        shader: ShaderHandle,
+        mesh: MeshHandle,
+        mesh_index: MeshIndex,
+        uniforms: &[UniformVar],
+    ) -> Result<u32> {
+        self.validate_draw(shader, mesh, mesh_index, uniforms)
+    }
+
+    unsafe fn draw_instanced(
+        &mut self,
+        shader: ShaderHandle,
+        mesh: MeshHandle,
+        mesh_index: MeshIndex,
+        buffer: InstanceBufferHandle,
+        instances: u32,
+        uniforms: &[UniformVar],
     ) -> Result<u32> {
-        Ok(0)
+        let tris = self.validate_draw(shader, mesh, mesh_index, uniforms)?;
+
+        let params = self
+            .instance_buffers
+            .get(buffer)
+            .ok_or_else(|| format_err!("{:?} is invalid.", buffer))?;
+
+        if instances as usize > params.max_instances() {
+            bail!("Trying to draw instances out of bounds of {:?}.", buffer);
+        }
+
+        Ok(tris * instances)
     }
 
     unsafe fn update_surface_scissor(&mut self, _: SurfaceScissor) -> Result<()> {
@@ -112,11 +379,386 @@ impl Visitor for HeadlessVisitor {
         Ok(())
     }
 
+    unsafe fn read_pixels(&mut self, handle: ReadbackHandle, area: math::Aabb2<u32>) -> Result<()> {
+        let dim = area.dim();
+        self.readbacks
+            .push((handle, vec![0; (dim.x * dim.y * 4) as usize]));
+        Ok(())
+    }
+
+    unsafe fn readbacks(&mut self) -> Result<Vec<(ReadbackHandle, Vec<u8>)>> {
+        Ok(::std::mem::replace(&mut self.readbacks, Vec::new()))
+    }
+
+    unsafe fn query_timestamp(&mut self) -> Result<()> {
+        Ok(())
+    }
+
+    unsafe fn timestamps(&mut self) -> Result<Vec<u64>> {
+        Ok(Vec::new())
+    }
+
+    unsafe fn insert_fence(&mut self) -> Result<u64> {
+        self.fence += 1;
+        Ok(self.fence)
+    }
+
+    unsafe fn signaled_fence(&mut self) -> Result<u64> {
+        Ok(self.fence)
+    }
+
     unsafe fn flush(&mut self) -> Result<()> {
         Ok(())
     }
 
+    unsafe fn finish(&mut self) -> Result<()> {
+        Ok(())
+    }
+
     unsafe fn advance(&mut self) -> Result<()> {
         Ok(())
     }
 }
+
+impl HeadlessVisitor {
+    fn validate_draw(
+        &self,
+        shader: ShaderHandle,
+        mesh: MeshHandle,
+        mesh_index: MeshIndex,
+        uniforms: &[UniformVar],
+    ) -> Result<u32> {
+        let params = self
+            .shaders
+            .get(shader)
+            .ok_or_else(|| format_err!("{:?} is invalid.", shader))?;
+
+        for &(field, variable) in uniforms {
+            params.uniforms.validate(shader, field, &variable)?;
+
+            if let UniformVariable::RenderTexture(handle) = variable {
+                if let Some(texture) = self.render_textures.get(handle) {
+                    if !texture.sampler {
+                        bail!("The render buffer does not have a sampler.");
+                    }
+                }
+            }
+        }
+
+        for &(ref name, slot) in &params.uniform_blocks {
+            if self.uniform_slots[slot].is_none() {
+                bail!(
+                    "Uniform block({:?}) of {:?} is not bound to any buffer.",
+                    name,
+                    shader
+                );
+            }
+        }
+
+        let mesh = self
+            .meshes
+            .get(mesh)
+            .ok_or_else(|| format_err!("{:?} is invalid.", mesh))?;
+
+        let (_, len) = mesh
+            .index_range(mesh_index)
+            .ok_or_else(|| format_err!("MeshIndex is out of bounds"))?;
+
+        Ok(mesh.primitive.assemble(len as u32))
+    }
+
+    fn update_buffer(&self, handle: MeshHandle) -> Result<()> {
+        let params = self
+            .meshes
+            .get(handle)
+            .ok_or_else(|| format_err!("{:?} is invalid.", handle))?;
+
+        if params.hint == MeshHint::Immutable {
+            bail!("Trying to update immutable buffer");
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::super::super::lifetime::VideoObject;
+    use super::super::super::VideoSystem;
+    use super::super::deletion::DeletionQueue;
+    use super::*;
+    use application::window::Window;
+    use utils::handle::Handle;
+
+    #[test]
+    fn draw() {
+        let mut visitor = HeadlessVisitor::new();
+        let shader: ShaderHandle = Handle::new(1, 1).into();
+        let mesh: MeshHandle = Handle::new(1, 1).into();
+
+        let mut params = ShaderParams::default();
+        params.uniforms = UniformVariableLayout::build()
+            .with("u_Color", UniformVariableType::Vector4f)
+            .finish();
+
+        let mut mesh_params = MeshParams::default();
+        mesh_params.num_idxes = 6;
+
+        unsafe {
+            assert!(visitor.draw(shader, mesh, MeshIndex::All, &[]).is_err());
+
+            visitor.create_shader(shader, params, "", "").unwrap();
+            visitor.create_mesh(mesh, mesh_params, None).unwrap();
+            assert_eq!(visitor.draw(shader, mesh, MeshIndex::All, &[]).unwrap(), 2);
+            assert!(visitor
+                .draw(shader, mesh, MeshIndex::Ptr(3, 6), &[])
+                .is_err());
+
+            let vars: [UniformVar; 1] = [("u_Color".into(), UniformVariable::Vector4f([1.0; 4]))];
+            assert_eq!(
+                visitor.draw(shader, mesh, MeshIndex::All, &vars).unwrap(),
+                2
+            );
+
+            let vars: [UniformVar; 1] = [("u_Missing".into(), UniformVariable::F32(1.0))];
+            assert!(visitor.draw(shader, mesh, MeshIndex::All, &vars).is_err());
+
+            visitor.delete_mesh(mesh).unwrap();
+            assert!(visitor.draw(shader, mesh, MeshIndex::All, &[]).is_err());
+            assert!(visitor.delete_mesh(mesh).is_err());
+        }
+    }
+
+    #[test]
+    fn update() {
+        let mut visitor = HeadlessVisitor::new();
+        let mesh: MeshHandle = Handle::new(1, 1).into();
+
+        unsafe {
+            assert!(visitor.update_vertex_buffer(mesh, 0, &[]).is_err());
+
+            visitor
+                .create_mesh(mesh, MeshParams::default(), None)
+                .unwrap();
+            assert!(visitor.update_vertex_buffer(mesh, 0, &[]).is_err());
+
+            let mut params = MeshParams::default();
+            params.hint = MeshHint::Dynamic;
+            visitor.create_mesh(mesh, params, None).unwrap();
+            assert!(visitor.update_vertex_buffer(mesh, 0, &[]).is_ok());
+            assert!(visitor.update_index_buffer(mesh, 0, &[]).is_ok());
+        }
+    }
+
+    #[test]
+    fn readbacks() {
+        let mut visitor = HeadlessVisitor::new();
+        let h1: ReadbackHandle = Handle::new(1, 1).into();
+        let h2: ReadbackHandle = Handle::new(2, 1).into();
+        let area = math::Aabb2::new(math::Point2::new(0, 0), math::Point2::new(2, 3));
+
+        unsafe {
+            assert!(visitor.readbacks().unwrap().is_empty());
+
+            visitor.read_pixels(h1, area).unwrap();
+            visitor.read_pixels(h2, area).unwrap();
+            let readbacks = visitor.readbacks().unwrap();
+            assert_eq!(readbacks.len(), 2);
+            assert_eq!(readbacks[0].0, h1);
+            assert_eq!(readbacks[1].0, h2);
+            assert_eq!(readbacks[0].1.len(), 2 * 3 * 4);
+
+            assert!(visitor.readbacks().unwrap().is_empty());
+        }
+    }
+
+    #[test]
+    fn draw_instanced() {
+        let mut visitor = HeadlessVisitor::new();
+        let shader: ShaderHandle = Handle::new(1, 1).into();
+        let mesh: MeshHandle = Handle::new(1, 1).into();
+        let buffer: InstanceBufferHandle = Handle::new(1, 1).into();
+
+        let mut mesh_params = MeshParams::default();
+        mesh_params.num_idxes = 6;
+
+        let mut params = InstanceBufferParams::default();
+        params.num = 4;
+
+        unsafe {
+            visitor
+                .create_shader(shader, ShaderParams::default(), "", "")
+                .unwrap();
+            visitor.create_mesh(mesh, mesh_params, None).unwrap();
+            let all = MeshIndex::All;
+            assert!(visitor
+                .draw_instanced(shader, mesh, all, buffer, 4, &[])
+                .is_err());
+
+            visitor
+                .create_instance_buffer(buffer, params, None)
+                .unwrap();
+            assert_eq!(
+                visitor
+                    .draw_instanced(shader, mesh, all, buffer, 4, &[])
+                    .unwrap(),
+                8
+            );
+            assert!(visitor
+                .draw_instanced(shader, mesh, all, buffer, 5, &[])
+                .is_err());
+            assert!(visitor.update_instance_buffer(buffer, 0, &[]).is_ok());
+
+            params.divisor = 2;
+            params.hint = MeshHint::Immutable;
+            visitor
+                .create_instance_buffer(buffer, params, None)
+                .unwrap();
+            assert!(visitor
+                .draw_instanced(shader, mesh, all, buffer, 8, &[])
+                .is_ok());
+            assert!(visitor.update_instance_buffer(buffer, 0, &[]).is_err());
+
+            visitor.delete_instance_buffer(buffer).unwrap();
+            assert!(visitor
+                .draw_instanced(shader, mesh, all, buffer, 1, &[])
+                .is_err());
+        }
+    }
+
+    #[test]
+    fn update_shader() {
+        let window = Window::headless();
+        let mut video = VideoSystem::headless();
+        let shared = video.shared();
+
+        let params = ShaderParams::default();
+        let shader = shared
+            .create_shader(params, "vs".into(), "fs".into())
+            .unwrap();
+        video.swap_frames();
+        video.advance(&window).unwrap();
+
+        assert!(shared
+            .update_shader(shader, "vs".into(), "fs".into())
+            .is_ok());
+
+        // The invalid sources are refused before queueing.
+        assert!(shared
+            .update_shader(shader, "vs".into(), String::new())
+            .is_err());
+
+        video.swap_frames();
+        video.advance(&window).unwrap();
+        assert!(shared.shader(shader).is_some());
+
+        shared.delete_shader(shader);
+        assert!(shared
+            .update_shader(shader, "vs".into(), "fs".into())
+            .is_err());
+    }
+
+    #[test]
+    fn profile() {
+        let video = VideoSystem::headless().shared();
+        assert!(video.capabilities().instancing);
+        assert!(video.capabilities().uniform_buffer);
+
+        let mut params = InstanceBufferParams::default();
+        params.num = 4;
+        let buffer = video.create_instance_buffer(params, None).unwrap();
+        video.delete_instance_buffer(buffer);
+
+        // The features missing on GLES 2.0 devices are refused before queueing.
+        let video = VideoSystem::headless_with(VideoProfile::Gles2).shared();
+        assert_eq!(video.capabilities().profile, VideoProfile::Gles2);
+        assert!(!video.capabilities().instancing);
+        assert!(!video.capabilities().uniform_buffer);
+        let caps = video.capabilities();
+        assert!(!caps.has_texture_format(TextureFormat::RGBA16F));
+
+        assert!(video.create_instance_buffer(params, None).is_err());
+        let mut params = UniformBufferParams::default();
+        params.size = 16;
+        assert!(video.create_uniform_buffer(params, None).is_err());
+    }
+
+    #[test]
+    fn uniform_buffers() {
+        let mut visitor = HeadlessVisitor::new();
+        let shader: ShaderHandle = Handle::new(1, 1).into();
+        let mesh: MeshHandle = Handle::new(1, 1).into();
+        let buffer: UniformBufferHandle = Handle::new(1, 1).into();
+
+        let mut mesh_params = MeshParams::default();
+        mesh_params.num_idxes = 6;
+
+        let mut params = ShaderParams::default();
+        params.uniform_blocks.push(("Camera".into(), 1));
+
+        let mut buffer_params = UniformBufferParams::default();
+        buffer_params.size = 64;
+
+        unsafe {
+            visitor.create_shader(shader, params, "", "").unwrap();
+            visitor.create_mesh(mesh, mesh_params, None).unwrap();
+            let all = MeshIndex::All;
+            assert!(visitor.draw(shader, mesh, all, &[]).is_err());
+            assert!(visitor.bind_uniform_buffer(1, Some(buffer)).is_err());
+
+            visitor
+                .create_uniform_buffer(buffer, buffer_params, None)
+                .unwrap();
+            assert!(visitor.update_uniform_buffer(buffer, 0, &[0; 64]).is_ok());
+            assert!(visitor.update_uniform_buffer(buffer, 8, &[0; 64]).is_err());
+
+            let slots = MAX_UNIFORM_BUFFER_SLOTS;
+            assert!(visitor.bind_uniform_buffer(slots, Some(buffer)).is_err());
+            visitor.bind_uniform_buffer(0, Some(buffer)).unwrap();
+            assert!(visitor.draw(shader, mesh, all, &[]).is_err());
+            visitor.bind_uniform_buffer(1, Some(buffer)).unwrap();
+            assert_eq!(visitor.draw(shader, mesh, all, &[]).unwrap(), 2);
+
+            visitor.bind_uniform_buffer(1, None).unwrap();
+            assert!(visitor.draw(shader, mesh, all, &[]).is_err());
+
+            visitor.bind_uniform_buffer(1, Some(buffer)).unwrap();
+            visitor.delete_uniform_buffer(buffer).unwrap();
+            assert!(visitor.draw(shader, mesh, all, &[]).is_err());
+            assert!(visitor.update_uniform_buffer(buffer, 0, &[]).is_err());
+        }
+    }
+
+    #[test]
+    fn reuse_retired_index() {
+        let mut visitor = HeadlessVisitor::new();
+        let mut deletions = DeletionQueue::default();
+        let shader: ShaderHandle = Handle::new(1, 1).into();
+        let retired: MeshHandle = Handle::new(1, 1).into();
+        let mesh: MeshHandle = Handle::new(1, 2).into();
+
+        let mut params = MeshParams::default();
+        params.num_idxes = 6;
+
+        unsafe {
+            visitor
+                .create_shader(shader, ShaderParams::default(), "", "")
+                .unwrap();
+            visitor.create_mesh(retired, params.clone(), None).unwrap();
+            deletions.retire(VideoObject::Mesh(retired));
+
+            // The index of retired mesh is reused before it's actually deleted.
+            visitor.create_mesh(mesh, params, None).unwrap();
+            assert!(visitor.draw(shader, retired, MeshIndex::All, &[]).is_ok());
+
+            let fence = visitor.insert_fence().unwrap();
+            deletions.fence(fence);
+            let signaled = visitor.signaled_fence().unwrap();
+            deletions.collect(&mut visitor, signaled);
+
+            assert!(visitor.draw(shader, retired, MeshIndex::All, &[]).is_err());
+            assert_eq!(visitor.draw(shader, mesh, MeshIndex::All, &[]).unwrap(), 2);
+            visitor.delete_mesh(mesh).unwrap();
+        }
+    }
+}
@@ -1,11 +1,14 @@
 //! The backend of renderer, which should be responsible for only one thing:
 //! submitting draw-calls using low-level OpenGL video APIs.
 
+pub mod deletion;
 pub mod frame;
 pub mod gl;
 pub mod headless;
 
 use super::assets::prelude::*;
+use super::capabilities::VideoCapabilities;
+use super::readback::ReadbackHandle;
 
 use errors::*;
 use math;
@@ -14,6 +17,9 @@ use utils::hash_value;
 pub type UniformVar = (hash_value::HashValue<str>, UniformVariable);
 
 pub trait Visitor {
+    /// Gets the capabilities of underlying device.
+    fn capabilities(&self) -> VideoCapabilities;
+
     unsafe fn create_surface(&mut self, handle: SurfaceHandle, params: SurfaceParams)
         -> Result<()>;
 
@@ -43,6 +49,17 @@ pub trait Visitor {
         bytes: &[u8],
     ) -> Result<()>;
 
+    /// Uploads the rows `area` of mipmap `level` into texture created without data. The
+    /// storage of all the `levels` is allocated with the first slice.
+    unsafe fn upload_texture(
+        &mut self,
+        handle: TextureHandle,
+        level: u32,
+        levels: u32,
+        area: math::Aabb2<u32>,
+        bytes: &[u8],
+    ) -> Result<()>;
+
     unsafe fn delete_texture(&mut self, handle: TextureHandle) -> Result<()>;
 
     unsafe fn create_render_texture(
@@ -57,7 +74,7 @@ pub trait Visitor {
         &mut self,
         handle: MeshHandle,
         ps: MeshParams,
-        data: Option<MeshData>,
+        data: Option<(&[u8], &[u8])>,
     ) -> Result<()>;
 
     unsafe fn update_vertex_buffer(
@@ -76,6 +93,46 @@ pub trait Visitor {
 
     unsafe fn delete_mesh(&mut self, handle: MeshHandle) -> Result<()>;
 
+    unsafe fn create_instance_buffer(
+        &mut self,
+        handle: InstanceBufferHandle,
+        params: InstanceBufferParams,
+        bytes: Option<&[u8]>,
+    ) -> Result<()>;
+
+    unsafe fn update_instance_buffer(
+        &mut self,
+        handle: InstanceBufferHandle,
+        o: usize,
+        bytes: &[u8],
+    ) -> Result<()>;
+
+    unsafe fn delete_instance_buffer(&mut self, handle: InstanceBufferHandle) -> Result<()>;
+
+    unsafe fn create_uniform_buffer(
+        &mut self,
+        handle: UniformBufferHandle,
+        params: UniformBufferParams,
+        bytes: Option<&[u8]>,
+    ) -> Result<()>;
+
+    unsafe fn update_uniform_buffer(
+        &mut self,
+        handle: UniformBufferHandle,
+        o: usize,
+        bytes: &[u8],
+    ) -> Result<()>;
+
+    unsafe fn delete_uniform_buffer(&mut self, handle: UniformBufferHandle) -> Result<()>;
+
+    /// Binds the uniform buffer to `slot`, which is read by the uniform blocks declared
+    /// with the same slot in the following drawcalls.
+    unsafe fn bind_uniform_buffer(
+        &mut self,
+        slot: usize,
+        handle: Option<UniformBufferHandle>,
+    ) -> Result<()>;
+
     unsafe fn bind(&mut self, surface: SurfaceHandle, dimensions: math::Vector2<u32>)
         -> Result<()>;
 
@@ -87,14 +144,52 @@ pub trait Visitor {
         vars: &[UniformVar],
     ) -> Result<u32>;
 
+    /// Draws `instances` instances of mesh, the attributes which could not be found in
+    /// the mesh are read from the instance `buffer`.
+    unsafe fn draw_instanced(
+        &mut self,
+        shader: ShaderHandle,
+        mesh: MeshHandle,
+        mesh_index: MeshIndex,
+        buffer: InstanceBufferHandle,
+        instances: u32,
+        vars: &[UniformVar],
+    ) -> Result<u32>;
+
     unsafe fn update_surface_scissor(&mut self, scissor: SurfaceScissor) -> Result<()>;
 
     unsafe fn update_surface_viewport(&mut self, vp: SurfaceViewport) -> Result<()>;
 
+    /// Starts reading the RGBA8 pixels in `area` of the binded surface. The pixels are
+    /// returned by `readbacks` once the GPU has drawn them.
+    unsafe fn read_pixels(&mut self, handle: ReadbackHandle, area: math::Aabb2<u32>) -> Result<()>;
+
+    /// Takes the pixels of finished reads without waiting for the GPU.
+    unsafe fn readbacks(&mut self) -> Result<Vec<(ReadbackHandle, Vec<u8>)>>;
+
+    /// Records the GPU time once all the previous commands are finished. Nothing is
+    /// recorded if timer queries are not supported.
+    unsafe fn query_timestamp(&mut self) -> Result<()>;
+
+    /// Takes the timestamps in nanoseconds recorded since last call, which should only
+    /// be called after `flush`.
+    unsafe fn timestamps(&mut self) -> Result<Vec<u64>>;
+
+    /// Inserts a fence after all the commands submitted so far, and returns its index
+    /// which increases by one for every fence.
+    unsafe fn insert_fence(&mut self) -> Result<u64>;
+
+    /// Gets the index of latest fence which is signaled by the GPU without waiting, all
+    /// the commands submitted before it are completed.
+    unsafe fn signaled_fence(&mut self) -> Result<u64>;
+
     /// Blocks until all execution is complete. Such effects include all changes to render state, all
     /// changes to connection state, and all changes to the frame buffer contents.
     unsafe fn flush(&mut self) -> Result<()>;
 
+    /// Blocks until all the commands are completed by GPU.
+    unsafe fn finish(&mut self) -> Result<()>;
+
     /// Advance one frame, it will be called every frames.
     unsafe fn advance(&mut self) -> Result<()>;
 }
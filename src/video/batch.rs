@@ -4,6 +4,7 @@ use utils::hash_value;
 
 use super::assets::prelude::*;
 use super::backends::frame::Command;
+use super::backends::UniformVar;
 use super::errors::*;
 use super::VideoSystemShared;
 use super::MAX_UNIFORM_VARIABLES;
@@ -29,15 +30,24 @@ impl<T: Ord + Copy> OrderDrawBatch<T> {
     pub fn draw(&mut self, order: T, dc: DrawCall) {
         let len = dc.uniforms_len;
         let ptr = self.bufs.extend_from_slice(&dc.uniforms[0..len]);
-        let cmd = Command::Draw(dc.shader, dc.mesh, dc.mesh_index, ptr);
-        self.cmds.push((order, cmd));
+        self.cmds.push((order, dc.build(ptr)));
     }
 
     /// Clears the batch, and submits all the sorted commands into video device. Its guaranteed that
     /// all the commands in this batch will be executed one by one in order.
     ///
+    /// The drawcalls whose uniforms do not match the layout of their shaders are dropped
+    /// and logged, the rest are still submitted, and the first error is returned.
+    ///
     /// Notes that this method has no effect on the allocated capacity of the underlying storage.
     pub fn submit(&mut self, video: &VideoSystemShared, surface: SurfaceHandle) -> Result<()> {
+        let mut result = Ok(());
+        {
+            let bufs = &self.bufs;
+            self.cmds
+                .retain(|v| validate(video, &v.1, bufs, &mut result));
+        }
+
         let mut frame = video.frames.front();
         frame.cmds.push(Command::Bind(surface));
 
@@ -51,12 +61,19 @@ impl<T: Ord + Copy> OrderDrawBatch<T> {
                     frame.cmds.push(cmd);
                 }
 
+                (_, Command::DrawInstanced(shader, mesh, mesh_index, buffer, num, ptr)) => {
+                    let vars = self.bufs.as_slice(ptr);
+                    let ptr = frame.bufs.extend_from_slice(vars);
+                    let cmd = Command::DrawInstanced(shader, mesh, mesh_index, buffer, num, ptr);
+                    frame.cmds.push(cmd);
+                }
+
                 _ => {}
             }
         }
 
         self.bufs.clear();
-        Ok(())
+        result
     }
 }
 
@@ -85,8 +102,7 @@ impl Batch {
     pub fn draw(&mut self, dc: DrawCall) {
         let len = dc.uniforms_len;
         let ptr = self.bufs.extend_from_slice(&dc.uniforms[0..len]);
-        let cmd = Command::Draw(dc.shader, dc.mesh, dc.mesh_index, ptr);
-        self.cmds.push(cmd);
+        self.cmds.push(dc.build(ptr));
     }
 
     /// Updates the scissor test of surface.
@@ -135,8 +151,17 @@ impl Batch {
     /// Clears the batch, and submits all the commands into video device. Its guaranteed that
     /// all the commands in this batch will be executed one by one in order.
     ///
+    /// The drawcalls whose uniforms do not match the layout of their shaders are dropped
+    /// and logged, the rest are still submitted, and the first error is returned.
+    ///
     /// Notes that this method has no effect on the allocated capacity of the underlying storage.
     pub fn submit(&mut self, video: &VideoSystemShared, surface: SurfaceHandle) -> Result<()> {
+        let mut result = Ok(());
+        {
+            let bufs = &self.bufs;
+            self.cmds.retain(|v| validate(video, v, bufs, &mut result));
+        }
+
         let mut frame = video.frames.front();
 
         frame.cmds.push(Command::Bind(surface));
@@ -150,6 +175,13 @@ impl Batch {
                     frame.cmds.push(cmd);
                 }
 
+                Command::DrawInstanced(shader, mesh, mesh_index, buffer, num, ptr) => {
+                    let vars = self.bufs.as_slice(ptr);
+                    let ptr = frame.bufs.extend_from_slice(vars);
+                    let cmd = Command::DrawInstanced(shader, mesh, mesh_index, buffer, num, ptr);
+                    frame.cmds.push(cmd);
+                }
+
                 Command::UpdateTexture(id, area, ptr) => {
                     let ptr = frame.bufs.extend_from_slice(self.bufs.as_slice(ptr));
                     frame.cmds.push(Command::UpdateTexture(id, area, ptr));
@@ -171,7 +203,32 @@ impl Batch {
         }
 
         self.bufs.clear();
-        Ok(())
+        result
+    }
+}
+
+// Validates the uniforms of draw commands, and keeps the first error in `result`.
+fn validate(
+    video: &VideoSystemShared,
+    cmd: &Command,
+    bufs: &data_buf::DataBuffer,
+    result: &mut Result<()>,
+) -> bool {
+    match *cmd {
+        Command::Draw(shader, _, _, ptr) | Command::DrawInstanced(shader, _, _, _, _, ptr) => {
+            match video.validate_uniforms(shader, bufs.as_slice(ptr)) {
+                Ok(_) => true,
+                Err(err) => {
+                    error!("Drops the drawcall, {}", err);
+                    if result.is_ok() {
+                        *result = Err(err);
+                    }
+
+                    false
+                }
+            }
+        }
+        _ => true,
     }
 }
 
@@ -184,6 +241,8 @@ pub struct DrawCall {
     pub shader: ShaderHandle,
     pub mesh: MeshHandle,
     pub mesh_index: MeshIndex,
+    /// The instance buffer and the number of instances of an instanced draw.
+    pub instances: Option<(InstanceBufferHandle, u32)>,
 }
 
 impl DrawCall {
@@ -196,17 +255,25 @@ impl DrawCall {
             uniforms_len: 0,
             mesh: mesh,
             mesh_index: MeshIndex::All,
+            instances: None,
         }
     }
 
+    /// Draws `num` instances of the mesh in one call. The attributes of shader which
+    /// could not be found in the mesh are read from the instance `buffer`.
+    ///
+    /// Notes that instanced drawing is not available on every device, check the
+    /// `instancing` of `VideoCapabilities` before using it.
+    pub fn set_instances(&mut self, buffer: InstanceBufferHandle, num: u32) {
+        self.instances = Some((buffer, num));
+    }
+
     /// Binds the named field with `UniformVariable`.
     pub fn set_uniform_variable<F, V>(&mut self, field: F, variable: V)
     where
         F: Into<hash_value::HashValue<str>>,
         V: Into<UniformVariable>,
     {
-        assert!(self.uniforms_len < MAX_UNIFORM_VARIABLES);
-
         let field = field.into();
         let variable = variable.into();
 
@@ -217,7 +284,95 @@ impl DrawCall {
             }
         }
 
+        assert!(self.uniforms_len < MAX_UNIFORM_VARIABLES);
         self.uniforms[self.uniforms_len] = (field, variable);
         self.uniforms_len += 1;
     }
+
+    /// Retains only the uniform variables whose fields satisfy `f`, e.g. strips the ones
+    /// which are not declared by the shader of drawcall.
+    pub fn retain_uniforms<F>(&mut self, mut f: F)
+    where
+        F: FnMut(hash_value::HashValue<str>) -> bool,
+    {
+        let mut len = 0;
+        for i in 0..self.uniforms_len {
+            if f(self.uniforms[i].0) {
+                self.uniforms[len] = self.uniforms[i];
+                len += 1;
+            }
+        }
+
+        self.uniforms_len = len;
+    }
+
+    pub(crate) fn build(&self, ptr: data_buf::DataBufferPtr<[UniformVar]>) -> Command {
+        match self.instances {
+            Some((buffer, num)) => {
+                Command::DrawInstanced(self.shader, self.mesh, self.mesh_index, buffer, num, ptr)
+            }
+            None => Command::Draw(self.shader, self.mesh, self.mesh_index, ptr),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::super::VideoSystem;
+    use super::*;
+
+    #[test]
+    fn submit() {
+        let video = VideoSystem::headless().shared();
+        let surface = video.create_surface(SurfaceParams::default()).unwrap();
+        let mesh = video.create_mesh(MeshParams::default(), None).unwrap();
+
+        let mut params = ShaderParams::default();
+        params.uniforms = UniformVariableLayout::build()
+            .with("u_Time", UniformVariableType::F32)
+            .finish();
+        let shader = video
+            .create_shader(params, "..".into(), "..".into())
+            .unwrap();
+
+        let mut valid = DrawCall::new(shader, mesh);
+        valid.set_uniform_variable("u_Time", 1.0f32);
+        let mut undefined = DrawCall::new(shader, mesh);
+        undefined.set_uniform_variable("u_Undefined", 1.0f32);
+        let mut mismatched = DrawCall::new(shader, mesh);
+        mismatched.set_uniform_variable("u_Time", 1i32);
+
+        // Only the invalid drawcalls are dropped, and the first error is returned.
+        let mut batch = Batch::new();
+        batch.draw(valid);
+        batch.draw(undefined);
+        batch.draw(mismatched);
+        match batch.submit(&video, surface) {
+            Err(Error::UniformUndefined(_, _)) => {}
+            _ => panic!(),
+        }
+
+        assert!(batch.submit(&video, surface).is_ok());
+
+        let mut batch = OrderDrawBatch::new();
+        batch.draw(1, undefined);
+        batch.draw(0, valid);
+        assert!(batch.submit(&video, surface).is_err());
+        assert!(batch.submit(&video, surface).is_ok());
+    }
+
+    #[test]
+    fn retain_uniforms() {
+        let mut dc = DrawCall::new(ShaderHandle::default(), MeshHandle::default());
+        dc.set_uniform_variable("u_A", 1.0f32);
+        dc.set_uniform_variable("u_B", 2.0f32);
+        dc.set_uniform_variable("u_C", 3.0f32);
+
+        let fields: Vec<hash_value::HashValue<str>> =
+            vec!["u_A".into(), "u_B".into(), "u_C".into()];
+        dc.retain_uniforms(|v| v != fields[1]);
+        assert_eq!(dc.uniforms_len, 2);
+        assert!(dc.uniforms[0].0 == fields[0]);
+        assert!(dc.uniforms[1].0 == fields[2]);
+    }
 }
@@ -0,0 +1,154 @@
+//! The capabilities of the underlying video device, which never change after the
+//! `VideoSystem` is created.
+
+use super::assets::texture::TextureFormat;
+
+/// The graphics API used by video backend.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum VideoApi {
+    /// Nothing would be drawn actually.
+    Headless,
+    /// Regular OpenGL with major and minor version.
+    GL(u8, u8),
+    /// OpenGL embedded system with major and minor version.
+    ES(u8, u8),
+}
+
+impl Default for VideoApi {
+    fn default() -> Self {
+        VideoApi::Headless
+    }
+}
+
+/// The feature set used by video backend.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum VideoProfile {
+    /// Uses everything available on the device.
+    Standard,
+    /// Sticks to the features of GLES 2.0, e.g. no vertex array objects, uniform
+    /// buffers or instancing, and translates shaders into GLSL ES 1.00. It's picked
+    /// automatically on GLES 2.0 devices, and could be forced on others to check how
+    /// the game runs on them. The desktop contexts accept GLSL ES 1.00 only since
+    /// GL 4.1 or with `ARB_ES2_compatibility`, otherwise creating `VideoSystem` fails.
+    Gles2,
+}
+
+impl Default for VideoProfile {
+    fn default() -> Self {
+        VideoProfile::Standard
+    }
+}
+
+/// The capabilities of video backend.
+#[derive(Debug, Clone, Default)]
+pub struct VideoCapabilities {
+    pub api: VideoApi,
+    /// The feature set which is actually used.
+    pub profile: VideoProfile,
+    /// The company responsible for the implementation.
+    pub vendor: String,
+    /// The name of the renderer, which is typically specific to the hardware.
+    pub renderer: String,
+    /// The list of detected extensions which are recognized by crayon.
+    pub extensions: Vec<String>,
+    /// Maximum width and height of textures.
+    pub max_texture_size: u32,
+    /// Maximum number of samples of multisample render targets, 0 if not supported.
+    pub max_samples: u32,
+    /// Maximum number of color attachments of render targets.
+    pub max_color_attachments: u32,
+    /// Whether 32-bit indices could be used in meshes.
+    pub u32_index: bool,
+    /// Whether vertex array objects are used to bind vertex attributes.
+    pub vertex_array_object: bool,
+    /// Whether uniform buffer objects are available.
+    pub uniform_buffer: bool,
+    /// Whether instanced drawing is available.
+    pub instancing: bool,
+    /// Whether the GPU timings could be measured with timer queries.
+    pub timer_query: bool,
+    /// Whether textures with 16-bit floating point formats could be created, which
+    /// requires `GL_OES_texture_half_float` on GLES 2.0.
+    pub half_float_texture: bool,
+    /// Whether textures with 32-bit floating point formats could be created, which
+    /// requires `GL_OES_texture_float` on GLES 2.0.
+    pub float_texture: bool,
+    pub etc2: bool,
+    pub pvrtc: bool,
+    pub s3tc: bool,
+}
+
+impl VideoCapabilities {
+    /// Returns true if the extension has been detected.
+    pub fn has_extension(&self, name: &str) -> bool {
+        self.extensions.iter().any(|v| v == name)
+    }
+
+    /// Gets the supported MSAA levels in ascending order, e.g. `[2, 4, 8]`.
+    pub fn msaa_levels(&self) -> Vec<u32> {
+        let mut levels = Vec::new();
+        let mut v = 2;
+        while v <= self.max_samples {
+            levels.push(v);
+            v *= 2;
+        }
+
+        levels
+    }
+
+    /// Returns true if textures with `format` could be created. The uncompressed 8-bit
+    /// formats are always supported.
+    pub fn has_texture_format(&self, format: TextureFormat) -> bool {
+        match format {
+            TextureFormat::PvrtcRGB4BPP
+            | TextureFormat::PvrtcRGB2BPP
+            | TextureFormat::PvrtcRGBA4BPP
+            | TextureFormat::PvrtcRGBA2BPP => self.pvrtc,
+            TextureFormat::Etc2RGB4BPP | TextureFormat::Etc2RGBA8BPP => self.etc2,
+            TextureFormat::S3tcDxt1RGB4BPP | TextureFormat::S3tcDxt5RGBA8BPP => self.s3tc,
+            TextureFormat::R16F
+            | TextureFormat::RG16F
+            | TextureFormat::RGB16F
+            | TextureFormat::RGBA16F => self.half_float_texture,
+            TextureFormat::R32F
+            | TextureFormat::RG32F
+            | TextureFormat::RGB32F
+            | TextureFormat::RGBA32F => self.float_texture,
+            _ => true,
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn msaa_levels() {
+        let mut caps = VideoCapabilities::default();
+        assert!(caps.msaa_levels().is_empty());
+
+        caps.max_samples = 8;
+        assert_eq!(caps.msaa_levels(), vec![2, 4, 8]);
+
+        caps.max_samples = 6;
+        assert_eq!(caps.msaa_levels(), vec![2, 4]);
+    }
+
+    #[test]
+    fn texture_formats() {
+        let mut caps = VideoCapabilities::default();
+        assert!(caps.has_texture_format(TextureFormat::RGBA8));
+        assert!(!caps.has_texture_format(TextureFormat::Etc2RGB4BPP));
+
+        caps.etc2 = true;
+        assert!(caps.has_texture_format(TextureFormat::Etc2RGBA8BPP));
+        assert!(!caps.has_texture_format(TextureFormat::S3tcDxt5RGBA8BPP));
+
+        assert!(!caps.has_texture_format(TextureFormat::RGBA16F));
+        assert!(!caps.has_texture_format(TextureFormat::R32F));
+        caps.half_float_texture = true;
+        assert!(caps.has_texture_format(TextureFormat::RGBA16F));
+        assert!(!caps.has_texture_format(TextureFormat::RGBA32F));
+    }
+}
@@ -0,0 +1,155 @@
+//! The culling statistics of views, which are reported by renderers and collected
+//! into `VideoFrameInfo::culling`. They help to find out why a scene is slow, e.g.
+//! too many objects pass the frustum test of a camera.
+//!
+//! The views are identified by ids which are chosen by renderers, e.g. `crayon-3d`
+//! uses `Camera::view_id` which is derived from the world and entity of camera.
+
+use std::time::Duration;
+
+/// The maximum number of views whose statistics are kept per frame. The views beyond
+/// it are merged into the last one.
+pub const MAX_CULLING_VIEWS: usize = 8;
+
+/// The culling statistics of a view, e.g. a camera.
+#[derive(Debug, Copy, Clone, Default, PartialEq)]
+pub struct CullingInfo {
+    /// The number of objects tested.
+    pub tested: u32,
+    /// The number of objects culled by the layer mask of view.
+    pub layer_culled: u32,
+    /// The number of objects culled by the view frustum.
+    pub frustum_culled: u32,
+    /// The number of objects culled by occluders.
+    pub occlusion_culled: u32,
+    /// The number of objects whose results of last frame are reused without tests,
+    /// which are included in the counters above.
+    pub reused: u32,
+    /// The time spent in culling.
+    pub duration: Duration,
+}
+
+impl CullingInfo {
+    /// Gets the number of objects which pass all the tests.
+    #[inline]
+    pub fn visible(&self) -> u32 {
+        let culled = self.layer_culled + self.frustum_culled + self.occlusion_culled;
+        self.tested.saturating_sub(culled)
+    }
+
+    /// Accumulates the statistics of another view.
+    pub fn merge(&mut self, other: &CullingInfo) {
+        self.tested += other.tested;
+        self.layer_culled += other.layer_culled;
+        self.frustum_culled += other.frustum_culled;
+        self.occlusion_culled += other.occlusion_culled;
+        self.reused += other.reused;
+        self.duration += other.duration;
+    }
+}
+
+/// The culling statistics of all the views during a frame with their ids, in the
+/// order they are first reported.
+#[derive(Debug, Copy, Clone, Default)]
+pub struct CullingFrameInfo {
+    views: [(u64, CullingInfo); MAX_CULLING_VIEWS],
+    len: usize,
+}
+
+impl CullingFrameInfo {
+    /// Gets the statistics of views with their ids.
+    #[inline]
+    pub fn views(&self) -> &[(u64, CullingInfo)] {
+        &self.views[0..self.len]
+    }
+
+    /// Gets the statistics of view with id.
+    pub fn view(&self, id: u64) -> Option<CullingInfo> {
+        self.views().iter().find(|v| v.0 == id).map(|v| v.1)
+    }
+
+    /// Gets the sum of statistics of all the views.
+    pub fn total(&self) -> CullingInfo {
+        let mut total = CullingInfo::default();
+        for v in self.views() {
+            total.merge(&v.1);
+        }
+
+        total
+    }
+
+    // The reports of the same view in a frame are merged. The views beyond the limit
+    // are merged into the last one, which keeps its id.
+    pub(crate) fn push(&mut self, id: u64, info: CullingInfo) {
+        if let Some(v) = self.views[0..self.len].iter_mut().find(|v| v.0 == id) {
+            v.1.merge(&info);
+            return;
+        }
+
+        if self.len < MAX_CULLING_VIEWS {
+            self.views[self.len] = (id, info);
+            self.len += 1;
+        } else {
+            self.views[MAX_CULLING_VIEWS - 1].1.merge(&info);
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn views() {
+        let mut frame = CullingFrameInfo::default();
+        assert!(frame.views().is_empty());
+
+        for i in 0..(MAX_CULLING_VIEWS + 2) {
+            frame.push(
+                i as u64,
+                CullingInfo {
+                    tested: 10,
+                    layer_culled: 1,
+                    frustum_culled: i as u32,
+                    occlusion_culled: 0,
+                    reused: 5,
+                    duration: Duration::from_millis(1),
+                },
+            );
+        }
+
+        let views = frame.views();
+        assert_eq!(views.len(), MAX_CULLING_VIEWS);
+        assert_eq!(views[1].0, 1);
+        assert_eq!(views[1].1.visible(), 8);
+        assert_eq!(views[MAX_CULLING_VIEWS - 1].0, MAX_CULLING_VIEWS as u64 - 1);
+        assert_eq!(views[MAX_CULLING_VIEWS - 1].1.tested, 30);
+
+        let total = frame.total();
+        assert_eq!(total.tested, 10 * (MAX_CULLING_VIEWS as u32 + 2));
+        assert_eq!(total.reused, 5 * (MAX_CULLING_VIEWS as u32 + 2));
+        assert_eq!(
+            total.duration,
+            Duration::from_millis(MAX_CULLING_VIEWS as u64 + 2)
+        );
+    }
+
+    #[test]
+    fn same_view() {
+        let mut frame = CullingFrameInfo::default();
+        let info = CullingInfo {
+            tested: 4,
+            frustum_culled: 1,
+            ..Default::default()
+        };
+
+        frame.push(7, info);
+        frame.push(3, info);
+        frame.push(7, info);
+
+        assert_eq!(frame.views().len(), 2);
+        assert_eq!(frame.view(7).unwrap().tested, 8);
+        assert_eq!(frame.view(3).unwrap().visible(), 3);
+        assert_eq!(frame.view(5), None);
+    }
+}
@@ -1,3 +1,6 @@
+use super::assets::shader::UniformVariableType;
+use super::assets::texture::TextureFormat;
+
 #[derive(Debug, Fail)]
 pub enum Error {
     #[fail(display = "Failed to create shader, errors: \n{}.", _0)]
@@ -14,6 +17,38 @@ pub enum Error {
     SurfaceInvalid(String),
     #[fail(display = "Attribute({}) is undefined.", _0)]
     AttributeUndefined(String),
+    #[fail(display = "Uniform({}) is undefined in {}.", _0, _1)]
+    UniformUndefined(String, String),
+    #[fail(display = "Uniform({}) of {} expects {:?}, but {:?} is provided.", _0, _1, _2, _3)]
+    UniformMismatch(String, String, UniformVariableType, UniformVariableType),
+    #[fail(display = "Image with {:?} format is unsupported.", _0)]
+    ImageFormatUnsupported(TextureFormat),
+    #[fail(display = "Uniform({}) with {:?} could not be declared in uniform blocks.", _0, _1)]
+    UniformBlockUnsupported(String, UniformVariableType),
+    #[fail(display = "{} are unavailable on this device.", _0)]
+    FeatureUnsupported(&'static str),
+}
+
+impl Error {
+    /// Gets the category of this error.
+    pub fn kind(&self) -> ::errors::ErrorKind {
+        use errors::ErrorKind;
+
+        match *self {
+            Error::ShaderInvalid(_) => ErrorKind::InvalidData,
+            Error::HandleInvalid(_) => ErrorKind::InvalidHandle,
+            Error::OutOfBounds => ErrorKind::OutOfBounds,
+            Error::UpdateImmutableBuffer => ErrorKind::Unsupported,
+            Error::SampleRenderBuffer => ErrorKind::Unsupported,
+            Error::SurfaceInvalid(_) => ErrorKind::InvalidData,
+            Error::AttributeUndefined(_) => ErrorKind::InvalidData,
+            Error::UniformUndefined(_, _) => ErrorKind::InvalidData,
+            Error::UniformMismatch(_, _, _, _) => ErrorKind::InvalidData,
+            Error::ImageFormatUnsupported(_) => ErrorKind::Unsupported,
+            Error::UniformBlockUnsupported(_, _) => ErrorKind::Unsupported,
+            Error::FeatureUnsupported(_) => ErrorKind::Unsupported,
+        }
+    }
 }
 
 pub type Result<T> = ::std::result::Result<T, Error>;
@@ -0,0 +1,96 @@
+//! The lifetime tracking of video objects, which helps to find the objects that are
+//! leaked by forgetting to delete them.
+
+use std::collections::HashMap;
+
+use super::assets::prelude::*;
+
+/// The handle of any kind of video object.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum VideoObject {
+    Surface(SurfaceHandle),
+    Shader(ShaderHandle),
+    Texture(TextureHandle),
+    RenderTexture(RenderTextureHandle),
+    Mesh(MeshHandle),
+    InstanceBuffer(InstanceBufferHandle),
+    UniformBuffer(UniformBufferHandle),
+}
+
+/// A video object which has been alive for a long time.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct LongLivedObject {
+    pub object: VideoObject,
+    /// The number of frames since its creation.
+    pub frames: u64,
+}
+
+#[derive(Default)]
+pub(crate) struct ObjectTracker {
+    objects: HashMap<VideoObject, u64>,
+    frame: u64,
+}
+
+impl ObjectTracker {
+    #[inline]
+    pub fn create(&mut self, object: VideoObject) {
+        self.objects.insert(object, self.frame);
+    }
+
+    #[inline]
+    pub fn delete(&mut self, object: VideoObject) {
+        self.objects.remove(&object);
+    }
+
+    #[inline]
+    pub fn advance(&mut self) {
+        self.frame += 1;
+    }
+
+    /// Lists the objects which are alive longer than `frames`, the oldest comes first.
+    pub fn long_lived(&self, frames: u64) -> Vec<LongLivedObject> {
+        let mut objects: Vec<_> = self.objects
+            .iter()
+            .map(|(&k, &v)| LongLivedObject {
+                object: k,
+                frames: self.frame - v,
+            })
+            .filter(|v| v.frames > frames)
+            .collect();
+
+        objects.sort_by(|lhs, rhs| rhs.frames.cmp(&lhs.frames));
+        objects
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use utils::handle::Handle;
+
+    #[test]
+    fn long_lived() {
+        let mesh = VideoObject::Mesh(Handle::new(1, 1).into());
+        let texture = VideoObject::Texture(Handle::new(1, 1).into());
+        let shader = VideoObject::Shader(Handle::new(1, 1).into());
+
+        let mut tracker = ObjectTracker::default();
+        tracker.create(mesh);
+        tracker.advance();
+        tracker.create(texture);
+        tracker.create(shader);
+        tracker.advance();
+        tracker.advance();
+        tracker.delete(shader);
+
+        let objects = tracker.long_lived(1);
+        assert_eq!(objects.len(), 2);
+        assert_eq!(objects[0].object, mesh);
+        assert_eq!(objects[0].frames, 3);
+        assert_eq!(objects[1].object, texture);
+        assert_eq!(objects[1].frames, 2);
+
+        assert_eq!(tracker.long_lived(2).len(), 1);
+        assert!(tracker.long_lived(3).is_empty());
+    }
+}
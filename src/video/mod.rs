@@ -212,6 +212,46 @@
 //! video.draw(surface, dc);
 //! ```
 //!
+//! Meshes could be drawn many times in one draw call with instancing, if the device
+//! supports it. The per-instance attributes, e.g. the model matrices, are stored in
+//! an instance buffer, and read by the attributes of shader which could not be found
+//! in the mesh:
+//!
+//! ```rust,ignore
+//! let mut params = InstanceBufferParams::default();
+//! params.layout = VertexLayout::build()
+//!     .with(Attribute::Texcoord1, VertexFormat::Float, 3, false)
+//!     .finish();
+//! params.num = 1024;
+//! let instances = video.create_instance_buffer(params, None)?;
+//! video.update_instance_buffer(instances, 0, offsets)?;
+//!
+//! let mut dc = DrawCall::new(shader, mesh);
+//! dc.set_instances(instances, 1024);
+//! video.draw(surface, dc);
+//! ```
+//!
+//! The uniform variables shared by many draw calls, e.g. the view and projection
+//! matrices of camera, could be uploaded once per frame into a uniform buffer, and
+//! bound to the slot which is declared for the uniform block in `ShaderParams`:
+//!
+//! ```rust,ignore
+//! let layout = UniformBlockLayout::build()
+//!     .with("u_View", UniformVariableType::Matrix4f)
+//!     .with("u_Projection", UniformVariableType::Matrix4f)
+//!     .finish()?;
+//!
+//! let mut params = UniformBufferParams::default();
+//! params.size = layout.size();
+//! let buffer = video.create_uniform_buffer(params, None)?;
+//!
+//! let mut block = UniformBlock::new(layout);
+//! block.set("u_View", view)?;
+//! block.set("u_Projection", projection)?;
+//! video.update_uniform_buffer(buffer, 0, block.as_bytes())?;
+//! video.bind_uniform_buffer(0, buffer)?;
+//! ```
+//!
 //! _TODO_: Batch
 //! _TODO_: OrderDrawBatch
 
@@ -219,37 +259,96 @@
 pub const MAX_VERTEX_ATTRIBUTES: usize = 12;
 /// Maximum number of attachments in framebuffer.
 pub const MAX_FRAMEBUFFER_ATTACHMENTS: usize = 8;
-/// Maximum number of uniform variables in shader.
-pub const MAX_UNIFORM_VARIABLES: usize = 32;
+/// Maximum number of uniform variables in shader. Every element of an uniform array
+/// counts as one variable.
+pub const MAX_UNIFORM_VARIABLES: usize = 64;
 /// Maximum number of textures in shader.
 pub const MAX_UNIFORM_TEXTURE_SLOTS: usize = 8;
+/// Maximum number of slots which uniform buffers could be bound to.
+pub const MAX_UNIFORM_BUFFER_SLOTS: usize = 8;
 
 #[macro_use]
 pub mod assets;
 pub mod batch;
+pub mod capabilities;
+pub mod culling;
 pub mod errors;
+pub mod lifetime;
+pub mod readback;
+pub mod sdf;
+pub mod temporary;
+pub mod timing;
 
 mod backends;
+mod upload;
 
 pub mod prelude {
     pub use super::assets::prelude::*;
     pub use super::batch::{Batch, DrawCall, OrderDrawBatch};
-    pub use super::{VideoFrameInfo, VideoSystem, VideoSystemShared};
+    pub use super::capabilities::{VideoApi, VideoCapabilities, VideoProfile};
+    pub use super::culling::{CullingFrameInfo, CullingInfo};
+    pub use super::lifetime::{LongLivedObject, VideoObject};
+    pub use super::readback::{Readback, ReadbackHandle};
+    pub use super::sdf::{
+        SdfAtlas, SdfFont, SdfGlyph, SdfQuads, SdfStyle, SdfText, SdfTextRenderer, SdfVertex,
+    };
+    pub use super::temporary::{PingPong, TemporaryRenderTarget};
+    pub use super::timing::{GpuFrameTimings, GpuTimings};
+    pub use super::{VideoFrameInfo, VideoParams, VideoSystem, VideoSystemShared};
 }
 
-use std::sync::{Arc, RwLock};
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex, RwLock};
 use std::time::Duration;
 
 use application::window::Window;
 use math;
+use res::vfs::FileSlice;
 use utils::object_pool;
 
 use self::assets::prelude::*;
+use self::backends::deletion::DeletionQueue;
 use self::backends::frame::*;
 use self::backends::gl::visitor::GLVisitor;
-use self::backends::Visitor;
+use self::backends::{UniformVar, Visitor};
 use self::batch::DrawCall;
+use self::capabilities::{VideoCapabilities, VideoProfile};
+use self::culling::{CullingFrameInfo, CullingInfo};
 use self::errors::*;
+use self::lifetime::{LongLivedObject, ObjectTracker, VideoObject};
+use self::readback::{Readback, ReadbackHandle};
+use self::temporary::{TemporaryPool, TemporaryRenderTarget};
+use self::timing::{GpuFrameTimings, GpuTimer, GpuTimings};
+use self::upload::UploadQueue;
+
+/// The setup of video system.
+#[derive(Debug, Copy, Clone)]
+pub struct VideoParams {
+    /// The maximum bytes of texture data uploaded in one frame. The data of textures
+    /// loaded asynchronously is queued and uploaded across frames. A value of 0 disables
+    /// the throttling.
+    pub upload_budget: usize,
+    /// The maximum bytes of a single upload, bigger textures are split into slices.
+    pub upload_slice: usize,
+    /// The feature set of video backend, which is only used when the `VideoSystem` is
+    /// created.
+    pub profile: VideoProfile,
+    /// The granularity of GPU timings, which are measured with timer queries if the
+    /// device supports it.
+    pub gpu_timings: GpuTimings,
+}
+
+impl Default for VideoParams {
+    fn default() -> Self {
+        VideoParams {
+            upload_budget: 4 * 1024 * 1024,
+            upload_slice: 512 * 1024,
+            profile: VideoProfile::Standard,
+            gpu_timings: GpuTimings::Disabled,
+        }
+    }
+}
 
 /// The information of video module during last frame.
 #[derive(Debug, Copy, Clone, Default)]
@@ -261,26 +360,43 @@ pub struct VideoFrameInfo {
     pub alive_shaders: u32,
     pub alive_meshes: u32,
     pub alive_textures: u32,
+    /// The estimated bytes of textures and render textures in video memory.
+    pub texture_memory: u64,
+    /// The number of deleted objects which are waiting for the frames in flight.
+    pub retiring: u32,
+    /// The bytes of texture data submitted during last frame.
+    pub uploaded: u64,
+    /// The bytes of texture data which are waiting for upload.
+    pub pending_uploads: u64,
+    /// The culling statistics reported by renderers, e.g. one for every camera.
+    pub culling: CullingFrameInfo,
 }
 
 /// The centralized management of video sub-system.
 pub struct VideoSystem {
     visitor: Box<Visitor>,
+    deletions: DeletionQueue,
     frames: Arc<DoubleFrame>,
     shared: Arc<VideoSystemShared>,
     last_dimensions: math::Vector2<u32>,
+    timer: GpuTimer,
+    gpu_timings: Option<GpuFrameTimings>,
 }
 
 impl VideoSystem {
-    /// Create a new `VideoSystem` with one `Window` context.
-    pub fn new(window: &Window) -> ::errors::Result<Self> {
+    /// Create a new `VideoSystem` with one `Window` context. The GLES 2.0 profile is
+    /// used on GLES 2.0 devices regardless of `profile`.
+    pub fn new(window: &Window, profile: VideoProfile) -> ::errors::Result<Self> {
         let frames = Arc::new(DoubleFrame::with_capacity(64 * 1024));
-        let shared = VideoSystemShared::new(frames.clone());
-        let visitor = unsafe { Box::new(GLVisitor::new(window)?) };
+        let visitor = unsafe { Box::new(GLVisitor::new(window, profile)?) };
+        let shared = VideoSystemShared::new(frames.clone(), visitor.capabilities());
 
         Ok(VideoSystem {
             last_dimensions: window.dimensions(),
             visitor: visitor,
+            deletions: DeletionQueue::default(),
+            timer: GpuTimer::default(),
+            gpu_timings: None,
 
             frames: frames,
             shared: Arc::new(shared),
@@ -289,13 +405,22 @@ impl VideoSystem {
 
     /// Creates a new headless `VideoSystem`.
     pub fn headless() -> Self {
+        Self::headless_with(VideoProfile::Standard)
+    }
+
+    /// Creates a new headless `VideoSystem` which reports the capabilities of
+    /// `profile`, e.g. no uniform buffers and instancing with `VideoProfile::Gles2`.
+    pub fn headless_with(profile: VideoProfile) -> Self {
         let frames = Arc::new(DoubleFrame::with_capacity(0));
-        let shared = VideoSystemShared::new(frames.clone());
-        let visitor = backends::headless::HeadlessVisitor::new();
+        let visitor = backends::headless::HeadlessVisitor::with_profile(profile);
+        let shared = VideoSystemShared::new(frames.clone(), visitor.capabilities());
 
         VideoSystem {
             last_dimensions: (0, 0).into(),
             visitor: Box::new(visitor),
+            deletions: DeletionQueue::default(),
+            timer: GpuTimer::default(),
+            gpu_timings: None,
             frames: frames,
             shared: Arc::new(shared),
         }
@@ -310,17 +435,23 @@ impl VideoSystem {
     #[inline]
     pub fn swap_frames(&self) {
         self.frames.swap_frames();
+
+        let mut culling = self.shared.culling.lock().unwrap();
+        culling.1 = culling.0;
+        culling.0 = CullingFrameInfo::default();
     }
 
     /// Advance to next frame.
     ///
-    /// Notes that this method MUST be called at main thread, and will NOT return
-    /// until all commands is finished by GPU.
+    /// Notes that this method MUST be called at main thread. The commands are flushed
+    /// to GPU without waiting for them, and the deleted objects are retired until the
+    /// frames which might reference them are completed.
     pub fn advance(&mut self, window: &Window) -> ::errors::Result<VideoFrameInfo> {
         use std::time;
 
         let ts = time::Instant::now();
         let dimensions = window.dimensions();
+        let (uploaded, pending_uploads) = self.shared.advance_uploads();
 
         // Resize the window, which would recreate the underlying framebuffer.
         if dimensions != self.last_dimensions {
@@ -328,9 +459,24 @@ impl VideoSystem {
             window.resize(dimensions);
         }
 
-        let (dc, tris) = self.frames
-            .back()
-            .dispatch(self.visitor.as_mut(), dimensions)?;
+        // Polls the reads of previous frames before dispatching new ones, so the pixels
+        // are always delivered at least one frame later without stalling.
+        let readbacks = unsafe { self.visitor.readbacks()? };
+        self.shared.finish_readbacks(readbacks.into_iter());
+
+        self.timer.set_mode(self.shared.params().gpu_timings);
+        let (dc, tris) = self.frames.back().dispatch(
+            self.visitor.as_mut(),
+            &mut self.deletions,
+            &mut self.timer,
+            dimensions,
+        )?;
+
+        let stamps = unsafe { self.visitor.timestamps()? };
+        self.gpu_timings = self.timer.resolve(&stamps);
+
+        self.shared.advance_temporaries();
+        self.shared.lifetimes.lock().unwrap().advance();
         let mut info = VideoFrameInfo::default();
 
         {
@@ -339,13 +485,44 @@ impl VideoSystem {
             info.alive_shaders = s.shaders.write().unwrap().len() as u32;
             info.alive_meshes = s.meshes.write().unwrap().len() as u32;
             info.alive_textures = s.textures.write().unwrap().len() as u32;
+            info.texture_memory = s.texture_memory();
+            info.retiring = self.deletions.len() as u32;
             info.drawcall = dc;
             info.triangles = tris;
+            info.uploaded = uploaded;
+            info.pending_uploads = pending_uploads;
+            info.culling = s.culling.lock().unwrap().1;
         }
 
         info.duration = time::Instant::now() - ts;
         Ok(info)
     }
+
+    /// Deletes the temporary render targets which are not borrowed, they are deleted
+    /// with the commands of current frame.
+    pub fn release_transient(&self) {
+        self.shared.purge_temporaries();
+    }
+
+    /// Blocks until all the dispatched commands are completed by GPU, and deletes the
+    /// retired objects immediately. It should be called before the application is
+    /// paused in background, since iOS terminates the applications which issue OpenGL
+    /// ES commands in background.
+    pub fn finish(&mut self) -> ::errors::Result<()> {
+        unsafe {
+            self.visitor.finish()?;
+            self.deletions.drain(self.visitor.as_mut());
+        }
+
+        Ok(())
+    }
+
+    /// Takes the GPU timings of last frame, which is `None` if the GPU timings are
+    /// disabled or not supported by the device.
+    #[inline]
+    pub fn take_gpu_timings(&mut self) -> Option<GpuFrameTimings> {
+        self.gpu_timings.take()
+    }
 }
 
 enum AsyncState<T> {
@@ -356,42 +533,179 @@ enum AsyncState<T> {
 /// The multi-thread friendly parts of `VideoSystem`.
 pub struct VideoSystemShared {
     pub(crate) frames: Arc<DoubleFrame>,
+    capabilities: VideoCapabilities,
+    params: RwLock<VideoParams>,
 
-    textures: RwLock<object_pool::ObjectPool<AsyncState<()>>>,
+    textures: RwLock<object_pool::ObjectPool<AsyncState<u32>>>,
     surfaces: RwLock<object_pool::ObjectPool<SurfaceParams>>,
     shaders: RwLock<object_pool::ObjectPool<ShaderParams>>,
+    builtin_shaders: RwLock<HashMap<BuiltinShader, ShaderHandle>>,
     render_textures: RwLock<object_pool::ObjectPool<RenderTextureParams>>,
+    temporaries: Mutex<TemporaryPool>,
+    lifetimes: Mutex<ObjectTracker>,
     meshes: RwLock<object_pool::ObjectPool<AsyncState<MeshParams>>>,
+    instance_buffers: RwLock<object_pool::ObjectPool<InstanceBufferParams>>,
+    uniform_buffers: RwLock<object_pool::ObjectPool<UniformBufferParams>>,
+    uploads: Mutex<UploadQueue>,
+    // Skips the lookups of pending uploads when drawing, if there is nothing to upload.
+    uploading: AtomicBool,
+    // The culling statistics of the frame being submitted, and the last one.
+    culling: Mutex<(CullingFrameInfo, CullingFrameInfo)>,
+    // The pending reads of surfaces, and their pixels once finished.
+    readbacks: Mutex<object_pool::ObjectPool<(math::Aabb2<u32>, Option<Vec<u8>>)>>,
 }
 
 impl VideoSystemShared {
     /// Create a new `VideoSystem` with one `Window` context.
-    fn new(frames: Arc<DoubleFrame>) -> Self {
+    fn new(frames: Arc<DoubleFrame>, capabilities: VideoCapabilities) -> Self {
         VideoSystemShared {
             frames: frames,
+            capabilities: capabilities,
+            params: RwLock::new(VideoParams::default()),
 
             surfaces: RwLock::new(object_pool::ObjectPool::new()),
             shaders: RwLock::new(object_pool::ObjectPool::new()),
+            builtin_shaders: RwLock::new(HashMap::new()),
             meshes: RwLock::new(object_pool::ObjectPool::new()),
+            instance_buffers: RwLock::new(object_pool::ObjectPool::new()),
+            uniform_buffers: RwLock::new(object_pool::ObjectPool::new()),
             textures: RwLock::new(object_pool::ObjectPool::new()),
             render_textures: RwLock::new(object_pool::ObjectPool::new()),
+            temporaries: Mutex::new(TemporaryPool::default()),
+            lifetimes: Mutex::new(ObjectTracker::default()),
+            uploads: Mutex::new(UploadQueue::default()),
+            uploading: AtomicBool::new(false),
+            culling: Mutex::new(Default::default()),
+            readbacks: Mutex::new(object_pool::ObjectPool::new()),
         }
     }
 
+    /// Gets the setup of video system.
+    #[inline]
+    pub fn params(&self) -> VideoParams {
+        *self.params.read().unwrap()
+    }
+
+    /// Changes the setup of video system, it takes effect from next frame.
+    pub fn set_params(&self, params: VideoParams) {
+        *self.params.write().unwrap() = params;
+    }
+
+    /// Reports the culling statistics of a view with id, e.g. a camera, which are
+    /// collected into the `VideoFrameInfo` of this frame. The reports of the same view
+    /// are merged.
+    pub fn report_culling(&self, view: u64, info: CullingInfo) {
+        self.culling.lock().unwrap().0.push(view, info);
+    }
+
+    /// Reads the RGBA8 pixels in `area` of surface asynchronously, after the draw calls
+    /// submitted before. The area is in pixels starting from the bottom-left corner.
+    ///
+    /// The GPU copies the pixels without stalling the frame, and they could be polled
+    /// with `readback` one frame later at the earliest.
+    pub fn read_pixels(
+        &self,
+        surface: SurfaceHandle,
+        area: math::Aabb2<u32>,
+    ) -> Result<ReadbackHandle> {
+        if !self.surfaces.read().unwrap().is_alive(surface) {
+            bail!("{:?} is invalid.", surface);
+        }
+
+        let handle = self.readbacks.lock().unwrap().create((area, None)).into();
+        let cmd = Command::ReadPixels(surface, area, handle);
+        self.frames.front().cmds.push(cmd);
+        Ok(handle)
+    }
+
+    /// Takes the pixels of a finished read, returns `None` if it's still pending. The
+    /// handle is freed once the pixels are taken.
+    pub fn readback(&self, handle: ReadbackHandle) -> Option<Readback> {
+        let mut readbacks = self.readbacks.lock().unwrap();
+        if let Some(&(_, None)) = readbacks.get(handle) {
+            return None;
+        }
+
+        readbacks
+            .free(handle)
+            .and_then(|(area, bytes)| bytes.map(|v| Readback { area: area, bytes: v }))
+    }
+
+    /// Discards a pending or finished read.
+    pub fn delete_readback(&self, handle: ReadbackHandle) {
+        self.readbacks.lock().unwrap().free(handle);
+    }
+
+    fn finish_readbacks<T>(&self, finished: T)
+    where
+        T: Iterator<Item = (ReadbackHandle, Vec<u8>)>,
+    {
+        let mut readbacks = self.readbacks.lock().unwrap();
+        for (handle, bytes) in finished {
+            if let Some(v) = readbacks.get_mut(handle) {
+                v.1 = Some(bytes);
+            }
+        }
+    }
+
+    /// Gets the capabilities of underlying video device.
+    #[inline]
+    pub fn capabilities(&self) -> &VideoCapabilities {
+        &self.capabilities
+    }
+
     /// Draws ur mesh.
     ///
     /// Notes that you should use [Batch](crate::video::batch::Batch) if possible.
     #[inline]
     pub fn draw(&self, handle: SurfaceHandle, dc: DrawCall) {
+        if let Err(err) = self.validate_drawcall(&dc) {
+            error!("Failed to draw {:?}, {}", dc.mesh, err);
+            return;
+        }
+
+        if self.uploading.load(Ordering::Relaxed) {
+            self.prioritize_uniforms(&dc.uniforms[0..dc.uniforms_len]);
+        }
+
         let mut frame = self.frames.front();
         let len = dc.uniforms_len;
         let ptr = frame.bufs.extend_from_slice(&dc.uniforms[0..len]);
-        let cmd = Command::Draw(dc.shader, dc.mesh, dc.mesh_index, ptr);
+        let cmd = dc.build(ptr);
 
         frame.cmds.push(Command::Bind(handle));
         frame.cmds.push(cmd);
     }
 
+    /// Checks if the uniform variables of drawcall match the declarations of its shader,
+    /// e.g. a `Vector3f` is provided for a `Matrix4f` uniform.
+    pub fn validate_drawcall(&self, dc: &DrawCall) -> Result<()> {
+        if let Some((buffer, _)) = dc.instances {
+            if !self.instance_buffers.read().unwrap().is_alive(buffer) {
+                return Err(Error::HandleInvalid(format!("{:?}", buffer)));
+            }
+        }
+
+        self.validate_uniforms(dc.shader, &dc.uniforms[0..dc.uniforms_len])
+    }
+
+    pub(crate) fn validate_uniforms(
+        &self,
+        shader: ShaderHandle,
+        vars: &[UniformVar],
+    ) -> Result<()> {
+        let shaders = self.shaders.read().unwrap();
+        let params = shaders
+            .get(shader)
+            .ok_or_else(|| Error::HandleInvalid(format!("{:?}", shader)))?;
+
+        for &(field, ref variable) in vars {
+            params.uniforms.validate(shader, field, variable)?;
+        }
+
+        Ok(())
+    }
+
     /// Updates the scissor test of surface.
     ///
     /// The test is initially disabled. While the test is enabled, only pixels that lie within
@@ -423,6 +737,7 @@ impl VideoSystemShared {
     /// Creates an surface with `SurfaceParams`.
     pub fn create_surface(&self, params: SurfaceParams) -> Result<SurfaceHandle> {
         let handle = self.surfaces.write().unwrap().create(params).into();
+        self.track(VideoObject::Surface(handle));
 
         {
             let cmd = Command::CreateSurface(handle, params);
@@ -440,6 +755,7 @@ impl VideoSystemShared {
     /// Deletes surface object.
     pub fn delete_surface(&self, handle: SurfaceHandle) {
         if self.surfaces.write().unwrap().free(handle).is_some() {
+            self.untrack(VideoObject::Surface(handle));
             let cmd = Command::DeleteSurface(handle);
             self.frames.front().cmds.push(cmd);
         }
@@ -458,6 +774,7 @@ impl VideoSystemShared {
         params.validate(&vs, &fs)?;
 
         let handle = self.shaders.write().unwrap().create(params.clone()).into();
+        self.track(VideoObject::Shader(handle));
 
         {
             let cmd = Command::CreateShader(handle, params, vs, fs);
@@ -467,14 +784,58 @@ impl VideoSystemShared {
         Ok(handle)
     }
 
+    /// Gets the built-in shader. It's created at the first request, and shared by all
+    /// the later requests.
+    pub fn builtin_shader(&self, shader: BuiltinShader) -> Result<ShaderHandle> {
+        let mut builtins = self.builtin_shaders.write().unwrap();
+        if let Some(&handle) = builtins.get(&shader) {
+            if self.shaders.read().unwrap().is_alive(handle) {
+                return Ok(handle);
+            }
+        }
+
+        let (vs, fs) = shader.sources();
+        let handle = self.create_shader(shader.params(), vs.to_owned(), fs.to_owned())?;
+        builtins.insert(shader, handle);
+        Ok(handle)
+    }
+
+    /// Finds the built-in shader by its name, e.g. `builtin/unlit_color`.
+    pub fn find_shader(&self, name: &str) -> Result<ShaderHandle> {
+        match name.parse::<BuiltinShader>() {
+            Ok(v) => self.builtin_shader(v),
+            Err(_) => Err(Error::HandleInvalid(format!("Shader({})", name))),
+        }
+    }
+
+    /// Recompiles the shader with new sources, e.g. when the sources are edited in
+    /// hot-reload mode. The shader keeps its previous program if the new sources could
+    /// not be compiled.
+    pub fn update_shader(&self, handle: ShaderHandle, vs: String, fs: String) -> Result<()> {
+        let params = self
+            .shaders
+            .read()
+            .unwrap()
+            .get(handle)
+            .cloned()
+            .ok_or_else(|| Error::HandleInvalid(format!("{:?}", handle)))?;
+
+        params.validate(&vs, &fs)?;
+
+        let cmd = Command::UpdateShader(handle, params, vs, fs);
+        self.frames.front().cmds.push(cmd);
+        Ok(())
+    }
+
     /// Gets the `ShaderParams` if available.
-    pub fn shader(&self, handle: MeshHandle) -> Option<ShaderParams> {
+    pub fn shader(&self, handle: ShaderHandle) -> Option<ShaderParams> {
         self.shaders.read().unwrap().get(handle).cloned()
     }
 
     /// Delete shader state object.
     pub fn delete_shader(&self, handle: ShaderHandle) {
         if self.shaders.write().unwrap().free(handle).is_some() {
+            self.untrack(VideoObject::Shader(handle));
             let cmd = Command::DeleteShader(handle);
             self.frames.front().cmds.push(cmd);
         }
@@ -495,6 +856,7 @@ impl VideoSystemShared {
             .unwrap()
             .create(AsyncState::Ok(params.clone()))
             .into();
+        self.track(VideoObject::Mesh(handle));
 
         {
             let mut frame = self.frames.front();
@@ -516,6 +878,28 @@ impl VideoSystemShared {
         })
     }
 
+    /// Gets the vertex layout of mesh if available.
+    pub fn mesh_layout(&self, handle: MeshHandle) -> Option<VertexLayout> {
+        self.meshes.read().unwrap().get(handle).and_then(|v| {
+            if let AsyncState::Ok(v) = v {
+                Some(v.layout)
+            } else {
+                None
+            }
+        })
+    }
+
+    /// Gets the number of sub-mesh sections if available.
+    pub fn mesh_sub_meshes(&self, handle: MeshHandle) -> Option<usize> {
+        self.meshes.read().unwrap().get(handle).and_then(|v| {
+            if let AsyncState::Ok(v) = v {
+                Some(v.num_sub_meshes())
+            } else {
+                None
+            }
+        })
+    }
+
     /// Update a subset of dynamic vertex buffer. Use `offset` specifies the offset
     /// into the buffer object's data store where data replacement will begin, measured
     /// in bytes.
@@ -560,6 +944,7 @@ impl VideoSystemShared {
     /// Delete mesh object.
     pub fn delete_mesh(&self, handle: MeshHandle) {
         if self.meshes.write().unwrap().free(handle).is_some() {
+            self.untrack(VideoObject::Mesh(handle));
             let cmd = Command::DeleteMesh(handle);
             self.frames.front().cmds.push(cmd);
         }
@@ -571,6 +956,7 @@ impl VideoSystemShared {
             .unwrap()
             .create(AsyncState::NotReady)
             .into();
+        self.track(VideoObject::Mesh(handle));
 
         Ok(handle)
     }
@@ -593,6 +979,213 @@ impl VideoSystemShared {
         // Its ok since the video resource might be freed before this call.
         Ok(())
     }
+
+    /// Updates the mesh with the vertices and indices in shared file contents. The
+    /// slices are uploaded to GPU directly, without an intermediate copy.
+    pub(crate) fn update_mesh_shared(
+        &self,
+        handle: MeshHandle,
+        params: MeshParams,
+        verts: FileSlice,
+        idxes: FileSlice,
+    ) -> Result<()> {
+        if verts.len() > params.vertex_buffer_len() || idxes.len() > params.index_buffer_len() {
+            return Err(Error::OutOfBounds);
+        }
+
+        params.validate(None)?;
+
+        if let Some(v) = self.meshes.write().unwrap().get_mut(handle) {
+            let mut frame = self.frames.front();
+            let task = Command::CreateMeshShared(handle, params.clone(), verts, idxes);
+            frame.cmds.push(task);
+            *v = AsyncState::Ok(params);
+        }
+
+        // Its ok since the video resource might be freed before this call.
+        Ok(())
+    }
+}
+
+impl VideoSystemShared {
+    /// Creates a new instance buffer object, which holds the per-instance attributes of
+    /// instanced draws, e.g. the model matrices of instances.
+    ///
+    /// Notes that instancing is not available with `VideoProfile::Gles2`, which could
+    /// be checked with `VideoCapabilities::instancing`. It returns an error immediately
+    /// on such devices.
+    pub fn create_instance_buffer<'a, T>(
+        &self,
+        params: InstanceBufferParams,
+        data: T,
+    ) -> Result<InstanceBufferHandle>
+    where
+        T: Into<Option<&'a [u8]>>,
+    {
+        if !self.capabilities.instancing {
+            return Err(Error::FeatureUnsupported("Instance buffers"));
+        }
+
+        let data = data.into();
+        params.validate(data)?;
+
+        let handle = self.instance_buffers.write().unwrap().create(params).into();
+        self.track(VideoObject::InstanceBuffer(handle));
+
+        {
+            let mut frame = self.frames.front();
+            let ptr = data.map(|v| frame.bufs.extend_from_slice(v));
+            let cmd = Command::CreateInstanceBuffer(handle, params, ptr);
+            frame.cmds.push(cmd);
+        }
+
+        Ok(handle)
+    }
+
+    /// Gets the `InstanceBufferParams` if available.
+    pub fn instance_buffer(&self, handle: InstanceBufferHandle) -> Option<InstanceBufferParams> {
+        self.instance_buffers.read().unwrap().get(handle).cloned()
+    }
+
+    /// Update a subset of dynamic instance buffer. Use `offset` specifies the offset
+    /// into the buffer object's data store where data replacement will begin, measured
+    /// in bytes.
+    pub fn update_instance_buffer(
+        &self,
+        handle: InstanceBufferHandle,
+        offset: usize,
+        data: &[u8],
+    ) -> Result<()> {
+        if let Some(params) = self.instance_buffers.read().unwrap().get(handle) {
+            if params.hint == MeshHint::Immutable {
+                return Err(Error::UpdateImmutableBuffer);
+            }
+
+            if offset + data.len() > params.buffer_len() {
+                return Err(Error::OutOfBounds);
+            }
+
+            let mut frame = self.frames.front();
+            let ptr = frame.bufs.extend_from_slice(data);
+            let cmd = Command::UpdateInstanceBuffer(handle, offset, ptr);
+            frame.cmds.push(cmd);
+            Ok(())
+        } else {
+            Err(Error::HandleInvalid(format!("{:?}", handle)))
+        }
+    }
+
+    /// Delete instance buffer object.
+    pub fn delete_instance_buffer(&self, handle: InstanceBufferHandle) {
+        let params = self.instance_buffers.write().unwrap().free(handle);
+        if params.is_some() {
+            self.untrack(VideoObject::InstanceBuffer(handle));
+            let cmd = Command::DeleteInstanceBuffer(handle);
+            self.frames.front().cmds.push(cmd);
+        }
+    }
+}
+
+impl VideoSystemShared {
+    /// Creates a new uniform buffer object, which holds the variables of uniform blocks
+    /// shared between shaders, e.g. the view and projection matrices of camera.
+    ///
+    /// Notes that uniform buffers are not available with `VideoProfile::Gles2`, which
+    /// could be checked with `VideoCapabilities::uniform_buffer`. It returns an error
+    /// immediately on such devices.
+    pub fn create_uniform_buffer<'a, T>(
+        &self,
+        params: UniformBufferParams,
+        data: T,
+    ) -> Result<UniformBufferHandle>
+    where
+        T: Into<Option<&'a [u8]>>,
+    {
+        if !self.capabilities.uniform_buffer {
+            return Err(Error::FeatureUnsupported("Uniform buffers"));
+        }
+
+        let data = data.into();
+        params.validate(data)?;
+
+        let handle = self.uniform_buffers.write().unwrap().create(params).into();
+        self.track(VideoObject::UniformBuffer(handle));
+
+        {
+            let mut frame = self.frames.front();
+            let ptr = data.map(|v| frame.bufs.extend_from_slice(v));
+            let cmd = Command::CreateUniformBuffer(handle, params, ptr);
+            frame.cmds.push(cmd);
+        }
+
+        Ok(handle)
+    }
+
+    /// Gets the `UniformBufferParams` if available.
+    pub fn uniform_buffer(&self, handle: UniformBufferHandle) -> Option<UniformBufferParams> {
+        self.uniform_buffers.read().unwrap().get(handle).cloned()
+    }
+
+    /// Update a subset of dynamic uniform buffer, which is usually the bytes of
+    /// `UniformBlock`. Use `offset` specifies the offset into the buffer object's data
+    /// store where data replacement will begin, measured in bytes.
+    pub fn update_uniform_buffer(
+        &self,
+        handle: UniformBufferHandle,
+        offset: usize,
+        data: &[u8],
+    ) -> Result<()> {
+        if let Some(params) = self.uniform_buffers.read().unwrap().get(handle) {
+            if params.hint == MeshHint::Immutable {
+                return Err(Error::UpdateImmutableBuffer);
+            }
+
+            if offset + data.len() > params.size {
+                return Err(Error::OutOfBounds);
+            }
+
+            let mut frame = self.frames.front();
+            let ptr = frame.bufs.extend_from_slice(data);
+            let cmd = Command::UpdateUniformBuffer(handle, offset, ptr);
+            frame.cmds.push(cmd);
+            Ok(())
+        } else {
+            Err(Error::HandleInvalid(format!("{:?}", handle)))
+        }
+    }
+
+    /// Binds the uniform buffer to `slot`, or unbinds the slot with `None`. The uniform
+    /// blocks declared with the same slot in `ShaderParams::uniform_blocks` read from
+    /// this buffer in all the draw calls submitted after this call.
+    pub fn bind_uniform_buffer<T>(&self, slot: usize, buffer: T) -> Result<()>
+    where
+        T: Into<Option<UniformBufferHandle>>,
+    {
+        if slot >= MAX_UNIFORM_BUFFER_SLOTS {
+            return Err(Error::OutOfBounds);
+        }
+
+        let buffer = buffer.into();
+        if let Some(handle) = buffer {
+            if !self.uniform_buffers.read().unwrap().is_alive(handle) {
+                return Err(Error::HandleInvalid(format!("{:?}", handle)));
+            }
+        }
+
+        let cmd = Command::BindUniformBuffer(slot, buffer);
+        self.frames.front().cmds.push(cmd);
+        Ok(())
+    }
+
+    /// Delete uniform buffer object.
+    pub fn delete_uniform_buffer(&self, handle: UniformBufferHandle) {
+        let params = self.uniform_buffers.write().unwrap().free(handle);
+        if params.is_some() {
+            self.untrack(VideoObject::UniformBuffer(handle));
+            let cmd = Command::DeleteUniformBuffer(handle);
+            self.frames.front().cmds.push(cmd);
+        }
+    }
 }
 
 impl VideoSystemShared {
@@ -605,11 +1198,13 @@ impl VideoSystemShared {
         let data = data.into();
         params.validate(data.as_ref())?;
 
+        let size = texture_size(&params, data.as_ref());
         let handle = self.textures
             .write()
             .unwrap()
-            .create(AsyncState::Ok(()))
+            .create(AsyncState::Ok(size))
             .into();
+        self.track(VideoObject::Texture(handle));
 
         {
             let mut frame = self.frames.front();
@@ -639,9 +1234,18 @@ impl VideoSystemShared {
         }
     }
 
+    /// Uploads the pending data of texture before the others, returns false if there is
+    /// nothing to upload. The textures used by draw calls are prioritized automatically,
+    /// this is useful to upload the textures which are going to be visible in advance.
+    pub fn prioritize_texture(&self, handle: TextureHandle) -> bool {
+        self.uploading.load(Ordering::Relaxed) && self.uploads.lock().unwrap().prioritize(handle)
+    }
+
     /// Delete the texture object.
     pub fn delete_texture(&self, handle: TextureHandle) {
         if self.textures.write().unwrap().free(handle).is_some() {
+            self.untrack(VideoObject::Texture(handle));
+            self.uploads.lock().unwrap().remove(handle);
             let cmd = Command::DeleteTexture(handle);
             self.frames.front().cmds.push(cmd);
         }
@@ -653,6 +1257,7 @@ impl VideoSystemShared {
             .unwrap()
             .create(AsyncState::NotReady)
             .into();
+        self.track(VideoObject::Texture(handle));
 
         Ok(handle)
     }
@@ -666,10 +1271,22 @@ impl VideoSystemShared {
         params.validate(Some(&data))?;
 
         if let Some(v) = self.textures.write().unwrap().get_mut(handle) {
-            let mut frame = self.frames.front();
-            let task = Command::CreateTexture(handle, params, Some(data));
-            frame.cmds.push(task);
-            *v = AsyncState::Ok(());
+            *v = AsyncState::Ok(texture_size(&params, Some(&data)));
+
+            // Drops the pending slices of previous data if the texture is reloaded.
+            self.uploads.lock().unwrap().remove(handle);
+
+            if self.params().upload_budget == 0 {
+                let task = Command::CreateTexture(handle, params, Some(data));
+                self.frames.front().cmds.push(task);
+            } else {
+                // The texture must be created before the slices are submitted.
+                let task = Command::CreateTexture(handle, params, None);
+                self.frames.front().cmds.push(task);
+
+                self.uploads.lock().unwrap().push(handle, params, data);
+                self.uploading.store(true, Ordering::Relaxed);
+            }
         }
 
         // Its ok since the video resource might be freed before this call.
@@ -677,6 +1294,52 @@ impl VideoSystemShared {
     }
 }
 
+impl VideoSystemShared {
+    // Submits the pending texture data within budget, returns the bytes submitted and
+    // the bytes remaining.
+    fn advance_uploads(&self) -> (u64, u64) {
+        if !self.uploading.load(Ordering::Relaxed) {
+            return (0, 0);
+        }
+
+        let params = self.params();
+        let budget = if params.upload_budget == 0 {
+            ::std::usize::MAX
+        } else {
+            params.upload_budget
+        };
+
+        let mut uploads = self.uploads.lock().unwrap();
+        let mut frame = self.frames.front();
+
+        let uploaded = uploads.advance(budget, params.upload_slice, |v| {
+            let ptr = frame.bufs.extend_from_slice(v.bytes);
+            let cmd = Command::UploadTexture(v.handle, (v.level, v.levels), v.area, ptr);
+            frame.cmds.push(cmd);
+        });
+
+        self.uploading.store(!uploads.is_empty(), Ordering::Relaxed);
+        (uploaded, uploads.pending())
+    }
+
+    fn prioritize_uniforms(&self, vars: &[UniformVar]) {
+        for &(_, ref v) in vars {
+            if let UniformVariable::Texture(handle) = *v {
+                self.uploads.lock().unwrap().prioritize(handle);
+            }
+        }
+    }
+}
+
+// The bytes of texture with all the mipmaps, or only the base level if the data
+// is not provided.
+fn texture_size(params: &TextureParams, data: Option<&TextureData>) -> u32 {
+    match data {
+        Some(data) => data.bytes.iter().map(|v| v.len() as u32).sum(),
+        None => params.format.size(params.dimensions),
+    }
+}
+
 impl VideoSystemShared {
     /// Create render texture object, which could be attached with a framebuffer.
     pub fn create_render_texture(
@@ -684,6 +1347,7 @@ impl VideoSystemShared {
         params: RenderTextureParams,
     ) -> Result<RenderTextureHandle> {
         let handle = self.render_textures.write().unwrap().create(params).into();
+        self.track(VideoObject::RenderTexture(handle));
 
         {
             let cmd = Command::CreateRenderTexture(handle, params);
@@ -698,11 +1362,109 @@ impl VideoSystemShared {
         self.render_textures.read().unwrap().get(handle).cloned()
     }
 
+    /// Gets the estimated bytes of alive textures and render textures in video memory.
+    pub fn texture_memory(&self) -> u64 {
+        let mut bytes = 0;
+
+        let textures = self.textures.read().unwrap();
+        for v in textures.iter() {
+            if let Some(&AsyncState::Ok(size)) = textures.get(v) {
+                bytes += u64::from(size);
+            }
+        }
+
+        let render_textures = self.render_textures.read().unwrap();
+        for v in render_textures.iter() {
+            if let Some(params) = render_textures.get(v) {
+                bytes += u64::from(params.format.size(params.dimensions));
+            }
+        }
+
+        bytes
+    }
+
     /// Delete the render texture object.
     pub fn delete_render_texture(&self, handle: RenderTextureHandle) {
         if self.render_textures.write().unwrap().free(handle).is_some() {
+            self.untrack(VideoObject::RenderTexture(handle));
             let cmd = Command::DeleteRenderTexture(handle);
             self.frames.front().cmds.push(cmd);
         }
     }
+    /// Borrows a temporary render target from pool. Targets released in previous
+    /// frames are reused if they have the same `params`, otherwise a new render texture
+    /// and the surface which draws into it are created.
+    ///
+    /// The target should be returned with `release_temporary_rt` once its done.
+    pub fn get_temporary_rt(&self, params: RenderTextureParams) -> Result<TemporaryRenderTarget> {
+        if let Some(v) = self.temporaries.lock().unwrap().acquire(params) {
+            return Ok(v);
+        }
+
+        let texture = self.create_render_texture(params)?;
+
+        let mut setup = SurfaceParams::default();
+        if params.format.is_color() {
+            setup.set_attachments(&[texture], None)?;
+        } else {
+            setup.set_attachments(&[], texture)?;
+        }
+
+        let surface = match self.create_surface(setup) {
+            Ok(v) => v,
+            Err(err) => {
+                self.delete_render_texture(texture);
+                return Err(err);
+            }
+        };
+
+        let target = TemporaryRenderTarget {
+            texture: texture,
+            surface: surface,
+        };
+
+        self.temporaries.lock().unwrap().insert(params, target);
+        Ok(target)
+    }
+
+    /// Returns the temporary render target into pool.
+    pub fn release_temporary_rt(&self, target: TemporaryRenderTarget) {
+        if !self.temporaries.lock().unwrap().release(target) {
+            warn!("{:?} is not a borrowed temporary render target.", target);
+        }
+    }
+
+    /// Lists the objects which have been alive for more than `frames` frames, the
+    /// oldest comes first. It helps to find the leaked objects which are never deleted.
+    pub fn long_lived_objects(&self, frames: u64) -> Vec<LongLivedObject> {
+        self.lifetimes.lock().unwrap().long_lived(frames)
+    }
+
+    #[inline]
+    fn track(&self, object: VideoObject) {
+        self.lifetimes.lock().unwrap().create(object);
+    }
+
+    #[inline]
+    fn untrack(&self, object: VideoObject) {
+        self.lifetimes.lock().unwrap().delete(object);
+    }
+
+    // Deletes the temporary render targets which are not borrowed.
+    fn purge_temporaries(&self) {
+        let purged = self.temporaries.lock().unwrap().purge();
+        for v in purged {
+            self.delete_surface(v.surface);
+            self.delete_render_texture(v.texture);
+        }
+    }
+
+    // Deletes the temporary render targets which are unused for a while.
+    fn advance_temporaries(&self) {
+        let expired = self.temporaries.lock().unwrap().advance();
+        for v in expired {
+            self.delete_surface(v.surface);
+            self.delete_render_texture(v.texture);
+        }
+    }
 }
@@ -0,0 +1,61 @@
+//! Reads the pixels of surfaces back into memory asynchronously.
+//!
+//! The read is recorded in the command frame like draw calls, so it happens after
+//! everything submitted before it. The pixels are usually available in the next frame,
+//! and are polled without blocking:
+//!
+//! ```rust,ignore
+//! let area = math::Aabb2::new(math::Point2::new(0, 0), math::Point2::new(64, 64));
+//! let handle = video.read_pixels(surface, area)?;
+//!
+//! // In later frames.
+//! if let Some(readback) = video.readback(handle) {
+//!     // readback.bytes ...
+//! }
+//! ```
+
+use math;
+
+impl_handle!(ReadbackHandle);
+
+/// The pixels read back from a surface.
+#[derive(Debug, Clone)]
+pub struct Readback {
+    /// The area of surface in pixels, starting from the bottom-left corner.
+    pub area: math::Aabb2<u32>,
+    /// The RGBA8 pixels in rows from bottom to top.
+    pub bytes: Vec<u8>,
+}
+
+impl Readback {
+    /// Gets the RGBA8 pixel at the position relative to the bottom-left corner of
+    /// area.
+    pub fn pixel(&self, x: u32, y: u32) -> Option<[u8; 4]> {
+        let dim = self.area.dim();
+        if x >= dim.x || y >= dim.y {
+            return None;
+        }
+
+        let i = ((y * dim.x + x) * 4) as usize;
+        self.bytes.get(i..(i + 4)).map(|v| [v[0], v[1], v[2], v[3]])
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn pixel() {
+        let area = math::Aabb2::new(math::Point2::new(2, 2), math::Point2::new(4, 3));
+        let readback = Readback {
+            area: area,
+            bytes: (0..8).collect(),
+        };
+
+        assert_eq!(readback.pixel(0, 0), Some([0, 1, 2, 3]));
+        assert_eq!(readback.pixel(1, 0), Some([4, 5, 6, 7]));
+        assert_eq!(readback.pixel(0, 1), None);
+        assert_eq!(readback.pixel(2, 0), None);
+    }
+}
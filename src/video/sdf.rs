@@ -0,0 +1,765 @@
+//! Signed distance fields, which keep text, icons and vector-ish shapes crisp at any
+//! scale, and make outlines, glows and drop shadows cheap.
+//!
+//! The distance field is generated from coverage bitmaps (e.g. rasterized glyphs) on
+//! CPU, packed into a `SdfAtlas`, and drawn as quads with `BuiltinShader::UiSdf`.
+//!
+//! ```rust,ignore
+//! let mut atlas = SdfAtlas::new((512, 512).into(), 4.0);
+//! let uv = atlas.add('A', &glyph_bitmap).unwrap();
+//!
+//! let (params, data) = atlas.image().clone().into_texture(false);
+//! let texture = video.create_texture(params, data)?;
+//!
+//! let mut quads = SdfQuads::new();
+//! quads.push(math::RectF::new(10.0, 10.0, 32.0, 32.0), uv, math::Color::white());
+//! let (params, data) = quads.mesh(MeshHint::Immutable);
+//! let mesh = video.create_mesh(params, data)?;
+//!
+//! let mut dc = DrawCall::new(video.builtin_shader(BuiltinShader::UiSdf)?, mesh);
+//! dc.set_uniform_variable("u_ProjMatrix", proj);
+//! dc.set_uniform_variable("u_Texture", texture);
+//! SdfStyle::default().apply(&mut dc);
+//! batch.draw(dc);
+//! ```
+//!
+//! Text is laid out with the glyph metrics of `SdfFont`, and drawn by `SdfTextRenderer`
+//! with the outline and shadow of `SdfStyle`.
+//!
+//! ```rust,ignore
+//! let mut font = SdfFont::new((512, 512).into(), 4.0, 32.0, 40.0);
+//! font.add('A', &glyph_bitmap, SdfGlyph::new([1.0, -23.0], 22.0));
+//!
+//! let mut text = SdfText::new("AAA", [10.0, 50.0], 64.0);
+//! text.style.outline_width = 0.1;
+//!
+//! let mut renderer = SdfTextRenderer::new(video.clone(), font)?;
+//! renderer.draw(surface, proj, &text)?;
+//! ```
+
+use std::collections::HashMap;
+use std::hash::Hash;
+use std::sync::Arc;
+
+use math;
+
+use super::assets::builtin::BuiltinShader;
+use super::assets::image::Image;
+use super::assets::mesh::{IndexFormat, MeshData, MeshHint, MeshParams};
+use super::assets::surface::SurfaceHandle;
+use super::assets::texture::{TextureFormat, TextureHandle};
+use super::batch::{Batch, DrawCall};
+use super::errors::*;
+use super::VideoSystemShared;
+
+// The squared distance of pixels which are far away from any seed.
+const FAR: f32 = 1e20;
+
+/// Generates the signed distance field of `coverage` into a `R8` image. The alpha
+/// channel of `RGBA8` and the red channel of other formats are used as coverage.
+///
+/// The edge of shape is mapped to 0.5 (128), and the distances are normalized by
+/// `spread` pixels, e.g. pixels which are `spread` pixels away outside have value 0.
+pub fn generate(coverage: &Image, spread: f32) -> Image {
+    let dimensions = coverage.dimensions();
+    let (w, h) = (dimensions.x as usize, dimensions.y as usize);
+    let channel = if coverage.format() == TextureFormat::RGBA8 { 3 } else { 0 };
+
+    let mut inside = Vec::with_capacity(w * h);
+    for y in 0..dimensions.y {
+        for x in 0..dimensions.x {
+            inside.push(coverage.pixel(x, y).map_or(false, |v| v[channel] >= 128));
+        }
+    }
+
+    // The squared distances to nearest inside and outside pixels.
+    let to_inside = distance_transform(w, h, |i| inside[i]);
+    let to_outside = distance_transform(w, h, |i| !inside[i]);
+
+    let mut sdf = Image::new(TextureFormat::R8, dimensions).unwrap();
+    let spread = spread.max(1.0);
+    for y in 0..h {
+        for x in 0..w {
+            let i = y * w + x;
+            let d = if inside[i] {
+                0.5 - to_outside[i].sqrt()
+            } else {
+                to_inside[i].sqrt() - 0.5
+            };
+
+            let v = (0.5 - d / (2.0 * spread)).max(0.0).min(1.0);
+            let v = (v * 255.0).round() as u8;
+            sdf.set_pixel(x as u32, y as u32, [v, v, v, 255]);
+        }
+    }
+
+    sdf
+}
+
+/// Computes the squared euclidean distances to nearest seed pixels, with the separable
+/// algorithm of Felzenszwalb and Huttenlocher.
+fn distance_transform<F: Fn(usize) -> bool>(w: usize, h: usize, seed: F) -> Vec<f32> {
+    let mut grid: Vec<f32> = (0..w * h).map(|i| if seed(i) { 0.0 } else { FAR }).collect();
+
+    let n = w.max(h);
+    let mut f = vec![0.0; n];
+    let mut d = vec![0.0; n];
+    let mut v = vec![0; n];
+    let mut z = vec![0.0; n + 1];
+
+    for x in 0..w {
+        for (y, v) in f.iter_mut().take(h).enumerate() {
+            *v = grid[y * w + x];
+        }
+
+        transform_1d(&f[..h], &mut d, &mut v, &mut z);
+        for (y, v) in d.iter().take(h).enumerate() {
+            grid[y * w + x] = *v;
+        }
+    }
+
+    for y in 0..h {
+        f[..w].copy_from_slice(&grid[y * w..(y + 1) * w]);
+        transform_1d(&f[..w], &mut d, &mut v, &mut z);
+        grid[y * w..(y + 1) * w].copy_from_slice(&d[..w]);
+    }
+
+    grid
+}
+
+fn transform_1d(f: &[f32], d: &mut [f32], v: &mut [usize], z: &mut [f32]) {
+    if f.is_empty() {
+        return;
+    }
+
+    let mut k = 0;
+    v[0] = 0;
+    z[0] = -::std::f32::INFINITY;
+    z[1] = ::std::f32::INFINITY;
+
+    for q in 1..f.len() {
+        let fq = f[q] + (q * q) as f32;
+
+        // Pops the parabolas hidden by `q`, it stops at the first one since `z[0]` is
+        // negative infinity.
+        let mut s;
+        loop {
+            let p = v[k];
+            s = (fq - (f[p] + (p * p) as f32)) / (2 * q - 2 * p) as f32;
+            if s > z[k] {
+                break;
+            }
+
+            k -= 1;
+        }
+
+        k += 1;
+        v[k] = q;
+        z[k] = s;
+        z[k + 1] = ::std::f32::INFINITY;
+    }
+
+    k = 0;
+    for (q, dq) in d.iter_mut().take(f.len()).enumerate() {
+        while z[k + 1] < q as f32 {
+            k += 1;
+        }
+
+        let p = v[k];
+        let delta = q as f32 - p as f32;
+        *dq = delta * delta + f[p];
+    }
+}
+
+/// `SdfAtlas` packs the distance fields of glyphs or icons into one `R8` image.
+pub struct SdfAtlas<K: Hash + Eq> {
+    image: Image,
+    packer: math::ShelfPacker,
+    spread: f32,
+    regions: HashMap<K, math::RectF>,
+    revision: usize,
+}
+
+impl<K: Hash + Eq> SdfAtlas<K> {
+    /// Creates an empty atlas, the distance fields are normalized by `spread` pixels.
+    pub fn new(dimensions: math::Vector2<u32>, spread: f32) -> Self {
+        SdfAtlas {
+            image: Image::new(TextureFormat::R8, dimensions).unwrap(),
+            packer: math::ShelfPacker::new(dimensions, 1),
+            spread: spread,
+            regions: HashMap::new(),
+            revision: 0,
+        }
+    }
+
+    #[inline]
+    pub fn spread(&self) -> f32 {
+        self.spread
+    }
+
+    /// Gets the revision of image, which changes every time the image is modified.
+    #[inline]
+    pub fn revision(&self) -> usize {
+        self.revision
+    }
+
+    /// Gets the image of atlas, which should be uploaded as texture after adding.
+    #[inline]
+    pub fn image(&self) -> &Image {
+        &self.image
+    }
+
+    /// Generates the distance field of `coverage` and packs it into atlas, returns the
+    /// normalized texture coordinates. Returns `None` if there is no enough space.
+    ///
+    /// Notes that the region contains `spread` pixels of paddings on each side, so the
+    /// outlines and glows are not clipped. The quads should be enlarged accordingly.
+    pub fn add(&mut self, key: K, coverage: &Image) -> Option<math::RectF> {
+        if let Some(&uv) = self.regions.get(&key) {
+            return Some(uv);
+        }
+
+        let padding = self.spread.ceil() as u32;
+        let src = coverage.dimensions();
+        let dimensions = math::Vector2::new(src.x + padding * 2, src.y + padding * 2);
+
+        let rect = self.packer.pack(dimensions)?;
+        let channel = if coverage.format() == TextureFormat::RGBA8 { 3 } else { 0 };
+
+        let mut padded = Image::new(TextureFormat::R8, dimensions).unwrap();
+        for y in 0..src.y {
+            for x in 0..src.x {
+                let v = coverage.pixel(x, y).map_or(0, |v| v[channel]);
+                padded.set_pixel(x + padding, y + padding, [v, 0, 0, 255]);
+            }
+        }
+
+        let sdf = generate(&padded, self.spread);
+        for y in 0..dimensions.y {
+            for x in 0..dimensions.x {
+                if let Some(v) = sdf.pixel(x, y) {
+                    let (dx, dy) = (rect.position.x + x, rect.position.y + y);
+                    self.image.set_pixel(dx, dy, v);
+                }
+            }
+        }
+
+        let size = self.image.dimensions();
+        let uv = math::RectF::new(
+            rect.position.x as f32 / size.x as f32,
+            rect.position.y as f32 / size.y as f32,
+            rect.size.x as f32 / size.x as f32,
+            rect.size.y as f32 / size.y as f32,
+        );
+
+        self.regions.insert(key, uv);
+        self.revision += 1;
+        Some(uv)
+    }
+
+    /// Gets the normalized texture coordinates of packed item.
+    #[inline]
+    pub fn get(&self, key: &K) -> Option<math::RectF> {
+        self.regions.get(key).cloned()
+    }
+
+    /// Removes all the items.
+    pub fn clear(&mut self) {
+        let dimensions = self.image.dimensions();
+        self.image = Image::new(TextureFormat::R8, dimensions).unwrap();
+        self.packer.clear();
+        self.regions.clear();
+        self.revision += 1;
+    }
+}
+
+impl_vertex!{
+    SdfVertex {
+        position => [Position; Float; 2; false],
+        texcoord => [Texcoord0; Float; 2; false],
+        color => [Color0; UByte; 4; true],
+    }
+}
+
+/// Builds the vertices of textured quads, which could be drawn with
+/// `BuiltinShader::UiSdf` or `BuiltinShader::Ui`.
+#[derive(Debug, Clone, Default)]
+pub struct SdfQuads {
+    verts: Vec<SdfVertex>,
+    idxes: Vec<u16>,
+}
+
+impl SdfQuads {
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    /// Appends a quad at `rect` sampling `uv`. The maximum number of quads is 16384,
+    /// since the indices are 16-bits.
+    pub fn push(&mut self, rect: math::RectF, uv: math::RectF, color: math::Color<f32>) {
+        assert!(self.verts.len() + 4 <= 0x1_0000);
+
+        let base = self.verts.len() as u16;
+        let color: [u8; 4] = color.into();
+        let (min, max) = (rect.min(), rect.max());
+        let (uv_min, uv_max) = (uv.min(), uv.max());
+
+        self.verts.extend_from_slice(&[
+            SdfVertex::new([min.x, min.y], [uv_min.x, uv_min.y], color),
+            SdfVertex::new([max.x, min.y], [uv_max.x, uv_min.y], color),
+            SdfVertex::new([max.x, max.y], [uv_max.x, uv_max.y], color),
+            SdfVertex::new([min.x, max.y], [uv_min.x, uv_max.y], color),
+        ]);
+
+        self.idxes
+            .extend_from_slice(&[base, base + 1, base + 2, base, base + 2, base + 3]);
+    }
+
+    /// Appends the quads of glyphs in `text`, which starts at the pen `position` on the
+    /// baseline of first line, and is scaled to `size` pixels. The glyphs which are not
+    /// added into `font` are skipped.
+    pub fn push_text<T>(
+        &mut self,
+        font: &SdfFont,
+        text: &str,
+        position: T,
+        size: f32,
+        color: math::Color<f32>,
+    ) where
+        T: Into<math::Vector2<f32>>,
+    {
+        let origin = position.into();
+        let scale = size / font.size;
+        let padding = font.atlas.spread().ceil();
+
+        let mut pen = origin;
+        for ch in text.chars() {
+            if ch == '\n' {
+                pen = math::Vector2::new(origin.x, pen.y + font.line_height * scale);
+                continue;
+            }
+
+            if let (Some(&(glyph, dimensions)), Some(uv)) =
+                (font.glyphs.get(&ch), font.atlas.get(&ch))
+            {
+                // The region in atlas contains the paddings of distance field.
+                let rect = math::RectF::new(
+                    pen.x + (glyph.bearing.x - padding) * scale,
+                    pen.y + (glyph.bearing.y - padding) * scale,
+                    (dimensions.x as f32 + padding * 2.0) * scale,
+                    (dimensions.y as f32 + padding * 2.0) * scale,
+                );
+
+                self.push(rect, uv, color);
+                pen.x += glyph.advance * scale;
+            }
+        }
+    }
+
+    /// Gets the number of quads.
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.verts.len() / 4
+    }
+
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.verts.is_empty()
+    }
+
+    #[inline]
+    pub fn clear(&mut self) {
+        self.verts.clear();
+        self.idxes.clear();
+    }
+
+    #[inline]
+    pub fn vertices(&self) -> &[SdfVertex] {
+        &self.verts
+    }
+
+    #[inline]
+    pub fn indices(&self) -> &[u16] {
+        &self.idxes
+    }
+
+    /// Gets the parameters and data to create mesh.
+    pub fn mesh(&self, hint: MeshHint) -> (MeshParams, MeshData) {
+        let mut params = MeshParams::default();
+        params.hint = hint;
+        params.layout = SdfVertex::layout();
+        params.index_format = IndexFormat::from_vertex_count(self.verts.len());
+        params.num_verts = self.verts.len();
+        params.num_idxes = self.idxes.len();
+
+        let iptr = params.index_format.encode_u16(&self.idxes).into_owned();
+        let data = MeshData {
+            vptr: SdfVertex::encode(&self.verts).to_vec().into_boxed_slice(),
+            iptr: iptr.into_boxed_slice(),
+        };
+
+        (params, data)
+    }
+}
+
+/// The appearance of shapes drawn with `BuiltinShader::UiSdf`. All the widths and
+/// softnesses are in normalized distance, where 0.5 is `spread` pixels.
+#[derive(Debug, Copy, Clone)]
+pub struct SdfStyle {
+    /// The half width of anti-aliased edge, which should be greater than zero.
+    pub softness: f32,
+    /// The width of outline which grows outwards, zero disables outline.
+    pub outline_width: f32,
+    pub outline_color: math::Color<f32>,
+    /// The offset of shadow in texture coordinates, the shadow works as glow if the
+    /// offset is zero.
+    pub shadow_offset: math::Vector2<f32>,
+    /// The half width of blurred shadow edge, which should be greater than zero.
+    pub shadow_softness: f32,
+    /// The color of shadow, transparent color disables shadow.
+    pub shadow_color: math::Color<f32>,
+}
+
+impl Default for SdfStyle {
+    fn default() -> Self {
+        SdfStyle {
+            softness: 0.05,
+            outline_width: 0.0,
+            outline_color: math::Color::black(),
+            shadow_offset: math::Vector2::new(0.0, 0.0),
+            shadow_softness: 0.1,
+            shadow_color: math::Color::transparent(),
+        }
+    }
+}
+
+impl SdfStyle {
+    /// Sets the uniform variables of style into draw call.
+    pub fn apply(&self, dc: &mut DrawCall) {
+        dc.set_uniform_variable("u_Softness", self.softness);
+        dc.set_uniform_variable("u_OutlineWidth", self.outline_width);
+        dc.set_uniform_variable("u_OutlineColor", self.outline_color.rgba());
+        dc.set_uniform_variable("u_ShadowOffset", self.shadow_offset);
+        dc.set_uniform_variable("u_ShadowSoftness", self.shadow_softness);
+        dc.set_uniform_variable("u_ShadowColor", self.shadow_color.rgba());
+    }
+}
+
+/// The metrics of glyph, in pixels of the coverage bitmap.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct SdfGlyph {
+    /// The offset from the pen position on baseline to the top-left corner of bitmap,
+    /// the y axis grows downwards.
+    pub bearing: math::Vector2<f32>,
+    /// The horizontal distance from the pen position to the next one.
+    pub advance: f32,
+}
+
+impl SdfGlyph {
+    pub fn new<T: Into<math::Vector2<f32>>>(bearing: T, advance: f32) -> Self {
+        SdfGlyph {
+            bearing: bearing.into(),
+            advance: advance,
+        }
+    }
+}
+
+/// `SdfFont` keeps the distance fields and metrics of glyphs which are rasterized at
+/// `size` pixels, the text could be drawn at any other sizes.
+pub struct SdfFont {
+    atlas: SdfAtlas<char>,
+    size: f32,
+    line_height: f32,
+    glyphs: HashMap<char, (SdfGlyph, math::Vector2<u32>)>,
+}
+
+impl SdfFont {
+    /// Creates an empty font whose glyphs are rasterized at `size` pixels, and lines are
+    /// `line_height` pixels apart.
+    pub fn new(dimensions: math::Vector2<u32>, spread: f32, size: f32, line_height: f32) -> Self {
+        SdfFont {
+            atlas: SdfAtlas::new(dimensions, spread),
+            size: size.max(1.0),
+            line_height: line_height,
+            glyphs: HashMap::new(),
+        }
+    }
+
+    #[inline]
+    pub fn size(&self) -> f32 {
+        self.size
+    }
+
+    #[inline]
+    pub fn line_height(&self) -> f32 {
+        self.line_height
+    }
+
+    #[inline]
+    pub fn atlas(&self) -> &SdfAtlas<char> {
+        &self.atlas
+    }
+
+    /// Adds the glyph with its coverage bitmap, returns false if there is no enough space
+    /// in atlas.
+    pub fn add(&mut self, ch: char, coverage: &Image, glyph: SdfGlyph) -> bool {
+        if self.atlas.add(ch, coverage).is_none() {
+            return false;
+        }
+
+        self.glyphs.insert(ch, (glyph, coverage.dimensions()));
+        true
+    }
+
+    /// Gets the metrics of glyph.
+    #[inline]
+    pub fn glyph(&self, ch: char) -> Option<SdfGlyph> {
+        self.glyphs.get(&ch).map(|v| v.0)
+    }
+
+    /// Measures the width and height of `text` at `size` pixels.
+    pub fn measure(&self, text: &str, size: f32) -> math::Vector2<f32> {
+        let scale = size / self.size;
+        let mut lines = 1;
+        let (mut width, mut line) = (0.0f32, 0.0);
+        for ch in text.chars() {
+            if ch == '\n' {
+                lines += 1;
+                line = 0.0;
+            } else if let Some(glyph) = self.glyph(ch) {
+                line += glyph.advance * scale;
+                width = width.max(line);
+            }
+        }
+
+        math::Vector2::new(width, lines as f32 * self.line_height * scale)
+    }
+}
+
+/// The text drawn with `SdfTextRenderer`.
+#[derive(Debug, Clone)]
+pub struct SdfText<'a> {
+    pub text: &'a str,
+    /// The pen position on the baseline of first line.
+    pub position: math::Vector2<f32>,
+    /// The size of text in pixels.
+    pub size: f32,
+    pub color: math::Color<f32>,
+    /// The outline and shadow of glyphs.
+    pub style: SdfStyle,
+}
+
+impl<'a> SdfText<'a> {
+    pub fn new<T: Into<math::Vector2<f32>>>(text: &'a str, position: T, size: f32) -> Self {
+        SdfText {
+            text: text,
+            position: position.into(),
+            size: size,
+            color: math::Color::white(),
+            style: SdfStyle::default(),
+        }
+    }
+}
+
+/// Draws the text of `SdfFont` with `BuiltinShader::UiSdf`. The atlas of font is
+/// uploaded again when new glyphs are added.
+pub struct SdfTextRenderer {
+    video: Arc<VideoSystemShared>,
+    font: SdfFont,
+    shader: ShaderHandle,
+    texture: Option<(TextureHandle, usize)>,
+    quads: SdfQuads,
+    batch: Batch,
+}
+
+impl SdfTextRenderer {
+    pub fn new(video: Arc<VideoSystemShared>, font: SdfFont) -> Result<Self> {
+        let shader = video.builtin_shader(BuiltinShader::UiSdf)?;
+        Ok(SdfTextRenderer {
+            video: video,
+            font: font,
+            shader: shader,
+            texture: None,
+            quads: SdfQuads::new(),
+            batch: Batch::new(),
+        })
+    }
+
+    #[inline]
+    pub fn font(&self) -> &SdfFont {
+        &self.font
+    }
+
+    #[inline]
+    pub fn font_mut(&mut self) -> &mut SdfFont {
+        &mut self.font
+    }
+
+    /// Draws the text into `surface`, `projection` transforms the pixel coordinates of
+    /// text into clip space.
+    pub fn draw(
+        &mut self,
+        surface: SurfaceHandle,
+        projection: math::Matrix4<f32>,
+        text: &SdfText,
+    ) -> Result<()> {
+        self.quads.clear();
+        self.quads
+            .push_text(&self.font, text.text, text.position, text.size, text.color);
+
+        if self.quads.is_empty() {
+            return Ok(());
+        }
+
+        let texture = self.update_texture()?;
+        let (params, data) = self.quads.mesh(MeshHint::Immutable);
+        let mesh = self.video.create_mesh(params, data)?;
+
+        let mut dc = DrawCall::new(self.shader, mesh);
+        dc.set_uniform_variable("u_ProjMatrix", projection);
+        dc.set_uniform_variable("u_Texture", texture);
+        text.style.apply(&mut dc);
+        self.batch.draw(dc);
+        let result = self.batch.submit(&self.video, surface);
+
+        // The deletion is executed after the drawcall in this frame.
+        self.video.delete_mesh(mesh);
+        result
+    }
+
+    // Uploads the atlas of font if it has been modified since the last upload.
+    fn update_texture(&mut self) -> Result<TextureHandle> {
+        let revision = self.font.atlas.revision();
+        if let Some((handle, v)) = self.texture {
+            if v == revision {
+                return Ok(handle);
+            }
+
+            self.video.delete_texture(handle);
+            self.texture = None;
+        }
+
+        let (params, data) = self.font.atlas.image().clone().into_texture(false);
+        let handle = self.video.create_texture(params, data)?;
+        self.texture = Some((handle, revision));
+        Ok(handle)
+    }
+}
+
+impl Drop for SdfTextRenderer {
+    fn drop(&mut self) {
+        if let Some((handle, _)) = self.texture.take() {
+            self.video.delete_texture(handle);
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn square() -> Image {
+        let mut image = Image::new(TextureFormat::R8, (16, 16).into()).unwrap();
+        for y in 4..12 {
+            for x in 4..12 {
+                image.set_pixel(x, y, [255, 0, 0, 255]);
+            }
+        }
+
+        image
+    }
+
+    #[test]
+    fn distance_field() {
+        let sdf = generate(&square(), 4.0);
+        assert_eq!(sdf.format(), TextureFormat::R8);
+
+        // Deep inside and far outside.
+        assert!(sdf.pixel(8, 8).unwrap()[0] > 224);
+        assert_eq!(sdf.pixel(0, 0).unwrap()[0], 0);
+
+        // Pixels next to the edge are close to 0.5.
+        let (inner, outer) = (sdf.pixel(4, 8).unwrap()[0], sdf.pixel(3, 8).unwrap()[0]);
+        assert!(inner > 128 && inner < 160);
+        assert!(outer < 128 && outer > 96);
+
+        // Monotonic along the distance to edge.
+        for x in 0..8 {
+            assert!(sdf.pixel(x, 8) <= sdf.pixel(x + 1, 8));
+        }
+    }
+
+    #[test]
+    fn atlas_and_quads() {
+        let mut atlas = SdfAtlas::new((64, 64).into(), 2.0);
+        let uv = atlas.add('a', &square()).unwrap();
+        assert_eq!(atlas.add('a', &square()), Some(uv));
+        assert_eq!(atlas.get(&'a'), Some(uv));
+        assert_eq!(uv.size, math::Vector2::new(20.0 / 64.0, 20.0 / 64.0));
+
+        let large = Image::new(TextureFormat::R8, (64, 64).into()).unwrap();
+        assert!(atlas.add('b', &large).is_none());
+
+        let mut quads = SdfQuads::new();
+        quads.push(math::RectF::new(0.0, 0.0, 8.0, 8.0), uv, math::Color::white());
+        quads.push(math::RectF::new(8.0, 0.0, 8.0, 8.0), uv, math::Color::white());
+        assert_eq!(quads.len(), 2);
+        assert_eq!(&quads.indices()[6..], &[4, 5, 6, 4, 6, 7]);
+
+        let (params, data) = quads.mesh(MeshHint::Immutable);
+        assert_eq!(params.num_verts, 8);
+        assert_eq!(data.iptr.len(), 24);
+    }
+
+    #[test]
+    fn text() {
+        let mut font = SdfFont::new((64, 64).into(), 2.0, 16.0, 20.0);
+        assert!(font.add('a', &square(), SdfGlyph::new([1.0, -12.0], 14.0)));
+        assert_eq!(font.glyph('a'), Some(SdfGlyph::new([1.0, -12.0], 14.0)));
+        assert_eq!(font.glyph('b'), None);
+
+        // The glyphs are scaled by the size of text, and the unknown ones are skipped.
+        let mut quads = SdfQuads::new();
+        quads.push_text(&font, "ab\na", [10.0, 30.0], 32.0, math::Color::white());
+        assert_eq!(quads.len(), 2);
+
+        let v = quads.vertices();
+        let (x, y) = (10.0 + (1.0 - 2.0) * 2.0, 30.0 + (-12.0 - 2.0) * 2.0);
+        assert_eq!(v[0].position, [x, y]);
+        assert_eq!(v[2].position, [x + 40.0, y + 40.0]);
+        assert_eq!(v[4].position, [x, y + 40.0]);
+
+        assert_eq!(font.measure("aa\na", 32.0), math::Vector2::new(56.0, 80.0));
+        assert_eq!(font.measure("", 16.0), math::Vector2::new(0.0, 20.0));
+    }
+
+    #[test]
+    fn text_renderer() {
+        use super::super::VideoSystem;
+
+        let video = VideoSystem::headless().shared();
+        let surface = video.create_surface(Default::default()).unwrap();
+
+        let mut font = SdfFont::new((64, 64).into(), 2.0, 16.0, 20.0);
+        font.add('a', &square(), SdfGlyph::new([0.0, -16.0], 16.0));
+
+        let mut renderer = SdfTextRenderer::new(video.clone(), font).unwrap();
+        let proj = math::Matrix4::from_scale(1.0);
+
+        let mut text = SdfText::new("aa", [0.0, 16.0], 16.0);
+        text.style.outline_width = 0.1;
+        text.style.shadow_color = math::Color::black();
+        renderer.draw(surface, proj, &text).unwrap();
+
+        // The atlas is only uploaded again after new glyphs are added.
+        let texture = renderer.texture.unwrap();
+        renderer.draw(surface, proj, &text).unwrap();
+        assert_eq!(renderer.texture.unwrap(), texture);
+
+        renderer
+            .font_mut()
+            .add('b', &square(), SdfGlyph::new([0.0, -16.0], 16.0));
+        renderer.draw(surface, proj, &text).unwrap();
+        assert!(renderer.texture.unwrap() != texture);
+    }
+}
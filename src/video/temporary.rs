@@ -0,0 +1,231 @@
+//! Transient render targets which could be shared by effects in frames.
+//!
+//! Instead of allocating its own render textures permanently, an effect could borrow
+//! targets from the pool with `VideoSystemShared::get_temporary_rt`, and return them
+//! with `VideoSystemShared::release_temporary_rt` once its done. The released targets
+//! are reused by later requests with the same `RenderTextureParams`, and deleted after
+//! being unused for `TEMPORARY_RT_LIFETIME` frames.
+
+use super::assets::prelude::*;
+use super::errors::*;
+use super::VideoSystemShared;
+
+/// The number of frames a released target is kept in pool before its deleted.
+pub const TEMPORARY_RT_LIFETIME: u64 = 4;
+
+/// A render texture and the surface which draws into it.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct TemporaryRenderTarget {
+    pub texture: RenderTextureHandle,
+    pub surface: SurfaceHandle,
+}
+
+struct Entry {
+    params: RenderTextureParams,
+    target: TemporaryRenderTarget,
+    in_use: bool,
+    released_at: u64,
+}
+
+#[derive(Default)]
+pub(crate) struct TemporaryPool {
+    entries: Vec<Entry>,
+    frame: u64,
+}
+
+impl TemporaryPool {
+    /// Borrows a released target with the same params.
+    pub fn acquire(&mut self, params: RenderTextureParams) -> Option<TemporaryRenderTarget> {
+        for v in &mut self.entries {
+            if !v.in_use && v.params == params {
+                v.in_use = true;
+                return Some(v.target);
+            }
+        }
+
+        None
+    }
+
+    /// Adds a new borrowed target into pool.
+    pub fn insert(&mut self, params: RenderTextureParams, target: TemporaryRenderTarget) {
+        self.entries.push(Entry {
+            params: params,
+            target: target,
+            in_use: true,
+            released_at: 0,
+        });
+    }
+
+    /// Returns the target into pool, so it could be reused by later requests.
+    pub fn release(&mut self, target: TemporaryRenderTarget) -> bool {
+        let frame = self.frame;
+        for v in &mut self.entries {
+            if v.in_use && v.target == target {
+                v.in_use = false;
+                v.released_at = frame;
+                return true;
+            }
+        }
+
+        false
+    }
+
+    /// Advances to next frame, and returns the targets which are expired.
+    pub fn advance(&mut self) -> Vec<TemporaryRenderTarget> {
+        self.frame += 1;
+
+        let frame = self.frame;
+        let mut expired = Vec::new();
+        self.entries.retain(|v| {
+            if !v.in_use && frame - v.released_at > TEMPORARY_RT_LIFETIME {
+                expired.push(v.target);
+                false
+            } else {
+                true
+            }
+        });
+
+        expired
+    }
+
+    /// Removes the released targets from pool, and returns them.
+    pub fn purge(&mut self) -> Vec<TemporaryRenderTarget> {
+        let mut purged = Vec::new();
+        self.entries.retain(|v| {
+            if v.in_use {
+                true
+            } else {
+                purged.push(v.target);
+                false
+            }
+        });
+
+        purged
+    }
+
+    /// Gets the number of targets in pool, including the borrowed ones.
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+}
+
+/// `PingPong` holds two temporary targets for iterative effects (e.g. blur passes),
+/// the result of each pass is drawn into `destination` by sampling the `source`, and
+/// then they are swapped for the next pass.
+#[derive(Debug, Copy, Clone)]
+pub struct PingPong {
+    targets: [TemporaryRenderTarget; 2],
+    index: usize,
+}
+
+impl PingPong {
+    /// Borrows two temporary targets with the same params.
+    pub fn new(video: &VideoSystemShared, params: RenderTextureParams) -> Result<Self> {
+        let first = video.get_temporary_rt(params)?;
+        let second = match video.get_temporary_rt(params) {
+            Ok(v) => v,
+            Err(err) => {
+                video.release_temporary_rt(first);
+                return Err(err);
+            }
+        };
+
+        Ok(PingPong {
+            targets: [first, second],
+            index: 0,
+        })
+    }
+
+    /// The target which holds the result of last pass.
+    #[inline]
+    pub fn source(&self) -> TemporaryRenderTarget {
+        self.targets[self.index]
+    }
+
+    /// The target which the current pass draws into.
+    #[inline]
+    pub fn destination(&self) -> TemporaryRenderTarget {
+        self.targets[1 - self.index]
+    }
+
+    /// Swaps the source and destination after a pass.
+    #[inline]
+    pub fn swap(&mut self) {
+        self.index = 1 - self.index;
+    }
+
+    /// Returns both targets into pool.
+    pub fn release(self, video: &VideoSystemShared) {
+        video.release_temporary_rt(self.targets[0]);
+        video.release_temporary_rt(self.targets[1]);
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use utils::handle::Handle;
+
+    fn target(i: u32) -> TemporaryRenderTarget {
+        TemporaryRenderTarget {
+            texture: Handle::new(i, 1).into(),
+            surface: Handle::new(i, 1).into(),
+        }
+    }
+
+    #[test]
+    fn reuse() {
+        let mut params = RenderTextureParams::default();
+        params.dimensions = (256, 256).into();
+
+        let mut pool = TemporaryPool::default();
+        assert!(pool.acquire(params).is_none());
+        pool.insert(params, target(1));
+        assert!(pool.acquire(params).is_none());
+
+        assert!(pool.release(target(1)));
+        assert!(!pool.release(target(1)));
+
+        // Targets are only reused with the same params.
+        let mut other = params;
+        other.dimensions = (128, 128).into();
+        assert!(pool.acquire(other).is_none());
+        assert_eq!(pool.acquire(params), Some(target(1)));
+    }
+
+    #[test]
+    fn expire() {
+        let params = RenderTextureParams::default();
+        let mut pool = TemporaryPool::default();
+        pool.insert(params, target(1));
+        pool.insert(params, target(2));
+        pool.release(target(1));
+
+        for _ in 0..TEMPORARY_RT_LIFETIME {
+            assert!(pool.advance().is_empty());
+        }
+
+        assert_eq!(pool.advance(), vec![target(1)]);
+        assert_eq!(pool.len(), 1);
+
+        // The borrowed targets are never expired.
+        for _ in 0..(TEMPORARY_RT_LIFETIME * 2) {
+            assert!(pool.advance().is_empty());
+        }
+    }
+
+    #[test]
+    fn purge() {
+        let params = RenderTextureParams::default();
+        let mut pool = TemporaryPool::default();
+        pool.insert(params, target(1));
+        pool.insert(params, target(2));
+        pool.release(target(1));
+
+        // The borrowed targets are kept.
+        assert_eq!(pool.purge(), vec![target(1)]);
+        assert_eq!(pool.len(), 1);
+        assert!(pool.acquire(params).is_none());
+    }
+}
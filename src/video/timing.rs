@@ -0,0 +1,187 @@
+//! The GPU timings of video commands, which are measured with the timer queries of
+//! backend when `VideoParams::gpu_timings` is enabled.
+//!
+//! The timestamps are recorded before each surface is binded, and optionally after
+//! every drawcall. Since the commands are always finished by GPU at the end of
+//! `VideoSystem::advance`, the results are collected without extra latency.
+
+use std::mem;
+use std::time::Duration;
+
+use super::assets::prelude::*;
+use super::backends::Visitor;
+
+use errors::*;
+
+/// The granularity of GPU timings.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum GpuTimings {
+    /// Nothing is measured.
+    Disabled,
+    /// Measures the time spent on each surface.
+    Surfaces,
+    /// Measures the time spent on each surface and drawcall. The state changes
+    /// between drawcalls are counted into the next drawcall.
+    DrawCalls,
+}
+
+impl Default for GpuTimings {
+    fn default() -> Self {
+        GpuTimings::Disabled
+    }
+}
+
+/// The GPU time spent on a single drawcall.
+#[derive(Debug, Copy, Clone)]
+pub struct GpuDrawCallTiming {
+    pub shader: ShaderHandle,
+    pub mesh: MeshHandle,
+    pub duration: Duration,
+}
+
+/// The GPU time spent on a surface, including the clearing.
+#[derive(Debug, Clone)]
+pub struct GpuSurfaceTiming {
+    pub surface: SurfaceHandle,
+    pub duration: Duration,
+    /// The drawcalls in submitted order, which is empty unless `GpuTimings::DrawCalls`
+    /// is used.
+    pub drawcalls: Vec<GpuDrawCallTiming>,
+}
+
+/// The GPU timings of a frame.
+#[derive(Debug, Clone, Default)]
+pub struct GpuFrameTimings {
+    /// The GPU time between the first surface and the end of frame.
+    pub duration: Duration,
+    /// The surfaces in binded order.
+    pub surfaces: Vec<GpuSurfaceTiming>,
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub(crate) enum TimerMark {
+    Surface(SurfaceHandle),
+    DrawCall(ShaderHandle, MeshHandle),
+    End,
+}
+
+/// Records the marks of timestamps during dispatching, and resolves them into
+/// `GpuFrameTimings`.
+#[derive(Default)]
+pub(crate) struct GpuTimer {
+    mode: GpuTimings,
+    marks: Vec<TimerMark>,
+}
+
+impl GpuTimer {
+    #[inline]
+    pub fn set_mode(&mut self, mode: GpuTimings) {
+        self.mode = mode;
+    }
+
+    /// Queries a timestamp for `mark`, if its required by the granularity.
+    pub unsafe fn mark(&mut self, visitor: &mut Visitor, mark: TimerMark) -> Result<()> {
+        let required = match (self.mode, mark) {
+            (GpuTimings::Disabled, _) => false,
+            (GpuTimings::Surfaces, TimerMark::DrawCall(_, _)) => false,
+            _ => true,
+        };
+
+        if required {
+            visitor.query_timestamp()?;
+            self.marks.push(mark);
+        }
+
+        Ok(())
+    }
+
+    /// Resolves the marks with the timestamps in nanoseconds. Returns `None` if nothing
+    /// is measured, or the timestamps are not available, e.g. on devices without timer
+    /// queries.
+    pub fn resolve(&mut self, stamps: &[u64]) -> Option<GpuFrameTimings> {
+        let marks = mem::replace(&mut self.marks, Vec::new());
+        if marks.is_empty() || marks.len() != stamps.len() {
+            return None;
+        }
+
+        let mut timings = GpuFrameTimings::default();
+        timings.duration = nanos(stamps[stamps.len() - 1].saturating_sub(stamps[0]));
+
+        let mut last = stamps[0];
+        for (&mark, &stamp) in marks.iter().zip(stamps) {
+            // The time since last mark belongs to the surface being drawn.
+            let elapsed = nanos(stamp.saturating_sub(last));
+            last = stamp;
+
+            if let Some(v) = timings.surfaces.last_mut() {
+                v.duration += elapsed;
+
+                if let TimerMark::DrawCall(shader, mesh) = mark {
+                    v.drawcalls.push(GpuDrawCallTiming {
+                        shader: shader,
+                        mesh: mesh,
+                        duration: elapsed,
+                    });
+                }
+            }
+
+            if let TimerMark::Surface(surface) = mark {
+                timings.surfaces.push(GpuSurfaceTiming {
+                    surface: surface,
+                    duration: Duration::new(0, 0),
+                    drawcalls: Vec::new(),
+                });
+            }
+        }
+
+        Some(timings)
+    }
+}
+
+fn nanos(v: u64) -> Duration {
+    Duration::new(v / 1_000_000_000, (v % 1_000_000_000) as u32)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use utils::handle::Handle;
+
+    #[test]
+    fn resolve() {
+        let surface: SurfaceHandle = Handle::new(0, 1).into();
+        let shader: ShaderHandle = Handle::new(1, 1).into();
+        let mesh: MeshHandle = Handle::new(2, 1).into();
+
+        let mut timer = GpuTimer::default();
+        assert!(timer.resolve(&[]).is_none());
+
+        timer.marks = vec![
+            TimerMark::Surface(surface),
+            TimerMark::DrawCall(shader, mesh),
+            TimerMark::DrawCall(shader, mesh),
+            TimerMark::End,
+        ];
+
+        assert!(timer.resolve(&[100, 200]).is_none());
+        assert!(timer.marks.is_empty());
+
+        timer.marks = vec![
+            TimerMark::Surface(surface),
+            TimerMark::DrawCall(shader, mesh),
+            TimerMark::DrawCall(shader, mesh),
+            TimerMark::End,
+        ];
+
+        let timings = timer.resolve(&[1_000, 1_500, 3_500, 4_000]).unwrap();
+        assert_eq!(timings.duration, Duration::new(0, 3_000));
+        assert_eq!(timings.surfaces.len(), 1);
+        assert_eq!(timings.surfaces[0].surface, surface);
+        assert_eq!(timings.surfaces[0].duration, Duration::new(0, 3_000));
+
+        let drawcalls = &timings.surfaces[0].drawcalls;
+        assert_eq!(drawcalls.len(), 2);
+        assert_eq!(drawcalls[0].duration, Duration::new(0, 500));
+        assert_eq!(drawcalls[1].duration, Duration::new(0, 2_000));
+    }
+}
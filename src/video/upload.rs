@@ -0,0 +1,277 @@
+//! Throttled texture uploads, which spread the data of loaded textures across frames.
+//!
+//! Uploading a level's worth of textures at once stalls the main thread for several
+//! milliseconds, since the driver has to copy all the data in `glTexImage2D`. Instead,
+//! the data of asynchronously loaded textures is queued here, and at most
+//! `VideoParams::upload_budget` bytes are submitted every frame.
+//!
+//! Big textures are split into slices of `VideoParams::upload_slice` bytes, which are
+//! a group of rows of a mipmap level (compressed levels are always uploaded as a whole).
+//! The textures referenced by draw calls are visible, so they are uploaded before the
+//! others.
+
+use math;
+
+use super::assets::prelude::*;
+
+/// A piece of texture data to upload.
+#[derive(Debug, Clone)]
+pub(crate) struct UploadSlice<'a> {
+    pub handle: TextureHandle,
+    /// The mipmap level of this slice.
+    pub level: u32,
+    /// The number of mipmap levels of texture.
+    pub levels: u32,
+    pub area: math::Aabb2<u32>,
+    pub bytes: &'a [u8],
+}
+
+struct Entry {
+    handle: TextureHandle,
+    params: TextureParams,
+    data: TextureData,
+    level: usize,
+    row: u32,
+    urgent: bool,
+}
+
+impl Entry {
+    fn remaining(&self) -> u64 {
+        let row = if self.level < self.data.bytes.len() {
+            let (_, h) = dimensions(&self.params, self.level);
+            let len = self.data.bytes[self.level].len();
+            (len / h as usize) * self.row as usize
+        } else {
+            0
+        };
+
+        let total: usize = self.data.bytes[self.level..].iter().map(|v| v.len()).sum();
+        (total - row) as u64
+    }
+}
+
+#[derive(Default)]
+pub(crate) struct UploadQueue {
+    entries: Vec<Entry>,
+}
+
+impl UploadQueue {
+    /// Queues the data of texture. The pending data of the same texture is discarded.
+    pub fn push(&mut self, handle: TextureHandle, params: TextureParams, data: TextureData) {
+        self.remove(handle);
+        self.entries.push(Entry {
+            handle: handle,
+            params: params,
+            data: data,
+            level: 0,
+            row: 0,
+            urgent: false,
+        });
+    }
+
+    /// Discards the pending data of texture.
+    pub fn remove(&mut self, handle: TextureHandle) {
+        self.entries.retain(|v| v.handle != handle);
+    }
+
+    /// Uploads the texture before the ones which are not prioritized.
+    pub fn prioritize(&mut self, handle: TextureHandle) -> bool {
+        for v in &mut self.entries {
+            if v.handle == handle {
+                v.urgent = true;
+                return true;
+            }
+        }
+
+        false
+    }
+
+    /// Returns true if there is no pending data.
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// Gets the bytes of pending data.
+    pub fn pending(&self) -> u64 {
+        self.entries.iter().map(|v| v.remaining()).sum()
+    }
+
+    /// Submits the slices until `budget` bytes are consumed, returns the bytes submitted.
+    /// The first slice is always submitted even if its bigger than the budget, so every
+    /// texture will be finished eventually.
+    pub fn advance<F>(&mut self, budget: usize, slice: usize, mut func: F) -> u64
+    where
+        F: FnMut(UploadSlice),
+    {
+        let mut submitted = 0;
+        while !self.entries.is_empty() && (submitted == 0 || submitted < budget) {
+            let index = self.entries.iter().position(|v| v.urgent).unwrap_or(0);
+
+            let finished = {
+                let v = &mut self.entries[index];
+                let bytes = &v.data.bytes[v.level];
+                let (w, h) = dimensions(&v.params, v.level);
+
+                // Compressed levels could only be specified as a whole.
+                let rows = if v.params.format.is_compression() || bytes.len() % h as usize != 0
+                {
+                    h
+                } else {
+                    let stride = bytes.len() / h as usize;
+                    let limit = if submitted == 0 {
+                        slice
+                    } else {
+                        slice.min(budget - submitted)
+                    };
+
+                    let rows = (limit / stride.max(1)).max(1);
+                    (h - v.row).min(rows as u32)
+                };
+
+                if !bytes.is_empty() {
+                    let stride = bytes.len() / h as usize;
+                    let (from, to) = if rows == h {
+                        (0, bytes.len())
+                    } else {
+                        (stride * v.row as usize, stride * (v.row + rows) as usize)
+                    };
+
+                    let area = math::Aabb2::new(
+                        math::Point2::new(0, v.row),
+                        math::Point2::new(w, v.row + rows),
+                    );
+
+                    submitted += to - from;
+                    func(UploadSlice {
+                        handle: v.handle,
+                        level: v.level as u32,
+                        levels: v.data.bytes.len() as u32,
+                        area: area,
+                        bytes: &bytes[from..to],
+                    });
+                }
+
+                v.row += rows;
+                if v.row >= h {
+                    v.level += 1;
+                    v.row = 0;
+                }
+
+                v.level >= v.data.bytes.len()
+            };
+
+            if finished {
+                self.entries.remove(index);
+            }
+        }
+
+        submitted as u64
+    }
+}
+
+// The dimensions of mipmap level.
+fn dimensions(params: &TextureParams, level: usize) -> (u32, u32) {
+    let w = (params.dimensions.x >> level).max(1);
+    let h = (params.dimensions.y >> level).max(1);
+    (w, h)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use utils::handle::Handle;
+
+    fn texture(dims: (u32, u32), levels: usize) -> (TextureParams, TextureData) {
+        let mut params = TextureParams::default();
+        params.dimensions = dims.into();
+
+        let bytes = (0..levels)
+            .map(|i| {
+                let (w, h) = dimensions(&params, i);
+                vec![i as u8; (w * h * 4) as usize].into_boxed_slice()
+            })
+            .collect();
+
+        (params, TextureData { bytes: bytes })
+    }
+
+    #[test]
+    fn slices() {
+        let handle: TextureHandle = Handle::new(1, 1).into();
+        let (params, data) = texture((16, 16), 2);
+
+        let mut queue = UploadQueue::default();
+        queue.push(handle, params, data);
+        assert_eq!(queue.pending(), 16 * 16 * 4 + 8 * 8 * 4);
+
+        // 4 rows of the base level every frame.
+        let mut rows = Vec::new();
+        let submitted = queue.advance(256, 256, |v| {
+            assert_eq!(v.levels, 2);
+            assert_eq!(v.bytes.len() as u32, v.area.volume() * 4);
+            rows.push((v.level, v.area.min.y, v.area.max.y));
+        });
+
+        assert_eq!(submitted, 256);
+        assert_eq!(rows, [(0, 0, 4)]);
+        assert_eq!(queue.pending(), 12 * 16 * 4 + 8 * 8 * 4);
+
+        rows.clear();
+        queue.advance(1024, 256, |v| rows.push((v.level, v.area.min.y, v.area.max.y)));
+        assert_eq!(rows, [(0, 4, 8), (0, 8, 12), (0, 12, 16), (1, 0, 8)]);
+        assert!(queue.is_empty());
+        assert_eq!(queue.pending(), 0);
+    }
+
+    #[test]
+    fn budget() {
+        let handle: TextureHandle = Handle::new(1, 1).into();
+        let (params, data) = texture((4, 4), 1);
+
+        // The slice bigger than budget is submitted alone.
+        let mut queue = UploadQueue::default();
+        queue.push(handle, params, data.clone());
+        queue.push(Handle::new(2, 1).into(), params, data);
+        assert_eq!(queue.advance(32, 1024, |_| {}), 64);
+        assert_eq!(queue.pending(), 64);
+
+        // The remaining budget limits the rows of next slice.
+        queue.push(handle, params, texture((4, 4), 1).1);
+        assert_eq!(queue.advance(96, 1024, |_| {}), 96);
+        assert_eq!(queue.pending(), 32);
+        queue.advance(96, 1024, |_| {});
+        assert!(queue.is_empty());
+
+        let mut params = TextureParams::default();
+        params.format = TextureFormat::Etc2RGBA8BPP;
+        params.dimensions = (8, 8).into();
+        let data = TextureData {
+            bytes: vec![vec![0; 64].into_boxed_slice()],
+        };
+
+        // Compressed levels are never split.
+        queue.push(handle, params, data);
+        assert_eq!(queue.advance(16, 16, |v| assert_eq!(v.area.dim().y, 8)), 64);
+        assert!(queue.is_empty());
+    }
+
+    #[test]
+    fn priority() {
+        let (params, data) = texture((4, 4), 1);
+        let handles: Vec<TextureHandle> = (0..3).map(|i| Handle::new(i, 1).into()).collect();
+
+        let mut queue = UploadQueue::default();
+        for &v in &handles {
+            queue.push(v, params, data.clone());
+        }
+
+        assert!(queue.prioritize(handles[2]));
+        queue.remove(handles[1]);
+
+        let mut order = Vec::new();
+        queue.advance(1024, 1024, |v| order.push(v.handle));
+        assert_eq!(order, [handles[2], handles[0]]);
+        assert!(!queue.prioritize(handles[1]));
+    }
+}
@@ -0,0 +1,22 @@
+extern crate crayon;
+
+use crayon::prelude::*;
+
+#[test]
+fn headless() {
+    let caps = crayon::capabilities();
+    assert_eq!(caps.version, env!("CARGO_PKG_VERSION"));
+    assert_eq!(caps.workers, 0);
+
+    let mut settings = Settings::default();
+    settings.headless = true;
+    let _engine = Engine::new_with(&settings).unwrap();
+
+    let caps = crayon::capabilities();
+    assert!(caps.headless);
+    assert!(caps.workers > 0);
+    assert_eq!(caps.background_workers, 1);
+    assert_eq!(caps.platform, Platform::current());
+    assert_eq!(caps.video.api, VideoApi::Headless);
+    assert!(caps.video.msaa_levels().is_empty());
+}
@@ -131,3 +131,42 @@ fn iter() {
         assert_eq!(*handle, iter.next().unwrap());
     }
 }
+
+#[test]
+fn compact() {
+    let mut set = HandlePool::new();
+    let v: Vec<_> = (0..10).map(|_| set.create()).collect();
+    for i in 0..8 {
+        set.free(v[i]);
+    }
+
+    let handles = set.compact(&[v[9], v[8]]);
+    assert_eq!(set.len(), 2);
+    assert_eq!(handles[0].index(), 0);
+    assert_eq!(handles[1].index(), 1);
+    assert!(set.is_alive(handles[0]) && set.is_alive(handles[1]));
+    assert!(!set.is_alive(v[8]) && !set.is_alive(v[9]));
+
+    // The previous handles never alias the new ones.
+    for _ in 0..8 {
+        let handle = set.create();
+        assert!(v.iter().all(|v| *v != handle));
+    }
+}
+
+#[test]
+#[should_panic]
+fn compact_duplicated() {
+    let mut set = HandlePool::new();
+    let v: Vec<_> = (0..3).map(|_| set.create()).collect();
+    set.compact(&[v[0], v[0]]);
+}
+
+#[test]
+#[should_panic]
+fn compact_dead() {
+    let mut set = HandlePool::new();
+    let v: Vec<_> = (0..3).map(|_| set.create()).collect();
+    set.free(v[1]);
+    set.compact(&[v[0], v[1]]);
+}
@@ -0,0 +1,61 @@
+extern crate crayon;
+
+use crayon::application::script::InputScript;
+use crayon::errors::Result;
+use crayon::prelude::*;
+
+#[derive(Default)]
+struct Counter {
+    frames: u32,
+    presses: u32,
+    text: String,
+}
+
+impl Application for Counter {
+    fn on_update(&mut self, ctx: &Context) -> Result<()> {
+        self.frames += 1;
+        if ctx.input.is_key_press(KeyboardButton::Space) {
+            self.presses += 1;
+        }
+
+        self.text.push_str(&ctx.input.text());
+        Ok(())
+    }
+}
+
+fn headless() -> Engine {
+    let mut settings = Settings::default();
+    settings.headless = true;
+    settings.engine.max_fps = 0;
+    Engine::new_with(&settings).unwrap()
+}
+
+#[test]
+fn script() {
+    InputScript::new()
+        .key_press(KeyboardButton::Space)
+        .wait_frames(1)
+        .assert("pressed", |_, v: &Counter| v.presses == 1)
+        .assert("down", |ctx, _| ctx.input.is_key_down(KeyboardButton::Space))
+        .wait_frames(3)
+        .assert("pressed once", |_, v: &Counter| v.presses == 1)
+        .key_release(KeyboardButton::Space)
+        .text("crayon")
+        .wait_frames(1)
+        .assert("released", |ctx, _| !ctx.input.is_key_down(KeyboardButton::Space))
+        .assert("text", |_, v: &Counter| v.text == "crayon")
+        .execute(|_, v: &mut Counter| v.presses = 0)
+        .assert("executed", |_, v: &Counter| v.presses == 0)
+        .run(headless(), Counter::default())
+        .unwrap();
+}
+
+#[test]
+fn failure() {
+    let result = InputScript::new()
+        .wait_frames(2)
+        .assert("never", |_, v: &Counter| v.frames == 0)
+        .run(headless(), Counter::default());
+
+    assert!(result.is_err());
+}